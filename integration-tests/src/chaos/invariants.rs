@@ -0,0 +1,128 @@
+use crate::Tester;
+use crate::chaos::scenario::{Fault, Stage};
+use crate::provider::ZksyncApi;
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::Address;
+use alloy::providers::Provider;
+use alloy::rpc::types::Filter;
+use alloy::sol_types::SolEvent;
+use anyhow::Context;
+use std::time::Duration;
+use zksync_os_contract_interface::IExecutor::BlockCommit;
+use zksync_os_contract_interface::l1_discovery::L1State;
+
+/// Invariant check that can be added to a scenario as [`Invariant::Custom`](super::Invariant::Custom).
+#[async_trait::async_trait]
+pub trait InvariantCheck: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Runs after the scenario completes, while the node is still running.
+    async fn check(&self, tester: &Tester, report: &ChaosReport) -> anyhow::Result<()>;
+}
+
+/// What happened during a scenario run.
+#[derive(Debug, Default)]
+pub struct ChaosReport {
+    /// Latest block when the scenario started.
+    pub start_block: u64,
+    /// Node state polled throughout the run.
+    pub samples: Vec<Sample>,
+    pub faults: Vec<AppliedFault>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    /// Time since the scenario started.
+    pub elapsed: Duration,
+    pub latest: u64,
+    pub committed: u64,
+    pub executed: u64,
+}
+
+impl Sample {
+    pub(crate) async fn fetch(tester: &Tester, elapsed: Duration) -> anyhow::Result<Self> {
+        Ok(Self {
+            elapsed,
+            latest: tester.l2_provider.get_block_number().await?,
+            committed: block_number_by_tag(tester, BlockNumberOrTag::Safe).await?,
+            executed: block_number_by_tag(tester, BlockNumberOrTag::Finalized).await?,
+        })
+    }
+
+    pub fn stage(&self, stage: Stage) -> u64 {
+        match stage {
+            Stage::Committed => self.committed,
+            Stage::Executed => self.executed,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AppliedFault {
+    pub fault: Fault,
+    pub started_at_block: u64,
+    pub ended_at_block: u64,
+}
+
+async fn block_number_by_tag(tester: &Tester, tag: BlockNumberOrTag) -> anyhow::Result<u64> {
+    let block = tester.l2_provider.get_block_by_number(tag).await?;
+    Ok(block.map_or(0, |block| block.header.number))
+}
+
+pub(crate) async fn check_no_block_gap(tester: &Tester) -> anyhow::Result<()> {
+    let latest = tester.l2_provider.get_block_number().await?;
+    let mut parent_hash = None;
+    for number in 0..=latest {
+        let block = tester
+            .l2_provider
+            .get_block_by_number(number.into())
+            .await?
+            .with_context(|| format!("block #{number} is missing"))?;
+        if let Some(parent_hash) = parent_hash {
+            anyhow::ensure!(
+                block.header.parent_hash == parent_hash,
+                "block #{number} does not extend block #{}",
+                number - 1
+            );
+        }
+        parent_hash = Some(block.header.hash);
+    }
+    Ok(())
+}
+
+pub(crate) async fn check_no_double_commit(tester: &Tester) -> anyhow::Result<()> {
+    let filter = Filter::new()
+        .address(diamond_proxy_address(tester).await?)
+        .event_signature(BlockCommit::SIGNATURE_HASH)
+        .from_block(0);
+    let logs = tester.l1_provider.get_logs(&filter).await?;
+    let mut last_committed = None;
+    for log in logs {
+        let batch_number: u64 = log
+            .log_decode::<BlockCommit>()?
+            .inner
+            .data
+            .batchNumber
+            .try_into()?;
+        if let Some(last_committed) = last_committed {
+            anyhow::ensure!(
+                batch_number == last_committed + 1,
+                "batch #{batch_number} committed after batch #{last_committed}"
+            );
+        }
+        last_committed = Some(batch_number);
+    }
+    Ok(())
+}
+
+async fn diamond_proxy_address(tester: &Tester) -> anyhow::Result<Address> {
+    let bridgehub_address = tester.l2_zk_provider.get_bridgehub_contract().await?;
+    let chain_id = tester.l2_provider.get_chain_id().await?;
+    let l1_state = L1State::fetch(
+        tester.l1_provider.clone().erased(),
+        bridgehub_address,
+        chain_id,
+    )
+    .await?;
+    Ok(l1_state.diamond_proxy_address())
+}
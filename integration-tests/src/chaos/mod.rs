@@ -0,0 +1,18 @@
+//! Fault-injection harness that runs the whole node pipeline (sequencer -> batcher -> L1) under
+//! scripted failures.
+//!
+//! A [`Scenario`] is plain data: a list of [`Fault`]s injected at given blocks or times, and
+//! [`Invariant`]s the node must uphold. Faults are injected by [`FaultProxy`]s placed between the
+//! node and the components it talks to over the network. Scenarios for new features can adjust
+//! the node config via [`Scenario::config_hook`] and add their own checks via
+//! [`Invariant::Custom`].
+
+mod invariants;
+mod proxy;
+mod runner;
+mod scenario;
+
+pub use invariants::{AppliedFault, ChaosReport, InvariantCheck, Sample};
+pub use proxy::{FaultMode, FaultProxy};
+pub use runner::run_scenario;
+pub use scenario::{Fault, FaultTarget, Invariant, Scenario, Stage, Trigger};
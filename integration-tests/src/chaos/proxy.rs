@@ -0,0 +1,155 @@
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// How [`FaultProxy`] treats the traffic passing through it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultMode {
+    /// Forward traffic as is.
+    Pass,
+    /// Close all open connections and refuse new ones.
+    Refuse,
+    /// Forward every chunk of data with the given delay.
+    Delay(Duration),
+    /// Accept connections and data, but hold all data until the mode changes.
+    Blackhole,
+}
+
+/// TCP proxy placed between two components to inject network faults into the link.
+#[derive(Debug)]
+pub struct FaultProxy {
+    address: SocketAddr,
+    upstream: Arc<OnceLock<String>>,
+    mode: watch::Sender<FaultMode>,
+    task: JoinHandle<()>,
+}
+
+impl FaultProxy {
+    /// Binds the proxy to a random local port. Connections are refused until the upstream is set
+    /// with [`Self::set_upstream()`].
+    pub async fn bind() -> anyhow::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let upstream = Arc::new(OnceLock::new());
+        let (mode, mode_receiver) = watch::channel(FaultMode::Pass);
+        let task = tokio::spawn(accept_loop(listener, upstream.clone(), mode_receiver));
+        Ok(Self {
+            address,
+            upstream,
+            mode,
+            task,
+        })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Sets the `host:port` address traffic is forwarded to. Has no effect if already set.
+    pub fn set_upstream(&self, upstream: impl Into<String>) {
+        self.upstream.get_or_init(|| upstream.into());
+    }
+
+    pub fn set_mode(&self, mode: FaultMode) {
+        tracing::info!(address = %self.address, ?mode, "changing fault proxy mode");
+        self.mode.send_replace(mode);
+    }
+
+    pub fn mode(&self) -> FaultMode {
+        *self.mode.borrow()
+    }
+}
+
+impl Drop for FaultProxy {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn accept_loop(
+    listener: TcpListener,
+    upstream: Arc<OnceLock<String>>,
+    mode: watch::Receiver<FaultMode>,
+) {
+    loop {
+        let inbound = match listener.accept().await {
+            Ok((inbound, _)) => inbound,
+            Err(err) => {
+                tracing::warn!(%err, "fault proxy failed to accept connection");
+                continue;
+            }
+        };
+        // Dropping the socket closes the connection
+        if *mode.borrow() == FaultMode::Refuse {
+            continue;
+        }
+        let Some(upstream) = upstream.get().cloned() else {
+            continue;
+        };
+        let mode = mode.clone();
+        tokio::spawn(async move {
+            if let Err(err) = forward(inbound, &upstream, mode).await {
+                tracing::debug!(%err, upstream, "fault proxy connection closed");
+            }
+        });
+    }
+}
+
+async fn forward(
+    inbound: TcpStream,
+    upstream: &str,
+    mode: watch::Receiver<FaultMode>,
+) -> io::Result<()> {
+    let outbound = TcpStream::connect(upstream).await?;
+    let (inbound_recv, inbound_send) = inbound.into_split();
+    let (outbound_recv, outbound_send) = outbound.into_split();
+    // The first error drops both directions, which closes both connections
+    tokio::try_join!(
+        pump(inbound_recv, outbound_send, mode.clone()),
+        pump(outbound_recv, inbound_send, mode),
+    )?;
+    Ok(())
+}
+
+async fn pump(
+    mut from: OwnedReadHalf,
+    mut to: OwnedWriteHalf,
+    mut mode: watch::Receiver<FaultMode>,
+) -> io::Result<()> {
+    let mut buf = vec![0; 16 * 1024];
+    loop {
+        let len = tokio::select! {
+            len = from.read(&mut buf) => len?,
+            _ = mode.wait_for(|mode| *mode == FaultMode::Refuse) => return Err(refused()),
+        };
+        if len == 0 {
+            return to.shutdown().await;
+        }
+        loop {
+            let current = *mode.borrow_and_update();
+            match current {
+                FaultMode::Pass => break,
+                FaultMode::Delay(delay) => {
+                    tokio::time::sleep(delay).await;
+                    break;
+                }
+                FaultMode::Blackhole => mode.changed().await.map_err(|_| refused())?,
+                FaultMode::Refuse => return Err(refused()),
+            }
+        }
+        to.write_all(&buf[..len]).await?;
+    }
+}
+
+fn refused() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::ConnectionReset,
+        "connection dropped by fault proxy",
+    )
+}
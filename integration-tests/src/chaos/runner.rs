@@ -0,0 +1,237 @@
+use crate::chaos::invariants::{
+    AppliedFault, ChaosReport, Sample, check_no_block_gap, check_no_double_commit,
+};
+use crate::chaos::proxy::{FaultMode, FaultProxy};
+use crate::chaos::scenario::{Fault, FaultTarget, Invariant, Scenario, Trigger};
+use crate::dyn_wallet_provider::EthDynProvider;
+use crate::utils::LockedPort;
+use crate::{ConfigHook, Tester};
+use alloy::network::TransactionBuilder;
+use alloy::primitives::{Address, U256};
+use alloy::providers::Provider;
+use alloy::rpc::types::TransactionRequest;
+use anyhow::Context;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// How often the node state is polled and faults are switched.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+/// Interval between transfers sent to keep the node producing blocks.
+const TRAFFIC_INTERVAL: Duration = Duration::from_millis(100);
+
+impl Trigger {
+    fn is_reached(&self, blocks: u64, elapsed: Duration) -> bool {
+        match *self {
+            Self::Block(block) => blocks >= block,
+            Self::Elapsed(at) => elapsed >= at,
+        }
+    }
+}
+
+struct ActiveFault {
+    fault: Fault,
+    started_at_block: u64,
+    ends_at: Instant,
+}
+
+/// Launches the nodes for `scenario`, runs it and checks its invariants.
+pub async fn run_scenario(scenario: Scenario) -> anyhow::Result<ChaosReport> {
+    tracing::info!(?scenario, "running chaos scenario");
+    let mut proxies = HashMap::new();
+
+    let l1_proxy = Arc::new(FaultProxy::bind().await?);
+    proxies.insert(FaultTarget::L1Rpc, l1_proxy.clone());
+    // Hold the port until the main node binds to it
+    let verifier_port = if scenario.with_verifier {
+        Some(LockedPort::acquire_unused().await?)
+    } else {
+        None
+    };
+    let verifier_address = verifier_port
+        .as_ref()
+        .map(|port| format!("127.0.0.1:{}", port.port));
+
+    let main_node_hook: ConfigHook = {
+        let scenario_hook = scenario.config_hook.clone();
+        let verifier_address = verifier_address.clone();
+        Arc::new(move |config| {
+            let l1_address = &config.general_config.l1_rpc_url;
+            l1_proxy.set_upstream(l1_address.trim_start_matches("http://"));
+            config.general_config.l1_rpc_url = format!("http://{}", l1_proxy.address());
+            if let Some(address) = &verifier_address {
+                config.batch_verification_config.server_enabled = true;
+                config.batch_verification_config.listen_address = address.clone();
+            }
+            if let Some(hook) = &scenario_hook {
+                hook(config);
+            }
+        })
+    };
+    let tester = Tester::builder()
+        .config_hook(main_node_hook)
+        .build()
+        .await
+        .context("failed to launch main node")?;
+    drop(verifier_port);
+
+    let external_node = match verifier_address {
+        Some(address) => {
+            let verifier_proxy = Arc::new(FaultProxy::bind().await?);
+            verifier_proxy.set_upstream(address);
+            proxies.insert(FaultTarget::BatchVerification, verifier_proxy.clone());
+            let hook: ConfigHook = Arc::new(move |config| {
+                config.batch_verification_config.client_enabled = true;
+                config.batch_verification_config.connect_address =
+                    verifier_proxy.address().to_string();
+            });
+            let external_node = tester
+                .launch_external_node_with(Some(hook))
+                .await
+                .context("failed to launch external node")?;
+            Some(external_node)
+        }
+        None => None,
+    };
+
+    let traffic = tokio::spawn(generate_traffic(tester.l2_provider.clone()));
+    let result = drive(&scenario, &tester, external_node.as_ref(), &proxies).await;
+    traffic.abort();
+    let report = result.with_context(|| format!("scenario `{}` failed", scenario.name))?;
+
+    for invariant in &scenario.invariants {
+        let result = match invariant {
+            Invariant::NoBlockGap => check_no_block_gap(&tester).await,
+            Invariant::NoDoubleCommit => check_no_double_commit(&tester).await,
+            // Checked while the scenario is running
+            Invariant::RecoversWithin { .. } => Ok(()),
+            Invariant::Custom(check) => check.check(&tester, &report).await,
+        };
+        result.with_context(|| {
+            format!(
+                "scenario `{}` violated invariant {invariant:?}",
+                scenario.name
+            )
+        })?;
+    }
+    tracing::info!(
+        scenario = scenario.name,
+        start_block = report.start_block,
+        last_sample = ?report.samples.last(),
+        "chaos scenario passed"
+    );
+    Ok(report)
+}
+
+/// Applies faults on schedule until the scenario is complete.
+async fn drive(
+    scenario: &Scenario,
+    tester: &Tester,
+    external_node: Option<&Tester>,
+    proxies: &HashMap<FaultTarget, Arc<FaultProxy>>,
+) -> anyhow::Result<ChaosReport> {
+    for fault in &scenario.faults {
+        proxy(proxies, fault.target)?;
+    }
+
+    let start_block = tester.l2_provider.get_block_number().await?;
+    let mut report = ChaosReport {
+        start_block,
+        ..ChaosReport::default()
+    };
+    let mut pending = scenario.faults.clone();
+    let mut active: Vec<ActiveFault> = vec![];
+    // Latest block at the moment the last fault was lifted
+    let mut recover_from = start_block;
+    let start = Instant::now();
+    let mut timer = tokio::time::interval(POLL_INTERVAL);
+    loop {
+        timer.tick().await;
+        anyhow::ensure!(tester.is_node_running(), "main node exited");
+        if let Some(external_node) = external_node {
+            anyhow::ensure!(external_node.is_node_running(), "external node exited");
+        }
+        let elapsed = start.elapsed();
+        anyhow::ensure!(
+            elapsed < scenario.timeout,
+            "timed out; last sample: {:?}",
+            report.samples.last()
+        );
+        let sample = Sample::fetch(tester, elapsed).await?;
+        let blocks = sample.latest - start_block;
+
+        for fault in std::mem::take(&mut pending) {
+            if fault.at.is_reached(blocks, elapsed) {
+                proxy(proxies, fault.target)?.set_mode(fault.mode);
+                active.push(ActiveFault {
+                    ends_at: Instant::now() + fault.duration,
+                    started_at_block: sample.latest,
+                    fault,
+                });
+            } else {
+                pending.push(fault);
+            }
+        }
+        for active_fault in std::mem::take(&mut active) {
+            if Instant::now() >= active_fault.ends_at {
+                proxy(proxies, active_fault.fault.target)?.set_mode(FaultMode::Pass);
+                recover_from = sample.latest;
+                report.faults.push(AppliedFault {
+                    fault: active_fault.fault,
+                    started_at_block: active_fault.started_at_block,
+                    ended_at_block: sample.latest,
+                });
+            } else {
+                active.push(active_fault);
+            }
+        }
+
+        let faults_over = pending.is_empty() && active.is_empty();
+        let mut recovered = faults_over;
+        if faults_over {
+            for invariant in &scenario.invariants {
+                let Invariant::RecoversWithin { stage, blocks } = invariant else {
+                    continue;
+                };
+                if sample.stage(*stage) >= recover_from {
+                    continue;
+                }
+                recovered = false;
+                anyhow::ensure!(
+                    sample.latest <= recover_from + blocks,
+                    "{stage:?} block #{} did not catch up with block #{recover_from} within {blocks} blocks",
+                    sample.stage(*stage)
+                );
+            }
+        }
+        report.samples.push(sample);
+        if recovered && blocks >= scenario.blocks {
+            return Ok(report);
+        }
+    }
+}
+
+fn proxy(
+    proxies: &HashMap<FaultTarget, Arc<FaultProxy>>,
+    target: FaultTarget,
+) -> anyhow::Result<&FaultProxy> {
+    proxies
+        .get(&target)
+        .map(Arc::as_ref)
+        .with_context(|| format!("fault target {target:?} is not set up by the scenario"))
+}
+
+/// Sends transfers so that the node keeps producing blocks; blocks are only produced when there
+/// are transactions to include.
+async fn generate_traffic(provider: EthDynProvider) {
+    loop {
+        let tx = TransactionRequest::default()
+            .with_to(Address::random())
+            .with_value(U256::from(1));
+        if let Err(err) = provider.send_transaction(tx).await {
+            tracing::warn!(%err, "failed to send transfer");
+        }
+        tokio::time::sleep(TRAFFIC_INTERVAL).await;
+    }
+}
@@ -0,0 +1,115 @@
+use crate::ConfigHook;
+use crate::chaos::invariants::InvariantCheck;
+use crate::chaos::proxy::FaultMode;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Failure scenario: faults injected into a running node together with invariants the node must
+/// uphold.
+pub struct Scenario {
+    pub name: &'static str,
+    /// Number of L2 blocks to produce after the scenario starts. The scenario keeps running past
+    /// this number until all faults are over and all invariants are satisfied.
+    pub blocks: u64,
+    /// Launch an external node that signs batches for the main node. Required for
+    /// [`FaultTarget::BatchVerification`].
+    pub with_verifier: bool,
+    /// Adjusts the main node config, e.g. to slow down provers.
+    pub config_hook: Option<ConfigHook>,
+    pub faults: Vec<Fault>,
+    pub invariants: Vec<Invariant>,
+    /// Upper bound on the scenario run time.
+    pub timeout: Duration,
+}
+
+impl Scenario {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            blocks: 30,
+            with_verifier: false,
+            config_hook: None,
+            faults: vec![],
+            invariants: vec![Invariant::NoBlockGap, Invariant::NoDoubleCommit],
+            timeout: Duration::from_secs(300),
+        }
+    }
+}
+
+impl fmt::Debug for Scenario {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scenario")
+            .field("name", &self.name)
+            .field("blocks", &self.blocks)
+            .field("with_verifier", &self.with_verifier)
+            .field("faults", &self.faults)
+            .field("invariants", &self.invariants)
+            .field("timeout", &self.timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Fault applied to a link between the node and another component for `duration`.
+#[derive(Debug, Clone)]
+pub struct Fault {
+    pub at: Trigger,
+    pub target: FaultTarget,
+    pub mode: FaultMode,
+    pub duration: Duration,
+}
+
+/// Moment a fault starts at, relative to the start of the scenario.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// Once the node has produced the given number of blocks.
+    Block(u64),
+    /// Once the given time has passed.
+    Elapsed(Duration),
+}
+
+/// Link a fault is injected into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FaultTarget {
+    /// Main node -> L1 RPC.
+    L1Rpc,
+    /// External node -> main node batch verification server.
+    BatchVerification,
+}
+
+/// Pipeline stage tracked by the node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    /// Last block committed on L1 (`safe` block tag).
+    Committed,
+    /// Last block executed on L1 (`finalized` block tag).
+    Executed,
+}
+
+#[derive(Clone)]
+pub enum Invariant {
+    /// All blocks up to the latest one are present and are chained by parent hashes.
+    NoBlockGap,
+    /// Every batch is committed on L1 exactly once, in order.
+    NoDoubleCommit,
+    /// Once the last fault is over, `stage` catches up with the latest block before the node
+    /// produces `blocks` more blocks.
+    RecoversWithin { stage: Stage, blocks: u64 },
+    /// Check provided by the scenario, run after the scenario completes.
+    Custom(Arc<dyn InvariantCheck>),
+}
+
+impl fmt::Debug for Invariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoBlockGap => f.write_str("NoBlockGap"),
+            Self::NoDoubleCommit => f.write_str("NoDoubleCommit"),
+            Self::RecoversWithin { stage, blocks } => f
+                .debug_struct("RecoversWithin")
+                .field("stage", stage)
+                .field("blocks", blocks)
+                .finish(),
+            Self::Custom(check) => f.debug_tuple("Custom").field(&check.name()).finish(),
+        }
+    }
+}
@@ -15,12 +15,14 @@ use tokio::sync::watch;
 use tokio::task::JoinHandle;
 use zksync_os_object_store::{ObjectStoreConfig, ObjectStoreMode};
 use zksync_os_server::config::{
-    Config, FakeFriProversConfig, FakeSnarkProversConfig, GeneralConfig, GenesisConfig,
-    ProverApiConfig, ProverInputGeneratorConfig, RpcConfig, SequencerConfig, StatusServerConfig,
+    AdminApiConfig, Config, FakeFriProversConfig, FakeSnarkProversConfig, GeneralConfig,
+    GenesisConfig, ProverApiConfig, ProverInputGeneratorConfig, RpcConfig, SequencerConfig,
+    StatusServerConfig,
 };
 use zksync_os_state_full_diffs::FullDiffsState;
 
 pub mod assert_traits;
+pub mod chaos;
 pub mod contracts;
 pub mod dyn_wallet_provider;
 mod network;
@@ -31,6 +33,12 @@ mod utils;
 /// L1 chain id as expected by contracts deployed in `zkos-l1-state.json`
 const L1_CHAIN_ID: u64 = 31337;
 
+/// Bearer token of the admin API of launched nodes.
+pub const ADMIN_API_TOKEN: &str = "integration-tests-admin-token";
+
+/// Adjusts node config right before the node is launched.
+pub type ConfigHook = Arc<dyn Fn(&mut Config) + Send + Sync>;
+
 pub struct Tester {
     pub l1_provider: EthDynProvider,
     pub l2_provider: EthDynProvider,
@@ -52,6 +60,11 @@ pub struct Tester {
     tempdir: Arc<tempfile::TempDir>,
     main_node_tempdir: Arc<tempfile::TempDir>,
 
+    /// URL of the node's admin JSON-RPC API (authenticated with [`ADMIN_API_TOKEN`]).
+    pub admin_api_url: String,
+    /// URL of the node's status server.
+    pub status_url: String,
+
     // Needed to be able to connect external nodes
     l1_address: String,
     replay_url: String,
@@ -68,6 +81,14 @@ impl Tester {
     }
 
     pub async fn launch_external_node(&self) -> anyhow::Result<Self> {
+        self.launch_external_node_with(None).await
+    }
+
+    /// Launches an external node with its config adjusted by `config_hook`.
+    pub async fn launch_external_node_with(
+        &self,
+        config_hook: Option<ConfigHook>,
+    ) -> anyhow::Result<Self> {
         Self::launch_node(
             self.l1_address.clone(),
             self.l1_provider.clone(),
@@ -76,10 +97,55 @@ impl Tester {
             Some((self.replay_url.clone(), self.l2_rpc_address.clone())),
             None,
             Some(self.main_node_tempdir.clone()),
+            config_hook,
         )
         .await
     }
 
+    /// Calls an admin API method and returns its result.
+    pub async fn admin_call(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let response = reqwest::Client::new()
+            .post(&self.admin_api_url)
+            .bearer_auth(ADMIN_API_TOKEN)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(request.to_string())
+            .send()
+            .await?
+            .text()
+            .await?;
+        let response: serde_json::Value = serde_json::from_str(&response)?;
+        if let Some(error) = response.get("error") {
+            anyhow::bail!("admin API call `{method}` failed: {error}");
+        }
+        Ok(response["result"].clone())
+    }
+
+    /// Fetches the node's `/debug/status`.
+    pub async fn debug_status(&self) -> anyhow::Result<serde_json::Value> {
+        let response = reqwest::get(format!("{}/debug/status", self.status_url))
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        Ok(serde_json::from_str(&response)?)
+    }
+
+    /// Returns `false` if the node has exited, e.g. because one of its components failed.
+    pub fn is_node_running(&self) -> bool {
+        !self.main_task.is_finished()
+    }
+
+    #[allow(clippy::too_many_arguments)]
     async fn launch_node(
         l1_address: String,
         l1_provider: EthDynProvider,
@@ -88,6 +154,7 @@ impl Tester {
         main_node_replay_and_rpc_urls: Option<(String, String)>,
         block_time: Option<Duration>,
         main_node_tempdir: Option<Arc<tempfile::TempDir>>,
+        config_hook: Option<ConfigHook>,
     ) -> anyhow::Result<Self> {
         (|| async {
             // Wait for L1 node to get up and be able to respond.
@@ -109,11 +176,13 @@ impl Tester {
         let prover_api_locked_port = LockedPort::acquire_unused().await?;
         let replay_locked_port = LockedPort::acquire_unused().await?;
         let status_locked_port = LockedPort::acquire_unused().await?;
+        let admin_api_locked_port = LockedPort::acquire_unused().await?;
         let l2_rpc_address = format!("0.0.0.0:{}", l2_locked_port.port);
         let l2_rpc_ws_url = format!("ws://localhost:{}", l2_locked_port.port);
         let prover_api_address = format!("0.0.0.0:{}", prover_api_locked_port.port);
         let replay_address = format!("0.0.0.0:{}", replay_locked_port.port);
         let status_address = format!("0.0.0.0:{}", status_locked_port.port);
+        let status_url = format!("http://localhost:{}", status_locked_port.port);
         let replay_url = format!("localhost:{}", replay_locked_port.port);
 
         let tempdir = tempfile::tempdir()?;
@@ -169,9 +238,10 @@ impl Tester {
 
         let status_server_config = StatusServerConfig {
             address: status_address,
+            ..Default::default()
         };
 
-        let config = Config {
+        let mut config = Config {
             general_config,
             genesis_config: GenesisConfig {
                 genesis_input_path: Some(
@@ -192,10 +262,21 @@ impl Tester {
             },
             prover_api_config,
             status_server_config,
+            admin_api_config: AdminApiConfig {
+                enabled: true,
+                address: format!("127.0.0.1:{}", admin_api_locked_port.port),
+                auth_token: Some(ADMIN_API_TOKEN.into()),
+                audit_log_path: None,
+                ..Default::default()
+            },
             observability_config: Default::default(),
             gas_adjuster_config: Default::default(),
             batch_verification_config: Default::default(),
+            backup_config: Default::default(),
         };
+        if let Some(config_hook) = config_hook {
+            config_hook(&mut config);
+        }
         let main_task = tokio::task::spawn(async move {
             zksync_os_server::run::<FullDiffsState>(stop_receiver, config).await;
         });
@@ -291,6 +372,8 @@ impl Tester {
             ),
             stop_sender,
             main_task,
+            admin_api_url: format!("http://localhost:{}", admin_api_locked_port.port),
+            status_url,
             l1_address,
             l2_rpc_address: l2_rpc_address.replace("0.0.0.0:", "http://localhost:"),
             replay_url,
@@ -304,6 +387,7 @@ impl Tester {
 pub struct TesterBuilder {
     enable_prover: bool,
     block_time: Option<Duration>,
+    config_hook: Option<ConfigHook>,
 }
 
 impl TesterBuilder {
@@ -318,6 +402,11 @@ impl TesterBuilder {
         self
     }
 
+    pub fn config_hook(mut self, config_hook: ConfigHook) -> Self {
+        self.config_hook = Some(config_hook);
+        self
+    }
+
     pub async fn build(self) -> anyhow::Result<Tester> {
         let l1_locked_port = LockedPort::acquire_unused().await?;
         let l1_address = format!("http://localhost:{}", l1_locked_port.port);
@@ -340,6 +429,7 @@ impl TesterBuilder {
             None,
             self.block_time,
             None,
+            self.config_hook,
         )
         .await
     }
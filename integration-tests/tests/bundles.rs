@@ -0,0 +1,198 @@
+use alloy::eips::Encodable2718;
+use alloy::network::{EthereumWallet, TransactionBuilder};
+use alloy::primitives::{Address, Bytes, U256};
+use alloy::providers::Provider;
+use alloy::rpc::types::TransactionRequest;
+use alloy::signers::SignerSync;
+use alloy::signers::local::PrivateKeySigner;
+use backon::{ConstantBuilder, Retryable};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use std::time::Duration;
+use zksync_os_integration_tests::Tester;
+use zksync_os_integration_tests::assert_traits::ReceiptAssert;
+use zksync_os_types::{BundleInclusion, SignedTransactionBundle, TransactionBundle};
+
+/// Launches a node accepting bundles signed by `bundle_signer` and funds a fresh account that
+/// bundle transactions are sent from.
+async fn setup(bundle_signer: &PrivateKeySigner) -> anyhow::Result<(Tester, PrivateKeySigner)> {
+    let bundle_signer = bundle_signer.address().to_string();
+    let tester = Tester::builder()
+        .config_hook(Arc::new(move |config| {
+            config.sequencer_config.bundle_signers = vec![bundle_signer.clone()];
+        }))
+        .build()
+        .await?;
+
+    let sender = PrivateKeySigner::random();
+    tester
+        .l2_provider
+        .send_transaction(
+            TransactionRequest::default()
+                .with_to(sender.address())
+                .with_value(U256::from(10).pow(U256::from(18))),
+        )
+        .await?
+        .expect_successful_receipt()
+        .await?;
+    Ok((tester, sender))
+}
+
+/// Signs a transfer from `sender` with the given nonce and returns it EIP-2718 encoded.
+async fn raw_transfer(
+    tester: &Tester,
+    sender: &PrivateKeySigner,
+    nonce: u64,
+) -> anyhow::Result<Bytes> {
+    let gas_price = tester.l2_provider.get_gas_price().await?;
+    let tx = TransactionRequest::default()
+        .with_from(sender.address())
+        .with_to(Address::random())
+        .with_value(U256::from(100))
+        .with_nonce(nonce)
+        .with_gas_limit(100_000)
+        .with_max_fee_per_gas(gas_price * 2)
+        .with_max_priority_fee_per_gas(0)
+        .with_chain_id(tester.l2_provider.get_chain_id().await?)
+        .build(&EthereumWallet::new(sender.clone()))
+        .await?;
+    Ok(tx.encoded_2718().into())
+}
+
+async fn submit_bundle(
+    tester: &Tester,
+    bundle_signer: &PrivateKeySigner,
+    transactions: Vec<Bytes>,
+    inclusion: BundleInclusion,
+) -> anyhow::Result<Value> {
+    let bundle = TransactionBundle {
+        transactions,
+        inclusion,
+    };
+    let signature = bundle_signer.sign_hash_sync(&bundle.hash())?;
+    let signed = SignedTransactionBundle { bundle, signature };
+    tester
+        .admin_call("admin_submitBundle", json!([signed]))
+        .await
+}
+
+/// Polls the bundle status until it's no longer pending. Bundles are only attempted in produced
+/// blocks, so a transfer is sent before each poll to keep blocks coming.
+async fn wait_for_resolution(tester: &Tester, hash: &Value) -> anyhow::Result<Value> {
+    (|| async {
+        tester
+            .l2_provider
+            .send_transaction(
+                TransactionRequest::default()
+                    .with_to(Address::random())
+                    .with_value(U256::from(1)),
+            )
+            .await?
+            .expect_successful_receipt()
+            .await?;
+        let status = tester
+            .admin_call("admin_getBundleStatus", json!([hash]))
+            .await?;
+        if status["status"] == "pending" {
+            anyhow::bail!("bundle is still pending: {status}");
+        }
+        Ok(status)
+    })
+    .retry(
+        ConstantBuilder::default()
+            .with_delay(Duration::from_millis(500))
+            .with_max_times(60),
+    )
+    .await
+}
+
+#[test_log::test(tokio::test)]
+async fn bundle_is_included_atomically() -> anyhow::Result<()> {
+    let bundle_signer = PrivateKeySigner::random();
+    let (tester, sender) = setup(&bundle_signer).await?;
+
+    let mut transactions = vec![];
+    for nonce in 0..3 {
+        transactions.push(raw_transfer(&tester, &sender, nonce).await?);
+    }
+    let tx_hashes: Vec<_> = transactions
+        .iter()
+        .map(alloy::primitives::keccak256)
+        .collect();
+    let next_block = tester.l2_provider.get_block_number().await? + 1;
+    let hash = submit_bundle(
+        &tester,
+        &bundle_signer,
+        transactions,
+        BundleInclusion::ExpiryBlock(next_block + 100),
+    )
+    .await?;
+
+    let status = wait_for_resolution(&tester, &hash).await?;
+    assert_eq!(status["status"], "included", "{status}");
+    let block_number = status["blockNumber"].as_u64().unwrap();
+
+    // All transactions are in the same block, in the bundle order and before other transactions
+    for (index, tx_hash) in tx_hashes.iter().enumerate() {
+        let receipt = tester
+            .l2_provider
+            .get_transaction_receipt(*tx_hash)
+            .await?
+            .expect("bundle transaction is not included");
+        assert!(receipt.status());
+        assert_eq!(receipt.block_number, Some(block_number));
+        assert_eq!(receipt.transaction_index, Some(index as u64));
+    }
+    assert_eq!(
+        tester
+            .l2_provider
+            .get_transaction_count(sender.address())
+            .await?,
+        3
+    );
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn bundle_with_failing_transaction_is_rejected_until_expiry() -> anyhow::Result<()> {
+    let bundle_signer = PrivateKeySigner::random();
+    let (tester, sender) = setup(&bundle_signer).await?;
+
+    // The second transaction has a nonce gap, so the first one must not be included either
+    let transactions = vec![
+        raw_transfer(&tester, &sender, 0).await?,
+        raw_transfer(&tester, &sender, 2).await?,
+    ];
+    let expiry_block = tester.l2_provider.get_block_number().await? + 3;
+    let hash = submit_bundle(
+        &tester,
+        &bundle_signer,
+        transactions,
+        BundleInclusion::ExpiryBlock(expiry_block),
+    )
+    .await?;
+
+    let status = wait_for_resolution(&tester, &hash).await?;
+    assert_eq!(status["status"], "expired", "{status}");
+    assert!(status["attempts"].as_u64().unwrap() >= 1, "{status}");
+    assert!(status["lastFailure"].is_string(), "{status}");
+    assert_eq!(
+        tester
+            .l2_provider
+            .get_transaction_count(sender.address())
+            .await?,
+        0
+    );
+
+    // Bundles from unknown signers are rejected
+    let error = submit_bundle(
+        &tester,
+        &PrivateKeySigner::random(),
+        vec![raw_transfer(&tester, &sender, 0).await?],
+        BundleInclusion::ExpiryBlock(expiry_block + 100),
+    )
+    .await
+    .expect_err("bundle from unknown signer should be rejected");
+    assert!(error.to_string().contains("not allowed"), "{error}");
+    Ok(())
+}
@@ -0,0 +1,254 @@
+#![cfg(feature = "chaos-tests")]
+
+use anyhow::Context;
+use std::sync::Arc;
+use std::time::Duration;
+use zksync_os_integration_tests::Tester;
+use zksync_os_integration_tests::chaos::{
+    ChaosReport, Fault, FaultMode, FaultTarget, Invariant, InvariantCheck, Scenario, Stage,
+    Trigger, run_scenario,
+};
+use zksync_os_server::config::Config;
+
+fn recovers_within(stage: Stage, blocks: u64) -> Vec<Invariant> {
+    vec![
+        Invariant::NoBlockGap,
+        Invariant::NoDoubleCommit,
+        Invariant::RecoversWithin { stage, blocks },
+    ]
+}
+
+#[test_log::test(tokio::test)]
+async fn l1_rpc_flapping() -> anyhow::Result<()> {
+    // Short L1 outages, each within the retry budget of the node's L1 provider
+    let faults = (1..=4)
+        .map(|i| Fault {
+            at: Trigger::Block(5 * i),
+            target: FaultTarget::L1Rpc,
+            mode: FaultMode::Refuse,
+            duration: Duration::from_millis(300),
+        })
+        .collect();
+    run_scenario(Scenario {
+        faults,
+        invariants: recovers_within(Stage::Committed, 40),
+        ..Scenario::new("l1_rpc_flapping")
+    })
+    .await?;
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn l1_rpc_slow() -> anyhow::Result<()> {
+    run_scenario(Scenario {
+        faults: vec![Fault {
+            at: Trigger::Block(5),
+            target: FaultTarget::L1Rpc,
+            mode: FaultMode::Delay(Duration::from_millis(300)),
+            duration: Duration::from_secs(15),
+        }],
+        invariants: recovers_within(Stage::Committed, 40),
+        ..Scenario::new("l1_rpc_slow")
+    })
+    .await?;
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn l1_rpc_stalled() -> anyhow::Result<()> {
+    // L1 requests hang instead of failing; the sequencer must not depend on L1 to produce blocks
+    let report = run_scenario(Scenario {
+        faults: vec![Fault {
+            at: Trigger::Block(5),
+            target: FaultTarget::L1Rpc,
+            mode: FaultMode::Blackhole,
+            duration: Duration::from_secs(5),
+        }],
+        invariants: recovers_within(Stage::Committed, 40),
+        ..Scenario::new("l1_rpc_stalled")
+    })
+    .await?;
+    let fault = &report.faults[0];
+    assert!(fault.ended_at_block > fault.started_at_block);
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn batch_verifier_stalled() -> anyhow::Result<()> {
+    // Signing requests time out on the server and are retried
+    run_scenario(Scenario {
+        with_verifier: true,
+        faults: vec![Fault {
+            at: Trigger::Block(5),
+            target: FaultTarget::BatchVerification,
+            mode: FaultMode::Blackhole,
+            duration: Duration::from_secs(10),
+        }],
+        invariants: recovers_within(Stage::Committed, 60),
+        ..Scenario::new("batch_verifier_stalled")
+    })
+    .await?;
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn batch_verifier_disconnected() -> anyhow::Result<()> {
+    // The external node reconnects once the server is reachable again
+    run_scenario(Scenario {
+        with_verifier: true,
+        faults: vec![Fault {
+            at: Trigger::Block(5),
+            target: FaultTarget::BatchVerification,
+            mode: FaultMode::Refuse,
+            duration: Duration::from_secs(10),
+        }],
+        invariants: recovers_within(Stage::Committed, 60),
+        ..Scenario::new("batch_verifier_disconnected")
+    })
+    .await?;
+    Ok(())
+}
+
+/// Commits keep landing on L1 while proving lags behind.
+struct CommitsAheadOfExecution;
+
+#[async_trait::async_trait]
+impl InvariantCheck for CommitsAheadOfExecution {
+    fn name(&self) -> &'static str {
+        "commits_ahead_of_execution"
+    }
+
+    async fn check(&self, _tester: &Tester, report: &ChaosReport) -> anyhow::Result<()> {
+        let last = report.samples.last().expect("no samples");
+        anyhow::ensure!(
+            last.committed > report.start_block,
+            "no blocks committed during the scenario"
+        );
+        anyhow::ensure!(
+            report
+                .samples
+                .iter()
+                .any(|sample| sample.committed > sample.executed),
+            "prover backlog has not formed"
+        );
+        Ok(())
+    }
+}
+
+#[test_log::test(tokio::test)]
+async fn prover_backlog() -> anyhow::Result<()> {
+    run_scenario(Scenario {
+        config_hook: Some(Arc::new(|config| {
+            let provers = &mut config.prover_api_config.fake_fri_provers;
+            provers.workers = 1;
+            provers.compute_time = Duration::from_secs(5);
+        })),
+        invariants: vec![
+            Invariant::NoBlockGap,
+            Invariant::NoDoubleCommit,
+            Invariant::RecoversWithin {
+                stage: Stage::Committed,
+                blocks: 40,
+            },
+            Invariant::Custom(Arc::new(CommitsAheadOfExecution)),
+        ],
+        ..Scenario::new("prover_backlog")
+    })
+    .await?;
+    Ok(())
+}
+
+/// Makes the L1 sender watchdog react within seconds. Thresholds leave room for the normal commit
+/// latency with fake provers.
+fn with_fast_watchdog(config: &mut Config) {
+    let l1_sender = &mut config.l1_sender_config;
+    l1_sender.watchdog_poll_interval = Duration::from_millis(500);
+    l1_sender.watchdog_warning_stall = Duration::from_secs(8);
+    l1_sender.watchdog_critical_stall = Duration::from_secs(12);
+}
+
+/// The L1 sender watchdog raised a critical alert with the expected diagnosis hint.
+struct WatchdogAlerted {
+    hint: &'static str,
+}
+
+#[async_trait::async_trait]
+impl InvariantCheck for WatchdogAlerted {
+    fn name(&self) -> &'static str {
+        "watchdog_alerted"
+    }
+
+    async fn check(&self, tester: &Tester, _report: &ChaosReport) -> anyhow::Result<()> {
+        let status = tester.debug_status().await?;
+        let alerts = status["batches"]["l1_sender_watchdog"]["recent_alerts"]
+            .as_array()
+            .context("watchdog status is missing")?;
+        anyhow::ensure!(
+            alerts
+                .iter()
+                .any(|alert| alert["level"] == "critical" && alert["hint"] == self.hint),
+            "no critical watchdog alert with hint `{}`: {alerts:?}",
+            self.hint
+        );
+        Ok(())
+    }
+}
+
+fn watchdog_alerted(hint: &'static str) -> Invariant {
+    Invariant::Custom(Arc::new(WatchdogAlerted { hint }))
+}
+
+#[test_log::test(tokio::test)]
+async fn watchdog_l1_unreachable() -> anyhow::Result<()> {
+    let mut invariants = recovers_within(Stage::Committed, 80);
+    invariants.push(watchdog_alerted("l1_unreachable"));
+    run_scenario(Scenario {
+        config_hook: Some(Arc::new(with_fast_watchdog)),
+        faults: vec![Fault {
+            at: Trigger::Block(5),
+            target: FaultTarget::L1Rpc,
+            mode: FaultMode::Blackhole,
+            duration: Duration::from_secs(30),
+        }],
+        invariants,
+        ..Scenario::new("watchdog_l1_unreachable")
+    })
+    .await?;
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn watchdog_batch_verifier_stalled() -> anyhow::Result<()> {
+    let mut invariants = recovers_within(Stage::Committed, 80);
+    invariants.push(watchdog_alerted("no_batches_to_commit"));
+    run_scenario(Scenario {
+        with_verifier: true,
+        config_hook: Some(Arc::new(with_fast_watchdog)),
+        faults: vec![Fault {
+            at: Trigger::Block(5),
+            target: FaultTarget::BatchVerification,
+            mode: FaultMode::Blackhole,
+            duration: Duration::from_secs(30),
+        }],
+        invariants,
+        ..Scenario::new("watchdog_batch_verifier_stalled")
+    })
+    .await?;
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn watchdog_operator_balance() -> anyhow::Result<()> {
+    // Commits never resume, so the alert is still active when the scenario completes
+    run_scenario(Scenario {
+        blocks: 80,
+        config_hook: Some(Arc::new(|config| {
+            with_fast_watchdog(config);
+            config.l1_sender_config.min_operator_balance_gwei = u64::MAX;
+        })),
+        invariants: vec![Invariant::NoBlockGap, watchdog_alerted("operator_balance")],
+        ..Scenario::new("watchdog_operator_balance")
+    })
+    .await?;
+    Ok(())
+}
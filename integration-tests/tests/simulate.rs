@@ -0,0 +1,230 @@
+use alloy::network::TransactionBuilder;
+use alloy::primitives::{Address, B256, U256, address};
+use alloy::providers::Provider;
+use alloy::rpc::types::simulate::{SimBlock, SimulatePayload, SimulatedBlock};
+use alloy::rpc::types::state::{AccountOverride, StateOverride};
+use alloy::rpc::types::{BlockOverrides, TransactionRequest};
+use std::collections::HashMap;
+use std::sync::Arc;
+use zksync_os_integration_tests::Tester;
+use zksync_os_integration_tests::contracts::TestERC20;
+use zksync_os_integration_tests::dyn_wallet_provider::EthDynProvider;
+
+/// Storage slot of `TestERC20.totalSupply`.
+const TOTAL_SUPPLY_SLOT: B256 = B256::with_last_byte(3);
+
+fn sim_block(calls: Vec<TransactionRequest>) -> SimBlock {
+    SimBlock {
+        block_overrides: None,
+        state_overrides: None,
+        calls,
+    }
+}
+
+fn payload(block_state_calls: Vec<SimBlock>, validation: bool) -> SimulatePayload {
+    SimulatePayload {
+        block_state_calls,
+        trace_transfers: false,
+        validation,
+        return_full_transactions: false,
+    }
+}
+
+/// Returns the return data of each call interpreted as `uint256`.
+fn returned_values(block: &SimulatedBlock) -> Vec<U256> {
+    block
+        .calls
+        .iter()
+        .map(|call| {
+            assert!(call.status, "call failed: {call:?}");
+            U256::from_be_slice(&call.return_data)
+        })
+        .collect()
+}
+
+async fn deploy_token(
+    tester: &Tester,
+) -> anyhow::Result<TestERC20::TestERC20Instance<EthDynProvider>> {
+    Ok(TestERC20::deploy(
+        tester.l2_provider.clone(),
+        U256::ZERO,
+        "Test token".to_string(),
+        "TEST".to_string(),
+    )
+    .await?)
+}
+
+#[test_log::test(tokio::test)]
+async fn simulate_carries_state_over() -> anyhow::Result<()> {
+    let tester = Tester::setup().await?;
+    let alice = tester.l2_wallet.default_signer().address();
+    let token = deploy_token(&tester).await?;
+    let mint = token
+        .mint(alice, U256::from(5))
+        .into_transaction_request()
+        .with_from(alice);
+    let total_supply = token.totalSupply().into_transaction_request();
+
+    let blocks = tester
+        .l2_provider
+        .simulate(&payload(
+            vec![
+                // State changes of a call are visible to the next calls in the block...
+                sim_block(vec![
+                    mint.clone(),
+                    total_supply.clone(),
+                    mint.clone(),
+                    total_supply.clone(),
+                ]),
+                // ...and in the next blocks
+                sim_block(vec![total_supply.clone()]),
+            ],
+            false,
+        ))
+        .await?;
+    assert_eq!(blocks.len(), 2);
+    let values = returned_values(&blocks[0]);
+    assert_eq!((values[1], values[3]), (U256::from(5), U256::from(10)));
+    assert_eq!(returned_values(&blocks[1]), [U256::from(10)]);
+    // Mints emit `Transfer` events
+    assert_eq!(blocks[0].calls[0].logs.len(), 1);
+    assert_eq!(blocks[0].calls[0].logs[0].address(), *token.address());
+
+    let latest = tester.l2_provider.get_block_number().await?;
+    assert_eq!(blocks[0].inner.header.number, latest + 1);
+    assert_eq!(blocks[1].inner.header.number, latest + 2);
+    assert_eq!(
+        blocks[1].inner.header.parent_hash,
+        blocks[0].inner.header.hash
+    );
+
+    // Nothing is persisted
+    let out = tester.l2_provider.call(total_supply).await?;
+    assert_eq!(U256::from_be_slice(&out), U256::ZERO);
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn simulate_overrides_take_precedence() -> anyhow::Result<()> {
+    let tester = Tester::setup().await?;
+    let alice = tester.l2_wallet.default_signer().address();
+    let token = deploy_token(&tester).await?;
+    let mint = token
+        .mint(alice, U256::from(5))
+        .into_transaction_request()
+        .with_from(alice);
+    let total_supply = token.totalSupply().into_transaction_request();
+
+    let overridden_block = SimBlock {
+        block_overrides: Some(BlockOverrides {
+            time: Some(4_000_000_000),
+            ..BlockOverrides::default()
+        }),
+        state_overrides: Some(StateOverride::from_iter([(
+            *token.address(),
+            AccountOverride {
+                state_diff: Some(HashMap::from([(
+                    TOTAL_SUPPLY_SLOT,
+                    B256::from(U256::from(42)),
+                )])),
+                ..AccountOverride::default()
+            },
+        )])),
+        calls: vec![total_supply.clone()],
+    };
+    let blocks = tester
+        .l2_provider
+        .simulate(&payload(
+            vec![
+                sim_block(vec![mint.clone()]),
+                // State overrides take precedence over state changes of previous blocks
+                overridden_block,
+                // Overrides persist in the next blocks
+                sim_block(vec![mint, total_supply]),
+            ],
+            false,
+        ))
+        .await?;
+    assert_eq!(returned_values(&blocks[1]), [U256::from(42)]);
+    assert_eq!(returned_values(&blocks[2])[1], U256::from(47));
+    assert_eq!(blocks[1].inner.header.timestamp, 4_000_000_000);
+    assert_eq!(blocks[2].inner.header.timestamp, 4_000_000_001);
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn simulate_validation() -> anyhow::Result<()> {
+    let tester = Tester::setup().await?;
+    let alice = tester.l2_wallet.default_signer().address();
+    let nonce = tester.l2_provider.get_transaction_count(alice).await?;
+    let transfer = TransactionRequest::default()
+        .with_from(alice)
+        .with_to(Address::random())
+        .with_value(U256::from(100))
+        .with_nonce(nonce + 10);
+
+    // Without validation, nonces are set automatically
+    let mut request = payload(vec![sim_block(vec![transfer.clone()])], false);
+    request.trace_transfers = true;
+    let blocks = tester.l2_provider.simulate(&request).await?;
+    let logs = &blocks[0].calls[0].logs;
+    assert_eq!(logs.len(), 1);
+    assert_eq!(
+        logs[0].address(),
+        address!("0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE")
+    );
+
+    let err = tester
+        .l2_provider
+        .simulate(&payload(vec![sim_block(vec![transfer])], true))
+        .await
+        .expect_err("simulation with invalid nonce should fail");
+    assert!(err.to_string().contains("invalid transaction"), "{err}");
+    Ok(())
+}
+
+#[test_log::test(tokio::test)]
+async fn simulate_limits() -> anyhow::Result<()> {
+    let tester = Tester::builder()
+        .config_hook(Arc::new(|config| {
+            config.rpc_config.simulate_max_blocks = 2;
+            config.rpc_config.simulate_max_calls = 2;
+            config.rpc_config.simulate_max_gas = 1_000_000;
+        }))
+        .build()
+        .await?;
+    let call = TransactionRequest::default().with_gas_limit(100_000);
+
+    let blocks = tester
+        .l2_provider
+        .simulate(&payload(
+            vec![sim_block(vec![call.clone()]), sim_block(vec![call.clone()])],
+            false,
+        ))
+        .await?;
+    assert_eq!(blocks.len(), 2);
+
+    let cases = [
+        (
+            vec![sim_block(vec![]), sim_block(vec![]), sim_block(vec![])],
+            "too many blocks to simulate (max: 2)",
+        ),
+        (
+            vec![sim_block(vec![call.clone(); 3])],
+            "too many calls to simulate (max: 2)",
+        ),
+        (
+            vec![sim_block(vec![call.with_gas_limit(2_000_000)])],
+            "total gas limit of simulated calls exceeds 1000000",
+        ),
+    ];
+    for (block_state_calls, expected_error) in cases {
+        let err = tester
+            .l2_provider
+            .simulate(&payload(block_state_calls, false))
+            .await
+            .expect_err("simulation over the limits should fail");
+        assert!(err.to_string().contains(expected_error), "{err}");
+    }
+    Ok(())
+}
@@ -1,13 +1,60 @@
-use alloy::primitives::{Address, Signature as AlloySignature, SignatureError};
+use crate::signature_verification::{
+    AcceptedSigner, SignatureVerificationContext, SignatureVerificationError,
+};
+use alloy::primitives::{
+    Address, B256, Signature as AlloySignature, SignatureError, U256, eip191_hash_message,
+    keccak256,
+};
 use alloy::signers::Signer;
 use alloy::signers::local::PrivateKeySigner;
 use alloy::sol_types::SolValue;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
 use zksync_os_contract_interface::IExecutor::CommitBatchInfoZKsyncOS;
 use zksync_os_contract_interface::models::CommitBatchInfo;
 
+/// Domain separator prepended to the payload of [`BatchSigningHash::signing_hash()`]. It names the
+/// purpose and version of the payload, so that batch signatures can't be passed off as signatures
+/// of other messages signed by verifier keys (e.g. the identity message of the batch verification
+/// handshake), and vice versa.
+pub const BATCH_SIGNING_DOMAIN: &[u8] = b"zksync-os batch verification commit v1:";
+
+/// Canonical payload signed by batch verifiers.
+pub trait BatchSigningHash {
+    /// Returns `keccak256(BATCH_SIGNING_DOMAIN ++ abi.encode(chainId, batchNumber, commitInfo))`,
+    /// where `commitInfo` is the `CommitBatchInfoZKsyncOS` struct committed to L1. The hash only
+    /// depends on the batch contents, so a signer produces the same signature for every request
+    /// of the batch. It's signed as an EIP-191 message.
+    fn signing_hash(&self, chain_id: u64) -> B256;
+}
+
+impl BatchSigningHash for CommitBatchInfo {
+    fn signing_hash(&self, chain_id: u64) -> B256 {
+        let payload = (
+            U256::from(chain_id),
+            U256::from(self.batch_number),
+            CommitBatchInfoZKsyncOS::from(self.clone()),
+        )
+            .abi_encode_params();
+        keccak256([BATCH_SIGNING_DOMAIN, payload.as_slice()].concat())
+    }
+}
+
+/// Signatures of a batch by distinct signers.
+///
+/// Signers can be weighted for quorum purposes; the weight is stored in each signature, so the
+/// set is serialized as a plain list of signatures.
 #[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct BatchSignatureSet(Vec<ValidatedBatchSignature>);
+#[serde(
+    from = "Vec<ValidatedBatchSignature>",
+    into = "Vec<ValidatedBatchSignature>"
+)]
+pub struct BatchSignatureSet {
+    signatures: Vec<ValidatedBatchSignature>,
+    /// Weights of signers; signers missing from the map have weight 1.
+    weights: Option<Arc<BTreeMap<Address, u64>>>,
+}
 
 #[derive(Debug, thiserror::Error)]
 pub enum BatchSignatureSetError {
@@ -18,57 +65,152 @@ pub enum BatchSignatureSetError {
 impl BatchSignatureSet {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
-        BatchSignatureSet(Vec::new())
+        BatchSignatureSet {
+            signatures: Vec::new(),
+            weights: None,
+        }
+    }
+
+    /// Creates a set weighting pushed signatures by their signer according to `weights`.
+    /// Signers missing from `weights` have weight 1.
+    pub fn with_weights(weights: Arc<BTreeMap<Address, u64>>) -> Self {
+        BatchSignatureSet {
+            signatures: Vec::new(),
+            weights: Some(weights),
+        }
     }
 
     pub fn push(
         &mut self,
-        signature: ValidatedBatchSignature,
+        mut signature: ValidatedBatchSignature,
     ) -> Result<(), BatchSignatureSetError> {
-        if self.0.contains(&signature) {
+        if self.signatures.contains(&signature) {
             return Err(BatchSignatureSetError::DuplicatedSignature);
         }
-        self.0.push(signature);
+        if let Some(weights) = &self.weights {
+            signature.weight = weights.get(&signature.signer).copied().unwrap_or(1);
+        }
+        self.signatures.push(signature);
         Ok(())
     }
 
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.signatures.len()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.0.is_empty()
+        self.signatures.is_empty()
+    }
+
+    /// Sum of weights of all signatures in the set; equals [`Self::len()`] without weights.
+    pub fn total_weight(&self) -> u64 {
+        self.signatures
+            .iter()
+            .map(ValidatedBatchSignature::weight)
+            .sum()
     }
 }
 
+impl From<Vec<ValidatedBatchSignature>> for BatchSignatureSet {
+    fn from(signatures: Vec<ValidatedBatchSignature>) -> Self {
+        BatchSignatureSet {
+            signatures,
+            weights: None,
+        }
+    }
+}
+
+impl From<BatchSignatureSet> for Vec<ValidatedBatchSignature> {
+    fn from(set: BatchSignatureSet) -> Self {
+        set.signatures
+    }
+}
+
+/// Signature of a batch by a verifier.
+///
+/// Serialized as a plain ECDSA signature for EOA signers, so that signatures stored before contract
+/// signers were supported are still readable.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
-pub struct BatchSignature(AlloySignature);
+#[serde(untagged)]
+pub enum BatchSignature {
+    /// ECDSA signature of an externally owned account; the signer is recovered from it.
+    Eoa(AlloySignature),
+    /// Signature accepted by the EIP-1271 `isValidSignature` method of the `signer` contract
+    /// (e.g. a multisig wallet).
+    Contract {
+        signer: Address,
+        signature: AlloySignature,
+    },
+}
 
 impl BatchSignature {
-    pub async fn sign_batch(batch_info: &CommitBatchInfo, private_key: &PrivateKeySigner) -> Self {
+    /// Signs the [signing hash](BatchSigningHash::signing_hash) of `batch_info`.
+    pub async fn sign_batch(
+        batch_info: &CommitBatchInfo,
+        chain_id: u64,
+        private_key: &PrivateKeySigner,
+    ) -> Self {
+        let hash = batch_info.signing_hash(chain_id);
+        let signature = private_key.sign_message(hash.as_slice()).await.unwrap();
+        BatchSignature::Eoa(signature)
+    }
+
+    /// Signs the ABI encoding of `batch_info`, as expected by servers using batch verification
+    /// wire format versions before the signing hash was introduced.
+    pub async fn sign_legacy_batch(
+        batch_info: &CommitBatchInfo,
+        private_key: &PrivateKeySigner,
+    ) -> Self {
         let encoded = encode_batch_for_signing(batch_info);
         let signature = private_key.sign_message(&encoded).await.unwrap();
-        BatchSignature(signature)
+        BatchSignature::Eoa(signature)
     }
 
-    pub fn verify_signature(
+    /// Checks that the signature is valid for the [signing hash](BatchSigningHash::signing_hash)
+    /// of `batch_info` and made by one of the signers accepted by `context`. Signatures received
+    /// over the wire don't declare the signer kind; if they don't recover to an accepted EOA, they
+    /// are checked with the accepted contract signers.
+    pub async fn verify_signature(
         self,
         batch_info: &CommitBatchInfo,
-    ) -> Result<ValidatedBatchSignature, SignatureError> {
-        let encoded = encode_batch_for_signing(batch_info);
-        Ok(ValidatedBatchSignature {
-            signer: self.0.recover_address_from_msg(encoded)?,
-            signature: self,
-        })
+        chain_id: u64,
+        context: &SignatureVerificationContext,
+    ) -> Result<ValidatedBatchSignature, SignatureVerificationError> {
+        let hash = eip191_hash_message(batch_info.signing_hash(chain_id));
+        match self {
+            BatchSignature::Eoa(signature) => match context.find_signer(hash, &signature).await? {
+                AcceptedSigner::Eoa(signer) => Ok(ValidatedBatchSignature::new(self, signer)),
+                AcceptedSigner::Contract(signer) => Ok(ValidatedBatchSignature::new(
+                    BatchSignature::Contract { signer, signature },
+                    signer,
+                )),
+            },
+            BatchSignature::Contract { signer, signature } => {
+                context
+                    .verify_contract_signature(signer, hash, &signature)
+                    .await?;
+                Ok(ValidatedBatchSignature::new(self, signer))
+            }
+        }
+    }
+
+    /// Digest identifying the commit data, regardless of how it's signed.
+    pub fn signing_digest(batch_info: &CommitBatchInfo) -> B256 {
+        keccak256(encode_batch_for_signing(batch_info))
     }
 
+    /// Raw signature bytes, as sent over the wire regardless of the signer kind.
     pub fn into_raw(self) -> [u8; 65] {
-        self.0.as_bytes()
+        match self {
+            BatchSignature::Eoa(signature) | BatchSignature::Contract { signature, .. } => {
+                signature.as_bytes()
+            }
+        }
     }
 
     pub fn from_raw_array(array: &[u8; 65]) -> Result<Self, SignatureError> {
         let signature = AlloySignature::from_raw_array(array)?;
-        Ok(BatchSignature(signature))
+        Ok(BatchSignature::Eoa(signature))
     }
 }
 
@@ -81,9 +223,24 @@ fn encode_batch_for_signing(batch_info: &CommitBatchInfo) -> Vec<u8> {
 pub struct ValidatedBatchSignature {
     signature: BatchSignature,
     signer: Address,
+    /// Weight of the signer in the quorum; missing in signatures stored before weights existed.
+    #[serde(default = "default_weight")]
+    weight: u64,
+}
+
+fn default_weight() -> u64 {
+    1
 }
 
 impl ValidatedBatchSignature {
+    fn new(signature: BatchSignature, signer: Address) -> Self {
+        Self {
+            signature,
+            signer,
+            weight: default_weight(),
+        }
+    }
+
     pub fn signature(&self) -> &BatchSignature {
         &self.signature
     }
@@ -91,6 +248,11 @@ impl ValidatedBatchSignature {
     pub fn signer(&self) -> &Address {
         &self.signer
     }
+
+    /// Weight of the signer, as set by the [`BatchSignatureSet`] the signature was pushed to.
+    pub fn weight(&self) -> u64 {
+        self.weight
+    }
 }
 
 impl PartialEq for ValidatedBatchSignature {
@@ -98,3 +260,181 @@ impl PartialEq for ValidatedBatchSignature {
         self.signer == other.signer
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::providers::{Provider, ProviderBuilder};
+    use alloy::transports::mock::Asserter;
+    use std::time::Duration;
+
+    const CHAIN_ID: u64 = 270;
+
+    fn commit_info() -> CommitBatchInfo {
+        CommitBatchInfo {
+            batch_number: 42,
+            new_state_commitment: B256::repeat_byte(1),
+            number_of_layer1_txs: 5,
+            priority_operations_hash: B256::repeat_byte(2),
+            dependency_roots_rolling_hash: B256::repeat_byte(3),
+            l2_to_l1_logs_root_hash: B256::repeat_byte(4),
+            l2_da_validator: Address::repeat_byte(5),
+            da_commitment: B256::repeat_byte(6),
+            first_block_timestamp: 1_700_000_000,
+            last_block_timestamp: 1_700_000_100,
+            chain_id: CHAIN_ID,
+            operator_da_input: vec![7, 8, 9],
+        }
+    }
+
+    fn signer() -> PrivateKeySigner {
+        PrivateKeySigner::from_bytes(&B256::repeat_byte(0x11)).unwrap()
+    }
+
+    fn context(signers: &[Address]) -> SignatureVerificationContext {
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(Asserter::new())
+            .erased();
+        let signers = signers.iter().copied().map(AcceptedSigner::Eoa).collect();
+        SignatureVerificationContext::new(signers, provider, Duration::from_secs(5))
+    }
+
+    fn signature(signer: u8) -> ValidatedBatchSignature {
+        ValidatedBatchSignature::new(
+            BatchSignature::Eoa(AlloySignature::test_signature()),
+            Address::repeat_byte(signer),
+        )
+    }
+
+    #[test]
+    fn signing_hash_is_stable() {
+        let info = commit_info();
+        assert_eq!(
+            info.signing_hash(CHAIN_ID),
+            "0xedf7963e7fc97deac98822b705ef8fb498720618a0ca5a0ce92e4f0229ab58a1"
+                .parse::<B256>()
+                .unwrap()
+        );
+        assert_ne!(info.signing_hash(CHAIN_ID + 1), info.signing_hash(CHAIN_ID));
+        let mut other_batch = info.clone();
+        other_batch.operator_da_input.push(10);
+        assert_ne!(
+            other_batch.signing_hash(CHAIN_ID),
+            info.signing_hash(CHAIN_ID)
+        );
+    }
+
+    #[tokio::test]
+    async fn batch_signature_is_stable() {
+        let signer = signer();
+        assert_eq!(
+            signer.address(),
+            "0x19e7e376e7c213b7e7e7e46cc70a5dd086daff2a"
+                .parse::<Address>()
+                .unwrap()
+        );
+        let BatchSignature::Eoa(signature) =
+            BatchSignature::sign_batch(&commit_info(), CHAIN_ID, &signer).await
+        else {
+            panic!("unexpected signature kind");
+        };
+        assert_eq!(
+            signature.r(),
+            "0xa6c338467667125ae9651538c096e33707e64132ea8f07b000fc92f2ccafd004"
+                .parse::<U256>()
+                .unwrap()
+        );
+        assert_eq!(
+            signature.s(),
+            "0x59ba103aee55a6c72b748ee82a69f84888c053a2fa42c46329d97b7bc1f2b062"
+                .parse::<U256>()
+                .unwrap()
+        );
+        assert!(signature.v());
+    }
+
+    #[tokio::test]
+    async fn signed_batches_are_verified() {
+        let signer = signer();
+        let context = context(&[signer.address()]);
+        let info = commit_info();
+        let signature = BatchSignature::sign_batch(&info, CHAIN_ID, &signer).await;
+        let validated = signature
+            .clone()
+            .verify_signature(&info, CHAIN_ID, &context)
+            .await
+            .unwrap();
+        assert_eq!(*validated.signer(), signer.address());
+
+        // Signatures are bound to the chain and the batch contents
+        let err = signature
+            .clone()
+            .verify_signature(&info, CHAIN_ID + 1, &context)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, SignatureVerificationError::UnknownSigner(_)),
+            "{err}"
+        );
+        let mut other_batch = info.clone();
+        other_batch.new_state_commitment = B256::repeat_byte(0xff);
+        signature
+            .verify_signature(&other_batch, CHAIN_ID, &context)
+            .await
+            .unwrap_err();
+
+        let legacy = BatchSignature::sign_legacy_batch(&info, &signer).await;
+        legacy
+            .verify_signature(&info, CHAIN_ID, &context)
+            .await
+            .unwrap_err();
+    }
+
+    #[test]
+    fn mixed_weight_signatures() {
+        let weights = BTreeMap::from([(Address::repeat_byte(1), 2), (Address::repeat_byte(2), 0)]);
+        let mut set = BatchSignatureSet::with_weights(Arc::new(weights));
+        set.push(signature(3)).unwrap();
+        assert_eq!(set.total_weight(), 1);
+        set.push(signature(1)).unwrap();
+        assert_eq!(set.total_weight(), 3);
+        set.push(signature(2)).unwrap();
+        assert_eq!(set.total_weight(), 3);
+        assert_eq!(set.len(), 3);
+
+        let unweighted = BatchSignatureSet::from(vec![signature(1), signature(3)]);
+        assert_eq!(unweighted.total_weight(), 2);
+    }
+
+    #[test]
+    fn duplicated_signatures_are_rejected() {
+        let weights = BTreeMap::from([(Address::repeat_byte(1), 2)]);
+        let mut set = BatchSignatureSet::with_weights(Arc::new(weights));
+        set.push(signature(1)).unwrap();
+        assert!(matches!(
+            set.push(signature(1)),
+            Err(BatchSignatureSetError::DuplicatedSignature)
+        ));
+        assert_eq!(set.len(), 1);
+        assert_eq!(set.total_weight(), 2);
+    }
+
+    #[test]
+    fn signatures_without_weights_are_deserialized() {
+        let legacy = serde_json::json!([{
+            "signature": BatchSignature::Eoa(AlloySignature::test_signature()),
+            "signer": Address::repeat_byte(1),
+        }]);
+        let set: BatchSignatureSet = serde_json::from_value(legacy).unwrap();
+        assert_eq!(set.total_weight(), 1);
+
+        let weights = BTreeMap::from([(Address::repeat_byte(1), 5)]);
+        let mut set = BatchSignatureSet::with_weights(Arc::new(weights));
+        set.push(signature(1)).unwrap();
+        let json = serde_json::to_value(&set).unwrap();
+        assert!(json.is_array());
+        let set: BatchSignatureSet = serde_json::from_value(json).unwrap();
+        assert_eq!(set.total_weight(), 5);
+    }
+}
@@ -1,6 +1,12 @@
 mod batch_signature;
 pub use batch_signature::{
-    BatchSignature, BatchSignatureSet, BatchSignatureSetError, ValidatedBatchSignature,
+    BATCH_SIGNING_DOMAIN, BatchSignature, BatchSignatureSet, BatchSignatureSetError,
+    BatchSigningHash, ValidatedBatchSignature,
+};
+
+mod signature_verification;
+pub use signature_verification::{
+    AcceptedSigner, SignatureVerificationContext, SignatureVerificationError,
 };
 
 mod block_merkle_tree_data;
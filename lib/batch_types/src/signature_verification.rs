@@ -0,0 +1,426 @@
+use alloy::contract::Error as ContractError;
+use alloy::primitives::{Address, B256, FixedBytes, Signature as AlloySignature};
+use alloy::providers::DynProvider;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+use zksync_os_contract_interface::IERC1271;
+
+/// Value returned by EIP-1271 `isValidSignature` for valid signatures.
+const EIP1271_MAGIC_VALUE: FixedBytes<4> = FixedBytes([0x16, 0x26, 0xba, 0x7e]);
+/// Max number of contract signatures remembered as valid.
+const MAX_CACHED_CONTRACT_SIGNATURES: usize = 1_024;
+const CONTRACT_SIGNER_PREFIX: &str = "contract:";
+
+/// Signer whose batch signatures are accepted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AcceptedSigner {
+    /// Externally owned account; parsed from a plain address.
+    Eoa(Address),
+    /// Smart-contract wallet validating signatures via EIP-1271; parsed from `contract:<address>`.
+    Contract(Address),
+}
+
+impl AcceptedSigner {
+    pub fn address(&self) -> Address {
+        match self {
+            AcceptedSigner::Eoa(address) | AcceptedSigner::Contract(address) => *address,
+        }
+    }
+}
+
+impl FromStr for AcceptedSigner {
+    type Err = <Address as FromStr>::Err;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix(CONTRACT_SIGNER_PREFIX) {
+            Some(address) => Ok(AcceptedSigner::Contract(address.trim().parse()?)),
+            None => Ok(AcceptedSigner::Eoa(s.parse()?)),
+        }
+    }
+}
+
+impl fmt::Display for AcceptedSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AcceptedSigner::Eoa(address) => write!(f, "{address}"),
+            AcceptedSigner::Contract(address) => write!(f, "{CONTRACT_SIGNER_PREFIX}{address}"),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SignatureVerificationError {
+    #[error("Invalid signature: {0}")]
+    Invalid(#[from] alloy::primitives::SignatureError),
+    #[error("Signature from unknown signer {0}")]
+    UnknownSigner(Address),
+    #[error("Signature is not accepted by any contract signer")]
+    RejectedByContracts,
+    #[error("Signer contract {0} is not accepted")]
+    UnknownContractSigner(Address),
+    #[error("Signature rejected by signer contract {0}")]
+    RejectedByContract(Address),
+    #[error("Failed to call signer contract {signer}: {reason}")]
+    Rpc { signer: Address, reason: String },
+}
+
+impl SignatureVerificationError {
+    /// Whether the signature may turn out valid if checked again, i.e. an L1 call has failed.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, SignatureVerificationError::Rpc { .. })
+    }
+}
+
+/// Accepted signers and the means to check signatures of contract signers.
+pub struct SignatureVerificationContext {
+    accepted_signers: Vec<AcceptedSigner>,
+    /// Used to call contract signers; `None` if there are none.
+    l1_provider: Option<DynProvider>,
+    call_timeout: Duration,
+    /// Signatures already accepted by signer contracts, keyed by `(signer, hash)`.
+    verified: Mutex<VerifiedSignatures>,
+}
+
+impl fmt::Debug for SignatureVerificationContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignatureVerificationContext")
+            .field("accepted_signers", &self.accepted_signers)
+            .field("call_timeout", &self.call_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Default)]
+struct VerifiedSignatures {
+    signatures: HashMap<(Address, B256), AlloySignature>,
+    order: VecDeque<(Address, B256)>,
+}
+
+impl VerifiedSignatures {
+    fn contains(&self, signer: Address, hash: B256, signature: &AlloySignature) -> bool {
+        self.signatures.get(&(signer, hash)) == Some(signature)
+    }
+
+    fn insert(&mut self, signer: Address, hash: B256, signature: AlloySignature) {
+        if self.signatures.insert((signer, hash), signature).is_none() {
+            self.order.push_back((signer, hash));
+        }
+        if self.order.len() > MAX_CACHED_CONTRACT_SIGNATURES
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.signatures.remove(&oldest);
+        }
+    }
+}
+
+impl SignatureVerificationContext {
+    /// `l1_provider` is only used if there are contract signers; each call to a signer contract
+    /// fails after `call_timeout`.
+    pub fn new(
+        accepted_signers: Vec<AcceptedSigner>,
+        l1_provider: DynProvider,
+        call_timeout: Duration,
+    ) -> Self {
+        let has_contract_signers = accepted_signers
+            .iter()
+            .any(|signer| matches!(signer, AcceptedSigner::Contract(_)));
+        Self {
+            accepted_signers,
+            l1_provider: has_contract_signers.then_some(l1_provider),
+            call_timeout,
+            verified: Mutex::default(),
+        }
+    }
+
+    pub fn accepted_signers(&self) -> &[AcceptedSigner] {
+        &self.accepted_signers
+    }
+
+    /// Returns the accepted signer of `signature` of `hash`: the recovered EOA if it's accepted,
+    /// or else the first contract signer accepting the signature.
+    pub async fn find_signer(
+        &self,
+        hash: B256,
+        signature: &AlloySignature,
+    ) -> Result<AcceptedSigner, SignatureVerificationError> {
+        let recovered = signature.recover_address_from_prehash(&hash);
+        if let Ok(signer) = recovered
+            && self.accepts_eoa(&signer)
+        {
+            return Ok(AcceptedSigner::Eoa(signer));
+        }
+        if !self.has_contract_signers() {
+            return match recovered {
+                Ok(signer) => Err(SignatureVerificationError::UnknownSigner(signer)),
+                Err(err) => Err(err.into()),
+            };
+        }
+        let signer = self.find_contract_signer(hash, signature).await?;
+        Ok(AcceptedSigner::Contract(signer))
+    }
+
+    fn accepts_eoa(&self, address: &Address) -> bool {
+        self.accepted_signers
+            .contains(&AcceptedSigner::Eoa(*address))
+    }
+
+    fn has_contract_signers(&self) -> bool {
+        self.l1_provider.is_some()
+    }
+
+    fn contract_signers(&self) -> impl Iterator<Item = Address> + '_ {
+        self.accepted_signers
+            .iter()
+            .filter_map(|signer| match signer {
+                AcceptedSigner::Contract(address) => Some(*address),
+                AcceptedSigner::Eoa(_) => None,
+            })
+    }
+
+    /// Returns the first contract signer accepting `signature` of `hash`. If no contract accepts
+    /// the signature and some of them couldn't be called, returns the last call error.
+    pub(crate) async fn find_contract_signer(
+        &self,
+        hash: B256,
+        signature: &AlloySignature,
+    ) -> Result<Address, SignatureVerificationError> {
+        let mut call_error = None;
+        for signer in self.contract_signers() {
+            match self.check_contract_signature(signer, hash, signature).await {
+                Ok(()) => return Ok(signer),
+                Err(err) if err.is_transient() => {
+                    tracing::warn!(%signer, "failed to check batch signature: {err}");
+                    call_error = Some(err);
+                }
+                Err(_) => {}
+            }
+        }
+        Err(call_error.unwrap_or(SignatureVerificationError::RejectedByContracts))
+    }
+
+    pub(crate) async fn verify_contract_signature(
+        &self,
+        signer: Address,
+        hash: B256,
+        signature: &AlloySignature,
+    ) -> Result<(), SignatureVerificationError> {
+        if !self
+            .accepted_signers
+            .contains(&AcceptedSigner::Contract(signer))
+        {
+            return Err(SignatureVerificationError::UnknownContractSigner(signer));
+        }
+        self.check_contract_signature(signer, hash, signature).await
+    }
+
+    async fn check_contract_signature(
+        &self,
+        signer: Address,
+        hash: B256,
+        signature: &AlloySignature,
+    ) -> Result<(), SignatureVerificationError> {
+        if self
+            .verified
+            .lock()
+            .unwrap()
+            .contains(signer, hash, signature)
+        {
+            return Ok(());
+        }
+        let l1_provider = self
+            .l1_provider
+            .as_ref()
+            .expect("contract signers are only accepted with an L1 provider");
+        let contract = IERC1271::new(signer, l1_provider);
+        let call = contract
+            .isValidSignature(hash, signature.as_bytes().into())
+            .call();
+        let magic_value = match tokio::time::timeout(self.call_timeout, call).await {
+            Ok(Ok(magic_value)) => magic_value,
+            Ok(Err(err)) if is_rejection(&err) => {
+                return Err(SignatureVerificationError::RejectedByContract(signer));
+            }
+            Ok(Err(err)) => {
+                return Err(SignatureVerificationError::Rpc {
+                    signer,
+                    reason: err.to_string(),
+                });
+            }
+            Err(_) => {
+                return Err(SignatureVerificationError::Rpc {
+                    signer,
+                    reason: format!("timed out after {:?}", self.call_timeout),
+                });
+            }
+        };
+        if magic_value != EIP1271_MAGIC_VALUE {
+            return Err(SignatureVerificationError::RejectedByContract(signer));
+        }
+        self.verified
+            .lock()
+            .unwrap()
+            .insert(signer, hash, *signature);
+        Ok(())
+    }
+}
+
+/// Whether a failed `isValidSignature` call means that the signature is invalid, as opposed to
+/// the call not reaching the contract. Reverts and unexpected return data are rejections.
+fn is_rejection(err: &ContractError) -> bool {
+    match err {
+        ContractError::TransportError(err) => err
+            .as_error_resp()
+            .is_some_and(|resp| resp.message.contains("revert")),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Bytes;
+    use alloy::providers::{Provider, ProviderBuilder};
+    use alloy::transports::mock::Asserter;
+
+    const WALLET: Address = Address::repeat_byte(1);
+    const OTHER_WALLET: Address = Address::repeat_byte(2);
+
+    fn context(asserter: &Asserter, signers: Vec<AcceptedSigner>) -> SignatureVerificationContext {
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(asserter.clone())
+            .erased();
+        SignatureVerificationContext::new(signers, provider, Duration::from_secs(5))
+    }
+
+    /// ABI-encoded `isValidSignature` return value.
+    fn returned_value(value: [u8; 4]) -> Bytes {
+        let mut word = [0_u8; 32];
+        word[..4].copy_from_slice(&value);
+        word.to_vec().into()
+    }
+
+    #[test]
+    fn parsing_accepted_signers() {
+        let eoa: AcceptedSigner = "0x36615Cf349d7F6344891B1e7CA7C72883F5dc049"
+            .parse()
+            .unwrap();
+        assert!(matches!(eoa, AcceptedSigner::Eoa(_)));
+        let contract: AcceptedSigner = format!("contract:{WALLET}").parse().unwrap();
+        assert_eq!(contract, AcceptedSigner::Contract(WALLET));
+        assert_eq!(
+            contract.to_string().parse::<AcceptedSigner>().unwrap(),
+            contract
+        );
+        "contract:0x123".parse::<AcceptedSigner>().unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn valid_contract_signature_is_cached() {
+        let asserter = Asserter::new();
+        let context = context(&asserter, vec![AcceptedSigner::Contract(WALLET)]);
+        let signature = AlloySignature::test_signature();
+        asserter.push_success(&returned_value(EIP1271_MAGIC_VALUE.0));
+        let signer = context
+            .find_contract_signer(B256::repeat_byte(1), &signature)
+            .await
+            .unwrap();
+        assert_eq!(signer, WALLET);
+
+        // No more responses are queued, so the signature must not be checked on L1 again
+        context
+            .verify_contract_signature(WALLET, B256::repeat_byte(1), &signature)
+            .await
+            .unwrap();
+        let err = context
+            .verify_contract_signature(WALLET, B256::repeat_byte(2), &signature)
+            .await
+            .unwrap_err();
+        assert!(err.is_transient(), "{err}");
+    }
+
+    #[tokio::test]
+    async fn invalid_contract_signature_is_rejected() {
+        let asserter = Asserter::new();
+        let context = context(&asserter, vec![AcceptedSigner::Contract(WALLET)]);
+        asserter.push_success(&returned_value([0xff; 4]));
+        let err = context
+            .verify_contract_signature(WALLET, B256::ZERO, &AlloySignature::test_signature())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, SignatureVerificationError::RejectedByContract(WALLET)),
+            "{err}"
+        );
+
+        let err = context
+            .verify_contract_signature(OTHER_WALLET, B256::ZERO, &AlloySignature::test_signature())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                SignatureVerificationError::UnknownContractSigner(OTHER_WALLET)
+            ),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn reverting_contract_rejects_signature() {
+        let asserter = Asserter::new();
+        let signers = vec![
+            AcceptedSigner::Contract(WALLET),
+            AcceptedSigner::Contract(OTHER_WALLET),
+        ];
+        let context = context(&asserter, signers);
+        asserter.push_failure_msg("execution reverted");
+        asserter.push_success(&returned_value(EIP1271_MAGIC_VALUE.0));
+        let signer = context
+            .find_contract_signer(B256::ZERO, &AlloySignature::test_signature())
+            .await
+            .unwrap();
+        assert_eq!(signer, OTHER_WALLET);
+
+        asserter.push_failure_msg("execution reverted");
+        asserter.push_failure_msg("execution reverted");
+        let err = context
+            .find_contract_signer(B256::repeat_byte(1), &AlloySignature::test_signature())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, SignatureVerificationError::RejectedByContracts),
+            "{err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_call_is_transient() {
+        let asserter = Asserter::new();
+        let signers = vec![
+            AcceptedSigner::Contract(WALLET),
+            AcceptedSigner::Contract(OTHER_WALLET),
+        ];
+        let context = context(&asserter, signers);
+        // The first contract rejects the signature, the call to the second one fails
+        // (no response is queued)
+        asserter.push_failure_msg("execution reverted");
+        let err = context
+            .find_contract_signer(B256::ZERO, &AlloySignature::test_signature())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                SignatureVerificationError::Rpc {
+                    signer: OTHER_WALLET,
+                    ..
+                }
+            ),
+            "{err}"
+        );
+    }
+}
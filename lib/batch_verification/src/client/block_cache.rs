@@ -1,69 +1,286 @@
-use std::collections::HashMap;
+use alloy::eips::Encodable2718;
+use std::collections::{BTreeMap, HashMap};
+use std::mem;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
 use zksync_os_batch_types::BlockMerkleTreeData;
 use zksync_os_interface::types::BlockOutput;
 use zksync_os_storage_api::ReadFinality;
 use zksync_os_storage_api::ReplayRecord;
 
+use super::BatchVerificationError;
 use super::metrics::BATCH_VERIFICATION_CLIENT_METRICS;
 
+/// Block data needed to verify batches.
+pub type VerificationBlock = (BlockOutput, ReplayRecord, BlockMerkleTreeData);
+
+/// Node's local storage that blocks missing in the [`BlockCache`] are loaded from, e.g. blocks
+/// of batches that the main node requests after a restart of the verifier.
+pub trait BlockSource<Block = VerificationBlock>: Send + Sync + 'static {
+    /// Loads a block processed by the node. Returns `None` if the block is not processed yet.
+    fn load_block(&self, block_number: u64) -> anyhow::Result<Option<Block>>;
+}
+
+pub(super) trait CachedBlock {
+    /// Rough estimate of the memory taken by the block.
+    fn estimated_size(&self) -> usize;
+}
+
+impl CachedBlock for VerificationBlock {
+    fn estimated_size(&self) -> usize {
+        let (block_output, replay_record, _) = self;
+        let preimages: usize = block_output
+            .published_preimages
+            .iter()
+            .map(|(_, preimage)| preimage.len())
+            .sum();
+        let transactions: usize = replay_record
+            .transactions
+            .iter()
+            .map(|tx| tx.inner.encode_2718_len())
+            .sum();
+        mem::size_of::<Self>()
+            + block_output.pubdata.len()
+            + mem::size_of_val(block_output.storage_writes.as_slice())
+            + mem::size_of_val(block_output.tx_results.as_slice())
+            + preimages
+            + transactions
+    }
+}
+
 /// Cache of blocks that are to be used for batch verification
-/// Accepts blocks only in ascending order. Old blocks are evicted when not
-/// needed through a call to remove_lower_then.
-///
-/// This may be optimized by using a ring buffer for data storage instead.
-pub(super) struct BlockCache<Finality> {
-    data: HashMap<u64, (BlockOutput, ReplayRecord, BlockMerkleTreeData)>,
-    range: Option<(u64, u64)>,
+/// Accepts blocks only in ascending order. Blocks are evicted once their batch is signed or
+/// committed, and when the cache exceeds `max_blocks`.
+pub(super) struct BlockCache<Finality, Block = VerificationBlock> {
+    data: BTreeMap<u64, Block>,
+    /// Last block received from the pipeline.
+    last_block: Option<u64>,
+    max_blocks: usize,
+    estimated_size: usize,
     finality: Finality,
 }
 
-impl<Finality: ReadFinality> BlockCache<Finality> {
-    pub fn new(finality: Finality) -> Self {
+impl<Finality: ReadFinality, Block: CachedBlock + Send + 'static> BlockCache<Finality, Block> {
+    pub fn new(finality: Finality, max_blocks: usize) -> Self {
         Self {
-            data: HashMap::new(),
-            range: None,
+            data: BTreeMap::new(),
+            last_block: None,
+            max_blocks,
+            estimated_size: 0,
             finality,
         }
     }
 
     /// Insert a block into the cache. Expected blocks to be added in order.
-    pub fn insert(
-        &mut self,
-        block_number: u64,
-        block: (BlockOutput, ReplayRecord, BlockMerkleTreeData),
-    ) -> anyhow::Result<()> {
+    pub fn insert(&mut self, block_number: u64, block: Block) -> anyhow::Result<()> {
+        if let Some(last_block) = self.last_block
+            && block_number != last_block + 1
+        {
+            anyhow::bail!("Out of order block received. This should never happen");
+        }
+        self.last_block = Some(block_number);
+        self.estimated_size += block.estimated_size();
         self.data.insert(block_number, block);
-        if let Some((low, high)) = self.range {
-            if block_number != high + 1 {
-                anyhow::bail!("Out of order block received. This should never happen");
-            }
-            self.range = Some((low, block_number));
-        } else {
-            self.range = Some((block_number, block_number));
+        while self.data.len() > self.max_blocks {
+            let (_, evicted) = self.data.pop_first().unwrap();
+            self.estimated_size -= evicted.estimated_size();
         }
 
         // evict block for committed batches
-        self.remove_lower_then(self.finality.get_finality_status().last_committed_block + 1);
-        // BATCH_VERIFICATION_CLIENT_METRICS.block_cache_size updated in remove_lower_then
+        self.remove_lower_than(self.finality.get_finality_status().last_committed_block + 1);
+        // metrics are updated in remove_lower_than
         Ok(())
     }
 
-    pub fn get(
-        &self,
-        block_number: u64,
-    ) -> Option<&(BlockOutput, ReplayRecord, BlockMerkleTreeData)> {
+    pub fn get(&self, block_number: u64) -> Option<&Block> {
         self.data.get(&block_number)
     }
 
     /// Removes all blocks lower than the given block number
-    pub fn remove_lower_then(&mut self, block_number: u64) {
-        if let Some((low, _)) = self.range {
-            for num in low..block_number {
-                self.data.remove(&num);
-            }
+    pub fn remove_lower_than(&mut self, block_number: u64) {
+        let retained = self.data.split_off(&block_number);
+        for evicted in mem::replace(&mut self.data, retained).into_values() {
+            self.estimated_size -= evicted.estimated_size();
         }
         BATCH_VERIFICATION_CLIENT_METRICS
             .block_cache_size
             .set(self.data.len());
+        BATCH_VERIFICATION_CLIENT_METRICS
+            .block_cache_estimated_bytes
+            .set(self.estimated_size);
+    }
+
+    /// Loads blocks of `range` that are missing in the cache from `source`. Loaded blocks are
+    /// not cached, since they are only needed for the current request.
+    pub async fn load_missing(
+        &self,
+        range: RangeInclusive<u64>,
+        source: &Arc<impl BlockSource<Block>>,
+    ) -> Result<HashMap<u64, Block>, BatchVerificationError> {
+        let missing: Vec<_> = range
+            .filter(|block_number| !self.data.contains_key(block_number))
+            .collect();
+        if missing.is_empty() {
+            return Ok(HashMap::new());
+        }
+        tracing::info!(
+            first_missing = missing[0],
+            count = missing.len(),
+            "Loading blocks missing in cache from local storage"
+        );
+        let source = source.clone();
+        tokio::task::spawn_blocking(move || {
+            missing
+                .into_iter()
+                .map(|block_number| match source.load_block(block_number) {
+                    Ok(Some(block)) => {
+                        BATCH_VERIFICATION_CLIENT_METRICS.loaded_blocks.inc();
+                        Ok((block_number, block))
+                    }
+                    Ok(None) => Err(BatchVerificationError::MissingBlock(block_number)),
+                    Err(error) => Err(BatchVerificationError::BlockLoad {
+                        block_number,
+                        error,
+                    }),
+                })
+                .collect()
+        })
+        .await
+        .expect("loading blocks panicked")
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tokio::sync::watch;
+    use zksync_os_storage_api::FinalityStatus;
+
+    #[derive(Clone)]
+    pub(in crate::client) struct MockFinality(watch::Sender<FinalityStatus>);
+
+    impl ReadFinality for MockFinality {
+        fn get_finality_status(&self) -> FinalityStatus {
+            self.0.borrow().clone()
+        }
+
+        fn subscribe(&self) -> watch::Receiver<FinalityStatus> {
+            self.0.subscribe()
+        }
+    }
+
+    pub(in crate::client) fn finality(last_committed_block: u64) -> MockFinality {
+        MockFinality(watch::Sender::new(FinalityStatus {
+            last_committed_block,
+            last_committed_batch: 0,
+            last_executed_block: 0,
+            last_executed_batch: 0,
+        }))
+    }
+
+    /// Block with the given estimated size.
+    #[derive(Debug, PartialEq)]
+    struct MockBlock(usize);
+
+    impl CachedBlock for MockBlock {
+        fn estimated_size(&self) -> usize {
+            self.0
+        }
+    }
+
+    /// Blocks up to `last_block` are stored; loaded block numbers are recorded.
+    #[derive(Default)]
+    struct MockRepository {
+        last_block: u64,
+        loaded: Mutex<Vec<u64>>,
+    }
+
+    impl BlockSource<MockBlock> for MockRepository {
+        fn load_block(&self, block_number: u64) -> anyhow::Result<Option<MockBlock>> {
+            anyhow::ensure!(block_number > 0, "genesis is not verified");
+            self.loaded.lock().unwrap().push(block_number);
+            Ok((block_number <= self.last_block).then_some(MockBlock(10)))
+        }
+    }
+
+    #[test]
+    fn blocks_are_pruned() {
+        let finality = finality(0);
+        let mut cache = BlockCache::new(finality.clone(), 5);
+        for block_number in 1..=4 {
+            cache.insert(block_number, MockBlock(10)).unwrap();
+        }
+        assert_eq!(cache.estimated_size, 40);
+        cache.insert(6, MockBlock(10)).unwrap_err();
+
+        // Batch with blocks 1..=2 is signed
+        cache.remove_lower_than(3);
+        assert_eq!(cache.get(2), None);
+        assert_eq!(cache.get(3), Some(&MockBlock(10)));
+        assert_eq!(cache.estimated_size, 20);
+
+        // Blocks over the retained window are evicted
+        for block_number in 5..=8 {
+            cache.insert(block_number, MockBlock(10)).unwrap();
+        }
+        assert_eq!(
+            cache.data.keys().copied().collect::<Vec<_>>(),
+            [4, 5, 6, 7, 8]
+        );
+        assert_eq!(cache.estimated_size, 50);
+
+        // Blocks of committed batches are evicted on the next insertion
+        finality.send_modify(|status| status.last_committed_block = 7);
+        cache.insert(9, MockBlock(10)).unwrap();
+        assert_eq!(cache.data.keys().copied().collect::<Vec<_>>(), [8, 9]);
+        assert_eq!(cache.estimated_size, 20);
+    }
+
+    #[tokio::test]
+    async fn missing_blocks_are_loaded() {
+        let mut cache = BlockCache::new(finality(0), 100);
+        for block_number in 5..=8 {
+            cache.insert(block_number, MockBlock(10)).unwrap();
+        }
+        let repository = Arc::new(MockRepository {
+            last_block: 8,
+            ..MockRepository::default()
+        });
+
+        // Blocks received before a restart are loaded, cached ones are not
+        let loaded = cache.load_missing(3..=6, &repository).await.unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[&3], MockBlock(10));
+        assert_eq!(*repository.loaded.lock().unwrap(), [3, 4]);
+        // Loaded blocks are not cached
+        assert_eq!(cache.get(3), None);
+        assert_eq!(cache.estimated_size, 40);
+
+        assert!(
+            cache
+                .load_missing(5..=8, &repository)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+
+        // Blocks not processed by the node yet are reported as missing
+        let err = cache.load_missing(8..=10, &repository).await.unwrap_err();
+        assert!(
+            matches!(err, BatchVerificationError::MissingBlock(9)),
+            "{err}"
+        );
+        let err = cache.load_missing(0..=1, &repository).await.unwrap_err();
+        assert!(
+            matches!(
+                err,
+                BatchVerificationError::BlockLoad {
+                    block_number: 0,
+                    ..
+                }
+            ),
+            "{err}"
+        );
     }
 }
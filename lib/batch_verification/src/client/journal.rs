@@ -0,0 +1,292 @@
+//! Append-only journal of batch signatures produced by this node.
+//!
+//! Signatures are the accountability mechanism of batch verification, so a node must never sign
+//! two different commits for the same batch - including across restarts. Every signature is
+//! appended to the journal (and synced) before it's returned to the server; on startup the journal
+//! is loaded back.
+//!
+//! Records are stored as JSON lines, so the journal file can be handed over for audits as is
+//! (or copied consistently with [`SigningJournal::export`]).
+
+use alloy::primitives::B256;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use zksync_os_batch_types::BatchSignature;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignedRequestRecord {
+    pub batch_number: u64,
+    /// Digest of the signed commit data, see [`BatchSignature::signing_digest`].
+    pub commit_data_digest: B256,
+    pub request_id: u64,
+    /// Unix timestamp (milliseconds) of signing.
+    pub timestamp_ms: u64,
+    pub signature: BatchSignature,
+}
+
+/// Result of checking a request against the journal.
+#[derive(Debug, PartialEq)]
+pub enum JournalLookup {
+    /// Batch was not signed before.
+    NotSigned,
+    /// Batch was already signed for the same commit data; the signature can be served again.
+    AlreadySigned(BatchSignature),
+    /// Batch was already signed for different commit data.
+    Conflict { signed_digest: B256 },
+    /// Batch is older than all journaled batches, so it may have been signed and then trimmed
+    /// from the journal.
+    BeyondRetention { oldest_batch: u64 },
+}
+
+#[derive(Debug)]
+pub struct SigningJournal {
+    path: PathBuf,
+    file: File,
+    /// Max number of records kept; older ones are trimmed.
+    retention: usize,
+    records: BTreeMap<u64, SignedRequestRecord>,
+    /// Number of lines in the file, which may exceed `records` until the file is compacted.
+    lines: usize,
+}
+
+impl SigningJournal {
+    /// Opens the journal at `path`, creating it if necessary.
+    pub fn open(path: &Path, retention: usize) -> anyhow::Result<Self> {
+        anyhow::ensure!(retention > 0, "signing journal retention must be positive");
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("cannot create `{}`", parent.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("cannot open signing journal `{}`", path.display()))?;
+
+        let mut records = BTreeMap::new();
+        let mut lines = 0;
+        for line in BufReader::new(File::open(path)?).lines() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let record: SignedRequestRecord = serde_json::from_str(&line)
+                .with_context(|| format!("corrupted signing journal `{}`", path.display()))?;
+            lines += 1;
+            if let Some(previous) = records.get(&record.batch_number)
+                && previous.commit_data_digest != record.commit_data_digest
+            {
+                anyhow::bail!(
+                    "signing journal `{}` contains conflicting signatures for batch {}",
+                    path.display(),
+                    record.batch_number
+                );
+            }
+            records.insert(record.batch_number, record);
+        }
+
+        let mut this = Self {
+            path: path.to_owned(),
+            file,
+            retention,
+            records,
+            lines,
+        };
+        this.trim();
+        if this.lines > this.retention {
+            this.compact()?;
+        }
+        tracing::info!(
+            path = %this.path.display(),
+            records = this.records.len(),
+            last_signed_batch = ?this.records.last_key_value().map(|(batch, _)| *batch),
+            "opened batch signing journal"
+        );
+        Ok(this)
+    }
+
+    pub fn lookup(&self, batch_number: u64, commit_data_digest: B256) -> JournalLookup {
+        if let Some(record) = self.records.get(&batch_number) {
+            return if record.commit_data_digest == commit_data_digest {
+                JournalLookup::AlreadySigned(record.signature.clone())
+            } else {
+                JournalLookup::Conflict {
+                    signed_digest: record.commit_data_digest,
+                }
+            };
+        }
+        match self.records.first_key_value() {
+            Some((&oldest_batch, _)) if batch_number < oldest_batch => {
+                JournalLookup::BeyondRetention { oldest_batch }
+            }
+            _ => JournalLookup::NotSigned,
+        }
+    }
+
+    /// Durably appends a record. Must be called before the signature leaves the node.
+    pub fn append(&mut self, record: SignedRequestRecord) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            matches!(
+                self.lookup(record.batch_number, record.commit_data_digest),
+                JournalLookup::NotSigned
+            ),
+            "batch {} cannot be journaled again",
+            record.batch_number
+        );
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        self.file
+            .write_all(&line)
+            .and_then(|()| self.file.sync_data())
+            .context("cannot write signing journal")?;
+        self.lines += 1;
+        self.records.insert(record.batch_number, record);
+
+        self.trim();
+        // Compacting amortizes to a constant number of rewritten records per append
+        if self.lines >= 2 * self.retention {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &SignedRequestRecord> {
+        self.records.values()
+    }
+
+    /// Writes retained records to `path` as JSON lines.
+    pub fn export(&self, path: &Path) -> anyhow::Result<()> {
+        write_records(path, self.records.values())
+    }
+
+    fn trim(&mut self) {
+        while self.records.len() > self.retention {
+            self.records.pop_first();
+        }
+    }
+
+    /// Rewrites the journal file with retained records only.
+    fn compact(&mut self) -> anyhow::Result<()> {
+        let tmp_path = self.path.with_extension("jsonl.tmp");
+        write_records(&tmp_path, self.records.values())?;
+        std::fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("cannot replace `{}`", self.path.display()))?;
+        if let Some(parent) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            // Persist the rename itself
+            File::open(parent).and_then(|dir| dir.sync_all())?;
+        }
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        self.lines = self.records.len();
+        tracing::debug!(records = self.lines, "compacted batch signing journal");
+        Ok(())
+    }
+}
+
+fn write_records<'a>(
+    path: &Path,
+    records: impl Iterator<Item = &'a SignedRequestRecord>,
+) -> anyhow::Result<()> {
+    let mut file =
+        File::create(path).with_context(|| format!("cannot create `{}`", path.display()))?;
+    for record in records {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        file.write_all(&line)?;
+    }
+    file.sync_all()
+        .with_context(|| format!("cannot sync `{}`", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(batch_number: u64, digest: u8) -> SignedRequestRecord {
+        let mut raw_signature = [digest; 65];
+        raw_signature[64] = 27;
+        SignedRequestRecord {
+            batch_number,
+            commit_data_digest: B256::repeat_byte(digest),
+            request_id: batch_number * 10,
+            timestamp_ms: 1_000 + batch_number,
+            signature: BatchSignature::from_raw_array(&raw_signature).unwrap(),
+        }
+    }
+
+    #[test]
+    fn conflicting_request_refused_after_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.jsonl");
+        let mut journal = SigningJournal::open(&path, 100).unwrap();
+        journal.append(record(5, 1)).unwrap();
+        drop(journal);
+
+        let journal = SigningJournal::open(&path, 100).unwrap();
+        assert_eq!(
+            journal.lookup(5, B256::repeat_byte(2)),
+            JournalLookup::Conflict {
+                signed_digest: B256::repeat_byte(1)
+            }
+        );
+        assert_eq!(
+            journal.lookup(6, B256::repeat_byte(2)),
+            JournalLookup::NotSigned
+        );
+    }
+
+    #[test]
+    fn identical_request_reserved_after_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.jsonl");
+        let mut journal = SigningJournal::open(&path, 100).unwrap();
+        journal.append(record(5, 1)).unwrap();
+        // Appending the same batch again is a bug in the caller
+        journal.append(record(5, 1)).unwrap_err();
+        drop(journal);
+
+        let journal = SigningJournal::open(&path, 100).unwrap();
+        assert_eq!(
+            journal.lookup(5, B256::repeat_byte(1)),
+            JournalLookup::AlreadySigned(record(5, 1).signature)
+        );
+        assert_eq!(journal.records().count(), 1);
+    }
+
+    #[test]
+    fn retention_trimming() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.jsonl");
+        let mut journal = SigningJournal::open(&path, 3).unwrap();
+        for batch_number in 1..=10 {
+            journal.append(record(batch_number, 1)).unwrap();
+        }
+        let retained = |journal: &SigningJournal| {
+            journal
+                .records()
+                .map(|record| record.batch_number)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(retained(&journal), [8, 9, 10]);
+        assert_eq!(
+            journal.lookup(7, B256::repeat_byte(1)),
+            JournalLookup::BeyondRetention { oldest_batch: 8 }
+        );
+        drop(journal);
+
+        // File is bounded as well
+        let file_lines = std::fs::read_to_string(&path).unwrap().lines().count();
+        assert!(file_lines < 6, "{file_lines}");
+        let journal = SigningJournal::open(&path, 3).unwrap();
+        assert_eq!(retained(&journal), [8, 9, 10]);
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+        let export_path = dir.path().join("export.jsonl");
+        journal.export(&export_path).unwrap();
+        let exported = SigningJournal::open(&export_path, 100).unwrap();
+        assert_eq!(retained(&exported), [8, 9, 10]);
+    }
+}
@@ -1,9 +1,20 @@
-use vise::{Gauge, Metrics};
+use vise::{Counter, Gauge, LabeledFamily, Metrics, Unit};
 
 #[derive(Debug, Metrics)]
 #[metrics(prefix = "batch_verification_client")]
 pub struct BatchVerificationClientMetrics {
     pub block_cache_size: Gauge<usize>,
+    /// Rough estimate of the memory taken by cached blocks.
+    #[metrics(unit = Unit::Bytes)]
+    pub block_cache_estimated_bytes: Gauge<usize>,
+    /// Blocks missing in the cache that were loaded from local storage.
+    pub loaded_blocks: Counter,
+    /// Requests answered from the signing journal, by outcome
+    /// (`already_signed`, `conflict` or `beyond_retention`).
+    #[metrics(labels = ["outcome"])]
+    pub journal_lookups: LabeledFamily<&'static str, Counter>,
+    /// Requests refused because the commit data didn't match the one computed from local blocks.
+    pub commit_data_mismatches: Counter,
 }
 
 #[vise::register]
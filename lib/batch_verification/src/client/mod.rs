@@ -1,20 +1,25 @@
 use crate::{
-    BatchVerificationRequest, BatchVerificationRequestDecoder, BatchVerificationResponse,
-    BatchVerificationResponseCodec, BatchVerificationResult,
+    BATCH_VERIFICATION_PATH, BatchVerificationRequest, BatchVerificationRequestDecoder,
+    BatchVerificationResponse, BatchVerificationResponseCodec, BatchVerificationResult,
+    FrameLimits, IDENTITY_HANDSHAKE_VERSION, RefusalReason, SIGNING_HASH_VERSION, SignerIdentity,
 };
-use alloy::primitives::Address;
+use alloy::primitives::{Address, B256};
 use alloy::signers::local::PrivateKeySigner;
 use async_trait::async_trait;
 use futures::{SinkExt, StreamExt};
 use secrecy::{ExposeSecret, SecretString};
+use std::path::PathBuf;
 use std::str::FromStr;
-use std::time::Duration;
-use structdiff::StructDiff;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::io::AsyncReadExt;
 use tokio::sync::mpsc;
 use tokio_util::codec::{FramedRead, FramedWrite};
 use zksync_os_batch_types::BatchSignature;
 use zksync_os_batch_types::BlockMerkleTreeData;
+use zksync_os_contract_interface::models::CommitBatchInfo;
+use zksync_os_error_reporting::codes::batch_verification as error_codes;
+use zksync_os_error_reporting::{ErrorCode, NodeError, report_error};
 use zksync_os_interface::types::BlockOutput;
 use zksync_os_l1_sender::commitment::BatchInfo;
 use zksync_os_merkle_tree::TreeBatchOutput;
@@ -22,75 +27,198 @@ use zksync_os_observability::ComponentStateHandle;
 use zksync_os_observability::ComponentStateReporter;
 use zksync_os_observability::GenericComponentState;
 use zksync_os_observability::StateLabel;
-use zksync_os_pipeline::{PeekableReceiver, PipelineComponent};
-use zksync_os_socket::connect;
+use zksync_os_pipeline::{JoinPolicy, Joined, JoinedReceiver, PipelineComponent2};
+use zksync_os_socket::{
+    ConnectOptions, KeepaliveConfig, MaybeTlsStream, TlsConfig, connect_tls_with_options,
+    connect_with_options,
+};
 use zksync_os_storage_api::ReadFinality;
 use zksync_os_storage_api::ReplayRecord;
 
 mod block_cache;
+mod journal;
 mod metrics;
 
 use block_cache::BlockCache;
+pub use block_cache::{BlockSource, VerificationBlock};
+use journal::{JournalLookup, SignedRequestRecord};
+use metrics::BATCH_VERIFICATION_CLIENT_METRICS;
+
+pub use journal::SigningJournal;
 
 /// Client that connects to the main sequencer for batch verification
-pub struct BatchVerificationClient<Finality> {
+pub struct BatchVerificationClient<Finality, Blocks> {
     chain_id: u64,
     diamond_proxy: Address,
     server_address: String,
+    tls_config: Option<TlsConfig>,
+    keepalive: KeepaliveConfig,
+    frame_limits: FrameLimits,
     signer: PrivateKeySigner,
     block_cache: BlockCache<Finality>,
+    block_source: Arc<Blocks>,
+    journal_path: PathBuf,
+    journal_retention: usize,
 }
 
 #[derive(Debug, thiserror::Error)]
 enum BatchVerificationError {
     #[error("Missing records for block {0}")]
     MissingBlock(u64),
+    #[error("Failed to load block {block_number} from local storage: {error:#}")]
+    BlockLoad {
+        block_number: u64,
+        error: anyhow::Error,
+    },
     #[error("Tree error")]
     TreeError,
-    #[error("Batch data mismatch: {0}")]
-    BatchDataMismatch(String),
+    #[error("Batch data mismatch in {field}: requested {requested}, computed {computed}")]
+    BatchDataMismatch {
+        /// First mismatching field.
+        field: String,
+        requested: String,
+        computed: String,
+    },
+    #[error(
+        "Batch {batch_number} was already signed for different commit data \
+         (signed digest {signed_digest}, requested {requested_digest})"
+    )]
+    ConflictingCommitData {
+        batch_number: u64,
+        signed_digest: B256,
+        requested_digest: B256,
+    },
+    #[error(
+        "Batch {batch_number} is older than the signing journal retention \
+         (oldest journaled batch {oldest_batch})"
+    )]
+    BeyondJournalRetention {
+        batch_number: u64,
+        oldest_batch: u64,
+    },
+    #[error("Failed to journal signature: {0:#}")]
+    Journal(anyhow::Error),
+}
+
+impl BatchVerificationError {
+    fn refusal_reason(&self) -> RefusalReason {
+        match self {
+            Self::MissingBlock(block_number) => RefusalReason::MissingBlocks {
+                first_missing: *block_number,
+            },
+            Self::BatchDataMismatch { field, .. } => RefusalReason::CommitmentMismatch {
+                field: field.clone(),
+            },
+            // The sequencer requests a signature for different data than it did before
+            Self::ConflictingCommitData { .. } => RefusalReason::CommitmentMismatch {
+                field: "commit_data_digest".to_owned(),
+            },
+            Self::BlockLoad { .. }
+            | Self::TreeError
+            | Self::BeyondJournalRetention { .. }
+            | Self::Journal(_) => RefusalReason::Internal(self.to_string()),
+        }
+    }
+}
+
+impl NodeError for BatchVerificationError {
+    fn error_code(&self) -> &'static ErrorCode {
+        match self {
+            Self::MissingBlock(_) => &error_codes::MISSING_BLOCK,
+            Self::BlockLoad { .. } => &error_codes::BLOCK_LOAD,
+            Self::TreeError => &error_codes::TREE,
+            Self::BatchDataMismatch { .. } => &error_codes::BATCH_DATA_MISMATCH,
+            Self::ConflictingCommitData { .. } => &error_codes::CONFLICTING_COMMIT_DATA,
+            Self::BeyondJournalRetention { .. } => &error_codes::BEYOND_JOURNAL_RETENTION,
+            Self::Journal(_) => &error_codes::JOURNAL,
+        }
+    }
 }
 
-type VerificationInput = (
-    BlockOutput,
-    zksync_os_storage_api::ReplayRecord,
-    BlockMerkleTreeData,
-);
+/// Sequenced block, zipped by block number with its [`BlockMerkleTreeData`] from the tree.
+type VerificationInput = JoinedReceiver<(BlockOutput, ReplayRecord), BlockMerkleTreeData>;
 
-impl<Finality: ReadFinality> BatchVerificationClient<Finality> {
+impl<Finality: ReadFinality, Blocks: BlockSource> BatchVerificationClient<Finality, Blocks> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         finality: Finality,
+        block_source: Blocks,
+        max_cached_blocks: usize,
         private_key: SecretString,
         chain_id: u64,
         diamond_proxy: Address,
         server_address: String,
+        tls_config: Option<TlsConfig>,
+        keepalive: KeepaliveConfig,
+        frame_limits: FrameLimits,
+        journal_path: PathBuf,
+        journal_retention: usize,
     ) -> Self {
         Self {
             signer: PrivateKeySigner::from_str(private_key.expose_secret())
                 .expect("Invalid batch verification private key"),
             chain_id,
             diamond_proxy,
-            block_cache: BlockCache::new(finality),
+            block_cache: BlockCache::new(finality, max_cached_blocks),
+            block_source: Arc::new(block_source),
             server_address,
+            tls_config,
+            keepalive,
+            frame_limits,
+            journal_path,
+            journal_retention,
         }
     }
 
     async fn connect_and_handle(
         &mut self,
-        input: &mut PeekableReceiver<VerificationInput>,
+        input: &mut VerificationInput,
+        journal: &mut SigningJournal,
         latency_tracker: &ComponentStateHandle<BatchVerificationClientState>,
     ) -> anyhow::Result<()> {
-        let mut socket = connect(&self.server_address, "/batch_verification").await?;
+        // Keepalive makes an unreachable server surface as a read error, so that the client
+        // reconnects instead of waiting for requests forever
+        let options = ConnectOptions::default().with_keepalive(Some(self.keepalive));
+        let mut socket = match &self.tls_config {
+            Some(tls_config) => {
+                connect_tls_with_options(
+                    &self.server_address,
+                    BATCH_VERIFICATION_PATH,
+                    tls_config,
+                    &options,
+                )
+                .await?
+            }
+            None => MaybeTlsStream::Plain(
+                connect_with_options(&self.server_address, BATCH_VERIFICATION_PATH, &options)
+                    .await?,
+            ),
+        };
 
         let batch_verification_version = socket.read_u32().await?;
-        let (recv, send) = socket.split();
+        if batch_verification_version >= IDENTITY_HANDSHAKE_VERSION {
+            // Prove to the server which signer we are, so that we count towards the quorum
+            let mut nonce = B256::ZERO;
+            socket.read_exact(nonce.as_mut_slice()).await?;
+            SignerIdentity::sign(nonce, &self.signer)
+                .await
+                .write(&mut socket)
+                .await?;
+        }
+        let (recv, send) = tokio::io::split(socket);
         let mut reader = FramedRead::new(
             recv,
-            BatchVerificationRequestDecoder::new(batch_verification_version),
+            BatchVerificationRequestDecoder::new(
+                batch_verification_version,
+                self.frame_limits.max_request_bytes,
+            ),
         );
         let mut writer = FramedWrite::new(
             send,
-            BatchVerificationResponseCodec::new(batch_verification_version),
+            BatchVerificationResponseCodec::new(
+                batch_verification_version,
+                self.frame_limits.max_response_bytes,
+            ),
         );
 
         tracing::info!("Connected to main sequencer for batch verification");
@@ -100,14 +228,15 @@ impl<Finality: ReadFinality> BatchVerificationClient<Finality> {
             tokio::select! {
                 block = input.recv() => {
                     match block {
-                        Some((block_output, replay_record, tree_data)) => {
-                            // we remove blocks from cache based on incoming singing requests.
-                            // this prevent memory exhaustion / leak
+                        Some(Joined::Both((block_output, replay_record), tree_data)) => {
+                            // blocks are evicted once their batch is signed or committed, or
+                            // when the cache is full
                             self.block_cache.insert(
                                 replay_record.block_context.block_number,
                                 (block_output, replay_record, tree_data),
                             )?;
                         }
+                        Some(Joined::A(_) | Joined::B(_)) => unreachable!("inputs are zipped"),
                         None => return Ok(()), // Channel closed, we are stopping now
                     }
                 }
@@ -119,23 +248,25 @@ impl<Finality: ReadFinality> BatchVerificationClient<Finality> {
 
                             let batch_number = message.batch_number;
                             let request_id = message.request_id;
-                            let verification_result = self.handle_verification_request(message).await;
+                            let verification_result = self
+                                .handle_verification_request(journal, message, batch_verification_version)
+                                .await;
 
                             latency_tracker.enter_state(BatchVerificationClientState::WaitingSend);
-                            match verification_result {
-                                Ok(signature) => {
+                            match &verification_result {
+                                Ok(_) => {
                                     tracing::info!(batch_number, request_id, "Approved batch verification request");
-                                    writer.send(BatchVerificationResponse { request_id, batch_number, result: BatchVerificationResult::Success(signature) }).await?;
                                 },
                                 Err(reason) => {
-                                    tracing::info!(batch_number, request_id, "Batch verification failed: {}", reason);
-                                    writer.send(BatchVerificationResponse { request_id, batch_number, result: BatchVerificationResult::Refused(reason.to_string()) }).await?;
+                                    tracing::info_span!("batch_verification", batch_number, request_id)
+                                        .in_scope(|| report_error(reason));
                                 },
                             }
+                            writer.send(verification_response(request_id, batch_number, verification_result)).await?;
                         }
-                        Some(Err(parsing_err)) =>
-                        {
-                            tracing::error!("Error parsing verification request message. Ignoring: {}", parsing_err);
+                        Some(Err(err)) => {
+                            // The reader yields nothing after an error, so the connection is re-established
+                            return Err(anyhow::Error::new(err).context("Error reading verification request"));
                         }
                         None => {
                             anyhow::bail!("Server has disconnected verification client");
@@ -146,9 +277,24 @@ impl<Finality: ReadFinality> BatchVerificationClient<Finality> {
         }
     }
 
-    async fn handle_verification_request(
+    /// Signs `commit_data` as expected by a server using `wire_format_version`.
+    async fn sign(
         &self,
+        commit_data: &CommitBatchInfo,
+        wire_format_version: u32,
+    ) -> BatchSignature {
+        if wire_format_version >= SIGNING_HASH_VERSION {
+            BatchSignature::sign_batch(commit_data, self.chain_id, &self.signer).await
+        } else {
+            BatchSignature::sign_legacy_batch(commit_data, &self.signer).await
+        }
+    }
+
+    async fn handle_verification_request(
+        &mut self,
+        journal: &mut SigningJournal,
         request: BatchVerificationRequest,
+        wire_format_version: u32,
     ) -> Result<BatchSignature, BatchVerificationError> {
         tracing::info!(
             batch_number = request.batch_number,
@@ -158,27 +304,73 @@ impl<Finality: ReadFinality> BatchVerificationClient<Finality> {
             request.last_block_number,
         );
 
-        let blocks: Vec<(&BlockOutput, &ReplayRecord, TreeBatchOutput)> =
-            (request.first_block_number..=request.last_block_number)
-                .map(|block_number| {
-                    let (block_output, replay_record, tree_data) = self
-                        .block_cache
-                        .get(block_number)
-                        .ok_or(BatchVerificationError::MissingBlock(block_number))?;
-
-                    let (root_hash, leaf_count) = tree_data
-                        .block_end
-                        .clone()
-                        .root_info()
-                        .map_err(|_| BatchVerificationError::TreeError)?;
-
-                    let tree_output = TreeBatchOutput {
-                        root_hash,
-                        leaf_count,
-                    };
-                    Ok((block_output, replay_record, tree_output))
-                })
-                .collect::<Result<Vec<_>, BatchVerificationError>>()?;
+        let commit_data_digest = BatchSignature::signing_digest(&request.commit_data);
+        match journal.lookup(request.batch_number, commit_data_digest) {
+            JournalLookup::NotSigned => {}
+            JournalLookup::AlreadySigned(journaled_signature) => {
+                tracing::info!(
+                    batch_number = request.batch_number,
+                    request_id = request.request_id,
+                    "Batch was already signed for the same commit data, serving journaled signature"
+                );
+                BATCH_VERIFICATION_CLIENT_METRICS.journal_lookups[&"already_signed"].inc();
+                // Signing is deterministic, so this reproduces the journaled signature unless it
+                // was made for a server using another signing scheme
+                let signature = self.sign(&request.commit_data, wire_format_version).await;
+                if signature != journaled_signature {
+                    tracing::info!(
+                        batch_number = request.batch_number,
+                        request_id = request.request_id,
+                        wire_format_version,
+                        "Journaled signature uses another signing scheme, re-signed the batch"
+                    );
+                }
+                return Ok(signature);
+            }
+            JournalLookup::Conflict { signed_digest } => {
+                BATCH_VERIFICATION_CLIENT_METRICS.journal_lookups[&"conflict"].inc();
+                return Err(BatchVerificationError::ConflictingCommitData {
+                    batch_number: request.batch_number,
+                    signed_digest,
+                    requested_digest: commit_data_digest,
+                });
+            }
+            JournalLookup::BeyondRetention { oldest_batch } => {
+                BATCH_VERIFICATION_CLIENT_METRICS.journal_lookups[&"beyond_retention"].inc();
+                return Err(BatchVerificationError::BeyondJournalRetention {
+                    batch_number: request.batch_number,
+                    oldest_batch,
+                });
+            }
+        }
+
+        // Blocks received before a restart (or already evicted) are loaded from local storage
+        let block_range = request.first_block_number..=request.last_block_number;
+        let loaded_blocks = self
+            .block_cache
+            .load_missing(block_range.clone(), &self.block_source)
+            .await?;
+        let blocks: Vec<(&BlockOutput, &ReplayRecord, TreeBatchOutput)> = block_range
+            .map(|block_number| {
+                let (block_output, replay_record, tree_data) = self
+                    .block_cache
+                    .get(block_number)
+                    .or_else(|| loaded_blocks.get(&block_number))
+                    .ok_or(BatchVerificationError::MissingBlock(block_number))?;
+
+                let (root_hash, leaf_count) = tree_data
+                    .block_end
+                    .clone()
+                    .root_info()
+                    .map_err(|_| BatchVerificationError::TreeError)?;
+
+                let tree_output = TreeBatchOutput {
+                    root_hash,
+                    leaf_count,
+                };
+                Ok((block_output, replay_record, tree_output))
+            })
+            .collect::<Result<Vec<_>, BatchVerificationError>>()?;
 
         let commit_batch_info = BatchInfo::new(
             blocks
@@ -198,20 +390,132 @@ impl<Finality: ReadFinality> BatchVerificationClient<Finality> {
         )
         .commit_info;
 
-        if commit_batch_info != request.commit_data {
-            let diff = request.commit_data.diff(&commit_batch_info);
-
-            return Err(BatchVerificationError::BatchDataMismatch(format!(
-                "Batch data mismatch: {diff:?}",
-            )));
-        }
+        check_commit_data(
+            request.batch_number,
+            &request.commit_data,
+            &commit_batch_info,
+        )?;
 
-        let signature = BatchSignature::sign_batch(&request.commit_data, &self.signer).await;
+        let signature = self.sign(&request.commit_data, wire_format_version).await;
+        journal
+            .append(SignedRequestRecord {
+                batch_number: request.batch_number,
+                commit_data_digest,
+                request_id: request.request_id,
+                timestamp_ms: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .expect("Incorrect system time")
+                    .as_millis() as u64,
+                signature: signature.clone(),
+            })
+            .map_err(BatchVerificationError::Journal)?;
+        // Blocks of the batch are not needed anymore; requests for it are served from the journal
+        self.block_cache
+            .remove_lower_than(request.last_block_number + 1);
 
         Ok(signature)
     }
 }
 
+fn verification_response(
+    request_id: u64,
+    batch_number: u64,
+    verification_result: Result<BatchSignature, BatchVerificationError>,
+) -> BatchVerificationResponse {
+    let result = match verification_result {
+        Ok(signature) => BatchVerificationResult::Success(signature),
+        Err(reason) => BatchVerificationResult::Refused(reason.refusal_reason()),
+    };
+    BatchVerificationResponse {
+        request_id,
+        batch_number,
+        result,
+    }
+}
+
+/// Compares the commit data requested to be signed with the one computed from local blocks.
+/// All mismatching fields are logged with both values; the error names the first one.
+fn check_commit_data(
+    batch_number: u64,
+    requested: &CommitBatchInfo,
+    computed: &CommitBatchInfo,
+) -> Result<(), BatchVerificationError> {
+    let mismatches = commit_data_mismatches(requested, computed);
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+
+    BATCH_VERIFICATION_CLIENT_METRICS
+        .commit_data_mismatches
+        .inc();
+    for (field, requested, computed) in &mismatches {
+        tracing::error!(
+            batch_number,
+            field,
+            requested,
+            computed,
+            "Requested commit data doesn't match local blocks"
+        );
+    }
+    let (field, requested, computed) = mismatches.into_iter().next().unwrap();
+    Err(BatchVerificationError::BatchDataMismatch {
+        field: field.to_owned(),
+        requested,
+        computed,
+    })
+}
+
+/// Returns `(field, requested, computed)` for each mismatching field, in declaration order.
+fn commit_data_mismatches(
+    requested: &CommitBatchInfo,
+    computed: &CommitBatchInfo,
+) -> Vec<(&'static str, String, String)> {
+    // Destructured, so that fields added later cannot be missed
+    let CommitBatchInfo {
+        batch_number,
+        new_state_commitment,
+        number_of_layer1_txs,
+        priority_operations_hash,
+        dependency_roots_rolling_hash,
+        l2_to_l1_logs_root_hash,
+        l2_da_validator,
+        da_commitment,
+        first_block_timestamp,
+        last_block_timestamp,
+        chain_id,
+        operator_da_input,
+    } = requested;
+    let mut mismatches = vec![];
+    macro_rules! compare {
+        ($($field:ident),+) => {
+            $(
+                if *$field != computed.$field {
+                    mismatches.push((
+                        stringify!($field),
+                        format!("{:?}", $field),
+                        format!("{:?}", computed.$field),
+                    ));
+                }
+            )+
+        };
+    }
+    compare!(
+        batch_number,
+        new_state_commitment,
+        number_of_layer1_txs,
+        priority_operations_hash,
+        dependency_roots_rolling_hash,
+        l2_to_l1_logs_root_hash,
+        l2_da_validator,
+        da_commitment,
+        first_block_timestamp,
+        last_block_timestamp,
+        chain_id,
+        operator_da_input
+    );
+    mismatches
+}
+
 enum BatchVerificationClientState {
     Connecting,
     WaitingRecv,
@@ -246,16 +550,27 @@ impl StateLabel for BatchVerificationClientState {
 }
 
 #[async_trait]
-impl<Finality: ReadFinality> PipelineComponent for BatchVerificationClient<Finality> {
-    type Input = VerificationInput;
+impl<Finality: ReadFinality, Blocks: BlockSource> PipelineComponent2
+    for BatchVerificationClient<Finality, Blocks>
+{
+    type InputA = (BlockOutput, ReplayRecord);
+    type InputB = BlockMerkleTreeData;
     type Output = ();
 
     const NAME: &'static str = "batch_verification_client";
+    const INPUT_NAMES: [&'static str; 2] = ["blocks", "tree"];
     const OUTPUT_BUFFER_SIZE: usize = 5;
 
+    fn join_policy(&self) -> JoinPolicy<Self::InputA, Self::InputB> {
+        JoinPolicy::ZipByBlockNumber {
+            block_number_a: |(block_output, _)| block_output.header.number,
+            block_number_b: |tree_data| tree_data.block_end.block,
+        }
+    }
+
     async fn run(
         mut self,
-        mut input: PeekableReceiver<Self::Input>,
+        mut input: VerificationInput,
         _output: mpsc::Sender<Self::Output>,
     ) -> anyhow::Result<()> {
         // Did not use backon due to borrowing issues
@@ -263,8 +578,13 @@ impl<Finality: ReadFinality> PipelineComponent for BatchVerificationClient<Final
             "batch_verification_client",
             BatchVerificationClientState::Connecting,
         );
+        // Signatures are journaled before being sent, so that no conflicting signature is produced
+        // after a restart
+        let mut journal = SigningJournal::open(&self.journal_path, self.journal_retention)?;
         loop {
-            let result = self.connect_and_handle(&mut input, &latency_tracker).await;
+            let result = self
+                .connect_and_handle(&mut input, &mut journal, &latency_tracker)
+                .await;
 
             match result {
                 Ok(()) => {
@@ -284,3 +604,244 @@ impl<Finality: ReadFinality> PipelineComponent for BatchVerificationClient<Final
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use tempfile::TempDir;
+    use zksync_os_error_reporting::{ERROR_METRICS, Severity};
+    use zksync_os_interface::tracing::NopTracer;
+    use zksync_os_interface::traits::{NoopTxCallback, TxListSource};
+    use zksync_os_interface::types::BlockContext;
+    use zksync_os_merkle_tree::{MerkleTree, MerkleTreeVersion, RocksDBWrapper};
+    use zksync_os_multivm::test_utils::EmptyState;
+
+    const CHAIN_ID: u64 = 270;
+
+    /// Empty blocks executed on top of [`EmptyState`], with a tree that has a version per block.
+    struct StoredBlocks {
+        tree: MerkleTree<RocksDBWrapper>,
+    }
+
+    impl StoredBlocks {
+        fn new(db_path: &Path, block_count: u64) -> Self {
+            let mut tree = MerkleTree::new(RocksDBWrapper::new(db_path).unwrap()).unwrap();
+            // Genesis and blocks `1..=block_count` don't write to the tree
+            for _ in 0..=block_count {
+                tree.extend(&[]).unwrap();
+            }
+            Self { tree }
+        }
+    }
+
+    impl BlockSource for StoredBlocks {
+        fn load_block(&self, block_number: u64) -> anyhow::Result<Option<VerificationBlock>> {
+            let replay_record = ReplayRecord {
+                block_context: BlockContext {
+                    block_number,
+                    timestamp: block_number,
+                    chain_id: CHAIN_ID,
+                    gas_limit: 100_000_000,
+                    execution_version: zksync_os_multivm::ExecutionVersion::V3 as u32,
+                    ..Default::default()
+                },
+                starting_l1_priority_id: 0,
+                transactions: vec![],
+                previous_block_timestamp: block_number - 1,
+                block_timestamp_millis: block_number * 1_000,
+                node_version: "0.1.0".parse().unwrap(),
+                block_output_hash: B256::ZERO,
+            };
+            let block_output = zksync_os_multivm::run_block(
+                replay_record.block_context,
+                EmptyState,
+                EmptyState,
+                TxListSource {
+                    transactions: Default::default(),
+                },
+                NoopTxCallback,
+                &mut NopTracer,
+            )?;
+            let tree_data = BlockMerkleTreeData {
+                block_start: MerkleTreeVersion {
+                    tree: self.tree.clone(),
+                    block: block_number - 1,
+                },
+                block_end: MerkleTreeVersion {
+                    tree: self.tree.clone(),
+                    block: block_number,
+                },
+            };
+            Ok(Some((block_output, replay_record, tree_data)))
+        }
+    }
+
+    /// Commit data of batch 1 consisting of stored block 1, as if the tree had `root_hash`.
+    fn stored_commit_data(blocks: &StoredBlocks, root_hash: B256) -> CommitBatchInfo {
+        let (block_output, replay_record, tree_data) = blocks.load_block(1).unwrap().unwrap();
+        let (_, leaf_count) = tree_data.block_end.root_info().unwrap();
+        let tree_output = TreeBatchOutput {
+            root_hash,
+            leaf_count,
+        };
+        BatchInfo::new(
+            vec![(
+                &block_output,
+                &replay_record.block_context,
+                replay_record.transactions.as_slice(),
+                &tree_output,
+            )],
+            CHAIN_ID,
+            Address::ZERO,
+            1,
+        )
+        .commit_info
+    }
+
+    fn commit_data() -> CommitBatchInfo {
+        CommitBatchInfo {
+            batch_number: 42,
+            new_state_commitment: B256::repeat_byte(1),
+            number_of_layer1_txs: 5,
+            priority_operations_hash: B256::ZERO,
+            dependency_roots_rolling_hash: B256::ZERO,
+            l2_to_l1_logs_root_hash: B256::ZERO,
+            l2_da_validator: Address::ZERO,
+            da_commitment: B256::repeat_byte(2),
+            first_block_timestamp: 1234567890,
+            last_block_timestamp: 1234567900,
+            chain_id: 270,
+            operator_da_input: vec![1, 2, 3],
+        }
+    }
+
+    #[test]
+    fn matching_commit_data_is_accepted() {
+        check_commit_data(42, &commit_data(), &commit_data()).unwrap();
+    }
+
+    #[test]
+    fn mismatching_state_root_is_refused() {
+        let requested = CommitBatchInfo {
+            new_state_commitment: B256::repeat_byte(3),
+            da_commitment: B256::repeat_byte(4),
+            ..commit_data()
+        };
+        let mismatches_before = BATCH_VERIFICATION_CLIENT_METRICS
+            .commit_data_mismatches
+            .get();
+
+        let err = check_commit_data(42, &requested, &commit_data()).unwrap_err();
+        let BatchVerificationError::BatchDataMismatch {
+            field,
+            requested,
+            computed,
+        } = &err
+        else {
+            panic!("unexpected error: {err}");
+        };
+        // The first mismatching field is reported
+        assert_eq!(field, "new_state_commitment");
+        assert_eq!(*requested, format!("{:?}", B256::repeat_byte(3)));
+        assert_eq!(*computed, format!("{:?}", B256::repeat_byte(1)));
+        assert_eq!(
+            err.refusal_reason(),
+            RefusalReason::CommitmentMismatch {
+                field: "new_state_commitment".to_owned()
+            }
+        );
+        assert!(
+            BATCH_VERIFICATION_CLIENT_METRICS
+                .commit_data_mismatches
+                .get()
+                > mismatches_before
+        );
+    }
+
+    #[test]
+    fn refusals_are_reported_with_codes() {
+        let labels = ("BV-104", "batch_verification", Severity::Critical);
+        let reported_before = ERROR_METRICS.errors[&labels].get();
+        let requested = CommitBatchInfo {
+            chain_id: 271,
+            ..commit_data()
+        };
+        let err = check_commit_data(42, &requested, &commit_data()).unwrap_err();
+        report_error(&err);
+        assert_eq!(ERROR_METRICS.errors[&labels].get() - reported_before, 1);
+
+        // Missing blocks are expected while the node catches up
+        let err = BatchVerificationError::MissingBlock(7);
+        assert_eq!(err.error_code().severity, Severity::Info);
+        assert!(err.error_code().retryable);
+    }
+    #[tokio::test]
+    async fn commit_data_not_matching_stored_blocks_is_refused() {
+        let temp_dir = TempDir::new().unwrap();
+        let blocks = StoredBlocks::new(&temp_dir.path().join("tree"), 1);
+        let (actual_root, _) = blocks.tree.root_info(1).unwrap().unwrap();
+        let claimed_commit_data = stored_commit_data(&blocks, B256::repeat_byte(0xaa));
+        let actual_commit_data = stored_commit_data(&blocks, actual_root);
+        let mut client = BatchVerificationClient::new(
+            block_cache::tests::finality(0),
+            blocks,
+            10,
+            SecretString::from(B256::repeat_byte(0x11).to_string()),
+            CHAIN_ID,
+            Address::ZERO,
+            "localhost:3072".to_owned(),
+            None,
+            KeepaliveConfig::default(),
+            FrameLimits {
+                max_request_bytes: 1 << 20,
+                max_response_bytes: 1 << 20,
+            },
+            temp_dir.path().join("journal"),
+            10,
+        );
+        let mut journal = SigningJournal::open(&client.journal_path, 10).unwrap();
+        let request = |commit_data| BatchVerificationRequest {
+            batch_number: 1,
+            first_block_number: 1,
+            last_block_number: 1,
+            request_id: 5,
+            commit_data,
+        };
+
+        let result = client
+            .handle_verification_request(
+                &mut journal,
+                request(claimed_commit_data.clone()),
+                SIGNING_HASH_VERSION,
+            )
+            .await;
+        assert_eq!(
+            verification_response(5, 1, result),
+            BatchVerificationResponse {
+                request_id: 5,
+                batch_number: 1,
+                result: BatchVerificationResult::Refused(RefusalReason::CommitmentMismatch {
+                    field: "new_state_commitment".to_owned()
+                }),
+            }
+        );
+        let claimed_digest = BatchSignature::signing_digest(&claimed_commit_data);
+        assert_eq!(journal.lookup(1, claimed_digest), JournalLookup::NotSigned);
+
+        // Commit data for the root of the stored tree is signed
+        let signature = client
+            .handle_verification_request(
+                &mut journal,
+                request(actual_commit_data.clone()),
+                SIGNING_HASH_VERSION,
+            )
+            .await
+            .unwrap();
+        let actual_digest = BatchSignature::signing_digest(&actual_commit_data);
+        assert_eq!(
+            journal.lookup(1, actual_digest),
+            JournalLookup::AlreadySigned(signature)
+        );
+    }
+}
@@ -1,6 +1,12 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
 use std::time::Duration;
 
+use alloy::primitives::Address;
 use secrecy::SecretString;
+use zksync_os_batch_types::{AcceptedSigner, BatchSignatureSet};
+use zksync_os_socket::{KeepaliveConfig, ListenerLimits, TlsConfig, TlsServerConfig};
 
 /// Struct matches zksync_os_server::config::BatchVerificationConfig.
 /// See there for documentation
@@ -10,10 +16,171 @@ pub struct BatchVerificationConfig {
     pub listen_address: String,
     pub client_enabled: bool,
     pub connect_address: String,
-    pub threshold: usize,
+    pub quorum: SignatureQuorum,
     pub accepted_signers: Vec<String>,
+    /// Max time of an EIP-1271 `isValidSignature` call to a contract signer.
+    pub contract_signer_call_timeout: Duration,
     pub request_timeout: Duration,
     pub retry_delay: Duration,
     pub total_timeout: Duration,
     pub signing_key: SecretString,
+    pub signing_journal_path: Option<PathBuf>,
+    pub signing_journal_retention: usize,
+    /// TLS for connections accepted by the server; plaintext if `None`.
+    pub server_tls: Option<TlsServerConfig>,
+    /// TLS for the client connection; plaintext if `None`.
+    pub client_tls: Option<TlsConfig>,
+    /// TCP keepalive for the client connection.
+    pub client_keepalive: KeepaliveConfig,
+    /// Limits of connections accepted by the server.
+    pub server_limits: ListenerLimits,
+    pub frame_limits: FrameLimits,
+}
+
+/// Max sizes of encoded messages, enforced by both the server and the client. A peer sending an
+/// oversized message is disconnected.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameLimits {
+    pub max_request_bytes: usize,
+    pub max_response_bytes: usize,
+}
+
+/// Signatures needed for a batch to be considered verified.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SignatureQuorum {
+    /// Number of signatures, each signer having weight 1.
+    Threshold(usize),
+    /// Total weight of signers; signers missing from `weights` have weight 1.
+    Weighted {
+        weights: Arc<BTreeMap<Address, u64>>,
+        quorum: u64,
+    },
+}
+
+impl SignatureQuorum {
+    pub fn required_weight(&self) -> u64 {
+        match self {
+            SignatureQuorum::Threshold(threshold) => *threshold as u64,
+            SignatureQuorum::Weighted { quorum, .. } => *quorum,
+        }
+    }
+
+    pub fn weight_of(&self, signer: &Address) -> u64 {
+        match self {
+            SignatureQuorum::Threshold(_) => 1,
+            SignatureQuorum::Weighted { weights, .. } => weights.get(signer).copied().unwrap_or(1),
+        }
+    }
+
+    /// Empty set to collect signatures into, weighting them as configured.
+    pub fn signature_set(&self) -> BatchSignatureSet {
+        match self {
+            SignatureQuorum::Threshold(_) => BatchSignatureSet::new(),
+            SignatureQuorum::Weighted { weights, .. } => {
+                BatchSignatureSet::with_weights(weights.clone())
+            }
+        }
+    }
+
+    pub fn is_reached(&self, signatures: &BatchSignatureSet) -> bool {
+        signatures.total_weight() >= self.required_weight()
+    }
+
+    /// Min number of signers from `accepted_signers` that can reach the quorum together.
+    pub fn min_signers(&self, accepted_signers: &[AcceptedSigner]) -> usize {
+        let mut weights: Vec<_> = accepted_signers
+            .iter()
+            .map(|signer| self.weight_of(&signer.address()))
+            .collect();
+        weights.sort_unstable_by(|a, b| b.cmp(a));
+        let required_weight = self.required_weight();
+        let mut total_weight = 0;
+        weights
+            .into_iter()
+            .take_while(|weight| {
+                let reached = total_weight >= required_weight;
+                total_weight += weight;
+                !reached
+            })
+            .count()
+    }
+
+    /// Checks that the quorum can be reached by `accepted_signers`.
+    pub fn validate(&self, accepted_signers: &[AcceptedSigner]) -> anyhow::Result<()> {
+        if let SignatureQuorum::Weighted { weights, .. } = self
+            && let Some(unknown) = weights.keys().find(|address| {
+                !accepted_signers
+                    .iter()
+                    .any(|signer| signer.address() == **address)
+            })
+        {
+            anyhow::bail!("weighted signer {unknown} is not an accepted signer");
+        }
+        let total_weight: u64 = accepted_signers
+            .iter()
+            .map(|signer| self.weight_of(&signer.address()))
+            .sum();
+        anyhow::ensure!(
+            total_weight >= self.required_weight(),
+            "signature quorum {} can never be reached: total weight of accepted signers is {total_weight}",
+            self.required_weight()
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signers(count: u8) -> Vec<AcceptedSigner> {
+        (1..=count)
+            .map(|i| AcceptedSigner::Eoa(Address::repeat_byte(i)))
+            .collect()
+    }
+
+    fn weighted(weights: &[(u8, u64)], quorum: u64) -> SignatureQuorum {
+        let weights = weights
+            .iter()
+            .map(|&(signer, weight)| (Address::repeat_byte(signer), weight))
+            .collect();
+        SignatureQuorum::Weighted {
+            weights: Arc::new(weights),
+            quorum,
+        }
+    }
+
+    #[test]
+    fn legacy_threshold() {
+        let quorum = SignatureQuorum::Threshold(2);
+        quorum.validate(&signers(3)).unwrap();
+        quorum.validate(&signers(1)).unwrap_err();
+        assert_eq!(quorum.min_signers(&signers(3)), 2);
+    }
+
+    #[test]
+    fn mixed_weight_quorum() {
+        // The first signer counts as 2; others as 1
+        let quorum = weighted(&[(1, 2)], 3);
+        quorum.validate(&signers(3)).unwrap();
+        assert_eq!(quorum.weight_of(&Address::repeat_byte(1)), 2);
+        assert_eq!(quorum.weight_of(&Address::repeat_byte(2)), 1);
+        assert_eq!(quorum.required_weight(), 3);
+        // The first signer and any other one
+        assert_eq!(quorum.min_signers(&signers(3)), 2);
+
+        let quorum = weighted(&[(1, 5), (2, 1), (3, 1)], 5);
+        assert_eq!(quorum.min_signers(&signers(3)), 1);
+        assert!(quorum.signature_set().is_empty());
+    }
+
+    #[test]
+    fn unreachable_quorum_is_rejected() {
+        let err = weighted(&[(1, 2)], 5).validate(&signers(2)).unwrap_err();
+        assert!(err.to_string().contains("can never be reached"), "{err}");
+        weighted(&[(1, 3)], 5).validate(&signers(3)).unwrap();
+        // Weights of signers that are not accepted don't count
+        let err = weighted(&[(4, 10)], 5).validate(&signers(3)).unwrap_err();
+        assert!(err.to_string().contains("not an accepted signer"), "{err}");
+    }
 }
@@ -0,0 +1,159 @@
+use alloy::primitives::{Address, B256, Signature, SignatureError, eip191_hash_message};
+use alloy::signers::Signer;
+use alloy::signers::local::PrivateKeySigner;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use zksync_os_batch_types::{SignatureVerificationContext, SignatureVerificationError};
+
+/// Wire format version starting from which clients prove their signer identity after the
+/// version exchange.
+pub const IDENTITY_HANDSHAKE_VERSION: u32 = 3;
+
+/// Prefix of the signed identity message, so that the signature can't be passed off as a batch
+/// signature.
+const IDENTITY_MESSAGE_PREFIX: &[u8] = b"zksync-os batch verification identity:";
+const ENCODED_LEN: usize = 20 + 65;
+
+/// Proof of the signer identity a client sends in response to the nonce sent by the server.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SignerIdentity {
+    /// Address the client claims to sign with.
+    pub signer: Address,
+    /// Signature of the identity message for the server's nonce.
+    pub signature: Signature,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum IdentityError {
+    #[error("identity of {claimed} is signed by {recovered}")]
+    Spoofed {
+        claimed: Address,
+        recovered: Address,
+    },
+    #[error("invalid identity signature: {0}")]
+    Invalid(#[from] SignatureError),
+    #[error(transparent)]
+    NotAccepted(#[from] SignatureVerificationError),
+}
+
+impl SignerIdentity {
+    pub async fn sign(nonce: B256, private_key: &PrivateKeySigner) -> Self {
+        let signature = private_key
+            .sign_message(&identity_message(nonce))
+            .await
+            .unwrap();
+        Self {
+            signer: private_key.address(),
+            signature,
+        }
+    }
+
+    /// Checks that the identity is signed by the claimed signer for `nonce`, and returns the
+    /// accepted signer it corresponds to. For contract signers, that's the wallet accepting the
+    /// signature rather than the claimed (owner) address.
+    pub async fn verify(
+        &self,
+        nonce: B256,
+        context: &SignatureVerificationContext,
+    ) -> Result<Address, IdentityError> {
+        let hash = eip191_hash_message(identity_message(nonce));
+        let recovered = self.signature.recover_address_from_prehash(&hash)?;
+        if recovered != self.signer {
+            return Err(IdentityError::Spoofed {
+                claimed: self.signer,
+                recovered,
+            });
+        }
+        Ok(context.find_signer(hash, &self.signature).await?.address())
+    }
+
+    pub async fn read(reader: &mut (impl AsyncRead + Unpin)) -> std::io::Result<Self> {
+        let mut bytes = [0_u8; ENCODED_LEN];
+        reader.read_exact(&mut bytes).await?;
+        let (signer, signature) = bytes.split_at(20);
+        let signature = Signature::from_raw(signature)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        Ok(Self {
+            signer: Address::from_slice(signer),
+            signature,
+        })
+    }
+
+    pub async fn write(&self, writer: &mut (impl AsyncWrite + Unpin)) -> std::io::Result<()> {
+        writer.write_all(self.signer.as_slice()).await?;
+        writer.write_all(&self.signature.as_bytes()).await?;
+        writer.flush().await
+    }
+}
+
+fn identity_message(nonce: B256) -> Vec<u8> {
+    [IDENTITY_MESSAGE_PREFIX, nonce.as_slice()].concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::providers::{Provider, ProviderBuilder};
+    use alloy::transports::mock::Asserter;
+    use std::time::Duration;
+    use zksync_os_batch_types::AcceptedSigner;
+
+    fn context(signers: &[&PrivateKeySigner]) -> SignatureVerificationContext {
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(Asserter::new())
+            .erased();
+        let signers = signers
+            .iter()
+            .map(|signer| AcceptedSigner::Eoa(signer.address()))
+            .collect();
+        SignatureVerificationContext::new(signers, provider, Duration::from_secs(5))
+    }
+
+    #[tokio::test]
+    async fn identity_roundtrip() {
+        let signer = PrivateKeySigner::random();
+        let identity = SignerIdentity::sign(B256::repeat_byte(1), &signer).await;
+        let mut encoded = vec![];
+        identity.write(&mut encoded).await.unwrap();
+        assert_eq!(encoded.len(), ENCODED_LEN);
+        let decoded = SignerIdentity::read(&mut encoded.as_slice()).await.unwrap();
+        assert_eq!(decoded, identity);
+
+        let context = context(&[&signer]);
+        let verified = identity.verify(B256::repeat_byte(1), &context).await;
+        assert_eq!(verified.unwrap(), signer.address());
+        // Identities can't be replayed for another nonce
+        let err = identity
+            .verify(B256::repeat_byte(2), &context)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, IdentityError::Spoofed { .. }), "{err}");
+    }
+
+    #[tokio::test]
+    async fn spoofed_identity_is_rejected() {
+        let accepted = PrivateKeySigner::random();
+        let other = PrivateKeySigner::random();
+        let context = context(&[&accepted]);
+
+        let mut identity = SignerIdentity::sign(B256::ZERO, &other).await;
+        identity.signer = accepted.address();
+        let err = identity.verify(B256::ZERO, &context).await.unwrap_err();
+        assert!(
+            matches!(err, IdentityError::Spoofed { claimed, recovered }
+                if claimed == accepted.address() && recovered == other.address()),
+            "{err}"
+        );
+
+        // Genuine identity of a signer that is not accepted
+        let identity = SignerIdentity::sign(B256::ZERO, &other).await;
+        let err = identity.verify(B256::ZERO, &context).await.unwrap_err();
+        assert!(
+            matches!(
+                err,
+                IdentityError::NotAccepted(SignatureVerificationError::UnknownSigner(_))
+            ),
+            "{err}"
+        );
+    }
+}
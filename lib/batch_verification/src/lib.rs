@@ -1,5 +1,8 @@
 mod wire_format;
-pub(crate) use wire_format::BATCH_VERIFICATION_WIRE_FORMAT_VERSION;
+pub(crate) use wire_format::{BATCH_VERIFICATION_WIRE_FORMAT_VERSION, SIGNING_HASH_VERSION};
+
+/// Path clients connect to, sent in the handshake.
+pub(crate) const BATCH_VERIFICATION_PATH: &str = "/batch_verification";
 
 mod request;
 pub(crate) use request::BatchVerificationRequest;
@@ -11,12 +14,17 @@ pub(crate) use response::BatchVerificationResponse;
 pub(crate) use response::BatchVerificationResponseCodec;
 pub(crate) use response::BatchVerificationResponseDecoder;
 pub(crate) use response::BatchVerificationResult;
+pub(crate) use response::RefusalReason;
+
+mod identity;
+pub(crate) use identity::IDENTITY_HANDSHAKE_VERSION;
+pub(crate) use identity::SignerIdentity;
 
 mod client;
-pub use client::BatchVerificationClient;
+pub use client::{BatchVerificationClient, BlockSource, SigningJournal, VerificationBlock};
 
 mod config;
-pub use config::BatchVerificationConfig;
+pub use config::{BatchVerificationConfig, FrameLimits, SignatureQuorum};
 
 mod sequencer;
 pub use sequencer::component::BatchVerificationPipelineStep;
@@ -1,6 +1,7 @@
 use tokio_util::bytes::BytesMut;
-use tokio_util::codec::{self, LengthDelimitedCodec};
+use tokio_util::codec;
 use zksync_os_contract_interface::models::CommitBatchInfo;
+use zksync_os_socket::FrameCodec;
 
 /// Request sent from main sequencer to external nodes for batch verification
 #[derive(Clone, PartialEq)]
@@ -24,14 +25,16 @@ impl std::fmt::Debug for BatchVerificationRequest {
 }
 
 pub struct BatchVerificationRequestDecoder {
-    inner: LengthDelimitedCodec,
+    inner: FrameCodec,
     wire_format_version: u32,
 }
 
 impl BatchVerificationRequestDecoder {
-    pub fn new(wire_format_version: u32) -> Self {
+    /// Requests larger than `max_frame_bytes` fail with
+    /// [`FrameTooLarge`](zksync_os_socket::FrameTooLarge).
+    pub fn new(wire_format_version: u32, max_frame_bytes: usize) -> Self {
         Self {
-            inner: LengthDelimitedCodec::new(),
+            inner: FrameCodec::new(max_frame_bytes),
             wire_format_version,
         }
     }
@@ -48,12 +51,11 @@ impl codec::Decoder for BatchVerificationRequestDecoder {
     }
 }
 
-pub struct BatchVerificationRequestCodec(LengthDelimitedCodec);
+pub struct BatchVerificationRequestCodec(FrameCodec);
 
 impl BatchVerificationRequestCodec {
-    #[allow(clippy::new_without_default)]
-    pub fn new() -> Self {
-        Self(LengthDelimitedCodec::new())
+    pub fn new(max_frame_bytes: usize) -> Self {
+        Self(FrameCodec::new(max_frame_bytes))
     }
 }
 
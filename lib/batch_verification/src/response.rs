@@ -1,13 +1,40 @@
 use serde::{Deserialize, Serialize};
-use tokio_util::codec::{self, LengthDelimitedCodec};
+use tokio_util::codec;
 
 use crate::BATCH_VERIFICATION_WIRE_FORMAT_VERSION;
 use zksync_os_batch_types::BatchSignature;
+use zksync_os_socket::FrameCodec;
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub enum BatchVerificationResult {
     Success(BatchSignature),
-    Refused(String),
+    Refused(RefusalReason),
+}
+
+/// Why an external node refused to sign a batch.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, thiserror::Error)]
+pub enum RefusalReason {
+    /// The node hasn't synced all blocks of the batch yet.
+    #[error("missing records for block {first_missing}")]
+    MissingBlocks { first_missing: u64 },
+    /// The node computed different commit data; `field` lists the mismatched fields.
+    #[error("commitment mismatch in {field}")]
+    CommitmentMismatch { field: String },
+    /// The node can't verify blocks of the batch's execution version.
+    #[error("execution version is not supported")]
+    ExecutionVersionUnsupported,
+    #[error("{0}")]
+    Internal(String),
+}
+
+impl RefusalReason {
+    /// Whether the node may sign the batch if the request is sent again later.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::MissingBlocks { .. } | Self::Internal(_) => true,
+            Self::CommitmentMismatch { .. } | Self::ExecutionVersionUnsupported => false,
+        }
+    }
 }
 
 /// Response sent from external nodes back to main sequencer
@@ -19,15 +46,16 @@ pub struct BatchVerificationResponse {
 }
 
 pub struct BatchVerificationResponseDecoder {
-    inner: LengthDelimitedCodec,
+    inner: FrameCodec,
     wire_format_version: u32,
 }
 
 impl BatchVerificationResponseDecoder {
-    #[allow(clippy::new_without_default)]
-    pub fn new() -> Self {
+    /// Responses larger than `max_frame_bytes` fail with
+    /// [`FrameTooLarge`](zksync_os_socket::FrameTooLarge).
+    pub fn new(max_frame_bytes: usize) -> Self {
         Self {
-            inner: LengthDelimitedCodec::new(),
+            inner: FrameCodec::new(max_frame_bytes),
             wire_format_version: BATCH_VERIFICATION_WIRE_FORMAT_VERSION, // server always uses the latest version
         }
     }
@@ -52,14 +80,14 @@ impl codec::Decoder for BatchVerificationResponseDecoder {
 }
 
 pub struct BatchVerificationResponseCodec {
-    inner: LengthDelimitedCodec,
+    inner: FrameCodec,
     wire_format_version: u32,
 }
 
 impl BatchVerificationResponseCodec {
-    pub fn new(wire_format_version: u32) -> Self {
+    pub fn new(wire_format_version: u32, max_frame_bytes: usize) -> Self {
         Self {
-            inner: LengthDelimitedCodec::new(),
+            inner: FrameCodec::new(max_frame_bytes),
             wire_format_version,
         }
     }
@@ -1,7 +1,9 @@
+use super::metrics::BATCH_VERIFICATION_METRICS;
 use super::server::{BatchVerificationRequestError, BatchVerificationServer};
 use crate::config::BatchVerificationConfig;
-use crate::{BatchVerificationResponse, BatchVerificationResult};
-use alloy::primitives::Address;
+use crate::{BatchVerificationResponse, BatchVerificationResult, RefusalReason};
+use alloy::providers::DynProvider;
+use anyhow::Context as _;
 use async_trait::async_trait;
 use dashmap::DashMap;
 use futures::FutureExt;
@@ -11,14 +13,20 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 use tokio::sync::mpsc::{self, Sender};
 use tokio::time::Instant;
-use zksync_os_batch_types::{BatchSignatureSet, ValidatedBatchSignature};
+use zksync_os_batch_types::{
+    AcceptedSigner, BatchSignature, BatchSignatureSet, SignatureVerificationContext,
+    ValidatedBatchSignature,
+};
 use zksync_os_contract_interface::models::CommitBatchInfo;
+use zksync_os_error_reporting::codes::batch_verification as error_codes;
+use zksync_os_error_reporting::{ErrorCode, NodeError, WithErrorCode, report_error};
 use zksync_os_l1_sender::batcher_metrics::BatchExecutionStage;
 use zksync_os_l1_sender::batcher_model::{
     BatchForSigning, BatchSignatureData, SignedBatchEnvelope,
 };
 use zksync_os_observability::{ComponentStateReporter, GenericComponentState};
 use zksync_os_pipeline::{PeekableReceiver, PipelineComponent};
+use zksync_os_socket::TlsServerConfig;
 
 fn report_exit<T, E: std::fmt::Debug>(name: &'static str) -> impl Fn(Result<T, E>) {
     move |result| match result {
@@ -28,13 +36,18 @@ fn report_exit<T, E: std::fmt::Debug>(name: &'static str) -> impl Fn(Result<T, E
 }
 pub struct BatchVerificationPipelineStep<E> {
     config: BatchVerificationConfig,
+    chain_id: u64,
+    /// Used to check signatures of contract signers.
+    l1_provider: DynProvider,
     _phantom: std::marker::PhantomData<E>,
 }
 
 impl<E> BatchVerificationPipelineStep<E> {
-    pub fn new(config: BatchVerificationConfig) -> Self {
+    pub fn new(config: BatchVerificationConfig, chain_id: u64, l1_provider: DynProvider) -> Self {
         Self {
             config,
+            chain_id,
+            l1_provider,
             _phantom: std::marker::PhantomData,
         }
     }
@@ -54,14 +67,36 @@ impl<E: Send + Sync + 'static> PipelineComponent for BatchVerificationPipelineSt
         output: mpsc::Sender<Self::Output>,
     ) -> anyhow::Result<()> {
         if self.config.server_enabled {
-            let (server, response_receiver) = BatchVerificationServer::new();
+            let accepted_signers: Vec<_> = self
+                .config
+                .accepted_signers
+                .iter()
+                .map(|s| s.parse::<AcceptedSigner>().unwrap())
+                .collect();
+            let signature_verification = Arc::new(SignatureVerificationContext::new(
+                accepted_signers,
+                self.l1_provider,
+                self.config.contract_signer_call_timeout,
+            ));
+            let (server, response_receiver) = BatchVerificationServer::new(
+                self.config.frame_limits,
+                signature_verification.clone(),
+            );
             let server = Arc::new(server);
             let response_channels = Arc::new(DashMap::new());
 
+            let tls_acceptor = self
+                .config
+                .server_tls
+                .as_ref()
+                .map(TlsServerConfig::acceptor)
+                .transpose()
+                .context("invalid batch verification server TLS config")?;
             let server_for_fut = server.clone();
             let server_address = self.config.listen_address.clone();
+            let server_limits = self.config.server_limits;
             let server_fut = server_for_fut
-                .run_server(server_address)
+                .run_server(server_address, tls_acceptor, server_limits)
                 .boxed()
                 .map(report_exit("Batch verification server"));
 
@@ -71,7 +106,13 @@ impl<E: Send + Sync + 'static> PipelineComponent for BatchVerificationPipelineSt
                     .boxed()
                     .map(report_exit("Batch response processor"));
 
-            let verifier = BatchVerifier::new(self.config, response_channels, server);
+            let verifier = BatchVerifier::new(
+                self.config,
+                self.chain_id,
+                signature_verification,
+                response_channels,
+                server,
+            )?;
             let verifier_fut = verifier
                 .run(input, output)
                 .boxed()
@@ -84,7 +125,8 @@ impl<E: Send + Sync + 'static> PipelineComponent for BatchVerificationPipelineSt
                 output
                     .send(batch.with_signatures(BatchSignatureData::NotNeeded))
                     .await
-                    .map_err(|_| anyhow::anyhow!("Failed to send signed batch envelope"))?
+                    .map_err(|_| anyhow::anyhow!("Failed to send signed batch envelope"))
+                    .with_code(&error_codes::FAILED)?
             }
             Ok(())
         }
@@ -129,7 +171,13 @@ async fn run_batch_response_processor(
 /// the batch. IDs are used to correlate requests and responses.
 struct BatchVerifier {
     config: BatchVerificationConfig,
-    accepted_signers: Vec<Address>,
+    /// Chain the batch signatures must be made for.
+    chain_id: u64,
+    signature_verification: Arc<SignatureVerificationContext>,
+    /// Min number of connected signers that can reach the quorum.
+    min_signers: usize,
+    /// Max number of responses to a single request.
+    max_responses: usize,
     request_id_counter: AtomicU64,
     server: Arc<BatchVerificationServer>,
     response_channels: Arc<DashMap<u64, mpsc::Sender<BatchVerificationResponse>>>,
@@ -141,6 +189,8 @@ enum BatchVerificationError {
     Timeout,
     #[error("Not enough signers: {0} < {1}")]
     NotEnoughSigners(usize, usize),
+    #[error("Signer refused to verify the batch: {0}")]
+    Refused(RefusalReason),
     #[error("Internal error: {0}")]
     Internal(String),
 }
@@ -158,31 +208,51 @@ impl From<BatchVerificationRequestError> for BatchVerificationError {
     }
 }
 
+impl NodeError for BatchVerificationError {
+    fn error_code(&self) -> &'static ErrorCode {
+        match self {
+            BatchVerificationError::Timeout => &error_codes::TIMEOUT,
+            BatchVerificationError::NotEnoughSigners(..) => &error_codes::NOT_ENOUGH_SIGNERS,
+            BatchVerificationError::Refused(RefusalReason::CommitmentMismatch { .. }) => {
+                &error_codes::REFUSED_COMMITMENT_MISMATCH
+            }
+            BatchVerificationError::Refused(_) => &error_codes::REFUSED,
+            BatchVerificationError::Internal(_) => &error_codes::INTERNAL,
+        }
+    }
+}
+
 impl BatchVerificationError {
     fn retryable(&self) -> bool {
-        !matches!(self, BatchVerificationError::Internal(_))
+        self.error_code().retryable
     }
 }
 
 impl BatchVerifier {
     pub fn new(
         config: BatchVerificationConfig,
+        chain_id: u64,
+        signature_verification: Arc<SignatureVerificationContext>,
         response_channels: Arc<DashMap<u64, mpsc::Sender<BatchVerificationResponse>>>,
         server: Arc<BatchVerificationServer>,
-    ) -> Self {
-        let accepted_signers = config
-            .accepted_signers
-            .clone()
-            .into_iter()
-            .map(|s| s.parse().unwrap())
-            .collect();
-        Self {
+    ) -> anyhow::Result<Self> {
+        let accepted_signers = signature_verification.accepted_signers();
+        config
+            .quorum
+            .validate(accepted_signers)
+            .context("invalid batch verification quorum")?;
+        let min_signers = config.quorum.min_signers(accepted_signers);
+        let max_responses = accepted_signers.len().max(1);
+        Ok(Self {
             config,
+            chain_id,
             request_id_counter: AtomicU64::new(1),
             response_channels,
             server,
-            accepted_signers,
-        }
+            signature_verification,
+            min_signers,
+            max_responses,
+        })
     }
 
     async fn run<E: Send + Sync>(
@@ -235,7 +305,8 @@ impl BatchVerifier {
                         break Err(err);
                     }
                 }
-            }?;
+            }
+            .inspect_err(|err| report_error(err))?;
             latency_tracker.enter_state(GenericComponentState::WaitingSend);
             singed_batcher_sender
                 .send(
@@ -244,7 +315,8 @@ impl BatchVerifier {
                         .with_stage(BatchExecutionStage::BatchSigned),
                 )
                 .await
-                .map_err(|_| anyhow::anyhow!("Failed to send signed batch envelope"))?;
+                .map_err(|_| anyhow::anyhow!("Failed to send signed batch envelope"))
+                .with_code(&error_codes::FAILED)?;
         }
     }
 
@@ -262,27 +334,52 @@ impl BatchVerifier {
         );
 
         // Create a channel for collecting responses for this request
-        let (response_sender, mut response_receiver) =
-            mpsc::channel::<BatchVerificationResponse>(self.config.threshold);
+        let (response_sender, response_receiver) =
+            mpsc::channel::<BatchVerificationResponse>(self.max_responses);
 
         // Register the channel for this request_id
         self.response_channels.insert(request_id, response_sender);
 
+        let result = self
+            .request_signatures(batch_envelope, request_id, response_receiver)
+            .await;
+
+        // Cleanup: remove the channel for this request_id. Also done if the request failed, so that
+        // responses arriving after a timeout are dropped rather than routed to a stale channel.
+        self.response_channels.remove(&request_id);
+
+        result
+    }
+
+    /// Sends a verification request to connected signers and collects their responses until the
+    /// quorum is reached.
+    async fn request_signatures<E: Send + Sync>(
+        &self,
+        batch_envelope: &BatchForSigning<E>,
+        request_id: u64,
+        mut response_receiver: mpsc::Receiver<BatchVerificationResponse>,
+    ) -> Result<BatchSignatureSet, BatchVerificationError> {
         // Send verification request to all connected clients
         self.server
-            .send_verification_request(batch_envelope, request_id, self.config.threshold)
+            .send_verification_request(batch_envelope, request_id, self.min_signers)
             .await?;
 
         let commit_data = batch_envelope.batch.batch_info.commit_info.clone();
 
         // Collect responses with timeout
-        let mut responses = BatchSignatureSet::new();
+        let mut responses = self.config.quorum.signature_set();
         let deadline = Instant::now() + self.config.request_timeout;
+        // If the quorum isn't reached, a refusal that won't change on retry fails the batch
+        let mut final_refusal = None;
+        let timed_out = |final_refusal: Option<RefusalReason>| match final_refusal {
+            Some(reason) => BatchVerificationError::Refused(reason),
+            None => BatchVerificationError::Timeout,
+        };
 
         loop {
             let remaining_time = deadline - Instant::now();
             if remaining_time <= Duration::from_secs(0) {
-                return Err(BatchVerificationError::Timeout);
+                return Err(timed_out(final_refusal));
             }
 
             let response =
@@ -293,11 +390,23 @@ impl BatchVerifier {
                             "Channel closed".to_string(),
                         ));
                     }
-                    Err(_) => return Err(BatchVerificationError::Timeout),
+                    Err(_) => return Err(timed_out(final_refusal)),
                 };
 
-            let Some(validated_signature) =
-                self.process_response(&commit_data, request_id, response)
+            let signature = match response.result {
+                BatchVerificationResult::Success(signature) => signature,
+                BatchVerificationResult::Refused(reason) => {
+                    log_refusal(commit_data.batch_number, request_id, &reason);
+                    if !reason.is_retryable() {
+                        final_refusal = Some(reason);
+                    }
+                    continue;
+                }
+            };
+
+            let Some(validated_signature) = self
+                .validate_signature(&commit_data, request_id, signature)
+                .await
             else {
                 continue;
             };
@@ -318,12 +427,12 @@ impl BatchVerifier {
                 batch_number = batch_envelope.batch_number(),
                 request_id = request_id,
                 signer = signer,
-                "Validated response {} of {}",
-                responses.len(),
-                self.config.threshold
+                "Validated responses with weight {} of {}",
+                responses.total_weight(),
+                self.config.quorum.required_weight()
             );
 
-            if responses.len() >= self.config.threshold {
+            if self.config.quorum.is_reached(&responses) {
                 break;
             }
         }
@@ -332,63 +441,223 @@ impl BatchVerifier {
         tracing::info!(
             batch_number = batch_envelope.batch_number(),
             request_id = request_id,
-            "Collected enough verification responses ({})",
+            "Collected enough verification responses ({}, weight {})",
             responses.len(),
+            responses.total_weight(),
         );
 
-        // Cleanup: remove the channel for this request_id
-        self.response_channels.remove(&request_id);
-
         Ok(responses)
     }
 
-    /// Processes BatchVerificationResponse, on any error logs and returns None
-    /// - extracts & validates signature
-    /// - checks against list of accepted signers
-    fn process_response(
+    /// Validates the signature of a response, on any error logs and returns None
+    /// - checks against list of accepted signers, calling contract signers on L1 if necessary
+    async fn validate_signature(
         &self,
         commit_data: &CommitBatchInfo,
         request_id: u64,
-        response: BatchVerificationResponse,
+        signature: BatchSignature,
     ) -> Option<ValidatedBatchSignature> {
-        let signature = match response {
-            BatchVerificationResponse {
-                result: BatchVerificationResult::Success(signature),
-                ..
-            } => signature,
-            BatchVerificationResponse {
-                result: BatchVerificationResult::Refused(reason),
-                ..
-            } => {
-                tracing::info!(
+        match signature
+            .verify_signature(commit_data, self.chain_id, &self.signature_verification)
+            .await
+        {
+            Ok(validated_signature) => Some(validated_signature),
+            Err(err) => {
+                tracing::warn!(
                     batch_number = commit_data.batch_number,
                     request_id = request_id,
-                    "Verification refused: {}",
-                    reason
+                    "Signature not accepted: {err}",
                 );
-                return None;
+                None
             }
-        };
+        }
+    }
+}
 
-        let Ok(validated_signature) = signature.verify_signature(commit_data) else {
-            tracing::warn!(
-                batch_number = commit_data.batch_number,
-                request_id = request_id,
-                "Invalid signature",
-            );
-            return None;
-        };
+fn log_refusal(batch_number: u64, request_id: u64, reason: &RefusalReason) {
+    match reason {
+        RefusalReason::MissingBlocks { .. } => {
+            tracing::info!(batch_number, request_id, "Verification refused: {reason}");
+        }
+        RefusalReason::CommitmentMismatch { .. } => {
+            BATCH_VERIFICATION_METRICS.commitment_mismatches.inc();
+            tracing::error!(batch_number, request_id, "Verification refused: {reason}");
+        }
+        RefusalReason::ExecutionVersionUnsupported | RefusalReason::Internal(_) => {
+            tracing::warn!(batch_number, request_id, "Verification refused: {reason}");
+        }
+    }
+}
 
-        if !self.accepted_signers.contains(validated_signature.signer()) {
-            tracing::warn!(
-                batch_number = commit_data.batch_number,
-                request_id = request_id,
-                signer = validated_signature.signer().to_string(),
-                "Signature from unknown signer",
-            );
-            return None;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SignatureQuorum;
+    use crate::{BatchVerificationRequest, FrameLimits};
+    use alloy::primitives::Address;
+    use alloy::providers::{Provider, ProviderBuilder};
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::transports::mock::Asserter;
+    use tokio::sync::broadcast;
+    use zksync_os_batch_types::ValidatedBatchSignature;
+    use zksync_os_error_reporting::{ERROR_METRICS, Severity};
+    use zksync_os_l1_sender::batcher_model::{BatchEnvelope, BatchMetadata};
+    use zksync_os_socket::{KeepaliveConfig, ListenerLimits};
+
+    fn config(accepted_signers: &[Address]) -> BatchVerificationConfig {
+        BatchVerificationConfig {
+            server_enabled: true,
+            listen_address: "127.0.0.1:0".to_owned(),
+            client_enabled: false,
+            connect_address: String::new(),
+            quorum: SignatureQuorum::Threshold(1),
+            accepted_signers: accepted_signers.iter().map(ToString::to_string).collect(),
+            contract_signer_call_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_millis(100),
+            retry_delay: Duration::from_millis(10),
+            total_timeout: Duration::from_secs(1),
+            signing_key: String::new().into(),
+            signing_journal_path: None,
+            signing_journal_retention: 0,
+            server_tls: None,
+            client_tls: None,
+            client_keepalive: KeepaliveConfig::default(),
+            server_limits: ListenerLimits::default(),
+            frame_limits: FrameLimits {
+                max_request_bytes: 1024,
+                max_response_bytes: 1024,
+            },
+        }
+    }
+
+    fn verifier(accepted_signers: &[Address]) -> BatchVerifier {
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(Asserter::new())
+            .erased();
+        let config = config(accepted_signers);
+        let signature_verification = Arc::new(SignatureVerificationContext::new(
+            accepted_signers
+                .iter()
+                .copied()
+                .map(AcceptedSigner::Eoa)
+                .collect(),
+            provider,
+            config.contract_signer_call_timeout,
+        ));
+        let (server, _) =
+            BatchVerificationServer::new(config.frame_limits, signature_verification.clone());
+        BatchVerifier::new(
+            config,
+            270,
+            signature_verification,
+            Arc::default(),
+            Arc::new(server),
+        )
+        .unwrap()
+    }
+
+    fn batch() -> BatchForSigning<()> {
+        let metadata = BatchMetadata::for_tests(1, 1..=1, 0);
+        BatchEnvelope::new(metadata, ())
+    }
+
+    /// Responds to the next request with signatures of `keys`.
+    async fn respond(
+        verifier: &BatchVerifier,
+        requests: &mut broadcast::Receiver<BatchVerificationRequest>,
+        keys: &[&PrivateKeySigner],
+    ) {
+        let request = requests.recv().await.unwrap();
+        let sender = verifier
+            .response_channels
+            .get(&request.request_id)
+            .unwrap()
+            .clone();
+        for key in keys {
+            let signature = BatchSignature::sign_batch(&request.commit_data, 270, key).await;
+            let response = BatchVerificationResponse {
+                request_id: request.request_id,
+                batch_number: request.batch_number,
+                result: BatchVerificationResult::Success(signature),
+            };
+            sender.send(response).await.unwrap();
         }
+    }
+
+    #[tokio::test]
+    async fn failed_request_is_cleaned_up() {
+        let key = PrivateKeySigner::random();
+        let verifier = verifier(&[key.address()]);
+        let batch = batch();
+
+        // No signers connected
+        let err = verifier
+            .collect_batch_verification_signatures(&batch)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, BatchVerificationError::NotEnoughSigners(0, 1)),
+            "{err}"
+        );
+        assert!(verifier.response_channels.is_empty());
+
+        // Connected signer doesn't respond
+        let (_signer, mut requests) = verifier.server.connect_test_signer(key.address());
+        let err = verifier
+            .collect_batch_verification_signatures(&batch)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, BatchVerificationError::Timeout), "{err}");
+        assert!(verifier.response_channels.is_empty());
+        let request = requests.try_recv().unwrap();
+        assert_eq!(request.batch_number, 1);
+    }
+
+    #[tokio::test]
+    async fn signatures_of_unknown_signers_are_rejected() {
+        let accepted_key = PrivateKeySigner::random();
+        let unknown_key = PrivateKeySigner::random();
+        let verifier = verifier(&[accepted_key.address()]);
+        let batch = batch();
+        let (_signer, mut requests) = verifier.server.connect_test_signer(accepted_key.address());
+
+        let (signatures, ()) = tokio::join!(
+            verifier.collect_batch_verification_signatures(&batch),
+            respond(&verifier, &mut requests, &[&unknown_key, &accepted_key])
+        );
+        let signatures = Vec::<ValidatedBatchSignature>::from(signatures.unwrap());
+        let signers: Vec<_> = signatures
+            .iter()
+            .map(|signature| *signature.signer())
+            .collect();
+        assert_eq!(signers, [accepted_key.address()]);
+        assert!(verifier.response_channels.is_empty());
+
+        // Only the unknown signer responds
+        let (result, ()) = tokio::join!(
+            verifier.collect_batch_verification_signatures(&batch),
+            respond(&verifier, &mut requests, &[&unknown_key])
+        );
+        let err = result.unwrap_err();
+        assert!(matches!(err, BatchVerificationError::Timeout), "{err}");
+    }
 
-        Some(validated_signature)
+    #[test]
+    fn refusals_are_reported_by_reason() {
+        let mismatch = BatchVerificationError::Refused(RefusalReason::CommitmentMismatch {
+            field: "new_state_commitment".to_owned(),
+        });
+        assert_eq!(mismatch.error_code().code, "BV-004");
+        assert!(!mismatch.retryable());
+        let unsupported =
+            BatchVerificationError::Refused(RefusalReason::ExecutionVersionUnsupported);
+        assert_eq!(unsupported.error_code().code, "BV-003");
+        assert!(BatchVerificationError::Timeout.retryable());
+
+        let labels = ("BV-004", "batch_verification", Severity::Critical);
+        let reported_before = ERROR_METRICS.errors[&labels].get();
+        report_error(&mismatch);
+        assert_eq!(ERROR_METRICS.errors[&labels].get() - reported_before, 1);
     }
 }
@@ -0,0 +1,142 @@
+use super::metrics::BATCH_VERIFICATION_METRICS;
+use alloy::primitives::Address;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::time::Instant;
+
+/// Authenticated connection of an accepted signer.
+#[derive(Clone, Debug)]
+pub(super) struct SignerConnection {
+    pub connection_id: u64,
+    pub client_addr: String,
+    pub connected_at: Instant,
+    pub last_response_at: Option<Instant>,
+}
+
+/// Registry of accepted signers connected to the server. A signer may have several connections
+/// at once (e.g. it has reconnected before the old connection was closed), but counts once.
+#[derive(Debug, Default)]
+pub(super) struct ConnectedSigners {
+    connections: Mutex<HashMap<Address, Vec<SignerConnection>>>,
+    next_connection_id: AtomicU64,
+}
+
+impl ConnectedSigners {
+    /// Registers a connection authenticated as `signer`. The connection is unregistered once the
+    /// returned handle is dropped.
+    pub fn register(self: &Arc<Self>, signer: Address, client_addr: String) -> SignerHandle {
+        let connection_id = self.next_connection_id.fetch_add(1, Ordering::Relaxed);
+        let mut connections = self.connections.lock().unwrap();
+        connections
+            .entry(signer)
+            .or_default()
+            .push(SignerConnection {
+                connection_id,
+                client_addr,
+                connected_at: Instant::now(),
+                last_response_at: None,
+            });
+        Self::report(&connections);
+        SignerHandle {
+            signers: self.clone(),
+            signer,
+            connection_id,
+        }
+    }
+
+    /// Number of distinct accepted signers connected.
+    pub fn count(&self) -> usize {
+        self.connections.lock().unwrap().len()
+    }
+
+    /// Connections of all connected signers.
+    pub fn snapshot(&self) -> Vec<(Address, SignerConnection)> {
+        let connections = self.connections.lock().unwrap();
+        connections
+            .iter()
+            .flat_map(|(signer, connections)| {
+                connections
+                    .iter()
+                    .map(|connection| (*signer, connection.clone()))
+            })
+            .collect()
+    }
+
+    fn report(connections: &HashMap<Address, Vec<SignerConnection>>) {
+        BATCH_VERIFICATION_METRICS
+            .connected_signers
+            .set(connections.len());
+    }
+}
+
+/// Handle of a registered signer connection.
+#[derive(Debug)]
+pub(super) struct SignerHandle {
+    signers: Arc<ConnectedSigners>,
+    signer: Address,
+    connection_id: u64,
+}
+
+impl SignerHandle {
+    pub fn signer(&self) -> Address {
+        self.signer
+    }
+
+    pub fn record_response(&self) {
+        let mut connections = self.signers.connections.lock().unwrap();
+        if let Some(connection) = connections.get_mut(&self.signer).and_then(|connections| {
+            connections
+                .iter_mut()
+                .find(|connection| connection.connection_id == self.connection_id)
+        }) {
+            connection.last_response_at = Some(Instant::now());
+        }
+    }
+}
+
+impl Drop for SignerHandle {
+    fn drop(&mut self) {
+        let mut connections = self.signers.connections.lock().unwrap();
+        if let Some(signer_connections) = connections.get_mut(&self.signer) {
+            signer_connections.retain(|connection| connection.connection_id != self.connection_id);
+            if signer_connections.is_empty() {
+                connections.remove(&self.signer);
+            }
+        }
+        ConnectedSigners::report(&connections);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signers_are_counted_once() {
+        let signers = Arc::new(ConnectedSigners::default());
+        let first = signers.register(Address::repeat_byte(1), "first".to_owned());
+        let reconnected = signers.register(Address::repeat_byte(1), "reconnected".to_owned());
+        let second = signers.register(Address::repeat_byte(2), "second".to_owned());
+        assert_eq!(signers.count(), 2);
+        assert_eq!(signers.snapshot().len(), 3);
+
+        reconnected.record_response();
+        let snapshot = signers.snapshot();
+        let responded: Vec<_> = snapshot
+            .iter()
+            .filter(|(_, connection)| connection.last_response_at.is_some())
+            .map(|(_, connection)| connection.client_addr.as_str())
+            .collect();
+        assert_eq!(responded, ["reconnected"]);
+
+        // The signer stays connected until its last connection is closed
+        drop(first);
+        assert_eq!(signers.count(), 2);
+        drop(reconnected);
+        assert_eq!(signers.count(), 1);
+        assert_eq!(second.signer(), Address::repeat_byte(2));
+        drop(second);
+        assert_eq!(signers.count(), 0);
+    }
+}
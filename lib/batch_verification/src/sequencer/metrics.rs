@@ -0,0 +1,17 @@
+use vise::{Counter, Gauge, Metrics};
+
+#[derive(Debug, Metrics)]
+#[metrics(prefix = "batch_verification")]
+pub struct BatchVerificationMetrics {
+    /// Refusals of external nodes that computed different commit data for a batch.
+    pub commitment_mismatches: Counter,
+    /// Distinct accepted signers with authenticated connections to the server.
+    pub connected_signers: Gauge<usize>,
+    /// Connections that failed to prove being an accepted signer; they stay connected but don't
+    /// count as signers.
+    pub rejected_identities: Counter,
+}
+
+#[vise::register]
+pub(crate) static BATCH_VERIFICATION_METRICS: vise::Global<BatchVerificationMetrics> =
+    vise::Global::new();
@@ -1,2 +1,4 @@
 pub mod component;
+mod connected_signers;
+mod metrics;
 mod server;
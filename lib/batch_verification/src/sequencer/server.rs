@@ -1,67 +1,139 @@
+use super::connected_signers::{ConnectedSigners, SignerHandle};
+use super::metrics::BATCH_VERIFICATION_METRICS;
 use crate::{
-    BATCH_VERIFICATION_WIRE_FORMAT_VERSION, BatchVerificationRequest,
+    BATCH_VERIFICATION_PATH, BATCH_VERIFICATION_WIRE_FORMAT_VERSION, BatchVerificationRequest,
     BatchVerificationRequestCodec, BatchVerificationResponse, BatchVerificationResponseDecoder,
+    FrameLimits, SignerIdentity,
 };
+use alloy::primitives::B256;
+use anyhow::Context as _;
 use futures::{SinkExt, StreamExt};
-use tokio::io::BufReader;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
 use tokio::net::ToSocketAddrs;
 use tokio::sync::broadcast;
 use tokio::sync::mpsc;
-use tokio::{
-    io::AsyncWriteExt,
-    net::{TcpListener, TcpStream},
-};
+use tokio::time::Instant;
 use tokio_util::codec::{FramedRead, FramedWrite};
+use zksync_os_batch_types::SignatureVerificationContext;
 use zksync_os_l1_sender::batcher_model::BatchForSigning;
-use zksync_os_socket::skip_http_headers;
+use zksync_os_socket::{
+    AcceptedConnection, ConnectionLimiter, ListenerLimits, MaybeTlsStream, TlsAcceptor,
+    accept_handshake, accept_maybe_tls,
+};
 
 /// Accepts connections from batch verification clients. Crafts and sends
 /// BatchVerificationRequests to all clients. Receives responses and forwards
 /// them through the channel to batch_response_processor
+///
+/// Clients prove which accepted signer they are in a handshake; clients that fail to do so stay
+/// connected, but don't count as signers.
 pub(super) struct BatchVerificationServer {
     verification_request_broadcast: broadcast::Sender<BatchVerificationRequest>,
     response_sender: mpsc::Sender<BatchVerificationResponse>,
+    frame_limits: FrameLimits,
+    signature_verification: Arc<SignatureVerificationContext>,
+    connected_signers: Arc<ConnectedSigners>,
+}
+
+/// Server state passed to each client connection task.
+#[derive(Clone)]
+struct ClientContext {
+    frame_limits: FrameLimits,
+    response_sender: mpsc::Sender<BatchVerificationResponse>,
+    signature_verification: Arc<SignatureVerificationContext>,
+    connected_signers: Arc<ConnectedSigners>,
 }
 
 #[derive(Debug, thiserror::Error)]
 #[allow(clippy::large_enum_variant)]
 pub enum BatchVerificationRequestError {
-    #[error("Not enough clients connected: {0} < {1}")]
+    #[error("Not enough accepted signers connected: {0} < {1}")]
     NotEnoughClients(usize, usize),
     #[error("Failed to send batch verification request: {0}")]
     SendError(#[from] broadcast::error::SendError<BatchVerificationRequest>),
 }
 
 impl BatchVerificationServer {
-    pub fn new() -> (Self, mpsc::Receiver<BatchVerificationResponse>) {
+    /// Client identities are checked against the signers accepted by `signature_verification`.
+    pub fn new(
+        frame_limits: FrameLimits,
+        signature_verification: Arc<SignatureVerificationContext>,
+    ) -> (Self, mpsc::Receiver<BatchVerificationResponse>) {
         let (response_sender, response_receiver) = mpsc::channel(100);
         let (verification_request_broadcast, _rx_unused) = broadcast::channel(16);
 
         let server = Self {
             verification_request_broadcast,
             response_sender,
+            frame_limits,
+            signature_verification,
+            connected_signers: Arc::default(),
         };
 
         (server, response_receiver)
     }
 
-    /// Start the TCP server that accepts connections from external nodes
-    pub async fn run_server(&self, address: impl ToSocketAddrs) -> anyhow::Result<()> {
+    /// Start the TCP server that accepts connections from external nodes.
+    /// Connections are wrapped in TLS if `tls_acceptor` is set.
+    ///
+    /// Connections over `limits.max_connections` are closed right away, and clients that don't
+    /// complete the TLS, HTTP and identity handshakes within `limits.handshake_timeout` are
+    /// disconnected.
+    pub async fn run_server(
+        &self,
+        address: impl ToSocketAddrs,
+        tls_acceptor: Option<TlsAcceptor>,
+        limits: ListenerLimits,
+    ) -> anyhow::Result<()> {
         let listener = TcpListener::bind(address).await?;
-        let response_sender = self.response_sender.clone();
+        self.serve(listener, tls_acceptor, limits).await
+    }
+
+    async fn serve(
+        &self,
+        listener: TcpListener,
+        tls_acceptor: Option<TlsAcceptor>,
+        limits: ListenerLimits,
+    ) -> anyhow::Result<()> {
+        let limiter = ConnectionLimiter::new("batch_verification", limits.max_connections);
+        let context = ClientContext {
+            frame_limits: self.frame_limits,
+            response_sender: self.response_sender.clone(),
+            signature_verification: self.signature_verification.clone(),
+            connected_signers: self.connected_signers.clone(),
+        };
 
         loop {
-            let (socket, addr) = listener.accept().await?;
+            let (socket, addr, permit) = limiter.accept(&listener).await?;
+            let handshake_deadline = Instant::now() + limits.handshake_timeout;
             let verification_request_rx = self.verification_request_broadcast.subscribe();
-            let response_sender = response_sender.clone();
+            let context = context.clone();
             let client_addr = addr.to_string();
+            let tls_acceptor = tls_acceptor.clone();
 
             tokio::spawn(async move {
+                let _permit = permit;
+                let tls_handshake = accept_maybe_tls(socket, tls_acceptor.as_ref());
+                let socket = match tokio::time::timeout_at(handshake_deadline, tls_handshake).await
+                {
+                    Ok(Ok(socket)) => socket,
+                    Ok(Err(e)) => {
+                        tracing::info!("TLS handshake with client {} failed: {}", addr, e);
+                        return;
+                    }
+                    Err(_) => {
+                        tracing::info!("TLS handshake with client {} timed out", addr);
+                        return;
+                    }
+                };
                 if let Err(e) = Self::handle_client(
                     socket,
                     client_addr,
+                    handshake_deadline,
+                    context,
                     verification_request_rx,
-                    response_sender,
                 )
                 .await
                 {
@@ -72,25 +144,69 @@ impl BatchVerificationServer {
     }
 
     async fn handle_client(
-        mut socket: TcpStream,
+        socket: MaybeTlsStream,
         client_addr: String,
+        handshake_deadline: Instant,
+        context: ClientContext,
         mut verification_request_rx: broadcast::Receiver<BatchVerificationRequest>,
-        response_sender: mpsc::Sender<BatchVerificationResponse>,
     ) -> anyhow::Result<()> {
-        let (recv, mut send) = socket.split();
-        let mut reader = BufReader::new(recv);
-
-        // Skip HTTP headers similar to replay_transport
-        skip_http_headers(&mut reader).await?;
+        let AcceptedConnection {
+            headers,
+            mut reader,
+            writer: mut send,
+        } = accept_handshake(
+            socket,
+            Some(BATCH_VERIFICATION_PATH),
+            BATCH_VERIFICATION_WIRE_FORMAT_VERSION,
+            handshake_deadline.saturating_duration_since(Instant::now()),
+        )
+        .await?;
 
-        // Write wire format version
-        send.write_u32(BATCH_VERIFICATION_WIRE_FORMAT_VERSION)
-            .await?;
+        let identity_handshake = async {
+            let nonce = B256::from(rand::random::<[u8; 32]>());
+            send.write_all(nonce.as_slice()).await?;
+            send.flush().await?;
+            let identity = SignerIdentity::read(&mut reader).await?;
+            anyhow::Ok((nonce, identity))
+        };
+        let (nonce, identity) = tokio::time::timeout_at(handshake_deadline, identity_handshake)
+            .await
+            .context("identity handshake timed out")?
+            .context("identity handshake failed")?;
+        let signer = match identity
+            .verify(nonce, &context.signature_verification)
+            .await
+        {
+            Ok(signer) => Some(
+                context
+                    .connected_signers
+                    .register(signer, client_addr.clone()),
+            ),
+            Err(err) => {
+                BATCH_VERIFICATION_METRICS.rejected_identities.inc();
+                tracing::warn!(
+                    claimed_signer = %identity.signer,
+                    "Client {client_addr} is not counted as an accepted signer: {err}"
+                );
+                None
+            }
+        };
 
-        tracing::info!("Batch verification client connected: {}", client_addr);
+        tracing::info!(
+            user_agent = headers.user_agent(),
+            signer = ?signer.as_ref().map(SignerHandle::signer),
+            "Batch verification client connected: {}",
+            client_addr
+        );
 
-        let mut writer = FramedWrite::new(send, BatchVerificationRequestCodec::new());
-        let mut reader = FramedRead::new(reader, BatchVerificationResponseDecoder::new());
+        let mut writer = FramedWrite::new(
+            send,
+            BatchVerificationRequestCodec::new(context.frame_limits.max_request_bytes),
+        );
+        let mut reader = FramedRead::new(
+            reader,
+            BatchVerificationResponseDecoder::new(context.frame_limits.max_response_bytes),
+        );
 
         // Handle bidirectional communication
         loop {
@@ -115,7 +231,10 @@ impl BatchVerificationServer {
                 response = reader.next() => {
                     match response {
                         Some(Ok(resp)) => {
-                            if let Err(e) = response_sender.send(resp).await {
+                            if let Some(signer) = &signer {
+                                signer.record_response();
+                            }
+                            if let Err(e) = context.response_sender.send(resp).await {
                                 tracing::error!(
                                     batch_number = e.0.batch_number,
                                     request_id = e.0.request_id,
@@ -152,24 +271,210 @@ impl BatchVerificationServer {
             request_id,
         };
 
-        let clients_count = self.verification_request_broadcast.receiver_count();
+        // Only clients authenticated as accepted signers can sign the batch
+        let signers_count = self.connected_signers.count();
 
-        if clients_count < required_clients {
+        if signers_count < required_clients {
+            for (signer, connection) in self.connected_signers.snapshot() {
+                tracing::debug!(
+                    %signer,
+                    client = connection.client_addr,
+                    connected_for = ?connection.connected_at.elapsed(),
+                    since_last_response = ?connection.last_response_at.map(|at| at.elapsed()),
+                    "Accepted signer is connected"
+                );
+            }
             return Err(BatchVerificationRequestError::NotEnoughClients(
-                clients_count,
+                signers_count,
                 required_clients,
             ));
         }
 
-        self.verification_request_broadcast.send(request)?;
+        let clients_count = self.verification_request_broadcast.send(request)?;
 
         tracing::info!(
             request_id,
             batch_number = batch_envelope.batch_number(),
-            "Sent batch verification request to {} clients",
+            "Sent batch verification request to {} clients ({} accepted signers)",
             clients_count,
+            signers_count,
         );
 
         Ok(())
     }
+
+    /// Registers `signer` as connected without a client, returning its registration along with
+    /// the requests the client would receive.
+    #[cfg(test)]
+    pub(super) fn connect_test_signer(
+        &self,
+        signer: alloy::primitives::Address,
+    ) -> (SignerHandle, broadcast::Receiver<BatchVerificationRequest>) {
+        let handle = self.connected_signers.register(signer, "test".to_owned());
+        (handle, self.verification_request_broadcast.subscribe())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+    use alloy::providers::{Provider, ProviderBuilder};
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::transports::mock::Asserter;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    use zksync_os_batch_types::AcceptedSigner;
+
+    const FRAME_LIMITS: FrameLimits = FrameLimits {
+        max_request_bytes: 1024,
+        max_response_bytes: 64,
+    };
+
+    async fn start_server(
+        accepted_signers: &[Address],
+    ) -> (std::net::SocketAddr, Arc<ConnectedSigners>) {
+        let provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .connect_mocked_client(Asserter::new())
+            .erased();
+        let accepted_signers = accepted_signers
+            .iter()
+            .copied()
+            .map(AcceptedSigner::Eoa)
+            .collect();
+        let signature_verification =
+            SignatureVerificationContext::new(accepted_signers, provider, Duration::from_secs(5));
+        let (server, _) =
+            BatchVerificationServer::new(FRAME_LIMITS, Arc::new(signature_verification));
+        let connected_signers = server.connected_signers.clone();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let limits = ListenerLimits {
+            max_connections: 4,
+            handshake_timeout: Duration::from_millis(200),
+        };
+        tokio::spawn(async move { server.serve(listener, None, limits).await });
+        (address, connected_signers)
+    }
+
+    /// Connects with an identity of `claimed_signer` signed by `key`.
+    async fn connect(
+        address: std::net::SocketAddr,
+        key: &PrivateKeySigner,
+        claimed_signer: Address,
+    ) -> TcpStream {
+        let mut client = connect_without_identity(address).await;
+        let mut nonce = B256::ZERO;
+        client.read_exact(nonce.as_mut_slice()).await.unwrap();
+        let mut identity = SignerIdentity::sign(nonce, key).await;
+        identity.signer = claimed_signer;
+        identity.write(&mut client).await.unwrap();
+        client
+    }
+
+    async fn connect_without_identity(address: std::net::SocketAddr) -> TcpStream {
+        let mut client = TcpStream::connect(address).await.unwrap();
+        let handshake = format!("POST {BATCH_VERIFICATION_PATH} HTTP/1.0\r\n\r\n");
+        client.write_all(handshake.as_bytes()).await.unwrap();
+        assert_eq!(
+            client.read_u32().await.unwrap(),
+            BATCH_VERIFICATION_WIRE_FORMAT_VERSION
+        );
+        client
+    }
+
+    /// Returns once the server closes the connection.
+    async fn assert_disconnected(client: &mut TcpStream) {
+        let mut buf = vec![];
+        let read = tokio::time::timeout(Duration::from_secs(5), client.read_to_end(&mut buf))
+            .await
+            .expect("client is not disconnected");
+        assert!(read.is_err() || buf.is_empty());
+    }
+
+    async fn assert_connected(client: &mut TcpStream) {
+        let mut buf = [0_u8; 1];
+        tokio::time::timeout(Duration::from_millis(300), client.read(&mut buf))
+            .await
+            .expect_err("client is disconnected");
+    }
+
+    async fn wait_for_signers(connected_signers: &ConnectedSigners, count: usize) {
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while connected_signers.count() != count {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("{count} signers are not connected"));
+    }
+
+    #[tokio::test]
+    async fn oversized_response_closes_connection() {
+        let (address, _) = start_server(&[]).await;
+        let key = PrivateKeySigner::random();
+        let mut client = connect(address, &key, key.address()).await;
+
+        // Only the length prefix of the oversized frame is sent
+        client
+            .write_u32(FRAME_LIMITS.max_response_bytes as u32 + 1)
+            .await
+            .unwrap();
+        assert_disconnected(&mut client).await;
+    }
+
+    #[tokio::test]
+    async fn slow_handshake_is_disconnected_at_deadline() {
+        let (address, _) = start_server(&[]).await;
+        let mut client = TcpStream::connect(address).await.unwrap();
+        let connected_at = Instant::now();
+        client.write_all(b"POST /batch_verif").await.unwrap();
+
+        assert_disconnected(&mut client).await;
+        assert!(connected_at.elapsed() >= Duration::from_millis(200));
+
+        // The identity is a part of the handshake
+        let mut client = connect_without_identity(address).await;
+        assert_disconnected(&mut client).await;
+    }
+
+    #[tokio::test]
+    async fn accepted_signers_are_counted_once() {
+        let key = PrivateKeySigner::random();
+        let (address, connected_signers) = start_server(&[key.address()]).await;
+
+        let mut client = connect(address, &key, key.address()).await;
+        let reconnected_client = connect(address, &key, key.address()).await;
+        wait_for_signers(&connected_signers, 1).await;
+        assert_eq!(connected_signers.snapshot().len(), 2);
+
+        drop(reconnected_client);
+        assert_connected(&mut client).await;
+        assert_eq!(connected_signers.count(), 1);
+        drop(client);
+        wait_for_signers(&connected_signers, 0).await;
+    }
+
+    #[tokio::test]
+    async fn spoofed_signer_is_not_counted() {
+        let accepted_key = PrivateKeySigner::random();
+        let spoofing_key = PrivateKeySigner::random();
+        let (address, connected_signers) = start_server(&[accepted_key.address()]).await;
+
+        // Clients claiming an accepted signer they don't have the key of, or proving a signer
+        // that is not accepted, stay connected but are not counted
+        let mut spoofing_client = connect(address, &spoofing_key, accepted_key.address()).await;
+        let mut unknown_client = connect(address, &spoofing_key, spoofing_key.address()).await;
+        assert_connected(&mut spoofing_client).await;
+        assert_connected(&mut unknown_client).await;
+        assert_eq!(connected_signers.count(), 0);
+
+        let _client = connect(address, &accepted_key, accepted_key.address()).await;
+        wait_for_signers(&connected_signers, 1).await;
+        let snapshot = connected_signers.snapshot();
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot[0].0, accepted_key.address());
+    }
 }
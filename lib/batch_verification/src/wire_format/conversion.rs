@@ -1,7 +1,14 @@
-use super::v1::{BatchVerificationRequestWireFormatV1, BatchVerificationResponseWireFormatV1};
+use super::v1::{
+    BatchVerificationRequestWireFormatV1, BatchVerificationResponseResultWireFormatV1,
+    BatchVerificationResponseWireFormatV1,
+};
+use super::v2::{
+    BatchVerificationRequestWireFormatV2, BatchVerificationResponseResultWireFormatV2,
+    BatchVerificationResponseWireFormatV2, RefusalReasonWireFormatV2,
+};
 use crate::{
-    BatchVerificationRequest, BatchVerificationResponse, response::BatchVerificationResult,
-    wire_format::v1::BatchVerificationResponseResultWireFormatV1,
+    BatchVerificationRequest, BatchVerificationResponse, RefusalReason,
+    response::BatchVerificationResult,
 };
 use alloy::sol_types::SolValue;
 use zksync_os_batch_types::BatchSignature;
@@ -16,15 +23,12 @@ impl From<BatchVerificationRequestWireFormatV1> for BatchVerificationRequest {
             request_id,
             commit_data,
         } = value;
-        let decoded_commit_data_alloy = CommitBatchInfoZKsyncOS::abi_decode(&commit_data)
-            .expect("Failed to decode commit data");
-        let decoded_commit_data = CommitBatchInfo::from(decoded_commit_data_alloy);
         Self {
             batch_number,
             first_block_number,
             last_block_number,
             request_id,
-            commit_data: decoded_commit_data,
+            commit_data: decode_commit_data(&commit_data),
         }
     }
 }
@@ -38,14 +42,12 @@ impl From<BatchVerificationRequest> for BatchVerificationRequestWireFormatV1 {
             request_id,
             commit_data,
         } = value;
-        let commit_data_alloy = CommitBatchInfoZKsyncOS::from(commit_data);
-        let encoded_commit_data = commit_data_alloy.abi_encode();
         Self {
             batch_number,
             first_block_number,
             last_block_number,
             request_id,
-            commit_data: encoded_commit_data,
+            commit_data: CommitBatchInfoZKsyncOS::from(commit_data).abi_encode(),
         }
     }
 }
@@ -64,7 +66,7 @@ impl TryFrom<BatchVerificationResponseWireFormatV1> for BatchVerificationRespons
                 BatchVerificationResult::Success(BatchSignature::from_raw_array(&bytes)?)
             }
             BatchVerificationResponseResultWireFormatV1::Refused(reason) => {
-                BatchVerificationResult::Refused(reason)
+                BatchVerificationResult::Refused(RefusalReason::Internal(reason))
             }
         };
         Ok(Self {
@@ -87,7 +89,133 @@ impl From<BatchVerificationResponse> for BatchVerificationResponseWireFormatV1 {
                 BatchVerificationResponseResultWireFormatV1::Success(signature.into_raw())
             }
             BatchVerificationResult::Refused(reason) => {
-                BatchVerificationResponseResultWireFormatV1::Refused(reason)
+                BatchVerificationResponseResultWireFormatV1::Refused(reason.to_string())
+            }
+        };
+        Self {
+            request_id,
+            batch_number,
+            result: wire_result,
+        }
+    }
+}
+
+fn decode_commit_data(commit_data: &[u8]) -> CommitBatchInfo {
+    let decoded_commit_data_alloy =
+        CommitBatchInfoZKsyncOS::abi_decode(commit_data).expect("Failed to decode commit data");
+    CommitBatchInfo::from(decoded_commit_data_alloy)
+}
+
+impl From<BatchVerificationRequestWireFormatV2> for BatchVerificationRequest {
+    fn from(value: BatchVerificationRequestWireFormatV2) -> Self {
+        let BatchVerificationRequestWireFormatV2 {
+            batch_number,
+            first_block_number,
+            last_block_number,
+            request_id,
+            commit_data,
+        } = value;
+        Self {
+            batch_number,
+            first_block_number,
+            last_block_number,
+            request_id,
+            commit_data: decode_commit_data(&commit_data),
+        }
+    }
+}
+
+impl From<BatchVerificationRequest> for BatchVerificationRequestWireFormatV2 {
+    fn from(value: BatchVerificationRequest) -> Self {
+        let BatchVerificationRequest {
+            batch_number,
+            first_block_number,
+            last_block_number,
+            request_id,
+            commit_data,
+        } = value;
+        Self {
+            batch_number,
+            first_block_number,
+            last_block_number,
+            request_id,
+            commit_data: CommitBatchInfoZKsyncOS::from(commit_data).abi_encode(),
+        }
+    }
+}
+
+impl From<RefusalReasonWireFormatV2> for RefusalReason {
+    fn from(value: RefusalReasonWireFormatV2) -> Self {
+        match value {
+            RefusalReasonWireFormatV2::MissingBlocks { first_missing } => {
+                RefusalReason::MissingBlocks { first_missing }
+            }
+            RefusalReasonWireFormatV2::CommitmentMismatch { field } => {
+                RefusalReason::CommitmentMismatch { field }
+            }
+            RefusalReasonWireFormatV2::ExecutionVersionUnsupported => {
+                RefusalReason::ExecutionVersionUnsupported
+            }
+            RefusalReasonWireFormatV2::Internal(reason) => RefusalReason::Internal(reason),
+        }
+    }
+}
+
+impl From<RefusalReason> for RefusalReasonWireFormatV2 {
+    fn from(value: RefusalReason) -> Self {
+        match value {
+            RefusalReason::MissingBlocks { first_missing } => {
+                RefusalReasonWireFormatV2::MissingBlocks { first_missing }
+            }
+            RefusalReason::CommitmentMismatch { field } => {
+                RefusalReasonWireFormatV2::CommitmentMismatch { field }
+            }
+            RefusalReason::ExecutionVersionUnsupported => {
+                RefusalReasonWireFormatV2::ExecutionVersionUnsupported
+            }
+            RefusalReason::Internal(reason) => RefusalReasonWireFormatV2::Internal(reason),
+        }
+    }
+}
+
+impl TryFrom<BatchVerificationResponseWireFormatV2> for BatchVerificationResponse {
+    type Error = anyhow::Error;
+
+    fn try_from(value: BatchVerificationResponseWireFormatV2) -> Result<Self, Self::Error> {
+        let BatchVerificationResponseWireFormatV2 {
+            request_id,
+            batch_number,
+            result: wire_result,
+        } = value;
+        let result = match wire_result {
+            BatchVerificationResponseResultWireFormatV2::Success(bytes) => {
+                BatchVerificationResult::Success(BatchSignature::from_raw_array(&bytes)?)
+            }
+            BatchVerificationResponseResultWireFormatV2::Refused(reason) => {
+                BatchVerificationResult::Refused(reason.into())
+            }
+        };
+        Ok(Self {
+            request_id,
+            batch_number,
+            result,
+        })
+    }
+}
+
+impl From<BatchVerificationResponse> for BatchVerificationResponseWireFormatV2 {
+    fn from(value: BatchVerificationResponse) -> Self {
+        let BatchVerificationResponse {
+            request_id,
+            batch_number,
+            result,
+        } = value;
+        let wire_result = match result {
+            BatchVerificationResult::Success(signature) => {
+                BatchVerificationResponseResultWireFormatV2::Success(signature.into_raw())
+            }
+            BatchVerificationResult::Refused(reason) => {
+                BatchVerificationResponseResultWireFormatV2::Refused(reason.into())
             }
         };
         Self {
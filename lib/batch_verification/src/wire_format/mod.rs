@@ -5,16 +5,27 @@ mod conversion;
 // Don't change the file even if we update formatting rules
 #[rustfmt::skip]
 mod v1;
+#[rustfmt::skip]
+mod v2;
 
 #[cfg(test)]
 mod tests;
 
-pub const BATCH_VERIFICATION_WIRE_FORMAT_VERSION: u32 = 1;
+/// Version 3 adds the identity handshake (see [`crate::SignerIdentity`]); version 4 changes the
+/// signed payload (see [`SIGNING_HASH_VERSION`]). Messages are encoded the same way as in
+/// version 2.
+pub const BATCH_VERIFICATION_WIRE_FORMAT_VERSION: u32 = 4;
+
+/// Wire format version starting from which batches are signed over their
+/// [signing hash](zksync_os_batch_types::BatchSigningHash::signing_hash), which is separated by
+/// [`zksync_os_batch_types::BATCH_SIGNING_DOMAIN`] and bound to the chain ID. Before, the ABI
+/// encoding of the commit info was signed.
+pub const SIGNING_HASH_VERSION: u32 = 4;
 
 impl BatchVerificationRequest {
     /// Encodes the request using the current wire format version
     pub fn encode_with_current_version(self) -> Vec<u8> {
-        let wire_format = v1::BatchVerificationRequestWireFormatV1::from(self);
+        let wire_format = v2::BatchVerificationRequestWireFormatV2::from(self);
         bincode::encode_to_vec(wire_format, bincode::config::standard()).unwrap()
     }
 
@@ -29,6 +40,13 @@ impl BatchVerificationRequest {
                         .0;
                 wire_format.into()
             }
+            2..=4 => {
+                let wire_format: v2::BatchVerificationRequestWireFormatV2 =
+                    bincode::decode_from_slice(bytes, bincode::config::standard())
+                        .unwrap()
+                        .0;
+                wire_format.into()
+            }
             _ => panic!("Unsupported batch verification wire format version: {version}"),
         }
     }
@@ -41,12 +59,18 @@ impl BatchVerificationResponse {
                 let wire_format = v1::BatchVerificationResponseWireFormatV1::from(self);
                 bincode::encode_to_vec(wire_format, bincode::config::standard()).unwrap()
             }
+            2..=4 => {
+                let wire_format = v2::BatchVerificationResponseWireFormatV2::from(self);
+                bincode::encode_to_vec(wire_format, bincode::config::standard()).unwrap()
+            }
             _ => panic!("Unsupported batch verification wire format version: {version}"),
         }
     }
 
     /// Decodes the response from the given bytes using the specified wire format version.
     /// Panics if the wire format version is too old.
+    /// Version 1 refusal reasons are free-form strings and are decoded as
+    /// [`RefusalReason::Internal`](crate::RefusalReason::Internal).
     pub fn decode(bytes: &[u8], version: u32) -> Result<Self, anyhow::Error> {
         match version {
             1 => {
@@ -54,6 +78,11 @@ impl BatchVerificationResponse {
                     bincode::decode_from_slice(bytes, bincode::config::standard())?.0;
                 Ok(wire_format.try_into()?)
             }
+            2..=4 => {
+                let wire_format: v2::BatchVerificationResponseWireFormatV2 =
+                    bincode::decode_from_slice(bytes, bincode::config::standard())?.0;
+                Ok(wire_format.try_into()?)
+            }
             _ => panic!("Unsupported batch verification wire format version: {version}"),
         }
     }
@@ -1,6 +1,6 @@
 use crate::{
     BATCH_VERIFICATION_WIRE_FORMAT_VERSION, BatchVerificationRequest, BatchVerificationResponse,
-    BatchVerificationResult,
+    BatchVerificationResult, RefusalReason,
 };
 use zksync_os_batch_types::BatchSignature;
 use zksync_os_contract_interface::models::CommitBatchInfo;
@@ -40,14 +40,34 @@ fn create_sample_response_success() -> BatchVerificationResponse {
     }
 }
 
-fn create_sample_response_refused() -> BatchVerificationResponse {
+fn create_sample_response_refused(reason: RefusalReason) -> BatchVerificationResponse {
     BatchVerificationResponse {
         request_id: 12345,
         batch_number: 42,
-        result: BatchVerificationResult::Refused("Test refusal reason".to_string()),
+        result: BatchVerificationResult::Refused(reason),
     }
 }
 
+/// Refusal stored in the v1 test data; v1 refusals are free-form strings.
+fn v1_refusal_reason() -> RefusalReason {
+    RefusalReason::Internal("Test refusal reason".to_string())
+}
+
+fn v2_refusal_reason() -> RefusalReason {
+    RefusalReason::CommitmentMismatch {
+        field: "new_state_commitment".to_string(),
+    }
+}
+
+fn all_refusal_reasons() -> Vec<RefusalReason> {
+    vec![
+        RefusalReason::MissingBlocks { first_missing: 101 },
+        v2_refusal_reason(),
+        RefusalReason::ExecutionVersionUnsupported,
+        v1_refusal_reason(),
+    ]
+}
+
 // This test generates the binary files for version testing
 // Run this once to create the test data files
 #[test]
@@ -55,29 +75,34 @@ fn create_sample_response_refused() -> BatchVerificationResponse {
 fn generate_test_data() {
     use std::fs;
 
-    // Generate request v1
+    let version = BATCH_VERIFICATION_WIRE_FORMAT_VERSION;
+
+    // Generate request
     let request = create_sample_request();
     let encoded = request.encode_with_current_version();
-    fs::write("src/wire_format/tests/encoded_request_v1.bin", &encoded)
-        .expect("Failed to write request v1");
+    fs::write(
+        format!("src/wire_format/tests/encoded_request_v{version}.bin"),
+        &encoded,
+    )
+    .expect("Failed to write request");
 
-    // Generate response success v1
+    // Generate response success
     let response_success = create_sample_response_success();
-    let encoded = response_success.encode_with_version(BATCH_VERIFICATION_WIRE_FORMAT_VERSION);
+    let encoded = response_success.encode_with_version(version);
     fs::write(
-        "src/wire_format/tests/encoded_response_success_v1.bin",
+        format!("src/wire_format/tests/encoded_response_success_v{version}.bin"),
         &encoded,
     )
-    .expect("Failed to write response success v1");
+    .expect("Failed to write response success");
 
-    // Generate response refused v1
-    let response_refused = create_sample_response_refused();
-    let encoded = response_refused.encode_with_version(BATCH_VERIFICATION_WIRE_FORMAT_VERSION);
+    // Generate response refused
+    let response_refused = create_sample_response_refused(v2_refusal_reason());
+    let encoded = response_refused.encode_with_version(version);
     fs::write(
-        "src/wire_format/tests/encoded_response_refused_v1.bin",
+        format!("src/wire_format/tests/encoded_response_refused_v{version}.bin"),
         &encoded,
     )
-    .expect("Failed to write response refused v1");
+    .expect("Failed to write response refused");
 }
 
 #[test]
@@ -102,7 +127,88 @@ pub fn can_decode_response_success_v1() {
 pub fn can_decode_response_refused_v1() {
     let encoded = include_bytes!("encoded_response_refused_v1.bin");
     let decoded = BatchVerificationResponse::decode(encoded, 1).unwrap();
-    let expected = create_sample_response_refused();
+    let expected = create_sample_response_refused(v1_refusal_reason());
+
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+pub fn can_decode_request_v2() {
+    let encoded = include_bytes!("encoded_request_v2.bin");
+    let decoded = BatchVerificationRequest::decode(encoded, 2);
+    let expected = create_sample_request();
+
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+pub fn can_decode_response_success_v2() {
+    let encoded = include_bytes!("encoded_response_success_v2.bin");
+    let decoded = BatchVerificationResponse::decode(encoded, 2).unwrap();
+    let expected = create_sample_response_success();
+
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+pub fn can_decode_response_refused_v2() {
+    let encoded = include_bytes!("encoded_response_refused_v2.bin");
+    let decoded = BatchVerificationResponse::decode(encoded, 2).unwrap();
+    let expected = create_sample_response_refused(v2_refusal_reason());
+
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+pub fn can_decode_request_v3() {
+    let encoded = include_bytes!("encoded_request_v3.bin");
+    let decoded = BatchVerificationRequest::decode(encoded, 3);
+    let expected = create_sample_request();
+
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+pub fn can_decode_response_success_v3() {
+    let encoded = include_bytes!("encoded_response_success_v3.bin");
+    let decoded = BatchVerificationResponse::decode(encoded, 3).unwrap();
+    let expected = create_sample_response_success();
+
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+pub fn can_decode_response_refused_v3() {
+    let encoded = include_bytes!("encoded_response_refused_v3.bin");
+    let decoded = BatchVerificationResponse::decode(encoded, 3).unwrap();
+    let expected = create_sample_response_refused(v2_refusal_reason());
+
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+pub fn can_decode_request_v4() {
+    let encoded = include_bytes!("encoded_request_v4.bin");
+    let decoded = BatchVerificationRequest::decode(encoded, 4);
+    let expected = create_sample_request();
+
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+pub fn can_decode_response_success_v4() {
+    let encoded = include_bytes!("encoded_response_success_v4.bin");
+    let decoded = BatchVerificationResponse::decode(encoded, 4).unwrap();
+    let expected = create_sample_response_success();
+
+    assert_eq!(decoded, expected);
+}
+
+#[test]
+pub fn can_decode_response_refused_v4() {
+    let encoded = include_bytes!("encoded_response_refused_v4.bin");
+    let decoded = BatchVerificationResponse::decode(encoded, 4).unwrap();
+    let expected = create_sample_response_refused(v2_refusal_reason());
 
     assert_eq!(decoded, expected);
 }
@@ -111,7 +217,8 @@ pub fn can_decode_response_refused_v1() {
 pub fn request_encode_decode() {
     let original = create_sample_request();
     let encoded = original.clone().encode_with_current_version();
-    let decoded = BatchVerificationRequest::decode(&encoded, 1);
+    let decoded =
+        BatchVerificationRequest::decode(&encoded, BATCH_VERIFICATION_WIRE_FORMAT_VERSION);
 
     assert_eq!(decoded, original);
 }
@@ -122,18 +229,46 @@ pub fn response_success_encode_decode() {
     let encoded = original
         .clone()
         .encode_with_version(BATCH_VERIFICATION_WIRE_FORMAT_VERSION);
-    let decoded = BatchVerificationResponse::decode(&encoded, 1).unwrap();
+    let decoded =
+        BatchVerificationResponse::decode(&encoded, BATCH_VERIFICATION_WIRE_FORMAT_VERSION)
+            .unwrap();
 
     assert_eq!(decoded, original);
 }
 
 #[test]
-pub fn response_refused_encode_decode() {
-    let original = create_sample_response_refused();
-    let encoded = original
-        .clone()
-        .encode_with_version(BATCH_VERIFICATION_WIRE_FORMAT_VERSION);
+pub fn response_success_encode_decode_v1() {
+    let original = create_sample_response_success();
+    let encoded = original.clone().encode_with_version(1);
     let decoded = BatchVerificationResponse::decode(&encoded, 1).unwrap();
 
     assert_eq!(decoded, original);
 }
+
+#[test]
+pub fn response_refused_encode_decode() {
+    for reason in all_refusal_reasons() {
+        let original = create_sample_response_refused(reason);
+        let encoded = original
+            .clone()
+            .encode_with_version(BATCH_VERIFICATION_WIRE_FORMAT_VERSION);
+        let decoded =
+            BatchVerificationResponse::decode(&encoded, BATCH_VERIFICATION_WIRE_FORMAT_VERSION)
+                .unwrap();
+
+        assert_eq!(decoded, original);
+    }
+}
+
+#[test]
+pub fn response_refused_encode_decode_v1() {
+    for reason in all_refusal_reasons() {
+        let message = reason.to_string();
+        let encoded = create_sample_response_refused(reason).encode_with_version(1);
+        let decoded = BatchVerificationResponse::decode(&encoded, 1).unwrap();
+
+        // v1 only carries the message
+        let expected = create_sample_response_refused(RefusalReason::Internal(message));
+        assert_eq!(decoded, expected);
+    }
+}
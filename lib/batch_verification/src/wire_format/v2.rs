@@ -0,0 +1,43 @@
+//! We need to not accidentally change the batch verification wire format
+//! but there is no way in Rust to get a stable unique ID for a type,
+//! so instead we define it in this separate file.
+//!
+//! Do not change this file under any circumstances. Copy it instead. May be deleted when obsolete.
+//! (This is enforced by CI)
+
+use bincode::{Decode, Encode};
+
+// Differences from v1:
+// - refusal reason is a `RefusalReasonWireFormatV2` instead of a free-form string
+
+/// The format BatchVerificationRequest is currently sent in
+#[derive(Encode, Decode)]
+pub struct BatchVerificationRequestWireFormatV2 {
+    pub batch_number: u64,
+    pub first_block_number: u64,
+    pub last_block_number: u64,
+    pub request_id: u64,
+    pub commit_data: Vec<u8>,
+}
+
+#[derive(Encode, Decode)]
+pub enum RefusalReasonWireFormatV2 {
+    MissingBlocks { first_missing: u64 },
+    CommitmentMismatch { field: String },
+    ExecutionVersionUnsupported,
+    Internal(String),
+}
+
+#[derive(Encode, Decode)]
+pub enum BatchVerificationResponseResultWireFormatV2 {
+    Success([u8; 65]),
+    Refused(RefusalReasonWireFormatV2),
+}
+
+/// The format BatchVerificationResponse is currently sent in
+#[derive(Encode, Decode)]
+pub struct BatchVerificationResponseWireFormatV2 {
+    pub request_id: u64,
+    pub batch_number: u64,
+    pub result: BatchVerificationResponseResultWireFormatV2,
+}
@@ -121,6 +121,49 @@ alloy::sol! {
         function getTotalBatchesExecuted() external view returns (uint256);
         function getTotalPriorityTxs() external view returns (uint256);
         function getPubdataPricingMode() external view returns (PubdataPricingMode);
+        function getChainId() external view returns (uint256);
+        function getBridgehub() external view returns (address);
+        function facetAddresses() external view returns (address[] memory);
+        function baseTokenGasPriceMultiplierNominator() external view returns (uint128);
+        function baseTokenGasPriceMultiplierDenominator() external view returns (uint128);
+        function getVerifier() external view returns (address);
+        function getAdmin() external view returns (address);
+    }
+
+    // `IAdmin.sol`
+    interface IAdmin {
+        // `ZKChainStorage.sol`
+        struct FeeParams {
+            PubdataPricingMode pubdataPricingMode;
+            uint32 batchOverheadL1Gas;
+            uint32 maxPubdataPerBatch;
+            uint32 maxL2GasPerBatch;
+            uint32 priorityTxMaxPubdata;
+            uint64 minimalL2GasPrice;
+        }
+
+        event NewFeeParams(FeeParams oldFeeParams, FeeParams newFeeParams);
+    }
+
+    // `IValidatorTimelock.sol`; validator roles are granted per chain
+    // (see `AccessControlEnumerablePerChainAddressUpgradeable.sol`)
+    #[sol(rpc)]
+    interface IValidatorTimelock {
+        function hasRoleForChainAddress(address _chainAddress, bytes32 _role, address _account) external view returns (bool);
+
+        event RoleGranted(address indexed chainAddress, bytes32 indexed role, address indexed account);
+        event RoleRevoked(address indexed chainAddress, bytes32 indexed role, address indexed account);
+    }
+
+    // `IChainAdmin.sol`
+    interface IChainAdmin {
+        event UpdateUpgradeTimestamp(uint256 indexed protocolVersion, uint256 upgradeTimestamp);
+    }
+
+    // `IVerifier.sol`
+    #[sol(rpc)]
+    interface IVerifier {
+        function verify(uint256[] calldata _publicInputs, uint256[] calldata _proof) external view returns (bool);
     }
 
     // Taken from `IExecutor.sol`
@@ -154,6 +197,7 @@ alloy::sol! {
 
         event BlockCommit(uint256 indexed batchNumber, bytes32 indexed batchHash, bytes32 indexed commitment);
         event BlockExecution(uint256 indexed batchNumber, bytes32 indexed batchHash, bytes32 indexed commitment);
+        event BlocksRevert(uint256 totalBatchesCommitted, uint256 totalBatchesVerified, uint256 totalBatchesExecuted);
 
         function commitBatchesSharedBridge(
             address _chainAddress,
@@ -194,6 +238,12 @@ alloy::sol! {
             bytes[] _factoryDeps
         );
     }
+
+    // EIP-1271
+    #[sol(rpc)]
+    interface IERC1271 {
+        function isValidSignature(bytes32 hash, bytes memory signature) external view returns (bytes4 magicValue);
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -397,6 +447,48 @@ impl<P: Provider> ZkChain<P> {
         self.instance.getPubdataPricingMode().call().await
     }
 
+    pub async fn get_chain_id(&self) -> alloy::contract::Result<u64> {
+        self.instance
+            .getChainId()
+            .call()
+            .await
+            .map(|n| n.saturating_to())
+    }
+
+    pub async fn get_bridgehub(&self) -> alloy::contract::Result<Address> {
+        self.instance.getBridgehub().call().await
+    }
+
+    /// Returns addresses of all facets the diamond proxy delegates to.
+    pub async fn facet_addresses(&self) -> alloy::contract::Result<Vec<Address>> {
+        self.instance.facetAddresses().call().await
+    }
+
+    /// Returns the ETH -> base token conversion ratio as `(numerator, denominator)`, i.e. the price
+    /// of 1 wei in the base token units is `numerator / denominator`. Equals `(1, 1)` for ETH-based chains.
+    pub async fn base_token_gas_price_multiplier(&self) -> alloy::contract::Result<(u128, u128)> {
+        let numerator = self
+            .instance
+            .baseTokenGasPriceMultiplierNominator()
+            .call()
+            .await?;
+        let denominator = self
+            .instance
+            .baseTokenGasPriceMultiplierDenominator()
+            .call()
+            .await?;
+        Ok((numerator, denominator))
+    }
+
+    pub async fn get_verifier(&self) -> alloy::contract::Result<Address> {
+        self.instance.getVerifier().call().await
+    }
+
+    /// Returns the chain admin, normally a `ChainAdmin` contract.
+    pub async fn get_admin(&self) -> alloy::contract::Result<Address> {
+        self.instance.getAdmin().call().await
+    }
+
     /// Returns true iff the contract has non-empty code at `block_id`.
     pub async fn code_exists_at_block(&self, block_id: BlockId) -> alloy::contract::Result<bool> {
         let code = self
@@ -408,3 +500,19 @@ impl<P: Provider> ZkChain<P> {
         Ok(!code.0.is_empty())
     }
 }
+
+impl<P: Provider + Clone> ZkChain<P> {
+    /// Checks `proof` against `public_inputs` with the verifier currently used by the chain, i.e.
+    /// exactly as a prove transaction would.
+    pub async fn verify_proof(
+        &self,
+        public_inputs: Vec<U256>,
+        proof: Vec<U256>,
+    ) -> alloy::contract::Result<bool> {
+        let verifier = self.get_verifier().await?;
+        IVerifier::new(verifier, self.provider().clone())
+            .verify(public_inputs, proof)
+            .call()
+            .await
+    }
+}
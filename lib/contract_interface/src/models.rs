@@ -7,7 +7,7 @@ use structdiff::Difference;
 use structdiff::StructDiff;
 
 /// User-friendly version of [`IExecutor::PriorityOpsBatchInfo`].
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct PriorityOpsBatchInfo {
     pub left_path: Vec<B256>,
     pub right_path: Vec<B256>,
@@ -24,6 +24,16 @@ impl From<PriorityOpsBatchInfo> for IExecutor::PriorityOpsBatchInfo {
     }
 }
 
+impl From<IExecutor::PriorityOpsBatchInfo> for PriorityOpsBatchInfo {
+    fn from(value: IExecutor::PriorityOpsBatchInfo) -> Self {
+        PriorityOpsBatchInfo {
+            left_path: value.leftPath,
+            right_path: value.rightPath,
+            item_hashes: value.itemHashes,
+        }
+    }
+}
+
 /// User-friendly version of [`crate::PubdataPricingMode`] with statically known possible variants.
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum BatchDaInputMode {
@@ -33,7 +43,7 @@ pub enum BatchDaInputMode {
 
 /// User-friendly version of [`IExecutor::StoredBatchInfo`] containing
 /// fields that are relevant for ZKsync OS.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StoredBatchInfo {
     pub batch_number: u64,
     pub state_commitment: B256,
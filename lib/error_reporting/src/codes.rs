@@ -0,0 +1,121 @@
+//! Catalog of all error codes, grouped by component.
+//!
+//! Codes are never reused or renumbered, since alerts refer to them; codes of errors that can no
+//! longer occur are kept. The `*-000` code of a component is its catch-all for errors without
+//! a more specific code. The catalog is documented in `docs/src/design/error_codes.md`, which is
+//! generated from it (see [`crate::error_codes_page()`]).
+
+macro_rules! error_codes {
+    ($(
+        $(#[doc = $component_doc:literal])*
+        $component:ident($prefix:literal) {
+            $($name:ident = $code:literal, $severity:ident, $retryable:literal, $description:literal;)*
+        }
+    )*) => {
+        $(
+            $(#[doc = $component_doc])*
+            pub mod $component {
+                use crate::{ErrorCode, Severity};
+
+                /// Prefix of the component's codes.
+                pub const PREFIX: &str = $prefix;
+
+                $(
+                    #[doc = $description]
+                    pub static $name: ErrorCode = ErrorCode {
+                        code: $code,
+                        component: stringify!($component),
+                        severity: Severity::$severity,
+                        retryable: $retryable,
+                        description: $description,
+                    };
+                )*
+
+                /// All codes of the component.
+                pub static ALL: &[&ErrorCode] = &[$(&$name),*];
+            }
+        )*
+
+        /// Returns all error codes, in catalog order.
+        pub fn all() -> impl Iterator<Item = &'static crate::ErrorCode> {
+            [$($component::ALL),*].into_iter().flatten().copied()
+        }
+
+        /// Returns the code prefix of `component`.
+        pub fn prefix(component: &str) -> Option<&'static str> {
+            match component {
+                $(stringify!($component) => Some($component::PREFIX),)*
+                _ => None,
+            }
+        }
+    };
+}
+
+error_codes! {
+    /// Errors not specific to a component.
+    general("GEN") {
+        UNCLASSIFIED = "GEN-000", Error, false, "Error without an assigned code.";
+    }
+
+    /// Errors of the L1 senders (commit, prove and execute).
+    l1_sender("L1S") {
+        FAILED = "L1S-000", Error, false, "L1 sender failed.";
+        L1_RPC = "L1S-001", Error, true, "L1 RPC request failed.";
+        SEND_FAILED = "L1S-002", Error, true, "Failed to send an L1 transaction.";
+        INCLUSION_TIMEOUT = "L1S-003", Critical, true,
+            "L1 transaction is not included in time after its last submission.";
+        TX_REVERTED = "L1S-004", Critical, false, "L1 transaction reverted.";
+        ZERO_BALANCE = "L1S-005", Critical, false, "Operator address has zero balance.";
+        INVALID_OPERATOR_KEY = "L1S-006", Critical, false,
+            "Operator private key cannot be parsed.";
+        REVERT_ACKNOWLEDGED = "L1S-007", Warning, true,
+            "Batches reverted on L1 were re-queued; the sender restarts to re-commit them.";
+        MISSING_VALIDATOR_ROLE = "L1S-008", Critical, false,
+            "Operator address doesn't have the validator role required to send L1 transactions.";
+    }
+
+    /// Errors of the L1 event watchers.
+    l1_watcher("L1W") {
+        FAILED = "L1W-000", Error, false, "L1 watcher failed to process an event.";
+        NO_L1_BLOCKS = "L1W-001", Error, true, "L1 doesn't have any blocks.";
+        L1_RPC = "L1W-002", Error, true, "L1 RPC request failed.";
+        EVENT_DECODING = "L1W-003", Critical, false, "L1 event log cannot be decoded.";
+        EVENT_CONVERSION = "L1W-004", Critical, false, "L1 event has unexpected contents.";
+        OUTPUT_CLOSED = "L1W-005", Warning, false,
+            "Output channel of the watcher is closed, e.g. on shutdown.";
+    }
+
+    /// Errors of batch verification. `BV-0xx` codes are reported by the main node collecting
+    /// signatures, `BV-1xx` codes by external nodes refusing to sign.
+    batch_verification("BV") {
+        FAILED = "BV-000", Error, false, "Batch verification failed.";
+        TIMEOUT = "BV-001", Warning, true, "Not enough signatures were collected in time.";
+        NOT_ENOUGH_SIGNERS = "BV-002", Warning, true,
+            "Not enough accepted signers are connected to reach the quorum.";
+        REFUSED = "BV-003", Error, false, "Signer refused to sign the batch.";
+        REFUSED_COMMITMENT_MISMATCH = "BV-004", Critical, false,
+            "Signer refused to sign the batch, since it computed different commit data.";
+        INTERNAL = "BV-005", Error, false, "Internal error of the main node.";
+        MISSING_BLOCK = "BV-101", Info, true, "Blocks of the batch are not synced yet.";
+        BLOCK_LOAD = "BV-102", Error, false, "Failed to load a block of the batch.";
+        TREE = "BV-103", Error, false, "Failed to read Merkle tree data of the batch.";
+        BATCH_DATA_MISMATCH = "BV-104", Critical, false,
+            "Requested commit data differs from the locally computed one.";
+        CONFLICTING_COMMIT_DATA = "BV-105", Critical, false,
+            "Batch was already signed for different commit data.";
+        BEYOND_JOURNAL_RETENTION = "BV-106", Error, false,
+            "Batch is older than the signing journal retention.";
+        JOURNAL = "BV-107", Critical, false, "Failed to journal a signature.";
+    }
+
+    /// Errors of the sequencer.
+    sequencer("SEQ") {
+        FAILED = "SEQ-000", Error, false, "Sequencer failed.";
+        COMMAND_PREPARATION = "SEQ-001", Error, false, "Failed to prepare a block command.";
+        BLOCK_EXECUTION = "SEQ-002", Critical, false,
+            "Block execution failed; a block dump is saved.";
+        REPLAY_DIVERGENCE = "SEQ-003", Critical, false,
+            "Output of a replayed block differs from the replay record.";
+        PERSISTENCE = "SEQ-004", Critical, false, "Failed to persist an executed block.";
+    }
+}
@@ -0,0 +1,299 @@
+//! Error taxonomy shared across node components.
+//!
+//! Errors that alerting needs to tell apart carry an [`ErrorCode`] from the [`codes`] catalog:
+//! a stable identifier namespaced per component (e.g. `L1S-004`), a [`Severity`] and a hint
+//! whether the failed operation may succeed when retried. Typed errors implement [`NodeError`];
+//! codes are attached to `anyhow` errors with [`WithErrorCode::with_code()`], which keeps the
+//! error message and its context chain intact.
+//!
+//! Errors are reported with [`report_error()`], which increments the `node_errors` counter
+//! labeled by `error_code`, `component` and `severity`, and logs the error with the same fields,
+//! so that alerts don't depend on log messages.
+
+use std::{error::Error as StdError, fmt};
+
+use vise::EncodeLabelValue;
+
+pub use crate::metrics::ERROR_METRICS;
+
+pub mod codes;
+mod metrics;
+
+/// How urgently an error needs attention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EncodeLabelValue)]
+#[metrics(label = "severity", rename_all = "snake_case")]
+pub enum Severity {
+    /// Expected condition that resolves on its own (e.g. a peer lagging behind).
+    Info,
+    /// Degraded operation; needs attention if it persists.
+    Warning,
+    /// Failed operation or component; usually resolved by a retry or restart.
+    Error,
+    /// Failure requiring operator action (e.g. a reverted L1 transaction or diverged state).
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+            Self::Critical => "critical",
+        }
+    }
+}
+
+/// Stable identifier of a class of errors. All codes are defined in [`codes`].
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct ErrorCode {
+    /// Code namespaced by the component prefix, e.g. `L1S-004`.
+    pub code: &'static str,
+    pub component: &'static str,
+    pub severity: Severity,
+    /// Whether retrying the failed operation (or restarting the component) may succeed.
+    pub retryable: bool,
+    pub description: &'static str,
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(self.code)
+    }
+}
+
+/// Error carrying an [`ErrorCode`].
+pub trait NodeError: fmt::Display {
+    fn error_code(&self) -> &'static ErrorCode;
+
+    /// Converts the error into an `anyhow` error, keeping its code.
+    fn into_anyhow(self) -> anyhow::Error
+    where
+        Self: StdError + Sized + Send + Sync + 'static,
+    {
+        let code = self.error_code();
+        anyhow::Error::new(self).with_code(code)
+    }
+}
+
+/// `anyhow` errors have the innermost (i.e., the most specific) code attached with
+/// [`WithErrorCode::with_code()`], or [`codes::general::UNCLASSIFIED`] if there is none.
+impl NodeError for anyhow::Error {
+    fn error_code(&self) -> &'static ErrorCode {
+        error_code(self).unwrap_or(&codes::general::UNCLASSIFIED)
+    }
+}
+
+/// Error with an attached code. Displays as the wrapped error and continues its source chain,
+/// so attaching a code doesn't change how the error is printed.
+#[derive(Debug)]
+struct CodedError {
+    code: &'static ErrorCode,
+    inner: anyhow::Error,
+}
+
+impl fmt::Display for CodedError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.inner, formatter)
+    }
+}
+
+impl StdError for CodedError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.inner.source()
+    }
+}
+
+/// Attaches [`ErrorCode`]s to `anyhow` errors.
+pub trait WithErrorCode {
+    type Output;
+
+    /// Attaches `code` to the error. Codes attached further down the chain take precedence, so
+    /// components can attach a catch-all code to errors without a more specific one.
+    ///
+    /// The error is printed as before, but can no longer be downcast to its original type.
+    fn with_code(self, code: &'static ErrorCode) -> Self::Output;
+}
+
+impl WithErrorCode for anyhow::Error {
+    type Output = Self;
+
+    fn with_code(self, code: &'static ErrorCode) -> Self {
+        Self::new(CodedError { code, inner: self })
+    }
+}
+
+impl<T, E: Into<anyhow::Error>> WithErrorCode for Result<T, E> {
+    type Output = anyhow::Result<T>;
+
+    fn with_code(self, code: &'static ErrorCode) -> anyhow::Result<T> {
+        self.map_err(|err| Into::<anyhow::Error>::into(err).with_code(code))
+    }
+}
+
+/// Returns the innermost code attached to `err`, if any.
+pub fn error_code(err: &anyhow::Error) -> Option<&'static ErrorCode> {
+    let mut code = None;
+    let mut next: Option<&(dyn StdError + 'static)> = Some(&**err);
+    while let Some(err) = next {
+        next = match err.downcast_ref::<CodedError>() {
+            Some(coded) => {
+                code = Some(coded.code);
+                // The wrapped error may have a code of its own
+                Some(&*coded.inner)
+            }
+            None => err.source(),
+        };
+    }
+    code
+}
+
+/// Reports an error: increments the error counter for its code and logs it with the code, the
+/// component and the severity as structured fields.
+pub fn report_error(err: &(impl NodeError + ?Sized)) {
+    let code = err.error_code();
+    ERROR_METRICS.errors[&(code.code, code.component, code.severity)].inc();
+
+    macro_rules! log {
+        ($level:expr) => {
+            tracing::event!(
+                $level,
+                error_code = code.code,
+                component = code.component,
+                severity = code.severity.as_str(),
+                retryable = code.retryable,
+                "{err:#}"
+            )
+        };
+    }
+    match code.severity {
+        Severity::Info => log!(tracing::Level::INFO),
+        Severity::Warning => log!(tracing::Level::WARN),
+        Severity::Error | Severity::Critical => log!(tracing::Level::ERROR),
+    }
+}
+
+/// Markdown page documenting all error codes, checked in at `docs/src/design/error_codes.md`.
+pub fn error_codes_page() -> String {
+    let mut page = String::from(
+        "# Error codes\n\
+         \n\
+         <!-- Generated by the `zksync_os_error_reporting` crate; regenerate with\n\
+         `cargo test -p zksync_os_error_reporting -- --ignored generate_error_codes_page` -->\n\
+         \n\
+         Errors reported by node components carry a stable code. Each reported error increments \
+         the `node_errors` counter\nlabeled by `error_code`, `component` and `severity`, and is \
+         logged with the same fields, so alerts can key on the\ncode rather than on the log \
+         message. Retryable errors may go away when the failed operation is retried or the\n\
+         component is restarted.\n\
+         \n\
+         | Code | Component | Severity | Retryable | Description |\n\
+         |------|-----------|----------|-----------|-------------|\n",
+    );
+    for code in codes::all() {
+        page += &format!(
+            "| `{}` | {} | {} | {} | {} |\n",
+            code.code,
+            code.component,
+            code.severity.as_str(),
+            if code.retryable { "yes" } else { "no" },
+            code.description
+        );
+    }
+    page
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::codes::{general, l1_sender};
+
+    const ERROR_CODES_PAGE: &str = "../../docs/src/design/error_codes.md";
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("typed failure")]
+    struct TypedError;
+
+    impl NodeError for TypedError {
+        fn error_code(&self) -> &'static ErrorCode {
+            &l1_sender::TX_REVERTED
+        }
+    }
+
+    #[test]
+    fn codes_are_unique_and_namespaced() {
+        let mut seen = HashSet::new();
+        for code in codes::all() {
+            assert!(seen.insert(code.code), "duplicate error code {code}");
+            let prefix = codes::prefix(code.component).unwrap();
+            let number = code
+                .code
+                .strip_prefix(prefix)
+                .and_then(|rest| rest.strip_prefix('-'))
+                .unwrap_or_else(|| panic!("{code} doesn't start with `{prefix}-`"));
+            assert!(
+                number.len() == 3 && number.bytes().all(|byte| byte.is_ascii_digit()),
+                "{code} doesn't end with a 3-digit number"
+            );
+            assert!(!code.description.is_empty(), "{code} has no description");
+        }
+    }
+
+    #[test]
+    fn error_codes_are_documented() {
+        let documented = std::fs::read_to_string(ERROR_CODES_PAGE).unwrap();
+        assert!(
+            documented == error_codes_page(),
+            "{ERROR_CODES_PAGE} is outdated; regenerate it with `generate_error_codes_page`"
+        );
+    }
+
+    // Regenerates the error codes page. To be run after changing the catalog
+    #[test]
+    #[ignore]
+    fn generate_error_codes_page() {
+        std::fs::write(ERROR_CODES_PAGE, error_codes_page()).unwrap();
+    }
+
+    #[test]
+    fn innermost_code_is_used() {
+        let err = anyhow::anyhow!("root cause");
+        assert_eq!(err.error_code(), &general::UNCLASSIFIED);
+
+        let err = err
+            .context("sending transaction")
+            .with_code(&l1_sender::SEND_FAILED)
+            .context("sending batches")
+            .with_code(&l1_sender::FAILED);
+        assert_eq!(err.error_code(), &l1_sender::SEND_FAILED);
+        // Codes don't change the message
+        assert_eq!(
+            format!("{err:#}"),
+            "sending batches: sending transaction: root cause"
+        );
+        assert_eq!(err.chain().count(), 3);
+
+        let err = TypedError.into_anyhow().context("committing batches");
+        assert_eq!(err.error_code(), &l1_sender::TX_REVERTED);
+        assert_eq!(format!("{err:#}"), "committing batches: typed failure");
+    }
+
+    #[test]
+    fn results_get_codes() {
+        let result: Result<(), _> = Err(std::io::Error::other("connection reset"));
+        let err = result.with_code(&l1_sender::L1_RPC).unwrap_err();
+        assert_eq!(err.error_code(), &l1_sender::L1_RPC);
+        assert_eq!(err.to_string(), "connection reset");
+    }
+
+    #[test]
+    fn reported_errors_are_counted_by_code() {
+        let labels = ("L1S-004", "l1_sender", Severity::Critical);
+        let reported_before = ERROR_METRICS.errors[&labels].get();
+        report_error(&TypedError);
+        report_error(&anyhow::anyhow!("reverted").with_code(&l1_sender::TX_REVERTED));
+        assert_eq!(ERROR_METRICS.errors[&labels].get() - reported_before, 2);
+    }
+}
@@ -0,0 +1,14 @@
+use vise::{Counter, LabeledFamily, Metrics};
+
+use crate::Severity;
+
+#[derive(Debug, Metrics)]
+#[metrics(prefix = "node")]
+pub struct ErrorMetrics {
+    /// Number of reported errors, by error code.
+    #[metrics(labels = ["error_code", "component", "severity"])]
+    pub errors: LabeledFamily<(&'static str, &'static str, Severity), Counter, 3>,
+}
+
+#[vise::register]
+pub static ERROR_METRICS: vise::Global<ErrorMetrics> = vise::Global::new();
@@ -0,0 +1,199 @@
+//! ETH -> base token conversion for chains with a custom base token.
+//!
+//! L1 costs (and hence prices derived from them) are denominated in ETH, while L2 fees are paid
+//! in the chain's base token. [`BaseTokenRateUpdater`] periodically fetches the conversion ratio from
+//! a [`BaseTokenRateProvider`] and publishes the last successfully fetched value.
+
+use crate::metrics::METRICS;
+use alloy::primitives::U256;
+use alloy::providers::DynProvider;
+use std::fmt;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use zksync_os_contract_interface::ZkChain;
+
+/// Conversion ratio from ETH to the base token: `amount_in_base_token = amount_in_eth * numerator / denominator`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BaseTokenConversionRatio {
+    pub numerator: u128,
+    pub denominator: u128,
+}
+
+impl BaseTokenConversionRatio {
+    /// Ratio for ETH-based chains.
+    pub const ONE: Self = Self {
+        numerator: 1,
+        denominator: 1,
+    };
+
+    pub fn new(numerator: u128, denominator: u128) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            numerator != 0 && denominator != 0,
+            "invalid base token conversion ratio {numerator}/{denominator}"
+        );
+        Ok(Self {
+            numerator,
+            denominator,
+        })
+    }
+
+    /// Converts an amount in ETH (wei) to the base token units. Saturates on overflow.
+    pub fn convert(&self, eth_amount: U256) -> U256 {
+        eth_amount.saturating_mul(U256::from(self.numerator)) / U256::from(self.denominator)
+    }
+}
+
+impl fmt::Display for BaseTokenConversionRatio {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+}
+
+/// Source of the ETH -> base token conversion ratio.
+#[async_trait::async_trait]
+pub trait BaseTokenRateProvider: fmt::Debug + Send + Sync {
+    async fn conversion_ratio(&self) -> anyhow::Result<BaseTokenConversionRatio>;
+}
+
+/// Static ratio, e.g. from the node config.
+#[async_trait::async_trait]
+impl BaseTokenRateProvider for BaseTokenConversionRatio {
+    async fn conversion_ratio(&self) -> anyhow::Result<BaseTokenConversionRatio> {
+        Ok(*self)
+    }
+}
+
+/// Ratio maintained on L1 by the chain admin.
+#[async_trait::async_trait]
+impl BaseTokenRateProvider for ZkChain<DynProvider> {
+    async fn conversion_ratio(&self) -> anyhow::Result<BaseTokenConversionRatio> {
+        let (numerator, denominator) = self.base_token_gas_price_multiplier().await?;
+        BaseTokenConversionRatio::new(numerator, denominator)
+    }
+}
+
+/// Conversion ratio together with the time it was fetched.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BaseTokenRate {
+    pub ratio: BaseTokenConversionRatio,
+    pub fetched_at: Instant,
+}
+
+impl BaseTokenRate {
+    /// Returns whether the rate was fetched more than `max_staleness` before `now`.
+    pub fn is_stale(&self, now: Instant, max_staleness: Duration) -> bool {
+        now.saturating_duration_since(self.fetched_at) > max_staleness
+    }
+}
+
+/// Periodically fetches the conversion ratio and publishes it. On failure, the last successfully fetched
+/// rate stays published; consumers decide how stale a rate they accept based on [`BaseTokenRate::fetched_at`].
+#[derive(Debug)]
+pub struct BaseTokenRateUpdater {
+    provider: Box<dyn BaseTokenRateProvider>,
+    poll_period: Duration,
+    rate_sender: watch::Sender<Option<BaseTokenRate>>,
+}
+
+impl BaseTokenRateUpdater {
+    pub async fn new(
+        provider: Box<dyn BaseTokenRateProvider>,
+        poll_period: Duration,
+        rate_sender: watch::Sender<Option<BaseTokenRate>>,
+    ) -> anyhow::Result<Self> {
+        let this = Self {
+            provider,
+            poll_period,
+            rate_sender,
+        };
+        this.update_rate().await?;
+        Ok(this)
+    }
+
+    async fn update_rate(&self) -> anyhow::Result<()> {
+        let ratio = self.provider.conversion_ratio().await?;
+        METRICS
+            .base_token_ratio
+            .set(ratio.numerator as f64 / ratio.denominator as f64);
+        self.rate_sender.send_replace(Some(BaseTokenRate {
+            ratio,
+            fetched_at: Instant::now(),
+        }));
+        Ok(())
+    }
+
+    pub async fn run(self) -> anyhow::Result<()> {
+        let mut timer = tokio::time::interval(self.poll_period);
+        // The first tick completes immediately; the rate was just fetched in `new()`
+        timer.tick().await;
+        loop {
+            timer.tick().await;
+            if let Err(err) = self.update_rate().await {
+                METRICS.base_token_rate_update_failures.inc();
+                let last_rate = *self.rate_sender.borrow();
+                tracing::warn!(
+                    ?last_rate,
+                    "Cannot update base token conversion ratio, keeping the last one: {err:#}"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    #[derive(Debug)]
+    struct MockProvider(Mutex<VecDeque<anyhow::Result<BaseTokenConversionRatio>>>);
+
+    #[async_trait::async_trait]
+    impl BaseTokenRateProvider for MockProvider {
+        async fn conversion_ratio(&self) -> anyhow::Result<BaseTokenConversionRatio> {
+            self.0.lock().unwrap().pop_front().unwrap()
+        }
+    }
+
+    #[test]
+    fn converting_amounts() {
+        let ratio = BaseTokenConversionRatio::new(3, 2).unwrap();
+        assert_eq!(ratio.convert(U256::from(1_000)), U256::from(1_500));
+        assert_eq!(ratio.convert(U256::from(1)), U256::from(1));
+        assert_eq!(ratio.convert(U256::MAX), U256::MAX / U256::from(2));
+        assert_eq!(
+            BaseTokenConversionRatio::ONE.convert(U256::from(42)),
+            U256::from(42)
+        );
+        BaseTokenConversionRatio::new(1, 0).unwrap_err();
+        BaseTokenConversionRatio::new(0, 1).unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn last_good_rate_is_kept_on_failure() {
+        let first = BaseTokenConversionRatio::new(2_000, 1).unwrap();
+        let second = BaseTokenConversionRatio::new(2_100, 1).unwrap();
+        let provider = MockProvider(Mutex::new(VecDeque::from([
+            Ok(first),
+            Err(anyhow::anyhow!("L1 is unavailable")),
+            Ok(second),
+        ])));
+        let (sender, mut receiver) = watch::channel(None);
+        let updater =
+            BaseTokenRateUpdater::new(Box::new(provider), Duration::from_secs(10), sender)
+                .await
+                .unwrap();
+        let initial = receiver.borrow_and_update().unwrap();
+        assert_eq!(initial.ratio, first);
+
+        updater.update_rate().await.unwrap_err();
+        assert!(!receiver.has_changed().unwrap());
+        assert_eq!(receiver.borrow().unwrap(), initial);
+
+        updater.update_rate().await.unwrap();
+        let updated = receiver.borrow_and_update().unwrap();
+        assert_eq!(updated.ratio, second);
+        assert!(updated.fetched_at >= initial.fetched_at);
+    }
+}
@@ -0,0 +1,60 @@
+use crate::BaseTokenConversionRatio;
+use alloy::primitives::U256;
+
+/// Price of a unit of native resources on ETH-based chains, in wei.
+pub const ETH_NATIVE_PRICE: u128 = 1_000_000;
+/// Native resources charged per unit of gas.
+pub const NATIVE_PER_GAS: u128 = 100;
+
+/// Inputs the base fee of the next L2 block is derived from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BaseFeeInputs {
+    /// Base fee fixed by config, denominated in the base token.
+    Override(U256),
+    /// ETH-denominated base fee converted to the base token; [`BaseTokenConversionRatio::ONE`]
+    /// for ETH-based chains.
+    Rate(BaseTokenConversionRatio),
+    /// The base token rate is stale or unknown (e.g., to an external node), so the base fee of
+    /// the previous block is kept.
+    Unchanged { previous_base_fee: U256 },
+}
+
+/// Base fee per gas of the next L2 block. Used both by the sequencer to produce blocks and by
+/// the RPC to report the expected base fee, so that the two never diverge.
+pub fn next_block_base_fee(inputs: BaseFeeInputs) -> U256 {
+    match inputs {
+        BaseFeeInputs::Override(base_fee) => base_fee,
+        BaseFeeInputs::Rate(ratio) => ratio.convert(U256::from(ETH_NATIVE_PRICE * NATIVE_PER_GAS)),
+        BaseFeeInputs::Unchanged { previous_base_fee } => previous_base_fee,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_block_base_fee_formula() {
+        // 0.1 gwei on ETH-based chains
+        assert_eq!(
+            next_block_base_fee(BaseFeeInputs::Rate(BaseTokenConversionRatio::ONE)),
+            U256::from(100_000_000)
+        );
+        // 1 ETH = 2500.5 base tokens
+        let ratio = BaseTokenConversionRatio::new(5_001, 2).unwrap();
+        assert_eq!(
+            next_block_base_fee(BaseFeeInputs::Rate(ratio)),
+            U256::from(250_050_000_000_u128)
+        );
+        assert_eq!(
+            next_block_base_fee(BaseFeeInputs::Override(U256::from(7))),
+            U256::from(7)
+        );
+        assert_eq!(
+            next_block_base_fee(BaseFeeInputs::Unchanged {
+                previous_base_fee: U256::from(42)
+            }),
+            U256::from(42)
+        );
+    }
+}
@@ -1,17 +1,33 @@
 //! This module determines the fees to pay in txs containing blocks submitted to the L1.
 
 use crate::statistics::GasStatistics;
-use alloy::providers::{DynProvider, Provider};
+use alloy::providers::DynProvider;
 use metrics::METRICS;
+use serde::Serialize;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::watch;
+use tokio::time::Instant;
+use vise::{Gauge, LabeledFamily};
 
+pub use self::base_token::{
+    BaseTokenConversionRatio, BaseTokenRate, BaseTokenRateProvider, BaseTokenRateUpdater,
+};
+pub use self::l2_base_fee::{BaseFeeInputs, ETH_NATIVE_PRICE, NATIVE_PER_GAS, next_block_base_fee};
+pub use self::provider::{EthFeeProvider, FallbackFeeProvider};
+pub use self::pubdata_composition::{PubdataComposition, count_zero_bytes};
+
+mod base_token;
+mod l2_base_fee;
 mod metrics;
+mod provider;
+mod pubdata_composition;
 mod statistics;
 
-/// This component keeps track of the median `base_fee` from the last `max_base_fee_samples` blocks.
+/// This component keeps track of the `base_fee` from the last `max_base_fee_samples` blocks.
 ///
-/// It also tracks the median `blob_base_fee` from the last `max_blob_base_fee_sample` blocks.
+/// It also tracks the `blob_base_fee` from the last `max_blob_base_fee_sample` blocks.
+/// Prices are estimated from the configured `base_fee_percentile` of the samples.
 /// It is used to adjust the base_fee of transactions sent to L1.
 #[derive(Debug)]
 pub struct GasAdjuster {
@@ -19,11 +35,15 @@ pub struct GasAdjuster {
     blob_base_fee_statistics: GasStatistics<u128>,
 
     config: GasAdjusterConfig,
-    provider: DynProvider,
+    provider: Box<dyn EthFeeProvider>,
     pubdata_price_sender: watch::Sender<Option<u128>>,
+    snapshot_sender: watch::Sender<GasAdjusterSnapshot>,
+    pubdata_composition: PubdataComposition,
+    /// When fee data was last successfully fetched from L1.
+    last_updated_at: Instant,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub enum PubdataMode {
     Blobs,
     Calldata,
@@ -38,43 +58,126 @@ pub struct GasAdjusterConfig {
     pub max_priority_fee_per_gas: u128,
     pub poll_period: Duration,
     pub pubdata_pricing_multiplier: f64,
+    /// Percentile (in `[0, 1]`) of the sampled base fees (and blob base fees) used to estimate
+    /// prices; 0.5 is the median. Higher values bid more aggressively during L1 fee ramps.
+    pub base_fee_percentile: f64,
+    /// L1 gas paid for a non-zero pubdata byte in `Calldata` mode; zero bytes cost a quarter of it.
+    pub calldata_gas_per_byte: u128,
+    /// Expected share (in `[0, 1]`) of zero pubdata bytes. Pubdata composition observed in committed
+    /// batches can only raise the calldata pubdata price above the one implied by this estimate.
+    pub zero_byte_ratio_estimate: f64,
+    /// Number of last committed batches whose pubdata composition is taken into account.
+    pub pubdata_composition_batches: usize,
+    /// If fee data couldn't be fetched from L1 for longer than this, it's considered stale.
+    pub max_fee_data_age: Duration,
+}
+
+/// What [`GasAdjuster`] currently knows about L1 fees, for operators and other components.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GasAdjusterSnapshot {
+    /// Latest L1 block that fee samples were collected up to.
+    pub last_processed_block: u64,
+    pub base_fee: FeeSamplesSnapshot,
+    pub blob_base_fee: FeeSamplesSnapshot,
+    pub pubdata_mode: PubdataMode,
+    /// Gas price for L1 transactions, as returned by [`GasAdjuster::gas_price()`].
+    pub gas_price: u128,
+    /// Price of a pubdata byte, as returned by [`GasAdjuster::pubdata_price()`].
+    pub pubdata_price: u128,
+}
+
+/// Summary of the retained samples of a single fee.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FeeSamplesSnapshot {
+    pub sample_count: usize,
+    /// Fee of the latest sampled L1 block.
+    pub current: u128,
+    pub median: u128,
+    pub p75: u128,
+    pub p95: u128,
+    /// Value at the configured `base_fee_percentile`, which prices are estimated from.
+    pub estimate: u128,
+    /// Retained samples in ascending order, for arbitrary percentiles.
+    #[serde(skip)]
+    sorted_samples: Arc<[u128]>,
 }
 
 impl GasAdjuster {
+    /// Creates an adjuster fetching fee data from `providers`, failing over to the next provider
+    /// in the list if the current one fails (see [`FallbackFeeProvider`]).
     pub async fn new(
-        provider: DynProvider,
+        providers: Vec<DynProvider>,
+        config: GasAdjusterConfig,
+        pubdata_price_sender: watch::Sender<Option<u128>>,
+    ) -> anyhow::Result<Self> {
+        let providers = providers
+            .into_iter()
+            .map(|provider| Box::new(provider) as Box<dyn EthFeeProvider>)
+            .collect();
+        Self::with_provider(
+            Box::new(FallbackFeeProvider::new(providers)),
+            config,
+            pubdata_price_sender,
+        )
+        .await
+    }
+
+    /// Creates an adjuster fetching fee data from a single `provider`.
+    pub async fn with_provider(
+        provider: Box<dyn EthFeeProvider>,
         config: GasAdjusterConfig,
         pubdata_price_sender: watch::Sender<Option<u128>>,
     ) -> anyhow::Result<Self> {
         // Subtracting 1 from the "latest" block number to prevent errors in case
         // the info about the latest block is not yet present on the node.
         // This sometimes happens on Infura.
-        let current_block = provider.get_block_number().await?.saturating_sub(1);
-        let fee_history =
-            Self::base_fee_history(&provider, current_block, config.max_base_fee_samples as u64)
-                .await?;
+        let current_block = provider.block_number().await?.saturating_sub(1);
+        let fee_history = Self::base_fee_history(
+            provider.as_ref(),
+            current_block,
+            config.max_base_fee_samples as u64,
+        )
+        .await?;
 
         let base_fee_statistics = GasStatistics::new(
             config.max_base_fee_samples,
             current_block,
-            fee_history.iter().map(|fee| fee.base_fee_per_gas),
+            fee_history
+                .iter()
+                .map(|(block, fee)| (*block, fee.base_fee_per_gas)),
         );
 
         let blob_base_fee_statistics = GasStatistics::new(
             config.num_samples_for_blob_base_fee_estimate,
             current_block,
-            fee_history.iter().map(|fee| fee.base_fee_per_blob_gas),
+            fee_history
+                .iter()
+                .map(|(block, fee)| (*block, fee.base_fee_per_blob_gas)),
         );
 
+        // The placeholder is replaced before any receiver can observe it
+        let snapshot_sender = watch::Sender::new(GasAdjusterSnapshot {
+            last_processed_block: current_block,
+            base_fee: FeeSamplesSnapshot::default(),
+            blob_base_fee: FeeSamplesSnapshot::default(),
+            pubdata_mode: config.pubdata_mode.clone(),
+            gas_price: 0,
+            pubdata_price: 0,
+        });
+        let pubdata_composition = PubdataComposition::new(config.pubdata_composition_batches);
         let this = Self {
             base_fee_statistics,
             blob_base_fee_statistics,
+            pubdata_composition,
+            last_updated_at: Instant::now(),
             config,
             provider,
             pubdata_price_sender,
+            snapshot_sender,
         };
         this.pubdata_price_sender
             .send_replace(Some(this.pubdata_price()));
+        this.snapshot_sender.send_replace(this.snapshot());
 
         Ok(this)
     }
@@ -85,16 +188,44 @@ impl GasAdjuster {
         // Subtracting 1 from the "latest" block number to prevent errors in case
         // the info about the latest block is not yet present on the node.
         // This sometimes happens on Infura.
-        let current_block = self.provider.get_block_number().await?.saturating_sub(1);
+        let current_block = self.provider.block_number().await?.saturating_sub(1);
 
         let last_processed_block = self.base_fee_statistics.last_processed_block();
+        let max_samples = self.config.max_base_fee_samples as u64;
 
-        if current_block > last_processed_block {
-            let n_blocks = current_block - last_processed_block;
-            let fee_data = Self::base_fee_history(&self.provider, current_block, n_blocks).await?;
+        if current_block < last_processed_block {
+            // L1 reorg, or a load-balanced provider switched to a lagging node. Retained samples
+            // may belong to blocks that are no longer canonical, so the window is rebuilt.
+            tracing::warn!(
+                current_block,
+                last_processed_block,
+                "L1 block number went back; resetting fee statistics"
+            );
+            let fee_data =
+                Self::base_fee_history(self.provider.as_ref(), current_block, max_samples).await?;
+            self.base_fee_statistics.reset(
+                current_block,
+                fee_data
+                    .iter()
+                    .map(|(block, fee)| (*block, fee.base_fee_per_gas)),
+            );
+            self.blob_base_fee_statistics.reset(
+                current_block,
+                fee_data
+                    .iter()
+                    .map(|(block, fee)| (*block, fee.base_fee_per_blob_gas)),
+            );
+            self.pubdata_price_sender
+                .send_replace(Some(self.pubdata_price()));
+        } else if current_block > last_processed_block {
+            // After an outage, blocks older than the window would be evicted right away
+            let n_blocks = (current_block - last_processed_block).min(max_samples);
+            let fee_data =
+                Self::base_fee_history(self.provider.as_ref(), current_block, n_blocks).await?;
 
             // We shouldn't rely on L1 provider to return consistent results, so we check that we have at least one new sample.
-            if let Some(current_base_fee_per_gas) = fee_data.last().map(|fee| fee.base_fee_per_gas)
+            if let Some(current_base_fee_per_gas) =
+                fee_data.last().map(|(_, fee)| fee.base_fee_per_gas)
             {
                 if current_base_fee_per_gas > u64::MAX as u128 {
                     tracing::info!(
@@ -106,16 +237,23 @@ impl GasAdjuster {
                         .set(current_base_fee_per_gas as u64);
                 }
             }
-            self.base_fee_statistics
-                .add_samples(fee_data.iter().map(|fee| fee.base_fee_per_gas));
+            self.base_fee_statistics.add_samples(
+                fee_data
+                    .iter()
+                    .map(|(block, fee)| (*block, fee.base_fee_per_gas)),
+            );
             if self.base_fee_statistics.median() <= u64::MAX as u128 {
                 METRICS
                     .median_base_fee_per_gas
                     .set(self.base_fee_statistics.median() as u64);
             }
+            report_percentiles(
+                &self.base_fee_statistics,
+                &METRICS.base_fee_per_gas_percentile,
+            );
 
             if let Some(current_blob_base_fee) =
-                fee_data.last().map(|fee| fee.base_fee_per_blob_gas)
+                fee_data.last().map(|(_, fee)| fee.base_fee_per_blob_gas)
             {
                 if current_blob_base_fee > u64::MAX as u128 {
                     tracing::info!(
@@ -127,17 +265,25 @@ impl GasAdjuster {
                         .set(current_blob_base_fee as u64);
                 }
             }
-            self.blob_base_fee_statistics
-                .add_samples(fee_data.iter().map(|fee| fee.base_fee_per_blob_gas));
+            self.blob_base_fee_statistics.add_samples(
+                fee_data
+                    .iter()
+                    .map(|(block, fee)| (*block, fee.base_fee_per_blob_gas)),
+            );
             if self.blob_base_fee_statistics.median() <= u64::MAX as u128 {
                 METRICS
                     .median_blob_base_fee
                     .set(self.blob_base_fee_statistics.median() as u64);
             }
+            report_percentiles(
+                &self.blob_base_fee_statistics,
+                &METRICS.blob_base_fee_percentile,
+            );
 
             self.pubdata_price_sender
                 .send_replace(Some(self.pubdata_price()));
         }
+        self.last_updated_at = Instant::now();
         Ok(())
     }
 
@@ -147,7 +293,13 @@ impl GasAdjuster {
         loop {
             if let Err(err) = self.update_fees().await {
                 attempts_failed_in_a_row += 1;
-                if attempts_failed_in_a_row >= 5 {
+                if self.is_stale() {
+                    tracing::error!(
+                        attempts_failed_in_a_row,
+                        fee_data_age = ?self.last_updated_at.elapsed(),
+                        "Cannot add the base fee to gas statistics, fee data is stale: {err}"
+                    );
+                } else if attempts_failed_in_a_row >= 5 {
                     tracing::warn!(
                         attempts_failed_in_a_row,
                         "Cannot add the base fee to gas statistics: {err}"
@@ -155,14 +307,69 @@ impl GasAdjuster {
                 }
             } else {
                 attempts_failed_in_a_row = 0;
+                self.snapshot_sender.send_replace(self.snapshot());
             }
             timer.tick().await;
         }
     }
 
+    /// Returns the current state of fee statistics and the prices derived from them.
+    pub fn snapshot(&self) -> GasAdjusterSnapshot {
+        let p = self.config.base_fee_percentile;
+        GasAdjusterSnapshot {
+            last_processed_block: self.base_fee_statistics.last_processed_block(),
+            base_fee: FeeSamplesSnapshot::new(&self.base_fee_statistics, p),
+            blob_base_fee: FeeSamplesSnapshot::new(&self.blob_base_fee_statistics, p),
+            pubdata_mode: self.config.pubdata_mode.clone(),
+            gas_price: self.gas_price(),
+            pubdata_price: self.pubdata_price(),
+        }
+    }
+
+    /// Returns a receiver of [snapshots](Self::snapshot()), published by [`Self::run()`] after
+    /// every successful fee update.
+    pub fn subscribe(&self) -> watch::Receiver<GasAdjusterSnapshot> {
+        self.snapshot_sender.subscribe()
+    }
+
+    /// Returns a handle to record the pubdata composition of committed batches with.
+    pub fn pubdata_composition(&self) -> PubdataComposition {
+        self.pubdata_composition.clone()
+    }
+
+    /// Average L1 gas paid per pubdata byte in `Calldata` mode. It's computed from the share of
+    /// zero bytes in recently committed batches, but is never lower than the one implied by the
+    /// configured `zero_byte_ratio_estimate`.
+    pub fn calldata_gas_per_byte(&self) -> f64 {
+        let floor = pubdata_composition::gas_per_byte(
+            self.config.calldata_gas_per_byte,
+            self.config.zero_byte_ratio_estimate,
+        );
+        self.pubdata_composition
+            .zero_byte_ratio()
+            .map_or(floor, |ratio| {
+                pubdata_composition::gas_per_byte(self.config.calldata_gas_per_byte, ratio)
+                    .max(floor)
+            })
+    }
+
+    /// Returns whether fee data wasn't fetched from L1 for longer than `max_fee_data_age`, so that
+    /// prices may be badly outdated.
+    pub fn is_stale(&self) -> bool {
+        self.last_updated_at.elapsed() > self.config.max_fee_data_age
+    }
+
     pub fn gas_price(&self) -> u128 {
-        let median = self.base_fee_statistics.median();
-        median + self.config.max_priority_fee_per_gas
+        if self.is_stale() {
+            tracing::error!(
+                fee_data_age = ?self.last_updated_at.elapsed(),
+                "L1 fee data is stale; gas price is estimated from outdated samples"
+            );
+        }
+        let base_fee = self
+            .base_fee_statistics
+            .percentile(self.config.base_fee_percentile);
+        base_fee + self.config.max_priority_fee_per_gas
     }
 
     pub fn pubdata_price(&self) -> u128 {
@@ -170,15 +377,13 @@ impl GasAdjuster {
             PubdataMode::Blobs => {
                 const BLOB_GAS_PER_BYTE: u128 = 1; // `BYTES_PER_BLOB` = `GAS_PER_BLOB` = 2 ^ 17.
 
-                let blob_base_fee_median = self.blob_base_fee_statistics.median();
-                blob_base_fee_median * BLOB_GAS_PER_BYTE
+                let blob_base_fee = self
+                    .blob_base_fee_statistics
+                    .percentile(self.config.base_fee_percentile);
+                blob_base_fee * BLOB_GAS_PER_BYTE
             }
             PubdataMode::Calldata => {
-                /// The amount of gas we need to pay for each non-zero pubdata byte.
-                /// Note that it is bigger than 16 to account for potential overhead.
-                const L1_GAS_PER_PUBDATA_BYTE: u128 = 17;
-
-                self.gas_price().saturating_mul(L1_GAS_PER_PUBDATA_BYTE)
+                (self.gas_price() as f64 * self.calldata_gas_per_byte()) as u128
             }
             PubdataMode::Validium => 0,
         };
@@ -188,13 +393,13 @@ impl GasAdjuster {
 
     /// Collects the base fee history for the specified block range.
     ///
-    /// Returns 1 value for each block in range, assuming that these blocks exist.
-    /// Will return an error if the `upto_block` is beyond the head block.
+    /// Returns 1 value for each block in range, paired with the block number, assuming that these
+    /// blocks exist. Will return an error if the `upto_block` is beyond the head block.
     async fn base_fee_history(
-        provider: &DynProvider,
+        provider: &dyn EthFeeProvider,
         upto_block: u64,
         block_count: u64,
-    ) -> anyhow::Result<Vec<BaseFees>> {
+    ) -> anyhow::Result<Vec<(u64, BaseFees)>> {
         const FEE_HISTORY_MAX_REQUEST_CHUNK: usize = 1023;
 
         let mut history = Vec::with_capacity(block_count as usize);
@@ -204,12 +409,11 @@ impl GasAdjuster {
         // `[from_block; upto_block]` in chunks of size `FEE_HISTORY_MAX_REQUEST_CHUNK`
         // starting from the oldest block.
         for chunk_start in (from_block..=upto_block).step_by(FEE_HISTORY_MAX_REQUEST_CHUNK) {
-            let chunk_end = (chunk_start + FEE_HISTORY_MAX_REQUEST_CHUNK as u64).min(upto_block);
+            let chunk_end =
+                (chunk_start + FEE_HISTORY_MAX_REQUEST_CHUNK as u64 - 1).min(upto_block);
             let chunk_size = chunk_end - chunk_start + 1;
 
-            let fee_history = provider
-                .get_fee_history(chunk_size, chunk_end.into(), &[])
-                .await?;
+            let fee_history = provider.fee_history(chunk_size, chunk_end).await?;
 
             if fee_history.oldest_block != chunk_start {
                 anyhow::bail!(
@@ -219,17 +423,18 @@ impl GasAdjuster {
             }
 
             // We take `chunk_size` entries and drop data for the block after `chunk_end`.
-            for (base_fee_per_gas, base_fee_per_blob_gas) in fee_history
-                .base_fee_per_gas
-                .into_iter()
-                .zip(fee_history.base_fee_per_blob_gas)
-                .take(chunk_size as usize)
-            {
+            for (block, (base_fee_per_gas, base_fee_per_blob_gas)) in (chunk_start..).zip(
+                fee_history
+                    .base_fee_per_gas
+                    .into_iter()
+                    .zip(fee_history.base_fee_per_blob_gas)
+                    .take(chunk_size as usize),
+            ) {
                 let fees = BaseFees {
                     base_fee_per_gas,
                     base_fee_per_blob_gas,
                 };
-                history.push(fees)
+                history.push((block, fees))
             }
         }
 
@@ -237,9 +442,439 @@ impl GasAdjuster {
     }
 }
 
+impl FeeSamplesSnapshot {
+    fn new(statistics: &GasStatistics<u128>, estimate_percentile: f64) -> Self {
+        Self {
+            sample_count: statistics.sample_count(),
+            current: statistics.latest().unwrap_or_default(),
+            median: statistics.median(),
+            p75: statistics.percentile(0.75),
+            p95: statistics.percentile(0.95),
+            estimate: statistics.percentile(estimate_percentile),
+            sorted_samples: statistics.sorted_samples().into(),
+        }
+    }
+
+    /// Creates a snapshot of arbitrary `samples`, e.g. to script fees in tests of components
+    /// consuming snapshots. The last sample is the current fee.
+    pub fn from_samples(samples: &[u128], estimate_percentile: f64) -> Self {
+        let mut sorted_samples = samples.to_vec();
+        sorted_samples.sort_unstable();
+        let percentile = |p| statistics::nearest_rank(&sorted_samples, p);
+        Self {
+            sample_count: samples.len(),
+            current: samples.last().copied().unwrap_or_default(),
+            median: percentile(0.5),
+            p75: percentile(0.75),
+            p95: percentile(0.95),
+            estimate: percentile(estimate_percentile),
+            sorted_samples: sorted_samples.into(),
+        }
+    }
+
+    /// Returns the `p`-th percentile (`p` in `[0, 1]`) of the retained samples.
+    pub fn percentile(&self, p: f64) -> u128 {
+        statistics::nearest_rank(&self.sorted_samples, p)
+    }
+}
+
+/// Reports the percentiles operators look at to see the spread of sampled fees.
+fn report_percentiles(
+    statistics: &GasStatistics<u128>,
+    gauges: &LabeledFamily<&'static str, Gauge<u64>>,
+) {
+    for (label, p) in [("p50", 0.5), ("p75", 0.75), ("p95", 0.95)] {
+        let value = statistics.percentile(p);
+        if value <= u64::MAX as u128 {
+            gauges[&label].set(value as u64);
+        }
+    }
+}
+
 /// Information about the base fees provided by the L1 client.
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct BaseFees {
     pub base_fee_per_gas: u128,
     pub base_fee_per_blob_gas: u128,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::rpc::types::FeeHistory;
+    use std::sync::{Arc, Mutex};
+
+    const WINDOW: usize = 5;
+
+    #[derive(Debug, Default)]
+    struct MockL1 {
+        head: u64,
+        /// Base fee of each block, indexed by the block number.
+        base_fees: Vec<u128>,
+        requested_block_counts: Vec<u64>,
+        /// Whether all requests fail, as if the RPC endpoint was down.
+        failing: bool,
+        requests: usize,
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct MockFeeProvider(Arc<Mutex<MockL1>>);
+
+    impl MockFeeProvider {
+        /// Sets the head to `head`, with `fee` for all blocks starting from `from_block`.
+        fn set_chain(&self, head: u64, from_block: u64, fee: u128) {
+            let mut l1 = self.0.lock().unwrap();
+            l1.head = head;
+            l1.base_fees.resize(head as usize + 2, fee);
+            l1.base_fees[from_block as usize..].fill(fee);
+        }
+
+        fn set_failing(&self, failing: bool) {
+            self.0.lock().unwrap().failing = failing;
+        }
+
+        fn requests(&self) -> usize {
+            self.0.lock().unwrap().requests
+        }
+
+        fn last_requested_block_count(&self) -> u64 {
+            *self
+                .0
+                .lock()
+                .unwrap()
+                .requested_block_counts
+                .last()
+                .unwrap()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl EthFeeProvider for MockFeeProvider {
+        async fn block_number(&self) -> anyhow::Result<u64> {
+            let mut l1 = self.0.lock().unwrap();
+            l1.requests += 1;
+            anyhow::ensure!(!l1.failing, "provider is down");
+            Ok(l1.head)
+        }
+
+        async fn fee_history(
+            &self,
+            block_count: u64,
+            newest_block: u64,
+        ) -> anyhow::Result<FeeHistory> {
+            let mut l1 = self.0.lock().unwrap();
+            l1.requests += 1;
+            anyhow::ensure!(!l1.failing, "provider is down");
+            anyhow::ensure!(newest_block <= l1.head, "block {newest_block} is not mined");
+            l1.requested_block_counts.push(block_count);
+            let oldest_block = newest_block + 1 - block_count;
+            // Like on L1, the base fee of the block after `newest_block` is included
+            let fees = l1.base_fees[oldest_block as usize..=newest_block as usize + 1].to_vec();
+            Ok(FeeHistory {
+                base_fee_per_gas: fees.clone(),
+                base_fee_per_blob_gas: fees,
+                oldest_block,
+                ..FeeHistory::default()
+            })
+        }
+    }
+
+    fn test_config() -> GasAdjusterConfig {
+        GasAdjusterConfig {
+            pubdata_mode: PubdataMode::Blobs,
+            max_base_fee_samples: WINDOW,
+            num_samples_for_blob_base_fee_estimate: WINDOW,
+            max_priority_fee_per_gas: 0,
+            poll_period: Duration::from_secs(1),
+            pubdata_pricing_multiplier: 1.0,
+            base_fee_percentile: 0.5,
+            calldata_gas_per_byte: 16,
+            zero_byte_ratio_estimate: 0.0,
+            pubdata_composition_batches: WINDOW,
+            max_fee_data_age: Duration::from_secs(60),
+        }
+    }
+
+    async fn gas_adjuster(provider: &MockFeeProvider) -> GasAdjuster {
+        gas_adjuster_with_config(provider, test_config()).await
+    }
+
+    async fn gas_adjuster_with_config(
+        provider: &MockFeeProvider,
+        config: GasAdjusterConfig,
+    ) -> GasAdjuster {
+        let (sender, _) = watch::channel(None);
+        GasAdjuster::with_provider(Box::new(provider.clone()), config, sender)
+            .await
+            .unwrap()
+    }
+
+    fn sampled_blocks(statistics: &GasStatistics<u128>) -> Vec<u64> {
+        statistics.samples().map(|(block, _)| block).collect()
+    }
+
+    #[tokio::test]
+    async fn statistics_recover_after_l1_reorg() {
+        let provider = MockFeeProvider::default();
+        provider.set_chain(100, 0, 10);
+        let mut adjuster = gas_adjuster(&provider).await;
+        assert_eq!(
+            sampled_blocks(&adjuster.base_fee_statistics),
+            [95, 96, 97, 98, 99]
+        );
+
+        // 3-block reorg: the new fork is shorter and has higher fees starting from block 95
+        provider.set_chain(97, 95, 100);
+        adjuster.update_fees().await.unwrap();
+        assert_eq!(adjuster.base_fee_statistics.last_processed_block(), 96);
+        assert_eq!(
+            sampled_blocks(&adjuster.base_fee_statistics),
+            [92, 93, 94, 95, 96]
+        );
+        assert_eq!(adjuster.base_fee_statistics.percentile(0.95), 100);
+
+        // The new fork catches up with the old head; all samples come from the new fork
+        provider.set_chain(100, 95, 100);
+        adjuster.update_fees().await.unwrap();
+        assert_eq!(
+            sampled_blocks(&adjuster.base_fee_statistics),
+            [95, 96, 97, 98, 99]
+        );
+        assert_eq!(adjuster.base_fee_statistics.median(), 100);
+        assert_eq!(adjuster.gas_price(), 100);
+        assert_eq!(adjuster.pubdata_price(), 100);
+    }
+
+    #[tokio::test]
+    async fn statistics_catch_up_after_outage() {
+        let provider = MockFeeProvider::default();
+        provider.set_chain(100, 0, 10);
+        let mut adjuster = gas_adjuster(&provider).await;
+        assert_eq!(adjuster.gas_price(), 10);
+
+        // ~10 minutes of L1 blocks produced while the provider was unreachable
+        provider.set_chain(150, 101, 50);
+        adjuster.update_fees().await.unwrap();
+        assert_eq!(provider.last_requested_block_count(), WINDOW as u64);
+        assert_eq!(
+            sampled_blocks(&adjuster.base_fee_statistics),
+            [145, 146, 147, 148, 149]
+        );
+        assert_eq!(adjuster.gas_price(), 50);
+
+        // Regular updates resume from the new head
+        provider.set_chain(152, 151, 70);
+        adjuster.update_fees().await.unwrap();
+        assert_eq!(provider.last_requested_block_count(), 2);
+        assert_eq!(
+            sampled_blocks(&adjuster.base_fee_statistics),
+            [147, 148, 149, 150, 151]
+        );
+        assert_eq!(adjuster.base_fee_statistics.percentile(0.95), 70);
+    }
+
+    #[tokio::test]
+    async fn snapshot_reflects_samples() {
+        let provider = MockFeeProvider::default();
+        provider.set_chain(100, 0, 10);
+        provider.set_chain(100, 98, 30);
+        let mut adjuster = gas_adjuster(&provider).await;
+        let snapshot = adjuster.snapshot();
+        assert_eq!(snapshot.last_processed_block, 99);
+        assert_eq!(snapshot.pubdata_mode, PubdataMode::Blobs);
+        // Samples: 10 10 10 30 30
+        let expected_fees = FeeSamplesSnapshot {
+            sample_count: WINDOW,
+            current: 30,
+            median: 10,
+            p75: 30,
+            p95: 30,
+            estimate: 10,
+            sorted_samples: [10, 10, 10, 30, 30].into(),
+        };
+        assert_eq!(snapshot.base_fee, expected_fees);
+        assert_eq!(snapshot.base_fee.percentile(0.5), 10);
+        assert_eq!(
+            FeeSamplesSnapshot::from_samples(&[10, 10, 30, 10, 30], 0.5),
+            expected_fees
+        );
+        assert_eq!(snapshot.blob_base_fee, expected_fees);
+        assert_eq!(snapshot.gas_price, 10);
+        assert_eq!(snapshot.pubdata_price, 10);
+
+        provider.set_chain(102, 101, 30);
+        adjuster.update_fees().await.unwrap();
+        let snapshot = adjuster.snapshot();
+        assert_eq!(snapshot.last_processed_block, 101);
+        assert_eq!(snapshot.base_fee.median, 30);
+        assert_eq!(snapshot.gas_price, 30);
+        assert_eq!(snapshot.pubdata_price, 30);
+
+        let json = serde_json::to_value(&snapshot).unwrap();
+        assert_eq!(json["base_fee"]["sample_count"], WINDOW);
+        assert_eq!(json["pubdata_mode"], "Blobs");
+    }
+
+    #[tokio::test]
+    async fn run_publishes_snapshots() {
+        let provider = MockFeeProvider::default();
+        provider.set_chain(100, 0, 10);
+        let adjuster = gas_adjuster(&provider).await;
+        let mut snapshots = adjuster.subscribe();
+        assert_eq!(snapshots.borrow_and_update().gas_price, 10);
+
+        provider.set_chain(110, 101, 50);
+        let _task = tokio::spawn(adjuster.run());
+        snapshots.changed().await.unwrap();
+        let snapshot = snapshots.borrow_and_update().clone();
+        assert_eq!(snapshot.last_processed_block, 109);
+        assert_eq!(snapshot.base_fee.sample_count, WINDOW);
+        assert_eq!(snapshot.gas_price, 50);
+    }
+
+    #[tokio::test]
+    async fn calldata_price_follows_pubdata_composition() {
+        let provider = MockFeeProvider::default();
+        provider.set_chain(100, 0, 10);
+        let config = GasAdjusterConfig {
+            pubdata_mode: PubdataMode::Calldata,
+            zero_byte_ratio_estimate: 0.5,
+            ..test_config()
+        };
+        let adjuster = gas_adjuster_with_config(&provider, config).await;
+        // No observations yet: 16 gas for non-zero bytes, 4 gas for zero ones, half of each
+        assert_eq!(adjuster.calldata_gas_per_byte(), 10.0);
+        assert_eq!(adjuster.pubdata_price(), 100);
+
+        let composition = adjuster.pubdata_composition();
+        for _ in 0..WINDOW {
+            composition.record_pubdata_composition(0, 100);
+        }
+        assert_eq!(adjuster.pubdata_price(), 160);
+
+        // The price goes down as batches with 25% of zero bytes replace the older ones
+        let mut prices = vec![];
+        for _ in 0..WINDOW {
+            composition.record_pubdata_composition(25, 75);
+            prices.push(adjuster.pubdata_price());
+        }
+        assert!(prices.is_sorted_by(|a, b| a > b), "{prices:?}");
+        assert_eq!(adjuster.calldata_gas_per_byte(), 13.0);
+        assert_eq!(adjuster.pubdata_price(), 130);
+        // Further batches with the same composition don't change the price
+        composition.record_pubdata_composition(50, 150);
+        assert_eq!(adjuster.pubdata_price(), 130);
+    }
+
+    #[tokio::test]
+    async fn calldata_price_respects_configured_floor() {
+        let provider = MockFeeProvider::default();
+        provider.set_chain(100, 0, 10);
+        let config = GasAdjusterConfig {
+            pubdata_mode: PubdataMode::Calldata,
+            calldata_gas_per_byte: 40,
+            zero_byte_ratio_estimate: 0.5,
+            ..test_config()
+        };
+        let adjuster = gas_adjuster_with_config(&provider, config).await;
+        assert_eq!(adjuster.calldata_gas_per_byte(), 25.0);
+
+        // Mostly zero pubdata would cost 13 gas per byte, but the estimate bounds it from below
+        let composition = adjuster.pubdata_composition();
+        for _ in 0..WINDOW {
+            composition.record_pubdata_composition(90, 10);
+        }
+        assert_eq!(adjuster.calldata_gas_per_byte(), 25.0);
+        assert_eq!(adjuster.pubdata_price(), 250);
+
+        for _ in 0..WINDOW {
+            composition.record_pubdata_composition(0, 100);
+        }
+        assert_eq!(adjuster.calldata_gas_per_byte(), 40.0);
+    }
+
+    #[tokio::test]
+    async fn fee_providers_fail_over() {
+        let primary = MockFeeProvider::default();
+        let secondary = MockFeeProvider::default();
+        primary.set_chain(100, 0, 10);
+        secondary.set_chain(100, 0, 10);
+        let fallback =
+            FallbackFeeProvider::new(vec![Box::new(primary.clone()), Box::new(secondary.clone())]);
+        let (sender, _) = watch::channel(None);
+        let mut adjuster = GasAdjuster::with_provider(Box::new(fallback), test_config(), sender)
+            .await
+            .unwrap();
+        assert_eq!(secondary.requests(), 0);
+
+        // The primary provider goes down mid-run; fees are fetched from the secondary one
+        primary.set_failing(true);
+        primary.set_chain(110, 101, 70);
+        secondary.set_chain(110, 101, 50);
+        adjuster.update_fees().await.unwrap();
+        assert_eq!(adjuster.gas_price(), 50);
+        assert!(!adjuster.is_stale());
+
+        // The secondary provider is tried first from now on, even after the primary one recovers
+        primary.set_failing(false);
+        let primary_requests = primary.requests();
+        secondary.set_chain(112, 111, 50);
+        adjuster.update_fees().await.unwrap();
+        assert_eq!(primary.requests(), primary_requests);
+        assert_eq!(adjuster.base_fee_statistics.last_processed_block(), 111);
+
+        // Both providers are down
+        secondary.set_failing(true);
+        primary.set_failing(true);
+        adjuster.update_fees().await.unwrap_err();
+        assert_eq!(adjuster.gas_price(), 50);
+    }
+
+    #[tokio::test]
+    async fn fallback_provider_remembers_last_succeeded() {
+        let providers: Vec<_> = (0..3).map(|_| MockFeeProvider::default()).collect();
+        for (i, provider) in providers.iter().enumerate() {
+            provider.set_chain(10 + i as u64, 0, 1);
+        }
+        let fallback = FallbackFeeProvider::new(
+            providers
+                .iter()
+                .map(|provider| Box::new(provider.clone()) as Box<dyn EthFeeProvider>)
+                .collect(),
+        );
+        assert_eq!(fallback.block_number().await.unwrap(), 10);
+        assert_eq!(fallback.last_succeeded(), 0);
+
+        providers[0].set_failing(true);
+        providers[1].set_failing(true);
+        assert_eq!(fallback.block_number().await.unwrap(), 12);
+        assert_eq!(fallback.last_succeeded(), 2);
+
+        // Wraps around to the first provider
+        providers[0].set_failing(false);
+        providers[2].set_failing(true);
+        assert_eq!(fallback.block_number().await.unwrap(), 10);
+        assert_eq!(fallback.last_succeeded(), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fee_data_becomes_stale() {
+        let provider = MockFeeProvider::default();
+        provider.set_chain(100, 0, 10);
+        let mut adjuster = gas_adjuster(&provider).await;
+        tokio::time::advance(Duration::from_secs(45)).await;
+        assert!(!adjuster.is_stale());
+
+        provider.set_failing(true);
+        adjuster.update_fees().await.unwrap_err();
+        tokio::time::advance(Duration::from_secs(30)).await;
+        adjuster.update_fees().await.unwrap_err();
+        assert!(adjuster.is_stale());
+        // Prices are still served
+        assert_eq!(adjuster.gas_price(), 10);
+
+        provider.set_failing(false);
+        adjuster.update_fees().await.unwrap();
+        assert!(!adjuster.is_stale());
+    }
+}
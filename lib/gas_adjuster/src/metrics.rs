@@ -1,6 +1,6 @@
 //! Gas adjuster metrics.
 
-use vise::{Gauge, Metrics};
+use vise::{Counter, Gauge, LabeledFamily, Metrics};
 
 #[derive(Debug, Metrics)]
 #[metrics(prefix = "server_gas_adjuster")]
@@ -9,6 +9,23 @@ pub(super) struct GasAdjusterMetrics {
     pub current_blob_base_fee: Gauge<u64>,
     pub median_base_fee_per_gas: Gauge<u64>,
     pub median_blob_base_fee: Gauge<u64>,
+    /// Percentiles of the sampled base fees.
+    #[metrics(labels = ["percentile"])]
+    pub base_fee_per_gas_percentile: LabeledFamily<&'static str, Gauge<u64>>,
+    /// Percentiles of the sampled blob base fees.
+    #[metrics(labels = ["percentile"])]
+    pub blob_base_fee_percentile: LabeledFamily<&'static str, Gauge<u64>>,
+    /// Requests to L1 fee providers by provider index and result (`success` or `failure`).
+    #[metrics(labels = ["provider", "result"])]
+    pub fee_provider_requests: LabeledFamily<(String, &'static str), Counter, 2>,
+    /// Index of the L1 fee provider that last returned data successfully.
+    pub active_fee_provider: Gauge<usize>,
+    /// Share of zero bytes in pubdata of the last committed batches.
+    pub pubdata_zero_byte_ratio: Gauge<f64>,
+    /// Last fetched ETH -> base token conversion ratio.
+    pub base_token_ratio: Gauge<f64>,
+    /// Failed attempts to fetch the base token conversion ratio.
+    pub base_token_rate_update_failures: Counter,
 }
 
 #[vise::register]
@@ -0,0 +1,116 @@
+//! Sources of L1 fee data.
+
+use crate::metrics::METRICS;
+use alloy::providers::{DynProvider, Provider};
+use alloy::rpc::types::FeeHistory;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Source of L1 fee data for [`GasAdjuster`](crate::GasAdjuster).
+#[async_trait::async_trait]
+pub trait EthFeeProvider: fmt::Debug + Send + Sync {
+    /// Returns the latest L1 block number.
+    async fn block_number(&self) -> anyhow::Result<u64>;
+
+    /// Returns `eth_feeHistory` for `block_count` blocks ending at `newest_block`.
+    async fn fee_history(&self, block_count: u64, newest_block: u64) -> anyhow::Result<FeeHistory>;
+}
+
+#[async_trait::async_trait]
+impl EthFeeProvider for DynProvider {
+    async fn block_number(&self) -> anyhow::Result<u64> {
+        Ok(self.get_block_number().await?)
+    }
+
+    async fn fee_history(&self, block_count: u64, newest_block: u64) -> anyhow::Result<FeeHistory> {
+        Ok(self
+            .get_fee_history(block_count, newest_block.into(), &[])
+            .await?)
+    }
+}
+
+/// [`EthFeeProvider`] failing over between several providers (e.g., L1 RPC endpoints).
+///
+/// Each call tries the providers in order, starting from the one that succeeded last, so that
+/// a failing provider only costs a single failed request until it's tried again.
+#[derive(Debug)]
+pub struct FallbackFeeProvider {
+    providers: Vec<Box<dyn EthFeeProvider>>,
+    last_succeeded: AtomicUsize,
+}
+
+impl FallbackFeeProvider {
+    /// Creates a provider from an ordered list of providers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `providers` is empty.
+    pub fn new(providers: Vec<Box<dyn EthFeeProvider>>) -> Self {
+        assert!(!providers.is_empty(), "no L1 fee providers");
+        Self {
+            providers,
+            last_succeeded: AtomicUsize::new(0),
+        }
+    }
+
+    /// Index of the provider that last returned data successfully.
+    pub fn last_succeeded(&self) -> usize {
+        self.last_succeeded.load(Ordering::Relaxed)
+    }
+
+    /// Indices of providers in the order they should be tried.
+    fn order(&self) -> impl Iterator<Item = usize> + use<> {
+        let first = self.last_succeeded();
+        let len = self.providers.len();
+        (0..len).map(move |offset| (first + offset) % len)
+    }
+
+    fn on_result<T>(&self, index: usize, method: &'static str, result: &anyhow::Result<T>) {
+        let label = index.to_string();
+        match result {
+            Ok(_) => {
+                METRICS.fee_provider_requests[&(label, "success")].inc();
+                if self.last_succeeded.swap(index, Ordering::Relaxed) != index {
+                    tracing::info!(provider = index, "switched to another L1 fee provider");
+                }
+                METRICS.active_fee_provider.set(index);
+            }
+            Err(err) => {
+                METRICS.fee_provider_requests[&(label, "failure")].inc();
+                tracing::warn!(provider = index, method, "L1 fee provider failed: {err:#}");
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl EthFeeProvider for FallbackFeeProvider {
+    async fn block_number(&self) -> anyhow::Result<u64> {
+        let mut last_error = None;
+        for index in self.order() {
+            let result = self.providers[index].block_number().await;
+            self.on_result(index, "block_number", &result);
+            match result {
+                Ok(block_number) => return Ok(block_number),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        // `providers` are not empty, so there's at least one error
+        Err(last_error.unwrap().context("all L1 fee providers failed"))
+    }
+
+    async fn fee_history(&self, block_count: u64, newest_block: u64) -> anyhow::Result<FeeHistory> {
+        let mut last_error = None;
+        for index in self.order() {
+            let result = self.providers[index]
+                .fee_history(block_count, newest_block)
+                .await;
+            self.on_result(index, "fee_history", &result);
+            match result {
+                Ok(fee_history) => return Ok(fee_history),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.unwrap().context("all L1 fee providers failed"))
+    }
+}
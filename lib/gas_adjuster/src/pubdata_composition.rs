@@ -0,0 +1,98 @@
+//! Tracking of zero / non-zero bytes in pubdata published via calldata.
+
+use crate::metrics::METRICS;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Zero calldata bytes cost a quarter of non-zero ones, both with EIP-2028 pricing (4 / 16 gas)
+/// and with the EIP-7623 floor (10 / 40 gas).
+const ZERO_BYTE_COST_DIVISOR: f64 = 4.0;
+
+/// Numbers of zero and non-zero pubdata bytes in the last committed batches.
+///
+/// Cloned handles share the same state: the L1 sender records committed batches and
+/// [`GasAdjuster`](crate::GasAdjuster) uses them to price pubdata in `Calldata` mode.
+#[derive(Debug, Clone)]
+pub struct PubdataComposition {
+    max_batches: usize,
+    batches: Arc<Mutex<VecDeque<(u64, u64)>>>,
+}
+
+impl PubdataComposition {
+    pub(crate) fn new(max_batches: usize) -> Self {
+        Self {
+            max_batches,
+            batches: Arc::new(Mutex::new(VecDeque::with_capacity(max_batches))),
+        }
+    }
+
+    /// Records the composition of pubdata in a committed batch. Only the last `max_batches`
+    /// batches are retained.
+    pub fn record_pubdata_composition(&self, zero_bytes: u64, nonzero_bytes: u64) {
+        if self.max_batches == 0 {
+            return;
+        }
+        let mut batches = self.batches.lock().unwrap();
+        if batches.len() == self.max_batches {
+            batches.pop_front();
+        }
+        batches.push_back((zero_bytes, nonzero_bytes));
+        if let Some(ratio) = zero_byte_ratio(&batches) {
+            METRICS.pubdata_zero_byte_ratio.set(ratio);
+        }
+    }
+
+    /// Share (in `[0, 1]`) of zero bytes in the retained batches; `None` if they have no pubdata.
+    pub fn zero_byte_ratio(&self) -> Option<f64> {
+        zero_byte_ratio(&self.batches.lock().unwrap())
+    }
+}
+
+fn zero_byte_ratio(batches: &VecDeque<(u64, u64)>) -> Option<f64> {
+    let (zero_bytes, nonzero_bytes) = batches.iter().fold(
+        (0_u64, 0_u64),
+        |(zero, nonzero), (batch_zero, batch_nonzero)| (zero + batch_zero, nonzero + batch_nonzero),
+    );
+    let total_bytes = zero_bytes + nonzero_bytes;
+    (total_bytes > 0).then(|| zero_bytes as f64 / total_bytes as f64)
+}
+
+/// Counts zero and non-zero bytes in `data`.
+pub fn count_zero_bytes(data: &[u8]) -> (u64, u64) {
+    let zero_bytes = data.iter().filter(|&&byte| byte == 0).count() as u64;
+    (zero_bytes, data.len() as u64 - zero_bytes)
+}
+
+/// Average L1 gas per pubdata byte given the cost of a non-zero byte and the share of zero bytes.
+pub(crate) fn gas_per_byte(nonzero_byte_gas: u128, zero_byte_ratio: f64) -> f64 {
+    let ratio = zero_byte_ratio.clamp(0.0, 1.0);
+    nonzero_byte_gas as f64 * (1.0 - ratio + ratio / ZERO_BYTE_COST_DIVISOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ratio_covers_last_batches() {
+        let composition = PubdataComposition::new(3);
+        assert_eq!(composition.zero_byte_ratio(), None);
+        composition.record_pubdata_composition(0, 0);
+        assert_eq!(composition.zero_byte_ratio(), None);
+
+        composition.record_pubdata_composition(30, 10);
+        composition.record_pubdata_composition(0, 40);
+        assert_eq!(composition.zero_byte_ratio(), Some(0.375));
+        // The empty batch is evicted first
+        composition.record_pubdata_composition(40, 0);
+        assert_eq!(composition.zero_byte_ratio(), Some(70.0 / 120.0));
+        composition.record_pubdata_composition(40, 0);
+        composition.record_pubdata_composition(40, 0);
+        assert_eq!(composition.zero_byte_ratio(), Some(1.0));
+
+        assert_eq!(count_zero_bytes(&[0, 1, 0, 0, 255]), (3, 2));
+        assert_eq!(gas_per_byte(16, 0.0), 16.0);
+        assert_eq!(gas_per_byte(16, 0.5), 10.0);
+        assert_eq!(gas_per_byte(16, 1.0), 4.0);
+    }
+}
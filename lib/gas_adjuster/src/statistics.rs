@@ -1,81 +1,225 @@
 use std::collections::VecDeque;
 
 /// Helper structure responsible for collecting the data about recent transactions,
-/// calculating the median base fee.
+/// calculating percentiles (e.g. the median) of the base fee.
 #[derive(Debug, Clone, Default)]
 pub(crate) struct GasStatistics<T> {
-    samples: VecDeque<T>,
-    median_cached: T,
+    /// `(L1 block number, sample)` pairs in ascending block order.
+    samples: VecDeque<(u64, T)>,
+    /// Retained samples in ascending order; rebuilt when samples are added, so that percentiles
+    /// are looked up without sorting on each request.
+    sorted_samples_cached: Vec<T>,
     max_samples: usize,
     last_processed_block: u64,
 }
 
 impl<T: Ord + Copy + Default> GasStatistics<T> {
-    pub fn new(max_samples: usize, block: u64, fee_history: impl IntoIterator<Item = T>) -> Self {
+    pub fn new(
+        max_samples: usize,
+        block: u64,
+        fee_history: impl IntoIterator<Item = (u64, T)>,
+    ) -> Self {
         let mut statistics = Self {
             max_samples,
             samples: VecDeque::with_capacity(max_samples),
-            median_cached: T::default(),
+            sorted_samples_cached: Vec::with_capacity(max_samples),
             last_processed_block: 0,
         };
+        statistics.reset(block, fee_history);
+        statistics
+    }
 
-        statistics.add_samples(fee_history);
-
-        Self {
-            last_processed_block: block,
-            ..statistics
-        }
+    /// Replaces all samples with `fee_history` ending at `block`, e.g. after an L1 reorg made
+    /// the retained samples stale.
+    pub fn reset(&mut self, block: u64, fee_history: impl IntoIterator<Item = (u64, T)>) {
+        self.samples.clear();
+        self.add_samples(fee_history);
+        self.last_processed_block = block;
     }
 
     pub fn median(&self) -> T {
-        self.median_cached
+        self.percentile(0.5)
+    }
+
+    /// Returns the `p`-th percentile (`p` in `[0, 1]`) of the retained samples using the
+    /// nearest-rank method, so that `percentile(0.5)` is the median. Returns `T::default()` if
+    /// there are no samples.
+    pub fn percentile(&self, p: f64) -> T {
+        nearest_rank(&self.sorted_samples_cached, p)
     }
 
-    pub fn add_samples(&mut self, fees: impl IntoIterator<Item = T>) {
-        let old_len = self.samples.len();
-        self.samples.extend(fees);
-        let processed_blocks = self.samples.len() - old_len;
-        self.last_processed_block += processed_blocks as u64;
+    /// Retained samples in ascending order.
+    pub fn sorted_samples(&self) -> &[T] {
+        &self.sorted_samples_cached
+    }
+
+    /// Sample for the latest retained L1 block.
+    pub fn latest(&self) -> Option<T> {
+        self.samples.back().map(|&(_, fee)| fee)
+    }
+
+    /// Adds `(L1 block number, sample)` pairs. Samples for blocks that are not newer than
+    /// the last retained one are discarded, so that overlapping history isn't counted twice.
+    pub fn add_samples(&mut self, fees: impl IntoIterator<Item = (u64, T)>) {
+        for (block, fee) in fees {
+            if self.samples.back().is_some_and(|&(last, _)| block <= last) {
+                continue;
+            }
+            self.samples.push_back((block, fee));
+            self.last_processed_block = self.last_processed_block.max(block);
+        }
 
         let extra = self.samples.len().saturating_sub(self.max_samples);
         self.samples.drain(..extra);
 
-        let mut samples: Vec<_> = self.samples.iter().cloned().collect();
+        self.sorted_samples_cached.clear();
+        self.sorted_samples_cached
+            .extend(self.samples.iter().map(|&(_, fee)| fee));
+        self.sorted_samples_cached.sort_unstable();
+    }
 
-        if !self.samples.is_empty() {
-            let (_, &mut median, _) = samples.select_nth_unstable(self.samples.len() / 2);
-            self.median_cached = median;
-        }
+    /// Number of retained samples.
+    pub fn sample_count(&self) -> usize {
+        self.samples.len()
     }
 
     pub fn last_processed_block(&self) -> u64 {
         self.last_processed_block
     }
+
+    /// Returns retained `(L1 block number, sample)` pairs in ascending block order.
+    #[cfg(test)]
+    pub fn samples(&self) -> impl Iterator<Item = (u64, T)> + '_ {
+        self.samples.iter().copied()
+    }
+}
+
+/// Returns the `p`-th percentile of `sorted` samples using the nearest-rank method, or
+/// `T::default()` if there are no samples.
+pub(crate) fn nearest_rank<T: Copy + Default>(sorted: &[T], p: f64) -> T {
+    let len = sorted.len();
+    if len == 0 {
+        return T::default();
+    }
+    let index = ((len as f64 * p.clamp(0.0, 1.0)) as usize).min(len - 1);
+    sorted[index]
 }
 
 #[cfg(test)]
 mod tests {
     use super::GasStatistics;
-    use std::collections::VecDeque;
+
+    /// Numbers samples with consecutive L1 blocks starting from `first_block`.
+    fn numbered<T>(
+        first_block: u64,
+        fees: impl IntoIterator<Item = T>,
+    ) -> impl Iterator<Item = (u64, T)> {
+        (first_block..).zip(fees)
+    }
+
+    fn samples<T: Ord + Copy + Default>(stats: &GasStatistics<T>) -> Vec<T> {
+        stats.samples().map(|(_, fee)| fee).collect()
+    }
 
     /// Check that we compute the median correctly
     #[test]
     fn median() {
         // sorted: 4 4 6 7 8
-        assert_eq!(GasStatistics::new(5, 5, [6, 4, 7, 8, 4]).median(), 6);
+        assert_eq!(
+            GasStatistics::new(5, 5, numbered(1, [6, 4, 7, 8, 4])).median(),
+            6
+        );
         // sorted: 4 4 8 10
-        assert_eq!(GasStatistics::new(4, 4, [8, 4, 4, 10]).median(), 8);
+        assert_eq!(
+            GasStatistics::new(4, 4, numbered(1, [8, 4, 4, 10])).median(),
+            8
+        );
     }
 
     /// Check that we properly manage the block base fee queue
     #[test]
     fn samples_queue() {
-        let mut stats = GasStatistics::new(5, 5, [6, 4, 7, 8, 4, 5]);
+        let mut stats = GasStatistics::new(5, 6, numbered(1, [6, 4, 7, 8, 4, 5]));
+
+        assert_eq!(samples(&stats), [4, 7, 8, 4, 5]);
+
+        stats.add_samples(numbered(7, [18, 18, 18]));
+
+        assert_eq!(samples(&stats), [4, 5, 18, 18, 18]);
+        assert_eq!(stats.last_processed_block(), 9);
+    }
+
+    #[test]
+    fn percentiles_of_monotonic_ramp() {
+        // Base fee rising by 10 per block, e.g. during an L1 congestion ramp
+        let stats = GasStatistics::new(20, 20, numbered(1, (1..=20).map(|i| i * 10)));
+        assert_eq!(stats.percentile(0.0), 10);
+        assert_eq!(stats.percentile(0.5), 110);
+        assert_eq!(stats.percentile(0.5), stats.median());
+        assert_eq!(stats.percentile(0.75), 160);
+        assert_eq!(stats.percentile(0.95), 200);
+        assert_eq!(stats.percentile(1.0), 200);
+        // Out-of-range percentiles are clamped
+        assert_eq!(stats.percentile(2.0), 200);
+        assert_eq!(stats.percentile(-1.0), 10);
+    }
+
+    #[test]
+    fn percentiles_with_spike() {
+        // A single spike moves only the highest percentiles
+        let mut fees = [100; 20];
+        fees[7] = 10_000;
+        let stats = GasStatistics::new(20, 20, numbered(1, fees));
+        assert_eq!(stats.median(), 100);
+        assert_eq!(stats.percentile(0.75), 100);
+        assert_eq!(stats.percentile(0.9), 100);
+        assert_eq!(stats.percentile(0.95), 10_000);
+    }
+
+    #[test]
+    fn percentiles_of_flat_history() {
+        let stats = GasStatistics::new(10, 10, numbered(1, [42; 10]));
+        for p in [0.0, 0.5, 0.75, 0.95, 1.0] {
+            assert_eq!(stats.percentile(p), 42);
+        }
+        let empty = GasStatistics::<u128>::new(10, 0, []);
+        assert_eq!(empty.percentile(0.95), 0);
+    }
 
-        assert_eq!(stats.samples, VecDeque::from([4, 7, 8, 4, 5]));
+    #[test]
+    fn percentiles_are_updated_with_samples() {
+        let mut stats = GasStatistics::new(4, 4, numbered(1, [1, 2, 3, 4]));
+        assert_eq!(stats.percentile(0.75), 4);
+        assert_eq!(stats.median(), 3);
+
+        // Evicts 1 and 2
+        stats.add_samples(numbered(5, [10, 20]));
+        assert_eq!(stats.percentile(0.0), 3);
+        assert_eq!(stats.median(), 10);
+        assert_eq!(stats.percentile(0.75), 20);
+
+        stats.add_samples(numbered(7, [0, 0, 0, 0]));
+        assert_eq!(stats.percentile(1.0), 0);
+    }
 
-        stats.add_samples([18, 18, 18]);
+    #[test]
+    fn overlapping_samples_are_discarded() {
+        let mut stats = GasStatistics::new(5, 3, numbered(1, [1, 2, 3]));
+        // Blocks 2 and 3 are already sampled
+        stats.add_samples(numbered(2, [20, 30, 40, 50]));
+        assert_eq!(samples(&stats), [1, 2, 3, 40, 50]);
+        assert_eq!(stats.last_processed_block(), 5);
+
+        stats.add_samples(numbered(4, [0]));
+        assert_eq!(samples(&stats), [1, 2, 3, 40, 50]);
+    }
 
-        assert_eq!(stats.samples, VecDeque::from([4, 5, 18, 18, 18]));
+    #[test]
+    fn reset_replaces_samples() {
+        let mut stats = GasStatistics::new(5, 5, numbered(1, [1, 2, 3, 4, 5]));
+        stats.reset(3, numbered(1, [10, 20, 30]));
+        assert_eq!(samples(&stats), [10, 20, 30]);
+        assert_eq!(stats.last_processed_block(), 3);
+        assert_eq!(stats.median(), 20);
     }
 }
@@ -0,0 +1,338 @@
+use crate::{
+    Genesis, GenesisHeaderVersion, GenesisInput, GenesisState, flat_storage_key, genesis_state,
+};
+use alloy::primitives::{Address, B256, Bytes, U256};
+use alloy::providers::DynProvider;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use zksync_os_contract_interface::ZkChain;
+
+/// Builds genesis programmatically, e.g. for tests and dev tooling, instead of editing
+/// `genesis.json` by hand.
+///
+/// With the `merkle-tree` feature, the genesis root is computed from the built state; otherwise,
+/// it is set to zero.
+#[derive(Debug, Clone)]
+pub struct GenesisBuilder {
+    contracts: BTreeMap<Address, Bytes>,
+    balances: BTreeMap<Address, U256>,
+    nonces: BTreeMap<Address, u64>,
+    storage: BTreeMap<B256, B256>,
+    chain_id: u64,
+    execution_version: u32,
+    header_version: GenesisHeaderVersion,
+}
+
+impl Default for GenesisBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GenesisBuilder {
+    /// Creates a builder for an empty genesis of chain 270 with execution version 4.
+    pub fn new() -> Self {
+        Self {
+            contracts: BTreeMap::new(),
+            balances: BTreeMap::new(),
+            nonces: BTreeMap::new(),
+            storage: BTreeMap::new(),
+            chain_id: 270,
+            execution_version: 4,
+            header_version: GenesisHeaderVersion::Legacy,
+        }
+    }
+
+    /// Deploys `bytecode` (deployed bytecode, without constructor) at `address`.
+    pub fn deploy_contract(mut self, address: Address, bytecode: Bytes) -> Self {
+        self.contracts.insert(address, bytecode);
+        self
+    }
+
+    /// Sets the base token balance of `address`.
+    pub fn fund(mut self, address: Address, amount: U256) -> Self {
+        self.balances.insert(address, amount);
+        self
+    }
+
+    /// Sets the nonce of `address`; contracts deployed with [`Self::deploy_contract()`] have
+    /// a nonce of 1 by default.
+    pub fn set_nonce(mut self, address: Address, nonce: u64) -> Self {
+        self.nonces.insert(address, nonce);
+        self
+    }
+
+    /// Sets `slot` in the storage of `address` to `value`.
+    pub fn set_storage(mut self, address: Address, slot: B256, value: B256) -> Self {
+        self.storage.insert(flat_storage_key(address, slot), value);
+        self
+    }
+
+    pub fn chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
+    pub fn execution_version(mut self, execution_version: u32) -> Self {
+        self.execution_version = execution_version;
+        self
+    }
+
+    pub fn header_version(mut self, header_version: GenesisHeaderVersion) -> Self {
+        self.header_version = header_version;
+        self
+    }
+
+    /// Builds the genesis input, with the genesis root filled in if the `merkle-tree` feature
+    /// is enabled.
+    pub fn build_input(&self) -> anyhow::Result<GenesisInput> {
+        let genesis_root = self.build_state()?.expected_genesis_root;
+        Ok(GenesisInput {
+            genesis_root,
+            ..self.input_without_root()
+        })
+    }
+
+    /// Builds the genesis state the same way the node does from the built input.
+    pub fn build_state(&self) -> anyhow::Result<GenesisState> {
+        let state = genesis_state(self.input_without_root(), self.chain_id)?;
+        #[cfg(feature = "merkle-tree")]
+        let state = GenesisState {
+            expected_genesis_root: state.compute_root()?,
+            ..state
+        };
+        Ok(state)
+    }
+
+    /// Serializes the built input in the `genesis.json` format, e.g. to be used as a fixture.
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(&self.build_input()?)?)
+    }
+
+    /// Builds [`Genesis`] for the chain at `zk_chain`.
+    pub fn into_genesis(self, zk_chain: ZkChain<DynProvider>) -> anyhow::Result<Genesis> {
+        let input = self.build_input()?;
+        Ok(Genesis::new(Arc::new(input), zk_chain, self.chain_id, None))
+    }
+
+    fn input_without_root(&self) -> GenesisInput {
+        GenesisInput {
+            initial_contracts: self.contracts.clone().into_iter().collect(),
+            initial_balances: self.balances.clone().into_iter().collect(),
+            initial_nonces: self.nonces.clone().into_iter().collect(),
+            additional_storage: self.storage.clone().into_iter().collect(),
+            execution_version: self.execution_version,
+            genesis_root: B256::ZERO,
+            header_version: self.header_version,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use zk_os_basic_system::system_implementation::flat_storage_model::{
+        ACCOUNT_PROPERTIES_STORAGE_ADDRESS, AccountProperties,
+    };
+
+    fn builder() -> GenesisBuilder {
+        GenesisBuilder::new()
+            .execution_version(3)
+            .deploy_contract(
+                Address::with_last_byte(1),
+                Bytes::from_static(&[0x60, 0x01, 0x60, 0x00, 0x55]),
+            )
+            .set_storage(
+                Address::with_last_byte(1),
+                B256::ZERO,
+                B256::with_last_byte(1),
+            )
+            .fund(Address::with_last_byte(1), U256::from(5))
+            .fund(Address::with_last_byte(2), U256::from(1_000_000))
+    }
+
+    #[test]
+    fn builder_matches_handwritten_json() {
+        let json = r#"{
+            "initial_contracts": [
+                ["0x0000000000000000000000000000000000000001", "0x6001600055"]
+            ],
+            "initial_balances": [
+                ["0x0000000000000000000000000000000000000001", "0x5"],
+                ["0x0000000000000000000000000000000000000002", "0xf4240"]
+            ],
+            "additional_storage": [
+                ["0x700127744470217ec1d2fe11c8913ffbbb3a1d6f1a30aaa72dfbbc7b66281d59", "0x0000000000000000000000000000000000000000000000000000000000000001"]
+            ],
+            "execution_version": 3,
+            "genesis_root": "0x0000000000000000000000000000000000000000000000000000000000000000"
+        }"#;
+        let handwritten: GenesisInput = serde_json::from_str(json).unwrap();
+
+        let built = GenesisInput {
+            genesis_root: B256::ZERO,
+            ..builder().build_input().unwrap()
+        };
+        assert_eq!(built, handwritten);
+
+        let built_state = builder().build_state().unwrap();
+        let handwritten_state = genesis_state(handwritten, 270).unwrap();
+        assert_eq!(built_state.storage_logs, handwritten_state.storage_logs);
+        assert_eq!(built_state.preimages, handwritten_state.preimages);
+        // The contract and the EOA have account properties, plus the storage slot
+        assert_eq!(built_state.storage_logs.len(), 3);
+        assert_eq!(built_state.context.execution_version, 3);
+    }
+
+    #[test]
+    fn json_fixture_roundtrip() {
+        let builder = builder();
+        let json = builder.to_json().unwrap();
+        let input: GenesisInput = serde_json::from_str(&json).unwrap();
+        assert_eq!(input, builder.build_input().unwrap());
+    }
+
+    /// Decodes account properties of `address` from the storage logs and preimages of `state`.
+    fn account_properties(state: &GenesisState, address: Address) -> AccountProperties {
+        let key = crate::account_properties_key(address);
+        let (_, hash) = state
+            .storage_logs
+            .iter()
+            .find(|(log_key, _)| *log_key == key)
+            .expect("no account properties");
+        let (_, preimage) = state
+            .preimages
+            .iter()
+            .find(|(preimage_hash, _)| preimage_hash == hash)
+            .expect("no account properties preimage");
+        AccountProperties::decode(&preimage.clone().try_into().unwrap())
+    }
+
+    #[test]
+    fn balances_and_nonces_serde_roundtrip() {
+        let input = GenesisBuilder::new()
+            .fund(Address::with_last_byte(2), U256::from(1_000))
+            .set_nonce(Address::with_last_byte(3), 7)
+            .build_input()
+            .unwrap();
+        let json = serde_json::to_value(&input).unwrap();
+        assert_eq!(
+            json["initial_balances"],
+            serde_json::json!([["0x0000000000000000000000000000000000000002", "0x3e8"]])
+        );
+        assert_eq!(
+            json["initial_nonces"],
+            serde_json::json!([["0x0000000000000000000000000000000000000003", 7]])
+        );
+        let parsed: GenesisInput = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, input);
+
+        // Both fields are optional and omitted if empty
+        let input = GenesisBuilder::new().build_input().unwrap();
+        let json = serde_json::to_value(&input).unwrap();
+        assert!(json.get("initial_balances").is_none(), "{json}");
+        assert!(json.get("initial_nonces").is_none(), "{json}");
+        assert_eq!(serde_json::from_value::<GenesisInput>(json).unwrap(), input);
+    }
+
+    #[test]
+    fn funded_eoas_and_contracts() {
+        let contract = Address::with_last_byte(1);
+        let eoa = Address::with_last_byte(2);
+        let eoa_with_nonce = Address::with_last_byte(3);
+        let state = GenesisBuilder::new()
+            .deploy_contract(contract, Bytes::from_static(&[0x60, 0x01]))
+            .fund(contract, U256::from(5))
+            .fund(eoa, U256::from(1_000_000))
+            .fund(eoa_with_nonce, U256::from(1))
+            .set_nonce(eoa_with_nonce, 4)
+            .build_state()
+            .unwrap();
+        assert_eq!(state.storage_logs.len(), 3);
+        // Bytecode of the contract + account properties of all accounts
+        assert_eq!(state.preimages.len(), 4);
+
+        // The balance is merged into the properties of the deployed contract
+        let properties = account_properties(&state, contract);
+        assert_eq!(properties.balance, U256::from(5));
+        assert_eq!(properties.nonce, 1);
+        assert_eq!(
+            B256::from(properties.bytecode_hash.as_u8_array()),
+            state.report.contracts[0].bytecode_hash
+        );
+
+        let properties = account_properties(&state, eoa);
+        assert_eq!(properties.balance, U256::from(1_000_000));
+        assert_eq!(properties.nonce, 0);
+        assert_eq!(
+            properties.bytecode_hash,
+            AccountProperties::default().bytecode_hash
+        );
+        let properties = account_properties(&state, eoa_with_nonce);
+        assert_eq!(properties.balance, U256::from(1));
+        assert_eq!(properties.nonce, 4);
+        // EOAs are not reported as contracts
+        assert_eq!(state.report.contracts.len(), 1);
+    }
+
+    #[test]
+    fn duplicate_balances_are_rejected() {
+        let mut input = GenesisBuilder::new()
+            .fund(Address::with_last_byte(2), U256::from(1))
+            .build_input()
+            .unwrap();
+        input
+            .initial_balances
+            .push((Address::with_last_byte(2), U256::from(2)));
+        let err = genesis_state(input, 270).unwrap_err();
+        assert!(err.to_string().contains("`initial_balances`"), "{err}");
+    }
+
+    #[test]
+    fn storage_conflicting_with_account_properties_is_rejected() {
+        // Account properties live in the storage of a system address, so a storage slot set there
+        // can collide with them
+        let account_properties_storage =
+            Address::from(ACCOUNT_PROPERTIES_STORAGE_ADDRESS.to_be_bytes::<20>());
+        let err = GenesisBuilder::new()
+            .fund(Address::with_last_byte(2), U256::from(1))
+            .set_storage(
+                account_properties_storage,
+                Address::with_last_byte(2).into_word(),
+                B256::ZERO,
+            )
+            .build_state()
+            .unwrap_err();
+        assert!(err.to_string().contains("duplicate storage key"), "{err}");
+    }
+
+    #[cfg(feature = "merkle-tree")]
+    #[test]
+    fn default_genesis_json_is_reproduced() {
+        // Rebuilds the default genesis used by the local setup; if contracts in `genesis.json` are
+        // updated, the expected `genesis_root` is printed on failure.
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../genesis/genesis.json");
+        let expected = GenesisInput::load_from_file(Path::new(path)).unwrap();
+        assert!(expected.additional_storage.is_empty());
+        assert!(expected.initial_balances.is_empty());
+
+        let builder = expected.initial_contracts.iter().fold(
+            GenesisBuilder::new().execution_version(expected.execution_version),
+            |builder, (address, bytecode)| builder.deploy_contract(*address, bytecode.clone()),
+        );
+        assert_eq!(builder.build_input().unwrap(), expected);
+    }
+
+    #[cfg(feature = "merkle-tree")]
+    #[test]
+    fn genesis_root_depends_on_state() {
+        let empty = GenesisBuilder::new().build_input().unwrap();
+        let funded = GenesisBuilder::new()
+            .fund(Address::with_last_byte(2), U256::from(1))
+            .build_input()
+            .unwrap();
+        assert_ne!(empty.genesis_root, B256::ZERO);
+        assert_ne!(empty.genesis_root, funded.genesis_root);
+    }
+}
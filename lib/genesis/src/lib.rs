@@ -1,18 +1,18 @@
 use alloy::consensus::{EMPTY_OMMER_ROOT_HASH, Header};
 use alloy::eips::eip1559::INITIAL_BASE_FEE;
-use alloy::primitives::{Address, B64, B256, Bloom, U256};
+use alloy::primitives::{Address, B64, B256, Bloom, TxHash, U256};
 use alloy::providers::{DynProvider, Provider};
-use alloy::rpc::types::Filter;
+use alloy::rpc::types::{Filter, Log};
 use alloy::sol_types::SolEvent;
 use anyhow::Context;
 use blake2::{Blake2s256, Digest};
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Debug;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::sync::OnceCell;
-use zk_os_api::helpers::{set_properties_code, set_properties_nonce};
+use zk_os_api::helpers::{set_properties_balance, set_properties_code, set_properties_nonce};
 use zk_os_basic_system::system_implementation::flat_storage_model::{
     ACCOUNT_PROPERTIES_STORAGE_ADDRESS, AccountProperties,
 };
@@ -21,17 +21,68 @@ use zksync_os_contract_interface::ZkChain;
 use zksync_os_interface::types::BlockContext;
 use zksync_os_types::L1UpgradeEnvelope;
 
+pub use self::builder::GenesisBuilder;
+pub use self::report::{GenesisContractReport, GenesisReport};
+pub use self::url_source::{UrlGenesisInputSource, sha256};
+
+mod builder;
+mod report;
+#[cfg(feature = "merkle-tree")]
+mod root;
+mod url_source;
+
+#[cfg(feature = "merkle-tree")]
+use self::root::state_tree_root;
+
+/// Gas limit of the genesis block context, and of the genesis block header since
+/// [`GenesisHeaderVersion::V1`].
+const GENESIS_GAS_LIMIT: u64 = 100_000_000;
+/// Gas limit of the genesis block header for [`GenesisHeaderVersion::Legacy`].
+const LEGACY_GENESIS_HEADER_GAS_LIMIT: u64 = 5_000;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GenesisInput {
     /// Initial contracts to deploy in genesis.
     /// Storage entries that set the contracts as deployed and preimages will be derived from this field.
     pub initial_contracts: Vec<(Address, alloy::primitives::Bytes)>,
+    /// Base token balances of accounts in genesis. Can be set for both contracts from
+    /// `initial_contracts` and EOAs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub initial_balances: Vec<(Address, U256)>,
+    /// Nonces of accounts in genesis. Accounts without a nonce set have a nonce of 0, or 1 for
+    /// contracts from `initial_contracts`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub initial_nonces: Vec<(Address, u64)>,
     /// Additional (not related to contract deployments) storage entries to add in genesis state.
     pub additional_storage: Vec<(B256, B256)>,
     /// Execution version used for genesis.
     pub execution_version: u32,
     /// The expected root hash of the genesis state.
     pub genesis_root: B256,
+    /// Format of the genesis block header; `legacy` if not set.
+    #[serde(default, skip_serializing_if = "GenesisHeaderVersion::is_legacy")]
+    pub header_version: GenesisHeaderVersion,
+}
+
+/// Format of the genesis block header.
+///
+/// The genesis root commits to the hash of the genesis block, so the format used by an existing
+/// chain cannot be changed without changing its genesis root.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GenesisHeaderVersion {
+    /// Zero state root and a placeholder gas limit. Used by chains created before [`Self::V1`].
+    #[default]
+    Legacy,
+    /// State root of the genesis state tree and the gas limit of the genesis block context.
+    /// Requires the `merkle-tree` feature.
+    V1,
+}
+
+impl GenesisHeaderVersion {
+    fn is_legacy(&self) -> bool {
+        *self == Self::Legacy
+    }
 }
 
 impl GenesisInput {
@@ -50,6 +101,25 @@ pub struct GenesisUpgradeTxInfo {
     pub force_deploy_preimages: Vec<(B256, Vec<u8>)>,
 }
 
+/// Error loading [`GenesisUpgradeTxInfo`] from L1.
+#[derive(Debug, thiserror::Error)]
+pub enum GenesisUpgradeTxError {
+    #[error("no GenesisUpgrade event emitted by {zk_chain} in L1 blocks {from_block}..={to_block}")]
+    NotFound {
+        zk_chain: Address,
+        from_block: u64,
+        to_block: u64,
+    },
+    #[error("invalid GenesisUpgrade event in L1 tx {tx_hash:?}")]
+    InvalidEvent {
+        tx_hash: Option<TxHash>,
+        #[source]
+        source: anyhow::Error,
+    },
+    #[error("failed to query GenesisUpgrade event from L1")]
+    L1(#[source] anyhow::Error),
+}
+
 /// Struct that represents the genesis state of the system.
 /// Lazy-initialized to avoid unnecessary computation at startup.
 #[derive(Clone)]
@@ -58,6 +128,8 @@ pub struct Genesis {
     zk_chain: ZkChain<DynProvider>,
     state: OnceCell<GenesisState>,
     genesis_upgrade_tx: OnceCell<GenesisUpgradeTxInfo>,
+    /// L1 block to look up the `GenesisUpgrade` event in instead of the chain deployment block.
+    genesis_upgrade_block_hint: Option<u64>,
     chain_id: u64,
 }
 
@@ -68,6 +140,10 @@ impl Debug for Genesis {
             .field("zk_chain", &self.zk_chain.address())
             .field("state", &self.state.get())
             .field("genesis_upgrade_tx", &self.genesis_upgrade_tx.get())
+            .field(
+                "genesis_upgrade_block_hint",
+                &self.genesis_upgrade_block_hint,
+            )
             .finish()
     }
 }
@@ -77,29 +153,43 @@ impl Genesis {
         input_source: Arc<dyn GenesisInputSource>,
         zk_chain: ZkChain<DynProvider>,
         chain_id: u64,
+        genesis_upgrade_block_hint: Option<u64>,
     ) -> Self {
         Self {
             input_source,
             zk_chain,
             state: OnceCell::new(),
             genesis_upgrade_tx: OnceCell::new(),
+            genesis_upgrade_block_hint,
             chain_id,
         }
     }
 
-    pub async fn state(&self) -> &GenesisState {
+    /// Builds the genesis state on the first call. A failed build is not cached, so that it can be
+    /// retried by calling this method again.
+    pub async fn state(&self) -> anyhow::Result<&GenesisState> {
         self.state
             .get_or_try_init(|| build_genesis(self.input_source.as_ref(), self.chain_id))
             .await
-            .expect("Failed to build genesis state")
+            .context("Failed to build genesis state")
+    }
+
+    /// Summary of the genesis state; builds the state if it's not built yet.
+    pub async fn report(&self) -> anyhow::Result<&GenesisReport> {
+        Ok(&self.state().await?.report)
     }
 
-    pub async fn genesis_upgrade_tx(&self) -> GenesisUpgradeTxInfo {
+    /// Loads the genesis upgrade transaction from the `GenesisUpgrade` event emitted on L1. If there
+    /// are several events (e.g. the chain was re-initialized), the latest one is used.
+    ///
+    /// Like [`Self::state()`], a failed load is not cached and is retried on the next call.
+    pub async fn genesis_upgrade_tx(&self) -> Result<GenesisUpgradeTxInfo, GenesisUpgradeTxError> {
         self.genesis_upgrade_tx
-            .get_or_try_init(|| load_genesis_upgrade_tx(self.zk_chain.clone()))
+            .get_or_try_init(|| {
+                load_genesis_upgrade_tx(self.zk_chain.clone(), self.genesis_upgrade_block_hint)
+            })
             .await
-            .expect("Failed to load genesis upgrade transaction")
-            .clone()
+            .cloned()
     }
 }
 
@@ -118,6 +208,15 @@ pub struct GenesisState {
     pub context: BlockContext,
     /// Expected genesis root (state commitment).
     pub expected_genesis_root: B256,
+    /// Summary of the state above.
+    pub report: GenesisReport,
+}
+
+impl GenesisState {
+    /// Writes [`GenesisReport`] to `path` as pretty-printed JSON.
+    pub fn write_report(&self, path: &Path) -> anyhow::Result<()> {
+        self.report.write(path)
+    }
 }
 
 async fn build_genesis(
@@ -125,58 +224,90 @@ async fn build_genesis(
     chain_id: u64,
 ) -> anyhow::Result<GenesisState> {
     let genesis_input = genesis_input_source.genesis_input().await?;
+    genesis_state(genesis_input, chain_id)
+}
 
+fn genesis_state(genesis_input: GenesisInput, chain_id: u64) -> anyhow::Result<GenesisState> {
     // BTreeMap is used to ensure that the storage logs are sorted by key, so that the order is deterministic
     // which is important for tree.
     let mut storage_logs: BTreeMap<B256, B256> = BTreeMap::new();
     let mut preimages = vec![];
 
+    let mut accounts: BTreeMap<Address, AccountProperties> = BTreeMap::new();
+    let mut code_lengths: BTreeMap<Address, usize> = BTreeMap::new();
     for (address, deployed_code) in genesis_input.initial_contracts {
-        let mut account_properties = AccountProperties::default();
+        code_lengths.insert(address, deployed_code.len());
+        let account_properties = accounts.entry(address).or_default();
         // When contracts are deployed, they have a nonce of 1.
-        set_properties_nonce(&mut account_properties, 1);
-        let bytecode_preimage = set_properties_code(&mut account_properties, &deployed_code);
+        set_properties_nonce(account_properties, 1);
+        let bytecode_preimage = set_properties_code(account_properties, &deployed_code);
         let bytecode_hash = account_properties.bytecode_hash;
-
-        let flat_storage_key = {
-            let mut bytes = [0u8; 64];
-            bytes[12..32].copy_from_slice(&ACCOUNT_PROPERTIES_STORAGE_ADDRESS.to_be_bytes::<20>());
-            bytes[44..64].copy_from_slice(address.as_slice());
-
-            B256::from_slice(Blake2s256::digest(bytes).as_slice())
-        };
-        let account_properties_hash = account_properties.compute_hash();
-        storage_logs.insert(
-            flat_storage_key,
-            account_properties_hash.as_u8_array().into(),
+        preimages.push((bytecode_hash.as_u8_array().into(), bytecode_preimage));
+    }
+    // Balances and nonces are merged into the properties of contracts deployed at the same address
+    let mut funded = BTreeSet::new();
+    for (address, balance) in genesis_input.initial_balances {
+        anyhow::ensure!(
+            funded.insert(address),
+            "Genesis input contains duplicate address in `initial_balances`: {address:?}"
+        );
+        set_properties_balance(accounts.entry(address).or_default(), balance);
+    }
+    let mut with_nonce = BTreeSet::new();
+    for (address, nonce) in genesis_input.initial_nonces {
+        anyhow::ensure!(
+            with_nonce.insert(address),
+            "Genesis input contains duplicate address in `initial_nonces`: {address:?}"
         );
+        set_properties_nonce(accounts.entry(address).or_default(), nonce);
+    }
 
-        preimages.push((bytecode_hash.as_u8_array().into(), bytecode_preimage));
+    let mut contracts = vec![];
+    for (address, account_properties) in accounts {
+        let account_properties_hash: B256 = account_properties.compute_hash().as_u8_array().into();
+        let flat_key = account_properties_key(address);
+        storage_logs.insert(flat_key, account_properties_hash);
+        if let Some(&code_length) = code_lengths.get(&address) {
+            let contract = GenesisContractReport {
+                address,
+                bytecode_hash: account_properties.bytecode_hash.as_u8_array().into(),
+                account_properties_hash,
+                flat_key,
+                code_length,
+            };
+            tracing::debug!(?contract, "genesis contract");
+            contracts.push(contract);
+        }
         preimages.push((
-            account_properties_hash.as_u8_array().into(),
+            account_properties_hash,
             account_properties.encoding().to_vec(),
         ));
     }
 
     for (key, value) in genesis_input.additional_storage {
         let duplicate = storage_logs.insert(key, value).is_some();
-        if duplicate {
-            panic!("Genesis input contains duplicate storage key: {key:?}");
-        }
+        anyhow::ensure!(
+            !duplicate,
+            "Genesis input contains duplicate storage key: {key:?}"
+        );
     }
 
+    let storage_logs: Vec<_> = storage_logs.into_iter().collect();
+    let (state_root, gas_limit) = match genesis_input.header_version {
+        GenesisHeaderVersion::Legacy => (B256::ZERO, LEGACY_GENESIS_HEADER_GAS_LIMIT),
+        GenesisHeaderVersion::V1 => (state_tree_root(&storage_logs)?, GENESIS_GAS_LIMIT),
+    };
     let header = Header {
         parent_hash: B256::ZERO,
         ommers_hash: EMPTY_OMMER_ROOT_HASH,
         beneficiary: Address::ZERO,
-        // for now state root is zero
-        state_root: B256::ZERO,
+        state_root,
         transactions_root: B256::ZERO,
         receipts_root: B256::ZERO,
         logs_bloom: Bloom::ZERO,
         difficulty: U256::ZERO,
         number: 0,
-        gas_limit: 5_000,
+        gas_limit,
         gas_used: 0,
         timestamp: 0,
         extra_data: Default::default(),
@@ -199,62 +330,124 @@ async fn build_genesis(
         pubdata_price: U256::from(0),
         native_price: U256::from(1),
         coinbase: header.beneficiary,
-        gas_limit: 100_000_000,
+        gas_limit: GENESIS_GAS_LIMIT,
         pubdata_limit: 100_000_000,
         mix_hash: U256::ZERO,
         execution_version: genesis_input.execution_version,
         blob_fee: U256::ZERO,
     };
 
+    let report = GenesisReport {
+        contracts,
+        storage_log_count: storage_logs.len(),
+        preimage_bytes: preimages.iter().map(|(_, preimage)| preimage.len()).sum(),
+    };
+    tracing::debug!(
+        contracts = report.contracts.len(),
+        report.storage_log_count,
+        report.preimage_bytes,
+        "built genesis state"
+    );
+    tracing::debug!(?storage_logs, "genesis storage logs");
+
     Ok(GenesisState {
-        storage_logs: storage_logs.into_iter().collect(),
+        storage_logs,
         preimages,
         header,
         context,
         expected_genesis_root: genesis_input.genesis_root,
+        report,
     })
 }
 
+#[cfg(not(feature = "merkle-tree"))]
+fn state_tree_root(_storage_logs: &[(B256, B256)]) -> anyhow::Result<B256> {
+    anyhow::bail!(
+        "genesis header version `v1` requires the `merkle-tree` feature of `zksync_os_genesis`"
+    )
+}
+
+/// Returns the flat storage key of `slot` in the storage of `address`.
+pub fn flat_storage_key(address: Address, slot: B256) -> B256 {
+    let mut bytes = [0u8; 64];
+    bytes[12..32].copy_from_slice(address.as_slice());
+    bytes[32..64].copy_from_slice(slot.as_slice());
+    B256::from_slice(Blake2s256::digest(bytes).as_slice())
+}
+
+/// Returns the flat storage key holding the hash of account properties of `address`.
+fn account_properties_key(address: Address) -> B256 {
+    flat_storage_key(
+        Address::from(ACCOUNT_PROPERTIES_STORAGE_ADDRESS.to_be_bytes::<20>()),
+        address.into_word(),
+    )
+}
+
+/// Computes the genesis state commitment (aka genesis root) from the state tree root and leaf count
+/// after genesis and the hash of the genesis block.
+pub fn genesis_state_commitment(
+    tree_root_hash: B256,
+    tree_leaf_count: u64,
+    genesis_block_hash: B256,
+) -> B256 {
+    let number = 0u64;
+    let timestamp = 0u64;
+
+    let last_256_block_hashes_blake = {
+        let mut blocks_hasher = Blake2s256::new();
+        for _ in 0..255 {
+            blocks_hasher.update([0u8; 32]);
+        }
+        blocks_hasher.update(genesis_block_hash);
+
+        blocks_hasher.finalize()
+    };
+
+    let mut hasher = Blake2s256::new();
+    hasher.update(tree_root_hash.as_slice());
+    hasher.update(tree_leaf_count.to_be_bytes());
+    hasher.update(number.to_be_bytes());
+    hasher.update(last_256_block_hashes_blake);
+    hasher.update(timestamp.to_be_bytes());
+    B256::from_slice(&hasher.finalize())
+}
+
 async fn load_genesis_upgrade_tx(
     zk_chain: ZkChain<DynProvider>,
-) -> anyhow::Result<GenesisUpgradeTxInfo> {
-    const MAX_L1_BLOCKS_LOOKBEHIND: u64 = 100_000;
-
+    block_hint: Option<u64>,
+) -> Result<GenesisUpgradeTxInfo, GenesisUpgradeTxError> {
     let zk_chain_address = *zk_chain.address();
     let provider = zk_chain.provider().clone();
-    let current_l1_block = zk_chain.provider().get_block_number().await?;
-    // Find the block when the zk chain was deployed or fallback to [0; latest_block] in localhost case.
-    let (from_block, to_block) = zksync_os_l1_watcher::util::find_l1_block_by_predicate(
-            Arc::new(zk_chain),
-            |_zk, _block| async { Ok(true) },
-        )
-        .await
-        .map(|b| (b, b))
-        .or_else(|err| {
-            // This may error on Anvil with `--load-state` - as it doesn't support requests even for recent blocks.
-            // We default to `[0; latest_block]` in this case - `eth_getLogs` are still supported.
-            // Assert that we don't fallback on longer chains (e.g. Sepolia)
-            if current_l1_block > MAX_L1_BLOCKS_LOOKBEHIND {
-                anyhow::bail!(
-                    "Binary search failed with {err}. Cannot default starting block to zero for a long chain. Current L1 block number: {current_l1_block}. Limit: {MAX_L1_BLOCKS_LOOKBEHIND}."
-                )
-            } else {
-                Ok((0, current_l1_block))
-            }
-        })?;
+    let (from_block, to_block) = match block_hint {
+        Some(block) => (block, block),
+        None => genesis_upgrade_block_range(zk_chain)
+            .await
+            .map_err(GenesisUpgradeTxError::L1)?,
+    };
     let event_sig = GenesisUpgrade::SIGNATURE_HASH;
     let filter = Filter::new()
         .from_block(from_block)
         .to_block(to_block)
         .event_signature(event_sig)
         .address(zk_chain_address);
-    let logs = provider.get_logs(&filter).await?;
-    anyhow::ensure!(
-        logs.len() == 1,
-        "Expected exactly one genesis upgrade tx log, found these {logs:?}"
-    );
-    let sol_event = GenesisUpgrade::decode_log(&logs[0].inner)?.data;
-    let upgrade_tx = L1UpgradeEnvelope::try_from(sol_event._l2Transaction)?;
+    let logs = provider
+        .get_logs(&filter)
+        .await
+        .map_err(|err| GenesisUpgradeTxError::L1(err.into()))?;
+    let log = select_genesis_upgrade_log(logs).ok_or(GenesisUpgradeTxError::NotFound {
+        zk_chain: zk_chain_address,
+        from_block,
+        to_block,
+    })?;
+    let invalid_event = |source: anyhow::Error| GenesisUpgradeTxError::InvalidEvent {
+        tx_hash: log.transaction_hash,
+        source,
+    };
+    let sol_event = GenesisUpgrade::decode_log(&log.inner)
+        .map_err(|err| invalid_event(err.into()))?
+        .data;
+    let upgrade_tx = L1UpgradeEnvelope::try_from(sol_event._l2Transaction)
+        .map_err(|err| invalid_event(err.into()))?;
     let preimages = sol_event
         ._factoryDeps
         .into_iter()
@@ -273,6 +466,50 @@ async fn load_genesis_upgrade_tx(
     })
 }
 
+/// L1 blocks to look up the `GenesisUpgrade` event in: the block the chain was deployed in.
+async fn genesis_upgrade_block_range(zk_chain: ZkChain<DynProvider>) -> anyhow::Result<(u64, u64)> {
+    const MAX_L1_BLOCKS_LOOKBEHIND: u64 = 100_000;
+
+    let current_l1_block = zk_chain.provider().get_block_number().await?;
+    // Find the block when the zk chain was deployed or fallback to [0; latest_block] in localhost case.
+    zksync_os_l1_watcher::util::find_l1_block_by_predicate(
+            Arc::new(zk_chain),
+            |_zk, _block| async { Ok(true) },
+        )
+        .await
+        .map(|b| (b, b))
+        .or_else(|err| {
+            // This may error on Anvil with `--load-state` - as it doesn't support requests even for recent blocks.
+            // We default to `[0; latest_block]` in this case - `eth_getLogs` are still supported.
+            // Assert that we don't fallback on longer chains (e.g. Sepolia)
+            if current_l1_block > MAX_L1_BLOCKS_LOOKBEHIND {
+                anyhow::bail!(
+                    "Binary search failed with {err}. Cannot default starting block to zero for a long chain. Current L1 block number: {current_l1_block}. Limit: {MAX_L1_BLOCKS_LOOKBEHIND}."
+                )
+            } else {
+                Ok((0, current_l1_block))
+            }
+        })
+}
+
+/// Picks the latest of `GenesisUpgrade` logs (by block number, then by log index). There is more
+/// than one log if the chain was re-initialized, in which case only the latest one is relevant.
+fn select_genesis_upgrade_log(logs: Vec<Log>) -> Option<Log> {
+    if logs.len() > 1 {
+        let events: Vec<_> = logs
+            .iter()
+            .map(|log| (log.block_number, log.log_index, log.transaction_hash))
+            .collect();
+        tracing::warn!(
+            ?events,
+            "found several GenesisUpgrade events (block number, log index, tx hash); \
+             using the latest one. Set `genesis_upgrade_block_hint` to use another one"
+        );
+    }
+    logs.into_iter()
+        .max_by_key(|log| (log.block_number, log.log_index))
+}
+
 #[async_trait::async_trait]
 pub trait GenesisInputSource: Debug + Send + Sync + 'static {
     async fn genesis_input(&self) -> anyhow::Result<GenesisInput>;
@@ -295,3 +532,229 @@ impl GenesisInputSource for FileGenesisInputSource {
         GenesisInput::load_from_file(&self.path)
     }
 }
+
+#[async_trait::async_trait]
+impl GenesisInputSource for GenesisInput {
+    async fn genesis_input(&self) -> anyhow::Result<GenesisInput> {
+        Ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Bytes, U64, address};
+    use alloy::providers::ProviderBuilder;
+    use alloy::transports::mock::Asserter;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use zksync_os_contract_interface::L2CanonicalTransaction;
+    use zksync_os_types::{L1TxType, UpgradeTxType};
+
+    const ZK_CHAIN: Address = address!("0x1000000000000000000000000000000000000001");
+
+    fn mock_zk_chain(asserter: &Asserter) -> ZkChain<DynProvider> {
+        let provider = ProviderBuilder::new()
+            .connect_mocked_client(asserter.clone())
+            .erased();
+        ZkChain::new(ZK_CHAIN, provider)
+    }
+
+    /// `GenesisUpgrade` log with a single factory dep `[marker]`, to tell the logs apart.
+    fn genesis_upgrade_log(block_number: u64, log_index: u64, marker: u8) -> Log {
+        let event = GenesisUpgrade {
+            _zkChain: ZK_CHAIN,
+            _l2Transaction: L2CanonicalTransaction {
+                txType: U256::from(UpgradeTxType::TX_TYPE),
+                from: U256::ZERO,
+                to: U256::ZERO,
+                gasLimit: U256::from(72_000_000),
+                gasPerPubdataByteLimit: U256::from(800),
+                maxFeePerGas: U256::ZERO,
+                maxPriorityFeePerGas: U256::ZERO,
+                paymaster: U256::ZERO,
+                nonce: U256::from(1),
+                value: U256::ZERO,
+                reserved: [U256::ZERO; 4],
+                data: Bytes::new(),
+                signature: Bytes::new(),
+                factoryDeps: vec![],
+                paymasterInput: Bytes::new(),
+                reservedDynamic: Bytes::new(),
+            },
+            _protocolVersion: U256::from(1),
+            _factoryDeps: vec![Bytes::from(vec![marker])],
+        };
+        Log {
+            inner: alloy::primitives::Log {
+                address: ZK_CHAIN,
+                data: event.encode_log_data(),
+            },
+            block_number: Some(block_number),
+            log_index: Some(log_index),
+            transaction_hash: Some(TxHash::repeat_byte(marker)),
+            ..Default::default()
+        }
+    }
+
+    fn marker(info: &GenesisUpgradeTxInfo) -> &[u8] {
+        &info.force_deploy_preimages[0].1
+    }
+
+    #[tokio::test]
+    async fn no_genesis_upgrade_logs() {
+        let asserter = Asserter::new();
+        asserter.push_success(&Vec::<Log>::new());
+        let err = load_genesis_upgrade_tx(mock_zk_chain(&asserter), Some(5))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                GenesisUpgradeTxError::NotFound {
+                    zk_chain: ZK_CHAIN,
+                    from_block: 5,
+                    to_block: 5,
+                }
+            ),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn single_genesis_upgrade_log() {
+        let asserter = Asserter::new();
+        asserter.push_success(&vec![genesis_upgrade_log(5, 0, 1)]);
+        let info = load_genesis_upgrade_tx(mock_zk_chain(&asserter), Some(5))
+            .await
+            .unwrap();
+        assert_eq!(marker(&info), [1]);
+    }
+
+    #[tokio::test]
+    async fn latest_of_several_genesis_upgrade_logs_is_used() {
+        let asserter = Asserter::new();
+        // The chain was deployed in the genesis L1 block: `eth_blockNumber` (twice) and `eth_getCode`
+        // from looking up the deployment block
+        asserter.push_success(&U64::ZERO);
+        asserter.push_success(&U64::ZERO);
+        asserter.push_success(&Bytes::from_static(b"code"));
+        asserter.push_success(&vec![
+            genesis_upgrade_log(7, 0, 1),
+            genesis_upgrade_log(9, 2, 2),
+            genesis_upgrade_log(9, 1, 3),
+        ]);
+        let info = load_genesis_upgrade_tx(mock_zk_chain(&asserter), None)
+            .await
+            .unwrap();
+        assert_eq!(marker(&info), [2]);
+    }
+
+    #[tokio::test]
+    async fn invalid_genesis_upgrade_log() {
+        let asserter = Asserter::new();
+        let mut log = genesis_upgrade_log(5, 0, 1);
+        log.inner.data = alloy::primitives::LogData::new_unchecked(
+            log.inner.data.topics().to_vec(),
+            Bytes::from_static(b"garbage"),
+        );
+        asserter.push_success(&vec![log]);
+        let err = load_genesis_upgrade_tx(mock_zk_chain(&asserter), Some(5))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                GenesisUpgradeTxError::InvalidEvent { tx_hash: Some(tx_hash), .. }
+                    if tx_hash == TxHash::repeat_byte(1)
+            ),
+            "{err:?}"
+        );
+    }
+
+    /// Input source failing on the first call.
+    #[derive(Debug)]
+    struct FlakyInputSource {
+        input: GenesisInput,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl GenesisInputSource for FlakyInputSource {
+        async fn genesis_input(&self) -> anyhow::Result<GenesisInput> {
+            if self.calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                anyhow::bail!("transient error");
+            }
+            Ok(self.input.clone())
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_state_build_is_retried() {
+        let input = GenesisBuilder::new().build_input().unwrap();
+        let expected_root = input.genesis_root;
+        let source = Arc::new(FlakyInputSource {
+            input,
+            calls: AtomicUsize::new(0),
+        });
+        let genesis = Genesis::new(source.clone(), mock_zk_chain(&Asserter::new()), 270, None);
+
+        let err = genesis.state().await.unwrap_err();
+        assert!(format!("{err:#}").contains("transient error"), "{err:#}");
+        let state = genesis.state().await.unwrap();
+        assert_eq!(state.expected_genesis_root, expected_root);
+
+        // The built state is cached
+        genesis.state().await.unwrap();
+        assert_eq!(source.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn failed_genesis_upgrade_tx_load_is_retried() {
+        let asserter = Asserter::new();
+        let input = Arc::new(GenesisBuilder::new().build_input().unwrap());
+        let genesis = Genesis::new(input, mock_zk_chain(&asserter), 270, Some(5));
+
+        asserter.push_failure_msg("transient error");
+        let err = genesis.genesis_upgrade_tx().await.unwrap_err();
+        assert!(matches!(err, GenesisUpgradeTxError::L1(_)), "{err:?}");
+
+        asserter.push_success(&vec![genesis_upgrade_log(5, 0, 1)]);
+        let info = genesis.genesis_upgrade_tx().await.unwrap();
+        assert_eq!(marker(&info), [1]);
+        // The loaded transaction is cached; the mock would fail on another request
+        genesis.genesis_upgrade_tx().await.unwrap();
+    }
+
+    /// The genesis block hash is committed to by the genesis root of existing chains, so it must
+    /// not change across releases.
+    #[test]
+    fn legacy_genesis_header_is_stable() {
+        let input = GenesisBuilder::new()
+            .fund(Address::with_last_byte(1), U256::from(1_000))
+            .build_input()
+            .unwrap();
+        assert_eq!(input.header_version, GenesisHeaderVersion::Legacy);
+        // Omitted for legacy inputs, so that existing `genesis.json` files are unchanged
+        let json = serde_json::to_value(&input).unwrap();
+        assert!(json.get("header_version").is_none(), "{json}");
+
+        let state = genesis_state(input, 270).unwrap();
+        assert_eq!(state.context.chain_id, 270);
+        assert_eq!(state.header.state_root, B256::ZERO);
+        let expected_hash: B256 =
+            "0xef97917ce9bd9fa7d12ad6a8d81ea26c80ac1d727acc37cbb9799ae199788dc5"
+                .parse()
+                .unwrap();
+        assert_eq!(state.header.hash_slow(), expected_hash);
+    }
+
+    #[cfg(not(feature = "merkle-tree"))]
+    #[test]
+    fn v1_genesis_header_requires_merkle_tree() {
+        let input = GenesisBuilder::new()
+            .header_version(GenesisHeaderVersion::V1)
+            .build_input();
+        let err = input.unwrap_err().to_string();
+        assert!(err.contains("`merkle-tree` feature"), "{err}");
+    }
+}
@@ -0,0 +1,101 @@
+use alloy::primitives::{Address, B256};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Summary of the genesis state, e.g. to compare genesis between environments or to audit
+/// contracts deployed in genesis.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisReport {
+    /// Contracts deployed in genesis, in the order of their addresses.
+    pub contracts: Vec<GenesisContractReport>,
+    /// Number of storage logs in the genesis block.
+    pub storage_log_count: usize,
+    /// Total size of preimages (bytecodes and account properties) in bytes.
+    pub preimage_bytes: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GenesisContractReport {
+    pub address: Address,
+    pub bytecode_hash: B256,
+    pub account_properties_hash: B256,
+    /// Flat storage key holding `account_properties_hash`.
+    pub flat_key: B256,
+    /// Length of the deployed bytecode (without padding and artifacts).
+    pub code_length: usize,
+}
+
+impl GenesisReport {
+    /// Writes the report to `path` as pretty-printed JSON.
+    pub fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json)
+            .with_context(|| format!("failed to write genesis report to {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{GenesisBuilder, GenesisReport};
+    use alloy::primitives::{Address, B256, Bytes, U256};
+
+    #[test]
+    fn report_matches_storage_logs() {
+        let contracts = [
+            (
+                Address::with_last_byte(1),
+                Bytes::from_static(&[0x60, 0x01]),
+            ),
+            (
+                Address::with_last_byte(3),
+                Bytes::from_static(&[0x60, 0x00, 0x55]),
+            ),
+        ];
+        let state = contracts
+            .iter()
+            .fold(GenesisBuilder::new(), |builder, (address, bytecode)| {
+                builder.deploy_contract(*address, bytecode.clone())
+            })
+            .fund(Address::with_last_byte(2), U256::from(1))
+            .set_storage(
+                Address::with_last_byte(1),
+                B256::ZERO,
+                B256::with_last_byte(7),
+            )
+            .build_state()
+            .unwrap();
+        let report = &state.report;
+
+        assert_eq!(report.storage_log_count, state.storage_logs.len());
+        assert_eq!(report.storage_log_count, 4);
+        let preimage_bytes: usize = state.preimages.iter().map(|(_, p)| p.len()).sum();
+        assert_eq!(report.preimage_bytes, preimage_bytes);
+
+        assert_eq!(report.contracts.len(), contracts.len());
+        for (contract, (address, bytecode)) in report.contracts.iter().zip(&contracts) {
+            assert_eq!(contract.address, *address);
+            assert_eq!(contract.code_length, bytecode.len());
+            assert!(
+                state
+                    .storage_logs
+                    .contains(&(contract.flat_key, contract.account_properties_hash)),
+                "{contract:?}"
+            );
+            assert!(
+                state
+                    .preimages
+                    .iter()
+                    .any(|(hash, _)| *hash == contract.bytecode_hash),
+                "{contract:?}"
+            );
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("genesis_report.json");
+        state.write_report(&path).unwrap();
+        let written: GenesisReport =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(written, *report);
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{GenesisState, genesis_state_commitment};
+use alloy::primitives::B256;
+use anyhow::Context;
+use std::fmt::Write as _;
+use zksync_os_merkle_tree::{Database, MerkleTree, PatchSet, TreeEntry, TreeOperation};
+
+/// Max number of keys diverging from the state tree listed in verification errors.
+const MAX_REPORTED_KEYS: usize = 5;
+
+impl GenesisState {
+    /// Computes the genesis root (state commitment) over the storage logs the same way as the node
+    /// after initializing the state tree with genesis.
+    pub fn compute_root(&self) -> anyhow::Result<B256> {
+        let mut tree = MerkleTree::new(PatchSet::default())?;
+        let output = tree.extend(&tree_entries(&self.storage_logs))?;
+        Ok(genesis_state_commitment(
+            output.root_hash,
+            output.leaf_count,
+            self.header.hash_slow(),
+        ))
+    }
+
+    /// Verifies that the genesis version of `tree` (a state tree initialized with genesis) results
+    /// in `expected_genesis_root`. On mismatch, the error lists the first storage logs whose values
+    /// diverge from the tree, if any.
+    pub fn verify_root<DB: Database>(&self, tree: &MerkleTree<DB>) -> anyhow::Result<()> {
+        let (root_hash, leaf_count) = tree
+            .root_info(0)?
+            .context("state tree is not initialized with genesis")?;
+        let root = genesis_state_commitment(root_hash, leaf_count, self.header.hash_slow());
+        if root == self.expected_genesis_root {
+            return Ok(());
+        }
+
+        let mut message = format!(
+            "genesis root mismatch: `genesis_root` in genesis input is {:?}, but the state tree \
+             with {} genesis storage logs results in {root:?}",
+            self.expected_genesis_root,
+            self.storage_logs.len()
+        );
+        let divergent_keys = self.divergent_keys(tree)?;
+        if divergent_keys.is_empty() {
+            message.push_str(
+                "; the tree matches storage logs derived from genesis input, so either \
+                 `genesis_root` or the input state (`initial_contracts`, `initial_balances`, \
+                 `additional_storage`) is wrong",
+            );
+        } else {
+            write!(
+                message,
+                "; {} storage logs diverge from the tree:",
+                divergent_keys.len()
+            )?;
+            for (key, expected, actual) in divergent_keys.iter().take(MAX_REPORTED_KEYS) {
+                write!(message, " {key:?} (expected {expected:?}, got {actual:?})")?;
+            }
+            if divergent_keys.len() > MAX_REPORTED_KEYS {
+                message.push_str(" ...");
+            }
+        }
+        anyhow::bail!(message)
+    }
+
+    /// Returns storage logs with values differing from the genesis version of `tree`, as
+    /// `(key, expected value, value in the tree)`.
+    fn divergent_keys<DB: Database>(
+        &self,
+        tree: &MerkleTree<DB>,
+    ) -> anyhow::Result<Vec<(B256, B256, Option<B256>)>> {
+        let keys: Vec<_> = self.storage_logs.iter().map(|(key, _)| *key).collect();
+        let proof = tree.prove(0, &keys)?;
+        let divergent_keys = self
+            .storage_logs
+            .iter()
+            .zip(&proof.read_operations)
+            .filter_map(|(&(key, expected), operation)| {
+                let actual = match operation {
+                    TreeOperation::Hit { index } => {
+                        proof.sorted_leaves.get(index).map(|leaf| leaf.value)
+                    }
+                    TreeOperation::Miss { .. } => None,
+                };
+                (actual != Some(expected)).then_some((key, expected, actual))
+            })
+            .collect();
+        Ok(divergent_keys)
+    }
+}
+
+/// Returns the root hash of the state tree initialized with `storage_logs`.
+pub(crate) fn state_tree_root(storage_logs: &[(B256, B256)]) -> anyhow::Result<B256> {
+    let mut tree = MerkleTree::new(PatchSet::default())?;
+    Ok(tree.extend(&tree_entries(storage_logs))?.root_hash)
+}
+
+fn tree_entries(storage_logs: &[(B256, B256)]) -> Vec<TreeEntry> {
+    storage_logs
+        .iter()
+        .map(|(key, value)| TreeEntry {
+            key: *key,
+            value: *value,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tree_entries;
+    use crate::{
+        GENESIS_GAS_LIMIT, GenesisBuilder, GenesisHeaderVersion, GenesisInput, GenesisState,
+        genesis_state,
+    };
+    use alloy::primitives::{Address, B256, Bytes, U256};
+    use std::path::Path;
+    use zksync_os_merkle_tree::{MerkleTree, PatchSet};
+
+    fn tree(state: &GenesisState) -> MerkleTree<PatchSet> {
+        let mut tree = MerkleTree::new(PatchSet::default()).unwrap();
+        tree.extend(&tree_entries(&state.storage_logs)).unwrap();
+        tree
+    }
+
+    #[test]
+    fn default_genesis_root_is_verified() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../genesis/genesis.json");
+        let input = GenesisInput::load_from_file(Path::new(path)).unwrap();
+        let state = genesis_state(input, 270).unwrap();
+        assert_eq!(state.compute_root().unwrap(), state.expected_genesis_root);
+        state.verify_root(&tree(&state)).unwrap();
+    }
+
+    #[test]
+    fn corrupted_genesis_is_rejected() {
+        let builder = GenesisBuilder::new()
+            .deploy_contract(Address::with_last_byte(1), Bytes::from_static(&[0x00]))
+            .set_storage(
+                Address::with_last_byte(1),
+                B256::ZERO,
+                B256::with_last_byte(1),
+            )
+            .fund(Address::with_last_byte(2), U256::from(1_000));
+        let input = builder.build_input().unwrap();
+        let state = genesis_state(input.clone(), 270).unwrap();
+        state.verify_root(&tree(&state)).unwrap();
+
+        // A typo in `additional_storage`
+        let mut corrupted_input = input;
+        corrupted_input.additional_storage[0].1 = B256::with_last_byte(2);
+        let corrupted = genesis_state(corrupted_input, 270).unwrap();
+        let err = corrupted
+            .verify_root(&tree(&corrupted))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("genesis root mismatch"), "{err}");
+        assert!(err.contains("the tree matches storage logs"), "{err}");
+
+        // The tree was initialized with the original genesis, so the corrupted key is reported
+        let err = corrupted
+            .verify_root(&tree(&state))
+            .unwrap_err()
+            .to_string();
+        let (corrupted_key, _) = corrupted
+            .storage_logs
+            .iter()
+            .zip(&state.storage_logs)
+            .find(|(corrupted, original)| corrupted != original)
+            .unwrap()
+            .0;
+        assert!(err.contains("1 storage logs diverge"), "{err}");
+        assert!(err.contains(&format!("{corrupted_key:?}")), "{err}");
+    }
+
+    /// The genesis block hash is committed to by the genesis root, so it must not change across
+    /// releases for the same input.
+    #[test]
+    fn v1_genesis_header_is_stable() {
+        let input = GenesisInput {
+            initial_contracts: vec![],
+            initial_balances: vec![],
+            initial_nonces: vec![],
+            additional_storage: vec![
+                (B256::with_last_byte(1), B256::repeat_byte(0x11)),
+                (B256::repeat_byte(0xab), B256::repeat_byte(0x22)),
+            ],
+            execution_version: 4,
+            genesis_root: B256::ZERO,
+            header_version: GenesisHeaderVersion::V1,
+        };
+        let state = genesis_state(input, 270).unwrap();
+
+        let expected_state_root: B256 =
+            "0xf4f60867ec53bd9e90f78fbe4193c3ef1bb6c4b1562d71e99f131454050dee13"
+                .parse()
+                .unwrap();
+        assert_eq!(state.header.state_root, expected_state_root);
+        assert_eq!(
+            Some(state.header.state_root),
+            tree(&state).root_hash(0).unwrap()
+        );
+        assert_eq!(state.header.gas_limit, GENESIS_GAS_LIMIT);
+        assert_eq!(state.header.gas_limit, state.context.gas_limit);
+        let expected_hash: B256 =
+            "0xc1dc43f0dab6db313f58fb08ccc5b7b259e8a6220bc6c357eeb4537b5453dc5b"
+                .parse()
+                .unwrap();
+        assert_eq!(state.header.hash_slow(), expected_hash);
+    }
+}
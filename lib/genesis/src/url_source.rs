@@ -0,0 +1,322 @@
+use crate::{GenesisInput, GenesisInputSource};
+use alloy::primitives::B256;
+use anyhow::Context;
+use backon::{ExponentialBuilder, Retryable};
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Genesis input fetched over HTTP(S), e.g. from an artifact server, and pinned by the SHA-256
+/// checksum of the raw file.
+///
+/// The verified file is cached at `cache_path`, so that restarts don't depend on the server.
+/// A cached file with a different checksum (e.g. left from a previous pin) is ignored and replaced.
+#[derive(Debug)]
+pub struct UrlGenesisInputSource {
+    url: String,
+    checksum: B256,
+    cache_path: PathBuf,
+    client: reqwest::Client,
+    backoff: ExponentialBuilder,
+}
+
+#[derive(Debug, thiserror::Error)]
+enum FetchError {
+    /// Network errors, timeouts and 5xx / 429 responses.
+    #[error("transient error: {0}")]
+    Transient(reqwest::Error),
+    #[error(transparent)]
+    Permanent(reqwest::Error),
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(err: reqwest::Error) -> Self {
+        let is_transient = err.is_timeout()
+            || err.is_connect()
+            || err.is_body()
+            || err.status().is_some_and(|status| {
+                status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+            });
+        if is_transient {
+            Self::Transient(err)
+        } else {
+            Self::Permanent(err)
+        }
+    }
+}
+
+impl UrlGenesisInputSource {
+    pub fn new(url: String, checksum: B256, cache_path: PathBuf) -> Self {
+        Self {
+            url,
+            checksum,
+            cache_path,
+            client: reqwest::Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .expect("failed to build HTTP client"),
+            backoff: ExponentialBuilder::default()
+                .with_min_delay(Duration::from_secs(1))
+                .with_max_delay(Duration::from_secs(30))
+                .with_max_times(5),
+        }
+    }
+
+    /// Sets the policy transient errors are retried with.
+    pub fn with_backoff(mut self, backoff: ExponentialBuilder) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Returns the cached file if it matches the checksum.
+    fn cached(&self) -> anyhow::Result<Option<Vec<u8>>> {
+        let bytes = match std::fs::read(&self.cache_path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(err).with_context(|| {
+                    format!(
+                        "failed to read cached genesis input {}",
+                        self.cache_path.display()
+                    )
+                });
+            }
+        };
+        let computed = sha256(&bytes);
+        if computed != self.checksum {
+            tracing::warn!(
+                cache_path = %self.cache_path.display(),
+                expected = ?self.checksum,
+                computed = ?computed,
+                "cached genesis input doesn't match the checksum; fetching it again"
+            );
+            return Ok(None);
+        }
+        Ok(Some(bytes))
+    }
+
+    async fn fetch(&self) -> anyhow::Result<Vec<u8>> {
+        let fetch = || async {
+            let response = self.client.get(&self.url).send().await?;
+            let bytes = response.error_for_status()?.bytes().await?;
+            Ok::<_, FetchError>(bytes)
+        };
+        let bytes = fetch
+            .retry(self.backoff)
+            .when(|err| matches!(err, FetchError::Transient(_)))
+            .notify(|err, delay| {
+                tracing::warn!(
+                    url = %self.url,
+                    %err,
+                    ?delay,
+                    "failed to fetch genesis input, retrying"
+                );
+            })
+            .await
+            .with_context(|| format!("failed to fetch genesis input from {}", self.url))?;
+        Ok(bytes.to_vec())
+    }
+
+    /// Writes the file to the cache; the file is replaced atomically, so that an interrupted write
+    /// doesn't leave a truncated file behind.
+    fn store(&self, bytes: &[u8]) -> anyhow::Result<()> {
+        if let Some(dir) = self.cache_path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let tmp_path = self.cache_path.with_extension("tmp");
+        std::fs::write(&tmp_path, bytes)
+            .and_then(|()| std::fs::rename(&tmp_path, &self.cache_path))
+            .with_context(|| {
+                format!(
+                    "failed to cache genesis input to {}",
+                    self.cache_path.display()
+                )
+            })
+    }
+}
+
+#[async_trait::async_trait]
+impl GenesisInputSource for UrlGenesisInputSource {
+    async fn genesis_input(&self) -> anyhow::Result<GenesisInput> {
+        let bytes = match self.cached()? {
+            Some(bytes) => bytes,
+            None => {
+                let bytes = self.fetch().await?;
+                let computed = sha256(&bytes);
+                anyhow::ensure!(
+                    computed == self.checksum,
+                    "genesis input checksum mismatch for {}: expected SHA-256 {:?}, computed {:?}",
+                    self.url,
+                    self.checksum,
+                    computed
+                );
+                self.store(&bytes)?;
+                tracing::info!(
+                    url = %self.url,
+                    cache_path = %self.cache_path.display(),
+                    "fetched and cached genesis input"
+                );
+                bytes
+            }
+        };
+        serde_json::from_slice(&bytes).context("Failed to parse genesis input file")
+    }
+}
+
+/// SHA-256 digest of `bytes`, as pinned for [`UrlGenesisInputSource`].
+pub fn sha256(bytes: &[u8]) -> B256 {
+    B256::from(<[u8; 32]>::from(Sha256::digest(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GenesisBuilder;
+    use alloy::primitives::{Address, U256};
+    use axum::Router;
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use axum::routing::get;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Fixture {
+        input: GenesisInput,
+        json: String,
+        base_url: String,
+        /// Number of requests to `/flaky.json`.
+        flaky_requests: Arc<AtomicUsize>,
+    }
+
+    /// Serves the fixture at `/genesis.json`, a corrupted variant at `/corrupted.json` and
+    /// the fixture failing with 503 on the first request at `/flaky.json`.
+    async fn serve_fixture() -> Fixture {
+        let builder = GenesisBuilder::new().fund(Address::with_last_byte(1), U256::from(1_000));
+        let input = builder.build_input().unwrap();
+        let json = builder.to_json().unwrap();
+        let corrupted = json.replace("0x3e8", "0x3e9");
+        assert_ne!(corrupted, json);
+
+        let flaky_requests = Arc::new(AtomicUsize::new(0));
+        let app = Router::new()
+            .route(
+                "/genesis.json",
+                get({
+                    let json = json.clone();
+                    || async move { json }
+                }),
+            )
+            .route("/corrupted.json", get(|| async move { corrupted }))
+            .route(
+                "/flaky.json",
+                get({
+                    let json = json.clone();
+                    |State(requests): State<Arc<AtomicUsize>>| async move {
+                        if requests.fetch_add(1, Ordering::SeqCst) == 0 {
+                            Err(StatusCode::SERVICE_UNAVAILABLE)
+                        } else {
+                            Ok(json)
+                        }
+                    }
+                }),
+            )
+            .with_state(flaky_requests.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        Fixture {
+            input,
+            json,
+            base_url,
+            flaky_requests,
+        }
+    }
+
+    fn source(url: String, checksum: B256, cache_path: PathBuf) -> UrlGenesisInputSource {
+        UrlGenesisInputSource::new(url, checksum, cache_path).with_backoff(
+            ExponentialBuilder::default()
+                .with_min_delay(Duration::from_millis(10))
+                .with_max_times(2),
+        )
+    }
+
+    #[tokio::test]
+    async fn input_is_verified_and_cached() {
+        let fixture = serve_fixture().await;
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("cache/genesis.json");
+        let checksum = sha256(fixture.json.as_bytes());
+
+        let url = format!("{}/genesis.json", fixture.base_url);
+        let input = source(url, checksum, cache_path.clone())
+            .genesis_input()
+            .await
+            .unwrap();
+        assert_eq!(input, fixture.input);
+        assert_eq!(std::fs::read_to_string(&cache_path).unwrap(), fixture.json);
+
+        // Restarts work offline
+        let unreachable = "http://127.0.0.1:1/genesis.json".to_owned();
+        let input = source(unreachable.clone(), checksum, cache_path.clone())
+            .genesis_input()
+            .await
+            .unwrap();
+        assert_eq!(input, fixture.input);
+
+        // A cache with another checksum is not used
+        let err = source(unreachable, B256::repeat_byte(1), cache_path)
+            .genesis_input()
+            .await
+            .unwrap_err();
+        assert!(
+            format!("{err:#}").contains("failed to fetch genesis input"),
+            "{err:#}"
+        );
+    }
+
+    #[tokio::test]
+    async fn checksum_mismatch_is_rejected() {
+        let fixture = serve_fixture().await;
+        let dir = tempfile::tempdir().unwrap();
+        let cache_path = dir.path().join("genesis.json");
+        let checksum = sha256(fixture.json.as_bytes());
+
+        let url = format!("{}/corrupted.json", fixture.base_url);
+        let err = source(url, checksum, cache_path.clone())
+            .genesis_input()
+            .await
+            .unwrap_err()
+            .to_string();
+        let computed = sha256(fixture.json.replace("0x3e8", "0x3e9").as_bytes());
+        assert!(err.contains("checksum mismatch"), "{err}");
+        assert!(err.contains(&format!("{checksum:?}")), "{err}");
+        assert!(err.contains(&format!("{computed:?}")), "{err}");
+        assert!(!cache_path.exists());
+    }
+
+    #[tokio::test]
+    async fn transient_errors_are_retried() {
+        let fixture = serve_fixture().await;
+        let dir = tempfile::tempdir().unwrap();
+        let checksum = sha256(fixture.json.as_bytes());
+
+        let url = format!("{}/flaky.json", fixture.base_url);
+        let input = source(url, checksum, dir.path().join("genesis.json"))
+            .genesis_input()
+            .await
+            .unwrap();
+        assert_eq!(input, fixture.input);
+        assert_eq!(fixture.flaky_requests.load(Ordering::SeqCst), 2);
+
+        // Client errors are not retried
+        let url = format!("{}/missing.json", fixture.base_url);
+        let err = source(url, checksum, dir.path().join("missing.json"))
+            .genesis_input()
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("404"), "{err:#}");
+    }
+}
@@ -1,6 +1,7 @@
 use std::time::Duration;
 use vise::{Buckets, Gauge, Histogram, LabeledFamily, Metrics, Unit};
 use vise::{Counter, EncodeLabelValue};
+use zksync_os_observability::exemplars::HistogramExemplars;
 
 // todo: these metrics are used throughout the batcher subsystem - not only l1 sender
 //       we will move them to `batcher_metrics` or `batcher` crate once we have one.
@@ -19,15 +20,20 @@ pub enum BatchExecutionStage {
     CommitL1TxSent,
     CommitL1TxMined,
     CommitL1Passthrough,
+    CommitL1TxFinalized,
+    /// Committed batch was reverted on L1 (e.g. by governance).
+    CommitL1Reverted,
     SnarkProverPicked,
     SnarkProvedReal,
     SnarkProvedFake,
     ProveL1TxSent,
     ProveL1TxMined,
     ProveL1Passthrough,
+    ProveL1TxFinalized,
     ExecuteL1TxSent,
     ExecuteL1TxMined,
     ExecuteL1Passthrough,
+    ExecuteL1TxFinalized,
 }
 
 #[derive(Debug, Metrics)]
@@ -56,6 +62,18 @@ pub struct BatcherSubsystemMetrics {
 
     #[metrics(buckets = Buckets::exponential(1_000.0..=1_000_000.0, 4.0))]
     pub pubdata_per_batch: Histogram<u64>,
+
+    /// Batches sealed with priority transactions close to expiring on L1.
+    pub priority_deadline_batches: Counter,
+
+    /// Time left until the earliest priority transaction of such a batch expires, once the batch
+    /// is executed on L1. Zero if it already expired.
+    #[metrics(unit = Unit::Seconds, buckets = Buckets::exponential(60.0..=86_400.0, 2.0))]
+    pub priority_deadline_margin: Histogram<Duration>,
 }
 #[vise::register]
 pub static BATCHER_METRICS: vise::Global<BatcherSubsystemMetrics> = vise::Global::new();
+
+/// Links `batcher_execution_stages` buckets to batch numbers.
+pub static EXECUTION_STAGE_EXEMPLARS: HistogramExemplars =
+    HistogramExemplars::new("batcher_execution_stages_seconds");
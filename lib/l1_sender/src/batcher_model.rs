@@ -1,15 +1,18 @@
-use crate::batcher_metrics::{BATCHER_METRICS, BatchExecutionStage};
+use crate::batcher_metrics::{BATCHER_METRICS, BatchExecutionStage, EXECUTION_STAGE_EXEMPLARS};
 use crate::commitment::BatchInfo;
-use alloy::primitives::Bytes;
+use alloy::primitives::{Bytes, TxHash};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fmt;
 use std::fmt::{Debug, Formatter};
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use time::UtcDateTime;
 use zksync_os_batch_types::BatchSignatureSet;
 use zksync_os_contract_interface::models::StoredBatchInfo;
-use zksync_os_multivm::ExecutionVersion;
+use zksync_os_multivm::{ExecutionVersion, MultivmError};
 use zksync_os_observability::LatencyDistributionTracker;
+use zksync_os_observability::exemplars::ExemplarLabels;
+use zksync_os_types::L1PricePrediction;
 // todo: these models are used throughout the batcher subsystem - not only l1 sender
 //       we will move them to `types` or `batcher_types` when an analogous crate is created in `zksync-os`
 
@@ -34,9 +37,53 @@ pub struct BatchMetadata {
     pub tx_count: usize,
     #[serde(default = "default_execution_version")]
     pub execution_version: u32,
+    /// Timestamp of the first block in milliseconds. Missing for batches sealed before it was recorded.
+    #[serde(default)]
+    pub first_block_timestamp_millis: Option<u64>,
+    /// Versions the batch prover input was generated for. Missing for batches sealed before it was recorded.
+    #[serde(default)]
+    pub prover_input_version: Option<ProverInputVersion>,
+    /// Commitment encoding version the batch was sealed with, see
+    /// [`COMMITMENT_ENCODING_VERSION`](crate::commitment::COMMITMENT_ENCODING_VERSION).
+    #[serde(default = "default_commitment_encoding_version")]
+    pub commitment_encoding_version: u8,
+    /// Set by the commit sender on the first batch committed with a different commitment encoding
+    /// version than the previous batch.
+    #[serde(default)]
+    pub commitment_format_transition: Option<CommitmentFormatTransition>,
+    /// Mean L1 prices predicted while the batch's blocks were produced. Missing for batches sealed
+    /// before it was recorded and for batches consisting of replayed blocks only.
+    #[serde(default)]
+    pub l1_price_prediction: Option<L1PricePrediction>,
+    /// Pubdata published by the batch's blocks. Missing for batches sealed before it was recorded.
+    #[serde(default)]
+    pub pubdata_bytes: Option<u64>,
+    /// Criterion the batch was sealed by. Missing for batches sealed before it was recorded.
+    #[serde(default)]
+    pub seal_reason: Option<BatchSealReason>,
+    /// Expiration timestamp (in seconds) of the earliest-expiring priority transaction in the
+    /// batch, set if it was within the batcher's priority deadline window when the batch was
+    /// sealed. Such batches are proven and committed ahead of others.
+    #[serde(default)]
+    pub priority_deadline: Option<u64>,
 }
 
 impl BatchMetadata {
+    /// Versions of the batch prover input. Falls back to the current version for batches sealed
+    /// before the version was recorded.
+    pub fn prover_input_version(&self) -> Result<ProverInputVersion, MultivmError> {
+        match self.prover_input_version {
+            Some(version) => Ok(version),
+            None => ProverInputVersion::current(self.execution_version),
+        }
+    }
+
+    /// Execution version to prove the batch with. Fails if the batch was executed with a version
+    /// unknown to this build, e.g. by a newer node version.
+    pub fn proving_execution_version(&self) -> Result<ExecutionVersion, MultivmError> {
+        zksync_os_multivm::proving_run_execution_version(self.execution_version)
+    }
+
     /// Gets batch metadata verification key hash.
     ///
     /// NOTE: Panics if the execution version is unsupported, which *should* never happen in practice.
@@ -47,12 +94,118 @@ impl BatchMetadata {
             .expect("Unsupported execution version")
             .vk_hash()
     }
+
+    /// Metadata of batch `batch_number` consisting of `blocks`, with zero hashes and timestamps.
+    /// Executed with the latest execution version on chain 270.
+    #[cfg(any(test, feature = "test-utils"))]
+    pub fn for_tests(
+        batch_number: u64,
+        blocks: std::ops::RangeInclusive<u64>,
+        tx_count: usize,
+    ) -> Self {
+        use alloy::primitives::{Address, B256};
+        use zksync_os_contract_interface::models::CommitBatchInfo;
+
+        Self {
+            previous_stored_batch_info: StoredBatchInfo {
+                batch_number: batch_number.saturating_sub(1),
+                state_commitment: B256::ZERO,
+                number_of_layer1_txs: 0,
+                priority_operations_hash: B256::ZERO,
+                dependency_roots_rolling_hash: B256::ZERO,
+                l2_to_l1_logs_root_hash: B256::ZERO,
+                commitment: B256::ZERO,
+                last_block_timestamp: 0,
+            },
+            batch_info: BatchInfo {
+                commit_info: CommitBatchInfo {
+                    batch_number,
+                    new_state_commitment: B256::ZERO,
+                    number_of_layer1_txs: 0,
+                    priority_operations_hash: B256::ZERO,
+                    dependency_roots_rolling_hash: B256::ZERO,
+                    l2_to_l1_logs_root_hash: B256::ZERO,
+                    l2_da_validator: Address::ZERO,
+                    da_commitment: B256::ZERO,
+                    first_block_timestamp: 0,
+                    last_block_timestamp: 0,
+                    chain_id: 270,
+                    operator_da_input: vec![],
+                },
+                chain_address: Address::repeat_byte(1),
+                upgrade_tx_hash: None,
+            },
+            first_block_number: *blocks.start(),
+            last_block_number: *blocks.end(),
+            tx_count,
+            execution_version: zksync_os_multivm::LATEST_EXECUTION_VERSION as u32,
+            first_block_timestamp_millis: None,
+            prover_input_version: None,
+            commitment_encoding_version: crate::commitment::COMMITMENT_ENCODING_VERSION,
+            commitment_format_transition: None,
+            l1_price_prediction: None,
+            pubdata_bytes: None,
+            seal_reason: None,
+            priority_deadline: None,
+        }
+    }
 }
 
 fn default_execution_version() -> u32 {
     1
 }
 
+/// Encoding version used before it was recorded in [`BatchMetadata`].
+fn default_commitment_encoding_version() -> u8 {
+    2
+}
+
+/// Change of the commitment encoding version between consecutive committed batches.
+/// May be an upgrade or a downgrade (e.g. after a node rollback).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitmentFormatTransition {
+    pub from_version: u8,
+    pub to_version: u8,
+}
+
+impl CommitmentFormatTransition {
+    pub fn is_downgrade(&self) -> bool {
+        self.to_version < self.from_version
+    }
+}
+
+/// Locally committed batches `reverted_to_batch + 1..=last_reverted_batch` reverted on L1
+/// (e.g. by governance via `revertBatches`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct L1BatchRevert {
+    /// Last batch that is still committed on L1.
+    pub reverted_to_batch: u64,
+    /// Last block of `reverted_to_batch`.
+    pub reverted_to_block: u64,
+    /// Last batch committed by this node before the revert.
+    pub last_reverted_batch: u64,
+    /// Last block of `last_reverted_batch`.
+    pub last_reverted_block: u64,
+    pub total_batches_verified: u64,
+    pub total_batches_executed: u64,
+    /// Whether the local batch `reverted_to_batch` differs from the one stored on L1. Reverted
+    /// batches cannot be re-committed on top of it, so local blocks must be rolled back manually.
+    pub requires_local_rollback: bool,
+}
+
+/// Status of the latest L1 batch revert, shared between the L1 watcher, L1 senders and admin API.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum L1RevertStatus {
+    #[default]
+    None,
+    /// Batches were reverted on L1; L1 senders are paused until the operator acknowledges it.
+    Detected(L1BatchRevert),
+    /// The operator confirmed that reverted batches are to be re-committed.
+    Acknowledged(L1BatchRevert),
+}
+
 #[derive(Debug)]
 pub struct MissingSignature;
 
@@ -107,10 +260,15 @@ impl<E, S> BatchEnvelope<E, S> {
     pub fn batch_number(&self) -> u64 {
         self.batch.batch_info.batch_number
     }
+    /// Batch age. Uses millisecond precision when available: with sub-second block times,
+    /// block timestamps in seconds understate the age by up to a second.
     pub fn time_since_first_block(&self) -> anyhow::Result<core::time::Duration> {
-        let first_block_time = SystemTime::from(UtcDateTime::from_unix_timestamp(
-            self.batch.batch_info.first_block_timestamp as i64,
-        )?);
+        let first_block_time = match self.batch.first_block_timestamp_millis {
+            Some(millis) => UNIX_EPOCH + Duration::from_millis(millis),
+            None => SystemTime::from(UtcDateTime::from_unix_timestamp(
+                self.batch.batch_info.first_block_timestamp as i64,
+            )?),
+        };
 
         Ok(SystemTime::now().duration_since(first_block_time)?)
     }
@@ -127,7 +285,12 @@ impl<E, S> BatchEnvelope<E, S> {
         let batch_number = self.batch_number();
         let last_block_number = self.batch.last_block_number;
         self.latency_tracker.record_stage(stage, |duration| {
-            BATCHER_METRICS.execution_stages[&stage].observe(duration);
+            EXECUTION_STAGE_EXEMPLARS.observe(
+                &BATCHER_METRICS.execution_stages[&stage],
+                &[("stage", &stage.to_string())],
+                duration,
+                ExemplarLabels::batch(batch_number),
+            );
             BATCHER_METRICS.batch_number[&stage].set(batch_number);
             BATCHER_METRICS.block_number[&stage].set(last_block_number);
         });
@@ -150,6 +313,40 @@ impl<E, S> BatchEnvelope<E, S> {
 
 pub type ProverInput = Vec<u32>;
 
+/// Version of the [`ProverInput`] layout. Must be bumped on any change that makes inputs incompatible
+/// with provers built for the previous layout.
+pub const PROVER_INPUT_FORMAT_VERSION: u32 = 1;
+
+/// Versions a [`ProverInput`] was generated for. Provers get them together with the input
+/// and echo them back when submitting the proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ProverInputVersion {
+    /// Execution version used for proving, see [`zksync_os_multivm::proving_run_execution_version`].
+    pub execution_version: u32,
+    pub input_format_version: u32,
+}
+
+impl ProverInputVersion {
+    /// Version of the prover input generated by this server for a batch executed with `execution_version`.
+    pub fn current(execution_version: u32) -> Result<Self, MultivmError> {
+        Ok(Self {
+            execution_version: zksync_os_multivm::proving_run_execution_version(execution_version)?
+                as u32,
+            input_format_version: PROVER_INPUT_FORMAT_VERSION,
+        })
+    }
+}
+
+impl fmt::Display for ProverInputVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "execution_version = {}, input_format_version = {}",
+            self.execution_version, self.input_format_version
+        )
+    }
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub enum FriProof {
     // Fake proof for testing purposes
@@ -260,6 +457,144 @@ impl RealSnarkProof {
     }
 }
 
+/// Criterion a batch was sealed by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchSealReason {
+    BlocksPerBatch,
+    NativeCycles,
+    Pubdata,
+    L2L1Logs,
+    ExecutionVersionChange,
+    Timeout,
+}
+
+impl BatchSealReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::BlocksPerBatch => "blocks_per_batch",
+            Self::NativeCycles => "native_cycles",
+            Self::Pubdata => "pubdata",
+            Self::L2L1Logs => "l2_l1_logs",
+            Self::ExecutionVersionChange => "execution_version_change",
+            Self::Timeout => "timeout",
+        }
+    }
+}
+
+/// L1 operation performed for a batch by one of the L1 senders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum L1BatchOperation {
+    Commit,
+    Prove,
+    Execute,
+}
+
+impl L1BatchOperation {
+    pub const ALL: [L1BatchOperation; 3] = [Self::Commit, Self::Prove, Self::Execute];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Commit => "commit",
+            Self::Prove => "prove",
+            Self::Execute => "execute",
+        }
+    }
+
+    /// Batcher stage reported once the operation's L1 transaction is finalized.
+    pub fn finalized_stage(&self) -> BatchExecutionStage {
+        match self {
+            Self::Commit => BatchExecutionStage::CommitL1TxFinalized,
+            Self::Prove => BatchExecutionStage::ProveL1TxFinalized,
+            Self::Execute => BatchExecutionStage::ExecuteL1TxFinalized,
+        }
+    }
+}
+
+/// Finality of an L1 transaction relative to the L1 `safe` and `finalized` block tags.
+///
+/// Ordered from the weakest to the strongest guarantee.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum L1TxFinality {
+    /// Transaction is not (or no longer) part of the canonical L1 chain.
+    Pending,
+    /// Transaction is included in a block that is not yet `safe`.
+    Included,
+    /// Transaction is included in a block at or below the `safe` head.
+    Safe,
+    /// Transaction is included in a block at or below the `finalized` head.
+    Finalized,
+}
+
+impl L1TxFinality {
+    /// Derives finality from the transaction's current inclusion block and L1 heads.
+    pub fn from_inclusion(
+        inclusion_block: Option<u64>,
+        safe_block: u64,
+        finalized_block: u64,
+    ) -> Self {
+        match inclusion_block {
+            None => Self::Pending,
+            Some(block) if block <= finalized_block => Self::Finalized,
+            Some(block) if block <= safe_block => Self::Safe,
+            Some(_) => Self::Included,
+        }
+    }
+}
+
+/// L1 transaction that performed `operation` for batches `first_batch..=last_batch`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1TxRecord {
+    pub operation: L1BatchOperation,
+    pub first_batch: u64,
+    pub last_batch: u64,
+    pub tx_hash: TxHash,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackedL1Tx {
+    pub record: L1TxRecord,
+    pub finality: L1TxFinality,
+    /// L1 block the transaction was last seen included in.
+    pub l1_block_number: Option<u64>,
+}
+
+/// Per-batch L1 finality state, as maintained by the L1 finality tracker.
+///
+/// Only non-finalized transactions are tracked individually - everything at or below
+/// `last_finalized_batch` is finalized for the corresponding operation.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1FinalitySnapshot {
+    pub last_finalized_batch: BTreeMap<L1BatchOperation, u64>,
+    pub tracked: Vec<TrackedL1Tx>,
+}
+
+impl L1FinalitySnapshot {
+    /// Returns finality of `operation` for `batch_number`, if it was ever sent by this node.
+    pub fn batch_finality(
+        &self,
+        batch_number: u64,
+        operation: L1BatchOperation,
+    ) -> Option<L1TxFinality> {
+        if self
+            .last_finalized_batch
+            .get(&operation)
+            .is_some_and(|last| batch_number <= *last)
+        {
+            return Some(L1TxFinality::Finalized);
+        }
+        self.tracked
+            .iter()
+            .find(|tx| {
+                tx.record.operation == operation
+                    && (tx.record.first_batch..=tx.record.last_batch).contains(&batch_number)
+            })
+            .map(|tx| tx.finality)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -270,5 +605,28 @@ mod tests {
         let data = r#"{"batch":{"previous_stored_batch_info":{"batch_number":9,"state_commitment":"0x7e7f4bbd2fac4431253feccd4688d4b060d720c9cdb5eb06267e9cc8fdfad39d","number_of_layer1_txs":0,"priority_operations_hash":"0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470","dependency_roots_rolling_hash":"0x0000000000000000000000000000000000000000000000000000000000000000","l2_to_l1_logs_root_hash":"0x692f35c99f9c698852289ffecf07f6dd45770904521149d79aa85aae598fa375","commitment":"0xf1dfa8fe5d6571e1c9bdb01f574cff0cbe8c23183c4fcd6d7dd1b4128e54287c","last_block_timestamp":1758115458},"commit_batch_info":{"batch_number":10,"new_state_commitment":"0x53680ad464b20f43921708bd3e024f365b788b9e11cf49e783607a42172136fc","number_of_layer1_txs":0,"priority_operations_hash":"0xc5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470","dependency_roots_rolling_hash":"0x0000000000000000000000000000000000000000000000000000000000000000","l2_to_l1_logs_root_hash":"0x692f35c99f9c698852289ffecf07f6dd45770904521149d79aa85aae598fa375","l2_da_validator":"0x0000000000000000000000000000000000000000","da_commitment":"0x86b130c978627d2acb4a68c823cfc31efadf6482862566d364cc4bc15e500e2b","first_block_timestamp":1758116549,"last_block_timestamp":1758116549,"chain_id":8022833,"chain_address":"0x02b1ac1cf0a592aefd3c2246b2431388365db272","operator_da_input":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,201,102,180,205,111,127,203,19,178,222,176,220,147,85,249,171,106,46,88,99,189,117,148,44,88,11,167,49,72,205,72,21,1,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,116,25,135,1,193,217,21,41,206,115,57,17,55,153,69,34,75,25,41,48,9,20,117,70,62,143,98,164,122,16,216,160,0,0,0,2,193,25,138,114,80,95,70,215,34,237,142,12,160,249,191,228,43,163,162,216,104,166,24,217,213,90,128,186,146,85,247,97,20,33,1,64,111,64,166,72,80,155,187,230,197,73,156,145,87,2,137,219,217,151,57,45,241,113,145,154,157,86,109,62,141,1,57,228,183,230,28,9,1,34,1,64,111,64,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0],"upgrade_tx_hash":null},"first_block_number":10,"last_block_number":10,"tx_count":1,"execution_version":1},"data":{"Real":[2,252,54,244]}}"#;
         let b = serde_json::from_str::<SignedBatchEnvelope<FriProof>>(data).unwrap();
         assert!(matches!(b.data, FriProof::Real(RealFriProof::V1(_))));
+        assert_eq!(b.batch.first_block_timestamp_millis, None);
+        assert_eq!(b.batch.commitment_encoding_version, 2);
+        assert_eq!(b.batch.commitment_format_transition, None);
+    }
+
+    #[test]
+    fn batch_age_uses_millis() {
+        let now_millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        // Batch of 4 blocks produced within the current second, the first one 750ms ago
+        let first_block_millis = now_millis - 750;
+        let mut metadata = BatchMetadata::for_tests(1, 1..=4, 0);
+        metadata.batch_info.first_block_timestamp = first_block_millis / 1000;
+        metadata.batch_info.last_block_timestamp = first_block_millis / 1000;
+
+        metadata.first_block_timestamp_millis = Some(first_block_millis);
+        let envelope = BatchEnvelope::new(metadata, ());
+        let age = envelope.time_since_first_block().unwrap();
+        // Exact up to the test run time, rather than rounded down to the second
+        assert!(age >= Duration::from_millis(750), "{age:?}");
+        assert!(age < Duration::from_millis(1_750), "{age:?}");
     }
 }
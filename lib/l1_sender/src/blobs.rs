@@ -0,0 +1,108 @@
+//! Packing of batch pubdata into EIP-4844 blobs for the `Blobs` pubdata mode.
+//!
+//! A blob consists of 4096 32-byte field elements. Every field element must be below the BLS
+//! modulus, so its first byte is always left zero and only the remaining 31 bytes carry pubdata.
+//! The last blob is zero-padded.
+
+use alloy::eips::eip4844::{BYTES_PER_BLOB, Blob, BlobTransactionSidecar, FIELD_ELEMENTS_PER_BLOB};
+
+const BYTES_PER_FIELD_ELEMENT: usize = BYTES_PER_BLOB / FIELD_ELEMENTS_PER_BLOB as usize;
+/// Pubdata bytes stored in a single field element.
+const DATA_BYTES_PER_FIELD_ELEMENT: usize = BYTES_PER_FIELD_ELEMENT - 1;
+
+/// Pubdata bytes stored in a single blob.
+pub const BLOB_DATA_CAPACITY: usize =
+    FIELD_ELEMENTS_PER_BLOB as usize * DATA_BYTES_PER_FIELD_ELEMENT;
+
+/// Number of blobs needed to publish `pubdata_len` bytes of pubdata.
+pub fn blob_count(pubdata_len: usize) -> usize {
+    pubdata_len.div_ceil(BLOB_DATA_CAPACITY)
+}
+
+/// Splits `pubdata` into blobs, see the module docs for the layout.
+pub fn pack_blobs(pubdata: &[u8]) -> Vec<Blob> {
+    pubdata
+        .chunks(BLOB_DATA_CAPACITY)
+        .map(|chunk| {
+            let mut blob = Blob::ZERO;
+            let field_elements = blob.chunks_exact_mut(BYTES_PER_FIELD_ELEMENT);
+            for (element, data) in field_elements.zip(chunk.chunks(DATA_BYTES_PER_FIELD_ELEMENT)) {
+                element[1..=data.len()].copy_from_slice(data);
+            }
+            blob
+        })
+        .collect()
+}
+
+/// Sidecar (blobs with their KZG commitments and proofs) publishing `pubdata`,
+/// or `None` if there is no pubdata to publish.
+pub fn blob_sidecar(pubdata: &[u8]) -> anyhow::Result<Option<BlobTransactionSidecar>> {
+    if pubdata.is_empty() {
+        return Ok(None);
+    }
+    let sidecar = BlobTransactionSidecar::try_from_blobs(pack_blobs(pubdata))
+        .map_err(|err| anyhow::anyhow!("failed to compute KZG commitments of blobs: {err}"))?;
+    Ok(Some(sidecar))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pubdata(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8 + 1).collect()
+    }
+
+    /// Reverses [`pack_blobs`], keeping the zero padding of the last blob.
+    fn unpack_blobs(blobs: &[Blob]) -> Vec<u8> {
+        blobs
+            .iter()
+            .flat_map(|blob| blob.chunks_exact(BYTES_PER_FIELD_ELEMENT))
+            .flat_map(|element| {
+                assert_eq!(element[0], 0);
+                element[1..].to_vec()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn empty_pubdata_has_no_sidecar() {
+        assert_eq!(blob_count(0), 0);
+        assert!(pack_blobs(&[]).is_empty());
+        assert!(blob_sidecar(&[]).unwrap().is_none());
+    }
+
+    #[test]
+    fn pubdata_filling_exactly_one_blob() {
+        let pubdata = pubdata(BLOB_DATA_CAPACITY);
+        assert_eq!(blob_count(pubdata.len()), 1);
+
+        let sidecar = blob_sidecar(&pubdata).unwrap().unwrap();
+        assert_eq!(sidecar.blobs.len(), 1);
+        assert_eq!(sidecar.commitments.len(), 1);
+        assert_eq!(sidecar.proofs.len(), 1);
+        assert_eq!(sidecar.versioned_hashes().count(), 1);
+        assert_eq!(unpack_blobs(&sidecar.blobs), pubdata);
+    }
+
+    #[test]
+    fn pubdata_just_over_several_blobs() {
+        let pubdata = pubdata(2 * BLOB_DATA_CAPACITY + 1);
+        assert_eq!(blob_count(pubdata.len()), 3);
+
+        let sidecar = blob_sidecar(&pubdata).unwrap().unwrap();
+        assert_eq!(sidecar.blobs.len(), 3);
+        assert_eq!(sidecar.commitments.len(), 3);
+        assert_eq!(sidecar.proofs.len(), 3);
+        assert_eq!(sidecar.versioned_hashes().count(), 3);
+
+        // The last blob only carries the last byte, right after the zero byte of its first element
+        let last_blob = &sidecar.blobs[2];
+        assert_eq!(last_blob[..2], [0, *pubdata.last().unwrap()]);
+        assert!(last_blob[2..].iter().all(|&byte| byte == 0));
+
+        let unpacked = unpack_blobs(&sidecar.blobs);
+        assert_eq!(unpacked[..pubdata.len()], pubdata);
+        assert!(unpacked[pubdata.len()..].iter().all(|&byte| byte == 0));
+    }
+}
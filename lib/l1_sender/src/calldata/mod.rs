@@ -0,0 +1,227 @@
+//! Decoding of the calldata of commit/prove/execute transactions built by [`crate::commands`].
+//!
+//! Decoders perform the same structural checks as the L1 `Executor` contract (version prefixes,
+//! batch ranges, unused fields) and additionally require the encoding to be canonical, so that
+//! any change to the calldata produced by the l1-sender is caught before it reaches L1.
+
+use crate::commands::execute::EXECUTE_ENCODING_VERSION;
+use crate::commands::prove::PROOF_ENCODING_VERSION;
+use crate::commitment::COMMITMENT_ENCODING_VERSION;
+use alloy::primitives::{Address, U256};
+use alloy::sol_types::{SolCall, SolValue};
+use anyhow::Context;
+use zksync_os_contract_interface::models::{
+    CommitBatchInfo, PriorityOpsBatchInfo, StoredBatchInfo,
+};
+use zksync_os_contract_interface::{IExecutor, InteropRoot};
+
+#[cfg(test)]
+mod tests;
+
+/// Decoded `commitBatchesSharedBridge` calldata.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedCommit {
+    pub chain_address: Address,
+    /// Last committed batch. `last_block_timestamp` is not part of the calldata and is set to 0.
+    pub previous_batch: StoredBatchInfo,
+    pub batches: Vec<CommitBatchInfo>,
+}
+
+/// Decoded `proveBatchesSharedBridge` calldata.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedProof {
+    pub chain_address: Address,
+    /// Last proven batch. `last_block_timestamp` is not part of the calldata and is set to 0.
+    pub previous_batch: StoredBatchInfo,
+    /// Proven batches. `last_block_timestamp` is not part of the calldata and is set to 0.
+    pub batches: Vec<StoredBatchInfo>,
+    /// Proof as passed to the verifier, including the proof type and public input.
+    pub proof: Vec<U256>,
+}
+
+/// Decoded `executeBatchesSharedBridge` calldata.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedExecute {
+    pub chain_address: Address,
+    /// Executed batches. `last_block_timestamp` is not part of the calldata and is set to 0.
+    pub batches: Vec<StoredBatchInfo>,
+    pub priority_ops: Vec<PriorityOpsBatchInfo>,
+}
+
+/// Decodes the input of a commit transaction.
+pub fn decode_commit(input: &[u8]) -> anyhow::Result<DecodedCommit> {
+    let call = IExecutor::commitBatchesSharedBridgeCall::abi_decode(input)
+        .context("invalid `commitBatchesSharedBridge` calldata")?;
+    let commit_data = versioned_data(&call._commitData, COMMITMENT_ENCODING_VERSION)?;
+    let (previous_batch, batches) = <(
+        IExecutor::StoredBatchInfo,
+        Vec<IExecutor::CommitBatchInfoZKsyncOS>,
+    )>::abi_decode_params(commit_data)
+    .context("invalid commit data")?;
+    ensure_canonical(
+        commit_data,
+        &(previous_batch.clone(), batches.clone()).abi_encode_params(),
+        "commit data",
+    )?;
+    ensure_canonical(input, &call.abi_encode(), "commit calldata")?;
+
+    check_batch_range(
+        call._processFrom,
+        call._processTo,
+        Some(previous_batch.batchNumber),
+        batches.iter().map(|batch| batch.batchNumber),
+    )?;
+    let batches = batches
+        .into_iter()
+        .map(|batch| {
+            let batch_number = batch.batchNumber;
+            anyhow::ensure!(
+                u64::try_from(batch.numberOfLayer1Txs).is_ok()
+                    && u64::try_from(batch.chainId).is_ok(),
+                "batch {batch_number} has out of range values"
+            );
+            Ok(CommitBatchInfo::from(batch))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    Ok(DecodedCommit {
+        chain_address: call._chainAddress,
+        previous_batch: stored_batch_info(previous_batch)?,
+        batches,
+    })
+}
+
+/// Decodes the input of a prove transaction.
+pub fn decode_proof(input: &[u8]) -> anyhow::Result<DecodedProof> {
+    let call = IExecutor::proveBatchesSharedBridgeCall::abi_decode(input)
+        .context("invalid `proveBatchesSharedBridge` calldata")?;
+    let proof_data = versioned_data(&call._proofData, PROOF_ENCODING_VERSION)?;
+    let payload =
+        IExecutor::proofPayloadCall::abi_decode_raw(proof_data).context("invalid proof data")?;
+    let mut encoded_payload = vec![];
+    payload.abi_encode_raw(&mut encoded_payload);
+    ensure_canonical(proof_data, &encoded_payload, "proof data")?;
+    ensure_canonical(input, &call.abi_encode(), "proof calldata")?;
+
+    check_batch_range(
+        call._processBatchFrom,
+        call._processBatchTo,
+        Some(payload.old.batchNumber),
+        payload.newInfo.iter().map(|batch| batch.batchNumber),
+    )?;
+    Ok(DecodedProof {
+        chain_address: call._chainAddress,
+        previous_batch: stored_batch_info(payload.old)?,
+        batches: payload
+            .newInfo
+            .into_iter()
+            .map(stored_batch_info)
+            .collect::<anyhow::Result<_>>()?,
+        proof: payload.proof,
+    })
+}
+
+/// Decodes the input of an execute transaction.
+pub fn decode_execute(input: &[u8]) -> anyhow::Result<DecodedExecute> {
+    let call = IExecutor::executeBatchesSharedBridgeCall::abi_decode(input)
+        .context("invalid `executeBatchesSharedBridge` calldata")?;
+    let execute_data = versioned_data(&call._executeData, EXECUTE_ENCODING_VERSION)?;
+    let (batches, priority_ops, interop_roots) = <(
+        Vec<IExecutor::StoredBatchInfo>,
+        Vec<IExecutor::PriorityOpsBatchInfo>,
+        Vec<Vec<InteropRoot>>,
+    )>::abi_decode_params(execute_data)
+    .context("invalid execute data")?;
+    ensure_canonical(
+        execute_data,
+        &(batches.clone(), priority_ops.clone(), interop_roots.clone()).abi_encode_params(),
+        "execute data",
+    )?;
+    ensure_canonical(input, &call.abi_encode(), "execute calldata")?;
+
+    check_batch_range(
+        call._processFrom,
+        call._processTo,
+        None,
+        batches.iter().map(|batch| batch.batchNumber),
+    )?;
+    anyhow::ensure!(
+        priority_ops.len() == batches.len() && interop_roots.len() == batches.len(),
+        "{} batches are executed with {} priority ops and {} interop roots",
+        batches.len(),
+        priority_ops.len(),
+        interop_roots.len()
+    );
+    // Interop roots are not sent yet
+    anyhow::ensure!(
+        interop_roots.iter().all(Vec::is_empty),
+        "unexpected interop roots"
+    );
+    Ok(DecodedExecute {
+        chain_address: call._chainAddress,
+        batches: batches
+            .into_iter()
+            .map(stored_batch_info)
+            .collect::<anyhow::Result<_>>()?,
+        priority_ops: priority_ops.into_iter().map(Into::into).collect(),
+    })
+}
+
+/// Splits off the encoding version prefix, checking that it's `expected_version`.
+fn versioned_data(data: &[u8], expected_version: u8) -> anyhow::Result<&[u8]> {
+    let (version, data) = data.split_first().context("empty data")?;
+    anyhow::ensure!(
+        *version == expected_version,
+        "unsupported encoding version {version}, expected {expected_version}"
+    );
+    Ok(data)
+}
+
+fn ensure_canonical(data: &[u8], reencoded: &[u8], name: &str) -> anyhow::Result<()> {
+    anyhow::ensure!(data == reencoded, "{name} is not canonically encoded");
+    Ok(())
+}
+
+/// Checks that `batch_numbers` are exactly the `process_from..=process_to` range following
+/// `previous_batch_number` (if any).
+fn check_batch_range(
+    process_from: U256,
+    process_to: U256,
+    previous_batch_number: Option<u64>,
+    batch_numbers: impl Iterator<Item = u64>,
+) -> anyhow::Result<()> {
+    let from = u64::try_from(process_from).context("batch range start is out of range")?;
+    let to = u64::try_from(process_to).context("batch range end is out of range")?;
+    let batch_numbers: Vec<_> = batch_numbers.collect();
+    anyhow::ensure!(
+        from <= to && batch_numbers.iter().copied().eq(from..=to),
+        "batches {batch_numbers:?} don't match range {from}..={to}"
+    );
+    if let Some(previous_batch_number) = previous_batch_number {
+        anyhow::ensure!(
+            previous_batch_number.checked_add(1) == Some(from),
+            "batch {from} doesn't follow previous batch {previous_batch_number}"
+        );
+    }
+    Ok(())
+}
+
+fn stored_batch_info(info: IExecutor::StoredBatchInfo) -> anyhow::Result<StoredBatchInfo> {
+    let batch_number = info.batchNumber;
+    // Not used in ZKsync OS, must be zero
+    anyhow::ensure!(
+        info.indexRepeatedStorageChanges == 0 && info.timestamp.is_zero(),
+        "stored batch info {batch_number} has unused fields set"
+    );
+    Ok(StoredBatchInfo {
+        batch_number,
+        state_commitment: info.batchHash,
+        number_of_layer1_txs: u64::try_from(info.numberOfLayer1Txs).with_context(|| {
+            format!("stored batch info {batch_number} has out of range number of L1 txs")
+        })?,
+        priority_operations_hash: info.priorityOperationsHash,
+        dependency_roots_rolling_hash: info.dependencyRootsRollingHash,
+        l2_to_l1_logs_root_hash: info.l2LogsTreeRoot,
+        commitment: info.commitment,
+        last_block_timestamp: 0,
+    })
+}
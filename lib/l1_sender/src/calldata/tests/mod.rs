@@ -0,0 +1,451 @@
+//! Golden and round-trip tests locking down the calldata of L1 batch transactions.
+
+use super::*;
+use crate::batcher_model::{RealSnarkProof, SnarkProof};
+use crate::commands::commit::CommitCommand;
+use crate::commands::execute::ExecuteCommand;
+use crate::commands::prove::ProofCommand;
+use alloy::hex;
+use alloy::network::TransactionBuilder;
+use alloy::primitives::{B256, keccak256};
+use alloy::providers::{Provider, ProviderBuilder};
+use alloy::rpc::types::TransactionRequest;
+use alloy::sol_types::SolError;
+use itertools::Itertools;
+use std::fmt;
+
+mod prop;
+
+const GOLDEN_DIR: &str = "src/calldata/tests/golden";
+
+/// Commit of a single batch following `previous_batch`.
+#[derive(Clone)]
+struct CommitFixture {
+    previous_batch: StoredBatchInfo,
+    batch: CommitBatchInfo,
+}
+
+// `CommitBatchInfo` skips the operator DA input in its debug output, but it's a part of calldata
+impl fmt::Debug for CommitFixture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CommitFixture")
+            .field("previous_batch", &self.previous_batch)
+            .field("batch", &self.batch)
+            .field(
+                "operator_da_input",
+                &hex::encode_prefixed(&self.batch.operator_da_input),
+            )
+            .finish()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ProofFixture {
+    previous_batch: StoredBatchInfo,
+    batches: Vec<StoredBatchInfo>,
+    proof: SnarkProof,
+}
+
+#[derive(Clone, Debug)]
+struct ExecuteFixture {
+    batches: Vec<StoredBatchInfo>,
+    priority_ops: Vec<PriorityOpsBatchInfo>,
+}
+
+#[derive(Clone, Debug)]
+enum Fixture {
+    Commit(CommitFixture),
+    Proof(ProofFixture),
+    Execute(ExecuteFixture),
+}
+
+impl Fixture {
+    fn calldata(&self, chain_address: Address) -> Vec<u8> {
+        match self {
+            Self::Commit(commit) => CommitCommand::commit_call(
+                chain_address,
+                &commit.previous_batch,
+                commit.batch.clone(),
+            )
+            .abi_encode(),
+            Self::Proof(proof) => ProofCommand::prove_call(
+                chain_address,
+                &proof.previous_batch,
+                &proof.batches,
+                &proof.proof,
+            )
+            .abi_encode(),
+            Self::Execute(execute) => {
+                ExecuteCommand::execute_call(chain_address, &execute.batches, &execute.priority_ops)
+                    .abi_encode()
+            }
+        }
+    }
+
+    /// Checks that calldata of the fixture decodes into the encoded structures.
+    fn assert_roundtrip(&self) {
+        let chain_address = Address::repeat_byte(0xaa);
+        let calldata = self.calldata(chain_address);
+        match self {
+            Self::Commit(commit) => {
+                let decoded = decode_commit(&calldata).unwrap();
+                let expected = DecodedCommit {
+                    chain_address,
+                    previous_batch: without_timestamp(&commit.previous_batch),
+                    batches: vec![commit.batch.clone()],
+                };
+                assert_eq!(decoded, expected, "{commit:?}");
+                // `CommitBatchInfo` equality covers the DA input, but its debug output doesn't
+                assert_eq!(
+                    hex::encode(&decoded.batches[0].operator_da_input),
+                    hex::encode(&commit.batch.operator_da_input)
+                );
+            }
+            Self::Proof(proof) => {
+                let decoded = decode_proof(&calldata).unwrap();
+                let (_, verifier_proof) = ProofCommand::verifier_inputs(
+                    &proof.previous_batch,
+                    &proof.batches,
+                    &proof.proof,
+                );
+                let expected = DecodedProof {
+                    chain_address,
+                    previous_batch: without_timestamp(&proof.previous_batch),
+                    batches: proof.batches.iter().map(without_timestamp).collect(),
+                    proof: verifier_proof,
+                };
+                assert_eq!(decoded, expected);
+            }
+            Self::Execute(execute) => {
+                let decoded = decode_execute(&calldata).unwrap();
+                let expected = DecodedExecute {
+                    chain_address,
+                    batches: execute.batches.iter().map(without_timestamp).collect(),
+                    priority_ops: execute.priority_ops.clone(),
+                };
+                assert_eq!(decoded, expected);
+            }
+        }
+    }
+}
+
+/// Stored batch info as decoded from calldata, which doesn't include the last block timestamp.
+fn without_timestamp(info: &StoredBatchInfo) -> StoredBatchInfo {
+    StoredBatchInfo {
+        last_block_timestamp: 0,
+        ..info.clone()
+    }
+}
+
+fn stored(batch_number: u64) -> StoredBatchInfo {
+    StoredBatchInfo {
+        batch_number,
+        state_commitment: B256::repeat_byte(batch_number as u8),
+        number_of_layer1_txs: batch_number,
+        priority_operations_hash: B256::repeat_byte(0x77),
+        dependency_roots_rolling_hash: B256::ZERO,
+        l2_to_l1_logs_root_hash: B256::repeat_byte(0x88),
+        commitment: B256::repeat_byte(0x80 | batch_number as u8),
+        last_block_timestamp: 1_700_000_000,
+    }
+}
+
+fn commit_batch_info(batch_number: u64, operator_da_input: Vec<u8>) -> CommitBatchInfo {
+    CommitBatchInfo {
+        batch_number,
+        new_state_commitment: B256::repeat_byte(0x11),
+        number_of_layer1_txs: 3,
+        priority_operations_hash: B256::repeat_byte(0x22),
+        dependency_roots_rolling_hash: B256::ZERO,
+        l2_to_l1_logs_root_hash: B256::repeat_byte(0x33),
+        l2_da_validator: Address::repeat_byte(0x44),
+        da_commitment: B256::repeat_byte(0x55),
+        first_block_timestamp: 1_700_000_000,
+        last_block_timestamp: 1_700_000_010,
+        chain_id: 270,
+        operator_da_input,
+    }
+}
+
+/// Rollup operator DA input in the format built by `BatchInfo::new()`.
+fn rollup_operator_da_input(pubdata: &[u8]) -> Vec<u8> {
+    [
+        B256::ZERO.as_slice(),
+        keccak256(pubdata).as_slice(),
+        &[1],
+        B256::ZERO.as_slice(),
+        &[0],
+        pubdata,
+        B256::ZERO.as_slice(),
+    ]
+    .concat()
+}
+
+fn commit(previous_batch: StoredBatchInfo, batch: CommitBatchInfo) -> Fixture {
+    Fixture::Commit(CommitFixture {
+        previous_batch,
+        batch,
+    })
+}
+
+fn fixtures() -> Vec<(&'static str, Fixture)> {
+    let pubdata: Vec<u8> = (0..45).collect();
+    let single_block = CommitBatchInfo {
+        number_of_layer1_txs: 0,
+        priority_operations_hash: keccak256(b""),
+        last_block_timestamp: 1_700_000_000,
+        ..commit_batch_info(1, rollup_operator_da_input(&[]))
+    };
+    let max_values = CommitBatchInfo {
+        batch_number: u64::MAX,
+        new_state_commitment: B256::repeat_byte(0xff),
+        number_of_layer1_txs: u64::MAX,
+        priority_operations_hash: B256::repeat_byte(0xff),
+        dependency_roots_rolling_hash: B256::repeat_byte(0xff),
+        l2_to_l1_logs_root_hash: B256::repeat_byte(0xff),
+        l2_da_validator: Address::repeat_byte(0xff),
+        da_commitment: B256::repeat_byte(0xff),
+        first_block_timestamp: u64::MAX,
+        last_block_timestamp: u64::MAX,
+        chain_id: u64::MAX,
+        operator_da_input: rollup_operator_da_input(&[0xff; 33]),
+    };
+    let max_values_previous = StoredBatchInfo {
+        number_of_layer1_txs: u64::MAX,
+        ..stored(u64::MAX - 1)
+    };
+    let real_proof = SnarkProof::Real(RealSnarkProof::V2 {
+        proof: [[0x01; 32], [0x02; 32]].concat(),
+        proving_execution_version: 3,
+    });
+    let priority_ops = PriorityOpsBatchInfo {
+        left_path: vec![B256::repeat_byte(0x01)],
+        right_path: vec![B256::repeat_byte(0x02)],
+        item_hashes: vec![B256::repeat_byte(0x03), B256::repeat_byte(0x04)],
+    };
+
+    vec![
+        (
+            "commit_calldata_pubdata",
+            commit(
+                stored(4),
+                commit_batch_info(5, rollup_operator_da_input(&pubdata)),
+            ),
+        ),
+        (
+            "commit_empty_pubdata",
+            commit(
+                stored(4),
+                commit_batch_info(5, rollup_operator_da_input(&[])),
+            ),
+        ),
+        (
+            "commit_validium",
+            commit(
+                stored(4),
+                commit_batch_info(5, U256::ZERO.to_be_bytes_vec()),
+            ),
+        ),
+        ("commit_single_block", commit(stored(0), single_block)),
+        ("commit_max_values", commit(max_values_previous, max_values)),
+        (
+            "prove_fake_single",
+            Fixture::Proof(ProofFixture {
+                previous_batch: stored(4),
+                batches: vec![stored(5)],
+                proof: SnarkProof::Fake,
+            }),
+        ),
+        (
+            "prove_fake_range",
+            Fixture::Proof(ProofFixture {
+                previous_batch: stored(4),
+                batches: (5..=7).map(stored).collect(),
+                proof: SnarkProof::Fake,
+            }),
+        ),
+        (
+            "prove_real_range",
+            Fixture::Proof(ProofFixture {
+                previous_batch: stored(4),
+                batches: (5..=7).map(stored).collect(),
+                proof: real_proof,
+            }),
+        ),
+        (
+            "execute_single_no_priority_ops",
+            Fixture::Execute(ExecuteFixture {
+                batches: vec![stored(1)],
+                priority_ops: vec![PriorityOpsBatchInfo::default()],
+            }),
+        ),
+        (
+            "execute_range_with_priority_ops",
+            Fixture::Execute(ExecuteFixture {
+                batches: (5..=6).map(stored).collect(),
+                priority_ops: vec![priority_ops, PriorityOpsBatchInfo::default()],
+            }),
+        ),
+    ]
+}
+
+fn fixture(name: &str) -> Fixture {
+    let (_, fixture) = fixtures()
+        .into_iter()
+        .find(|(fixture_name, _)| *fixture_name == name)
+        .unwrap();
+    fixture
+}
+
+/// Formats calldata as the selector followed by 32-byte words, one per line, so that golden file
+/// diffs point to the changed words.
+fn format_calldata(calldata: &[u8]) -> String {
+    let (selector, words) = calldata.split_at(4);
+    assert_eq!(words.len() % 32, 0, "calldata is not word-aligned");
+    std::iter::once(selector)
+        .chain(words.chunks(32))
+        .map(hex::encode)
+        .join("\n")
+        + "\n"
+}
+
+#[test]
+fn calldata_matches_golden_files() {
+    for (name, fixture) in fixtures() {
+        let path = format!("{GOLDEN_DIR}/{name}.hex");
+        let expected = std::fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("failed to read golden file {path}: {err}"));
+        let actual = format_calldata(&fixture.calldata(Address::repeat_byte(0xaa)));
+
+        let expected: Vec<_> = expected.lines().collect();
+        let actual: Vec<_> = actual.lines().collect();
+        let mismatched_lines: Vec<_> = (0..expected.len().max(actual.len()))
+            .filter(|&i| expected.get(i) != actual.get(i))
+            .collect();
+        assert!(
+            mismatched_lines.is_empty(),
+            "calldata of `{name}` differs from {path} in lines {mismatched_lines:?} (line 0 is the \
+             selector, line N is the word at offset 4 + 32 * (N - 1)); if the change is intended \
+             and L1 contracts accept it, regenerate golden files with `generate_golden_files`.\n\
+             fixture: {fixture:?}"
+        );
+    }
+}
+
+// Regenerates the golden files. Only to be run on intended calldata changes
+#[test]
+#[ignore]
+fn generate_golden_files() {
+    for (name, fixture) in fixtures() {
+        let calldata = fixture.calldata(Address::repeat_byte(0xaa));
+        std::fs::write(
+            format!("{GOLDEN_DIR}/{name}.hex"),
+            format_calldata(&calldata),
+        )
+        .expect("Failed to write golden file");
+    }
+}
+
+#[test]
+fn fixtures_roundtrip() {
+    for (_, fixture) in fixtures() {
+        fixture.assert_roundtrip();
+    }
+}
+
+#[test]
+fn invalid_calldata_is_rejected() {
+    let Fixture::Commit(commit) = fixture("commit_calldata_pubdata") else {
+        unreachable!()
+    };
+    let call = CommitCommand::commit_call(
+        Address::repeat_byte(0xaa),
+        &commit.previous_batch,
+        commit.batch,
+    );
+
+    let mut unsupported_version = call.clone();
+    let mut commit_data = unsupported_version._commitData.to_vec();
+    commit_data[0] = COMMITMENT_ENCODING_VERSION + 1;
+    unsupported_version._commitData = commit_data.into();
+    let err = decode_commit(&unsupported_version.abi_encode()).unwrap_err();
+    assert!(
+        err.to_string().contains("unsupported encoding version"),
+        "{err}"
+    );
+
+    let mut wrong_range = call.clone();
+    wrong_range._processTo += U256::from(1);
+    let err = decode_commit(&wrong_range.abi_encode()).unwrap_err();
+    assert!(err.to_string().contains("don't match range"), "{err}");
+
+    let mut trailing_bytes = call.clone();
+    let mut commit_data = trailing_bytes._commitData.to_vec();
+    commit_data.extend_from_slice(&[0; 32]);
+    trailing_bytes._commitData = commit_data.into();
+    let err = decode_commit(&trailing_bytes.abi_encode()).unwrap_err();
+    assert!(err.to_string().contains("not canonically encoded"), "{err}");
+
+    let proof_calldata = fixture("prove_fake_single").calldata(Address::repeat_byte(0xaa));
+    decode_commit(&proof_calldata).unwrap_err();
+    decode_execute(&proof_calldata).unwrap_err();
+}
+
+alloy::sol! {
+    // Errors of the L1 `BatchDecoder` library, i.e. errors meaning that calldata cannot be decoded
+    error EmptyData();
+    error UnsupportedCommitBatchEncoding(uint8 version);
+    error UnsupportedProofBatchEncoding(uint8 version);
+    error UnsupportedExecuteBatchEncoding(uint8 version);
+    error IncorrectBatchBounds(
+        uint256 processFromExpected,
+        uint256 processToExpected,
+        uint256 processFromProvided,
+        uint256 processToProvided
+    );
+}
+
+/// Checks that the L1 contracts decode the golden calldata. Fixture batches don't extend the
+/// actual chain, so calls are expected to revert, but not with a decoding error. Requires an L1
+/// node with deployed contracts (e.g. anvil with the local setup state):
+///
+/// `L1_RPC_URL=http://localhost:8545 L1_CHAIN_ADDRESS=.. L1_VALIDATOR_TIMELOCK=.. L1_OPERATOR=..
+/// cargo test -p zksync_os_l1_sender -- --ignored l1_contracts_decode_calldata`
+#[tokio::test]
+#[ignore]
+async fn l1_contracts_decode_calldata() {
+    let env = |name: &str| std::env::var(name).unwrap_or_else(|_| panic!("{name} is not set"));
+    let provider = ProviderBuilder::new()
+        .disable_recommended_fillers()
+        .connect_http(env("L1_RPC_URL").parse().unwrap());
+    let chain_address: Address = env("L1_CHAIN_ADDRESS").parse().unwrap();
+    let validator_timelock: Address = env("L1_VALIDATOR_TIMELOCK").parse().unwrap();
+    let operator: Address = env("L1_OPERATOR").parse().unwrap();
+    let decoding_errors = [
+        EmptyData::SELECTOR,
+        UnsupportedCommitBatchEncoding::SELECTOR,
+        UnsupportedProofBatchEncoding::SELECTOR,
+        UnsupportedExecuteBatchEncoding::SELECTOR,
+        IncorrectBatchBounds::SELECTOR,
+    ];
+
+    for (name, fixture) in fixtures() {
+        let tx = TransactionRequest::default()
+            .with_from(operator)
+            .with_to(validator_timelock)
+            .with_input(fixture.calldata(chain_address));
+        let Err(err) = provider.call(tx).await else {
+            continue;
+        };
+        let revert_data = err
+            .as_error_resp()
+            .and_then(|payload| payload.as_revert_data())
+            .unwrap_or_else(|| panic!("`{name}` call failed: {err}"));
+        // `abi.decode()` failures revert without data
+        assert!(
+            revert_data.len() >= 4
+                && !decoding_errors.contains(&revert_data[..4].try_into().unwrap()),
+            "L1 contracts failed to decode `{name}` calldata: {revert_data}"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Property tests checking that calldata built by L1 commands decodes into the encoded structures.
+//! On failure, proptest reports the minimal (shrunk) fixture that doesn't round-trip.
+
+use super::*;
+use proptest::prelude::*;
+
+const MAX_BATCHES: usize = 5;
+
+fn hash() -> impl Strategy<Value = B256> {
+    any::<[u8; 32]>().prop_map(B256::from)
+}
+
+fn address() -> impl Strategy<Value = Address> {
+    any::<[u8; 20]>().prop_map(Address::from)
+}
+
+fn gen_stored_batch_info(batch_number: u64) -> impl Strategy<Value = StoredBatchInfo> {
+    (
+        hash(),
+        any::<u64>(),
+        hash(),
+        hash(),
+        hash(),
+        hash(),
+        any::<u64>(),
+    )
+        .prop_map(
+            move |(
+                state_commitment,
+                number_of_layer1_txs,
+                priority_operations_hash,
+                dependency_roots_rolling_hash,
+                l2_to_l1_logs_root_hash,
+                commitment,
+                last_block_timestamp,
+            )| StoredBatchInfo {
+                batch_number,
+                state_commitment,
+                number_of_layer1_txs,
+                priority_operations_hash,
+                dependency_roots_rolling_hash,
+                l2_to_l1_logs_root_hash,
+                commitment,
+                last_block_timestamp,
+            },
+        )
+}
+
+fn gen_commit_batch_info(batch_number: u64) -> impl Strategy<Value = CommitBatchInfo> {
+    let operator_da_input = prop_oneof![
+        // Validium
+        Just(U256::ZERO.to_be_bytes_vec()),
+        proptest::collection::vec(any::<u8>(), 0..200)
+            .prop_map(|pubdata| rollup_operator_da_input(&pubdata)),
+        // Arbitrary input, e.g. with versioned hashes of blobs
+        proptest::collection::vec(any::<u8>(), 0..200),
+    ];
+    (
+        (hash(), any::<u64>(), hash(), hash(), hash()),
+        (address(), hash(), any::<u64>(), any::<u64>(), any::<u64>()),
+        operator_da_input,
+    )
+        .prop_map(
+            move |(
+                (
+                    new_state_commitment,
+                    number_of_layer1_txs,
+                    priority_operations_hash,
+                    dependency_roots_rolling_hash,
+                    l2_to_l1_logs_root_hash,
+                ),
+                (
+                    l2_da_validator,
+                    da_commitment,
+                    first_block_timestamp,
+                    last_block_timestamp,
+                    chain_id,
+                ),
+                operator_da_input,
+            )| CommitBatchInfo {
+                batch_number,
+                new_state_commitment,
+                number_of_layer1_txs,
+                priority_operations_hash,
+                dependency_roots_rolling_hash,
+                l2_to_l1_logs_root_hash,
+                l2_da_validator,
+                da_commitment,
+                first_block_timestamp,
+                last_block_timestamp,
+                chain_id,
+                operator_da_input,
+            },
+        )
+}
+
+/// Consecutive batches `first_batch_number..` of the given length.
+fn gen_batches(
+    first_batch_number: u64,
+    count: usize,
+) -> Vec<impl Strategy<Value = StoredBatchInfo>> {
+    (first_batch_number..)
+        .take(count)
+        .map(gen_stored_batch_info)
+        .collect()
+}
+
+fn gen_snark_proof() -> impl Strategy<Value = SnarkProof> {
+    prop_oneof![
+        Just(SnarkProof::Fake),
+        (
+            proptest::collection::vec(any::<[u8; 32]>(), 0..4),
+            // Execution versions with a verifier
+            proptest::sample::select(vec![1_u32, 2, 3, 4]),
+        )
+            .prop_map(|(words, proving_execution_version)| {
+                SnarkProof::Real(RealSnarkProof::V2 {
+                    proof: words.concat(),
+                    proving_execution_version,
+                })
+            }),
+    ]
+}
+
+fn gen_priority_ops() -> impl Strategy<Value = PriorityOpsBatchInfo> {
+    let hashes = || proptest::collection::vec(hash(), 0..3);
+    (hashes(), hashes(), hashes()).prop_map(|(left_path, right_path, item_hashes)| {
+        PriorityOpsBatchInfo {
+            left_path,
+            right_path,
+            item_hashes,
+        }
+    })
+}
+
+fn gen_commit() -> impl Strategy<Value = Fixture> {
+    (1..=u64::MAX).prop_flat_map(|batch_number| {
+        (
+            gen_stored_batch_info(batch_number - 1),
+            gen_commit_batch_info(batch_number),
+        )
+            .prop_map(|(previous_batch, batch)| commit(previous_batch, batch))
+    })
+}
+
+fn gen_proof() -> impl Strategy<Value = Fixture> {
+    // Batch numbers are bounded, since public input computation indexes batches by `usize`
+    (1..u64::from(u32::MAX), 1..=MAX_BATCHES).prop_flat_map(|(first_batch_number, count)| {
+        (
+            gen_stored_batch_info(first_batch_number - 1),
+            gen_batches(first_batch_number, count),
+            gen_snark_proof(),
+        )
+            .prop_map(|(previous_batch, batches, proof)| {
+                Fixture::Proof(ProofFixture {
+                    previous_batch,
+                    batches,
+                    proof,
+                })
+            })
+    })
+}
+
+fn gen_execute() -> impl Strategy<Value = Fixture> {
+    (any::<u64>(), 1..=MAX_BATCHES).prop_flat_map(|(first_batch_number, count)| {
+        let first_batch_number = first_batch_number.min(u64::MAX - count as u64);
+        (
+            gen_batches(first_batch_number, count),
+            proptest::collection::vec(gen_priority_ops(), count),
+        )
+            .prop_map(|(batches, priority_ops)| {
+                Fixture::Execute(ExecuteFixture {
+                    batches,
+                    priority_ops,
+                })
+            })
+    })
+}
+
+proptest! {
+    #[test]
+    fn commit_calldata_roundtrips(fixture in gen_commit()) {
+        fixture.assert_roundtrip();
+    }
+
+    #[test]
+    fn proof_calldata_roundtrips(fixture in gen_proof()) {
+        fixture.assert_roundtrip();
+    }
+
+    #[test]
+    fn execute_calldata_roundtrips(fixture in gen_execute()) {
+        fixture.assert_roundtrip();
+    }
+}
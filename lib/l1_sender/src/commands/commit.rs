@@ -1,40 +1,85 @@
 use crate::batcher_metrics::BatchExecutionStage;
-use crate::batcher_model::{FriProof, SignedBatchEnvelope};
+use crate::batcher_model::{FriProof, L1BatchOperation, SignedBatchEnvelope};
+use crate::blobs::blob_sidecar;
 use crate::commands::SendToL1;
-use alloy::primitives::U256;
+use crate::commitment::{
+    COMMITMENT_ENCODING_VERSION, blobs_operator_da_input, split_calldata_operator_da_input,
+};
+use alloy::eips::eip4844::BlobTransactionSidecar;
+use alloy::primitives::{Address, U256};
 use alloy::sol_types::{SolCall, SolValue};
+use anyhow::Context;
 use std::fmt::Display;
 use zksync_os_contract_interface::IExecutor;
-use zksync_os_contract_interface::models::BatchDaInputMode;
+use zksync_os_contract_interface::models::{CommitBatchInfo, StoredBatchInfo};
+use zksync_os_gas_adjuster::{PubdataMode, count_zero_bytes};
 
 #[derive(Debug)]
 pub struct CommitCommand {
     input: SignedBatchEnvelope<FriProof>,
-    da_input_mode: BatchDaInputMode,
+    pubdata_mode: PubdataMode,
+    /// Blobs publishing the batch pubdata; only set in `Blobs` pubdata mode for batches
+    /// with non-empty pubdata.
+    blob_sidecar: Option<BlobTransactionSidecar>,
 }
 
 impl CommitCommand {
-    pub fn new(input: SignedBatchEnvelope<FriProof>, da_input_mode: BatchDaInputMode) -> Self {
-        Self {
+    /// Fails if pubdata is published in blobs, but cannot be extracted from the batch
+    /// or packed into blobs.
+    pub fn new(
+        input: SignedBatchEnvelope<FriProof>,
+        pubdata_mode: PubdataMode,
+    ) -> anyhow::Result<Self> {
+        let blob_sidecar = match pubdata_mode {
+            PubdataMode::Blobs => {
+                let operator_da_input = &input.batch.batch_info.commit_info.operator_da_input;
+                let (_, pubdata) = split_calldata_operator_da_input(operator_da_input)
+                    .with_context(|| {
+                        format!(
+                            "batch {} has no rollup pubdata to publish in blobs",
+                            input.batch_number()
+                        )
+                    })?;
+                blob_sidecar(pubdata)?
+            }
+            PubdataMode::Calldata | PubdataMode::Validium => None,
+        };
+        Ok(Self {
             input,
-            da_input_mode,
-        }
+            pubdata_mode,
+            blob_sidecar,
+        })
     }
 }
 
 impl SendToL1 for CommitCommand {
     const NAME: &'static str = "commit";
+    const OPERATION: L1BatchOperation = L1BatchOperation::Commit;
     const SENT_STAGE: BatchExecutionStage = BatchExecutionStage::CommitL1TxSent;
     const MINED_STAGE: BatchExecutionStage = BatchExecutionStage::CommitL1TxMined;
     const PASSTHROUGH_STAGE: BatchExecutionStage = BatchExecutionStage::CommitL1Passthrough;
+    const VALIDATOR_ROLE: &'static str = "COMMITTER_ROLE";
+    const GUARDS_COMMITMENT_FORMAT: bool = true;
 
     fn solidity_call(&self) -> impl SolCall {
-        IExecutor::commitBatchesSharedBridgeCall::new((
+        Self::commit_call(
             self.input.batch.batch_info.chain_address,
-            U256::from(self.input.batch_number()),
-            U256::from(self.input.batch_number()),
-            self.to_calldata_suffix().into(),
-        ))
+            &self.input.batch.previous_stored_batch_info,
+            self.committed_batch_info(),
+        )
+    }
+
+    fn pubdata_composition(&self) -> Option<(u64, u64)> {
+        match self.pubdata_mode {
+            PubdataMode::Calldata | PubdataMode::Blobs => Some(count_zero_bytes(
+                &self.input.batch.batch_info.commit_info.operator_da_input,
+            )),
+            PubdataMode::Validium => None,
+        }
+    }
+
+    fn blob_sidecar(&self) -> Option<&BlobTransactionSidecar> {
+        self.blob_sidecar.as_ref()
     }
 }
 
@@ -64,34 +109,133 @@ impl Display for CommitCommand {
 }
 
 impl CommitCommand {
-    /// `commitBatchesSharedBridge` expects the rest of calldata to be of very specific form. This
-    /// function makes sure last committed batch and new batch are encoded correctly.
-    fn to_calldata_suffix(&self) -> Vec<u8> {
-        /// Current commitment encoding version for ZKsync OS.
-        const SUPPORTED_ENCODING_VERSION: u8 = 2;
+    /// `commitBatchesSharedBridge` call committing `batch` that follows `previous_batch`.
+    pub(crate) fn commit_call(
+        chain_address: Address,
+        previous_batch: &StoredBatchInfo,
+        batch: CommitBatchInfo,
+    ) -> IExecutor::commitBatchesSharedBridgeCall {
+        IExecutor::commitBatchesSharedBridgeCall::new((
+            chain_address,
+            U256::from(batch.batch_number),
+            U256::from(batch.batch_number),
+            Self::commit_data(previous_batch, batch).into(),
+        ))
+    }
 
-        let stored_batch_info =
-            IExecutor::StoredBatchInfo::from(&self.input.batch.previous_stored_batch_info);
-        let mut batch_info = self.input.batch.batch_info.clone();
+    /// Batch info with the operator DA input as committed in the configured pubdata mode.
+    fn committed_batch_info(&self) -> CommitBatchInfo {
+        let mut commit_info = self.input.batch.batch_info.commit_info.clone();
         // `BatchInfo` has full da input - even for validium chains we only drop `operator_da_input`
         // field when we are actually committing the batch this way, we don't need to consider the DA
-        // mode in advance - it's only known to the l1-sender.
-        batch_info.commit_info.operator_da_input = match self.da_input_mode {
-            BatchDaInputMode::Rollup => batch_info.commit_info.operator_da_input,
-            BatchDaInputMode::Validium => U256::ZERO.to_be_bytes_vec(),
+        // mode in advance - it's only known to the l1-sender. Similarly, pubdata published in blobs
+        // is replaced with the versioned hashes of the blobs.
+        commit_info.operator_da_input = match self.pubdata_mode {
+            PubdataMode::Calldata => commit_info.operator_da_input,
+            PubdataMode::Blobs => {
+                // Safe unwrap: checked in `new()`
+                let (header, _) =
+                    split_calldata_operator_da_input(&commit_info.operator_da_input).unwrap();
+                let versioned_hashes = self
+                    .blob_sidecar
+                    .iter()
+                    .flat_map(|sidecar| sidecar.versioned_hashes());
+                blobs_operator_da_input(header, versioned_hashes)
+            }
+            PubdataMode::Validium => U256::ZERO.to_be_bytes_vec(),
         };
-        let commit_batch_info = IExecutor::CommitBatchInfoZKsyncOS::from(batch_info.commit_info);
+        commit_info
+    }
+
+    /// `commitBatchesSharedBridge` expects the rest of calldata to be of very specific form. This
+    /// function makes sure last committed batch and new batch are encoded correctly.
+    fn commit_data(previous_batch: &StoredBatchInfo, batch: CommitBatchInfo) -> Vec<u8> {
+        let stored_batch_info = IExecutor::StoredBatchInfo::from(previous_batch);
+        let commit_batch_info = IExecutor::CommitBatchInfoZKsyncOS::from(batch);
         tracing::debug!(
-            last_batch_hash = ?self.input.batch.previous_stored_batch_info.hash(),
-            last_batch_number = ?self.input.batch.previous_stored_batch_info.batch_number,
+            last_batch_hash = ?previous_batch.hash(),
+            last_batch_number = ?previous_batch.batch_number,
             new_batch_number = ?commit_batch_info.batchNumber,
             "preparing commit calldata"
         );
         let encoded_data = (stored_batch_info, vec![commit_batch_info]).abi_encode_params();
 
         // Prefixed by current encoding version as expected by protocol
-        [[SUPPORTED_ENCODING_VERSION].to_vec(), encoded_data]
+        [[COMMITMENT_ENCODING_VERSION].to_vec(), encoded_data]
             .concat()
             .to_vec()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batcher_model::{BatchEnvelope, BatchMetadata, BatchSignatureData};
+    use crate::blobs::BLOB_DATA_CAPACITY;
+    use crate::calldata::decode_commit;
+    use alloy::primitives::B256;
+
+    const HEADER: [u8; 97] = [0x11; 97];
+
+    /// Rollup operator DA input in the format built by `BatchInfo::new()`.
+    fn operator_da_input(pubdata: &[u8]) -> Vec<u8> {
+        [&HEADER[..], &[0], pubdata, B256::ZERO.as_slice()].concat()
+    }
+
+    fn envelope(operator_da_input: Vec<u8>) -> SignedBatchEnvelope<FriProof> {
+        let mut metadata = BatchMetadata::for_tests(1, 1..=10, 5);
+        metadata.batch_info.operator_da_input = operator_da_input;
+        BatchEnvelope::new(metadata, FriProof::Fake).with_signatures(BatchSignatureData::NotNeeded)
+    }
+
+    /// Operator DA input committed by `command`, as decoded from its calldata.
+    fn committed_operator_da_input(command: &CommitCommand) -> Vec<u8> {
+        let decoded = decode_commit(&command.solidity_call().abi_encode()).unwrap();
+        assert_eq!(decoded.batches.len(), 1);
+        decoded.batches[0].operator_da_input.clone()
+    }
+
+    #[test]
+    fn calldata_mode_publishes_pubdata_in_calldata() {
+        let operator_da_input = operator_da_input(&vec![7; BLOB_DATA_CAPACITY + 1]);
+        let command =
+            CommitCommand::new(envelope(operator_da_input.clone()), PubdataMode::Calldata).unwrap();
+
+        assert!(command.blob_sidecar().is_none());
+        assert_eq!(committed_operator_da_input(&command), operator_da_input);
+        assert_eq!(
+            command.pubdata_composition(),
+            Some(count_zero_bytes(&operator_da_input))
+        );
+    }
+
+    #[test]
+    fn blobs_mode_publishes_pubdata_in_blobs() {
+        let pubdata = vec![7; BLOB_DATA_CAPACITY + 1];
+        let command =
+            CommitCommand::new(envelope(operator_da_input(&pubdata)), PubdataMode::Blobs).unwrap();
+
+        let sidecar = command.blob_sidecar().unwrap();
+        assert_eq!(sidecar.blobs.len(), 2);
+        let expected: Vec<u8> = [HEADER.to_vec(), vec![1]]
+            .into_iter()
+            .chain(sidecar.versioned_hashes().map(|hash| hash.to_vec()))
+            .flatten()
+            .collect();
+        assert_eq!(committed_operator_da_input(&command), expected);
+    }
+
+    #[test]
+    fn blobs_mode_without_pubdata() {
+        let command =
+            CommitCommand::new(envelope(operator_da_input(&[])), PubdataMode::Blobs).unwrap();
+
+        assert!(command.blob_sidecar().is_none());
+        let expected = [&HEADER[..], &[1]].concat();
+        assert_eq!(committed_operator_da_input(&command), expected);
+
+        // Validium DA input cannot be published in blobs
+        let validium_da_input = U256::ZERO.to_be_bytes_vec();
+        CommitCommand::new(envelope(validium_da_input), PubdataMode::Blobs).unwrap_err();
+    }
+}
@@ -1,12 +1,15 @@
 use crate::batcher_metrics::BatchExecutionStage;
-use crate::batcher_model::{FriProof, SignedBatchEnvelope};
+use crate::batcher_model::{FriProof, L1BatchOperation, SignedBatchEnvelope};
 use crate::commands::SendToL1;
-use alloy::primitives::U256;
+use alloy::primitives::{Address, U256};
 use alloy::sol_types::{SolCall, SolValue};
 use std::fmt::Display;
-use zksync_os_contract_interface::models::PriorityOpsBatchInfo;
+use zksync_os_contract_interface::models::{PriorityOpsBatchInfo, StoredBatchInfo};
 use zksync_os_contract_interface::{IExecutor, InteropRoot};
 
+/// Current execute data encoding version as per protocol.
+pub(crate) const EXECUTE_ENCODING_VERSION: u8 = 1;
+
 #[derive(Debug)]
 pub struct ExecuteCommand {
     batches: Vec<SignedBatchEnvelope<FriProof>>,
@@ -28,18 +31,24 @@ impl ExecuteCommand {
 
 impl SendToL1 for ExecuteCommand {
     const NAME: &'static str = "execute";
+    const OPERATION: L1BatchOperation = L1BatchOperation::Execute;
     const SENT_STAGE: BatchExecutionStage = BatchExecutionStage::ExecuteL1TxSent;
     const MINED_STAGE: BatchExecutionStage = BatchExecutionStage::ExecuteL1TxMined;
 
     const PASSTHROUGH_STAGE: BatchExecutionStage = BatchExecutionStage::ExecuteL1Passthrough;
+    const VALIDATOR_ROLE: &'static str = "EXECUTOR_ROLE";
 
     fn solidity_call(&self) -> impl SolCall {
-        IExecutor::executeBatchesSharedBridgeCall::new((
+        let stored_batch_infos: Vec<StoredBatchInfo> = self
+            .batches
+            .iter()
+            .map(|batch| batch.batch.batch_info.clone().into_stored())
+            .collect();
+        Self::execute_call(
             self.batches.first().unwrap().batch.batch_info.chain_address,
-            U256::from(self.batches.first().unwrap().batch_number()),
-            U256::from(self.batches.last().unwrap().batch_number()),
-            self.to_calldata_suffix().into(),
-        ))
+            &stored_batch_infos,
+            &self.priority_ops,
+        )
     }
 }
 
@@ -74,28 +83,37 @@ impl Display for ExecuteCommand {
 }
 
 impl ExecuteCommand {
-    fn to_calldata_suffix(&self) -> Vec<u8> {
-        let stored_batch_infos = self
-            .batches
+    /// `executeBatchesSharedBridge` call executing `batches` (non-empty) with their priority
+    /// operations.
+    pub(crate) fn execute_call(
+        chain_address: Address,
+        batches: &[StoredBatchInfo],
+        priority_ops: &[PriorityOpsBatchInfo],
+    ) -> IExecutor::executeBatchesSharedBridgeCall {
+        IExecutor::executeBatchesSharedBridgeCall::new((
+            chain_address,
+            U256::from(batches.first().unwrap().batch_number),
+            U256::from(batches.last().unwrap().batch_number),
+            Self::execute_data(batches, priority_ops).into(),
+        ))
+    }
+
+    fn execute_data(batches: &[StoredBatchInfo], priority_ops: &[PriorityOpsBatchInfo]) -> Vec<u8> {
+        let stored_batch_infos = batches
             .iter()
-            .map(|batch| batch.batch.batch_info.clone().into_stored())
-            .map(|batch| IExecutor::StoredBatchInfo::from(&batch))
+            .map(IExecutor::StoredBatchInfo::from)
             .collect::<Vec<_>>();
-        let priority_ops = self
-            .priority_ops
+        let priority_ops = priority_ops
             .iter()
             .cloned()
             .map(IExecutor::PriorityOpsBatchInfo::from)
             .collect::<Vec<_>>();
         // For now interop roots are empty.
-        let interop_roots: Vec<Vec<InteropRoot>> = vec![vec![]; self.batches.len()];
+        let interop_roots: Vec<Vec<InteropRoot>> = vec![vec![]; batches.len()];
         let encoded_data = (stored_batch_infos, priority_ops, interop_roots).abi_encode_params();
 
-        /// Current commitment encoding version as per protocol.
-        const SUPPORTED_ENCODING_VERSION: u8 = 1;
-
         // Prefixed by current encoding version as expected by protocol
-        [vec![SUPPORTED_ENCODING_VERSION], encoded_data]
+        [vec![EXECUTE_ENCODING_VERSION], encoded_data]
             .concat()
             .to_vec()
     }
@@ -1,5 +1,6 @@
 use crate::batcher_metrics::BatchExecutionStage;
-use crate::batcher_model::{FriProof, SignedBatchEnvelope};
+use crate::batcher_model::{FriProof, L1BatchOperation, SignedBatchEnvelope};
+use alloy::eips::eip4844::BlobTransactionSidecar;
 use alloy::sol_types::SolCall;
 use itertools::Itertools;
 use std::fmt::Display;
@@ -23,21 +24,45 @@ pub trait SendToL1:
     + Display
 {
     const NAME: &'static str;
+    const OPERATION: L1BatchOperation;
     const SENT_STAGE: BatchExecutionStage;
     const MINED_STAGE: BatchExecutionStage;
     const PASSTHROUGH_STAGE: BatchExecutionStage;
+    /// Name of the role the operator must have on the validator timelock to send the command.
+    const VALIDATOR_ROLE: &'static str;
+    /// Whether sending pauses when the commitment encoding version changes between consecutive
+    /// batches, see [`crate::commitment::COMMITMENT_ENCODING_VERSION`].
+    const GUARDS_COMMITMENT_FORMAT: bool = false;
     fn solidity_call(&self) -> impl SolCall;
 
+    /// Numbers of zero and non-zero pubdata bytes published by the L1 transaction (in calldata or
+    /// in blobs), if it publishes any.
+    fn pubdata_composition(&self) -> Option<(u64, u64)> {
+        None
+    }
+
+    /// Blobs to attach to the L1 transaction, turning it into an EIP-4844 transaction.
+    fn blob_sidecar(&self) -> Option<&BlobTransactionSidecar> {
+        None
+    }
+
+    /// Inclusive range of batch numbers covered by this command.
+    fn batch_range(&self) -> (u64, u64) {
+        let envelopes = self.as_ref();
+        // Safe unwraps as each command contains at least one envelope
+        (
+            envelopes.first().unwrap().batch_number(),
+            envelopes.last().unwrap().batch_number(),
+        )
+    }
+
     /// Only used for logging - as we send commands in bulk, it's natural to print a single range
     /// for the whole group, e.g. "1-3, 4, 5-6" instead of "1, 2, 3, 4, 5, 6"
     /// Note that one `L1SenderCommand` is still always a single L1 transaction.
     fn display_range(cmds: &[Self]) -> String {
         cmds.iter()
             .map(|cmd| {
-                let envelopes = cmd.as_ref();
-                // Safe unwraps as each command contains at least one envelope
-                let first = envelopes.first().unwrap().batch_number();
-                let last = envelopes.last().unwrap().batch_number();
+                let (first, last) = cmd.batch_range();
                 if first == last {
                     format!("{first}")
                 } else {
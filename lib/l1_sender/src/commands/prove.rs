@@ -1,7 +1,7 @@
 use crate::batcher_metrics::BatchExecutionStage;
-use crate::batcher_model::{FriProof, SignedBatchEnvelope, SnarkProof};
+use crate::batcher_model::{FriProof, L1BatchOperation, SignedBatchEnvelope, SnarkProof};
 use crate::commands::SendToL1;
-use alloy::primitives::{B256, U256, keccak256};
+use alloy::primitives::{Address, B256, U256, keccak256};
 use alloy::sol_types::SolCall;
 use std::collections::HashMap;
 use std::fmt::Display;
@@ -12,6 +12,8 @@ use zksync_os_contract_interface::models::StoredBatchInfo;
 const OHBENDER_PROOF_TYPE: u32 = 2;
 const FAKE_PROOF_TYPE: u32 = 3;
 const FAKE_PROOF_MAGIC_VALUE: u32 = 13;
+/// Current proof data encoding version as per protocol.
+pub(crate) const PROOF_ENCODING_VERSION: u8 = 1;
 
 #[derive(Debug)]
 pub struct ProofCommand {
@@ -27,17 +29,25 @@ impl ProofCommand {
 
 impl SendToL1 for ProofCommand {
     const NAME: &'static str = "prove";
+    const OPERATION: L1BatchOperation = L1BatchOperation::Prove;
     const SENT_STAGE: BatchExecutionStage = BatchExecutionStage::ProveL1TxSent;
     const MINED_STAGE: BatchExecutionStage = BatchExecutionStage::ProveL1TxMined;
     const PASSTHROUGH_STAGE: BatchExecutionStage = BatchExecutionStage::ProveL1Passthrough;
+    const VALIDATOR_ROLE: &'static str = "PROVER_ROLE";
 
     fn solidity_call(&self) -> impl SolCall {
-        proveBatchesSharedBridgeCall::new((
-            self.batches.first().unwrap().batch.batch_info.chain_address,
-            U256::from(self.batches.first().unwrap().batch_number()),
-            U256::from(self.batches.last().unwrap().batch_number()),
-            self.to_calldata_suffix().into(),
-        ))
+        let first_batch = &self.batches.first().unwrap().batch;
+        let stored_batch_infos: Vec<StoredBatchInfo> = self
+            .batches
+            .iter()
+            .map(|batch| batch.batch.batch_info.clone().into_stored())
+            .collect();
+        Self::prove_call(
+            first_batch.batch_info.chain_address,
+            &first_batch.previous_stored_batch_info,
+            &stored_batch_infos,
+            &self.proof,
+        )
     }
 }
 
@@ -118,20 +128,27 @@ impl ProofCommand {
         }
         result.unwrap()
     }
-    fn to_calldata_suffix(&self) -> Vec<u8> {
-        let previous_batch_info = &self
-            .batches
-            .first()
-            .unwrap()
-            .batch
-            .previous_stored_batch_info;
-        let stored_batch_infos: Vec<StoredBatchInfo> = self
-            .batches
+    /// Public inputs of `batches`, as passed to the L1 verifier by the prove transaction. The
+    /// verifier chains them into the single public input of the SNARK proof.
+    fn batch_public_inputs(
+        previous_batch: &StoredBatchInfo,
+        batches: &[StoredBatchInfo],
+    ) -> Vec<U256> {
+        let mut prev_batch = previous_batch;
+        batches
             .iter()
-            .map(|batch| batch.batch.batch_info.clone().into_stored())
-            .collect();
+            .map(|batch| {
+                let public_input = Self::get_batch_public_input(prev_batch, batch);
+                prev_batch = batch;
+                U256::from_be_bytes(Self::shift_b256_right(&public_input).0)
+            })
+            .collect()
+    }
+
+    /// `proof` as passed to the L1 verifier by the prove transaction.
+    fn verifier_proof(proof: &SnarkProof, public_input: B256) -> Vec<U256> {
         // todo: awful and temporary
-        let verifier_version = match self.proof.proving_execution_version() {
+        let verifier_version = match proof.proving_execution_version() {
             // Use default verifier for fake proofs.
             None => 0,
             // Use default verifier for v1.
@@ -145,12 +162,7 @@ impl ProofCommand {
             ),
         };
 
-        // todo: remove tostring
-        let public_input = Self::snark_public_input(previous_batch_info, &stored_batch_infos);
-
-        tracing::info!(">> public input: {}", public_input);
-
-        let proof: Vec<U256> = match &self.proof {
+        match proof {
             SnarkProof::Fake => {
                 vec![
                     // Fake proof type
@@ -184,21 +196,63 @@ impl ProofCommand {
                 .chain(proof)
                 .collect()
             }
-        };
+        }
+    }
+
+    /// Arguments of the `verify` call the L1 verifier receives from the prove transaction for
+    /// `batches` proven by `proof`: public inputs of the batches and the proof.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `batches` is empty, the proof is not a sequence of 32-byte words or its execution
+    /// version has no verifier.
+    pub fn verifier_inputs(
+        previous_batch: &StoredBatchInfo,
+        batches: &[StoredBatchInfo],
+        proof: &SnarkProof,
+    ) -> (Vec<U256>, Vec<U256>) {
+        let public_inputs = Self::batch_public_inputs(previous_batch, batches);
+        let public_input = Self::snark_public_input(previous_batch, batches);
+        (public_inputs, Self::verifier_proof(proof, public_input))
+    }
+
+    /// `proveBatchesSharedBridge` call proving `batches` (non-empty) that follow `previous_batch`.
+    pub(crate) fn prove_call(
+        chain_address: Address,
+        previous_batch: &StoredBatchInfo,
+        batches: &[StoredBatchInfo],
+        proof: &SnarkProof,
+    ) -> proveBatchesSharedBridgeCall {
+        proveBatchesSharedBridgeCall::new((
+            chain_address,
+            U256::from(batches.first().unwrap().batch_number),
+            U256::from(batches.last().unwrap().batch_number),
+            Self::proof_data(previous_batch, batches, proof).into(),
+        ))
+    }
+
+    fn proof_data(
+        previous_batch: &StoredBatchInfo,
+        batches: &[StoredBatchInfo],
+        proof: &SnarkProof,
+    ) -> Vec<u8> {
+        // todo: remove tostring
+        let public_input = Self::snark_public_input(previous_batch, batches);
+
+        tracing::info!(">> public input: {}", public_input);
+
+        let proof = Self::verifier_proof(proof, public_input);
 
         let proof_payload = proofPayloadCall {
-            old: IExecutor::StoredBatchInfo::from(previous_batch_info),
-            newInfo: stored_batch_infos
+            old: IExecutor::StoredBatchInfo::from(previous_batch),
+            newInfo: batches
                 .iter()
                 .map(Into::into) // into `IExecutor::StoredBatchInfo`
                 .collect(),
             proof,
         };
 
-        /// Current commitment encoding version as per protocol.
-        const SUPPORTED_ENCODING_VERSION: u8 = 1;
-
-        let mut proof_data = vec![SUPPORTED_ENCODING_VERSION];
+        let mut proof_data = vec![PROOF_ENCODING_VERSION];
         proof_payload.abi_encode_raw(&mut proof_data);
         proof_data
     }
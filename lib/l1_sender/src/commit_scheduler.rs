@@ -0,0 +1,437 @@
+//! Scheduling of commit transactions between batch sealing and the commit sender.
+//!
+//! A batch is not committed before it's `min_batch_age` old. After that, the commit is deferred
+//! while the current L1 base fee is above the configured percentile of recent base fees sampled
+//! by the gas adjuster, but never for longer than `max_commit_delay` since the batch was sealed.
+//! The batch age is measured from the timestamp of its last block, so that the delay survives
+//! restarts. Only commits are scheduled; prove and execute transactions are sent as usual.
+//!
+//! Batches with priority transactions close to expiring (see `BatchMetadata::priority_deadline`)
+//! are committed right away. Since batches are committed in order, so are the batches queued
+//! before them.
+
+use crate::batcher_model::{FriProof, SignedBatchEnvelope};
+use crate::commands::L1SenderCommand;
+use crate::commands::commit::CommitCommand;
+use crate::metrics::L1_SENDER_METRICS;
+use async_trait::async_trait;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, watch};
+use tokio::time::Instant;
+use zksync_os_gas_adjuster::{FeeSamplesSnapshot, GasAdjusterSnapshot};
+use zksync_os_observability::{ComponentStateReporter, GenericComponentState};
+use zksync_os_pipeline::{PeekableReceiver, PipelineComponent};
+
+/// How often L1 fees are re-checked while a commit is deferred due to high fees.
+const FEE_RECHECK_INTERVAL: Duration = Duration::from_secs(12);
+/// Max number of queued commands checked for batches with priority transactions close to expiring.
+const PRIORITY_DEADLINE_LOOKAHEAD: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CommitSchedulerConfig {
+    /// Min time between sealing a batch and committing it.
+    pub min_batch_age: Duration,
+    /// Batches are committed once they are this old, regardless of L1 fees.
+    pub max_commit_delay: Duration,
+    /// Commits are deferred while the current L1 base fee is above this percentile (in `[0, 1]`)
+    /// of recent base fees. `None` disables fee-based deferral.
+    pub fee_percentile: Option<f64>,
+}
+
+/// What to do with a batch waiting for commit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommitDecision {
+    Submit(SubmitReason),
+    /// Check again after `recheck_in`.
+    Defer {
+        reason: DeferReason,
+        recheck_in: Duration,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubmitReason {
+    /// The batch is old enough and L1 fees are acceptable (or unknown).
+    Ready,
+    /// The batch reached `max_commit_delay`.
+    Deadline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeferReason {
+    /// The batch is younger than `min_batch_age`.
+    MinBatchAge,
+    /// The current L1 base fee is above the configured percentile.
+    HighFees,
+}
+
+impl CommitSchedulerConfig {
+    /// Decides whether a batch of `batch_age` is committed now, given the recent L1 base fees.
+    pub fn decide(
+        &self,
+        batch_age: Duration,
+        base_fees: Option<&FeeSamplesSnapshot>,
+    ) -> CommitDecision {
+        if batch_age >= self.max_commit_delay {
+            return CommitDecision::Submit(SubmitReason::Deadline);
+        }
+        let until_deadline = self.max_commit_delay - batch_age;
+        if batch_age < self.min_batch_age {
+            return CommitDecision::Defer {
+                reason: DeferReason::MinBatchAge,
+                recheck_in: (self.min_batch_age - batch_age).min(until_deadline),
+            };
+        }
+        if let (Some(percentile), Some(base_fees)) = (self.fee_percentile, base_fees)
+            && base_fees.sample_count > 0
+            && base_fees.current > base_fees.percentile(percentile)
+        {
+            return CommitDecision::Defer {
+                reason: DeferReason::HighFees,
+                recheck_in: FEE_RECHECK_INTERVAL.min(until_deadline),
+            };
+        }
+        CommitDecision::Submit(SubmitReason::Ready)
+    }
+}
+
+/// Pipeline component holding commit commands until they are due according to
+/// [`CommitSchedulerConfig`]. Commands are passed on in order.
+pub struct CommitScheduler {
+    pub config: CommitSchedulerConfig,
+    /// Source of L1 fees; fee-based deferral is disabled without it.
+    pub l1_fees: Option<watch::Receiver<GasAdjusterSnapshot>>,
+}
+
+#[async_trait]
+impl PipelineComponent for CommitScheduler {
+    type Input = L1SenderCommand<CommitCommand>;
+    type Output = L1SenderCommand<CommitCommand>;
+
+    const NAME: &'static str = "commit_scheduler";
+    const OUTPUT_BUFFER_SIZE: usize = 1;
+
+    async fn run(
+        self,
+        mut input: PeekableReceiver<Self::Input>,
+        output: mpsc::Sender<Self::Output>,
+    ) -> anyhow::Result<()> {
+        let latency_tracker = ComponentStateReporter::global()
+            .handle_for("commit_scheduler", GenericComponentState::WaitingRecv);
+        loop {
+            latency_tracker.enter_state(GenericComponentState::WaitingRecv);
+            let Some(command) = input.recv().await else {
+                anyhow::bail!("inbound channel closed");
+            };
+            if let L1SenderCommand::SendToL1(commit) = &command {
+                latency_tracker.enter_state(GenericComponentState::Processing);
+                self.wait_until_due(commit.as_ref(), &mut input).await;
+            }
+            latency_tracker.enter_state(GenericComponentState::WaitingSend);
+            if output.send(command).await.is_err() {
+                anyhow::bail!("outbound channel closed");
+            }
+        }
+    }
+}
+
+impl CommitScheduler {
+    async fn wait_until_due(
+        &self,
+        batches: &[SignedBatchEnvelope<FriProof>],
+        queued: &mut PeekableReceiver<L1SenderCommand<CommitCommand>>,
+    ) {
+        // Safe as each command contains at least one batch
+        let batch = &batches[0].batch;
+        let batch_number = batch.batch_info.batch_number;
+        let sealed_at = UNIX_EPOCH + Duration::from_secs(batch.batch_info.last_block_timestamp);
+        let age_on_arrival = SystemTime::now()
+            .duration_since(sealed_at)
+            .unwrap_or_default();
+        let arrived_at = Instant::now();
+        let mut deferred_due_to_fees = false;
+
+        loop {
+            let batch_age = age_on_arrival + arrived_at.elapsed();
+            if let Some(prioritized_batch) = priority_deadline_batch(batches, queued) {
+                tracing::info!(
+                    batch_number,
+                    prioritized_batch,
+                    ?batch_age,
+                    "scheduling batch commit right away, batch {prioritized_batch} has priority \
+                     transactions close to expiring"
+                );
+                L1_SENDER_METRICS.commits_for_priority_deadlines.inc();
+                L1_SENDER_METRICS
+                    .commit_scheduler_delay
+                    .observe(arrived_at.elapsed());
+                return;
+            }
+            let base_fees = self
+                .l1_fees
+                .as_ref()
+                .map(|fees| fees.borrow().base_fee.clone());
+            let decision = self.config.decide(batch_age, base_fees.as_ref());
+            let (current_fee, threshold_fee) = match (&base_fees, self.config.fee_percentile) {
+                (Some(fees), Some(percentile)) => {
+                    (Some(fees.current), Some(fees.percentile(percentile)))
+                }
+                _ => (None, None),
+            };
+            match decision {
+                CommitDecision::Submit(reason) => {
+                    let delay = arrived_at.elapsed();
+                    tracing::info!(
+                        batch_number,
+                        ?batch_age,
+                        ?delay,
+                        ?reason,
+                        ?current_fee,
+                        ?threshold_fee,
+                        "scheduling batch commit"
+                    );
+                    L1_SENDER_METRICS.commit_scheduler_delay.observe(delay);
+                    return;
+                }
+                CommitDecision::Defer { reason, recheck_in } => {
+                    if reason == DeferReason::HighFees && !deferred_due_to_fees {
+                        deferred_due_to_fees = true;
+                        L1_SENDER_METRICS.commits_deferred_due_to_fees.inc();
+                        tracing::info!(
+                            batch_number,
+                            ?batch_age,
+                            ?current_fee,
+                            ?threshold_fee,
+                            "deferring batch commit while L1 fees are high"
+                        );
+                    } else {
+                        tracing::debug!(
+                            batch_number,
+                            ?batch_age,
+                            ?reason,
+                            ?recheck_in,
+                            "deferring batch commit"
+                        );
+                    }
+                    // Re-checked at least as often as fees, so that queued batches with priority
+                    // transactions close to expiring are not held back
+                    tokio::time::sleep(recheck_in.min(FEE_RECHECK_INTERVAL)).await;
+                }
+            }
+        }
+    }
+}
+
+/// Number of the first batch with priority transactions close to expiring, among `batches` and
+/// the next queued commands.
+fn priority_deadline_batch(
+    batches: &[SignedBatchEnvelope<FriProof>],
+    queued: &mut PeekableReceiver<L1SenderCommand<CommitCommand>>,
+) -> Option<u64> {
+    let find = |batches: &[SignedBatchEnvelope<FriProof>]| {
+        batches
+            .iter()
+            .find(|batch| batch.batch.priority_deadline.is_some())
+            .map(|batch| batch.batch_number())
+    };
+    find(batches).or_else(|| {
+        queued
+            .peek_until(PRIORITY_DEADLINE_LOOKAHEAD, |command| match command {
+                L1SenderCommand::SendToL1(commit) => Some(find(commit.as_ref())),
+                L1SenderCommand::Passthrough(_) => Some(None),
+            })
+            .into_iter()
+            .flatten()
+            .next()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batcher_model::{BatchEnvelope, BatchMetadata, BatchSignatureData};
+    use zksync_os_gas_adjuster::PubdataMode;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    fn config() -> CommitSchedulerConfig {
+        CommitSchedulerConfig {
+            min_batch_age: MINUTE,
+            max_commit_delay: 10 * MINUTE,
+            fee_percentile: Some(0.75),
+        }
+    }
+
+    /// Base fees sampled from L1 with the last value being the current one.
+    fn fees(samples: &[u128]) -> FeeSamplesSnapshot {
+        FeeSamplesSnapshot::from_samples(samples, 0.5)
+    }
+
+    /// Replays a scripted sequence of `(batch age, base fees)` and returns the decisions.
+    fn replay(script: &[(Duration, FeeSamplesSnapshot)]) -> Vec<CommitDecision> {
+        script
+            .iter()
+            .map(|(age, fees)| config().decide(*age, Some(fees)))
+            .collect()
+    }
+
+    fn deferred(reason: DeferReason, recheck_in: Duration) -> CommitDecision {
+        CommitDecision::Defer { reason, recheck_in }
+    }
+
+    #[test]
+    fn commits_are_deferred_during_fee_spikes() {
+        let decisions = replay(&[
+            (2 * MINUTE, fees(&[10, 10, 11, 10, 12, 10, 50])),
+            (3 * MINUTE, fees(&[10, 11, 10, 12, 10, 50, 80])),
+            // The spike is over
+            (4 * MINUTE, fees(&[10, 12, 10, 50, 80, 40, 11])),
+        ]);
+        assert_eq!(
+            decisions,
+            [
+                deferred(DeferReason::HighFees, FEE_RECHECK_INTERVAL),
+                deferred(DeferReason::HighFees, FEE_RECHECK_INTERVAL),
+                CommitDecision::Submit(SubmitReason::Ready),
+            ]
+        );
+    }
+
+    #[test]
+    fn commits_are_submitted_right_away_when_fees_are_low() {
+        let decisions = replay(&[
+            (MINUTE, fees(&[10, 10, 10, 10])),
+            (MINUTE, fees(&[20, 30, 15, 10])),
+        ]);
+        assert_eq!(decisions, [CommitDecision::Submit(SubmitReason::Ready); 2]);
+
+        // Without fee data, only the batch age matters
+        assert_eq!(
+            config().decide(MINUTE, None),
+            CommitDecision::Submit(SubmitReason::Ready)
+        );
+        assert_eq!(
+            config().decide(MINUTE, Some(&fees(&[]))),
+            CommitDecision::Submit(SubmitReason::Ready)
+        );
+        let no_fee_deferral = CommitSchedulerConfig {
+            fee_percentile: None,
+            ..config()
+        };
+        assert_eq!(
+            no_fee_deferral.decide(MINUTE, Some(&fees(&[10, 10, 100]))),
+            CommitDecision::Submit(SubmitReason::Ready)
+        );
+    }
+
+    #[test]
+    fn young_batches_wait_for_min_age() {
+        let decisions = replay(&[
+            (Duration::ZERO, fees(&[10, 10, 10])),
+            (MINUTE / 2, fees(&[10, 10, 10])),
+            (MINUTE, fees(&[10, 10, 10])),
+        ]);
+        assert_eq!(
+            decisions,
+            [
+                deferred(DeferReason::MinBatchAge, MINUTE),
+                deferred(DeferReason::MinBatchAge, MINUTE / 2),
+                CommitDecision::Submit(SubmitReason::Ready),
+            ]
+        );
+    }
+
+    #[test]
+    fn deadline_overrides_high_fees() {
+        let spike = fees(&[10, 10, 10, 10, 100]);
+        let decisions = replay(&[
+            (9 * MINUTE, spike.clone()),
+            (10 * MINUTE - Duration::from_secs(5), spike.clone()),
+            (10 * MINUTE, spike.clone()),
+            (11 * MINUTE, spike),
+        ]);
+        assert_eq!(
+            decisions,
+            [
+                deferred(DeferReason::HighFees, FEE_RECHECK_INTERVAL),
+                // Never sleeps past the deadline
+                deferred(DeferReason::HighFees, Duration::from_secs(5)),
+                CommitDecision::Submit(SubmitReason::Deadline),
+                CommitDecision::Submit(SubmitReason::Deadline),
+            ]
+        );
+
+        // The deadline also takes precedence over the min batch age
+        let config = CommitSchedulerConfig {
+            min_batch_age: 10 * MINUTE,
+            max_commit_delay: 5 * MINUTE,
+            fee_percentile: None,
+        };
+        assert_eq!(
+            config.decide(5 * MINUTE, None),
+            CommitDecision::Submit(SubmitReason::Deadline)
+        );
+    }
+
+    /// Commit of a batch sealed just now.
+    fn commit(batch_number: u64, priority_deadline: Option<u64>) -> L1SenderCommand<CommitCommand> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let mut metadata = BatchMetadata::for_tests(batch_number, batch_number..=batch_number, 1);
+        metadata.previous_stored_batch_info.last_block_timestamp = now;
+        metadata.batch_info.first_block_timestamp = now;
+        metadata.batch_info.last_block_timestamp = now;
+        metadata.priority_deadline = priority_deadline;
+        let envelope = BatchEnvelope::new(metadata, FriProof::Fake)
+            .with_signatures(BatchSignatureData::NotNeeded);
+        L1SenderCommand::SendToL1(CommitCommand::new(envelope, PubdataMode::Calldata).unwrap())
+    }
+
+    fn batch_number(command: &L1SenderCommand<CommitCommand>) -> u64 {
+        match command {
+            L1SenderCommand::SendToL1(commit) => commit.as_ref()[0].batch_number(),
+            L1SenderCommand::Passthrough(envelope) => envelope.batch_number(),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn batches_close_to_priority_deadline_release_queued_commits() {
+        let (input_sender, input) = mpsc::channel(10);
+        let (output, mut output_receiver) = mpsc::channel(10);
+        let scheduler = CommitScheduler {
+            config: CommitSchedulerConfig {
+                min_batch_age: 60 * MINUTE,
+                max_commit_delay: 120 * MINUTE,
+                fee_percentile: None,
+            },
+            l1_fees: None,
+        };
+        tokio::spawn(scheduler.run(PeekableReceiver::new(input), output));
+        let started_at = Instant::now();
+
+        // Young batches wait for `min_batch_age`
+        input_sender.send(commit(1, None)).await.unwrap();
+        input_sender.send(commit(2, None)).await.unwrap();
+        tokio::time::timeout(MINUTE, output_receiver.recv())
+            .await
+            .unwrap_err();
+
+        // A later batch close to its priority deadline is committed right away, together with
+        // the batches before it
+        input_sender.send(commit(3, Some(1_000))).await.unwrap();
+        let mut committed = vec![];
+        for _ in 0..3 {
+            committed.push(batch_number(&output_receiver.recv().await.unwrap()));
+        }
+        assert_eq!(committed, [1, 2, 3]);
+        assert!(started_at.elapsed() < 2 * MINUTE);
+
+        // Later batches are scheduled as usual
+        input_sender.send(commit(4, None)).await.unwrap();
+        tokio::time::timeout(MINUTE, output_receiver.recv())
+            .await
+            .unwrap_err();
+    }
+}
@@ -10,6 +10,14 @@ use zksync_os_mini_merkle_tree::MiniMerkleTree;
 use zksync_os_types::{L2_TO_L1_TREE_SIZE, L2ToL1Log, ZkEnvelope, ZkTransaction};
 
 const PUBDATA_SOURCE_CALLDATA: u8 = 0;
+const PUBDATA_SOURCE_BLOBS: u8 = 1;
+/// Length of the operator DA input header hashed into `da_commitment`, see [`BatchInfo::new`].
+const OPERATOR_DA_INPUT_HEADER_LEN: usize = 97;
+
+/// Version of the commitment encoding expected by the L1 contracts, prepended to the commit calldata.
+/// Must be bumped on any change to the encoding of committed batches. The commit sender pauses on
+/// a change between consecutive batches until the transition is acknowledged by the operator.
+pub const COMMITMENT_ENCODING_VERSION: u8 = 2;
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct BatchInfo {
@@ -199,6 +207,29 @@ impl BatchInfo {
     }
 }
 
+/// Splits the rollup operator DA input built by [`BatchInfo::new`] into its header and the pubdata
+/// published in calldata. Returns `None` if the input doesn't publish pubdata in calldata.
+pub(crate) fn split_calldata_operator_da_input(operator_da_input: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (header, rest) = operator_da_input.split_at_checked(OPERATOR_DA_INPUT_HEADER_LEN)?;
+    let (&source, rest) = rest.split_first()?;
+    let pubdata_len = rest.len().checked_sub(B256::len_bytes())?;
+    (source == PUBDATA_SOURCE_CALLDATA).then_some((header, &rest[..pubdata_len]))
+}
+
+/// Operator DA input publishing pubdata in blobs with the given versioned hashes instead of
+/// calldata. The header (and thus `da_commitment`) is the same as for calldata.
+pub(crate) fn blobs_operator_da_input(
+    header: &[u8],
+    versioned_hashes: impl IntoIterator<Item = B256>,
+) -> Vec<u8> {
+    let mut operator_da_input = header.to_vec();
+    operator_da_input.push(PUBDATA_SOURCE_BLOBS);
+    for versioned_hash in versioned_hashes {
+        operator_da_input.extend(versioned_hash.as_slice());
+    }
+    operator_da_input
+}
+
 impl Deref for BatchInfo {
     type Target = CommitBatchInfo;
 
@@ -0,0 +1,228 @@
+use crate::batcher_model::{BatchMetadata, CommitmentFormatTransition};
+use crate::metrics::L1SenderState;
+use anyhow::Context;
+use tokio::sync::watch;
+use zksync_os_observability::ComponentStateHandle;
+
+/// Pauses committing when the commitment encoding version changes between consecutive batches
+/// (e.g. after a node upgrade or rollback), so that a format change never reaches L1 unnoticed.
+///
+/// Committing resumes once the transition is acknowledged by the operator - either up front with
+/// `allow_commitment_format_transition` or by acknowledging the new version through `acks`
+/// (admin API).
+pub(crate) struct CommitmentFormatGuard {
+    /// Encoding version of the last committed (or passed through) batch.
+    last_version: Option<u8>,
+    allow_transition: bool,
+    acks: Option<watch::Receiver<Option<u8>>>,
+}
+
+impl CommitmentFormatGuard {
+    pub fn new(allow_transition: bool, acks: Option<watch::Receiver<Option<u8>>>) -> Self {
+        Self {
+            last_version: None,
+            allow_transition,
+            acks,
+        }
+    }
+
+    /// Records a batch that is already committed on L1.
+    pub fn observe_committed(&mut self, batch: &BatchMetadata) {
+        self.last_version = Some(batch.commitment_encoding_version);
+    }
+
+    /// Waits until `batch` may be committed. Records the format transition in the batch metadata,
+    /// if there is one.
+    pub async fn check(
+        &mut self,
+        batch: &mut BatchMetadata,
+        latency_tracker: &ComponentStateHandle<L1SenderState>,
+    ) -> anyhow::Result<()> {
+        let to_version = batch.commitment_encoding_version;
+        let from_version = match self.last_version {
+            Some(from_version) if from_version != to_version => from_version,
+            // Either the same version, or nothing was committed yet - there is nothing to compare with
+            _ => {
+                self.last_version = Some(to_version);
+                return Ok(());
+            }
+        };
+        let transition = CommitmentFormatTransition {
+            from_version,
+            to_version,
+        };
+        let batch_number = batch.batch_info.batch_number;
+        let downgrade = transition.is_downgrade();
+        if self.allow_transition {
+            tracing::warn!(
+                batch_number,
+                from_version,
+                to_version,
+                downgrade,
+                "commitment encoding version changed, transition is allowed by config"
+            );
+        } else {
+            latency_tracker.enter_state(L1SenderState::PausedOnFormatTransition);
+            tracing::error!(
+                batch_number,
+                from_version,
+                to_version,
+                downgrade,
+                "commitment encoding version changed, pausing commits until the transition is \
+                 acknowledged (via `allow_commitment_format_transition` config or \
+                 `admin_acknowledgeCommitmentFormatTransition({to_version})`)"
+            );
+            match &mut self.acks {
+                Some(acks) => {
+                    acks.wait_for(|ack| *ack == Some(to_version))
+                        .await
+                        .context("commitment format acknowledgment channel closed")?;
+                }
+                None => std::future::pending().await,
+            }
+            tracing::info!(
+                batch_number,
+                from_version,
+                to_version,
+                "commitment format transition acknowledged, resuming commits"
+            );
+        }
+        self.last_version = Some(to_version);
+        batch.commitment_format_transition = Some(transition);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use zksync_os_observability::ComponentStateReporter;
+
+    fn batch(batch_number: u64, commitment_encoding_version: u8) -> BatchMetadata {
+        let mut batch = BatchMetadata::for_tests(batch_number, 1..=1, 0);
+        batch.commitment_encoding_version = commitment_encoding_version;
+        batch
+    }
+
+    fn latency_tracker() -> ComponentStateHandle<L1SenderState> {
+        ComponentStateReporter::global().handle_for("commit", L1SenderState::WaitingRecv)
+    }
+
+    /// Checks `batch`, expecting the guard to pause.
+    async fn assert_paused(guard: &mut CommitmentFormatGuard, batch: &mut BatchMetadata) {
+        tokio::time::timeout(
+            Duration::from_millis(50),
+            guard.check(batch, &latency_tracker()),
+        )
+        .await
+        .unwrap_err();
+    }
+
+    #[tokio::test]
+    async fn same_version_is_not_paused() {
+        let mut guard = CommitmentFormatGuard::new(false, None);
+        guard.observe_committed(&batch(1, 2));
+        for batch_number in 2..5 {
+            let mut batch = batch(batch_number, 2);
+            guard.check(&mut batch, &latency_tracker()).await.unwrap();
+            assert_eq!(batch.commitment_format_transition, None);
+        }
+    }
+
+    #[tokio::test]
+    async fn first_batch_is_not_paused() {
+        // Nothing is committed yet, e.g. a fresh chain
+        let mut guard = CommitmentFormatGuard::new(false, None);
+        let mut batch = batch(1, 3);
+        guard.check(&mut batch, &latency_tracker()).await.unwrap();
+        assert_eq!(batch.commitment_format_transition, None);
+    }
+
+    #[tokio::test]
+    async fn upgrade_is_paused_until_acknowledged() {
+        let (acks_sender, acks) = watch::channel(None);
+        let mut guard = CommitmentFormatGuard::new(false, Some(acks));
+        guard.observe_committed(&batch(1, 2));
+
+        let mut upgraded = batch(2, 3);
+        assert_paused(&mut guard, &mut upgraded).await;
+        // Acknowledging another version doesn't resume commits
+        acks_sender.send_replace(Some(4));
+        assert_paused(&mut guard, &mut upgraded).await;
+        assert_eq!(upgraded.commitment_format_transition, None);
+
+        acks_sender.send_replace(Some(3));
+        guard
+            .check(&mut upgraded, &latency_tracker())
+            .await
+            .unwrap();
+        assert_eq!(
+            upgraded.commitment_format_transition,
+            Some(CommitmentFormatTransition {
+                from_version: 2,
+                to_version: 3
+            })
+        );
+        // Later batches of the new format are committed as usual
+        let mut next = batch(3, 3);
+        guard.check(&mut next, &latency_tracker()).await.unwrap();
+        assert_eq!(next.commitment_format_transition, None);
+    }
+
+    #[tokio::test]
+    async fn rollback_is_paused_until_acknowledged() {
+        let (acks_sender, acks) = watch::channel(None);
+        let mut guard = CommitmentFormatGuard::new(false, Some(acks));
+        // Batches committed by the upgraded node, followed by a batch sealed by the previous release
+        guard.observe_committed(&batch(1, 3));
+        let mut rolled_back = batch(2, 2);
+        assert_paused(&mut guard, &mut rolled_back).await;
+
+        acks_sender.send_replace(Some(2));
+        guard
+            .check(&mut rolled_back, &latency_tracker())
+            .await
+            .unwrap();
+        let transition = rolled_back.commitment_format_transition.unwrap();
+        assert!(transition.is_downgrade());
+        assert_eq!((transition.from_version, transition.to_version), (3, 2));
+    }
+
+    #[tokio::test]
+    async fn transition_without_acknowledgment_channel_stays_paused() {
+        let mut guard = CommitmentFormatGuard::new(false, None);
+        guard.observe_committed(&batch(1, 2));
+        assert_paused(&mut guard, &mut batch(2, 3)).await;
+    }
+
+    #[tokio::test]
+    async fn transition_allowed_by_config() {
+        let mut guard = CommitmentFormatGuard::new(true, None);
+        guard.observe_committed(&batch(1, 2));
+        let mut upgraded = batch(2, 3);
+        guard
+            .check(&mut upgraded, &latency_tracker())
+            .await
+            .unwrap();
+        assert_eq!(
+            upgraded.commitment_format_transition,
+            Some(CommitmentFormatTransition {
+                from_version: 2,
+                to_version: 3
+            })
+        );
+        // A rollback is allowed too, and is recorded as well
+        let mut rolled_back = batch(3, 2);
+        guard
+            .check(&mut rolled_back, &latency_tracker())
+            .await
+            .unwrap();
+        assert!(
+            rolled_back
+                .commitment_format_transition
+                .unwrap()
+                .is_downgrade()
+        );
+    }
+}
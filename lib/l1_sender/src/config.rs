@@ -1,6 +1,8 @@
 use alloy::consensus::constants::GWEI_TO_WEI;
+use alloy::primitives::U256;
 use secrecy::SecretString;
 use std::marker::PhantomData;
+use std::path::PathBuf;
 use std::time::Duration;
 
 /// Configuration of L1 sender.
@@ -16,12 +18,45 @@ pub struct L1SenderConfig<Input> {
     /// Max priority fee per gas we are willing to spend (in gwei).
     pub max_priority_fee_per_gas_gwei: u64,
 
+    /// Max fee per blob gas we are willing to spend (in gwei).
+    /// Only used for transactions publishing pubdata in blobs.
+    pub max_fee_per_blob_gas_gwei: u64,
+
+    /// Multiplier applied to the gas estimated for an L1 transaction to get its gas limit.
+    pub gas_limit_safety_factor: f64,
+
+    /// Upper bound of the gas limit of an L1 transaction; also used as the gas limit if gas
+    /// estimation fails.
+    pub max_gas_limit: u64,
+
     /// Max number of commands (to commit/prove/execute one batch) to be processed at a time.
     pub command_limit: usize,
 
     /// How often to poll L1 for new blocks.
     pub poll_interval: Duration,
 
+    /// Time after which a sent transaction that is not included on L1 is replaced with one paying
+    /// higher fees (up to the max fees).
+    pub resubmission_interval: Duration,
+
+    /// Max number of batches that may wait to be accepted by the next pipeline step after being
+    /// processed on L1. Sending pauses while the backlog is full, so that a stall downstream
+    /// (e.g. no SNARK proofs for committed batches) doesn't block this sender right away.
+    pub max_outbound_backlog: usize,
+
+    /// Sending pauses while the operator balance is below this value (in gwei).
+    /// Zero disables the check.
+    pub min_operator_balance_gwei: u64,
+
+    /// Whether to commit batches with a commitment encoding version different from the previous
+    /// batch without waiting for the operator to acknowledge the transition.
+    /// Only used by the commit sender.
+    pub allow_commitment_format_transition: bool,
+
+    /// File a [`DryRunReport`](crate::dry_run::DryRunReport) is written to (as JSON) by
+    /// [`run_l1_sender_dry_run`](crate::dry_run::run_l1_sender_dry_run).
+    pub dry_run_report_path: Option<PathBuf>,
+
     pub phantom_data: PhantomData<Input>,
 }
 
@@ -35,4 +70,14 @@ impl<T> L1SenderConfig<T> {
     pub fn max_priority_fee_per_gas(&self) -> u128 {
         self.max_priority_fee_per_gas_gwei as u128 * (GWEI_TO_WEI as u128)
     }
+
+    /// Max fee per blob gas we are willing to spend (in wei).
+    pub fn max_fee_per_blob_gas(&self) -> u128 {
+        self.max_fee_per_blob_gas_gwei as u128 * (GWEI_TO_WEI as u128)
+    }
+
+    /// Min operator balance to keep sending transactions (in wei).
+    pub fn min_operator_balance(&self) -> U256 {
+        U256::from(self.min_operator_balance_gwei) * U256::from(GWEI_TO_WEI)
+    }
 }
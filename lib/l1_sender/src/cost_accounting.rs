@@ -0,0 +1,715 @@
+//! Accounting of ETH spent on L1 by the L1 senders.
+//!
+//! For every included commit/prove/execute transaction, the execution fee
+//! (`gas_used * effective_gas_price`) and the blob fee (`blob_gas_used * blob_gas_price`) are
+//! attributed to the batches covered by the transaction. Per-batch costs are persisted in the
+//! batch storage; running daily/weekly aggregates are persisted alongside and exported as metrics.
+//!
+//! Transactions covering several batches are split evenly between them, with the remainder of the
+//! division attributed to the first batch - so per-batch costs always sum up to the paid fees.
+//!
+//! Per-batch costs are also compared with the L1 prices predicted for the batches, see
+//! [`price_drift`](crate::price_drift).
+
+use crate::batcher_model::L1BatchOperation;
+use crate::metrics::L1_SENDER_METRICS;
+use crate::price_drift::{PredictedL1Prices, PredictionError, PriceDriftConfig, PriceDriftMonitor};
+use alloy::primitives::TxHash;
+use alloy::rpc::types::TransactionReceipt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use tokio::sync::{mpsc, watch};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+const SECONDS_PER_WEEK: u64 = 7 * SECONDS_PER_DAY;
+/// Unix epoch is a Thursday - weeks are aligned to Mondays.
+const WEEK_ALIGNMENT_OFFSET: u64 = 3 * SECONDS_PER_DAY;
+/// Number of daily and weekly aggregates that are retained.
+const RETAINED_DAYS: usize = 62;
+const RETAINED_WEEKS: usize = 53;
+/// Number of most recent batches exposed in [`L1CostSummary`].
+const RECENT_BATCHES: usize = 16;
+
+/// Fees paid for a single included L1 transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1TxCost {
+    pub operation: L1BatchOperation,
+    pub first_batch: u64,
+    pub last_batch: u64,
+    pub tx_hash: TxHash,
+    /// L1 block the transaction was included in. Missing for transactions recorded before it was
+    /// tracked.
+    #[serde(default)]
+    pub l1_block_number: Option<u64>,
+    /// Unix timestamp (seconds) at which the transaction was observed as included.
+    pub timestamp: u64,
+    pub gas_used: u64,
+    pub effective_gas_price: u128,
+    /// Zero for transactions without blobs.
+    pub blob_gas_used: u64,
+    pub blob_gas_price: u128,
+    /// L1 prices predicted for the covered batches; batches without a prediction are missing.
+    #[serde(default)]
+    pub predicted_prices: BTreeMap<u64, PredictedL1Prices>,
+}
+
+impl L1TxCost {
+    pub fn from_receipt(
+        operation: L1BatchOperation,
+        (first_batch, last_batch): (u64, u64),
+        receipt: &TransactionReceipt,
+        timestamp: u64,
+    ) -> Self {
+        Self {
+            operation,
+            first_batch,
+            last_batch,
+            tx_hash: receipt.transaction_hash,
+            l1_block_number: receipt.block_number,
+            timestamp,
+            gas_used: receipt.gas_used,
+            effective_gas_price: receipt.effective_gas_price,
+            blob_gas_used: receipt.blob_gas_used.unwrap_or_default(),
+            blob_gas_price: receipt.blob_gas_price.unwrap_or_default(),
+            predicted_prices: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_predicted_prices(
+        mut self,
+        predicted_prices: BTreeMap<u64, PredictedL1Prices>,
+    ) -> Self {
+        self.predicted_prices = predicted_prices;
+        self
+    }
+
+    pub fn execution_fee_wei(&self) -> u128 {
+        self.gas_used as u128 * self.effective_gas_price
+    }
+
+    pub fn blob_fee_wei(&self) -> u128 {
+        self.blob_gas_used as u128 * self.blob_gas_price
+    }
+
+    pub fn total_wei(&self) -> u128 {
+        self.execution_fee_wei() + self.blob_fee_wei()
+    }
+
+    /// Splits the transaction cost between the batches it covers.
+    pub fn per_batch(&self) -> impl Iterator<Item = (u64, OperationCost)> + '_ {
+        let batch_count = (self.last_batch - self.first_batch + 1) as u128;
+        let split = move |value: u128, is_first: bool| {
+            value / batch_count + if is_first { value % batch_count } else { 0 }
+        };
+        (self.first_batch..=self.last_batch).map(move |batch_number| {
+            let is_first = batch_number == self.first_batch;
+            let cost = OperationCost {
+                tx_hash: self.tx_hash,
+                l1_block_number: self.l1_block_number,
+                timestamp: self.timestamp,
+                gas_used: split(self.gas_used as u128, is_first) as u64,
+                execution_fee_wei: split(self.execution_fee_wei(), is_first),
+                blob_fee_wei: split(self.blob_fee_wei(), is_first),
+                predicted_prices: self.predicted_prices.get(&batch_number).copied(),
+            };
+            (batch_number, cost)
+        })
+    }
+}
+
+/// Share of an L1 transaction's fees attributed to a single batch.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperationCost {
+    pub tx_hash: TxHash,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub l1_block_number: Option<u64>,
+    pub timestamp: u64,
+    pub gas_used: u64,
+    pub execution_fee_wei: u128,
+    pub blob_fee_wei: u128,
+    /// L1 prices predicted for the batch. Missing for batches sealed before predictions were
+    /// recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub predicted_prices: Option<PredictedL1Prices>,
+}
+
+impl OperationCost {
+    pub fn total_wei(&self) -> u128 {
+        self.execution_fee_wei + self.blob_fee_wei
+    }
+}
+
+/// L1 fees paid for a batch, by operation.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchCost {
+    pub batch_number: u64,
+    pub operations: BTreeMap<L1BatchOperation, OperationCost>,
+}
+
+impl BatchCost {
+    pub fn new(batch_number: u64) -> Self {
+        Self {
+            batch_number,
+            operations: BTreeMap::new(),
+        }
+    }
+
+    pub fn total_wei(&self) -> u128 {
+        self.operations.values().map(OperationCost::total_wei).sum()
+    }
+}
+
+/// Fees paid within a period, by operation.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeriodCost {
+    pub wei: BTreeMap<L1BatchOperation, u128>,
+    pub transactions: BTreeMap<L1BatchOperation, u64>,
+}
+
+impl PeriodCost {
+    fn add(&mut self, cost: &L1TxCost) {
+        *self.wei.entry(cost.operation).or_default() += cost.total_wei();
+        *self.transactions.entry(cost.operation).or_default() += 1;
+    }
+
+    pub fn total_wei(&self) -> u128 {
+        self.wei.values().sum()
+    }
+}
+
+/// Running L1 cost aggregates. Daily and weekly periods are keyed by their start (unix timestamp,
+/// UTC; weeks start on Monday) and only the most recent ones are retained.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1CostAggregates {
+    pub daily: BTreeMap<u64, PeriodCost>,
+    pub weekly: BTreeMap<u64, PeriodCost>,
+    pub total: PeriodCost,
+}
+
+impl L1CostAggregates {
+    pub fn record(&mut self, cost: &L1TxCost) {
+        self.daily
+            .entry(day_start(cost.timestamp))
+            .or_default()
+            .add(cost);
+        self.weekly
+            .entry(week_start(cost.timestamp))
+            .or_default()
+            .add(cost);
+        self.total.add(cost);
+        while self.daily.len() > RETAINED_DAYS {
+            self.daily.pop_first();
+        }
+        while self.weekly.len() > RETAINED_WEEKS {
+            self.weekly.pop_first();
+        }
+    }
+}
+
+fn day_start(timestamp: u64) -> u64 {
+    timestamp - timestamp % SECONDS_PER_DAY
+}
+
+fn week_start(timestamp: u64) -> u64 {
+    let aligned = timestamp + WEEK_ALIGNMENT_OFFSET;
+    (aligned - aligned % SECONDS_PER_WEEK).saturating_sub(WEEK_ALIGNMENT_OFFSET)
+}
+
+/// State published by [`L1CostTracker`] (e.g. for the debug status).
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1CostSummary {
+    pub aggregates: L1CostAggregates,
+    /// Most recent batches with recorded costs, in ascending order.
+    pub recent_batches: Vec<BatchCost>,
+}
+
+/// Persistence for per-batch costs and running aggregates.
+#[allow(async_fn_in_trait)]
+pub trait L1CostStorage: Send + Sync + 'static {
+    async fn load_batch_cost(&self, batch_number: u64) -> anyhow::Result<Option<BatchCost>>;
+
+    async fn save_batch_cost(&self, cost: &BatchCost) -> anyhow::Result<()>;
+
+    async fn load_l1_cost_aggregates(&self) -> anyhow::Result<Option<L1CostAggregates>>;
+
+    async fn save_l1_cost_aggregates(&self, aggregates: &L1CostAggregates) -> anyhow::Result<()>;
+
+    /// Returns costs of batches `from_batch..=to_batch`. Batches without any recorded
+    /// L1 transaction are skipped.
+    async fn costs(&self, from_batch: u64, to_batch: u64) -> anyhow::Result<Vec<BatchCost>> {
+        let mut costs = Vec::new();
+        for batch_number in from_batch..=to_batch {
+            if let Some(cost) = self.load_batch_cost(batch_number).await? {
+                costs.push(cost);
+            }
+        }
+        Ok(costs)
+    }
+}
+
+/// Renders batch costs as CSV - one row per batch and operation.
+pub fn costs_to_csv(costs: &[BatchCost]) -> String {
+    let mut csv = String::from(
+        "batch_number,operation,tx_hash,timestamp,gas_used,execution_fee_wei,blob_fee_wei,total_wei\n",
+    );
+    for batch in costs {
+        for (operation, cost) in &batch.operations {
+            writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{}",
+                batch.batch_number,
+                operation.as_str(),
+                cost.tx_hash,
+                cost.timestamp,
+                cost.gas_used,
+                cost.execution_fee_wei,
+                cost.blob_fee_wei,
+                cost.total_wei(),
+            )
+            .unwrap();
+        }
+    }
+    csv
+}
+
+/// Attributes fees of L1 transactions reported by the L1 senders to batches,
+/// persists them and maintains the running aggregates.
+pub struct L1CostTracker<Storage> {
+    storage: Storage,
+    inbound: mpsc::UnboundedReceiver<L1TxCost>,
+    summary: watch::Sender<L1CostSummary>,
+    price_drift: PriceDriftMonitor,
+}
+
+impl<Storage: L1CostStorage> L1CostTracker<Storage> {
+    pub async fn new(
+        storage: Storage,
+        inbound: mpsc::UnboundedReceiver<L1TxCost>,
+        summary: watch::Sender<L1CostSummary>,
+        price_drift: PriceDriftConfig,
+    ) -> anyhow::Result<Self> {
+        let aggregates = storage.load_l1_cost_aggregates().await?.unwrap_or_default();
+        tracing::info!(
+            total_wei = aggregates.total.total_wei(),
+            "initializing L1 cost tracker"
+        );
+        report_metrics(&aggregates);
+        summary.send_replace(L1CostSummary {
+            aggregates,
+            recent_batches: Vec::new(),
+        });
+        Ok(Self {
+            storage,
+            inbound,
+            summary,
+            price_drift: PriceDriftMonitor::new(price_drift),
+        })
+    }
+
+    pub async fn run(mut self) -> anyhow::Result<()> {
+        while let Some(cost) = self.inbound.recv().await {
+            let tx_hash = cost.tx_hash;
+            // Only bookkeeping, so storage errors are not worth stopping the node for
+            if let Err(err) = self.record(cost).await {
+                tracing::warn!(%err, ?tx_hash, "failed to record L1 transaction cost; skipping it");
+                L1_SENDER_METRICS.l1_cost_recording_failures.inc();
+            }
+        }
+        anyhow::bail!("inbound channel closed");
+    }
+
+    async fn record(&mut self, cost: L1TxCost) -> anyhow::Result<()> {
+        let mut summary = self.summary.borrow().clone();
+        let mut already_recorded = false;
+        for (batch_number, share) in cost.per_batch() {
+            let mut batch_cost = self
+                .storage
+                .load_batch_cost(batch_number)
+                .await?
+                .unwrap_or_else(|| BatchCost::new(batch_number));
+            let is_duplicate = batch_cost
+                .operations
+                .get(&cost.operation)
+                .is_some_and(|existing| existing.tx_hash == cost.tx_hash);
+            already_recorded |= is_duplicate;
+            if !is_duplicate {
+                self.price_drift
+                    .observe(cost.timestamp, PredictionError::new(cost.operation, &share));
+            }
+            batch_cost.operations.insert(cost.operation, share);
+            self.storage.save_batch_cost(&batch_cost).await?;
+
+            match summary
+                .recent_batches
+                .binary_search_by_key(&batch_number, |batch| batch.batch_number)
+            {
+                Ok(index) => summary.recent_batches[index] = batch_cost,
+                Err(index) => summary.recent_batches.insert(index, batch_cost),
+            }
+        }
+        let excess = summary.recent_batches.len().saturating_sub(RECENT_BATCHES);
+        summary.recent_batches.drain(..excess);
+
+        // Receipts are only reported once, but keep aggregates exact if one is ever reported again
+        if !already_recorded {
+            summary.aggregates.record(&cost);
+            self.storage
+                .save_l1_cost_aggregates(&summary.aggregates)
+                .await?;
+        }
+        tracing::debug!(
+            operation = cost.operation.as_str(),
+            first_batch = cost.first_batch,
+            last_batch = cost.last_batch,
+            tx_hash = ?cost.tx_hash,
+            execution_fee_wei = cost.execution_fee_wei(),
+            blob_fee_wei = cost.blob_fee_wei(),
+            "recorded L1 transaction cost"
+        );
+        report_metrics(&summary.aggregates);
+        self.summary.send_replace(summary);
+        Ok(())
+    }
+}
+
+/// Exports the latest day, the latest week and the total.
+fn report_metrics(aggregates: &L1CostAggregates) {
+    let periods = [
+        (
+            "day",
+            aggregates.daily.last_key_value().map(|(_, cost)| cost),
+        ),
+        (
+            "week",
+            aggregates.weekly.last_key_value().map(|(_, cost)| cost),
+        ),
+        ("total", Some(&aggregates.total)),
+    ];
+    for (period, cost) in periods {
+        for operation in L1BatchOperation::ALL {
+            let wei = cost
+                .and_then(|cost| cost.wei.get(&operation))
+                .copied()
+                .unwrap_or_default();
+            L1_SENDER_METRICS.l1_cost_wei[&(period, operation.as_str())].set(wei as f64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price_drift::{PredictedPrice, PriceDriftReport};
+    use std::collections::{HashMap, HashSet};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    const GWEI: u128 = 1_000_000_000;
+    /// Monday, 2025-09-15 00:00:00 UTC
+    const MONDAY: u64 = 1_757_894_400;
+
+    #[derive(Clone, Default)]
+    struct InMemoryStorage {
+        batches: Arc<Mutex<HashMap<u64, BatchCost>>>,
+        aggregates: Arc<Mutex<Option<L1CostAggregates>>>,
+        /// Batches whose costs fail to be saved.
+        failing_batches: Arc<Mutex<HashSet<u64>>>,
+    }
+
+    impl L1CostStorage for InMemoryStorage {
+        async fn load_batch_cost(&self, batch_number: u64) -> anyhow::Result<Option<BatchCost>> {
+            Ok(self.batches.lock().unwrap().get(&batch_number).cloned())
+        }
+
+        async fn save_batch_cost(&self, cost: &BatchCost) -> anyhow::Result<()> {
+            let is_failing = self
+                .failing_batches
+                .lock()
+                .unwrap()
+                .contains(&cost.batch_number);
+            anyhow::ensure!(!is_failing, "cannot save batch #{}", cost.batch_number);
+            self.batches
+                .lock()
+                .unwrap()
+                .insert(cost.batch_number, cost.clone());
+            Ok(())
+        }
+
+        async fn load_l1_cost_aggregates(&self) -> anyhow::Result<Option<L1CostAggregates>> {
+            Ok(self.aggregates.lock().unwrap().clone())
+        }
+
+        async fn save_l1_cost_aggregates(
+            &self,
+            aggregates: &L1CostAggregates,
+        ) -> anyhow::Result<()> {
+            *self.aggregates.lock().unwrap() = Some(aggregates.clone());
+            Ok(())
+        }
+    }
+
+    /// Builds a receipt the way it is returned by an L1 node.
+    fn receipt(
+        tx_hash: u8,
+        gas_used: u64,
+        effective_gas_price: u128,
+        blob: Option<(u64, u128)>,
+    ) -> TransactionReceipt {
+        let mut receipt = serde_json::json!({
+            "type": if blob.is_some() { "0x3" } else { "0x2" },
+            "status": "0x1",
+            "cumulativeGasUsed": format!("{gas_used:#x}"),
+            "logs": [],
+            "logsBloom": format!("0x{}", "0".repeat(512)),
+            "transactionHash": TxHash::repeat_byte(tx_hash),
+            "transactionIndex": "0x0",
+            "blockHash": TxHash::repeat_byte(0xbb),
+            "blockNumber": "0x10",
+            "gasUsed": format!("{gas_used:#x}"),
+            "effectiveGasPrice": format!("{effective_gas_price:#x}"),
+            "from": "0x36615cf349d7f6344891b1e7ca7c72883f5dc049",
+            "to": "0xa61464658afeaf65cccaafd3a512b69a83b77618",
+            "contractAddress": null,
+        });
+        if let Some((blob_gas_used, blob_gas_price)) = blob {
+            receipt["blobGasUsed"] = format!("{blob_gas_used:#x}").into();
+            receipt["blobGasPrice"] = format!("{blob_gas_price:#x}").into();
+        }
+        serde_json::from_value(receipt).unwrap()
+    }
+
+    async fn tracker(
+        storage: InMemoryStorage,
+    ) -> (
+        L1CostTracker<InMemoryStorage>,
+        mpsc::UnboundedSender<L1TxCost>,
+    ) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (summary, _) = watch::channel(L1CostSummary::default());
+        let price_drift = PriceDriftConfig {
+            window: Duration::from_secs(3600),
+            threshold: 0.25,
+        };
+        let tracker = L1CostTracker::new(storage, receiver, summary, price_drift)
+            .await
+            .unwrap();
+        (tracker, sender)
+    }
+
+    #[test]
+    fn blob_commit_cost() {
+        let receipt = receipt(1, 100_000, 20 * GWEI, Some((131_072, 3 * GWEI)));
+        let cost = L1TxCost::from_receipt(L1BatchOperation::Commit, (5, 5), &receipt, MONDAY);
+        assert_eq!(cost.execution_fee_wei(), 2_000_000 * GWEI);
+        assert_eq!(cost.blob_fee_wei(), 393_216 * GWEI);
+        assert_eq!(cost.total_wei(), 2_393_216 * GWEI);
+
+        let receipt = receipt(2, 100_000, 20 * GWEI, None);
+        let cost = L1TxCost::from_receipt(L1BatchOperation::Commit, (5, 5), &receipt, MONDAY);
+        assert_eq!(cost.blob_fee_wei(), 0);
+    }
+
+    #[test]
+    fn multi_batch_cost_is_split_exactly() {
+        let receipt = receipt(1, 100_001, 7, None);
+        let cost = L1TxCost::from_receipt(L1BatchOperation::Prove, (1, 3), &receipt, MONDAY);
+        let shares: Vec<_> = cost.per_batch().collect();
+        assert_eq!(
+            shares.iter().map(|(batch, _)| *batch).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        assert_eq!(shares[0].1.gas_used, 33_335);
+        assert_eq!(shares[1].1.gas_used, 33_333);
+        let total: u128 = shares.iter().map(|(_, share)| share.total_wei()).sum();
+        assert_eq!(total, cost.total_wei());
+    }
+
+    #[test]
+    fn periods_are_aligned() {
+        assert_eq!(day_start(MONDAY + 5 * 3600), MONDAY);
+        assert_eq!(week_start(MONDAY), MONDAY);
+        assert_eq!(week_start(MONDAY + 6 * SECONDS_PER_DAY + 1), MONDAY);
+        assert_eq!(week_start(MONDAY - 1), MONDAY - SECONDS_PER_WEEK);
+        assert_eq!(week_start(0), 0);
+    }
+
+    #[tokio::test]
+    async fn per_batch_and_aggregate_costs() {
+        let (mut tracker, _sender) = tracker(InMemoryStorage::default()).await;
+        let commit = L1TxCost::from_receipt(
+            L1BatchOperation::Commit,
+            (1, 1),
+            &receipt(1, 100_000, 20 * GWEI, Some((131_072, 3 * GWEI))),
+            MONDAY + 3600,
+        );
+        let prove = L1TxCost::from_receipt(
+            L1BatchOperation::Prove,
+            (1, 2),
+            &receipt(2, 300_000, 10 * GWEI, None),
+            MONDAY + 7200,
+        );
+        // Next day, same week
+        let execute = L1TxCost::from_receipt(
+            L1BatchOperation::Execute,
+            (1, 2),
+            &receipt(3, 50_000, 10 * GWEI, None),
+            MONDAY + SECONDS_PER_DAY + 60,
+        );
+        for cost in [&commit, &prove, &execute] {
+            tracker.record(cost.clone()).await.unwrap();
+        }
+        // Duplicate reports don't inflate aggregates
+        tracker.record(prove.clone()).await.unwrap();
+
+        let costs = tracker.storage.costs(0, 10).await.unwrap();
+        assert_eq!(costs.len(), 2);
+        let batch_1 = &costs[0];
+        assert_eq!(batch_1.batch_number, 1);
+        assert_eq!(
+            batch_1.operations[&L1BatchOperation::Commit].blob_fee_wei,
+            393_216 * GWEI
+        );
+        assert_eq!(
+            batch_1.operations[&L1BatchOperation::Prove].execution_fee_wei,
+            1_500_000 * GWEI
+        );
+        assert_eq!(
+            batch_1.total_wei(),
+            (2_000_000 + 393_216 + 1_500_000 + 250_000) * GWEI
+        );
+        assert_eq!(costs[1].total_wei(), (1_500_000 + 250_000) * GWEI);
+        assert!(!costs[1].operations.contains_key(&L1BatchOperation::Commit));
+
+        let summary = tracker.summary.borrow().clone();
+        let aggregates = &summary.aggregates;
+        assert_eq!(
+            aggregates.total.total_wei(),
+            commit.total_wei() + prove.total_wei() + execute.total_wei()
+        );
+        assert_eq!(aggregates.total.transactions[&L1BatchOperation::Prove], 1);
+        assert_eq!(aggregates.daily.len(), 2);
+        assert_eq!(
+            aggregates.daily[&MONDAY].total_wei(),
+            commit.total_wei() + prove.total_wei()
+        );
+        assert_eq!(
+            aggregates.daily[&(MONDAY + SECONDS_PER_DAY)].wei[&L1BatchOperation::Execute],
+            execute.total_wei()
+        );
+        assert_eq!(aggregates.weekly.len(), 1);
+        assert_eq!(aggregates.weekly[&MONDAY], aggregates.total);
+        assert_eq!(
+            summary
+                .recent_batches
+                .iter()
+                .map(|batch| batch.batch_number)
+                .collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert_eq!(
+            tracker.storage.load_l1_cost_aggregates().await.unwrap(),
+            Some(summary.aggregates)
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_records_are_skipped() {
+        let storage = InMemoryStorage::default();
+        storage.failing_batches.lock().unwrap().insert(1);
+        let (tracker, sender) = tracker(storage.clone()).await;
+        let costs: Vec<_> = (1..=2)
+            .map(|batch_number| {
+                L1TxCost::from_receipt(
+                    L1BatchOperation::Commit,
+                    (batch_number, batch_number),
+                    &receipt(batch_number as u8, 100_000, 20 * GWEI, None),
+                    MONDAY,
+                )
+            })
+            .collect();
+        for cost in &costs {
+            sender.send(cost.clone()).unwrap();
+        }
+        drop(sender);
+        let failures_before = L1_SENDER_METRICS.l1_cost_recording_failures.get();
+        // Only returns once all costs are processed
+        tracker.run().await.unwrap_err();
+
+        assert_eq!(
+            L1_SENDER_METRICS.l1_cost_recording_failures.get(),
+            failures_before + 1
+        );
+        let recorded = storage.costs(1, 2).await.unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].batch_number, 2);
+        let aggregates = storage.load_l1_cost_aggregates().await.unwrap().unwrap();
+        assert_eq!(aggregates.total.total_wei(), costs[1].total_wei());
+    }
+
+    #[tokio::test]
+    async fn drift_of_predicted_prices() {
+        let (mut tracker, _sender) = tracker(InMemoryStorage::default()).await;
+        let predicted = PredictedL1Prices {
+            gas_price: 10 * GWEI,
+            pubdata_price: GWEI,
+            pubdata_bytes: 131_072,
+        };
+        for batch_number in 1..=3 {
+            // Gas price is 50% above the predicted one; blob fee per pubdata byte matches it
+            let cost = L1TxCost::from_receipt(
+                L1BatchOperation::Commit,
+                (batch_number, batch_number),
+                &receipt(
+                    batch_number as u8,
+                    100_000,
+                    15 * GWEI,
+                    Some((131_072, GWEI)),
+                ),
+                MONDAY + batch_number * 60,
+            )
+            .with_predicted_prices(BTreeMap::from([(batch_number, predicted)]));
+            tracker.record(cost.clone()).await.unwrap();
+            // Duplicate reports are not observed again
+            tracker.record(cost).await.unwrap();
+        }
+        let price_drift = &tracker.price_drift;
+        assert_eq!(price_drift.drift(PredictedPrice::GasPrice), Some((0.5, 3)));
+        assert!(price_drift.is_alerting(PredictedPrice::GasPrice));
+        assert_eq!(
+            price_drift.drift(PredictedPrice::PubdataPrice),
+            Some((0.0, 3))
+        );
+        assert!(!price_drift.is_alerting(PredictedPrice::PubdataPrice));
+
+        let costs = tracker.storage.costs(1, 3).await.unwrap();
+        assert_eq!(
+            costs[0].operations[&L1BatchOperation::Commit].predicted_prices,
+            Some(predicted)
+        );
+        let report = PriceDriftReport::new(&costs, 0, u64::MAX);
+        assert_eq!(report.gas_price.samples, 3);
+        assert_eq!(report.gas_price.mean_error, Some(0.5));
+        assert_eq!(report.pubdata_price.max_abs_error, Some(0.0));
+    }
+
+    #[test]
+    fn csv_rows() {
+        let receipt = receipt(1, 100_000, 20 * GWEI, Some((131_072, 3 * GWEI)));
+        let cost = L1TxCost::from_receipt(L1BatchOperation::Commit, (5, 5), &receipt, MONDAY);
+        let mut batch = BatchCost::new(5);
+        batch
+            .operations
+            .extend(cost.per_batch().map(|(_, share)| (cost.operation, share)));
+        let csv = costs_to_csv(&[batch]);
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[1],
+            format!(
+                "5,commit,{},{MONDAY},100000,2000000000000000,393216000000000,2393216000000000",
+                TxHash::repeat_byte(1)
+            )
+        );
+    }
+}
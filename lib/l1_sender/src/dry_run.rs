@@ -0,0 +1,293 @@
+//! Dry run of L1 senders: estimates L1 transactions for commands without sending them.
+//!
+//! [`run_l1_sender_dry_run`] builds the transactions an L1 sender would send for a group of
+//! commands and reports their calldata, gas and cost at current gas adjuster prices as a
+//! [`DryRunReport`]. The report can be written to `dry_run_report_path` as JSON, so that e.g.
+//! release pipelines can diff expected commit costs before enabling the real sender. Nothing is
+//! signed or sent.
+
+use crate::batcher_model::L1BatchOperation;
+use crate::blobs::blob_count;
+use crate::commands::SendToL1;
+use crate::config::L1SenderConfig;
+use crate::gas_limit_with_safety_factor;
+use alloy::eips::eip4844::DATA_GAS_PER_BLOB;
+use alloy::network::TransactionBuilder;
+use alloy::primitives::{Address, Selector};
+use alloy::providers::Provider;
+use alloy::rpc::types::TransactionRequest;
+use alloy::sol_types::SolCall;
+use anyhow::Context;
+use serde::Serialize;
+use zksync_os_gas_adjuster::{GasAdjusterSnapshot, PubdataMode};
+
+/// L1 transaction an L1 sender would send for a single command.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DryRunTransaction {
+    pub operation: L1BatchOperation,
+    pub first_batch: u64,
+    pub last_batch: u64,
+    pub to_address: Address,
+    pub function_selector: Selector,
+    pub calldata_bytes: usize,
+    /// `None` if estimation failed, e.g. because previous batches are not committed on L1 yet.
+    pub estimated_gas: Option<u64>,
+    pub estimation_error: Option<String>,
+    /// Gas limit the sender would set, i.e. the max gas limit if estimation failed.
+    pub gas_limit: u64,
+    /// Only set in `Blobs` pubdata mode, for commands publishing pubdata.
+    pub blob_count: Option<u64>,
+    /// Estimated gas at the current gas price plus blob fees; `None` if estimation failed.
+    pub estimated_cost_wei: Option<u128>,
+}
+
+/// Result of [`run_l1_sender_dry_run`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DryRunReport {
+    pub command_name: &'static str,
+    pub operator_address: Address,
+    /// Prices the costs are estimated at, as reported by the gas adjuster.
+    pub gas_price: u128,
+    pub blob_base_fee: u128,
+    pub pubdata_mode: PubdataMode,
+    pub transactions: Vec<DryRunTransaction>,
+}
+
+impl DryRunReport {
+    /// Sum of the costs of all transactions with an estimated cost.
+    pub fn total_estimated_cost_wei(&self) -> u128 {
+        self.transactions
+            .iter()
+            .filter_map(|tx| tx.estimated_cost_wei)
+            .sum()
+    }
+}
+
+/// Estimates the L1 transactions that would be sent for `commands` (one per command, as with the
+/// real sender) at `prices`. Writes the report to `config.dry_run_report_path`, if set.
+pub async fn run_l1_sender_dry_run<Input: SendToL1>(
+    commands: &[Input],
+    to_address: Address,
+    provider: &dyn Provider,
+    operator_address: Address,
+    prices: &GasAdjusterSnapshot,
+    config: &L1SenderConfig<Input>,
+) -> anyhow::Result<DryRunReport> {
+    let mut transactions = Vec::with_capacity(commands.len());
+    for command in commands {
+        let calldata = command.solidity_call().abi_encode();
+        let function_selector = Selector::from_slice(&calldata[..4]);
+        let calldata_bytes = calldata.len();
+        let tx = TransactionRequest::default()
+            .with_from(operator_address)
+            .with_to(to_address)
+            .with_input(calldata);
+        let (estimated_gas, estimation_error) = match provider.estimate_gas(tx).await {
+            Ok(gas) => (Some(gas), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+        let gas_limit = estimated_gas.map_or(config.max_gas_limit, |gas| {
+            gas_limit_with_safety_factor(gas, config.gas_limit_safety_factor)
+                .min(config.max_gas_limit)
+        });
+
+        let blob_count = match (&prices.pubdata_mode, command.pubdata_composition()) {
+            (PubdataMode::Blobs, Some((zero_bytes, nonzero_bytes))) => {
+                Some(blob_count((zero_bytes + nonzero_bytes) as usize) as u64)
+            }
+            _ => None,
+        };
+        let blob_fee = blob_count.unwrap_or(0) as u128
+            * DATA_GAS_PER_BLOB as u128
+            * prices.blob_base_fee.estimate;
+        let (first_batch, last_batch) = command.batch_range();
+        transactions.push(DryRunTransaction {
+            operation: Input::OPERATION,
+            first_batch,
+            last_batch,
+            to_address,
+            function_selector,
+            calldata_bytes,
+            estimated_gas,
+            estimation_error,
+            gas_limit,
+            blob_count,
+            estimated_cost_wei: estimated_gas.map(|gas| gas as u128 * prices.gas_price + blob_fee),
+        });
+    }
+
+    let report = DryRunReport {
+        command_name: Input::NAME,
+        operator_address,
+        gas_price: prices.gas_price,
+        blob_base_fee: prices.blob_base_fee.estimate,
+        pubdata_mode: prices.pubdata_mode.clone(),
+        transactions,
+    };
+    tracing::info!(
+        command_name = Input::NAME,
+        range = Input::display_range(commands),
+        total_estimated_cost_wei = report.total_estimated_cost_wei(),
+        "finished L1 sender dry run"
+    );
+    if let Some(path) = &config.dry_run_report_path {
+        let json = serde_json::to_vec_pretty(&report)?;
+        std::fs::write(path, json)
+            .with_context(|| format!("failed to write dry run report to {path:?}"))?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batcher_model::{
+        BatchEnvelope, BatchMetadata, BatchSignatureData, FriProof, SignedBatchEnvelope,
+    };
+    use crate::commands::commit::CommitCommand;
+    use alloy::eips::eip4844::BYTES_PER_BLOB;
+    use alloy::primitives::U64;
+    use alloy::providers::ProviderBuilder;
+    use alloy::transports::mock::Asserter;
+    use serde_json::json;
+    use std::time::Duration;
+    use zksync_os_contract_interface::IExecutor;
+    use zksync_os_gas_adjuster::FeeSamplesSnapshot;
+
+    const GWEI: u128 = 1_000_000_000;
+    const TIMELOCK: Address = Address::repeat_byte(2);
+
+    fn commit(batch_number: u64, pubdata_len: usize) -> CommitCommand {
+        let mut metadata =
+            BatchMetadata::for_tests(batch_number, batch_number * 10 - 9..=batch_number * 10, 5);
+        metadata.batch_info.operator_da_input = vec![1; pubdata_len];
+        let envelope: SignedBatchEnvelope<FriProof> = BatchEnvelope::new(metadata, FriProof::Fake)
+            .with_signatures(BatchSignatureData::NotNeeded);
+        CommitCommand::new(envelope, PubdataMode::Calldata).unwrap()
+    }
+
+    fn config(dry_run_report_path: Option<PathBuf>) -> L1SenderConfig<CommitCommand> {
+        L1SenderConfig {
+            operator_pk: String::new().into(),
+            max_fee_per_gas_gwei: 200,
+            max_priority_fee_per_gas_gwei: 2,
+            max_fee_per_blob_gas_gwei: 10,
+            gas_limit_safety_factor: 1.2,
+            max_gas_limit: 10_000_000,
+            command_limit: 16,
+            poll_interval: Duration::from_secs(1),
+            resubmission_interval: Duration::from_secs(60),
+            max_outbound_backlog: 1,
+            min_operator_balance_gwei: 0,
+            allow_commitment_format_transition: false,
+            dry_run_report_path,
+            phantom_data: Default::default(),
+        }
+    }
+
+    fn prices(pubdata_mode: PubdataMode) -> GasAdjusterSnapshot {
+        GasAdjusterSnapshot {
+            last_processed_block: 100,
+            base_fee: FeeSamplesSnapshot::from_samples(&[10 * GWEI], 0.5),
+            blob_base_fee: FeeSamplesSnapshot::from_samples(&[3], 0.5),
+            pubdata_mode,
+            gas_price: 12 * GWEI,
+            pubdata_price: 3,
+        }
+    }
+
+    #[tokio::test]
+    async fn report_covers_all_commands() {
+        let asserter = Asserter::new();
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+        asserter.push_success(&U64::from(500_000));
+        // Batch 2 cannot be committed before batch 1
+        asserter.push_failure_msg("execution reverted");
+        let dir = tempfile::tempdir().unwrap();
+        let report_path = dir.path().join("dry_run.json");
+
+        let commands = [commit(1, 1_000), commit(2, BYTES_PER_BLOB + 1)];
+        let report = run_l1_sender_dry_run(
+            &commands,
+            TIMELOCK,
+            &provider,
+            Address::repeat_byte(0xaa),
+            &prices(PubdataMode::Blobs),
+            &config(Some(report_path.clone())),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(report.command_name, "commit");
+        assert_eq!(report.gas_price, 12 * GWEI);
+        assert_eq!(report.blob_base_fee, 3);
+        let [first, second] = report.transactions.as_slice() else {
+            panic!("unexpected transactions: {:?}", report.transactions);
+        };
+        assert_eq!(first.operation, L1BatchOperation::Commit);
+        assert_eq!((first.first_batch, first.last_batch), (1, 1));
+        assert_eq!(first.to_address, TIMELOCK);
+        assert_eq!(
+            first.function_selector,
+            IExecutor::commitBatchesSharedBridgeCall::SELECTOR
+        );
+        assert!(first.calldata_bytes > 1_000);
+        assert_eq!(first.estimated_gas, Some(500_000));
+        assert_eq!(first.estimation_error, None);
+        assert_eq!(first.gas_limit, 600_000);
+        assert_eq!(first.blob_count, Some(1));
+        let blob_fee = DATA_GAS_PER_BLOB as u128 * 3;
+        assert_eq!(
+            first.estimated_cost_wei,
+            Some(500_000 * 12 * GWEI + blob_fee)
+        );
+
+        assert_eq!((second.first_batch, second.last_batch), (2, 2));
+        assert!(second.calldata_bytes > BYTES_PER_BLOB);
+        assert_eq!(second.estimated_gas, None);
+        assert!(
+            second
+                .estimation_error
+                .as_ref()
+                .unwrap()
+                .contains("reverted")
+        );
+        assert_eq!(second.gas_limit, 10_000_000);
+        assert_eq!(second.blob_count, Some(2));
+        assert_eq!(second.estimated_cost_wei, None);
+        assert_eq!(
+            report.total_estimated_cost_wei(),
+            first.estimated_cost_wei.unwrap()
+        );
+
+        let written: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&report_path).unwrap()).unwrap();
+        assert_eq!(written["pubdata_mode"], "Blobs");
+        assert_eq!(
+            written["transactions"][0]["function_selector"],
+            json!(first.function_selector)
+        );
+        assert_eq!(written["transactions"][1]["estimated_gas"], json!(null));
+    }
+
+    #[tokio::test]
+    async fn blobs_are_only_counted_in_blobs_mode() {
+        let asserter = Asserter::new();
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+        asserter.push_success(&U64::from(500_000));
+
+        let report = run_l1_sender_dry_run(
+            &[commit(1, 1_000)],
+            TIMELOCK,
+            &provider,
+            Address::repeat_byte(0xaa),
+            &prices(PubdataMode::Calldata),
+            &config(None),
+        )
+        .await
+        .unwrap();
+        let tx = &report.transactions[0];
+        assert_eq!(tx.blob_count, None);
+        assert_eq!(tx.estimated_cost_wei, Some(500_000 * 12 * GWEI));
+    }
+}
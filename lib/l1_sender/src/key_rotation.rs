@@ -0,0 +1,262 @@
+//! Rotation of the operator key of a running L1 sender.
+//!
+//! [`KeyRotationHandle::rotate_operator`] asks the sender to switch to a new signer. The sender
+//! only applies rotations between groups of commands, i.e. after all transactions signed by
+//! the old key (or their replacements) are included, so no nonce of the old key is orphaned.
+//! Nonces of the next transactions are then fetched for the new operator address.
+//!
+//! Like the configured key at startup, the new key is only accepted if its address has non-zero
+//! balance and the validator role of the sender's command on the validator timelock. Rotated keys
+//! are not persisted: after a restart, the sender uses the configured `operator_pk` again.
+
+use crate::commands::SendToL1;
+use crate::metrics::L1_SENDER_METRICS;
+use crate::register_signer;
+use alloy::network::EthereumWallet;
+use alloy::primitives::Address;
+use alloy::providers::{Provider, WalletProvider};
+use alloy::signers::local::PrivateKeySigner;
+use anyhow::Context;
+use tokio::sync::{mpsc, oneshot};
+
+#[derive(Debug)]
+pub(crate) struct KeyRotationRequest {
+    signer: PrivateKeySigner,
+    response: oneshot::Sender<anyhow::Result<Address>>,
+}
+
+/// Requests operator key rotations from an L1 sender, see the module docs.
+#[derive(Debug, Clone)]
+pub struct KeyRotationHandle {
+    requests: mpsc::Sender<KeyRotationRequest>,
+}
+
+/// Receiving side of a [`KeyRotationHandle`], passed to the L1 sender.
+#[derive(Debug)]
+pub struct KeyRotationRequests {
+    requests: mpsc::Receiver<KeyRotationRequest>,
+}
+
+impl KeyRotationHandle {
+    pub fn new() -> (Self, KeyRotationRequests) {
+        let (sender, receiver) = mpsc::channel(1);
+        (
+            Self { requests: sender },
+            KeyRotationRequests { requests: receiver },
+        )
+    }
+
+    /// Switches the L1 sender to `new_signer` once its in-flight transactions are included.
+    /// Returns the new operator address; fails (keeping the old key) if the new operator has
+    /// zero balance, doesn't have the validator role, or the sender is not running.
+    /// The new key is not persisted, see the module docs.
+    pub async fn rotate_operator(&self, new_signer: PrivateKeySigner) -> anyhow::Result<Address> {
+        let (response, response_receiver) = oneshot::channel();
+        self.requests
+            .send(KeyRotationRequest {
+                signer: new_signer,
+                response,
+            })
+            .await
+            .ok()
+            .context("L1 sender is not running")?;
+        response_receiver
+            .await
+            .context("L1 sender stopped before rotating the operator key")?
+    }
+}
+
+impl KeyRotationRequests {
+    /// Takes a pending rotation request without waiting.
+    pub(crate) fn try_next(requests: &mut Option<Self>) -> Option<KeyRotationRequest> {
+        requests.as_mut()?.requests.try_recv().ok()
+    }
+
+    /// Waits for the next rotation request; never resolves if there are no (more) requests.
+    pub(crate) async fn next(requests: &mut Option<Self>) -> KeyRotationRequest {
+        match requests.as_mut() {
+            Some(this) => match this.requests.recv().await {
+                Some(request) => request,
+                None => {
+                    *requests = None;
+                    std::future::pending().await
+                }
+            },
+            None => std::future::pending().await,
+        }
+    }
+}
+
+/// Applies `request` to the sender using `provider`, updating `operator_address` on success.
+/// Must only be called when no transactions of the sender are in flight.
+pub(crate) async fn rotate_operator<P, Input>(
+    provider: &mut P,
+    operator_address: &mut Address,
+    validator_timelock: Address,
+    chain_address: Address,
+    request: KeyRotationRequest,
+) where
+    P: Provider + WalletProvider<Wallet = EthereumWallet>,
+    Input: SendToL1,
+{
+    let command_name = Input::NAME;
+    let old_address = *operator_address;
+    let result =
+        register_signer::<_, Input>(provider, request.signer, validator_timelock, chain_address)
+            .await;
+    match &result {
+        Ok(new_address) => {
+            *operator_address = *new_address;
+            let old_address_string: &'static str = old_address.to_string().leak();
+            L1_SENDER_METRICS.l1_operator_address[&(command_name, old_address_string)].set(0);
+            L1_SENDER_METRICS.operator_rotations[&command_name].inc();
+            tracing::warn!(
+                command_name,
+                %old_address,
+                %new_address,
+                "rotated L1 sender's operator key; the new key is not persisted, the sender \
+                 reverts to the configured `operator_pk` after a restart unless it's updated"
+            );
+        }
+        Err(err) => {
+            tracing::warn!(
+                command_name,
+                %old_address,
+                "failed to rotate L1 sender's operator key, keeping the old one: {err:#}"
+            );
+        }
+    }
+    // The requester may have given up waiting
+    let _ = request.response.send(result);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::commit::CommitCommand;
+    use crate::register_operator;
+    use alloy::consensus::Transaction;
+    use alloy::consensus::transaction::SignerRecoverable;
+    use alloy::network::TransactionBuilder;
+    use alloy::primitives::{Bytes, U256};
+    use alloy::providers::ProviderBuilder;
+    use alloy::rpc::types::TransactionRequest;
+    use alloy::sol_types::SolValue;
+    use alloy::transports::mock::Asserter;
+
+    const VALIDATOR_TIMELOCK: Address = Address::repeat_byte(0x11);
+    const CHAIN_ADDRESS: Address = Address::repeat_byte(0x22);
+
+    /// Mocks the L1 state checked when registering an operator.
+    fn push_operator_state(asserter: &Asserter, balance: U256, has_role: bool) {
+        asserter.push_success(&balance);
+        asserter.push_success(&Bytes::from(has_role.abi_encode()));
+    }
+
+    async fn rotate(
+        provider: &mut (impl Provider + WalletProvider<Wallet = EthereumWallet>),
+        operator_address: &mut Address,
+        request: KeyRotationRequest,
+    ) {
+        rotate_operator::<_, CommitCommand>(
+            provider,
+            operator_address,
+            VALIDATOR_TIMELOCK,
+            CHAIN_ADDRESS,
+            request,
+        )
+        .await;
+    }
+
+    /// Signs the next transaction of `operator_address` like the sender does, returning its
+    /// sender and nonce; the pending nonce is mocked as `nonce`.
+    async fn next_transaction(
+        provider: &(impl Provider + WalletProvider<Wallet = EthereumWallet>),
+        asserter: &Asserter,
+        operator_address: Address,
+        nonce: u64,
+    ) -> (Address, u64) {
+        asserter.push_success(&U256::from(nonce));
+        let nonce = provider
+            .get_transaction_count(operator_address)
+            .pending()
+            .await
+            .unwrap();
+        let tx = TransactionRequest::default()
+            .with_from(operator_address)
+            .with_to(Address::repeat_byte(1))
+            .with_chain_id(1)
+            .with_nonce(nonce)
+            .with_gas_limit(100_000)
+            .with_max_fee_per_gas(10)
+            .with_max_priority_fee_per_gas(1)
+            .build(provider.wallet())
+            .await
+            .unwrap();
+        (tx.recover_signer().unwrap(), tx.nonce())
+    }
+
+    #[tokio::test]
+    async fn transactions_after_rotation_use_new_operator() {
+        let asserter = Asserter::new();
+        let mut provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .wallet(EthereumWallet::default())
+            .connect_mocked_client(asserter.clone());
+        let old_signer = PrivateKeySigner::random();
+        let new_signer = PrivateKeySigner::random();
+        let balance = U256::from(10).pow(U256::from(18));
+
+        push_operator_state(&asserter, balance, true);
+        let old_key = old_signer.to_bytes().to_string();
+        let mut operator_address = register_operator::<_, CommitCommand>(
+            &mut provider,
+            old_key.into(),
+            VALIDATOR_TIMELOCK,
+            CHAIN_ADDRESS,
+        )
+        .await
+        .unwrap();
+        assert_eq!(operator_address, old_signer.address());
+        let before = next_transaction(&provider, &asserter, operator_address, 7).await;
+        assert_eq!(before, (old_signer.address(), 7));
+
+        let (handle, requests) = KeyRotationHandle::new();
+        let mut requests = Some(requests);
+        assert!(KeyRotationRequests::try_next(&mut requests).is_none());
+        let rotation = tokio::spawn({
+            let handle = handle.clone();
+            let new_signer = new_signer.clone();
+            async move { handle.rotate_operator(new_signer).await }
+        });
+        let request = KeyRotationRequests::next(&mut requests).await;
+        push_operator_state(&asserter, balance, true);
+        rotate(&mut provider, &mut operator_address, request).await;
+        assert_eq!(rotation.await.unwrap().unwrap(), new_signer.address());
+        assert_eq!(operator_address, new_signer.address());
+        assert_eq!(L1_SENDER_METRICS.operator_rotations[&"commit"].get(), 1);
+
+        // Nonces are tracked for the new account
+        let after = next_transaction(&provider, &asserter, operator_address, 0).await;
+        assert_eq!(after, (new_signer.address(), 0));
+
+        // Operators without balance or the validator role are rejected
+        for (balance, has_role) in [(U256::ZERO, true), (balance, false)] {
+            let rotation = tokio::spawn({
+                let handle = handle.clone();
+                async move { handle.rotate_operator(PrivateKeySigner::random()).await }
+            });
+            let request = KeyRotationRequests::next(&mut requests).await;
+            if balance.is_zero() {
+                asserter.push_success(&balance);
+            } else {
+                push_operator_state(&asserter, balance, has_role);
+            }
+            rotate(&mut provider, &mut operator_address, request).await;
+            rotation.await.unwrap().unwrap_err();
+            assert_eq!(operator_address, new_signer.address());
+        }
+        let after = next_transaction(&provider, &asserter, operator_address, 1).await;
+        assert_eq!(after, (new_signer.address(), 1));
+    }
+}
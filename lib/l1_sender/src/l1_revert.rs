@@ -0,0 +1,128 @@
+use crate::batcher_model::L1RevertStatus;
+use crate::metrics::L1SenderState;
+use anyhow::Context;
+use tokio::sync::watch;
+use zksync_os_error_reporting::WithErrorCode;
+use zksync_os_error_reporting::codes::l1_sender as error_codes;
+use zksync_os_observability::ComponentStateHandle;
+
+/// Waits while batches reverted on L1 are not acknowledged by the operator - sending on top of
+/// an unexpected L1 state would only produce failing L1 transactions.
+pub(crate) async fn wait_while_reverted(
+    reverts: &mut Option<watch::Receiver<L1RevertStatus>>,
+    latency_tracker: &ComponentStateHandle<L1SenderState>,
+    command_name: &'static str,
+) -> anyhow::Result<()> {
+    let Some(reverts) = reverts else {
+        return Ok(());
+    };
+    let revert = match *reverts.borrow_and_update() {
+        L1RevertStatus::None => return Ok(()),
+        L1RevertStatus::Detected(revert) => revert,
+        // Nothing must be sent anymore - the sender exits in `exit_on_revert_acknowledgment`
+        L1RevertStatus::Acknowledged(_) => return std::future::pending().await,
+    };
+    latency_tracker.enter_state(L1SenderState::PausedOnL1Revert);
+    tracing::error!(
+        command_name,
+        reverted_to_batch = revert.reverted_to_batch,
+        last_reverted_batch = revert.last_reverted_batch,
+        "batches were reverted on L1, pausing until the revert is acknowledged \
+         (`admin_requeueRevertedBatches({})`)",
+        revert.reverted_to_batch
+    );
+    let status = *reverts
+        .wait_for(|status| !matches!(status, L1RevertStatus::Detected(_)))
+        .await
+        .context("L1 revert status channel closed")?;
+    if matches!(status, L1RevertStatus::Acknowledged(_)) {
+        std::future::pending::<()>().await;
+    }
+    Ok(())
+}
+
+/// Resolves with an error once the operator acknowledges an L1 revert; never resolves otherwise.
+///
+/// Reverted batches are re-queued through the regular startup recovery: the sender exits, and
+/// after a restart batches above the last batch committed on L1 are sent for commit again.
+pub(crate) async fn exit_on_revert_acknowledgment(
+    reverts: Option<watch::Receiver<L1RevertStatus>>,
+    command_name: &'static str,
+) -> anyhow::Result<()> {
+    let Some(mut reverts) = reverts else {
+        return std::future::pending().await;
+    };
+    let status = *reverts
+        .wait_for(|status| matches!(status, L1RevertStatus::Acknowledged(_)))
+        .await
+        .context("L1 revert status channel closed")?;
+    let L1RevertStatus::Acknowledged(revert) = status else {
+        unreachable!("waited for an acknowledged revert");
+    };
+    tracing::warn!(
+        command_name,
+        reverted_to_batch = revert.reverted_to_batch,
+        last_reverted_batch = revert.last_reverted_batch,
+        "L1 revert acknowledged, restarting to re-commit reverted batches"
+    );
+    Err(anyhow::anyhow!(
+        "batches {}..={} reverted on L1 are re-queued for commit; restart to resume from L1 state",
+        revert.reverted_to_batch + 1,
+        revert.last_reverted_batch
+    )
+    .with_code(&error_codes::REVERT_ACKNOWLEDGED))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batcher_model::L1BatchRevert;
+    use std::time::Duration;
+    use zksync_os_observability::ComponentStateReporter;
+
+    const REVERT: L1BatchRevert = L1BatchRevert {
+        reverted_to_batch: 3,
+        reverted_to_block: 30,
+        last_reverted_batch: 5,
+        last_reverted_block: 50,
+        total_batches_verified: 3,
+        total_batches_executed: 2,
+        requires_local_rollback: false,
+    };
+
+    fn latency_tracker() -> ComponentStateHandle<L1SenderState> {
+        ComponentStateReporter::global().handle_for("commit", L1SenderState::WaitingRecv)
+    }
+
+    #[tokio::test]
+    async fn sending_is_paused_until_revert_is_acknowledged() {
+        let (sender, receiver) = watch::channel(L1RevertStatus::None);
+        let mut reverts = Some(receiver);
+        wait_while_reverted(&mut reverts, &latency_tracker(), "commit")
+            .await
+            .unwrap();
+
+        sender.send_replace(L1RevertStatus::Detected(REVERT));
+        tokio::time::timeout(
+            Duration::from_millis(50),
+            wait_while_reverted(&mut reverts, &latency_tracker(), "commit"),
+        )
+        .await
+        .unwrap_err();
+
+        let exit = tokio::spawn(exit_on_revert_acknowledgment(
+            Some(sender.subscribe()),
+            "commit",
+        ));
+        sender.send_replace(L1RevertStatus::Acknowledged(REVERT));
+        // Acknowledged batches are not sent on top of the reverted L1 state
+        tokio::time::timeout(
+            Duration::from_millis(50),
+            wait_while_reverted(&mut reverts, &latency_tracker(), "commit"),
+        )
+        .await
+        .unwrap_err();
+        let err = exit.await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("4..=5"), "{err}");
+    }
+}
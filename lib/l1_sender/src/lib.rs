@@ -1,50 +1,81 @@
 pub mod batcher_metrics;
 pub mod batcher_model;
+pub mod blobs;
+pub mod calldata;
 pub mod commands;
+pub mod commit_scheduler;
 pub mod commitment;
+mod commitment_format;
 pub mod config;
+pub mod cost_accounting;
+pub mod dry_run;
+pub mod key_rotation;
+mod l1_revert;
 mod metrics;
 pub mod pipeline_component;
+pub mod price_drift;
+mod resubmission;
+pub mod watchdog;
 
-use crate::batcher_model::{FriProof, SignedBatchEnvelope};
+use crate::batcher_model::{FriProof, L1RevertStatus, L1TxRecord, SignedBatchEnvelope};
 use crate::commands::{L1SenderCommand, SendToL1};
+use crate::commitment_format::CommitmentFormatGuard;
 use crate::config::L1SenderConfig;
+use crate::cost_accounting::L1TxCost;
+use crate::key_rotation::{KeyRotationRequests, rotate_operator};
+use crate::l1_revert::{exit_on_revert_acknowledgment, wait_while_reverted};
 use crate::metrics::{L1_SENDER_METRICS, L1SenderState};
-use alloy::network::{EthereumWallet, TransactionBuilder};
-use alloy::primitives::Address;
+use crate::price_drift::PredictedL1Prices;
+use crate::resubmission::{InFlightTransaction, ResubmissionPolicy};
+use alloy::eips::eip4844::BlobTransactionSidecar;
+use alloy::network::{EthereumWallet, TransactionBuilder, TransactionBuilder4844};
 use alloy::primitives::utils::format_ether;
+use alloy::primitives::{Address, U256, keccak256};
 use alloy::providers::ext::DebugApi;
-use alloy::providers::{PendingTransactionError, Provider, WalletProvider};
+use alloy::providers::{Provider, WalletProvider};
 use alloy::rpc::types::trace::geth::{CallConfig, GethDebugTracingOptions};
 use alloy::rpc::types::{TransactionReceipt, TransactionRequest};
 use alloy::signers::local::PrivateKeySigner;
+use alloy::sol_types::SolCall;
 use anyhow::Context;
-use futures::future::BoxFuture;
-use futures::{FutureExt, StreamExt, TryStreamExt};
 use secrecy::{ExposeSecret, SecretString};
 use std::str::FromStr;
-use std::time::Duration;
-use tokio::sync::mpsc::Sender;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::{Sender, UnboundedSender};
+use tokio::sync::watch;
+use zksync_os_contract_interface::IValidatorTimelock;
+use zksync_os_error_reporting::WithErrorCode;
+use zksync_os_error_reporting::codes::l1_sender as error_codes;
+use zksync_os_gas_adjuster::PubdataComposition;
 use zksync_os_observability::{ComponentStateHandle, ComponentStateReporter};
 use zksync_os_pipeline::PeekableReceiver;
 
-/// Maximum time to wait for a transaction to be included on L1.
-///
-/// Normally 15-30 seconds is enough for normal priority transactions, and 60-120 is enough for
-/// lower gas price transactions. We picked 300 seconds conservatively as it should cover most
-/// scenarios with network congestion.
-const TRANSACTION_TIMEOUT: Duration = Duration::from_secs(300);
-
-/// Future that resolves into a (fallible) transaction receipt.
-type TransactionReceiptFuture =
-    BoxFuture<'static, Result<TransactionReceipt, PendingTransactionError>>;
+/// How often to re-check operator balance while paused on insufficient balance.
+const BALANCE_POLL_INTERVAL: Duration = Duration::from_secs(30);
 
 /// Process responsible for sending transactions to L1.
 /// Handles one type of l1 command (e.g. Commit or Prove).
 /// Loads up to `command_limit` commands from the channel and sends them to L1 in parallel.
-/// Waits for all transactions to be mined, sends them to the output channel
+/// Waits for all transactions to be mined, queues them for the output channel
 /// and then starts with the next `command_limit` commands.
 ///
+/// Commit, prove and execute senders run as independent processes with their own operators
+/// (and thus nonces), input channels and pause conditions:
+///   * Mined batches wait for the next pipeline step in a backlog of up to `max_outbound_backlog`
+///     batches. Sending pauses only once the backlog is full, so e.g. a stall in SNARK proving
+///     doesn't block commits until that many batches are committed but not proven.
+///   * Sending pauses while the operator balance is below `min_operator_balance_gwei`.
+///   * Committing pauses when the commitment encoding version changes between consecutive
+///     batches, until the transition is acknowledged by the operator (see `CommitmentFormatGuard`).
+///   * Sending pauses when batches are reverted on L1, until the revert is acknowledged by
+///     the operator; the sender then exits so that reverted batches are re-committed on restart.
+///   * The operator key can be rotated via `key_rotations`, between groups of commands
+///     (see [`key_rotation`]).
+///
+/// Ordering across command types is enforced by the pipeline: a batch only reaches the prove
+/// (execute) sender after its commit (proof) transaction is mined.
+///
 /// Important: the same provider (sender address) must not be used outside this process.
 ///     Otherwise, there will be a nonce conflict and a failed L1 transaction
 ///     (recoverable on restart)
@@ -53,15 +84,29 @@ type TransactionReceiptFuture =
 ///   * Crashes when there is a gap in incoming L1 blocks (happens periodically with Infura provider)
 ///   * Does not attempt to detect in-flight L1 transactions on startup - just crashes if they get mined
 ///
-/// Note: we pass `to_address` - L1 contract address to send transactions to.
-/// It differs between commit/prove/execute (e.g., timelock vs diamond proxy)
+/// Note: we pass `to_address` - the validator timelock to send transactions to - and
+/// `chain_address` - the diamond proxy of the chain. The operator must have the validator role of
+/// the command (see [`SendToL1::VALIDATOR_ROLE`]) for the chain on the validator timelock.
 pub async fn run_l1_sender<Input: SendToL1>(
     // == plumbing ==
-    mut inbound: PeekableReceiver<L1SenderCommand<Input>>,
+    inbound: PeekableReceiver<L1SenderCommand<Input>>,
     outbound: Sender<SignedBatchEnvelope<FriProof>>,
+    // Receives every successfully included L1 transaction (e.g. for finality tracking)
+    l1_tx_records: Option<UnboundedSender<L1TxRecord>>,
+    // Receives fees paid for every successfully included L1 transaction (for cost accounting)
+    l1_tx_costs: Option<UnboundedSender<L1TxCost>>,
+    // Receives commitment encoding versions acknowledged by the operator (only used for commits)
+    commitment_format_acks: Option<watch::Receiver<Option<u8>>>,
+    // Receives the composition of pubdata in included L1 transactions (for pubdata pricing)
+    pubdata_composition: Option<PubdataComposition>,
+    // Receives batch reverts detected on L1 and their acknowledgments by the operator
+    l1_reverts: Option<watch::Receiver<L1RevertStatus>>,
+    // Receives operator key rotations requested via the corresponding `KeyRotationHandle`
+    key_rotations: Option<KeyRotationRequests>,
 
     // == command-specific settings ==
     to_address: Address,
+    chain_address: Address,
 
     // == config ==
     mut provider: impl Provider + WalletProvider<Wallet = EthereumWallet> + 'static,
@@ -71,28 +116,128 @@ pub async fn run_l1_sender<Input: SendToL1>(
         ComponentStateReporter::global().handle_for(Input::NAME, L1SenderState::WaitingRecv);
     let command_name = Input::NAME;
 
-    let operator_address =
-        register_operator::<_, Input>(&mut provider, config.operator_pk.clone()).await?;
+    let operator_address = register_operator::<_, Input>(
+        &mut provider,
+        config.operator_pk.clone(),
+        to_address,
+        chain_address,
+    )
+    .await?;
+    // All batches (including passthrough ones) go downstream through the backlog to preserve order
+    let (backlog, backlog_receiver) = mpsc::channel(config.max_outbound_backlog.max(1));
+    tokio::select! {
+        result = forward_backlog(backlog_receiver, outbound, command_name) => result,
+        result = exit_on_revert_acknowledgment(l1_reverts.clone(), command_name) => result,
+        result = send_commands(
+            inbound,
+            backlog,
+            l1_tx_records,
+            l1_tx_costs,
+            commitment_format_acks,
+            pubdata_composition,
+            l1_reverts,
+            key_rotations,
+            to_address,
+            chain_address,
+            provider,
+            operator_address,
+            config,
+            latency_tracker,
+        ) => result,
+    }
+}
+
+/// Forwards batches from the backlog downstream, in order.
+async fn forward_backlog<T>(
+    mut backlog: mpsc::Receiver<T>,
+    outbound: Sender<T>,
+    command_name: &'static str,
+) -> anyhow::Result<()> {
+    while let Some(envelope) = backlog.recv().await {
+        L1_SENDER_METRICS.outbound_backlog[&command_name].set(backlog.len() + 1);
+        outbound.send(envelope).await?;
+        L1_SENDER_METRICS.outbound_backlog[&command_name].set(backlog.len());
+    }
+    anyhow::bail!("outbound backlog closed")
+}
+
+/// Sends commands to L1 and queues processed batches in the backlog.
+#[allow(clippy::too_many_arguments)]
+async fn send_commands<Input: SendToL1>(
+    mut inbound: PeekableReceiver<L1SenderCommand<Input>>,
+    backlog: mpsc::Sender<SignedBatchEnvelope<FriProof>>,
+    l1_tx_records: Option<UnboundedSender<L1TxRecord>>,
+    l1_tx_costs: Option<UnboundedSender<L1TxCost>>,
+    commitment_format_acks: Option<watch::Receiver<Option<u8>>>,
+    pubdata_composition: Option<PubdataComposition>,
+    mut l1_reverts: Option<watch::Receiver<L1RevertStatus>>,
+    mut key_rotations: Option<KeyRotationRequests>,
+    to_address: Address,
+    chain_address: Address,
+    mut provider: impl Provider + WalletProvider<Wallet = EthereumWallet>,
+    mut operator_address: Address,
+    config: L1SenderConfig<Input>,
+    latency_tracker: ComponentStateHandle<L1SenderState>,
+) -> anyhow::Result<()> {
+    let command_name = Input::NAME;
+    let resubmission_policy = ResubmissionPolicy::new(&config);
     let mut cmd_buffer = Vec::with_capacity(config.command_limit);
+    let mut format_guard = CommitmentFormatGuard::new(
+        config.allow_commitment_format_transition,
+        commitment_format_acks,
+    );
 
     // Process all potential passthrough commands first
     process_prepending_passthrough_commands(
         &mut inbound,
-        &outbound,
+        &backlog,
+        &mut format_guard,
         &latency_tracker,
         command_name,
     )
     .await?;
     // At this point, only actual SendToL1 commands are expected
     loop {
+        if backlog.capacity() == 0 {
+            latency_tracker.enter_state(L1SenderState::PausedOnBacklog);
+            tracing::info!(
+                command_name,
+                max_outbound_backlog = backlog.max_capacity(),
+                "outbound backlog is full, pausing until the next pipeline step catches up"
+            );
+            // Only waiting for a free slot here, so the permit is dropped right away
+            drop(backlog.reserve().await?);
+        }
+        // No transactions are in flight at this point, so the operator key can be rotated
+        while let Some(request) = KeyRotationRequests::try_next(&mut key_rotations) {
+            rotate_operator::<_, Input>(
+                &mut provider,
+                &mut operator_address,
+                to_address,
+                chain_address,
+                request,
+            )
+            .await;
+        }
         latency_tracker.enter_state(L1SenderState::WaitingRecv);
         // This sleeps until **at least one** command is received from the channel. Additionally,
         // receives up to `self.command_limit` commands from the channel if they are ready (i.e. does
         // not wait for them). Extends `cmd_buffer` with received values and, as `cmd_buffer` is
         // emptied in every iteration, its size never exceeds `self.command_limit`.
-        let received = inbound
-            .recv_many(&mut cmd_buffer, config.command_limit)
-            .await;
+        let received = tokio::select! {
+            received = inbound.recv_many(&mut cmd_buffer, config.command_limit) => received,
+            request = KeyRotationRequests::next(&mut key_rotations) => {
+                rotate_operator::<_, Input>(
+                    &mut provider,
+                    &mut operator_address,
+                    to_address,
+                    chain_address,
+                    request,
+                )
+                .await;
+                continue;
+            }
+        };
         let mut commands = cmd_buffer
             .drain(..)
             .map(|cmd| -> anyhow::Result<Input> {
@@ -111,6 +256,28 @@ pub async fn run_l1_sender<Input: SendToL1>(
         if received == 0 {
             anyhow::bail!("inbound channel closed");
         }
+        if Input::GUARDS_COMMITMENT_FORMAT {
+            // Batches before a transition in the same group wait for the acknowledgment as well;
+            // in practice, transitions happen on the first batch committed after a restart
+            for command in &mut commands {
+                for envelope in command.as_mut() {
+                    format_guard
+                        .check(&mut envelope.batch, &latency_tracker)
+                        .await?;
+                }
+            }
+        }
+        wait_while_reverted(&mut l1_reverts, &latency_tracker, command_name).await?;
+        if !config.min_operator_balance().is_zero() {
+            wait_for_min_balance(
+                &provider,
+                operator_address,
+                config.min_operator_balance(),
+                &latency_tracker,
+                command_name,
+            )
+            .await?;
+        }
         latency_tracker.enter_state(L1SenderState::SendingToL1);
         let range = Input::display_range(&commands); // Only for logging
         tracing::info!(command_name, range, "sending L1 transactions");
@@ -118,56 +285,105 @@ pub async fn run_l1_sender<Input: SendToL1>(
         // It's important to preserve the order of commands -
         // so that we send them downstream also in order.
         // This holds true because l1 transactions are included in the order of sender nonce.
-        // Keep this in mind if changing sending logic (that is, if adding `buffer` we'd need to set nonce manually)
-        let pending_txs: Vec<(TransactionReceiptFuture, Input)> =
-            futures::stream::iter(commands.drain(..))
-                .then(|mut cmd| async {
-                    let tx_request = tx_request_with_gas_fields(
-                        &provider,
-                        operator_address,
-                        config.max_fee_per_gas(),
-                        config.max_priority_fee_per_gas(),
-                    )
-                    .await?
-                    .with_to(to_address)
-                    .with_call(&cmd.solidity_call());
-                    // We don't wait for receipt here, instead we register an alloy watcher that
-                    // polls for the receipt in the background. This future resolves when the watcher
-                    // finds it.
-                    let receipt_fut = provider
-                        .send_transaction(tx_request)
-                        .await?
-                        // We are being optimistic with our transaction inclusion here. But, even if
-                        // reorg happens and transaction will not be included in the new fork (very-very
-                        // unlikely), L1 sender will crash at some point (because a consequent L1
-                        // transactions will fail) and recover from the new L1 state after restart.
-                        .with_required_confirmations(1)
-                        // Ensure we don't wait indefinitely and crash if the transaction is not
-                        // included on L1 in a reasonable time.
-                        .with_timeout(Some(TRANSACTION_TIMEOUT))
-                        .get_receipt()
-                        .boxed();
-                    cmd.as_mut()
-                        .iter_mut()
-                        .for_each(|envelope| envelope.set_stage(Input::SENT_STAGE));
-                    anyhow::Ok((receipt_fut, cmd))
-                })
-                // We could buffer the stream here to enable sending multiple batches of transactions in parallel,
-                // but this is not necessary for now - we wait for them to be included in parallel
-                .try_collect::<Vec<_>>()
-                .await?;
+        // Nonces are set explicitly, so that stuck transactions can be replaced.
+        let mut nonce = provider
+            .get_transaction_count(operator_address)
+            .pending()
+            .await
+            .with_code(&error_codes::L1_RPC)?;
+        let mut pending_txs = Vec::with_capacity(commands.len());
+        for mut cmd in commands.drain(..) {
+            let tx_request = tx_request_with_gas_fields(
+                &provider,
+                operator_address,
+                to_address,
+                &cmd.solidity_call(),
+                cmd.blob_sidecar(),
+                &config,
+            )
+            .await?
+            .with_nonce(nonce);
+            nonce += 1;
+            // We don't wait for receipt here, instead we wait for all sent transactions
+            // to be included below.
+            let in_flight = InFlightTransaction::send(
+                &provider,
+                tx_request,
+                &resubmission_policy,
+                command_name,
+            )
+            .await?;
+            cmd.as_mut()
+                .iter_mut()
+                .for_each(|envelope| envelope.set_stage(Input::SENT_STAGE));
+            pending_txs.push((in_flight, cmd));
+        }
         tracing::info!(command_name, range, "sent to L1, waiting for inclusion");
         latency_tracker.enter_state(L1SenderState::WaitingL1Inclusion);
 
         let mut completed_commands = Vec::with_capacity(pending_txs.len());
-        for (receipt_fut, command) in pending_txs {
-            let receipt = receipt_fut.await?;
+        for (in_flight, command) in pending_txs {
+            // We are being optimistic with our transaction inclusion here. But, even if
+            // reorg happens and transaction will not be included in the new fork (very-very
+            // unlikely), L1 sender will crash at some point (because a consequent L1
+            // transactions will fail) and recover from the new L1 state after restart.
+            let receipt = in_flight
+                .wait_for_inclusion(&provider, &resubmission_policy)
+                .await?;
+            let tx_hash = receipt.transaction_hash;
+            if let Some(l1_tx_costs) = &l1_tx_costs
+                && receipt.status()
+            {
+                let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+                // Cost accounting is best-effort - it must never block the sender
+                let predicted_prices = command
+                    .as_ref()
+                    .iter()
+                    .filter_map(|envelope| {
+                        Some((
+                            envelope.batch_number(),
+                            PredictedL1Prices::new(&envelope.batch)?,
+                        ))
+                    })
+                    .collect();
+                let _ = l1_tx_costs.send(
+                    L1TxCost::from_receipt(
+                        Input::OPERATION,
+                        command.batch_range(),
+                        &receipt,
+                        timestamp,
+                    )
+                    .with_predicted_prices(predicted_prices),
+                );
+            }
             validate_tx_receipt(&provider, &command, receipt).await?;
+            if let Some(pubdata_composition) = &pubdata_composition
+                && let Some((zero_bytes, nonzero_bytes)) = command.pubdata_composition()
+            {
+                pubdata_composition.record_pubdata_composition(zero_bytes, nonzero_bytes);
+            }
+            if let Some(l1_tx_records) = &l1_tx_records {
+                let (first_batch, last_batch) = command.batch_range();
+                // Finality tracking is best-effort - it must never block the sender
+                let _ = l1_tx_records.send(L1TxRecord {
+                    operation: Input::OPERATION,
+                    first_batch,
+                    last_batch,
+                    tx_hash,
+                });
+            }
             completed_commands.push(command);
         }
 
-        let balance = format_ether(provider.get_balance(operator_address).await?);
-        let nonce = provider.get_transaction_count(operator_address).await?;
+        let balance = provider
+            .get_balance(operator_address)
+            .await
+            .with_code(&error_codes::L1_RPC)?;
+        let balance = format_ether(balance);
+        let nonce = provider
+            .get_transaction_count(operator_address)
+            .await
+            .with_code(&error_codes::L1_RPC)?;
         tracing::info!(
             command_name,
             range,
@@ -181,15 +397,47 @@ pub async fn run_l1_sender<Input: SendToL1>(
         for command in completed_commands {
             for mut output_envelope in command.into() {
                 output_envelope.set_stage(Input::MINED_STAGE);
-                outbound.send(output_envelope).await?;
+                backlog.send(output_envelope).await?;
             }
         }
+        L1_SENDER_METRICS.outbound_backlog[&command_name]
+            .set(backlog.max_capacity() - backlog.capacity());
+    }
+}
+
+/// Waits until the operator balance is at least `min_balance`.
+async fn wait_for_min_balance(
+    provider: &impl Provider,
+    operator_address: Address,
+    min_balance: U256,
+    latency_tracker: &ComponentStateHandle<L1SenderState>,
+    command_name: &'static str,
+) -> anyhow::Result<()> {
+    loop {
+        let balance = provider
+            .get_balance(operator_address)
+            .await
+            .with_code(&error_codes::L1_RPC)?;
+        L1_SENDER_METRICS.balance[&command_name].set(format_ether(balance).parse()?);
+        if balance >= min_balance {
+            return Ok(());
+        }
+        latency_tracker.enter_state(L1SenderState::PausedOnBalance);
+        tracing::warn!(
+            command_name,
+            %operator_address,
+            balance = format_ether(balance),
+            min_balance = format_ether(min_balance),
+            "L1 sender's operator balance is below the minimum, pausing"
+        );
+        tokio::time::sleep(BALANCE_POLL_INTERVAL).await;
     }
 }
 
 async fn process_prepending_passthrough_commands<Input: SendToL1>(
     inbound: &mut PeekableReceiver<L1SenderCommand<Input>>,
     outbound: &Sender<SignedBatchEnvelope<FriProof>>,
+    format_guard: &mut CommitmentFormatGuard,
     latency_tracker: &ComponentStateHandle<L1SenderState>,
     command_name: &str,
 ) -> anyhow::Result<()> {
@@ -216,6 +464,7 @@ async fn process_prepending_passthrough_commands<Input: SendToL1>(
                             batch_number = batch.batch_number(),
                             "Not actually sending to L1, just passing through"
                         );
+                        format_guard.observe_committed(&batch.batch);
                         latency_tracker.enter_state(L1SenderState::WaitingSend);
                         outbound
                             .send((*batch).with_stage(Input::PASSTHROUGH_STAGE))
@@ -227,13 +476,22 @@ async fn process_prepending_passthrough_commands<Input: SendToL1>(
     }
 }
 
-async fn tx_request_with_gas_fields(
+/// Builds an L1 transaction calling `call` on `to_address`, with fees and the gas limit estimated
+/// by `provider` and capped according to `config`.
+async fn tx_request_with_gas_fields<Input: SendToL1>(
     provider: &dyn Provider,
     operator_address: Address,
-    max_fee_per_gas: u128,
-    max_priority_fee_per_gas: u128,
+    to_address: Address,
+    call: &impl SolCall,
+    blob_sidecar: Option<&BlobTransactionSidecar>,
+    config: &L1SenderConfig<Input>,
 ) -> anyhow::Result<TransactionRequest> {
-    let eip1559_est = provider.estimate_eip1559_fees().await?;
+    let max_fee_per_gas = config.max_fee_per_gas();
+    let max_priority_fee_per_gas = config.max_priority_fee_per_gas();
+    let eip1559_est = provider
+        .estimate_eip1559_fees()
+        .await
+        .with_code(&error_codes::L1_RPC)?;
     tracing::debug!(
         eip1559_est.max_priority_fee_per_gas,
         "estimated median priority fee (20% percentile) for the last 10 blocks"
@@ -253,13 +511,89 @@ async fn tx_request_with_gas_fields(
         );
     }
 
-    let tx = TransactionRequest::default()
+    // Estimated fees are used as long as they are below the configured max fees; transactions
+    // that are not included in time are replaced with ones paying higher fees, up to the max fees
+    let mut tx = TransactionRequest::default()
         .with_from(operator_address)
-        .with_max_fee_per_gas(max_fee_per_gas)
-        .with_max_priority_fee_per_gas(max_priority_fee_per_gas)
-        // Default value for `max_aggregated_tx_gas` from zksync-era, should always be enough
-        .with_gas_limit(15000000);
-    Ok(tx)
+        .with_to(to_address)
+        .with_call(call)
+        .with_max_fee_per_gas(eip1559_est.max_fee_per_gas.min(max_fee_per_gas))
+        .with_max_priority_fee_per_gas(
+            eip1559_est
+                .max_priority_fee_per_gas
+                .min(max_priority_fee_per_gas),
+        );
+    if let Some(blob_sidecar) = blob_sidecar {
+        let max_fee_per_blob_gas = config.max_fee_per_blob_gas();
+        // Same headroom over the current base fee as in `estimate_eip1559_fees()`
+        let estimated_max_fee_per_blob_gas = provider
+            .get_blob_base_fee()
+            .await
+            .with_code(&error_codes::L1_RPC)?
+            .saturating_mul(2);
+        if estimated_max_fee_per_blob_gas > max_fee_per_blob_gas {
+            tracing::warn!(
+                max_fee_per_blob_gas,
+                estimated_max_fee_per_blob_gas,
+                "L1 sender's configured maxFeePerBlobGas is lower than the one estimated from network"
+            );
+        }
+        tx = tx
+            .with_blob_sidecar(blob_sidecar.clone())
+            .with_max_fee_per_blob_gas(estimated_max_fee_per_blob_gas.min(max_fee_per_blob_gas));
+    }
+    let gas_limit = estimate_gas_limit(
+        provider,
+        &tx,
+        config.gas_limit_safety_factor,
+        config.max_gas_limit,
+        Input::NAME,
+    )
+    .await;
+    Ok(tx.with_gas_limit(gas_limit))
+}
+
+/// Estimates gas used by `tx` and multiplies it by `safety_factor`, capped at `max_gas_limit`.
+/// Falls back to `max_gas_limit` if estimation fails, so that the transaction is still sent
+/// (and, if it reverts, the revert is reported with its reason).
+async fn estimate_gas_limit(
+    provider: &dyn Provider,
+    tx: &TransactionRequest,
+    safety_factor: f64,
+    max_gas_limit: u64,
+    command_name: &'static str,
+) -> u64 {
+    let gas_limit = match provider.estimate_gas(tx.clone()).await {
+        Ok(estimated_gas) => {
+            let gas_limit = gas_limit_with_safety_factor(estimated_gas, safety_factor);
+            if gas_limit > max_gas_limit {
+                tracing::warn!(
+                    command_name,
+                    estimated_gas,
+                    gas_limit,
+                    max_gas_limit,
+                    "gas limit of L1 transaction exceeds the configured max, using the max"
+                );
+            }
+            gas_limit.min(max_gas_limit)
+        }
+        Err(err) => {
+            tracing::warn!(
+                command_name,
+                max_gas_limit,
+                "failed to estimate gas of L1 transaction, using the max gas limit: {err}"
+            );
+            L1_SENDER_METRICS.gas_estimation_failures[&command_name].inc();
+            max_gas_limit
+        }
+    };
+    L1_SENDER_METRICS.gas_limit[&command_name].set(gas_limit);
+    gas_limit
+}
+
+/// Gas limit for a transaction estimated to use `estimated_gas`, not capped yet.
+fn gas_limit_with_safety_factor(estimated_gas: u64, safety_factor: f64) -> u64 {
+    (estimated_gas as f64 * safety_factor).ceil() as u64
 }
 
 async fn register_operator<
@@ -268,26 +602,61 @@ async fn register_operator<
 >(
     provider: &mut P,
     private_key: SecretString,
+    validator_timelock: Address,
+    chain_address: Address,
 ) -> anyhow::Result<Address> {
     let signer = PrivateKeySigner::from_str(private_key.expose_secret())
-        .context("failed to parse operator private key")?;
+        .context("failed to parse operator private key")
+        .with_code(&error_codes::INVALID_OPERATOR_KEY)?;
+    let address =
+        register_signer::<_, Input>(provider, signer, validator_timelock, chain_address).await?;
+    tracing::info!(command_name = Input::NAME, %address, "initialized L1 sender");
+    Ok(address)
+}
+
+/// Registers `signer` in the provider's wallet, so that transactions from its address are signed.
+/// Fails if the signer's address has zero balance or doesn't have the validator role of `Input`
+/// for `chain_address` on `validator_timelock`.
+async fn register_signer<P: Provider + WalletProvider<Wallet = EthereumWallet>, Input: SendToL1>(
+    provider: &mut P,
+    signer: PrivateKeySigner,
+    validator_timelock: Address,
+    chain_address: Address,
+) -> anyhow::Result<Address> {
     let address = signer.address();
+    let balance = provider
+        .get_balance(address)
+        .await
+        .with_code(&error_codes::L1_RPC)?;
+    if balance.is_zero() {
+        return Err(
+            anyhow::anyhow!("L1 sender's address {address} has zero balance")
+                .with_code(&error_codes::ZERO_BALANCE),
+        );
+    }
+    let has_role = IValidatorTimelock::new(validator_timelock, &*provider)
+        .hasRoleForChainAddress(chain_address, keccak256(Input::VALIDATOR_ROLE), address)
+        .call()
+        .await
+        .with_code(&error_codes::L1_RPC)?;
+    if !has_role {
+        return Err(anyhow::anyhow!(
+            "L1 sender's address {address} doesn't have {} for chain {chain_address} on validator \
+             timelock {validator_timelock}",
+            Input::VALIDATOR_ROLE
+        )
+        .with_code(&error_codes::MISSING_VALIDATOR_ROLE));
+    }
     provider.wallet_mut().register_signer(signer);
 
-    let balance = provider.get_balance(address).await?;
     L1_SENDER_METRICS.balance[&Input::NAME].set(format_ether(balance).parse()?);
     let address_string: &'static str = address.to_string().leak();
     L1_SENDER_METRICS.l1_operator_address[&(Input::NAME, address_string)].set(1);
-
-    if balance.is_zero() {
-        anyhow::bail!("L1 sender's address {address} has zero balance");
-    }
-
     tracing::info!(
         command_name = Input::NAME,
         balance_eth = format_ether(balance),
         %address,
-        "initialized L1 sender",
+        "registered L1 sender's operator",
     );
     Ok(address)
 }
@@ -362,10 +731,118 @@ async fn validate_tx_receipt<Input: SendToL1>(
                 "Failed transaction's top-level call frame"
             );
         }
-        anyhow::bail!(
+        Err(anyhow::anyhow!(
             "{} L1 command transaction failed, see L1 transaction's trace for more details (tx_hash='{:?}')",
             command,
             receipt.transaction_hash
+        )
+        .with_code(&error_codes::TX_REVERTED))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::commit::CommitCommand;
+    use alloy::primitives::U64;
+    use alloy::providers::ProviderBuilder;
+    use alloy::transports::mock::Asserter;
+    use zksync_os_error_reporting::{ERROR_METRICS, Severity, report_error};
+
+    #[tokio::test]
+    async fn downstream_stall_blocks_sender_only_when_backlog_is_full() {
+        let (backlog, backlog_receiver) = mpsc::channel(3);
+        // Next pipeline step (e.g. SNARK proving for the commit sender) that doesn't make progress
+        let (outbound, mut outbound_receiver) = mpsc::channel(1);
+        let forward_task = tokio::spawn(forward_backlog(backlog_receiver, outbound, "test"));
+
+        // One batch is accepted by the stalled step, one is being forwarded,
+        // three more fit into the backlog
+        for batch_number in 0..5 {
+            tokio::time::timeout(Duration::from_secs(1), backlog.send(batch_number))
+                .await
+                .expect("sender must not be blocked")
+                .unwrap();
+        }
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while backlog.capacity() > 0 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .unwrap();
+        // The sender pauses now...
+        tokio::time::timeout(Duration::from_millis(50), backlog.reserve())
+            .await
+            .unwrap_err();
+
+        // ...until the step catches up; order is preserved
+        for expected in 0..5 {
+            assert_eq!(outbound_receiver.recv().await, Some(expected));
+        }
+        backlog.send(5).await.unwrap();
+        assert_eq!(outbound_receiver.recv().await, Some(5));
+
+        drop(backlog);
+        forward_task.await.unwrap().unwrap_err();
+    }
+
+    async fn gas_limit(asserter: &Asserter, max_gas_limit: u64, command_name: &'static str) -> u64 {
+        let provider = ProviderBuilder::new().connect_mocked_client(asserter.clone());
+        let tx = TransactionRequest::default().with_to(Address::repeat_byte(1));
+        estimate_gas_limit(&provider, &tx, 1.2, max_gas_limit, command_name).await
+    }
+
+    #[tokio::test]
+    async fn gas_limit_is_estimated_with_safety_factor() {
+        let asserter = Asserter::new();
+        asserter.push_success(&U64::from(1_000_000));
+        assert_eq!(
+            gas_limit(&asserter, 30_000_000, "estimated").await,
+            1_200_000
+        );
+        assert_eq!(L1_SENDER_METRICS.gas_limit[&"estimated"].get(), 1_200_000);
+    }
+
+    #[tokio::test]
+    async fn gas_limit_is_capped() {
+        let asserter = Asserter::new();
+        asserter.push_success(&U64::from(9_000_000));
+        assert_eq!(gas_limit(&asserter, 10_000_000, "capped").await, 10_000_000);
+    }
+
+    #[tokio::test]
+    async fn operator_without_balance_is_reported() {
+        let asserter = Asserter::new();
+        let mut provider = ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .wallet(EthereumWallet::default())
+            .connect_mocked_client(asserter.clone());
+        let labels = ("L1S-005", "l1_sender", Severity::Critical);
+        let reported_before = ERROR_METRICS.errors[&labels].get();
+
+        asserter.push_success(&U256::ZERO);
+        let err = register_signer::<_, CommitCommand>(
+            &mut provider,
+            PrivateKeySigner::random(),
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("has zero balance"), "{err}");
+        report_error(&err);
+        assert_eq!(ERROR_METRICS.errors[&labels].get() - reported_before, 1);
+    }
+
+    #[tokio::test]
+    async fn max_gas_limit_is_used_if_estimation_fails() {
+        let asserter = Asserter::new();
+        asserter.push_failure_msg("execution reverted");
+        assert_eq!(gas_limit(&asserter, 10_000_000, "failed").await, 10_000_000);
+        assert_eq!(
+            L1_SENDER_METRICS.gas_estimation_failures[&"failed"].get(),
+            1
         );
     }
 }
@@ -1,4 +1,5 @@
-use vise::{Buckets, EncodeLabelValue, Gauge, Histogram, LabeledFamily, Metrics};
+use std::time::Duration;
+use vise::{Buckets, Counter, EncodeLabelValue, Gauge, Histogram, LabeledFamily, Metrics, Unit};
 use zksync_os_observability::{GenericComponentState, StateLabel};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EncodeLabelValue)]
@@ -8,6 +9,34 @@ pub enum L1SenderState {
     WaitingSend,
     SendingToL1,
     WaitingL1Inclusion,
+    /// Outbound backlog is full - the next pipeline step doesn't keep up.
+    PausedOnBacklog,
+    /// Operator balance is below the configured minimum.
+    PausedOnBalance,
+    /// Commitment encoding version changed and the transition is not acknowledged yet.
+    PausedOnFormatTransition,
+    /// Batches were reverted on L1 and the revert is not acknowledged yet.
+    PausedOnL1Revert,
+}
+
+impl L1SenderState {
+    const ALL: [Self; 8] = [
+        Self::WaitingRecv,
+        Self::WaitingSend,
+        Self::SendingToL1,
+        Self::WaitingL1Inclusion,
+        Self::PausedOnBacklog,
+        Self::PausedOnBalance,
+        Self::PausedOnFormatTransition,
+        Self::PausedOnL1Revert,
+    ];
+
+    /// Inverse of [`StateLabel::specific()`].
+    pub(crate) fn from_specific(label: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|state| state.specific() == label)
+    }
 }
 
 impl StateLabel for L1SenderState {
@@ -17,6 +46,10 @@ impl StateLabel for L1SenderState {
             L1SenderState::WaitingSend => GenericComponentState::WaitingSend,
             L1SenderState::SendingToL1 => GenericComponentState::Processing,
             L1SenderState::WaitingL1Inclusion => GenericComponentState::Processing,
+            L1SenderState::PausedOnBacklog => GenericComponentState::WaitingSend,
+            L1SenderState::PausedOnBalance => GenericComponentState::Processing,
+            L1SenderState::PausedOnFormatTransition => GenericComponentState::Processing,
+            L1SenderState::PausedOnL1Revert => GenericComponentState::Processing,
         }
     }
     fn specific(&self) -> &'static str {
@@ -25,6 +58,10 @@ impl StateLabel for L1SenderState {
             L1SenderState::WaitingSend => "waiting_send",
             L1SenderState::SendingToL1 => "sending_to_l1",
             L1SenderState::WaitingL1Inclusion => "waiting_l1_inclusion",
+            L1SenderState::PausedOnBacklog => "paused_on_backlog",
+            L1SenderState::PausedOnBalance => "paused_on_balance",
+            L1SenderState::PausedOnFormatTransition => "paused_on_format_transition",
+            L1SenderState::PausedOnL1Revert => "paused_on_l1_revert",
         }
     }
 }
@@ -61,9 +98,83 @@ pub struct L1SenderMetrics {
     #[metrics(labels = ["command"], buckets = Buckets::exponential(1.0..=10_000_000.0, 3.0))]
     pub gas_used_per_l2_tx: LabeledFamily<&'static str, Histogram<u64>>,
 
+    /// Gas limit of the last sent L1 transaction - see `l1_sender_gas_limit_safety_factor`.
+    #[metrics(labels = ["command"])]
+    pub gas_limit: LabeledFamily<&'static str, Gauge<u64>>,
+
+    /// L1 transactions sent with the max gas limit because gas estimation failed.
+    #[metrics(labels = ["command"])]
+    pub gas_estimation_failures: LabeledFamily<&'static str, Counter>,
+
+    /// L1 transactions replaced with ones paying higher fees - see `resubmission`.
+    #[metrics(labels = ["command"])]
+    pub fee_bumps: LabeledFamily<&'static str, Counter>,
+
+    /// Time from sending an L1 transaction until it (or its replacement) is included.
+    #[metrics(unit = Unit::Seconds, labels = ["command"], buckets = Buckets::exponential(1.0..=3_600.0, 2.0))]
+    pub time_to_inclusion: LabeledFamily<&'static str, Histogram<Duration>>,
+
+    /// Number of batches processed on L1 that wait to be accepted by the next pipeline step.
+    #[metrics(labels = ["command"])]
+    pub outbound_backlog: LabeledFamily<&'static str, Gauge<usize>>,
+
+    /// Operator key rotations applied - see `key_rotation`.
+    #[metrics(labels = ["command"])]
+    pub operator_rotations: LabeledFamily<&'static str, Counter>,
+
     /// Last nonce used
     #[metrics(labels = ["command"])]
     pub nonce: LabeledFamily<&'static str, Gauge<u64>>,
+
+    /// Wei spent on L1 transactions (execution and blob fees) in the current UTC day / week
+    /// and in total - see `cost_accounting`.
+    #[metrics(labels = ["period", "command"])]
+    pub l1_cost_wei: LabeledFamily<(&'static str, &'static str), Gauge<f64>, 2>,
+
+    /// Costs of L1 transactions that failed to be recorded and were skipped.
+    pub l1_cost_recording_failures: Counter,
+
+    /// Relative error of the L1 price predicted by the gas adjuster (`actual / predicted - 1`)
+    /// for the last batch processed on L1 - see `price_drift`.
+    #[metrics(labels = ["price"])]
+    pub l1_price_prediction_error: LabeledFamily<&'static str, Gauge<f64>>,
+
+    /// Absolute relative error of predicted L1 prices.
+    #[metrics(labels = ["price"], buckets = Buckets::exponential(0.01..=10.0, 2.0))]
+    pub l1_price_prediction_abs_error: LabeledFamily<&'static str, Histogram<f64>>,
+
+    /// Mean absolute relative error of predicted L1 prices within the drift window.
+    #[metrics(labels = ["price"])]
+    pub l1_price_drift: LabeledFamily<&'static str, Gauge<f64>>,
+
+    /// Set to 1 while `l1_price_drift` exceeds the configured threshold.
+    #[metrics(labels = ["price"])]
+    pub l1_price_drift_alert: LabeledFamily<&'static str, Gauge<u64>>,
+
+    /// Time commit commands spend in the commit scheduler - see `commit_scheduler`.
+    #[metrics(unit = Unit::Seconds, buckets = Buckets::LATENCIES)]
+    pub commit_scheduler_delay: Histogram<Duration>,
+
+    /// Batch commits deferred because the current L1 base fee was high.
+    pub commits_deferred_due_to_fees: Counter,
+
+    /// Batch commits submitted early because a batch with priority transactions close to
+    /// expiring was waiting for them.
+    pub commits_for_priority_deadlines: Counter,
+
+    /// Number of sealed batches not committed on L1 yet, as sampled by the watchdog.
+    pub watchdog_commit_lag_batches: Gauge<u64>,
+
+    /// Time since the last committed batch advanced while sealed batches were waiting for a commit.
+    #[metrics(unit = Unit::Seconds)]
+    pub watchdog_commit_stall: Gauge<f64>,
+
+    /// Set to 1 while the watchdog raises a critical alert on stalled commits.
+    pub watchdog_stall_alert: Gauge<u64>,
+
+    /// Critical alerts raised by the watchdog, by diagnosis hint.
+    #[metrics(labels = ["hint"])]
+    pub watchdog_alerts: LabeledFamily<&'static str, Counter>,
 }
 
 #[vise::register]
@@ -1,12 +1,17 @@
-use crate::batcher_model::{FriProof, SignedBatchEnvelope};
+use crate::batcher_model::{FriProof, L1RevertStatus, L1TxRecord, SignedBatchEnvelope};
 use crate::commands::{L1SenderCommand, SendToL1};
 use crate::config::L1SenderConfig;
+use crate::cost_accounting::L1TxCost;
+use crate::key_rotation::KeyRotationRequests;
 use crate::run_l1_sender;
 use alloy::network::EthereumWallet;
 use alloy::primitives::Address;
 use alloy::providers::{Provider, WalletProvider};
 use async_trait::async_trait;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, watch};
+use zksync_os_error_reporting::WithErrorCode;
+use zksync_os_error_reporting::codes::l1_sender as error_codes;
+use zksync_os_gas_adjuster::PubdataComposition;
 use zksync_os_pipeline::{PeekableReceiver, PipelineComponent};
 
 /// Generic L1 Sender pipeline component
@@ -15,6 +20,22 @@ pub struct L1Sender<P, C> {
     pub provider: P,
     pub config: L1SenderConfig<C>,
     pub to_address: Address,
+    /// Diamond proxy of the chain; see [`run_l1_sender`] for details.
+    pub chain_address: Address,
+    /// Optional sink for included L1 transactions (used for L1 finality tracking).
+    pub l1_tx_records: Option<mpsc::UnboundedSender<L1TxRecord>>,
+    /// Optional sink for fees paid for included L1 transactions (used for L1 cost accounting).
+    pub l1_tx_costs: Option<mpsc::UnboundedSender<L1TxCost>>,
+    /// Optional source of commitment encoding versions acknowledged by the operator
+    /// (only used by the commit sender).
+    pub commitment_format_acks: Option<watch::Receiver<Option<u8>>>,
+    /// Optional sink for the composition of pubdata sent to L1 (used for calldata pubdata pricing).
+    pub pubdata_composition: Option<PubdataComposition>,
+    /// Optional source of batch reverts detected on L1; sending pauses until they are acknowledged.
+    pub l1_reverts: Option<watch::Receiver<L1RevertStatus>>,
+    /// Optional source of operator key rotations, requested via the corresponding
+    /// [`KeyRotationHandle`](crate::key_rotation::KeyRotationHandle).
+    pub key_rotations: Option<KeyRotationRequests>,
 }
 
 #[async_trait]
@@ -34,6 +55,21 @@ where
         input: PeekableReceiver<Self::Input>,
         output: mpsc::Sender<Self::Output>,
     ) -> anyhow::Result<()> {
-        run_l1_sender(input, output, self.to_address, self.provider, self.config).await
+        run_l1_sender(
+            input,
+            output,
+            self.l1_tx_records,
+            self.l1_tx_costs,
+            self.commitment_format_acks,
+            self.pubdata_composition,
+            self.l1_reverts,
+            self.key_rotations,
+            self.to_address,
+            self.chain_address,
+            self.provider,
+            self.config,
+        )
+        .await
+        .with_code(&error_codes::FAILED)
     }
 }
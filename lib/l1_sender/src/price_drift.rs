@@ -0,0 +1,416 @@
+//! Closed-loop verification of the L1 prices predicted by the gas adjuster.
+//!
+//! Batches record the prices predicted while their blocks were produced
+//! ([`BatchMetadata::l1_price_prediction`]). Once an L1 transaction covering a batch is included,
+//! [`L1CostTracker`](crate::cost_accounting::L1CostTracker) compares the batch's share of its cost
+//! with the prediction:
+//!
+//! - the L1 gas price is compared with the effective gas price of commit/prove/execute txs;
+//! - the pubdata price is compared with the blob fee per pubdata byte of commit transactions.
+//!   In calldata mode, pubdata is paid within the execution fee and cannot be told apart from it.
+//!
+//! Errors are exported as metrics; if the mean absolute error within the trailing window exceeds
+//! the configured threshold, a drift alert is raised. Predictions are persisted along with
+//! per-batch costs, so that drift can be reported for past batches.
+
+use crate::batcher_model::{BatchMetadata, L1BatchOperation};
+use crate::cost_accounting::{BatchCost, OperationCost};
+use crate::metrics::L1_SENDER_METRICS;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+/// Min number of errors within the window required to raise a drift alert, so that a single
+/// outlier doesn't trigger it.
+const MIN_ALERT_SAMPLES: usize = 3;
+
+/// Prices predicted for a batch (see [`zksync_os_types::L1PricePrediction`]) along with the
+/// pubdata they apply to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PredictedL1Prices {
+    pub gas_price: u128,
+    pub pubdata_price: u128,
+    pub pubdata_bytes: u64,
+}
+
+impl PredictedL1Prices {
+    /// Returns `None` for batches without a recorded prediction.
+    pub fn new(batch: &BatchMetadata) -> Option<Self> {
+        let prediction = batch.l1_price_prediction?;
+        Some(Self {
+            gas_price: prediction.gas_price,
+            pubdata_price: prediction.pubdata_price,
+            pubdata_bytes: batch.pubdata_bytes?,
+        })
+    }
+}
+
+/// Price predicted by the gas adjuster.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PredictedPrice {
+    GasPrice,
+    PubdataPrice,
+}
+
+impl PredictedPrice {
+    pub const ALL: [PredictedPrice; 2] = [Self::GasPrice, Self::PubdataPrice];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::GasPrice => "gas_price",
+            Self::PubdataPrice => "pubdata_price",
+        }
+    }
+}
+
+/// Relative errors of predicted prices: `actual / predicted - 1`, so positive values mean that L1
+/// was more expensive than predicted. `None` if a price cannot be compared.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct PredictionError {
+    pub gas_price: Option<f64>,
+    pub pubdata_price: Option<f64>,
+}
+
+impl PredictionError {
+    /// Compares prices paid for a batch's share of an L1 transaction with the ones predicted for
+    /// the batch.
+    pub fn new(operation: L1BatchOperation, cost: &OperationCost) -> Self {
+        let Some(predicted) = cost.predicted_prices else {
+            return Self::default();
+        };
+        let gas_price = (cost.gas_used > 0)
+            .then(|| cost.execution_fee_wei as f64 / cost.gas_used as f64)
+            .and_then(|actual| relative_error(actual, predicted.gas_price));
+        let pubdata_price = (operation == L1BatchOperation::Commit
+            && cost.blob_fee_wei > 0
+            && predicted.pubdata_bytes > 0)
+            .then(|| cost.blob_fee_wei as f64 / predicted.pubdata_bytes as f64)
+            .and_then(|actual| relative_error(actual, predicted.pubdata_price));
+        Self {
+            gas_price,
+            pubdata_price,
+        }
+    }
+
+    pub fn get(&self, price: PredictedPrice) -> Option<f64> {
+        match price {
+            PredictedPrice::GasPrice => self.gas_price,
+            PredictedPrice::PubdataPrice => self.pubdata_price,
+        }
+    }
+}
+
+fn relative_error(actual: f64, predicted: u128) -> Option<f64> {
+    (predicted > 0).then(|| actual / predicted as f64 - 1.0)
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PriceDriftConfig {
+    /// Window the mean prediction error is computed over.
+    pub window: Duration,
+    /// Mean absolute relative error within the window above which a drift alert is raised.
+    pub threshold: f64,
+}
+
+/// Tracks prediction errors within the trailing window and raises alerts on sustained drift.
+#[derive(Debug)]
+pub struct PriceDriftMonitor {
+    config: PriceDriftConfig,
+    /// `(timestamp, price, error)` in the order of observation.
+    samples: VecDeque<(u64, PredictedPrice, f64)>,
+    alerts: HashSet<PredictedPrice>,
+}
+
+impl PriceDriftMonitor {
+    pub fn new(config: PriceDriftConfig) -> Self {
+        Self {
+            config,
+            samples: VecDeque::new(),
+            alerts: HashSet::new(),
+        }
+    }
+
+    /// Records prediction errors for an L1 transaction included at `timestamp` (unix seconds).
+    pub fn observe(&mut self, timestamp: u64, error: PredictionError) {
+        for price in PredictedPrice::ALL {
+            if let Some(error) = error.get(price) {
+                L1_SENDER_METRICS.l1_price_prediction_error[&price.as_str()].set(error);
+                L1_SENDER_METRICS.l1_price_prediction_abs_error[&price.as_str()]
+                    .observe(error.abs());
+                self.samples.push_back((timestamp, price, error));
+            }
+        }
+        let window_start = timestamp.saturating_sub(self.config.window.as_secs());
+        while self
+            .samples
+            .front()
+            .is_some_and(|(sampled_at, _, _)| *sampled_at < window_start)
+        {
+            self.samples.pop_front();
+        }
+
+        for price in PredictedPrice::ALL {
+            let drift = self.drift(price);
+            if let Some((mean_abs_error, _)) = drift {
+                L1_SENDER_METRICS.l1_price_drift[&price.as_str()].set(mean_abs_error);
+            }
+            let alerting = drift.is_some_and(|(mean_abs_error, samples)| {
+                samples >= MIN_ALERT_SAMPLES && mean_abs_error > self.config.threshold
+            });
+            L1_SENDER_METRICS.l1_price_drift_alert[&price.as_str()].set(alerting.into());
+            if alerting && self.alerts.insert(price) {
+                tracing::warn!(
+                    price = price.as_str(),
+                    mean_abs_error = drift.map(|(error, _)| error),
+                    threshold = self.config.threshold,
+                    window = ?self.config.window,
+                    "predicted L1 prices drift from the paid ones; consider tuning gas adjuster multipliers"
+                );
+            } else if !alerting && self.alerts.remove(&price) {
+                tracing::info!(
+                    price = price.as_str(),
+                    "L1 price drift is back within threshold"
+                );
+            }
+        }
+    }
+
+    /// Mean absolute prediction error of `price` within the window and the number of errors it's
+    /// computed from. `None` if there are no errors within the window.
+    pub fn drift(&self, price: PredictedPrice) -> Option<(f64, usize)> {
+        let errors: Vec<_> = self
+            .samples
+            .iter()
+            .filter(|(_, sampled_price, _)| *sampled_price == price)
+            .map(|(_, _, error)| error.abs())
+            .collect();
+        (!errors.is_empty()).then(|| {
+            (
+                errors.iter().sum::<f64>() / errors.len() as f64,
+                errors.len(),
+            )
+        })
+    }
+
+    pub fn is_alerting(&self, price: PredictedPrice) -> bool {
+        self.alerts.contains(&price)
+    }
+}
+
+/// Summary of prediction errors of L1 transactions, e.g. to tune `pubdata_pricing_multiplier`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct PriceDriftReport {
+    /// Inclusion timestamps of the first and the last L1 transaction with compared prices.
+    pub first_timestamp: Option<u64>,
+    pub last_timestamp: Option<u64>,
+    pub gas_price: DriftSummary,
+    pub pubdata_price: DriftSummary,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct DriftSummary {
+    /// Number of batch operations the prices were compared for.
+    pub samples: usize,
+    /// Mean relative error; positive if L1 was more expensive than predicted on average.
+    pub mean_error: Option<f64>,
+    pub mean_abs_error: Option<f64>,
+    pub max_abs_error: Option<f64>,
+}
+
+impl DriftSummary {
+    fn new(errors: &[f64]) -> Self {
+        let count = errors.len() as f64;
+        let abs_errors = errors.iter().map(|error| error.abs());
+        Self {
+            samples: errors.len(),
+            mean_error: (!errors.is_empty()).then(|| errors.iter().sum::<f64>() / count),
+            mean_abs_error: (!errors.is_empty()).then(|| abs_errors.clone().sum::<f64>() / count),
+            max_abs_error: abs_errors.reduce(f64::max),
+        }
+    }
+}
+
+impl PriceDriftReport {
+    /// Summarizes prediction errors of the operations in `costs` included within
+    /// `from_timestamp..=to_timestamp`.
+    pub fn new(costs: &[BatchCost], from_timestamp: u64, to_timestamp: u64) -> Self {
+        let mut timestamps = Vec::new();
+        let mut gas_price_errors = Vec::new();
+        let mut pubdata_price_errors = Vec::new();
+        let operations = costs
+            .iter()
+            .flat_map(|batch| &batch.operations)
+            .filter(|(_, cost)| (from_timestamp..=to_timestamp).contains(&cost.timestamp));
+        for (operation, cost) in operations {
+            let error = PredictionError::new(*operation, cost);
+            if error == PredictionError::default() {
+                continue;
+            }
+            timestamps.push(cost.timestamp);
+            gas_price_errors.extend(error.gas_price);
+            pubdata_price_errors.extend(error.pubdata_price);
+        }
+        Self {
+            first_timestamp: timestamps.iter().min().copied(),
+            last_timestamp: timestamps.iter().max().copied(),
+            gas_price: DriftSummary::new(&gas_price_errors),
+            pubdata_price: DriftSummary::new(&pubdata_price_errors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::TxHash;
+
+    const GWEI: u128 = 1_000_000_000;
+    const HOUR: u64 = 3600;
+
+    fn predicted(gas_price: u128, pubdata_price: u128, pubdata_bytes: u64) -> PredictedL1Prices {
+        PredictedL1Prices {
+            gas_price,
+            pubdata_price,
+            pubdata_bytes,
+        }
+    }
+
+    /// Batch share of an L1 transaction paying `gas_price` per gas and `blob_fee_wei` for blobs.
+    fn cost(
+        timestamp: u64,
+        gas_price: u128,
+        blob_fee_wei: u128,
+        predicted_prices: Option<PredictedL1Prices>,
+    ) -> OperationCost {
+        OperationCost {
+            tx_hash: TxHash::repeat_byte(1),
+            l1_block_number: None,
+            timestamp,
+            gas_used: 100_000,
+            execution_fee_wei: 100_000 * gas_price,
+            blob_fee_wei,
+            predicted_prices,
+        }
+    }
+
+    fn monitor() -> PriceDriftMonitor {
+        PriceDriftMonitor::new(PriceDriftConfig {
+            window: Duration::from_secs(HOUR),
+            threshold: 0.25,
+        })
+    }
+
+    fn gas_price_error(error: f64) -> PredictionError {
+        PredictionError {
+            gas_price: Some(error),
+            pubdata_price: None,
+        }
+    }
+
+    #[test]
+    fn prediction_errors() {
+        // Paid 30 gwei per gas while 20 gwei were predicted; 2 gwei per pubdata byte vs 4 predicted
+        let prices = predicted(20 * GWEI, 4 * GWEI, 1_000);
+        let commit = cost(0, 30 * GWEI, 2_000 * GWEI, Some(prices));
+        let error = PredictionError::new(L1BatchOperation::Commit, &commit);
+        assert_eq!(
+            error,
+            PredictionError {
+                gas_price: Some(0.5),
+                pubdata_price: Some(-0.5),
+            }
+        );
+
+        // Pubdata is only compared for commits
+        let error = PredictionError::new(L1BatchOperation::Execute, &commit);
+        assert_eq!(error, gas_price_error(0.5));
+        // Calldata commits don't pay for blobs
+        let calldata_commit = cost(0, 20 * GWEI, 0, Some(prices));
+        let error = PredictionError::new(L1BatchOperation::Commit, &calldata_commit);
+        assert_eq!(error, gas_price_error(0.0));
+        // Validium batches have a zero pubdata price
+        let validium_commit = cost(0, 20 * GWEI, GWEI, Some(predicted(20 * GWEI, 0, 1_000)));
+        let error = PredictionError::new(L1BatchOperation::Commit, &validium_commit);
+        assert_eq!(error.pubdata_price, None);
+        // Batches without a prediction are skipped
+        let error = PredictionError::new(L1BatchOperation::Commit, &cost(0, GWEI, GWEI, None));
+        assert_eq!(error, PredictionError::default());
+    }
+
+    #[test]
+    fn sustained_drift_raises_alert() {
+        let mut monitor = monitor();
+        // A single outlier doesn't raise an alert
+        monitor.observe(0, gas_price_error(2.0));
+        assert!(!monitor.is_alerting(PredictedPrice::GasPrice));
+        monitor.observe(HOUR / 2, gas_price_error(0.4));
+        assert!(!monitor.is_alerting(PredictedPrice::GasPrice));
+        monitor.observe(HOUR / 2 + 1, gas_price_error(-0.4));
+        assert!(monitor.is_alerting(PredictedPrice::GasPrice));
+        assert_eq!(
+            monitor.drift(PredictedPrice::GasPrice),
+            Some(((2.0 + 0.4 + 0.4) / 3.0, 3))
+        );
+        assert!(!monitor.is_alerting(PredictedPrice::PubdataPrice));
+        assert_eq!(monitor.drift(PredictedPrice::PubdataPrice), None);
+
+        // The outlier leaves the window; accurate predictions bring the mean below the threshold
+        monitor.observe(HOUR + 1, gas_price_error(0.1));
+        assert_eq!(monitor.drift(PredictedPrice::GasPrice).unwrap().1, 3);
+        assert!(monitor.is_alerting(PredictedPrice::GasPrice));
+        monitor.observe(HOUR + 2, gas_price_error(0.0));
+        assert!(!monitor.is_alerting(PredictedPrice::GasPrice));
+    }
+
+    #[test]
+    fn drift_report() {
+        let prices = predicted(10 * GWEI, GWEI, 1_000);
+        let mut first = BatchCost::new(1);
+        first.operations.insert(
+            L1BatchOperation::Commit,
+            cost(HOUR, 12 * GWEI, 1_500 * GWEI, Some(prices)),
+        );
+        first.operations.insert(
+            L1BatchOperation::Execute,
+            cost(2 * HOUR, 9 * GWEI, 0, Some(prices)),
+        );
+        let mut second = BatchCost::new(2);
+        second.operations.insert(
+            L1BatchOperation::Commit,
+            cost(3 * HOUR, 10 * GWEI, 1_000 * GWEI, None),
+        );
+        second.operations.insert(
+            L1BatchOperation::Execute,
+            cost(4 * HOUR, 10 * GWEI, 0, Some(prices)),
+        );
+        let costs = [first, second];
+
+        let report = PriceDriftReport::new(&costs, 0, u64::MAX);
+        assert_eq!(report.first_timestamp, Some(HOUR));
+        assert_eq!(report.last_timestamp, Some(4 * HOUR));
+        assert_eq!(report.gas_price.samples, 3);
+        let mean_error = report.gas_price.mean_error.unwrap();
+        assert!(
+            (mean_error - (0.2 - 0.1 + 0.0) / 3.0).abs() < 1e-9,
+            "{mean_error}"
+        );
+        let mean_abs_error = report.gas_price.mean_abs_error.unwrap();
+        assert!((mean_abs_error - 0.1).abs() < 1e-9, "{mean_abs_error}");
+        assert!((report.gas_price.max_abs_error.unwrap() - 0.2).abs() < 1e-9);
+        assert_eq!(
+            report.pubdata_price,
+            DriftSummary {
+                samples: 1,
+                mean_error: Some(0.5),
+                mean_abs_error: Some(0.5),
+                max_abs_error: Some(0.5),
+            }
+        );
+
+        let report = PriceDriftReport::new(&costs, 2 * HOUR, 3 * HOUR);
+        assert_eq!(report.first_timestamp, Some(2 * HOUR));
+        assert_eq!(report.last_timestamp, Some(2 * HOUR));
+        assert_eq!(report.gas_price.samples, 1);
+        assert_eq!(report.pubdata_price, DriftSummary::default());
+    }
+}
@@ -0,0 +1,380 @@
+//! Tracking of sent L1 transactions until inclusion, replacing stuck ones.
+//!
+//! A transaction may stay unmined for a long time if L1 fees rise after it's sent. Every
+//! `resubmission_interval` without inclusion, the transaction is replaced with one having the same
+//! nonce and fees bumped by 12.5% (the minimal bump accepted by L1 mempools is 10%), up to the max
+//! fees configured for the sender. Fees per blob gas of blob transactions are doubled, as required
+//! for replacing them in L1 blob pools. Until one of them is included, receipts are checked for the
+//! original transaction and all its replacements, so it doesn't matter which one lands.
+
+use crate::config::L1SenderConfig;
+use crate::metrics::L1_SENDER_METRICS;
+use alloy::network::{TransactionBuilder, TransactionBuilder4844};
+use alloy::primitives::TxHash;
+use alloy::providers::Provider;
+use alloy::rpc::types::{TransactionReceipt, TransactionRequest};
+use std::time::Duration;
+use tokio::time::Instant;
+use zksync_os_error_reporting::WithErrorCode;
+use zksync_os_error_reporting::codes::l1_sender as error_codes;
+
+/// Maximum time to wait for a transaction to be included on L1 after it (or its last replacement)
+/// was sent.
+///
+/// Normally 15-30 seconds is enough for normal priority transactions, and 60-120 is enough for
+/// lower gas price transactions. We picked 300 seconds conservatively as it should cover most
+/// scenarios with network congestion.
+const TRANSACTION_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ResubmissionPolicy {
+    poll_interval: Duration,
+    resubmission_interval: Duration,
+    max_fee_per_gas: u128,
+    max_priority_fee_per_gas: u128,
+    max_fee_per_blob_gas: u128,
+}
+
+impl ResubmissionPolicy {
+    pub(crate) fn new<Input>(config: &L1SenderConfig<Input>) -> Self {
+        Self {
+            poll_interval: config.poll_interval,
+            resubmission_interval: config.resubmission_interval,
+            max_fee_per_gas: config.max_fee_per_gas(),
+            max_priority_fee_per_gas: config.max_priority_fee_per_gas(),
+            max_fee_per_blob_gas: config.max_fee_per_blob_gas(),
+        }
+    }
+
+    /// Fees of a replacement for a transaction paying `max_fee_per_gas` and
+    /// `max_priority_fee_per_gas`, or `None` if the max fees are paid already.
+    fn bump_fees(
+        &self,
+        max_fee_per_gas: u128,
+        max_priority_fee_per_gas: u128,
+    ) -> Option<(u128, u128)> {
+        // At least 12.5%, i.e. 1/8, and at least 1 wei for zero fees
+        let bump = |fee: u128, max: u128| (fee + fee.div_ceil(8).max(1)).min(max);
+        let bumped_max_fee = bump(max_fee_per_gas, self.max_fee_per_gas);
+        let bumped_priority_fee =
+            bump(max_priority_fee_per_gas, self.max_priority_fee_per_gas).min(bumped_max_fee);
+        (bumped_max_fee > max_fee_per_gas || bumped_priority_fee > max_priority_fee_per_gas)
+            .then_some((bumped_max_fee, bumped_priority_fee))
+    }
+
+    /// Fee per blob gas of a replacement for a blob transaction paying `max_fee_per_blob_gas`.
+    fn bump_blob_fee(&self, max_fee_per_blob_gas: u128) -> u128 {
+        (max_fee_per_blob_gas * 2)
+            .max(1)
+            .min(self.max_fee_per_blob_gas)
+    }
+}
+
+/// L1 transaction that was sent but is not known to be included yet.
+#[derive(Debug)]
+pub(crate) struct InFlightTransaction {
+    /// Request of the last submission, with the nonce set.
+    request: TransactionRequest,
+    /// Hashes of the original transaction and its replacements, in the order of submission.
+    hashes: Vec<TxHash>,
+    first_sent_at: Instant,
+    last_sent_at: Instant,
+    next_resubmission_at: Instant,
+    command_name: &'static str,
+}
+
+impl InFlightTransaction {
+    /// Sends `request`, which must have the nonce set.
+    pub(crate) async fn send(
+        provider: &dyn Provider,
+        request: TransactionRequest,
+        policy: &ResubmissionPolicy,
+        command_name: &'static str,
+    ) -> anyhow::Result<Self> {
+        anyhow::ensure!(request.nonce.is_some(), "L1 transaction nonce is not set");
+        let hash = *provider
+            .send_transaction(request.clone())
+            .await
+            .with_code(&error_codes::SEND_FAILED)?
+            .tx_hash();
+        let now = Instant::now();
+        Ok(Self {
+            request,
+            hashes: vec![hash],
+            first_sent_at: now,
+            last_sent_at: now,
+            next_resubmission_at: now + policy.resubmission_interval,
+            command_name,
+        })
+    }
+
+    /// Waits until the transaction or one of its replacements is included on L1, replacing it
+    /// with one paying higher fees every `resubmission_interval`.
+    pub(crate) async fn wait_for_inclusion(
+        mut self,
+        provider: &dyn Provider,
+        policy: &ResubmissionPolicy,
+    ) -> anyhow::Result<TransactionReceipt> {
+        // Transactions are waited for one by one, so the timeout doesn't start before the wait
+        let wait_started_at = Instant::now();
+        loop {
+            if let Some(receipt) = self.find_receipt(provider).await? {
+                L1_SENDER_METRICS.time_to_inclusion[&self.command_name]
+                    .observe(self.first_sent_at.elapsed());
+                if receipt.transaction_hash != *self.hashes.last().unwrap() {
+                    tracing::info!(
+                        command_name = self.command_name,
+                        nonce = self.request.nonce,
+                        tx_hash = ?receipt.transaction_hash,
+                        "L1 transaction was included before its last replacement"
+                    );
+                }
+                return Ok(receipt);
+            }
+
+            let now = Instant::now();
+            if now.duration_since(self.last_sent_at.max(wait_started_at)) >= TRANSACTION_TIMEOUT {
+                return Err(anyhow::anyhow!(
+                    "L1 transaction with nonce {:?} (hashes: {:?}) is not included within {:?} \
+                     after the last submission",
+                    self.request.nonce,
+                    self.hashes,
+                    TRANSACTION_TIMEOUT
+                )
+                .with_code(&error_codes::INCLUSION_TIMEOUT));
+            }
+            if now >= self.next_resubmission_at {
+                self.next_resubmission_at = now + policy.resubmission_interval;
+                self.resubmit(provider, policy).await;
+            }
+            tokio::time::sleep(policy.poll_interval).await;
+        }
+    }
+
+    async fn find_receipt(
+        &self,
+        provider: &dyn Provider,
+    ) -> anyhow::Result<Option<TransactionReceipt>> {
+        for hash in &self.hashes {
+            let receipt = provider
+                .get_transaction_receipt(*hash)
+                .await
+                .with_code(&error_codes::L1_RPC)?;
+            if let Some(receipt) = receipt {
+                return Ok(Some(receipt));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Sends a replacement paying higher fees. Failures are only logged: in particular, sending
+    /// fails if one of the already sent transactions has been included in the meantime.
+    async fn resubmit(&mut self, provider: &dyn Provider, policy: &ResubmissionPolicy) {
+        let command_name = self.command_name;
+        let max_fee_per_gas = self.request.max_fee_per_gas.unwrap_or_default();
+        let max_priority_fee_per_gas = self.request.max_priority_fee_per_gas.unwrap_or_default();
+        let Some((bumped_max_fee, bumped_priority_fee)) =
+            policy.bump_fees(max_fee_per_gas, max_priority_fee_per_gas)
+        else {
+            tracing::warn!(
+                command_name,
+                nonce = self.request.nonce,
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+                "L1 transaction is not included, but already pays the configured max fees"
+            );
+            return;
+        };
+
+        let mut replacement = self
+            .request
+            .clone()
+            .with_max_fee_per_gas(bumped_max_fee)
+            .with_max_priority_fee_per_gas(bumped_priority_fee);
+        if let Some(max_fee_per_blob_gas) = self.request.max_fee_per_blob_gas {
+            replacement.set_max_fee_per_blob_gas(policy.bump_blob_fee(max_fee_per_blob_gas));
+        }
+        match provider.send_transaction(replacement.clone()).await {
+            Ok(pending) => {
+                let tx_hash = *pending.tx_hash();
+                tracing::info!(
+                    command_name,
+                    nonce = self.request.nonce,
+                    ?tx_hash,
+                    max_fee_per_gas = bumped_max_fee,
+                    max_priority_fee_per_gas = bumped_priority_fee,
+                    "L1 transaction is not included in time, sent a replacement with bumped fees"
+                );
+                L1_SENDER_METRICS.fee_bumps[&command_name].inc();
+                self.hashes.push(tx_hash);
+                self.request = replacement;
+                self.last_sent_at = Instant::now();
+            }
+            Err(err) => {
+                tracing::warn!(
+                    command_name,
+                    nonce = self.request.nonce,
+                    "failed to send replacement L1 transaction: {err}"
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::network::EthereumWallet;
+    use alloy::primitives::{Address, B256};
+    use alloy::providers::ProviderBuilder;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::transports::mock::Asserter;
+    use serde_json::json;
+
+    const GWEI: u128 = 1_000_000_000;
+
+    fn policy() -> ResubmissionPolicy {
+        ResubmissionPolicy {
+            poll_interval: Duration::from_secs(1),
+            resubmission_interval: Duration::from_secs(3),
+            max_fee_per_gas: 100 * GWEI,
+            max_priority_fee_per_gas: 10 * GWEI,
+            max_fee_per_blob_gas: 10 * GWEI,
+        }
+    }
+
+    fn provider(asserter: &Asserter) -> impl Provider {
+        ProviderBuilder::new()
+            .disable_recommended_fillers()
+            .wallet(EthereumWallet::new(PrivateKeySigner::random()))
+            .connect_mocked_client(asserter.clone())
+    }
+
+    fn request() -> TransactionRequest {
+        TransactionRequest::default()
+            .with_to(Address::repeat_byte(1))
+            .with_chain_id(1)
+            .with_nonce(5)
+            .with_gas_limit(100_000)
+            .with_max_fee_per_gas(10 * GWEI)
+            .with_max_priority_fee_per_gas(GWEI)
+    }
+
+    fn tx_hash(byte: u8) -> TxHash {
+        B256::repeat_byte(byte)
+    }
+
+    fn receipt(tx_hash: TxHash) -> serde_json::Value {
+        json!({
+            "type": "0x2",
+            "status": "0x1",
+            "cumulativeGasUsed": "0x5208",
+            "logs": [],
+            "logsBloom": format!("0x{}", "00".repeat(256)),
+            "transactionHash": tx_hash,
+            "transactionIndex": "0x0",
+            "blockHash": B256::repeat_byte(0xbb),
+            "blockNumber": "0x10",
+            "gasUsed": "0x5208",
+            "effectiveGasPrice": "0x3b9aca00",
+            "from": Address::repeat_byte(2),
+            "to": Address::repeat_byte(1),
+            "contractAddress": null,
+        })
+    }
+
+    /// Sends the original transaction; `eth_sendRawTransaction` responds with `tx_hash(0)`.
+    async fn send(asserter: &Asserter, provider: &dyn Provider) -> InFlightTransaction {
+        asserter.push_success(&tx_hash(0));
+        InFlightTransaction::send(provider, request(), &policy(), "test_send")
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn fees_are_bumped_up_to_max_fees() {
+        let policy = policy();
+        assert_eq!(
+            policy.bump_fees(8 * GWEI, GWEI),
+            Some((9 * GWEI, GWEI + GWEI / 8))
+        );
+        // Max fee is capped, the priority fee is still bumped
+        assert_eq!(
+            policy.bump_fees(95 * GWEI, 8 * GWEI),
+            Some((100 * GWEI, 9 * GWEI))
+        );
+        assert_eq!(policy.bump_fees(0, 0), Some((1, 1)));
+        assert_eq!(policy.bump_fees(100 * GWEI, 10 * GWEI), None);
+
+        assert_eq!(policy.bump_blob_fee(GWEI), 2 * GWEI);
+        assert_eq!(policy.bump_blob_fee(0), 1);
+        assert_eq!(policy.bump_blob_fee(6 * GWEI), 10 * GWEI);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stuck_transaction_is_replaced() {
+        let asserter = Asserter::new();
+        let provider = provider(&asserter);
+        let in_flight = send(&asserter, &provider).await;
+        let bumps_before = L1_SENDER_METRICS.fee_bumps[&"test_send"].get();
+
+        // Polls at 0s, 1s and 2s find no receipt
+        for _ in 0..3 {
+            asserter.push_success(&serde_json::Value::Null);
+        }
+        // At 3s, the transaction is replaced...
+        asserter.push_success(&serde_json::Value::Null);
+        asserter.push_success(&tx_hash(1));
+        // ...and the replacement is included by 4s
+        asserter.push_success(&serde_json::Value::Null);
+        asserter.push_success(&receipt(tx_hash(1)));
+
+        let receipt = in_flight
+            .wait_for_inclusion(&provider, &policy())
+            .await
+            .unwrap();
+        assert_eq!(receipt.transaction_hash, tx_hash(1));
+        assert!(L1_SENDER_METRICS.fee_bumps[&"test_send"].get() > bumps_before);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn original_transaction_included_during_replacement() {
+        let asserter = Asserter::new();
+        let provider = provider(&asserter);
+        let in_flight = send(&asserter, &provider).await;
+
+        for _ in 0..4 {
+            asserter.push_success(&serde_json::Value::Null);
+        }
+        // The original transaction is included while its replacement is being sent, so the
+        // replacement is refused
+        asserter.push_failure_msg("nonce too low");
+        asserter.push_success(&receipt(tx_hash(0)));
+
+        let receipt = in_flight
+            .wait_for_inclusion(&provider, &policy())
+            .await
+            .unwrap();
+        assert_eq!(receipt.transaction_hash, tx_hash(0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn original_transaction_included_after_replacement() {
+        let asserter = Asserter::new();
+        let provider = provider(&asserter);
+        let in_flight = send(&asserter, &provider).await;
+
+        for _ in 0..4 {
+            asserter.push_success(&serde_json::Value::Null);
+        }
+        asserter.push_success(&tx_hash(1));
+        // The replacement is accepted by the mempool, but the original transaction gets included
+        asserter.push_success(&receipt(tx_hash(0)));
+
+        let receipt = in_flight
+            .wait_for_inclusion(&provider, &policy())
+            .await
+            .unwrap();
+        assert_eq!(receipt.transaction_hash, tx_hash(0));
+    }
+}
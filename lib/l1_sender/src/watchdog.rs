@@ -0,0 +1,565 @@
+//! Watchdog cross-checking L1 sender progress against chain activity.
+//!
+//! L1 senders report the state they are in, but a stuck sender cannot be relied on to notice that
+//! it's stuck. [`L1SenderWatchdog`] is an independent, read-only task that periodically samples the
+//! latest block, the latest sealed batch and batch progress on L1, and raises alerts once commits
+//! fall behind while blocks keep being produced:
+//!
+//! - a warning once the commit lag (in batches or in time) reaches the warning threshold;
+//! - an error log and the `l1_sender_watchdog_stall_alert` metric once it reaches the critical
+//!   threshold.
+//!
+//! Alerts carry a [`StallHint`] derived from the states of the commit pipeline components (see
+//! [`ComponentStateReporter`]) and are cleared once commits catch up. The latest sample is
+//! published as [`WatchdogStatus`] and served on `/debug/status`.
+
+use crate::commands::SendToL1;
+use crate::commands::commit::CommitCommand;
+use crate::commit_scheduler::CommitScheduler;
+use crate::metrics::{L1_SENDER_METRICS, L1SenderState};
+use alloy::eips::BlockId;
+use alloy::providers::DynProvider;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+use zksync_os_contract_interface::ZkChain;
+use zksync_os_observability::{ComponentStateReporter, GenericComponentState, StateLabel};
+use zksync_os_pipeline::PipelineComponent;
+
+/// Number of alerts kept in [`WatchdogStatus::recent_alerts`].
+const MAX_RECENT_ALERTS: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct WatchdogConfig {
+    /// How often to sample progress.
+    pub poll_interval: Duration,
+    /// Number of sealed batches not committed on L1 at which a warning is raised.
+    pub warning_lag_batches: u64,
+    /// Number of sealed batches not committed on L1 at which a critical alert is raised.
+    pub critical_lag_batches: u64,
+    /// Time without commit progress (while sealed batches wait) at which a warning is raised.
+    pub warning_stall: Duration,
+    /// Time without commit progress (while sealed batches wait) at which a critical alert is
+    /// raised.
+    pub critical_stall: Duration,
+}
+
+/// Progress of the node itself, sampled by [`L1SenderWatchdog`].
+pub trait LocalProgress: Send + Sync + 'static {
+    /// Latest block produced by the sequencer.
+    fn latest_block(&self) -> u64;
+
+    /// Latest batch sealed by the batcher.
+    fn latest_sealed_batch(&self) -> u64;
+}
+
+/// Batch progress on L1.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct L1BatchProgress {
+    pub committed: u64,
+    pub proved: u64,
+    pub executed: u64,
+}
+
+/// Progress sampled by the watchdog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgressSample {
+    pub latest_block: u64,
+    pub sealed_batch: u64,
+    /// `None` if L1 could not be queried.
+    pub l1: Option<L1BatchProgress>,
+}
+
+/// How far each pipeline stage lags behind the previous one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SenderLag {
+    pub latest_block: u64,
+    pub sealed_batch: u64,
+    /// Batch progress as of the last successful L1 query.
+    pub l1: L1BatchProgress,
+    /// Whether the last L1 query succeeded.
+    pub l1_reachable: bool,
+    /// Sealed batches not committed yet.
+    pub commit_lag_batches: u64,
+    /// Committed batches not proved yet.
+    pub prove_lag_batches: u64,
+    /// Proved batches not executed yet.
+    pub execute_lag_batches: u64,
+    /// Time since commits last advanced while sealed batches were waiting.
+    pub commit_stall_secs: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertLevel {
+    Warning,
+    Critical,
+}
+
+/// Likely cause of lagging commits, derived from the states of the commit pipeline components.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StallHint {
+    L1Unreachable,
+    OperatorBalance,
+    OutboundBacklog,
+    FormatTransition,
+    L1Revert,
+    L1Inclusion,
+    CommitsDeferred,
+    NoBatchesToCommit,
+    Unknown,
+}
+
+impl StallHint {
+    /// Diagnoses lagging commits given whether L1 is reachable and the current states of the commit
+    /// sender and the commit scheduler, see [`ComponentStateReporter::current_state()`].
+    pub fn diagnose(
+        l1_reachable: bool,
+        commit_sender: Option<&str>,
+        commit_scheduler: Option<&str>,
+    ) -> Self {
+        if !l1_reachable {
+            return Self::L1Unreachable;
+        }
+        let scheduler_busy = commit_scheduler == Some(GenericComponentState::Processing.specific());
+        match commit_sender.and_then(L1SenderState::from_specific) {
+            Some(L1SenderState::PausedOnBalance) => Self::OperatorBalance,
+            Some(L1SenderState::PausedOnBacklog | L1SenderState::WaitingSend) => {
+                Self::OutboundBacklog
+            }
+            Some(L1SenderState::PausedOnFormatTransition) => Self::FormatTransition,
+            Some(L1SenderState::PausedOnL1Revert) => Self::L1Revert,
+            Some(L1SenderState::SendingToL1 | L1SenderState::WaitingL1Inclusion) => {
+                Self::L1Inclusion
+            }
+            Some(L1SenderState::WaitingRecv) if scheduler_busy => Self::CommitsDeferred,
+            Some(L1SenderState::WaitingRecv) => Self::NoBatchesToCommit,
+            None => Self::Unknown,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::L1Unreachable => "l1_unreachable",
+            Self::OperatorBalance => "operator_balance",
+            Self::OutboundBacklog => "outbound_backlog",
+            Self::FormatTransition => "format_transition",
+            Self::L1Revert => "l1_revert",
+            Self::L1Inclusion => "l1_inclusion",
+            Self::CommitsDeferred => "commits_deferred",
+            Self::NoBatchesToCommit => "no_batches_to_commit",
+            Self::Unknown => "unknown",
+        }
+    }
+
+    /// What to check first, for logs.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::L1Unreachable => "L1 RPC does not respond",
+            Self::OperatorBalance => {
+                "commit sender is paused: operator balance is below the configured minimum"
+            }
+            Self::OutboundBacklog => {
+                "commit sender is paused: committed batches are not picked up by SNARK proving"
+            }
+            Self::FormatTransition => {
+                "commit sender is paused: commitment format transition is not acknowledged"
+            }
+            Self::L1Revert => "commit sender is paused: L1 revert is not acknowledged",
+            Self::L1Inclusion => "commit transactions are not included on L1 (check L1 fees)",
+            Self::CommitsDeferred => "commit scheduler defers commits (check L1 fees)",
+            Self::NoBatchesToCommit => {
+                "commit sender waits for batches: check batch verification and FRI proving"
+            }
+            Self::Unknown => "states of commit pipeline components are unknown",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct WatchdogAlert {
+    pub level: AlertLevel,
+    pub hint: StallHint,
+    /// Unix timestamp (in seconds) the alert was raised at.
+    pub raised_at: u64,
+}
+
+/// Published by [`L1SenderWatchdog`] after every sample.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct WatchdogStatus {
+    /// `None` until progress is sampled for the first time.
+    pub lag: Option<SenderLag>,
+    /// Active alert, if any.
+    pub alert: Option<WatchdogAlert>,
+    /// Alerts raised recently (including escalations and hint changes), oldest first. Kept after
+    /// the alerts are cleared.
+    pub recent_alerts: VecDeque<WatchdogAlert>,
+}
+
+/// Last time commits advanced.
+#[derive(Debug, Clone, Copy)]
+struct CommitProgress {
+    committed: u64,
+    latest_block: u64,
+    at: Instant,
+}
+
+/// Alerting logic of [`L1SenderWatchdog`], fed with progress samples.
+#[derive(Debug)]
+struct StallDetector {
+    config: WatchdogConfig,
+    last_l1: Option<L1BatchProgress>,
+    last_commit_progress: Option<CommitProgress>,
+    prev_latest_block: Option<u64>,
+    status: WatchdogStatus,
+}
+
+impl StallDetector {
+    fn new(config: WatchdogConfig) -> Self {
+        Self {
+            config,
+            last_l1: None,
+            last_commit_progress: None,
+            prev_latest_block: None,
+            status: WatchdogStatus::default(),
+        }
+    }
+
+    fn observe(
+        &mut self,
+        sample: ProgressSample,
+        hint: StallHint,
+        now: Instant,
+        timestamp: u64,
+    ) -> &WatchdogStatus {
+        if sample.l1.is_some() {
+            self.last_l1 = sample.l1;
+        }
+        let l1 = self.last_l1.unwrap_or_default();
+        let caught_up = sample.sealed_batch <= l1.committed;
+        let progress = self.last_commit_progress.get_or_insert(CommitProgress {
+            committed: l1.committed,
+            latest_block: sample.latest_block,
+            at: now,
+        });
+        if progress.committed != l1.committed || caught_up {
+            *progress = CommitProgress {
+                committed: l1.committed,
+                latest_block: sample.latest_block,
+                at: now,
+            };
+        }
+        let commit_lag = sample.sealed_batch.saturating_sub(l1.committed);
+        let stall = now.duration_since(progress.at);
+        // Lagging commits are expected on an idle chain, e.g. if batches are sealed by timeout
+        let blocks_produced = sample.latest_block > progress.latest_block
+            || self
+                .prev_latest_block
+                .is_some_and(|prev| sample.latest_block > prev);
+        self.prev_latest_block = Some(sample.latest_block);
+
+        let config = &self.config;
+        let level = if !blocks_produced {
+            None
+        } else if commit_lag >= config.critical_lag_batches || stall >= config.critical_stall {
+            Some(AlertLevel::Critical)
+        } else if commit_lag >= config.warning_lag_batches || stall >= config.warning_stall {
+            Some(AlertLevel::Warning)
+        } else {
+            None
+        };
+
+        let lag = SenderLag {
+            latest_block: sample.latest_block,
+            sealed_batch: sample.sealed_batch,
+            l1,
+            l1_reachable: sample.l1.is_some(),
+            commit_lag_batches: commit_lag,
+            prove_lag_batches: l1.committed.saturating_sub(l1.proved),
+            execute_lag_batches: l1.proved.saturating_sub(l1.executed),
+            commit_stall_secs: stall.as_secs(),
+        };
+        L1_SENDER_METRICS
+            .watchdog_commit_lag_batches
+            .set(commit_lag);
+        L1_SENDER_METRICS
+            .watchdog_commit_stall
+            .set(stall.as_secs_f64());
+        self.update_alert(level, hint, timestamp, &lag);
+        self.status.lag = Some(lag);
+        &self.status
+    }
+
+    fn update_alert(
+        &mut self,
+        level: Option<AlertLevel>,
+        hint: StallHint,
+        timestamp: u64,
+        lag: &SenderLag,
+    ) {
+        L1_SENDER_METRICS
+            .watchdog_stall_alert
+            .set((level == Some(AlertLevel::Critical)).into());
+        let Some(level) = level else {
+            if let Some(alert) = self.status.alert.take() {
+                tracing::info!(
+                    hint = alert.hint.as_str(),
+                    commit_lag_batches = lag.commit_lag_batches,
+                    "L1 commits are catching up, clearing watchdog alert"
+                );
+            }
+            return;
+        };
+        if self
+            .status
+            .alert
+            .is_some_and(|alert| alert.level == level && alert.hint == hint)
+        {
+            return;
+        }
+
+        let alert = WatchdogAlert {
+            level,
+            hint,
+            raised_at: timestamp,
+        };
+        match level {
+            AlertLevel::Warning => tracing::warn!(
+                hint = hint.as_str(),
+                ?lag,
+                "L1 commits are lagging while blocks are produced: {}",
+                hint.description()
+            ),
+            AlertLevel::Critical => {
+                L1_SENDER_METRICS.watchdog_alerts[&hint.as_str()].inc();
+                tracing::error!(
+                    hint = hint.as_str(),
+                    ?lag,
+                    "L1 commits are stalled while blocks are produced: {}",
+                    hint.description()
+                );
+            }
+        }
+        self.status.alert = Some(alert);
+        if self.status.recent_alerts.len() == MAX_RECENT_ALERTS {
+            self.status.recent_alerts.pop_front();
+        }
+        self.status.recent_alerts.push_back(alert);
+    }
+}
+
+/// Read-only task sampling commit progress and raising alerts when it stalls, see the module docs.
+pub struct L1SenderWatchdog<Local> {
+    local: Local,
+    zk_chain: ZkChain<DynProvider>,
+    detector: StallDetector,
+    status: watch::Sender<WatchdogStatus>,
+}
+
+impl<Local: LocalProgress> L1SenderWatchdog<Local> {
+    pub fn new(
+        config: WatchdogConfig,
+        local: Local,
+        zk_chain: ZkChain<DynProvider>,
+        status: watch::Sender<WatchdogStatus>,
+    ) -> Self {
+        Self {
+            local,
+            zk_chain,
+            detector: StallDetector::new(config),
+            status,
+        }
+    }
+
+    pub async fn run(mut self) -> anyhow::Result<()> {
+        let poll_interval = self.detector.config.poll_interval;
+        tracing::info!(config = ?self.detector.config, "starting L1 sender watchdog");
+        let mut timer = tokio::time::interval(poll_interval);
+        loop {
+            timer.tick().await;
+            // L1 requests may hang rather than fail, which is a stall cause on its own
+            let l1 = match tokio::time::timeout(poll_interval, self.fetch_l1_progress()).await {
+                Ok(Ok(progress)) => Some(progress),
+                Ok(Err(err)) => {
+                    tracing::debug!(%err, "failed to fetch batch progress from L1");
+                    None
+                }
+                Err(_) => {
+                    tracing::debug!("timed out fetching batch progress from L1");
+                    None
+                }
+            };
+            let sample = ProgressSample {
+                latest_block: self.local.latest_block(),
+                sealed_batch: self.local.latest_sealed_batch(),
+                l1,
+            };
+            let reporter = ComponentStateReporter::global();
+            let hint = StallHint::diagnose(
+                l1.is_some(),
+                reporter.current_state(CommitCommand::NAME),
+                reporter.current_state(CommitScheduler::NAME),
+            );
+            let status = self
+                .detector
+                .observe(sample, hint, Instant::now(), unix_timestamp());
+            self.status.send_replace(status.clone());
+        }
+    }
+
+    async fn fetch_l1_progress(&self) -> alloy::contract::Result<L1BatchProgress> {
+        let latest = BlockId::latest();
+        Ok(L1BatchProgress {
+            committed: self.zk_chain.get_total_batches_committed(latest).await?,
+            proved: self.zk_chain.get_total_batches_proved(latest).await?,
+            executed: self.zk_chain.get_total_batches_executed(latest).await?,
+        })
+    }
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> WatchdogConfig {
+        WatchdogConfig {
+            poll_interval: Duration::from_secs(10),
+            warning_lag_batches: 5,
+            critical_lag_batches: 10,
+            warning_stall: Duration::from_secs(60),
+            critical_stall: Duration::from_secs(180),
+        }
+    }
+
+    fn sample(latest_block: u64, sealed_batch: u64, committed: Option<u64>) -> ProgressSample {
+        ProgressSample {
+            latest_block,
+            sealed_batch,
+            l1: committed.map(|committed| L1BatchProgress {
+                committed,
+                proved: committed.saturating_sub(1),
+                executed: committed.saturating_sub(2),
+            }),
+        }
+    }
+
+    fn level(status: &WatchdogStatus) -> Option<AlertLevel> {
+        status.alert.map(|alert| alert.level)
+    }
+
+    #[test]
+    fn stalled_commits_escalate_and_clear() {
+        let mut detector = StallDetector::new(config());
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let hint = StallHint::OperatorBalance;
+
+        let status = detector.observe(sample(100, 10, Some(9)), hint, at(0), 0);
+        assert_eq!(level(status), None);
+        let lag = status.lag.as_ref().unwrap();
+        assert_eq!(
+            (
+                lag.commit_lag_batches,
+                lag.prove_lag_batches,
+                lag.execute_lag_batches
+            ),
+            (1, 1, 1)
+        );
+
+        let status = detector.observe(sample(110, 11, Some(9)), hint, at(60), 60);
+        assert_eq!(level(status), Some(AlertLevel::Warning));
+        assert_eq!(status.lag.as_ref().unwrap().commit_stall_secs, 60);
+        let status = detector.observe(sample(120, 12, Some(9)), hint, at(180), 180);
+        assert_eq!(level(status), Some(AlertLevel::Critical));
+        // Unchanged alerts are not repeated
+        let status = detector.observe(sample(130, 12, Some(9)), hint, at(190), 190);
+        assert_eq!(status.alert.unwrap().raised_at, 180);
+        assert_eq!(status.recent_alerts.len(), 2);
+
+        // Commits advance, but the lag is still above the warning threshold
+        let status = detector.observe(sample(140, 15, Some(10)), hint, at(200), 200);
+        assert_eq!(level(status), Some(AlertLevel::Warning));
+        let status = detector.observe(sample(150, 15, Some(14)), hint, at(210), 210);
+        assert_eq!(level(status), None);
+        assert_eq!(status.recent_alerts.len(), 3);
+        assert_eq!(status.recent_alerts[1].level, AlertLevel::Critical);
+    }
+
+    #[test]
+    fn commit_lag_alerts_without_stall() {
+        let mut detector = StallDetector::new(config());
+        let now = Instant::now();
+        let hint = StallHint::L1Inclusion;
+        detector.observe(sample(100, 10, Some(9)), hint, now, 0);
+        // Commits keep advancing, but fall behind sealed batches
+        let status = detector.observe(sample(110, 20, Some(10)), hint, now, 0);
+        assert_eq!(level(status), Some(AlertLevel::Critical));
+        assert_eq!(status.alert.unwrap().hint, StallHint::L1Inclusion);
+    }
+
+    #[test]
+    fn idle_chain_does_not_alert() {
+        let mut detector = StallDetector::new(config());
+        let start = Instant::now();
+        let hint = StallHint::NoBatchesToCommit;
+        detector.observe(sample(100, 10, Some(9)), hint, start, 0);
+        let later = start + Duration::from_secs(3_600);
+        let status = detector.observe(sample(100, 10, Some(9)), hint, later, 3_600);
+        assert_eq!(level(status), None);
+        assert_eq!(status.lag.as_ref().unwrap().commit_stall_secs, 3_600);
+
+        // Once blocks are produced again, the stall is reported right away
+        let status = detector.observe(sample(101, 10, Some(9)), hint, later, 3_600);
+        assert_eq!(level(status), Some(AlertLevel::Critical));
+    }
+
+    #[test]
+    fn unreachable_l1_keeps_last_known_progress() {
+        let mut detector = StallDetector::new(config());
+        let start = Instant::now();
+        detector.observe(sample(100, 10, Some(9)), StallHint::Unknown, start, 0);
+        let hint = StallHint::diagnose(false, Some("waiting_l1_inclusion"), None);
+        assert_eq!(hint, StallHint::L1Unreachable);
+        let later = start + Duration::from_secs(180);
+        let status = detector.observe(sample(110, 11, None), hint, later, 180);
+        let lag = status.lag.as_ref().unwrap();
+        assert!(!lag.l1_reachable);
+        assert_eq!(lag.l1.committed, 9);
+        assert_eq!(status.alert.unwrap().hint, StallHint::L1Unreachable);
+    }
+
+    #[test]
+    fn hints_follow_component_states() {
+        let diagnose = |sender, scheduler| StallHint::diagnose(true, sender, scheduler);
+        assert_eq!(
+            diagnose(Some("paused_on_balance"), Some("waiting_send")),
+            StallHint::OperatorBalance
+        );
+        assert_eq!(
+            diagnose(Some("paused_on_backlog"), None),
+            StallHint::OutboundBacklog
+        );
+        assert_eq!(
+            diagnose(Some("paused_on_format_transition"), None),
+            StallHint::FormatTransition
+        );
+        assert_eq!(
+            diagnose(Some("waiting_recv"), Some("waiting_recv")),
+            StallHint::NoBatchesToCommit
+        );
+        assert_eq!(
+            diagnose(Some("waiting_recv"), Some("processing")),
+            StallHint::CommitsDeferred
+        );
+        assert_eq!(diagnose(None, None), StallHint::Unknown);
+    }
+}
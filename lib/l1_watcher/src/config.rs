@@ -14,4 +14,10 @@ pub struct L1WatcherConfig {
     /// the node will retry for this duration before panicking.
     /// This allows time for a sidecar sync process to fetch proofs from the main node.
     pub proof_storage_grace_period: Duration,
+
+    /// How often to check L1 `safe`/`finalized` heads for the L1 finality tracker.
+    pub finality_poll_interval: Duration,
+
+    /// How often to poll L1 for governance events.
+    pub governance_poll_interval: Duration,
 }
@@ -0,0 +1,441 @@
+use crate::metrics::METRICS;
+use alloy::eips::BlockNumberOrTag;
+use alloy::primitives::{BlockNumber, TxHash};
+use alloy::providers::{DynProvider, Provider};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use zksync_os_l1_sender::batcher_metrics::BATCHER_METRICS;
+use zksync_os_l1_sender::batcher_model::{
+    L1BatchOperation, L1FinalitySnapshot, L1TxFinality, L1TxRecord, TrackedL1Tx,
+};
+
+/// Source of L1 chain information needed to determine finality of L1 transactions.
+#[allow(async_fn_in_trait)]
+pub trait L1FinalitySource: Send + Sync + 'static {
+    /// Returns the current `safe` and `finalized` L1 block numbers.
+    async fn safe_and_finalized_blocks(&self) -> anyhow::Result<(BlockNumber, BlockNumber)>;
+
+    /// Returns the L1 block that currently includes `tx_hash`, or `None` if the transaction is
+    /// not part of the canonical chain (e.g. it was reorged out).
+    async fn inclusion_block(&self, tx_hash: TxHash) -> anyhow::Result<Option<BlockNumber>>;
+}
+
+impl L1FinalitySource for DynProvider {
+    async fn safe_and_finalized_blocks(&self) -> anyhow::Result<(BlockNumber, BlockNumber)> {
+        let block_number = |tag| async move {
+            anyhow::Ok(
+                self.get_block_by_number(tag)
+                    .await?
+                    .map_or(0, |block| block.header.number),
+            )
+        };
+        Ok((
+            block_number(BlockNumberOrTag::Safe).await?,
+            block_number(BlockNumberOrTag::Finalized).await?,
+        ))
+    }
+
+    async fn inclusion_block(&self, tx_hash: TxHash) -> anyhow::Result<Option<BlockNumber>> {
+        Ok(self
+            .get_transaction_receipt(tx_hash)
+            .await?
+            .and_then(|receipt| receipt.block_number))
+    }
+}
+
+/// Persistence for [`L1FinalitySnapshot`] so that tracking survives restarts.
+#[allow(async_fn_in_trait)]
+pub trait L1FinalityStorage: Send + Sync + 'static {
+    async fn load_l1_finality(&self) -> anyhow::Result<Option<L1FinalitySnapshot>>;
+
+    async fn save_l1_finality(&self, snapshot: &L1FinalitySnapshot) -> anyhow::Result<()>;
+}
+
+/// Tracks L1 transactions sent by the L1 senders against the `safe` and `finalized` L1 block
+/// tags and maintains per-batch finality (included → safe → finalized).
+///
+/// If a previously included transaction disappears from the canonical chain, its batches are
+/// reset to pending and an alert is raised - L1 senders are not resubmitting anything on their own.
+pub struct L1FinalityTracker<Source, Storage> {
+    source: Source,
+    storage: Storage,
+    inbound: mpsc::UnboundedReceiver<L1TxRecord>,
+    snapshot: watch::Sender<L1FinalitySnapshot>,
+    poll_interval: Duration,
+    /// Whether the published snapshot has changes that failed to be persisted.
+    unsaved_changes: bool,
+}
+
+impl<Source: L1FinalitySource, Storage: L1FinalityStorage> L1FinalityTracker<Source, Storage> {
+    pub async fn new(
+        source: Source,
+        storage: Storage,
+        inbound: mpsc::UnboundedReceiver<L1TxRecord>,
+        snapshot: watch::Sender<L1FinalitySnapshot>,
+        poll_interval: Duration,
+    ) -> anyhow::Result<Self> {
+        let persisted = storage.load_l1_finality().await?.unwrap_or_default();
+        tracing::info!(
+            last_finalized_batch = ?persisted.last_finalized_batch,
+            tracked_txs = persisted.tracked.len(),
+            "initializing L1 finality tracker"
+        );
+        snapshot.send_replace(persisted);
+        Ok(Self {
+            source,
+            storage,
+            inbound,
+            snapshot,
+            poll_interval,
+            unsaved_changes: false,
+        })
+    }
+
+    pub async fn run(mut self) -> anyhow::Result<()> {
+        let mut timer = tokio::time::interval(self.poll_interval);
+        loop {
+            timer.tick().await;
+            // Purely informational, so errors are not worth stopping the node for
+            if let Err(err) = self.poll().await {
+                tracing::warn!(%err, "failed to update L1 finality of transactions");
+                METRICS.finality_poll_failures.inc();
+            }
+        }
+    }
+
+    async fn poll(&mut self) -> anyhow::Result<()> {
+        // New transactions are published right away, so that they aren't lost if polling L1 fails
+        self.unsaved_changes |= self.snapshot.send_if_modified(|snapshot| {
+            let mut received = false;
+            while let Ok(record) = self.inbound.try_recv() {
+                snapshot.tracked.push(TrackedL1Tx {
+                    record,
+                    finality: L1TxFinality::Included,
+                    l1_block_number: None,
+                });
+                received = true;
+            }
+            received
+        });
+        let mut snapshot = self.snapshot.borrow().clone();
+        let mut changed = self.unsaved_changes;
+        if snapshot.tracked.is_empty() && !changed {
+            return Ok(());
+        }
+
+        let (safe_block, finalized_block) = self.source.safe_and_finalized_blocks().await?;
+        for tx in &mut snapshot.tracked {
+            let inclusion_block = self.source.inclusion_block(tx.record.tx_hash).await?;
+            let finality =
+                L1TxFinality::from_inclusion(inclusion_block, safe_block, finalized_block);
+            if finality < tx.finality {
+                tracing::error!(
+                    operation = tx.record.operation.as_str(),
+                    first_batch = tx.record.first_batch,
+                    last_batch = tx.record.last_batch,
+                    tx_hash = ?tx.record.tx_hash,
+                    previous_l1_block = tx.l1_block_number,
+                    ?inclusion_block,
+                    from = ?tx.finality,
+                    to = ?finality,
+                    "L1 transaction finality regressed (L1 reorg)"
+                );
+                METRICS.finality_regressions[&tx.record.operation.as_str()].inc();
+            }
+            if finality != tx.finality || inclusion_block != tx.l1_block_number {
+                tx.finality = finality;
+                tx.l1_block_number = inclusion_block;
+                changed = true;
+            }
+        }
+        changed |= prune_finalized(&mut snapshot);
+
+        if changed {
+            report_metrics(&snapshot);
+            self.snapshot.send_replace(snapshot.clone());
+            self.unsaved_changes = true;
+            self.storage.save_l1_finality(&snapshot).await?;
+            self.unsaved_changes = false;
+        }
+        Ok(())
+    }
+}
+
+/// Moves finalized transactions into `last_finalized_batch`. Only a contiguous prefix of
+/// finalized transactions (per operation) is pruned so that older non-finalized ones remain
+/// visible. Returns whether anything was pruned.
+fn prune_finalized(snapshot: &mut L1FinalitySnapshot) -> bool {
+    snapshot
+        .tracked
+        .sort_by_key(|tx| (tx.record.operation, tx.record.first_batch));
+    let mut pruned = false;
+    for operation in L1BatchOperation::ALL {
+        loop {
+            let Some(position) = snapshot
+                .tracked
+                .iter()
+                .position(|tx| tx.record.operation == operation)
+            else {
+                break;
+            };
+            if snapshot.tracked[position].finality != L1TxFinality::Finalized {
+                break;
+            }
+            let tx = snapshot.tracked.remove(position);
+            snapshot
+                .last_finalized_batch
+                .insert(operation, tx.record.last_batch);
+            pruned = true;
+        }
+    }
+    pruned
+}
+
+fn report_metrics(snapshot: &L1FinalitySnapshot) {
+    for (operation, last_batch) in &snapshot.last_finalized_batch {
+        BATCHER_METRICS.batch_number[&operation.finalized_stage()].set(*last_batch);
+    }
+    for operation in L1BatchOperation::ALL {
+        for finality in [
+            L1TxFinality::Pending,
+            L1TxFinality::Included,
+            L1TxFinality::Safe,
+        ] {
+            let count = snapshot
+                .tracked
+                .iter()
+                .filter(|tx| tx.record.operation == operation && tx.finality == finality)
+                .count();
+            METRICS.tracked_l1_txs[&(operation.as_str(), finality_label(finality))]
+                .set(count as u64);
+        }
+    }
+}
+
+fn finality_label(finality: L1TxFinality) -> &'static str {
+    match finality {
+        L1TxFinality::Pending => "pending",
+        L1TxFinality::Included => "included",
+        L1TxFinality::Safe => "safe",
+        L1TxFinality::Finalized => "finalized",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct MockL1 {
+        inner: Arc<Mutex<MockL1Inner>>,
+    }
+
+    #[derive(Default)]
+    struct MockL1Inner {
+        safe: BlockNumber,
+        finalized: BlockNumber,
+        inclusions: HashMap<TxHash, BlockNumber>,
+        saved: Option<L1FinalitySnapshot>,
+        unavailable: bool,
+    }
+
+    impl MockL1 {
+        fn include(&self, tx_hash: TxHash, block: BlockNumber) {
+            self.inner.lock().unwrap().inclusions.insert(tx_hash, block);
+        }
+
+        fn reorg_out(&self, tx_hash: TxHash) {
+            self.inner.lock().unwrap().inclusions.remove(&tx_hash);
+        }
+
+        fn set_heads(&self, safe: BlockNumber, finalized: BlockNumber) {
+            let mut inner = self.inner.lock().unwrap();
+            inner.safe = safe;
+            inner.finalized = finalized;
+        }
+
+        fn set_unavailable(&self, unavailable: bool) {
+            self.inner.lock().unwrap().unavailable = unavailable;
+        }
+    }
+
+    impl L1FinalitySource for MockL1 {
+        async fn safe_and_finalized_blocks(&self) -> anyhow::Result<(BlockNumber, BlockNumber)> {
+            let inner = self.inner.lock().unwrap();
+            anyhow::ensure!(!inner.unavailable, "L1 is unavailable");
+            Ok((inner.safe, inner.finalized))
+        }
+
+        async fn inclusion_block(&self, tx_hash: TxHash) -> anyhow::Result<Option<BlockNumber>> {
+            Ok(self.inner.lock().unwrap().inclusions.get(&tx_hash).copied())
+        }
+    }
+
+    impl L1FinalityStorage for MockL1 {
+        async fn load_l1_finality(&self) -> anyhow::Result<Option<L1FinalitySnapshot>> {
+            Ok(self.inner.lock().unwrap().saved.clone())
+        }
+
+        async fn save_l1_finality(&self, snapshot: &L1FinalitySnapshot) -> anyhow::Result<()> {
+            self.inner.lock().unwrap().saved = Some(snapshot.clone());
+            Ok(())
+        }
+    }
+
+    fn record(operation: L1BatchOperation, batch: u64) -> L1TxRecord {
+        L1TxRecord {
+            operation,
+            first_batch: batch,
+            last_batch: batch,
+            tx_hash: TxHash::with_last_byte(batch as u8),
+        }
+    }
+
+    async fn tracker(
+        l1: &MockL1,
+    ) -> (
+        L1FinalityTracker<MockL1, MockL1>,
+        mpsc::UnboundedSender<L1TxRecord>,
+        watch::Receiver<L1FinalitySnapshot>,
+    ) {
+        let (records_sender, records_receiver) = mpsc::unbounded_channel();
+        let (snapshot_sender, snapshot_receiver) = watch::channel(Default::default());
+        let tracker = L1FinalityTracker::new(
+            l1.clone(),
+            l1.clone(),
+            records_receiver,
+            snapshot_sender,
+            Duration::from_secs(1),
+        )
+        .await
+        .unwrap();
+        (tracker, records_sender, snapshot_receiver)
+    }
+
+    #[tokio::test]
+    async fn transitions_through_safe_to_finalized() {
+        let l1 = MockL1::default();
+        let (mut tracker, records, snapshot) = tracker(&l1).await;
+        let commit = record(L1BatchOperation::Commit, 1);
+        l1.include(commit.tx_hash, 10);
+        records.send(commit).unwrap();
+
+        tracker.poll().await.unwrap();
+        assert_eq!(
+            snapshot
+                .borrow()
+                .batch_finality(1, L1BatchOperation::Commit),
+            Some(L1TxFinality::Included)
+        );
+
+        l1.set_heads(10, 5);
+        tracker.poll().await.unwrap();
+        assert_eq!(
+            snapshot
+                .borrow()
+                .batch_finality(1, L1BatchOperation::Commit),
+            Some(L1TxFinality::Safe)
+        );
+
+        l1.set_heads(12, 10);
+        tracker.poll().await.unwrap();
+        let current = snapshot.borrow().clone();
+        assert_eq!(
+            current.batch_finality(1, L1BatchOperation::Commit),
+            Some(L1TxFinality::Finalized)
+        );
+        assert!(current.tracked.is_empty());
+        assert_eq!(
+            current.last_finalized_batch.get(&L1BatchOperation::Commit),
+            Some(&1)
+        );
+        assert_eq!(current.batch_finality(1, L1BatchOperation::Execute), None);
+    }
+
+    #[tokio::test]
+    async fn reorged_out_transaction_resets_to_pending() {
+        let l1 = MockL1::default();
+        let (mut tracker, records, snapshot) = tracker(&l1).await;
+        let commit = record(L1BatchOperation::Commit, 1);
+        l1.include(commit.tx_hash, 10);
+        l1.set_heads(10, 5);
+        records.send(commit.clone()).unwrap();
+        tracker.poll().await.unwrap();
+        assert_eq!(
+            snapshot
+                .borrow()
+                .batch_finality(1, L1BatchOperation::Commit),
+            Some(L1TxFinality::Safe)
+        );
+
+        let regressions_before = METRICS.finality_regressions[&"commit"].get();
+        l1.reorg_out(commit.tx_hash);
+        tracker.poll().await.unwrap();
+        assert_eq!(
+            snapshot
+                .borrow()
+                .batch_finality(1, L1BatchOperation::Commit),
+            Some(L1TxFinality::Pending)
+        );
+        assert_eq!(
+            METRICS.finality_regressions[&"commit"].get(),
+            regressions_before + 1
+        );
+    }
+
+    #[tokio::test]
+    async fn state_survives_restart() {
+        let l1 = MockL1::default();
+        let (mut tracker, records, _) = tracker(&l1).await;
+        let first = record(L1BatchOperation::Execute, 1);
+        let second = record(L1BatchOperation::Execute, 2);
+        l1.include(first.tx_hash, 10);
+        l1.include(second.tx_hash, 20);
+        l1.set_heads(15, 15);
+        records.send(first).unwrap();
+        records.send(second).unwrap();
+        tracker.poll().await.unwrap();
+        drop(tracker);
+
+        let (_, _, snapshot) = tracker(&l1).await;
+        let restored = snapshot.borrow().clone();
+        assert_eq!(
+            restored.batch_finality(1, L1BatchOperation::Execute),
+            Some(L1TxFinality::Finalized)
+        );
+        assert_eq!(
+            restored.batch_finality(2, L1BatchOperation::Execute),
+            Some(L1TxFinality::Included)
+        );
+    }
+
+    #[tokio::test]
+    async fn transactions_are_kept_on_l1_errors() {
+        let l1 = MockL1::default();
+        let (mut tracker, records, snapshot) = tracker(&l1).await;
+        let commit = record(L1BatchOperation::Commit, 1);
+        l1.include(commit.tx_hash, 10);
+        l1.set_unavailable(true);
+        records.send(commit).unwrap();
+
+        tracker.poll().await.unwrap_err();
+        assert_eq!(
+            snapshot
+                .borrow()
+                .batch_finality(1, L1BatchOperation::Commit),
+            Some(L1TxFinality::Included)
+        );
+        assert!(l1.inner.lock().unwrap().saved.is_none());
+
+        l1.set_unavailable(false);
+        l1.set_heads(10, 5);
+        tracker.poll().await.unwrap();
+        let saved = l1.inner.lock().unwrap().saved.clone().unwrap();
+        assert_eq!(
+            saved.batch_finality(1, L1BatchOperation::Commit),
+            Some(L1TxFinality::Safe)
+        );
+    }
+}
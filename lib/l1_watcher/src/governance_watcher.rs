@@ -0,0 +1,588 @@
+//! Observability of governance actions on L1.
+//!
+//! Chain governance changes validator roles on the validator timelock, fee parameters on the
+//! diamond proxy and schedules protocol upgrades on the chain admin. [`L1GovernanceWatcher`]
+//! decodes such events into [`GovernanceEvent`]s, detects the ones directly affecting this node
+//! (e.g. a validator role revoked from one of its operator addresses) and reports them in logs,
+//! metrics and [`GovernanceStatus`] served on `/debug/status`. No automatic action is taken.
+
+use crate::L1WatcherConfig;
+use crate::metrics::METRICS;
+use alloy::primitives::{Address, B256, BlockNumber, TxHash, U256, keccak256};
+use alloy::providers::{DynProvider, Provider};
+use alloy::rpc::types::{Filter, Log};
+use alloy::sol_types::SolEvent;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+use zksync_os_contract_interface::IAdmin::{self, NewFeeParams};
+use zksync_os_contract_interface::IChainAdmin::UpdateUpgradeTimestamp;
+use zksync_os_contract_interface::IValidatorTimelock::{RoleGranted, RoleRevoked};
+use zksync_os_contract_interface::models::BatchDaInputMode;
+use zksync_os_contract_interface::{PubdataPricingMode, ZkChain};
+
+/// Number of events kept in [`GovernanceStatus::recent_events`].
+const MAX_RECENT_EVENTS: usize = 32;
+
+/// Per-chain role on the validator timelock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidatorRole {
+    Precommitter,
+    Committer,
+    Reverter,
+    Prover,
+    Executor,
+    /// Role not known to this node, e.g. a role admin.
+    Other,
+}
+
+impl ValidatorRole {
+    const ALL: [Self; 5] = [
+        Self::Precommitter,
+        Self::Committer,
+        Self::Reverter,
+        Self::Prover,
+        Self::Executor,
+    ];
+
+    fn role_name(&self) -> &'static str {
+        match self {
+            Self::Precommitter => "PRECOMMITTER_ROLE",
+            Self::Committer => "COMMITTER_ROLE",
+            Self::Reverter => "REVERTER_ROLE",
+            Self::Prover => "PROVER_ROLE",
+            Self::Executor => "EXECUTOR_ROLE",
+            Self::Other => "other",
+        }
+    }
+
+    /// Decodes a role from its identifier, i.e. the hash of its name.
+    pub fn from_id(id: B256) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|role| keccak256(role.role_name()) == id)
+            .unwrap_or(Self::Other)
+    }
+
+    /// Identifier of the role, as used by the validator timelock.
+    pub fn id(&self) -> Option<B256> {
+        (*self != Self::Other).then(|| keccak256(self.role_name()))
+    }
+}
+
+/// Fee parameters of the chain, see `FeeParams` in `ZKChainStorage.sol`.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct FeeParams {
+    /// `None` for a pricing mode unknown to this node.
+    pub pubdata_pricing_mode: Option<BatchDaInputMode>,
+    pub batch_overhead_l1_gas: u32,
+    pub max_pubdata_per_batch: u32,
+    pub max_l2_gas_per_batch: u32,
+    pub priority_tx_max_pubdata: u32,
+    pub minimal_l2_gas_price: u64,
+}
+
+impl From<IAdmin::FeeParams> for FeeParams {
+    fn from(params: IAdmin::FeeParams) -> Self {
+        Self {
+            pubdata_pricing_mode: match params.pubdataPricingMode {
+                PubdataPricingMode::Rollup => Some(BatchDaInputMode::Rollup),
+                PubdataPricingMode::Validium => Some(BatchDaInputMode::Validium),
+                _ => None,
+            },
+            batch_overhead_l1_gas: params.batchOverheadL1Gas,
+            max_pubdata_per_batch: params.maxPubdataPerBatch,
+            max_l2_gas_per_batch: params.maxL2GasPerBatch,
+            priority_tx_max_pubdata: params.priorityTxMaxPubdata,
+            minimal_l2_gas_price: params.minimalL2GasPrice,
+        }
+    }
+}
+
+/// Governance action on L1 concerning this chain.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GovernanceEvent {
+    ValidatorRoleGranted {
+        role: ValidatorRole,
+        account: Address,
+    },
+    ValidatorRoleRevoked {
+        role: ValidatorRole,
+        account: Address,
+    },
+    FeeParamsUpdated {
+        old: FeeParams,
+        new: FeeParams,
+    },
+    UpgradeScheduled {
+        /// Semantic protocol version, e.g. `0.29.1`.
+        protocol_version: String,
+        /// Unix timestamp (in seconds) the upgrade can be executed from.
+        upgrade_timestamp: u64,
+    },
+}
+
+impl GovernanceEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ValidatorRoleGranted { .. } => "validator_role_granted",
+            Self::ValidatorRoleRevoked { .. } => "validator_role_revoked",
+            Self::FeeParamsUpdated { .. } => "fee_params_updated",
+            Self::UpgradeScheduled { .. } => "upgrade_scheduled",
+        }
+    }
+
+    /// Whether the event directly affects this node (as opposed to e.g. another operator's role).
+    pub fn affects_node(&self, node: &NodeGovernanceParams) -> bool {
+        match self {
+            Self::ValidatorRoleGranted { account, .. }
+            | Self::ValidatorRoleRevoked { account, .. } => {
+                node.operator_addresses.contains(account)
+            }
+            Self::FeeParamsUpdated { new, .. } => !matches!(
+                (new.pubdata_pricing_mode, node.da_input_mode),
+                (Some(BatchDaInputMode::Rollup), BatchDaInputMode::Rollup)
+                    | (Some(BatchDaInputMode::Validium), BatchDaInputMode::Validium)
+            ),
+            // The node must be updated to a version supporting the upgrade in time
+            Self::UpgradeScheduled { .. } => true,
+        }
+    }
+
+    fn description(&self, affects_node: bool, now: u64) -> String {
+        match self {
+            Self::ValidatorRoleGranted { role, account } if affects_node => {
+                format!("operator address {account} was granted the {role:?} validator role")
+            }
+            Self::ValidatorRoleGranted { role, account } => {
+                format!("{account} was granted the {role:?} validator role")
+            }
+            Self::ValidatorRoleRevoked { role, account } if affects_node => format!(
+                "operator address {account} lost the {role:?} validator role, L1 transactions \
+                 requiring it will fail"
+            ),
+            Self::ValidatorRoleRevoked { role, account } => {
+                format!("{account} lost the {role:?} validator role")
+            }
+            Self::FeeParamsUpdated { new, .. } if affects_node => format!(
+                "fee params were updated, pubdata pricing mode {:?} doesn't match the DA mode \
+                 of this node",
+                new.pubdata_pricing_mode
+            ),
+            Self::FeeParamsUpdated { .. } => "fee params were updated".to_owned(),
+            Self::UpgradeScheduled {
+                protocol_version,
+                upgrade_timestamp,
+            } => {
+                let hours_left = upgrade_timestamp.saturating_sub(now) / 3_600;
+                format!(
+                    "upgrade to protocol version {protocol_version} is scheduled at \
+                     {upgrade_timestamp} (in {hours_left}h), the node must support it by then"
+                )
+            }
+        }
+    }
+}
+
+/// Parameters of this node that governance actions are checked against.
+#[derive(Debug, Clone)]
+pub struct NodeGovernanceParams {
+    /// L1 addresses this node sends transactions from; empty on external nodes.
+    pub operator_addresses: Vec<Address>,
+    pub da_input_mode: BatchDaInputMode,
+}
+
+/// Governance event seen on L1.
+#[derive(Debug, Clone, Serialize)]
+pub struct GovernanceEventRecord {
+    #[serde(flatten)]
+    pub event: GovernanceEvent,
+    pub affects_node: bool,
+    pub l1_block_number: Option<BlockNumber>,
+    pub l1_tx_hash: Option<TxHash>,
+}
+
+/// Published by [`L1GovernanceWatcher`] on every new event.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GovernanceStatus {
+    /// Events seen since the node has started, oldest first.
+    pub recent_events: VecDeque<GovernanceEventRecord>,
+}
+
+/// L1 contracts governance events are emitted by.
+#[derive(Debug, Clone, Copy)]
+struct GovernanceContracts {
+    zk_chain: Address,
+    validator_timelock: Address,
+    chain_admin: Address,
+}
+
+/// Watches governance actions on L1, see the module docs.
+///
+/// Only events emitted after the node has started are reported. The chain admin is resolved once,
+/// on startup.
+pub struct L1GovernanceWatcher {
+    provider: DynProvider,
+    contracts: GovernanceContracts,
+    node: NodeGovernanceParams,
+    next_l1_block: BlockNumber,
+    max_blocks_to_process: u64,
+    poll_interval: Duration,
+    status: watch::Sender<GovernanceStatus>,
+}
+
+impl L1GovernanceWatcher {
+    pub async fn new(
+        config: L1WatcherConfig,
+        zk_chain: ZkChain<DynProvider>,
+        validator_timelock: Address,
+        node: NodeGovernanceParams,
+        status: watch::Sender<GovernanceStatus>,
+    ) -> anyhow::Result<Self> {
+        let contracts = GovernanceContracts {
+            zk_chain: *zk_chain.address(),
+            validator_timelock,
+            chain_admin: zk_chain.get_admin().await?,
+        };
+        let current_l1_block = zk_chain.provider().get_block_number().await?;
+        tracing::info!(
+            current_l1_block,
+            ?contracts,
+            ?node,
+            "initializing L1 governance watcher"
+        );
+        Ok(Self {
+            provider: zk_chain.provider().clone(),
+            contracts,
+            node,
+            next_l1_block: current_l1_block,
+            max_blocks_to_process: config.max_blocks_to_process,
+            poll_interval: config.governance_poll_interval,
+            status,
+        })
+    }
+
+    pub async fn run(mut self) -> anyhow::Result<()> {
+        let mut timer = tokio::time::interval(self.poll_interval);
+        loop {
+            timer.tick().await;
+            // Purely informational, so L1 errors are not worth stopping the node for
+            if let Err(err) = self.poll().await {
+                tracing::warn!(%err, "failed to poll L1 for governance events");
+            }
+        }
+    }
+
+    async fn poll(&mut self) -> anyhow::Result<()> {
+        let latest_block = self.provider.get_block_number().await?;
+        while self.next_l1_block <= latest_block {
+            let from_block = self.next_l1_block;
+            let to_block = latest_block.min(from_block + self.max_blocks_to_process - 1);
+            let filter = Filter::new()
+                .from_block(from_block)
+                .to_block(to_block)
+                .address(vec![
+                    self.contracts.zk_chain,
+                    self.contracts.validator_timelock,
+                    self.contracts.chain_admin,
+                ])
+                .event_signature(vec![
+                    RoleGranted::SIGNATURE_HASH,
+                    RoleRevoked::SIGNATURE_HASH,
+                    NewFeeParams::SIGNATURE_HASH,
+                    UpdateUpgradeTimestamp::SIGNATURE_HASH,
+                ]);
+            let logs = self.provider.get_logs(&filter).await?;
+            METRICS.events_loaded[&"governance"].inc_by(logs.len() as u64);
+            METRICS.most_recently_scanned_l1_block[&"governance"].set(to_block);
+            for log in &logs {
+                if let Some(event) = self.decode(log)? {
+                    self.report(event, log);
+                }
+            }
+            self.next_l1_block = to_block + 1;
+        }
+        Ok(())
+    }
+
+    /// Decodes `log`; returns `None` for events concerning other chains.
+    fn decode(&self, log: &Log) -> Result<Option<GovernanceEvent>, alloy::sol_types::Error> {
+        let contracts = &self.contracts;
+        let Some(&topic0) = log.topic0() else {
+            return Ok(None);
+        };
+        let address = log.address();
+        let event = match topic0 {
+            RoleGranted::SIGNATURE_HASH if address == contracts.validator_timelock => {
+                let event = RoleGranted::decode_log(&log.inner)?.data;
+                if event.chainAddress != contracts.zk_chain {
+                    return Ok(None);
+                }
+                GovernanceEvent::ValidatorRoleGranted {
+                    role: ValidatorRole::from_id(event.role),
+                    account: event.account,
+                }
+            }
+            RoleRevoked::SIGNATURE_HASH if address == contracts.validator_timelock => {
+                let event = RoleRevoked::decode_log(&log.inner)?.data;
+                if event.chainAddress != contracts.zk_chain {
+                    return Ok(None);
+                }
+                GovernanceEvent::ValidatorRoleRevoked {
+                    role: ValidatorRole::from_id(event.role),
+                    account: event.account,
+                }
+            }
+            NewFeeParams::SIGNATURE_HASH if address == contracts.zk_chain => {
+                let event = NewFeeParams::decode_log(&log.inner)?.data;
+                GovernanceEvent::FeeParamsUpdated {
+                    old: event.oldFeeParams.into(),
+                    new: event.newFeeParams.into(),
+                }
+            }
+            UpdateUpgradeTimestamp::SIGNATURE_HASH if address == contracts.chain_admin => {
+                let event = UpdateUpgradeTimestamp::decode_log(&log.inner)?.data;
+                GovernanceEvent::UpgradeScheduled {
+                    protocol_version: format_protocol_version(event.protocolVersion),
+                    upgrade_timestamp: event.upgradeTimestamp.saturating_to(),
+                }
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(event))
+    }
+
+    fn report(&self, event: GovernanceEvent, log: &Log) {
+        let affects_node = event.affects_node(&self.node);
+        let event_type = event.as_str();
+        METRICS.governance_events[&event_type].inc();
+        let description = event.description(affects_node, unix_timestamp());
+        if affects_node {
+            METRICS.governance_events_affecting_node[&event_type].inc();
+            tracing::warn!(
+                event_type,
+                ?event,
+                l1_block_number = log.block_number,
+                "L1 governance action affects this node: {description}"
+            );
+        } else {
+            tracing::info!(
+                event_type,
+                ?event,
+                l1_block_number = log.block_number,
+                "L1 governance action: {description}"
+            );
+        }
+
+        let record = GovernanceEventRecord {
+            event,
+            affects_node,
+            l1_block_number: log.block_number,
+            l1_tx_hash: log.transaction_hash,
+        };
+        self.status.send_modify(|status| {
+            if status.recent_events.len() == MAX_RECENT_EVENTS {
+                status.recent_events.pop_front();
+            }
+            status.recent_events.push_back(record);
+        });
+    }
+}
+
+/// Formats a packed protocol version (`major << 64 | minor << 32 | patch`) as `major.minor.patch`.
+fn format_protocol_version(packed: U256) -> String {
+    let part = |shift: usize| (packed >> shift).as_limbs()[0] as u32;
+    format!("{}.{}.{}", part(64), part(32), part(0))
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U64;
+    use alloy::providers::ProviderBuilder;
+    use alloy::transports::mock::Asserter;
+    use serde_json::json;
+
+    const ZK_CHAIN: Address = Address::repeat_byte(1);
+    const VALIDATOR_TIMELOCK: Address = Address::repeat_byte(2);
+    const CHAIN_ADMIN: Address = Address::repeat_byte(3);
+    const OPERATOR: Address = Address::repeat_byte(0xaa);
+
+    fn watcher(asserter: &Asserter) -> (L1GovernanceWatcher, watch::Receiver<GovernanceStatus>) {
+        let (status, status_receiver) = watch::channel(GovernanceStatus::default());
+        let watcher = L1GovernanceWatcher {
+            provider: ProviderBuilder::new()
+                .connect_mocked_client(asserter.clone())
+                .erased(),
+            contracts: GovernanceContracts {
+                zk_chain: ZK_CHAIN,
+                validator_timelock: VALIDATOR_TIMELOCK,
+                chain_admin: CHAIN_ADMIN,
+            },
+            node: NodeGovernanceParams {
+                operator_addresses: vec![OPERATOR],
+                da_input_mode: BatchDaInputMode::Rollup,
+            },
+            next_l1_block: 10,
+            max_blocks_to_process: 1_000,
+            poll_interval: Duration::from_secs(60),
+            status,
+        };
+        (watcher, status_receiver)
+    }
+
+    fn log(address: Address, event: &impl SolEvent, l1_block_number: u64) -> Log {
+        Log {
+            inner: alloy::primitives::Log {
+                address,
+                data: event.encode_log_data(),
+            },
+            block_number: Some(l1_block_number),
+            transaction_hash: Some(TxHash::with_last_byte(l1_block_number as u8)),
+            ..Log::default()
+        }
+    }
+
+    fn role_event(chain_address: Address, role: ValidatorRole, account: Address) -> RoleGranted {
+        RoleGranted {
+            chainAddress: chain_address,
+            role: role.id().unwrap(),
+            account,
+        }
+    }
+
+    fn fee_params(pubdata_pricing_mode: PubdataPricingMode) -> IAdmin::FeeParams {
+        IAdmin::FeeParams {
+            pubdataPricingMode: pubdata_pricing_mode,
+            batchOverheadL1Gas: 1_000_000,
+            maxPubdataPerBatch: 120_000,
+            maxL2GasPerBatch: 80_000_000,
+            priorityTxMaxPubdata: 99_000,
+            minimalL2GasPrice: 250_000_000,
+        }
+    }
+
+    #[test]
+    fn roles_and_protocol_versions_are_decoded() {
+        for role in ValidatorRole::ALL {
+            assert_eq!(ValidatorRole::from_id(role.id().unwrap()), role);
+        }
+        assert_eq!(ValidatorRole::from_id(B256::ZERO), ValidatorRole::Other);
+        let packed = (U256::from(29) << 32) | U256::from(1);
+        assert_eq!(format_protocol_version(packed), "0.29.1");
+    }
+
+    #[tokio::test]
+    async fn governance_events_are_reported() {
+        let asserter = Asserter::new();
+        let (mut watcher, status) = watcher(&asserter);
+        let revoked_before =
+            METRICS.governance_events_affecting_node[&"validator_role_revoked"].get();
+
+        let revoked = RoleRevoked {
+            chainAddress: ZK_CHAIN,
+            role: ValidatorRole::Committer.id().unwrap(),
+            account: OPERATOR,
+        };
+        let logs = vec![
+            log(VALIDATOR_TIMELOCK, &revoked, 11),
+            // Another operator of this chain
+            log(
+                VALIDATOR_TIMELOCK,
+                &role_event(ZK_CHAIN, ValidatorRole::Prover, Address::repeat_byte(0xbb)),
+                12,
+            ),
+            // Another chain using the same validator timelock
+            log(
+                VALIDATOR_TIMELOCK,
+                &role_event(Address::repeat_byte(9), ValidatorRole::Committer, OPERATOR),
+                12,
+            ),
+            log(
+                ZK_CHAIN,
+                &NewFeeParams {
+                    oldFeeParams: fee_params(PubdataPricingMode::Rollup),
+                    newFeeParams: fee_params(PubdataPricingMode::Validium),
+                },
+                13,
+            ),
+            log(
+                ZK_CHAIN,
+                &NewFeeParams {
+                    oldFeeParams: fee_params(PubdataPricingMode::Rollup),
+                    newFeeParams: fee_params(PubdataPricingMode::Rollup),
+                },
+                14,
+            ),
+            log(
+                CHAIN_ADMIN,
+                &UpdateUpgradeTimestamp {
+                    protocolVersion: (U256::from(30) << 32) | U256::from(2),
+                    upgradeTimestamp: U256::from(1_800_000_000),
+                },
+                15,
+            ),
+            // Not emitted by the chain admin
+            log(
+                Address::repeat_byte(9),
+                &UpdateUpgradeTimestamp {
+                    protocolVersion: U256::from(31) << 32,
+                    upgradeTimestamp: U256::from(1_800_000_000),
+                },
+                15,
+            ),
+        ];
+        asserter.push_success(&U64::from(20));
+        asserter.push_success(&logs);
+        watcher.poll().await.unwrap();
+        assert_eq!(watcher.next_l1_block, 21);
+
+        let status = status.borrow().clone();
+        let events: Vec<_> = status
+            .recent_events
+            .iter()
+            .map(|record| (record.event.as_str(), record.affects_node))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                ("validator_role_revoked", true),
+                ("validator_role_granted", false),
+                ("fee_params_updated", true),
+                ("fee_params_updated", false),
+                ("upgrade_scheduled", true),
+            ]
+        );
+        assert!(
+            METRICS.governance_events_affecting_node[&"validator_role_revoked"].get()
+                > revoked_before
+        );
+
+        let status = serde_json::to_value(&status).unwrap();
+        let events = &status["recent_events"];
+        assert_eq!(
+            events[0],
+            json!({
+                "type": "validator_role_revoked",
+                "role": "committer",
+                "account": OPERATOR,
+                "affects_node": true,
+                "l1_block_number": 11,
+                "l1_tx_hash": TxHash::with_last_byte(11),
+            })
+        );
+        assert_eq!(events[2]["new"]["pubdata_pricing_mode"], "Validium");
+        assert_eq!(events[4]["protocol_version"], "0.30.2");
+        assert_eq!(events[4]["upgrade_timestamp"], 1_800_000_000);
+    }
+}
@@ -12,5 +12,20 @@ pub use commit_watcher::L1CommitWatcher;
 mod execute_watcher;
 pub use execute_watcher::L1ExecuteWatcher;
 
+mod finality_tracker;
+pub use finality_tracker::{L1FinalitySource, L1FinalityStorage, L1FinalityTracker};
+
+mod revert_watcher;
+pub use revert_watcher::{L1RevertWatcher, StoredBatchHashes};
+
+mod priority_expiry;
+pub use priority_expiry::{PriorityDeadlinesStorage, PriorityExpiryMonitor};
+
+mod governance_watcher;
+pub use governance_watcher::{
+    FeeParams, GovernanceEvent, GovernanceEventRecord, GovernanceStatus, L1GovernanceWatcher,
+    NodeGovernanceParams, ValidatorRole,
+};
+
 pub mod util;
 mod watcher;
@@ -1,5 +1,5 @@
 use alloy::primitives::BlockNumber;
-use vise::{Counter, Gauge, LabeledFamily, Metrics};
+use vise::{Counter, Gauge, LabeledFamily, Metrics, Unit};
 
 #[derive(Debug, Metrics)]
 pub struct L1Metrics {
@@ -7,6 +7,32 @@ pub struct L1Metrics {
     pub most_recently_scanned_l1_block: LabeledFamily<&'static str, Gauge<BlockNumber>>,
     #[metrics(labels = ["event"])]
     pub events_loaded: LabeledFamily<&'static str, Counter>,
+    /// Number of tracked (not yet finalized) L1 transactions per operation and finality state.
+    #[metrics(labels = ["operation", "finality"])]
+    pub tracked_l1_txs: LabeledFamily<(&'static str, &'static str), Gauge<u64>, 2>,
+    /// Number of times an L1 transaction lost finality it previously had (i.e. L1 reorg).
+    #[metrics(labels = ["operation"])]
+    pub finality_regressions: LabeledFamily<&'static str, Counter>,
+    /// Number of failed attempts to update L1 finality of tracked transactions.
+    pub finality_poll_failures: Counter,
+    /// Number of detected L1 batch reverts affecting batches committed by this node.
+    pub batch_reverts: Counter,
+    /// Number of batches reverted by the last detected L1 batch revert.
+    pub reverted_batches: Gauge<u64>,
+    /// Priority transactions seen on L1 that are not yet processed by the sequencer.
+    pub unprocessed_priority_txs: Gauge<usize>,
+    /// Time left until the oldest unprocessed priority transaction expires on L1; negative once
+    /// expired. Only updated while there are unprocessed priority transactions.
+    #[metrics(unit = Unit::Seconds)]
+    pub priority_tx_time_to_expiry: Gauge<i64>,
+    /// Number of failed attempts to persist priority transaction deadlines.
+    pub priority_deadlines_save_failures: Counter,
+    /// Governance events concerning this chain seen on L1, per event type.
+    #[metrics(labels = ["event"])]
+    pub governance_events: LabeledFamily<&'static str, Counter>,
+    /// Governance events directly affecting this node (e.g. its operators), per event type.
+    #[metrics(labels = ["event"])]
+    pub governance_events_affecting_node: LabeledFamily<&'static str, Counter>,
 }
 
 #[vise::register]
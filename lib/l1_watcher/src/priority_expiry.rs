@@ -0,0 +1,236 @@
+use crate::metrics::METRICS;
+use std::collections::BTreeMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use zksync_os_types::{L1TxSerialId, PriorityDeadlines};
+
+/// Deadlines are hours away in practice, so there is no need to check (and persist them) often.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Persistence for [`PriorityDeadlines`] so that deadlines are known right after a restart,
+/// before the L1 transaction watcher rescans L1.
+#[allow(async_fn_in_trait)]
+pub trait PriorityDeadlinesStorage: Send + Sync + 'static {
+    async fn load_priority_deadlines(&self) -> anyhow::Result<Option<BTreeMap<L1TxSerialId, u64>>>;
+
+    async fn save_priority_deadlines(
+        &self,
+        deadlines: &BTreeMap<L1TxSerialId, u64>,
+    ) -> anyhow::Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ExpiryLevel {
+    Ok,
+    Warning,
+    Critical,
+}
+
+/// Watches the deadline of the oldest unprocessed priority transaction, exports time left until it
+/// expires and raises alerts when it gets close, giving operators time to react (e.g. restore the
+/// sequencer or prepare for the escape hatch) before L1 contracts consider the transaction expired.
+///
+/// Deadlines themselves are recorded by [`L1TxWatcher`](crate::L1TxWatcher) and pruned by the sequencer.
+pub struct PriorityExpiryMonitor<Storage> {
+    deadlines: PriorityDeadlines,
+    storage: Storage,
+    warning_threshold: Duration,
+    critical_threshold: Duration,
+    last_saved: BTreeMap<L1TxSerialId, u64>,
+    /// Last raised alert, so that it is not repeated on every poll.
+    last_alert: Option<(L1TxSerialId, ExpiryLevel)>,
+}
+
+impl<Storage: PriorityDeadlinesStorage> PriorityExpiryMonitor<Storage> {
+    pub async fn new(
+        deadlines: PriorityDeadlines,
+        storage: Storage,
+        next_l1_priority_id: L1TxSerialId,
+        warning_threshold: Duration,
+        critical_threshold: Duration,
+    ) -> anyhow::Result<Self> {
+        let persisted = storage.load_priority_deadlines().await?.unwrap_or_default();
+        tracing::info!(
+            persisted_deadlines = persisted.len(),
+            next_l1_priority_id,
+            ?warning_threshold,
+            ?critical_threshold,
+            "initializing priority transaction expiry monitor"
+        );
+        deadlines.extend(persisted);
+        deadlines.remove_processed(next_l1_priority_id);
+        Ok(Self {
+            deadlines,
+            storage,
+            warning_threshold,
+            critical_threshold,
+            last_saved: BTreeMap::new(),
+            last_alert: None,
+        })
+    }
+
+    pub async fn run(mut self) -> anyhow::Result<()> {
+        let mut timer = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            timer.tick().await;
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("Incorrect system time")
+                .as_secs();
+            self.poll(now).await;
+        }
+    }
+
+    async fn poll(&mut self, now: u64) -> ExpiryLevel {
+        let snapshot = self.deadlines.snapshot();
+        let unprocessed = snapshot.len();
+        if snapshot != self.last_saved {
+            // Alerting must go on regardless; the save is retried on the next poll
+            match self.storage.save_priority_deadlines(&snapshot).await {
+                Ok(()) => self.last_saved = snapshot,
+                Err(err) => {
+                    tracing::warn!(%err, "failed to persist priority transaction deadlines");
+                    METRICS.priority_deadlines_save_failures.inc();
+                }
+            }
+        }
+        METRICS.unprocessed_priority_txs.set(unprocessed);
+
+        let Some((priority_id, deadline)) = self.deadlines.oldest() else {
+            self.last_alert = None;
+            return ExpiryLevel::Ok;
+        };
+        let time_to_expiry = deadline as i64 - now as i64;
+        METRICS.priority_tx_time_to_expiry.set(time_to_expiry);
+
+        let level = if time_to_expiry <= self.critical_threshold.as_secs() as i64 {
+            ExpiryLevel::Critical
+        } else if time_to_expiry <= self.warning_threshold.as_secs() as i64 {
+            ExpiryLevel::Warning
+        } else {
+            ExpiryLevel::Ok
+        };
+        if level != ExpiryLevel::Ok && self.last_alert != Some((priority_id, level)) {
+            tracing::error!(
+                priority_id,
+                deadline,
+                time_to_expiry_secs = time_to_expiry,
+                unprocessed,
+                level = if level == ExpiryLevel::Critical {
+                    "critical"
+                } else {
+                    "warning"
+                },
+                "oldest unprocessed priority transaction is close to expiring on L1"
+            );
+        }
+        self.last_alert = (level != ExpiryLevel::Ok).then_some((priority_id, level));
+        level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct MockStorage {
+        saved: Arc<Mutex<Option<BTreeMap<L1TxSerialId, u64>>>>,
+        unavailable: Arc<AtomicBool>,
+    }
+
+    impl PriorityDeadlinesStorage for MockStorage {
+        async fn load_priority_deadlines(
+            &self,
+        ) -> anyhow::Result<Option<BTreeMap<L1TxSerialId, u64>>> {
+            Ok(self.saved.lock().unwrap().clone())
+        }
+
+        async fn save_priority_deadlines(
+            &self,
+            deadlines: &BTreeMap<L1TxSerialId, u64>,
+        ) -> anyhow::Result<()> {
+            anyhow::ensure!(
+                !self.unavailable.load(Ordering::Relaxed),
+                "storage is unavailable"
+            );
+            *self.saved.lock().unwrap() = Some(deadlines.clone());
+            Ok(())
+        }
+    }
+
+    async fn monitor_for(
+        deadlines: &PriorityDeadlines,
+        storage: &MockStorage,
+        next_l1_priority_id: L1TxSerialId,
+    ) -> PriorityExpiryMonitor<MockStorage> {
+        PriorityExpiryMonitor::new(
+            deadlines.clone(),
+            storage.clone(),
+            next_l1_priority_id,
+            Duration::from_secs(3_600),
+            Duration::from_secs(600),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn tracks_oldest_deadline() {
+        let storage = MockStorage::default();
+        let deadlines = PriorityDeadlines::default();
+        let mut monitor = monitor_for(&deadlines, &storage, 0).await;
+        assert_eq!(monitor.poll(1_000).await, ExpiryLevel::Ok);
+
+        deadlines.record(0, 10_000);
+        deadlines.record(1, 12_000);
+        assert_eq!(monitor.poll(1_000).await, ExpiryLevel::Ok);
+        assert_eq!(METRICS.priority_tx_time_to_expiry.get(), 9_000);
+
+        assert_eq!(monitor.poll(7_000).await, ExpiryLevel::Warning);
+        assert_eq!(monitor.last_alert, Some((0, ExpiryLevel::Warning)));
+        assert_eq!(monitor.poll(9_500).await, ExpiryLevel::Critical);
+        assert_eq!(monitor.last_alert, Some((0, ExpiryLevel::Critical)));
+        assert_eq!(METRICS.priority_tx_time_to_expiry.get(), 500);
+        // Expired transactions remain critical
+        assert_eq!(monitor.poll(10_100).await, ExpiryLevel::Critical);
+        assert_eq!(METRICS.priority_tx_time_to_expiry.get(), -100);
+
+        // Once the sequencer processes the oldest transaction, the next one is tracked
+        deadlines.remove_processed(1);
+        assert_eq!(monitor.poll(10_100).await, ExpiryLevel::Warning);
+        assert_eq!(monitor.last_alert, Some((1, ExpiryLevel::Warning)));
+        deadlines.remove_processed(2);
+        assert_eq!(monitor.poll(10_100).await, ExpiryLevel::Ok);
+        assert_eq!(monitor.last_alert, None);
+
+        // Deadlines survive a restart; transaction #3 is processed before it
+        deadlines.record(3, 20_000);
+        deadlines.record(4, 21_000);
+        monitor.poll(10_100).await;
+        let restored = PriorityDeadlines::default();
+        let mut monitor = monitor_for(&restored, &storage, 4).await;
+        assert_eq!(restored.snapshot(), BTreeMap::from([(4, 21_000)]));
+        assert_eq!(monitor.poll(20_500).await, ExpiryLevel::Critical);
+        assert_eq!(METRICS.priority_tx_time_to_expiry.get(), 500);
+    }
+
+    #[tokio::test]
+    async fn alerts_are_raised_when_deadlines_cannot_be_saved() {
+        let storage = MockStorage::default();
+        let deadlines = PriorityDeadlines::default();
+        let mut monitor = monitor_for(&deadlines, &storage, 0).await;
+        storage.unavailable.store(true, Ordering::Relaxed);
+        deadlines.record(0, 10_000);
+        assert_eq!(monitor.poll(9_500).await, ExpiryLevel::Critical);
+        assert!(monitor.last_saved.is_empty());
+        assert!(storage.saved.lock().unwrap().is_none());
+
+        // The save is retried once storage is available again
+        storage.unavailable.store(false, Ordering::Relaxed);
+        assert_eq!(monitor.poll(9_500).await, ExpiryLevel::Critical);
+        let saved = storage.saved.lock().unwrap().clone();
+        assert_eq!(saved, Some(BTreeMap::from([(0, 10_000)])));
+    }
+}
@@ -0,0 +1,391 @@
+use crate::L1WatcherConfig;
+use crate::metrics::METRICS;
+use crate::watcher::{L1Watcher, L1WatcherError, ProcessL1Event};
+use alloy::primitives::B256;
+use alloy::providers::{DynProvider, Provider};
+use anyhow::Context;
+use std::convert::Infallible;
+use tokio::sync::watch;
+use zksync_os_contract_interface::IExecutor::BlocksRevert;
+use zksync_os_contract_interface::ZkChain;
+use zksync_os_l1_sender::batcher_metrics::{BATCHER_METRICS, BatchExecutionStage};
+use zksync_os_l1_sender::batcher_model::{L1BatchRevert, L1RevertStatus};
+use zksync_os_storage_api::{ReadBatch, WriteFinality};
+
+/// Source of `StoredBatchInfo` hashes, either on L1 or as sealed locally.
+#[allow(async_fn_in_trait)]
+pub trait StoredBatchHashes: Send + Sync + 'static {
+    /// Returns the hash of `batch_number`'s `StoredBatchInfo`, or `None` if the batch is unknown.
+    async fn stored_batch_hash(&self, batch_number: u64) -> anyhow::Result<Option<B256>>;
+}
+
+impl StoredBatchHashes for ZkChain<DynProvider> {
+    async fn stored_batch_hash(&self, batch_number: u64) -> anyhow::Result<Option<B256>> {
+        let hash = ZkChain::stored_batch_hash(self, batch_number).await?;
+        Ok((!hash.is_zero()).then_some(hash))
+    }
+}
+
+/// Detects batch reverts on L1 (`BlocksRevert`, e.g. governance calling `revertBatches`) that
+/// affect batches committed by this node.
+///
+/// Reverted batches are marked with [`BatchExecutionStage::CommitL1Reverted`], finality is moved
+/// back to the last batch still committed on L1 and the revert is published to L1 senders, which
+/// pause until the operator acknowledges it (`admin_requeueRevertedBatches`). If the local batch
+/// the chain was reverted to doesn't match L1, reverted batches cannot be re-committed and the
+/// revert must be resolved manually.
+pub struct L1RevertWatcher<Finality, BatchStorage, L1> {
+    finality: Finality,
+    batch_storage: BatchStorage,
+    l1: L1,
+    reverts: watch::Sender<L1RevertStatus>,
+}
+
+impl<Finality, BatchStorage> L1RevertWatcher<Finality, BatchStorage, ZkChain<DynProvider>>
+where
+    Finality: WriteFinality,
+    BatchStorage: ReadBatch + StoredBatchHashes,
+{
+    pub async fn new(
+        config: L1WatcherConfig,
+        zk_chain: ZkChain<DynProvider>,
+        finality: Finality,
+        batch_storage: BatchStorage,
+        reverts: watch::Sender<L1RevertStatus>,
+    ) -> anyhow::Result<L1Watcher<Self>> {
+        let current_l1_block = zk_chain.provider().get_block_number().await?;
+        tracing::info!(
+            current_l1_block,
+            last_committed_batch = finality.get_finality_status().last_committed_batch,
+            zk_chain_address = ?zk_chain.address(),
+            "initializing L1 revert watcher"
+        );
+        let this = Self {
+            finality,
+            batch_storage,
+            l1: zk_chain.clone(),
+            reverts,
+        };
+        let l1_watcher = L1Watcher::new(
+            zk_chain,
+            // Earlier reverts are already reflected in the L1 state the node has started from
+            current_l1_block,
+            config.max_blocks_to_process,
+            config.poll_interval,
+            this,
+        );
+        Ok(l1_watcher)
+    }
+}
+
+impl<Finality, BatchStorage, L1> L1RevertWatcher<Finality, BatchStorage, L1>
+where
+    Finality: WriteFinality,
+    BatchStorage: ReadBatch + StoredBatchHashes,
+    L1: StoredBatchHashes,
+{
+    async fn last_block(&self, batch_number: u64) -> anyhow::Result<u64> {
+        let (_, last_block) = self
+            .batch_storage
+            .get_batch_range_by_number(batch_number)
+            .await?
+            .with_context(|| format!("batch {batch_number} is missing in batch storage"))?;
+        Ok(last_block)
+    }
+
+    /// Whether the local batch `batch_number` differs from the one stored on L1.
+    async fn diverges_from_l1(&self, batch_number: u64) -> anyhow::Result<bool> {
+        if batch_number == 0 {
+            // Genesis batch is never committed by the node
+            return Ok(false);
+        }
+        let local_hash = self.batch_storage.stored_batch_hash(batch_number).await?;
+        let l1_hash = self.l1.stored_batch_hash(batch_number).await?;
+        if local_hash.is_none() || local_hash != l1_hash {
+            tracing::error!(
+                batch_number,
+                ?local_hash,
+                ?l1_hash,
+                "local batch doesn't match the batch stored on L1"
+            );
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+impl<Finality, BatchStorage, L1> ProcessL1Event for L1RevertWatcher<Finality, BatchStorage, L1>
+where
+    Finality: WriteFinality,
+    BatchStorage: ReadBatch + StoredBatchHashes,
+    L1: StoredBatchHashes,
+{
+    const NAME: &'static str = "blocks_revert";
+
+    type SolEvent = BlocksRevert;
+    type WatchedEvent = BlocksRevert;
+    type Error = Infallible;
+
+    async fn process_event(
+        &mut self,
+        event: BlocksRevert,
+    ) -> Result<(), L1WatcherError<Self::Error>> {
+        let reverted_to_batch = event.totalBatchesCommitted.to::<u64>();
+        let total_batches_verified = event.totalBatchesVerified.to::<u64>();
+        let total_batches_executed = event.totalBatchesExecuted.to::<u64>();
+        let finality = self.finality.get_finality_status();
+        if reverted_to_batch >= finality.last_committed_batch {
+            tracing::info!(
+                reverted_to_batch,
+                last_committed_batch = finality.last_committed_batch,
+                "L1 batch revert doesn't affect batches committed by this node"
+            );
+            return Ok(());
+        }
+
+        let mut revert = L1BatchRevert {
+            reverted_to_batch,
+            reverted_to_block: self.last_block(reverted_to_batch).await?,
+            last_reverted_batch: finality.last_committed_batch,
+            last_reverted_block: finality.last_committed_block,
+            total_batches_verified,
+            total_batches_executed,
+            requires_local_rollback: self.diverges_from_l1(reverted_to_batch).await?,
+        };
+        // A deeper revert before the previous one is acknowledged extends the reverted range
+        if let L1RevertStatus::Detected(previous) = *self.reverts.borrow() {
+            revert.last_reverted_batch =
+                revert.last_reverted_batch.max(previous.last_reverted_batch);
+            revert.last_reverted_block =
+                revert.last_reverted_block.max(previous.last_reverted_block);
+        }
+
+        self.finality.update_finality_status(|finality| {
+            finality.last_committed_batch = reverted_to_batch;
+            finality.last_committed_block = revert.reverted_to_block;
+        });
+        let stage = BatchExecutionStage::CommitL1Reverted;
+        BATCHER_METRICS.batch_number[&stage].set(revert.last_reverted_batch);
+        BATCHER_METRICS.block_number[&stage].set(revert.last_reverted_block);
+        METRICS.batch_reverts.inc();
+        METRICS
+            .reverted_batches
+            .set(revert.last_reverted_batch - reverted_to_batch);
+
+        tracing::error!(
+            reverted_to_batch,
+            reverted_to_block = revert.reverted_to_block,
+            first_reverted_batch = reverted_to_batch + 1,
+            last_reverted_batch = revert.last_reverted_batch,
+            last_reverted_block = revert.last_reverted_block,
+            total_batches_verified,
+            total_batches_executed,
+            requires_local_rollback = revert.requires_local_rollback,
+            "CRITICAL: batches {}..={} committed by this node were reverted on L1, L1 senders pause \
+             until the revert is acknowledged",
+            reverted_to_batch + 1,
+            revert.last_reverted_batch
+        );
+        if revert.requires_local_rollback {
+            tracing::error!(
+                "Automatic recovery is refused: local batch {reverted_to_batch} (last block {}) \
+                 doesn't match L1, so reverted batches cannot be re-committed. Manual recovery: stop \
+                 the node, restore its databases from a backup consistent with L1 batch \
+                 {reverted_to_batch} (blocks up to {}), drop local blocks above it and restart",
+                revert.reverted_to_block,
+                revert.reverted_to_block
+            );
+        } else {
+            tracing::warn!(
+                "Reverted batches {}..={} (blocks {}..={}) can be re-committed as is. If that is \
+                 intended, confirm with `admin_requeueRevertedBatches({reverted_to_batch})`; \
+                 otherwise stop the node and roll back local blocks above {} manually",
+                reverted_to_batch + 1,
+                revert.last_reverted_batch,
+                revert.reverted_to_block + 1,
+                revert.last_reverted_block,
+                revert.reverted_to_block
+            );
+        }
+        self.reverts.send_replace(L1RevertStatus::Detected(revert));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{BlockNumber, U256};
+    use std::collections::HashMap;
+    use zksync_os_storage_api::{FinalityStatus, ReadFinality};
+    use zksync_os_types::BatchDetails;
+
+    #[derive(Clone)]
+    struct MockFinality(watch::Sender<FinalityStatus>);
+
+    impl ReadFinality for MockFinality {
+        fn get_finality_status(&self) -> FinalityStatus {
+            self.0.borrow().clone()
+        }
+
+        fn subscribe(&self) -> watch::Receiver<FinalityStatus> {
+            self.0.subscribe()
+        }
+    }
+
+    impl WriteFinality for MockFinality {
+        fn update_finality_status(&self, f: impl FnOnce(&mut FinalityStatus)) {
+            self.0.send_modify(f);
+        }
+    }
+
+    /// Batches of 10 blocks each, with `StoredBatchInfo` hashes derived from batch numbers.
+    #[derive(Default)]
+    struct MockBatches {
+        hashes: HashMap<u64, B256>,
+    }
+
+    impl MockBatches {
+        fn up_to(last_batch: u64) -> Self {
+            Self {
+                hashes: (1..=last_batch)
+                    .map(|batch_number| (batch_number, B256::with_last_byte(batch_number as u8)))
+                    .collect(),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ReadBatch for MockBatches {
+        async fn get_batch_by_block_number(
+            &self,
+            block_number: BlockNumber,
+            _finality: &dyn ReadFinality,
+        ) -> anyhow::Result<Option<u64>> {
+            Ok(Some(block_number.div_ceil(10)))
+        }
+
+        async fn get_batch_range_by_number(
+            &self,
+            batch_number: u64,
+        ) -> anyhow::Result<Option<(BlockNumber, BlockNumber)>> {
+            if batch_number == 0 {
+                return Ok(Some((0, 0)));
+            }
+            Ok(Some((batch_number * 10 - 9, batch_number * 10)))
+        }
+
+        async fn get_batch_details(
+            &self,
+            _batch_number: u64,
+            _finality: &dyn ReadFinality,
+        ) -> anyhow::Result<Option<BatchDetails>> {
+            Ok(None)
+        }
+    }
+
+    impl StoredBatchHashes for MockBatches {
+        async fn stored_batch_hash(&self, batch_number: u64) -> anyhow::Result<Option<B256>> {
+            Ok(self.hashes.get(&batch_number).copied())
+        }
+    }
+
+    fn revert_watcher(
+        l1: MockBatches,
+    ) -> (
+        L1RevertWatcher<MockFinality, MockBatches, MockBatches>,
+        MockFinality,
+        watch::Receiver<L1RevertStatus>,
+    ) {
+        let finality = MockFinality(
+            watch::channel(FinalityStatus {
+                last_committed_block: 50,
+                last_committed_batch: 5,
+                last_executed_block: 20,
+                last_executed_batch: 2,
+            })
+            .0,
+        );
+        let (reverts, reverts_receiver) = watch::channel(L1RevertStatus::None);
+        let watcher = L1RevertWatcher {
+            finality: finality.clone(),
+            batch_storage: MockBatches::up_to(5),
+            l1,
+            reverts,
+        };
+        (watcher, finality, reverts_receiver)
+    }
+
+    fn revert_event(committed: u64, verified: u64, executed: u64) -> BlocksRevert {
+        BlocksRevert {
+            totalBatchesCommitted: U256::from(committed),
+            totalBatchesVerified: U256::from(verified),
+            totalBatchesExecuted: U256::from(executed),
+        }
+    }
+
+    #[tokio::test]
+    async fn revert_of_committed_batches_pauses_senders() {
+        let (mut watcher, finality, reverts) = revert_watcher(MockBatches::up_to(3));
+        let reverts_before = METRICS.batch_reverts.get();
+        watcher.process_event(revert_event(3, 3, 2)).await.unwrap();
+
+        assert_eq!(
+            *reverts.borrow(),
+            L1RevertStatus::Detected(L1BatchRevert {
+                reverted_to_batch: 3,
+                reverted_to_block: 30,
+                last_reverted_batch: 5,
+                last_reverted_block: 50,
+                total_batches_verified: 3,
+                total_batches_executed: 2,
+                requires_local_rollback: false,
+            })
+        );
+        let status = finality.get_finality_status();
+        assert_eq!(status.last_committed_batch, 3);
+        assert_eq!(status.last_committed_block, 30);
+        assert_eq!(status.last_executed_batch, 2);
+        assert_eq!(METRICS.batch_reverts.get(), reverts_before + 1);
+
+        // A deeper revert before the acknowledgment extends the reverted range
+        watcher.process_event(revert_event(2, 2, 2)).await.unwrap();
+        let L1RevertStatus::Detected(revert) = *reverts.borrow() else {
+            panic!("revert is not detected");
+        };
+        assert_eq!(revert.reverted_to_batch, 2);
+        assert_eq!(revert.last_reverted_batch, 5);
+        assert_eq!(revert.last_reverted_block, 50);
+        assert_eq!(finality.get_finality_status().last_committed_batch, 2);
+    }
+
+    #[tokio::test]
+    async fn revert_of_unknown_batches_is_ignored() {
+        let (mut watcher, finality, reverts) = revert_watcher(MockBatches::up_to(5));
+        watcher.process_event(revert_event(5, 4, 2)).await.unwrap();
+        watcher.process_event(revert_event(7, 6, 2)).await.unwrap();
+        assert_eq!(*reverts.borrow(), L1RevertStatus::None);
+        assert_eq!(finality.get_finality_status().last_committed_batch, 5);
+    }
+
+    #[tokio::test]
+    async fn diverged_batch_requires_local_rollback() {
+        let mut l1 = MockBatches::up_to(3);
+        l1.hashes.insert(3, B256::repeat_byte(0xff));
+        let (mut watcher, _, reverts) = revert_watcher(l1);
+        watcher.process_event(revert_event(3, 3, 2)).await.unwrap();
+        let L1RevertStatus::Detected(revert) = *reverts.borrow() else {
+            panic!("revert is not detected");
+        };
+        assert!(revert.requires_local_rollback);
+
+        // Reverting to genesis never requires a rollback of local blocks
+        let (mut watcher, finality, reverts) = revert_watcher(MockBatches::default());
+        watcher.process_event(revert_event(0, 0, 0)).await.unwrap();
+        let L1RevertStatus::Detected(revert) = *reverts.borrow() else {
+            panic!("revert is not detected");
+        };
+        assert!(!revert.requires_local_rollback);
+        assert_eq!(finality.get_finality_status().last_committed_block, 0);
+    }
+}
@@ -6,7 +6,7 @@ use std::sync::Arc;
 use tokio::sync::mpsc;
 use zksync_os_contract_interface::IMailbox::NewPriorityRequest;
 use zksync_os_contract_interface::ZkChain;
-use zksync_os_types::{L1EnvelopeError, L1PriorityEnvelope};
+use zksync_os_types::{L1EnvelopeError, L1PriorityEnvelope, PriorityDeadlines};
 
 /// Don't try to process that many block linearly
 const MAX_L1_BLOCKS_LOOKBEHIND: u64 = 100_000;
@@ -14,6 +14,7 @@ const MAX_L1_BLOCKS_LOOKBEHIND: u64 = 100_000;
 pub struct L1TxWatcher {
     next_l1_priority_id: u64,
     output: mpsc::Sender<L1PriorityEnvelope>,
+    deadlines: PriorityDeadlines,
 }
 
 impl L1TxWatcher {
@@ -22,6 +23,7 @@ impl L1TxWatcher {
         zk_chain: ZkChain<DynProvider>,
         output: mpsc::Sender<L1PriorityEnvelope>,
         next_l1_priority_id: u64,
+        deadlines: PriorityDeadlines,
     ) -> anyhow::Result<L1Watcher<Self>> {
         tracing::info!(
             config.max_blocks_to_process,
@@ -51,6 +53,7 @@ impl L1TxWatcher {
         let this = Self {
             next_l1_priority_id,
             output,
+            deadlines,
         };
         let l1_watcher = L1Watcher::new(
             zk_chain,
@@ -75,17 +78,39 @@ async fn find_l1_block_by_priority_id(
     .await
 }
 
+/// Priority transaction along with the deadline for processing it.
+pub struct PriorityRequest {
+    tx: L1PriorityEnvelope,
+    /// Unix timestamp (in seconds) after which the transaction is considered expired by L1 contracts.
+    expiration_timestamp: u64,
+}
+
+impl TryFrom<NewPriorityRequest> for PriorityRequest {
+    type Error = L1EnvelopeError;
+
+    fn try_from(value: NewPriorityRequest) -> Result<Self, Self::Error> {
+        Ok(Self {
+            expiration_timestamp: value.expirationTimestamp,
+            tx: value.transaction.try_into()?,
+        })
+    }
+}
+
 impl ProcessL1Event for L1TxWatcher {
     const NAME: &'static str = "priority_tx";
 
     type SolEvent = NewPriorityRequest;
-    type WatchedEvent = L1PriorityEnvelope;
+    type WatchedEvent = PriorityRequest;
     type Error = L1EnvelopeError;
 
     async fn process_event(
         &mut self,
-        tx: L1PriorityEnvelope,
+        request: PriorityRequest,
     ) -> Result<(), L1WatcherError<Self::Error>> {
+        let PriorityRequest {
+            tx,
+            expiration_timestamp,
+        } = request;
         if tx.priority_id() < self.next_l1_priority_id {
             tracing::debug!(
                 priority_id = tx.priority_id(),
@@ -97,8 +122,12 @@ impl ProcessL1Event for L1TxWatcher {
             tracing::debug!(
                 priority_id = tx.priority_id(),
                 hash = ?tx.hash(),
+                expiration_timestamp,
                 "sending new priority transaction for processing",
             );
+            // Recorded before sending so that the sequencer knows the deadline once it receives the tx
+            self.deadlines
+                .record(tx.priority_id(), expiration_timestamp);
             self.output
                 .send(tx)
                 .await
@@ -5,6 +5,8 @@ use alloy::rpc::types::Filter;
 use alloy::sol_types::SolEvent;
 use std::time::Duration;
 use zksync_os_contract_interface::ZkChain;
+use zksync_os_error_reporting::codes::l1_watcher as error_codes;
+use zksync_os_error_reporting::{ErrorCode, NodeError, report_error};
 
 pub struct L1Watcher<Processor> {
     zk_chain: ZkChain<DynProvider>,
@@ -37,7 +39,7 @@ impl<Processor: ProcessL1Event> L1Watcher<Processor> {
         let mut timer = tokio::time::interval(self.poll_interval);
         loop {
             timer.tick().await;
-            self.poll().await?;
+            self.poll().await.inspect_err(|err| report_error(err))?;
         }
     }
 
@@ -131,3 +133,50 @@ pub enum L1WatcherError<E> {
     #[error("output has been closed")]
     OutputClosed,
 }
+
+impl<E: std::error::Error> NodeError for L1WatcherError<E> {
+    fn error_code(&self) -> &'static ErrorCode {
+        match self {
+            Self::NoL1Blocks => &error_codes::NO_L1_BLOCKS,
+            Self::Sol(_) => &error_codes::EVENT_DECODING,
+            Self::Transport(_) => &error_codes::L1_RPC,
+            Self::Batch(err) => {
+                zksync_os_error_reporting::error_code(err).unwrap_or(&error_codes::FAILED)
+            }
+            Self::Convert(_) => &error_codes::EVENT_CONVERSION,
+            Self::OutputClosed => &error_codes::OUTPUT_CLOSED,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use zksync_os_error_reporting::{ERROR_METRICS, Severity, WithErrorCode};
+
+    type Error = L1WatcherError<Infallible>;
+
+    #[test]
+    fn errors_are_reported_with_codes() {
+        let no_blocks = ("L1W-001", "l1_watcher", Severity::Error);
+        let processing = ("L1W-000", "l1_watcher", Severity::Error);
+        let output_closed = ("L1W-005", "l1_watcher", Severity::Warning);
+        let reported = |labels| ERROR_METRICS.errors[&labels].get();
+        let reported_before = [no_blocks, processing, output_closed].map(reported);
+
+        report_error(&Error::NoL1Blocks);
+        report_error(&Error::Batch(anyhow::anyhow!("batch not found")));
+        report_error(&Error::OutputClosed);
+        let reported_after = [no_blocks, processing, output_closed].map(reported);
+        for (before, after) in reported_before.into_iter().zip(reported_after) {
+            assert_eq!(after - before, 1);
+        }
+
+        // Codes attached by event processors take precedence
+        let err = Error::Batch(
+            anyhow::anyhow!("unexpected L1 transaction").with_code(&error_codes::EVENT_CONVERSION),
+        );
+        assert_eq!(err.error_code(), &error_codes::EVENT_CONVERSION);
+    }
+}
@@ -0,0 +1,223 @@
+//! Admission policy: basic spam controls applied to transactions before any other validation.
+//!
+//! Transactions can be rejected for a priority fee below the configured minimum, for a sender on
+//! the deny-list or, in allow-list mode (e.g. on permissioned chains), for a sender missing from
+//! the allow-list.
+
+use crate::TxValidatorConfig;
+use crate::metrics::{AdmissionRejectionReason, MEMPOOL_METRICS};
+use crate::transaction::L2PooledTransaction;
+use alloy::consensus::Transaction;
+use alloy::primitives::Address;
+use reth_transaction_pool::PoolTransaction;
+use reth_transaction_pool::error::PoolTransactionError;
+use std::any::Any;
+use std::collections::HashSet;
+
+/// Transaction rejected by the admission policy.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AdmissionError {
+    #[error("priority fee per gas {priority_fee} is below the minimum of {min_priority_fee}")]
+    PriorityFeeTooLow {
+        priority_fee: u128,
+        min_priority_fee: u128,
+    },
+    #[error("sender {0} is denied")]
+    SenderDenied(Address),
+    #[error("sender {0} is not on the allow-list")]
+    SenderNotAllowed(Address),
+}
+
+impl AdmissionError {
+    fn reason(&self) -> AdmissionRejectionReason {
+        match self {
+            Self::PriorityFeeTooLow { .. } => AdmissionRejectionReason::PriorityFeeTooLow,
+            Self::SenderDenied(_) => AdmissionRejectionReason::SenderDenied,
+            Self::SenderNotAllowed(_) => AdmissionRejectionReason::SenderNotAllowed,
+        }
+    }
+}
+
+impl PoolTransactionError for AdmissionError {
+    fn is_bad_transaction(&self) -> bool {
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct AdmissionPolicy {
+    min_priority_fee_per_gas: Option<u128>,
+    denied_senders: HashSet<Address>,
+    /// `None` unless in allow-list mode.
+    allowed_senders: Option<HashSet<Address>>,
+}
+
+impl AdmissionPolicy {
+    pub(crate) fn new(config: &TxValidatorConfig) -> Self {
+        Self {
+            min_priority_fee_per_gas: config.min_priority_fee_per_gas,
+            denied_senders: config.denied_senders.iter().copied().collect(),
+            allowed_senders: config
+                .allowed_senders
+                .as_ref()
+                .map(|senders| senders.iter().copied().collect()),
+        }
+    }
+
+    /// Checks `tx` against the policy; rejections are counted in metrics.
+    pub(crate) fn check(&self, tx: &L2PooledTransaction) -> Result<(), AdmissionError> {
+        self.check_inner(tx).inspect_err(|err| {
+            MEMPOOL_METRICS.admission_rejections[&err.reason()].inc();
+        })
+    }
+
+    fn check_inner(&self, tx: &L2PooledTransaction) -> Result<(), AdmissionError> {
+        let sender = tx.sender();
+        if self.denied_senders.contains(&sender) {
+            return Err(AdmissionError::SenderDenied(sender));
+        }
+        if let Some(allowed_senders) = &self.allowed_senders
+            && !allowed_senders.contains(&sender)
+        {
+            return Err(AdmissionError::SenderNotAllowed(sender));
+        }
+        if let Some(min_priority_fee) = self.min_priority_fee_per_gas {
+            // Legacy transactions pay their whole gas price as a priority fee
+            let priority_fee = tx.priority_fee_or_price();
+            if priority_fee < min_priority_fee {
+                return Err(AdmissionError::PriorityFeeTooLow {
+                    priority_fee,
+                    min_priority_fee,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::consensus::transaction::Recovered;
+    use alloy::consensus::{SignableTransaction, TxEip1559, TxLegacy};
+    use alloy::primitives::Signature;
+    use std::time::Duration;
+    use zksync_os_multivm::ExecutionVersion;
+    use zksync_os_types::L2Envelope;
+
+    fn config() -> TxValidatorConfig {
+        TxValidatorConfig {
+            max_input_bytes: 1024,
+            execution_version: ExecutionVersion::V4,
+            schedule: Default::default(),
+            sponsorship: Default::default(),
+            max_content_entries: 100,
+            max_tx_lifetime: Duration::from_secs(3_600),
+            min_priority_fee_per_gas: None,
+            denied_senders: vec![],
+            allowed_senders: None,
+        }
+    }
+
+    fn sender(byte: u8) -> Address {
+        Address::repeat_byte(byte)
+    }
+
+    fn tx(sender_byte: u8, priority_fee: u128) -> L2PooledTransaction {
+        let tx = TxEip1559 {
+            max_fee_per_gas: 1_000_000_000,
+            max_priority_fee_per_gas: priority_fee,
+            ..TxEip1559::default()
+        };
+        let envelope = L2Envelope::from(tx.into_signed(Signature::test_signature()));
+        L2PooledTransaction::from_pooled(Recovered::new_unchecked(envelope, sender(sender_byte)))
+    }
+
+    fn reason_count(reason: AdmissionRejectionReason) -> u64 {
+        MEMPOOL_METRICS.admission_rejections[&reason].get()
+    }
+
+    #[test]
+    fn default_policy_accepts_everything() {
+        let policy = AdmissionPolicy::new(&config());
+        policy.check(&tx(1, 0)).unwrap();
+    }
+
+    #[test]
+    fn priority_fee_below_minimum_is_rejected() {
+        let policy = AdmissionPolicy::new(&TxValidatorConfig {
+            min_priority_fee_per_gas: Some(100),
+            ..config()
+        });
+        let rejections = reason_count(AdmissionRejectionReason::PriorityFeeTooLow);
+        policy.check(&tx(1, 100)).unwrap();
+        assert_eq!(
+            policy.check(&tx(1, 99)),
+            Err(AdmissionError::PriorityFeeTooLow {
+                priority_fee: 99,
+                min_priority_fee: 100,
+            })
+        );
+        assert!(reason_count(AdmissionRejectionReason::PriorityFeeTooLow) > rejections);
+
+        // Legacy transactions are checked against their gas price
+        let legacy = |gas_price| {
+            let tx = TxLegacy {
+                gas_price,
+                ..TxLegacy::default()
+            };
+            let envelope = L2Envelope::from(tx.into_signed(Signature::test_signature()));
+            L2PooledTransaction::from_pooled(Recovered::new_unchecked(envelope, sender(1)))
+        };
+        policy.check(&legacy(100)).unwrap();
+        assert!(policy.check(&legacy(99)).is_err());
+    }
+
+    #[test]
+    fn deny_list_only_blocks_listed_senders() {
+        let policy = AdmissionPolicy::new(&TxValidatorConfig {
+            denied_senders: vec![sender(1)],
+            ..config()
+        });
+        let rejections = reason_count(AdmissionRejectionReason::SenderDenied);
+        assert_eq!(
+            policy.check(&tx(1, 0)),
+            Err(AdmissionError::SenderDenied(sender(1)))
+        );
+        assert!(reason_count(AdmissionRejectionReason::SenderDenied) > rejections);
+        policy.check(&tx(2, 0)).unwrap();
+        policy.check(&tx(3, 0)).unwrap();
+    }
+
+    #[test]
+    fn allow_list_rejects_unknown_senders() {
+        let policy = AdmissionPolicy::new(&TxValidatorConfig {
+            allowed_senders: Some(vec![sender(1), sender(2)]),
+            denied_senders: vec![sender(2)],
+            ..config()
+        });
+        let rejections = reason_count(AdmissionRejectionReason::SenderNotAllowed);
+        policy.check(&tx(1, 0)).unwrap();
+        assert_eq!(
+            policy.check(&tx(3, 0)),
+            Err(AdmissionError::SenderNotAllowed(sender(3)))
+        );
+        assert!(reason_count(AdmissionRejectionReason::SenderNotAllowed) > rejections);
+        // The deny-list takes precedence
+        assert_eq!(
+            policy.check(&tx(2, 0)),
+            Err(AdmissionError::SenderDenied(sender(2)))
+        );
+
+        // An empty allow-list rejects all senders
+        let policy = AdmissionPolicy::new(&TxValidatorConfig {
+            allowed_senders: Some(vec![]),
+            ..config()
+        });
+        assert!(policy.check(&tx(1, 0)).is_err());
+    }
+}
@@ -1,4 +1,34 @@
+use crate::{SponsorshipConfig, TxScheduleConfig};
+use alloy::primitives::Address;
+use std::time::Duration;
+use zksync_os_multivm::ExecutionVersion;
+
 pub struct TxValidatorConfig {
     /// Max input size of a transaction to be accepted by mempool
     pub max_input_bytes: usize,
+    /// Execution version of the latest block. Transactions are validated against its limits;
+    /// updated on protocol upgrades via [`L2TransactionPool::on_execution_version_change`].
+    ///
+    /// [`L2TransactionPool::on_execution_version_change`]: crate::L2TransactionPool::on_execution_version_change
+    pub execution_version: ExecutionVersion,
+    /// Limits on scheduled transactions, see [`TxSchedule`](crate::TxSchedule).
+    pub schedule: TxScheduleConfig,
+    /// Registered sponsors and their budgets, see [`Sponsorships`](crate::Sponsorships).
+    pub sponsorship: SponsorshipConfig,
+    /// Max number of transactions listed by [`L2TransactionPool::content`] and
+    /// [`L2TransactionPool::inspect`].
+    ///
+    /// [`L2TransactionPool::content`]: crate::L2TransactionPool::content
+    /// [`L2TransactionPool::inspect`]: crate::L2TransactionPool::inspect
+    pub max_content_entries: usize,
+    /// Pooled transactions are removed after this time by the
+    /// [pool maintenance task](crate::run_pool_maintenance).
+    pub max_tx_lifetime: Duration,
+    /// Transactions with a lower priority fee per gas (gas price for legacy transactions) are
+    /// rejected.
+    pub min_priority_fee_per_gas: Option<u128>,
+    /// Transactions from these senders are rejected.
+    pub denied_senders: Vec<Address>,
+    /// If set, only transactions from these senders are accepted (e.g. on permissioned chains).
+    pub allowed_senders: Option<Vec<Address>>,
 }
@@ -0,0 +1,299 @@
+//! Views of the pool grouped by sender and nonce, backing the `txpool_content`,
+//! `txpool_inspect` and `txpool_status` RPC methods.
+//!
+//! Scheduled transactions that are not in the pool yet are listed as queued. Views list at most a
+//! configured number of transactions (pending ones first) so that a huge pool doesn't blow up the
+//! response; [`TxPoolContent::truncated`] is set if some transactions were left out.
+
+use crate::schedule::ScheduledTransactions;
+use crate::transaction::L2PooledTransaction;
+use alloy::consensus::Transaction;
+use alloy::primitives::{Address, TxHash, U256};
+use reth_transaction_pool::{PoolTransaction, TransactionPool};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Transactions of a sub-pool by sender and nonce.
+pub type TxsBySender<T> = BTreeMap<Address, BTreeMap<u64, T>>;
+
+/// Summary of a pooled transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxSummary {
+    pub hash: TxHash,
+    pub nonce: u64,
+    /// `None` for contract deployments.
+    pub to: Option<Address>,
+    pub value: U256,
+    pub gas_limit: u64,
+    pub max_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: Option<u128>,
+}
+
+impl TxSummary {
+    pub(crate) fn new(tx: &L2PooledTransaction) -> Self {
+        Self {
+            hash: *tx.hash(),
+            nonce: tx.nonce(),
+            to: tx.to(),
+            value: tx.value(),
+            gas_limit: tx.gas_limit(),
+            max_fee_per_gas: tx.max_fee_per_gas(),
+            max_priority_fee_per_gas: tx.max_priority_fee_per_gas(),
+        }
+    }
+
+    /// One-line description in the format used by geth's `txpool_inspect`.
+    pub(crate) fn inspect(tx: &L2PooledTransaction) -> String {
+        let recipient = match tx.to() {
+            Some(to) => to.to_string(),
+            None => "contract creation".to_owned(),
+        };
+        format!(
+            "{recipient}: {} wei + {} gas × {} wei",
+            tx.value(),
+            tx.gas_limit(),
+            tx.max_fee_per_gas()
+        )
+    }
+}
+
+/// Pending and queued transactions grouped by sender and nonce.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TxPoolContent<T = TxSummary> {
+    pub pending: TxsBySender<T>,
+    pub queued: TxsBySender<T>,
+    /// Whether some transactions were left out because of the entry limit.
+    pub truncated: bool,
+}
+
+/// [`TxPoolContent`] with transactions described by a single line.
+pub type TxPoolInspect = TxPoolContent<String>;
+
+/// Number of transactions in the pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TxPoolStatus {
+    pub pending: usize,
+    /// Transactions waiting for a nonce gap to be filled, for the fee to become sufficient or for
+    /// their schedule to come due.
+    pub queued: usize,
+}
+
+impl TxPoolStatus {
+    pub(crate) fn new<Pool>(pool: &Pool, scheduled: &ScheduledTransactions) -> Self
+    where
+        Pool: TransactionPool<Transaction = L2PooledTransaction> + ?Sized,
+    {
+        let size = pool.pool_size();
+        Self {
+            pending: size.pending,
+            queued: size.basefee + size.queued + scheduled.len(),
+        }
+    }
+}
+
+impl<T> TxPoolContent<T> {
+    /// Lists at most `max_entries` transactions from `pool` and `scheduled`, described by
+    /// `describe`.
+    pub(crate) fn new<Pool>(
+        pool: &Pool,
+        scheduled: &ScheduledTransactions,
+        max_entries: usize,
+        describe: impl Fn(&L2PooledTransaction) -> T,
+    ) -> Self
+    where
+        Pool: TransactionPool<Transaction = L2PooledTransaction> + ?Sized,
+    {
+        let mut content = Self {
+            pending: BTreeMap::new(),
+            queued: BTreeMap::new(),
+            truncated: false,
+        };
+        let mut remaining = max_entries;
+        let mut insert = |txs: &mut TxsBySender<T>, tx: &L2PooledTransaction| {
+            if remaining == 0 {
+                return false;
+            }
+            remaining -= 1;
+            txs.entry(tx.sender())
+                .or_default()
+                .insert(tx.nonce(), describe(tx));
+            true
+        };
+
+        for tx in pool.pending_transactions_max(max_entries) {
+            insert(&mut content.pending, &tx.transaction);
+        }
+        let queued_complete = pool
+            .queued_transactions()
+            .iter()
+            .all(|tx| insert(&mut content.queued, &tx.transaction));
+        let scheduled_complete = scheduled.with_transactions(|txs| {
+            txs.iter()
+                .all(|tx| insert(&mut content.queued, &tx.transaction))
+        });
+        content.truncated =
+            !queued_complete || !scheduled_complete || pool.pool_size().pending > max_entries;
+        content
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TxScheduleConfig;
+    use crate::schedule::TxSchedule;
+    use alloy::consensus::transaction::Recovered;
+    use alloy::consensus::{SignableTransaction, TxEip1559};
+    use alloy::primitives::{Signature, TxKind};
+    use futures::executor::block_on;
+    use reth_transaction_pool::blobstore::NoopBlobStore;
+    use reth_transaction_pool::{
+        CoinbaseTipOrdering, Pool, PoolConfig, TransactionOrigin, TransactionValidationOutcome,
+        TransactionValidator, ValidTransaction,
+    };
+    use zksync_os_types::L2Envelope;
+
+    /// Accepts all transactions from funded senders with zero on-chain nonces.
+    #[derive(Debug)]
+    struct FreshSendersValidator;
+
+    impl TransactionValidator for FreshSendersValidator {
+        type Transaction = L2PooledTransaction;
+
+        async fn validate_transaction(
+            &self,
+            _origin: TransactionOrigin,
+            transaction: Self::Transaction,
+        ) -> TransactionValidationOutcome<Self::Transaction> {
+            TransactionValidationOutcome::Valid {
+                balance: U256::MAX,
+                state_nonce: 0,
+                bytecode_hash: None,
+                transaction: ValidTransaction::Valid(transaction),
+                propagate: true,
+                authorities: None,
+            }
+        }
+    }
+
+    type TestPool =
+        Pool<FreshSendersValidator, CoinbaseTipOrdering<L2PooledTransaction>, NoopBlobStore>;
+
+    fn sender(byte: u8) -> Address {
+        Address::repeat_byte(byte)
+    }
+
+    fn tx(sender_byte: u8, nonce: u64) -> L2PooledTransaction {
+        let tx = TxEip1559 {
+            chain_id: 270,
+            nonce,
+            gas_limit: 21_000,
+            max_fee_per_gas: 1_000_000_000,
+            max_priority_fee_per_gas: 1,
+            to: TxKind::Call(Address::repeat_byte(0x22)),
+            value: U256::from(1_000),
+            ..TxEip1559::default()
+        };
+        let envelope = L2Envelope::from(tx.into_signed(Signature::test_signature()));
+        L2PooledTransaction::from_pooled(Recovered::new_unchecked(envelope, sender(sender_byte)))
+    }
+
+    /// Pool with sender 1 having nonces 0, 1, 3, 4 (i.e. a gap at 2) and sender 2 having nonce 0.
+    fn gapped_pool() -> TestPool {
+        let pool = Pool::new(
+            FreshSendersValidator,
+            CoinbaseTipOrdering::default(),
+            NoopBlobStore::default(),
+            PoolConfig::default(),
+        );
+        for (sender, nonce) in [(1, 0), (1, 1), (1, 3), (1, 4), (2, 0)] {
+            block_on(pool.add_transaction(TransactionOrigin::Local, tx(sender, nonce))).unwrap();
+        }
+        pool
+    }
+
+    fn nonces<T>(txs: &TxsBySender<T>, sender_byte: u8) -> Vec<u64> {
+        txs.get(&sender(sender_byte))
+            .map(|txs| txs.keys().copied().collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn nonce_gap_splits_sender_transactions() {
+        let pool = gapped_pool();
+        let scheduled = ScheduledTransactions::new(TxScheduleConfig::default());
+        let content = TxPoolContent::new(&pool, &scheduled, 100, TxSummary::new);
+
+        assert_eq!(nonces(&content.pending, 1), [0, 1]);
+        assert_eq!(nonces(&content.queued, 1), [3, 4]);
+        assert_eq!(nonces(&content.pending, 2), [0]);
+        assert!(!content.queued.contains_key(&sender(2)));
+        assert!(!content.truncated);
+        assert_eq!(content.pending[&sender(1)][&1], TxSummary::new(&tx(1, 1)));
+        assert_eq!(
+            TxPoolStatus::new(&pool, &scheduled),
+            TxPoolStatus {
+                pending: 3,
+                queued: 2
+            }
+        );
+
+        let inspect = TxPoolContent::new(&pool, &scheduled, 100, TxSummary::inspect);
+        assert_eq!(
+            inspect.queued[&sender(1)][&3],
+            format!(
+                "{}: 1000 wei + 21000 gas × 1000000000 wei",
+                Address::repeat_byte(0x22)
+            )
+        );
+
+        // Nonces are keyed by their decimal representation, as in geth
+        let json = serde_json::to_value(&content).unwrap();
+        let queued = json["queued"].as_object().unwrap();
+        assert_eq!(queued.len(), 1);
+        let sender_txs = queued.values().next().unwrap();
+        assert_eq!(sender_txs["3"]["gasLimit"], 21_000);
+    }
+
+    #[test]
+    fn scheduled_transactions_are_queued() {
+        let pool = gapped_pool();
+        let scheduled = ScheduledTransactions::new(TxScheduleConfig::default());
+        let schedule = TxSchedule {
+            not_before_block: Some(100),
+            not_before_timestamp: None,
+        };
+        scheduled
+            .insert(tx(3, 0).with_schedule(schedule), None)
+            .unwrap();
+
+        let content = TxPoolContent::new(&pool, &scheduled, 100, TxSummary::new);
+        assert_eq!(nonces(&content.queued, 3), [0]);
+        assert_eq!(TxPoolStatus::new(&pool, &scheduled).queued, 3);
+    }
+
+    #[test]
+    fn content_is_truncated() {
+        let pool = gapped_pool();
+        let scheduled = ScheduledTransactions::new(TxScheduleConfig::default());
+
+        let content = TxPoolContent::new(&pool, &scheduled, 4, TxSummary::new);
+        // Pending transactions go first
+        assert_eq!(nonces(&content.pending, 1), [0, 1]);
+        assert_eq!(nonces(&content.pending, 2), [0]);
+        assert_eq!(content.queued.values().map(BTreeMap::len).sum::<usize>(), 1);
+        assert!(content.truncated);
+
+        let content = TxPoolContent::new(&pool, &scheduled, 2, TxSummary::new);
+        assert_eq!(
+            content.pending.values().map(BTreeMap::len).sum::<usize>(),
+            2
+        );
+        assert!(content.queued.is_empty());
+        assert!(content.truncated);
+
+        let content = TxPoolContent::new(&pool, &scheduled, 5, TxSummary::new);
+        assert!(!content.truncated);
+    }
+}
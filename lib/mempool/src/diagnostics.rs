@@ -0,0 +1,169 @@
+use crate::schedule::TxSchedule;
+use alloy::primitives::{Address, TxHash};
+
+/// Snapshot of a single sender's standing in the mempool.
+///
+/// Primarily used to explain "stuck" transactions to users: most of them turn out to be waiting
+/// behind a nonce gap or carrying a fee cap below the current base fee.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SenderDiagnostics {
+    pub sender: Address,
+    /// Account nonce as of the latest block known to the pool's state provider.
+    pub on_chain_nonce: u64,
+    /// Transactions that are executable right away (sorted by nonce).
+    pub pending: Vec<PooledTxDiagnostics>,
+    /// Transactions that are waiting for a nonce gap to be filled, for the fee to become
+    /// sufficient or for their schedule to come due (sorted by nonce).
+    pub queued: Vec<PooledTxDiagnostics>,
+    /// First nonce that is missing from the pool and blocks subsequent transactions, if any.
+    pub first_missing_nonce: Option<u64>,
+}
+
+/// Lightweight view of a pooled transaction relevant for sender diagnostics.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PooledTxDiagnostics {
+    pub hash: TxHash,
+    pub nonce: u64,
+    pub max_fee_per_gas: u128,
+    /// Whether `max_fee_per_gas` covers the pool's pending base fee.
+    pub fee_adequate: bool,
+    /// Earliest block the transaction can be included in, if it was submitted with a schedule.
+    pub schedule: Option<TxSchedule>,
+}
+
+impl PooledTxDiagnostics {
+    pub fn new(hash: TxHash, nonce: u64, max_fee_per_gas: u128, base_fee: u64) -> Self {
+        Self {
+            hash,
+            nonce,
+            max_fee_per_gas,
+            fee_adequate: max_fee_per_gas >= base_fee as u128,
+            schedule: None,
+        }
+    }
+
+    pub fn with_schedule(mut self, schedule: Option<TxSchedule>) -> Self {
+        self.schedule = schedule;
+        self
+    }
+}
+
+impl SenderDiagnostics {
+    pub fn new(
+        sender: Address,
+        on_chain_nonce: u64,
+        mut pending: Vec<PooledTxDiagnostics>,
+        mut queued: Vec<PooledTxDiagnostics>,
+    ) -> Self {
+        pending.sort_by_key(|tx| tx.nonce);
+        queued.sort_by_key(|tx| tx.nonce);
+        let first_missing_nonce = first_missing_nonce(
+            on_chain_nonce,
+            pending.iter().chain(&queued).map(|tx| tx.nonce),
+        );
+        Self {
+            sender,
+            on_chain_nonce,
+            pending,
+            queued,
+            first_missing_nonce,
+        }
+    }
+
+    /// Whether any of the sender's pooled transactions cannot pay the current base fee.
+    pub fn has_underpriced_transactions(&self) -> bool {
+        self.pending
+            .iter()
+            .chain(&self.queued)
+            .any(|tx| !tx.fee_adequate)
+    }
+}
+
+/// Returns the lowest nonce `>= on_chain_nonce` that is absent from `pooled_nonces` while some
+/// higher nonce is present, i.e. the nonce that the sender has to submit to unblock the queue.
+fn first_missing_nonce(
+    on_chain_nonce: u64,
+    pooled_nonces: impl Iterator<Item = u64>,
+) -> Option<u64> {
+    let mut nonces: Vec<u64> = pooled_nonces.filter(|n| *n >= on_chain_nonce).collect();
+    nonces.sort_unstable();
+    nonces.dedup();
+    let mut expected = on_chain_nonce;
+    for nonce in nonces {
+        if nonce != expected {
+            return Some(expected);
+        }
+        expected += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE_FEE: u64 = 100;
+
+    fn tx(nonce: u64, max_fee_per_gas: u128) -> PooledTxDiagnostics {
+        PooledTxDiagnostics::new(
+            TxHash::with_last_byte(nonce as u8),
+            nonce,
+            max_fee_per_gas,
+            BASE_FEE,
+        )
+    }
+
+    #[test]
+    fn no_gap() {
+        let diagnostics =
+            SenderDiagnostics::new(Address::ZERO, 5, vec![tx(6, 200), tx(5, 200)], vec![]);
+        assert_eq!(diagnostics.first_missing_nonce, None);
+        assert_eq!(
+            diagnostics
+                .pending
+                .iter()
+                .map(|tx| tx.nonce)
+                .collect::<Vec<_>>(),
+            [5, 6]
+        );
+        assert!(!diagnostics.has_underpriced_transactions());
+    }
+
+    #[test]
+    fn single_gap() {
+        let diagnostics =
+            SenderDiagnostics::new(Address::ZERO, 6, vec![], vec![tx(7, 200), tx(8, 200)]);
+        assert_eq!(diagnostics.first_missing_nonce, Some(6));
+    }
+
+    #[test]
+    fn multiple_gaps() {
+        let diagnostics = SenderDiagnostics::new(
+            Address::ZERO,
+            3,
+            vec![tx(3, 200), tx(4, 200)],
+            vec![tx(6, 200), tx(9, 200)],
+        );
+        assert_eq!(diagnostics.first_missing_nonce, Some(5));
+    }
+
+    #[test]
+    fn stale_nonces_are_ignored() {
+        let diagnostics = SenderDiagnostics::new(Address::ZERO, 10, vec![], vec![tx(2, 200)]);
+        assert_eq!(diagnostics.first_missing_nonce, None);
+    }
+
+    #[test]
+    fn fee_too_low() {
+        let diagnostics = SenderDiagnostics::new(
+            Address::ZERO,
+            0,
+            vec![tx(0, 200)],
+            vec![tx(1, BASE_FEE as u128 - 1)],
+        );
+        assert_eq!(diagnostics.first_missing_nonce, None);
+        assert!(diagnostics.pending[0].fee_adequate);
+        assert!(!diagnostics.queued[0].fee_adequate);
+        assert!(diagnostics.has_underpriced_transactions());
+    }
+}
@@ -10,6 +10,33 @@ pub use transaction::L2PooledTransaction;
 mod config;
 pub use config::TxValidatorConfig;
 
+mod validator;
+pub use validator::ExecutionVersionError;
+
+mod admission;
+pub use admission::AdmissionError;
+
+mod content;
+pub use content::{TxPoolContent, TxPoolInspect, TxPoolStatus, TxSummary, TxsBySender};
+
+mod diagnostics;
+pub use diagnostics::{PooledTxDiagnostics, SenderDiagnostics};
+
+mod schedule;
+pub use schedule::{ScheduleError, ScheduledTransactions, TxSchedule, TxScheduleConfig};
+
+mod sponsorship;
+pub use sponsorship::{SponsorshipConfig, SponsorshipError, SponsorshipVoucher, Sponsorships};
+
+mod spam;
+pub use spam::{SpamEvent, SpamRejection, SpamScores, SpamScoringConfig, SpamSource};
+
+mod maintenance;
+pub use maintenance::{run_pool_maintenance, spawn_pool_maintenance};
+
+mod persistence;
+pub use persistence::{PoolPersistence, PoolPersistenceConfig, restore_into};
+
 mod metrics;
 mod reth_state;
 
@@ -20,9 +47,11 @@ pub use reth_transaction_pool::{
     PoolUpdateKind, SubPoolLimit,
 };
 
-use crate::metrics::ViseRecorder;
+use crate::admission::AdmissionPolicy;
+use crate::metrics::{MEMPOOL_METRICS, ViseRecorder};
 use crate::reth_state::ZkClient;
 use crate::traits::RethPool;
+use crate::validator::ZkTransactionValidator;
 use reth_transaction_pool::CoinbaseTipOrdering;
 use reth_transaction_pool::blobstore::NoopBlobStore;
 use reth_transaction_pool::validate::EthTransactionValidatorBuilder;
@@ -34,21 +63,66 @@ pub fn in_memory<State: ReadStateHistory + Clone, Repository: ReadRepository + C
     chain_id: u64,
     pool_config: PoolConfig,
     validator_config: TxValidatorConfig,
+    spam_scores: SpamScores,
 ) -> impl L2TransactionPool {
     let client = ZkClient::new(state, repository, chain_id);
     let blob_store = NoopBlobStore::default();
+    MEMPOOL_METRICS
+        .execution_version
+        .set(validator_config.execution_version as u64);
     // Use `ViseRecorder` during mempool initialization to register metrics. This will make sure
     // reth mempool metrics are propagated to `vise` collector. Only code inside the closure is
     // affected.
     ::metrics::with_local_recorder(&ViseRecorder, move || {
+        let eth_validator = EthTransactionValidatorBuilder::new(client)
+            .no_prague()
+            .with_max_tx_input_bytes(validator_config.max_input_bytes)
+            .build(blob_store);
         RethPool::new(
-            EthTransactionValidatorBuilder::new(client)
-                .no_prague()
-                .with_max_tx_input_bytes(validator_config.max_input_bytes)
-                .build(blob_store),
+            ZkTransactionValidator::new(
+                eth_validator,
+                AdmissionPolicy::new(&validator_config),
+                validator_config.execution_version,
+                spam_scores,
+                ScheduledTransactions::new(validator_config.schedule),
+                Sponsorships::new(validator_config.sponsorship),
+                validator_config.max_content_entries,
+                validator_config.max_tx_lifetime,
+            ),
             CoinbaseTipOrdering::default(),
             blob_store,
             pool_config,
         )
     })
 }
+
+/// Same as [`in_memory`], but with pool contents journaled as configured by `persistence_config`
+/// (if any). Transactions journaled by the previous run are restored once the returned
+/// [`PoolPersistence`] is [run](PoolPersistence::run).
+pub fn in_memory_with_persistence<
+    State: ReadStateHistory + Clone,
+    Repository: ReadRepository + Clone,
+>(
+    state: State,
+    repository: Repository,
+    chain_id: u64,
+    pool_config: PoolConfig,
+    validator_config: TxValidatorConfig,
+    spam_scores: SpamScores,
+    persistence_config: Option<PoolPersistenceConfig>,
+) -> (
+    impl L2TransactionPool,
+    Option<PoolPersistence<impl L2TransactionPool, Repository>>,
+) {
+    let pool = in_memory(
+        state,
+        repository.clone(),
+        chain_id,
+        pool_config,
+        validator_config,
+        spam_scores,
+    );
+    let persistence =
+        persistence_config.map(|config| PoolPersistence::new(pool.clone(), repository, config));
+    (pool, persistence)
+}
@@ -0,0 +1,178 @@
+//! Age-based expiry of pooled transactions.
+//!
+//! Transactions that can never be mined (e.g. with a nonce gap that is never filled or a fee
+//! permanently below the base fee) would otherwise stay in the pool until evicted by sub-pool size
+//! limits. The maintenance task removes transactions that have been in the pool for longer than
+//! [`TxValidatorConfig::max_tx_lifetime`](crate::TxValidatorConfig::max_tx_lifetime).
+
+use crate::metrics::MEMPOOL_METRICS;
+use crate::traits::L2TransactionPool;
+use crate::transaction::L2PooledTransaction;
+use alloy::primitives::TxHash;
+use reth_transaction_pool::TransactionPool;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// Spawns [`run_pool_maintenance`] on the current tokio runtime.
+pub fn spawn_pool_maintenance<Pool: L2TransactionPool>(
+    pool: Pool,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(run_pool_maintenance(pool, interval))
+}
+
+/// Removes expired transactions from `pool` every `interval`. Transaction age is counted from the
+/// first scan the transaction was seen by, so transactions are removed at most `interval` after
+/// they expire. Never returns.
+pub async fn run_pool_maintenance<Pool: L2TransactionPool>(pool: Pool, interval: Duration) {
+    let max_tx_lifetime = pool.max_tx_lifetime();
+    expire_transactions_periodically(&pool, interval, max_tx_lifetime).await
+}
+
+async fn expire_transactions_periodically<Pool>(
+    pool: &Pool,
+    interval: Duration,
+    max_tx_lifetime: Duration,
+) where
+    Pool: TransactionPool<Transaction = L2PooledTransaction>,
+{
+    let mut first_seen = HashMap::new();
+    let mut timer = tokio::time::interval(interval);
+    loop {
+        timer.tick().await;
+        first_seen = expire_transactions(pool, first_seen, max_tx_lifetime);
+    }
+}
+
+/// Removes transactions first seen more than `max_tx_lifetime` ago. Returns first-seen times of
+/// the remaining transactions.
+fn expire_transactions<Pool>(
+    pool: &Pool,
+    mut first_seen: HashMap<TxHash, Instant>,
+    max_tx_lifetime: Duration,
+) -> HashMap<TxHash, Instant>
+where
+    Pool: TransactionPool<Transaction = L2PooledTransaction>,
+{
+    let now = Instant::now();
+    let pooled = pool.pooled_transactions();
+    let mut remaining = HashMap::with_capacity(pooled.len());
+    let mut expired = Vec::new();
+    for tx in &pooled {
+        let hash = *tx.hash();
+        let seen_at = first_seen.remove(&hash).unwrap_or(now);
+        if now.duration_since(seen_at) >= max_tx_lifetime {
+            expired.push(hash);
+        } else {
+            remaining.insert(hash, seen_at);
+        }
+    }
+    if expired.is_empty() {
+        return remaining;
+    }
+
+    let removed = pool.remove_transactions(expired);
+    MEMPOOL_METRICS
+        .expired_transactions
+        .inc_by(removed.len() as u64);
+    for tx in &removed {
+        tracing::debug!(hash = %tx.hash(), sender = %tx.sender(), "expired pooled transaction");
+    }
+    tracing::info!(
+        expired = removed.len(),
+        ?max_tx_lifetime,
+        "removed expired transactions from the pool"
+    );
+    remaining
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::consensus::transaction::Recovered;
+    use alloy::consensus::{SignableTransaction, TxEip1559};
+    use alloy::primitives::{Address, Signature, U256};
+    use reth_transaction_pool::blobstore::NoopBlobStore;
+    use reth_transaction_pool::{
+        CoinbaseTipOrdering, Pool, PoolConfig, PoolTransaction, TransactionOrigin,
+        TransactionValidationOutcome, TransactionValidator, ValidTransaction,
+    };
+    use zksync_os_types::L2Envelope;
+
+    /// Accepts all transactions from funded senders with zero on-chain nonces.
+    #[derive(Debug)]
+    struct FreshSendersValidator;
+
+    impl TransactionValidator for FreshSendersValidator {
+        type Transaction = L2PooledTransaction;
+
+        async fn validate_transaction(
+            &self,
+            _origin: TransactionOrigin,
+            transaction: Self::Transaction,
+        ) -> TransactionValidationOutcome<Self::Transaction> {
+            TransactionValidationOutcome::Valid {
+                balance: U256::MAX,
+                state_nonce: 0,
+                bytecode_hash: None,
+                transaction: ValidTransaction::Valid(transaction),
+                propagate: true,
+                authorities: None,
+            }
+        }
+    }
+
+    fn tx(sender_byte: u8, nonce: u64) -> L2PooledTransaction {
+        let tx = TxEip1559 {
+            nonce,
+            gas_limit: 21_000,
+            max_fee_per_gas: 1_000_000_000,
+            ..TxEip1559::default()
+        };
+        let envelope = L2Envelope::from(tx.into_signed(Signature::test_signature()));
+        let sender = Address::repeat_byte(sender_byte);
+        L2PooledTransaction::from_pooled(Recovered::new_unchecked(envelope, sender))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn old_transactions_expire() {
+        let pool = Pool::new(
+            FreshSendersValidator,
+            CoinbaseTipOrdering::<L2PooledTransaction>::default(),
+            NoopBlobStore::default(),
+            PoolConfig::default(),
+        );
+        // Nonce gap that is never filled
+        let stuck = tx(1, 5);
+        pool.add_transaction(TransactionOrigin::Local, stuck.clone())
+            .await
+            .unwrap();
+        let expired_before = MEMPOOL_METRICS.expired_transactions.get();
+
+        let maintained_pool = pool.clone();
+        tokio::spawn(async move {
+            expire_transactions_periodically(
+                &maintained_pool,
+                Duration::from_secs(10),
+                Duration::from_secs(60),
+            )
+            .await
+        });
+        tokio::time::sleep(Duration::from_secs(35)).await;
+        let fresh = tx(2, 0);
+        pool.add_transaction(TransactionOrigin::Local, fresh.clone())
+            .await
+            .unwrap();
+
+        tokio::time::sleep(Duration::from_secs(30)).await;
+        assert!(!pool.contains(stuck.hash()));
+        assert!(pool.contains(fresh.hash()));
+        assert!(MEMPOOL_METRICS.expired_transactions.get() > expired_before);
+
+        tokio::time::sleep(Duration::from_secs(40)).await;
+        assert!(!pool.contains(fresh.hash()));
+        assert_eq!(pool.pool_size().total, 0);
+    }
+}
@@ -1,8 +1,9 @@
+use crate::spam::SpamEvent;
 use metrics::{
     CounterFn, GaugeFn, HistogramFn, Key, KeyName, Metadata, Recorder, SharedString, Unit,
 };
 use std::sync::Arc;
-use vise::{Buckets, Counter, Gauge, Histogram, Metrics};
+use vise::{Buckets, Counter, EncodeLabelValue, Family, Gauge, Histogram, Metrics};
 
 /// Mempool metrics.
 ///
@@ -125,6 +126,88 @@ pub struct TxPoolValidatorMetrics {
     pub(crate) inflight_validation_jobs: Gauge,
 }
 
+/// Reason for discarding transactions that were already accepted into the pool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EncodeLabelValue)]
+#[metrics(label = "reason", rename_all = "snake_case")]
+pub(crate) enum DiscardReason {
+    /// Transaction is invalid under the new execution version after a protocol upgrade.
+    ExecutionVersionChange,
+    /// Scheduled transaction failed validation when promoted to the pool.
+    ScheduledPromotionFailure,
+}
+
+/// Reason for rejecting a transaction by the admission policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EncodeLabelValue)]
+#[metrics(label = "reason", rename_all = "snake_case")]
+pub(crate) enum AdmissionRejectionReason {
+    PriorityFeeTooLow,
+    SenderDenied,
+    SenderNotAllowed,
+}
+
+/// Reason for rejecting a sponsored transaction at submission or skipping it in block building.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EncodeLabelValue)]
+#[metrics(label = "reason", rename_all = "snake_case")]
+pub(crate) enum SponsorshipRejectionReason {
+    Unsupported,
+    UnknownSponsor,
+    InvalidSignature,
+    Expiry,
+    FeeNotCovered,
+    BudgetExhausted,
+    InsufficientSponsorBalance,
+    AlreadyUsed,
+}
+
+/// Outcome of restoring a journaled transaction on startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EncodeLabelValue)]
+#[metrics(label = "outcome", rename_all = "snake_case")]
+pub(crate) enum RestoreOutcome {
+    Restored,
+    /// Transaction's sender nonce was already used on-chain.
+    Mined,
+    /// Transaction failed validation.
+    Invalid,
+}
+
+/// ZKsync OS-specific mempool metrics.
+#[derive(Debug, Metrics)]
+#[metrics(prefix = "mempool")]
+pub(crate) struct MempoolMetrics {
+    /// Number of pooled transactions discarded, by reason
+    pub discarded_transactions: Family<DiscardReason, Counter>,
+    /// Execution version transactions are validated against
+    pub execution_version: Gauge<u64>,
+    /// Number of events increasing spam scores, by event
+    pub spam_events: Family<SpamEvent, Counter>,
+    /// Number of submissions rejected because of the sender's or origin's spam score
+    pub spam_rejections: Counter,
+    /// Number of senders deprioritized because of their spam score
+    pub spam_deprioritized_senders: Gauge<usize>,
+    /// Number of transactions rejected by the admission policy, by reason
+    pub admission_rejections: Family<AdmissionRejectionReason, Counter>,
+    /// Number of scheduled transactions waiting to be promoted to the pool
+    pub scheduled_transactions: Gauge<usize>,
+    /// Number of scheduled transactions promoted to the pool
+    pub promoted_scheduled_transactions: Counter,
+    /// Number of times a scheduled transaction was skipped by a block it's not due in
+    pub scheduled_transactions_skipped: Counter,
+    /// Number of sponsored transactions rejected at submission, by reason
+    pub sponsorship_rejections: Family<SponsorshipRejectionReason, Counter>,
+    /// Number of times a sponsored transaction was skipped by block building, by reason
+    pub sponsored_transactions_skipped: Family<SponsorshipRejectionReason, Counter>,
+    /// Number of sponsored transactions charged to sponsor budgets by block building
+    pub sponsored_transactions_charged: Counter,
+    /// Number of pooled transactions removed because of their age
+    pub expired_transactions: Counter,
+    /// Number of journaled transactions processed on startup, by outcome
+    pub restored_transactions: Family<RestoreOutcome, Counter>,
+    /// Number of entries in the pool journal
+    pub journal_entries: Gauge<usize>,
+}
+
+#[vise::register]
+pub(crate) static MEMPOOL_METRICS: vise::Global<MempoolMetrics> = vise::Global::new();
 #[vise::register]
 pub(crate) static TRANSACTION_POOL_METRICS: vise::Global<TxPoolMetrics> = vise::Global::new();
 #[vise::register]
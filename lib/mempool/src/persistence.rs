@@ -0,0 +1,524 @@
+//! Journal of pool contents, allowing pooled transactions to survive node restarts.
+//!
+//! [`PoolPersistence`] listens to pool events and appends added (raw 2718-encoded) and removed
+//! transactions to an append-only JSON lines file. On startup, [`restore_into`] re-validates and
+//! re-inserts journaled transactions, skipping ones whose nonce was already used on-chain. The
+//! journal is periodically compacted by rewriting it from the current pool contents; this also
+//! fixes up events dropped by the pool's listeners under load, so the journal is best-effort
+//! between compactions.
+//!
+//! Scheduled transactions that are not in the pool yet are not journaled.
+
+use crate::metrics::{MEMPOOL_METRICS, RestoreOutcome};
+use crate::transaction::L2PooledTransaction;
+use alloy::consensus::Transaction;
+use alloy::consensus::transaction::SignerRecoverable;
+use alloy::eips::{Decodable2718, Encodable2718};
+use alloy::primitives::{Bytes, TxHash};
+use anyhow::Context;
+use futures::{FutureExt, StreamExt};
+use reth_transaction_pool::{
+    AllTransactionsEvents, FullTransactionEvent, NewTransactionEvent, PoolTransaction,
+    TransactionListenerKind, TransactionOrigin, TransactionPool,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use zksync_os_storage_api::ReadRepository;
+use zksync_os_types::{L2Envelope, L2Transaction};
+
+#[derive(Debug, Clone)]
+pub struct PoolPersistenceConfig {
+    /// File pool contents are journaled to.
+    pub journal_path: PathBuf,
+    /// How often the journal is rewritten from the pool contents.
+    pub compaction_interval: Duration,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum JournalEntry {
+    /// Raw 2718-encoded transaction added to the pool.
+    Added(Bytes),
+    Removed(TxHash),
+}
+
+impl JournalEntry {
+    fn added(tx: &L2PooledTransaction) -> Self {
+        Self::Added(tx.transaction().inner().encoded_2718().into())
+    }
+}
+
+/// Journals transactions added to and removed from the pool.
+pub struct PoolPersistence<Pool: TransactionPool, Repository> {
+    pool: Pool,
+    repository: Repository,
+    config: PoolPersistenceConfig,
+    /// Journal opened for appending; `None` until the first compaction.
+    journal: Option<File>,
+    /// Transactions journaled as added and not removed since.
+    journaled: HashSet<TxHash>,
+    /// Number of entries in the journal.
+    entries: usize,
+    new_transactions: mpsc::Receiver<NewTransactionEvent<Pool::Transaction>>,
+    events: AllTransactionsEvents<Pool::Transaction>,
+}
+
+impl<Pool, Repository> PoolPersistence<Pool, Repository>
+where
+    Pool: TransactionPool<Transaction = L2PooledTransaction>,
+    Repository: ReadRepository,
+{
+    /// Subscribes to events of `pool`. Transactions added from this point on are journaled once
+    /// [`Self::run`] is started.
+    pub fn new(pool: Pool, repository: Repository, config: PoolPersistenceConfig) -> Self {
+        let new_transactions = pool.new_transactions_listener_for(TransactionListenerKind::All);
+        let events = pool.all_transactions_event_listener();
+        Self {
+            pool,
+            repository,
+            config,
+            journal: None,
+            journaled: HashSet::new(),
+            entries: 0,
+            new_transactions,
+            events,
+        }
+    }
+
+    /// Restores transactions journaled by the previous run into the pool, then journals pool
+    /// events and compacts the journal every [`PoolPersistenceConfig::compaction_interval`].
+    /// Never returns.
+    pub async fn run(mut self) {
+        let path = self.config.journal_path.clone();
+        if let Err(err) = restore_into(&self.pool, &path, &self.repository).await {
+            tracing::warn!("failed to restore mempool journal: {err:#}");
+        }
+
+        let mut timer = tokio::time::interval(self.config.compaction_interval);
+        loop {
+            tokio::select! {
+                // The first tick completes immediately, replacing the restored journal
+                _ = timer.tick() => self.compact(),
+                Some(event) = self.new_transactions.recv() => {
+                    self.on_new_transaction(event);
+                }
+                Some(event) = self.events.next() => self.on_event(event),
+            }
+        }
+    }
+
+    /// Journals all events received so far, without waiting for new ones.
+    fn journal_received_events(&mut self) {
+        while let Ok(event) = self.new_transactions.try_recv() {
+            self.on_new_transaction(event);
+        }
+        while let Some(Some(event)) = self.events.next().now_or_never() {
+            self.on_event(event);
+        }
+    }
+
+    fn on_new_transaction(&mut self, event: NewTransactionEvent<L2PooledTransaction>) {
+        let hash = *event.transaction.hash();
+        // Transactions can be removed before their addition is journaled
+        if self.journaled.contains(&hash) || !self.pool.contains(&hash) {
+            return;
+        }
+        self.append(&JournalEntry::added(&event.transaction.transaction));
+        self.journaled.insert(hash);
+    }
+
+    fn on_event(&mut self, event: FullTransactionEvent<L2PooledTransaction>) {
+        let hash = match event {
+            FullTransactionEvent::Mined { tx_hash, .. } => tx_hash,
+            FullTransactionEvent::Replaced { transaction, .. } => *transaction.hash(),
+            FullTransactionEvent::Discarded(hash) | FullTransactionEvent::Invalid(hash) => hash,
+            _ => return,
+        };
+        if self.journaled.remove(&hash) {
+            self.append(&JournalEntry::Removed(hash));
+        }
+    }
+
+    fn append(&mut self, entry: &JournalEntry) {
+        let Some(journal) = &mut self.journal else {
+            // Not compacted yet; the entry will be covered by the first compaction
+            return;
+        };
+        let mut line = serde_json::to_vec(entry).expect("failed to serialize journal entry");
+        line.push(b'\n');
+        if let Err(err) = journal.write_all(&line) {
+            tracing::warn!(
+                path = %self.config.journal_path.display(),
+                "failed to append to mempool journal: {err}"
+            );
+            return;
+        }
+        self.entries += 1;
+        MEMPOOL_METRICS.journal_entries.set(self.entries);
+    }
+
+    /// Rewrites the journal from the current pool contents.
+    fn compact(&mut self) {
+        self.journal_received_events();
+        if let Err(err) = self.compact_inner() {
+            tracing::warn!("failed to compact mempool journal: {err:#}");
+        }
+    }
+
+    fn compact_inner(&mut self) -> anyhow::Result<()> {
+        let path = &self.config.journal_path;
+        let pooled = self.pool.pooled_transactions();
+        let mut contents = Vec::new();
+        for tx in &pooled {
+            serde_json::to_writer(&mut contents, &JournalEntry::added(&tx.transaction))?;
+            contents.push(b'\n');
+        }
+        // Write to a temporary file first, so that a crash doesn't leave a truncated journal behind
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, contents)
+            .with_context(|| format!("cannot write `{}`", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, path)
+            .with_context(|| format!("cannot replace `{}`", path.display()))?;
+        self.journal = Some(
+            OpenOptions::new()
+                .append(true)
+                .open(path)
+                .with_context(|| format!("cannot open `{}`", path.display()))?,
+        );
+
+        let previous_entries = self.entries;
+        self.journaled = pooled.iter().map(|tx| *tx.hash()).collect();
+        self.entries = self.journaled.len();
+        MEMPOOL_METRICS.journal_entries.set(self.entries);
+        tracing::debug!(
+            previous_entries,
+            entries = self.entries,
+            "compacted mempool journal"
+        );
+        Ok(())
+    }
+}
+
+/// Re-validates and inserts transactions journaled at `path` into `pool`. Transactions whose
+/// sender nonce is already used by a transaction in `repository` (e.g. ones mined after the
+/// journal was last updated) are skipped. Returns the number of restored transactions.
+pub async fn restore_into<Pool>(
+    pool: &Pool,
+    path: &Path,
+    repository: &impl ReadRepository,
+) -> anyhow::Result<usize>
+where
+    Pool: TransactionPool<Transaction = L2PooledTransaction>,
+{
+    let journaled = read_journal(path)?;
+    let total = journaled.len();
+    let mut restored = 0;
+    for tx in journaled {
+        let (hash, sender, nonce) = (*tx.tx_hash(), tx.signer(), tx.nonce());
+        let outcome = if repository
+            .get_transaction_hash_by_sender_nonce(sender, nonce)?
+            .is_some()
+        {
+            RestoreOutcome::Mined
+        } else {
+            match pool
+                .add_transaction(
+                    TransactionOrigin::Local,
+                    L2PooledTransaction::from_pooled(tx),
+                )
+                .await
+            {
+                Ok(_) => RestoreOutcome::Restored,
+                Err(err) => {
+                    tracing::debug!(%hash, %err, "journaled transaction failed validation");
+                    RestoreOutcome::Invalid
+                }
+            }
+        };
+        if outcome == RestoreOutcome::Restored {
+            restored += 1;
+        }
+        MEMPOOL_METRICS.restored_transactions[&outcome].inc();
+    }
+    tracing::info!(
+        path = %path.display(),
+        journaled = total,
+        restored,
+        "restored mempool transactions from journal"
+    );
+    Ok(restored)
+}
+
+/// Reads transactions that are journaled as added and not removed since, ordered by sender and
+/// nonce.
+fn read_journal(path: &Path) -> anyhow::Result<Vec<L2Transaction>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            return Err(err)
+                .with_context(|| format!("cannot open mempool journal `{}`", path.display()));
+        }
+    };
+
+    let mut transactions = HashMap::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("cannot read `{}`", path.display()))?;
+        // The last line may be truncated if the node crashed while appending to the journal
+        let entry = match serde_json::from_str::<JournalEntry>(&line) {
+            Ok(entry) => entry,
+            Err(err) => {
+                tracing::warn!(
+                    line = i + 1,
+                    "skipping corrupted mempool journal entry: {err}"
+                );
+                continue;
+            }
+        };
+        match entry {
+            JournalEntry::Added(raw) => {
+                let tx = L2Envelope::decode_2718(&mut raw.as_ref())
+                    .map_err(anyhow::Error::from)
+                    .and_then(|tx| Ok(tx.try_into_recovered()?));
+                match tx {
+                    Ok(tx) => {
+                        transactions.insert(*tx.tx_hash(), tx);
+                    }
+                    Err(err) => {
+                        tracing::warn!(
+                            line = i + 1,
+                            "skipping undecodable journaled transaction: {err}"
+                        );
+                    }
+                }
+            }
+            JournalEntry::Removed(hash) => {
+                transactions.remove(&hash);
+            }
+        }
+    }
+    let mut transactions: Vec<_> = transactions.into_values().collect();
+    transactions.sort_by_key(|tx| (tx.signer(), tx.nonce()));
+    Ok(transactions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::consensus::{SignableTransaction, TxEip1559};
+    use alloy::primitives::{Address, BlockHash, BlockNumber, TxKind, TxNonce, U256};
+    use alloy::signers::SignerSync;
+    use alloy::signers::local::PrivateKeySigner;
+    use futures::executor::block_on;
+    use reth_transaction_pool::blobstore::NoopBlobStore;
+    use reth_transaction_pool::{
+        CoinbaseTipOrdering, Pool, PoolConfig, TransactionValidationOutcome, TransactionValidator,
+        ValidTransaction,
+    };
+    use std::sync::{Arc, Mutex};
+    use zksync_os_storage_api::{RepositoryBlock, RepositoryResult, StoredTxData, TxMeta};
+    use zksync_os_types::{ZkReceiptEnvelope, ZkTransaction};
+
+    /// Accepts all transactions from funded senders with zero on-chain nonces.
+    #[derive(Debug)]
+    struct FreshSendersValidator;
+
+    impl TransactionValidator for FreshSendersValidator {
+        type Transaction = L2PooledTransaction;
+
+        async fn validate_transaction(
+            &self,
+            _origin: TransactionOrigin,
+            transaction: Self::Transaction,
+        ) -> TransactionValidationOutcome<Self::Transaction> {
+            TransactionValidationOutcome::Valid {
+                balance: U256::MAX,
+                state_nonce: 0,
+                bytecode_hash: None,
+                transaction: ValidTransaction::Valid(transaction),
+                propagate: true,
+                authorities: None,
+            }
+        }
+    }
+
+    type TestPool =
+        Pool<FreshSendersValidator, CoinbaseTipOrdering<L2PooledTransaction>, NoopBlobStore>;
+
+    fn pool() -> TestPool {
+        Pool::new(
+            FreshSendersValidator,
+            CoinbaseTipOrdering::default(),
+            NoopBlobStore::default(),
+            PoolConfig::default(),
+        )
+    }
+
+    /// Repository only knowing about mined sender nonces.
+    #[derive(Debug, Clone, Default)]
+    struct MinedNonces(Arc<Mutex<HashMap<(Address, TxNonce), TxHash>>>);
+
+    impl MinedNonces {
+        fn mine(&self, tx: &L2Transaction) {
+            let mut mined = self.0.lock().unwrap();
+            mined.insert((tx.signer(), tx.nonce()), *tx.tx_hash());
+        }
+    }
+
+    impl ReadRepository for MinedNonces {
+        fn get_block_by_number(&self, _: BlockNumber) -> RepositoryResult<Option<RepositoryBlock>> {
+            unimplemented!()
+        }
+
+        fn get_block_by_hash(&self, _: BlockHash) -> RepositoryResult<Option<RepositoryBlock>> {
+            unimplemented!()
+        }
+
+        fn get_raw_transaction(&self, _: TxHash) -> RepositoryResult<Option<Vec<u8>>> {
+            unimplemented!()
+        }
+
+        fn get_transaction(&self, _: TxHash) -> RepositoryResult<Option<ZkTransaction>> {
+            unimplemented!()
+        }
+
+        fn get_transaction_receipt(
+            &self,
+            _: TxHash,
+        ) -> RepositoryResult<Option<ZkReceiptEnvelope>> {
+            unimplemented!()
+        }
+
+        fn get_transaction_meta(&self, _: TxHash) -> RepositoryResult<Option<TxMeta>> {
+            unimplemented!()
+        }
+
+        fn get_transaction_hash_by_sender_nonce(
+            &self,
+            sender: Address,
+            nonce: TxNonce,
+        ) -> RepositoryResult<Option<TxHash>> {
+            Ok(self.0.lock().unwrap().get(&(sender, nonce)).copied())
+        }
+
+        fn get_stored_transaction(&self, _: TxHash) -> RepositoryResult<Option<StoredTxData>> {
+            unimplemented!()
+        }
+
+        fn get_latest_block(&self) -> u64 {
+            0
+        }
+    }
+
+    fn tx(signer: &PrivateKeySigner) -> L2Transaction {
+        let tx = TxEip1559 {
+            chain_id: 270,
+            gas_limit: 21_000,
+            max_fee_per_gas: 1_000_000_000,
+            max_priority_fee_per_gas: 1,
+            to: TxKind::Call(Address::repeat_byte(0x22)),
+            value: U256::from(1_000),
+            ..TxEip1559::default()
+        };
+        let signature = signer.sign_hash_sync(&tx.signature_hash()).unwrap();
+        L2Envelope::from(tx.into_signed(signature))
+            .try_into_recovered()
+            .unwrap()
+    }
+
+    fn add(pool: &TestPool, tx: &L2Transaction) {
+        let tx = L2PooledTransaction::from_pooled(tx.clone());
+        block_on(pool.add_transaction(TransactionOrigin::Local, tx)).unwrap();
+    }
+
+    fn persistence(
+        pool: &TestPool,
+        repository: &MinedNonces,
+        path: &Path,
+    ) -> PoolPersistence<TestPool, MinedNonces> {
+        PoolPersistence::new(
+            pool.clone(),
+            repository.clone(),
+            PoolPersistenceConfig {
+                journal_path: path.to_owned(),
+                compaction_interval: Duration::from_secs(60),
+            },
+        )
+    }
+
+    fn journal_lines(path: &Path) -> usize {
+        std::fs::read_to_string(path).unwrap().lines().count()
+    }
+
+    #[test]
+    fn pending_transactions_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mempool_journal.jsonl");
+        let repository = MinedNonces::default();
+        let [pending, mined, removed, truncated] =
+            std::array::from_fn(|_| tx(&PrivateKeySigner::random()));
+
+        let pool = pool();
+        let mut persistence = persistence(&pool, &repository, &path);
+        persistence.compact();
+        for tx in [&pending, &mined, &removed] {
+            add(&pool, tx);
+        }
+        persistence.journal_received_events();
+        pool.remove_transactions(vec![*removed.tx_hash()]);
+        persistence.journal_received_events();
+        assert_eq!(journal_lines(&path), 4);
+
+        // Simulate a crash while appending, after `mined` was included in a block
+        repository.mine(&mined);
+        let mut journal = OpenOptions::new().append(true).open(&path).unwrap();
+        let entry = serde_json::to_string(&JournalEntry::Added(
+            truncated.inner().encoded_2718().into(),
+        ))
+        .unwrap();
+        journal.write_all(&entry.as_bytes()[..20]).unwrap();
+        drop((persistence, pool));
+
+        let pool = self::pool();
+        let restored = block_on(restore_into(&pool, &path, &repository)).unwrap();
+        assert_eq!(restored, 1);
+        assert!(pool.contains(pending.tx_hash()));
+        assert_eq!(pool.pool_size().total, 1);
+    }
+
+    #[test]
+    fn compaction_rewrites_journal_from_pool() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mempool_journal.jsonl");
+        let repository = MinedNonces::default();
+        let txs: Vec<_> = (0..3).map(|_| tx(&PrivateKeySigner::random())).collect();
+
+        let pool = pool();
+        let mut persistence = persistence(&pool, &repository, &path);
+        persistence.compact();
+        for tx in &txs {
+            add(&pool, tx);
+        }
+        persistence.journal_received_events();
+        pool.remove_transactions(vec![*txs[0].tx_hash()]);
+        persistence.journal_received_events();
+        assert_eq!(journal_lines(&path), 4);
+
+        persistence.compact();
+        assert_eq!(journal_lines(&path), 2);
+        // Appending still works after compaction
+        pool.remove_transactions(vec![*txs[1].tx_hash()]);
+        persistence.journal_received_events();
+        assert_eq!(journal_lines(&path), 3);
+
+        let restored_pool = self::pool();
+        let restored = block_on(restore_into(&restored_pool, &path, &repository)).unwrap();
+        assert_eq!(restored, 1);
+        assert!(restored_pool.contains(txs[2].tx_hash()));
+    }
+}
@@ -43,6 +43,11 @@ impl<State: ReadStateHistory, Repository: ReadRepository> ZkClient<State, Reposi
             repository,
         }
     }
+
+    /// Number of the latest block in the repository.
+    pub(crate) fn latest_block_number(&self) -> u64 {
+        self.repository.get_latest_block()
+    }
 }
 
 impl<State: ReadStateHistory, Repository: ReadRepository> ChainSpecProvider
@@ -0,0 +1,344 @@
+//! Scheduled transactions.
+//!
+//! A transaction can be submitted with a [`TxSchedule`], making it includable only starting from a
+//! certain block number and/or block timestamp. Reth's pool has no notion of such conditions, so
+//! scheduled transactions are held in [`ScheduledTransactions`] (and reported as queued) until they
+//! are due in the block following the chain head. They are then promoted to the pool through the
+//! regular validation, see [`L2TransactionPool::promote_scheduled_transactions`].
+//!
+//! The next block's timestamp is only an estimate at promotion time, so
+//! [`best_transactions`](crate::best_transactions) checks the schedule of every transaction again
+//! against the block being built and skips the ones that are not due yet.
+//!
+//! [`L2TransactionPool::promote_scheduled_transactions`]: crate::L2TransactionPool::promote_scheduled_transactions
+
+use crate::transaction::L2PooledTransaction;
+use alloy::consensus::Transaction;
+use alloy::primitives::{Address, TxHash};
+use reth_transaction_pool::PoolTransaction;
+use reth_transaction_pool::error::PoolTransactionError;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Earliest block a transaction can be included in. Unset conditions are always met.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxSchedule {
+    /// The transaction can be included in this block or later.
+    pub not_before_block: Option<u64>,
+    /// The transaction can be included in blocks with this timestamp (in seconds) or later.
+    pub not_before_timestamp: Option<u64>,
+}
+
+impl TxSchedule {
+    /// Whether the transaction can be included in a block with the given number and timestamp.
+    pub fn is_due(&self, block_number: u64, block_timestamp: u64) -> bool {
+        self.not_before_block
+            .is_none_or(|not_before| block_number >= not_before)
+            && self
+                .not_before_timestamp
+                .is_none_or(|not_before| block_timestamp >= not_before)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TxScheduleConfig {
+    /// Max number of blocks between the next block and `not_before_block`.
+    pub max_blocks_ahead: u64,
+    /// Max time between the submission and `not_before_timestamp`.
+    pub max_time_ahead: Duration,
+    /// Max number of scheduled transactions waiting for promotion.
+    pub max_scheduled_transactions: usize,
+}
+
+impl Default for TxScheduleConfig {
+    fn default() -> Self {
+        Self {
+            max_blocks_ahead: 86_400,
+            max_time_ahead: Duration::from_secs(24 * 3_600),
+            max_scheduled_transactions: 10_000,
+        }
+    }
+}
+
+/// Scheduled transaction rejected at submission.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ScheduleError {
+    #[error(
+        "transaction cannot be scheduled more than {max_blocks_ahead} blocks ahead: \
+         block {not_before_block} requested, block {next_block} is next"
+    )]
+    TooManyBlocksAhead {
+        not_before_block: u64,
+        next_block: u64,
+        max_blocks_ahead: u64,
+    },
+    #[error(
+        "transaction cannot be scheduled more than {max_time_ahead:?} ahead: \
+         timestamp {not_before_timestamp} requested, current timestamp is {now}"
+    )]
+    TooFarAhead {
+        not_before_timestamp: u64,
+        now: u64,
+        max_time_ahead: Duration,
+    },
+    #[error("too many scheduled transactions")]
+    StoreFull,
+}
+
+impl PoolTransactionError for ScheduleError {
+    fn is_bad_transaction(&self) -> bool {
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Scheduled transaction that is not in the pool yet.
+#[derive(Debug, Clone)]
+pub(crate) struct ScheduledTx {
+    pub transaction: L2PooledTransaction,
+    /// Submission origin, kept for spam scoring on promotion.
+    pub origin: Option<String>,
+}
+
+/// Scheduled transactions waiting to be promoted to the pool.
+#[derive(Debug, Clone)]
+pub struct ScheduledTransactions {
+    config: Arc<TxScheduleConfig>,
+    transactions: Arc<Mutex<Vec<ScheduledTx>>>,
+}
+
+impl ScheduledTransactions {
+    pub fn new(config: TxScheduleConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            transactions: Arc::default(),
+        }
+    }
+
+    /// Checks that `schedule` is within the configured horizon from the next block and `now`.
+    pub(crate) fn check(
+        &self,
+        schedule: &TxSchedule,
+        next_block: u64,
+        now: u64,
+    ) -> Result<(), ScheduleError> {
+        let max_blocks_ahead = self.config.max_blocks_ahead;
+        if let Some(not_before_block) = schedule.not_before_block
+            && not_before_block > next_block.saturating_add(max_blocks_ahead)
+        {
+            return Err(ScheduleError::TooManyBlocksAhead {
+                not_before_block,
+                next_block,
+                max_blocks_ahead,
+            });
+        }
+        let max_time_ahead = self.config.max_time_ahead;
+        if let Some(not_before_timestamp) = schedule.not_before_timestamp
+            && not_before_timestamp > now.saturating_add(max_time_ahead.as_secs())
+        {
+            return Err(ScheduleError::TooFarAhead {
+                not_before_timestamp,
+                now,
+                max_time_ahead,
+            });
+        }
+        Ok(())
+    }
+
+    /// Holds `transaction` until it's due. A scheduled transaction with the same sender and nonce
+    /// is replaced.
+    pub(crate) fn insert(
+        &self,
+        transaction: L2PooledTransaction,
+        origin: Option<&str>,
+    ) -> Result<(), ScheduleError> {
+        let mut transactions = self.transactions.lock().unwrap();
+        let (sender, nonce) = (transaction.sender(), transaction.nonce());
+        transactions
+            .retain(|tx| tx.transaction.sender() != sender || tx.transaction.nonce() != nonce);
+        if transactions.len() >= self.config.max_scheduled_transactions {
+            return Err(ScheduleError::StoreFull);
+        }
+        transactions.push(ScheduledTx {
+            transaction,
+            origin: origin.map(str::to_owned),
+        });
+        Ok(())
+    }
+
+    /// Removes and returns transactions that are due in a block with the given number and
+    /// timestamp, in submission order.
+    pub(crate) fn take_due(&self, block_number: u64, block_timestamp: u64) -> Vec<ScheduledTx> {
+        let mut transactions = self.transactions.lock().unwrap();
+        let (due, pending) = std::mem::take(&mut *transactions)
+            .into_iter()
+            .partition(|tx| {
+                tx.transaction
+                    .schedule
+                    .is_none_or(|schedule| schedule.is_due(block_number, block_timestamp))
+            });
+        *transactions = pending;
+        due
+    }
+
+    /// Runs `f` on scheduled transactions in submission order.
+    pub(crate) fn with_transactions<R>(&self, f: impl FnOnce(&[ScheduledTx]) -> R) -> R {
+        f(&self.transactions.lock().unwrap())
+    }
+
+    /// Scheduled transactions of `sender` that are not in the pool yet.
+    pub fn by_sender(&self, sender: Address) -> Vec<L2PooledTransaction> {
+        self.transactions
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|tx| tx.transaction.sender() == sender)
+            .map(|tx| tx.transaction.clone())
+            .collect()
+    }
+
+    pub fn contains(&self, hash: &TxHash) -> bool {
+        self.transactions
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|tx| tx.transaction.hash() == hash)
+    }
+
+    pub fn len(&self) -> usize {
+        self.transactions.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Current Unix timestamp in seconds.
+pub(crate) fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::consensus::transaction::Recovered;
+    use alloy::consensus::{SignableTransaction, TxEip1559};
+    use alloy::primitives::Signature;
+    use zksync_os_types::L2Envelope;
+
+    fn config() -> TxScheduleConfig {
+        TxScheduleConfig {
+            max_blocks_ahead: 100,
+            max_time_ahead: Duration::from_secs(3_600),
+            max_scheduled_transactions: 2,
+        }
+    }
+
+    fn scheduled_tx(nonce: u64, schedule: TxSchedule) -> L2PooledTransaction {
+        let tx = TxEip1559 {
+            nonce,
+            ..TxEip1559::default()
+        };
+        let envelope = L2Envelope::from(tx.into_signed(Signature::test_signature()));
+        L2PooledTransaction::from_pooled(Recovered::new_unchecked(
+            envelope,
+            Address::repeat_byte(1),
+        ))
+        .with_schedule(schedule)
+    }
+
+    fn at_block(block: u64) -> TxSchedule {
+        TxSchedule {
+            not_before_block: Some(block),
+            not_before_timestamp: None,
+        }
+    }
+
+    fn at_timestamp(timestamp: u64) -> TxSchedule {
+        TxSchedule {
+            not_before_block: None,
+            not_before_timestamp: Some(timestamp),
+        }
+    }
+
+    fn nonces(txs: Vec<ScheduledTx>) -> Vec<u64> {
+        txs.iter().map(|tx| tx.transaction.nonce()).collect()
+    }
+
+    #[test]
+    fn promoted_exactly_at_boundary() {
+        let store = ScheduledTransactions::new(config());
+        store.insert(scheduled_tx(0, at_block(10)), None).unwrap();
+        store
+            .insert(scheduled_tx(1, at_timestamp(1_000)), Some("origin"))
+            .unwrap();
+
+        assert!(store.take_due(9, 999).is_empty());
+        assert_eq!(nonces(store.take_due(10, 999)), [0]);
+        assert_eq!(store.len(), 1);
+        let due = store.take_due(10, 1_000);
+        assert_eq!(due[0].origin.as_deref(), Some("origin"));
+        assert_eq!(nonces(due), [1]);
+        assert!(store.is_empty());
+
+        // Both conditions must be met
+        let schedule = TxSchedule {
+            not_before_block: Some(10),
+            not_before_timestamp: Some(1_000),
+        };
+        assert!(!schedule.is_due(10, 999));
+        assert!(!schedule.is_due(9, 1_000));
+        assert!(schedule.is_due(10, 1_000));
+        assert!(TxSchedule::default().is_due(0, 0));
+    }
+
+    #[test]
+    fn conditions_beyond_cap_are_rejected() {
+        let store = ScheduledTransactions::new(config());
+        store.check(&at_block(110), 10, 0).unwrap();
+        assert_eq!(
+            store.check(&at_block(111), 10, 0),
+            Err(ScheduleError::TooManyBlocksAhead {
+                not_before_block: 111,
+                next_block: 10,
+                max_blocks_ahead: 100,
+            })
+        );
+        store.check(&at_timestamp(4_600), 10, 1_000).unwrap();
+        assert_eq!(
+            store.check(&at_timestamp(4_601), 10, 1_000),
+            Err(ScheduleError::TooFarAhead {
+                not_before_timestamp: 4_601,
+                now: 1_000,
+                max_time_ahead: Duration::from_secs(3_600),
+            })
+        );
+    }
+
+    #[test]
+    fn store_capacity() {
+        let store = ScheduledTransactions::new(config());
+        store.insert(scheduled_tx(0, at_block(10)), None).unwrap();
+        store.insert(scheduled_tx(1, at_block(10)), None).unwrap();
+        // Replacements don't take extra space
+        let replacement = scheduled_tx(1, at_block(20));
+        store.insert(replacement.clone(), None).unwrap();
+        assert!(store.contains(replacement.hash()));
+        assert_eq!(
+            store.insert(scheduled_tx(2, at_block(10)), None),
+            Err(ScheduleError::StoreFull)
+        );
+        assert_eq!(store.by_sender(Address::repeat_byte(1)).len(), 2);
+        assert!(store.by_sender(Address::repeat_byte(2)).is_empty());
+    }
+}
@@ -0,0 +1,490 @@
+//! Spam scoring of mempool submissions.
+//!
+//! Every sender, and every submission origin tagged by the RPC layer (e.g. the client IP), has a
+//! score that grows on abusive events - replacing pooled transactions, submitting transactions
+//! that fail validation, having transactions evicted - and decays exponentially over time.
+//! Transactions of senders scoring at least [`SpamScoringConfig::deprioritize_score`] are yielded
+//! after all other transactions by [`best_transactions`](crate::best_transactions); submissions
+//! from senders or origins scoring at least [`SpamScoringConfig::reject_score`] are rejected with
+//! [`SpamRejection`] until their score decays.
+//!
+//! Scores are persisted to a file (see [`SpamScores::run_persist_loop`]), so that restarting the
+//! node doesn't give known spammers a clean slate.
+
+use crate::metrics::MEMPOOL_METRICS;
+use alloy::primitives::Address;
+use anyhow::Context;
+use reth_transaction_pool::error::PoolTransactionError;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use vise::EncodeLabelValue;
+
+/// Scores below this value are dropped rather than persisted.
+const NEGLIGIBLE_SCORE: f64 = 0.01;
+
+/// Event increasing the spam score of a sender (and of the submission origin, if known).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EncodeLabelValue)]
+#[metrics(label = "event", rename_all = "snake_case")]
+pub enum SpamEvent {
+    /// A pooled transaction was replaced by one with the same nonce.
+    Replacement,
+    /// A submitted transaction failed validation.
+    ValidationFailure,
+    /// A pooled transaction was evicted without being included.
+    Eviction,
+}
+
+impl SpamEvent {
+    fn weight(self) -> f64 {
+        match self {
+            Self::Replacement | Self::ValidationFailure => 1.0,
+            // Evicted transactions occupied the pool until the very end
+            Self::Eviction => 2.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SpamScoringConfig {
+    /// Score starting from which the sender's transactions are yielded after all other ones.
+    pub deprioritize_score: f64,
+    /// Score starting from which new submissions from the sender or origin are rejected.
+    pub reject_score: f64,
+    /// Time it takes for a score to halve.
+    pub half_life: Duration,
+    /// Max number of origins scores are tracked for. Once reached, tracking a new origin drops
+    /// the lowest-scoring one, so that rotating origins cannot grow the scores unboundedly.
+    pub max_origins: usize,
+}
+
+impl Default for SpamScoringConfig {
+    fn default() -> Self {
+        Self {
+            deprioritize_score: 10.0,
+            reject_score: 50.0,
+            half_life: Duration::from_secs(600),
+            max_origins: 100_000,
+        }
+    }
+}
+
+/// Entity a spam score is tracked for.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpamSource {
+    Sender(Address),
+    /// Tag supplied by the RPC layer with the submission, e.g. the client IP.
+    Origin(String),
+}
+
+impl fmt::Display for SpamSource {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sender(address) => write!(formatter, "sender {address}"),
+            Self::Origin(origin) => write!(formatter, "origin {origin}"),
+        }
+    }
+}
+
+/// Submission rejected because its sender or origin has a spam score above the hard limit.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error(
+    "{offender} is temporarily not allowed to submit transactions: spam score {score:.1} reached {limit}"
+)]
+pub struct SpamRejection {
+    pub offender: SpamSource,
+    pub score: f64,
+    pub limit: f64,
+}
+
+impl PoolTransactionError for SpamRejection {
+    fn is_bad_transaction(&self) -> bool {
+        // The transaction itself may be fine; it's the submitter who is throttled
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Score as of `updated_at_ms` (Unix timestamp in milliseconds).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Score {
+    value: f64,
+    updated_at_ms: u64,
+}
+
+impl Score {
+    fn decayed(self, now_ms: u64, half_life: Duration) -> f64 {
+        let elapsed = now_ms.saturating_sub(self.updated_at_ms) as f64;
+        self.value * 0.5_f64.powf(elapsed / half_life.as_millis().max(1) as f64)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PersistedScores {
+    scores: Vec<(SpamSource, Score)>,
+}
+
+/// Decaying spam scores shared between the mempool and the task persisting them.
+#[derive(Debug, Clone)]
+pub struct SpamScores {
+    config: Arc<SpamScoringConfig>,
+    /// File scores are persisted to; `None` if they are kept in memory only.
+    path: Option<Arc<PathBuf>>,
+    scores: Arc<Mutex<HashMap<SpamSource, Score>>>,
+}
+
+impl SpamScores {
+    /// Creates in-memory scores that are lost on restart.
+    pub fn new(config: SpamScoringConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            path: None,
+            scores: Arc::default(),
+        }
+    }
+
+    /// Loads scores persisted at `path`, if any. Scores are saved back to `path` by
+    /// [`Self::save`].
+    pub fn open(path: &Path, config: SpamScoringConfig) -> anyhow::Result<Self> {
+        let scores = match std::fs::read(path) {
+            Ok(bytes) => {
+                let persisted: PersistedScores = serde_json::from_slice(&bytes)
+                    .with_context(|| format!("corrupted spam scores `{}`", path.display()))?;
+                persisted.scores.into_iter().collect()
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("cannot read spam scores `{}`", path.display()));
+            }
+        };
+        tracing::info!(
+            path = %path.display(),
+            sources = scores.len(),
+            "loaded mempool spam scores"
+        );
+        Ok(Self {
+            config: Arc::new(config),
+            path: Some(Arc::new(path.to_owned())),
+            scores: Arc::new(Mutex::new(scores)),
+        })
+    }
+
+    /// Records `event` against `sender` and, if known, the submission `origin`.
+    pub fn record(&self, sender: Address, origin: Option<&str>, event: SpamEvent) {
+        self.record_at(sender, origin, event, unix_millis());
+    }
+
+    fn record_at(&self, sender: Address, origin: Option<&str>, event: SpamEvent, now_ms: u64) {
+        MEMPOOL_METRICS.spam_events[&event].inc();
+        let mut scores = self.scores.lock().unwrap();
+        let sources = std::iter::once(SpamSource::Sender(sender))
+            .chain(origin.map(|origin| SpamSource::Origin(origin.to_owned())));
+        for source in sources {
+            if matches!(source, SpamSource::Origin(_)) && !scores.contains_key(&source) {
+                self.make_room_for_origin(&mut scores, now_ms);
+            }
+            let score = scores.entry(source).or_insert(Score {
+                value: 0.0,
+                updated_at_ms: now_ms,
+            });
+            *score = Score {
+                value: score.decayed(now_ms, self.config.half_life) + event.weight(),
+                updated_at_ms: now_ms,
+            };
+        }
+    }
+
+    /// Drops the lowest-scoring origin if the max number of origins is tracked.
+    fn make_room_for_origin(&self, scores: &mut HashMap<SpamSource, Score>, now_ms: u64) {
+        // Cheap check first; senders are tracked in the same map
+        if scores.len() < self.config.max_origins {
+            return;
+        }
+        let origins = scores
+            .iter()
+            .filter(|(source, _)| matches!(source, SpamSource::Origin(_)));
+        if origins.clone().count() < self.config.max_origins {
+            return;
+        }
+        let lowest = origins
+            .min_by(|(_, a), (_, b)| {
+                let a = a.decayed(now_ms, self.config.half_life);
+                a.total_cmp(&b.decayed(now_ms, self.config.half_life))
+            })
+            .map(|(source, _)| source.clone());
+        if let Some(lowest) = lowest {
+            scores.remove(&lowest);
+        }
+    }
+
+    /// Current score of `source`.
+    pub fn score(&self, source: &SpamSource) -> f64 {
+        self.score_at(source, unix_millis())
+    }
+
+    fn score_at(&self, source: &SpamSource, now_ms: u64) -> f64 {
+        let scores = self.scores.lock().unwrap();
+        scores
+            .get(source)
+            .map_or(0.0, |score| score.decayed(now_ms, self.config.half_life))
+    }
+
+    /// Checks whether a new submission from `sender` and `origin` may be accepted.
+    pub fn check(&self, sender: Address, origin: Option<&str>) -> Result<(), SpamRejection> {
+        self.check_at(sender, origin, unix_millis())
+    }
+
+    fn check_at(
+        &self,
+        sender: Address,
+        origin: Option<&str>,
+        now_ms: u64,
+    ) -> Result<(), SpamRejection> {
+        let limit = self.config.reject_score;
+        let sources = std::iter::once(SpamSource::Sender(sender))
+            .chain(origin.map(|origin| SpamSource::Origin(origin.to_owned())));
+        for source in sources {
+            let score = self.score_at(&source, now_ms);
+            if score >= limit {
+                MEMPOOL_METRICS.spam_rejections.inc();
+                return Err(SpamRejection {
+                    offender: source,
+                    score,
+                    limit,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Senders whose transactions should be yielded after all other ones.
+    pub fn deprioritized_senders(&self) -> HashSet<Address> {
+        self.deprioritized_senders_at(unix_millis())
+    }
+
+    fn deprioritized_senders_at(&self, now_ms: u64) -> HashSet<Address> {
+        let scores = self.scores.lock().unwrap();
+        let senders: HashSet<_> = scores
+            .iter()
+            .filter_map(|(source, score)| match source {
+                SpamSource::Sender(sender)
+                    if score.decayed(now_ms, self.config.half_life)
+                        >= self.config.deprioritize_score =>
+                {
+                    Some(*sender)
+                }
+                _ => None,
+            })
+            .collect();
+        MEMPOOL_METRICS
+            .spam_deprioritized_senders
+            .set(senders.len());
+        senders
+    }
+
+    /// Returns up to `limit` senders with the highest current scores, highest first. Origins are
+    /// not included, since they may identify clients.
+    pub fn top_senders(&self, limit: usize) -> Vec<(Address, f64)> {
+        self.top_senders_at(limit, unix_millis())
+    }
+
+    fn top_senders_at(&self, limit: usize, now_ms: u64) -> Vec<(Address, f64)> {
+        let scores = self.scores.lock().unwrap();
+        let mut top: Vec<_> = scores
+            .iter()
+            .filter_map(|(source, score)| match source {
+                SpamSource::Sender(sender) => {
+                    Some((*sender, score.decayed(now_ms, self.config.half_life)))
+                }
+                SpamSource::Origin(_) => None,
+            })
+            .filter(|(_, score)| *score >= NEGLIGIBLE_SCORE)
+            .collect();
+        top.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        top.truncate(limit);
+        top
+    }
+
+    /// Drops negligible scores and writes the remaining ones to the file the scores were
+    /// [opened](Self::open) from. No-op for in-memory scores.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let now_ms = unix_millis();
+        let persisted = {
+            let mut scores = self.scores.lock().unwrap();
+            scores.retain(|_, score| {
+                score.decayed(now_ms, self.config.half_life) >= NEGLIGIBLE_SCORE
+            });
+            PersistedScores {
+                scores: scores
+                    .iter()
+                    .map(|(source, score)| (source.clone(), *score))
+                    .collect(),
+            }
+        };
+        // Write to a temporary file first, so that a crash doesn't leave a truncated file behind
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec(&persisted)?)
+            .with_context(|| format!("cannot write `{}`", tmp_path.display()))?;
+        std::fs::rename(&tmp_path, path.as_ref())
+            .with_context(|| format!("cannot replace `{}`", path.display()))?;
+        Ok(())
+    }
+
+    /// Saves scores every `period`. Never returns.
+    pub async fn run_persist_loop(self, period: Duration) {
+        let mut timer = tokio::time::interval(period);
+        loop {
+            timer.tick().await;
+            if let Err(err) = self.save() {
+                tracing::warn!("failed to persist mempool spam scores: {err:#}");
+            }
+        }
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time is before Unix epoch")
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE_MS: u64 = 60_000;
+
+    fn scores() -> SpamScores {
+        SpamScores::new(SpamScoringConfig {
+            deprioritize_score: 3.0,
+            reject_score: 5.0,
+            half_life: Duration::from_secs(60),
+            max_origins: 3,
+        })
+    }
+
+    #[test]
+    fn events_accumulate_per_sender_and_origin() {
+        let scores = scores();
+        let (alice, bob) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        scores.record_at(alice, Some("10.0.0.1"), SpamEvent::Replacement, 0);
+        scores.record_at(alice, None, SpamEvent::ValidationFailure, 0);
+        scores.record_at(bob, Some("10.0.0.1"), SpamEvent::Eviction, 0);
+        scores.record_at(bob, None, SpamEvent::Replacement, 0);
+
+        assert_eq!(scores.score_at(&SpamSource::Sender(alice), 0), 2.0);
+        assert_eq!(scores.score_at(&SpamSource::Sender(bob), 0), 3.0);
+        let origin = SpamSource::Origin("10.0.0.1".to_owned());
+        assert_eq!(scores.score_at(&origin, 0), 3.0);
+        assert_eq!(scores.top_senders_at(1, 0), [(bob, 3.0)]);
+        assert_eq!(scores.top_senders_at(10, 0), [(bob, 3.0), (alice, 2.0)]);
+    }
+
+    #[test]
+    fn deprioritization_and_rejection_thresholds() {
+        let scores = scores();
+        let sender = Address::repeat_byte(1);
+        for _ in 0..3 {
+            scores.record_at(sender, None, SpamEvent::Replacement, 0);
+        }
+        assert_eq!(scores.deprioritized_senders_at(0), HashSet::from([sender]));
+        scores.check_at(sender, None, 0).unwrap();
+
+        scores.record_at(sender, None, SpamEvent::Eviction, 0);
+        let err = scores.check_at(sender, None, 0).unwrap_err();
+        assert_eq!(err.offender, SpamSource::Sender(sender));
+        assert_eq!(err.score, 5.0);
+
+        // Other senders submitting from the same origin are only rejected once the origin itself
+        // accumulates enough score
+        let other_sender = Address::repeat_byte(2);
+        scores.check_at(other_sender, Some("10.0.0.1"), 0).unwrap();
+        for i in 0..5 {
+            let sender = Address::with_last_byte(10 + i);
+            scores.record_at(sender, Some("10.0.0.1"), SpamEvent::ValidationFailure, 0);
+        }
+        let err = scores
+            .check_at(other_sender, Some("10.0.0.1"), 0)
+            .unwrap_err();
+        assert_eq!(err.offender, SpamSource::Origin("10.0.0.1".to_owned()));
+    }
+
+    #[test]
+    fn scores_decay_back_to_normal() {
+        let scores = scores();
+        let sender = Address::repeat_byte(1);
+        for _ in 0..8 {
+            scores.record_at(sender, None, SpamEvent::ValidationFailure, 0);
+        }
+        scores.check_at(sender, None, 0).unwrap_err();
+
+        // One half-life: 8 -> 4, accepted again but still deprioritized
+        scores.check_at(sender, None, MINUTE_MS).unwrap();
+        assert!(scores.deprioritized_senders_at(MINUTE_MS).contains(&sender));
+        // Two half-lives: 8 -> 2, back to normal
+        assert!(scores.deprioritized_senders_at(2 * MINUTE_MS).is_empty());
+
+        // New events add to the decayed score
+        scores.record_at(sender, None, SpamEvent::Eviction, 2 * MINUTE_MS);
+        assert_eq!(
+            scores.score_at(&SpamSource::Sender(sender), 2 * MINUTE_MS),
+            4.0
+        );
+        assert!(scores.top_senders_at(10, 100 * MINUTE_MS).is_empty());
+    }
+
+    #[test]
+    fn lowest_scoring_origins_are_dropped() {
+        let scores = scores();
+        let sender = Address::repeat_byte(1);
+        for (i, origin) in ["10.0.0.1", "10.0.0.2", "10.0.0.3"].into_iter().enumerate() {
+            for _ in 0..=i {
+                scores.record_at(sender, Some(origin), SpamEvent::Replacement, 0);
+            }
+        }
+        scores.record_at(sender, Some("10.0.0.4"), SpamEvent::Replacement, 0);
+
+        let origin_score = |origin: &str| scores.score_at(&SpamSource::Origin(origin.into()), 0);
+        assert_eq!(origin_score("10.0.0.1"), 0.0);
+        assert_eq!(origin_score("10.0.0.2"), 2.0);
+        assert_eq!(origin_score("10.0.0.3"), 3.0);
+        assert_eq!(origin_score("10.0.0.4"), 1.0);
+        // Senders are not affected
+        assert_eq!(scores.score_at(&SpamSource::Sender(sender), 0), 7.0);
+    }
+
+    #[test]
+    fn scores_survive_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("spam_scores.json");
+        let sender = Address::repeat_byte(1);
+
+        let scores = SpamScores::open(&path, SpamScoringConfig::default()).unwrap();
+        for _ in 0..3 {
+            scores.record(sender, Some("10.0.0.1"), SpamEvent::Eviction);
+        }
+        scores.save().unwrap();
+
+        let reopened = SpamScores::open(&path, SpamScoringConfig::default()).unwrap();
+        for source in [
+            SpamSource::Sender(sender),
+            SpamSource::Origin("10.0.0.1".into()),
+        ] {
+            let score = reopened.score(&source);
+            assert!((5.9..=6.0).contains(&score), "{source}: {score}");
+        }
+    }
+}
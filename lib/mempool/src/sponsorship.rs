@@ -0,0 +1,599 @@
+//! Sponsored transactions.
+//!
+//! A transaction can be submitted with a [`SponsorshipVoucher`] signed by a registered sponsor,
+//! who then covers the transaction's fee in place of the sender (the sender still pays the
+//! transferred value). Sponsors are registered in [`SponsorshipConfig`] along with a budget: the
+//! max amount of fees they can be charged within a rolling window.
+//!
+//! At submission, the voucher is checked against the transaction and the sponsor's balance and
+//! remaining budget, see [`L2TransactionPool::add_sponsored_l2_transaction`]. Budgets are not
+//! reserved for pooled transactions; instead, [`best_transactions`](crate::best_transactions)
+//! charges a sponsor's budget for every sponsored transaction it yields and skips transactions
+//! once the budget is exhausted. Each voucher is charged at most once and cannot be submitted
+//! again after that.
+//!
+//! The actual fee is only known after execution, so budgets are charged the max fee a transaction
+//! can cost (`gas_limit * max_fee_per_gas`).
+//!
+//! Sponsored transactions can only be executed by versions supporting
+//! [sponsored fee payment](zksync_os_multivm::ExecutionVersion::supports_sponsored_fee_payment);
+//! otherwise, they are rejected at submission and skipped by block building.
+//!
+//! [`L2TransactionPool::add_sponsored_l2_transaction`]: crate::L2TransactionPool::add_sponsored_l2_transaction
+
+use crate::metrics::{MEMPOOL_METRICS, SponsorshipRejectionReason};
+use alloy::consensus::Transaction;
+use alloy::primitives::{Address, B256, Signature, SignatureError, TxHash, U256, keccak256};
+use reth_transaction_pool::error::PoolTransactionError;
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use zksync_os_multivm::ExecutionVersion;
+
+/// Sponsor's commitment to pay the fee of a specific transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SponsorshipVoucher {
+    pub sponsor: Address,
+    /// Max fee (in wei) covered by the sponsor. Must be at least the max fee the transaction can
+    /// cost, i.e. `gas_limit * max_fee_per_gas`.
+    pub max_fee: u128,
+    /// Unix timestamp (in seconds) after which the voucher can no longer be included.
+    pub expiry: u64,
+    /// Sponsor's signature over [`Self::signing_hash`].
+    pub signature: Signature,
+}
+
+impl SponsorshipVoucher {
+    /// Domain separator preventing voucher signatures from being valid for other payloads.
+    const DOMAIN: &'static [u8] = b"zksync-os:sponsorship:v1";
+
+    /// Hash signed by the sponsor: keccak256 of the domain separator followed by the hash of the
+    /// sponsored transaction and tightly packed terms (integers are big-endian).
+    pub fn signing_hash(&self, tx_hash: TxHash) -> B256 {
+        let mut payload = Self::DOMAIN.to_vec();
+        payload.extend_from_slice(tx_hash.as_slice());
+        payload.extend_from_slice(self.sponsor.as_slice());
+        payload.extend_from_slice(&self.max_fee.to_be_bytes());
+        payload.extend_from_slice(&self.expiry.to_be_bytes());
+        keccak256(payload)
+    }
+
+    /// Recovers the signer address for the transaction `tx_hash`; it must match `sponsor`.
+    pub fn recover_signer(&self, tx_hash: TxHash) -> Result<Address, SignatureError> {
+        self.signature
+            .recover_address_from_prehash(&self.signing_hash(tx_hash))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SponsorshipConfig {
+    /// Registered sponsors with the max amount of fees (in wei) they can be charged within
+    /// `budget_window`.
+    pub budgets: HashMap<Address, u128>,
+    /// Rolling window sponsor budgets apply to.
+    pub budget_window: Duration,
+    /// Max time between the submission and the voucher's expiry.
+    pub max_voucher_lifetime: Duration,
+}
+
+impl Default for SponsorshipConfig {
+    fn default() -> Self {
+        Self {
+            budgets: HashMap::new(),
+            budget_window: Duration::from_secs(24 * 3_600),
+            max_voucher_lifetime: Duration::from_secs(3_600),
+        }
+    }
+}
+
+/// Sponsored transaction rejected at submission or skipped by block building.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum SponsorshipError {
+    #[error("sponsored fee payment is not supported by execution version {0:?}")]
+    Unsupported(ExecutionVersion),
+    #[error("{0} is not a registered sponsor")]
+    UnknownSponsor(Address),
+    #[error("voucher is not signed by sponsor {0}")]
+    InvalidSignature(Address),
+    #[error("voucher expired at {expiry}, current timestamp is {now}")]
+    Expired { expiry: u64, now: u64 },
+    #[error(
+        "voucher cannot expire more than {max_lifetime:?} ahead: \
+         expiry {expiry} requested, current timestamp is {now}"
+    )]
+    ExpiryTooFar {
+        expiry: u64,
+        now: u64,
+        max_lifetime: Duration,
+    },
+    #[error("voucher covers a fee of up to {max_fee} wei, but the transaction can cost {fee} wei")]
+    FeeNotCovered { max_fee: u128, fee: u128 },
+    #[error(
+        "sponsor {sponsor} has {remaining} wei of its budget left, \
+         but the transaction can cost {fee} wei"
+    )]
+    BudgetExhausted {
+        sponsor: Address,
+        remaining: u128,
+        fee: u128,
+    },
+    #[error("sponsor {sponsor} balance of {balance} wei doesn't cover the fee of {fee} wei")]
+    InsufficientSponsorBalance {
+        sponsor: Address,
+        balance: U256,
+        fee: u128,
+    },
+    #[error("voucher for transaction {0} was already used")]
+    AlreadyUsed(TxHash),
+}
+
+impl SponsorshipError {
+    pub(crate) fn reason(&self) -> SponsorshipRejectionReason {
+        match self {
+            Self::Unsupported(_) => SponsorshipRejectionReason::Unsupported,
+            Self::UnknownSponsor(_) => SponsorshipRejectionReason::UnknownSponsor,
+            Self::InvalidSignature(_) => SponsorshipRejectionReason::InvalidSignature,
+            Self::Expired { .. } | Self::ExpiryTooFar { .. } => SponsorshipRejectionReason::Expiry,
+            Self::FeeNotCovered { .. } => SponsorshipRejectionReason::FeeNotCovered,
+            Self::BudgetExhausted { .. } => SponsorshipRejectionReason::BudgetExhausted,
+            Self::InsufficientSponsorBalance { .. } => {
+                SponsorshipRejectionReason::InsufficientSponsorBalance
+            }
+            Self::AlreadyUsed(_) => SponsorshipRejectionReason::AlreadyUsed,
+        }
+    }
+}
+
+impl PoolTransactionError for SponsorshipError {
+    fn is_bad_transaction(&self) -> bool {
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Max fee `tx` can cost, which is charged to its sponsor.
+pub(crate) fn sponsored_fee(tx: &impl Transaction) -> u128 {
+    tx.max_fee_per_gas().saturating_mul(tx.gas_limit() as u128)
+}
+
+/// Fee charged to a sponsor.
+#[derive(Debug, Clone, Copy)]
+struct Charge {
+    tx_hash: TxHash,
+    /// Block timestamp the charge was made at.
+    timestamp: u64,
+    fee: u128,
+}
+
+#[derive(Debug, Default)]
+struct SponsorLedger {
+    /// Charges within the budget window by sponsor, oldest first.
+    charges: HashMap<Address, VecDeque<Charge>>,
+    /// Expiry of used vouchers by transaction hash. Kept until the vouchers expire, after which
+    /// they are rejected anyway.
+    used_vouchers: HashMap<TxHash, u64>,
+}
+
+impl SponsorLedger {
+    /// Drops charges outside the budget window and expired vouchers.
+    fn prune(&mut self, now: u64, budget_window: Duration) {
+        let window_start = now.saturating_sub(budget_window.as_secs());
+        self.charges.retain(|_, charges| {
+            while charges
+                .front()
+                .is_some_and(|charge| charge.timestamp < window_start)
+            {
+                charges.pop_front();
+            }
+            !charges.is_empty()
+        });
+        self.used_vouchers.retain(|_, expiry| *expiry >= now);
+    }
+
+    fn spent(&self, sponsor: Address) -> u128 {
+        self.charges
+            .get(&sponsor)
+            .map_or(0, |charges| charges.iter().map(|charge| charge.fee).sum())
+    }
+}
+
+/// Registered sponsors and fees charged to them.
+#[derive(Debug, Clone)]
+pub struct Sponsorships {
+    config: Arc<SponsorshipConfig>,
+    ledger: Arc<Mutex<SponsorLedger>>,
+}
+
+impl Sponsorships {
+    pub fn new(config: SponsorshipConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            ledger: Arc::default(),
+        }
+    }
+
+    /// Budget `sponsor` has left within the current window, or `None` if it's not registered.
+    pub fn remaining_budget(&self, sponsor: Address, now: u64) -> Option<u128> {
+        let budget = *self.config.budgets.get(&sponsor)?;
+        let mut ledger = self.ledger.lock().unwrap();
+        ledger.prune(now, self.config.budget_window);
+        Some(budget.saturating_sub(ledger.spent(sponsor)))
+    }
+
+    /// Checks a voucher submitted for the transaction `tx_hash` with the max fee of `fee`.
+    /// `sponsor_balance` is the sponsor's balance as of the latest block.
+    pub(crate) fn check(
+        &self,
+        tx_hash: TxHash,
+        fee: u128,
+        voucher: &SponsorshipVoucher,
+        sponsor_balance: U256,
+        now: u64,
+    ) -> Result<(), SponsorshipError> {
+        let sponsor = voucher.sponsor;
+        if !self.config.budgets.contains_key(&sponsor) {
+            return Err(SponsorshipError::UnknownSponsor(sponsor));
+        }
+        if voucher.recover_signer(tx_hash).ok() != Some(sponsor) {
+            return Err(SponsorshipError::InvalidSignature(sponsor));
+        }
+        let max_lifetime = self.config.max_voucher_lifetime;
+        if voucher.expiry > now.saturating_add(max_lifetime.as_secs()) {
+            return Err(SponsorshipError::ExpiryTooFar {
+                expiry: voucher.expiry,
+                now,
+                max_lifetime,
+            });
+        }
+        if voucher.max_fee < fee {
+            return Err(SponsorshipError::FeeNotCovered {
+                max_fee: voucher.max_fee,
+                fee,
+            });
+        }
+        if sponsor_balance < U256::from(fee) {
+            return Err(SponsorshipError::InsufficientSponsorBalance {
+                sponsor,
+                balance: sponsor_balance,
+                fee,
+            });
+        }
+        let mut ledger = self.ledger.lock().unwrap();
+        ledger.prune(now, self.config.budget_window);
+        check_expiry(voucher, now)?;
+        if ledger.used_vouchers.contains_key(&tx_hash) {
+            return Err(SponsorshipError::AlreadyUsed(tx_hash));
+        }
+        self.check_budget(&ledger, fee, voucher)
+    }
+
+    /// Charges the sponsor of the transaction `tx_hash` included in a block with the timestamp
+    /// `now`. Fails if the voucher is expired or if the sponsor's budget doesn't cover `fee`.
+    /// A voucher is charged at most once, even if the transaction is yielded again because a
+    /// previous block didn't include it.
+    pub(crate) fn charge(
+        &self,
+        tx_hash: TxHash,
+        fee: u128,
+        voucher: &SponsorshipVoucher,
+        now: u64,
+    ) -> Result<(), SponsorshipError> {
+        let mut ledger = self.ledger.lock().unwrap();
+        ledger.prune(now, self.config.budget_window);
+        check_expiry(voucher, now)?;
+        if ledger.used_vouchers.contains_key(&tx_hash) {
+            return Ok(());
+        }
+        self.check_budget(&ledger, fee, voucher)?;
+        ledger
+            .charges
+            .entry(voucher.sponsor)
+            .or_default()
+            .push_back(Charge {
+                tx_hash,
+                timestamp: now,
+                fee,
+            });
+        ledger.used_vouchers.insert(tx_hash, voucher.expiry);
+        MEMPOOL_METRICS.sponsored_transactions_charged.inc();
+        Ok(())
+    }
+
+    /// Reverts the charge for the transaction `tx_hash` (e.g. if it turned out to be invalid
+    /// during execution), allowing its voucher to be used again.
+    pub(crate) fn refund(&self, tx_hash: TxHash) {
+        let mut ledger = self.ledger.lock().unwrap();
+        if ledger.used_vouchers.remove(&tx_hash).is_none() {
+            return;
+        }
+        ledger.charges.retain(|_, charges| {
+            charges.retain(|charge| charge.tx_hash != tx_hash);
+            !charges.is_empty()
+        });
+    }
+
+    fn check_budget(
+        &self,
+        ledger: &SponsorLedger,
+        fee: u128,
+        voucher: &SponsorshipVoucher,
+    ) -> Result<(), SponsorshipError> {
+        let sponsor = voucher.sponsor;
+        let budget = self
+            .config
+            .budgets
+            .get(&sponsor)
+            .copied()
+            .ok_or(SponsorshipError::UnknownSponsor(sponsor))?;
+        let remaining = budget.saturating_sub(ledger.spent(sponsor));
+        if remaining < fee {
+            return Err(SponsorshipError::BudgetExhausted {
+                sponsor,
+                remaining,
+                fee,
+            });
+        }
+        Ok(())
+    }
+}
+
+fn check_expiry(voucher: &SponsorshipVoucher, now: u64) -> Result<(), SponsorshipError> {
+    if voucher.expiry < now {
+        return Err(SponsorshipError::Expired {
+            expiry: voucher.expiry,
+            now,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::SignerSync;
+    use alloy::signers::local::PrivateKeySigner;
+
+    const NOW: u64 = 1_000_000;
+    const FEE: u128 = 1_000;
+
+    fn config(sponsor: Address, budget: u128) -> SponsorshipConfig {
+        SponsorshipConfig {
+            budgets: HashMap::from([(sponsor, budget)]),
+            budget_window: Duration::from_secs(100),
+            max_voucher_lifetime: Duration::from_secs(60),
+        }
+    }
+
+    fn voucher(signer: &PrivateKeySigner, tx_hash: TxHash, expiry: u64) -> SponsorshipVoucher {
+        let mut voucher = SponsorshipVoucher {
+            sponsor: signer.address(),
+            max_fee: FEE,
+            expiry,
+            signature: Signature::test_signature(),
+        };
+        voucher.signature = signer
+            .sign_hash_sync(&voucher.signing_hash(tx_hash))
+            .unwrap();
+        voucher
+    }
+
+    fn balance(amount: u128) -> U256 {
+        U256::from(amount)
+    }
+
+    #[test]
+    fn voucher_signature_is_checked() {
+        let signer = PrivateKeySigner::random();
+        let sponsorships = Sponsorships::new(config(signer.address(), 10 * FEE));
+        let tx_hash = TxHash::repeat_byte(1);
+        let voucher = voucher(&signer, tx_hash, NOW);
+        sponsorships
+            .check(tx_hash, FEE, &voucher, balance(FEE), NOW)
+            .unwrap();
+
+        let invalid_signature = Err(SponsorshipError::InvalidSignature(signer.address()));
+        // Signed for another transaction
+        let other_tx = TxHash::repeat_byte(2);
+        assert_eq!(
+            sponsorships.check(other_tx, FEE, &voucher, balance(FEE), NOW),
+            invalid_signature
+        );
+        // Terms differ from the signed ones
+        let raised_fee = SponsorshipVoucher {
+            max_fee: 2 * FEE,
+            ..voucher
+        };
+        assert_eq!(
+            sponsorships.check(tx_hash, FEE, &raised_fee, balance(FEE), NOW),
+            invalid_signature
+        );
+        let extended = SponsorshipVoucher {
+            expiry: NOW + 1,
+            ..voucher
+        };
+        assert_eq!(
+            sponsorships.check(tx_hash, FEE, &extended, balance(FEE), NOW),
+            invalid_signature
+        );
+        // Signed by someone else on behalf of the sponsor
+        let impostor = SponsorshipVoucher {
+            sponsor: signer.address(),
+            ..self::voucher(&PrivateKeySigner::random(), tx_hash, NOW)
+        };
+        assert_eq!(
+            sponsorships.check(tx_hash, FEE, &impostor, balance(FEE), NOW),
+            invalid_signature
+        );
+
+        // Unregistered sponsors are rejected before anything else
+        let unknown = PrivateKeySigner::random();
+        assert_eq!(
+            sponsorships.check(
+                tx_hash,
+                FEE,
+                &self::voucher(&unknown, tx_hash, NOW),
+                balance(FEE),
+                NOW
+            ),
+            Err(SponsorshipError::UnknownSponsor(unknown.address()))
+        );
+    }
+
+    #[test]
+    fn voucher_terms_are_checked() {
+        let signer = PrivateKeySigner::random();
+        let sponsorships = Sponsorships::new(config(signer.address(), 10 * FEE));
+        let tx_hash = TxHash::repeat_byte(1);
+
+        // Expiry boundaries
+        let check = |expiry| {
+            sponsorships.check(
+                tx_hash,
+                FEE,
+                &voucher(&signer, tx_hash, expiry),
+                balance(FEE),
+                NOW,
+            )
+        };
+        check(NOW).unwrap();
+        check(NOW + 60).unwrap();
+        assert_eq!(
+            check(NOW - 1),
+            Err(SponsorshipError::Expired {
+                expiry: NOW - 1,
+                now: NOW,
+            })
+        );
+        assert_eq!(
+            check(NOW + 61),
+            Err(SponsorshipError::ExpiryTooFar {
+                expiry: NOW + 61,
+                now: NOW,
+                max_lifetime: Duration::from_secs(60),
+            })
+        );
+
+        let voucher = voucher(&signer, tx_hash, NOW);
+        assert_eq!(
+            sponsorships.check(tx_hash, FEE + 1, &voucher, balance(FEE + 1), NOW),
+            Err(SponsorshipError::FeeNotCovered {
+                max_fee: FEE,
+                fee: FEE + 1,
+            })
+        );
+        assert_eq!(
+            sponsorships.check(tx_hash, FEE, &voucher, balance(FEE - 1), NOW),
+            Err(SponsorshipError::InsufficientSponsorBalance {
+                sponsor: signer.address(),
+                balance: balance(FEE - 1),
+                fee: FEE,
+            })
+        );
+    }
+
+    #[test]
+    fn budget_is_rolling() {
+        let signer = PrivateKeySigner::random();
+        let sponsor = signer.address();
+        let sponsorships = Sponsorships::new(config(sponsor, 2 * FEE));
+        let (first, second, third) = (
+            TxHash::repeat_byte(1),
+            TxHash::repeat_byte(2),
+            TxHash::repeat_byte(3),
+        );
+        let expiry = NOW + 150;
+
+        assert_eq!(sponsorships.remaining_budget(sponsor, NOW), Some(2 * FEE));
+        sponsorships
+            .charge(first, FEE, &voucher(&signer, first, expiry), NOW)
+            .unwrap();
+        sponsorships
+            .charge(second, FEE, &voucher(&signer, second, expiry), NOW + 50)
+            .unwrap();
+        assert_eq!(sponsorships.remaining_budget(sponsor, NOW + 50), Some(0));
+
+        let third_voucher = voucher(&signer, third, expiry);
+        let exhausted = Err(SponsorshipError::BudgetExhausted {
+            sponsor,
+            remaining: 0,
+            fee: FEE,
+        });
+        assert_eq!(
+            sponsorships.charge(third, FEE, &third_voucher, NOW + 100),
+            exhausted
+        );
+        // Submissions are checked against the remaining budget as well
+        assert_eq!(
+            sponsorships.check(third, FEE, &third_voucher, balance(FEE), NOW + 100),
+            exhausted
+        );
+        // The first charge leaves the window
+        assert_eq!(sponsorships.remaining_budget(sponsor, NOW + 101), Some(FEE));
+        sponsorships
+            .charge(third, FEE, &third_voucher, NOW + 101)
+            .unwrap();
+        assert_eq!(sponsorships.remaining_budget(sponsor, NOW + 101), Some(0));
+        assert_eq!(
+            sponsorships.remaining_budget(sponsor, NOW + 202),
+            Some(2 * FEE)
+        );
+        assert_eq!(
+            sponsorships.remaining_budget(Address::repeat_byte(1), NOW),
+            None
+        );
+    }
+
+    #[test]
+    fn vouchers_cannot_be_replayed() {
+        let signer = PrivateKeySigner::random();
+        let sponsor = signer.address();
+        let sponsorships = Sponsorships::new(config(sponsor, 10 * FEE));
+        let tx_hash = TxHash::repeat_byte(1);
+        let voucher = voucher(&signer, tx_hash, NOW + 10);
+
+        sponsorships.charge(tx_hash, FEE, &voucher, NOW).unwrap();
+        assert_eq!(sponsorships.remaining_budget(sponsor, NOW), Some(9 * FEE));
+        // Yielding the transaction again (e.g. if the previous block didn't include it) doesn't
+        // charge the sponsor twice
+        sponsorships
+            .charge(tx_hash, FEE, &voucher, NOW + 1)
+            .unwrap();
+        assert_eq!(
+            sponsorships.remaining_budget(sponsor, NOW + 1),
+            Some(9 * FEE)
+        );
+        // ...but the voucher cannot be submitted again
+        let already_used = Err(SponsorshipError::AlreadyUsed(tx_hash));
+        assert_eq!(
+            sponsorships.check(tx_hash, FEE, &voucher, balance(FEE), NOW + 1),
+            already_used
+        );
+
+        // Refunded vouchers can be used again
+        sponsorships.refund(tx_hash);
+        assert_eq!(
+            sponsorships.remaining_budget(sponsor, NOW + 1),
+            Some(10 * FEE)
+        );
+        sponsorships
+            .check(tx_hash, FEE, &voucher, balance(FEE), NOW + 1)
+            .unwrap();
+        sponsorships
+            .charge(tx_hash, FEE, &voucher, NOW + 1)
+            .unwrap();
+
+        // Used vouchers are forgotten once expired, since they are rejected anyway
+        assert_eq!(
+            sponsorships.charge(tx_hash, FEE, &voucher, NOW + 11),
+            Err(SponsorshipError::Expired {
+                expiry: NOW + 10,
+                now: NOW + 11,
+            })
+        );
+        assert!(sponsorships.ledger.lock().unwrap().used_vouchers.is_empty());
+    }
+}
@@ -1,15 +1,20 @@
 use crate::L2TransactionPool;
+use crate::metrics::MEMPOOL_METRICS;
+use crate::schedule::unix_timestamp;
+use crate::sponsorship::{SponsorshipError, Sponsorships, sponsored_fee};
 use crate::transaction::L2PooledTransaction;
 use alloy::consensus::transaction::Recovered;
-use alloy::primitives::TxHash;
+use alloy::primitives::{Address, TxHash};
 use futures::{Stream, StreamExt};
 use reth_primitives_traits::transaction::error::InvalidTransactionError;
 use reth_transaction_pool::error::InvalidPoolTransactionError;
 use reth_transaction_pool::{BestTransactions, TransactionListenerKind, ValidPoolTransaction};
+use std::collections::{HashSet, VecDeque};
 use std::pin::Pin;
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use tokio::sync::mpsc;
+use zksync_os_multivm::ExecutionVersion;
 use zksync_os_types::{L1PriorityEnvelope, L1UpgradeEnvelope, L2Envelope, ZkTransaction};
 
 pub trait TxStream: Stream {
@@ -24,16 +29,35 @@ pub struct BestTransactionsStream<'a> {
         Box<dyn BestTransactions<Item = Arc<ValidPoolTransaction<L2PooledTransaction>>>>,
     last_polled_l2_tx: Option<Arc<ValidPoolTransaction<L2PooledTransaction>>>,
     peeked_tx: Option<ZkTransaction>,
+    /// Senders with a high spam score, whose transactions are only yielded once there are no
+    /// other ones.
+    deprioritized_senders: HashSet<Address>,
+    /// Transactions of deprioritized senders taken from `best_l2_transactions`, in order.
+    deferred_l2_transactions: VecDeque<Arc<ValidPoolTransaction<L2PooledTransaction>>>,
+    /// Number of the block being built; scheduled transactions not due in it are skipped.
+    block_number: u64,
+    /// Timestamp of the block being built, if known. Until then, scheduled transactions and
+    /// sponsorship vouchers are checked against the current time.
+    block_timestamp: Option<u64>,
+    /// Sponsor budgets charged for the yielded sponsored transactions.
+    sponsorships: Sponsorships,
+    /// Execution version of the block being built.
+    execution_version: ExecutionVersion,
+    /// Whether `execution_version` supports sponsored fee payment; sponsored transactions are
+    /// skipped otherwise.
+    sponsored_fees_supported: bool,
 }
 
-/// Convenience method to stream best L2 transactions
+/// Convenience method to stream best L2 transactions for the block `block_number`.
 pub fn best_transactions<'a>(
     l2_mempool: &impl L2TransactionPool,
     l1_transactions: &'a mut mpsc::Receiver<L1PriorityEnvelope>,
     upgrade_tx: Option<L1UpgradeEnvelope>,
+    block_number: u64,
 ) -> BestTransactionsStream<'a> {
     let pending_transactions_listener =
         l2_mempool.pending_transactions_listener_for(TransactionListenerKind::All);
+    let execution_version = l2_mempool.execution_version();
     BestTransactionsStream {
         l1_transactions,
         upgrade_tx,
@@ -41,6 +65,13 @@ pub fn best_transactions<'a>(
         best_l2_transactions: l2_mempool.best_transactions(),
         last_polled_l2_tx: None,
         peeked_tx: None,
+        deprioritized_senders: l2_mempool.spam_scores().deprioritized_senders(),
+        deferred_l2_transactions: VecDeque::new(),
+        block_number,
+        block_timestamp: None,
+        sponsorships: l2_mempool.sponsorships().clone(),
+        execution_version,
+        sponsored_fees_supported: execution_version.supports_sponsored_fee_payment(),
     }
 }
 
@@ -65,18 +96,39 @@ impl Stream for BestTransactionsStream<'_> {
             }
 
             if let Some(tx) = this.best_l2_transactions.next() {
-                this.last_polled_l2_tx = Some(tx.clone());
-                let (tx, signer) = tx.to_consensus().into_parts();
-                let tx = L2Envelope::from(tx);
-                return Poll::Ready(Some(Recovered::new_unchecked(tx, signer).into()));
+                if !this.is_due(&tx) {
+                    this.skip_not_due_l2_tx(&tx);
+                    continue;
+                }
+                if this.deprioritized_senders.contains(&tx.sender()) {
+                    this.deferred_l2_transactions.push_back(tx);
+                    continue;
+                }
+                if let Err(err) = this.charge_sponsor(&tx) {
+                    this.skip_sponsored_l2_tx(&tx, &err);
+                    continue;
+                }
+                return Poll::Ready(Some(this.yield_l2_tx(tx)));
             }
 
-            match this.pending_transactions_listener.poll_recv(cx) {
+            if this.pending_transactions_listener.poll_recv(cx).is_ready() {
                 // Try to take the next best transaction again
-                Poll::Ready(_) => continue,
-                // Defer until there is a new pending transaction
-                Poll::Pending => return Poll::Pending,
+                continue;
             }
+
+            if let Some(tx) = this.deferred_l2_transactions.pop_front() {
+                if !this.is_due(&tx) {
+                    this.skip_not_due_l2_tx(&tx);
+                    continue;
+                }
+                if let Err(err) = this.charge_sponsor(&tx) {
+                    this.skip_sponsored_l2_tx(&tx, &err);
+                    continue;
+                }
+                return Poll::Ready(Some(this.yield_l2_tx(tx)));
+            }
+            // Defer until there is a new pending transaction
+            return Poll::Pending;
         }
     }
 }
@@ -85,6 +137,15 @@ impl TxStream for BestTransactionsStream<'_> {
     fn mark_last_tx_as_invalid(self: Pin<&mut Self>) {
         let this = self.get_mut();
         let tx = this.last_polled_l2_tx.take().unwrap();
+        // Invalid transactions are not included, so their sponsors are not charged
+        if tx.transaction.sponsorship.is_some() {
+            this.sponsorships.refund(*tx.hash());
+        }
+        // Deferred transactions were already taken from `best_l2_transactions`, so descendants of
+        // the invalid transaction have to be dropped here
+        let sender = tx.sender();
+        this.deferred_l2_transactions
+            .retain(|deferred| deferred.sender() != sender);
         // Error kind is actually not used internally, but we need to provide it.
         // Reth provides `TxTypeNotSupported` and we do the same just in case.
         this.best_l2_transactions.mark_invalid(
@@ -104,6 +165,113 @@ impl BestTransactionsStream<'_> {
         }
         self.peeked_tx.as_ref()
     }
+
+    /// Sets the timestamp of the block being built. A peeked transaction that is not due in a
+    /// block with this timestamp, or whose sponsorship voucher is expired by then, is skipped.
+    pub fn set_block_timestamp(&mut self, timestamp: u64) {
+        self.block_timestamp = Some(timestamp);
+        let Some(tx) = self.last_polled_l2_tx.clone() else {
+            return;
+        };
+        let is_peeked = self
+            .peeked_tx
+            .as_ref()
+            .is_some_and(|peeked| peeked.hash() == tx.hash());
+        if !is_peeked {
+            return;
+        }
+        // The sponsor was charged against the current time; charge it against the block instead
+        if tx.transaction.sponsorship.is_some() {
+            self.sponsorships.refund(*tx.hash());
+        }
+        if !self.is_due(&tx) {
+            self.peeked_tx = None;
+            self.last_polled_l2_tx = None;
+            self.skip_not_due_l2_tx(&tx);
+        } else if let Err(err) = self.charge_sponsor(&tx) {
+            self.peeked_tx = None;
+            self.last_polled_l2_tx = None;
+            self.skip_sponsored_l2_tx(&tx, &err);
+        }
+    }
+
+    fn block_timestamp(&self) -> u64 {
+        self.block_timestamp.unwrap_or_else(unix_timestamp)
+    }
+
+    /// Whether `tx` can be included in the block being built.
+    fn is_due(&self, tx: &ValidPoolTransaction<L2PooledTransaction>) -> bool {
+        tx.transaction
+            .schedule
+            .is_none_or(|schedule| schedule.is_due(self.block_number, self.block_timestamp()))
+    }
+
+    /// Charges the max fee of a sponsored `tx` to its sponsor's budget.
+    fn charge_sponsor(
+        &self,
+        tx: &ValidPoolTransaction<L2PooledTransaction>,
+    ) -> Result<(), SponsorshipError> {
+        let Some(voucher) = &tx.transaction.sponsorship else {
+            return Ok(());
+        };
+        if !self.sponsored_fees_supported {
+            return Err(SponsorshipError::Unsupported(self.execution_version));
+        }
+        self.sponsorships.charge(
+            *tx.hash(),
+            sponsored_fee(&tx.transaction),
+            voucher,
+            self.block_timestamp(),
+        )
+    }
+
+    /// Skips a scheduled transaction that is not due in the block being built.
+    fn skip_not_due_l2_tx(&mut self, tx: &Arc<ValidPoolTransaction<L2PooledTransaction>>) {
+        tracing::debug!(
+            hash = %tx.hash(),
+            schedule = ?tx.transaction.schedule,
+            block_number = self.block_number,
+            block_timestamp = ?self.block_timestamp,
+            "skipping scheduled transaction that is not due yet"
+        );
+        MEMPOOL_METRICS.scheduled_transactions_skipped.inc();
+        self.skip_l2_tx(tx);
+    }
+
+    /// Skips a sponsored transaction whose sponsor cannot be charged in the block being built.
+    fn skip_sponsored_l2_tx(
+        &mut self,
+        tx: &Arc<ValidPoolTransaction<L2PooledTransaction>>,
+        err: &SponsorshipError,
+    ) {
+        tracing::debug!(
+            hash = %tx.hash(),
+            block_number = self.block_number,
+            %err,
+            "skipping sponsored transaction"
+        );
+        MEMPOOL_METRICS.sponsored_transactions_skipped[&err.reason()].inc();
+        self.skip_l2_tx(tx);
+    }
+
+    /// Skips `tx` along with its descendants in the block being built. The transaction stays in
+    /// the pool.
+    fn skip_l2_tx(&mut self, tx: &Arc<ValidPoolTransaction<L2PooledTransaction>>) {
+        let (sender, nonce) = (tx.sender(), tx.nonce());
+        self.deferred_l2_transactions
+            .retain(|deferred| deferred.sender() != sender || deferred.nonce() < nonce);
+        self.best_l2_transactions.mark_invalid(
+            tx,
+            InvalidPoolTransactionError::Consensus(InvalidTransactionError::TxTypeNotSupported),
+        );
+    }
+
+    fn yield_l2_tx(&mut self, tx: Arc<ValidPoolTransaction<L2PooledTransaction>>) -> ZkTransaction {
+        self.last_polled_l2_tx = Some(tx.clone());
+        let (tx, signer) = tx.to_consensus().into_parts();
+        let tx = L2Envelope::from(tx);
+        Recovered::new_unchecked(tx, signer).into()
+    }
 }
 
 pub struct ReplayTxStream {
@@ -129,3 +297,328 @@ impl ReplayTxStream {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::SponsorshipRejectionReason;
+    use crate::{SponsorshipConfig, SponsorshipVoucher, TxSchedule};
+    use alloy::consensus::{SignableTransaction, TxEip1559};
+    use alloy::primitives::Signature;
+    use futures::FutureExt;
+    use reth_transaction_pool::TransactionOrigin;
+    use reth_transaction_pool::identifier::{SenderId, TransactionId};
+    use std::time::Instant;
+
+    type PooledTx = Arc<ValidPoolTransaction<L2PooledTransaction>>;
+
+    /// Yields transactions in the given order; invalid transactions' descendants are skipped.
+    struct MockBestTransactions(VecDeque<PooledTx>);
+
+    impl Iterator for MockBestTransactions {
+        type Item = PooledTx;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            self.0.pop_front()
+        }
+    }
+
+    impl BestTransactions for MockBestTransactions {
+        fn mark_invalid(&mut self, transaction: &Self::Item, _kind: InvalidPoolTransactionError) {
+            self.0.retain(|tx| tx.sender() != transaction.sender());
+        }
+
+        fn no_updates(&mut self) {}
+
+        fn set_skip_blobs(&mut self, _skip_blobs: bool) {}
+    }
+
+    fn pooled_tx(sender: u8, nonce: u64) -> PooledTx {
+        scheduled_tx(sender, nonce, None)
+    }
+
+    fn scheduled_tx(sender: u8, nonce: u64, schedule: Option<TxSchedule>) -> PooledTx {
+        let tx = TxEip1559 {
+            nonce,
+            ..TxEip1559::default()
+        };
+        let envelope = L2Envelope::from(tx.into_signed(Signature::test_signature()));
+        let mut transaction = L2PooledTransaction::from_pooled(Recovered::new_unchecked(
+            envelope,
+            Address::repeat_byte(sender),
+        ));
+        transaction.schedule = schedule;
+        valid_tx(transaction, sender, nonce)
+    }
+
+    const SPONSOR: Address = Address::repeat_byte(0xff);
+    /// Max fee of sponsored transactions.
+    const FEE: u128 = 21_000;
+
+    fn sponsored_tx(sender: u8, nonce: u64, expiry: u64) -> PooledTx {
+        let tx = TxEip1559 {
+            nonce,
+            gas_limit: 21_000,
+            max_fee_per_gas: 1,
+            ..TxEip1559::default()
+        };
+        let envelope = L2Envelope::from(tx.into_signed(Signature::test_signature()));
+        // Vouchers are only verified at submission
+        let voucher = SponsorshipVoucher {
+            sponsor: SPONSOR,
+            max_fee: FEE,
+            expiry,
+            signature: Signature::test_signature(),
+        };
+        let transaction = L2PooledTransaction::from_pooled(Recovered::new_unchecked(
+            envelope,
+            Address::repeat_byte(sender),
+        ))
+        .with_sponsorship(voucher);
+        valid_tx(transaction, sender, nonce)
+    }
+
+    fn sponsorships(budget: u128) -> Sponsorships {
+        Sponsorships::new(SponsorshipConfig {
+            budgets: [(SPONSOR, budget)].into(),
+            ..SponsorshipConfig::default()
+        })
+    }
+
+    fn valid_tx(transaction: L2PooledTransaction, sender: u8, nonce: u64) -> PooledTx {
+        Arc::new(ValidPoolTransaction {
+            transaction,
+            transaction_id: TransactionId::new(SenderId::from(sender as u64), nonce),
+            propagate: true,
+            timestamp: Instant::now(),
+            origin: TransactionOrigin::Local,
+            authority_ids: None,
+        })
+    }
+
+    /// Polls the stream once; `None` if it has no transaction ready.
+    fn poll_tx(stream: &mut BestTransactionsStream<'_>) -> Option<(Address, u64)> {
+        let tx = stream.next().now_or_never()??;
+        Some((tx.signer(), tx.nonce()))
+    }
+
+    fn stream<'a>(
+        l1_transactions: &'a mut mpsc::Receiver<L1PriorityEnvelope>,
+        l2_transactions: Vec<PooledTx>,
+        block_number: u64,
+        block_timestamp: Option<u64>,
+    ) -> BestTransactionsStream<'a> {
+        let (_pending_sender, pending_transactions_listener) = mpsc::channel(1);
+        BestTransactionsStream {
+            l1_transactions,
+            upgrade_tx: None,
+            pending_transactions_listener,
+            best_l2_transactions: Box::new(MockBestTransactions(l2_transactions.into())),
+            last_polled_l2_tx: None,
+            peeked_tx: None,
+            deprioritized_senders: HashSet::new(),
+            deferred_l2_transactions: VecDeque::new(),
+            block_number,
+            block_timestamp,
+            sponsorships: sponsorships(0),
+            execution_version: ExecutionVersion::V4,
+            sponsored_fees_supported: true,
+        }
+    }
+
+    #[test]
+    fn scheduled_transactions_are_not_included_early() {
+        let (_l1_sender, mut l1_transactions) = mpsc::channel(1);
+        let schedule = TxSchedule {
+            not_before_block: Some(10),
+            not_before_timestamp: Some(1_000),
+        };
+        let txs = || {
+            vec![
+                scheduled_tx(1, 0, Some(schedule)),
+                // Descendant of a scheduled transaction
+                pooled_tx(1, 1),
+                pooled_tx(2, 0),
+            ]
+        };
+        let (scheduled, other) = (Address::repeat_byte(1), Address::repeat_byte(2));
+
+        // Promoted against the chain head, but the block being built is too early
+        for (block_number, block_timestamp) in [(9, 1_000), (10, 999)] {
+            let mut stream = stream(
+                &mut l1_transactions,
+                txs(),
+                block_number,
+                Some(block_timestamp),
+            );
+            assert_eq!(poll_tx(&mut stream), Some((other, 0)));
+            assert_eq!(poll_tx(&mut stream), None);
+        }
+
+        let mut stream = stream(&mut l1_transactions, txs(), 10, Some(1_000));
+        assert_eq!(poll_tx(&mut stream), Some((scheduled, 0)));
+        assert_eq!(poll_tx(&mut stream), Some((scheduled, 1)));
+        assert_eq!(poll_tx(&mut stream), Some((other, 0)));
+    }
+
+    #[test]
+    fn peeked_transaction_is_rechecked_against_block_timestamp() {
+        let (_l1_sender, mut l1_transactions) = mpsc::channel(1);
+        let now = unix_timestamp();
+        let schedule = TxSchedule {
+            not_before_block: None,
+            not_before_timestamp: Some(now),
+        };
+        let txs = vec![scheduled_tx(1, 0, Some(schedule)), pooled_tx(2, 0)];
+        // The block timestamp is not known yet, so the current time is used
+        let mut stream = stream(&mut l1_transactions, txs, 10, None);
+        let peeked = stream.wait_peek().now_or_never().unwrap().unwrap();
+        assert_eq!(peeked.signer(), Address::repeat_byte(1));
+        // ...but the actual block timestamp is earlier
+        stream.set_block_timestamp(now - 1);
+        assert_eq!(poll_tx(&mut stream), Some((Address::repeat_byte(2), 0)));
+        assert_eq!(poll_tx(&mut stream), None);
+    }
+
+    #[test]
+    fn deprioritized_senders_go_last() {
+        let (_l1_sender, mut l1_transactions) = mpsc::channel(1);
+        let (_pending_sender, pending_transactions_listener) = mpsc::channel(1);
+        let (spammer, honest) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let mut stream = BestTransactionsStream {
+            l1_transactions: &mut l1_transactions,
+            upgrade_tx: None,
+            pending_transactions_listener,
+            best_l2_transactions: Box::new(MockBestTransactions(VecDeque::from([
+                pooled_tx(1, 0),
+                pooled_tx(1, 1),
+                pooled_tx(2, 0),
+                pooled_tx(1, 2),
+                pooled_tx(2, 1),
+            ]))),
+            last_polled_l2_tx: None,
+            peeked_tx: None,
+            deprioritized_senders: HashSet::from([spammer]),
+            deferred_l2_transactions: VecDeque::new(),
+            block_number: 1,
+            block_timestamp: None,
+            sponsorships: sponsorships(0),
+            execution_version: ExecutionVersion::V4,
+            sponsored_fees_supported: false,
+        };
+
+        assert_eq!(poll_tx(&mut stream), Some((honest, 0)));
+        assert_eq!(poll_tx(&mut stream), Some((honest, 1)));
+        assert_eq!(poll_tx(&mut stream), Some((spammer, 0)));
+        // Descendants of an invalid deferred transaction are dropped
+        Pin::new(&mut stream).mark_last_tx_as_invalid();
+        assert_eq!(poll_tx(&mut stream), None);
+    }
+
+    #[test]
+    fn sponsor_running_out_of_budget_mid_block() {
+        let (_l1_sender, mut l1_transactions) = mpsc::channel(1);
+        let now = unix_timestamp();
+        let txs = vec![
+            sponsored_tx(1, 0, now + 60),
+            sponsored_tx(2, 0, now + 60),
+            sponsored_tx(3, 0, now + 60),
+            // Descendant of a transaction skipped because of the budget
+            pooled_tx(3, 1),
+            pooled_tx(4, 0),
+        ];
+        let mut stream = stream(&mut l1_transactions, txs, 10, Some(now));
+        stream.sponsorships = sponsorships(2 * FEE);
+        let skipped = || {
+            MEMPOOL_METRICS.sponsored_transactions_skipped
+                [&SponsorshipRejectionReason::BudgetExhausted]
+                .get()
+        };
+        let skipped_before = skipped();
+
+        assert_eq!(poll_tx(&mut stream), Some((Address::repeat_byte(1), 0)));
+        assert_eq!(poll_tx(&mut stream), Some((Address::repeat_byte(2), 0)));
+        assert_eq!(poll_tx(&mut stream), Some((Address::repeat_byte(4), 0)));
+        assert_eq!(poll_tx(&mut stream), None);
+        assert_eq!(stream.sponsorships.remaining_budget(SPONSOR, now), Some(0));
+        assert!(skipped() > skipped_before);
+    }
+
+    #[test]
+    fn sponsors_are_not_charged_for_invalid_transactions() {
+        let (_l1_sender, mut l1_transactions) = mpsc::channel(1);
+        let now = unix_timestamp();
+        let txs = vec![sponsored_tx(1, 0, now + 60), sponsored_tx(2, 0, now + 60)];
+        let mut stream = stream(&mut l1_transactions, txs, 10, Some(now));
+        stream.sponsorships = sponsorships(FEE);
+
+        assert_eq!(poll_tx(&mut stream), Some((Address::repeat_byte(1), 0)));
+        assert_eq!(stream.sponsorships.remaining_budget(SPONSOR, now), Some(0));
+        Pin::new(&mut stream).mark_last_tx_as_invalid();
+        assert_eq!(
+            stream.sponsorships.remaining_budget(SPONSOR, now),
+            Some(FEE)
+        );
+        // The refunded budget covers the next transaction
+        assert_eq!(poll_tx(&mut stream), Some((Address::repeat_byte(2), 0)));
+        assert_eq!(poll_tx(&mut stream), None);
+    }
+
+    #[test]
+    fn sponsored_transactions_are_skipped_if_they_cannot_be_charged() {
+        let (_l1_sender, mut l1_transactions) = mpsc::channel(1);
+        let now = unix_timestamp();
+        let txs = || {
+            vec![
+                sponsored_tx(1, 0, now),
+                sponsored_tx(2, 0, now + 60),
+                pooled_tx(3, 0),
+            ]
+        };
+
+        // Unsupported by the execution version
+        let mut unsupported = stream(&mut l1_transactions, txs(), 10, Some(now));
+        unsupported.sponsorships = sponsorships(10 * FEE);
+        unsupported.sponsored_fees_supported = false;
+        assert_eq!(
+            poll_tx(&mut unsupported),
+            Some((Address::repeat_byte(3), 0))
+        );
+        assert_eq!(poll_tx(&mut unsupported), None);
+        assert_eq!(
+            unsupported.sponsorships.remaining_budget(SPONSOR, now),
+            Some(10 * FEE)
+        );
+        drop(unsupported);
+
+        // Expired by the block timestamp
+        let mut stream = stream(&mut l1_transactions, txs(), 10, Some(now + 1));
+        stream.sponsorships = sponsorships(10 * FEE);
+        assert_eq!(poll_tx(&mut stream), Some((Address::repeat_byte(2), 0)));
+        assert_eq!(poll_tx(&mut stream), Some((Address::repeat_byte(3), 0)));
+        assert_eq!(
+            stream.sponsorships.remaining_budget(SPONSOR, now + 1),
+            Some(9 * FEE)
+        );
+    }
+
+    #[test]
+    fn peeked_sponsored_transaction_is_recharged_against_block_timestamp() {
+        let (_l1_sender, mut l1_transactions) = mpsc::channel(1);
+        let now = unix_timestamp();
+        let txs = vec![sponsored_tx(1, 0, now + 10), pooled_tx(2, 0)];
+        let mut stream = stream(&mut l1_transactions, txs, 10, None);
+        stream.sponsorships = sponsorships(FEE);
+        let peeked = stream.wait_peek().now_or_never().unwrap().unwrap();
+        assert_eq!(peeked.signer(), Address::repeat_byte(1));
+        assert_eq!(stream.sponsorships.remaining_budget(SPONSOR, now), Some(0));
+        // The voucher expires before the actual block timestamp
+        stream.set_block_timestamp(now + 11);
+        assert_eq!(
+            stream.sponsorships.remaining_budget(SPONSOR, now),
+            Some(FEE)
+        );
+        assert_eq!(poll_tx(&mut stream), Some((Address::repeat_byte(2), 0)));
+        assert_eq!(poll_tx(&mut stream), None);
+    }
+}
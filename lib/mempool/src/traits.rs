@@ -1,16 +1,32 @@
+use crate::content::{TxPoolContent, TxPoolInspect, TxPoolStatus, TxSummary};
+use crate::diagnostics::{PooledTxDiagnostics, SenderDiagnostics};
+use crate::metrics::{DiscardReason, MEMPOOL_METRICS};
 use crate::reth_state::ZkClient;
+use crate::schedule::{ScheduledTransactions, ScheduledTx, TxSchedule, unix_timestamp};
+use crate::spam::{SpamEvent, SpamScores};
+use crate::sponsorship::{SponsorshipError, SponsorshipVoucher, Sponsorships, sponsored_fee};
 use crate::transaction::L2PooledTransaction;
+use crate::validator::{ZkTransactionValidator, invalidated_transactions};
+use alloy::consensus::Transaction;
+use alloy::primitives::{Address, TxHash, U256};
+use reth_storage_api::StateProviderFactory;
 use reth_transaction_pool::blobstore::NoopBlobStore;
+use reth_transaction_pool::error::{
+    InvalidPoolTransactionError, PoolError, PoolErrorKind, PoolTransactionError,
+};
 use reth_transaction_pool::{
-    AddedTransactionOutcome, CoinbaseTipOrdering, EthTransactionValidator, Pool, PoolResult,
-    PoolTransaction, TransactionOrigin, TransactionPoolExt,
+    AddedTransactionOutcome, CoinbaseTipOrdering, Pool, PoolResult, PoolTransaction,
+    TransactionOrigin, TransactionPool, TransactionPoolExt, ValidPoolTransaction,
 };
 use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::Duration;
+use zksync_os_multivm::ExecutionVersion;
 use zksync_os_storage_api::{ReadRepository, ReadStateHistory};
 use zksync_os_types::L2Transaction;
 
 pub(crate) type RethPool<State, Repository> = Pool<
-    EthTransactionValidator<ZkClient<State, Repository>, L2PooledTransaction>,
+    ZkTransactionValidator<ZkClient<State, Repository>>,
     CoinbaseTipOrdering<L2PooledTransaction>,
     NoopBlobStore,
 >;
@@ -20,19 +36,387 @@ pub(crate) type RethPool<State, Repository> = Pool<
 pub trait L2TransactionPool:
     TransactionPoolExt<Transaction = L2PooledTransaction> + Send + Sync + Debug + 'static
 {
-    /// Convenience method to add a local L2 transaction
+    /// Convenience method to add a local L2 transaction. `origin` is an optional tag of the
+    /// submission source supplied by the RPC layer (e.g. the client IP).
+    ///
+    /// Submissions from senders or origins with a high spam score are rejected with
+    /// [`SpamRejection`](crate::SpamRejection); replacements and failed submissions increase
+    /// their scores.
     fn add_l2_transaction(
         &self,
         transaction: L2Transaction,
+        origin: Option<&str>,
     ) -> impl Future<Output = PoolResult<AddedTransactionOutcome>> + Send {
-        self.add_transaction(
-            TransactionOrigin::Local,
-            L2PooledTransaction::from_pooled(transaction),
+        add_pooled_l2_transaction(self, L2PooledTransaction::from_pooled(transaction), origin)
+    }
+
+    /// Adds a local L2 transaction that can only be included starting from the block described by
+    /// `schedule`. Conditions further ahead than allowed by [`TxScheduleConfig`] are rejected with
+    /// [`ScheduleError`](crate::ScheduleError).
+    ///
+    /// Transactions that are not due in the next block are held back (and reported as queued)
+    /// until [`Self::promote_scheduled_transactions`] adds them to the pool.
+    ///
+    /// [`TxScheduleConfig`]: crate::TxScheduleConfig
+    fn add_scheduled_l2_transaction(
+        &self,
+        transaction: L2Transaction,
+        origin: Option<&str>,
+        schedule: TxSchedule,
+    ) -> impl Future<Output = PoolResult<TxHash>> + Send {
+        async move {
+            let (hash, sender) = (*transaction.hash(), transaction.signer());
+            let spam_scores = self.spam_scores();
+            if let Err(rejection) = spam_scores.check(sender, origin) {
+                return Err(invalid_transaction(hash, rejection));
+            }
+
+            let (next_block, now) = (self.latest_block_number() + 1, unix_timestamp());
+            let scheduled = self.scheduled_transactions();
+            if let Err(err) = scheduled.check(&schedule, next_block, now) {
+                spam_scores.record(sender, origin, SpamEvent::ValidationFailure);
+                return Err(invalid_transaction(hash, err));
+            }
+            let transaction = L2PooledTransaction::from_pooled(transaction).with_schedule(schedule);
+            if schedule.is_due(next_block, now) {
+                return add_pooled_l2_transaction(self, transaction, origin)
+                    .await
+                    .map(|outcome| outcome.hash);
+            }
+            scheduled
+                .insert(transaction, origin)
+                .map_err(|err| invalid_transaction(hash, err))?;
+            MEMPOOL_METRICS.scheduled_transactions.set(scheduled.len());
+            tracing::debug!(%hash, %sender, ?schedule, "scheduled transaction");
+            Ok(hash)
+        }
+    }
+
+    /// Adds a local L2 transaction whose fee is paid by the sponsor described by `voucher`. The
+    /// voucher must be signed by a registered sponsor whose balance and remaining budget cover the
+    /// max fee of the transaction; otherwise, the transaction is rejected with
+    /// [`SponsorshipError`]. The sender's balance only has to cover the transferred value.
+    ///
+    /// Sponsored transactions are rejected if the current execution version doesn't support
+    /// sponsored fee payment.
+    fn add_sponsored_l2_transaction(
+        &self,
+        transaction: L2Transaction,
+        origin: Option<&str>,
+        voucher: SponsorshipVoucher,
+    ) -> impl Future<Output = PoolResult<TxHash>> + Send {
+        async move {
+            let (hash, sender) = (*transaction.hash(), transaction.signer());
+            let spam_scores = self.spam_scores();
+            if let Err(rejection) = spam_scores.check(sender, origin) {
+                return Err(invalid_transaction(hash, rejection));
+            }
+
+            let version = self.execution_version();
+            let check = if version.supports_sponsored_fee_payment() {
+                let sponsor = voucher.sponsor;
+                let sponsor_balance = self.on_chain_balance(sponsor).unwrap_or_else(|err| {
+                    tracing::warn!(%sponsor, %err, "failed to read sponsor balance");
+                    U256::ZERO
+                });
+                self.sponsorships().check(
+                    hash,
+                    sponsored_fee(transaction.inner()),
+                    &voucher,
+                    sponsor_balance,
+                    unix_timestamp(),
+                )
+            } else {
+                Err(SponsorshipError::Unsupported(version))
+            };
+            if let Err(err) = check {
+                MEMPOOL_METRICS.sponsorship_rejections[&err.reason()].inc();
+                spam_scores.record(sender, origin, SpamEvent::ValidationFailure);
+                return Err(invalid_transaction(hash, err));
+            }
+            let transaction =
+                L2PooledTransaction::from_pooled(transaction).with_sponsorship(voucher);
+            let outcome = add_pooled_l2_transaction(self, transaction, origin).await?;
+            tracing::debug!(
+                %hash,
+                %sender,
+                sponsor = %voucher.sponsor,
+                "added sponsored transaction"
+            );
+            Ok(outcome.hash)
+        }
+    }
+
+    /// Adds scheduled transactions that are due in the next block to the pool, validating them
+    /// as regular submissions. Transactions failing validation are dropped. Returns hashes of the
+    /// promoted transactions.
+    ///
+    /// The next block's timestamp is assumed to be the current time; the block builder re-checks
+    /// the schedule against the actual block.
+    fn promote_scheduled_transactions(&self) -> impl Future<Output = Vec<TxHash>> + Send {
+        async move {
+            let scheduled = self.scheduled_transactions();
+            let due = scheduled.take_due(self.latest_block_number() + 1, unix_timestamp());
+            MEMPOOL_METRICS.scheduled_transactions.set(scheduled.len());
+            let mut promoted = Vec::with_capacity(due.len());
+            for ScheduledTx {
+                transaction,
+                origin,
+            } in due
+            {
+                let hash = *transaction.hash();
+                match add_pooled_l2_transaction(self, transaction, origin.as_deref()).await {
+                    Ok(_) => promoted.push(hash),
+                    Err(err) => {
+                        tracing::info!(
+                            %hash,
+                            %err,
+                            "scheduled transaction failed validation on promotion"
+                        );
+                        MEMPOOL_METRICS.discarded_transactions
+                            [&DiscardReason::ScheduledPromotionFailure]
+                            .inc();
+                    }
+                }
+            }
+            MEMPOOL_METRICS
+                .promoted_scheduled_transactions
+                .inc_by(promoted.len() as u64);
+            promoted
+        }
+    }
+
+    /// Periodically promotes due scheduled transactions. Promotion also happens on every canonical
+    /// state change; this loop covers timestamp conditions met while no blocks are produced.
+    fn run_schedule_promotion_loop(&self, period: Duration) -> impl Future<Output = ()> + Send {
+        async move {
+            let mut timer = tokio::time::interval(period);
+            loop {
+                timer.tick().await;
+                if !self.scheduled_transactions().is_empty() {
+                    self.promote_scheduled_transactions().await;
+                }
+            }
+        }
+    }
+
+    /// Scheduled transactions that are not in the pool yet.
+    fn scheduled_transactions(&self) -> &ScheduledTransactions;
+
+    /// Number of the latest block known to the pool's state provider.
+    fn latest_block_number(&self) -> u64;
+
+    /// Spam scores of transaction submitters.
+    fn spam_scores(&self) -> &SpamScores;
+
+    /// Registered sponsors and fees charged to their budgets.
+    fn sponsorships(&self) -> &Sponsorships;
+
+    /// Returns the on-chain nonce of `sender` as seen by the pool's state provider.
+    fn on_chain_nonce(&self, sender: Address) -> anyhow::Result<u64>;
+
+    /// Returns the on-chain balance of `address` as seen by the pool's state provider.
+    fn on_chain_balance(&self, address: Address) -> anyhow::Result<U256>;
+
+    /// Execution version new transactions are validated against.
+    fn execution_version(&self) -> ExecutionVersion;
+
+    /// Switches validation to `version` (e.g., after a protocol upgrade) and evicts pooled
+    /// transactions that are invalid under it. Returns hashes of the evicted transactions.
+    fn on_execution_version_change(&self, version: ExecutionVersion) -> Vec<TxHash>;
+
+    /// Explains the state of `sender`'s transactions in the pool: pending vs queued split, the
+    /// first missing nonce (if any) and whether each transaction covers the current base fee.
+    /// Scheduled transactions that are not due yet are reported as queued.
+    fn sender_diagnostics(&self, sender: Address) -> anyhow::Result<SenderDiagnostics> {
+        let on_chain_nonce = self.on_chain_nonce(sender)?;
+        let base_fee = self.block_info().pending_basefee;
+        let view = |tx: &L2PooledTransaction| {
+            PooledTxDiagnostics::new(*tx.hash(), tx.nonce(), tx.max_fee_per_gas(), base_fee)
+                .with_schedule(tx.schedule)
+        };
+        let pooled_view = |txs: Vec<Arc<ValidPoolTransaction<L2PooledTransaction>>>| {
+            txs.iter()
+                .map(|tx| view(&tx.transaction))
+                .collect::<Vec<_>>()
+        };
+        let mut queued = pooled_view(self.get_queued_transactions_by_sender(sender));
+        queued.extend(
+            self.scheduled_transactions()
+                .by_sender(sender)
+                .iter()
+                .map(view),
+        );
+        Ok(SenderDiagnostics::new(
+            sender,
+            on_chain_nonce,
+            pooled_view(self.get_pending_transactions_by_sender(sender)),
+            queued,
+        ))
+    }
+
+    /// Batch version of [`Self::sender_diagnostics`].
+    fn senders_diagnostics(&self, senders: &[Address]) -> anyhow::Result<Vec<SenderDiagnostics>> {
+        senders
+            .iter()
+            .map(|sender| self.sender_diagnostics(*sender))
+            .collect()
+    }
+
+    /// Pending and queued transactions grouped by sender and nonce, as returned by
+    /// `txpool_content`. Lists at most [`Self::max_content_entries`] transactions, pending first.
+    fn content(&self) -> TxPoolContent {
+        TxPoolContent::new(
+            self,
+            self.scheduled_transactions(),
+            self.max_content_entries(),
+            TxSummary::new,
+        )
+    }
+
+    /// Same as [`Self::content`], but with transactions described by a single line, as returned by
+    /// `txpool_inspect`.
+    fn inspect(&self) -> TxPoolInspect {
+        TxPoolContent::new(
+            self,
+            self.scheduled_transactions(),
+            self.max_content_entries(),
+            TxSummary::inspect,
         )
     }
+
+    /// Number of pending and queued transactions, as returned by `txpool_status`.
+    fn status(&self) -> TxPoolStatus {
+        TxPoolStatus::new(self, self.scheduled_transactions())
+    }
+
+    /// Max number of transactions listed by [`Self::content`] and [`Self::inspect`].
+    fn max_content_entries(&self) -> usize;
+
+    /// Time after which pooled transactions are removed by
+    /// [`run_pool_maintenance`](crate::run_pool_maintenance).
+    fn max_tx_lifetime(&self) -> Duration;
+}
+
+/// Adds `transaction` to `pool`, rejecting it if its submitter has a high spam score. Replacements
+/// and failed submissions increase spam scores.
+async fn add_pooled_l2_transaction<Pool: L2TransactionPool + ?Sized>(
+    pool: &Pool,
+    transaction: L2PooledTransaction,
+    origin: Option<&str>,
+) -> PoolResult<AddedTransactionOutcome> {
+    let (hash, sender, nonce) = (
+        *transaction.hash(),
+        transaction.sender(),
+        transaction.nonce(),
+    );
+    let spam_scores = pool.spam_scores();
+    if let Err(rejection) = spam_scores.check(sender, origin) {
+        return Err(invalid_transaction(hash, rejection));
+    }
+
+    let replaces_pooled = pool
+        .get_transaction_by_sender_and_nonce(sender, nonce)
+        .is_some();
+    let result = pool
+        .add_transaction(TransactionOrigin::Local, transaction)
+        .await;
+    let event = match &result {
+        Ok(_) => replaces_pooled.then_some(SpamEvent::Replacement),
+        Err(err) => match &err.kind {
+            PoolErrorKind::InvalidTransaction(_)
+            | PoolErrorKind::ReplacementUnderpriced
+            | PoolErrorKind::SpammerExceededCapacity(_) => Some(SpamEvent::ValidationFailure),
+            PoolErrorKind::DiscardedOnInsert => Some(SpamEvent::Eviction),
+            // Resubmissions of known transactions are common for wallets
+            _ => None,
+        },
+    };
+    if let Some(event) = event {
+        spam_scores.record(sender, origin, event);
+    }
+    result
+}
+
+fn invalid_transaction(hash: TxHash, err: impl PoolTransactionError + 'static) -> PoolError {
+    PoolError::new(
+        hash,
+        PoolErrorKind::InvalidTransaction(InvalidPoolTransactionError::Other(Box::new(err))),
+    )
 }
 
 impl<State: ReadStateHistory + Clone, Repository: ReadRepository + Clone> L2TransactionPool
     for RethPool<State, Repository>
 {
+    fn scheduled_transactions(&self) -> &ScheduledTransactions {
+        self.validator().scheduled_transactions()
+    }
+
+    fn latest_block_number(&self) -> u64 {
+        self.validator().client().latest_block_number()
+    }
+
+    fn max_content_entries(&self) -> usize {
+        self.validator().max_content_entries()
+    }
+
+    fn max_tx_lifetime(&self) -> Duration {
+        self.validator().max_tx_lifetime()
+    }
+
+    fn on_chain_nonce(&self, sender: Address) -> anyhow::Result<u64> {
+        Ok(self
+            .validator()
+            .client()
+            .latest()?
+            .account_nonce(&sender)?
+            .unwrap_or_default())
+    }
+
+    fn on_chain_balance(&self, address: Address) -> anyhow::Result<U256> {
+        Ok(self
+            .validator()
+            .client()
+            .latest()?
+            .account_balance(&address)?
+            .unwrap_or_default())
+    }
+
+    fn spam_scores(&self) -> &SpamScores {
+        self.validator().spam_scores()
+    }
+
+    fn sponsorships(&self) -> &Sponsorships {
+        self.validator().sponsorships()
+    }
+
+    fn execution_version(&self) -> ExecutionVersion {
+        self.validator().execution_version()
+    }
+
+    fn on_execution_version_change(&self, version: ExecutionVersion) -> Vec<TxHash> {
+        let previous = self.validator().set_execution_version(version);
+        if previous == version {
+            return Vec::new();
+        }
+        MEMPOOL_METRICS.execution_version.set(version as u64);
+
+        let pooled = self.pooled_transactions();
+        let invalidated =
+            invalidated_transactions(version, pooled.iter().map(|tx| &tx.transaction));
+        let evicted: Vec<_> = self
+            .remove_transactions(invalidated)
+            .iter()
+            .map(|tx| *tx.hash())
+            .collect();
+        MEMPOOL_METRICS.discarded_transactions[&DiscardReason::ExecutionVersionChange]
+            .inc_by(evicted.len() as u64);
+        tracing::info!(
+            ?previous,
+            ?version,
+            evicted = evicted.len(),
+            "execution version changed; evicted transactions invalid under the new version"
+        );
+        evicted
+    }
 }
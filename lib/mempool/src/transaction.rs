@@ -1,3 +1,5 @@
+use crate::schedule::TxSchedule;
+use crate::sponsorship::SponsorshipVoucher;
 use alloy::consensus::private::alloy_primitives;
 use alloy::consensus::transaction::Recovered;
 use alloy::consensus::{BlobTransactionValidationError, Transaction, Typed2718};
@@ -26,6 +28,7 @@ pub struct L2PooledTransaction {
     /// For legacy transactions: `gas_price * gas_limit + tx_value`.
     /// For EIP-4844 blob transactions: `max_fee_per_gas * gas_limit + tx_value +
     /// max_blob_fee_per_gas * blob_gas_used`.
+    /// For sponsored transactions: `tx_value`, since the sponsor pays the fee.
     pub cost: U256,
 
     /// This is the RLP length of the transaction, computed when the transaction is added to the
@@ -34,6 +37,12 @@ pub struct L2PooledTransaction {
 
     /// The blob side car for this transaction
     pub blob_sidecar: EthBlobTransactionSidecar,
+
+    /// Earliest block the transaction can be included in, if submitted with a schedule.
+    pub schedule: Option<TxSchedule>,
+
+    /// Voucher of the account paying the transaction's fee, if submitted with one.
+    pub sponsorship: Option<SponsorshipVoucher>,
 }
 
 impl L2PooledTransaction {
@@ -68,9 +77,25 @@ impl L2PooledTransaction {
             cost,
             encoded_length,
             blob_sidecar,
+            schedule: None,
+            sponsorship: None,
         }
     }
 
+    /// Sets the earliest block the transaction can be included in.
+    pub fn with_schedule(mut self, schedule: TxSchedule) -> Self {
+        self.schedule = Some(schedule);
+        self
+    }
+
+    /// Makes the sponsor described by `voucher` pay the transaction's fee. The sender's balance
+    /// only has to cover the transferred value.
+    pub fn with_sponsorship(mut self, voucher: SponsorshipVoucher) -> Self {
+        self.cost = self.transaction.value();
+        self.sponsorship = Some(voucher);
+        self
+    }
+
     /// Return the reference to the underlying transaction.
     pub const fn transaction(&self) -> &L2Transaction {
         &self.transaction
@@ -0,0 +1,324 @@
+use crate::admission::AdmissionPolicy;
+use crate::schedule::ScheduledTransactions;
+use crate::spam::SpamScores;
+use crate::sponsorship::Sponsorships;
+use crate::transaction::L2PooledTransaction;
+use alloy::consensus::Transaction;
+use alloy::primitives::TxHash;
+use reth_primitives_traits::{Block, SealedBlock};
+use reth_transaction_pool::error::{InvalidPoolTransactionError, PoolTransactionError};
+use reth_transaction_pool::{
+    EthTransactionValidator, PoolTransaction, TransactionOrigin, TransactionValidationOutcome,
+    TransactionValidator,
+};
+use std::any::Any;
+use std::sync::RwLock;
+use std::time::Duration;
+use zksync_os_multivm::{ExecutionVersion, intrinsic_gas};
+
+/// Transaction that would be rejected by the VM of the current execution version.
+#[derive(Debug, thiserror::Error)]
+pub enum ExecutionVersionError {
+    #[error(
+        "intrinsic gas too low: gas limit {gas_limit} is below {intrinsic_gas} required by execution version {version:?}"
+    )]
+    IntrinsicGasTooLow {
+        gas_limit: u64,
+        intrinsic_gas: u64,
+        version: ExecutionVersion,
+    },
+    #[error(
+        "calldata too large: {size} bytes exceed the limit of {limit} bytes for execution version {version:?}"
+    )]
+    CalldataTooLarge {
+        size: usize,
+        limit: usize,
+        version: ExecutionVersion,
+    },
+    #[error(
+        "init code too large: {size} bytes exceed the limit of {limit} bytes for execution version {version:?}"
+    )]
+    InitcodeTooLarge {
+        size: usize,
+        limit: usize,
+        version: ExecutionVersion,
+    },
+}
+
+impl PoolTransactionError for ExecutionVersionError {
+    fn is_bad_transaction(&self) -> bool {
+        // The transaction may be valid for another execution version
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Checks `tx` against the limits of the execution version `version`.
+pub(crate) fn check_execution_version(
+    version: ExecutionVersion,
+    tx: &impl Transaction,
+) -> Result<(), ExecutionVersionError> {
+    let limits = version.tx_limits();
+    let size = tx.input().len();
+    if size > limits.max_calldata_bytes {
+        return Err(ExecutionVersionError::CalldataTooLarge {
+            size,
+            limit: limits.max_calldata_bytes,
+            version,
+        });
+    }
+    if tx.kind().is_create() && size > limits.max_initcode_bytes {
+        return Err(ExecutionVersionError::InitcodeTooLarge {
+            size,
+            limit: limits.max_initcode_bytes,
+            version,
+        });
+    }
+    let intrinsic_gas = intrinsic_gas(version, tx);
+    if tx.gas_limit() < intrinsic_gas {
+        return Err(ExecutionVersionError::IntrinsicGasTooLow {
+            gas_limit: tx.gas_limit(),
+            intrinsic_gas,
+            version,
+        });
+    }
+    Ok(())
+}
+
+/// Hashes of `transactions` that are invalid under the execution version `version`.
+pub(crate) fn invalidated_transactions<'a>(
+    version: ExecutionVersion,
+    transactions: impl IntoIterator<Item = &'a L2PooledTransaction>,
+) -> Vec<TxHash> {
+    transactions
+        .into_iter()
+        .filter(|tx| check_execution_version(version, *tx).is_err())
+        .map(|tx| *tx.hash())
+        .collect()
+}
+
+/// Wraps reth's [`EthTransactionValidator`] with the checks ZKsync OS performs before executing
+/// a transaction. These depend on the execution version, which changes on protocol upgrades.
+/// Transactions are checked against the [`AdmissionPolicy`] before anything else.
+///
+/// Also holds spam scores of submitters, scheduled transactions, sponsor budgets and other
+/// ZKsync OS-specific pool state, since the validator is the only component of the reth pool that
+/// ZKsync OS customizes.
+#[derive(Debug)]
+pub(crate) struct ZkTransactionValidator<Client> {
+    inner: EthTransactionValidator<Client, L2PooledTransaction>,
+    admission: AdmissionPolicy,
+    execution_version: RwLock<ExecutionVersion>,
+    spam_scores: SpamScores,
+    scheduled_transactions: ScheduledTransactions,
+    sponsorships: Sponsorships,
+    /// Max number of transactions listed in pool content views.
+    max_content_entries: usize,
+    /// Time after which pooled transactions are removed.
+    max_tx_lifetime: Duration,
+}
+
+impl<Client> ZkTransactionValidator<Client> {
+    pub(crate) fn new(
+        inner: EthTransactionValidator<Client, L2PooledTransaction>,
+        admission: AdmissionPolicy,
+        execution_version: ExecutionVersion,
+        spam_scores: SpamScores,
+        scheduled_transactions: ScheduledTransactions,
+        sponsorships: Sponsorships,
+        max_content_entries: usize,
+        max_tx_lifetime: Duration,
+    ) -> Self {
+        Self {
+            inner,
+            admission,
+            execution_version: RwLock::new(execution_version),
+            spam_scores,
+            scheduled_transactions,
+            sponsorships,
+            max_content_entries,
+            max_tx_lifetime,
+        }
+    }
+
+    pub(crate) fn client(&self) -> &Client {
+        self.inner.client()
+    }
+
+    pub(crate) fn spam_scores(&self) -> &SpamScores {
+        &self.spam_scores
+    }
+
+    pub(crate) fn scheduled_transactions(&self) -> &ScheduledTransactions {
+        &self.scheduled_transactions
+    }
+
+    pub(crate) fn sponsorships(&self) -> &Sponsorships {
+        &self.sponsorships
+    }
+
+    pub(crate) fn max_content_entries(&self) -> usize {
+        self.max_content_entries
+    }
+
+    pub(crate) fn max_tx_lifetime(&self) -> Duration {
+        self.max_tx_lifetime
+    }
+
+    pub(crate) fn execution_version(&self) -> ExecutionVersion {
+        *self
+            .execution_version
+            .read()
+            .expect("execution version lock is poisoned")
+    }
+
+    /// Sets the execution version new transactions are validated against; returns the previous one.
+    pub(crate) fn set_execution_version(&self, version: ExecutionVersion) -> ExecutionVersion {
+        std::mem::replace(
+            &mut *self
+                .execution_version
+                .write()
+                .expect("execution version lock is poisoned"),
+            version,
+        )
+    }
+}
+
+impl<Client> TransactionValidator for ZkTransactionValidator<Client>
+where
+    Client: std::fmt::Debug + Send + Sync,
+    EthTransactionValidator<Client, L2PooledTransaction>:
+        TransactionValidator<Transaction = L2PooledTransaction>,
+{
+    type Transaction = L2PooledTransaction;
+
+    async fn validate_transaction(
+        &self,
+        origin: TransactionOrigin,
+        transaction: Self::Transaction,
+    ) -> TransactionValidationOutcome<Self::Transaction> {
+        if let Err(err) = self.admission.check(&transaction) {
+            return TransactionValidationOutcome::Invalid(
+                transaction,
+                InvalidPoolTransactionError::Other(Box::new(err)),
+            );
+        }
+        if let Err(err) = check_execution_version(self.execution_version(), &transaction) {
+            return TransactionValidationOutcome::Invalid(
+                transaction,
+                InvalidPoolTransactionError::Other(Box::new(err)),
+            );
+        }
+        self.inner.validate_transaction(origin, transaction).await
+    }
+
+    fn on_new_head_block<B>(&self, new_tip_block: &SealedBlock<B>)
+    where
+        B: Block,
+    {
+        self.inner.on_new_head_block(new_tip_block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::consensus::transaction::Recovered;
+    use alloy::consensus::{SignableTransaction, TxEip1559};
+    use alloy::primitives::{Address, Bytes, Signature, TxKind};
+    use zksync_os_types::L2Envelope;
+
+    const VERSIONS: [ExecutionVersion; 4] = [
+        ExecutionVersion::V1,
+        ExecutionVersion::V2,
+        ExecutionVersion::V3,
+        ExecutionVersion::V4,
+    ];
+
+    fn tx(input: Vec<u8>, gas_limit: u64) -> TxEip1559 {
+        TxEip1559 {
+            to: TxKind::Call(Address::repeat_byte(1)),
+            input: Bytes::from(input),
+            gas_limit,
+            ..TxEip1559::default()
+        }
+    }
+
+    #[test]
+    fn intrinsic_gas_boundary() {
+        for version in VERSIONS {
+            let input = vec![1; 100];
+            let required = intrinsic_gas(version, &tx(input.clone(), 0));
+            check_execution_version(version, &tx(input.clone(), required)).unwrap();
+            let err = check_execution_version(version, &tx(input, required - 1)).unwrap_err();
+            assert!(
+                matches!(
+                    err,
+                    ExecutionVersionError::IntrinsicGasTooLow { intrinsic_gas, .. }
+                        if intrinsic_gas == required
+                ),
+                "{version:?}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn calldata_size_boundary() {
+        for version in VERSIONS {
+            let limit = version.tx_limits().max_calldata_bytes;
+            check_execution_version(version, &tx(vec![0; limit], u64::MAX)).unwrap();
+            let err =
+                check_execution_version(version, &tx(vec![0; limit + 1], u64::MAX)).unwrap_err();
+            assert!(
+                matches!(err, ExecutionVersionError::CalldataTooLarge { size, .. } if size == limit + 1),
+                "{version:?}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn initcode_size_boundary() {
+        let version = ExecutionVersion::V4;
+        let limit = version.tx_limits().max_initcode_bytes;
+        let deploy = |len| TxEip1559 {
+            to: TxKind::Create,
+            ..tx(vec![0; len], u64::MAX)
+        };
+        check_execution_version(version, &deploy(limit)).unwrap();
+        let err = check_execution_version(version, &deploy(limit + 1)).unwrap_err();
+        assert!(
+            matches!(err, ExecutionVersionError::InitcodeTooLarge { .. }),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn transactions_invalidated_by_upgrade() {
+        let pooled = |tx: TxEip1559| {
+            let envelope = L2Envelope::from(tx.into_signed(Signature::test_signature()));
+            L2PooledTransaction::from_pooled(Recovered::new_unchecked(
+                envelope,
+                Address::repeat_byte(2),
+            ))
+        };
+        // Enough gas for V3, but below the V4 calldata floor
+        let input = vec![1; 100];
+        let v3_gas = intrinsic_gas(ExecutionVersion::V3, &tx(input.clone(), 0));
+        let invalidated = pooled(tx(input.clone(), v3_gas));
+        let v4_gas = intrinsic_gas(ExecutionVersion::V4, &tx(input.clone(), 0));
+        let valid = pooled(TxEip1559 {
+            nonce: 1,
+            ..tx(input, v4_gas)
+        });
+        let transactions = [invalidated.clone(), valid];
+
+        assert!(invalidated_transactions(ExecutionVersion::V3, &transactions).is_empty());
+        assert_eq!(
+            invalidated_transactions(ExecutionVersion::V4, &transactions),
+            [*invalidated.hash()]
+        );
+    }
+}
@@ -1,6 +1,6 @@
 //! This module provides a unified interface for running blocks and simulating transactions.
-//! When adding new ZKsync OS execution version, make sure it is handled in `run_block` and `simulate_tx` methods.
-//! Also, update the `LATEST_EXECUTION_VERSION` constant accordingly.
+//! When adding new ZKsync OS execution version, describe it in the `versions` module and update
+//! the `LATEST_EXECUTION_VERSION` constant accordingly.
 
 use num_enum::TryFromPrimitive;
 use zk_os_forward_system::run::RunBlockForward as RunBlockForwardV4;
@@ -15,8 +15,22 @@ use zksync_os_interface::types::{BlockOutput, TxOutput};
 
 mod adapter;
 pub mod apps;
+pub mod call_tracer;
+mod replay_stats;
+mod scratch;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
+mod tx_limits;
+mod versions;
 
 pub use adapter::AbiTxSource;
+pub use call_tracer::{CallTraceFrame, CallTraceTracer, TraceConfig};
+pub use replay_stats::{BlockReplayStats, TxReplayStats, TxReplayStatus, replay_block_with_stats};
+pub use scratch::ExecutionScratch;
+use scratch::ScratchStorage;
+pub use tx_limits::{TxLimits, intrinsic_gas};
+use versions::ForwardSystem;
+pub use versions::{VersionSpec, supported_versions};
 
 #[derive(Debug, Clone, Copy, TryFromPrimitive, PartialEq)]
 #[repr(u32)]
@@ -27,46 +41,33 @@ pub enum ExecutionVersion {
     V4 = 4,
 }
 
-impl ExecutionVersion {
-    // NOTE: V1 and V2 have a slight chance of being off as they've been backfilled.
-    // If you find a divergence in what you expect and the actual value, most likely a bug.
-
-    /// verification key hash generated from zksync-os v0.0.21, zksync-airbender v0.4.4 and zkos-wrapper v0.4.3
-    const V1_VK_HASH: &'static str =
-        "0x80a72fbdf9d6ab299fb5dfc2bcc807cfc7be38c9cfb0bc9b1ce6f9510fb110ea";
-    /// verification key hash generated from zksync-os v0.0.25, zksync-airbender v0.4.5 and zkos-wrapper v0.4.6
-    const V2_VK_HASH: &'static str =
-        "0x83d49897775e6c1f1d7247ec228e18158e8e3accda545c604de4c44eee1a9845";
-    /// verification key hash generated from zksync-os v0.0.26, zksync-airbender v0.5.0 and zkos-wrapper v0.5.0
-    const V3_VK_HASH: &'static str =
-        "0x6a4509801ec284b8921c63dc6aaba668a0d71382d87ae4095ffc2235154e9fa3";
-    /// verification key hash generated from zksync-os v0.1.0, zksync-airbender v0.5.1 and zkos-wrapper v0.5.3
-    const V4_VK_HASH: &'static str =
-        "0xa385a997a63cc78e724451dca8b044b5ef29fcdc9d8b6ced33d9f58de531faa5";
-
-    /// Get the verification key hash associated with this execution version.
-    pub fn vk_hash(&self) -> &'static str {
-        match self {
-            ExecutionVersion::V1 => Self::V1_VK_HASH,
-            ExecutionVersion::V2 => Self::V2_VK_HASH,
-            ExecutionVersion::V3 => Self::V3_VK_HASH,
-            ExecutionVersion::V4 => Self::V4_VK_HASH,
-        }
-    }
+pub const LATEST_EXECUTION_VERSION: ExecutionVersion = ExecutionVersion::V4;
 
-    /// Try to get ExecutionVersion from verification key hash.
-    pub fn try_from_vk_hash(vk_hash: &str) -> anyhow::Result<Self> {
-        match vk_hash {
-            Self::V1_VK_HASH => Ok(ExecutionVersion::V1),
-            Self::V2_VK_HASH => Ok(ExecutionVersion::V2),
-            Self::V3_VK_HASH => Ok(ExecutionVersion::V3),
-            Self::V4_VK_HASH => Ok(ExecutionVersion::V4),
-            val => Err(anyhow::anyhow!("unknown verification key hash: {val}")),
-        }
-    }
+/// Error returned when running a block or simulating a transaction.
+#[derive(Debug, thiserror::Error)]
+pub enum MultivmError {
+    /// Execution version is unknown to this build, e.g. because the block was produced by a newer
+    /// node version.
+    #[error(
+        "unsupported ZKsync OS execution version {got} (latest supported: {})",
+        *latest_supported as u32
+    )]
+    UnsupportedExecutionVersion {
+        got: u32,
+        latest_supported: ExecutionVersion,
+    },
+    #[error(transparent)]
+    ExecutionFailed(anyhow::Error),
 }
 
-pub const LATEST_EXECUTION_VERSION: ExecutionVersion = ExecutionVersion::V4;
+fn supported_execution_version(execution_version: u32) -> Result<ExecutionVersion, MultivmError> {
+    execution_version
+        .try_into()
+        .map_err(|_| MultivmError::UnsupportedExecutionVersion {
+            got: execution_version,
+            latest_supported: LATEST_EXECUTION_VERSION,
+        })
+}
 
 pub fn run_block<
     Storage: ReadStorage,
@@ -81,13 +82,10 @@ pub fn run_block<
     tx_source: TrSrc,
     tx_result_callback: TrCallback,
     tracer: &mut Tracer,
-) -> Result<BlockOutput, anyhow::Error> {
-    let execution_version: ExecutionVersion = block_context
-        .execution_version
-        .try_into()
-        .expect("Unsupported ZKsync OS execution version");
-    match execution_version {
-        ExecutionVersion::V1 | ExecutionVersion::V2 | ExecutionVersion::V3 => {
+) -> Result<BlockOutput, MultivmError> {
+    let execution_version = supported_execution_version(block_context.execution_version)?;
+    match execution_version.spec().forward_system {
+        ForwardSystem::V0_0_26 => {
             let object = RunBlockForwardV3 {};
             object
                 .run_block(
@@ -99,9 +97,9 @@ pub fn run_block<
                     tx_result_callback,
                     tracer,
                 )
-                .map_err(|err| anyhow::anyhow!(err))
+                .map_err(|err| MultivmError::ExecutionFailed(anyhow::anyhow!(err)))
         }
-        ExecutionVersion::V4 => {
+        ForwardSystem::V0_1 => {
             let object = RunBlockForwardV4 {};
             object
                 .run_block(
@@ -113,24 +111,48 @@ pub fn run_block<
                     tx_result_callback,
                     tracer,
                 )
-                .map_err(|err| anyhow::anyhow!(err))
+                .map_err(|err| MultivmError::ExecutionFailed(anyhow::anyhow!(err)))
         }
     }
 }
 
+/// Same as [`run_block`], but storage reads are served through buffers borrowed from `scratch`,
+/// see [`ExecutionScratch`].
+pub fn run_block_with_scratch<
+    Storage: ReadStorage,
+    PreimgSrc: PreimageSource,
+    TrSrc: TxSource,
+    TrCallback: TxResultCallback,
+    Tracer: AnyTracer,
+>(
+    scratch: &ExecutionScratch,
+    block_context: BlockContext,
+    storage: Storage,
+    preimage_source: PreimgSrc,
+    tx_source: TrSrc,
+    tx_result_callback: TrCallback,
+    tracer: &mut Tracer,
+) -> Result<BlockOutput, MultivmError> {
+    run_block(
+        block_context,
+        ScratchStorage::new(storage, scratch),
+        preimage_source,
+        tx_source,
+        tx_result_callback,
+        tracer,
+    )
+}
+
 pub fn simulate_tx<Storage: ReadStorage, PreimgSrc: PreimageSource, Tracer: AnyTracer>(
     transaction: EncodedTx,
     block_context: BlockContext,
     storage: Storage,
     preimage_source: PreimgSrc,
     tracer: &mut Tracer,
-) -> Result<Result<TxOutput, InvalidTransaction>, anyhow::Error> {
-    let execution_version: ExecutionVersion = block_context
-        .execution_version
-        .try_into()
-        .expect("Unsupported ZKsync OS execution version");
-    match execution_version {
-        ExecutionVersion::V1 | ExecutionVersion::V2 | ExecutionVersion::V3 => {
+) -> Result<Result<TxOutput, InvalidTransaction>, MultivmError> {
+    let execution_version = supported_execution_version(block_context.execution_version)?;
+    match execution_version.spec().forward_system {
+        ForwardSystem::V0_0_26 => {
             let object = RunBlockForwardV3 {};
             object
                 .simulate_tx(
@@ -141,9 +163,9 @@ pub fn simulate_tx<Storage: ReadStorage, PreimgSrc: PreimageSource, Tracer: AnyT
                     preimage_source,
                     tracer,
                 )
-                .map_err(|err| anyhow::anyhow!(err))
+                .map_err(|err| MultivmError::ExecutionFailed(anyhow::anyhow!(err)))
         }
-        ExecutionVersion::V4 => {
+        ForwardSystem::V0_1 => {
             let object = RunBlockForwardV4 {};
             object
                 .simulate_tx(
@@ -154,11 +176,55 @@ pub fn simulate_tx<Storage: ReadStorage, PreimgSrc: PreimageSource, Tracer: AnyT
                     preimage_source,
                     tracer,
                 )
-                .map_err(|err| anyhow::anyhow!(err))
+                .map_err(|err| MultivmError::ExecutionFailed(anyhow::anyhow!(err)))
         }
     }
 }
 
+/// Same as [`simulate_tx`], but storage reads are served through buffers borrowed from `scratch`,
+/// see [`ExecutionScratch`].
+pub fn simulate_tx_with_scratch<
+    Storage: ReadStorage,
+    PreimgSrc: PreimageSource,
+    Tracer: AnyTracer,
+>(
+    scratch: &ExecutionScratch,
+    transaction: EncodedTx,
+    block_context: BlockContext,
+    storage: Storage,
+    preimage_source: PreimgSrc,
+    tracer: &mut Tracer,
+) -> Result<Result<TxOutput, InvalidTransaction>, MultivmError> {
+    simulate_tx(
+        transaction,
+        block_context,
+        ScratchStorage::new(storage, scratch),
+        preimage_source,
+        tracer,
+    )
+}
+
+/// Same as [`simulate_tx`], but also returns the call frames recorded by [`CallTraceTracer`].
+/// The frame is empty if the transaction is invalid and so wasn't executed.
+pub fn simulate_tx_traced<Storage: ReadStorage, PreimgSrc: PreimageSource>(
+    transaction: EncodedTx,
+    block_context: BlockContext,
+    storage: Storage,
+    preimage_source: PreimgSrc,
+    trace_config: TraceConfig,
+) -> Result<(Result<TxOutput, InvalidTransaction>, CallTraceFrame), MultivmError> {
+    let mut tracer = CallTraceTracer::new(trace_config);
+    let output = simulate_tx(
+        transaction,
+        block_context,
+        storage,
+        preimage_source,
+        &mut tracer,
+    )?;
+    let frame = tracer.into_traces().pop().unwrap_or_default();
+    Ok((output, frame))
+}
+
 /// Method to decide what execution version/VK should the prover use.
 ///
 /// Generally speaking, we could have a single execution version, the one used by the server.
@@ -166,12 +232,78 @@ pub fn simulate_tx<Storage: ReadStorage, PreimgSrc: PreimageSource, Tracer: AnyT
 /// with a different execution version than the one it was generated/sealed.
 /// For such cases, we have this mapping defined here, that will allow us to release patch versions that will say:
 /// "oh, you executed with v3? np, prove with v4 as v3 proving is bugged".
-pub fn proving_run_execution_version(forward_run_execution_version: u32) -> ExecutionVersion {
-    let forward_run_execution_version: ExecutionVersion = forward_run_execution_version
-        .try_into()
-        .expect("Unsupported ZKsync OS execution version");
-    match forward_run_execution_version {
-        ExecutionVersion::V1 | ExecutionVersion::V2 | ExecutionVersion::V3 => ExecutionVersion::V3,
-        ExecutionVersion::V4 => ExecutionVersion::V4,
+pub fn proving_run_execution_version(
+    forward_run_execution_version: u32,
+) -> Result<ExecutionVersion, MultivmError> {
+    let forward_run_execution_version = supported_execution_version(forward_run_execution_version)?;
+    Ok(forward_run_execution_version.spec().proving_version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::EmptyState;
+    use zksync_os_interface::tracing::NopTracer;
+    use zksync_os_interface::traits::{NoopTxCallback, TxListSource};
+
+    fn assert_unsupported(err: MultivmError, expected: u32) {
+        match err {
+            MultivmError::UnsupportedExecutionVersion {
+                got,
+                latest_supported,
+            } => {
+                assert_eq!(got, expected);
+                assert_eq!(latest_supported, LATEST_EXECUTION_VERSION);
+            }
+            err => panic!("unexpected error: {err:?}"),
+        }
+    }
+
+    #[test]
+    fn unsupported_execution_versions_are_rejected() {
+        for execution_version in [0, 999] {
+            let block_context = BlockContext {
+                execution_version,
+                ..Default::default()
+            };
+
+            let err = run_block(
+                block_context,
+                EmptyState,
+                EmptyState,
+                TxListSource {
+                    transactions: Default::default(),
+                },
+                NoopTxCallback,
+                &mut NopTracer,
+            )
+            .unwrap_err();
+            assert_unsupported(err, execution_version);
+
+            let err = simulate_tx(
+                EncodedTx::Abi(vec![]),
+                block_context,
+                EmptyState,
+                EmptyState,
+                &mut NopTracer,
+            )
+            .unwrap_err();
+            assert_unsupported(err, execution_version);
+
+            let err = proving_run_execution_version(execution_version).unwrap_err();
+            assert_unsupported(err, execution_version);
+        }
+    }
+
+    #[test]
+    fn proving_execution_version_of_supported_versions() {
+        assert_eq!(
+            proving_run_execution_version(1).unwrap(),
+            ExecutionVersion::V3
+        );
+        assert_eq!(
+            proving_run_execution_version(4).unwrap(),
+            ExecutionVersion::V4
+        );
     }
 }
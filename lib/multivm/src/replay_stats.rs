@@ -0,0 +1,210 @@
+//! Re-execution of blocks collecting per-transaction statistics, e.g. for benchmarking.
+
+use crate::{MultivmError, run_block};
+use serde::{Deserialize, Serialize};
+use std::sync::mpsc;
+use std::time::Instant;
+use zksync_os_interface::error::InvalidTransaction;
+use zksync_os_interface::tracing::NopTracer;
+use zksync_os_interface::traits::{
+    EncodedTx, PreimageSource, ReadStorage, TxListSource, TxResultCallback,
+};
+use zksync_os_interface::types::{BlockContext, TxProcessingOutputOwned};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TxReplayStatus {
+    Success,
+    Reverted,
+    /// The transaction was rejected by validation and not included in the block.
+    Invalid,
+}
+
+/// Statistics of a single transaction in [`BlockReplayStats`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TxReplayStats {
+    /// Index of the transaction in the replayed list.
+    pub index: usize,
+    pub status: TxReplayStatus,
+    /// Reason why the transaction is invalid.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invalid_reason: Option<String>,
+    pub gas_used: u64,
+    pub pubdata_used: u64,
+    /// Wall-clock time since the previous transaction was processed; for the first transaction,
+    /// since the block execution started, so it includes block setup.
+    pub execution_time_micros: u64,
+}
+
+/// Statistics of a block re-executed with [`replay_block_with_stats`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockReplayStats {
+    pub block_number: u64,
+    pub execution_version: u32,
+    pub transactions: Vec<TxReplayStats>,
+    pub total_gas_used: u64,
+    pub total_pubdata_used: u64,
+    /// Sum of execution times of the transactions.
+    pub total_tx_execution_time_micros: u64,
+    /// Wall-clock time of the entire block execution, including block finalization.
+    pub block_execution_time_micros: u64,
+    /// Index of the transaction with the longest execution time.
+    pub slowest_tx_index: Option<usize>,
+}
+
+/// Collects [`TxReplayStats`] as transactions are executed.
+struct StatsCallback {
+    next_index: usize,
+    last_processed_at: Instant,
+    sender: mpsc::Sender<TxReplayStats>,
+}
+
+impl TxResultCallback for StatsCallback {
+    fn tx_executed(
+        &mut self,
+        tx_execution_result: Result<TxProcessingOutputOwned, InvalidTransaction>,
+    ) {
+        let now = Instant::now();
+        let execution_time = now - self.last_processed_at;
+        self.last_processed_at = now;
+
+        let (status, invalid_reason, gas_used, pubdata_used) = match tx_execution_result {
+            Ok(output) => {
+                let status = if output.status {
+                    TxReplayStatus::Success
+                } else {
+                    TxReplayStatus::Reverted
+                };
+                (status, None, output.gas_used, output.pubdata_used)
+            }
+            Err(err) => (TxReplayStatus::Invalid, Some(format!("{err:?}")), 0, 0),
+        };
+        let stats = TxReplayStats {
+            index: self.next_index,
+            status,
+            invalid_reason,
+            gas_used,
+            pubdata_used,
+            execution_time_micros: execution_time.as_micros() as u64,
+        };
+        self.next_index += 1;
+        // The receiver outlives block execution
+        self.sender.send(stats).ok();
+    }
+}
+
+/// Re-executes `txs` in a block with `block_context` on top of `storage` and returns
+/// per-transaction statistics. The block output is discarded.
+pub fn replay_block_with_stats<Storage: ReadStorage, PreimgSrc: PreimageSource>(
+    block_context: BlockContext,
+    storage: Storage,
+    preimage_source: PreimgSrc,
+    txs: Vec<EncodedTx>,
+) -> Result<BlockReplayStats, MultivmError> {
+    let (sender, receiver) = mpsc::channel();
+    let started_at = Instant::now();
+    let callback = StatsCallback {
+        next_index: 0,
+        last_processed_at: started_at,
+        sender,
+    };
+    run_block(
+        block_context,
+        storage,
+        preimage_source,
+        TxListSource {
+            transactions: txs.into(),
+        },
+        callback,
+        &mut NopTracer,
+    )?;
+    let block_execution_time = started_at.elapsed();
+
+    let transactions: Vec<_> = receiver.try_iter().collect();
+    let slowest_tx_index = transactions
+        .iter()
+        .max_by_key(|tx| tx.execution_time_micros)
+        .map(|tx| tx.index);
+    Ok(BlockReplayStats {
+        block_number: block_context.block_number,
+        execution_version: block_context.execution_version,
+        total_gas_used: transactions.iter().map(|tx| tx.gas_used).sum(),
+        total_pubdata_used: transactions.iter().map(|tx| tx.pubdata_used).sum(),
+        total_tx_execution_time_micros: transactions
+            .iter()
+            .map(|tx| tx.execution_time_micros)
+            .sum(),
+        block_execution_time_micros: block_execution_time.as_micros() as u64,
+        slowest_tx_index,
+        transactions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{TestState, block_context, reverting_code, transaction};
+    use alloy::primitives::{Address, U256};
+
+    const SENDER: Address = Address::repeat_byte(1);
+    const REVERTER: Address = Address::repeat_byte(3);
+
+    #[test]
+    fn aggregates_match_transactions() {
+        let state = TestState::default()
+            .with_account(SENDER, U256::from(10).pow(U256::from(18)), &[])
+            .with_account(REVERTER, U256::ZERO, &reverting_code("nope"));
+        let txs = vec![
+            transaction(SENDER, 0, Address::repeat_byte(0xaa), U256::from(1_000)),
+            transaction(SENDER, 1, REVERTER, U256::ZERO),
+        ];
+
+        let stats = replay_block_with_stats(block_context(), state.clone(), state, txs).unwrap();
+
+        assert_eq!(stats.block_number, 1);
+        let statuses: Vec<_> = stats.transactions.iter().map(|tx| tx.status).collect();
+        assert_eq!(
+            statuses,
+            [TxReplayStatus::Success, TxReplayStatus::Reverted]
+        );
+        for (i, tx) in stats.transactions.iter().enumerate() {
+            assert_eq!(tx.index, i);
+            assert!(tx.gas_used > 0, "{tx:?}");
+        }
+        assert_eq!(
+            stats.total_gas_used,
+            stats.transactions.iter().map(|tx| tx.gas_used).sum::<u64>()
+        );
+        assert_eq!(
+            stats.total_pubdata_used,
+            stats
+                .transactions
+                .iter()
+                .map(|tx| tx.pubdata_used)
+                .sum::<u64>()
+        );
+        assert_eq!(
+            stats.total_tx_execution_time_micros,
+            stats
+                .transactions
+                .iter()
+                .map(|tx| tx.execution_time_micros)
+                .sum::<u64>()
+        );
+        assert!(stats.total_tx_execution_time_micros <= stats.block_execution_time_micros);
+        let slowest = &stats.transactions[stats.slowest_tx_index.unwrap()];
+        assert!(
+            stats
+                .transactions
+                .iter()
+                .all(|tx| tx.execution_time_micros <= slowest.execution_time_micros)
+        );
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["transactions"][1]["status"], "reverted");
+        let parsed: BlockReplayStats = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, stats);
+    }
+}
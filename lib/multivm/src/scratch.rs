@@ -0,0 +1,247 @@
+//! Scratch buffers reused across block executions.
+//!
+//! The forward-run API takes storage and preimage sources by value and drops them after the block,
+//! so buffers held by our adapters would be reallocated (and regrown) for every block. Instead,
+//! adapters borrow buffers from an [`ExecutionScratch`] and return them on drop, cleared but with
+//! their capacity retained. Capacity is trimmed back if a block needed far less than retained,
+//! so that a single huge block doesn't pin memory forever.
+
+use alloy::primitives::B256;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use zksync_os_interface::traits::ReadStorage;
+
+/// Capacity that is always retained, regardless of the usage.
+const MIN_RETAINED_CAPACITY: usize = 1 << 10;
+/// Capacity is trimmed if it exceeds the usage of the last execution by more than this factor.
+const TRIM_FACTOR: usize = 4;
+
+#[derive(Debug, Default)]
+struct ScratchBuffers {
+    /// Values read from the underlying storage during a single execution. Storage is immutable
+    /// for the duration of an execution, so reads of the same slot are served from here.
+    storage_reads: HashMap<B256, Option<B256>>,
+}
+
+impl ScratchBuffers {
+    fn recycle(&mut self) {
+        let used = self.storage_reads.len();
+        self.storage_reads.clear();
+        if self.storage_reads.capacity() > (used * TRIM_FACTOR).max(MIN_RETAINED_CAPACITY) {
+            self.storage_reads
+                .shrink_to(used.max(MIN_RETAINED_CAPACITY));
+        }
+    }
+}
+
+/// Pool of scratch buffers for `run_block` / `simulate_tx`.
+///
+/// Meant to be kept by each execution worker and passed to every execution it runs. Cloning
+/// is cheap; clones share the pool. If the buffers are already borrowed (e.g., by a concurrent
+/// execution), fresh ones are allocated.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionScratch {
+    buffers: Arc<Mutex<Option<ScratchBuffers>>>,
+}
+
+impl ExecutionScratch {
+    fn borrow(&self) -> ScratchBuffers {
+        self.buffers
+            .lock()
+            .expect("scratch is poisoned")
+            .take()
+            .unwrap_or_default()
+    }
+
+    fn give_back(&self, mut buffers: ScratchBuffers) {
+        buffers.recycle();
+        let mut slot = self.buffers.lock().expect("scratch is poisoned");
+        if slot.is_none() {
+            *slot = Some(buffers);
+        }
+    }
+
+    /// Capacity currently retained by the pool (for observability and tests).
+    pub fn retained_capacity(&self) -> usize {
+        self.buffers
+            .lock()
+            .expect("scratch is poisoned")
+            .as_ref()
+            .map_or(0, |buffers| buffers.storage_reads.capacity())
+    }
+}
+
+/// Storage adapter backed by buffers borrowed from [`ExecutionScratch`].
+#[derive(Debug)]
+pub(crate) struct ScratchStorage<S> {
+    inner: S,
+    buffers: Option<ScratchBuffers>,
+    scratch: ExecutionScratch,
+}
+
+impl<S> ScratchStorage<S> {
+    pub(crate) fn new(inner: S, scratch: &ExecutionScratch) -> Self {
+        Self {
+            inner,
+            buffers: Some(scratch.borrow()),
+            scratch: scratch.clone(),
+        }
+    }
+
+    fn buffers(&mut self) -> &mut ScratchBuffers {
+        self.buffers
+            .as_mut()
+            .expect("buffers are only taken on drop")
+    }
+}
+
+impl<S> Drop for ScratchStorage<S> {
+    fn drop(&mut self) {
+        if let Some(buffers) = self.buffers.take() {
+            self.scratch.give_back(buffers);
+        }
+    }
+}
+
+impl<S: ReadStorage> ReadStorage for ScratchStorage<S> {
+    fn read(&mut self, key: B256) -> Option<B256> {
+        if let Some(value) = self.buffers().storage_reads.get(&key) {
+            return *value;
+        }
+        let value = self.inner.read(key);
+        self.buffers().storage_reads.insert(key, value);
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    /// Counts allocations made by the current thread, so that concurrently running tests
+    /// don't affect each other.
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            ALLOCATIONS.with(|count| count.set(count.get() + 1));
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            ALLOCATIONS.with(|count| count.set(count.get() + 1));
+            unsafe { System.realloc(ptr, layout, new_size) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn allocations() -> usize {
+        ALLOCATIONS.with(Cell::get)
+    }
+
+    /// Storage that allocates on every read, like a RocksDB lookup.
+    #[derive(Debug)]
+    struct AllocatingStorage;
+
+    impl ReadStorage for AllocatingStorage {
+        fn read(&mut self, key: B256) -> Option<B256> {
+            let raw = key.to_vec();
+            (raw[31] % 3 != 0).then(|| B256::left_padding_from(&raw[..31]))
+        }
+    }
+
+    /// Reads of a block: `slots` distinct slots, each read several times.
+    fn block_reads(block: u8, slots: u16) -> impl Iterator<Item = B256> {
+        (0..3).flat_map(move |_| {
+            (0..slots).map(move |slot| {
+                let mut key = B256::repeat_byte(block);
+                key[30..].copy_from_slice(&slot.to_be_bytes());
+                key
+            })
+        })
+    }
+
+    fn execute_block(storage: &mut impl ReadStorage, block: u8, slots: u16) -> Vec<Option<B256>> {
+        block_reads(block, slots)
+            .map(|key| storage.read(key))
+            .collect()
+    }
+
+    #[test]
+    fn pooled_reads_are_identical() {
+        let scratch = ExecutionScratch::default();
+        for block in 0..5 {
+            let expected = execute_block(&mut AllocatingStorage, block, 500);
+            let mut storage = ScratchStorage::new(AllocatingStorage, &scratch);
+            assert_eq!(execute_block(&mut storage, block, 500), expected);
+        }
+    }
+
+    #[test]
+    fn pooling_reduces_allocations_per_block() {
+        const SLOTS: u16 = 5_000;
+
+        let measure = |scratch: &ExecutionScratch, block: u8| {
+            let mut results = Vec::with_capacity(3 * SLOTS as usize);
+            let before = allocations();
+            let mut storage = ScratchStorage::new(AllocatingStorage, scratch);
+            for key in block_reads(block, SLOTS) {
+                results.push(storage.read(key));
+            }
+            drop(storage);
+            allocations() - before
+        };
+
+        let pooled = ExecutionScratch::default();
+        measure(&pooled, 0); // warm up the pool
+        let mut pooled_allocations = 0;
+        let mut fresh_allocations = 0;
+        for block in 1..=5 {
+            pooled_allocations += measure(&pooled, block);
+            fresh_allocations += measure(&ExecutionScratch::default(), block);
+        }
+        // With a warm pool, the only allocations are underlying reads, one per distinct slot
+        assert_eq!(pooled_allocations, 5 * SLOTS as usize);
+        assert!(
+            pooled_allocations < fresh_allocations,
+            "pooled: {pooled_allocations}, fresh: {fresh_allocations}"
+        );
+    }
+
+    #[test]
+    fn retained_capacity_is_trimmed() {
+        let scratch = ExecutionScratch::default();
+        let mut storage = ScratchStorage::new(AllocatingStorage, &scratch);
+        execute_block(&mut storage, 0, 20_000);
+        drop(storage);
+        assert!(scratch.retained_capacity() >= 20_000);
+
+        // Capacity is retained while usage is comparable...
+        let mut storage = ScratchStorage::new(AllocatingStorage, &scratch);
+        execute_block(&mut storage, 1, 10_000);
+        drop(storage);
+        assert!(scratch.retained_capacity() >= 20_000);
+
+        // ...and trimmed once a block needs far less
+        let mut storage = ScratchStorage::new(AllocatingStorage, &scratch);
+        execute_block(&mut storage, 2, 100);
+        drop(storage);
+        let capacity = scratch.retained_capacity();
+        assert!(
+            (MIN_RETAINED_CAPACITY..20_000).contains(&capacity),
+            "{capacity}"
+        );
+    }
+}
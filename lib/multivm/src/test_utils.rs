@@ -0,0 +1,133 @@
+//! Fixtures for executing transactions against in-memory state.
+
+use crate::LATEST_EXECUTION_VERSION;
+use alloy::consensus::{SignableTransaction, TxEip1559};
+use alloy::primitives::ruint::aliases::B160;
+use alloy::primitives::{Address, B256, Signature, TxKind, U256};
+use alloy::sol_types::{Revert, SolError};
+use std::collections::HashMap;
+use zk_ee::common_structs::derive_flat_storage_key;
+use zk_os_api::helpers::{set_properties_balance, set_properties_code};
+use zk_os_basic_system::system_implementation::flat_storage_model::{
+    ACCOUNT_PROPERTIES_STORAGE_ADDRESS, AccountProperties, address_into_special_storage_key,
+};
+use zksync_os_interface::traits::{EncodedTx, PreimageSource, ReadStorage};
+use zksync_os_interface::types::BlockContext;
+use zksync_os_types::{L2Envelope, L2Transaction, ZkTransaction, ZksyncOsEncode};
+
+pub const CHAIN_ID: u64 = 270;
+
+/// State without any slots or preimages.
+#[derive(Debug, Clone, Copy)]
+pub struct EmptyState;
+
+impl ReadStorage for EmptyState {
+    fn read(&mut self, _key: B256) -> Option<B256> {
+        None
+    }
+}
+
+impl PreimageSource for EmptyState {
+    fn get_preimage(&mut self, _hash: B256) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// In-memory storage and preimages.
+#[derive(Debug, Clone, Default)]
+pub struct TestState {
+    storage: HashMap<B256, B256>,
+    preimages: HashMap<B256, Vec<u8>>,
+}
+
+impl TestState {
+    /// Adds an account with `balance` and (unless empty) deployed `code`.
+    pub fn with_account(mut self, address: Address, balance: U256, code: &[u8]) -> Self {
+        let mut properties = AccountProperties::default();
+        set_properties_balance(&mut properties, balance);
+        if !code.is_empty() {
+            let bytecode_preimage = set_properties_code(&mut properties, code);
+            let bytecode_hash = properties.bytecode_hash.as_u8_array().into();
+            self.preimages.insert(bytecode_hash, bytecode_preimage);
+        }
+        let properties_hash: B256 = properties.compute_hash().as_u8_array().into();
+        self.preimages
+            .insert(properties_hash, properties.encoding().to_vec());
+        let key = derive_flat_storage_key(
+            &ACCOUNT_PROPERTIES_STORAGE_ADDRESS,
+            &address_into_special_storage_key(&B160::from_be_bytes(address.into_array())),
+        );
+        self.storage
+            .insert(B256::from(key.as_u8_array()), properties_hash);
+        self
+    }
+}
+
+impl ReadStorage for TestState {
+    fn read(&mut self, key: B256) -> Option<B256> {
+        self.storage.get(&key).copied()
+    }
+}
+
+impl PreimageSource for TestState {
+    fn get_preimage(&mut self, hash: B256) -> Option<Vec<u8>> {
+        self.preimages.get(&hash).cloned()
+    }
+}
+
+/// Runtime code reverting with `Error(reason)`.
+pub fn reverting_code(reason: &str) -> Vec<u8> {
+    let revert_data = Revert {
+        reason: reason.to_owned(),
+    }
+    .abi_encode();
+    let len = u8::try_from(revert_data.len()).unwrap();
+    // CODECOPY the revert data appended to the code into memory and revert with it
+    let mut code = vec![
+        0x60, len, 0x60, 12, 0x60, 0x00, 0x39, 0x60, len, 0x60, 0x00, 0xfd,
+    ];
+    code.extend(revert_data);
+    code
+}
+
+/// Runtime code calling `callee` with all gas and no calldata, ignoring the result.
+pub fn calling_code(callee: Address) -> Vec<u8> {
+    let mut code = vec![
+        0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x73,
+    ];
+    code.extend_from_slice(callee.as_slice());
+    // GAS, CALL, POP, STOP
+    code.extend_from_slice(&[0x5a, 0xf1, 0x50, 0x00]);
+    code
+}
+
+/// Zero-fee EIP-1559 transaction from `sender`.
+pub fn transaction(sender: Address, nonce: u64, to: Address, value: U256) -> EncodedTx {
+    let envelope = L2Envelope::from(
+        TxEip1559 {
+            chain_id: CHAIN_ID,
+            nonce,
+            gas_limit: 1_000_000,
+            to: TxKind::Call(to),
+            value,
+            ..Default::default()
+        }
+        .into_signed(Signature::test_signature()),
+    );
+    let tx: ZkTransaction = L2Transaction::new_unchecked(envelope, sender).into();
+    tx.encode()
+}
+
+/// Context of a block with zero base fee executed with the latest execution version.
+pub fn block_context() -> BlockContext {
+    BlockContext {
+        block_number: 1,
+        timestamp: 1,
+        chain_id: CHAIN_ID,
+        gas_limit: 100_000_000,
+        pubdata_limit: 1_000_000,
+        native_price: U256::ONE,
+        execution_version: LATEST_EXECUTION_VERSION as u32,
+        ..Default::default()
+    }
+}
@@ -0,0 +1,152 @@
+//! Transaction-level limits enforced by ZKsync OS, per execution version.
+//!
+//! These mirror the checks the VM performs before executing a transaction, so that the mempool
+//! can reject transactions that would fail them instead of picking and purging them every block.
+
+use crate::ExecutionVersion;
+use alloy::consensus::Transaction;
+
+/// Base cost of every transaction.
+const TX_BASE_GAS: u64 = 21_000;
+const ZERO_BYTE_GAS: u64 = 4;
+const NON_ZERO_BYTE_GAS: u64 = 16;
+/// Extra cost of a deployment transaction.
+const CREATE_GAS: u64 = 32_000;
+/// Cost per 32-byte word of init code (EIP-3860).
+const INITCODE_WORD_GAS: u64 = 2;
+const ACCESS_LIST_ADDRESS_GAS: u64 = 2_400;
+const ACCESS_LIST_STORAGE_KEY_GAS: u64 = 1_900;
+/// Cost per EIP-7702 authorization.
+const AUTHORIZATION_GAS: u64 = 25_000;
+/// Floor cost per calldata token (EIP-7623); a zero byte is 1 token, a non-zero byte is 4 tokens.
+const CALLDATA_FLOOR_GAS_PER_TOKEN: u64 = 10;
+const NON_ZERO_BYTE_TOKENS: u64 = 4;
+
+/// Max init code size (EIP-3860).
+const MAX_INITCODE_BYTES: usize = 2 * 24_576;
+
+/// Limits on a single transaction's size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxLimits {
+    /// Max calldata size of a transaction.
+    pub max_calldata_bytes: usize,
+    /// Max init code size of a deployment transaction.
+    pub max_initcode_bytes: usize,
+}
+
+impl ExecutionVersion {
+    /// Transaction size limits enforced by this execution version.
+    pub fn tx_limits(&self) -> TxLimits {
+        match self {
+            ExecutionVersion::V1 | ExecutionVersion::V2 | ExecutionVersion::V3 => TxLimits {
+                max_calldata_bytes: 128 * 1024,
+                max_initcode_bytes: MAX_INITCODE_BYTES,
+            },
+            ExecutionVersion::V4 => TxLimits {
+                max_calldata_bytes: 1024 * 1024,
+                max_initcode_bytes: MAX_INITCODE_BYTES,
+            },
+        }
+    }
+}
+
+/// Intrinsic gas of `tx`, i.e. the minimum gas limit accepted by the VM of the given `version`.
+///
+/// Starting from V4, calldata is subject to the EIP-7623 floor cost.
+pub fn intrinsic_gas(version: ExecutionVersion, tx: &impl Transaction) -> u64 {
+    let input = tx.input();
+    let zero_bytes = input.iter().filter(|byte| **byte == 0).count() as u64;
+    let non_zero_bytes = input.len() as u64 - zero_bytes;
+
+    let mut gas = TX_BASE_GAS + zero_bytes * ZERO_BYTE_GAS + non_zero_bytes * NON_ZERO_BYTE_GAS;
+    if tx.kind().is_create() {
+        gas += CREATE_GAS + INITCODE_WORD_GAS * (input.len() as u64).div_ceil(32);
+    }
+    if let Some(access_list) = tx.access_list() {
+        let storage_keys: usize = access_list
+            .0
+            .iter()
+            .map(|item| item.storage_keys.len())
+            .sum();
+        gas += access_list.0.len() as u64 * ACCESS_LIST_ADDRESS_GAS
+            + storage_keys as u64 * ACCESS_LIST_STORAGE_KEY_GAS;
+    }
+    gas += tx.authorization_list().map_or(0, |list| list.len() as u64) * AUTHORIZATION_GAS;
+
+    match version {
+        ExecutionVersion::V1 | ExecutionVersion::V2 | ExecutionVersion::V3 => gas,
+        ExecutionVersion::V4 => {
+            let tokens = zero_bytes + non_zero_bytes * NON_ZERO_BYTE_TOKENS;
+            gas.max(TX_BASE_GAS + tokens * CALLDATA_FLOOR_GAS_PER_TOKEN)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::consensus::TxEip1559;
+    use alloy::eips::eip2930::{AccessList, AccessListItem};
+    use alloy::primitives::{Address, B256, Bytes, TxKind};
+
+    const PRE_FLOOR_VERSIONS: [ExecutionVersion; 3] = [
+        ExecutionVersion::V1,
+        ExecutionVersion::V2,
+        ExecutionVersion::V3,
+    ];
+
+    fn call(input: Vec<u8>) -> TxEip1559 {
+        TxEip1559 {
+            to: TxKind::Call(Address::repeat_byte(1)),
+            input: Bytes::from(input),
+            ..TxEip1559::default()
+        }
+    }
+
+    #[test]
+    fn plain_transfer_costs_base_gas() {
+        for version in PRE_FLOOR_VERSIONS.into_iter().chain([ExecutionVersion::V4]) {
+            assert_eq!(intrinsic_gas(version, &call(vec![])), TX_BASE_GAS);
+        }
+    }
+
+    #[test]
+    fn calldata_cost_per_version() {
+        let tx = call(vec![0, 0, 1]);
+        for version in PRE_FLOOR_VERSIONS {
+            assert_eq!(intrinsic_gas(version, &tx), 21_000 + 2 * 4 + 16);
+        }
+        // Floor cost: (2 + 4) tokens * 10
+        assert_eq!(intrinsic_gas(ExecutionVersion::V4, &tx), 21_000 + 60);
+    }
+
+    #[test]
+    fn floor_is_not_applied_if_execution_cost_is_higher() {
+        let access_list = AccessList(vec![AccessListItem {
+            address: Address::repeat_byte(2),
+            storage_keys: vec![B256::ZERO, B256::repeat_byte(1)],
+        }]);
+        let tx = TxEip1559 {
+            access_list,
+            ..call(vec![1; 10])
+        };
+        let expected = 21_000 + 10 * 16 + 2_400 + 2 * 1_900;
+        for version in PRE_FLOOR_VERSIONS.into_iter().chain([ExecutionVersion::V4]) {
+            assert_eq!(intrinsic_gas(version, &tx), expected);
+        }
+    }
+
+    #[test]
+    fn deployment_cost_rounds_init_code_up_to_words() {
+        for (len, words) in [(32, 1), (33, 2)] {
+            let tx = TxEip1559 {
+                to: TxKind::Create,
+                ..call(vec![0; len])
+            };
+            let expected = 21_000 + len as u64 * 4 + 32_000 + words * 2;
+            for version in PRE_FLOOR_VERSIONS.into_iter().chain([ExecutionVersion::V4]) {
+                assert_eq!(intrinsic_gas(version, &tx), expected, "{version:?}, {len}");
+            }
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Registry of supported ZKsync OS execution versions.
+//!
+//! Everything the node needs to know about a version is described by its [`VersionSpec`] in
+//! [`VERSION_SPECS`], which is the only place to touch when adding a new version.
+
+use crate::ExecutionVersion;
+
+/// ZKsync OS build used to run blocks forward.
+///
+/// Forward systems are generic over storage, transaction source and tracer types, so they are
+/// dispatched via this enum rather than trait objects. A variant is only added along with a new
+/// `zk_os_forward_system` dependency; versions sharing a build reuse its variant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ForwardSystem {
+    /// `zk_os_forward_system` v0.0.26. Accepts ABI-encoded transactions only.
+    V0_0_26,
+    /// `zk_os_forward_system` v0.1.
+    V0_1,
+}
+
+/// Description of a supported execution version.
+#[derive(Debug)]
+pub struct VersionSpec {
+    pub version: ExecutionVersion,
+    /// Verification key hash of the circuits proving this version.
+    pub vk_hash: &'static str,
+    /// Version whose circuits prove batches executed with this version,
+    /// see [`proving_run_execution_version`](crate::proving_run_execution_version).
+    pub proving_version: ExecutionVersion,
+    /// Whether the VM can charge the fee of a signed L2 transaction to an account other than its
+    /// sender, as it does for L1 transactions paying with minted funds. Required to include
+    /// sponsored transactions, see `zksync_os_mempool::SponsorshipVoucher`.
+    pub sponsored_fee_payment: bool,
+    pub(crate) forward_system: ForwardSystem,
+}
+
+/// Specs of all supported versions in ascending order.
+///
+/// NOTE: V1 and V2 VK hashes have a slight chance of being off as they've been backfilled.
+/// If you find a divergence in what you expect and the actual value, most likely a bug.
+static VERSION_SPECS: [VersionSpec; 4] = [
+    VersionSpec {
+        version: ExecutionVersion::V1,
+        // Generated from zksync-os v0.0.21, zksync-airbender v0.4.4 and zkos-wrapper v0.4.3
+        vk_hash: "0x80a72fbdf9d6ab299fb5dfc2bcc807cfc7be38c9cfb0bc9b1ce6f9510fb110ea",
+        proving_version: ExecutionVersion::V3,
+        sponsored_fee_payment: false,
+        forward_system: ForwardSystem::V0_0_26,
+    },
+    VersionSpec {
+        version: ExecutionVersion::V2,
+        // Generated from zksync-os v0.0.25, zksync-airbender v0.4.5 and zkos-wrapper v0.4.6
+        vk_hash: "0x83d49897775e6c1f1d7247ec228e18158e8e3accda545c604de4c44eee1a9845",
+        proving_version: ExecutionVersion::V3,
+        sponsored_fee_payment: false,
+        forward_system: ForwardSystem::V0_0_26,
+    },
+    VersionSpec {
+        version: ExecutionVersion::V3,
+        // Generated from zksync-os v0.0.26, zksync-airbender v0.5.0 and zkos-wrapper v0.5.0
+        vk_hash: "0x6a4509801ec284b8921c63dc6aaba668a0d71382d87ae4095ffc2235154e9fa3",
+        proving_version: ExecutionVersion::V3,
+        sponsored_fee_payment: false,
+        forward_system: ForwardSystem::V0_0_26,
+    },
+    VersionSpec {
+        version: ExecutionVersion::V4,
+        // Generated from zksync-os v0.1.0, zksync-airbender v0.5.1 and zkos-wrapper v0.5.3
+        vk_hash: "0xa385a997a63cc78e724451dca8b044b5ef29fcdc9d8b6ced33d9f58de531faa5",
+        proving_version: ExecutionVersion::V4,
+        sponsored_fee_payment: false,
+        forward_system: ForwardSystem::V0_1,
+    },
+];
+
+/// Returns specs of all supported execution versions in ascending order.
+pub fn supported_versions() -> &'static [VersionSpec] {
+    &VERSION_SPECS
+}
+
+impl ExecutionVersion {
+    /// Returns the spec of this version.
+    pub fn spec(&self) -> &'static VersionSpec {
+        VERSION_SPECS
+            .iter()
+            .find(|spec| spec.version == *self)
+            .expect("every execution version has a spec")
+    }
+
+    /// Get the verification key hash associated with this execution version.
+    pub fn vk_hash(&self) -> &'static str {
+        self.spec().vk_hash
+    }
+
+    /// Whether transactions with sponsored fees can be executed with this version.
+    pub fn supports_sponsored_fee_payment(&self) -> bool {
+        self.spec().sponsored_fee_payment
+    }
+
+    /// Try to get ExecutionVersion from verification key hash.
+    pub fn try_from_vk_hash(vk_hash: &str) -> anyhow::Result<Self> {
+        VERSION_SPECS
+            .iter()
+            .find(|spec| spec.vk_hash == vk_hash)
+            .map(|spec| spec.version)
+            .ok_or_else(|| anyhow::anyhow!("unknown verification key hash: {vk_hash}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LATEST_EXECUTION_VERSION;
+    use std::collections::HashSet;
+
+    /// All variants of [`ExecutionVersion`].
+    fn all_versions() -> Vec<ExecutionVersion> {
+        (0..=u8::MAX as u32)
+            .filter_map(|version| ExecutionVersion::try_from(version).ok())
+            .collect()
+    }
+
+    #[test]
+    fn every_version_has_a_spec() {
+        let versions: Vec<_> = supported_versions()
+            .iter()
+            .map(|spec| spec.version)
+            .collect();
+        assert_eq!(versions, all_versions());
+        assert_eq!(versions.last(), Some(&LATEST_EXECUTION_VERSION));
+
+        for spec in supported_versions() {
+            assert_eq!(spec.version.spec().version, spec.version);
+            // Batches are proven with versions that prove themselves
+            let proving_spec = spec.proving_version.spec();
+            assert_eq!(proving_spec.proving_version, spec.proving_version);
+        }
+    }
+
+    #[test]
+    fn vk_hashes_are_unique() {
+        let vk_hashes: HashSet<_> = supported_versions()
+            .iter()
+            .map(|spec| spec.vk_hash)
+            .collect();
+        assert_eq!(vk_hashes.len(), supported_versions().len());
+
+        for version in all_versions() {
+            assert_eq!(
+                ExecutionVersion::try_from_vk_hash(version.vk_hash()).unwrap(),
+                version
+            );
+        }
+        ExecutionVersion::try_from_vk_hash("0x00").unwrap_err();
+    }
+}
@@ -5,12 +5,14 @@
 //! - A background task periodically (every TICK_SECS) increments a single metric family
 //!   `component_time_spent_in_state[component, GenericComponentState, specific_state]` with
 //!   time spent in the current state. Transitions are also finalized immediately on EnterState.
+//! - The current state of each component can be read back with
+//!   `ComponentStateReporter::current_state(...)`, e.g. to diagnose stalls.
 //!
 //!
 use crate::generic_component_state::GenericComponentState;
 use crate::metrics::GENERAL_METRICS;
 use std::collections::HashMap;
-use std::sync::OnceLock;
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::Instant;
 use tokio::sync::mpsc::{self, Receiver, Sender};
 
@@ -54,9 +56,13 @@ impl RegistryEntry {
     }
 }
 
+/// Specific state label of each component, updated synchronously on `enter_state`.
+type CurrentStates = Arc<Mutex<HashMap<&'static str, &'static str>>>;
+
 #[derive(Clone)]
 pub struct ComponentStateReporter {
     tx: Sender<ReporterMsg>,
+    current: CurrentStates,
 }
 
 impl ComponentStateReporter {
@@ -69,7 +75,15 @@ impl ComponentStateReporter {
         let (tx, rx) = mpsc::channel(512);
         // Spawn background task
         tokio::spawn(run_reporter(rx));
-        Self { tx }
+        Self {
+            tx,
+            current: CurrentStates::default(),
+        }
+    }
+
+    /// Specific label of the state `component` is currently in, if it has reported any.
+    pub fn current_state(&self, component: &str) -> Option<&'static str> {
+        self.current.lock().unwrap().get(component).copied()
     }
 
     pub fn handle_for<S>(
@@ -80,16 +94,14 @@ impl ComponentStateReporter {
     where
         S: StateLabel,
     {
-        let _ = self.tx.try_send(ReporterMsg {
-            component,
-            new_label: Box::new(initial_state),
-        });
-
-        ComponentStateHandle {
+        let handle = ComponentStateHandle {
             component,
             tx: self.tx.clone(),
+            current: self.current.clone(),
             _marker: std::marker::PhantomData,
-        }
+        };
+        handle.enter_state(initial_state);
+        handle
     }
 }
 
@@ -97,11 +109,16 @@ impl ComponentStateReporter {
 pub struct ComponentStateHandle<S> {
     component: &'static str,
     tx: Sender<ReporterMsg>,
+    current: CurrentStates,
     _marker: std::marker::PhantomData<S>,
 }
 
 impl<S: StateLabel> ComponentStateHandle<S> {
     pub fn enter_state(&self, new_state: S) {
+        self.current
+            .lock()
+            .unwrap()
+            .insert(self.component, new_state.specific());
         let _ = self.tx.try_send(ReporterMsg {
             component: self.component,
             new_label: Box::new(new_state),
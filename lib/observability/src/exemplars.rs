@@ -0,0 +1,336 @@
+//! Exemplars for `vise` histograms.
+//!
+//! An exemplar links a histogram bucket to a specific observation that fell into it (e.g., the block
+//! or batch that took this long), so that dashboards can deep-link from a latency spike to the
+//! offending block. `vise` doesn't support exemplars, so they are kept in [`HistogramExemplars`]
+//! alongside the histogram and merged into the OpenMetrics exposition by [`merge_exemplars()`].
+//!
+//! Memory is strictly bounded: each histogram keeps at most [`MAX_SERIES`] label sets, with
+//! a single (latest) exemplar per a quarter of a power of 2 of the observed value.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use opentelemetry::trace::TraceContextExt;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use vise::Histogram;
+
+/// Maximum number of label sets (i.e., labeled histograms in a family) tracked per histogram.
+/// Exemplars for other label sets are dropped.
+pub const MAX_SERIES: usize = 64;
+
+/// Exemplars are bucketed by `floor(log2(value) * SLOTS_PER_OCTAVE)`, so that each histogram bucket
+/// (which are at least this wide for all histograms we use) has its own exemplar.
+const SLOTS_PER_OCTAVE: f64 = 4.0;
+/// Bounds the number of slots per label set; values outside of `2^(±MAX_SLOT / SLOTS_PER_OCTAVE)`
+/// share the outermost slots.
+const MAX_SLOT: i32 = 256;
+
+/// Histograms with exemplars, registered on their first observation.
+static REGISTERED: Mutex<Vec<&'static HistogramExemplars>> = Mutex::new(Vec::new());
+
+/// Labels identifying an observation, e.g., the number of the block it was made for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExemplarLabels(Vec<(&'static str, String)>);
+
+impl ExemplarLabels {
+    /// Labels an observation made for the block with the specified number.
+    pub fn block(number: u64) -> Self {
+        Self::new("block_number", number)
+    }
+
+    /// Labels an observation made for the batch with the specified number.
+    pub fn batch(number: u64) -> Self {
+        Self::new("batch_number", number)
+    }
+
+    /// Also includes the ID of the current OpenTelemetry trace if traces are exported.
+    fn new(name: &'static str, number: u64) -> Self {
+        let mut labels = vec![(name, number.to_string())];
+        let context = tracing::Span::current().context();
+        let span = context.span();
+        let span_context = span.span_context();
+        if span_context.is_valid() {
+            labels.push(("trace_id", span_context.trace_id().to_string()));
+        }
+        Self(labels)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Exemplar {
+    labels: ExemplarLabels,
+    value: f64,
+    /// Unix timestamp in seconds.
+    timestamp: f64,
+}
+
+/// Latest exemplars for each label set of a histogram (or a histogram family).
+#[derive(Debug)]
+pub struct HistogramExemplars {
+    /// Metric name as it appears in the exposition, including the unit suffix (e.g.,
+    /// `tree_block_time_seconds`).
+    metric_name: &'static str,
+    registered: AtomicBool,
+    /// Keyed by label set (`label="value"` pairs in the exposition order) and value slot.
+    series: Mutex<BTreeMap<String, BTreeMap<i32, Exemplar>>>,
+}
+
+impl HistogramExemplars {
+    pub const fn new(metric_name: &'static str) -> Self {
+        Self {
+            metric_name,
+            registered: AtomicBool::new(false),
+            series: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Observes `duration` in `histogram` and records it as an exemplar for the histogram labeled
+    /// with `series` (label names and values, in their declaration order; empty for unlabeled
+    /// histograms).
+    pub fn observe(
+        &'static self,
+        histogram: &Histogram<Duration>,
+        series: &[(&str, &str)],
+        duration: Duration,
+        labels: ExemplarLabels,
+    ) {
+        histogram.observe(duration);
+        self.record(series, duration.as_secs_f64(), labels);
+    }
+
+    /// Records an exemplar without observing it in the histogram.
+    pub fn record(&'static self, series: &[(&str, &str)], value: f64, labels: ExemplarLabels) {
+        if !self.registered.swap(true, Ordering::Relaxed) {
+            REGISTERED.lock().unwrap().push(self);
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let key = series_key(series.iter().copied());
+
+        let mut all_series = self.series.lock().unwrap();
+        if !all_series.contains_key(&key) && all_series.len() >= MAX_SERIES {
+            return;
+        }
+        all_series.entry(key).or_default().insert(
+            slot(value),
+            Exemplar {
+                labels,
+                value,
+                timestamp,
+            },
+        );
+    }
+
+    /// Returns the latest exemplar for the `series` with a value in `(lower_bound, upper_bound]`.
+    fn latest_in_bucket(
+        &self,
+        series: &str,
+        lower_bound: f64,
+        upper_bound: f64,
+    ) -> Option<Exemplar> {
+        let all_series = self.series.lock().unwrap();
+        all_series
+            .get(series)?
+            .range(slot(lower_bound)..=slot(upper_bound))
+            .map(|(_, exemplar)| exemplar)
+            .filter(|exemplar| exemplar.value > lower_bound && exemplar.value <= upper_bound)
+            .max_by(|a, b| a.timestamp.total_cmp(&b.timestamp))
+            .cloned()
+    }
+}
+
+fn slot(value: f64) -> i32 {
+    if value.is_nan() || value <= 0.0 {
+        return i32::MIN;
+    }
+    (value.log2() * SLOTS_PER_OCTAVE)
+        .floor()
+        .clamp(-MAX_SLOT as f64, MAX_SLOT as f64) as i32
+}
+
+fn series_key<'a>(labels: impl Iterator<Item = (&'a str, &'a str)>) -> String {
+    let mut key = String::new();
+    for (name, value) in labels {
+        if !key.is_empty() {
+            key.push(',');
+        }
+        write!(key, "{name}=\"{value}\"").unwrap();
+    }
+    key
+}
+
+/// Parses a histogram bucket line, e.g. `foo_bucket{stage="a",le="0.5"} 3`, into the label set
+/// (without `le`) and the bucket upper bound.
+fn parse_bucket_line(labels: &str) -> Option<(String, f64)> {
+    let mut upper_bound = None;
+    let mut series = vec![];
+    for pair in labels.split(',').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=')?;
+        let value = value.strip_prefix('"')?.strip_suffix('"')?;
+        if name == "le" {
+            upper_bound = Some(match value {
+                "+Inf" => f64::INFINITY,
+                _ => value.parse().ok()?,
+            });
+        } else {
+            series.push((name, value));
+        }
+    }
+    Some((series_key(series.into_iter()), upper_bound?))
+}
+
+/// Appends exemplars of all registered histograms to the corresponding bucket lines of
+/// an OpenMetrics exposition.
+pub fn merge_exemplars(exposition: &str) -> String {
+    let registered = REGISTERED.lock().unwrap().clone();
+    merge_exemplars_for(exposition, &registered)
+}
+
+fn merge_exemplars_for(exposition: &str, histograms: &[&HistogramExemplars]) -> String {
+    let mut merged = String::with_capacity(exposition.len());
+    // Label set and upper bound of the previous bucket line, which is the lower bound of the next one
+    let mut previous_bucket: Option<(String, f64)> = None;
+    for line in exposition.lines() {
+        merged.push_str(line);
+        let exemplar = histograms.iter().find_map(|histogram| {
+            let labels = line
+                .strip_prefix(histogram.metric_name)?
+                .strip_prefix("_bucket{")?;
+            let (labels, _) = labels.split_once('}')?;
+            let (series, upper_bound) = parse_bucket_line(labels)?;
+            let lower_bound = match previous_bucket.take() {
+                Some((previous_series, bound)) if previous_series == series => bound,
+                _ => f64::NEG_INFINITY,
+            };
+            let exemplar = histogram.latest_in_bucket(&series, lower_bound, upper_bound);
+            previous_bucket = Some((series, upper_bound));
+            Some(exemplar)
+        });
+        match exemplar {
+            Some(Some(exemplar)) => {
+                let labels = series_key(
+                    exemplar
+                        .labels
+                        .0
+                        .iter()
+                        .map(|(name, value)| (*name, value.as_str())),
+                );
+                write!(
+                    merged,
+                    " # {{{labels}}} {} {:.3}",
+                    exemplar.value, exemplar.timestamp
+                )
+                .unwrap();
+            }
+            Some(None) => {}
+            None => previous_bucket = None,
+        }
+        merged.push('\n');
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPOSITION: &str = "\
+# TYPE test_stage_seconds histogram
+# UNIT test_stage_seconds seconds
+test_stage_seconds_sum{stage=\"execute\"} 2.6
+test_stage_seconds_count{stage=\"execute\"} 3
+test_stage_seconds_bucket{stage=\"execute\",le=\"0.1\"} 1
+test_stage_seconds_bucket{stage=\"execute\",le=\"1.0\"} 2
+test_stage_seconds_bucket{stage=\"execute\",le=\"+Inf\"} 3
+test_stage_seconds_bucket{stage=\"seal\",le=\"0.1\"} 0
+test_stage_seconds_bucket{stage=\"seal\",le=\"1.0\"} 0
+test_stage_seconds_bucket{stage=\"seal\",le=\"+Inf\"} 0
+# EOF
+";
+
+    fn exemplar_of<'a>(exposition: &'a str, line_prefix: &str) -> Option<&'a str> {
+        let line = exposition
+            .lines()
+            .find(|line| line.starts_with(line_prefix))
+            .unwrap();
+        line.split_once(" # ").map(|(_, exemplar)| exemplar)
+    }
+
+    #[test]
+    fn exemplars_are_attached_to_buckets() {
+        static EXEMPLARS: HistogramExemplars = HistogramExemplars::new("test_stage_seconds");
+        let execute = [("stage", "execute")];
+        EXEMPLARS.record(&execute, 0.05, ExemplarLabels::block(1));
+        EXEMPLARS.record(&execute, 0.5, ExemplarLabels::block(2));
+        EXEMPLARS.record(&execute, 2.0, ExemplarLabels::block(3));
+        // Replaces the exemplar for block 2 in the same bucket
+        EXEMPLARS.record(&execute, 0.55, ExemplarLabels::batch(4));
+
+        let merged = merge_exemplars_for(EXPOSITION, &[&EXEMPLARS]);
+        let exemplar = exemplar_of(
+            &merged,
+            "test_stage_seconds_bucket{stage=\"execute\",le=\"0.1\"}",
+        );
+        assert!(
+            exemplar.unwrap().starts_with("{block_number=\"1\"} 0.05 "),
+            "{merged}"
+        );
+        let exemplar = exemplar_of(
+            &merged,
+            "test_stage_seconds_bucket{stage=\"execute\",le=\"1.0\"}",
+        );
+        assert!(
+            exemplar.unwrap().starts_with("{batch_number=\"4\"} 0.55 "),
+            "{merged}"
+        );
+        let exemplar = exemplar_of(
+            &merged,
+            "test_stage_seconds_bucket{stage=\"execute\",le=\"+Inf\"}",
+        );
+        assert!(
+            exemplar.unwrap().starts_with("{block_number=\"3\"} 2 "),
+            "{merged}"
+        );
+        // Other series and lines are not changed
+        for prefix in [
+            "test_stage_seconds_bucket{stage=\"seal\"",
+            "test_stage_seconds_sum",
+            "# EOF",
+        ] {
+            assert_eq!(exemplar_of(&merged, prefix), None, "{merged}");
+        }
+        assert_eq!(merged.lines().count(), EXPOSITION.lines().count());
+    }
+
+    #[test]
+    fn exemplar_store_is_bounded() {
+        static EXEMPLARS: HistogramExemplars = HistogramExemplars::new("test_bounded_seconds");
+        for i in 0..(MAX_SERIES * 2) {
+            let stage = i.to_string();
+            for block in 0..100 {
+                EXEMPLARS.record(&[("stage", &stage)], 0.01, ExemplarLabels::block(block));
+            }
+            EXEMPLARS.record(&[("stage", &stage)], f64::MAX, ExemplarLabels::block(0));
+            EXEMPLARS.record(
+                &[("stage", &stage)],
+                f64::MIN_POSITIVE,
+                ExemplarLabels::block(0),
+            );
+        }
+        let series = EXEMPLARS.series.lock().unwrap();
+        assert_eq!(series.len(), MAX_SERIES);
+        for slots in series.values() {
+            assert_eq!(slots.len(), 3);
+            assert_eq!(
+                slots.keys().copied().collect::<Vec<_>>(),
+                [-MAX_SLOT, slot(0.01), MAX_SLOT]
+            );
+        }
+    }
+}
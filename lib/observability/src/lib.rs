@@ -13,6 +13,7 @@ pub use crate::{
     sentry::Sentry,
 };
 
+pub mod exemplars;
 pub mod logs;
 pub mod opentelemetry;
 pub mod prometheus;
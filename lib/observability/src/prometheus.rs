@@ -1,12 +1,24 @@
 //! Prometheus-related functionality, such as [`PrometheusExporterConfig`].
 
-use std::{env, net::Ipv4Addr, time::Duration};
+use std::{env, net::Ipv4Addr, sync::Arc, time::Duration};
 
 use anyhow::Context as _;
-use tokio::sync::watch;
-use vise::MetricsCollection;
+use axum::{
+    Router,
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+};
+use tokio::{net::TcpListener, sync::watch};
+use vise::{Format, MetricsCollection, Registry};
 use vise_exporter::MetricsExporter;
 
+use crate::exemplars::merge_exemplars;
+
+/// Exemplars are only supported by the OpenMetrics exposition format.
+const OPEN_METRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
 #[derive(Debug, Clone)]
 enum PrometheusTransport {
     Pull {
@@ -52,20 +64,24 @@ impl PrometheusExporterConfig {
     }
 
     /// Runs the exporter. This future should be spawned in a separate Tokio task.
+    ///
+    /// The pull server merges [exemplars](crate::exemplars) into the exposition; the push gateway
+    /// doesn't support exemplars, so they are not pushed.
     pub async fn run(self, mut stop_receiver: watch::Receiver<bool>) -> anyhow::Result<()> {
-        let registry = MetricsCollection::lazy().collect();
-        let metrics_exporter =
-            MetricsExporter::new(registry.into()).with_graceful_shutdown(async move {
-                stop_receiver.changed().await.ok();
-            });
+        let registry = Arc::new(MetricsCollection::lazy().collect());
 
         match self.transport {
             PrometheusTransport::Pull { port } => {
-                let prom_bind_address = (Ipv4Addr::UNSPECIFIED, port).into();
-                metrics_exporter
-                    .start(prom_bind_address)
+                let app = Router::new().fallback(serve_metrics).with_state(registry);
+                let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, port))
                     .await
                     .context("Failed starting metrics server")?;
+                axum::serve(listener, app)
+                    .with_graceful_shutdown(async move {
+                        stop_receiver.changed().await.ok();
+                    })
+                    .await
+                    .context("Metrics server failed")?;
             }
             PrometheusTransport::Push {
                 gateway_uri,
@@ -74,9 +90,27 @@ impl PrometheusExporterConfig {
                 let endpoint = gateway_uri
                     .parse()
                     .context("Failed parsing Prometheus push gateway endpoint")?;
-                metrics_exporter.push_to_gateway(endpoint, interval).await;
+                MetricsExporter::new(registry)
+                    .with_graceful_shutdown(async move {
+                        stop_receiver.changed().await.ok();
+                    })
+                    .push_to_gateway(endpoint, interval)
+                    .await;
             }
         }
         Ok(())
     }
 }
+
+async fn serve_metrics(State(registry): State<Arc<Registry>>) -> Response {
+    let mut exposition = String::new();
+    if let Err(err) = registry.encode(&mut exposition, Format::OpenMetricsForPrometheus) {
+        tracing::warn!("Failed encoding metrics: {err}");
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+    (
+        [(header::CONTENT_TYPE, OPEN_METRICS_CONTENT_TYPE)],
+        merge_exemplars(&exposition),
+    )
+        .into_response()
+}
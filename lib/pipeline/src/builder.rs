@@ -1,9 +1,12 @@
-use crate::PipelineComponent;
+use crate::join::{JoinPolicy, Joined, JoinedReceiver};
 use crate::peekable_receiver::PeekableReceiver;
+use crate::{PipelineComponent, PipelineComponent2};
 use anyhow::Result;
+use async_trait::async_trait;
 use futures::FutureExt;
 use futures::future::BoxFuture;
 use tokio::sync::mpsc;
+use zksync_os_error_reporting::report_error;
 
 /// A named pipeline task: component name and its spawnable task function
 type PipelineTask = (&'static str, BoxFuture<'static, Result<()>>);
@@ -36,7 +39,10 @@ impl Pipeline<()> {
             tasks.spawn(async move {
                 match task_fn.await {
                     Ok(_) => tracing::warn!("{name} component unexpectedly exited"),
-                    Err(err) => tracing::error!(?err, "{name} component failed"),
+                    Err(err) => {
+                        let _span = tracing::error_span!("pipeline", component = name).entered();
+                        report_error(&err);
+                    }
                 }
             });
         }
@@ -65,6 +71,121 @@ impl<Output: Send + 'static> Pipeline<Output> {
         }
     }
 
+    /// Add a two-input component. The output of this pipeline is its first input, and the output
+    /// of `other` is the second one.
+    pub fn join<C>(mut self, other: Pipeline<C::InputB>, component: C) -> Pipeline<C::Output>
+    where
+        C: PipelineComponent2<InputA = Output>,
+    {
+        let (output_sender, output_receiver) = mpsc::channel(C::OUTPUT_BUFFER_SIZE);
+        let input = JoinedReceiver::new(
+            C::NAME,
+            C::INPUT_NAMES,
+            component.join_policy(),
+            self.receiver,
+            other.receiver,
+        );
+
+        self.tasks.extend(other.tasks);
+        self.tasks.push((
+            C::NAME,
+            async move { component.run(input, output_sender).await }.boxed(),
+        ));
+
+        Pipeline {
+            tasks: self.tasks,
+            receiver: PeekableReceiver::new(output_receiver),
+        }
+    }
+
+    /// Zip the output of this pipeline with the output of `other` by block number, so that
+    /// a single-input component can consume them as pairs.
+    pub fn zip<B: Send + 'static>(
+        self,
+        other: Pipeline<B>,
+        block_number_a: fn(&Output) -> u64,
+        block_number_b: fn(&B) -> u64,
+    ) -> Pipeline<(Output, B)> {
+        self.join(
+            other,
+            Zip {
+                block_number_a,
+                block_number_b,
+            },
+        )
+    }
+
+    /// Split the output of this pipeline into two streams with `f`, e.g. to feed both inputs of
+    /// a [`PipelineComponent2`]. Each stream is buffered separately with `buffer_size` items.
+    pub fn split<A, B, F>(
+        mut self,
+        name: &'static str,
+        buffer_size: usize,
+        f: F,
+    ) -> (Pipeline<A>, Pipeline<B>)
+    where
+        A: Send + 'static,
+        B: Send + 'static,
+        F: Fn(Output) -> (A, B) + Send + 'static,
+    {
+        let (sender_a, receiver_a) = mpsc::channel(buffer_size);
+        let (sender_b, receiver_b) = mpsc::channel(buffer_size);
+        let mut input = self.receiver;
+
+        self.tasks.push((
+            name,
+            async move {
+                while let Some(item) = input.recv().await {
+                    let (a, b) = f(item);
+                    if sender_a.send(a).await.is_err() || sender_b.send(b).await.is_err() {
+                        anyhow::bail!("outbound channel closed");
+                    }
+                }
+                Ok(())
+            }
+            .boxed(),
+        ));
+
+        // Tasks are kept by the first pipeline; they are merged back on `join`
+        let a = Pipeline {
+            tasks: self.tasks,
+            receiver: PeekableReceiver::new(receiver_a),
+        };
+        let b = Pipeline {
+            tasks: vec![],
+            receiver: PeekableReceiver::new(receiver_b),
+        };
+        (a, b)
+    }
+
+    /// Transform each item of the output with `f`.
+    pub fn map<T, F>(mut self, name: &'static str, buffer_size: usize, f: F) -> Pipeline<T>
+    where
+        T: Send + 'static,
+        F: Fn(Output) -> T + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel(buffer_size);
+        let mut input = self.receiver;
+
+        self.tasks.push((
+            name,
+            async move {
+                while let Some(item) = input.recv().await {
+                    if sender.send(f(item)).await.is_err() {
+                        anyhow::bail!("outbound channel closed");
+                    }
+                }
+                Ok(())
+            }
+            .boxed(),
+        ));
+
+        Pipeline {
+            tasks: self.tasks,
+            receiver: PeekableReceiver::new(receiver),
+        }
+    }
+
     /// Conditionally add a component if present. The component must keep the same item type.
     pub fn pipe_opt<C>(self, component: Option<C>) -> Pipeline<Output>
     where
@@ -93,3 +214,42 @@ impl<Output: Send + 'static> Pipeline<Output> {
         }
     }
 }
+
+/// Adapter feeding pairs of items with the same block number to a single-input component.
+struct Zip<A, B> {
+    block_number_a: fn(&A) -> u64,
+    block_number_b: fn(&B) -> u64,
+}
+
+#[async_trait]
+impl<A: Send + 'static, B: Send + 'static> PipelineComponent2 for Zip<A, B> {
+    type InputA = A;
+    type InputB = B;
+    type Output = (A, B);
+
+    const NAME: &'static str = "zip";
+    const OUTPUT_BUFFER_SIZE: usize = 1;
+
+    fn join_policy(&self) -> JoinPolicy<A, B> {
+        JoinPolicy::ZipByBlockNumber {
+            block_number_a: self.block_number_a,
+            block_number_b: self.block_number_b,
+        }
+    }
+
+    async fn run(
+        self,
+        mut input: JoinedReceiver<A, B>,
+        output: mpsc::Sender<(A, B)>,
+    ) -> Result<()> {
+        while let Some(item) = input.recv().await {
+            let Joined::Both(a, b) = item else {
+                unreachable!("zipped inputs are received in pairs");
+            };
+            if output.send((a, b)).await.is_err() {
+                anyhow::bail!("outbound channel closed");
+            }
+        }
+        Ok(())
+    }
+}
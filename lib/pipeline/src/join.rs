@@ -0,0 +1,224 @@
+use crate::metrics::PIPELINE_METRICS;
+use crate::peekable_receiver::PeekableReceiver;
+use std::cmp::Ordering;
+
+/// How a [`PipelineComponent2`](crate::PipelineComponent2) consumes its two inputs.
+pub enum JoinPolicy<A, B> {
+    /// Items are matched by block number and received in pairs. Both inputs must be ordered by
+    /// block number; an item without a counterpart in the other input (i.e. the other input has
+    /// already moved past its block number) is dropped with a warning.
+    ZipByBlockNumber {
+        block_number_a: fn(&A) -> u64,
+        block_number_b: fn(&B) -> u64,
+    },
+    /// Items are received from whichever input has one available, so that a stall in one
+    /// upstream doesn't hold back the other one.
+    Independent,
+}
+
+impl<A, B> Clone for JoinPolicy<A, B> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A, B> Copy for JoinPolicy<A, B> {}
+
+/// Item received by [`JoinedReceiver::recv`].
+#[derive(Debug)]
+pub enum Joined<A, B> {
+    /// Matched items; the only variant returned for [`JoinPolicy::ZipByBlockNumber`].
+    Both(A, B),
+    A(A),
+    B(B),
+}
+
+/// Two inputs of a [`PipelineComponent2`](crate::PipelineComponent2), each with its own buffered
+/// channel, joined according to a [`JoinPolicy`].
+///
+/// Buffer depth of each input is reported in metrics after each received item.
+pub struct JoinedReceiver<A, B> {
+    component: &'static str,
+    input_names: [&'static str; 2],
+    policy: JoinPolicy<A, B>,
+    a: PeekableReceiver<A>,
+    b: PeekableReceiver<B>,
+}
+
+impl<A, B> JoinedReceiver<A, B> {
+    pub fn new(
+        component: &'static str,
+        input_names: [&'static str; 2],
+        policy: JoinPolicy<A, B>,
+        a: PeekableReceiver<A>,
+        b: PeekableReceiver<B>,
+    ) -> Self {
+        Self {
+            component,
+            input_names,
+            policy,
+            a,
+            b,
+        }
+    }
+
+    /// Receives the next item according to the join policy, awaiting if necessary.
+    ///
+    /// Returns `None` once the inputs are exhausted: when either input is closed for
+    /// [`JoinPolicy::ZipByBlockNumber`], or when both are closed for [`JoinPolicy::Independent`].
+    ///
+    /// Cancel-safe: items received from the channels are buffered until they are returned.
+    pub async fn recv(&mut self) -> Option<Joined<A, B>> {
+        let item = match self.policy {
+            JoinPolicy::ZipByBlockNumber {
+                block_number_a,
+                block_number_b,
+            } => self.recv_zipped(block_number_a, block_number_b).await,
+            JoinPolicy::Independent => {
+                tokio::select! {
+                    Some(a) = self.a.recv() => Some(Joined::A(a)),
+                    Some(b) = self.b.recv() => Some(Joined::B(b)),
+                    else => None,
+                }
+            }
+        };
+        self.report_depth();
+        item
+    }
+
+    async fn recv_zipped(
+        &mut self,
+        block_number_a: fn(&A) -> u64,
+        block_number_b: fn(&B) -> u64,
+    ) -> Option<Joined<A, B>> {
+        loop {
+            // Items stay buffered until both heads are available, so either input may arrive first
+            let a = self.a.peek_recv(block_number_a).await?;
+            let b = self.b.peek_recv(block_number_b).await?;
+            match a.cmp(&b) {
+                Ordering::Equal => {
+                    let a = self.a.pop_buffer().expect("peeked item is buffered");
+                    let b = self.b.pop_buffer().expect("peeked item is buffered");
+                    return Some(Joined::Both(a, b));
+                }
+                Ordering::Less => {
+                    self.a.pop_buffer();
+                    self.on_unmatched(0, a, b);
+                }
+                Ordering::Greater => {
+                    self.b.pop_buffer();
+                    self.on_unmatched(1, b, a);
+                }
+            }
+        }
+    }
+
+    fn on_unmatched(&self, input: usize, block_number: u64, other_block_number: u64) {
+        let input = self.input_names[input];
+        tracing::warn!(
+            component = self.component,
+            input,
+            block_number,
+            other_block_number,
+            "dropping pipeline input item without a counterpart in the other input"
+        );
+        PIPELINE_METRICS.unmatched_inputs[&(self.component, input)].inc();
+    }
+
+    fn report_depth(&self) {
+        let [name_a, name_b] = self.input_names;
+        PIPELINE_METRICS.input_depth[&(self.component, name_a)].set(self.a.len());
+        PIPELINE_METRICS.input_depth[&(self.component, name_b)].set(self.b.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+
+    fn zip_policy() -> JoinPolicy<(u64, &'static str), u64> {
+        JoinPolicy::ZipByBlockNumber {
+            block_number_a: |(number, _)| *number,
+            block_number_b: |number| *number,
+        }
+    }
+
+    fn joined<A, B>(
+        policy: JoinPolicy<A, B>,
+        buffer_size: usize,
+    ) -> (mpsc::Sender<A>, mpsc::Sender<B>, JoinedReceiver<A, B>) {
+        let (sender_a, receiver_a) = mpsc::channel(buffer_size);
+        let (sender_b, receiver_b) = mpsc::channel(buffer_size);
+        let receiver = JoinedReceiver::new(
+            "test",
+            ["a", "b"],
+            policy,
+            PeekableReceiver::new(receiver_a),
+            PeekableReceiver::new(receiver_b),
+        );
+        (sender_a, sender_b, receiver)
+    }
+
+    #[tokio::test]
+    async fn zip_by_block_number_with_out_of_order_arrival() {
+        let (sender_a, sender_b, mut receiver) = joined(zip_policy(), 10);
+
+        // Input B is ahead of input A
+        for number in 1..=3 {
+            sender_b.send(number).await.unwrap();
+        }
+        let recv = tokio::spawn(async move {
+            let mut pairs = vec![];
+            while let Some(item) = receiver.recv().await {
+                match item {
+                    Joined::Both(a, b) => pairs.push((a, b)),
+                    _ => panic!("unexpected item: {item:?}"),
+                }
+            }
+            pairs
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        sender_a.send((1, "one")).await.unwrap();
+        // Block 2 is missing in input A; its counterpart in B is dropped
+        sender_a.send((3, "three")).await.unwrap();
+        // Then input A is ahead of input B
+        sender_a.send((4, "four")).await.unwrap();
+        sender_a.send((5, "five")).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        sender_b.send(5).await.unwrap();
+        drop((sender_a, sender_b));
+
+        let pairs = recv.await.unwrap();
+        assert_eq!(
+            pairs,
+            [((1, "one"), 1), ((3, "three"), 3), ((5, "five"), 5)]
+        );
+    }
+
+    #[tokio::test]
+    async fn independent_inputs_are_isolated() {
+        let (sender_a, sender_b, mut receiver) = joined::<u64, u64>(JoinPolicy::Independent, 2);
+
+        // Input A stalls; input B keeps flowing past its buffer size
+        let producer_b = tokio::spawn(async move {
+            for number in 0..5 {
+                sender_b.send(number).await.unwrap();
+            }
+        });
+        for expected in 0..5 {
+            let item = tokio::time::timeout(Duration::from_secs(1), receiver.recv())
+                .await
+                .expect("input B is blocked by input A");
+            assert!(matches!(item, Some(Joined::B(number)) if number == expected));
+        }
+        producer_b.await.unwrap();
+
+        // Closing one input doesn't stop the other one
+        sender_a.send(10).await.unwrap();
+        drop(sender_a);
+        assert!(matches!(receiver.recv().await, Some(Joined::A(10))));
+        assert!(receiver.recv().await.is_none());
+    }
+}
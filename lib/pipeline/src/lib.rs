@@ -8,12 +8,16 @@
 //!
 //! - **Source**: Components that generate messages (command producers)
 //! - **PipelineComponent**: Components that transform messages (e.g., batchers, provers)
+//! - **PipelineComponent2**: Components that join two inputs (e.g., blocks and their tree data)
 //! - **Sink**: End of pipeline (e.g. BatchSink)
 
 pub mod builder;
+pub mod join;
+mod metrics;
 pub mod peekable_receiver;
 pub mod traits;
 
 pub use builder::Pipeline;
+pub use join::{JoinPolicy, Joined, JoinedReceiver};
 pub use peekable_receiver::PeekableReceiver;
-pub use traits::PipelineComponent;
+pub use traits::{PipelineComponent, PipelineComponent2};
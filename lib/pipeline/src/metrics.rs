@@ -0,0 +1,15 @@
+use vise::{Counter, Gauge, LabeledFamily, Metrics};
+
+#[derive(Debug, Metrics)]
+#[metrics(prefix = "pipeline")]
+pub(crate) struct PipelineMetrics {
+    /// Number of items waiting in an input of a multi-input component, including peeked items.
+    #[metrics(labels = ["component", "input"])]
+    pub input_depth: LabeledFamily<(&'static str, &'static str), Gauge<usize>, 2>,
+    /// Number of input items dropped for not having a counterpart with the same block number.
+    #[metrics(labels = ["component", "input"])]
+    pub unmatched_inputs: LabeledFamily<(&'static str, &'static str), Counter, 2>,
+}
+
+#[vise::register]
+pub(crate) static PIPELINE_METRICS: vise::Global<PipelineMetrics> = vise::Global::new();
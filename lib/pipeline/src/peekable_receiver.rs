@@ -188,6 +188,28 @@ impl<T> PeekableReceiver<T> {
         out
     }
 
+    /// Consumes the first of the next `limit` items for which `f` returns true, keeping the
+    /// order of the other items.
+    ///
+    /// Non-blocking: uses only `try_recv()` to extend the local buffer.
+    pub fn try_recv_first<F>(&mut self, limit: usize, mut f: F) -> Option<T>
+    where
+        F: FnMut(&T) -> bool,
+    {
+        for i in 0..limit {
+            if self.buf.len() <= i {
+                match self.rx.try_recv() {
+                    Ok(v) => self.buf.push_back(v),
+                    Err(_) => break, // channel empty or disconnected; nothing more to look at
+                }
+            }
+            if f(&self.buf[i]) {
+                return self.buf.remove(i);
+            }
+        }
+        None
+    }
+
     /// Returns `true` if the channel is closed and no further messages will arrive.
     /// Note: There still may be buffered items locally.
     pub fn is_closed(&self) -> bool {
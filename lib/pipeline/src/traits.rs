@@ -1,3 +1,4 @@
+use crate::join::{JoinPolicy, JoinedReceiver};
 use crate::peekable_receiver::PeekableReceiver;
 use anyhow::Result;
 use async_trait::async_trait;
@@ -28,3 +29,39 @@ pub trait PipelineComponent: Send + 'static {
         output: mpsc::Sender<Self::Output>,
     ) -> Result<()>;
 }
+
+/// A component with two inputs, e.g. joining sequenced blocks with data computed for them by
+/// another component.
+///
+/// Each input has its own buffered channel, so that a stall in one upstream doesn't block the other
+/// one until its buffer fills up. Single-input components keep implementing [`PipelineComponent`].
+#[async_trait]
+pub trait PipelineComponent2: Send + 'static {
+    /// The type of messages this component receives from the first input
+    type InputA: Send + 'static;
+
+    /// The type of messages this component receives from the second input
+    type InputB: Send + 'static;
+
+    /// The type of messages this component produces
+    type Output: Send + 'static;
+
+    /// Human-readable name for logging and metrics
+    const NAME: &'static str;
+
+    /// Names of the inputs for logging and metrics
+    const INPUT_NAMES: [&'static str; 2] = ["a", "b"];
+
+    /// Buffer size for the output channel
+    const OUTPUT_BUFFER_SIZE: usize;
+
+    /// How items of the two inputs are received
+    fn join_policy(&self) -> JoinPolicy<Self::InputA, Self::InputB>;
+
+    /// Run the component, receiving from both inputs and sending to output.
+    async fn run(
+        self,
+        input: JoinedReceiver<Self::InputA, Self::InputB>,
+        output: mpsc::Sender<Self::Output>,
+    ) -> Result<()>;
+}
@@ -11,7 +11,7 @@ use zksync_os_interface::types::BlockOutput;
 use zksync_os_observability::{ComponentStateReporter, GenericComponentState};
 use zksync_os_pipeline::{PeekableReceiver, PipelineComponent};
 use zksync_os_revm::{DefaultZk, ZkBuilder, ZkSpecId};
-use zksync_os_storage_api::{ReadStateHistory, ReplayRecord};
+use zksync_os_storage_api::{BlockStats, ReadStateHistory, ReplayRecord};
 
 use crate::helpers::zk_tx_into_revm_tx;
 use crate::revm_state_provider::RevmStateProvider;
@@ -38,16 +38,16 @@ impl<State> PipelineComponent for RevmConsistencyChecker<State>
 where
     State: ReadStateHistory + Clone + Send + 'static,
 {
-    type Input = (BlockOutput, ReplayRecord);
-    type Output = (BlockOutput, ReplayRecord);
+    type Input = (BlockOutput, ReplayRecord, BlockStats);
+    type Output = (BlockOutput, ReplayRecord, BlockStats);
 
     const NAME: &'static str = "revm_consistency_checker";
     const OUTPUT_BUFFER_SIZE: usize = 5;
 
     async fn run(
         mut self,
-        mut input: PeekableReceiver<Self::Input>, // PeekableReceiver<(BlockOutput, ReplayRecord)>
-        output: Sender<Self::Output>,             // Sender<(BlockOutput, ReplayRecord)>
+        mut input: PeekableReceiver<Self::Input>, // PeekableReceiver<(BlockOutput, ReplayRecord, BlockStats)>
+        output: Sender<Self::Output>,             // Sender<(BlockOutput, ReplayRecord, BlockStats)>
     ) -> anyhow::Result<()> {
         let latency_tracker = ComponentStateReporter::global().handle_for(
             "revm_consistency_checker",
@@ -58,7 +58,7 @@ where
 
         loop {
             latency_tracker.enter_state(GenericComponentState::WaitingRecv);
-            let Some((block_output, replay_record)) = input.recv().await else {
+            let Some((block_output, replay_record, stats)) = input.recv().await else {
                 anyhow::bail!("inbound channel closed");
             };
             let exec_ver = replay_record.block_context.execution_version;
@@ -142,7 +142,7 @@ where
 
             latency_tracker.enter_state(GenericComponentState::WaitingSend);
             if output
-                .send((block_output.clone(), replay_record.clone()))
+                .send((block_output.clone(), replay_record.clone(), stats))
                 .await
                 .is_err()
             {
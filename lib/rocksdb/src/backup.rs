@@ -0,0 +1,190 @@
+//! Online backups of RocksDB instances based on the RocksDB backup engine.
+//!
+//! Backups are taken from DB instances that are open in the current process, without stopping writes.
+//! All backups of a DB are kept in a single backup directory and share unchanged SST files, so that
+//! each backup only copies files created since the previous one.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Mutex, Weak},
+};
+
+use once_cell::sync::Lazy;
+use rocksdb::{
+    Env,
+    backup::{BackupEngine, BackupEngineInfo, BackupEngineOptions, RestoreOptions},
+};
+
+use crate::db::RocksDBInner;
+
+/// Weak refs to DB instances open in this process, keyed by their canonical path.
+static OPEN_INSTANCES: Lazy<Mutex<HashMap<PathBuf, Weak<RocksDBInner>>>> =
+    Lazy::new(Mutex::default);
+
+pub(crate) fn register(path: &Path, instance: Weak<RocksDBInner>) {
+    let mut instances = OPEN_INSTANCES.lock().expect("instances are poisoned");
+    instances.retain(|_, instance| instance.strong_count() > 0);
+    instances.insert(canonical_path(path), instance);
+}
+
+fn canonical_path(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_owned())
+}
+
+/// Information about a backup in a backup directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackupInfo {
+    pub backup_id: u32,
+    /// Unix timestamp (in seconds) of the backup creation.
+    pub timestamp: i64,
+    /// Size of the backup in bytes, including files shared with other backups.
+    pub size: u64,
+    pub num_files: u32,
+}
+
+impl From<BackupEngineInfo> for BackupInfo {
+    fn from(info: BackupEngineInfo) -> Self {
+        Self {
+            backup_id: info.backup_id,
+            timestamp: info.timestamp,
+            size: info.size,
+            num_files: info.num_files,
+        }
+    }
+}
+
+fn open_engine(backup_dir: &Path) -> Result<BackupEngine, rocksdb::Error> {
+    BackupEngine::open(&BackupEngineOptions::new(backup_dir)?, &Env::new()?)
+}
+
+/// Creates a backup of the DB instance open in this process at `db_path`. Writes to the DB are not
+/// blocked while the backup is taken.
+///
+/// Only `backups_to_keep` most recent backups are retained in `backup_dir`. Returns `Ok(None)` if
+/// no DB instance is open at `db_path`.
+pub fn backup_open_instance(
+    db_path: &Path,
+    backup_dir: &Path,
+    backups_to_keep: usize,
+) -> Result<Option<BackupInfo>, rocksdb::Error> {
+    let instance = OPEN_INSTANCES
+        .lock()
+        .expect("instances are poisoned")
+        .get(&canonical_path(db_path))
+        .and_then(Weak::upgrade);
+    let Some(instance) = instance else {
+        return Ok(None);
+    };
+
+    let mut engine = open_engine(backup_dir)?;
+    engine.create_new_backup_flush(&instance.db, true)?;
+    let info = engine
+        .get_backup_info()
+        .into_iter()
+        .max_by_key(|info| info.backup_id)
+        .expect("backup was just created");
+    engine.verify_backup(info.backup_id)?;
+    engine.purge_old_backups(backups_to_keep.max(1))?;
+    tracing::info!(
+        db_name = instance.db_name,
+        backup_id = info.backup_id,
+        size = info.size,
+        "Created RocksDB backup in `{}`",
+        backup_dir.display()
+    );
+    Ok(Some(info.into()))
+}
+
+/// Lists backups in `backup_dir`, oldest first.
+pub fn list_backups(backup_dir: &Path) -> Result<Vec<BackupInfo>, rocksdb::Error> {
+    let mut backups: Vec<BackupInfo> = open_engine(backup_dir)?
+        .get_backup_info()
+        .into_iter()
+        .map(Into::into)
+        .collect();
+    backups.sort_by_key(|info| info.backup_id);
+    Ok(backups)
+}
+
+/// Restores the backup with `backup_id` from `backup_dir` into `db_path`. RocksDB verifies checksums
+/// of all restored files, so this also serves as a full integrity check of the backup.
+pub fn restore_backup(
+    backup_dir: &Path,
+    backup_id: u32,
+    db_path: &Path,
+) -> Result<(), rocksdb::Error> {
+    open_engine(backup_dir)?.restore_from_backup(
+        db_path,
+        db_path,
+        &RestoreOptions::default(),
+        backup_id,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::{RocksDB, db::NamedColumnFamily};
+
+    #[derive(Debug, Clone, Copy)]
+    struct TestColumnFamily;
+
+    impl NamedColumnFamily for TestColumnFamily {
+        const DB_NAME: &'static str = "backup_test";
+        const ALL: &'static [Self] = &[Self];
+
+        fn name(&self) -> &'static str {
+            "data"
+        }
+    }
+
+    fn put(db: &RocksDB<TestColumnFamily>, key: &[u8], value: &[u8]) {
+        let mut batch = db.new_write_batch();
+        batch.put_cf(TestColumnFamily, key, value);
+        db.write(batch).unwrap();
+    }
+
+    #[test]
+    fn backup_and_restore() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("db");
+        let backup_dir = temp_dir.path().join("backups");
+        assert_eq!(
+            backup_open_instance(&db_path, &backup_dir, 2).unwrap(),
+            None
+        );
+
+        let db = RocksDB::<TestColumnFamily>::new(&db_path).unwrap();
+        for i in 0_u8..3 {
+            put(&db, &[i], b"value");
+            backup_open_instance(&db_path, &backup_dir, 2)
+                .unwrap()
+                .unwrap();
+        }
+        // Writes after the backup are not included in it
+        put(&db, b"after_backup", b"value");
+
+        let backups = list_backups(&backup_dir).unwrap();
+        assert_eq!(backups.len(), 2);
+        let restored_path = temp_dir.path().join("restored");
+        restore_backup(&backup_dir, backups[0].backup_id, &restored_path).unwrap();
+        let restored = RocksDB::<TestColumnFamily>::new(&restored_path).unwrap();
+        assert!(restored.get_cf(TestColumnFamily, &[1]).unwrap().is_some());
+        assert!(restored.get_cf(TestColumnFamily, &[2]).unwrap().is_none());
+        drop(restored);
+
+        std::fs::remove_dir_all(&restored_path).unwrap();
+        restore_backup(&backup_dir, backups[1].backup_id, &restored_path).unwrap();
+        let restored = RocksDB::<TestColumnFamily>::new(&restored_path).unwrap();
+        assert!(restored.get_cf(TestColumnFamily, &[2]).unwrap().is_some());
+        assert!(
+            restored
+                .get_cf(TestColumnFamily, b"after_backup")
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -9,7 +9,7 @@ use std::{
     path::Path,
     sync::{
         Arc, Condvar, Mutex, Weak,
-        atomic::{AtomicU64, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
     },
     thread,
     time::{Duration, Instant},
@@ -18,19 +18,23 @@ use std::{
 use rocksdb::{
     BlockBasedOptions, Cache, ColumnFamily, ColumnFamilyDescriptor, DB, DBPinnableSlice, Direction,
     IteratorMode, Options, PrefixRange, ReadOptions, WriteOptions, perf, properties,
+    statistics::Ticker,
 };
 use thread_local::ThreadLocal;
 use vise::MetricsFamily;
 
 use crate::metrics::{
     BlockCacheKind, DbLabel, METRICS, PROF_METRICS, RocksdbLabels, RocksdbProfilingLabels,
-    RocksdbSizeMetrics,
+    RocksdbSizeMetrics, RocksdbStatisticsMetrics,
 };
 
 /// Number of active RocksDB instances used to determine if it's safe to exit current process.
 /// Not properly dropped RocksDB instances can lead to DB corruption.
 static ROCKSDB_INSTANCE_COUNTER: (Mutex<usize>, Condvar) = (Mutex::new(0), Condvar::new());
 
+/// Default for [`RocksDBOptions::enable_statistics`].
+static STATISTICS_ENABLED_BY_DEFAULT: AtomicBool = AtomicBool::new(false);
+
 /// Describes column family used in a [`RocksDB`] instance.
 pub trait NamedColumnFamily: 'static + Copy {
     /// Name of the database. Used in metrics reporting.
@@ -104,6 +108,17 @@ impl RocksDBCaches {
     }
 }
 
+/// DB options holding the RocksDB statistics object shared with the DB.
+struct RocksDBStatistics(Options);
+
+impl fmt::Debug for RocksDBStatistics {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter
+            .debug_struct("RocksDBStatistics")
+            .finish_non_exhaustive()
+    }
+}
+
 /// Size statistics for a column family. All sizes are measured in bytes.
 #[derive(Debug)]
 pub struct SizeStats {
@@ -117,9 +132,10 @@ pub struct SizeStats {
 
 #[derive(Debug)]
 pub(crate) struct RocksDBInner {
-    db: DB,
-    db_name: &'static str,
+    pub(crate) db: DB,
+    pub(crate) db_name: &'static str,
     cf_names: HashSet<&'static str>,
+    statistics: Option<RocksDBStatistics>,
     _registry_entry: RegistryEntry,
     // Importantly, `Cache`s must be dropped after `DB`, so we place them as the last field
     // (fields in a struct are dropped in the declaration order).
@@ -181,6 +197,32 @@ impl RocksDBInner {
         }
     }
 
+    pub(crate) fn collect_statistics(
+        &self,
+        metrics: &MetricsFamily<DbLabel, RocksdbStatisticsMetrics>,
+    ) {
+        let Some(RocksDBStatistics(statistics)) = &self.statistics else {
+            return;
+        };
+        let metrics = &metrics[&self.db_name.into()];
+        metrics
+            .block_cache_hits
+            .set(statistics.get_ticker_count(Ticker::BlockCacheHit));
+        metrics
+            .block_cache_misses
+            .set(statistics.get_ticker_count(Ticker::BlockCacheMiss));
+        let stall_micros = statistics.get_ticker_count(Ticker::StallMicros);
+        metrics
+            .write_stall_time
+            .set(Duration::from_micros(stall_micros).as_secs_f64());
+        metrics
+            .compaction_read_bytes
+            .set(statistics.get_ticker_count(Ticker::CompactReadBytes));
+        metrics
+            .compaction_write_bytes
+            .set(statistics.get_ticker_count(Ticker::CompactWriteBytes));
+    }
+
     fn int_property(&self, cf: &ColumnFamily, name: &CStr) -> Option<u64> {
         let property = self.db.property_int_value_cf(cf, name);
         let property = property.unwrap_or_else(|err| {
@@ -335,6 +377,18 @@ pub struct RocksDBOptions {
     pub stalled_writes_retries: StalledWritesRetries,
     /// Number of open files that can be used by the DB. Default is None, for no limit.
     pub max_open_files: Option<NonZeroU32>,
+    /// Enables RocksDB statistics (block cache hits / misses, write stall time etc.) exported
+    /// in metrics. Statistics have a performance overhead, so they are disabled by default unless
+    /// switched on process-wide with [`Self::enable_statistics_by_default()`].
+    pub enable_statistics: bool,
+}
+
+impl RocksDBOptions {
+    /// Enables statistics for all DB instances opened with the default options afterwards,
+    /// including ones opened with [`RocksDB::new()`].
+    pub fn enable_statistics_by_default() {
+        STATISTICS_ENABLED_BY_DEFAULT.store(true, Ordering::Relaxed);
+    }
 }
 
 impl Default for RocksDBOptions {
@@ -345,6 +399,7 @@ impl Default for RocksDBOptions {
             large_memtable_capacity: None,
             stalled_writes_retries: StalledWritesRetries::new(Duration::from_secs(10)),
             max_open_files: None,
+            enable_statistics: STATISTICS_ENABLED_BY_DEFAULT.load(Ordering::Relaxed),
         }
     }
 }
@@ -354,7 +409,7 @@ impl Default for RocksDBOptions {
 /// The wrapper is cheaply cloneable; internally, it wraps a DB instance in an [`Arc`].
 #[derive(Debug)]
 pub struct RocksDB<CF> {
-    inner: Arc<RocksDBInner>,
+    pub(crate) inner: Arc<RocksDBInner>,
     sync_writes: bool,
     stalled_writes_retries: StalledWritesRetries,
     _cf: PhantomData<CF>,
@@ -385,6 +440,9 @@ impl<CF: NamedColumnFamily> RocksDB<CF> {
             -1
         };
         db_options.set_max_open_files(max_open_files);
+        if options.enable_statistics {
+            db_options.enable_statistics();
+        }
         let existing_cfs = DB::list_cf(&db_options, path).unwrap_or_else(|err| {
             tracing::warn!(
                 db_name = CF::DB_NAME,
@@ -445,10 +503,14 @@ impl<CF: NamedColumnFamily> RocksDB<CF> {
             db,
             db_name: CF::DB_NAME,
             cf_names,
+            statistics: options
+                .enable_statistics
+                .then_some(RocksDBStatistics(db_options)),
             _registry_entry: RegistryEntry::new(),
             _caches: caches,
         });
         RocksdbSizeMetrics::register(CF::DB_NAME, Arc::downgrade(&inner));
+        crate::backup::register(path, Arc::downgrade(&inner));
 
         tracing::info!(
             "Initialized RocksDB `{}` at `{}` with {options:?}",
@@ -1150,6 +1212,68 @@ mod tests {
         );
     }
 
+    #[derive(Debug, Clone, Copy)]
+    struct MetricsColumnFamily;
+
+    impl NamedColumnFamily for MetricsColumnFamily {
+        const DB_NAME: &'static str = "metrics_test";
+        const ALL: &'static [Self] = &[Self];
+
+        fn name(&self) -> &'static str {
+            "data"
+        }
+    }
+
+    #[test]
+    fn engine_metrics_are_collected() {
+        let temp_dir = TempDir::new().unwrap();
+        let options = RocksDBOptions {
+            block_cache_capacity: Some(1 << 20),
+            enable_statistics: true,
+            ..RocksDBOptions::default()
+        };
+        let db = RocksDB::<MetricsColumnFamily>::with_options(temp_dir.path(), options).unwrap();
+
+        // Produce several L0 files and compact them
+        for round in 0_u32..3 {
+            let mut batch = db.new_write_batch();
+            for i in 0_u32..1_000 {
+                let key = [round.to_be_bytes(), i.to_be_bytes()].concat();
+                batch.put_cf(MetricsColumnFamily, &key, &[round as u8; 64]);
+            }
+            db.write(batch).unwrap();
+            let cf = db.column_family(MetricsColumnFamily);
+            db.inner.db.flush_cf(cf).unwrap();
+        }
+        let cf = db.column_family(MetricsColumnFamily);
+        db.inner
+            .db
+            .compact_range_cf(cf, None::<&[u8]>, None::<&[u8]>);
+        // The first read of a block misses the cache, and the following ones hit it
+        for _ in 0..2 {
+            let key = [0_u32.to_be_bytes(), 1_u32.to_be_bytes()].concat();
+            db.get_cf(MetricsColumnFamily, &key).unwrap().unwrap();
+        }
+
+        let metrics = RocksdbSizeMetrics::scrape();
+        let metrics = &metrics[&RocksdbLabels::new("metrics_test", "data")];
+        assert!(metrics.total_sst_size.get() > 0);
+        assert!(metrics.live_data_size.get() > 0);
+        assert_eq!(metrics.writes_stopped.get(), 0);
+        let total_files: u64 = (0..=6)
+            .map(|level| metrics.files_at_level[&level].get())
+            .sum();
+        assert!(total_files > 0);
+        assert_eq!(metrics.files_at_level[&0].get(), 0);
+
+        let statistics = RocksdbStatisticsMetrics::scrape();
+        let statistics = &statistics[&"metrics_test".into()];
+        assert!(statistics.block_cache_hits.get() > 0);
+        assert!(statistics.block_cache_misses.get() > 0);
+        assert!(statistics.compaction_read_bytes.get() > 0);
+        assert!(statistics.compaction_write_bytes.get() > 0);
+    }
+
     #[test]
     fn parsing_metrics_str() {
         let metrics_str = "\
@@ -1,5 +1,7 @@
+pub mod backup;
 pub mod db;
 mod metrics;
+pub mod migrations;
 
 pub use db::{RocksDB, RocksDBOptions, StalledWritesRetries, WeakRocksDB};
 pub use rocksdb;
@@ -92,6 +92,27 @@ pub(crate) struct RocksdbSizeMetrics {
     pub files_at_level: LabeledFamily<usize, Gauge<u64>>,
 }
 
+/// Metrics based on RocksDB statistics, which are only collected for DB instances with
+/// statistics enabled. Values are cumulative since the DB instance was opened.
+#[derive(Debug, Metrics)]
+#[metrics(prefix = "rocksdb_statistics")]
+pub(crate) struct RocksdbStatisticsMetrics {
+    /// Number of block cache hits.
+    pub block_cache_hits: Gauge<u64>,
+    /// Number of block cache misses.
+    pub block_cache_misses: Gauge<u64>,
+    /// Total time writes were delayed or stopped by RocksDB because of pending flushes /
+    /// compactions.
+    #[metrics(unit = Unit::Seconds)]
+    pub write_stall_time: Gauge<f64>,
+    /// Total number of bytes read by compactions.
+    #[metrics(unit = Unit::Bytes)]
+    pub compaction_read_bytes: Gauge<u64>,
+    /// Total number of bytes written by compactions.
+    #[metrics(unit = Unit::Bytes)]
+    pub compaction_write_bytes: Gauge<u64>,
+}
+
 /// Weak refs to DB instances registered using [`RocksdbSizeMetrics::register()`].
 static INSTANCES: Lazy<Mutex<HashMap<&'static str, Weak<RocksDBInner>>>> =
     Lazy::new(Mutex::default);
@@ -101,6 +122,9 @@ impl RocksdbSizeMetrics {
         #[vise::register]
         static COLLECTOR: Collector<MetricsFamily<RocksdbLabels, RocksdbSizeMetrics>> =
             Collector::new();
+        #[vise::register]
+        static STATISTICS_COLLECTOR: Collector<MetricsFamily<DbLabel, RocksdbStatisticsMetrics>> =
+            Collector::new();
 
         INSTANCES
             .lock()
@@ -108,26 +132,41 @@ impl RocksdbSizeMetrics {
             .insert(db_name, instance);
         // Set up the collector. This will return an error on subsequent calls, but we're OK with it.
         COLLECTOR.before_scrape(Self::scrape).ok();
+        STATISTICS_COLLECTOR
+            .before_scrape(RocksdbStatisticsMetrics::scrape)
+            .ok();
     }
 
-    fn scrape() -> MetricsFamily<RocksdbLabels, Self> {
+    pub(crate) fn scrape() -> MetricsFamily<RocksdbLabels, Self> {
         let metrics = MetricsFamily::default();
-        // Remove instances that have been dropped, and collect metrics for the alive instances.
-        INSTANCES
-            .lock()
-            .expect("instances are poisoned")
-            .retain(|_, instance| {
-                if let Some(instance) = instance.upgrade() {
-                    instance.collect_metrics(&metrics);
-                    true
-                } else {
-                    false
-                }
-            });
+        for_each_instance(|instance| instance.collect_metrics(&metrics));
+        metrics
+    }
+}
+
+impl RocksdbStatisticsMetrics {
+    pub(crate) fn scrape() -> MetricsFamily<DbLabel, Self> {
+        let metrics = MetricsFamily::default();
+        for_each_instance(|instance| instance.collect_statistics(&metrics));
         metrics
     }
 }
 
+fn for_each_instance(mut action: impl FnMut(&RocksDBInner)) {
+    // Remove instances that have been dropped, and collect metrics for the alive instances.
+    INSTANCES
+        .lock()
+        .expect("instances are poisoned")
+        .retain(|_, instance| {
+            if let Some(instance) = instance.upgrade() {
+                action(&instance);
+                true
+            } else {
+                false
+            }
+        });
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EncodeLabelSet)]
 pub(crate) struct RocksdbProfilingLabels {
     pub db: &'static str,
@@ -0,0 +1,550 @@
+//! Versioned DB schemas and migrations between them.
+//!
+//! The schema version of a DB is stamped into its meta column family. DBs created before
+//! versioning was introduced have no stamp and are treated as version 0; the first migration of
+//! every schema is a baseline migration that only stamps such DBs. Migrations are run on node
+//! startup before DBs are opened by node components; a DB stamped with a version newer than
+//! the binary supports is refused.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use anyhow::Context as _;
+use rocksdb::checkpoint::Checkpoint;
+
+use crate::{RocksDB, db::NamedColumnFamily};
+
+/// Key of the schema version (big-endian `u32`) in the meta column family.
+pub const SCHEMA_VERSION_KEY: &[u8] = b"schema_version";
+/// Key present in the meta column family while migrations run; holds the version (big-endian
+/// `u32`) the DB is migrated from.
+const MIGRATION_IN_PROGRESS_KEY: &[u8] = b"migration_in_progress";
+
+/// Migration of a DB from the `from` to the `to` schema version.
+#[derive(Debug)]
+pub struct Migration<CF> {
+    pub from: u32,
+    pub to: u32,
+    pub description: &'static str,
+    /// Migrates the DB. Must be idempotent: an interrupted migration is run again on the next
+    /// startup.
+    pub run: fn(&RocksDB<CF>) -> anyhow::Result<()>,
+}
+
+impl<CF: NamedColumnFamily> Migration<CF> {
+    /// Migration stamping DBs created before versioning was introduced.
+    pub const fn baseline() -> Self {
+        Self {
+            from: 0,
+            to: 1,
+            description: "stamp the baseline schema version",
+            run: |_| Ok(()),
+        }
+    }
+}
+
+/// Schema of a DB: the column family storing the schema version and the migrations leading to
+/// the latest version, in order.
+#[derive(Debug)]
+pub struct Schema<CF: 'static> {
+    pub meta_cf: CF,
+    pub migrations: &'static [Migration<CF>],
+}
+
+/// Migration step as reported in a [`MigrationPlan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationStep {
+    pub from: u32,
+    pub to: u32,
+    pub description: &'static str,
+}
+
+/// Migrations to run for a DB.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationPlan {
+    /// The DB doesn't exist yet; it will be created and stamped with the latest version.
+    Fresh { version: u32 },
+    /// The DB is stamped with the latest version.
+    UpToDate { version: u32 },
+    /// The DB needs to be migrated from `current_version` by running `steps`.
+    Pending {
+        current_version: u32,
+        steps: Vec<MigrationStep>,
+    },
+}
+
+impl fmt::Display for MigrationPlan {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Fresh { version } => write!(formatter, "new DB, stamped with version {version}"),
+            Self::UpToDate { version } => write!(formatter, "up to date at version {version}"),
+            Self::Pending {
+                current_version,
+                steps,
+            } => {
+                write!(
+                    formatter,
+                    "at version {current_version}, pending migrations:"
+                )?;
+                for step in steps {
+                    write!(
+                        formatter,
+                        "\n  {} -> {}: {}",
+                        step.from, step.to, step.description
+                    )?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Type-erased [`Schema`], so that schemas of DBs with different column families can be
+/// registered together.
+pub trait VersionedDb: Send + Sync {
+    fn db_name(&self) -> &'static str;
+
+    /// Latest schema version supported by this binary.
+    fn latest_version(&self) -> u32;
+
+    /// Returns migrations that would be run for the DB at `db_path`, or an error if the DB is
+    /// stamped with a version newer than [`Self::latest_version()`].
+    fn plan(&self, db_path: &Path) -> anyhow::Result<MigrationPlan>;
+
+    /// Runs pending migrations for the DB at `db_path`, creating and stamping the DB if it
+    /// doesn't exist. Returns the plan that was executed.
+    fn migrate(&self, db_path: &Path) -> anyhow::Result<MigrationPlan>;
+}
+
+impl<CF: NamedColumnFamily + Send + Sync> VersionedDb for Schema<CF> {
+    fn db_name(&self) -> &'static str {
+        CF::DB_NAME
+    }
+
+    fn latest_version(&self) -> u32 {
+        Schema::latest_version(self)
+    }
+
+    fn plan(&self, db_path: &Path) -> anyhow::Result<MigrationPlan> {
+        if is_fresh(db_path) {
+            return Ok(MigrationPlan::Fresh {
+                version: self.latest_version(),
+            });
+        }
+        let db = RocksDB::<CF>::new(db_path)?;
+        self.plan_for(&db)
+    }
+
+    fn migrate(&self, db_path: &Path) -> anyhow::Result<MigrationPlan> {
+        let is_fresh = is_fresh(db_path);
+        if is_fresh {
+            std::fs::create_dir_all(db_path)
+                .with_context(|| format!("failed creating `{}`", db_path.display()))?;
+        }
+        let db = RocksDB::<CF>::new(db_path)?;
+        if is_fresh {
+            let version = self.latest_version();
+            self.stamp(&db, version, false)?;
+            tracing::info!(db_name = CF::DB_NAME, version, "Stamped new DB");
+            return Ok(MigrationPlan::Fresh { version });
+        }
+
+        let plan = self.plan_for(&db)?;
+        if let MigrationPlan::Pending {
+            current_version, ..
+        } = &plan
+        {
+            self.run_pending(&db, db_path, *current_version)?;
+        }
+        Ok(plan)
+    }
+}
+
+impl<CF: NamedColumnFamily> Schema<CF> {
+    pub fn latest_version(&self) -> u32 {
+        self.migrations.last().map_or(0, |migration| migration.to)
+    }
+
+    fn plan_for(&self, db: &RocksDB<CF>) -> anyhow::Result<MigrationPlan> {
+        self.validate()?;
+        let current_version = read_version(db, self.meta_cf, SCHEMA_VERSION_KEY)?.unwrap_or(0);
+        let latest_version = self.latest_version();
+        anyhow::ensure!(
+            current_version <= latest_version,
+            "`{}` DB has schema version {current_version}, which is newer than the latest \
+             version {latest_version} supported by this binary; it was likely written by a newer \
+             node version",
+            CF::DB_NAME
+        );
+        if current_version == latest_version {
+            return Ok(MigrationPlan::UpToDate {
+                version: current_version,
+            });
+        }
+
+        let steps = self
+            .migrations
+            .iter()
+            .skip_while(|migration| migration.from < current_version)
+            .map(|migration| MigrationStep {
+                from: migration.from,
+                to: migration.to,
+                description: migration.description,
+            })
+            .collect();
+        Ok(MigrationPlan::Pending {
+            current_version,
+            steps,
+        })
+    }
+
+    /// Checks that migrations form a chain starting from version 0.
+    fn validate(&self) -> anyhow::Result<()> {
+        let mut version = 0;
+        for migration in self.migrations {
+            anyhow::ensure!(
+                migration.from == version && migration.to > migration.from,
+                "invalid migration {} -> {} of `{}` DB schema",
+                migration.from,
+                migration.to,
+                CF::DB_NAME
+            );
+            version = migration.to;
+        }
+        Ok(())
+    }
+
+    fn run_pending(
+        &self,
+        db: &RocksDB<CF>,
+        db_path: &Path,
+        current_version: u32,
+    ) -> anyhow::Result<()> {
+        let db_name = CF::DB_NAME;
+        if let Some(from) = read_version(db, self.meta_cf, MIGRATION_IN_PROGRESS_KEY)? {
+            tracing::warn!(
+                db_name,
+                from,
+                current_version,
+                "Previous migration of DB was interrupted; resuming"
+            );
+        }
+        let checkpoint_path = pre_migration_checkpoint_path(db_path, current_version);
+        if checkpoint_path.exists() {
+            tracing::info!(
+                db_name,
+                "Keeping existing pre-migration checkpoint at `{}`",
+                checkpoint_path.display()
+            );
+        } else {
+            Checkpoint::new(&db.inner.db)
+                .and_then(|checkpoint| checkpoint.create_checkpoint(&checkpoint_path))
+                .with_context(|| {
+                    format!("failed creating pre-migration checkpoint of `{db_name}` DB")
+                })?;
+            tracing::info!(
+                db_name,
+                "Created pre-migration checkpoint at `{}`; remove it once the migrated node works",
+                checkpoint_path.display()
+            );
+        }
+        let mut batch = db.new_write_batch();
+        batch.put_cf(
+            self.meta_cf,
+            MIGRATION_IN_PROGRESS_KEY,
+            &current_version.to_be_bytes(),
+        );
+        db.write(batch)?;
+
+        let latest_version = self.latest_version();
+        for migration in self
+            .migrations
+            .iter()
+            .skip_while(|migration| migration.from < current_version)
+        {
+            let (from, to) = (migration.from, migration.to);
+            tracing::info!(
+                db_name,
+                from,
+                to,
+                "Running migration: {}",
+                migration.description
+            );
+            let started_at = Instant::now();
+            (migration.run)(db)
+                .with_context(|| format!("migration {from} -> {to} of `{db_name}` DB failed"))?;
+            self.stamp(db, to, to < latest_version)?;
+            tracing::info!(
+                db_name,
+                from,
+                to,
+                elapsed = ?started_at.elapsed(),
+                "Finished migration"
+            );
+        }
+        Ok(())
+    }
+
+    /// Atomically stamps `version`, clearing the in-progress marker unless `in_progress` is set.
+    fn stamp(&self, db: &RocksDB<CF>, version: u32, in_progress: bool) -> anyhow::Result<()> {
+        let mut batch = db.new_write_batch();
+        batch.put_cf(self.meta_cf, SCHEMA_VERSION_KEY, &version.to_be_bytes());
+        if !in_progress {
+            batch.delete_cf(self.meta_cf, MIGRATION_IN_PROGRESS_KEY);
+        }
+        db.write(batch)?;
+        Ok(())
+    }
+}
+
+/// Path of the checkpoint (a hard-linked copy) of the DB at `db_path` taken before migrating it
+/// from `version`.
+pub fn pre_migration_checkpoint_path(db_path: &Path, version: u32) -> PathBuf {
+    let mut file_name = db_path.file_name().unwrap_or_default().to_owned();
+    file_name.push(format!(".pre_migration_v{version}"));
+    db_path.with_file_name(file_name)
+}
+
+fn is_fresh(db_path: &Path) -> bool {
+    std::fs::read_dir(db_path).map_or(true, |mut entries| entries.next().is_none())
+}
+
+fn read_version<CF: NamedColumnFamily>(
+    db: &RocksDB<CF>,
+    meta_cf: CF,
+    key: &[u8],
+) -> anyhow::Result<Option<u32>> {
+    let Some(bytes) = db.get_cf(meta_cf, key)? else {
+        return Ok(None);
+    };
+    let bytes = <[u8; 4]>::try_from(bytes.as_slice())
+        .with_context(|| format!("invalid schema version in `{}` DB", CF::DB_NAME))?;
+    Ok(Some(u32::from_be_bytes(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy)]
+    enum TestColumnFamily {
+        Data,
+        Meta,
+    }
+
+    impl NamedColumnFamily for TestColumnFamily {
+        const DB_NAME: &'static str = "migrations_test";
+        const ALL: &'static [Self] = &[Self::Data, Self::Meta];
+
+        fn name(&self) -> &'static str {
+            match self {
+                Self::Data => "data",
+                Self::Meta => "meta",
+            }
+        }
+    }
+
+    /// Copies values from `old_key` to `new_key`.
+    fn rename_key(db: &RocksDB<TestColumnFamily>) -> anyhow::Result<()> {
+        if let Some(value) = db.get_cf(TestColumnFamily::Data, b"old_key")? {
+            let mut batch = db.new_write_batch();
+            batch.put_cf(TestColumnFamily::Data, b"new_key", &value);
+            batch.delete_cf(TestColumnFamily::Data, b"old_key");
+            db.write(batch)?;
+        }
+        Ok(())
+    }
+
+    fn fail(_: &RocksDB<TestColumnFamily>) -> anyhow::Result<()> {
+        anyhow::bail!("oops")
+    }
+
+    const V1: Schema<TestColumnFamily> = Schema {
+        meta_cf: TestColumnFamily::Meta,
+        migrations: &[Migration::baseline()],
+    };
+    const V2: Schema<TestColumnFamily> = Schema {
+        meta_cf: TestColumnFamily::Meta,
+        migrations: &[
+            Migration::baseline(),
+            Migration {
+                from: 1,
+                to: 2,
+                description: "rename key",
+                run: rename_key,
+            },
+        ],
+    };
+
+    fn put(db_path: &Path, key: &[u8]) {
+        let db = RocksDB::<TestColumnFamily>::new(db_path).unwrap();
+        let mut batch = db.new_write_batch();
+        batch.put_cf(TestColumnFamily::Data, key, b"value");
+        db.write(batch).unwrap();
+    }
+
+    fn read(db_path: &Path, cf: TestColumnFamily, key: &[u8]) -> Option<Vec<u8>> {
+        RocksDB::<TestColumnFamily>::new(db_path)
+            .unwrap()
+            .get_cf(cf, key)
+            .unwrap()
+    }
+
+    #[test]
+    fn fresh_db_is_stamped_with_latest_version() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("db");
+        assert_eq!(
+            V2.plan(&db_path).unwrap(),
+            MigrationPlan::Fresh { version: 2 }
+        );
+        // Planning doesn't create the DB
+        assert!(!db_path.exists());
+
+        assert_eq!(
+            V2.migrate(&db_path).unwrap(),
+            MigrationPlan::Fresh { version: 2 }
+        );
+        assert_eq!(
+            read(&db_path, TestColumnFamily::Meta, SCHEMA_VERSION_KEY),
+            Some(2_u32.to_be_bytes().to_vec())
+        );
+        assert_eq!(
+            V2.plan(&db_path).unwrap(),
+            MigrationPlan::UpToDate { version: 2 }
+        );
+    }
+
+    #[test]
+    fn pending_migrations_are_run() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("db");
+        // DB created before versioning was introduced
+        put(&db_path, b"old_key");
+
+        let plan = V2.plan(&db_path).unwrap();
+        let MigrationPlan::Pending {
+            current_version: 0,
+            steps,
+        } = &plan
+        else {
+            panic!("unexpected plan: {plan:?}");
+        };
+        let steps: Vec<_> = steps.iter().map(|step| (step.from, step.to)).collect();
+        assert_eq!(steps, [(0, 1), (1, 2)]);
+        // Planning doesn't run migrations
+        assert!(read(&db_path, TestColumnFamily::Data, b"old_key").is_some());
+
+        assert_eq!(V2.migrate(&db_path).unwrap(), plan);
+        assert!(read(&db_path, TestColumnFamily::Data, b"old_key").is_none());
+        assert!(read(&db_path, TestColumnFamily::Data, b"new_key").is_some());
+        assert!(read(&db_path, TestColumnFamily::Meta, MIGRATION_IN_PROGRESS_KEY).is_none());
+        // The checkpoint holds the DB state before the migration
+        let checkpoint_path = pre_migration_checkpoint_path(&db_path, 0);
+        assert!(read(&checkpoint_path, TestColumnFamily::Data, b"old_key").is_some());
+
+        // Migrating an up-to-date DB is a no-op
+        put(&db_path, b"old_key");
+        assert_eq!(
+            V2.migrate(&db_path).unwrap(),
+            MigrationPlan::UpToDate { version: 2 }
+        );
+        assert!(read(&db_path, TestColumnFamily::Data, b"old_key").is_some());
+    }
+
+    #[test]
+    fn migration_starts_from_stamped_version() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("db");
+        V1.migrate(&db_path).unwrap();
+        put(&db_path, b"old_key");
+
+        let plan = V2.migrate(&db_path).unwrap();
+        assert_eq!(
+            plan,
+            MigrationPlan::Pending {
+                current_version: 1,
+                steps: vec![MigrationStep {
+                    from: 1,
+                    to: 2,
+                    description: "rename key",
+                }],
+            }
+        );
+        assert!(read(&db_path, TestColumnFamily::Data, b"new_key").is_some());
+    }
+
+    #[test]
+    fn failed_migration_is_resumed() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("db");
+        V1.migrate(&db_path).unwrap();
+        const FAILING: Schema<TestColumnFamily> = Schema {
+            meta_cf: TestColumnFamily::Meta,
+            migrations: &[
+                Migration::baseline(),
+                Migration {
+                    from: 1,
+                    to: 2,
+                    description: "fail",
+                    run: fail,
+                },
+            ],
+        };
+        let err = FAILING.migrate(&db_path).unwrap_err();
+        assert!(format!("{err:#}").contains("oops"), "{err:#}");
+        assert_eq!(
+            read(&db_path, TestColumnFamily::Meta, SCHEMA_VERSION_KEY),
+            Some(1_u32.to_be_bytes().to_vec())
+        );
+        assert!(read(&db_path, TestColumnFamily::Meta, MIGRATION_IN_PROGRESS_KEY).is_some());
+
+        V2.migrate(&db_path).unwrap();
+        assert_eq!(
+            V2.plan(&db_path).unwrap(),
+            MigrationPlan::UpToDate { version: 2 }
+        );
+        assert!(read(&db_path, TestColumnFamily::Meta, MIGRATION_IN_PROGRESS_KEY).is_none());
+    }
+
+    #[test]
+    fn too_new_db_is_refused() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("db");
+        V2.migrate(&db_path).unwrap();
+
+        let err = V1.plan(&db_path).unwrap_err();
+        assert!(
+            err.to_string().contains("newer than the latest version"),
+            "{err}"
+        );
+        V1.migrate(&db_path).unwrap_err();
+        assert_eq!(
+            read(&db_path, TestColumnFamily::Meta, SCHEMA_VERSION_KEY),
+            Some(2_u32.to_be_bytes().to_vec())
+        );
+    }
+
+    #[test]
+    fn invalid_migration_chain_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let db_path = temp_dir.path().join("db");
+        put(&db_path, b"old_key");
+        const NO_BASELINE: Schema<TestColumnFamily> = Schema {
+            meta_cf: TestColumnFamily::Meta,
+            migrations: &[Migration {
+                from: 1,
+                to: 2,
+                description: "rename key",
+                run: rename_key,
+            }],
+        };
+        let err = NO_BASELINE.plan(&db_path).unwrap_err();
+        assert!(err.to_string().contains("invalid migration"), "{err}");
+    }
+}
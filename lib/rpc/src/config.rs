@@ -1,3 +1,5 @@
+use crate::PreconfirmationConfig;
+use std::net::IpAddr;
 use std::time::Duration;
 
 #[derive(Clone, Debug)]
@@ -25,6 +27,28 @@ pub struct RpcConfig {
 
     /// Duration since the last filter poll, after which the filter is considered stale
     pub stale_filter_ttl: Duration,
+
+    /// Maximum number of blocks returned by `eth_feeHistory`; larger requests are capped
+    pub max_fee_history_blocks: u64,
+
+    /// Maximum number of blocks simulated by a single `eth_simulateV1` request
+    pub simulate_max_blocks: u64,
+
+    /// Maximum number of calls in a single `eth_simulateV1` request
+    pub simulate_max_calls: usize,
+
+    /// Maximum total gas limit of calls in a single `eth_simulateV1` request
+    pub simulate_max_gas: u64,
+
+    /// Whether to check that blocks are fully indexed before serving them (debug option)
+    pub verify_block_completeness: bool,
+
+    /// Proxies whose `X-Forwarded-For` entries are trusted when determining request origins for
+    /// spam scoring. Requests from other peers are attributed to the peer address.
+    pub trusted_proxies: Vec<IpAddr>,
+
+    /// Preconfirmations of accepted transactions; disabled if `None`.
+    pub preconfirmations: Option<PreconfirmationConfig>,
 }
 
 impl RpcConfig {
@@ -54,6 +54,9 @@ impl<RpcStorage: ReadRpcStorage> DebugNamespace<RpcStorage> {
             // Short-circuit for genesis block.
             return Ok(Vec::new());
         }
+        self.storage
+            .repository()
+            .ensure_transactions_available(&block)?;
 
         let Some(block_context) = self.storage.replay_storage().get_context(block.number) else {
             tracing::error!(
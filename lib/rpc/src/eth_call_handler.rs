@@ -2,15 +2,18 @@ use crate::call_fees::{CallFees, CallFeesError};
 use crate::config::RpcConfig;
 use crate::result::RevertError;
 use crate::rpc_storage::ReadRpcStorage;
-use crate::sandbox::{call_trace_simulate, execute};
+use crate::sandbox::{call_trace_simulate, execute, simulate_block};
+use crate::simulate::{SimulatedChain, SimulationError, build_simulated_block};
 use alloy::consensus::transaction::Recovered;
 use alloy::consensus::{SignableTransaction, TxEip1559, TxEip2930, TxLegacy, TxType};
 use alloy::eips::{BlockId, BlockNumberOrTag};
 use alloy::network::TransactionBuilder;
 use alloy::primitives::{Address, B256, Bytes, Signature, TxKind, U256};
+use alloy::rpc::types::simulate::{SimBlock, SimulatePayload, SimulatedBlock};
 use alloy::rpc::types::state::StateOverride;
 use alloy::rpc::types::trace::geth::{CallConfig, GethTrace};
 use alloy::rpc::types::{BlockOverrides, TransactionRequest};
+use std::collections::HashMap;
 use tokio::sync::watch;
 use zk_os_api::helpers::{get_balance, get_nonce};
 use zksync_os_interface::types::ExecutionOutput;
@@ -20,7 +23,7 @@ use zksync_os_interface::{
 };
 use zksync_os_storage_api::ViewState;
 use zksync_os_storage_api::{
-    RepositoryError, StateError, state_override_view::OverriddenStateView,
+    RepositoryError, StackedStateView, StateError, state_override_view::OverriddenStateView,
 };
 use zksync_os_types::{
     L1_TX_MINIMAL_GAS_LIMIT, L1Envelope, L1PriorityTxType, L1Tx, L1TxType, L2Envelope,
@@ -288,8 +291,9 @@ impl<RpcStorage: ReadRpcStorage> EthCallHandler<RpcStorage> {
                 call_config,
             ),
         }
+        .map_err(EthCallError::ForwardSubsystemError)?
         .map(GethTrace::CallTracer)
-        .map_err(|err| EthCallError::ForwardSubsystemError(anyhow::anyhow!(err)))
+        .map_err(EthCallError::InvalidTransaction)
     }
 
     pub fn estimate_gas_impl(
@@ -338,6 +342,106 @@ impl<RpcStorage: ReadRpcStorage> EthCallHandler<RpcStorage> {
             None => self.estimate_gas_with_view(request, block_context, base_view),
         }
     }
+
+    /// Simulates blocks of calls on top of the given block. Blocks are executed one after another
+    /// with state changes of previous blocks (and state overrides) carried over in a
+    /// [`StackedStateView`].
+    ///
+    /// Without `validation`, fees are not charged (base fee and gas prices are zero by default)
+    /// and nonces are set automatically.
+    pub fn simulate_v1_impl(
+        &self,
+        payload: SimulatePayload,
+        block: Option<BlockId>,
+    ) -> Result<Vec<SimulatedBlock>, EthCallError> {
+        let SimulatePayload {
+            block_state_calls,
+            trace_transfers,
+            validation,
+            // todo: only transaction hashes are returned
+            return_full_transactions: _,
+        } = payload;
+        let call_count: usize = block_state_calls
+            .iter()
+            .map(|block| block.calls.len())
+            .sum();
+        if call_count > self.config.simulate_max_calls {
+            return Err(SimulationError::TooManyCalls {
+                max: self.config.simulate_max_calls,
+            }
+            .into());
+        }
+
+        let block_id = block.unwrap_or_default();
+        let Some(parent) = self.storage.get_block_by_id(block_id)? else {
+            return Err(EthCallError::BlockNotFound(block_id));
+        };
+        let parent_context = self
+            .storage
+            .replay_storage()
+            .get_context(parent.number)
+            .ok_or(EthCallError::BlockNotFound(block_id))?;
+        let mut chain = SimulatedChain::new(
+            parent_context,
+            parent.hash(),
+            self.config.simulate_max_blocks,
+        );
+        let mut state = StackedStateView::new(self.storage.state_view_at(parent.number)?);
+
+        let mut gas_left = self.config.simulate_max_gas;
+        let mut blocks = Vec::with_capacity(block_state_calls.len());
+        for SimBlock {
+            block_overrides,
+            state_overrides,
+            calls,
+        } in block_state_calls
+        {
+            let block_context = chain.next_block_context(block_overrides, validation)?;
+            if let Some(state_overrides) = state_overrides {
+                state.push_overrides(state_overrides);
+            }
+
+            let mut block_gas_left = block_context.gas_limit;
+            // Next nonce of each sender in this block
+            let mut nonces = HashMap::new();
+            let mut txs = Vec::with_capacity(calls.len());
+            for mut request in calls {
+                let from = request.from.unwrap_or_default();
+                let next_nonce = nonces
+                    .entry(from)
+                    .or_insert_with(|| state.account_nonce(from).unwrap_or_default());
+                if !validation || request.nonce.is_none() {
+                    request.nonce = Some(*next_nonce);
+                }
+                *next_nonce = request.nonce.unwrap() + 1;
+
+                let gas_limit = request
+                    .gas
+                    .unwrap_or((self.config.eth_call_gas as u64).min(block_gas_left));
+                gas_left =
+                    gas_left
+                        .checked_sub(gas_limit)
+                        .ok_or(SimulationError::GasLimitExceeded {
+                            max: self.config.simulate_max_gas,
+                        })?;
+                block_gas_left = block_gas_left.saturating_sub(gas_limit);
+                request.gas = Some(gas_limit);
+
+                txs.push(self.create_tx_from_request(request, &block_context, validation)?);
+            }
+
+            let (block_output, call_traces) =
+                simulate_block(txs.clone(), block_context, state.clone(), trace_transfers)
+                    .map_err(EthCallError::ForwardSubsystemError)?;
+            state.push_state_diff(
+                &block_output.storage_writes,
+                &block_output.published_preimages,
+            );
+            chain.push_block(block_context, block_output.header.hash());
+            blocks.push(build_simulated_block(block_output, &txs, &call_traces)?);
+        }
+        Ok(blocks)
+    }
 }
 
 impl<RpcStorage: ReadRpcStorage> EthCallHandler<RpcStorage> {
@@ -605,6 +709,9 @@ pub enum EthCallError {
     /// Error while decoding or validating transaction request fees.
     #[error(transparent)]
     CallFees(#[from] CallFeesError),
+    /// Error specific to `eth_simulateV1`.
+    #[error(transparent)]
+    Simulation(#[from] SimulationError),
     /// Missing a mandatary field `maxPriorityFeePerGas`. Only returned if transaction's minimal
     /// buildable type enforces this field to be present (i.e., not legacy or EIP-2930).
     #[error("missing `maxPriorityFeePerGas` field for EIP-1559 transaction")]
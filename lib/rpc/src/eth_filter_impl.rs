@@ -205,6 +205,9 @@ impl<RpcStorage: ReadRpcStorage, Mempool: L2TransactionPool>
                         ?filter,
                         "Block matches bloom filter, scanning receipts",
                     );
+                    self.storage
+                        .repository()
+                        .ensure_transactions_available(&block)?;
                     let stored_txs = block
                         .unseal()
                         .body
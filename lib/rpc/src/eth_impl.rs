@@ -1,7 +1,11 @@
+use crate::RpcConfig;
 use crate::eth_call_handler::EthCallHandler;
+use crate::fee_history::{
+    BlockFees, FeeHistoryCache, build_fee_history, validate_reward_percentiles,
+};
 use crate::result::{ToRpcResult, internal_rpc_err, unimplemented_rpc_err};
 use crate::rpc_storage::{ReadRpcStorage, RpcStorageError};
-use crate::tx_handler::TxHandler;
+use crate::tx_handler::{RequestOrigin, TxHandler};
 use alloy::consensus::Account;
 use alloy::consensus::transaction::Recovered;
 use alloy::dyn_abi::TypedData;
@@ -9,7 +13,7 @@ use alloy::eips::eip2930::AccessListResult;
 use alloy::eips::{BlockId, BlockNumberOrTag, Encodable2718};
 use alloy::network::BlockResponse;
 use alloy::network::primitives::BlockTransactions;
-use alloy::primitives::{Address, B256, Bytes, TxHash, U64, U256};
+use alloy::primitives::{Address, B256, BlockNumber, Bytes, TxHash, U64, U256};
 use alloy::rpc::types::simulate::{SimulatePayload, SimulatedBlock};
 use alloy::rpc::types::state::StateOverride;
 use alloy::rpc::types::{
@@ -18,12 +22,14 @@ use alloy::rpc::types::{
 };
 use alloy::serde::JsonStorageKey;
 use async_trait::async_trait;
+use jsonrpsee::Extensions;
 use jsonrpsee::core::RpcResult;
 use ruint::aliases::B160;
 use std::convert::identity;
-use tokio::sync::watch;
+use std::sync::Arc;
 use zk_ee::common_structs::derive_flat_storage_key;
 use zk_os_api::helpers::{get_balance, get_code};
+use zksync_os_gas_adjuster::{BaseFeeInputs, next_block_base_fee};
 use zksync_os_interface::traits::ReadStorage;
 use zksync_os_mempool::L2TransactionPool;
 use zksync_os_rpc_api::eth::EthApiServer;
@@ -31,7 +37,10 @@ use zksync_os_rpc_api::types::{
     RpcBlockConvert, ZkApiBlock, ZkApiTransaction, ZkHeader, ZkTransactionReceipt,
 };
 use zksync_os_storage_api::{RepositoryError, StateError, TxMeta, ViewState};
-use zksync_os_types::{L2Envelope, TransactionAcceptanceState, ZkReceiptEnvelope};
+use zksync_os_types::{L2Envelope, ZkReceiptEnvelope};
+
+/// Number of recent blocks whose fees are cached for `eth_feeHistory`.
+const FEE_HISTORY_CACHE_BLOCKS: usize = 1_024;
 
 pub struct EthNamespace<RpcStorage, Mempool> {
     tx_handler: TxHandler<Mempool>,
@@ -43,29 +52,44 @@ pub struct EthNamespace<RpcStorage, Mempool> {
     mempool: Mempool,
 
     chain_id: u64,
+
+    max_fee_history_blocks: u64,
+    fee_history_cache: FeeHistoryCache,
+    verify_block_completeness: bool,
 }
 
 impl<RpcStorage: ReadRpcStorage, Mempool: L2TransactionPool> EthNamespace<RpcStorage, Mempool> {
     pub fn new(
+        config: &RpcConfig,
         storage: RpcStorage,
         mempool: Mempool,
         eth_call_handler: EthCallHandler<RpcStorage>,
         chain_id: u64,
-        acceptance_state: watch::Receiver<TransactionAcceptanceState>,
+        tx_handler: TxHandler<Mempool>,
     ) -> Self {
-        let tx_handler = TxHandler::new(mempool.clone(), acceptance_state);
-
         Self {
             tx_handler,
             eth_call_handler,
             storage,
             mempool,
             chain_id,
+            max_fee_history_blocks: config.max_fee_history_blocks,
+            // Wallets mostly request a few recent blocks
+            fee_history_cache: FeeHistoryCache::new(FEE_HISTORY_CACHE_BLOCKS),
+            verify_block_completeness: config.verify_block_completeness,
         }
     }
 }
 
 impl<RpcStorage: ReadRpcStorage, Mempool: L2TransactionPool> EthNamespace<RpcStorage, Mempool> {
+    /// Fails if the block is partially indexed and completeness checks are enabled.
+    fn check_block_complete(&self, number: BlockNumber) -> EthResult<()> {
+        if self.verify_block_completeness {
+            self.storage.repository().verify_block_complete(number)?;
+        }
+        Ok(())
+    }
+
     fn block_number_impl(&self) -> EthResult<U256> {
         Ok(U256::from(self.storage.repository().get_latest_block()))
     }
@@ -81,6 +105,12 @@ impl<RpcStorage: ReadRpcStorage, Mempool: L2TransactionPool> EthNamespace<RpcSto
         let Some(block) = self.storage.get_block_by_id(block_id)? else {
             return Ok(None);
         };
+        self.check_block_complete(block.number)?;
+        if full {
+            self.storage
+                .repository()
+                .ensure_transactions_available(&block)?;
+        }
         let mut rpc_block = block.into_rpc();
         if full {
             let tx_hashes = rpc_block.transactions().hashes();
@@ -113,6 +143,7 @@ impl<RpcStorage: ReadRpcStorage, Mempool: L2TransactionPool> EthNamespace<RpcSto
         let Some(block) = self.storage.get_block_by_id(block_id)? else {
             return Ok(None);
         };
+        self.check_block_complete(block.number)?;
         let mut receipts = Vec::new();
         for tx_hash in block.unseal().body.transactions {
             let Some(rpc_receipt) = self.transaction_receipt_impl(tx_hash)? else {
@@ -174,6 +205,9 @@ impl<RpcStorage: ReadRpcStorage, Mempool: L2TransactionPool> EthNamespace<RpcSto
         let Some(tx_hash) = block.body.transactions.get(index.0) else {
             return Ok(None);
         };
+        self.storage
+            .repository()
+            .ensure_transactions_available(&block)?;
         Ok(self
             .storage
             .repository()
@@ -192,6 +226,9 @@ impl<RpcStorage: ReadRpcStorage, Mempool: L2TransactionPool> EthNamespace<RpcSto
         let Some(tx_hash) = block.body.transactions.get(index.0) else {
             return Ok(None);
         };
+        self.storage
+            .repository()
+            .ensure_transactions_available(&block)?;
         let Some(tx) = self.storage.repository().get_transaction(*tx_hash)? else {
             return Ok(None);
         };
@@ -333,11 +370,14 @@ impl<RpcStorage: ReadRpcStorage, Mempool: L2TransactionPool> EthNamespace<RpcSto
         &self,
         block_count: U64,
         mut newest_block: BlockNumberOrTag,
-        _reward_percentiles: Option<Vec<f64>>,
+        reward_percentiles: Option<Vec<f64>>,
     ) -> EthResult<FeeHistory> {
         if block_count == 0 {
             return Ok(FeeHistory::default());
         }
+        if let Some(percentiles) = &reward_percentiles {
+            validate_reward_percentiles(percentiles).map_err(EthError::InvalidRewardPercentiles)?;
+        }
         if newest_block.is_pending() {
             // cap the target block since we don't have fee history for the pending block
             newest_block = BlockNumberOrTag::Latest;
@@ -348,47 +388,74 @@ impl<RpcStorage: ReadRpcStorage, Mempool: L2TransactionPool> EthNamespace<RpcSto
 
         let end_block_plus = end_block + 1;
         // Ensure that we would not be querying outside of genesis
-        let block_count = end_block_plus.min(block_count.try_into().unwrap());
+        let block_count = end_block_plus
+            .min(block_count.saturating_to())
+            .min(self.max_fee_history_blocks);
         let start_block = end_block_plus - block_count;
 
-        let mut base_fee_per_gas = Vec::with_capacity(block_count as usize + 1);
-        for block in start_block..=end_block {
-            let base_fee = self
-                .storage
-                .replay_storage()
-                .get_context(block)
-                .map(|c| c.eip1559_basefee)
-                .ok_or(EthError::BlockNotFound(BlockId::Number(
-                    BlockNumberOrTag::Number(block),
-                )))?;
-            base_fee_per_gas.push(base_fee.saturating_to());
-        }
-        if let Some(base_fee) = self
+        let blocks = (start_block..=end_block)
+            .map(|block| self.block_fees(block))
+            .collect::<EthResult<Vec<_>>>()?;
+
+        let next_base_fee: u128 = if let Some(block) = self
             .storage
-            .replay_storage()
-            .get_context(end_block_plus)
-            .map(|c| c.eip1559_basefee)
+            .repository()
+            .get_block_by_number(end_block_plus)?
         {
-            base_fee_per_gas.push(base_fee.saturating_to());
+            block.header.base_fee_per_gas.unwrap_or_default().into()
         } else if let Some(c) = self.eth_call_handler.pending_block_context()
             && c.block_number == end_block_plus
         {
-            base_fee_per_gas.push(c.eip1559_basefee.saturating_to());
+            c.eip1559_basefee.saturating_to()
         } else {
+            // The sequencer hasn't started the next block yet, so its pricing inputs are unknown.
             // block_count is >= 1 so last must be there.
-            base_fee_per_gas.push(*base_fee_per_gas.last().unwrap());
+            next_block_base_fee(BaseFeeInputs::Unchanged {
+                previous_base_fee: U256::from(blocks.last().unwrap().base_fee),
+            })
+            .saturating_to()
+        };
+
+        Ok(build_fee_history(
+            start_block,
+            &blocks,
+            next_base_fee,
+            reward_percentiles.as_deref(),
+        ))
+    }
+
+    /// Loads fees of a block from the repository, or from the cache if the block is recent.
+    fn block_fees(&self, number: BlockNumber) -> EthResult<Arc<BlockFees>> {
+        let repository = self.storage.repository();
+        let block = repository
+            .get_block_by_number(number)?
+            .ok_or(EthError::BlockNotFound(BlockId::Number(
+                BlockNumberOrTag::Number(number),
+            )))?;
+        let hash = block.hash();
+        if let Some(fees) = self.fee_history_cache.get(number, hash) {
+            return Ok(fees);
         }
 
-        Ok(FeeHistory {
-            base_fee_per_gas,
-            oldest_block: start_block,
-            // Conventional values.
-            gas_used_ratio: vec![0.5; block_count as usize],
-            base_fee_per_blob_gas: vec![],
-            blob_gas_used_ratio: vec![],
-            // TODO: fill reward
-            reward: None,
-        })
+        let base_fee = u128::from(block.header.base_fee_per_gas.unwrap_or_default());
+        let mut priority_fees = Vec::with_capacity(block.body.transactions.len());
+        for tx_hash in &block.body.transactions {
+            let Some(meta) = repository.get_transaction_meta(*tx_hash)? else {
+                continue;
+            };
+            priority_fees.push((
+                meta.effective_gas_price.saturating_sub(base_fee),
+                meta.gas_used,
+            ));
+        }
+        let fees = Arc::new(BlockFees::new(
+            base_fee,
+            block.header.gas_used,
+            block.header.gas_limit,
+            priority_fees,
+        ));
+        self.fee_history_cache.insert(number, hash, fees.clone());
+        Ok(fees)
     }
 }
 
@@ -593,11 +660,12 @@ impl<RpcStorage: ReadRpcStorage, Mempool: L2TransactionPool> EthApiServer
 
     async fn simulate_v1(
         &self,
-        _opts: SimulatePayload,
-        _block_number: Option<BlockId>,
+        opts: SimulatePayload,
+        block_number: Option<BlockId>,
     ) -> RpcResult<Vec<SimulatedBlock>> {
-        // todo(#51): implement
-        Err(unimplemented_rpc_err())
+        self.eth_call_handler
+            .simulate_v1_impl(opts, block_number)
+            .to_rpc_result()
     }
 
     async fn call(
@@ -675,9 +743,9 @@ impl<RpcStorage: ReadRpcStorage, Mempool: L2TransactionPool> EthApiServer
         Err(internal_rpc_err("node has no signer accounts"))
     }
 
-    async fn send_raw_transaction(&self, bytes: Bytes) -> RpcResult<B256> {
+    async fn send_raw_transaction(&self, ext: &Extensions, bytes: Bytes) -> RpcResult<B256> {
         self.tx_handler
-            .send_raw_transaction_impl(bytes)
+            .send_raw_transaction_impl(bytes, RequestOrigin::of(ext))
             .await
             .to_rpc_result()
     }
@@ -784,6 +852,9 @@ pub enum EthError {
     /// Incrementing the nonce would lead to invalid state (overflow)
     #[error("nonce has max value")]
     NonceMaxValue,
+    /// Reward percentiles passed to `eth_feeHistory` are invalid.
+    #[error("invalid reward percentiles: {0}")]
+    InvalidRewardPercentiles(String),
 
     #[error(transparent)]
     RpcStorage(#[from] RpcStorageError),
@@ -0,0 +1,251 @@
+//! Per-block data backing `eth_feeHistory`.
+
+use alloy::primitives::{B256, BlockNumber};
+use alloy::rpc::types::FeeHistory;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Maximum number of reward percentiles accepted per request.
+const MAX_REWARD_PERCENTILES: usize = 100;
+
+/// Fee data of a single block needed to answer `eth_feeHistory`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BlockFees {
+    pub base_fee: u128,
+    pub gas_used: u64,
+    pub gas_limit: u64,
+    /// `(effective priority fee, gas used)` of each transaction in the block, sorted by the fee.
+    priority_fees: Vec<(u128, u64)>,
+}
+
+impl BlockFees {
+    pub fn new(
+        base_fee: u128,
+        gas_used: u64,
+        gas_limit: u64,
+        mut priority_fees: Vec<(u128, u64)>,
+    ) -> Self {
+        priority_fees.sort_unstable_by_key(|(fee, _)| *fee);
+        Self {
+            base_fee,
+            gas_used,
+            gas_limit,
+            priority_fees,
+        }
+    }
+
+    pub fn gas_used_ratio(&self) -> f64 {
+        if self.gas_limit == 0 {
+            return 0.0;
+        }
+        self.gas_used as f64 / self.gas_limit as f64
+    }
+
+    /// Effective priority fees at the given percentiles, weighted by the gas used by transactions
+    /// (i.e. the fee paid for the `p`-th percentile of the block's gas). Follows geth, so that
+    /// wallets get the same estimates as on L1. All zeros for empty blocks.
+    ///
+    /// `percentiles` must be validated with [`validate_reward_percentiles`].
+    pub fn rewards(&self, percentiles: &[f64]) -> Vec<u128> {
+        if self.priority_fees.is_empty() {
+            return vec![0; percentiles.len()];
+        }
+        let mut tx_index = 0;
+        let mut cumulative_gas_used = self.priority_fees[0].1;
+        percentiles
+            .iter()
+            .map(|percentile| {
+                let threshold = (self.gas_used as f64 * percentile / 100.0) as u64;
+                while cumulative_gas_used < threshold && tx_index < self.priority_fees.len() - 1 {
+                    tx_index += 1;
+                    cumulative_gas_used += self.priority_fees[tx_index].1;
+                }
+                self.priority_fees[tx_index].0
+            })
+            .collect()
+    }
+}
+
+/// Checks that reward percentiles are within `[0, 100]` and non-decreasing.
+pub(crate) fn validate_reward_percentiles(percentiles: &[f64]) -> Result<(), String> {
+    if percentiles.len() > MAX_REWARD_PERCENTILES {
+        return Err(format!(
+            "at most {MAX_REWARD_PERCENTILES} reward percentiles are allowed, got {}",
+            percentiles.len()
+        ));
+    }
+    let mut previous = 0.0;
+    for &percentile in percentiles {
+        if !(0.0..=100.0).contains(&percentile) {
+            return Err(format!("reward percentile {percentile} is out of [0, 100]"));
+        }
+        if percentile < previous {
+            return Err(format!(
+                "reward percentiles must be non-decreasing, got {percentile} after {previous}"
+            ));
+        }
+        previous = percentile;
+    }
+    Ok(())
+}
+
+/// Builds the `eth_feeHistory` response for consecutive `blocks` starting from `oldest_block`.
+/// `next_block_base_fee` is the base fee of the block following the last one.
+pub(crate) fn build_fee_history(
+    oldest_block: BlockNumber,
+    blocks: &[Arc<BlockFees>],
+    next_block_base_fee: u128,
+    reward_percentiles: Option<&[f64]>,
+) -> FeeHistory {
+    let base_fee_per_gas = blocks
+        .iter()
+        .map(|block| block.base_fee)
+        .chain([next_block_base_fee])
+        .collect();
+    FeeHistory {
+        base_fee_per_gas,
+        gas_used_ratio: blocks.iter().map(|block| block.gas_used_ratio()).collect(),
+        // There are no blobs on L2
+        base_fee_per_blob_gas: vec![],
+        blob_gas_used_ratio: vec![],
+        oldest_block,
+        reward: reward_percentiles.map(|percentiles| {
+            blocks
+                .iter()
+                .map(|block| block.rewards(percentiles))
+                .collect()
+        }),
+    }
+}
+
+/// Cache of [`BlockFees`] for recent blocks, so that wallets polling `eth_feeHistory` don't cause
+/// transaction metadata of the same blocks to be read over and over.
+///
+/// Entries are keyed by block number and checked against the block hash, so that blocks replaced
+/// after a rollback are never served from the cache.
+#[derive(Debug)]
+pub(crate) struct FeeHistoryCache {
+    capacity: usize,
+    blocks: Mutex<BTreeMap<BlockNumber, (B256, Arc<BlockFees>)>>,
+}
+
+impl FeeHistoryCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            blocks: Mutex::default(),
+        }
+    }
+
+    pub fn get(&self, number: BlockNumber, hash: B256) -> Option<Arc<BlockFees>> {
+        let blocks = self.blocks.lock().unwrap();
+        let (cached_hash, fees) = blocks.get(&number)?;
+        (*cached_hash == hash).then(|| fees.clone())
+    }
+
+    /// Inserts fees of a block, evicting the oldest blocks if the cache is full.
+    pub fn insert(&self, number: BlockNumber, hash: B256, fees: Arc<BlockFees>) {
+        let mut blocks = self.blocks.lock().unwrap();
+        blocks.insert(number, (hash, fees));
+        while blocks.len() > self.capacity {
+            blocks.pop_first();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+    use zksync_os_gas_adjuster::{BaseFeeInputs, next_block_base_fee};
+
+    #[test]
+    fn rewards_are_weighted_by_gas() {
+        // Block gas used: 21_000 + 42_000 + 37_000 = 100_000
+        let fees = BlockFees::new(
+            100,
+            100_000,
+            400_000,
+            vec![(30, 37_000), (10, 21_000), (20, 42_000)],
+        );
+        assert_eq!(fees.gas_used_ratio(), 0.25);
+        // Gas thresholds: 0, 20_000, 21_000, 50_000, 63_000, 63_001, 100_000
+        // Cumulative gas by fee: 10 -> 21_000, 20 -> 63_000, 30 -> 100_000
+        assert_eq!(
+            fees.rewards(&[0.0, 20.0, 21.0, 50.0, 63.0, 63.001, 100.0]),
+            [10, 10, 10, 20, 20, 30, 30]
+        );
+        assert_eq!(fees.rewards(&[]), Vec::<u128>::new());
+    }
+
+    #[test]
+    fn rewards_of_empty_block() {
+        let fees = BlockFees::new(100, 0, 400_000, vec![]);
+        assert_eq!(fees.gas_used_ratio(), 0.0);
+        assert_eq!(fees.rewards(&[10.0, 90.0]), [0, 0]);
+        assert_eq!(BlockFees::new(100, 0, 0, vec![]).gas_used_ratio(), 0.0);
+    }
+
+    #[test]
+    fn reward_percentiles_validation() {
+        validate_reward_percentiles(&[]).unwrap();
+        validate_reward_percentiles(&[0.0, 25.0, 25.0, 100.0]).unwrap();
+        validate_reward_percentiles(&[50.0, 10.0]).unwrap_err();
+        validate_reward_percentiles(&[-1.0]).unwrap_err();
+        validate_reward_percentiles(&[100.5]).unwrap_err();
+        validate_reward_percentiles(&[f64::NAN]).unwrap_err();
+        validate_reward_percentiles(&[50.0; 101]).unwrap_err();
+    }
+
+    #[test]
+    fn fee_history_response() {
+        let blocks = [
+            Arc::new(BlockFees::new(
+                100_000_000,
+                84_000,
+                1_000_000,
+                vec![(2, 21_000), (1, 63_000)],
+            )),
+            Arc::new(BlockFees::new(100_000_000, 0, 1_000_000, vec![])),
+        ];
+        // No newer block or pending context is known, so the base fee of the last block is kept
+        let next_base_fee = next_block_base_fee(BaseFeeInputs::Unchanged {
+            previous_base_fee: U256::from(blocks[1].base_fee),
+        });
+        let history = build_fee_history(
+            7,
+            &blocks,
+            next_base_fee.saturating_to(),
+            Some(&[25.0, 75.0, 90.0]),
+        );
+        assert_eq!(
+            history,
+            FeeHistory {
+                base_fee_per_gas: vec![100_000_000, 100_000_000, 100_000_000],
+                gas_used_ratio: vec![0.084, 0.0],
+                base_fee_per_blob_gas: vec![],
+                blob_gas_used_ratio: vec![],
+                oldest_block: 7,
+                // Thresholds of the first block: 21_000, 63_000, 75_600 gas
+                reward: Some(vec![vec![1, 1, 2], vec![0, 0, 0]]),
+            }
+        );
+
+        let history = build_fee_history(7, &blocks[..1], 250_050_000_000, None);
+        assert_eq!(history.base_fee_per_gas, [100_000_000, 250_050_000_000]);
+        assert_eq!(history.reward, None);
+    }
+
+    #[test]
+    fn cache_evicts_oldest_blocks() {
+        let cache = FeeHistoryCache::new(2);
+        let fees = Arc::new(BlockFees::new(1, 0, 0, vec![]));
+        for number in 1..=3 {
+            cache.insert(number, B256::with_last_byte(number as u8), fees.clone());
+        }
+        assert_eq!(cache.get(1, B256::with_last_byte(1)), None);
+        assert_eq!(cache.get(3, B256::with_last_byte(3)), Some(fees));
+        // A block replaced with another one is not served from the cache
+        assert_eq!(cache.get(2, B256::with_last_byte(0xff)), None);
+    }
+}
@@ -10,6 +10,7 @@ mod eth_call_handler;
 mod eth_filter_impl;
 mod eth_impl;
 mod eth_pubsub_impl;
+mod fee_history;
 mod metrics;
 mod ots_impl;
 mod result;
@@ -18,7 +19,10 @@ pub use rpc_storage::{ReadRpcStorage, RpcStorage};
 mod debug_impl;
 mod monitoring_middleware;
 mod net_impl;
+mod preconfirmation;
+pub use preconfirmation::PreconfirmationConfig;
 mod sandbox;
+mod simulate;
 mod tx_handler;
 mod types;
 mod web3_impl;
@@ -32,14 +36,22 @@ use crate::eth_pubsub_impl::EthPubsubNamespace;
 use crate::monitoring_middleware::Monitoring;
 use crate::net_impl::NetNamespace;
 use crate::ots_impl::OtsNamespace;
+use crate::preconfirmation::Preconfirmations;
+use crate::tx_handler::{TxHandler, tag_request_origin};
 use crate::web3_impl::Web3Namespace;
 use crate::zks_impl::ZksNamespace;
 use alloy::primitives::Address;
 use anyhow::Context;
 use hyper::Method;
-use jsonrpsee::RpcModule;
-use jsonrpsee::server::{ServerBuilder, ServerConfigBuilder};
+use hyper::body::Incoming;
+use jsonrpsee::server::{
+    HttpRequest, ServerBuilder, ServerConfigBuilder, serve_with_graceful_shutdown, stop_channel,
+};
 use jsonrpsee::ws_client::RpcServiceBuilder;
+use jsonrpsee::{Methods, RpcModule};
+use std::net::IpAddr;
+use std::time::Duration;
+use tokio::net::TcpListener;
 use tower_http::cors::{Any, CorsLayer};
 use zksync_os_genesis::GenesisInputSource;
 use zksync_os_interface::types::BlockContext;
@@ -54,6 +66,9 @@ use zksync_os_rpc_api::web3::Web3ApiServer;
 use zksync_os_rpc_api::zks::ZksApiServer;
 use zksync_os_types::TransactionAcceptanceState;
 
+/// Delay before accepting connections again after a failure to accept one.
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
 #[allow(clippy::too_many_arguments)]
 pub async fn run_jsonrpsee_server<RpcStorage: ReadRpcStorage, Mempool: L2TransactionPool>(
     config: RpcConfig,
@@ -67,6 +82,16 @@ pub async fn run_jsonrpsee_server<RpcStorage: ReadRpcStorage, Mempool: L2Transac
 ) -> anyhow::Result<()> {
     tracing::info!("Starting JSON-RPC server at {}", config.address);
 
+    let (preconfirmations, preconfirmation_signer) = match config.preconfirmations.clone() {
+        Some(preconfirmation_config) => {
+            let (preconfirmations, signer) =
+                Preconfirmations::new(preconfirmation_config, chain_id);
+            (Some(preconfirmations), Some(signer))
+        }
+        None => (None, None),
+    };
+    let tx_handler = TxHandler::new(mempool.clone(), acceptance_state, preconfirmations);
+
     let mut rpc = RpcModule::new(());
     let eth_call_handler = EthCallHandler::new(
         config.clone(),
@@ -76,20 +101,28 @@ pub async fn run_jsonrpsee_server<RpcStorage: ReadRpcStorage, Mempool: L2Transac
     );
     rpc.merge(
         EthNamespace::new(
+            &config,
             storage.clone(),
             mempool.clone(),
             eth_call_handler.clone(),
             chain_id,
-            acceptance_state,
+            tx_handler.clone(),
         )
         .into_rpc(),
     )?;
     rpc.merge(
         EthFilterNamespace::new(config.clone(), storage.clone(), mempool.clone()).into_rpc(),
     )?;
-    rpc.merge(EthPubsubNamespace::new(storage.clone(), mempool).into_rpc())?;
+    rpc.merge(EthPubsubNamespace::new(storage.clone(), mempool.clone()).into_rpc())?;
     rpc.merge(
-        ZksNamespace::new(bridgehub_address, storage.clone(), genesis_input_source).into_rpc(),
+        ZksNamespace::new(
+            bridgehub_address,
+            storage.clone(),
+            mempool,
+            genesis_input_source,
+            tx_handler,
+        )
+        .into_rpc(),
     )?;
     rpc.merge(OtsNamespace::new(storage.clone()).into_rpc())?;
     rpc.merge(DebugNamespace::new(storage.clone(), eth_call_handler).into_rpc())?;
@@ -117,18 +150,56 @@ pub async fn run_jsonrpsee_server<RpcStorage: ReadRpcStorage, Mempool: L2Transac
         .max_request_body_size(config.max_request_size_bytes())
         .max_response_body_size(config.max_response_size_bytes())
         .build();
-    let server_builder = ServerBuilder::default()
+    let service_builder = ServerBuilder::default()
         .set_config(server_config)
         .set_http_middleware(middleware)
-        .set_rpc_middleware(rpc_middleware);
-
-    let server = server_builder
-        .build(config.address)
+        .set_rpc_middleware(rpc_middleware)
+        .to_service_builder();
+    let listener = TcpListener::bind(&config.address)
         .await
-        .context("Failed building HTTP JSON-RPC server")?;
+        .with_context(|| format!("Failed binding HTTP JSON-RPC server to {}", config.address))?;
 
-    let server_handle = server.start(rpc);
+    // Connections are accepted here rather than by `Server::start()`, since request origins
+    // depend on the connection peer. The server is never stopped, so the handle is only kept alive.
+    let (stop_handle, _server_handle) = stop_channel();
+    let methods = Methods::from(rpc);
+    let trusted_proxies: Arc<[IpAddr]> = config.trusted_proxies.into();
+    let server = async move {
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(err) => {
+                    // E.g., the process ran out of file descriptors; let other connections close
+                    tracing::warn!("Failed accepting JSON-RPC connection: {err}");
+                    tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                    continue;
+                }
+            };
+            let trusted_proxies = trusted_proxies.clone();
+            let service = tower::ServiceBuilder::new()
+                .map_request(move |request: HttpRequest<Incoming>| {
+                    tag_request_origin(request, peer.ip(), &trusted_proxies)
+                })
+                .service(
+                    service_builder
+                        .clone()
+                        .build(methods.clone(), stop_handle.clone()),
+                );
+            tokio::spawn(serve_with_graceful_shutdown(
+                stream,
+                service,
+                std::future::pending::<()>(),
+            ));
+        }
+    };
 
-    server_handle.stopped().await;
-    Ok(())
+    match preconfirmation_signer {
+        Some(signer) => {
+            tokio::select! {
+                never = server => never,
+                result = signer.run(storage) => result.context("preconfirmation signer failed"),
+            }
+        }
+        None => server.await,
+    }
 }
@@ -1,5 +1,5 @@
 use std::time::Duration;
-use vise::{Buckets, Histogram, LabeledFamily, Metrics, Unit};
+use vise::{Buckets, Counter, Gauge, Histogram, LabeledFamily, Metrics, Unit};
 
 const LATENCIES_FAST: Buckets = Buckets::exponential(0.0000001..=1.0, 2.0);
 const BLOCK_COUNTS: Buckets = Buckets::exponential(1.0..=100000.0, 10.0);
@@ -21,3 +21,19 @@ pub struct ApiMetrics {
 
 #[vise::register]
 pub static API_METRICS: vise::Global<ApiMetrics> = vise::Global::new();
+
+#[derive(Debug, Metrics)]
+#[metrics(prefix = "preconfirmation")]
+pub struct PreconfirmationMetrics {
+    /// Number of signed preconfirmations.
+    pub signed: Counter,
+    /// Number of preconfirmation requests dropped because the signing queue was full.
+    pub dropped_requests: Counter,
+    /// Number of preconfirmed transactions that were not included before the deadline.
+    pub breaches: Counter,
+    /// Number of preconfirmations currently available for lookup.
+    pub stored: Gauge<usize>,
+}
+
+#[vise::register]
+pub static PRECONFIRMATION_METRICS: vise::Global<PreconfirmationMetrics> = vise::Global::new();
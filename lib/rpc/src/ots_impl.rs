@@ -71,6 +71,9 @@ impl<RpcStorage: ReadRpcStorage> OtsNamespace<RpcStorage> {
             .storage
             .get_block_by_id(block_id)?
             .ok_or(EthError::BlockNotFound(block_id))?;
+        self.storage
+            .repository()
+            .ensure_transactions_available(&block)?;
         let block = block.into_rpc();
         let transaction_count = block.transactions.len();
         let mut total_fees = U256::ZERO;
@@ -105,6 +108,9 @@ impl<RpcStorage: ReadRpcStorage> OtsNamespace<RpcStorage> {
             .storage
             .get_block_by_id(block_id)?
             .ok_or(EthError::BlockNotFound(block_id))?;
+        self.storage
+            .repository()
+            .ensure_transactions_available(&block)?;
         let block = block.into_rpc();
         let transaction_count = block.transactions.len();
 
@@ -178,6 +184,9 @@ impl<RpcStorage: ReadRpcStorage> OtsNamespace<RpcStorage> {
             else {
                 break;
             };
+            self.storage
+                .repository()
+                .ensure_transactions_available(&block)?;
             let (block, _) = block.into_parts();
             for tx_hash in block.body.transactions {
                 let Some(StoredTxData { tx, receipt, meta }) =
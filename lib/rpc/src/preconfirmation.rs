@@ -0,0 +1,428 @@
+//! Transaction preconfirmations: acknowledgments signed by the sequencer once a transaction is
+//! accepted into the pending subpool.
+//!
+//! Signing happens in a separate worker with a bounded queue, so that transaction insertion is never
+//! slowed down by it; if the queue is full, the transaction simply doesn't get a preconfirmation.
+//! Signed preconfirmations are kept in a bounded store for `ttl`. The worker also checks that
+//! preconfirmed transactions are included before their deadlines and records breaches otherwise.
+
+use crate::ReadRpcStorage;
+use crate::metrics::PRECONFIRMATION_METRICS;
+use alloy::primitives::{Address, TxHash};
+use alloy::signers::SignerSync;
+use alloy::signers::local::PrivateKeySigner;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, oneshot};
+use zksync_os_rpc_api::types::{Preconfirmation, SignedPreconfirmation};
+
+/// How often to check deadlines of preconfirmed transactions.
+const DEADLINE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug)]
+pub struct PreconfirmationConfig {
+    /// Sequencer key used to sign preconfirmations.
+    pub signer: PrivateKeySigner,
+    /// Time since acceptance by which a preconfirmed transaction is to be included.
+    pub inclusion_deadline: Duration,
+    /// How long signed preconfirmations are available via `zks_getPreconfirmation`.
+    pub ttl: Duration,
+    /// Max number of stored preconfirmations; the oldest ones are evicted first.
+    pub store_capacity: usize,
+    /// Max number of transactions waiting to be signed.
+    pub queue_capacity: usize,
+}
+
+struct SignRequest {
+    tx_hash: TxHash,
+    sender: Address,
+    nonce: u64,
+    response: oneshot::Sender<SignedPreconfirmation>,
+}
+
+/// Handle used by the API to request and look up preconfirmations.
+#[derive(Clone)]
+pub struct Preconfirmations {
+    requests: mpsc::Sender<SignRequest>,
+    store: Arc<PreconfirmationStore>,
+}
+
+impl Preconfirmations {
+    pub fn new(config: PreconfirmationConfig, chain_id: u64) -> (Self, PreconfirmationSigner) {
+        let (requests, requests_receiver) = mpsc::channel(config.queue_capacity.max(1));
+        let store = Arc::new(PreconfirmationStore::new(
+            config.ttl.max(config.inclusion_deadline),
+            config.store_capacity,
+        ));
+        let this = Self {
+            requests,
+            store: store.clone(),
+        };
+        let signer = PreconfirmationSigner {
+            signer: config.signer,
+            chain_id,
+            inclusion_deadline: config.inclusion_deadline,
+            requests: requests_receiver,
+            store,
+        };
+        (this, signer)
+    }
+
+    /// Queues a transaction accepted into the pending subpool for signing. Never waits; returns `None`
+    /// if the signing queue is full.
+    pub fn request(
+        &self,
+        tx_hash: TxHash,
+        sender: Address,
+        nonce: u64,
+    ) -> Option<oneshot::Receiver<SignedPreconfirmation>> {
+        let (response, response_receiver) = oneshot::channel();
+        let request = SignRequest {
+            tx_hash,
+            sender,
+            nonce,
+            response,
+        };
+        match self.requests.try_send(request) {
+            Ok(()) => Some(response_receiver),
+            Err(_) => {
+                PRECONFIRMATION_METRICS.dropped_requests.inc();
+                None
+            }
+        }
+    }
+
+    pub fn get(&self, tx_hash: TxHash) -> Option<SignedPreconfirmation> {
+        self.store.get(tx_hash, Instant::now())
+    }
+}
+
+/// Worker signing preconfirmations and checking their deadlines.
+pub struct PreconfirmationSigner {
+    signer: PrivateKeySigner,
+    chain_id: u64,
+    inclusion_deadline: Duration,
+    requests: mpsc::Receiver<SignRequest>,
+    store: Arc<PreconfirmationStore>,
+}
+
+impl PreconfirmationSigner {
+    pub async fn run(mut self, storage: impl ReadRpcStorage) -> anyhow::Result<()> {
+        tracing::info!(
+            sequencer = %self.signer.address(),
+            inclusion_deadline = ?self.inclusion_deadline,
+            "signing transaction preconfirmations"
+        );
+        let mut timer = tokio::time::interval(DEADLINE_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                request = self.requests.recv() => {
+                    let Some(request) = request else {
+                        anyhow::bail!("preconfirmation requests channel closed");
+                    };
+                    self.handle(request)?;
+                }
+                _ = timer.tick() => {
+                    self.store.check_deadlines(unix_timestamp(), |tx_hash| {
+                        Ok(storage.repository().get_transaction_meta(tx_hash)?.is_some())
+                    })?;
+                }
+            }
+        }
+    }
+
+    fn handle(&self, request: SignRequest) -> anyhow::Result<()> {
+        let signed = self.sign(request.tx_hash, request.sender, request.nonce)?;
+        self.store.insert(signed.clone(), Instant::now());
+        // The requester is not obliged to wait for the response
+        request.response.send(signed).ok();
+        Ok(())
+    }
+
+    fn sign(
+        &self,
+        tx_hash: TxHash,
+        sender: Address,
+        nonce: u64,
+    ) -> anyhow::Result<SignedPreconfirmation> {
+        let preconfirmation = Preconfirmation {
+            tx_hash,
+            sender,
+            nonce,
+            deadline: unix_timestamp() + self.inclusion_deadline.as_secs(),
+            sequencer: self.signer.address(),
+            chain_id: self.chain_id,
+        };
+        let signature = self
+            .signer
+            .sign_hash_sync(&preconfirmation.signing_hash())?;
+        PRECONFIRMATION_METRICS.signed.inc();
+        Ok(SignedPreconfirmation {
+            preconfirmation,
+            signature,
+        })
+    }
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Incorrect system time")
+        .as_secs()
+}
+
+#[derive(Debug)]
+struct StoredPreconfirmation {
+    signed: SignedPreconfirmation,
+    inserted_at: Instant,
+    /// Whether inclusion was already checked after the deadline.
+    deadline_checked: bool,
+}
+
+/// Bounded store of signed preconfirmations with TTL-based expiration.
+#[derive(Debug)]
+struct PreconfirmationStore {
+    ttl: Duration,
+    capacity: usize,
+    inner: Mutex<StoreInner>,
+}
+
+#[derive(Debug, Default)]
+struct StoreInner {
+    entries: HashMap<TxHash, StoredPreconfirmation>,
+    /// Insertion order. The same transaction may be preconfirmed again, so items not matching
+    /// `inserted_at` of the entry are stale.
+    order: VecDeque<(Instant, TxHash)>,
+}
+
+impl StoreInner {
+    fn evict(&mut self, now: Instant, ttl: Duration, capacity: usize) {
+        while let Some(&(inserted_at, tx_hash)) = self.order.front() {
+            if self.entries.len() <= capacity && now.duration_since(inserted_at) < ttl {
+                break;
+            }
+            self.order.pop_front();
+            if self
+                .entries
+                .get(&tx_hash)
+                .is_some_and(|stored| stored.inserted_at == inserted_at)
+            {
+                self.entries.remove(&tx_hash);
+            }
+        }
+    }
+}
+
+impl PreconfirmationStore {
+    fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity: capacity.max(1),
+            inner: Mutex::default(),
+        }
+    }
+
+    fn insert(&self, signed: SignedPreconfirmation, now: Instant) {
+        let mut inner = self.inner.lock().unwrap();
+        let tx_hash = signed.preconfirmation.tx_hash;
+        inner.entries.insert(
+            tx_hash,
+            StoredPreconfirmation {
+                signed,
+                inserted_at: now,
+                deadline_checked: false,
+            },
+        );
+        inner.order.push_back((now, tx_hash));
+        inner.evict(now, self.ttl, self.capacity);
+        PRECONFIRMATION_METRICS.stored.set(inner.entries.len());
+    }
+
+    fn get(&self, tx_hash: TxHash, now: Instant) -> Option<SignedPreconfirmation> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.evict(now, self.ttl, self.capacity);
+        inner
+            .entries
+            .get(&tx_hash)
+            .map(|stored| stored.signed.clone())
+    }
+
+    /// Checks inclusion of preconfirmed transactions with passed deadlines. Returns breached
+    /// preconfirmations, i.e. ones for transactions that were not included in time.
+    fn check_deadlines(
+        &self,
+        now_unix: u64,
+        is_included: impl Fn(TxHash) -> anyhow::Result<bool>,
+    ) -> anyhow::Result<Vec<SignedPreconfirmation>> {
+        let mut breached = vec![];
+        let mut inner = self.inner.lock().unwrap();
+        for stored in inner.entries.values_mut() {
+            let preconfirmation = &stored.signed.preconfirmation;
+            if stored.deadline_checked || preconfirmation.deadline > now_unix {
+                continue;
+            }
+            stored.deadline_checked = true;
+            if !is_included(preconfirmation.tx_hash)? {
+                PRECONFIRMATION_METRICS.breaches.inc();
+                tracing::error!(
+                    tx_hash = %preconfirmation.tx_hash,
+                    sender = %preconfirmation.sender,
+                    nonce = preconfirmation.nonce,
+                    deadline = preconfirmation.deadline,
+                    "preconfirmed transaction was not included before the deadline"
+                );
+                breached.push(stored.signed.clone());
+            }
+        }
+        Ok(breached)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(ttl: Duration, store_capacity: usize) -> PreconfirmationConfig {
+        PreconfirmationConfig {
+            signer: PrivateKeySigner::random(),
+            inclusion_deadline: Duration::from_secs(30),
+            ttl,
+            store_capacity,
+            queue_capacity: 2,
+        }
+    }
+
+    #[test]
+    fn signature_is_recoverable() {
+        let config = config(Duration::from_secs(60), 10);
+        let sequencer = config.signer.address();
+        let (_, signer) = Preconfirmations::new(config, 270);
+        let tx_hash = TxHash::repeat_byte(1);
+        let sender = Address::repeat_byte(2);
+
+        let signed = signer.sign(tx_hash, sender, 5).unwrap();
+        let preconfirmation = &signed.preconfirmation;
+        assert_eq!(preconfirmation.tx_hash, tx_hash);
+        assert_eq!(preconfirmation.sender, sender);
+        assert_eq!(preconfirmation.nonce, 5);
+        assert_eq!(preconfirmation.sequencer, sequencer);
+        assert_eq!(preconfirmation.chain_id, 270);
+        assert!(preconfirmation.deadline >= unix_timestamp() + 29);
+        assert_eq!(signed.recover_signer().unwrap(), sequencer);
+
+        let mut tampered = signed.clone();
+        tampered.preconfirmation.nonce = 6;
+        assert_ne!(tampered.recover_signer().ok(), Some(sequencer));
+
+        // Fields are flattened next to the signature
+        let json = serde_json::to_value(&signed).unwrap();
+        assert_eq!(json["txHash"], serde_json::json!(tx_hash));
+        assert!(!json["signature"].is_null());
+    }
+
+    #[test]
+    fn signed_preconfirmations_can_be_looked_up() {
+        let (preconfirmations, mut signer) =
+            Preconfirmations::new(config(Duration::from_secs(60), 10), 270);
+        let tx_hash = TxHash::repeat_byte(1);
+        assert_eq!(preconfirmations.get(tx_hash), None);
+
+        let response = preconfirmations
+            .request(tx_hash, Address::repeat_byte(2), 0)
+            .unwrap();
+        signer.handle(signer.requests.try_recv().unwrap()).unwrap();
+        let signed = response.blocking_recv().unwrap();
+        assert_eq!(preconfirmations.get(tx_hash), Some(signed));
+        assert_eq!(preconfirmations.get(TxHash::repeat_byte(2)), None);
+    }
+
+    #[test]
+    fn requests_are_dropped_when_queue_is_full() {
+        let (preconfirmations, _signer) =
+            Preconfirmations::new(config(Duration::from_secs(60), 10), 270);
+        for i in 0..2 {
+            assert!(
+                preconfirmations
+                    .request(TxHash::repeat_byte(i), Address::ZERO, 0)
+                    .is_some()
+            );
+        }
+        assert!(
+            preconfirmations
+                .request(TxHash::repeat_byte(2), Address::ZERO, 0)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn store_expiry_and_capacity() {
+        let (_, signer) = Preconfirmations::new(config(Duration::from_secs(60), 2), 270);
+        let store = PreconfirmationStore::new(Duration::from_secs(60), 2);
+        let start = Instant::now();
+        let signed: Vec<_> = (0..3)
+            .map(|i| {
+                signer
+                    .sign(TxHash::repeat_byte(i), Address::ZERO, 0)
+                    .unwrap()
+            })
+            .collect();
+        let hash = |i: usize| signed[i].preconfirmation.tx_hash;
+
+        store.insert(signed[0].clone(), start);
+        store.insert(signed[1].clone(), start + Duration::from_secs(10));
+        assert!(
+            store
+                .get(hash(0), start + Duration::from_secs(59))
+                .is_some()
+        );
+        assert!(
+            store
+                .get(hash(0), start + Duration::from_secs(60))
+                .is_none()
+        );
+        assert!(
+            store
+                .get(hash(1), start + Duration::from_secs(60))
+                .is_some()
+        );
+
+        // Capacity evicts the oldest entries
+        store.insert(signed[0].clone(), start + Duration::from_secs(61));
+        store.insert(signed[2].clone(), start + Duration::from_secs(62));
+        let now = start + Duration::from_secs(62);
+        assert!(store.get(hash(1), now).is_none());
+        assert!(store.get(hash(0), now).is_some());
+        assert!(store.get(hash(2), now).is_some());
+    }
+
+    #[test]
+    fn breaches_are_detected_after_deadline() {
+        let (_, signer) = Preconfirmations::new(config(Duration::from_secs(60), 10), 270);
+        let store = PreconfirmationStore::new(Duration::from_secs(60), 10);
+        let included = signer
+            .sign(TxHash::repeat_byte(1), Address::ZERO, 0)
+            .unwrap();
+        let dropped = signer
+            .sign(TxHash::repeat_byte(2), Address::ZERO, 1)
+            .unwrap();
+        let deadline = included.preconfirmation.deadline;
+        store.insert(included.clone(), Instant::now());
+        store.insert(dropped.clone(), Instant::now());
+        let is_included = |tx_hash| Ok(tx_hash == included.preconfirmation.tx_hash);
+
+        assert_eq!(
+            store.check_deadlines(deadline - 1, is_included).unwrap(),
+            []
+        );
+        assert_eq!(
+            store.check_deadlines(deadline + 1, is_included).unwrap(),
+            [dropped]
+        );
+        // Each breach is recorded once
+        assert_eq!(
+            store.check_deadlines(deadline + 2, is_included).unwrap(),
+            []
+        );
+    }
+}
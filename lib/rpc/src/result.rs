@@ -15,6 +15,10 @@ use alloy::rpc::types::error::EthRpcErrorCode;
 use alloy::sol_types::{ContractError, RevertReason};
 use jsonrpsee::core::RpcResult;
 use std::fmt;
+use zksync_os_storage_api::RepositoryError;
+
+/// Error code for requests to history pruned on this node. The same code is used by Geth.
+pub const PRUNED_HISTORY_ERROR_CODE: i32 = 4444;
 
 /// Helper trait to easily convert various `Result` types into [`RpcResult`]
 pub trait ToRpcResult<Ok, Err>: Sized {
@@ -38,10 +42,45 @@ macro_rules! impl_to_rpc_result {
 }
 
 impl_to_rpc_result!(EthSendRawTransactionError);
-impl_to_rpc_result!(EthFilterError);
-impl_to_rpc_result!(EthError);
-impl_to_rpc_result!(ZksError);
-impl_to_rpc_result!(DebugError);
+
+impl<Ok> ToRpcResult<Ok, EthError> for Result<Ok, EthError> {
+    fn to_rpc_result(self) -> RpcResult<Ok> {
+        self.map_err(|err| match err {
+            EthError::InvalidRewardPercentiles(_) => invalid_params_rpc_err(err.to_string()),
+            EthError::Repository(RepositoryError::Pruned(_)) => pruned_history_rpc_err(err),
+            err => internal_rpc_err(err.to_string()),
+        })
+    }
+}
+
+impl<Ok> ToRpcResult<Ok, EthFilterError> for Result<Ok, EthFilterError> {
+    fn to_rpc_result(self) -> RpcResult<Ok> {
+        self.map_err(|err| match err {
+            EthFilterError::RepositoryError(RepositoryError::Pruned(_)) => {
+                pruned_history_rpc_err(err)
+            }
+            err => internal_rpc_err(err.to_string()),
+        })
+    }
+}
+
+impl<Ok> ToRpcResult<Ok, ZksError> for Result<Ok, ZksError> {
+    fn to_rpc_result(self) -> RpcResult<Ok> {
+        self.map_err(|err| match err {
+            ZksError::Repository(RepositoryError::Pruned(_)) => pruned_history_rpc_err(err),
+            err => internal_rpc_err(err.to_string()),
+        })
+    }
+}
+
+impl<Ok> ToRpcResult<Ok, DebugError> for Result<Ok, DebugError> {
+    fn to_rpc_result(self) -> RpcResult<Ok> {
+        self.map_err(|err| match err {
+            DebugError::Repository(RepositoryError::Pruned(_)) => pruned_history_rpc_err(err),
+            err => internal_rpc_err(err.to_string()),
+        })
+    }
+}
 
 impl<Ok> ToRpcResult<Ok, EthCallError> for Result<Ok, EthCallError> {
     fn to_rpc_result(self) -> RpcResult<Ok> {
@@ -51,6 +90,7 @@ impl<Ok> ToRpcResult<Ok, EthCallError> for Result<Ok, EthCallError> {
                 revert.to_string(),
                 revert.output.as_ref().map(|out| out.as_ref()),
             ),
+            EthCallError::Simulation(err) => rpc_err(err.code(), err.to_string(), None),
             err => internal_rpc_err(err.to_string()),
         })
     }
@@ -68,6 +108,13 @@ pub fn invalid_params_rpc_err(
     rpc_err(jsonrpsee::types::error::INVALID_PARAMS_CODE, msg, None)
 }
 
+/// Constructs a JSON-RPC error for requests to history pruned on this node.
+pub fn pruned_history_rpc_err(
+    err: impl fmt::Display,
+) -> jsonrpsee::types::error::ErrorObject<'static> {
+    rpc_err(PRUNED_HISTORY_ERROR_CODE, err.to_string(), None)
+}
+
 /// Constructs an internal JSON-RPC error.
 pub fn internal_rpc_err(msg: impl Into<String>) -> jsonrpsee::types::error::ErrorObject<'static> {
     rpc_err(jsonrpsee::types::error::INTERNAL_ERROR_CODE, msg, None)
@@ -0,0 +1,426 @@
+//! Building blocks of `eth_simulateV1`, see `EthCallHandler::simulate_v1_impl()`.
+
+use crate::eth_call_handler::EthCallError;
+use crate::result::RevertError;
+use crate::types::ZkHeader;
+use alloy::network::primitives::BlockTransactions;
+use alloy::primitives::{Address, B256, Bloom, Bytes, LogData, U256, address, b256};
+use alloy::rpc::types::error::EthRpcErrorCode;
+use alloy::rpc::types::simulate::{SimCallResult, SimulateError, SimulatedBlock};
+use alloy::rpc::types::trace::geth::CallFrame;
+use alloy::rpc::types::{Block, BlockOverrides, Log};
+use zksync_os_interface::types::{
+    BlockContext, BlockHashes, BlockOutput, ExecutionOutput, ExecutionResult,
+};
+use zksync_os_types::ZkTransaction;
+
+/// Pseudo-address that emits logs of native token transfers (ERC-7528), as done by geth when
+/// `traceTransfers` is set.
+const TRANSFER_LOG_ADDRESS: Address = address!("0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE");
+/// `keccak256("Transfer(address,address,uint256)")`
+const TRANSFER_EVENT_TOPIC: B256 =
+    b256!("0xddf252ad1be2c89b69c2b068fc378daa952ba7f163c4a11628f55a4df523b3ef");
+
+/// Error codes of `eth_simulateV1` used by geth.
+const INVALID_BLOCK_NUMBER_CODE: i32 = -38020;
+const INVALID_BLOCK_TIMESTAMP_CODE: i32 = -38021;
+const BLOCK_GAS_LIMIT_REACHED_CODE: i32 = -38015;
+const CLIENT_LIMIT_EXCEEDED_CODE: i32 = -38026;
+
+/// Errors specific to `eth_simulateV1` requests.
+#[derive(Debug, thiserror::Error)]
+pub enum SimulationError {
+    #[error("too many blocks to simulate (max: {max})")]
+    TooManyBlocks { max: u64 },
+    #[error("too many calls to simulate (max: {max})")]
+    TooManyCalls { max: usize },
+    #[error("total gas limit of simulated calls exceeds {max}")]
+    GasLimitExceeded { max: u64 },
+    #[error("block number {number} is not greater than the previous block number {previous}")]
+    BlockNumberNotIncreasing { number: u64, previous: u64 },
+    #[error("block timestamp {timestamp} is lower than the previous block timestamp {previous}")]
+    TimestampDecreasing { timestamp: u64, previous: u64 },
+    #[error("only {executed} out of {total} calls fit into simulated block {block_number}")]
+    BlockLimitReached {
+        block_number: u64,
+        executed: usize,
+        total: usize,
+    },
+}
+
+impl SimulationError {
+    /// JSON-RPC error code of the error.
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::TooManyBlocks { .. }
+            | Self::TooManyCalls { .. }
+            | Self::GasLimitExceeded { .. } => CLIENT_LIMIT_EXCEEDED_CODE,
+            Self::BlockNumberNotIncreasing { .. } => INVALID_BLOCK_NUMBER_CODE,
+            Self::TimestampDecreasing { .. } => INVALID_BLOCK_TIMESTAMP_CODE,
+            Self::BlockLimitReached { .. } => BLOCK_GAS_LIMIT_REACHED_CODE,
+        }
+    }
+}
+
+/// Chain of synthetic blocks on top of a real parent block.
+#[derive(Debug)]
+pub struct SimulatedChain {
+    /// Context of the last block in the chain.
+    last_block: BlockContext,
+    last_block_hash: B256,
+    /// Number of the real block the chain is anchored on.
+    anchor_block_number: u64,
+    max_blocks: u64,
+}
+
+impl SimulatedChain {
+    pub fn new(parent: BlockContext, parent_hash: B256, max_blocks: u64) -> Self {
+        Self {
+            last_block: parent,
+            last_block_hash: parent_hash,
+            anchor_block_number: parent.block_number,
+            max_blocks,
+        }
+    }
+
+    /// Returns the context of the next block in the chain. Fields not set in `overrides` are
+    /// inherited from the previous block, except for the base fee that is zero unless
+    /// `validation` is set.
+    pub fn next_block_context(
+        &self,
+        overrides: Option<BlockOverrides>,
+        validation: bool,
+    ) -> Result<BlockContext, SimulationError> {
+        let overrides = overrides.unwrap_or_default();
+        let previous = &self.last_block;
+
+        let block_number = match overrides.number {
+            Some(number) => {
+                let number = number.saturating_to::<u64>();
+                if number <= previous.block_number {
+                    return Err(SimulationError::BlockNumberNotIncreasing {
+                        number,
+                        previous: previous.block_number,
+                    });
+                }
+                number
+            }
+            None => previous.block_number + 1,
+        };
+        // Skipped block numbers count towards the limit as well
+        if block_number - self.anchor_block_number > self.max_blocks {
+            return Err(SimulationError::TooManyBlocks {
+                max: self.max_blocks,
+            });
+        }
+        let timestamp = overrides.time.unwrap_or(previous.timestamp + 1);
+        if timestamp < previous.timestamp {
+            return Err(SimulationError::TimestampDecreasing {
+                timestamp,
+                previous: previous.timestamp,
+            });
+        }
+
+        // Hashes of skipped blocks are unknown to the simulated code
+        let skipped_blocks = (block_number - previous.block_number - 1) as usize;
+        let mut block_hashes = previous.block_hashes.0;
+        for hash in std::iter::once(self.last_block_hash)
+            .chain(std::iter::repeat_n(B256::ZERO, skipped_blocks))
+            .take(block_hashes.len())
+        {
+            block_hashes.rotate_left(1);
+            block_hashes[255] = U256::from_be_bytes(hash.0);
+        }
+        for (number, hash) in overrides.block_hash.unwrap_or_default() {
+            if let Some(depth) = block_number.checked_sub(number)
+                && (1..=256).contains(&depth)
+            {
+                block_hashes[256 - depth as usize] = U256::from_be_bytes(hash.0);
+            }
+        }
+
+        Ok(BlockContext {
+            block_number,
+            timestamp,
+            block_hashes: BlockHashes(block_hashes),
+            eip1559_basefee: overrides.base_fee.unwrap_or(if validation {
+                previous.eip1559_basefee
+            } else {
+                U256::ZERO
+            }),
+            gas_limit: overrides.gas_limit.unwrap_or(previous.gas_limit),
+            coinbase: overrides.coinbase.unwrap_or(previous.coinbase),
+            mix_hash: overrides
+                .random
+                .map_or(previous.mix_hash, |random| U256::from_be_bytes(random.0)),
+            ..*previous
+        })
+    }
+
+    /// Appends a simulated block to the chain.
+    pub fn push_block(&mut self, block_context: BlockContext, block_hash: B256) {
+        self.last_block = block_context;
+        self.last_block_hash = block_hash;
+    }
+}
+
+/// Converts the output of a simulated block into the `eth_simulateV1` response format.
+/// `call_traces` are used to add transfer logs; they are empty unless `traceTransfers` is set.
+pub fn build_simulated_block(
+    block_output: BlockOutput,
+    txs: &[ZkTransaction],
+    call_traces: &[CallFrame],
+) -> Result<SimulatedBlock, EthCallError> {
+    let BlockOutput {
+        header, tx_results, ..
+    } = block_output;
+    if tx_results.len() != txs.len() {
+        return Err(SimulationError::BlockLimitReached {
+            block_number: header.number,
+            executed: tx_results.len(),
+            total: txs.len(),
+        }
+        .into());
+    }
+
+    let block_hash = header.hash();
+    let mut log_index = 0;
+    let mut bloom = Bloom::default();
+    let mut calls = Vec::with_capacity(txs.len());
+    for (tx_index, (tx, result)) in txs.iter().zip(tx_results).enumerate() {
+        let output = result.map_err(EthCallError::InvalidTransaction)?;
+        let transfer_logs = call_traces
+            .get(tx_index)
+            .map(transfer_logs)
+            .unwrap_or_default();
+        let logs = transfer_logs
+            .into_iter()
+            .chain(output.logs)
+            .map(|inner| {
+                bloom.accrue_log(&inner);
+                let log = Log {
+                    inner,
+                    block_hash: Some(block_hash),
+                    block_number: Some(header.number),
+                    block_timestamp: Some(header.timestamp),
+                    transaction_hash: Some(*tx.hash()),
+                    transaction_index: Some(tx_index as u64),
+                    log_index: Some(log_index),
+                    removed: false,
+                };
+                log_index += 1;
+                log
+            })
+            .collect();
+
+        let (status, return_data, error) = match output.execution_result {
+            ExecutionResult::Success(
+                ExecutionOutput::Call(return_bytes) | ExecutionOutput::Create(return_bytes, _),
+            ) => (true, Bytes::from(return_bytes), None),
+            ExecutionResult::Revert(return_bytes) => {
+                let return_data = Bytes::from(return_bytes);
+                let error = SimulateError {
+                    code: EthRpcErrorCode::ExecutionError.code(),
+                    message: RevertError::new(return_data.clone()).to_string(),
+                    data: Some(return_data.clone()),
+                };
+                (false, return_data, Some(error))
+            }
+        };
+        calls.push(SimCallResult {
+            return_data,
+            logs,
+            gas_used: output.gas_used,
+            status,
+            error,
+        });
+    }
+
+    // Like for stored blocks, the block hash doesn't depend on the logs bloom
+    let header = {
+        let mut header = header.unseal();
+        header.logs_bloom = bloom;
+        header.seal(block_hash)
+    };
+    Ok(SimulatedBlock {
+        inner: Block::new(
+            ZkHeader::from_consensus(header, None, None),
+            BlockTransactions::Hashes(txs.iter().map(|tx| *tx.hash()).collect()),
+        ),
+        calls,
+    })
+}
+
+/// Returns ERC-7528 logs of native token transfers made by successful calls in `frame`, in the
+/// order of calls.
+fn transfer_logs(frame: &CallFrame) -> Vec<alloy::primitives::Log> {
+    let mut logs = Vec::new();
+    collect_transfer_logs(frame, &mut logs);
+    logs
+}
+
+fn collect_transfer_logs(frame: &CallFrame, logs: &mut Vec<alloy::primitives::Log>) {
+    // Transfers of reverted calls are reverted too
+    if frame.error.is_some() {
+        return;
+    }
+    // `DELEGATECALL` frames have the value of the parent call, but don't transfer it
+    let transfers_value = matches!(frame.typ.as_str(), "CALL" | "CREATE" | "CREATE2");
+    if transfers_value
+        && let Some(value) = frame.value
+        && !value.is_zero()
+    {
+        logs.push(alloy::primitives::Log {
+            address: TRANSFER_LOG_ADDRESS,
+            data: LogData::new_unchecked(
+                vec![
+                    TRANSFER_EVENT_TOPIC,
+                    frame.from.into_word(),
+                    frame.to.unwrap_or_default().into_word(),
+                ],
+                Bytes::from(value.to_be_bytes::<32>()),
+            ),
+        });
+    }
+    for call in &frame.calls {
+        collect_transfer_logs(call, logs);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn parent() -> BlockContext {
+        BlockContext {
+            block_number: 10,
+            timestamp: 1_000,
+            eip1559_basefee: U256::from(100),
+            gas_limit: 1_000_000,
+            ..Default::default()
+        }
+    }
+
+    fn call(typ: &str, value: u64, calls: Vec<CallFrame>) -> CallFrame {
+        CallFrame {
+            typ: typ.to_owned(),
+            from: Address::repeat_byte(1),
+            to: Some(Address::repeat_byte(2)),
+            value: Some(U256::from(value)),
+            calls,
+            ..CallFrame::default()
+        }
+    }
+
+    #[test]
+    fn block_contexts_are_chained() {
+        let parent_hash = B256::repeat_byte(10);
+        let mut chain = SimulatedChain::new(parent(), parent_hash, 5);
+
+        let first = chain.next_block_context(None, true).unwrap();
+        assert_eq!((first.block_number, first.timestamp), (11, 1_001));
+        assert_eq!(first.eip1559_basefee, U256::from(100));
+        assert_eq!(first.gas_limit, 1_000_000);
+        assert_eq!(
+            first.block_hashes.0[255],
+            U256::from_be_bytes(parent_hash.0)
+        );
+        chain.push_block(first, B256::repeat_byte(11));
+
+        // Overrides take precedence over inherited values
+        let overrides = BlockOverrides {
+            number: Some(U256::from(13)),
+            time: Some(2_000),
+            base_fee: Some(U256::from(5)),
+            block_hash: Some(BTreeMap::from([(9, B256::repeat_byte(9))])),
+            ..BlockOverrides::default()
+        };
+        let second = chain.next_block_context(Some(overrides), true).unwrap();
+        assert_eq!((second.block_number, second.timestamp), (13, 2_000));
+        assert_eq!(second.eip1559_basefee, U256::from(5));
+        let hashes = &second.block_hashes.0;
+        // Block 12 is skipped
+        assert_eq!(hashes[255], U256::ZERO);
+        assert_eq!(hashes[254], U256::from_be_bytes([11; 32]));
+        assert_eq!(hashes[253], U256::from_be_bytes(parent_hash.0));
+        assert_eq!(hashes[252], U256::from_be_bytes([9; 32]));
+        chain.push_block(second, B256::repeat_byte(13));
+
+        // Base fee is zero without validation
+        let third = chain.next_block_context(None, false).unwrap();
+        assert_eq!(third.eip1559_basefee, U256::ZERO);
+    }
+
+    #[test]
+    fn invalid_block_overrides_are_rejected() {
+        let chain = SimulatedChain::new(parent(), B256::ZERO, 5);
+        let err = chain
+            .next_block_context(
+                Some(BlockOverrides {
+                    number: Some(U256::from(10)),
+                    ..BlockOverrides::default()
+                }),
+                true,
+            )
+            .unwrap_err();
+        assert!(
+            matches!(err, SimulationError::BlockNumberNotIncreasing { .. }),
+            "{err}"
+        );
+        let err = chain
+            .next_block_context(
+                Some(BlockOverrides {
+                    time: Some(999),
+                    ..BlockOverrides::default()
+                }),
+                true,
+            )
+            .unwrap_err();
+        assert!(
+            matches!(err, SimulationError::TimestampDecreasing { .. }),
+            "{err}"
+        );
+
+        // Skipped blocks count towards the limit
+        let err = chain
+            .next_block_context(
+                Some(BlockOverrides {
+                    number: Some(U256::from(16)),
+                    ..BlockOverrides::default()
+                }),
+                true,
+            )
+            .unwrap_err();
+        assert!(
+            matches!(err, SimulationError::TooManyBlocks { max: 5 }),
+            "{err}"
+        );
+        assert_eq!(err.code(), CLIENT_LIMIT_EXCEEDED_CODE);
+    }
+
+    #[test]
+    fn transfers_of_successful_calls_are_logged() {
+        let mut reverted = call("CALL", 3, vec![call("CALL", 4, vec![])]);
+        reverted.error = Some("execution reverted".to_owned());
+        let frame = call(
+            "CALL",
+            1,
+            vec![
+                call("DELEGATECALL", 1, vec![call("CALL", 2, vec![])]),
+                call("STATICCALL", 0, vec![]),
+                reverted,
+                call("CALL", 0, vec![]),
+            ],
+        );
+        let values: Vec<_> = transfer_logs(&frame)
+            .into_iter()
+            .map(|log| {
+                assert_eq!(log.address, TRANSFER_LOG_ADDRESS);
+                assert_eq!(log.topics()[0], TRANSFER_EVENT_TOPIC);
+                U256::from_be_slice(&log.data.data)
+            })
+            .collect();
+        assert_eq!(values, [U256::from(1), U256::from(2)]);
+    }
+}
@@ -1,31 +1,197 @@
+use crate::preconfirmation::Preconfirmations;
+use alloy::consensus::Transaction;
 use alloy::consensus::transaction::SignerRecoverable;
 use alloy::eips::Decodable2718;
 use alloy::primitives::{B256, Bytes};
-use tokio::sync::watch;
-use zksync_os_mempool::{L2TransactionPool, PoolError};
+use jsonrpsee::Extensions;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tokio::sync::{oneshot, watch};
+use zksync_os_mempool::{L2TransactionPool, PoolError, SponsorshipVoucher, TxSchedule};
+use zksync_os_rpc_api::types::{SendRawTransactionResponse, SignedPreconfirmation};
 use zksync_os_types::{L2Envelope, L2Transaction, NotAcceptingReason, TransactionAcceptanceState};
 
+/// Max time to wait for a preconfirmation to be signed before responding without it.
+const PRECONFIRMATION_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Header listing the client IP and the proxies a request passed through. Each proxy appends the
+/// address of its peer, so only the entries appended by trusted proxies can be relied on.
+const FORWARDED_FOR_HEADER: &str = "x-forwarded-for";
+
+/// Source of an RPC request (the client IP), used to tag mempool submissions for spam scoring.
+/// Attached to HTTP request extensions by [`tag_request_origin`], which jsonrpsee passes on to
+/// methods.
+#[derive(Debug, Clone)]
+pub(crate) struct RequestOrigin(String);
+
+impl RequestOrigin {
+    pub(crate) fn of(extensions: &Extensions) -> Option<&str> {
+        extensions.get::<Self>().map(|origin| origin.0.as_str())
+    }
+}
+
+/// Attaches [`RequestOrigin`] to a request received from `peer`. Requests from `trusted_proxies`
+/// are attributed to the rightmost `X-Forwarded-For` entry not appended by a trusted proxy; entries
+/// to the left of it are client-controlled and are ignored. Other requests are attributed to
+/// `peer` itself.
+pub(crate) fn tag_request_origin<B>(
+    mut request: hyper::Request<B>,
+    peer: IpAddr,
+    trusted_proxies: &[IpAddr],
+) -> hyper::Request<B> {
+    let mut origin = peer.to_canonical();
+    if trusted_proxies.contains(&origin) {
+        let forwarded_for: Vec<_> = request
+            .headers()
+            .get_all(FORWARDED_FOR_HEADER)
+            .iter()
+            .flat_map(|value| value.to_str().unwrap_or_default().split(','))
+            .map(str::trim)
+            .collect();
+        for hop in forwarded_for.into_iter().rev() {
+            // Unparsable entries cannot be attributed; stop at the last trusted proxy
+            let Some(hop) = parse_forwarded_address(hop) else {
+                break;
+            };
+            origin = hop;
+            if !trusted_proxies.contains(&origin) {
+                break;
+            }
+        }
+    }
+    request
+        .extensions_mut()
+        .insert(RequestOrigin(origin.to_string()));
+    request
+}
+
+/// Parses an `X-Forwarded-For` entry, which is an IP address optionally followed by a port.
+fn parse_forwarded_address(entry: &str) -> Option<IpAddr> {
+    let address = entry
+        .parse::<IpAddr>()
+        .or_else(|_| entry.parse::<SocketAddr>().map(|address| address.ip()))
+        .ok()?;
+    Some(address.to_canonical())
+}
+
 /// Handles transactions received in API
+#[derive(Clone)]
 pub struct TxHandler<Mempool> {
     mempool: Mempool,
     acceptance_state: watch::Receiver<TransactionAcceptanceState>,
+    preconfirmations: Option<Preconfirmations>,
 }
 
 impl<Mempool: L2TransactionPool> TxHandler<Mempool> {
     pub fn new(
         mempool: Mempool,
         acceptance_state: watch::Receiver<TransactionAcceptanceState>,
+        preconfirmations: Option<Preconfirmations>,
     ) -> Self {
         Self {
             mempool,
             acceptance_state,
+            preconfirmations,
         }
     }
 
     pub async fn send_raw_transaction_impl(
         &self,
         tx_bytes: Bytes,
+        origin: Option<&str>,
+    ) -> Result<B256, EthSendRawTransactionError> {
+        let (hash, _) = self.add_transaction(tx_bytes, origin).await?;
+        Ok(hash)
+    }
+
+    pub async fn send_raw_transaction_with_preconfirmation_impl(
+        &self,
+        tx_bytes: Bytes,
+        origin: Option<&str>,
+    ) -> Result<SendRawTransactionResponse, EthSendRawTransactionError> {
+        let (transaction_hash, response) = self.add_transaction(tx_bytes, origin).await?;
+        let preconfirmation = match response {
+            Some(response) => tokio::time::timeout(PRECONFIRMATION_TIMEOUT, response)
+                .await
+                .ok()
+                .and_then(Result::ok),
+            None => None,
+        };
+        Ok(SendRawTransactionResponse {
+            transaction_hash,
+            preconfirmation,
+        })
+    }
+
+    /// Adds a transaction that can only be included starting from the block described by
+    /// `schedule`. Scheduled transactions are not preconfirmed.
+    pub async fn send_raw_transaction_scheduled_impl(
+        &self,
+        tx_bytes: Bytes,
+        schedule: TxSchedule,
+        origin: Option<&str>,
+    ) -> Result<B256, EthSendRawTransactionError> {
+        let l2_tx = self.decode_transaction(tx_bytes)?;
+        Ok(self
+            .mempool
+            .add_scheduled_l2_transaction(l2_tx, origin, schedule)
+            .await?)
+    }
+
+    /// Adds a transaction whose fee is paid by the sponsor described by `voucher`. Sponsored
+    /// transactions are not preconfirmed.
+    pub async fn send_raw_transaction_sponsored_impl(
+        &self,
+        tx_bytes: Bytes,
+        voucher: SponsorshipVoucher,
+        origin: Option<&str>,
     ) -> Result<B256, EthSendRawTransactionError> {
+        let l2_tx = self.decode_transaction(tx_bytes)?;
+        Ok(self
+            .mempool
+            .add_sponsored_l2_transaction(l2_tx, origin, voucher)
+            .await?)
+    }
+
+    pub fn get_preconfirmation(&self, hash: B256) -> Option<SignedPreconfirmation> {
+        self.preconfirmations.as_ref()?.get(hash)
+    }
+
+    /// Adds a transaction to the mempool. If preconfirmations are enabled and the transaction is
+    /// executable right away, also requests a preconfirmation for it. `origin` tags the submission
+    /// for spam scoring.
+    async fn add_transaction(
+        &self,
+        tx_bytes: Bytes,
+        origin: Option<&str>,
+    ) -> Result<(B256, Option<oneshot::Receiver<SignedPreconfirmation>>), EthSendRawTransactionError>
+    {
+        let l2_tx = self.decode_transaction(tx_bytes)?;
+        let hash = *l2_tx.hash();
+        let (sender, nonce) = (l2_tx.signer(), l2_tx.nonce());
+        self.mempool.add_l2_transaction(l2_tx, origin).await?;
+
+        let preconfirmation = self.preconfirmations.as_ref().and_then(|preconfirmations| {
+            // Queued transactions (e.g. ones behind a nonce gap) are not preconfirmed
+            let is_pending = self
+                .mempool
+                .get_pending_transactions_by_sender(sender)
+                .iter()
+                .any(|tx| *tx.hash() == hash);
+            if is_pending {
+                preconfirmations.request(hash, sender, nonce)
+            } else {
+                None
+            }
+        });
+        Ok((hash, preconfirmation))
+    }
+
+    /// Decodes a submitted transaction, provided that the node is accepting transactions.
+    fn decode_transaction(
+        &self,
+        tx_bytes: Bytes,
+    ) -> Result<L2Transaction, EthSendRawTransactionError> {
         if let TransactionAcceptanceState::NotAccepting(reason) = &*self.acceptance_state.borrow() {
             return Err(EthSendRawTransactionError::NotAcceptingTransactions(
                 *reason,
@@ -34,13 +200,9 @@ impl<Mempool: L2TransactionPool> TxHandler<Mempool> {
 
         let transaction = L2Envelope::decode_2718(&mut tx_bytes.as_ref())
             .map_err(|_| EthSendRawTransactionError::FailedToDecodeSignedTransaction)?;
-        let l2_tx: L2Transaction = transaction
+        transaction
             .try_into_recovered()
-            .map_err(|_| EthSendRawTransactionError::InvalidTransactionSignature)?;
-        let hash = *l2_tx.hash();
-        self.mempool.add_l2_transaction(l2_tx).await?;
-
-        Ok(hash)
+            .map_err(|_| EthSendRawTransactionError::InvalidTransactionSignature)
     }
 }
 
@@ -60,3 +222,51 @@ pub enum EthSendRawTransactionError {
     #[error(transparent)]
     PoolError(#[from] PoolError),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PEER: &str = "198.51.100.1";
+    const PROXIES: [&str; 2] = ["10.0.0.1", "10.0.0.2"];
+
+    fn origin(peer: &str, forwarded_for: &[&str]) -> String {
+        let mut request = hyper::Request::builder();
+        for &value in forwarded_for {
+            request = request.header(FORWARDED_FOR_HEADER, value);
+        }
+        let trusted_proxies = PROXIES.map(|proxy| proxy.parse().unwrap());
+        let request = tag_request_origin(
+            request.body(()).unwrap(),
+            peer.parse().unwrap(),
+            &trusted_proxies,
+        );
+        RequestOrigin::of(request.extensions()).unwrap().to_owned()
+    }
+
+    #[test]
+    fn untrusted_peers_are_the_origin() {
+        assert_eq!(origin(PEER, &[]), PEER);
+        // Forwarded addresses of untrusted peers are spoofable
+        assert_eq!(origin(PEER, &["203.0.113.7"]), PEER);
+        assert_eq!(origin("::ffff:198.51.100.1", &["203.0.113.7"]), PEER);
+    }
+
+    #[test]
+    fn origin_is_the_rightmost_untrusted_hop() {
+        assert_eq!(origin("10.0.0.1", &[]), "10.0.0.1");
+        assert_eq!(origin("10.0.0.1", &[""]), "10.0.0.1");
+        assert_eq!(origin("10.0.0.1", &["203.0.113.7"]), "203.0.113.7");
+        // The leftmost entry is client-controlled
+        assert_eq!(
+            origin("10.0.0.1", &["192.0.2.99, 203.0.113.7, 10.0.0.2"]),
+            "203.0.113.7"
+        );
+        assert_eq!(
+            origin("10.0.0.1", &["192.0.2.99", "203.0.113.7:4711", "10.0.0.2"]),
+            "203.0.113.7"
+        );
+        assert_eq!(origin("10.0.0.1", &["10.0.0.2"]), "10.0.0.2");
+        assert_eq!(origin("10.0.0.1", &["unknown, 10.0.0.2"]), "10.0.0.2");
+    }
+}
@@ -1,39 +1,136 @@
 use crate::ReadRpcStorage;
 use crate::result::ToRpcResult;
-use alloy::primitives::{Address, B256, BlockNumber, TxHash, keccak256};
+use crate::tx_handler::{RequestOrigin, TxHandler};
+use alloy::eips::BlockId;
+use alloy::primitives::{Address, B256, BlockNumber, Bytes, TxHash, U64, keccak256};
 use alloy::rpc::types::Index;
 use async_trait::async_trait;
+use jsonrpsee::Extensions;
 use jsonrpsee::core::RpcResult;
 use std::sync::Arc;
 use zksync_os_genesis::{GenesisInput, GenesisInputSource};
+use zksync_os_mempool::{
+    L2TransactionPool, PooledTxDiagnostics, SenderDiagnostics, SponsorshipVoucher, TxSchedule,
+};
 use zksync_os_mini_merkle_tree::MiniMerkleTree;
-use zksync_os_rpc_api::{types::L2ToL1LogProof, zks::ZksApiServer};
-use zksync_os_storage_api::RepositoryError;
-use zksync_os_types::L2_TO_L1_TREE_SIZE;
+use zksync_os_rpc_api::types::{
+    BlockDetails, L2ToL1LogProof, PooledTransactionState, SendRawTransactionResponse,
+    SenderPoolState, SenderSpamScore, SignedPreconfirmation, TransactionSchedule,
+    TransactionSponsorship,
+};
+use zksync_os_rpc_api::zks::ZksApiServer;
+use zksync_os_storage_api::{FinalityStatus, RepositoryError};
+use zksync_os_types::{BatchDetails, BatchStatus, L2_TO_L1_TREE_SIZE};
 
 const LOG_PROOF_SUPPORTED_METADATA_VERSION: u8 = 1;
 
-pub struct ZksNamespace<RpcStorage> {
+/// Max number of senders that can be inspected in a single `zks_getSenderPoolState` or
+/// `zks_getTopSpamScores` call.
+const MAX_SENDERS_PER_POOL_STATE_REQUEST: usize = 100;
+
+pub struct ZksNamespace<RpcStorage, Mempool> {
     bridgehub_address: Address,
     storage: RpcStorage,
+    mempool: Mempool,
     genesis_input_source: Arc<dyn GenesisInputSource>,
+    tx_handler: TxHandler<Mempool>,
 }
 
-impl<RpcStorage> ZksNamespace<RpcStorage> {
+impl<RpcStorage, Mempool> ZksNamespace<RpcStorage, Mempool> {
     pub fn new(
         bridgehub_address: Address,
         storage: RpcStorage,
+        mempool: Mempool,
         genesis_input_source: Arc<dyn GenesisInputSource>,
+        tx_handler: TxHandler<Mempool>,
     ) -> Self {
         Self {
             bridgehub_address,
             storage,
+            mempool,
             genesis_input_source,
+            tx_handler,
         }
     }
 }
 
-impl<RpcStorage: ReadRpcStorage> ZksNamespace<RpcStorage> {
+impl<RpcStorage: ReadRpcStorage, Mempool: L2TransactionPool> ZksNamespace<RpcStorage, Mempool> {
+    fn get_sender_pool_state_impl(&self, senders: Vec<Address>) -> ZksResult<Vec<SenderPoolState>> {
+        if senders.len() > MAX_SENDERS_PER_POOL_STATE_REQUEST {
+            return Err(ZksError::TooManySenders(
+                senders.len(),
+                MAX_SENDERS_PER_POOL_STATE_REQUEST,
+            ));
+        }
+        Ok(self
+            .mempool
+            .senders_diagnostics(&senders)
+            .map_err(ZksError::Mempool)?
+            .into_iter()
+            .map(sender_pool_state)
+            .collect())
+    }
+
+    fn get_top_spam_scores_impl(&self, limit: usize) -> ZksResult<Vec<SenderSpamScore>> {
+        if limit > MAX_SENDERS_PER_POOL_STATE_REQUEST {
+            return Err(ZksError::TooManySenders(
+                limit,
+                MAX_SENDERS_PER_POOL_STATE_REQUEST,
+            ));
+        }
+        Ok(self
+            .mempool
+            .spam_scores()
+            .top_senders(limit)
+            .into_iter()
+            .map(|(sender, score)| SenderSpamScore { sender, score })
+            .collect())
+    }
+
+    fn get_block_timestamp_millis_impl(&self, block_id: BlockId) -> ZksResult<Option<U64>> {
+        let Some(block_number) = self.storage.resolve_block_number(block_id)? else {
+            return Ok(None);
+        };
+        Ok(self
+            .storage
+            .replay_storage()
+            .get_block_timestamp_millis(block_number)
+            .map(U64::from))
+    }
+
+    async fn get_block_details_impl(&self, block_number: u64) -> ZksResult<Option<BlockDetails>> {
+        let Some(block) = self
+            .storage
+            .repository()
+            .get_block_by_number(block_number)?
+        else {
+            return Ok(None);
+        };
+        let finality = self.storage.finality();
+        let batch_number = self
+            .storage
+            .batch()
+            .get_batch_by_block_number(block_number, finality)
+            .await?;
+        let batch = match batch_number {
+            Some(batch_number) => {
+                self.storage
+                    .batch()
+                    .get_batch_details(batch_number, finality)
+                    .await?
+            }
+            None => None,
+        };
+        Ok(Some(block_details(
+            block_number,
+            block.hash(),
+            block.header.timestamp,
+            batch_number,
+            batch,
+            &finality.get_finality_status(),
+        )))
+    }
+
     async fn get_l2_to_l1_log_proof_impl(
         &self,
         tx_hash: TxHash,
@@ -82,6 +179,9 @@ impl<RpcStorage: ReadRpcStorage> ZksNamespace<RpcStorage> {
             let Some(block) = self.storage.repository().get_block_by_number(block)? else {
                 return Err(ZksError::BlockNotAvailable(block));
             };
+            self.storage
+                .repository()
+                .ensure_transactions_available(&block)?;
             for block_tx_hash in block.unseal().body.transactions {
                 let Some(receipt) = self
                     .storage
@@ -145,8 +245,74 @@ impl<RpcStorage: ReadRpcStorage> ZksNamespace<RpcStorage> {
     }
 }
 
+/// Combines a block with the details of the batch it belongs to. Blocks of batches without details
+/// (e.g. the genesis batch) get their status from the L1 finality status.
+fn block_details(
+    number: BlockNumber,
+    hash: B256,
+    timestamp: u64,
+    batch_number: Option<u64>,
+    batch: Option<BatchDetails>,
+    finality: &FinalityStatus,
+) -> BlockDetails {
+    // Batch lookup only covers committed batches and may point to the last committed batch for
+    // later blocks
+    let (batch_number, batch) = match batch {
+        Some(batch) if !(batch.first_block_number..=batch.last_block_number).contains(&number) => {
+            (None, None)
+        }
+        batch => (batch_number, batch),
+    };
+    let status = match &batch {
+        Some(batch) => batch.status,
+        None if number <= finality.last_executed_block => BatchStatus::Executed,
+        None if number <= finality.last_committed_block => BatchStatus::Committed,
+        None => BatchStatus::Sequenced,
+    };
+    let [commit_tx_hash, prove_tx_hash, execute_tx_hash] = batch
+        .as_ref()
+        .map(BatchDetails::l1_tx_hashes)
+        .unwrap_or_default();
+    BlockDetails {
+        number,
+        hash,
+        timestamp,
+        batch_number,
+        status,
+        commit_tx_hash,
+        prove_tx_hash,
+        execute_tx_hash,
+    }
+}
+
+fn sender_pool_state(diagnostics: SenderDiagnostics) -> SenderPoolState {
+    let convert = |txs: Vec<PooledTxDiagnostics>| {
+        txs.into_iter()
+            .map(|tx| PooledTransactionState {
+                hash: tx.hash,
+                nonce: tx.nonce,
+                max_fee_per_gas: tx.max_fee_per_gas,
+                fee_adequate: tx.fee_adequate,
+                schedule: tx.schedule.map(|schedule| TransactionSchedule {
+                    not_before_block: schedule.not_before_block,
+                    not_before_timestamp: schedule.not_before_timestamp,
+                }),
+            })
+            .collect()
+    };
+    SenderPoolState {
+        sender: diagnostics.sender,
+        on_chain_nonce: diagnostics.on_chain_nonce,
+        pending: convert(diagnostics.pending),
+        queued: convert(diagnostics.queued),
+        first_missing_nonce: diagnostics.first_missing_nonce,
+    }
+}
+
 #[async_trait]
-impl<RpcStorage: ReadRpcStorage> ZksApiServer for ZksNamespace<RpcStorage> {
+impl<RpcStorage: ReadRpcStorage, Mempool: L2TransactionPool> ZksApiServer
+    for ZksNamespace<RpcStorage, Mempool>
+{
     async fn get_bridgehub_contract(&self) -> RpcResult<Address> {
         Ok(self.bridgehub_address)
     }
@@ -168,6 +334,89 @@ impl<RpcStorage: ReadRpcStorage> ZksApiServer for ZksNamespace<RpcStorage> {
             .map_err(ZksError::GenesisSource)
             .to_rpc_result()
     }
+
+    async fn get_sender_pool_state(
+        &self,
+        senders: Vec<Address>,
+    ) -> RpcResult<Vec<SenderPoolState>> {
+        self.get_sender_pool_state_impl(senders).to_rpc_result()
+    }
+
+    async fn get_top_spam_scores(&self, limit: usize) -> RpcResult<Vec<SenderSpamScore>> {
+        self.get_top_spam_scores_impl(limit).to_rpc_result()
+    }
+
+    async fn send_raw_transaction_with_preconfirmation(
+        &self,
+        ext: &Extensions,
+        bytes: Bytes,
+    ) -> RpcResult<SendRawTransactionResponse> {
+        self.tx_handler
+            .send_raw_transaction_with_preconfirmation_impl(bytes, RequestOrigin::of(ext))
+            .await
+            .to_rpc_result()
+    }
+
+    async fn send_raw_transaction_scheduled(
+        &self,
+        ext: &Extensions,
+        bytes: Bytes,
+        schedule: TransactionSchedule,
+    ) -> RpcResult<TxHash> {
+        let schedule = TxSchedule {
+            not_before_block: schedule.not_before_block,
+            not_before_timestamp: schedule.not_before_timestamp,
+        };
+        self.tx_handler
+            .send_raw_transaction_scheduled_impl(bytes, schedule, RequestOrigin::of(ext))
+            .await
+            .to_rpc_result()
+    }
+
+    async fn send_raw_transaction_sponsored(
+        &self,
+        ext: &Extensions,
+        bytes: Bytes,
+        sponsorship: TransactionSponsorship,
+    ) -> RpcResult<TxHash> {
+        let voucher = SponsorshipVoucher {
+            sponsor: sponsorship.sponsor,
+            max_fee: sponsorship.max_fee.to(),
+            expiry: sponsorship.expiry,
+            signature: sponsorship.signature,
+        };
+        self.tx_handler
+            .send_raw_transaction_sponsored_impl(bytes, voucher, RequestOrigin::of(ext))
+            .await
+            .to_rpc_result()
+    }
+
+    async fn get_block_timestamp_millis(&self, block_id: BlockId) -> RpcResult<Option<U64>> {
+        self.get_block_timestamp_millis_impl(block_id)
+            .to_rpc_result()
+    }
+
+    async fn get_preconfirmation(
+        &self,
+        tx_hash: TxHash,
+    ) -> RpcResult<Option<SignedPreconfirmation>> {
+        Ok(self.tx_handler.get_preconfirmation(tx_hash))
+    }
+
+    async fn get_block_details(&self, block_number: u64) -> RpcResult<Option<BlockDetails>> {
+        self.get_block_details_impl(block_number)
+            .await
+            .to_rpc_result()
+    }
+
+    async fn get_batch_details(&self, batch_number: u64) -> RpcResult<Option<BatchDetails>> {
+        self.storage
+            .batch()
+            .get_batch_details(batch_number, self.storage.finality())
+            .await
+            .map_err(ZksError::Batch)
+            .to_rpc_result()
+    }
 }
 
 /// `zks` namespace result type.
@@ -191,6 +440,8 @@ pub enum ZksError {
         "provided L2->L1 log index ({0}) does not exist; there are only {1} L2->L1 logs in the transaction"
     )]
     IndexOutOfBounds(usize, usize),
+    #[error("too many senders requested ({0}); at most {1} are allowed")]
+    TooManySenders(usize, usize),
 
     #[error(transparent)]
     Batch(#[from] anyhow::Error),
@@ -198,4 +449,101 @@ pub enum ZksError {
     Repository(#[from] RepositoryError),
     #[error(transparent)]
     GenesisSource(anyhow::Error),
+    #[error(transparent)]
+    Mempool(anyhow::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use zksync_os_types::BatchL1Tx;
+
+    const FINALITY: FinalityStatus = FinalityStatus {
+        last_committed_block: 20,
+        last_committed_batch: 2,
+        last_executed_block: 10,
+        last_executed_batch: 1,
+    };
+
+    fn batch(status: BatchStatus, l1_txs: usize) -> BatchDetails {
+        let l1_tx = |tx_hash: u8| BatchL1Tx {
+            tx_hash: TxHash::repeat_byte(tx_hash),
+            l1_block_number: Some(100 + tx_hash as u64),
+            timestamp: 1_700_000_000,
+            finalized: false,
+            gas_used: 100_000,
+            execution_fee_wei: 2_000_000,
+            blob_fee_wei: 0,
+        };
+        BatchDetails {
+            number: 2,
+            status,
+            first_block_number: 11,
+            last_block_number: 20,
+            tx_count: 5,
+            seal_reason: Some("timeout".to_owned()),
+            commitment: B256::repeat_byte(0xcc),
+            commit_tx: (l1_txs > 0).then(|| l1_tx(1)),
+            prove_tx: (l1_txs > 1).then(|| l1_tx(2)),
+            execute_tx: (l1_txs > 2).then(|| l1_tx(3)),
+        }
+    }
+
+    fn block_details_json(
+        number: BlockNumber,
+        batch_number: Option<u64>,
+        batch: Option<BatchDetails>,
+    ) -> serde_json::Value {
+        let details = block_details(
+            number,
+            B256::repeat_byte(0xbb),
+            1_700_000_000,
+            batch_number,
+            batch,
+            &FINALITY,
+        );
+        serde_json::to_value(details).unwrap()
+    }
+
+    #[test]
+    fn block_details_of_committed_batch() {
+        assert_eq!(
+            block_details_json(15, Some(2), Some(batch(BatchStatus::Committed, 1))),
+            json!({
+                "number": 15,
+                "hash": B256::repeat_byte(0xbb),
+                "timestamp": 1_700_000_000,
+                "batchNumber": 2,
+                "status": "committed",
+                "commitTxHash": TxHash::repeat_byte(1),
+                "proveTxHash": null,
+                "executeTxHash": null,
+            })
+        );
+
+        let json = block_details_json(15, Some(2), Some(batch(BatchStatus::Finalized, 3)));
+        assert_eq!(json["status"], "finalized");
+        assert_eq!(json["proveTxHash"], json!(TxHash::repeat_byte(2)));
+        assert_eq!(json["executeTxHash"], json!(TxHash::repeat_byte(3)));
+    }
+
+    #[test]
+    fn block_details_without_batch_details() {
+        // Not committed yet; batch lookup returned the last committed batch
+        let json = block_details_json(25, Some(2), Some(batch(BatchStatus::Committed, 1)));
+        assert_eq!(json["batchNumber"], json!(null));
+        assert_eq!(json["status"], "sequenced");
+        assert_eq!(json["commitTxHash"], json!(null));
+
+        // Genesis batch is not stored
+        let json = block_details_json(0, Some(0), None);
+        assert_eq!(json["batchNumber"], 0);
+        assert_eq!(json["status"], "executed");
+        assert_eq!(json["executeTxHash"], json!(null));
+
+        // Committed by another node
+        let json = block_details_json(15, Some(2), None);
+        assert_eq!(json["status"], "committed");
+    }
 }
@@ -1,14 +1,16 @@
 use alloy::consensus::Sealed;
 use alloy::network::primitives::BlockTransactions;
-use alloy::primitives::{B256, TxHash, U256};
+use alloy::primitives::{Address, B256, Signature, SignatureError, TxHash, U128, U256, keccak256};
 use alloy::rpc::types::Log;
 use jsonrpsee::core::Serialize;
 use serde::Deserialize;
-use zksync_os_types::{BlockExt, ZkEnvelope, ZkReceiptEnvelope};
+use zksync_os_types::{BatchStatus, BlockExt, ZkEnvelope, ZkReceiptEnvelope};
 
 pub type ZkTransactionReceipt = alloy::rpc::types::TransactionReceipt<ZkReceiptEnvelope<Log>>;
 pub type ZkHeader = alloy::rpc::types::Header;
 
+/// Transaction in RPC responses: the canonical JSON representation of the transaction (see
+/// `ZkTransaction::to_rpc_json`) with block-related fields on top.
 pub type ZkApiTransaction = alloy::rpc::types::Transaction<ZkEnvelope>;
 
 pub type ZkApiBlock = alloy::rpc::types::Block<ZkApiTransaction>;
@@ -41,3 +43,147 @@ pub struct L2ToL1LogProof {
     /// The root of the tree.
     pub root: B256,
 }
+
+/// Block with the batch it belongs to, as returned by `zks_getBlockDetails`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockDetails {
+    pub number: u64,
+    pub hash: B256,
+    pub timestamp: u64,
+    /// Batch containing the block. Missing until the batch is committed to L1.
+    pub batch_number: Option<u64>,
+    pub status: BatchStatus,
+    /// L1 transactions of the batch lifecycle stages, see `zks_getBatchDetails`.
+    pub commit_tx_hash: Option<TxHash>,
+    pub prove_tx_hash: Option<TxHash>,
+    pub execute_tx_hash: Option<TxHash>,
+}
+
+/// State of a sender's transactions in the mempool, as returned by `zks_getSenderPoolState`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SenderPoolState {
+    /// Sender address.
+    pub sender: Address,
+    /// Account nonce as of the latest block.
+    pub on_chain_nonce: u64,
+    /// Transactions that are ready to be included.
+    pub pending: Vec<PooledTransactionState>,
+    /// Transactions that are blocked by a nonce gap or an insufficient fee.
+    pub queued: Vec<PooledTransactionState>,
+    /// Nonce that has to be submitted to unblock queued transactions, if any.
+    pub first_missing_nonce: Option<u64>,
+}
+
+/// Mempool spam score of a sender, as returned by `zks_getTopSpamScores`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SenderSpamScore {
+    pub sender: Address,
+    /// Current (decayed) score.
+    pub score: f64,
+}
+
+/// A single transaction in [`SenderPoolState`].
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PooledTransactionState {
+    pub hash: TxHash,
+    pub nonce: u64,
+    pub max_fee_per_gas: u128,
+    /// Whether the transaction's max fee covers the current base fee.
+    pub fee_adequate: bool,
+    /// Earliest block the transaction can be included in, if it was submitted with
+    /// `zks_sendRawTransactionScheduled`.
+    pub schedule: Option<TransactionSchedule>,
+}
+
+/// Earliest block a transaction submitted with `zks_sendRawTransactionScheduled` can be included
+/// in. Unset conditions are always met.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionSchedule {
+    /// The transaction can be included in this block or later.
+    pub not_before_block: Option<u64>,
+    /// The transaction can be included in blocks with this timestamp (in seconds) or later.
+    pub not_before_timestamp: Option<u64>,
+}
+
+/// Voucher of a sponsor paying the fee of a transaction submitted with
+/// `zks_sendRawTransactionSponsored`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionSponsorship {
+    /// Sponsor registered on the node.
+    pub sponsor: Address,
+    /// Max fee (in wei) covered by the sponsor; must be at least `gasLimit * maxFeePerGas`.
+    pub max_fee: U128,
+    /// Unix timestamp (in seconds) after which the transaction can no longer be included.
+    pub expiry: u64,
+    /// Sponsor's signature over keccak256 of `zksync-os:sponsorship:v1` followed by the
+    /// transaction hash, `sponsor`, `maxFee` (16 bytes) and `expiry` (8 bytes), integers being
+    /// big-endian.
+    pub signature: Signature,
+}
+
+/// Sequencer's acknowledgment that a transaction was accepted into the pending subpool and is to be
+/// included in a block before `deadline` (unless it gets invalidated, e.g. by a sender balance change).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Preconfirmation {
+    pub tx_hash: TxHash,
+    pub sender: Address,
+    pub nonce: u64,
+    /// Unix timestamp (in seconds) by which the transaction is to be included.
+    pub deadline: u64,
+    /// Address of the sequencer key that signed the preconfirmation.
+    pub sequencer: Address,
+    pub chain_id: u64,
+}
+
+impl Preconfirmation {
+    /// Domain separator preventing preconfirmation signatures from being valid for other payloads.
+    const DOMAIN: &'static [u8] = b"zksync-os:preconfirmation:v1";
+
+    /// Hash signed by the sequencer: keccak256 of the domain separator followed by tightly packed
+    /// fields (integers are big-endian `u64`s).
+    pub fn signing_hash(&self) -> B256 {
+        let mut payload = Self::DOMAIN.to_vec();
+        payload.extend_from_slice(self.tx_hash.as_slice());
+        payload.extend_from_slice(self.sender.as_slice());
+        payload.extend_from_slice(&self.nonce.to_be_bytes());
+        payload.extend_from_slice(&self.deadline.to_be_bytes());
+        payload.extend_from_slice(self.sequencer.as_slice());
+        payload.extend_from_slice(&self.chain_id.to_be_bytes());
+        keccak256(payload)
+    }
+}
+
+/// [`Preconfirmation`] together with the sequencer's signature over its
+/// [signing hash](Preconfirmation::signing_hash).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedPreconfirmation {
+    #[serde(flatten)]
+    pub preconfirmation: Preconfirmation,
+    pub signature: Signature,
+}
+
+impl SignedPreconfirmation {
+    /// Recovers the signer address; it must be checked against `preconfirmation.sequencer`.
+    pub fn recover_signer(&self) -> Result<Address, SignatureError> {
+        self.signature
+            .recover_address_from_prehash(&self.preconfirmation.signing_hash())
+    }
+}
+
+/// Response of `zks_sendRawTransactionWithPreconfirmation`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SendRawTransactionResponse {
+    pub transaction_hash: TxHash,
+    /// `None` if the transaction is queued (not executable yet), if preconfirmations are disabled,
+    /// or if the sequencer is overloaded with signing requests.
+    pub preconfirmation: Option<SignedPreconfirmation>,
+}
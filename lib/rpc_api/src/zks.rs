@@ -1,9 +1,14 @@
-use crate::types::L2ToL1LogProof;
-use alloy::primitives::{Address, TxHash};
+use crate::types::{
+    BlockDetails, L2ToL1LogProof, SendRawTransactionResponse, SenderPoolState, SenderSpamScore,
+    SignedPreconfirmation, TransactionSchedule, TransactionSponsorship,
+};
+use alloy::eips::BlockId;
+use alloy::primitives::{Address, Bytes, TxHash, U64};
 use alloy::rpc::types::Index;
 use jsonrpsee::core::RpcResult;
 use jsonrpsee::proc_macros::rpc;
 use zksync_os_genesis::GenesisInput;
+use zksync_os_types::BatchDetails;
 
 #[cfg_attr(not(feature = "server"), rpc(client, namespace = "zks"))]
 #[cfg_attr(feature = "server", rpc(server, client, namespace = "zks"))]
@@ -20,4 +25,62 @@ pub trait ZksApi {
 
     #[method(name = "getGenesis")]
     async fn get_genesis(&self) -> RpcResult<GenesisInput>;
+
+    #[method(name = "getSenderPoolState")]
+    async fn get_sender_pool_state(&self, senders: Vec<Address>)
+    -> RpcResult<Vec<SenderPoolState>>;
+
+    /// Returns up to `limit` senders with the highest mempool spam scores, highest first.
+    /// Transactions of high-scoring senders are deprioritized or rejected.
+    #[method(name = "getTopSpamScores")]
+    async fn get_top_spam_scores(&self, limit: usize) -> RpcResult<Vec<SenderSpamScore>>;
+
+    /// Same as `eth_sendRawTransaction`, but additionally returns a preconfirmation signed by
+    /// the sequencer if the transaction was accepted into the pending subpool.
+    #[method(name = "sendRawTransactionWithPreconfirmation", with_extensions)]
+    async fn send_raw_transaction_with_preconfirmation(
+        &self,
+        bytes: Bytes,
+    ) -> RpcResult<SendRawTransactionResponse>;
+
+    /// Same as `eth_sendRawTransaction`, but the transaction is only included starting from the
+    /// block described by `schedule`; until then, it's reported as queued by
+    /// `zks_getSenderPoolState`. Schedules too far in the future are rejected.
+    #[method(name = "sendRawTransactionScheduled", with_extensions)]
+    async fn send_raw_transaction_scheduled(
+        &self,
+        bytes: Bytes,
+        schedule: TransactionSchedule,
+    ) -> RpcResult<TxHash>;
+
+    /// Same as `eth_sendRawTransaction`, but the transaction's fee is paid by the sponsor that
+    /// signed `sponsorship` rather than by the sender. The sponsor must be registered on the node
+    /// and have enough balance and remaining budget to cover the max fee of the transaction.
+    #[method(name = "sendRawTransactionSponsored", with_extensions)]
+    async fn send_raw_transaction_sponsored(
+        &self,
+        bytes: Bytes,
+        sponsorship: TransactionSponsorship,
+    ) -> RpcResult<TxHash>;
+
+    /// Returns the block timestamp in milliseconds. `timestamp` in the block header (and
+    /// `block.timestamp` in contracts) is this value in seconds, so it's shared by all blocks
+    /// produced within the same second.
+    #[method(name = "getBlockTimestampMillis")]
+    async fn get_block_timestamp_millis(&self, block_id: BlockId) -> RpcResult<Option<U64>>;
+
+    #[method(name = "getPreconfirmation")]
+    async fn get_preconfirmation(
+        &self,
+        tx_hash: TxHash,
+    ) -> RpcResult<Option<SignedPreconfirmation>>;
+
+    /// Returns the batch a block belongs to and the block's status on L1.
+    #[method(name = "getBlockDetails")]
+    async fn get_block_details(&self, block_number: u64) -> RpcResult<Option<BlockDetails>>;
+
+    /// Returns the block range, commitment and L1 transactions of a batch. Stages not reached yet
+    /// have their transaction set to `null`.
+    #[method(name = "getBatchDetails")]
+    async fn get_batch_details(&self, batch_number: u64) -> RpcResult<Option<BatchDetails>>;
 }
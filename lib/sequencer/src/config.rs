@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::time::Duration;
 
@@ -6,12 +7,28 @@ pub struct SequencerConfig {
     /// Defines the block time for the sequencer.
     pub block_time: Duration,
 
+    /// Whether consecutive blocks must have strictly increasing timestamps (in seconds).
+    pub strict_block_timestamps: bool,
+
     /// Max number of transactions in a block.
     pub max_transactions_in_block: usize,
 
     /// Path to the directory where block dumps for unexpected failures will be saved.
     pub block_dump_path: PathBuf,
 
+    /// How much of the failed block is written to block dumps.
+    pub dump_detail_level: DumpDetailLevel,
+
+    /// Hard size limit of a single block dump. Larger dumps are truncated section by section.
+    pub max_dump_bytes: u64,
+
+    /// Directory for decision traces of produced blocks, see [`crate::execution::block_trace`].
+    /// Traces are not recorded if not set.
+    pub block_trace_path: Option<PathBuf>,
+
+    /// Number of most recent block traces to keep.
+    pub max_block_traces: usize,
+
     /// Where to serve block replays
     pub block_replay_server_address: String,
 
@@ -28,6 +45,28 @@ pub struct SequencerConfig {
     /// Maximum number of blocks to produce
     /// None for indefinite block production (normal operations)
     pub max_blocks_to_produce: Option<u64>,
+
+    /// Number of best mempool transactions to pre-execute while waiting for the next block.
+    /// `0` disables the speculative warm-up.
+    pub warm_up_max_txs: usize,
+
+    /// Max number of blocking tasks used by the speculative warm-up.
+    pub warm_up_concurrency: usize,
+}
+
+/// Amount of transaction data written to block dumps.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DumpDetailLevel {
+    /// Complete transactions - the block can be re-executed from the dump.
+    #[default]
+    Full,
+    /// Transactions are reduced to their hash, type, signer, recipient, nonce, gas limit and value;
+    /// calldata is replaced with its hash and length. The error message is kept as is and may
+    /// still quote transaction data.
+    Redacted,
+    /// Only block context, transaction hashes and the error.
+    Minimal,
 }
 
 impl SequencerConfig {
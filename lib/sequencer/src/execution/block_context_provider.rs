@@ -1,22 +1,30 @@
+use crate::execution::block_trace::BlockTraceRecorder;
+use crate::execution::bundles::{BundleOutcome, BundleStore};
 use crate::execution::metrics::EXECUTION_METRICS;
 use crate::model::blocks::{
     BlockCommand, BlockCommandType, InvalidTxPolicy, PreparedBlockCommand, SealPolicy,
 };
+use alloy::consensus::transaction::Recovered;
 use alloy::consensus::{Block, BlockBody, Header};
-use alloy::primitives::{Address, BlockHash, TxHash, U128, U256};
+use alloy::primitives::{Address, B256, BlockHash, TxHash, U128, U256};
 use reth_execution_types::ChangedAccount;
 use reth_primitives::SealedBlock;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::{mpsc, watch};
+use zksync_os_gas_adjuster::{
+    BaseFeeInputs, BaseTokenConversionRatio, BaseTokenRate, ETH_NATIVE_PRICE, next_block_base_fee,
+};
 use zksync_os_genesis::Genesis;
 use zksync_os_interface::types::{BlockContext, BlockHashes, BlockOutput};
 use zksync_os_mempool::{
     CanonicalStateUpdate, L2TransactionPool, PoolUpdateKind, ReplayTxStream, best_transactions,
 };
-use zksync_os_multivm::LATEST_EXECUTION_VERSION;
+use zksync_os_multivm::{ExecutionVersion, LATEST_EXECUTION_VERSION};
 use zksync_os_storage_api::ReplayRecord;
-use zksync_os_types::{L1PriorityEnvelope, L2Envelope, ZkEnvelope};
+use zksync_os_types::{
+    L1PriorityEnvelope, L1TxSerialId, L2Envelope, PriorityDeadlines, ZkEnvelope, ZkTransaction,
+};
 
 /// Component that turns `BlockCommand`s into `PreparedBlockCommand`s.
 /// Last step in the stream where `Produce` and `Replay` are differentiated.
@@ -31,9 +39,16 @@ use zksync_os_types::{L1PriorityEnvelope, L2Envelope, ZkEnvelope};
 pub struct BlockContextProvider<Mempool> {
     next_l1_priority_id: u64,
     l1_transactions: mpsc::Receiver<L1PriorityEnvelope>,
+    /// Expiration deadlines of priority transactions not yet included in a block.
+    priority_deadlines: PriorityDeadlines,
+    /// Priority transactions expiring within this time are force-included in the next produced block.
+    forced_inclusion_threshold: Duration,
     l2_mempool: Mempool,
     block_hashes_for_next_block: BlockHashes,
     previous_block_timestamp: u64,
+    previous_block_timestamp_millis: u64,
+    /// Whether produced blocks must have strictly increasing timestamps (in seconds).
+    strict_block_timestamps: bool,
     chain_id: u64,
     gas_limit: u64,
     pubdata_limit: u64,
@@ -44,7 +59,39 @@ pub struct BlockContextProvider<Mempool> {
     pubdata_price_override: Option<U256>,
     native_price_override: Option<U256>,
     pubdata_price_provider: watch::Receiver<Option<u128>>,
+    /// Set for chains with a custom base token; `None` for ETH-based chains.
+    base_token_pricing: Option<BaseTokenPricing>,
+    /// Prices of the previous block; used if the base token rate is stale.
+    previous_block_prices: Option<BlockPrices>,
     pending_block_context_sender: watch::Sender<Option<BlockContext>>,
+    /// Pending transaction bundles; `None` if bundles are disabled.
+    bundles: Option<BundleStore>,
+}
+
+/// Conversion of ETH-denominated prices (L1 costs) to the custom base token.
+#[derive(Debug)]
+pub struct BaseTokenPricing {
+    pub rate_provider: watch::Receiver<Option<BaseTokenRate>>,
+    /// If the last fetched rate is older than this, prices are not adjusted until a fresh rate is available.
+    pub max_rate_staleness: Duration,
+}
+
+/// Fee-related fields of [`BlockContext`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct BlockPrices {
+    eip1559_basefee: U256,
+    native_price: U256,
+    pubdata_price: U256,
+}
+
+impl From<&BlockContext> for BlockPrices {
+    fn from(context: &BlockContext) -> Self {
+        Self {
+            eip1559_basefee: context.eip1559_basefee,
+            native_price: context.native_price,
+            pubdata_price: context.pubdata_price,
+        }
+    }
 }
 
 impl<Mempool: L2TransactionPool> BlockContextProvider<Mempool> {
@@ -52,9 +99,13 @@ impl<Mempool: L2TransactionPool> BlockContextProvider<Mempool> {
     pub fn new(
         next_l1_priority_id: u64,
         l1_transactions: mpsc::Receiver<L1PriorityEnvelope>,
+        priority_deadlines: PriorityDeadlines,
+        forced_inclusion_threshold: Duration,
         l2_mempool: Mempool,
         block_hashes_for_next_block: BlockHashes,
         previous_block_timestamp: u64,
+        previous_block_timestamp_millis: u64,
+        strict_block_timestamps: bool,
         chain_id: u64,
         gas_limit: u64,
         pubdata_limit: u64,
@@ -65,14 +116,20 @@ impl<Mempool: L2TransactionPool> BlockContextProvider<Mempool> {
         pubdata_price_override: Option<U128>,
         native_price_override: Option<U128>,
         pubdata_price_provider: watch::Receiver<Option<u128>>,
+        base_token_pricing: Option<BaseTokenPricing>,
         pending_block_context_sender: watch::Sender<Option<BlockContext>>,
+        bundles: Option<BundleStore>,
     ) -> Self {
         Self {
             next_l1_priority_id,
             l1_transactions,
+            priority_deadlines,
+            forced_inclusion_threshold,
             l2_mempool,
             block_hashes_for_next_block,
             previous_block_timestamp,
+            previous_block_timestamp_millis,
+            strict_block_timestamps,
             chain_id,
             gas_limit,
             pubdata_limit,
@@ -83,7 +140,10 @@ impl<Mempool: L2TransactionPool> BlockContextProvider<Mempool> {
             pubdata_price_override: pubdata_price_override.map(U256::from),
             native_price_override: native_price_override.map(U256::from),
             pubdata_price_provider,
+            base_token_pricing,
+            previous_block_prices: None,
             pending_block_context_sender,
+            bundles,
         }
     }
 
@@ -94,7 +154,7 @@ impl<Mempool: L2TransactionPool> BlockContextProvider<Mempool> {
         let prepared_command = match block_command {
             BlockCommand::Produce(produce_command) => {
                 let upgrade_tx = if produce_command.block_number == 1 {
-                    Some(self.genesis.genesis_upgrade_tx().await.tx)
+                    Some(self.genesis.genesis_upgrade_tx().await?.tx)
                 } else {
                     None
                 };
@@ -102,8 +162,12 @@ impl<Mempool: L2TransactionPool> BlockContextProvider<Mempool> {
                 // Create stream:
                 // - For block #1 genesis upgrade tx goes first.
                 // - L1 transactions first, then L2 transactions.
-                let mut best_txs =
-                    best_transactions(&self.l2_mempool, &mut self.l1_transactions, upgrade_tx);
+                let mut best_txs = best_transactions(
+                    &self.l2_mempool,
+                    &mut self.l1_transactions,
+                    upgrade_tx,
+                    produce_command.block_number,
+                );
 
                 // Peek to ensure that at least one transaction is available so that timestamp is accurate.
                 let stream_closed = best_txs.wait_peek().await.is_none();
@@ -114,40 +178,26 @@ impl<Mempool: L2TransactionPool> BlockContextProvider<Mempool> {
                     ));
                 }
 
-                let timestamp = (millis_since_epoch() / 1000) as u64;
-
-                const NATIVE_PRICE: u128 = 1_000_000;
-                const NATIVE_PER_GAS: u128 = 100;
-                let eip1559_basefee = NATIVE_PRICE * NATIVE_PER_GAS;
-                let block_context = BlockContext {
-                    eip1559_basefee: self
-                        .base_fee_override
-                        .unwrap_or(U256::from(eip1559_basefee)),
-                    native_price: self
-                        .native_price_override
-                        .unwrap_or(U256::from(NATIVE_PRICE)),
-                    pubdata_price: self.pubdata_price_override.unwrap_or(U256::from(
-                        self.pubdata_price_provider
-                            .borrow()
-                            .expect("Pubdata price must be available"),
-                    )),
-                    block_number: produce_command.block_number,
-                    timestamp,
-                    chain_id: self.chain_id,
-                    coinbase: self.fee_collector_address,
-                    block_hashes: self.block_hashes_for_next_block,
-                    gas_limit: self.gas_limit,
-                    pubdata_limit: self.pubdata_limit,
-                    // todo: initialize as source of randomness, i.e. the value of prevRandao
-                    mix_hash: Default::default(),
-                    execution_version: LATEST_EXECUTION_VERSION as u32,
-                    blob_fee: U256::ZERO,
+                let block_timestamp_millis = self.next_block_timestamp_millis().await;
+                let timestamp = block_timestamp_millis / 1000;
+                best_txs.set_block_timestamp(timestamp);
+                let block_context =
+                    self.produce_block_context(produce_command.block_number, timestamp);
+                let force_include_l1_until =
+                    self.force_include_l1_until(produce_command.block_number, timestamp);
+                // The genesis upgrade transaction must be the first one in its block
+                let bundles = match &self.bundles {
+                    Some(store) if produce_command.block_number > 1 => {
+                        store.eligible(produce_command.block_number)
+                    }
+                    _ => Vec::new(),
                 };
                 self.pending_block_context_sender
                     .send_replace(Some(block_context));
                 PreparedBlockCommand {
                     block_context,
                     tx_source: Box::pin(best_txs),
+                    bundles,
                     seal_policy: SealPolicy::Decide(
                         produce_command.block_time,
                         produce_command.max_transactions_in_block,
@@ -155,9 +205,12 @@ impl<Mempool: L2TransactionPool> BlockContextProvider<Mempool> {
                     invalid_tx_policy: InvalidTxPolicy::RejectAndContinue,
                     metrics_label: "produce",
                     starting_l1_priority_id: self.next_l1_priority_id,
+                    force_include_l1_until,
                     node_version: self.node_version.clone(),
                     expected_block_output_hash: None,
                     previous_block_timestamp: self.previous_block_timestamp,
+                    block_timestamp_millis,
+                    trace: BlockTraceRecorder::default(),
                 }
             }
             BlockCommand::Replay(record) => {
@@ -180,11 +233,16 @@ impl<Mempool: L2TransactionPool> BlockContextProvider<Mempool> {
                     },
                     invalid_tx_policy: InvalidTxPolicy::Abort,
                     tx_source: Box::pin(ReplayTxStream::new(record.transactions)),
+                    bundles: Vec::new(),
                     starting_l1_priority_id: record.starting_l1_priority_id,
+                    force_include_l1_until: None,
                     metrics_label: "replay",
                     node_version: record.node_version,
                     expected_block_output_hash: Some(record.block_output_hash),
                     previous_block_timestamp: self.previous_block_timestamp,
+                    // Recorded by the producing node, never derived on replay
+                    block_timestamp_millis: record.block_timestamp_millis,
+                    trace: BlockTraceRecorder::default(),
                 }
             }
             BlockCommand::Rebuild(rebuild) => {
@@ -235,15 +293,19 @@ impl<Mempool: L2TransactionPool> BlockContextProvider<Mempool> {
                 PreparedBlockCommand {
                     block_context,
                     tx_source: Box::pin(ReplayTxStream::new(txs)),
+                    bundles: Vec::new(),
                     seal_policy: SealPolicy::UntilExhausted {
                         allowed_to_finish_early: true,
                     },
                     invalid_tx_policy: InvalidTxPolicy::RejectAndContinue,
                     metrics_label: "rebuild",
                     starting_l1_priority_id: self.next_l1_priority_id,
+                    force_include_l1_until: None,
                     node_version: self.node_version.clone(),
                     expected_block_output_hash: None,
                     previous_block_timestamp: self.previous_block_timestamp,
+                    block_timestamp_millis: rebuild.replay_record.block_timestamp_millis,
+                    trace: BlockTraceRecorder::default(),
                 }
             }
         };
@@ -251,10 +313,147 @@ impl<Mempool: L2TransactionPool> BlockContextProvider<Mempool> {
         Ok(prepared_command)
     }
 
+    /// Timestamp (in milliseconds) of the next produced block. See [`next_block_timestamp_millis`].
+    async fn next_block_timestamp_millis(&self) -> u64 {
+        loop {
+            let now_millis = millis_since_epoch() as u64;
+            if now_millis < self.previous_block_timestamp_millis {
+                tracing::warn!(
+                    now_millis,
+                    previous_block_timestamp_millis = self.previous_block_timestamp_millis,
+                    "system clock is behind the previous block; reusing its timestamp"
+                );
+            }
+            match next_block_timestamp_millis(
+                self.previous_block_timestamp_millis,
+                now_millis,
+                self.strict_block_timestamps,
+            ) {
+                Ok(millis) => return millis,
+                Err(wait) => tokio::time::sleep(wait).await,
+            }
+        }
+    }
+
+    /// Last priority transaction that expires on L1 within `forced_inclusion_threshold` and hence
+    /// must be included in the produced block.
+    fn force_include_l1_until(&self, block_number: u64, timestamp: u64) -> Option<L1TxSerialId> {
+        let until = self
+            .priority_deadlines
+            .last_expiring_by(timestamp + self.forced_inclusion_threshold.as_secs())?;
+        tracing::warn!(
+            block_number,
+            next_l1_priority_id = self.next_l1_priority_id,
+            force_include_until = until,
+            "forcing inclusion of priority transactions close to expiring on L1"
+        );
+        EXECUTION_METRICS.forced_l1_inclusion_blocks.inc();
+        Some(until)
+    }
+
+    /// Block context for a `Produce` command with the given block number and timestamp.
+    fn produce_block_context(&self, block_number: u64, timestamp: u64) -> BlockContext {
+        let eth_prices = BlockPrices {
+            eip1559_basefee: next_block_base_fee(BaseFeeInputs::Rate(
+                BaseTokenConversionRatio::ONE,
+            )),
+            native_price: U256::from(ETH_NATIVE_PRICE),
+            pubdata_price: U256::from(
+                self.pubdata_price_provider
+                    .borrow()
+                    .expect("Pubdata price must be available"),
+            ),
+        };
+        let prices = match &self.base_token_pricing {
+            Some(pricing) => self.base_token_prices(pricing, eth_prices),
+            None => eth_prices,
+        };
+        // Overrides are already denominated in the base token
+        BlockContext {
+            eip1559_basefee: match self.base_fee_override {
+                Some(base_fee) => next_block_base_fee(BaseFeeInputs::Override(base_fee)),
+                None => prices.eip1559_basefee,
+            },
+            native_price: self.native_price_override.unwrap_or(prices.native_price),
+            pubdata_price: self.pubdata_price_override.unwrap_or(prices.pubdata_price),
+            block_number,
+            timestamp,
+            chain_id: self.chain_id,
+            coinbase: self.fee_collector_address,
+            block_hashes: self.block_hashes_for_next_block,
+            gas_limit: self.gas_limit,
+            pubdata_limit: self.pubdata_limit,
+            // todo: initialize as source of randomness, i.e. the value of prevRandao
+            mix_hash: Default::default(),
+            execution_version: LATEST_EXECUTION_VERSION as u32,
+            blob_fee: U256::ZERO,
+        }
+    }
+
+    /// Converts `eth_prices` to the base token, or keeps the previous block prices if the rate is stale.
+    fn base_token_prices(
+        &self,
+        pricing: &BaseTokenPricing,
+        eth_prices: BlockPrices,
+    ) -> BlockPrices {
+        let rate = pricing
+            .rate_provider
+            .borrow()
+            .expect("Base token rate must be available");
+        let is_stale = rate.is_stale(Instant::now(), pricing.max_rate_staleness);
+        EXECUTION_METRICS.base_token_rate_stale.set(is_stale.into());
+        if is_stale {
+            tracing::warn!(
+                ratio = %rate.ratio,
+                age = ?rate.fetched_at.elapsed(),
+                max_staleness = ?pricing.max_rate_staleness,
+                "base token rate is stale; pausing fee adjustments"
+            );
+        }
+        base_token_prices(eth_prices, rate.ratio, is_stale, self.previous_block_prices)
+    }
+
+    /// Block context the next `Produce` command would most likely use - for speculative execution only.
+    /// Returns `None` if the pubdata price or the base token rate is not known yet.
+    pub fn speculative_block_context(&self, block_number: u64) -> Option<BlockContext> {
+        if self.pubdata_price_override.is_none() && self.pubdata_price_provider.borrow().is_none() {
+            return None;
+        }
+        if let Some(pricing) = &self.base_token_pricing
+            && pricing.rate_provider.borrow().is_none()
+        {
+            return None;
+        }
+        let timestamp_millis =
+            (millis_since_epoch() as u64).max(self.previous_block_timestamp_millis);
+        Some(self.produce_block_context(block_number, timestamp_millis / 1000))
+    }
+
+    /// Returns up to `limit` best L2 transactions without removing them from the mempool.
+    pub fn peek_best_l2_transactions(&self, limit: usize) -> Vec<ZkTransaction> {
+        self.l2_mempool
+            .best_transactions()
+            .take(limit)
+            .map(|tx| {
+                let (tx, signer) = tx.to_consensus().into_parts();
+                Recovered::new_unchecked(L2Envelope::from(tx), signer).into()
+            })
+            .collect()
+    }
+
     pub fn remove_txs(&self, tx_hashes: Vec<TxHash>) {
         self.l2_mempool.remove_transactions(tx_hashes);
     }
 
+    /// Records outcomes of bundles attempted in the canonical block `block_number`.
+    pub fn on_bundles_attempted(&self, block_number: u64, outcomes: Vec<(B256, BundleOutcome)>) {
+        if let Some(store) = &self.bundles
+            && !outcomes.is_empty()
+        {
+            store.on_block_executed(block_number, outcomes);
+        }
+    }
+
     pub async fn on_canonical_state_change(
         &mut self,
         block_output: &BlockOutput,
@@ -283,6 +482,8 @@ impl<Mempool: L2TransactionPool> BlockContextProvider<Mempool> {
         EXECUTION_METRICS
             .next_l1_priority_id
             .set(self.next_l1_priority_id);
+        self.priority_deadlines
+            .remove_processed(self.next_l1_priority_id);
 
         // Advance `block_hashes_for_next_block`.
         let last_block_hash = block_output.header.hash();
@@ -297,6 +498,8 @@ impl<Mempool: L2TransactionPool> BlockContextProvider<Mempool> {
                 .unwrap(),
         );
         self.previous_block_timestamp = block_output.header.timestamp;
+        self.previous_block_timestamp_millis = replay_record.block_timestamp_millis;
+        self.previous_block_prices = Some(BlockPrices::from(&replay_record.block_context));
 
         // TODO: confirm whether constructing a real block is absolutely necessary here;
         //       so far it looks like below is sufficient
@@ -329,6 +532,18 @@ impl<Mempool: L2TransactionPool> BlockContextProvider<Mempool> {
                 mined_transactions: l2_transactions,
                 update_kind: PoolUpdateKind::Commit,
             });
+
+        // Protocol upgrades change the execution version; transactions pooled under the previous
+        // version may no longer be accepted by the VM.
+        if let Ok(version) =
+            ExecutionVersion::try_from(replay_record.block_context.execution_version)
+            && version != self.l2_mempool.execution_version()
+        {
+            self.l2_mempool.on_execution_version_change(version);
+        }
+
+        // Scheduled transactions due in the next block are validated against the new state
+        self.l2_mempool.promote_scheduled_transactions().await;
     }
 }
 
@@ -338,3 +553,157 @@ pub fn millis_since_epoch() -> u128 {
         .expect("Incorrect system time")
         .as_millis()
 }
+
+/// Returns the timestamp (in milliseconds) for a block produced at `now_millis` after a block
+/// with `previous_millis`, or how long to wait before the block can be produced.
+///
+/// Timestamps never decrease, even if the system clock goes back. With sub-second block times,
+/// consecutive blocks may share the timestamp in seconds; in `strict` mode, this is not allowed,
+/// and the block must wait for the next second instead.
+fn next_block_timestamp_millis(
+    previous_millis: u64,
+    now_millis: u64,
+    strict: bool,
+) -> Result<u64, Duration> {
+    let millis = now_millis.max(previous_millis);
+    if strict && millis / 1000 <= previous_millis / 1000 {
+        let next_second_millis = (previous_millis / 1000 + 1) * 1000;
+        return Err(Duration::from_millis(next_second_millis - now_millis));
+    }
+    Ok(millis)
+}
+
+/// Converts ETH-denominated prices to the base token using `ratio`.
+///
+/// If the rate is stale, prices of the previous block are returned instead (i.e., fees are not adjusted
+/// until a fresh rate is available), so that an outdated rate doesn't produce prices far off the market.
+/// If there is no previous block yet, the last known rate is used.
+fn base_token_prices(
+    eth_prices: BlockPrices,
+    ratio: BaseTokenConversionRatio,
+    is_stale: bool,
+    previous_block_prices: Option<BlockPrices>,
+) -> BlockPrices {
+    if is_stale && let Some(previous) = previous_block_prices {
+        return previous;
+    }
+    BlockPrices {
+        eip1559_basefee: ratio.convert(eth_prices.eip1559_basefee),
+        native_price: ratio.convert(eth_prices.native_price),
+        pubdata_price: ratio.convert(eth_prices.pubdata_price),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Produces 4 blocks at 250ms intervals within one second, starting after `previous_millis`.
+    fn produce_blocks(previous_millis: u64, strict: bool) -> Vec<Result<u64, Duration>> {
+        let mut previous_millis = previous_millis;
+        (1..=4)
+            .map(|i| {
+                let result =
+                    next_block_timestamp_millis(previous_millis, 1_000_000 + i * 250 - 250, strict);
+                if let Ok(millis) = result {
+                    previous_millis = millis;
+                }
+                result
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sub_second_blocks_share_timestamp() {
+        let timestamps = produce_blocks(999_900, false);
+        assert_eq!(
+            timestamps,
+            [Ok(1_000_000), Ok(1_000_250), Ok(1_000_500), Ok(1_000_750)]
+        );
+        // Same block timestamp in seconds, but still ordered by millis
+        let seconds: Vec<_> = timestamps.iter().map(|t| t.unwrap() / 1000).collect();
+        assert_eq!(seconds, [1_000; 4]);
+    }
+
+    #[test]
+    fn strict_mode_waits_for_next_second() {
+        let timestamps = produce_blocks(999_900, true);
+        assert_eq!(timestamps[0], Ok(1_000_000));
+        assert_eq!(
+            timestamps[1..],
+            [
+                Err(Duration::from_millis(750)),
+                Err(Duration::from_millis(500)),
+                Err(Duration::from_millis(250)),
+            ]
+        );
+        assert_eq!(
+            next_block_timestamp_millis(1_000_000, 1_001_000, true),
+            Ok(1_001_000)
+        );
+    }
+
+    #[test]
+    fn timestamps_never_decrease() {
+        // System clock went back
+        assert_eq!(
+            next_block_timestamp_millis(1_000_500, 1_000_100, false),
+            Ok(1_000_500)
+        );
+        assert_eq!(
+            next_block_timestamp_millis(1_000_500, 1_000_100, true),
+            Err(Duration::from_millis(900))
+        );
+    }
+
+    const ETH_PRICES: BlockPrices = BlockPrices {
+        eip1559_basefee: U256::from_limbs([100_000_000, 0, 0, 0]),
+        native_price: U256::from_limbs([1_000_000, 0, 0, 0]),
+        pubdata_price: U256::from_limbs([17_000_000_000, 0, 0, 0]),
+    };
+
+    #[test]
+    fn base_token_rate_is_applied_to_prices() {
+        // 1 ETH = 2500.5 base tokens
+        let ratio = BaseTokenConversionRatio::new(5_001, 2).unwrap();
+        let prices = base_token_prices(ETH_PRICES, ratio, false, None);
+        assert_eq!(
+            prices,
+            BlockPrices {
+                eip1559_basefee: U256::from(250_050_000_000_u128),
+                native_price: U256::from(2_500_500_000_u128),
+                pubdata_price: U256::from(42_508_500_000_000_u128),
+            }
+        );
+
+        let prices = base_token_prices(ETH_PRICES, BaseTokenConversionRatio::ONE, false, None);
+        assert_eq!(prices, ETH_PRICES);
+    }
+
+    #[test]
+    fn stale_base_token_rate_keeps_previous_prices() {
+        let ratio = BaseTokenConversionRatio::new(3_000, 1).unwrap();
+        let previous = base_token_prices(ETH_PRICES, ratio, false, None);
+        // L1 prices doubled, but the rate is stale
+        let eth_prices = BlockPrices {
+            eip1559_basefee: ETH_PRICES.eip1559_basefee * U256::from(2),
+            native_price: ETH_PRICES.native_price * U256::from(2),
+            pubdata_price: ETH_PRICES.pubdata_price * U256::from(2),
+        };
+        let stale_ratio = BaseTokenConversionRatio::new(1, 1_000).unwrap();
+        assert_eq!(
+            base_token_prices(eth_prices, stale_ratio, true, Some(previous)),
+            previous
+        );
+        // Without a previous block, the last known rate is the best estimate
+        assert_eq!(
+            base_token_prices(eth_prices, stale_ratio, true, None).pubdata_price,
+            U256::from(34_000_000)
+        );
+        // Adjustments resume once the rate is fresh
+        assert_eq!(
+            base_token_prices(eth_prices, ratio, false, Some(previous)).pubdata_price,
+            previous.pubdata_price * U256::from(2)
+        );
+    }
+}
@@ -1,30 +1,50 @@
+use crate::execution::block_trace::{TraceCounters, TraceDecision};
+use crate::execution::bundles::{BundleOutcome, simulate_bundle};
 use crate::execution::metrics::{EXECUTION_METRICS, SequencerState};
+use crate::execution::priority_inclusion::ForcedL1Inclusion;
 use crate::execution::utils::{BlockDump, hash_block_output};
 use crate::execution::vm_wrapper::VmWrapper;
+use crate::execution::warm_up::{WarmStorageCache, WarmedViewState};
 use crate::model::blocks::{InvalidTxPolicy, PreparedBlockCommand, SealPolicy};
 use crate::model::debug_formatting::BlockOutputDebug;
 use alloy::consensus::Transaction;
-use alloy::primitives::TxHash;
+use alloy::primitives::{B256, TxHash};
 use futures::StreamExt;
+use serde::{Deserialize, Serialize};
 use std::pin::Pin;
 use tokio::time::Sleep;
 use vise::EncodeLabelValue;
 use zksync_os_interface::error::InvalidTransaction;
 use zksync_os_interface::types::BlockOutput;
+use zksync_os_multivm::ExecutionScratch;
 use zksync_os_observability::ComponentStateHandle;
-use zksync_os_storage_api::{MeteredViewState, ReadStateHistory, ReplayRecord, WriteState};
+use zksync_os_storage_api::{
+    BlockStats, MeteredViewState, ReadStateHistory, ReplayRecord, WriteState,
+};
 use zksync_os_types::{ZkTransaction, ZkTxType, ZksyncOsEncode};
 // Note that this is a pure function without a container struct (e.g. `struct BlockExecutor`)
 // MAINTAIN this to ensure the function is completely stateless - explicit or implicit.
 
 // a side effect of this is that it's harder to pass config values (normally we'd just pass the whole config object)
 // please be mindful when adding new parameters here
+// (`scratch` only holds reusable buffers - it doesn't affect execution results)
 
 pub async fn execute_block<R: ReadStateHistory + WriteState>(
     mut command: PreparedBlockCommand<'_>,
     state: R,
+    warm_cache: Option<WarmStorageCache>,
+    scratch: &ExecutionScratch,
     latency_tracker: &ComponentStateHandle<SequencerState>,
-) -> Result<(BlockOutput, ReplayRecord, Vec<(TxHash, InvalidTransaction)>), BlockDump> {
+) -> Result<
+    (
+        BlockOutput,
+        ReplayRecord,
+        BlockStats,
+        Vec<(TxHash, InvalidTransaction)>,
+        Vec<(B256, BundleOutcome)>,
+    ),
+    BlockDump,
+> {
     tracing::debug!(command = ?command, block_number=command.block_context.block_number, "Executing command");
     latency_tracker.enter_state(SequencerState::InitializingVm);
     let ctx = command.block_context;
@@ -39,9 +59,12 @@ pub async fn execute_block<R: ReadStateHistory + WriteState>(
         })?;
     let metered_state_view = MeteredViewState {
         component_state_tracker: latency_tracker.clone(),
-        state_view,
+        state_view: WarmedViewState::new(state_view, warm_cache, ctx.block_number - 1),
     };
-    let mut runner = VmWrapper::new(ctx, metered_state_view);
+    // Bundles are simulated on a separate VM before being applied, so a copy of the state view
+    // is only needed if there are any.
+    let simulation_state_view = (!command.bundles.is_empty()).then(|| metered_state_view.clone());
+    let mut runner = VmWrapper::new(ctx, metered_state_view, scratch);
 
     let mut executed_txs = Vec::<ZkTransaction>::new();
     let mut cumulative_gas_used = 0u64;
@@ -55,6 +78,91 @@ pub async fn execute_block<R: ReadStateHistory + WriteState>(
         SealPolicy::UntilExhausted { .. } => None,
     };
     let mut deadline: Option<Pin<Box<Sleep>>> = None; // will arm after 1st tx success
+    // priority txs close to expiry on L1 postpone sealing by deadline or tx count
+    let mut forced_l1 = ForcedL1Inclusion::new(
+        command.force_include_l1_until,
+        command.starting_l1_priority_id,
+    );
+
+    /* ---------- bundles -------------------------------------------- */
+    // Each bundle is simulated on top of the transactions already in the block and is only applied
+    // if all of its transactions succeed, since the VM cannot roll back executed transactions.
+    let mut bundle_outcomes = Vec::with_capacity(command.bundles.len());
+    for bundle in std::mem::take(&mut command.bundles) {
+        let tx_count_exceeded = match command.seal_policy {
+            SealPolicy::Decide(_, limit) => executed_txs.len() + bundle.transactions.len() > limit,
+            SealPolicy::UntilExhausted { .. } => false,
+        };
+        if tx_count_exceeded || cumulative_gas_used + bundle.gas_limit() > ctx.gas_limit {
+            // Doesn't count as an attempt; the bundle may still fit into a later block
+            tracing::debug!(block = ctx.block_number, bundle = %bundle.hash, "bundle doesn't fit into the block");
+            continue;
+        }
+        latency_tracker.enter_state(SequencerState::Execution);
+        let simulation = simulate_bundle(
+            ctx,
+            simulation_state_view.clone().expect("bundles are present"),
+            scratch,
+            &executed_txs,
+            &bundle,
+        )
+        .await
+        .map_err(|e| BlockDump {
+            ctx,
+            txs: all_processed_txs.clone(),
+            error: e
+                .context(format!("simulating bundle {}", bundle.hash))
+                .to_string(),
+        })?;
+        if let Err(reason) = simulation {
+            tracing::info!(block = ctx.block_number, bundle = %bundle.hash, reason, "bundle rejected");
+            bundle_outcomes.push((bundle.hash, BundleOutcome::Failed(reason)));
+            continue;
+        }
+
+        for tx in &bundle.transactions {
+            all_processed_txs.push(tx.clone());
+            let res = runner
+                .execute_next_tx(tx.clone().encode())
+                .await
+                .map_err(|e| BlockDump {
+                    ctx,
+                    txs: all_processed_txs.clone(),
+                    error: e.to_string(),
+                })?;
+            // Execution is deterministic, so it must match the simulation
+            let res = match res {
+                Ok(res) if res.status => res,
+                other => {
+                    return Err(BlockDump {
+                        ctx,
+                        txs: all_processed_txs.clone(),
+                        error: format!(
+                            "bundle {} tx {} diverged from simulation: {other:?}",
+                            bundle.hash,
+                            tx.hash()
+                        ),
+                    });
+                }
+            };
+            EXECUTION_METRICS.executed_transactions.inc();
+            EXECUTION_METRICS.transaction_gas_used.observe(res.gas_used);
+            EXECUTION_METRICS.transaction_status[&"success"].inc();
+            executed_txs.push(tx.clone());
+            cumulative_gas_used += res.gas_used;
+        }
+        tracing::info!(block = ctx.block_number, bundle = %bundle.hash, txs = bundle.transactions.len(), "bundle included");
+        bundle_outcomes.push((bundle.hash, BundleOutcome::Included));
+    }
+    command.trace.bundles_applied(TraceCounters {
+        txs_in_block: executed_txs.len(),
+        cumulative_gas_used,
+    });
+    if !executed_txs.is_empty()
+        && let Some(dur) = deadline_dur
+    {
+        deadline = Some(Box::pin(tokio::time::sleep(dur)));
+    }
 
     /* ---------- main loop ------------------------------------------ */
     // seal_reason must only be used for observability - handling must remain generic
@@ -67,7 +175,7 @@ pub async fn execute_block<R: ReadStateHistory + WriteState>(
                         d.as_mut().await
                     }
                 },
-                if deadline.is_some()
+                if deadline.is_some() && !forced_l1.is_pending()
             => {
                 tracing::debug!(block = ctx.block_number,
                                txs = executed_txs.len(),
@@ -115,6 +223,11 @@ pub async fn execute_block<R: ReadStateHistory + WriteState>(
                                     "Transaction executed"
                                 );
 
+                                command.trace.decide(
+                                    TraceDecision::Included { gas_used: res.gas_used },
+                                    TraceCounters { txs_in_block: executed_txs.len(), cumulative_gas_used },
+                                );
+                                forced_l1.on_executed(&tx);
                                 executed_txs.push(tx);
                                 cumulative_gas_used += res.gas_used;
 
@@ -123,7 +236,7 @@ pub async fn execute_block<R: ReadStateHistory + WriteState>(
                                     deadline = Some(Box::pin(tokio::time::sleep(dur)));
                                 }
                                 match command.seal_policy {
-                                    SealPolicy::Decide(_, limit) if executed_txs.len() >= limit => {
+                                    SealPolicy::Decide(_, limit) if executed_txs.len() >= limit && !forced_l1.is_pending() => {
                                     tracing::debug!(block = ctx.block_number,
                                                    txs = executed_txs.len(),
                                                    "tx limit reached → sealing");
@@ -148,16 +261,20 @@ pub async fn execute_block<R: ReadStateHistory + WriteState>(
 
                                         // mark the tx as invalid regardless of the `rejection_method`.
                                         command.tx_source.as_mut().mark_last_tx_as_invalid();
+                                        let counters = TraceCounters { txs_in_block: executed_txs.len(), cumulative_gas_used };
                                         // add tx to `purged_txs` only if we are purging it.
                                         match rejection_method {
                                             TxRejectionMethod::Purge => {
+                                                command.trace.decide(TraceDecision::Purged { reason: format!("{e:?}") }, counters);
                                                 purged_txs.push((*tx.hash(), e.clone()));
                                                 tracing::warn!(tx_hash = %tx.hash(), block = ctx.block_number, ?e, "invalid tx → purged");
                                             }
                                             TxRejectionMethod::Skip => {
+                                                command.trace.decide(TraceDecision::Skipped { reason: format!("{e:?}") }, counters);
                                                 tracing::warn!(tx_hash = %tx.hash(), block = ctx.block_number, ?e, "invalid tx → skipped");
                                             },
                                             TxRejectionMethod::SealBlock(reason) => {
+                                                command.trace.decide(TraceDecision::Deferred { seal_reason: reason }, counters);
                                                 tracing::debug!(tx_hash = %tx.hash(), block = ctx.block_number, ?e, ?reason, "sealing block by criterion");
                                                 break reason;
                                             }
@@ -178,6 +295,10 @@ pub async fn execute_block<R: ReadStateHistory + WriteState>(
                     }
                     /* ----- got a transaction that cannot be included because of gas --- */
                     Some(_tx) => {
+                        command.trace.decide(
+                            TraceDecision::Deferred { seal_reason: SealReason::GasLimit },
+                            TraceCounters { txs_in_block: executed_txs.len(), cumulative_gas_used },
+                        );
                         tracing::debug!(block = ctx.block_number, "sealing block as next tx cannot be included");
                         break SealReason::GasLimit;
                     }
@@ -194,6 +315,7 @@ pub async fn execute_block<R: ReadStateHistory + WriteState>(
             }
         }
     };
+    command.trace.seal(seal_reason);
 
     // seal reason validation
     match command.seal_policy {
@@ -231,29 +353,18 @@ pub async fn execute_block<R: ReadStateHistory + WriteState>(
         error: e.context("seal_block()").to_string(),
     })?;
 
-    EXECUTION_METRICS
-        .storage_writes_per_block
-        .observe(output.storage_writes.len() as u64);
+    let stats = BlockStats::new(&output);
     EXECUTION_METRICS.seal_reason[&seal_reason].inc();
-    EXECUTION_METRICS.gas_per_block.observe(cumulative_gas_used);
-    EXECUTION_METRICS
-        .pubdata_per_block
-        .observe(output.pubdata.len() as u64);
-    EXECUTION_METRICS
-        .transactions_per_block
-        .observe(executed_txs.len() as u64);
-    EXECUTION_METRICS
-        .computational_native_used_per_block
-        .observe(output.computaional_native_used);
+    EXECUTION_METRICS.report_block_stats(&stats);
 
     tracing::info!(
         block_number = output.header.number,
         command = command.metrics_label,
         ?seal_reason,
         tx_count = executed_txs.len(),
-        storage_writes = output.storage_writes.len(),
-        preimages = output.published_preimages.len(),
-        pubdata_bytes = output.pubdata.len(),
+        storage_writes = stats.storage_writes,
+        preimages = stats.new_preimages,
+        pubdata_bytes = stats.pubdata_bytes,
         cumulative_gas_used,
         purged_txs_len = purged_txs.len(),
         "Block sealed in block executor"
@@ -265,24 +376,9 @@ pub async fn execute_block<R: ReadStateHistory + WriteState>(
         "Block output"
     );
 
+    // Compared against `command.expected_block_output_hash` by the caller
     let block_hash_output = hash_block_output(&output);
 
-    // Check if the block output matches the expected hash.
-    if let Some(expected_hash) = command.expected_block_output_hash
-        && expected_hash != block_hash_output
-    {
-        let error = format!(
-            "Block #{} output hash mismatch: expected {expected_hash}, got {block_hash_output}",
-            ctx.block_number,
-        );
-        tracing::error!(?output, block_number = ctx.block_number, expected = %expected_hash, actual = %block_hash_output, "Block output hash mismatch");
-        return Err(BlockDump {
-            ctx,
-            txs: all_processed_txs.clone(),
-            error,
-        });
-    }
-
     Ok((
         output,
         ReplayRecord::new(
@@ -290,10 +386,13 @@ pub async fn execute_block<R: ReadStateHistory + WriteState>(
             command.starting_l1_priority_id,
             executed_txs,
             command.previous_block_timestamp,
+            command.block_timestamp_millis,
             command.node_version,
             block_hash_output,
         ),
+        stats,
         purged_txs,
+        bundle_outcomes,
     ))
 }
 
@@ -306,8 +405,9 @@ enum TxRejectionMethod {
     SealBlock(SealReason),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EncodeLabelValue)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EncodeLabelValue, Serialize, Deserialize)]
 #[metrics(label = "seal_reason", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
 pub enum SealReason {
     TxStreamExhausted,
     Timeout,
@@ -0,0 +1,515 @@
+//! Block-building traces: the decision log of produced blocks.
+//!
+//! When enabled, transactions yielded by the transaction source of a produced block are recorded
+//! by [`TracedTxSource`] in the order they are considered, and the block executor records what it
+//! did with each of them (included, skipped, purged or deferred to a later block) along with the
+//! block counters at that point. This makes block-building decisions reproducible after the
+//! mempool state they depended on is gone, e.g. when investigating why a transaction was skipped.
+//!
+//! Traces only contain transaction hashes and a few scalar fields - never calldata - to stay small.
+//! Bundles are applied before the transaction source is polled and are not traced.
+//!
+//! [`replay_trace`] replays a trace with a decision function (e.g. one implementing a fix) and
+//! reports the first point where its decisions diverge from the recorded ones.
+
+use crate::execution::block_executor::SealReason;
+use alloy::consensus::Transaction;
+use alloy::primitives::{Address, TxHash};
+use anyhow::Context;
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context as TaskContext, Poll};
+use zksync_os_interface::types::BlockContext;
+use zksync_os_mempool::TxStream;
+use zksync_os_types::ZkTransaction;
+
+/// Transaction yielded by the transaction source of a produced block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceCandidate {
+    pub hash: TxHash,
+    pub signer: Address,
+    pub nonce: u64,
+    pub gas_limit: u64,
+}
+
+impl From<&ZkTransaction> for TraceCandidate {
+    fn from(tx: &ZkTransaction) -> Self {
+        Self {
+            hash: *tx.hash(),
+            signer: tx.signer(),
+            nonce: tx.nonce(),
+            gas_limit: tx.inner.gas_limit(),
+        }
+    }
+}
+
+/// Block counters right before a decision was taken.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TraceCounters {
+    /// Transactions already included in the block, including ones from bundles.
+    pub txs_in_block: usize,
+    pub cumulative_gas_used: u64,
+}
+
+/// What the block executor did with a candidate transaction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum TraceDecision {
+    Included {
+        gas_used: u64,
+    },
+    /// Skipped for this block; the transaction stays in the mempool.
+    Skipped {
+        reason: String,
+    },
+    /// Invalid - removed from the mempool.
+    Purged {
+        reason: String,
+    },
+    /// Doesn't fit into the block, which is sealed; left for a later block.
+    Deferred {
+        seal_reason: SealReason,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceEntry {
+    pub candidate: TraceCandidate,
+    pub counters: TraceCounters,
+    /// `None` if the block was sealed before the executor decided on the candidate.
+    pub decision: Option<TraceDecision>,
+}
+
+/// Decision log of a single produced block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockTrace {
+    pub block_number: u64,
+    pub timestamp: u64,
+    pub gas_limit: u64,
+    /// Transactions from bundles, applied before the first candidate, and gas used by them.
+    pub bundle_txs: usize,
+    pub bundle_gas_used: u64,
+    pub entries: Vec<TraceEntry>,
+    pub seal_reason: Option<SealReason>,
+    /// Hashes of candidates included in the sealed block, in order.
+    pub included: Vec<TxHash>,
+}
+
+impl BlockTrace {
+    fn new(ctx: &BlockContext) -> Self {
+        Self {
+            block_number: ctx.block_number,
+            timestamp: ctx.timestamp,
+            gas_limit: ctx.gas_limit,
+            bundle_txs: 0,
+            bundle_gas_used: 0,
+            entries: Vec::new(),
+            seal_reason: None,
+            included: Vec::new(),
+        }
+    }
+}
+
+/// Records the trace of a block being built; shared by [`TracedTxSource`] and the block executor.
+/// A disabled recorder (the default) ignores everything.
+#[derive(Debug, Clone, Default)]
+pub struct BlockTraceRecorder(Option<Arc<Mutex<BlockTrace>>>);
+
+impl BlockTraceRecorder {
+    pub fn new(ctx: &BlockContext) -> Self {
+        Self(Some(Arc::new(Mutex::new(BlockTrace::new(ctx)))))
+    }
+
+    fn update(&self, f: impl FnOnce(&mut BlockTrace)) {
+        if let Some(trace) = &self.0 {
+            f(&mut trace.lock().unwrap());
+        }
+    }
+
+    fn candidate(&self, tx: &ZkTransaction) {
+        self.update(|trace| {
+            trace.entries.push(TraceEntry {
+                candidate: tx.into(),
+                counters: TraceCounters::default(),
+                decision: None,
+            })
+        });
+    }
+
+    pub(crate) fn bundles_applied(&self, counters: TraceCounters) {
+        self.update(|trace| {
+            trace.bundle_txs = counters.txs_in_block;
+            trace.bundle_gas_used = counters.cumulative_gas_used;
+        });
+    }
+
+    /// Records the decision on the last yielded candidate.
+    pub(crate) fn decide(&self, decision: TraceDecision, counters: TraceCounters) {
+        self.update(|trace| {
+            if let Some(entry) = trace.entries.last_mut() {
+                if let TraceDecision::Included { .. } = decision {
+                    trace.included.push(entry.candidate.hash);
+                }
+                entry.counters = counters;
+                entry.decision = Some(decision);
+            }
+        });
+    }
+
+    pub(crate) fn seal(&self, seal_reason: SealReason) {
+        self.update(|trace| trace.seal_reason = Some(seal_reason));
+    }
+
+    /// Returns the recorded trace, or `None` if the recorder is disabled.
+    pub fn finish(&self) -> Option<BlockTrace> {
+        Some(self.0.as_ref()?.lock().unwrap().clone())
+    }
+}
+
+/// Transaction source recording every yielded transaction as a candidate.
+pub struct TracedTxSource<'a> {
+    inner: Pin<Box<dyn TxStream<Item = ZkTransaction> + Send + 'a>>,
+    recorder: BlockTraceRecorder,
+}
+
+impl<'a> TracedTxSource<'a> {
+    pub fn new(
+        inner: Pin<Box<dyn TxStream<Item = ZkTransaction> + Send + 'a>>,
+        recorder: BlockTraceRecorder,
+    ) -> Self {
+        Self { inner, recorder }
+    }
+}
+
+impl Stream for TracedTxSource<'_> {
+    type Item = ZkTransaction;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
+        let poll = self.inner.as_mut().poll_next(cx);
+        if let Poll::Ready(Some(tx)) = &poll {
+            self.recorder.candidate(tx);
+        }
+        poll
+    }
+}
+
+impl TxStream for TracedTxSource<'_> {
+    fn mark_last_tx_as_invalid(mut self: Pin<&mut Self>) {
+        self.inner.as_mut().mark_last_tx_as_invalid();
+    }
+}
+
+/// First difference between a replayed trace and the recorded one.
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum TraceMismatch {
+    #[error("candidate #{index} ({hash}): expected counters {expected:?}, got {actual:?}")]
+    Counters {
+        index: usize,
+        hash: TxHash,
+        expected: TraceCounters,
+        actual: TraceCounters,
+    },
+    #[error("candidate #{index} ({hash}): expected decision {expected:?}, got {actual:?}")]
+    Decision {
+        index: usize,
+        hash: TxHash,
+        expected: Option<TraceDecision>,
+        actual: Option<TraceDecision>,
+    },
+    #[error("expected block composition {expected:?}, got {actual:?}")]
+    Composition {
+        expected: Vec<TxHash>,
+        actual: Vec<TxHash>,
+    },
+}
+
+/// Replays the candidates of `trace` in the recorded order, taking decisions with `decide`, and
+/// checks that decisions, counters and the resulting block composition match the recorded ones.
+/// `decide` returns `None` for candidates the block is sealed before.
+pub fn replay_trace(
+    trace: &BlockTrace,
+    mut decide: impl FnMut(&TraceCandidate, TraceCounters) -> Option<TraceDecision>,
+) -> Result<(), TraceMismatch> {
+    let mut counters = TraceCounters {
+        txs_in_block: trace.bundle_txs,
+        cumulative_gas_used: trace.bundle_gas_used,
+    };
+    let mut included = Vec::new();
+    for (index, entry) in trace.entries.iter().enumerate() {
+        let hash = entry.candidate.hash;
+        let decision = decide(&entry.candidate, counters);
+        if decision != entry.decision {
+            return Err(TraceMismatch::Decision {
+                index,
+                hash,
+                expected: entry.decision.clone(),
+                actual: decision,
+            });
+        }
+        let Some(decision) = decision else {
+            continue;
+        };
+        if counters != entry.counters {
+            return Err(TraceMismatch::Counters {
+                index,
+                hash,
+                expected: entry.counters,
+                actual: counters,
+            });
+        }
+        if let TraceDecision::Included { gas_used } = decision {
+            included.push(hash);
+            counters.txs_in_block += 1;
+            counters.cumulative_gas_used += gas_used;
+        }
+    }
+    if included != trace.included {
+        return Err(TraceMismatch::Composition {
+            expected: trace.included.clone(),
+            actual: included,
+        });
+    }
+    Ok(())
+}
+
+/// Loads a trace written by [`save_trace`] and replays it with `decide`, see [`replay_trace`].
+pub fn rebuild_from_trace(
+    path: &Path,
+    decide: impl FnMut(&TraceCandidate, TraceCounters) -> Option<TraceDecision>,
+) -> anyhow::Result<()> {
+    let trace = load_trace(path)?;
+    replay_trace(&trace, decide)
+        .with_context(|| format!("block #{} diverged from trace {path:?}", trace.block_number))
+}
+
+pub fn load_trace(path: &Path) -> anyhow::Result<BlockTrace> {
+    let bytes = std::fs::read(path).with_context(|| format!("failed to read trace {path:?}"))?;
+    serde_json::from_slice(&bytes).context("failed to deserialize trace")
+}
+
+/// Writes `trace` to the `path` directory, removing the trace written `max_traces` blocks
+/// earlier (if any), so that only traces of the last `max_traces` blocks are kept.
+pub(crate) fn save_trace(
+    path: &Path,
+    trace: &BlockTrace,
+    max_traces: usize,
+) -> anyhow::Result<PathBuf> {
+    std::fs::create_dir_all(path).context("create_dir_all")?;
+    let file_path = path.join(trace_file_name(trace.block_number));
+    let bytes = serde_json::to_vec(trace).context("failed to serialize trace")?;
+    std::fs::write(&file_path, bytes).context("failed to write trace file")?;
+    if let Some(expired) = trace.block_number.checked_sub(max_traces as u64) {
+        match std::fs::remove_file(path.join(trace_file_name(expired))) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                return Err(err).context("failed to remove expired trace file");
+            }
+            _ => {}
+        }
+    }
+    Ok(file_path)
+}
+
+fn trace_file_name(block_number: u64) -> String {
+    format!("block_trace_{block_number}.json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::consensus::{SignableTransaction, TxEip1559};
+    use alloy::primitives::{Bytes, Signature, TxKind};
+    use futures::StreamExt;
+    use zksync_os_mempool::ReplayTxStream;
+    use zksync_os_types::{L2Envelope, L2Transaction};
+
+    fn tx(signer: u8, nonce: u64, gas_limit: u64) -> ZkTransaction {
+        let envelope = L2Envelope::from(
+            TxEip1559 {
+                chain_id: 270,
+                nonce,
+                gas_limit,
+                to: TxKind::Call(Address::repeat_byte(1)),
+                input: Bytes::from(vec![0xab; 1_000]),
+                ..Default::default()
+            }
+            .into_signed(Signature::test_signature()),
+        );
+        L2Transaction::new_unchecked(envelope, Address::repeat_byte(signer)).into()
+    }
+
+    /// Builds a block from `txs` like the block executor does: transactions with gas limit above
+    /// 500k are skipped for lack of funds, ones from signer 9 are purged, nonces above 5 are
+    /// skipped as too high, and the block is sealed once 1M gas would be exceeded.
+    async fn build_block(txs: Vec<ZkTransaction>) -> BlockTrace {
+        let ctx = BlockContext {
+            block_number: 5,
+            gas_limit: 1_000_000,
+            ..Default::default()
+        };
+        let recorder = BlockTraceRecorder::new(&ctx);
+        let mut counters = TraceCounters {
+            txs_in_block: 1,
+            cumulative_gas_used: 100_000,
+        };
+        recorder.bundles_applied(counters);
+        let mut source = TracedTxSource::new(Box::pin(ReplayTxStream::new(txs)), recorder.clone());
+        let seal_reason = loop {
+            let Some(tx) = source.next().await else {
+                break SealReason::TxStreamExhausted;
+            };
+            if counters.cumulative_gas_used + tx.inner.gas_limit() > ctx.gas_limit {
+                let seal_reason = SealReason::GasLimit;
+                recorder.decide(TraceDecision::Deferred { seal_reason }, counters);
+                break seal_reason;
+            }
+            let decision = decide(&(&tx).into(), counters).unwrap();
+            recorder.decide(decision.clone(), counters);
+            if let TraceDecision::Included { gas_used } = decision {
+                counters.txs_in_block += 1;
+                counters.cumulative_gas_used += gas_used;
+            } else {
+                Pin::new(&mut source).mark_last_tx_as_invalid();
+            }
+        };
+        recorder.seal(seal_reason);
+        recorder.finish().unwrap()
+    }
+
+    fn decide(candidate: &TraceCandidate, _counters: TraceCounters) -> Option<TraceDecision> {
+        Some(if candidate.signer == Address::repeat_byte(9) {
+            TraceDecision::Purged {
+                reason: "InvalidChainId".into(),
+            }
+        } else if candidate.gas_limit > 500_000 {
+            TraceDecision::Skipped {
+                reason: "LackOfFundForMaxFee".into(),
+            }
+        } else if candidate.nonce > 5 {
+            TraceDecision::Skipped {
+                reason: "NonceTooHigh".into(),
+            }
+        } else {
+            TraceDecision::Included {
+                gas_used: candidate.gas_limit / 2,
+            }
+        })
+    }
+
+    fn txs() -> Vec<ZkTransaction> {
+        vec![
+            tx(1, 0, 400_000),
+            tx(2, 0, 600_000),
+            tx(3, 7, 100_000),
+            tx(9, 0, 100_000),
+            tx(4, 0, 400_000),
+            tx(5, 0, 400_000),
+            tx(6, 0, 500_000),
+        ]
+    }
+
+    #[tokio::test]
+    async fn trace_records_decisions() {
+        let trace = build_block(txs()).await;
+        assert_eq!(trace.block_number, 5);
+        assert_eq!(trace.seal_reason, Some(SealReason::GasLimit));
+        assert_eq!(trace.entries.len(), 7);
+        let decisions: Vec<_> = trace
+            .entries
+            .iter()
+            .map(|entry| entry.decision.clone().unwrap())
+            .collect();
+        assert_eq!(
+            decisions,
+            [
+                TraceDecision::Included { gas_used: 200_000 },
+                TraceDecision::Skipped {
+                    reason: "LackOfFundForMaxFee".into()
+                },
+                TraceDecision::Skipped {
+                    reason: "NonceTooHigh".into()
+                },
+                TraceDecision::Purged {
+                    reason: "InvalidChainId".into()
+                },
+                TraceDecision::Included { gas_used: 200_000 },
+                TraceDecision::Included { gas_used: 200_000 },
+                TraceDecision::Deferred {
+                    seal_reason: SealReason::GasLimit
+                },
+            ]
+        );
+        assert_eq!(
+            trace.entries[5].counters,
+            TraceCounters {
+                txs_in_block: 3,
+                cumulative_gas_used: 500_000,
+            }
+        );
+        let expected_included: Vec<_> = [0, 4, 5].map(|i| *txs()[i].hash()).into();
+        assert_eq!(trace.included, expected_included);
+
+        // No calldata in the trace
+        let bytes = serde_json::to_vec(&trace).unwrap();
+        let calldata_hex = alloy::hex::encode([0xab; 32]);
+        assert!(!String::from_utf8(bytes).unwrap().contains(&calldata_hex));
+    }
+
+    #[tokio::test]
+    async fn trace_is_replayed() {
+        let dir = tempfile::tempdir().unwrap();
+        let trace = build_block(txs()).await;
+        let path = save_trace(dir.path(), &trace, 10).unwrap();
+        assert_eq!(load_trace(&path).unwrap(), trace);
+
+        let recorded = |candidate: &TraceCandidate, counters: TraceCounters| {
+            if counters.cumulative_gas_used + candidate.gas_limit > trace.gas_limit {
+                return Some(TraceDecision::Deferred {
+                    seal_reason: SealReason::GasLimit,
+                });
+            }
+            decide(candidate, counters)
+        };
+        rebuild_from_trace(&path, &recorded).unwrap();
+
+        // A "fix" accepting transactions with too high nonces changes the third decision
+        let fixed = |candidate: &TraceCandidate, counters: TraceCounters| {
+            if candidate.nonce > 5 {
+                return Some(TraceDecision::Included { gas_used: 50_000 });
+            }
+            recorded(candidate, counters)
+        };
+        let mismatch = replay_trace(&trace, fixed).unwrap_err();
+        assert_eq!(
+            mismatch,
+            TraceMismatch::Decision {
+                index: 2,
+                hash: *txs()[2].hash(),
+                expected: Some(TraceDecision::Skipped {
+                    reason: "NonceTooHigh".into()
+                }),
+                actual: Some(TraceDecision::Included { gas_used: 50_000 }),
+            }
+        );
+    }
+
+    #[test]
+    fn old_traces_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut trace = BlockTrace::new(&BlockContext::default());
+        for block_number in 1..=5 {
+            trace.block_number = block_number;
+            save_trace(dir.path(), &trace, 2).unwrap();
+        }
+        let mut files: Vec<_> = dir
+            .path()
+            .read_dir()
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        files.sort();
+        assert_eq!(files, ["block_trace_4.json", "block_trace_5.json"]);
+    }
+}
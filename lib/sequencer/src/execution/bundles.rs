@@ -0,0 +1,477 @@
+//! Bundle lane of the sequencer.
+//!
+//! A bundle is an ordered group of L2 transactions submitted by a trusted service (see
+//! `admin_submitBundle`) that must be included in a block atomically: either all transactions in
+//! the given order, or none. Pending bundles are kept in [`BundleStore`]. Every produced block
+//! attempts the bundles eligible for it before any mempool transaction: each bundle is simulated on
+//! top of the transactions already in the block, and only included if every transaction in it
+//! succeeds. Bundles that fail are retried in the next block until they expire.
+//!
+//! Bundles get no special treatment from the VM: sender nonces, balances and block limits are
+//! enforced exactly as for mempool transactions.
+
+use crate::execution::metrics::EXECUTION_METRICS;
+use crate::execution::vm_wrapper::VmWrapper;
+use alloy::consensus::Transaction;
+use alloy::consensus::transaction::SignerRecoverable;
+use alloy::eips::Decodable2718;
+use alloy::primitives::{Address, B256};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use zksync_os_interface::types::BlockContext;
+use zksync_os_multivm::ExecutionScratch;
+use zksync_os_storage_api::ViewState;
+use zksync_os_types::{
+    BundleInclusion, L2Envelope, SignedTransactionBundle, ZkTransaction, ZksyncOsEncode,
+};
+
+/// Number of included / expired bundles whose status is retained for `admin_getBundleStatus`.
+const RESOLVED_BUNDLES_CAPACITY: usize = 1_024;
+
+/// Bundle accepted by [`BundleStore`], with decoded transactions.
+#[derive(Debug)]
+pub struct Bundle {
+    pub hash: B256,
+    pub transactions: Vec<ZkTransaction>,
+    pub inclusion: BundleInclusion,
+}
+
+impl Bundle {
+    /// Total gas limit of bundle transactions.
+    pub fn gas_limit(&self) -> u64 {
+        self.transactions
+            .iter()
+            .map(|tx| tx.inner.gas_limit())
+            .sum()
+    }
+}
+
+/// Result of attempting a bundle in a block.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BundleOutcome {
+    Included,
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum BundleStatus {
+    #[serde(rename_all = "camelCase")]
+    Pending {
+        attempts: u32,
+        last_failure: Option<String>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Included { block_number: u64 },
+    #[serde(rename_all = "camelCase")]
+    Expired {
+        attempts: u32,
+        last_failure: Option<String>,
+    },
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum BundleError {
+    #[error("bundle must contain 1 to {0} transactions")]
+    InvalidSize(usize),
+    #[error("invalid bundle signature")]
+    InvalidSignature,
+    #[error("{0} is not allowed to submit bundles")]
+    UnauthorizedSigner(Address),
+    #[error("failed to decode bundle transaction #{0}")]
+    InvalidTransaction(usize),
+    #[error("bundle cannot be included after block {last_block}, and block {next_block} is next")]
+    Expired { last_block: u64, next_block: u64 },
+    #[error("bundle {0} is already submitted")]
+    Duplicate(B256),
+    #[error("too many pending bundles")]
+    StoreFull,
+}
+
+#[derive(Debug, Clone)]
+pub struct BundleStoreConfig {
+    /// Addresses whose signatures are accepted on bundles.
+    pub allowed_signers: Vec<Address>,
+    pub max_pending_bundles: usize,
+    pub max_bundle_transactions: usize,
+}
+
+#[derive(Debug)]
+struct PendingBundle {
+    bundle: Arc<Bundle>,
+    attempts: u32,
+    last_failure: Option<String>,
+}
+
+impl PendingBundle {
+    fn status(&self) -> BundleStatus {
+        BundleStatus::Pending {
+            attempts: self.attempts,
+            last_failure: self.last_failure.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct StoreInner {
+    /// Pending bundles in submission order, which is also the order they are attempted in.
+    pending: Vec<PendingBundle>,
+    resolved: VecDeque<(B256, BundleStatus)>,
+    /// Block that will be produced next, as far as the store knows.
+    next_block: u64,
+}
+
+impl StoreInner {
+    fn resolve(&mut self, hash: B256, status: BundleStatus) {
+        if self.resolved.len() >= RESOLVED_BUNDLES_CAPACITY {
+            self.resolved.pop_front();
+        }
+        self.resolved.push_back((hash, status));
+    }
+}
+
+/// Pending bundles shared between the admin API (submissions) and the sequencer (inclusion).
+#[derive(Debug, Clone)]
+pub struct BundleStore {
+    config: Arc<BundleStoreConfig>,
+    inner: Arc<Mutex<StoreInner>>,
+}
+
+impl BundleStore {
+    pub fn new(config: BundleStoreConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            inner: Arc::default(),
+        }
+    }
+
+    /// Validates a bundle and adds it to the pending bundles. Returns the bundle hash.
+    pub fn submit(&self, signed: SignedTransactionBundle) -> Result<B256, BundleError> {
+        let request = &signed.bundle;
+        let max_transactions = self.config.max_bundle_transactions;
+        if request.transactions.is_empty() || request.transactions.len() > max_transactions {
+            return Err(BundleError::InvalidSize(max_transactions));
+        }
+        let signer = signed
+            .recover_signer()
+            .map_err(|_| BundleError::InvalidSignature)?;
+        if !self.config.allowed_signers.contains(&signer) {
+            return Err(BundleError::UnauthorizedSigner(signer));
+        }
+        let transactions = request
+            .transactions
+            .iter()
+            .enumerate()
+            .map(|(i, raw_tx)| {
+                let tx = L2Envelope::decode_2718(&mut raw_tx.as_ref())
+                    .map_err(|_| BundleError::InvalidTransaction(i))?;
+                let tx = tx
+                    .try_into_recovered()
+                    .map_err(|_| BundleError::InvalidTransaction(i))?;
+                Ok(ZkTransaction::from(tx))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let bundle = Bundle {
+            hash: request.hash(),
+            transactions,
+            inclusion: request.inclusion,
+        };
+        self.insert(bundle)?;
+        tracing::info!(
+            bundle = %request.hash(),
+            %signer,
+            transactions = request.transactions.len(),
+            inclusion = ?request.inclusion,
+            "bundle submitted"
+        );
+        Ok(request.hash())
+    }
+
+    fn insert(&self, bundle: Bundle) -> Result<(), BundleError> {
+        let mut inner = self.inner.lock().unwrap();
+        let last_block = bundle.inclusion.last_block();
+        if last_block < inner.next_block {
+            return Err(BundleError::Expired {
+                last_block,
+                next_block: inner.next_block,
+            });
+        }
+        let is_known = inner.pending.iter().any(|p| p.bundle.hash == bundle.hash)
+            || inner.resolved.iter().any(|(hash, _)| *hash == bundle.hash);
+        if is_known {
+            return Err(BundleError::Duplicate(bundle.hash));
+        }
+        if inner.pending.len() >= self.config.max_pending_bundles {
+            return Err(BundleError::StoreFull);
+        }
+        inner.pending.push(PendingBundle {
+            bundle: Arc::new(bundle),
+            attempts: 0,
+            last_failure: None,
+        });
+        EXECUTION_METRICS.bundles[&"submitted"].inc();
+        EXECUTION_METRICS.pending_bundles.set(inner.pending.len());
+        Ok(())
+    }
+
+    /// Expires bundles that can no longer be included starting from `block_number` and returns
+    /// bundles that may be included in it, in submission order.
+    pub fn eligible(&self, block_number: u64) -> Vec<Arc<Bundle>> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.next_block = block_number;
+        let (expired, pending) = std::mem::take(&mut inner.pending)
+            .into_iter()
+            .partition::<Vec<_>, _>(|p| p.bundle.inclusion.last_block() < block_number);
+        inner.pending = pending;
+        for expired in expired {
+            tracing::info!(
+                bundle = %expired.bundle.hash,
+                attempts = expired.attempts,
+                last_failure = ?expired.last_failure,
+                "bundle expired"
+            );
+            EXECUTION_METRICS.bundles[&"expired"].inc();
+            let status = BundleStatus::Expired {
+                attempts: expired.attempts,
+                last_failure: expired.last_failure,
+            };
+            inner.resolve(expired.bundle.hash, status);
+        }
+        EXECUTION_METRICS.pending_bundles.set(inner.pending.len());
+        inner
+            .pending
+            .iter()
+            .filter(|p| p.bundle.inclusion.allows(block_number))
+            .map(|p| p.bundle.clone())
+            .collect()
+    }
+
+    /// Records outcomes of bundles attempted in the block `block_number`, which is now canonical.
+    pub fn on_block_executed(&self, block_number: u64, outcomes: Vec<(B256, BundleOutcome)>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.next_block = inner.next_block.max(block_number + 1);
+        for (hash, outcome) in outcomes {
+            let Some(index) = inner.pending.iter().position(|p| p.bundle.hash == hash) else {
+                continue;
+            };
+            match outcome {
+                BundleOutcome::Included => {
+                    inner.pending.remove(index);
+                    EXECUTION_METRICS.bundles[&"included"].inc();
+                    inner.resolve(hash, BundleStatus::Included { block_number });
+                }
+                BundleOutcome::Failed(reason) => {
+                    let pending = &mut inner.pending[index];
+                    pending.attempts += 1;
+                    pending.last_failure = Some(reason);
+                    EXECUTION_METRICS.bundles[&"failed"].inc();
+                }
+            }
+        }
+        EXECUTION_METRICS.pending_bundles.set(inner.pending.len());
+    }
+
+    pub fn status(&self, hash: B256) -> Option<BundleStatus> {
+        let inner = self.inner.lock().unwrap();
+        if let Some(pending) = inner.pending.iter().find(|p| p.bundle.hash == hash) {
+            return Some(pending.status());
+        }
+        inner
+            .resolved
+            .iter()
+            .find(|(resolved_hash, _)| *resolved_hash == hash)
+            .map(|(_, status)| status.clone())
+    }
+}
+
+/// Simulates `bundle` on top of `in_block_txs` (transactions already included in the block being
+/// built) in a separate VM instance, so that a failing bundle leaves no trace in the real block.
+///
+/// Returns the reason if any bundle transaction is rejected by the VM or reverts.
+pub(crate) async fn simulate_bundle(
+    block_context: BlockContext,
+    state_view: impl ViewState + 'static,
+    scratch: &ExecutionScratch,
+    in_block_txs: &[ZkTransaction],
+    bundle: &Bundle,
+) -> anyhow::Result<Result<(), String>> {
+    let mut vm = VmWrapper::new(block_context, state_view, scratch);
+    for tx in in_block_txs {
+        if let Err(err) = vm.execute_next_tx(tx.clone().encode()).await? {
+            anyhow::bail!(
+                "transaction {} already in the block is rejected in simulation: {err:?}",
+                tx.hash()
+            );
+        }
+    }
+    let mut failure = None;
+    for tx in &bundle.transactions {
+        match vm.execute_next_tx(tx.clone().encode()).await? {
+            Ok(output) if output.status => {}
+            Ok(_) => failure = Some(format!("transaction {} reverted", tx.hash())),
+            Err(err) => failure = Some(format!("transaction {} is invalid: {err:?}", tx.hash())),
+        }
+        if failure.is_some() {
+            break;
+        }
+    }
+    vm.seal_block().await?;
+    Ok(failure.map_or(Ok(()), Err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::consensus::{SignableTransaction, TxLegacy};
+    use alloy::eips::Encodable2718;
+    use alloy::primitives::{Bytes, TxKind, U256};
+    use alloy::signers::SignerSync;
+    use alloy::signers::local::PrivateKeySigner;
+    use zksync_os_types::TransactionBundle;
+
+    fn raw_transfer(signer: &PrivateKeySigner, nonce: u64) -> Bytes {
+        let tx = TxLegacy {
+            chain_id: Some(270),
+            nonce,
+            gas_price: 1_000_000_000,
+            gas_limit: 21_000,
+            to: TxKind::Call(Address::repeat_byte(1)),
+            value: U256::from(1),
+            input: Bytes::new(),
+        };
+        let signature = signer.sign_hash_sync(&tx.signature_hash()).unwrap();
+        L2Envelope::from(tx.into_signed(signature))
+            .encoded_2718()
+            .into()
+    }
+
+    fn signed_bundle(
+        bundle_signer: &PrivateKeySigner,
+        tx_nonces: &[u64],
+        inclusion: BundleInclusion,
+    ) -> SignedTransactionBundle {
+        let tx_signer = PrivateKeySigner::random();
+        let bundle = TransactionBundle {
+            transactions: tx_nonces
+                .iter()
+                .map(|&nonce| raw_transfer(&tx_signer, nonce))
+                .collect(),
+            inclusion,
+        };
+        let signature = bundle_signer.sign_hash_sync(&bundle.hash()).unwrap();
+        SignedTransactionBundle { bundle, signature }
+    }
+
+    fn store(bundle_signer: &PrivateKeySigner) -> BundleStore {
+        BundleStore::new(BundleStoreConfig {
+            allowed_signers: vec![bundle_signer.address()],
+            max_pending_bundles: 2,
+            max_bundle_transactions: 3,
+        })
+    }
+
+    #[test]
+    fn submission_is_validated() {
+        let signer = PrivateKeySigner::random();
+        let store = store(&signer);
+        let inclusion = BundleInclusion::ExpiryBlock(10);
+
+        let err = store
+            .submit(signed_bundle(&PrivateKeySigner::random(), &[0], inclusion))
+            .unwrap_err();
+        assert!(matches!(err, BundleError::UnauthorizedSigner(_)), "{err}");
+        let err = store
+            .submit(signed_bundle(&signer, &[0, 1, 2, 3], inclusion))
+            .unwrap_err();
+        assert!(matches!(err, BundleError::InvalidSize(3)), "{err}");
+        let mut tampered = signed_bundle(&signer, &[0, 1], inclusion);
+        tampered.bundle.transactions.pop();
+        let err = store.submit(tampered).unwrap_err();
+        assert!(matches!(err, BundleError::UnauthorizedSigner(_)), "{err}");
+
+        let bundle = signed_bundle(&signer, &[0, 1], inclusion);
+        let hash = store.submit(bundle.clone()).unwrap();
+        assert_eq!(hash, bundle.bundle.hash());
+        let err = store.submit(bundle).unwrap_err();
+        assert!(matches!(err, BundleError::Duplicate(_)), "{err}");
+        store
+            .submit(signed_bundle(&signer, &[0], inclusion))
+            .unwrap();
+        let err = store
+            .submit(signed_bundle(&signer, &[0], inclusion))
+            .unwrap_err();
+        assert!(matches!(err, BundleError::StoreFull), "{err}");
+
+        let eligible = store.eligible(1);
+        assert_eq!(eligible.len(), 2);
+        assert_eq!(eligible[0].hash, hash);
+        assert_eq!(eligible[0].transactions.len(), 2);
+    }
+
+    #[test]
+    fn failed_bundles_are_retried_until_expiry() {
+        let signer = PrivateKeySigner::random();
+        let store = store(&signer);
+        let targeted = store
+            .submit(signed_bundle(
+                &signer,
+                &[0],
+                BundleInclusion::TargetBlock(6),
+            ))
+            .unwrap();
+        let expiring = store
+            .submit(signed_bundle(
+                &signer,
+                &[0],
+                BundleInclusion::ExpiryBlock(6),
+            ))
+            .unwrap();
+
+        let hashes = |bundles: Vec<Arc<Bundle>>| bundles.iter().map(|b| b.hash).collect::<Vec<_>>();
+        assert_eq!(hashes(store.eligible(5)), [expiring]);
+        store.on_block_executed(
+            5,
+            vec![(expiring, BundleOutcome::Failed("reverted".to_owned()))],
+        );
+        assert_eq!(
+            store.status(expiring),
+            Some(BundleStatus::Pending {
+                attempts: 1,
+                last_failure: Some("reverted".to_owned()),
+            })
+        );
+
+        // Retried in the next block, together with the bundle targeting it
+        assert_eq!(hashes(store.eligible(6)), [targeted, expiring]);
+        store.on_block_executed(
+            6,
+            vec![
+                (targeted, BundleOutcome::Included),
+                (expiring, BundleOutcome::Failed("reverted again".to_owned())),
+            ],
+        );
+        assert_eq!(
+            store.status(targeted),
+            Some(BundleStatus::Included { block_number: 6 })
+        );
+
+        assert!(store.eligible(7).is_empty());
+        assert_eq!(
+            store.status(expiring),
+            Some(BundleStatus::Expired {
+                attempts: 2,
+                last_failure: Some("reverted again".to_owned()),
+            })
+        );
+        // Bundles that can no longer be included are rejected right away
+        let err = store
+            .submit(signed_bundle(
+                &signer,
+                &[0],
+                BundleInclusion::TargetBlock(6),
+            ))
+            .unwrap_err();
+        assert!(matches!(err, BundleError::Expired { .. }), "{err}");
+    }
+}
@@ -0,0 +1,204 @@
+//! Quarantine of external nodes whose replayed block output diverges from the replay stream.
+//!
+//! Instead of crashing, the sequencer stops applying blocks at the diverged block, writes a block
+//! dump with the divergence evidence and waits for the operator to resume replay via the admin API.
+//! Nothing of the diverged block is persisted before that, so the node keeps serving its state at
+//! the last good block.
+
+use alloy::primitives::{Address, B256};
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use zksync_os_interface::traits::ReadStorage;
+use zksync_os_interface::types::{BlockOutput, StorageWrite};
+use zksync_os_types::{ReplayDivergence, ReplayDivergenceStatus};
+
+/// Evidence of a replay divergence stored in block dumps: the expected output hash from the replay
+/// record and the parts of the locally computed output that the output hash is derived from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputDivergence {
+    pub expected_output_hash: B256,
+    pub computed_output_hash: B256,
+    pub computed_block_hash: B256,
+    /// Results of the executed transactions, in block order.
+    pub tx_results: Vec<TxResultSummary>,
+    /// Storage writes of the computed output, diffed against the state before the block.
+    pub storage_writes: Vec<StorageWriteDiff>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxResultSummary {
+    pub success: bool,
+    pub gas_used: u64,
+}
+
+/// Storage write of the diverged block along with the value of the slot before the block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageWriteDiff {
+    pub key: B256,
+    pub account: Address,
+    pub account_key: B256,
+    /// `None` if the slot was not initialized before the block.
+    pub previous_value: Option<B256>,
+    pub computed_value: B256,
+}
+
+impl OutputDivergence {
+    /// Collects the evidence; `state_before` must be the state view the block was executed on.
+    pub fn new(
+        expected_output_hash: B256,
+        computed_output_hash: B256,
+        output: &BlockOutput,
+        state_before: impl ReadStorage,
+    ) -> Self {
+        Self {
+            expected_output_hash,
+            computed_output_hash,
+            computed_block_hash: output.header.hash(),
+            tx_results: output
+                .tx_results
+                .iter()
+                .flatten()
+                .map(|tx| TxResultSummary {
+                    success: tx.is_success(),
+                    gas_used: tx.gas_used,
+                })
+                .collect(),
+            storage_writes: diff_storage_writes(&output.storage_writes, state_before),
+        }
+    }
+}
+
+/// Diffs `writes` against `state_before`; writes that don't change the slot value are kept as well,
+/// since they contribute to the block output hash.
+pub fn diff_storage_writes(
+    writes: &[StorageWrite],
+    mut state_before: impl ReadStorage,
+) -> Vec<StorageWriteDiff> {
+    writes
+        .iter()
+        .map(|write| StorageWriteDiff {
+            key: write.key,
+            account: write.account,
+            account_key: write.account_key,
+            previous_value: state_before.read(write.key),
+            computed_value: write.value,
+        })
+        .collect()
+}
+
+/// Publishes `divergence` and waits until the operator resumes replay. Never returns if the
+/// operator keeps replay halted.
+pub(crate) async fn wait_for_resume(
+    status: &watch::Sender<ReplayDivergenceStatus>,
+    divergence: ReplayDivergence,
+) -> anyhow::Result<()> {
+    let mut receiver = status.subscribe();
+    let block_number = divergence.block_number;
+    status.send_replace(ReplayDivergenceStatus::Diverged(divergence));
+    tracing::error!(
+        block_number,
+        "replay halted at a diverged block; inspect the block dump and resolve via \
+         `admin_resolveReplayDivergence({block_number}, \"resume\" | \"keepHalted\")`"
+    );
+    receiver
+        .wait_for(|status| {
+            matches!(
+                status,
+                ReplayDivergenceStatus::Resumed(resumed) if resumed.block_number == block_number
+            )
+        })
+        .await
+        .context("replay divergence status channel closed")?;
+    tracing::warn!(
+        block_number,
+        "replay divergence acknowledged as benign, applying the locally computed output"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    #[derive(Debug, Clone, Default)]
+    struct MapStorage(HashMap<B256, B256>);
+
+    impl ReadStorage for MapStorage {
+        fn read(&mut self, key: B256) -> Option<B256> {
+            self.0.get(&key).copied()
+        }
+    }
+
+    fn write(key: u8, value: u8) -> StorageWrite {
+        StorageWrite {
+            key: B256::repeat_byte(key),
+            value: B256::repeat_byte(value),
+            account: Address::repeat_byte(key),
+            account_key: B256::with_last_byte(key),
+        }
+    }
+
+    fn divergence(block_number: u64) -> ReplayDivergence {
+        ReplayDivergence {
+            block_number,
+            expected_output_hash: B256::repeat_byte(1),
+            computed_output_hash: B256::repeat_byte(2),
+            dump_path: None,
+        }
+    }
+
+    #[test]
+    fn storage_writes_are_diffed_against_previous_state() {
+        let state = MapStorage(HashMap::from([(
+            B256::repeat_byte(1),
+            B256::repeat_byte(5),
+        )]));
+        let diff = diff_storage_writes(&[write(1, 6), write(2, 7)], state);
+        assert_eq!(
+            diff,
+            [
+                StorageWriteDiff {
+                    key: B256::repeat_byte(1),
+                    account: Address::repeat_byte(1),
+                    account_key: B256::with_last_byte(1),
+                    previous_value: Some(B256::repeat_byte(5)),
+                    computed_value: B256::repeat_byte(6),
+                },
+                StorageWriteDiff {
+                    key: B256::repeat_byte(2),
+                    account: Address::repeat_byte(2),
+                    account_key: B256::with_last_byte(2),
+                    previous_value: None,
+                    computed_value: B256::repeat_byte(7),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn replay_is_halted_until_resumed() {
+        let (status, _) = watch::channel(ReplayDivergenceStatus::None);
+        let wait = tokio::spawn({
+            let status = status.clone();
+            async move { wait_for_resume(&status, divergence(5)).await }
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert_eq!(status.borrow().halted_at(), Some(&divergence(5)));
+
+        // Keeping replay halted doesn't resume it; neither does resuming another block
+        status.send_replace(ReplayDivergenceStatus::Halted(divergence(5)));
+        status.send_replace(ReplayDivergenceStatus::Resumed(divergence(4)));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(!wait.is_finished());
+
+        status.send_replace(ReplayDivergenceStatus::Resumed(divergence(5)));
+        tokio::time::timeout(Duration::from_secs(1), wait)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+}
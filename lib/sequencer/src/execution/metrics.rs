@@ -1,14 +1,16 @@
 use crate::execution::block_executor::SealReason;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use vise::{Buckets, Gauge, Histogram, LabeledFamily, Metrics, Unit};
 use vise::{Counter, EncodeLabelValue};
+use zksync_os_observability::exemplars::{ExemplarLabels, HistogramExemplars};
 use zksync_os_observability::{GenericComponentState, StateLabel};
-use zksync_os_storage_api::StateAccessLabel;
+use zksync_os_storage_api::{BlockStats, StateAccessLabel};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EncodeLabelValue)]
 #[metrics(label = "state", rename_all = "snake_case")]
 pub enum SequencerState {
     ConfiguredBlockLimitReached,
+    QuarantinedOnDivergence,
 
     WaitingForCommand,
 
@@ -29,9 +31,10 @@ pub enum SequencerState {
 impl StateLabel for SequencerState {
     fn generic(&self) -> GenericComponentState {
         match self {
-            Self::WaitingForCommand | Self::WaitingForTx | Self::ConfiguredBlockLimitReached => {
-                GenericComponentState::WaitingRecv
-            }
+            Self::WaitingForCommand
+            | Self::WaitingForTx
+            | Self::ConfiguredBlockLimitReached
+            | Self::QuarantinedOnDivergence => GenericComponentState::WaitingRecv,
             Self::WaitingSend => GenericComponentState::WaitingSend,
             _ => GenericComponentState::Processing,
         }
@@ -39,6 +42,7 @@ impl StateLabel for SequencerState {
     fn specific(&self) -> &'static str {
         match self {
             SequencerState::ConfiguredBlockLimitReached => "configured_limit_reached",
+            SequencerState::QuarantinedOnDivergence => "quarantined_on_divergence",
             SequencerState::WaitingForCommand => "waiting_for_command",
             SequencerState::WaitingForTx => "waiting_for_tx",
             SequencerState::Execution => "execution",
@@ -80,6 +84,10 @@ pub struct ExecutionMetrics {
     #[metrics(unit = Unit::Seconds, labels = ["measure"], buckets = Buckets::exponential(0.0000001..=1.0, 2.0))]
     pub tx_execution: LabeledFamily<&'static str, Histogram<Duration>>,
 
+    /// Time spent on each stage of processing a block (`execute`, `replay_storage`, `state`, `repos`, `mempool`).
+    #[metrics(unit = Unit::Seconds, labels = ["stage"], buckets = Buckets::LATENCIES)]
+    pub block_stage_latency: LabeledFamily<&'static str, Histogram<Duration>>,
+
     #[metrics(buckets = Buckets::exponential(1.0..=10_000.0, 2.0))]
     pub transactions_per_block: Histogram<u64>,
 
@@ -114,8 +122,91 @@ pub struct ExecutionMetrics {
 
     pub next_l1_priority_id: Gauge<u64>,
 
+    /// Produced blocks that had to include priority transactions close to expiring on L1.
+    pub forced_l1_inclusion_blocks: Counter,
+
+    /// 1 if the base token rate used by the last produced block was stale (prices were not adjusted).
+    pub base_token_rate_stale: Gauge<u64>,
+
     pub last_execution_version: Gauge<u64>,
+
+    /// Replayed blocks whose output diverged from the replay record.
+    pub replay_divergences: Counter,
+
+    /// Blocks handled by shadow execution, by outcome (`matched`, `diverged`, `failed`, `dropped`).
+    #[metrics(labels = ["outcome"])]
+    pub shadow_execution_blocks: LabeledFamily<&'static str, Counter>,
+
+    /// Lookups of the warm-up cache during real execution, by kind (`storage_hit`, `preimage_miss` etc.).
+    #[metrics(labels = ["kind"])]
+    pub warm_up_cache: LabeledFamily<&'static str, Counter>,
+
+    /// Transactions handled by the speculative warm-up, by outcome.
+    #[metrics(labels = ["outcome"])]
+    pub warm_up_txs: LabeledFamily<&'static str, Counter>,
+
+    #[metrics(buckets = Buckets::exponential(1.0..=1_000.0, 1.7))]
+    pub unique_storage_keys_per_block: Histogram<u64>,
+
+    #[metrics(buckets = Buckets::exponential(1.0..=1_000.0, 1.7))]
+    pub preimages_per_block: Histogram<u64>,
+
+    #[metrics(buckets = Buckets::exponential(1_000.0..=10_000_000.0, 4.0))]
+    pub preimage_bytes_per_block: Histogram<u64>,
+
+    #[metrics(buckets = Buckets::exponential(1.0..=1_000.0, 1.7))]
+    pub account_diffs_per_block: Histogram<u64>,
+
+    #[metrics(buckets = Buckets::exponential(1.0..=1_000.0, 1.7))]
+    pub l2_to_l1_logs_per_block: Histogram<u64>,
+
+    /// Average share of the limit used by recent blocks, by resource (`gas`, `pubdata`).
+    #[metrics(labels = ["resource"])]
+    pub block_utilization: LabeledFamily<&'static str, Gauge<f64>>,
+
+    /// Transaction bundles by event (`submitted`, `included`, `failed` per attempt, `expired`).
+    #[metrics(labels = ["event"])]
+    pub bundles: LabeledFamily<&'static str, Counter>,
+
+    /// Bundles waiting for inclusion.
+    pub pending_bundles: Gauge<usize>,
+}
+
+impl ExecutionMetrics {
+    pub fn report_block_stats(&self, stats: &BlockStats) {
+        self.transactions_per_block.observe(stats.tx_count);
+        self.gas_per_block.observe(stats.gas_used);
+        self.computational_native_used_per_block
+            .observe(stats.computational_native_used);
+        self.pubdata_per_block.observe(stats.pubdata_bytes);
+        self.storage_writes_per_block.observe(stats.storage_writes);
+        self.unique_storage_keys_per_block
+            .observe(stats.unique_storage_keys);
+        self.preimages_per_block.observe(stats.new_preimages);
+        self.preimage_bytes_per_block.observe(stats.preimage_bytes);
+        self.account_diffs_per_block.observe(stats.account_diffs);
+        self.l2_to_l1_logs_per_block.observe(stats.l2_to_l1_logs);
+    }
 }
 
 #[vise::register]
 pub(crate) static EXECUTION_METRICS: vise::Global<ExecutionMetrics> = vise::Global::new();
+
+static BLOCK_STAGE_EXEMPLARS: HistogramExemplars =
+    HistogramExemplars::new("execution_block_stage_latency_seconds");
+
+/// Observes the time spent on `stage` of processing a block since `started_at`, and restarts
+/// `started_at` for the next stage.
+pub(crate) fn observe_block_stage(
+    stage: &'static str,
+    block_number: u64,
+    started_at: &mut Instant,
+) {
+    BLOCK_STAGE_EXEMPLARS.observe(
+        &EXECUTION_METRICS.block_stage_latency[&stage],
+        &[("stage", stage)],
+        started_at.elapsed(),
+        ExemplarLabels::block(block_number),
+    );
+    *started_at = Instant::now();
+}
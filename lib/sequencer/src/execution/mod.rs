@@ -1,26 +1,47 @@
 use crate::config::SequencerConfig;
 use crate::execution::block_context_provider::BlockContextProvider;
 use crate::execution::block_executor::execute_block;
-use crate::execution::metrics::{EXECUTION_METRICS, SequencerState};
-use crate::execution::utils::save_dump;
-use crate::model::blocks::BlockCommand;
+use crate::execution::block_trace::{BlockTrace, BlockTraceRecorder, TracedTxSource, save_trace};
+use crate::execution::divergence::{OutputDivergence, wait_for_resume};
+use crate::execution::metrics::{EXECUTION_METRICS, SequencerState, observe_block_stage};
+use crate::execution::utilization::RollingUtilization;
+use crate::execution::utils::{BlockDump, StoredBlockDump, save_dump};
+use crate::execution::warm_up::{WarmStorageCache, warm_up};
+use crate::model::blocks::{BlockCommand, BlockCommandType};
+use alloy::primitives::B256;
 use anyhow::Context;
 use async_trait::async_trait;
+use std::path::PathBuf;
+use std::time::Instant;
 use tokio::sync::{mpsc::Sender, watch};
-use zksync_os_interface::types::BlockOutput;
+use zksync_os_error_reporting::WithErrorCode;
+use zksync_os_error_reporting::codes::sequencer as error_codes;
+use zksync_os_gas_adjuster::GasAdjusterSnapshot;
+use zksync_os_interface::types::{BlockContext, BlockOutput};
 use zksync_os_mempool::L2TransactionPool;
+use zksync_os_multivm::ExecutionScratch;
 use zksync_os_observability::{ComponentStateHandle, ComponentStateReporter};
 use zksync_os_pipeline::{PeekableReceiver, PipelineComponent};
 use zksync_os_storage_api::{
-    ReadStateHistory, ReplayRecord, WriteReplay, WriteRepository, WriteState,
+    BlockStats, ReadStateHistory, ReplayRecord, WriteReplay, WriteRepository, WriteState,
+};
+use zksync_os_types::{
+    L1PricePrediction, NotAcceptingReason, ReplayDivergence, ReplayDivergenceStatus,
+    TransactionAcceptanceState, ZkTransaction,
 };
-use zksync_os_types::{NotAcceptingReason, TransactionAcceptanceState};
 
 pub mod block_context_provider;
 pub mod block_executor;
+pub mod block_trace;
+pub mod bundles;
+pub mod divergence;
 pub(crate) mod metrics;
-pub(crate) mod utils;
+mod priority_inclusion;
+pub mod shadow;
+mod utilization;
+pub mod utils;
 pub mod vm_wrapper;
+pub mod warm_up;
 
 /// Sequencer pipeline component
 /// Contains all the dependencies needed to run the sequencer
@@ -39,6 +60,17 @@ where
     /// Controls transaction acceptance state.
     /// When max_blocks_to_produce limit is reached, sequencer sends NotAccepting to stop RPC from accepting new txs.
     pub tx_acceptance_state_sender: watch::Sender<TransactionAcceptanceState>,
+    /// Graceful shutdown signal. Once it's set, the sequencer takes no more commands: the block
+    /// being processed is still persisted and sent downstream, then transaction acceptance is
+    /// turned off and the component exits.
+    pub stop_receiver: watch::Receiver<bool>,
+    /// Enables replay divergence quarantine (external nodes only): when set, a replayed block whose
+    /// output differs from the replay record halts the sequencer until the operator resumes it,
+    /// instead of failing it.
+    pub replay_divergence: Option<watch::Sender<ReplayDivergenceStatus>>,
+    /// L1 prices predicted by the gas adjuster (main node only); recorded in [`BlockStats`] of
+    /// produced blocks, so that they can be compared with the prices paid on L1.
+    pub l1_price_predictions: Option<watch::Receiver<GasAdjusterSnapshot>>,
 }
 
 #[async_trait]
@@ -50,7 +82,7 @@ where
     Repo: WriteRepository + Send + 'static,
 {
     type Input = BlockCommand;
-    type Output = (BlockOutput, ReplayRecord);
+    type Output = (BlockOutput, ReplayRecord, BlockStats);
 
     const NAME: &'static str = "sequencer";
     const OUTPUT_BUFFER_SIZE: usize = 5;
@@ -58,7 +90,7 @@ where
     async fn run(
         mut self,
         mut input: PeekableReceiver<Self::Input>, // PeekableReceiver<BlockCommand>
-        output: Sender<Self::Output>,             // Sender<BlockOutput>
+        output: Sender<Self::Output>,             // Sender<(BlockOutput, ReplayRecord, BlockStats)>
     ) -> anyhow::Result<()> {
         let latency_tracker = ComponentStateReporter::global()
             .handle_for("sequencer", SequencerState::WaitingForCommand);
@@ -66,11 +98,57 @@ where
         // Track how many Produce commands we've processed (for `sequencer_max_blocks_to_produce` config)
         let mut produced_blocks_count = 0u64;
 
+        let warm_cache = (self.sequencer_config.is_main_node()
+            && self.sequencer_config.warm_up_max_txs > 0)
+            .then(WarmStorageCache::default);
+        if let Some(cache) = &warm_cache {
+            cache.reset(*self.state.block_range_available().end());
+        }
+        let mut utilization =
+            RollingUtilization::new(self.sequencer_config.block_pubdata_limit_bytes);
+        // Buffers reused by consecutive block executions
+        let scratch = ExecutionScratch::default();
+        let mut stop_receiver = self.stop_receiver.clone();
+
         loop {
             latency_tracker.enter_state(SequencerState::WaitingForCommand);
 
-            let Some(cmd) = input.recv().await else {
-                anyhow::bail!("inbound channel closed");
+            let warm_up_candidates = warm_cache
+                .as_ref()
+                .and_then(|cache| Some((cache, self.warm_up_candidates()?)));
+            let next_command = async {
+                match warm_up_candidates {
+                    Some((cache, (block_context, txs))) => {
+                        let warm_up = warm_up(
+                            cache,
+                            &self.state,
+                            block_context,
+                            txs,
+                            self.sequencer_config.warm_up_concurrency,
+                        );
+                        // Warm-up is cancelled as soon as the next command arrives.
+                        tokio::select! {
+                            cmd = input.recv() => cmd,
+                            _ = async {
+                                warm_up.await;
+                                std::future::pending::<()>().await
+                            } => unreachable!(),
+                        }
+                    }
+                    None => input.recv().await,
+                }
+            };
+            // The stop signal is only checked between blocks, so that a block is never left
+            // partially persisted. It takes precedence over already queued commands.
+            let cmd = tokio::select! {
+                biased;
+                Ok(_) = stop_receiver.wait_for(|stop| *stop) => break,
+                cmd = next_command => cmd,
+            };
+            let Some(cmd) = cmd else {
+                return Err(
+                    anyhow::anyhow!("inbound channel closed").with_code(&error_codes::FAILED)
+                );
             };
             let block_number = cmd.block_number();
             let cmd_type = cmd.command_type();
@@ -101,7 +179,35 @@ where
             );
             latency_tracker.enter_state(SequencerState::BlockContextTxs);
 
-            let prepared_command = self.block_context_provider.prepare_command(cmd).await?;
+            let l1_price_prediction = self
+                .l1_price_predictions
+                .as_ref()
+                .filter(|_| matches!(cmd, BlockCommand::Produce(_)))
+                .map(|snapshot| {
+                    let snapshot = snapshot.borrow();
+                    L1PricePrediction {
+                        gas_price: snapshot.gas_price,
+                        pubdata_price: snapshot.pubdata_price,
+                    }
+                });
+            let mut prepared_command = self
+                .block_context_provider
+                .prepare_command(cmd)
+                .await
+                .with_code(&error_codes::COMMAND_PREPARATION)?;
+            if matches!(cmd_type, BlockCommandType::Produce)
+                && self.sequencer_config.block_trace_path.is_some()
+            {
+                let recorder = BlockTraceRecorder::new(&prepared_command.block_context);
+                prepared_command.tx_source = Box::pin(TracedTxSource::new(
+                    prepared_command.tx_source,
+                    recorder.clone(),
+                ));
+                prepared_command.trace = recorder;
+            }
+            let trace = prepared_command.trace.clone();
+            let expected_block_output_hash = prepared_command.expected_block_output_hash;
+            let mut stage_started_at = Instant::now();
 
             tracing::debug!(
                 block_number,
@@ -109,25 +215,59 @@ where
                 "Prepared command. Executing..",
             );
 
-            let (block_output, replay_record, purged_txs) =
-                execute_block(prepared_command, self.state.clone(), &latency_tracker)
-                    .await
-                    .map_err(|dump| {
-                        let error = anyhow::anyhow!("{}", dump.error);
-                        tracing::info!("Saving dump..");
-                        if let Err(err) =
-                            save_dump(self.sequencer_config.block_dump_path.clone(), dump)
-                        {
-                            tracing::error!(?err, "Failed to write block dump");
-                        }
-                        error
-                    })
-                    .context("execute_block")?;
+            let (block_output, replay_record, stats, purged_txs, bundle_outcomes) = execute_block(
+                prepared_command,
+                self.state.clone(),
+                warm_cache.clone(),
+                &scratch,
+                &latency_tracker,
+            )
+            .await
+            .map_err(|dump| {
+                let error =
+                    anyhow::anyhow!("{}", dump.error).with_code(&error_codes::BLOCK_EXECUTION);
+                self.save_dump(StoredBlockDump::new(
+                    dump,
+                    self.sequencer_config.dump_detail_level,
+                ));
+                error
+            })
+            .context("execute_block")?;
+            if let Some(expected_hash) = expected_block_output_hash
+                && expected_hash != replay_record.block_output_hash
+            {
+                self.handle_divergence(
+                    expected_hash,
+                    &block_output,
+                    &replay_record,
+                    &latency_tracker,
+                )
+                .await
+                .context("execute_block")?;
+            }
+            if let Some(trace) = trace.finish() {
+                self.save_trace(&trace);
+            }
+            observe_block_stage("execute", block_number, &mut stage_started_at);
+            let stats = BlockStats {
+                l1_price_prediction,
+                ..stats
+            };
 
-            tracing::debug!(block_number, "Executed. Adding to block replay storage...");
+            let (rolling_gas_utilization, rolling_pubdata_utilization) = utilization.push(&stats);
+            tracing::debug!(
+                block_number,
+                gas_utilization = stats.gas_utilization(),
+                pubdata_utilization =
+                    stats.pubdata_utilization(self.sequencer_config.block_pubdata_limit_bytes),
+                rolling_gas_utilization,
+                rolling_pubdata_utilization,
+                "Executed. Adding to block replay storage...",
+            );
             latency_tracker.enter_state(SequencerState::AddingToReplayStorage);
 
             self.replay.write(replay_record.clone(), override_allowed);
+            observe_block_stage("replay_storage", block_number, &mut stage_started_at);
 
             tracing::debug!(block_number, "Added to replay storage. Adding to state...");
             latency_tracker.enter_state(SequencerState::AddingToState);
@@ -136,15 +276,21 @@ where
             // for FullDiffs state backend it requires iterating over each storage write which is costly.
             // Therefore, we pass the override_allowed flag here. If it's set to true then override happens, otherwise,
             // changes are validated against existing storage.
-            self.state.add_block_result(
-                block_number,
-                block_output.storage_writes.clone(),
-                block_output
-                    .published_preimages
-                    .iter()
-                    .map(|(k, v)| (*k, v)),
-                override_allowed,
-            )?;
+            self.state
+                .add_block_result(
+                    block_number,
+                    block_output.storage_writes.clone(),
+                    block_output
+                        .published_preimages
+                        .iter()
+                        .map(|(k, v)| (*k, v)),
+                    override_allowed,
+                )
+                .with_code(&error_codes::PERSISTENCE)?;
+            if let Some(cache) = &warm_cache {
+                cache.reset(block_number);
+            }
+            observe_block_stage("state", block_number, &mut stage_started_at);
 
             tracing::debug!(block_number, "Added to state. Adding to repos...");
             latency_tracker.enter_state(SequencerState::AddingToRepos);
@@ -152,7 +298,9 @@ where
             // todo: do not call if api is not enabled.
             self.repositories
                 .populate(block_output.clone(), replay_record.transactions.clone())
-                .await?;
+                .await
+                .with_code(&error_codes::PERSISTENCE)?;
+            observe_block_stage("repos", block_number, &mut stage_started_at);
 
             tracing::debug!(block_number, "Added to repos. Updating mempools...",);
             latency_tracker.enter_state(SequencerState::UpdatingMempool);
@@ -163,6 +311,9 @@ where
                 .await;
             let purged_txs_hashes = purged_txs.into_iter().map(|(hash, _)| hash).collect();
             self.block_context_provider.remove_txs(purged_txs_hashes);
+            self.block_context_provider
+                .on_bundles_attempted(block_number, bundle_outcomes);
+            observe_block_stage("mempool", block_number, &mut stage_started_at);
 
             tracing::debug!(
                 block_number,
@@ -175,15 +326,115 @@ where
 
             latency_tracker.enter_state(SequencerState::WaitingSend);
             if output
-                .send((block_output.clone(), replay_record.clone()))
+                .send((block_output.clone(), replay_record.clone(), stats))
                 .await
                 .is_err()
             {
-                anyhow::bail!("Outbound channel closed");
+                return Err(
+                    anyhow::anyhow!("Outbound channel closed").with_code(&error_codes::FAILED)
+                );
             }
 
             tracing::debug!(block_number, "Block fully processed");
         }
+
+        tracing::info!("Stop signal received, sequencer stopped");
+        self.tx_acceptance_state_sender
+            .send_replace(TransactionAcceptanceState::NotAccepting(
+                NotAcceptingReason::ShuttingDown,
+            ));
+        Ok(())
+    }
+}
+
+impl<Mempool, State, Replay, Repo> Sequencer<Mempool, State, Replay, Repo>
+where
+    Mempool: L2TransactionPool + Send + 'static,
+    State: ReadStateHistory + WriteState + Clone + Send + 'static,
+    Replay: WriteReplay + Send + 'static,
+    Repo: WriteRepository + Send + 'static,
+{
+    /// Handles a replayed block whose output differs from the one in the replay record.
+    /// Without quarantine, saves a block dump and fails. With quarantine, saves a block dump with
+    /// the divergence evidence and returns once the operator resumes replay; nothing of the block
+    /// is persisted until then.
+    async fn handle_divergence(
+        &self,
+        expected_hash: B256,
+        output: &BlockOutput,
+        replay_record: &ReplayRecord,
+        latency_tracker: &ComponentStateHandle<SequencerState>,
+    ) -> anyhow::Result<()> {
+        let ctx = replay_record.block_context;
+        let computed_hash = replay_record.block_output_hash;
+        tracing::error!(?output, block_number = ctx.block_number, expected = %expected_hash, actual = %computed_hash, "Block output hash mismatch");
+        EXECUTION_METRICS.replay_divergences.inc();
+        let error = format!(
+            "Block #{} output hash mismatch: expected {expected_hash}, got {computed_hash}",
+            ctx.block_number,
+        );
+        let dump = StoredBlockDump::new(
+            BlockDump {
+                ctx,
+                txs: replay_record.transactions.clone(),
+                error: error.clone(),
+            },
+            self.sequencer_config.dump_detail_level,
+        );
+        let Some(status) = &self.replay_divergence else {
+            self.save_dump(dump);
+            return Err(anyhow::anyhow!(error).with_code(&error_codes::REPLAY_DIVERGENCE));
+        };
+
+        latency_tracker.enter_state(SequencerState::QuarantinedOnDivergence);
+        let state_before = self.state.state_view_at(ctx.block_number - 1)?;
+        let evidence = OutputDivergence::new(expected_hash, computed_hash, output, state_before);
+        let dump_path = self.save_dump(dump.with_divergence(evidence));
+        let divergence = ReplayDivergence {
+            block_number: ctx.block_number,
+            expected_output_hash: expected_hash,
+            computed_output_hash: computed_hash,
+            dump_path,
+        };
+        wait_for_resume(status, divergence).await
+    }
+
+    /// Saves a block dump, logging failures. Returns the path of the dump file if it was written.
+    fn save_dump(&self, dump: StoredBlockDump) -> Option<PathBuf> {
+        tracing::info!("Saving dump..");
+        save_dump(
+            self.sequencer_config.block_dump_path.clone(),
+            dump,
+            self.sequencer_config.max_dump_bytes,
+        )
+        .inspect_err(|err| tracing::error!(?err, "Failed to write block dump"))
+        .ok()
+    }
+
+    /// Saves the decision trace of a produced block, logging failures.
+    fn save_trace(&self, trace: &BlockTrace) {
+        let Some(path) = &self.sequencer_config.block_trace_path else {
+            return;
+        };
+        if let Err(err) = save_trace(path, trace, self.sequencer_config.max_block_traces) {
+            tracing::warn!(
+                ?err,
+                block_number = trace.block_number,
+                "Failed to write block trace"
+            );
+        }
+    }
+
+    /// Block context and best mempool transactions to pre-execute on top of the current head.
+    fn warm_up_candidates(&self) -> Option<(BlockContext, Vec<ZkTransaction>)> {
+        let next_block_number = *self.state.block_range_available().end() + 1;
+        let block_context = self
+            .block_context_provider
+            .speculative_block_context(next_block_number)?;
+        let txs = self
+            .block_context_provider
+            .peek_best_l2_transactions(self.sequencer_config.warm_up_max_txs);
+        Some((block_context, txs))
     }
 }
 
@@ -212,3 +463,335 @@ async fn check_block_production_limit(
         std::future::pending::<()>().await;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DumpDetailLevel;
+    use crate::model::blocks::RebuildCommand;
+    use alloy::primitives::{Address, BlockHash, BlockNumber, TxHash, TxNonce};
+    use alloy::providers::{Provider, ProviderBuilder};
+    use alloy::transports::mock::Asserter;
+    use std::collections::BTreeMap;
+    use std::ops::RangeInclusive;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use zksync_os_contract_interface::ZkChain;
+    use zksync_os_genesis::{Genesis, GenesisBuilder};
+    use zksync_os_interface::types::StorageWrite;
+    use zksync_os_mempool::{PoolConfig, SpamScores, SpamScoringConfig, TxValidatorConfig};
+    use zksync_os_multivm::LATEST_EXECUTION_VERSION;
+    use zksync_os_multivm::test_utils::EmptyState;
+    use zksync_os_storage_api::{
+        ReadReplay, ReadRepository, RepositoryBlock, RepositoryResult, StateResult, StoredTxData,
+        TxMeta, ViewState,
+    };
+    use zksync_os_types::{PriorityDeadlines, ZkReceiptEnvelope};
+
+    const CHAIN_ID: u64 = 270;
+
+    /// Empty state that is slow to persist blocks: the node is asked to stop while a block is
+    /// being added to it.
+    #[derive(Debug, Clone)]
+    struct SlowState {
+        blocks: Arc<Mutex<Vec<BlockNumber>>>,
+        stop_sender: Arc<watch::Sender<bool>>,
+    }
+
+    impl ReadStateHistory for SlowState {
+        fn state_view_at(&self, _block_number: BlockNumber) -> StateResult<impl ViewState> {
+            Ok(EmptyState)
+        }
+
+        fn block_range_available(&self) -> RangeInclusive<u64> {
+            0..=self.blocks.lock().unwrap().last().copied().unwrap_or(0)
+        }
+
+        fn changed_keys(
+            &self,
+            _range: RangeInclusive<BlockNumber>,
+        ) -> StateResult<impl Iterator<Item = (B256, B256)> + '_> {
+            Ok(std::iter::empty())
+        }
+
+        fn key_history(
+            &self,
+            _key: B256,
+            _range: RangeInclusive<BlockNumber>,
+        ) -> StateResult<impl Iterator<Item = (BlockNumber, B256)> + '_> {
+            Ok(std::iter::empty())
+        }
+    }
+
+    impl WriteState for SlowState {
+        fn add_block_result<'a, J>(
+            &self,
+            block_number: u64,
+            _storage_diffs: Vec<StorageWrite>,
+            _new_preimages: J,
+            _override_allowed: bool,
+        ) -> anyhow::Result<()>
+        where
+            J: IntoIterator<Item = (B256, &'a Vec<u8>)>,
+        {
+            self.stop_sender.send_replace(true);
+            self.blocks.lock().unwrap().push(block_number);
+            Ok(())
+        }
+    }
+
+    #[derive(Debug, Clone, Default)]
+    struct TestReplay(Arc<Mutex<BTreeMap<BlockNumber, ReplayRecord>>>);
+
+    impl ReadReplay for TestReplay {
+        fn get_context(&self, block_number: BlockNumber) -> Option<BlockContext> {
+            self.get_replay_record(block_number)
+                .map(|record| record.block_context)
+        }
+
+        fn get_replay_record(&self, block_number: BlockNumber) -> Option<ReplayRecord> {
+            self.0.lock().unwrap().get(&block_number).cloned()
+        }
+
+        fn latest_record(&self) -> BlockNumber {
+            self.0
+                .lock()
+                .unwrap()
+                .keys()
+                .next_back()
+                .copied()
+                .unwrap_or(0)
+        }
+    }
+
+    impl WriteReplay for TestReplay {
+        fn write(&self, record: ReplayRecord, _override_allowed: bool) -> bool {
+            let block_number = record.block_context.block_number;
+            self.0.lock().unwrap().insert(block_number, record);
+            true
+        }
+    }
+
+    /// Repository only tracking populated blocks.
+    #[derive(Debug, Clone, Default)]
+    struct TestRepository(Arc<Mutex<Vec<BlockNumber>>>);
+
+    impl ReadRepository for TestRepository {
+        fn get_block_by_number(&self, _: BlockNumber) -> RepositoryResult<Option<RepositoryBlock>> {
+            Ok(None)
+        }
+
+        fn get_block_by_hash(&self, _: BlockHash) -> RepositoryResult<Option<RepositoryBlock>> {
+            Ok(None)
+        }
+
+        fn get_raw_transaction(&self, _: TxHash) -> RepositoryResult<Option<Vec<u8>>> {
+            Ok(None)
+        }
+
+        fn get_transaction(&self, _: TxHash) -> RepositoryResult<Option<ZkTransaction>> {
+            Ok(None)
+        }
+
+        fn get_transaction_receipt(
+            &self,
+            _: TxHash,
+        ) -> RepositoryResult<Option<ZkReceiptEnvelope>> {
+            Ok(None)
+        }
+
+        fn get_transaction_meta(&self, _: TxHash) -> RepositoryResult<Option<TxMeta>> {
+            Ok(None)
+        }
+
+        fn get_transaction_hash_by_sender_nonce(
+            &self,
+            _: Address,
+            _: TxNonce,
+        ) -> RepositoryResult<Option<TxHash>> {
+            Ok(None)
+        }
+
+        fn get_stored_transaction(&self, _: TxHash) -> RepositoryResult<Option<StoredTxData>> {
+            Ok(None)
+        }
+
+        fn get_latest_block(&self) -> u64 {
+            self.0.lock().unwrap().last().copied().unwrap_or(0)
+        }
+    }
+
+    impl WriteRepository for TestRepository {
+        fn populate(
+            &self,
+            block_output: BlockOutput,
+            _transactions: Vec<ZkTransaction>,
+        ) -> impl Future<Output = RepositoryResult<()>> + Send {
+            self.0.lock().unwrap().push(block_output.header.number);
+            std::future::ready(Ok(()))
+        }
+    }
+
+    fn sequencer(
+        state: SlowState,
+        replay: TestReplay,
+        repositories: TestRepository,
+        block_dump_path: &Path,
+        stop_receiver: watch::Receiver<bool>,
+        tx_acceptance_state_sender: watch::Sender<TransactionAcceptanceState>,
+    ) -> Sequencer<impl L2TransactionPool, SlowState, TestReplay, TestRepository> {
+        let mempool = zksync_os_mempool::in_memory(
+            state.clone(),
+            repositories.clone(),
+            CHAIN_ID,
+            PoolConfig::default(),
+            TxValidatorConfig {
+                max_input_bytes: 1024,
+                execution_version: LATEST_EXECUTION_VERSION,
+                schedule: Default::default(),
+                sponsorship: Default::default(),
+                max_content_entries: 100,
+                max_tx_lifetime: Duration::from_secs(3_600),
+                min_priority_fee_per_gas: None,
+                denied_senders: vec![],
+                allowed_senders: None,
+            },
+            SpamScores::new(SpamScoringConfig::default()),
+        );
+        // Only used to produce block #1
+        let l1_provider = ProviderBuilder::new()
+            .connect_mocked_client(Asserter::new())
+            .erased();
+        let genesis = Genesis::new(
+            Arc::new(GenesisBuilder::new().build_input().unwrap()),
+            ZkChain::new(Address::ZERO, l1_provider),
+            CHAIN_ID,
+            None,
+        );
+        let (_, l1_transactions) = mpsc::channel(1);
+        let block_context_provider = BlockContextProvider::new(
+            0,
+            l1_transactions,
+            PriorityDeadlines::default(),
+            Duration::from_secs(60),
+            mempool,
+            BlockContext::default().block_hashes,
+            0,
+            0,
+            false,
+            CHAIN_ID,
+            100_000_000,
+            1_000_000,
+            "0.1.0".parse().unwrap(),
+            Arc::new(genesis),
+            Address::ZERO,
+            None,
+            None,
+            None,
+            watch::channel(None).1,
+            None,
+            watch::channel(None).0,
+            None,
+        );
+        Sequencer {
+            block_context_provider,
+            state,
+            replay,
+            repositories,
+            sequencer_config: SequencerConfig {
+                block_time: Duration::from_millis(100),
+                strict_block_timestamps: false,
+                max_transactions_in_block: 100,
+                block_dump_path: block_dump_path.to_owned(),
+                dump_detail_level: DumpDetailLevel::default(),
+                max_dump_bytes: 1 << 20,
+                block_trace_path: None,
+                max_block_traces: 0,
+                block_replay_server_address: String::new(),
+                block_replay_download_address: None,
+                block_gas_limit: 100_000_000,
+                block_pubdata_limit_bytes: 1_000_000,
+                max_blocks_to_produce: None,
+                warm_up_max_txs: 0,
+                warm_up_concurrency: 1,
+            },
+            tx_acceptance_state_sender,
+            stop_receiver,
+            replay_divergence: None,
+            l1_price_predictions: None,
+        }
+    }
+
+    /// Command rebuilding block `block_number` as an empty block.
+    fn rebuild_command(block_number: u64) -> BlockCommand {
+        let replay_record = ReplayRecord {
+            block_context: BlockContext {
+                block_number,
+                timestamp: block_number,
+                chain_id: CHAIN_ID,
+                gas_limit: 100_000_000,
+                ..Default::default()
+            },
+            starting_l1_priority_id: 0,
+            transactions: vec![],
+            previous_block_timestamp: block_number - 1,
+            block_timestamp_millis: block_number * 1_000,
+            node_version: "0.1.0".parse().unwrap(),
+            block_output_hash: B256::ZERO,
+        };
+        BlockCommand::Rebuild(Box::new(RebuildCommand {
+            replay_record,
+            make_empty: true,
+        }))
+    }
+
+    #[tokio::test]
+    async fn block_in_progress_is_completed_on_stop() {
+        let block_dump_dir = tempfile::TempDir::new().unwrap();
+        let (stop_sender, stop_receiver) = watch::channel(false);
+        let state = SlowState {
+            blocks: Arc::default(),
+            stop_sender: Arc::new(stop_sender),
+        };
+        let replay = TestReplay::default();
+        let repositories = TestRepository::default();
+        let (tx_acceptance_state_sender, tx_acceptance_state) =
+            watch::channel(TransactionAcceptanceState::Accepting);
+        let sequencer = sequencer(
+            state.clone(),
+            replay.clone(),
+            repositories.clone(),
+            block_dump_dir.path(),
+            stop_receiver,
+            tx_acceptance_state_sender,
+        );
+
+        let (command_sender, commands) = mpsc::channel(2);
+        let (output, mut output_receiver) = mpsc::channel(2);
+        // The stop signal arrives while block #1 is added to state; block #2 is already queued
+        command_sender.send(rebuild_command(1)).await.unwrap();
+        command_sender.send(rebuild_command(2)).await.unwrap();
+        tokio::time::timeout(
+            Duration::from_secs(30),
+            sequencer.run(PeekableReceiver::new(commands), output),
+        )
+        .await
+        .expect("sequencer didn't stop")
+        .unwrap();
+
+        // Block #1 is fully persisted and sent downstream, block #2 isn't started
+        assert_eq!(*state.blocks.lock().unwrap(), [1]);
+        assert_eq!(replay.latest_record(), 1);
+        assert_eq!(*repositories.0.lock().unwrap(), [1]);
+        let (block_output, replay_record, _) = output_receiver.recv().await.unwrap();
+        assert_eq!(block_output.header.number, 1);
+        assert_eq!(replay_record.block_context.block_number, 1);
+        assert!(output_receiver.recv().await.is_none());
+        assert!(matches!(
+            *tx_acceptance_state.borrow(),
+            TransactionAcceptanceState::NotAccepting(NotAcceptingReason::ShuttingDown)
+        ));
+    }
+}
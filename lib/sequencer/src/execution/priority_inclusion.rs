@@ -0,0 +1,85 @@
+use zksync_os_types::{L1TxSerialId, ZkEnvelope, ZkTransaction};
+
+/// Tracks priority transactions that must be included in the block being produced because they are
+/// close to expiring on L1.
+///
+/// While such transactions are pending, the block is not sealed by the block time or the transaction
+/// count limit. Gas limit still applies - the remaining transactions then go into the next block.
+#[derive(Debug)]
+pub(crate) struct ForcedL1Inclusion {
+    /// Last priority id that must be included, inclusive.
+    until: Option<L1TxSerialId>,
+    next_priority_id: L1TxSerialId,
+}
+
+impl ForcedL1Inclusion {
+    pub fn new(until: Option<L1TxSerialId>, starting_priority_id: L1TxSerialId) -> Self {
+        Self {
+            until,
+            next_priority_id: starting_priority_id,
+        }
+    }
+
+    pub fn on_executed(&mut self, tx: &ZkTransaction) {
+        if let ZkEnvelope::L1(l1_tx) = tx.envelope() {
+            self.next_priority_id = l1_tx.priority_id() + 1;
+        }
+    }
+
+    /// Whether some of the forced priority transactions are not included yet.
+    pub fn is_pending(&self) -> bool {
+        self.until
+            .is_some_and(|until| self.next_priority_id <= until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::consensus::{SignableTransaction, TxEip1559};
+    use alloy::primitives::{Address, B256, Bytes, Signature, U256};
+    use zksync_os_types::{L1PriorityEnvelope, L1Tx, L2Envelope, L2Transaction};
+
+    fn l1_tx(priority_id: L1TxSerialId) -> ZkTransaction {
+        L1PriorityEnvelope {
+            inner: L1Tx {
+                hash: B256::with_last_byte(priority_id as u8),
+                initiator: Address::repeat_byte(1),
+                to: Address::repeat_byte(2),
+                gas_limit: 200_000,
+                gas_per_pubdata_byte_limit: 800,
+                max_fee_per_gas: 1,
+                max_priority_fee_per_gas: 0,
+                nonce: priority_id,
+                value: U256::ZERO,
+                to_mint: U256::ZERO,
+                refund_recipient: Address::repeat_byte(1),
+                input: Bytes::new(),
+                factory_deps: vec![],
+                marker: Default::default(),
+            },
+        }
+        .into()
+    }
+
+    fn l2_tx() -> ZkTransaction {
+        let envelope =
+            L2Envelope::from(TxEip1559::default().into_signed(Signature::test_signature()));
+        L2Transaction::new_unchecked(envelope, Address::repeat_byte(3)).into()
+    }
+
+    #[test]
+    fn pending_until_forced_txs_are_executed() {
+        let mut forced = ForcedL1Inclusion::new(Some(6), 5);
+        assert!(forced.is_pending());
+        forced.on_executed(&l1_tx(5));
+        forced.on_executed(&l2_tx());
+        assert!(forced.is_pending());
+        forced.on_executed(&l1_tx(6));
+        assert!(!forced.is_pending());
+
+        // Nothing to force, or forced txs were included in previous blocks
+        assert!(!ForcedL1Inclusion::new(None, 5).is_pending());
+        assert!(!ForcedL1Inclusion::new(Some(4), 5).is_pending());
+    }
+}
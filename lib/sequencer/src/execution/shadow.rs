@@ -0,0 +1,547 @@
+//! Shadow execution: blocks executed with the active execution version are re-executed with
+//! another (usually the next) version, and divergent outputs are reported. This gives evidence
+//! that a new version produces identical results on production traffic before it's activated.
+//!
+//! Shadow execution never affects block production: blocks are passed downstream unchanged,
+//! shadow runs only read state, and blocks are skipped instead of waited for if the shadow
+//! executor falls behind.
+
+use crate::execution::divergence::TxResultSummary;
+use crate::execution::metrics::EXECUTION_METRICS;
+use alloy::primitives::B256;
+use anyhow::Context;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, watch};
+use zksync_os_interface::tracing::NopTracer;
+use zksync_os_interface::traits::{NoopTxCallback, TxListSource};
+use zksync_os_interface::types::{BlockContext, BlockOutput, StorageWrite};
+use zksync_os_multivm::ExecutionVersion;
+use zksync_os_pipeline::{PeekableReceiver, PipelineComponent};
+use zksync_os_storage_api::{BlockStats, ReadStateHistory, ReplayRecord};
+use zksync_os_types::{ShadowDivergence, ShadowExecutionStatus, ZksyncOsEncode};
+
+#[derive(Debug, Clone)]
+pub struct ShadowExecutionConfig {
+    /// Version blocks are re-executed with.
+    pub execution_version: ExecutionVersion,
+    /// Max number of blocks waiting for shadow execution; further blocks are skipped.
+    pub queue_size: usize,
+    /// Max number of detailed divergence reports written to `report_path`.
+    pub max_reports: usize,
+    /// Directory for divergence reports.
+    pub report_path: PathBuf,
+}
+
+/// Executes blocks with the shadow execution version.
+pub trait ShadowRunner: Send + Sync + 'static {
+    fn run(&self, record: &ReplayRecord) -> anyhow::Result<BlockOutput>;
+}
+
+/// Runs blocks with `zksync_os_multivm` on top of the state before the block.
+#[derive(Debug)]
+pub struct MultivmShadowRunner<State> {
+    state: State,
+    execution_version: ExecutionVersion,
+}
+
+impl<State: ReadStateHistory> MultivmShadowRunner<State> {
+    pub fn new(state: State, execution_version: ExecutionVersion) -> Self {
+        Self {
+            state,
+            execution_version,
+        }
+    }
+}
+
+impl<State: ReadStateHistory> ShadowRunner for MultivmShadowRunner<State> {
+    fn run(&self, record: &ReplayRecord) -> anyhow::Result<BlockOutput> {
+        let block_context = BlockContext {
+            execution_version: self.execution_version as u32,
+            ..record.block_context
+        };
+        run_block_on_state(&self.state, block_context, record)
+    }
+}
+
+/// Runs transactions of `record` with `zksync_os_multivm` on top of the state before the block.
+/// Used to re-execute already sequenced blocks, e.g. to recompute outputs that are not persisted.
+pub fn run_block_on_state(
+    state: &impl ReadStateHistory,
+    block_context: BlockContext,
+    record: &ReplayRecord,
+) -> anyhow::Result<BlockOutput> {
+    let state_view = state
+        .state_view_at(block_context.block_number - 1)
+        .context("state before the block is not available")?;
+    let tx_source = TxListSource {
+        transactions: record
+            .transactions
+            .iter()
+            .map(|tx| tx.clone().encode())
+            .collect(),
+    };
+    Ok(zksync_os_multivm::run_block(
+        block_context,
+        state_view.clone(),
+        state_view,
+        tx_source,
+        NoopTxCallback,
+        &mut NopTracer,
+    )?)
+}
+
+/// Differences between outputs of a block computed with the active and shadow execution versions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShadowDivergenceReport {
+    pub block_number: u64,
+    pub active_execution_version: u32,
+    pub shadow_execution_version: u32,
+    pub active_block_hash: B256,
+    pub shadow_block_hash: B256,
+    /// Transactions with different results.
+    pub tx_results: Vec<TxResultDiff>,
+    /// Storage slots with different final values in the block.
+    pub storage_writes: Vec<StorageValueDiff>,
+    pub pubdata_differs: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxResultDiff {
+    /// Index of the transaction in the block.
+    pub index: usize,
+    /// `None` if the transaction is invalid or missing in the output.
+    pub active: Option<TxResultSummary>,
+    pub shadow: Option<TxResultSummary>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageValueDiff {
+    pub key: B256,
+    /// `None` if the slot isn't written in the output.
+    pub active: Option<B256>,
+    pub shadow: Option<B256>,
+}
+
+impl ShadowDivergenceReport {
+    /// Compares outputs of the same block. Returns `None` if they are identical.
+    pub fn compare(
+        active_execution_version: u32,
+        shadow_execution_version: u32,
+        active: &BlockOutput,
+        shadow: &BlockOutput,
+    ) -> Option<Self> {
+        let active_results = tx_result_summaries(active);
+        let shadow_results = tx_result_summaries(shadow);
+        let tx_count = active_results.len().max(shadow_results.len());
+        let tx_results: Vec<_> = (0..tx_count)
+            .map(|index| TxResultDiff {
+                index,
+                active: active_results.get(index).copied().flatten(),
+                shadow: shadow_results.get(index).copied().flatten(),
+            })
+            .filter(|diff| diff.active != diff.shadow)
+            .collect();
+
+        let active_writes = final_values(&active.storage_writes);
+        let mut shadow_writes = final_values(&shadow.storage_writes);
+        let mut storage_writes: Vec<_> = active_writes
+            .into_iter()
+            .map(|(key, value)| StorageValueDiff {
+                key,
+                active: Some(value),
+                shadow: shadow_writes.remove(&key),
+            })
+            .chain(
+                shadow_writes
+                    .into_iter()
+                    .map(|(key, value)| StorageValueDiff {
+                        key,
+                        active: None,
+                        shadow: Some(value),
+                    }),
+            )
+            .filter(|diff| diff.active != diff.shadow)
+            .collect();
+        storage_writes.sort_unstable_by_key(|diff| diff.key);
+
+        let active_block_hash = active.header.hash();
+        let shadow_block_hash = shadow.header.hash();
+        let pubdata_differs = active.pubdata != shadow.pubdata;
+        let diverged = active_block_hash != shadow_block_hash
+            || !tx_results.is_empty()
+            || !storage_writes.is_empty()
+            || pubdata_differs;
+        diverged.then(|| Self {
+            block_number: active.header.number,
+            active_execution_version,
+            shadow_execution_version,
+            active_block_hash,
+            shadow_block_hash,
+            tx_results,
+            storage_writes,
+            pubdata_differs,
+        })
+    }
+}
+
+fn tx_result_summaries(output: &BlockOutput) -> Vec<Option<TxResultSummary>> {
+    output
+        .tx_results
+        .iter()
+        .map(|result| {
+            let tx = result.as_ref().ok()?;
+            Some(TxResultSummary {
+                success: tx.is_success(),
+                gas_used: tx.gas_used,
+            })
+        })
+        .collect()
+}
+
+/// Values of written slots after the block.
+fn final_values(writes: &[StorageWrite]) -> BTreeMap<B256, B256> {
+    writes
+        .iter()
+        .map(|write| (write.key, write.value))
+        .collect()
+}
+
+/// Pipeline component passing blocks through unchanged and queueing them for shadow execution.
+/// Disabled unless explicitly configured.
+pub struct ShadowExecution<Runner> {
+    runner: Arc<Runner>,
+    config: ShadowExecutionConfig,
+    status: watch::Sender<ShadowExecutionStatus>,
+}
+
+impl<Runner: ShadowRunner> ShadowExecution<Runner> {
+    pub fn new(
+        runner: Runner,
+        config: ShadowExecutionConfig,
+        status: watch::Sender<ShadowExecutionStatus>,
+    ) -> Self {
+        Self {
+            runner: Arc::new(runner),
+            config,
+            status,
+        }
+    }
+}
+
+#[async_trait]
+impl<Runner: ShadowRunner> PipelineComponent for ShadowExecution<Runner> {
+    type Input = (BlockOutput, ReplayRecord, BlockStats);
+    type Output = (BlockOutput, ReplayRecord, BlockStats);
+
+    const NAME: &'static str = "shadow_execution";
+    const OUTPUT_BUFFER_SIZE: usize = 5;
+
+    async fn run(
+        self,
+        mut input: PeekableReceiver<Self::Input>,
+        output: mpsc::Sender<Self::Output>,
+    ) -> anyhow::Result<()> {
+        let shadow_version = self.config.execution_version;
+        tracing::info!(?shadow_version, "shadow execution is enabled");
+        self.status.send_modify(|status| {
+            status.execution_version = Some(shadow_version as u32);
+        });
+        let (queue_sender, queue) = mpsc::channel(self.config.queue_size);
+        tokio::spawn(
+            ShadowWorker {
+                runner: self.runner,
+                config: self.config,
+                status: self.status.clone(),
+                reports_written: 0,
+            }
+            .run(queue),
+        );
+
+        loop {
+            let Some((block_output, replay_record, stats)) = input.recv().await else {
+                anyhow::bail!("inbound channel closed");
+            };
+            let block_number = replay_record.block_context.block_number;
+            match queue_sender.try_send((block_output.clone(), replay_record.clone())) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    tracing::debug!(
+                        block_number,
+                        "shadow execution queue is full; skipping block"
+                    );
+                    EXECUTION_METRICS.shadow_execution_blocks[&"dropped"].inc();
+                    self.status.send_modify(|status| status.dropped_blocks += 1);
+                }
+                Err(TrySendError::Closed(_)) => {
+                    // The worker never exits on its own; this can only be a panic, which is
+                    // already logged. Shadow execution must not stop block processing.
+                    tracing::debug!(block_number, "shadow execution worker stopped");
+                }
+            }
+
+            if output
+                .send((block_output, replay_record, stats))
+                .await
+                .is_err()
+            {
+                anyhow::bail!("Outbound channel closed");
+            }
+        }
+    }
+}
+
+struct ShadowWorker<Runner> {
+    runner: Arc<Runner>,
+    config: ShadowExecutionConfig,
+    status: watch::Sender<ShadowExecutionStatus>,
+    reports_written: usize,
+}
+
+impl<Runner: ShadowRunner> ShadowWorker<Runner> {
+    async fn run(mut self, mut queue: mpsc::Receiver<(BlockOutput, ReplayRecord)>) {
+        while let Some((active_output, replay_record)) = queue.recv().await {
+            let block_number = replay_record.block_context.block_number;
+            let active_version = replay_record.block_context.execution_version;
+            let runner = self.runner.clone();
+            let shadow_output =
+                tokio::task::spawn_blocking(move || runner.run(&replay_record)).await;
+            let shadow_output = match shadow_output {
+                Ok(Ok(output)) => output,
+                Ok(Err(err)) => {
+                    tracing::warn!(block_number, "shadow execution failed: {err:#}");
+                    EXECUTION_METRICS.shadow_execution_blocks[&"failed"].inc();
+                    continue;
+                }
+                Err(err) => {
+                    tracing::warn!(block_number, "shadow execution panicked: {err}");
+                    EXECUTION_METRICS.shadow_execution_blocks[&"failed"].inc();
+                    continue;
+                }
+            };
+
+            let report = ShadowDivergenceReport::compare(
+                active_version,
+                self.config.execution_version as u32,
+                &active_output,
+                &shadow_output,
+            );
+            let divergence = report.map(|report| self.on_divergence(report));
+            let outcome = if divergence.is_some() {
+                "diverged"
+            } else {
+                "matched"
+            };
+            EXECUTION_METRICS.shadow_execution_blocks[&outcome].inc();
+            self.status.send_modify(|status| {
+                status.last_compared_block = Some(block_number);
+                status.compared_blocks += 1;
+                if let Some(divergence) = divergence {
+                    status.divergent_blocks += 1;
+                    status.first_divergence.get_or_insert(divergence);
+                }
+            });
+        }
+    }
+
+    fn on_divergence(&mut self, report: ShadowDivergenceReport) -> ShadowDivergence {
+        tracing::error!(
+            block_number = report.block_number,
+            active_execution_version = report.active_execution_version,
+            shadow_execution_version = report.shadow_execution_version,
+            diverged_txs = report.tx_results.len(),
+            diverged_slots = report.storage_writes.len(),
+            pubdata_differs = report.pubdata_differs,
+            "shadow execution output diverged from the active execution version"
+        );
+        let report_path = if self.reports_written < self.config.max_reports {
+            self.reports_written += 1;
+            save_report(&self.config.report_path, &report)
+                .inspect_err(|err| {
+                    tracing::error!(?err, "failed to write shadow divergence report")
+                })
+                .ok()
+        } else {
+            None
+        };
+        ShadowDivergence {
+            block_number: report.block_number,
+            active_execution_version: report.active_execution_version,
+            active_block_hash: report.active_block_hash,
+            shadow_block_hash: report.shadow_block_hash,
+            report_path,
+        }
+    }
+}
+
+fn save_report(path: &Path, report: &ShadowDivergenceReport) -> anyhow::Result<PathBuf> {
+    std::fs::create_dir_all(path).context("create_dir_all")?;
+    let file_path = path.join(format!("shadow_divergence_{}.json", report.block_number));
+    let bytes = serde_json::to_vec_pretty(report)?;
+    std::fs::write(&file_path, bytes).context("failed to write report file")?;
+    Ok(file_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+    use std::time::Duration;
+    use zksync_os_multivm::test_utils::EmptyState;
+
+    /// Executes blocks on top of [`EmptyState`].
+    struct EmptyStateRunner(ExecutionVersion);
+
+    impl ShadowRunner for EmptyStateRunner {
+        fn run(&self, record: &ReplayRecord) -> anyhow::Result<BlockOutput> {
+            let block_context = BlockContext {
+                execution_version: self.0 as u32,
+                ..record.block_context
+            };
+            Ok(zksync_os_multivm::run_block(
+                block_context,
+                EmptyState,
+                EmptyState,
+                TxListSource {
+                    transactions: Default::default(),
+                },
+                NoopTxCallback,
+                &mut NopTracer,
+            )?)
+        }
+    }
+
+    /// Injects an extra storage write into the shadow output.
+    struct DivergingRunner(EmptyStateRunner);
+
+    impl ShadowRunner for DivergingRunner {
+        fn run(&self, record: &ReplayRecord) -> anyhow::Result<BlockOutput> {
+            let mut output = self.0.run(record)?;
+            output.storage_writes.push(StorageWrite {
+                key: B256::repeat_byte(0xaa),
+                value: B256::repeat_byte(0xbb),
+                account: Address::repeat_byte(0xaa),
+                account_key: B256::with_last_byte(1),
+            });
+            Ok(output)
+        }
+    }
+
+    fn replay_record(block_number: u64) -> ReplayRecord {
+        ReplayRecord {
+            block_context: BlockContext {
+                block_number,
+                timestamp: block_number,
+                chain_id: 270,
+                gas_limit: 100_000_000,
+                execution_version: ExecutionVersion::V3 as u32,
+                ..Default::default()
+            },
+            starting_l1_priority_id: 0,
+            transactions: vec![],
+            previous_block_timestamp: block_number - 1,
+            block_timestamp_millis: block_number * 1_000,
+            node_version: "0.1.0".parse().unwrap(),
+            block_output_hash: B256::ZERO,
+        }
+    }
+
+    /// Runs blocks `1..=block_count` executed with V3 through shadow execution with `runner` and
+    /// returns the status once all of them are compared.
+    async fn run_shadow_execution(
+        runner: impl ShadowRunner,
+        report_path: &Path,
+        block_count: u64,
+    ) -> ShadowExecutionStatus {
+        let config = ShadowExecutionConfig {
+            execution_version: ExecutionVersion::V3,
+            queue_size: block_count as usize,
+            max_reports: 1,
+            report_path: report_path.to_owned(),
+        };
+        let (status, mut status_receiver) = watch::channel(ShadowExecutionStatus::default());
+        let (input_sender, input) = mpsc::channel(block_count as usize);
+        let (output, mut output_receiver) = mpsc::channel(block_count as usize);
+        let component = ShadowExecution::new(runner, config, status);
+        tokio::spawn(component.run(PeekableReceiver::new(input), output));
+
+        let active_runner = EmptyStateRunner(ExecutionVersion::V3);
+        for block_number in 1..=block_count {
+            let record = replay_record(block_number);
+            let active_output = active_runner.run(&record).unwrap();
+            input_sender
+                .send((active_output, record, BlockStats::default()))
+                .await
+                .unwrap();
+            // Blocks are passed downstream unchanged
+            let (_, record, _) = output_receiver.recv().await.unwrap();
+            assert_eq!(record.block_context.block_number, block_number);
+        }
+
+        tokio::time::timeout(
+            Duration::from_secs(30),
+            status_receiver.wait_for(|status| status.compared_blocks == block_count),
+        )
+        .await
+        .expect("blocks were not compared in time")
+        .unwrap()
+        .clone()
+    }
+
+    #[tokio::test]
+    async fn same_version_does_not_diverge() {
+        let report_dir = tempfile::TempDir::new().unwrap();
+        let status =
+            run_shadow_execution(EmptyStateRunner(ExecutionVersion::V3), report_dir.path(), 3)
+                .await;
+
+        assert_eq!(status.execution_version, Some(3));
+        assert_eq!(status.last_compared_block, Some(3));
+        assert_eq!(status.divergent_blocks, 0);
+        assert_eq!(status.first_divergence, None);
+        assert_eq!(report_dir.path().read_dir().unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn injected_divergence_is_reported() {
+        let report_dir = tempfile::TempDir::new().unwrap();
+        let diverged_before = EXECUTION_METRICS.shadow_execution_blocks[&"diverged"].get();
+        let status = run_shadow_execution(
+            DivergingRunner(EmptyStateRunner(ExecutionVersion::V3)),
+            report_dir.path(),
+            2,
+        )
+        .await;
+
+        assert_eq!(status.divergent_blocks, 2);
+        assert_eq!(
+            EXECUTION_METRICS.shadow_execution_blocks[&"diverged"].get() - diverged_before,
+            2
+        );
+        let divergence = status.first_divergence.unwrap();
+        assert_eq!(divergence.block_number, 1);
+        assert_eq!(divergence.active_execution_version, 3);
+        assert_ne!(divergence.active_block_hash, divergence.shadow_block_hash);
+
+        // Only `max_reports` reports are written
+        let report_path = divergence.report_path.unwrap();
+        assert_eq!(report_dir.path().read_dir().unwrap().count(), 1);
+        let report: ShadowDivergenceReport =
+            serde_json::from_slice(&std::fs::read(report_path).unwrap()).unwrap();
+        assert_eq!(report.block_number, 1);
+        assert_eq!(report.shadow_execution_version, 3);
+        assert_eq!(
+            report.storage_writes,
+            [StorageValueDiff {
+                key: B256::repeat_byte(0xaa),
+                active: None,
+                shadow: Some(B256::repeat_byte(0xbb)),
+            }]
+        );
+        assert!(report.tx_results.is_empty());
+    }
+}
@@ -0,0 +1,72 @@
+use crate::execution::metrics::EXECUTION_METRICS;
+use std::collections::VecDeque;
+use zksync_os_storage_api::BlockStats;
+
+/// Number of recent blocks the rolling utilization is averaged over.
+const UTILIZATION_WINDOW: usize = 100;
+
+/// Rolling average of gas and pubdata utilization over recent blocks.
+#[derive(Debug)]
+pub(crate) struct RollingUtilization {
+    pubdata_limit_bytes: u64,
+    /// `(gas, pubdata)` utilization of the recent blocks, oldest first.
+    window: VecDeque<(f64, f64)>,
+}
+
+impl RollingUtilization {
+    pub fn new(pubdata_limit_bytes: u64) -> Self {
+        Self {
+            pubdata_limit_bytes,
+            window: VecDeque::with_capacity(UTILIZATION_WINDOW),
+        }
+    }
+
+    /// Adds a block and returns the average `(gas, pubdata)` utilization over the window.
+    pub fn push(&mut self, stats: &BlockStats) -> (f64, f64) {
+        if self.window.len() == UTILIZATION_WINDOW {
+            self.window.pop_front();
+        }
+        self.window.push_back((
+            stats.gas_utilization(),
+            stats.pubdata_utilization(self.pubdata_limit_bytes),
+        ));
+        let len = self.window.len() as f64;
+        let (gas, pubdata) = self
+            .window
+            .iter()
+            .fold((0.0, 0.0), |(gas, pubdata), (g, p)| (gas + g, pubdata + p));
+        let averages = (gas / len, pubdata / len);
+        EXECUTION_METRICS.block_utilization[&"gas"].set(averages.0);
+        EXECUTION_METRICS.block_utilization[&"pubdata"].set(averages.1);
+        averages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(gas_used: u64, pubdata_bytes: u64) -> BlockStats {
+        BlockStats {
+            gas_used,
+            gas_limit: 100,
+            pubdata_bytes,
+            ..BlockStats::default()
+        }
+    }
+
+    #[test]
+    fn rolling_average() {
+        let mut utilization = RollingUtilization::new(1_000);
+        assert_eq!(utilization.push(&stats(50, 100)), (0.5, 0.1));
+        assert_eq!(utilization.push(&stats(100, 300)), (0.75, 0.2));
+
+        // Old blocks fall out of the window
+        for _ in 0..UTILIZATION_WINDOW {
+            utilization.push(&stats(10, 0));
+        }
+        let (gas, pubdata) = utilization.push(&stats(10, 0));
+        assert!((gas - 0.1).abs() < 1e-9, "{gas}");
+        assert_eq!(pubdata, 0.0);
+    }
+}
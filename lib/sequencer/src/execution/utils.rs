@@ -1,7 +1,11 @@
-use alloy::primitives::{B256, keccak256};
+use crate::config::DumpDetailLevel;
+use crate::execution::divergence::OutputDivergence;
+use alloy::consensus::Transaction;
+use alloy::primitives::{Address, B256, TxHash, U256, keccak256};
 use anyhow::Context;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 use zksync_os_interface::types::{BlockContext, BlockOutput};
 use zksync_os_types::ZkTransaction;
@@ -31,15 +35,425 @@ pub struct BlockDump {
     pub error: String,
 }
 
-pub(crate) fn save_dump(path: PathBuf, dump: BlockDump) -> anyhow::Result<()> {
+/// Block dump as written to disk. Depending on [`DumpDetailLevel`], only some of the transaction
+/// sections are populated:
+///  * `Full` - `txs` (complete transactions) and `tx_hashes`;
+///  * `Redacted` - `redacted_txs` and `tx_hashes`;
+///  * `Minimal` - `tx_hashes` only.
+///
+/// Dumps of replayed blocks whose output diverged from the replay record additionally contain
+/// the divergence evidence.
+///
+/// Dumps written before detail levels were introduced only contain `ctx`, `txs` and `error` and
+/// are read as `Full` dumps.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StoredBlockDump {
+    #[serde(default)]
+    pub detail_level: DumpDetailLevel,
+    pub ctx: BlockContext,
+    #[serde(default)]
+    pub tx_hashes: Vec<TxHash>,
+    #[serde(default)]
+    pub txs: Vec<ZkTransaction>,
+    #[serde(default)]
+    pub redacted_txs: Vec<RedactedTransaction>,
+    pub error: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub divergence: Option<OutputDivergence>,
+    /// Sections that were truncated to fit into `max_dump_bytes`, in truncation order.
+    #[serde(default)]
+    pub truncated_sections: Vec<DumpSection>,
+}
+
+/// Summary of a transaction with its calldata replaced by a hash and a length. Keeps enough
+/// information to follow the control flow of the failed block without disclosing the payload;
+/// signature, fee parameters and access / authorization lists are dropped.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedactedTransaction {
+    pub hash: TxHash,
+    pub tx_type: u8,
+    pub signer: Address,
+    pub to: Option<Address>,
+    pub nonce: u64,
+    pub gas_limit: u64,
+    pub value: U256,
+    pub input_hash: B256,
+    pub input_len: usize,
+}
+
+impl From<&ZkTransaction> for RedactedTransaction {
+    fn from(tx: &ZkTransaction) -> Self {
+        let input = tx.inner.input();
+        Self {
+            hash: *tx.hash(),
+            tx_type: tx.inner.ty(),
+            signer: tx.signer(),
+            to: tx.to(),
+            nonce: tx.nonce(),
+            gas_limit: tx.gas_limit(),
+            value: tx.inner.value(),
+            input_hash: keccak256(input),
+            input_len: input.len(),
+        }
+    }
+}
+
+/// Sections of a stored dump, listed in the order they are truncated when the dump exceeds
+/// `max_dump_bytes`. Transactions are dropped from the end of a section first. Block context is
+/// never truncated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DumpSection {
+    Txs,
+    RedactedTxs,
+    StorageWrites,
+    TxHashes,
+    Error,
+}
+
+const TRUNCATION_ORDER: [DumpSection; 5] = [
+    DumpSection::Txs,
+    DumpSection::RedactedTxs,
+    DumpSection::StorageWrites,
+    DumpSection::TxHashes,
+    DumpSection::Error,
+];
+
+/// Extra bytes removed on truncation to account for the truncation marker itself.
+const TRUNCATION_MARKER_SLACK: usize = 32;
+
+impl StoredBlockDump {
+    pub fn new(dump: BlockDump, detail_level: DumpDetailLevel) -> Self {
+        let tx_hashes = dump.txs.iter().map(|tx| *tx.hash()).collect();
+        let (txs, redacted_txs) = match detail_level {
+            DumpDetailLevel::Full => (dump.txs, Vec::new()),
+            DumpDetailLevel::Redacted => (Vec::new(), dump.txs.iter().map(Into::into).collect()),
+            DumpDetailLevel::Minimal => (Vec::new(), Vec::new()),
+        };
+        Self {
+            detail_level,
+            ctx: dump.ctx,
+            tx_hashes,
+            txs,
+            redacted_txs,
+            error: dump.error,
+            divergence: None,
+            truncated_sections: Vec::new(),
+        }
+    }
+
+    pub fn with_divergence(mut self, divergence: OutputDivergence) -> Self {
+        self.divergence = Some(divergence);
+        self
+    }
+
+    /// Returns complete transactions if the dump can be used to re-execute the block,
+    /// i.e. it's a `Full` dump that wasn't truncated.
+    pub fn replayable_txs(&self) -> Option<&[ZkTransaction]> {
+        (self.detail_level == DumpDetailLevel::Full
+            && !self.truncated_sections.contains(&DumpSection::Txs))
+        .then_some(self.txs.as_slice())
+    }
+
+    /// Serializes the dump, truncating sections in [`TRUNCATION_ORDER`] until it fits into `max_bytes`.
+    /// Oversized attempts never buffer more than `max_bytes`.
+    pub fn encode(mut self, max_bytes: usize) -> anyhow::Result<Vec<u8>> {
+        for section in TRUNCATION_ORDER {
+            let mut writer = LimitedWriter::new(Vec::new(), max_bytes);
+            serde_json::to_writer(&mut writer, &self).context("failed to serialize dump")?;
+            if writer.len <= max_bytes {
+                return Ok(writer.inner);
+            }
+            self.truncate(section, writer.len - max_bytes + TRUNCATION_MARKER_SLACK)?;
+        }
+        // Only the block context is left - it's small and never truncated.
+        serde_json::to_vec(&self).context("failed to serialize dump")
+    }
+
+    fn truncate(&mut self, section: DumpSection, excess: usize) -> anyhow::Result<()> {
+        let removed = match section {
+            DumpSection::Txs => truncate_tail(&mut self.txs, excess)?,
+            DumpSection::RedactedTxs => truncate_tail(&mut self.redacted_txs, excess)?,
+            DumpSection::StorageWrites => match &mut self.divergence {
+                Some(divergence) => truncate_tail(&mut divergence.storage_writes, excess)?,
+                None => false,
+            },
+            DumpSection::TxHashes => truncate_tail(&mut self.tx_hashes, excess)?,
+            DumpSection::Error => {
+                let mut len = self.error.len().saturating_sub(excess);
+                while !self.error.is_char_boundary(len) {
+                    len -= 1;
+                }
+                let removed = self.error.len() - len;
+                self.error.truncate(len);
+                removed > 0
+            }
+        };
+        if removed {
+            self.truncated_sections.push(section);
+        }
+        Ok(())
+    }
+}
+
+/// Drops items from the end of `items` until at least `excess` serialized bytes are removed.
+/// Returns whether anything was removed.
+fn truncate_tail<T: Serialize>(items: &mut Vec<T>, excess: usize) -> anyhow::Result<bool> {
+    let mut removed_bytes = 0;
+    let mut removed = false;
+    while removed_bytes < excess {
+        let Some(item) = items.pop() else {
+            break;
+        };
+        // +1 for the separating comma
+        let mut writer = LimitedWriter::new(io::sink(), 0);
+        serde_json::to_writer(&mut writer, &item).context("failed to serialize dump item")?;
+        removed_bytes += writer.len + 1;
+        removed = true;
+    }
+    Ok(removed)
+}
+
+/// Forwards the first `limit` written bytes to `inner`. Bytes over the limit are only counted, so
+/// that the full serialized size is known without buffering it.
+struct LimitedWriter<W> {
+    inner: W,
+    limit: usize,
+    /// Total number of written bytes, including the ones over the limit.
+    len: usize,
+}
+
+impl<W: Write> LimitedWriter<W> {
+    fn new(inner: W, limit: usize) -> Self {
+        Self {
+            inner,
+            limit,
+            len: 0,
+        }
+    }
+}
+
+impl<W: Write> Write for LimitedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let forwarded = self.limit.saturating_sub(self.len).min(buf.len());
+        self.inner.write_all(&buf[..forwarded])?;
+        self.len += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writes `dump` to the `path` directory and returns the path of the dump file.
+pub(crate) fn save_dump(
+    path: PathBuf,
+    dump: StoredBlockDump,
+    max_dump_bytes: u64,
+) -> anyhow::Result<PathBuf> {
     let seconds = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .expect("Incorrect system time")
         .as_secs();
     let file_name = format!("dump_{}_{seconds}.json", dump.ctx.block_number);
-    let bytes = serde_json::to_vec(&dump).context("failed to serialize dump")?;
+    let bytes = dump.encode(max_dump_bytes as usize)?;
     std::fs::create_dir_all(&path).context("create_dir_all")?;
-    std::fs::write(path.join(file_name), bytes).context("failed to write dump file")?;
+    let file_path = path.join(file_name);
+    std::fs::write(&file_path, bytes).context("failed to write dump file")?;
+
+    Ok(file_path)
+}
+
+/// Loads a block dump, regardless of its detail level. Dumps written by older versions are
+/// read as `Full` dumps.
+pub fn load_dump(path: &Path) -> anyhow::Result<StoredBlockDump> {
+    let bytes = std::fs::read(path).with_context(|| format!("failed to read dump {path:?}"))?;
+    decode_dump(&bytes)
+}
+
+pub fn decode_dump(bytes: &[u8]) -> anyhow::Result<StoredBlockDump> {
+    serde_json::from_slice(bytes).context("failed to deserialize dump")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::consensus::{SignableTransaction, TxEip1559};
+    use alloy::primitives::{Bytes, Signature, TxKind};
+    use zksync_os_types::{L2Envelope, L2Transaction};
+
+    const CALLDATA: [u8; 4] = [0xde, 0xad, 0xbe, 0xef];
+
+    fn tx(nonce: u64) -> ZkTransaction {
+        let envelope = L2Envelope::from(
+            TxEip1559 {
+                chain_id: 270,
+                nonce,
+                gas_limit: 100_000,
+                to: TxKind::Call(Address::repeat_byte(1)),
+                input: Bytes::from(CALLDATA.repeat(64)),
+                ..Default::default()
+            }
+            .into_signed(Signature::test_signature()),
+        );
+        L2Transaction::new_unchecked(envelope, Address::repeat_byte(2)).into()
+    }
+
+    fn forced_failure() -> BlockDump {
+        BlockDump {
+            ctx: BlockContext {
+                block_number: 7,
+                ..Default::default()
+            },
+            txs: (0..10).map(tx).collect(),
+            error: "forced failure".to_owned(),
+        }
+    }
+
+    fn encode(level: DumpDetailLevel, max_bytes: usize) -> Vec<u8> {
+        StoredBlockDump::new(forced_failure(), level)
+            .encode(max_bytes)
+            .unwrap()
+    }
+
+    fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+        haystack.windows(needle.len()).any(|w| w == needle)
+    }
 
-    Ok(())
+    #[test]
+    fn detail_levels_are_ordered_by_size() {
+        let full = encode(DumpDetailLevel::Full, usize::MAX);
+        let redacted = encode(DumpDetailLevel::Redacted, usize::MAX);
+        let minimal = encode(DumpDetailLevel::Minimal, usize::MAX);
+        assert!(full.len() > redacted.len());
+        assert!(redacted.len() > minimal.len());
+    }
+
+    #[test]
+    fn calldata_is_redacted() {
+        let calldata_hex = alloy::hex::encode(CALLDATA.repeat(64));
+        let full = encode(DumpDetailLevel::Full, usize::MAX);
+        assert!(contains(&full, calldata_hex.as_bytes()));
+
+        for level in [DumpDetailLevel::Redacted, DumpDetailLevel::Minimal] {
+            let bytes = encode(level, usize::MAX);
+            assert!(!contains(&bytes, calldata_hex.as_bytes()), "{level:?}");
+        }
+
+        let redacted = decode_dump(&encode(DumpDetailLevel::Redacted, usize::MAX)).unwrap();
+        assert_eq!(redacted.redacted_txs.len(), 10);
+        assert_eq!(redacted.redacted_txs[3].nonce, 3);
+        assert_eq!(redacted.redacted_txs[3].input_len, 256);
+        assert_eq!(
+            redacted.redacted_txs[3].input_hash,
+            keccak256(CALLDATA.repeat(64))
+        );
+    }
+
+    #[test]
+    fn loader_handles_all_levels() {
+        for level in [
+            DumpDetailLevel::Full,
+            DumpDetailLevel::Redacted,
+            DumpDetailLevel::Minimal,
+        ] {
+            let dump = decode_dump(&encode(level, usize::MAX)).unwrap();
+            assert_eq!(dump.detail_level, level);
+            assert_eq!(dump.ctx.block_number, 7);
+            assert_eq!(dump.tx_hashes.len(), 10);
+            assert_eq!(dump.error, "forced failure");
+            assert_eq!(
+                dump.replayable_txs().is_some(),
+                level == DumpDetailLevel::Full
+            );
+        }
+
+        // Dumps written before detail levels were introduced.
+        let legacy = serde_json::to_vec(&forced_failure()).unwrap();
+        let dump = decode_dump(&legacy).unwrap();
+        assert_eq!(dump.detail_level, DumpDetailLevel::Full);
+        assert_eq!(dump.replayable_txs().unwrap().len(), 10);
+    }
+
+    #[test]
+    fn oversized_dump_is_truncated() {
+        let full = encode(DumpDetailLevel::Full, usize::MAX);
+        let max_bytes = full.len() / 2;
+        let bytes = encode(DumpDetailLevel::Full, max_bytes);
+        assert!(bytes.len() <= max_bytes);
+
+        let dump = decode_dump(&bytes).unwrap();
+        assert_eq!(dump.truncated_sections, [DumpSection::Txs]);
+        assert!(dump.txs.len() < 10);
+        assert_eq!(dump.tx_hashes.len(), 10);
+        assert!(dump.replayable_txs().is_none());
+
+        // Tiny limit: everything but the block context goes.
+        let dump = decode_dump(&encode(DumpDetailLevel::Full, 0)).unwrap();
+        assert_eq!(
+            dump.truncated_sections,
+            [DumpSection::Txs, DumpSection::TxHashes, DumpSection::Error]
+        );
+        assert!(dump.txs.is_empty() && dump.tx_hashes.is_empty() && dump.error.is_empty());
+    }
+
+    #[test]
+    fn oversized_attempts_are_not_buffered() {
+        let mut writer = LimitedWriter::new(Vec::new(), 3);
+        writer.write_all(b"he").unwrap();
+        writer.write_all(b"llo").unwrap();
+        assert_eq!(writer.inner, b"hel");
+        assert_eq!(writer.len, 5);
+    }
+
+    #[test]
+    fn divergence_evidence_is_stored() {
+        use crate::execution::divergence::{StorageWriteDiff, TxResultSummary};
+
+        let divergence = OutputDivergence {
+            expected_output_hash: B256::repeat_byte(1),
+            computed_output_hash: B256::repeat_byte(2),
+            computed_block_hash: B256::repeat_byte(3),
+            tx_results: vec![TxResultSummary {
+                success: true,
+                gas_used: 21_000,
+            }],
+            storage_writes: (0..100)
+                .map(|i| StorageWriteDiff {
+                    key: B256::with_last_byte(i),
+                    account: Address::with_last_byte(i),
+                    account_key: B256::ZERO,
+                    previous_value: None,
+                    computed_value: B256::with_last_byte(i),
+                })
+                .collect(),
+        };
+        let dump = || {
+            StoredBlockDump::new(forced_failure(), DumpDetailLevel::Minimal)
+                .with_divergence(divergence.clone())
+        };
+        let bytes = dump().encode(usize::MAX).unwrap();
+        assert_eq!(
+            decode_dump(&bytes).unwrap().divergence,
+            Some(divergence.clone())
+        );
+        // Dumps without divergence don't have the section at all
+        assert!(!contains(
+            &encode(DumpDetailLevel::Minimal, usize::MAX),
+            b"divergence"
+        ));
+
+        // Storage writes go before transaction hashes
+        let max_bytes = bytes.len() - 100;
+        let truncated = decode_dump(&dump().encode(max_bytes).unwrap()).unwrap();
+        assert_eq!(truncated.truncated_sections, [DumpSection::StorageWrites]);
+        assert_eq!(truncated.tx_hashes.len(), 10);
+        let truncated = truncated.divergence.unwrap();
+        assert!(truncated.storage_writes.len() < 100);
+        assert_eq!(
+            truncated.computed_output_hash,
+            divergence.computed_output_hash
+        );
+    }
 }
@@ -9,6 +9,7 @@ use zksync_os_interface::error::InvalidTransaction;
 use zksync_os_interface::tracing::NopTracer;
 use zksync_os_interface::traits::{EncodedTx, NextTxResponse, TxResultCallback, TxSource};
 use zksync_os_interface::types::{BlockContext, BlockOutput, TxProcessingOutputOwned};
+use zksync_os_multivm::{ExecutionScratch, MultivmError};
 use zksync_os_storage_api::ViewState;
 
 /// A one‐by‐one driver around `run_block`, enabling `execute_next_tx` interface
@@ -22,7 +23,13 @@ pub struct VmWrapper {
 
 impl VmWrapper {
     /// Spawn the VM runner in a blocking task.
-    pub fn new(context: BlockContext, state_view: impl ViewState) -> Self {
+    ///
+    /// `scratch` should be kept by the caller and reused across blocks.
+    pub fn new(
+        context: BlockContext,
+        state_view: impl ViewState,
+        scratch: &ExecutionScratch,
+    ) -> Self {
         // Channel for sending NextTxResponse (Tx bytes or SealBlock).
         let (tx_sender, tx_receiver) = channel(1);
         // Channel for receiving per‐tx execution results.
@@ -33,8 +40,10 @@ impl VmWrapper {
         let tx_callback = ChannelTxResultCallback::new(res_sender);
 
         // Spawn the blocking run_block(...) call.
+        let scratch = scratch.clone();
         let join_handle = spawn_blocking(move || {
-            zksync_os_multivm::run_block(
+            zksync_os_multivm::run_block_with_scratch(
+                &scratch,
                 context,
                 state_view.clone(),
                 state_view,
@@ -42,6 +51,7 @@ impl VmWrapper {
                 tx_callback,
                 &mut NopTracer,
             )
+            .map_err(vm_error)
         });
 
         Self {
@@ -154,3 +164,12 @@ impl TxResultCallback for ChannelTxResultCallback {
         let _ = self.sender.blocking_send(tx_execution_result);
     }
 }
+
+fn vm_error(err: MultivmError) -> anyhow::Error {
+    match err {
+        MultivmError::UnsupportedExecutionVersion { got, .. } => anyhow::Error::new(err).context(
+            format!("block uses execution version {got} unknown to this node; upgrade the node"),
+        ),
+        MultivmError::ExecutionFailed(err) => err,
+    }
+}
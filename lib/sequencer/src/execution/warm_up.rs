@@ -0,0 +1,386 @@
+//! Speculative warm-up of the next block.
+//!
+//! While the sequencer waits for the next command, the best mempool transactions are simulated
+//! against the current head state. Results are thrown away - the only effect is that storage slots
+//! and preimages read during simulation end up in [`WarmStorageCache`], which the real execution
+//! consults before hitting the state backend.
+//!
+//! The cache only ever holds values *read* from the committed state view of its base block
+//! (speculative writes stay inside the simulating VM), and it is invalidated whenever a new block
+//! is added to state. A warm-up task that outlives its base block cannot write into the cache,
+//! since every insertion is checked against the epoch the task started in.
+
+use crate::execution::metrics::EXECUTION_METRICS;
+use alloy::primitives::B256;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use tokio::task::{JoinHandle, spawn_blocking};
+use zksync_os_interface::tracing::NopTracer;
+use zksync_os_interface::traits::{PreimageSource, ReadStorage};
+use zksync_os_interface::types::BlockContext;
+use zksync_os_multivm::ExecutionScratch;
+use zksync_os_storage_api::{ReadStateHistory, ViewState};
+use zksync_os_types::{ZkTransaction, ZksyncOsEncode};
+
+/// Upper bound on the number of cached preimages (bytecodes, account properties).
+const MAX_CACHED_PREIMAGES: usize = 16_384;
+
+/// Storage reads and preimages observed during warm-up, shared with the real execution.
+#[derive(Debug, Clone, Default)]
+pub struct WarmStorageCache {
+    inner: Arc<RwLock<CacheInner>>,
+}
+
+#[derive(Debug, Default)]
+struct CacheInner {
+    /// Block whose state the cached storage values belong to.
+    base_block: Option<u64>,
+    /// Incremented on every reset; warm-up tasks may only write into the epoch they started in.
+    epoch: u64,
+    storage: HashMap<B256, Option<B256>>,
+    /// Preimages are content-addressed, so they stay valid across resets.
+    preimages: HashMap<B256, Vec<u8>>,
+}
+
+impl WarmStorageCache {
+    /// Drops all cached storage values and rebases the cache on `base_block`.
+    /// Must be called whenever state for `base_block` (or any earlier block) changes.
+    pub fn reset(&self, base_block: u64) {
+        let mut inner = self.inner.write().unwrap();
+        inner.base_block = Some(base_block);
+        inner.epoch += 1;
+        inner.storage.clear();
+    }
+
+    /// Returns `(base_block, epoch)` the next warm-up should run with.
+    fn current(&self) -> Option<(u64, u64)> {
+        let inner = self.inner.read().unwrap();
+        inner.base_block.map(|base_block| (base_block, inner.epoch))
+    }
+
+    fn record_storage(&self, epoch: u64, key: B256, value: Option<B256>) {
+        let mut inner = self.inner.write().unwrap();
+        if inner.epoch == epoch {
+            inner.storage.insert(key, value);
+        }
+    }
+
+    fn record_preimage(&self, hash: B256, preimage: &[u8]) {
+        let mut inner = self.inner.write().unwrap();
+        if inner.preimages.len() < MAX_CACHED_PREIMAGES {
+            inner
+                .preimages
+                .entry(hash)
+                .or_insert_with(|| preimage.to_vec());
+        }
+    }
+
+    fn lookup_storage(&self, base_block: u64, key: B256) -> Option<Option<B256>> {
+        let inner = self.inner.read().unwrap();
+        if inner.base_block != Some(base_block) {
+            return None;
+        }
+        inner.storage.get(&key).copied()
+    }
+
+    fn lookup_preimage(&self, hash: B256) -> Option<Vec<u8>> {
+        self.inner.read().unwrap().preimages.get(&hash).cloned()
+    }
+}
+
+/// State view used by the real execution: consults [`WarmStorageCache`] before `inner`.
+#[derive(Debug, Clone)]
+pub struct WarmedViewState<V> {
+    inner: V,
+    /// Cache is only consulted if it is based on the same block as `inner`.
+    cache: Option<(WarmStorageCache, u64)>,
+}
+
+impl<V> WarmedViewState<V> {
+    /// `base_block` must be the block `inner` is a view at.
+    pub fn new(inner: V, cache: Option<WarmStorageCache>, base_block: u64) -> Self {
+        Self {
+            inner,
+            cache: cache.map(|cache| (cache, base_block)),
+        }
+    }
+}
+
+impl<V: ReadStorage> ReadStorage for WarmedViewState<V> {
+    fn read(&mut self, key: B256) -> Option<B256> {
+        let Some((cache, base_block)) = &self.cache else {
+            return self.inner.read(key);
+        };
+        match cache.lookup_storage(*base_block, key) {
+            Some(value) => {
+                EXECUTION_METRICS.warm_up_cache[&"storage_hit"].inc();
+                value
+            }
+            None => {
+                EXECUTION_METRICS.warm_up_cache[&"storage_miss"].inc();
+                self.inner.read(key)
+            }
+        }
+    }
+}
+
+impl<V: PreimageSource> PreimageSource for WarmedViewState<V> {
+    fn get_preimage(&mut self, hash: B256) -> Option<Vec<u8>> {
+        let Some((cache, _)) = &self.cache else {
+            return self.inner.get_preimage(hash);
+        };
+        match cache.lookup_preimage(hash) {
+            Some(preimage) => {
+                EXECUTION_METRICS.warm_up_cache[&"preimage_hit"].inc();
+                Some(preimage)
+            }
+            None => {
+                EXECUTION_METRICS.warm_up_cache[&"preimage_miss"].inc();
+                self.inner.get_preimage(hash)
+            }
+        }
+    }
+}
+
+/// State view used by warm-up simulations: records everything read from `inner` into the cache.
+#[derive(Debug, Clone)]
+struct RecordingViewState<V> {
+    inner: V,
+    cache: WarmStorageCache,
+    epoch: u64,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl<V: ReadStorage> ReadStorage for RecordingViewState<V> {
+    fn read(&mut self, key: B256) -> Option<B256> {
+        let value = self.inner.read(key);
+        if !self.cancelled.load(Ordering::Relaxed) {
+            self.cache.record_storage(self.epoch, key, value);
+        }
+        value
+    }
+}
+
+impl<V: PreimageSource> PreimageSource for RecordingViewState<V> {
+    fn get_preimage(&mut self, hash: B256) -> Option<Vec<u8>> {
+        let preimage = self.inner.get_preimage(hash);
+        if let Some(preimage) = &preimage
+            && !self.cancelled.load(Ordering::Relaxed)
+        {
+            self.cache.record_preimage(hash, preimage);
+        }
+        preimage
+    }
+}
+
+/// Sets the flag when dropped, i.e. when the warm-up future is cancelled.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Simulates `txs` against the cache's base block to populate the cache.
+///
+/// Transactions are split among at most `concurrency` blocking tasks. Dropping the returned future
+/// cancels the warm-up: blocking tasks stop before their next transaction and stop recording
+/// immediately. Errors are logged and otherwise ignored - warm-up is strictly best-effort.
+pub async fn warm_up(
+    cache: &WarmStorageCache,
+    state: &impl ReadStateHistory,
+    block_context: BlockContext,
+    txs: Vec<ZkTransaction>,
+    concurrency: usize,
+) {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let _guard = CancelOnDrop(cancelled.clone());
+    let handles = spawn_simulations(cache, state, block_context, txs, concurrency, cancelled);
+    for handle in handles {
+        if let Err(err) = handle.await {
+            tracing::debug!(?err, "Warm-up task failed");
+        }
+    }
+}
+
+fn spawn_simulations(
+    cache: &WarmStorageCache,
+    state: &impl ReadStateHistory,
+    block_context: BlockContext,
+    txs: Vec<ZkTransaction>,
+    concurrency: usize,
+    cancelled: Arc<AtomicBool>,
+) -> Vec<JoinHandle<()>> {
+    let Some((base_block, epoch)) = cache.current() else {
+        return Vec::new();
+    };
+    if txs.is_empty() || block_context.block_number != base_block + 1 {
+        return Vec::new();
+    }
+    let state_view = match state.state_view_at(base_block) {
+        Ok(state_view) => state_view,
+        Err(err) => {
+            tracing::debug!(?err, base_block, "Skipping warm-up: state is not available");
+            return Vec::new();
+        }
+    };
+
+    let concurrency = concurrency.clamp(1, txs.len());
+    let mut chunks = vec![Vec::new(); concurrency];
+    for (i, tx) in txs.into_iter().enumerate() {
+        chunks[i % concurrency].push(tx);
+    }
+    chunks
+        .into_iter()
+        .map(|chunk| {
+            let view = RecordingViewState {
+                inner: state_view.clone(),
+                cache: cache.clone(),
+                epoch,
+                cancelled: cancelled.clone(),
+            };
+            let cancelled = cancelled.clone();
+            spawn_blocking(move || simulate_chunk(chunk, block_context, view, &cancelled))
+        })
+        .collect()
+}
+
+fn simulate_chunk(
+    txs: Vec<ZkTransaction>,
+    block_context: BlockContext,
+    view: impl ViewState,
+    cancelled: &AtomicBool,
+) {
+    let scratch = ExecutionScratch::default();
+    for tx in txs {
+        if cancelled.load(Ordering::Relaxed) {
+            EXECUTION_METRICS.warm_up_txs[&"cancelled"].inc();
+            continue;
+        }
+        // Outcome is irrelevant: reverted and invalid transactions warm up the cache just as well.
+        let label = match zksync_os_multivm::simulate_tx_with_scratch(
+            &scratch,
+            tx.encode(),
+            block_context,
+            view.clone(),
+            view.clone(),
+            &mut NopTracer,
+        ) {
+            Ok(_) => "simulated",
+            Err(err) => {
+                tracing::debug!(?err, "Warm-up simulation failed");
+                "failed"
+            }
+        };
+        EXECUTION_METRICS.warm_up_txs[&label].inc();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Backing storage that counts reads reaching it.
+    #[derive(Debug, Clone, Default)]
+    struct CountingStorage {
+        reads: Arc<AtomicUsize>,
+    }
+
+    impl ReadStorage for CountingStorage {
+        fn read(&mut self, key: B256) -> Option<B256> {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            Some(key)
+        }
+    }
+
+    fn recording_view(cache: &WarmStorageCache, epoch: u64) -> RecordingViewState<CountingStorage> {
+        RecordingViewState {
+            inner: CountingStorage::default(),
+            cache: cache.clone(),
+            epoch,
+            cancelled: Arc::default(),
+        }
+    }
+
+    /// Reads performed by a typical transfer-heavy workload touching the same hot slots.
+    fn run_workload(view: &mut impl ReadStorage) {
+        for _ in 0..10 {
+            for slot in 0..100u8 {
+                view.read(B256::with_last_byte(slot));
+            }
+        }
+    }
+
+    #[test]
+    fn warm_up_reduces_backing_reads() {
+        let cache = WarmStorageCache::default();
+        cache.reset(5);
+        let (_, epoch) = cache.current().unwrap();
+
+        let cold = CountingStorage::default();
+        run_workload(&mut WarmedViewState::new(cold.clone(), None, 5));
+        let cold_reads = cold.reads.load(Ordering::Relaxed);
+
+        run_workload(&mut recording_view(&cache, epoch));
+        let warm = CountingStorage::default();
+        let mut view = WarmedViewState::new(warm.clone(), Some(cache.clone()), 5);
+        run_workload(&mut view);
+        let warm_reads = warm.reads.load(Ordering::Relaxed);
+
+        assert_eq!(cold_reads, 1_000);
+        assert_eq!(warm_reads, 0);
+        // Values are served unchanged.
+        assert_eq!(
+            view.read(B256::with_last_byte(7)),
+            Some(B256::with_last_byte(7))
+        );
+    }
+
+    #[test]
+    fn cache_is_ignored_for_other_base_block() {
+        let cache = WarmStorageCache::default();
+        cache.reset(5);
+        let (_, epoch) = cache.current().unwrap();
+        run_workload(&mut recording_view(&cache, epoch));
+
+        let storage = CountingStorage::default();
+        run_workload(&mut WarmedViewState::new(
+            storage.clone(),
+            Some(cache.clone()),
+            6,
+        ));
+        assert_eq!(storage.reads.load(Ordering::Relaxed), 1_000);
+    }
+
+    #[test]
+    fn stale_warm_up_cannot_write_after_reset() {
+        let cache = WarmStorageCache::default();
+        cache.reset(5);
+        let (_, stale_epoch) = cache.current().unwrap();
+        cache.reset(6);
+
+        run_workload(&mut recording_view(&cache, stale_epoch));
+        let storage = CountingStorage::default();
+        run_workload(&mut WarmedViewState::new(
+            storage.clone(),
+            Some(cache.clone()),
+            6,
+        ));
+        assert_eq!(storage.reads.load(Ordering::Relaxed), 1_000);
+    }
+
+    #[test]
+    fn cancelled_warm_up_stops_recording() {
+        let cache = WarmStorageCache::default();
+        cache.reset(5);
+        let (_, epoch) = cache.current().unwrap();
+        let view = recording_view(&cache, epoch);
+        drop(CancelOnDrop(view.cancelled.clone()));
+
+        run_workload(&mut view.clone());
+        assert!(cache.inner.read().unwrap().storage.is_empty());
+    }
+}
@@ -1,6 +1,9 @@
+use crate::execution::block_trace::BlockTraceRecorder;
+use crate::execution::bundles::Bundle;
 use alloy::primitives::B256;
 use std::fmt::Display;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::time::Duration;
 use zksync_os_interface::types::BlockContext;
 use zksync_os_mempool::TxStream;
@@ -94,20 +97,29 @@ impl Display for BlockCommand {
 /// which should handle them uniformly.
 ///
 /// Downstream transform:
-/// `BlockExecutor: (State, PreparedBlockCommand) -> (BlockOutput, ReplayRecord)`
+/// `BlockExecutor: (State, PreparedBlockCommand) -> (BlockOutput, ReplayRecord, BlockStats)`
 pub struct PreparedBlockCommand<'a> {
     pub block_context: BlockContext,
     pub seal_policy: SealPolicy,
     pub invalid_tx_policy: InvalidTxPolicy,
     pub tx_source: Pin<Box<dyn TxStream<Item = ZkTransaction> + Send + 'a>>,
+    /// Bundles to attempt before transactions from `tx_source`. Only set for produced blocks.
+    pub bundles: Vec<Arc<Bundle>>,
     /// L1 transaction serial id expected at the beginning of this block.
     /// Not used in execution directly, but required to construct ReplayRecord
     pub starting_l1_priority_id: L1TxSerialId,
+    /// Last priority transaction that must be included in this block as it's close to expiring on L1.
+    /// Only set for produced blocks.
+    pub force_include_l1_until: Option<L1TxSerialId>,
     pub metrics_label: &'static str,
     pub node_version: semver::Version,
     /// Expected hash of the block output (missing for command generated from `BlockCommand::Produce`)
     pub expected_block_output_hash: Option<B256>,
     pub previous_block_timestamp: u64,
+    /// Block timestamp in milliseconds; `block_context.timestamp` is this value in seconds.
+    pub block_timestamp_millis: u64,
+    /// Records block-building decisions. Only enabled for produced blocks if block traces are on.
+    pub trace: BlockTraceRecorder,
 }
 
 /// Behaviour when VM returns an InvalidTransaction error.
@@ -25,6 +25,7 @@ impl<'a> fmt::Debug for PreparedBlockCommand<'a> {
             &self.expected_block_output_hash,
         );
         ds.field("previous_block_timestamp", &self.previous_block_timestamp);
+        ds.field("block_timestamp_millis", &self.block_timestamp_millis);
         ds.finish()
     }
 }
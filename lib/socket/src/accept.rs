@@ -0,0 +1,150 @@
+//! Server side of the handshake performed by [`connect`](crate::connect).
+
+use crate::http::{
+    DEFAULT_MAX_HTTP_HEADER_BYTES, HttpHeaders, HttpHeadersError, read_http_headers,
+};
+use std::io;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, ReadHalf, WriteHalf};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AcceptHandshakeError {
+    #[error("client did not complete the handshake within {0:?}")]
+    Timeout(Duration),
+    #[error("client requested path {actual:?}, expected {expected:?}")]
+    UnexpectedPath {
+        expected: String,
+        actual: Option<String>,
+    },
+    #[error(transparent)]
+    Headers(#[from] HttpHeadersError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Connection that completed the handshake, split into halves ready for framed codecs.
+#[derive(Debug)]
+pub struct AcceptedConnection<S> {
+    /// Handshake headers sent by the client.
+    pub headers: HttpHeaders,
+    /// Read half, buffered; nothing after the handshake headers is consumed yet.
+    pub reader: BufReader<ReadHalf<S>>,
+    pub writer: WriteHalf<S>,
+}
+
+/// Performs the server side of the handshake: reads the client's HTTP headers, checks that the
+/// requested path is `expected_path` (if set) and responds with the protocol `version`.
+///
+/// Fails if the handshake is not completed within `timeout`, so that a client that never finishes
+/// its headers doesn't hold the connection forever.
+pub async fn accept_handshake<S: AsyncRead + AsyncWrite>(
+    stream: S,
+    expected_path: Option<&str>,
+    version: u32,
+    timeout: Duration,
+) -> Result<AcceptedConnection<S>, AcceptHandshakeError> {
+    let handshake = async {
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut reader = BufReader::new(reader);
+        let headers = read_http_headers(&mut reader, DEFAULT_MAX_HTTP_HEADER_BYTES).await?;
+        if let Some(expected) = expected_path
+            && headers.path() != Some(expected)
+        {
+            return Err(AcceptHandshakeError::UnexpectedPath {
+                expected: expected.to_owned(),
+                actual: headers.path().map(str::to_owned),
+            });
+        }
+        writer.write_u32(version).await?;
+        writer.flush().await?;
+        Ok(AcceptedConnection {
+            headers,
+            reader,
+            writer,
+        })
+    };
+    tokio::time::timeout(timeout, handshake)
+        .await
+        .map_err(|_| AcceptHandshakeError::Timeout(timeout))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, DuplexStream};
+
+    const VERSION: u32 = 7;
+    const TIMEOUT: Duration = Duration::from_millis(100);
+
+    async fn accept(
+        server: DuplexStream,
+    ) -> Result<AcceptedConnection<DuplexStream>, AcceptHandshakeError> {
+        accept_handshake(server, Some("/test"), VERSION, TIMEOUT).await
+    }
+
+    #[tokio::test]
+    async fn handshake_is_accepted() {
+        let (mut client, server) = tokio::io::duplex(1024);
+        client
+            .write_all(b"POST /test HTTP/1.0\r\nUser-Agent: test\r\n\r\n")
+            .await
+            .unwrap();
+        client.write_u64(42).await.unwrap();
+
+        let mut connection = accept(server).await.unwrap();
+        assert_eq!(connection.headers.user_agent(), Some("test"));
+        assert_eq!(client.read_u32().await.unwrap(), VERSION);
+        // Data sent right after the headers is kept for the protocol
+        assert_eq!(connection.reader.read_u64().await.unwrap(), 42);
+        connection.writer.write_u8(1).await.unwrap();
+        assert_eq!(client.read_u8().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn client_stalled_mid_headers() {
+        let (mut client, server) = tokio::io::duplex(1024);
+        client
+            .write_all(b"POST /test HTTP/1.0\r\nUser-Ag")
+            .await
+            .unwrap();
+
+        let err = accept(server).await.unwrap_err();
+        assert!(matches!(err, AcceptHandshakeError::Timeout(_)), "{err}");
+        // The version is not sent; the connection is closed
+        let mut response = vec![];
+        client.read_to_end(&mut response).await.unwrap();
+        assert!(response.is_empty());
+    }
+
+    #[tokio::test]
+    async fn wrong_path_is_rejected() {
+        let (mut client, server) = tokio::io::duplex(1024);
+        client
+            .write_all(b"POST /block_replays HTTP/1.0\r\n\r\n")
+            .await
+            .unwrap();
+
+        let err = accept(server).await.unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                AcceptHandshakeError::UnexpectedPath { actual: Some(path), .. } if path == "/block_replays"
+            ),
+            "{err}"
+        );
+        let mut response = vec![];
+        client.read_to_end(&mut response).await.unwrap();
+        assert!(response.is_empty());
+
+        // Any path is accepted if none is expected
+        let (mut client, server) = tokio::io::duplex(1024);
+        client
+            .write_all(b"POST /block_replays HTTP/1.0\r\n\r\n")
+            .await
+            .unwrap();
+        accept_handshake(server, None, VERSION, TIMEOUT)
+            .await
+            .unwrap();
+        assert_eq!(client.read_u32().await.unwrap(), VERSION);
+    }
+}
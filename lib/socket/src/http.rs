@@ -0,0 +1,444 @@
+//! HTTP-like handshake: headers sent by clients on connection, and the server response checked by
+//! [`connect_checked`](crate::connect_checked).
+
+use std::collections::BTreeMap;
+use std::io;
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt,
+};
+
+/// Max size of the status line and headers of a response.
+const MAX_RESPONSE_HEAD_BYTES: usize = 8 * 1024;
+/// Default max size of the start line and headers read by [`read_http_headers`].
+pub const DEFAULT_MAX_HTTP_HEADER_BYTES: usize = 16 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum HttpHandshakeError {
+    #[error("server responded with HTTP status {code} {reason}")]
+    Status { code: u16, reason: String },
+    #[error("malformed HTTP response: {0}")]
+    Malformed(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl HttpHandshakeError {
+    /// Returns whether the handshake may succeed if retried, e.g. once a load balancer
+    /// finds a healthy backend.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            // Request Timeout, Too Many Requests, Bad Gateway, Service Unavailable, Gateway Timeout
+            Self::Status { code, .. } => matches!(code, 408 | 429 | 502 | 503 | 504),
+            Self::Malformed(_) => false,
+            Self::Io(_) => true,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum HttpHeadersError {
+    #[error("HTTP headers exceed {limit} bytes")]
+    TooLarge { limit: usize },
+    #[error("malformed HTTP headers: {0}")]
+    Malformed(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl From<HttpHeadersError> for io::Error {
+    fn from(err: HttpHeadersError) -> Self {
+        match err {
+            HttpHeadersError::Io(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+/// Start line and headers of an HTTP request or response.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HttpHeaders {
+    /// Request line (e.g. `POST /block_replays HTTP/1.0`) or status line.
+    pub start_line: String,
+    /// Header values by lowercase header name. Values of repeated headers are joined with `, `.
+    pub headers: BTreeMap<String, String>,
+}
+
+impl HttpHeaders {
+    /// Returns the value of the header, `name` is case-insensitive.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.headers
+            .get(&name.to_ascii_lowercase())
+            .map(String::as_str)
+    }
+
+    /// Request target (e.g. `/block_replays`). `None` if the start line is not a request line.
+    pub fn path(&self) -> Option<&str> {
+        let mut parts = self.start_line.split_whitespace();
+        let method = parts.next()?;
+        if method.starts_with("HTTP/") {
+            return None;
+        }
+        parts.next()
+    }
+
+    pub fn user_agent(&self) -> Option<&str> {
+        self.get("user-agent")
+    }
+
+    fn parse(head: &[u8]) -> Result<Self, HttpHeadersError> {
+        let head = std::str::from_utf8(head)
+            .map_err(|_| HttpHeadersError::Malformed("headers are not valid UTF-8".into()))?;
+        let mut lines = head.lines();
+        let start_line = lines.next().unwrap_or_default().trim().to_owned();
+        let mut headers = BTreeMap::<String, String>::new();
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| HttpHeadersError::Malformed(format!("invalid header {line:?}")))?;
+            let value = value.trim();
+            headers
+                .entry(name.trim().to_ascii_lowercase())
+                .and_modify(|values| {
+                    values.push_str(", ");
+                    values.push_str(value);
+                })
+                .or_insert_with(|| value.to_owned());
+        }
+        Ok(Self {
+            start_line,
+            headers,
+        })
+    }
+}
+
+/// Reads the start line and headers, up to and including the empty line that ends them.
+/// Nothing after the headers is consumed.
+///
+/// Fails with [`HttpHeadersError::TooLarge`] once more than `max_bytes` are read without reaching
+/// the end of headers, so that a misbehaving peer cannot make the reader consume an unbounded
+/// amount of data.
+pub async fn read_http_headers<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_bytes: usize,
+) -> Result<HttpHeaders, HttpHeadersError> {
+    let mut head = Vec::new();
+    // Detects two consecutive line endings, which may be \r\n or \n.
+    let mut empty_line = false;
+    loop {
+        let buf = reader.fill_buf().await?;
+        if buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "EOF reached before end of headers",
+            )
+            .into());
+        }
+
+        let end = buf.iter().position(|&byte| {
+            if byte == b'\n' {
+                if empty_line {
+                    return true;
+                }
+                empty_line = true;
+            } else if byte != b'\r' {
+                empty_line = false;
+            }
+            false
+        });
+        let len = end.map_or(buf.len(), |end| end + 1);
+        if head.len() + len > max_bytes {
+            return Err(HttpHeadersError::TooLarge { limit: max_bytes });
+        }
+        head.extend_from_slice(&buf[..len]);
+        reader.consume(len);
+        if end.is_some() {
+            return HttpHeaders::parse(&head);
+        }
+    }
+}
+
+/// Same as [`read_http_headers`] with [`DEFAULT_MAX_HTTP_HEADER_BYTES`], but discards the headers.
+pub async fn skip_http_headers<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<()> {
+    read_http_headers(reader, DEFAULT_MAX_HTTP_HEADER_BYTES).await?;
+    Ok(())
+}
+
+/// Responds to the client handshake, which lets clients using
+/// [`connect_checked`](crate::connect_checked) know the connection was accepted.
+pub async fn write_http_ok<S: AsyncWrite + Unpin>(socket: &mut S) -> io::Result<()> {
+    socket.write_all(b"HTTP/1.0 200 OK\r\n\r\n").await?;
+    socket.flush().await
+}
+
+/// Reads the status line and headers of the response, and checks that the status is 2xx.
+///
+/// The response is read byte by byte, so that nothing after the headers is consumed.
+pub(crate) async fn read_http_response<R: AsyncRead + Unpin>(
+    reader: &mut R,
+) -> Result<(), HttpHandshakeError> {
+    let head = read_response_head(reader).await?;
+    let head = std::str::from_utf8(&head)
+        .map_err(|_| HttpHandshakeError::Malformed("response is not valid UTF-8".into()))?;
+    let (code, reason) = parse_status_line(head.lines().next().unwrap_or_default())?;
+    if !(200..300).contains(&code) {
+        return Err(HttpHandshakeError::Status { code, reason });
+    }
+    Ok(())
+}
+
+async fn read_response_head<R: AsyncRead + Unpin>(
+    reader: &mut R,
+) -> Result<Vec<u8>, HttpHandshakeError> {
+    let mut head = Vec::new();
+    // Detects two consecutive line endings, which may be \r\n or \n.
+    while !head.ends_with(b"\n\n") && !head.ends_with(b"\r\n\r\n") {
+        if head.len() == MAX_RESPONSE_HEAD_BYTES {
+            return Err(HttpHandshakeError::Malformed(format!(
+                "no end of headers within {MAX_RESPONSE_HEAD_BYTES} bytes"
+            )));
+        }
+        head.push(reader.read_u8().await?);
+    }
+    Ok(head)
+}
+
+fn parse_status_line(line: &str) -> Result<(u16, String), HttpHandshakeError> {
+    let malformed = || HttpHandshakeError::Malformed(format!("invalid status line {line:?}"));
+    let mut parts = line.splitn(3, ' ');
+    if !parts
+        .next()
+        .is_some_and(|version| version.starts_with("HTTP/"))
+    {
+        return Err(malformed());
+    }
+    let code = parts
+        .next()
+        .and_then(|code| code.parse::<u16>().ok())
+        .filter(|code| (100..1000).contains(code))
+        .ok_or_else(malformed)?;
+    let reason = parts.next().unwrap_or_default().trim().to_owned();
+    Ok((code, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ConnectOptions, connect_checked_with_options};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tokio::io::BufReader;
+    use tokio::net::TcpListener;
+
+    const PAYLOAD: u32 = 42;
+
+    fn fast_options() -> ConnectOptions {
+        ConnectOptions {
+            min_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            max_attempts: Some(3),
+            ..ConnectOptions::default()
+        }
+    }
+
+    /// Serves connections with `responses` in order, repeating the last one. Successful responses
+    /// are followed by [`PAYLOAD`]. Returns the server address and the number of accepted connections.
+    async fn mock_server(responses: Vec<&'static str>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = connections.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let i = accepted.fetch_add(1, Ordering::Relaxed);
+                let response = responses[i.min(responses.len() - 1)];
+                let (recv, mut send) = socket.split();
+                skip_http_headers(&mut BufReader::new(recv)).await.unwrap();
+                // The client may close the connection before reading everything
+                send.write_all(response.as_bytes()).await.ok();
+                if response.starts_with("HTTP/1.1 2") || response.starts_with("HTTP/1.0 2") {
+                    send.write_u32(PAYLOAD).await.ok();
+                }
+            }
+        });
+        (address, connections)
+    }
+
+    async fn connect_to(responses: Vec<&'static str>) -> (anyhow::Result<u32>, usize) {
+        let (address, connections) = mock_server(responses).await;
+        let result = async {
+            let mut socket =
+                connect_checked_with_options(&address, "/test", &fast_options()).await?;
+            anyhow::Ok(socket.read_u32().await?)
+        }
+        .await;
+        (result, connections.load(Ordering::Relaxed))
+    }
+
+    #[tokio::test]
+    async fn successful_response_keeps_payload() {
+        for response in [
+            "HTTP/1.0 200 OK\r\n\r\n",
+            "HTTP/1.1 204 No Content\r\nServer: test\r\nConnection: keep-alive\r\n\r\n",
+            "HTTP/1.1 200 OK\n\n",
+        ] {
+            let (result, connections) = connect_to(vec![response]).await;
+            assert_eq!(result.unwrap(), PAYLOAD);
+            assert_eq!(connections, 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn client_error_fails_fast() {
+        let (result, connections) = connect_to(vec!["HTTP/1.1 404 Not Found\r\n\r\n"]).await;
+        let err = result.unwrap_err();
+        assert!(format!("{err:#}").contains("404 Not Found"), "{err:#}");
+        assert_eq!(connections, 1);
+    }
+
+    #[tokio::test]
+    async fn unavailable_server_is_retried() {
+        let (result, connections) = connect_to(vec![
+            "HTTP/1.1 503 Service Unavailable\r\n\r\n",
+            "HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 200 OK\r\n\r\n",
+        ])
+        .await;
+        assert_eq!(result.unwrap(), PAYLOAD);
+        assert_eq!(connections, 3);
+
+        let (result, connections) =
+            connect_to(vec!["HTTP/1.1 503 Service Unavailable\r\n\r\n"]).await;
+        let err = result.unwrap_err();
+        assert!(format!("{err:#}").contains("503"), "{err:#}");
+        // The first attempt + 3 retries
+        assert_eq!(connections, 4);
+    }
+
+    #[tokio::test]
+    async fn garbage_response_is_rejected() {
+        let garbage: &'static str = "x".repeat(MAX_RESPONSE_HEAD_BYTES + 1).leak();
+        for response in [
+            garbage,
+            "\x00\x00\x00\x05\r\n\r\n",
+            "5\r\nhello\r\n0\r\n\r\n",
+            "HTTP/1.1 OK\r\n\r\n",
+            "HTTP/1.1 99999 Huge\r\n\r\n",
+        ] {
+            let (result, connections) = connect_to(vec![response]).await;
+            let err = result.unwrap_err();
+            assert!(format!("{err:#}").contains("malformed"), "{err:#}");
+            assert_eq!(connections, 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn response_without_end_of_headers_is_rejected() {
+        // The server closes the connection mid-headers; treated as a connection error and retried
+        let (result, connections) = connect_to(vec!["HTTP/1.1 200 OK\r\nServer: test\r\n"]).await;
+        result.unwrap_err();
+        assert_eq!(connections, 4);
+    }
+
+    const HANDSHAKE: &[u8] = b"POST /block_replays HTTP/1.0\r\nUser-Agent: test/1.0\r\n\
+        X-Forwarded-For: 10.0.0.1\r\nx-forwarded-for:10.0.0.2\r\n\r\n";
+
+    #[tokio::test]
+    async fn headers_split_across_reads() {
+        let data = [HANDSHAKE, &PAYLOAD.to_be_bytes()].concat();
+        // Buffer sizes that split the lines (and the empty line ending the headers) in all possible ways
+        for capacity in 1..=16 {
+            let mut reader = BufReader::with_capacity(capacity, data.as_slice());
+            let headers = read_http_headers(&mut reader, DEFAULT_MAX_HTTP_HEADER_BYTES)
+                .await
+                .unwrap();
+            assert_eq!(headers.start_line, "POST /block_replays HTTP/1.0");
+            assert_eq!(headers.path(), Some("/block_replays"));
+            assert_eq!(headers.user_agent(), Some("test/1.0"));
+            assert_eq!(headers.get("X-FORWARDED-FOR"), Some("10.0.0.1, 10.0.0.2"));
+            // Nothing after the headers is consumed
+            assert_eq!(reader.read_u32().await.unwrap(), PAYLOAD, "{capacity}");
+        }
+    }
+
+    #[tokio::test]
+    async fn headers_with_lf_line_endings() {
+        let data = [
+            b"POST /test HTTP/1.0\nHost: localhost\n\n".as_slice(),
+            b"rest",
+        ]
+        .concat();
+        let mut reader = BufReader::with_capacity(4, data.as_slice());
+        let headers = read_http_headers(&mut reader, DEFAULT_MAX_HTTP_HEADER_BYTES)
+            .await
+            .unwrap();
+        assert_eq!(headers.path(), Some("/test"));
+        assert_eq!(headers.get("host"), Some("localhost"));
+        let mut rest = String::new();
+        reader.read_to_string(&mut rest).await.unwrap();
+        assert_eq!(rest, "rest");
+
+        let mut reader = BufReader::new(b"HTTP/1.1 200 OK\n\n".as_slice());
+        let headers = read_http_headers(&mut reader, DEFAULT_MAX_HTTP_HEADER_BYTES)
+            .await
+            .unwrap();
+        assert_eq!(headers.start_line, "HTTP/1.1 200 OK");
+        assert_eq!(headers.path(), None);
+        assert!(headers.headers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn oversized_headers_are_rejected() {
+        // Exactly at the limit
+        let headers = read_http_headers(&mut BufReader::new(HANDSHAKE), HANDSHAKE.len()).await;
+        headers.unwrap();
+        let err = read_http_headers(&mut BufReader::new(HANDSHAKE), HANDSHAKE.len() - 1)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HttpHeadersError::TooLarge { .. }), "{err}");
+
+        // A peer streaming garbage without ever ending the headers
+        let mut garbage = BufReader::new(tokio::io::repeat(b'x'));
+        let err = read_http_headers(&mut garbage, 1024).await.unwrap_err();
+        assert!(
+            matches!(err, HttpHeadersError::TooLarge { limit: 1024 }),
+            "{err}"
+        );
+        let err = skip_http_headers(&mut garbage).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn incomplete_or_malformed_headers_are_rejected() {
+        let mut reader = BufReader::new(b"POST /test HTTP/1.0\r\nHost: localhost\r\n".as_slice());
+        let err = read_http_headers(&mut reader, DEFAULT_MAX_HTTP_HEADER_BYTES)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, HttpHeadersError::Io(err) if err.kind() == io::ErrorKind::UnexpectedEof),
+            "{err}"
+        );
+
+        let mut reader = BufReader::new(b"POST /test HTTP/1.0\r\nno colon\r\n\r\n".as_slice());
+        let err = read_http_headers(&mut reader, DEFAULT_MAX_HTTP_HEADER_BYTES)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, HttpHeadersError::Malformed(_)), "{err}");
+    }
+
+    #[test]
+    fn parsing_status_line() {
+        assert_eq!(
+            parse_status_line("HTTP/1.1 503 Service Unavailable").unwrap(),
+            (503, "Service Unavailable".to_owned())
+        );
+        assert_eq!(
+            parse_status_line("HTTP/1.0 200").unwrap(),
+            (200, String::new())
+        );
+        parse_status_line("").unwrap_err();
+        parse_status_line("HTTP/1.1").unwrap_err();
+        parse_status_line("SSH-2.0 200 OK").unwrap_err();
+    }
+}
@@ -4,84 +4,406 @@
 //! Each connection is established with retry logic and HTTP-like handshake to
 //! work with HTTP load balancers. Its then dropped to raw TCP that is handled
 //! depending on component implementation
+//!
+//! Connections are plaintext by default; see [`connect_tls`] and [`accept_maybe_tls`] for TLS.
+//! [`connect`] does not wait for the server to respond to the handshake; [`connect_checked`] does and
+//! fails on non-2xx responses, e.g. from a load balancer without healthy backends.
+//! Servers perform their side of the handshake with [`accept_handshake`].
+//!
+//! TCP keepalive is enabled on connections by default (see [`KeepaliveConfig`]), so that a peer
+//! dropped silently (e.g. by a NAT) is noticed even if the connection is idle. Protocols can
+//! additionally detect dead peers with [`spawn_liveness_probe`].
+//!
+//! Servers protect their listeners with [`ListenerLimits`]: a [`ConnectionLimiter`] caps concurrent
+//! connections, and frames are read with a [`FrameCodec`] bounding their size.
 
 use anyhow::Context as _;
 use backon::ExponentialBuilder;
 use backon::Retryable;
 use std::fmt::Display;
+use std::future::Future;
 use std::time::Duration;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 use tokio::net::{TcpStream, ToSocketAddrs};
+use tokio_util::sync::CancellationToken;
+
+mod accept;
+mod http;
+mod limits;
+mod liveness;
+mod metrics;
+mod tls;
+
+/// Sent with the handshake, so that servers can tell which release a client is running.
+const USER_AGENT: &str = concat!("zksync-os/", env!("CARGO_PKG_VERSION"));
+
+pub use accept::{AcceptHandshakeError, AcceptedConnection, accept_handshake};
+use http::read_http_response;
+pub use http::{
+    DEFAULT_MAX_HTTP_HEADER_BYTES, HttpHandshakeError, HttpHeaders, HttpHeadersError,
+    read_http_headers, skip_http_headers, write_http_ok,
+};
+pub use limits::{ConnectionLimiter, FrameCodec, FrameTooLarge, ListenerLimits};
+pub use liveness::{ActivityReader, spawn_liveness_probe};
+pub use tls::{
+    MaybeTlsStream, TlsAcceptor, TlsConfig, TlsConnector, TlsIdentity, TlsServerConfig,
+    accept_maybe_tls,
+};
+
+/// TCP keepalive settings. The OS probes an idle connection after `time`, then every `interval`,
+/// and closes the connection after `retries` unanswered probes.
+///
+/// With the default settings, a dead peer is noticed within about a minute of inactivity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeepaliveConfig {
+    pub time: Duration,
+    pub interval: Duration,
+    pub retries: u32,
+}
+
+impl Default for KeepaliveConfig {
+    fn default() -> Self {
+        Self {
+            time: Duration::from_secs(30),
+            interval: Duration::from_secs(10),
+            retries: 3,
+        }
+    }
+}
+
+impl KeepaliveConfig {
+    fn apply(&self, socket: &TcpStream) -> std::io::Result<()> {
+        let keepalive = socket2::TcpKeepalive::new()
+            .with_time(self.time)
+            .with_interval(self.interval);
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        let keepalive = keepalive.with_retries(self.retries);
+        socket2::SockRef::from(socket).set_tcp_keepalive(&keepalive)
+    }
+}
+
+/// Connection options for [`connect_with_options`]. Delays between attempts grow exponentially
+/// from `min_delay` to `max_delay`.
+///
+/// The default matches the policy of [`connect`]: 1s to 20s delays, doubled after each attempt, up to 15 retries,
+/// and the default [`KeepaliveConfig`].
+#[derive(Debug, Clone)]
+pub struct ConnectOptions {
+    pub min_delay: Duration,
+    pub max_delay: Duration,
+    pub factor: f32,
+    /// Maximum number of retries after the first attempt. `None` means retrying indefinitely.
+    pub max_attempts: Option<usize>,
+    /// Gives up if the connection is not established within this time (including all retries).
+    pub deadline: Option<Duration>,
+    /// Gives up as soon as the token is cancelled, e.g. when the component is shutting down.
+    pub cancellation: Option<CancellationToken>,
+    /// TCP keepalive for the established connection; disabled if `None`.
+    pub keepalive: Option<KeepaliveConfig>,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        Self {
+            min_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(20),
+            factor: 2.0,
+            max_attempts: Some(15),
+            deadline: None,
+            cancellation: None,
+            keepalive: Some(KeepaliveConfig::default()),
+        }
+    }
+}
+
+impl ConnectOptions {
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    pub fn with_keepalive(mut self, keepalive: Option<KeepaliveConfig>) -> Self {
+        self.keepalive = keepalive;
+        self
+    }
+
+    fn backoff(&self) -> ExponentialBuilder {
+        let builder = ExponentialBuilder::default()
+            .with_factor(self.factor)
+            .with_min_delay(self.min_delay)
+            .with_max_delay(self.max_delay);
+        match self.max_attempts {
+            Some(max_attempts) => builder.with_max_times(max_attempts),
+            None => builder.without_max_times(),
+        }
+    }
+}
 
 /// Connects to a TCP server with retry logic and performs HTTP handshake.
 ///
-/// This function uses exponential backoff retry logic with hardcoded parameters
+/// This function uses exponential backoff retry logic with default [`ConnectOptions`].
 /// After establishing the TCP connection, it automatically performs an HTTP-like
 /// handshake.
 pub async fn connect<A: ToSocketAddrs + Display>(
     address: A,
     path: &str,
 ) -> anyhow::Result<TcpStream> {
-    let mut socket = (|| TcpStream::connect(&address))
-        .retry(
-            ExponentialBuilder::default()
-                .with_factor(2.0)
-                .with_min_delay(Duration::from_secs(1))
-                .with_max_delay(Duration::from_secs(20))
-                .with_max_times(15),
-        )
-        .notify(|err, dur| {
-            tracing::info!(
-                ?err,
-                ?dur,
-                "retrying connection to server {}{}",
-                &address,
-                path
-            );
-        })
+    connect_with_options(address, path, &ConnectOptions::default()).await
+}
+
+/// Same as [`connect`], but with a custom retry policy.
+pub async fn connect_with_options<A: ToSocketAddrs + Display>(
+    address: A,
+    path: &str,
+    options: &ConnectOptions,
+) -> anyhow::Result<TcpStream> {
+    let target = format!("{address}{path}");
+    let mut socket = retry(options, &target, || connect_tcp(&address, options))
         .await
         .context("Failed to connect to server")?;
+    write_http_handshake(&mut socket, path)
+        .await
+        .context("Failed to write HTTP handshake")?;
+    Ok(socket)
+}
+
+/// Same as [`connect`], but also checks the HTTP response of the server before dropping to raw TCP.
+///
+/// Fails if the response status is not 2xx. Statuses signaling a temporary condition (e.g., 503 from
+/// a load balancer) are retried according to the default [`ConnectOptions`]; other statuses fail right away.
+/// Requires the server to respond to the handshake, e.g. with [`write_http_ok`].
+pub async fn connect_checked<A: ToSocketAddrs + Display>(
+    address: A,
+    path: &str,
+) -> anyhow::Result<TcpStream> {
+    connect_checked_with_options(address, path, &ConnectOptions::default()).await
+}
+
+/// Same as [`connect_checked`], but with a custom retry policy.
+pub async fn connect_checked_with_options<A: ToSocketAddrs + Display>(
+    address: A,
+    path: &str,
+    options: &ConnectOptions,
+) -> anyhow::Result<TcpStream> {
+    let target = format!("{address}{path}");
+    let address = &address;
+    retry_when(
+        options,
+        &target,
+        || async move {
+            let mut socket = connect_tcp(address, options).await?;
+            write_http_handshake(&mut socket, path).await?;
+            read_http_response(&mut socket).await?;
+            Ok::<_, HttpHandshakeError>(socket)
+        },
+        HttpHandshakeError::is_retryable,
+    )
+    .await
+    .with_context(|| format!("Failed to connect to {target}"))
+}
+
+/// Same as [`connect`], but the connection is wrapped in TLS before the HTTP handshake.
+pub async fn connect_tls<A: ToSocketAddrs + Display>(
+    address: A,
+    path: &str,
+    tls_config: &TlsConfig,
+) -> anyhow::Result<MaybeTlsStream> {
+    connect_tls_with_options(address, path, tls_config, &ConnectOptions::default()).await
+}
 
-    // Perform HTTP handshake
-    let handshake = format!("POST {path} HTTP/1.0\r\n\r\n");
-    socket
-        .write_all(handshake.as_bytes())
+/// Same as [`connect_tls`], but with custom connection options.
+pub async fn connect_tls_with_options<A: ToSocketAddrs + Display>(
+    address: A,
+    path: &str,
+    tls_config: &TlsConfig,
+    options: &ConnectOptions,
+) -> anyhow::Result<MaybeTlsStream> {
+    let connector = tls_config.connector()?;
+    let server_name = tls_config.server_name(&address.to_string())?;
+    let target = format!("{address}{path}");
+    let socket = retry(options, &target, || connect_tcp(&address, options))
+        .await
+        .context("Failed to connect to server")?;
+    let mut stream = connector
+        .connect(server_name, socket)
+        .await
+        .with_context(|| format!("TLS handshake with {target} failed"))?;
+    write_http_handshake(&mut stream, path)
         .await
         .context("Failed to write HTTP handshake")?;
+    Ok(MaybeTlsStream::Tls(Box::new(stream.into())))
+}
 
+async fn connect_tcp<A: ToSocketAddrs>(
+    address: A,
+    options: &ConnectOptions,
+) -> std::io::Result<TcpStream> {
+    let socket = TcpStream::connect(address).await?;
+    if let Some(keepalive) = &options.keepalive {
+        keepalive.apply(&socket)?;
+    }
     Ok(socket)
 }
 
-use tokio::io::AsyncBufRead;
-use tokio::io::AsyncBufReadExt;
-
-pub async fn skip_http_headers<R: AsyncBufRead + Unpin>(
-    reader: &mut R,
-) -> Result<(), std::io::Error> {
-    // Detects two consecutive line endings, which may be \r\n or \n.
-    let mut empty_line = false;
-    loop {
-        let buf = reader.fill_buf().await?;
-        if buf.is_empty() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::UnexpectedEof,
-                "EOF reached before end of headers",
-            ));
+async fn write_http_handshake<S: AsyncWrite + Unpin>(
+    socket: &mut S,
+    path: &str,
+) -> std::io::Result<()> {
+    let handshake = format!("POST {path} HTTP/1.0\r\nUser-Agent: {USER_AGENT}\r\n\r\n");
+    socket.write_all(handshake.as_bytes()).await?;
+    // TLS streams buffer writes until flushed
+    socket.flush().await
+}
+
+async fn retry<T, Fut>(
+    options: &ConnectOptions,
+    target: &str,
+    attempt: impl FnMut() -> Fut,
+) -> anyhow::Result<T>
+where
+    Fut: Future<Output = std::io::Result<T>>,
+{
+    retry_when(options, target, attempt, |_| true).await
+}
+
+/// Same as [`retry`], but only errors for which `retryable` returns `true` are retried.
+async fn retry_when<T, E, Fut>(
+    options: &ConnectOptions,
+    target: &str,
+    attempt: impl FnMut() -> Fut,
+    retryable: impl FnMut(&E) -> bool,
+) -> anyhow::Result<T>
+where
+    Fut: Future<Output = Result<T, E>>,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    let retries = attempt
+        .retry(options.backoff())
+        .when(retryable)
+        .notify(|err, dur| {
+            tracing::info!(?err, ?dur, "retrying connection to server {target}");
+        });
+    let with_deadline = async {
+        match options.deadline {
+            Some(deadline) => tokio::time::timeout(deadline, retries)
+                .await
+                .with_context(|| format!("connection to {target} timed out after {deadline:?}"))?
+                .map_err(Into::into),
+            None => retries.await.map_err(Into::into),
+        }
+    };
+    match &options.cancellation {
+        Some(cancellation) => tokio::select! {
+            result = with_deadline => result,
+            () = cancellation.cancelled() => {
+                anyhow::bail!("connection to {target} was cancelled")
+            }
+        },
+        None => with_deadline.await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    fn fast_options(max_attempts: Option<usize>) -> ConnectOptions {
+        ConnectOptions {
+            min_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            max_attempts,
+            ..ConnectOptions::default()
         }
+    }
 
-        for (i, &byte) in buf.iter().enumerate() {
-            if byte == b'\n' {
-                if empty_line {
-                    reader.consume(i + 1);
-                    return Ok(());
-                }
-                empty_line = true;
-            } else if byte != b'\r' {
-                empty_line = false;
+    fn refused() -> std::io::Result<()> {
+        Err(std::io::ErrorKind::ConnectionRefused.into())
+    }
+
+    #[tokio::test]
+    async fn retries_respect_max_attempts() {
+        let attempts = &AtomicUsize::new(0);
+        let result = retry(&fast_options(Some(3)), "test", || async move {
+            attempts.fetch_add(1, Ordering::Relaxed);
+            refused()
+        })
+        .await;
+        result.unwrap_err();
+        // The first attempt + 3 retries
+        assert_eq!(attempts.load(Ordering::Relaxed), 4);
+
+        let attempts = &AtomicUsize::new(0);
+        let result = retry(&fast_options(Some(3)), "test", || async move {
+            if attempts.fetch_add(1, Ordering::Relaxed) < 2 {
+                refused()
+            } else {
+                Ok(())
             }
+        })
+        .await;
+        result.unwrap();
+        assert_eq!(attempts.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test]
+    async fn keepalive_is_configured() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let keepalive = KeepaliveConfig {
+            time: Duration::from_secs(42),
+            ..KeepaliveConfig::default()
+        };
+
+        let options = ConnectOptions::default().with_keepalive(Some(keepalive));
+        let socket = connect_tcp(address, &options).await.unwrap();
+        let socket = socket2::SockRef::from(&socket);
+        assert!(socket.keepalive().unwrap());
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        assert_eq!(socket.keepalive_time().unwrap(), Duration::from_secs(42));
+
+        let options = ConnectOptions::default().with_keepalive(None);
+        let socket = connect_tcp(address, &options).await.unwrap();
+        assert!(!socket2::SockRef::from(&socket).keepalive().unwrap());
+    }
+
+    #[tokio::test]
+    async fn deadline_stops_retries() {
+        let options = ConnectOptions {
+            deadline: Some(Duration::from_millis(50)),
+            ..fast_options(None)
+        };
+        let err = retry(&options, "test", || async { refused() })
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("timed out"), "{err:#}");
+    }
+
+    #[tokio::test]
+    async fn cancellation_interrupts_retries() {
+        let cancellation = CancellationToken::new();
+        // Retrying indefinitely, with delays much longer than the test
+        let options = ConnectOptions {
+            min_delay: Duration::from_secs(60),
+            max_delay: Duration::from_secs(60),
+            max_attempts: None,
+            ..ConnectOptions::default()
         }
+        .with_cancellation(cancellation.clone());
 
-        let len = buf.len();
-        reader.consume(len);
+        tokio::spawn({
+            let cancellation = cancellation.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                cancellation.cancel();
+            }
+        });
+        let started_at = Instant::now();
+        let err = retry(&options, "test", || async { refused() })
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("cancelled"), "{err:#}");
+        assert!(started_at.elapsed() < Duration::from_secs(5));
     }
 }
@@ -0,0 +1,200 @@
+//! Limits protecting listeners from misbehaving peers: a cap on concurrent connections, a deadline
+//! on the handshake and a max size of frames.
+
+use crate::metrics::SOCKET_METRICS;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_util::bytes::{Bytes, BytesMut};
+use tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec, LengthDelimitedCodecError};
+
+/// Limits of connections accepted by a listener.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListenerLimits {
+    /// Max number of connections served at the same time. Connections over the cap are closed
+    /// right after being accepted.
+    pub max_connections: usize,
+    /// Max time for a client to complete the handshake (TLS, if enabled, and HTTP headers) after
+    /// connecting.
+    pub handshake_timeout: Duration,
+}
+
+impl Default for ListenerLimits {
+    fn default() -> Self {
+        Self {
+            max_connections: 256,
+            handshake_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Caps the number of concurrent connections of a listener.
+#[derive(Debug, Clone)]
+pub struct ConnectionLimiter {
+    listener: &'static str,
+    permits: Arc<Semaphore>,
+}
+
+impl ConnectionLimiter {
+    /// `listener` names the listener in logs and metrics.
+    pub fn new(listener: &'static str, max_connections: usize) -> Self {
+        Self {
+            listener,
+            permits: Arc::new(Semaphore::new(max_connections)),
+        }
+    }
+
+    /// Accepts the next connection that fits under the cap. The connection counts towards the cap
+    /// until the returned permit is dropped.
+    ///
+    /// Connections over the cap are closed right away (and counted in metrics) instead of being
+    /// left in the backlog, so that clients notice the rejection and can retry elsewhere.
+    pub async fn accept(
+        &self,
+        listener: &TcpListener,
+    ) -> io::Result<(TcpStream, SocketAddr, OwnedSemaphorePermit)> {
+        loop {
+            let (socket, addr) = listener.accept().await?;
+            match self.permits.clone().try_acquire_owned() {
+                Ok(permit) => return Ok((socket, addr, permit)),
+                Err(_) => {
+                    tracing::warn!(
+                        listener = self.listener,
+                        "Rejecting connection from {addr}: connection limit reached"
+                    );
+                    SOCKET_METRICS.rejected_connections[&self.listener].inc();
+                }
+            }
+        }
+    }
+}
+
+/// Frame exceeding the max size of a [`FrameCodec`]. Surfaced as [`io::ErrorKind::InvalidData`];
+/// the peer sending it is expected to be disconnected.
+#[derive(Debug, thiserror::Error)]
+#[error("frame exceeds the limit of {limit} bytes")]
+pub struct FrameTooLarge {
+    pub limit: usize,
+}
+
+impl From<FrameTooLarge> for io::Error {
+    fn from(err: FrameTooLarge) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+impl FrameTooLarge {
+    /// Returns the error if `err` was caused by an oversized frame.
+    pub fn from_io(err: &io::Error) -> Option<&Self> {
+        err.get_ref()?.downcast_ref()
+    }
+}
+
+/// Length-delimited frames (4-byte big-endian length prefix) of at most `max_frame_bytes`.
+///
+/// Both decoding and encoding an oversized frame fail with [`FrameTooLarge`]; the decoder doesn't
+/// buffer the frame before failing.
+#[derive(Debug)]
+pub struct FrameCodec {
+    inner: LengthDelimitedCodec,
+    max_frame_bytes: usize,
+}
+
+impl FrameCodec {
+    pub fn new(max_frame_bytes: usize) -> Self {
+        Self {
+            inner: LengthDelimitedCodec::builder()
+                .max_frame_length(max_frame_bytes)
+                .new_codec(),
+            max_frame_bytes,
+        }
+    }
+
+    fn map_err(&self, err: io::Error) -> io::Error {
+        let too_large = err
+            .get_ref()
+            .is_some_and(|inner| inner.is::<LengthDelimitedCodecError>());
+        if too_large {
+            FrameTooLarge {
+                limit: self.max_frame_bytes,
+            }
+            .into()
+        } else {
+            err
+        }
+    }
+}
+
+impl Decoder for FrameCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<BytesMut>> {
+        self.inner.decode(src).map_err(|err| self.map_err(err))
+    }
+}
+
+impl Encoder<Bytes> for FrameCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: Bytes, dst: &mut BytesMut) -> io::Result<()> {
+        self.inner
+            .encode(item, dst)
+            .map_err(|err| self.map_err(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn connections_over_cap_are_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let limiter = ConnectionLimiter::new("cap_test", 1);
+
+        let _first = TcpStream::connect(address).await.unwrap();
+        let (_socket, _, permit) = limiter.accept(&listener).await.unwrap();
+
+        let mut second = TcpStream::connect(address).await.unwrap();
+        let accepting = tokio::spawn({
+            let limiter = limiter.clone();
+            async move { limiter.accept(&listener).await.map(|_| ()) }
+        });
+        // The second client is disconnected while the first one holds the only permit
+        let mut buf = [0; 1];
+        assert_eq!(second.read(&mut buf).await.unwrap_or(0), 0);
+        assert_eq!(SOCKET_METRICS.rejected_connections[&"cap_test"].get(), 1);
+
+        // Once the permit is released, new connections are accepted again
+        drop(permit);
+        let _third = TcpStream::connect(address).await.unwrap();
+        accepting.await.unwrap().unwrap();
+    }
+
+    #[test]
+    fn oversized_frames_are_rejected() {
+        let mut codec = FrameCodec::new(8);
+        let mut buf = BytesMut::new();
+        codec
+            .encode(Bytes::from_static(b"12345678"), &mut buf)
+            .unwrap();
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), &b"12345678"[..]);
+
+        let err = codec
+            .encode(Bytes::from_static(b"123456789"), &mut buf)
+            .unwrap_err();
+        assert_eq!(FrameTooLarge::from_io(&err).unwrap().limit, 8);
+
+        // Only the length prefix of an oversized frame needs to arrive for it to be rejected
+        let mut buf = BytesMut::from(&1_000_000_u32.to_be_bytes()[..]);
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(FrameTooLarge::from_io(&err).unwrap().limit, 8);
+    }
+}
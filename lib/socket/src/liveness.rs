@@ -0,0 +1,122 @@
+//! Application-level detection of dead peers.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+/// Read half of a connection that records when data was last received from the peer.
+/// Created by [`spawn_liveness_probe`].
+#[derive(Debug)]
+pub struct ActivityReader<R> {
+    inner: R,
+    last_read: watch::Sender<Instant>,
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for ActivityReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let filled_before = buf.filled().len();
+        let poll = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if matches!(poll, Poll::Ready(Ok(()))) && buf.filled().len() > filled_before {
+            this.last_read.send_replace(Instant::now());
+        }
+        poll
+    }
+}
+
+/// Watches for data received through `reader`. The returned channel flips to `false` once nothing
+/// is received for `timeout`.
+///
+/// Useful for protocols where the peer can stay silent for a long time: the protocol owner sends
+/// ping frames more often than `timeout` and the peer answers them, so that a peer that
+/// disappeared without closing the connection is noticed. Any received data counts as a sign of
+/// life, not just the answers to pings.
+///
+/// The probe stops when the returned reader is dropped.
+pub fn spawn_liveness_probe<R: AsyncRead + Unpin>(
+    reader: R,
+    timeout: Duration,
+) -> (ActivityReader<R>, watch::Receiver<bool>) {
+    let (last_read, mut last_read_receiver) = watch::channel(Instant::now());
+    let (alive, alive_receiver) = watch::channel(true);
+    tokio::spawn(async move {
+        loop {
+            let deadline = *last_read_receiver.borrow_and_update() + timeout;
+            tokio::select! {
+                changed = last_read_receiver.changed() => {
+                    if changed.is_err() {
+                        // The reader is dropped
+                        return;
+                    }
+                }
+                () = tokio::time::sleep_until(deadline) => {
+                    tracing::info!(?timeout, "peer has not sent anything, considering it dead");
+                    alive.send_replace(false);
+                    return;
+                }
+                () = alive.closed() => return,
+            }
+        }
+    });
+    let reader = ActivityReader {
+        inner: reader,
+        last_read,
+    };
+    (reader, alive_receiver)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    const TIMEOUT: Duration = Duration::from_millis(100);
+
+    #[tokio::test]
+    async fn active_peer_stays_alive() {
+        let (mut peer, stream) = tokio::io::duplex(64);
+        let (mut reader, alive) = spawn_liveness_probe(stream, TIMEOUT);
+        for _ in 0..5 {
+            tokio::time::sleep(TIMEOUT / 2).await;
+            peer.write_u8(1).await.unwrap();
+            reader.read_u8().await.unwrap();
+            assert!(*alive.borrow());
+        }
+    }
+
+    #[tokio::test]
+    async fn silent_peer_is_considered_dead() {
+        let (mut peer, stream) = tokio::io::duplex(64);
+        let (mut reader, mut alive) = spawn_liveness_probe(stream, TIMEOUT);
+        peer.write_u8(1).await.unwrap();
+        reader.read_u8().await.unwrap();
+
+        let started_at = Instant::now();
+        tokio::time::timeout(TIMEOUT * 10, alive.wait_for(|alive| !alive))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(started_at.elapsed() >= TIMEOUT / 2);
+    }
+
+    #[tokio::test]
+    async fn probe_stops_with_reader() {
+        let (_peer, stream) = tokio::io::duplex(64);
+        let (reader, mut alive) = spawn_liveness_probe(stream, Duration::from_secs(60));
+        drop(reader);
+        // The probe task exits, dropping the sender
+        tokio::time::timeout(TIMEOUT, alive.changed())
+            .await
+            .unwrap()
+            .unwrap_err();
+        assert!(*alive.borrow());
+    }
+}
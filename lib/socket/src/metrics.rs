@@ -0,0 +1,13 @@
+use vise::{Counter, LabeledFamily, Metrics};
+
+#[derive(Debug, Metrics)]
+#[metrics(prefix = "socket")]
+pub(crate) struct SocketMetrics {
+    /// Number of accepted connections closed right away because the listener was at its
+    /// connection cap.
+    #[metrics(labels = ["listener"])]
+    pub rejected_connections: LabeledFamily<&'static str, Counter>,
+}
+
+#[vise::register]
+pub(crate) static SOCKET_METRICS: vise::Global<SocketMetrics> = vise::Global::new();
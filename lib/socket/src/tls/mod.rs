@@ -0,0 +1,224 @@
+//! Optional TLS on top of raw TCP links.
+//!
+//! Plaintext stays the default; TLS is enabled by passing a [`TlsConfig`] to
+//! [`connect_tls`](crate::connect_tls) on the client side, and a [`TlsAcceptor`] built from
+//! [`TlsServerConfig`] to [`accept_maybe_tls`] on the server side.
+
+use anyhow::Context as _;
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsStream;
+use tokio_rustls::rustls::crypto::{CryptoProvider, ring};
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore, ServerConfig};
+
+pub use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+#[cfg(test)]
+mod tests;
+
+/// Certificate chain and the matching private key, both PEM-encoded.
+#[derive(Debug, Clone)]
+pub struct TlsIdentity {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+impl TlsIdentity {
+    fn load(&self) -> anyhow::Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)> {
+        let certs = load_certs(&self.cert_path)?;
+        let key = PrivateKeyDer::from_pem_file(&self.key_path)
+            .with_context(|| format!("cannot read private key `{}`", self.key_path.display()))?;
+        Ok((certs, key))
+    }
+}
+
+/// Client-side TLS settings.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// PEM bundle with the root CAs the server certificate is verified against.
+    pub root_ca_path: PathBuf,
+    /// Client certificate presented to servers that require client authentication.
+    pub client_identity: Option<TlsIdentity>,
+    /// Name the server certificate is verified for. Defaults to the host part of the address.
+    pub server_name: Option<String>,
+}
+
+impl TlsConfig {
+    pub fn connector(&self) -> anyhow::Result<TlsConnector> {
+        let builder = ClientConfig::builder_with_provider(crypto_provider())
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(load_roots(&self.root_ca_path)?);
+        let config = match &self.client_identity {
+            Some(identity) => {
+                let (certs, key) = identity.load()?;
+                builder
+                    .with_client_auth_cert(certs, key)
+                    .context("invalid client certificate")?
+            }
+            None => builder.with_no_client_auth(),
+        };
+        Ok(TlsConnector::from(Arc::new(config)))
+    }
+
+    pub(crate) fn server_name(&self, address: &str) -> anyhow::Result<ServerName<'static>> {
+        let name = match &self.server_name {
+            Some(name) => name.as_str(),
+            None => host(address),
+        };
+        ServerName::try_from(name.to_owned())
+            .with_context(|| format!("invalid TLS server name `{name}`"))
+    }
+}
+
+/// Server-side TLS settings.
+#[derive(Debug, Clone)]
+pub struct TlsServerConfig {
+    pub identity: TlsIdentity,
+    /// If set, clients must present a certificate issued by one of the CAs in this PEM bundle.
+    pub client_ca_path: Option<PathBuf>,
+}
+
+impl TlsServerConfig {
+    pub fn acceptor(&self) -> anyhow::Result<TlsAcceptor> {
+        let builder = ServerConfig::builder_with_provider(crypto_provider())
+            .with_safe_default_protocol_versions()?;
+        let builder = match &self.client_ca_path {
+            Some(path) => {
+                let verifier = WebPkiClientVerifier::builder_with_provider(
+                    Arc::new(load_roots(path)?),
+                    crypto_provider(),
+                )
+                .build()?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let (certs, key) = self.identity.load()?;
+        let config = builder
+            .with_single_cert(certs, key)
+            .context("invalid server certificate")?;
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
+/// Both `ring` and `aws-lc-rs` end up enabled in the dependency graph, so rustls cannot pick
+/// the default provider on its own.
+fn crypto_provider() -> Arc<CryptoProvider> {
+    Arc::new(ring::default_provider())
+}
+
+fn load_certs(path: &Path) -> anyhow::Result<Vec<CertificateDer<'static>>> {
+    CertificateDer::pem_file_iter(path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("cannot read certificates `{}`", path.display()))
+}
+
+fn load_roots(path: &Path) -> anyhow::Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(path)? {
+        roots.add(cert)?;
+    }
+    Ok(roots)
+}
+
+/// Host part of `host:port`, without brackets for IPv6 addresses.
+fn host(address: &str) -> &str {
+    let host = address
+        .rsplit_once(':')
+        .map_or(address, |(host, _port)| host);
+    host.trim_start_matches('[').trim_end_matches(']')
+}
+
+/// Performs the server side of the TLS handshake if `acceptor` is set; otherwise, keeps the connection plaintext.
+pub async fn accept_maybe_tls(
+    socket: TcpStream,
+    acceptor: Option<&TlsAcceptor>,
+) -> io::Result<MaybeTlsStream> {
+    Ok(match acceptor {
+        Some(acceptor) => MaybeTlsStream::Tls(Box::new(acceptor.accept(socket).await?.into())),
+        None => MaybeTlsStream::Plain(socket),
+    })
+}
+
+/// TCP connection that may be wrapped in TLS.
+#[derive(Debug)]
+pub enum MaybeTlsStream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl MaybeTlsStream {
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            Self::Plain(stream) => stream.peer_addr(),
+            Self::Tls(stream) => stream.get_ref().0.peer_addr(),
+        }
+    }
+}
+
+impl AsyncRead for MaybeTlsStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_read(cx, buf),
+            Self::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for MaybeTlsStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_write(cx, buf),
+            Self::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_flush(cx),
+            Self::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_shutdown(cx),
+            Self::Tls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Plain(stream) => Pin::new(stream).poll_write_vectored(cx, bufs),
+            Self::Tls(stream) => Pin::new(stream.as_mut()).poll_write_vectored(cx, bufs),
+        }
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        match self {
+            Self::Plain(stream) => stream.is_write_vectored(),
+            Self::Tls(stream) => stream.is_write_vectored(),
+        }
+    }
+}
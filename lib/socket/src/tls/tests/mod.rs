@@ -0,0 +1,120 @@
+//! Certificates in this directory are issued by a test CA (`ca.pem`) for 100 years: `server.pem`
+//! for `localhost` / `127.0.0.1` and `client.pem` for client authentication.
+
+use super::*;
+use crate::{connect, connect_tls, skip_http_headers};
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+fn test_file(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("src/tls/tests")
+        .join(name)
+}
+
+fn identity(name: &str) -> TlsIdentity {
+    TlsIdentity {
+        cert_path: test_file(&format!("{name}.pem")),
+        key_path: test_file(&format!("{name}.key")),
+    }
+}
+
+fn client_config(client_identity: Option<TlsIdentity>) -> TlsConfig {
+    TlsConfig {
+        root_ca_path: test_file("ca.pem"),
+        client_identity,
+        server_name: None,
+    }
+}
+
+fn server_config(require_client_cert: bool) -> TlsServerConfig {
+    TlsServerConfig {
+        identity: identity("server"),
+        client_ca_path: require_client_cert.then(|| test_file("ca.pem")),
+    }
+}
+
+/// Serves a single connection: skips HTTP headers, reads a number and echoes it back incremented.
+async fn spawn_server(
+    acceptor: Option<TlsAcceptor>,
+) -> (String, tokio::task::JoinHandle<io::Result<()>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap().to_string();
+    let server = tokio::spawn(async move {
+        let (socket, _) = listener.accept().await?;
+        let stream = accept_maybe_tls(socket, acceptor.as_ref()).await?;
+        let (recv, mut send) = tokio::io::split(stream);
+        let mut reader = BufReader::new(recv);
+        skip_http_headers(&mut reader).await?;
+        let value = reader.read_u64().await?;
+        send.write_u64(value + 1).await?;
+        send.flush().await
+    });
+    (address, server)
+}
+
+async fn exchange(stream: &mut MaybeTlsStream) -> io::Result<u64> {
+    stream.write_u64(41).await?;
+    stream.flush().await?;
+    stream.read_u64().await
+}
+
+#[tokio::test]
+async fn plaintext_is_kept_without_acceptor() {
+    let (address, server) = spawn_server(None).await;
+    let socket = connect(&address, "/test").await.unwrap();
+    let mut stream = MaybeTlsStream::Plain(socket);
+    assert_eq!(exchange(&mut stream).await.unwrap(), 42);
+    server.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn http_handshake_over_tls() {
+    let acceptor = server_config(false).acceptor().unwrap();
+    let (address, server) = spawn_server(Some(acceptor)).await;
+    let mut stream = connect_tls(&address, "/test", &client_config(None))
+        .await
+        .unwrap();
+    assert!(matches!(stream, MaybeTlsStream::Tls(_)));
+    assert_eq!(exchange(&mut stream).await.unwrap(), 42);
+    server.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn client_certificate_is_required_if_configured() {
+    let acceptor = server_config(true).acceptor().unwrap();
+    let (address, server) = spawn_server(Some(acceptor)).await;
+    let mut stream = connect_tls(&address, "/test", &client_config(Some(identity("client"))))
+        .await
+        .unwrap();
+    assert_eq!(exchange(&mut stream).await.unwrap(), 42);
+    server.await.unwrap().unwrap();
+
+    let acceptor = server_config(true).acceptor().unwrap();
+    let (address, server) = spawn_server(Some(acceptor)).await;
+    // With TLS 1.3, the client learns about the rejection only after the handshake
+    if let Ok(mut stream) = connect_tls(&address, "/test", &client_config(None)).await {
+        exchange(&mut stream).await.unwrap_err();
+    }
+    server.await.unwrap().unwrap_err();
+}
+
+#[tokio::test]
+async fn server_name_is_verified() {
+    let acceptor = server_config(false).acceptor().unwrap();
+    let (address, _server) = spawn_server(Some(acceptor)).await;
+    let config = TlsConfig {
+        server_name: Some("other.example".to_owned()),
+        ..client_config(None)
+    };
+    let err = connect_tls(&address, "/test", &config).await.unwrap_err();
+    assert!(format!("{err:#}").contains("TLS handshake"), "{err:#}");
+}
+
+#[test]
+fn host_is_extracted_from_address() {
+    assert_eq!(host("localhost:3000"), "localhost");
+    assert_eq!(host("10.0.0.1:3000"), "10.0.0.1");
+    assert_eq!(host("[::1]:3000"), "::1");
+    assert_eq!(host("sequencer.internal"), "sequencer.internal");
+}
@@ -0,0 +1,339 @@
+//! Optional reverse index from account addresses to the flat storage keys written for them.
+//!
+//! Flat storage keys are hashes of `(address, slot)`, so the state itself can't tell which keys
+//! belong to an account. The index stores an `address ++ flat_key` entry for every write, so that
+//! the keys of an account can be listed with a prefix scan.
+//!
+//! The index is **not** part of the consensus state. Blocks are indexed after they are added to
+//! the state, and blocks produced before the index was enabled are backfilled by re-executing
+//! them (see [`AccountKeysIndex::backfill()`]). Genesis storage is not indexed, since the genesis
+//! state only contains flat keys.
+
+use crate::metrics::ACCOUNT_KEYS_METRICS;
+use alloy::primitives::{Address, B256, BlockNumber};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use zksync_os_interface::types::StorageWrite;
+use zksync_os_rocksdb::RocksDB;
+use zksync_os_rocksdb::db::NamedColumnFamily;
+use zksync_os_rocksdb::migrations::{Migration, Schema};
+use zksync_os_storage_api::ReadAccountKeys;
+
+pub const ACCOUNT_KEYS_DB_NAME: &str = "account_keys";
+
+#[derive(Clone, Copy, Debug)]
+pub enum AccountKeysCF {
+    /// `address ++ flat_key` -> empty value.
+    Keys,
+    Meta,
+}
+
+impl NamedColumnFamily for AccountKeysCF {
+    const DB_NAME: &'static str = "account_keys";
+    const ALL: &'static [Self] = &[AccountKeysCF::Keys, AccountKeysCF::Meta];
+
+    fn name(&self) -> &'static str {
+        match self {
+            AccountKeysCF::Keys => "keys",
+            AccountKeysCF::Meta => "meta",
+        }
+    }
+}
+
+/// Schema of the account keys index DB, migrated on node startup.
+pub static ACCOUNT_KEYS_SCHEMA: Schema<AccountKeysCF> = Schema {
+    meta_cf: AccountKeysCF::Meta,
+    migrations: &[Migration::baseline()],
+};
+
+impl AccountKeysCF {
+    fn indexed_block_key() -> &'static [u8] {
+        b"indexed_block"
+    }
+}
+
+#[derive(Debug, Default)]
+struct Progress {
+    /// See [`ReadAccountKeys::indexed_block()`]; persisted in [`AccountKeysCF::Meta`].
+    indexed_block: Option<BlockNumber>,
+    /// Blocks after `indexed_block + 1` that are already indexed (e.g. by the live path while
+    /// earlier blocks are backfilled). Not persisted - they are backfilled again after a restart.
+    ahead: Option<RangeInclusive<BlockNumber>>,
+}
+
+/// Reverse index of flat storage keys, see the module docs.
+///
+/// Cheaply clonable / thread safe
+#[derive(Debug, Clone)]
+pub struct AccountKeysIndex {
+    rocks: RocksDB<AccountKeysCF>,
+    progress: Arc<Mutex<Progress>>,
+}
+
+impl AccountKeysIndex {
+    pub fn new(path: &Path) -> anyhow::Result<Self> {
+        let rocks = RocksDB::<AccountKeysCF>::new(path)?;
+        let indexed_block = rocks
+            .get_cf(AccountKeysCF::Meta, AccountKeysCF::indexed_block_key())?
+            .map(|v| u64::from_be_bytes(v.as_slice().try_into().unwrap()));
+        if let Some(indexed_block) = indexed_block {
+            ACCOUNT_KEYS_METRICS.indexed_block.set(indexed_block);
+        }
+        Ok(Self {
+            rocks,
+            progress: Arc::new(Mutex::new(Progress {
+                indexed_block,
+                ahead: None,
+            })),
+        })
+    }
+
+    /// Indexes storage writes of the block. Blocks may be added more than once and out of order.
+    pub fn add_block(&self, block_number: BlockNumber, writes: &[StorageWrite]) {
+        let mut batch = self.rocks.new_write_batch();
+        for write in writes {
+            batch.put_cf(
+                AccountKeysCF::Keys,
+                &index_key(write.account, write.key),
+                &[],
+            );
+        }
+
+        let mut progress = self.progress.lock().unwrap();
+        let next_block = progress.indexed_block.map_or(1, |block| block + 1);
+        let mut indexed_block = progress.indexed_block;
+        if block_number == next_block {
+            let ahead_end = progress
+                .ahead
+                .take_if(|ahead| *ahead.start() == block_number + 1)
+                .map(|ahead| *ahead.end());
+            indexed_block = Some(ahead_end.unwrap_or(block_number));
+        } else if block_number > next_block {
+            progress.ahead = match progress.ahead.take() {
+                Some(ahead)
+                    if *ahead.end() + 1 >= block_number && *ahead.start() <= block_number =>
+                {
+                    Some(*ahead.start()..=block_number.max(*ahead.end()))
+                }
+                // Blocks before `block_number` are missing; they are indexed on the next backfill
+                _ => Some(block_number..=block_number),
+            };
+        }
+        if indexed_block != progress.indexed_block
+            && let Some(indexed_block) = indexed_block
+        {
+            batch.put_cf(
+                AccountKeysCF::Meta,
+                AccountKeysCF::indexed_block_key(),
+                indexed_block.to_be_bytes().as_ref(),
+            );
+            ACCOUNT_KEYS_METRICS.indexed_block.set(indexed_block);
+        }
+        self.rocks.write(batch).expect("RocksDB write failed");
+        progress.indexed_block = indexed_block;
+    }
+
+    /// Indexes blocks after [`ReadAccountKeys::indexed_block()`] up to `last_block` (inclusive).
+    /// `block_writes` returns the storage writes of a block, normally by re-executing it.
+    pub fn backfill(
+        &self,
+        last_block: BlockNumber,
+        mut block_writes: impl FnMut(BlockNumber) -> anyhow::Result<Vec<StorageWrite>>,
+    ) -> anyhow::Result<()> {
+        let first_block = self.indexed_block().map_or(1, |block| block + 1);
+        if first_block > last_block {
+            return Ok(());
+        }
+        tracing::info!(first_block, last_block, "backfilling account keys index");
+        for block_number in first_block..=last_block {
+            // Blocks may be indexed by the live path in the meantime
+            if self.indexed_block() >= Some(block_number) {
+                continue;
+            }
+            let writes = block_writes(block_number)?;
+            self.add_block(block_number, &writes);
+            ACCOUNT_KEYS_METRICS.backfilled_blocks.inc();
+        }
+        tracing::info!(
+            indexed_block = self.indexed_block(),
+            "backfilled account keys index"
+        );
+        Ok(())
+    }
+}
+
+impl ReadAccountKeys for AccountKeysIndex {
+    fn storage_keys_for_account(&self, address: Address) -> anyhow::Result<Vec<B256>> {
+        Ok(self
+            .rocks
+            .prefix_iterator_cf(AccountKeysCF::Keys, address.as_slice())
+            .map(|(key, _)| B256::from_slice(&key[Address::len_bytes()..]))
+            .collect())
+    }
+
+    fn indexed_block(&self) -> Option<BlockNumber> {
+        self.progress.lock().unwrap().indexed_block
+    }
+}
+
+fn index_key(address: Address, key: B256) -> [u8; 52] {
+    let mut index_key = [0; 52];
+    let (address_part, key_part) = index_key.split_at_mut(Address::len_bytes());
+    address_part.copy_from_slice(address.as_slice());
+    key_part.copy_from_slice(key.as_slice());
+    index_key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn write(account: u8, slot: u8, value: u8) -> StorageWrite {
+        StorageWrite {
+            // Not derived from `(account, slot)`, but unique for each of them
+            key: B256::from_slice(&[[account; 16], [slot; 16]].concat()),
+            value: B256::repeat_byte(value),
+            account: Address::repeat_byte(account),
+            account_key: B256::with_last_byte(slot),
+        }
+    }
+
+    /// Writes of blocks 1..=10: accounts 1 and 2 write slot `block % 3`, account 3 writes
+    /// slot 0 in even blocks.
+    fn blocks() -> HashMap<BlockNumber, Vec<StorageWrite>> {
+        (1..=10)
+            .map(|block: u64| {
+                let slot = (block % 3) as u8;
+                let mut writes = vec![write(1, slot, block as u8), write(2, slot, block as u8)];
+                if block % 2 == 0 {
+                    writes.push(write(3, 0, block as u8));
+                }
+                (block, writes)
+            })
+            .collect()
+    }
+
+    fn all_keys(index: &AccountKeysIndex) -> Vec<Vec<B256>> {
+        (1..=4)
+            .map(|account| {
+                index
+                    .storage_keys_for_account(Address::repeat_byte(account))
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn keys_are_indexed_per_account() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = AccountKeysIndex::new(dir.path()).unwrap();
+        assert_eq!(index.indexed_block(), None);
+
+        index.add_block(1, &[write(1, 1, 1), write(2, 1, 1)]);
+        // Overwrites (including zeroing) don't duplicate keys
+        index.add_block(2, &[write(1, 1, 2), write(1, 2, 2), write(2, 1, 0)]);
+        assert_eq!(index.indexed_block(), Some(2));
+
+        assert_eq!(
+            index
+                .storage_keys_for_account(Address::repeat_byte(1))
+                .unwrap(),
+            [write(1, 1, 0).key, write(1, 2, 0).key]
+        );
+        assert_eq!(
+            index
+                .storage_keys_for_account(Address::repeat_byte(2))
+                .unwrap(),
+            [write(2, 1, 0).key]
+        );
+        assert!(
+            index
+                .storage_keys_for_account(Address::repeat_byte(3))
+                .unwrap()
+                .is_empty()
+        );
+
+        // Progress is persisted
+        drop(index);
+        let index = AccountKeysIndex::new(dir.path()).unwrap();
+        assert_eq!(index.indexed_block(), Some(2));
+        assert_eq!(
+            index
+                .storage_keys_for_account(Address::repeat_byte(1))
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn backfill_is_equivalent_to_live_indexing() {
+        let blocks = blocks();
+        let live_dir = tempfile::tempdir().unwrap();
+        let live = AccountKeysIndex::new(live_dir.path()).unwrap();
+        for block in 1..=10 {
+            live.add_block(block, &blocks[&block]);
+        }
+        assert_eq!(live.indexed_block(), Some(10));
+
+        // Index enabled at block 7: blocks 7..=10 are indexed live while 1..=6 are backfilled
+        let backfilled_dir = tempfile::tempdir().unwrap();
+        let backfilled = AccountKeysIndex::new(backfilled_dir.path()).unwrap();
+        for block in 7..=8 {
+            backfilled.add_block(block, &blocks[&block]);
+        }
+        assert_eq!(backfilled.indexed_block(), None);
+        let mut executed = vec![];
+        backfilled
+            .backfill(6, |block| {
+                executed.push(block);
+                Ok(blocks[&block].clone())
+            })
+            .unwrap();
+        assert_eq!(executed, (1..=6).collect::<Vec<_>>());
+        // Blocks indexed ahead are accounted for once the gap is filled
+        assert_eq!(backfilled.indexed_block(), Some(8));
+        for block in 9..=10 {
+            backfilled.add_block(block, &blocks[&block]);
+        }
+        assert_eq!(backfilled.indexed_block(), Some(10));
+        assert_eq!(all_keys(&backfilled), all_keys(&live));
+        assert!(all_keys(&live)[3].is_empty());
+
+        // Nothing is left to backfill
+        backfilled
+            .backfill(10, |_| panic!("no blocks should be executed"))
+            .unwrap();
+    }
+
+    #[test]
+    fn interrupted_backfill_resumes() {
+        let blocks = blocks();
+        let dir = tempfile::tempdir().unwrap();
+        let index = AccountKeysIndex::new(dir.path()).unwrap();
+        index.add_block(9, &blocks[&9]);
+        let err = index
+            .backfill(8, |block| {
+                anyhow::ensure!(block < 5, "block {block} is not available");
+                Ok(blocks[&block].clone())
+            })
+            .unwrap_err();
+        assert_eq!(err.to_string(), "block 5 is not available");
+        assert_eq!(index.indexed_block(), Some(4));
+
+        // Blocks indexed ahead aren't persisted, so they are backfilled after a restart
+        drop(index);
+        let index = AccountKeysIndex::new(dir.path()).unwrap();
+        let mut executed = vec![];
+        index
+            .backfill(9, |block| {
+                executed.push(block);
+                Ok(blocks[&block].clone())
+            })
+            .unwrap();
+        assert_eq!(executed, (5..=9).collect::<Vec<_>>());
+        assert_eq!(index.indexed_block(), Some(9));
+    }
+}
@@ -1,3 +1,4 @@
+mod account_keys;
 mod metrics;
 mod persistent_preimages;
 pub mod persistent_storage_map;
@@ -6,8 +7,9 @@ mod storage_map_view;
 mod storage_metrics;
 
 use alloy::primitives::{B256, BlockNumber};
+use anyhow::Context;
 use std::ops::RangeInclusive;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::Ordering;
 use std::time::Duration;
 use zksync_os_interface::traits::{PreimageSource, ReadStorage};
@@ -16,13 +18,15 @@ use zksync_os_rocksdb::RocksDB;
 // Re-export commonly used types
 use crate::persistent_preimages::{PersistentPreimages, PreimagesCF};
 use crate::storage_map_view::StorageMapView;
-pub use persistent_storage_map::{PersistentStorageMap, StorageMapCF};
+pub use account_keys::{ACCOUNT_KEYS_DB_NAME, ACCOUNT_KEYS_SCHEMA, AccountKeysIndex};
+pub use persistent_preimages::PREIMAGES_SCHEMA;
+pub use persistent_storage_map::{PersistentStorageMap, STORAGE_MAP_SCHEMA, StorageMapCF};
 pub use storage_map::{Diff, StorageMap};
 use zksync_os_genesis::Genesis;
-use zksync_os_storage_api::{ReadStateHistory, StateError, ViewState, WriteState};
+use zksync_os_storage_api::{ReadStateHistory, StateError, StateResult, ViewState, WriteState};
 
-const STATE_STORAGE_DB_NAME: &str = "state";
-const PREIMAGES_STORAGE_DB_NAME: &str = "preimages";
+pub const STATE_STORAGE_DB_NAME: &str = "state";
+pub const PREIMAGES_STORAGE_DB_NAME: &str = "preimages";
 
 const COMPACTING_DURATION: Duration = Duration::from_millis(100);
 
@@ -43,18 +47,18 @@ impl StateHandle {
         rocks_db_path: PathBuf,
         blocks_to_retain_in_memory: usize,
         genesis: &Genesis,
-    ) -> Self {
+    ) -> anyhow::Result<Self> {
         let state_db = RocksDB::<StorageMapCF>::new(&rocks_db_path.join(STATE_STORAGE_DB_NAME))
-            .expect("Failed to open State DB");
-        let persistent_storage_map = PersistentStorageMap::new(state_db, genesis).await;
+            .context("Failed to open State DB")?;
+        let persistent_storage_map = PersistentStorageMap::new(state_db, genesis).await?;
 
         let storage_map = StorageMap::new(persistent_storage_map, blocks_to_retain_in_memory);
 
         let preimages_db =
             RocksDB::<PreimagesCF>::new(&rocks_db_path.join(PREIMAGES_STORAGE_DB_NAME))
-                .expect("Failed to open Preimages DB");
+                .context("Failed to open Preimages DB")?;
 
-        let persistent_preimages = PersistentPreimages::new(preimages_db, genesis).await;
+        let persistent_preimages = PersistentPreimages::new(preimages_db, genesis).await?;
 
         let storage_map_block = storage_map.latest_block.load(Ordering::Relaxed);
         let preimages_block = persistent_preimages.rocksdb_block_number();
@@ -65,10 +69,10 @@ impl StateHandle {
             "Initializing state storage",
         );
 
-        Self {
+        Ok(Self {
             storage_map,
             persistent_preimages,
-        }
+        })
     }
 
     pub fn compacted_block_number(&self) -> u64 {
@@ -77,6 +81,13 @@ impl StateHandle {
             .rocksdb_block_number()
     }
 
+    /// Reads the last compacted block from the state DB in `rocks_db_path` without initializing
+    /// the state (e.g., to check a restored backup). Returns `None` if the DB is empty.
+    pub fn read_compacted_block_number(rocks_db_path: &Path) -> anyhow::Result<Option<u64>> {
+        let state_db = RocksDB::<StorageMapCF>::new(&rocks_db_path.join(STATE_STORAGE_DB_NAME))?;
+        Ok(persistent_storage_map::rocksdb_block_number(&state_db))
+    }
+
     pub fn state_view_at_block(&self, block_number: u64) -> anyhow::Result<StateView> {
         Ok(StateView {
             storage_map_view: self.storage_map.view_at(block_number)?,
@@ -125,6 +136,21 @@ impl ReadStateHistory for StateHandle {
     fn block_range_available(&self) -> RangeInclusive<u64> {
         self.compacted_block_number()..=self.storage_map.latest_block.load(Ordering::Relaxed)
     }
+
+    fn changed_keys(
+        &self,
+        range: RangeInclusive<BlockNumber>,
+    ) -> StateResult<impl Iterator<Item = (B256, B256)> + '_> {
+        Ok(self.storage_map.changed_keys(range)?.into_iter())
+    }
+
+    fn key_history(
+        &self,
+        key: B256,
+        range: RangeInclusive<BlockNumber>,
+    ) -> StateResult<impl Iterator<Item = (BlockNumber, B256)> + '_> {
+        Ok(self.storage_map.key_history(key, range)?.into_iter())
+    }
 }
 
 impl WriteState for StateHandle {
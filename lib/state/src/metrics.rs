@@ -1,5 +1,5 @@
 use std::time::Duration;
-use vise::{Buckets, Histogram, LabeledFamily, Metrics, Unit};
+use vise::{Buckets, Counter, Gauge, Histogram, LabeledFamily, Metrics, Unit};
 
 const LATENCIES_FAST: Buckets = Buckets::exponential(0.0000001..=1.0, 2.0);
 const BLOCKS_SCANNED: Buckets = Buckets::linear(1.0..=1000.0, 100.0);
@@ -37,9 +37,20 @@ pub struct PreimagesMetrics {
     pub set: LabeledFamily<&'static str, Histogram<Duration>>,
 }
 
+#[derive(Debug, Metrics)]
+#[metrics(prefix = "account_keys")]
+pub struct AccountKeysMetrics {
+    /// Block up to which all blocks are indexed.
+    pub indexed_block: Gauge<u64>,
+    /// Blocks re-executed to backfill the index.
+    pub backfilled_blocks: Counter,
+}
+
 #[vise::register]
 pub(crate) static STORAGE_VIEW_METRICS: vise::Global<StorageViewMetrics> = vise::Global::new();
 #[vise::register]
 pub(crate) static STORAGE_MAP_METRICS: vise::Global<StorageWriteMetrics> = vise::Global::new();
 #[vise::register]
 pub(crate) static PREIMAGES_METRICS: vise::Global<PreimagesMetrics> = vise::Global::new();
+#[vise::register]
+pub(crate) static ACCOUNT_KEYS_METRICS: vise::Global<AccountKeysMetrics> = vise::Global::new();
@@ -4,6 +4,7 @@ use zksync_os_genesis::Genesis;
 use zksync_os_interface::traits::PreimageSource;
 use zksync_os_rocksdb::RocksDB;
 use zksync_os_rocksdb::db::NamedColumnFamily;
+use zksync_os_rocksdb::migrations::{Migration, Schema};
 
 #[derive(Clone, Debug)]
 pub struct PersistentPreimages {
@@ -29,6 +30,12 @@ impl NamedColumnFamily for PreimagesCF {
     }
 }
 
+/// Schema of the preimages DB, migrated on node startup.
+pub static PREIMAGES_SCHEMA: Schema<PreimagesCF> = Schema {
+    meta_cf: PreimagesCF::Meta,
+    migrations: &[Migration::baseline()],
+};
+
 impl PreimagesCF {
     pub fn block_key() -> &'static [u8] {
         b"block"
@@ -36,14 +43,14 @@ impl PreimagesCF {
 }
 
 impl PersistentPreimages {
-    pub async fn new(rocks: RocksDB<PreimagesCF>, genesis: &Genesis) -> Self {
+    pub async fn new(rocks: RocksDB<PreimagesCF>, genesis: &Genesis) -> anyhow::Result<Self> {
         let genesis_needed = rocksdb_block_number(&rocks).is_none();
         let this = Self { rocks };
         if genesis_needed {
-            let force_deploy_preimages = genesis.genesis_upgrade_tx().await.force_deploy_preimages;
+            let force_deploy_preimages = genesis.genesis_upgrade_tx().await?.force_deploy_preimages;
             let iter = genesis
                 .state()
-                .await
+                .await?
                 .preimages
                 .iter()
                 .chain(force_deploy_preimages.iter())
@@ -51,7 +58,7 @@ impl PersistentPreimages {
             this.add(0, iter);
         }
 
-        this
+        Ok(this)
     }
 
     pub fn rocksdb_block_number(&self) -> u64 {
@@ -7,6 +7,7 @@ use tokio::time::Instant;
 use zksync_os_genesis::Genesis;
 use zksync_os_rocksdb::RocksDB;
 use zksync_os_rocksdb::db::NamedColumnFamily;
+use zksync_os_rocksdb::migrations::{Migration, Schema};
 
 /// Wrapper for map of storage diffs that are persisted in RocksDB.
 ///
@@ -40,6 +41,12 @@ impl NamedColumnFamily for StorageMapCF {
     }
 }
 
+/// Schema of the state storage DB, migrated on node startup.
+pub static STORAGE_MAP_SCHEMA: Schema<StorageMapCF> = Schema {
+    meta_cf: StorageMapCF::Meta,
+    migrations: &[Migration::baseline()],
+};
+
 impl StorageMapCF {
     fn base_block_key() -> &'static [u8] {
         b"base_block"
@@ -47,7 +54,7 @@ impl StorageMapCF {
 }
 
 impl PersistentStorageMap {
-    pub async fn new(rocks: RocksDB<StorageMapCF>, genesis: &Genesis) -> Self {
+    pub async fn new(rocks: RocksDB<StorageMapCF>, genesis: &Genesis) -> anyhow::Result<Self> {
         let rocksdb_block_number = rocksdb_block_number(&rocks);
         let this = Self {
             rocks,
@@ -59,14 +66,14 @@ impl PersistentStorageMap {
                 0,
                 genesis
                     .state()
-                    .await
+                    .await?
                     .storage_logs
                     .clone()
                     .into_iter()
                     .collect(),
             );
         }
-        this
+        Ok(this)
     }
 
     pub fn compact_sync(&self, new_block_number: u64, diffs: HashMap<B256, B256>) {
@@ -139,7 +146,7 @@ impl PersistentStorageMap {
     }
 }
 
-fn rocksdb_block_number(rocks_db: &RocksDB<StorageMapCF>) -> Option<u64> {
+pub(crate) fn rocksdb_block_number(rocks_db: &RocksDB<StorageMapCF>) -> Option<u64> {
     rocks_db
         .get_cf(StorageMapCF::Meta, StorageMapCF::base_block_key())
         .unwrap()
@@ -3,6 +3,7 @@ use crate::persistent_storage_map::PersistentStorageMap;
 use crate::storage_map_view::StorageMapView;
 use alloy::primitives::B256;
 use dashmap::DashMap;
+use std::ops::RangeInclusive;
 use std::sync::atomic::AtomicU64;
 use std::{
     collections::HashMap,
@@ -193,6 +194,54 @@ impl StorageMap {
         }
     }
 
+    /// Latest value of every key written in blocks `range`.
+    pub fn changed_keys(&self, range: RangeInclusive<u64>) -> StateResult<HashMap<B256, B256>> {
+        if range.is_empty() {
+            return Ok(HashMap::new());
+        }
+        self.check_diffs_available(&range)?;
+        // Diffs may get compacted concurrently
+        self.collect_diffs_range(*range.start(), *range.end())
+            .map_err(|_| StateError::Compacted(*range.start()))
+    }
+
+    /// Writes to `key` in blocks `range` as `(block_number, value)`, ordered by block number.
+    pub fn key_history(
+        &self,
+        key: B256,
+        range: RangeInclusive<u64>,
+    ) -> StateResult<Vec<(u64, B256)>> {
+        if range.is_empty() {
+            return Ok(vec![]);
+        }
+        self.check_diffs_available(&range)?;
+        let mut history = vec![];
+        for block_number in range {
+            let diff = self
+                .diffs
+                .get(&block_number)
+                .ok_or(StateError::Compacted(block_number))?;
+            if let Some(value) = diff.map.get(&key) {
+                history.push((block_number, *value));
+            }
+        }
+        Ok(history)
+    }
+
+    /// Checks that diffs for all blocks in `range` are in memory. Diffs up to and including
+    /// the persistent block are compacted into RocksDB, which keeps only the latest values.
+    fn check_diffs_available(&self, range: &RangeInclusive<u64>) -> StateResult<()> {
+        let compacted_block = self.persistent_storage_map.persistent_block_upper_bound();
+        if *range.start() <= compacted_block {
+            return Err(StateError::Compacted(*range.start()));
+        }
+        let latest_block = self.latest_block.load(Ordering::Relaxed);
+        if *range.end() > latest_block {
+            return Err(StateError::NotFound(*range.end()));
+        }
+        Ok(())
+    }
+
     /// Aggregates all key-value updates between `from` and `to` (inclusive),
     /// returning the last written value for each key
     pub fn collect_diffs_range(&self, from: u64, to: u64) -> anyhow::Result<HashMap<B256, B256>> {
@@ -212,3 +261,95 @@ impl StorageMap {
         Ok(aggregated_map)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistent_storage_map::StorageMapCF;
+    use zksync_os_rocksdb::RocksDB;
+
+    const BLOCKS_TO_RETAIN: usize = 5;
+
+    fn write(i: u8, value: u64) -> StorageWrite {
+        StorageWrite {
+            key: B256::repeat_byte(i),
+            value: B256::left_padding_from(&value.to_be_bytes()),
+            account: Default::default(),
+            account_key: Default::default(),
+        }
+    }
+
+    /// Blocks 1..=20 with key 1 written in every block and key 2 in odd blocks; compacted up to block 15.
+    fn storage_map(dir: &tempfile::TempDir) -> StorageMap {
+        let rocks = RocksDB::<StorageMapCF>::new(dir.path()).unwrap();
+        let persistent_storage_map = PersistentStorageMap {
+            rocks,
+            persistent_block_lower_bound: Arc::new(0.into()),
+            persistent_block_upper_bound: Arc::new(0.into()),
+        };
+        persistent_storage_map.compact_sync(0, HashMap::new());
+        let storage_map = StorageMap::new(persistent_storage_map, BLOCKS_TO_RETAIN);
+        for block_number in 1..=20 {
+            let mut writes = vec![write(1, block_number)];
+            if block_number % 2 == 1 {
+                writes.push(write(2, block_number));
+            }
+            storage_map.add_diff(block_number, writes);
+        }
+        storage_map.compact();
+        storage_map
+    }
+
+    #[test]
+    fn changed_keys_in_memory_range() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage_map = storage_map(&dir);
+
+        let changed = storage_map.changed_keys(16..=20).unwrap();
+        assert_eq!(
+            changed,
+            HashMap::from([
+                (write(1, 20).key, write(1, 20).value),
+                (write(2, 19).key, write(2, 19).value)
+            ])
+        );
+        let changed = storage_map.changed_keys(18..=18).unwrap();
+        assert_eq!(
+            changed,
+            HashMap::from([(write(1, 18).key, write(1, 18).value)])
+        );
+
+        let history = storage_map
+            .key_history(B256::repeat_byte(2), 16..=20)
+            .unwrap();
+        assert_eq!(
+            history,
+            [(17, write(2, 17).value), (19, write(2, 19).value)]
+        );
+    }
+
+    #[test]
+    fn compacted_range_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage_map = storage_map(&dir);
+        assert_eq!(
+            storage_map
+                .persistent_storage_map
+                .persistent_block_upper_bound(),
+            15
+        );
+
+        assert!(matches!(
+            storage_map.changed_keys(10..=18),
+            Err(StateError::Compacted(10))
+        ));
+        assert!(matches!(
+            storage_map.key_history(B256::repeat_byte(1), 15..=16),
+            Err(StateError::Compacted(15))
+        ));
+        assert!(matches!(
+            storage_map.changed_keys(16..=21),
+            Err(StateError::NotFound(21))
+        ));
+    }
+}
@@ -2,17 +2,19 @@ mod preimages;
 mod storage;
 
 use alloy::primitives::{B256, BlockNumber};
-use std::path::PathBuf;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
 use zksync_os_interface::traits::{PreimageSource, ReadStorage};
 use zksync_os_interface::types::StorageWrite;
 use zksync_os_storage_api::{ReadStateHistory, StateError, StateResult, ViewState, WriteState};
 
 use preimages::FullDiffsPreimages;
 use storage::FullDiffsStorage;
+pub use storage::STORAGE_SCHEMA;
 use zksync_os_genesis::Genesis;
 
-const STATE_STORAGE_DB_NAME: &str = "state_full_diffs";
-const PREIMAGES_STORAGE_DB_NAME: &str = "preimages_full_diffs";
+pub const STATE_STORAGE_DB_NAME: &str = "state_full_diffs";
+pub const PREIMAGES_STORAGE_DB_NAME: &str = "preimages_full_diffs";
 
 #[derive(Debug, Clone)]
 pub struct FullDiffsState {
@@ -30,7 +32,7 @@ impl FullDiffsState {
         if this.storage.latest_block() == 0 {
             let storage_logs = genesis
                 .state()
-                .await
+                .await?
                 .storage_logs
                 .clone()
                 .into_iter()
@@ -44,10 +46,10 @@ impl FullDiffsState {
                 })
                 .collect();
 
-            let force_deploy_preimages = genesis.genesis_upgrade_tx().await.force_deploy_preimages;
+            let force_deploy_preimages = genesis.genesis_upgrade_tx().await?.force_deploy_preimages;
             let preimages = genesis
                 .state()
-                .await
+                .await?
                 .preimages
                 .iter()
                 .chain(force_deploy_preimages.iter())
@@ -58,6 +60,12 @@ impl FullDiffsState {
 
         Ok(this)
     }
+
+    /// Reads the latest block from the state DB in `base_path` without initializing the state
+    /// (e.g., to check a restored backup). Returns 0 if the DB is empty.
+    pub fn read_latest_block(base_path: &Path) -> anyhow::Result<u64> {
+        Ok(FullDiffsStorage::new(&base_path.join(STATE_STORAGE_DB_NAME))?.latest_block())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -95,6 +103,23 @@ impl ReadStateHistory for FullDiffsState {
     fn block_range_available(&self) -> std::ops::RangeInclusive<u64> {
         0..=self.storage.latest_block()
     }
+
+    fn changed_keys(
+        &self,
+        range: RangeInclusive<BlockNumber>,
+    ) -> StateResult<impl Iterator<Item = (B256, B256)> + '_> {
+        self.storage.check_range(&range)?;
+        Ok(self.storage.changed_keys(range))
+    }
+
+    fn key_history(
+        &self,
+        key: B256,
+        range: RangeInclusive<BlockNumber>,
+    ) -> StateResult<impl Iterator<Item = (BlockNumber, B256)> + '_> {
+        self.storage.check_range(&range)?;
+        Ok(self.storage.key_history(key, range))
+    }
 }
 
 impl WriteState for FullDiffsState {
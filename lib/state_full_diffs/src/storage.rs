@@ -1,11 +1,15 @@
 use alloy::primitives::B256;
 use std::collections::HashMap;
+use std::ops::RangeInclusive;
 use std::path::Path;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use zksync_os_interface::types::StorageWrite;
 use zksync_os_rocksdb::RocksDB;
 use zksync_os_rocksdb::db::NamedColumnFamily;
+use zksync_os_rocksdb::migrations::{Migration, Schema};
+use zksync_os_rocksdb::rocksdb::ReadOptions;
+use zksync_os_storage_api::{StateError, StateResult};
 
 #[derive(Clone, Copy, Debug)]
 pub enum StorageCF {
@@ -25,6 +29,12 @@ impl NamedColumnFamily for StorageCF {
     }
 }
 
+/// Schema of the full diffs storage DB, migrated on node startup.
+pub static STORAGE_SCHEMA: Schema<StorageCF> = Schema {
+    meta_cf: StorageCF::Meta,
+    migrations: &[Migration::baseline()],
+};
+
 impl StorageCF {
     fn latest_block_key() -> &'static [u8] {
         b"latest_block"
@@ -157,6 +167,82 @@ impl FullDiffsStorage {
         None
     }
 
+    /// Full diffs are never pruned, so only the end of `range` needs to be checked.
+    pub fn check_range(&self, range: &RangeInclusive<u64>) -> StateResult<()> {
+        if !range.is_empty() && *range.end() > self.latest_block() {
+            return Err(StateError::NotFound(*range.end()));
+        }
+        Ok(())
+    }
+
+    /// Latest write within blocks `range` for every key written in `range`, in the key order.
+    ///
+    /// Versions are ordered by block within each key, so the lookup takes a few seeks per distinct
+    /// key in the state; versions outside `range` are skipped without being read.
+    pub fn changed_keys(
+        &self,
+        range: RangeInclusive<u64>,
+    ) -> impl Iterator<Item = (B256, B256)> + '_ {
+        let (from, to) = range.into_inner();
+        let mut iter = self
+            .rocks
+            .raw_iterator(StorageCF::Data, ReadOptions::default());
+        iter.seek_to_first();
+        std::iter::from_fn(move || {
+            while let Some(k) = iter.key() {
+                let key = B256::from_slice(&k[..32]);
+                // Earliest write to `key` at or after `from`
+                iter.seek(Self::key_for_storage_write(&from, key));
+                let changed = iter
+                    .key()
+                    .is_some_and(|k| k[..32] == key[..] && Self::block_number(k) <= to);
+                let mut latest_write = None;
+                if changed {
+                    // Latest write to `key` at or before `to`
+                    iter.seek_for_prev(Self::key_for_storage_write(&to, key));
+                    let value = iter.value().expect("write found by the previous seek");
+                    latest_write = Some((key, B256::from_slice(value)));
+                }
+                // Skip to the first write of the next key
+                iter.seek(Self::key_for_storage_write(&u64::MAX, key));
+                if iter.key().is_some_and(|k| k[..32] == key[..]) {
+                    iter.next();
+                }
+                if latest_write.is_some() {
+                    return latest_write;
+                }
+            }
+            iter.status().expect("RocksDB iteration failed");
+            None
+        })
+    }
+
+    /// Writes to `key` within blocks `range` as `(block_number, value)`, ordered by block number.
+    pub fn key_history(
+        &self,
+        key: B256,
+        range: RangeInclusive<u64>,
+    ) -> impl Iterator<Item = (u64, B256)> + '_ {
+        let mut iter = self
+            .rocks
+            .raw_iterator(StorageCF::Data, ReadOptions::default());
+        iter.seek(Self::key_for_storage_write(range.start(), key));
+        std::iter::from_fn(move || {
+            let k = iter.key()?;
+            let block_number = Self::block_number(k);
+            if k[..32] != key[..] || block_number > *range.end() {
+                return None;
+            }
+            let write = (block_number, B256::from_slice(iter.value()?));
+            iter.next();
+            Some(write)
+        })
+    }
+
+    fn block_number(key: &[u8]) -> u64 {
+        u64::from_be_bytes(key[32..40].try_into().unwrap())
+    }
+
     fn key_for_storage_write(block_number: &u64, k: B256) -> Vec<u8> {
         let mut key = Vec::with_capacity(40);
         key.extend_from_slice(k.as_slice());
@@ -164,3 +250,105 @@ impl FullDiffsStorage {
         key
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    const BLOCKS: u64 = 40;
+    const KEYS: u64 = 16;
+
+    fn key(i: u64) -> B256 {
+        B256::left_padding_from(&i.to_be_bytes())
+    }
+
+    fn value(block_number: u64, i: u64) -> B256 {
+        B256::left_padding_from(&(block_number * 1_000 + i + 1).to_be_bytes())
+    }
+
+    /// Key `i` is written in every `i + 1`-th block, so that keys change at different rates.
+    fn is_written(block_number: u64, i: u64) -> bool {
+        block_number % (i + 1) == 0
+    }
+
+    fn storage_with_blocks() -> (tempfile::TempDir, FullDiffsStorage) {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = FullDiffsStorage::new(dir.path()).unwrap();
+        for block_number in 0..BLOCKS {
+            let writes = (0..KEYS)
+                .filter(|&i| is_written(block_number, i))
+                .map(|i| StorageWrite {
+                    key: key(i),
+                    value: value(block_number, i),
+                    account: Default::default(),
+                    account_key: Default::default(),
+                })
+                .collect();
+            storage.add_block(block_number, writes, false).unwrap();
+        }
+        (dir, storage)
+    }
+
+    fn expected_changed_keys(range: RangeInclusive<u64>) -> BTreeMap<B256, B256> {
+        let mut expected = BTreeMap::new();
+        for block_number in range {
+            for i in (0..KEYS).filter(|&i| is_written(block_number, i)) {
+                expected.insert(key(i), value(block_number, i));
+            }
+        }
+        expected
+    }
+
+    const RANGES: [RangeInclusive<u64>; 7] =
+        [0..=0, 0..=39, 1..=1, 5..=7, 10..=25, 17..=31, 33..=39];
+
+    #[test]
+    fn changed_keys_are_complete_and_latest_wins() {
+        let (_dir, storage) = storage_with_blocks();
+        for range in RANGES {
+            let changed: Vec<_> = storage.changed_keys(range.clone()).collect();
+            let expected = expected_changed_keys(range.clone());
+            // Keys are yielded once, in order
+            assert_eq!(
+                changed,
+                expected.into_iter().collect::<Vec<_>>(),
+                "{range:?}"
+            );
+        }
+        // Key 15 is written in blocks 0, 16 and 32 only
+        let changed: Vec<_> = storage.changed_keys(17..=31).map(|(k, _)| k).collect();
+        assert!(!changed.contains(&key(15)));
+        assert!(changed.contains(&key(0)));
+    }
+
+    #[test]
+    fn key_history_lists_writes_in_range() {
+        let (_dir, storage) = storage_with_blocks();
+        for range in RANGES {
+            for i in 0..KEYS {
+                let history: Vec<_> = storage.key_history(key(i), range.clone()).collect();
+                let expected: Vec<_> = range
+                    .clone()
+                    .filter(|&block_number| is_written(block_number, i))
+                    .map(|block_number| (block_number, value(block_number, i)))
+                    .collect();
+                assert_eq!(history, expected, "key {i}, {range:?}");
+            }
+        }
+        // Key that is never written
+        assert_eq!(storage.key_history(key(KEYS), 0..=39).count(), 0);
+    }
+
+    #[test]
+    fn range_after_latest_block_is_not_found() {
+        let (_dir, storage) = storage_with_blocks();
+        storage.check_range(&(0..=BLOCKS - 1)).unwrap();
+        assert!(matches!(
+            storage.check_range(&(30..=BLOCKS)),
+            Err(StateError::NotFound(block_number)) if block_number == BLOCKS
+        ));
+        // Empty ranges are always fine
+        storage.check_range(&(BLOCKS + 10..=BLOCKS)).unwrap();
+    }
+}
@@ -0,0 +1,20 @@
+use alloy::primitives::B256;
+use serde::{Deserialize, Serialize};
+
+/// Result of verifying the state the node was bootstrapped from (a restored backup) against L1.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckpointStatus {
+    /// ID of the restored backup set.
+    pub backup_set_id: u64,
+    /// Last block of the restored state; the last block of `batch_number`.
+    pub block_number: u64,
+    pub batch_number: u64,
+    /// Root hash of the restored state tree at `block_number`.
+    pub tree_root: B256,
+    /// State commitment recomputed from the restored state, equal to the one committed on L1.
+    pub state_commitment: B256,
+    /// L1 transaction that committed `batch_number`.
+    pub l1_commit_tx_hash: B256,
+    /// Unix timestamp (in seconds) of the verification.
+    pub verified_at: u64,
+}
@@ -0,0 +1,61 @@
+use crate::{AppState, CheckpointStatus, ReadinessStatus, ReplaySubscriberStatus};
+use axum::Json;
+use serde::Serialize;
+use zksync_os_l1_sender::batcher_model::L1FinalitySnapshot;
+use zksync_os_l1_sender::cost_accounting::L1CostSummary;
+use zksync_os_l1_sender::watchdog::WatchdogStatus;
+use zksync_os_l1_watcher::GovernanceStatus;
+use zksync_os_types::{ReplayDivergenceStatus, ShadowExecutionStatus};
+
+#[derive(Serialize)]
+pub struct DebugStatusResponse {
+    /// Same as served by `/status/ready`.
+    readiness: ReadinessStatus,
+    batches: BatchesStatus,
+    replay: ReplayStatus,
+    /// Shadow execution with another execution version, if enabled.
+    shadow_execution: ShadowExecutionStatus,
+    /// L1 verification of the restored backup the node was bootstrapped from, if any.
+    checkpoint: Option<CheckpointStatus>,
+    /// Governance actions on L1 (validator roles, fee params, upgrades) seen since the node has
+    /// started.
+    l1_governance: GovernanceStatus,
+}
+
+#[derive(Serialize)]
+pub struct BatchesStatus {
+    /// L1 finality of batches sent by this node (empty on external nodes).
+    l1_finality: L1FinalitySnapshot,
+    /// L1 fees paid by this node: recent batches and daily/weekly aggregates (empty on external nodes).
+    l1_costs: L1CostSummary,
+    /// Lag of batch commits behind sealed batches, and alerts raised on it (empty on external nodes).
+    l1_sender_watchdog: WatchdogStatus,
+}
+
+#[derive(Serialize)]
+pub struct ReplayStatus {
+    /// External nodes currently syncing block replays from this node.
+    subscribers: Vec<ReplaySubscriberStatus>,
+    /// Replay divergence quarantine of this node (external nodes only).
+    divergence: ReplayDivergenceStatus,
+}
+
+pub(crate) async fn debug_status(
+    state: axum::extract::State<AppState>,
+) -> Json<DebugStatusResponse> {
+    Json(DebugStatusResponse {
+        readiness: ReadinessStatus::from_state(&state),
+        batches: BatchesStatus {
+            l1_finality: state.l1_finality.borrow().clone(),
+            l1_costs: state.l1_costs.borrow().clone(),
+            l1_sender_watchdog: state.l1_sender_watchdog.borrow().clone(),
+        },
+        replay: ReplayStatus {
+            subscribers: state.replay_subscribers.borrow().clone(),
+            divergence: state.replay_divergence.borrow().clone(),
+        },
+        shadow_execution: state.shadow_execution.borrow().clone(),
+        checkpoint: state.checkpoint.clone(),
+        l1_governance: state.l1_governance.borrow().clone(),
+    })
+}
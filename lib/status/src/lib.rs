@@ -1,22 +1,71 @@
+mod checkpoint;
+mod debug;
 mod health;
+mod readiness;
+mod replay;
 
+use crate::debug::debug_status;
 use crate::health::health;
+use crate::readiness::readiness;
 use axum::{Router, routing::get};
 use std::net::SocketAddr;
+use std::time::Duration;
 use tokio::{net::TcpListener, sync::watch};
+use tower_http::timeout::TimeoutLayer;
+use zksync_os_l1_sender::batcher_model::L1FinalitySnapshot;
+use zksync_os_l1_sender::cost_accounting::L1CostSummary;
+use zksync_os_l1_sender::watchdog::WatchdogStatus;
+use zksync_os_l1_watcher::GovernanceStatus;
+use zksync_os_types::{ReplayDivergenceStatus, ShadowExecutionStatus};
+
+pub use crate::checkpoint::CheckpointStatus;
+pub use crate::readiness::{NotReadyReason, ReadinessStatus};
+pub use crate::replay::{ReplaySubscriberState, ReplaySubscriberStatus};
 
 #[derive(Clone)]
 struct AppState {
     stop_receiver: watch::Receiver<bool>,
+    l1_finality: watch::Receiver<L1FinalitySnapshot>,
+    l1_costs: watch::Receiver<L1CostSummary>,
+    l1_sender_watchdog: watch::Receiver<WatchdogStatus>,
+    replay_subscribers: watch::Receiver<Vec<ReplaySubscriberStatus>>,
+    replay_divergence: watch::Receiver<ReplayDivergenceStatus>,
+    shadow_execution: watch::Receiver<ShadowExecutionStatus>,
+    checkpoint: Option<CheckpointStatus>,
+    l1_governance: watch::Receiver<GovernanceStatus>,
 }
 
+/// Requests time out with `408 Request Timeout` after `request_timeout`.
+#[allow(clippy::too_many_arguments)]
 pub async fn run_status_server(
     bind_address: String,
+    request_timeout: Duration,
     stop_receiver: watch::Receiver<bool>,
+    l1_finality: watch::Receiver<L1FinalitySnapshot>,
+    l1_costs: watch::Receiver<L1CostSummary>,
+    l1_sender_watchdog: watch::Receiver<WatchdogStatus>,
+    replay_subscribers: watch::Receiver<Vec<ReplaySubscriberStatus>>,
+    replay_divergence: watch::Receiver<ReplayDivergenceStatus>,
+    shadow_execution: watch::Receiver<ShadowExecutionStatus>,
+    checkpoint: Option<CheckpointStatus>,
+    l1_governance: watch::Receiver<GovernanceStatus>,
 ) -> anyhow::Result<()> {
     let app = Router::new()
         .route("/status/health", get(health))
-        .with_state(AppState { stop_receiver });
+        .route("/status/ready", get(readiness))
+        .route("/debug/status", get(debug_status))
+        .with_state(AppState {
+            stop_receiver,
+            l1_finality,
+            l1_costs,
+            l1_sender_watchdog,
+            replay_subscribers,
+            replay_divergence,
+            shadow_execution,
+            checkpoint,
+            l1_governance,
+        })
+        .layer(TimeoutLayer::new(request_timeout));
 
     let addr: SocketAddr = bind_address.parse()?;
     let listener = TcpListener::bind(addr).await?;
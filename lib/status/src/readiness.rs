@@ -0,0 +1,99 @@
+use crate::AppState;
+use axum::Json;
+use axum::http::StatusCode;
+use serde::Serialize;
+use zksync_os_types::{ReplayDivergence, ReplayDivergenceStatus};
+
+/// Reason why the node is not ready to serve traffic.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "reason", rename_all = "camelCase")]
+pub enum NotReadyReason {
+    Terminating,
+    /// Replay is halted at a block whose output diverged from the replay stream; the node serves
+    /// state as of the previous block.
+    Diverged(ReplayDivergence),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReadinessStatus {
+    pub ready: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub not_ready_reason: Option<NotReadyReason>,
+}
+
+impl ReadinessStatus {
+    pub(crate) fn new(is_terminating: bool, replay_divergence: &ReplayDivergenceStatus) -> Self {
+        let not_ready_reason = if is_terminating {
+            Some(NotReadyReason::Terminating)
+        } else {
+            replay_divergence
+                .halted_at()
+                .map(|divergence| NotReadyReason::Diverged(divergence.clone()))
+        };
+        Self {
+            ready: not_ready_reason.is_none(),
+            not_ready_reason,
+        }
+    }
+
+    pub(crate) fn from_state(state: &AppState) -> Self {
+        Self::new(
+            *state.stop_receiver.borrow(),
+            &state.replay_divergence.borrow(),
+        )
+    }
+}
+
+pub(crate) async fn readiness(
+    state: axum::extract::State<AppState>,
+) -> (StatusCode, Json<ReadinessStatus>) {
+    let readiness = ReadinessStatus::from_state(&state);
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(readiness))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::B256;
+
+    #[test]
+    fn diverged_node_is_not_ready() {
+        let divergence = ReplayDivergence {
+            block_number: 5,
+            expected_output_hash: B256::repeat_byte(1),
+            computed_output_hash: B256::repeat_byte(2),
+            dump_path: Some("db/block_dumps/dump_5_0.json".into()),
+        };
+        let ready = ReadinessStatus::new(false, &ReplayDivergenceStatus::None);
+        assert!(ready.ready);
+        assert_eq!(
+            serde_json::to_value(&ready).unwrap(),
+            serde_json::json!({ "ready": true })
+        );
+
+        for status in [
+            ReplayDivergenceStatus::Diverged(divergence.clone()),
+            ReplayDivergenceStatus::Halted(divergence.clone()),
+        ] {
+            let readiness = ReadinessStatus::new(false, &status);
+            assert!(!readiness.ready);
+            let json = serde_json::to_value(&readiness).unwrap();
+            assert_eq!(json["not_ready_reason"]["reason"], "diverged", "{json}");
+            assert_eq!(json["not_ready_reason"]["blockNumber"], 5, "{json}");
+        }
+
+        // Resumed by the operator
+        let resumed = ReadinessStatus::new(false, &ReplayDivergenceStatus::Resumed(divergence));
+        assert!(resumed.ready);
+        let terminating = ReadinessStatus::new(true, &ReplayDivergenceStatus::None);
+        assert_eq!(
+            terminating.not_ready_reason,
+            Some(NotReadyReason::Terminating)
+        );
+    }
+}
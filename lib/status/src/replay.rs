@@ -0,0 +1,28 @@
+use serde::Serialize;
+
+/// State of a block replay subscriber (external node syncing from this node).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplaySubscriberState {
+    /// In sync with the head; records are streamed as soon as they are produced.
+    Live,
+    /// Catching up, waiting for a free historical reader.
+    WaitingForReader,
+    /// Catching up, reading historical records.
+    Backfilling,
+    /// Catching up, delayed by the per-connection send rate limit.
+    Throttled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplaySubscriberStatus {
+    pub id: u64,
+    pub peer: String,
+    /// Next block to be sent to the subscriber.
+    pub position: u64,
+    /// Number of stored blocks not yet sent to the subscriber.
+    pub lag: u64,
+    pub state: ReplaySubscriberState,
+    pub records_sent: u64,
+    pub bytes_sent: u64,
+}
@@ -2,4 +2,7 @@ mod replay;
 pub use replay::BlockReplayStorage;
 
 mod repository;
-pub use repository::RepositoryDb;
+pub use repository::{PruneStats, REPOSITORY_SCHEMA, RepositoryDb};
+
+#[cfg(test)]
+pub(crate) use repository::tests;
@@ -39,6 +39,8 @@ pub enum BlockReplayColumnFamily {
     Txs,
     NodeVersion,
     BlockOutputHash,
+    /// Missing for blocks appended before millisecond timestamps were recorded.
+    BlockTimestampMillis,
     /// Stores the latest appended block number under a fixed key.
     Latest,
 }
@@ -51,6 +53,7 @@ impl NamedColumnFamily for BlockReplayColumnFamily {
         BlockReplayColumnFamily::Txs,
         BlockReplayColumnFamily::NodeVersion,
         BlockReplayColumnFamily::BlockOutputHash,
+        BlockReplayColumnFamily::BlockTimestampMillis,
         BlockReplayColumnFamily::Latest,
     ];
 
@@ -61,6 +64,7 @@ impl NamedColumnFamily for BlockReplayColumnFamily {
             BlockReplayColumnFamily::Txs => "txs",
             BlockReplayColumnFamily::NodeVersion => "node_version",
             BlockReplayColumnFamily::BlockOutputHash => "block_output_hash",
+            BlockReplayColumnFamily::BlockTimestampMillis => "block_timestamp_millis",
             BlockReplayColumnFamily::Latest => "latest",
         }
     }
@@ -77,7 +81,11 @@ impl BlockReplayStorage {
 
         let this = Self { db };
         if this.latest_record_checked().is_none() {
-            let genesis_context = &genesis.state().await.context;
+            let genesis_context = &genesis
+                .state()
+                .await
+                .expect("Failed to build genesis state")
+                .context;
             tracing::info!(
                 "block replay DB is empty, assuming start of the chain; appending genesis"
             );
@@ -86,6 +94,7 @@ impl BlockReplayStorage {
                 starting_l1_priority_id: 0,
                 transactions: vec![],
                 previous_block_timestamp: 0,
+                block_timestamp_millis: genesis_context.timestamp * 1000,
                 node_version,
                 block_output_hash: B256::ZERO,
             })
@@ -93,6 +102,25 @@ impl BlockReplayStorage {
         this
     }
 
+    /// Reads the latest appended block number from the storage at `db_path` without initializing
+    /// it (e.g., to check a restored backup). Returns `None` if the storage is empty.
+    pub fn read_latest_record(db_path: &Path) -> Option<BlockNumber> {
+        let db = RocksDB::<BlockReplayColumnFamily>::new(db_path)
+            .expect("Failed to open BlockReplayStorage");
+        Self { db }.latest_record_checked()
+    }
+
+    /// Opens the storage at `db_path` without initializing it (e.g., to import blocks into it).
+    /// Returns `None` if the storage is empty.
+    pub fn open_existing(db_path: &Path) -> Option<Self> {
+        let db = RocksDB::<BlockReplayColumnFamily>::new(db_path)
+            .expect("Failed to open BlockReplayStorage")
+            .with_sync_writes();
+        let this = Self { db };
+        this.latest_record_checked()?;
+        Some(this)
+    }
+
     fn write_replay_unchecked(&self, record: ReplayRecord) {
         // Prepare record
         let block_num = record.block_context.block_number.to_be_bytes();
@@ -137,6 +165,11 @@ impl BlockReplayStorage {
             &block_num,
             &record.block_output_hash.0,
         );
+        batch.put_cf(
+            BlockReplayColumnFamily::BlockTimestampMillis,
+            &block_num,
+            &record.block_timestamp_millis.to_be_bytes(),
+        );
 
         self.db
             .write(batch)
@@ -145,6 +178,18 @@ impl BlockReplayStorage {
 
     /// Returns the greatest block number that has been appended, or `None` if empty.
     /// This can only return `None` on the very first start before genesis got inserted.
+    fn block_timestamp_millis(&self, block_number: BlockNumber, context: &BlockContext) -> u64 {
+        self.db
+            .get_cf(
+                BlockReplayColumnFamily::BlockTimestampMillis,
+                &block_number.to_be_bytes(),
+            )
+            .expect("Failed to read from BlockTimestampMillis CF")
+            .map_or(context.timestamp * 1000, |bytes| {
+                u64::from_be_bytes(bytes.as_slice().try_into().unwrap())
+            })
+    }
+
     fn latest_record_checked(&self) -> Option<BlockNumber> {
         self.db
             .get_cf(BlockReplayColumnFamily::Latest, Self::LATEST_KEY)
@@ -217,13 +262,14 @@ impl ReadReplay for BlockReplayStorage {
             .expect("Failed to read from BlockOutputHash CF")
             .expect("BlockOutputHash must be written atomically with Context");
 
+        let block_context: BlockContext =
+            bincode::serde::decode_from_slice(&block_context, bincode::config::standard())
+                .expect("Failed to deserialize context")
+                .0;
+        let block_timestamp_millis = self.block_timestamp_millis(block_number, &block_context);
+
         Some(ReplayRecord {
-            block_context: bincode::serde::decode_from_slice(
-                &block_context,
-                bincode::config::standard(),
-            )
-            .expect("Failed to deserialize context")
-            .0,
+            block_context,
             starting_l1_priority_id: bincode::serde::decode_from_slice(
                 &starting_l1_priority_id,
                 bincode::config::standard(),
@@ -234,6 +280,7 @@ impl ReadReplay for BlockReplayStorage {
                 .expect("Failed to deserialize transactions")
                 .0,
             previous_block_timestamp,
+            block_timestamp_millis,
             node_version: String::from_utf8(node_version)
                 .expect("Failed to deserialize node version")
                 .parse()
@@ -242,6 +289,11 @@ impl ReadReplay for BlockReplayStorage {
         })
     }
 
+    fn get_block_timestamp_millis(&self, block_number: BlockNumber) -> Option<u64> {
+        let context = self.get_context(block_number)?;
+        Some(self.block_timestamp_millis(block_number, &context))
+    }
+
     fn latest_record(&self) -> BlockNumber {
         // This is guaranteed to be non-`None` because genesis is always inserted on storage initialization.
         self.latest_record_checked()
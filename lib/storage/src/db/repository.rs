@@ -12,12 +12,14 @@ use tokio::sync::watch;
 use zksync_os_genesis::Genesis;
 use zksync_os_rocksdb::RocksDB;
 use zksync_os_rocksdb::db::{NamedColumnFamily, WriteBatch};
+use zksync_os_rocksdb::migrations::{Migration, Schema};
 use zksync_os_storage_api::{
-    ReadRepository, RepositoryBlock, RepositoryResult, StoredTxData, TxMeta,
+    MissingBlockEntry, ReadRepository, RepositoryBlock, RepositoryError, RepositoryResult,
+    StoredTxData, TxMeta,
 };
 use zksync_os_types::{ZkEnvelope, ZkReceiptEnvelope, ZkTransaction};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum RepositoryCF {
     // block hash => (block header, array of tx hashes)
     BlockData,
@@ -31,7 +33,8 @@ pub enum RepositoryCF {
     TxMeta,
     // (initiator address, nonce) => tx hash
     InitiatorAndNonceToHash,
-    // meta fields: currently only latest block number
+    // meta fields: latest block number (written last for every block, marking it as complete)
+    // and the first block with unpruned transactions
     Meta,
 }
 
@@ -39,6 +42,10 @@ impl RepositoryCF {
     fn block_number_key() -> &'static [u8] {
         b"block_number"
     }
+
+    fn first_unpruned_block_key() -> &'static [u8] {
+        b"first_unpruned_block"
+    }
 }
 
 impl NamedColumnFamily for RepositoryCF {
@@ -66,12 +73,33 @@ impl NamedColumnFamily for RepositoryCF {
     }
 }
 
+/// Schema of the repository DB, migrated on node startup.
+pub static REPOSITORY_SCHEMA: Schema<RepositoryCF> = Schema {
+    meta_cf: RepositoryCF::Meta,
+    migrations: &[Migration::baseline()],
+};
+
+/// Single write persisting a block: column family, key and value.
+type BlockWrite = (RepositoryCF, Vec<u8>, Vec<u8>);
+
 #[derive(Clone, Debug)]
 pub struct RepositoryDb {
     db: RocksDB<RepositoryCF>,
     /// Points to the latest block whose data has been persisted in `db`. There might be partial
     /// data written for the next block, in other words `db` is caught up to *AT LEAST* this number.
     latest_block_number: watch::Sender<u64>,
+    /// Transactions, receipts and transaction metadata of blocks before this one are pruned.
+    first_unpruned_block: watch::Sender<u64>,
+}
+
+/// Data deleted by [`RepositoryDb::prune()`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PruneStats {
+    pub transactions: usize,
+    /// Deleted entries across all column families.
+    pub entries: usize,
+    /// Total size of deleted keys and values.
+    pub reclaimed_bytes: usize,
 }
 
 impl RepositoryDb {
@@ -84,7 +112,12 @@ impl RepositoryDb {
         let latest_block_number = if let Some(n) = latest_block_number {
             n
         } else {
-            let header = genesis.state().await.header.clone();
+            let header = genesis
+                .state()
+                .await
+                .expect("Failed to build genesis state")
+                .header
+                .clone();
             let hash = header.hash_slow();
             let block = Sealed::new_unchecked(
                 Block {
@@ -102,10 +135,47 @@ impl RepositoryDb {
             0
         };
 
-        Self {
+        let first_unpruned_block = Self::read_first_unpruned_block(&db);
+        let repository = Self {
             db,
             latest_block_number: watch::channel(latest_block_number).0,
-        }
+            first_unpruned_block: watch::channel(first_unpruned_block).0,
+        };
+        repository
+            .repair_head()
+            .expect("Failed to repair repository head");
+        repository
+    }
+
+    fn read_first_unpruned_block(db: &RocksDB<RepositoryCF>) -> u64 {
+        db.get_cf(RepositoryCF::Meta, RepositoryCF::first_unpruned_block_key())
+            .unwrap()
+            .map_or(0, |v| u64::from_be_bytes(v.as_slice().try_into().unwrap()))
+    }
+
+    /// Reads the latest persisted block number from the DB at `db_path` without initializing it
+    /// (e.g., to check a restored backup). Returns `None` if the DB is empty.
+    pub fn read_latest_block(db_path: &Path) -> Option<u64> {
+        let db = RocksDB::<RepositoryCF>::new(db_path).expect("Failed to open db");
+        db.get_cf(RepositoryCF::Meta, RepositoryCF::block_number_key())
+            .unwrap()
+            .map(|v| u64::from_be_bytes(v.as_slice().try_into().unwrap()))
+    }
+
+    /// Opens the DB at `db_path` without initializing it (e.g., to inspect a restored backup).
+    /// Returns `None` if the DB is empty.
+    pub fn open_existing(db_path: &Path) -> Option<Self> {
+        let db = RocksDB::<RepositoryCF>::new(db_path).expect("Failed to open db");
+        let latest_block_number = db
+            .get_cf(RepositoryCF::Meta, RepositoryCF::block_number_key())
+            .unwrap()
+            .map(|v| u64::from_be_bytes(v.as_slice().try_into().unwrap()))?;
+        let first_unpruned_block = Self::read_first_unpruned_block(&db);
+        Some(Self {
+            db,
+            latest_block_number: watch::channel(latest_block_number).0,
+            first_unpruned_block: watch::channel(first_unpruned_block).0,
+        })
     }
 
     /// Waits until the latest block number is at least `block_number`.
@@ -124,29 +194,11 @@ impl RepositoryDb {
         block: &Sealed<Block<TxHash>>,
         txs: &[Arc<StoredTxData>],
     ) {
-        let block_number = block.number;
-        let block_hash = block.hash();
-        let block_number_bytes = block_number.to_be_bytes();
-        let block_hash_bytes = block_hash.to_vec();
-
         let mut batch = db.new_write_batch();
-        batch.put_cf(
-            RepositoryCF::BlockNumberToHash,
-            &block_number_bytes,
-            &block_hash_bytes,
-        );
-
-        let mut block_bytes = Vec::new();
-        block.encode(&mut block_bytes);
-        batch.put_cf(RepositoryCF::BlockData, block_hash.as_slice(), &block_bytes);
-
-        for tx in txs {
-            Self::add_tx_to_write_batch(&mut batch, tx);
+        for (cf, key, value) in Self::block_writes(block, txs) {
+            batch.put_cf(cf, &key, &value);
         }
 
-        let block_number_key = RepositoryCF::block_number_key();
-        batch.put_cf(RepositoryCF::Meta, block_number_key, &block_number_bytes);
-
         REPOSITORIES_METRICS
             .block_data_size
             .observe(batch.size_in_bytes());
@@ -156,35 +208,140 @@ impl RepositoryDb {
         db.write(batch).unwrap();
     }
 
-    pub fn write_block(&self, block: &Sealed<Block<TxHash>>, txs: &[Arc<StoredTxData>]) {
-        Self::write_block_inner(&self.db, block, txs);
-        self.latest_block_number.send_replace(block.number);
+    /// Returns writes persisting `block` in the order they are applied. All writes go to a single
+    /// atomic write batch; still, the latest block number is written last, so that a block whose
+    /// writes were cut short is detected by [`ReadRepository::verify_block_complete()`].
+    fn block_writes(block: &Sealed<Block<TxHash>>, txs: &[Arc<StoredTxData>]) -> Vec<BlockWrite> {
+        let block_number_bytes = block.number.to_be_bytes().to_vec();
+        let mut block_bytes = Vec::new();
+        block.encode(&mut block_bytes);
+
+        let mut writes = vec![
+            (
+                RepositoryCF::BlockNumberToHash,
+                block_number_bytes.clone(),
+                block.hash().to_vec(),
+            ),
+            (RepositoryCF::BlockData, block.hash().to_vec(), block_bytes),
+        ];
+        for tx in txs {
+            writes.extend(Self::tx_writes(tx));
+        }
+        writes.push((
+            RepositoryCF::Meta,
+            RepositoryCF::block_number_key().to_vec(),
+            block_number_bytes,
+        ));
+        writes
     }
 
-    fn add_tx_to_write_batch(batch: &mut WriteBatch<RepositoryCF>, tx: &StoredTxData) {
-        let tx_hash = tx.tx.hash();
+    fn tx_writes(tx: &StoredTxData) -> [BlockWrite; 4] {
+        let tx_hash = tx.tx.hash().to_vec();
         let mut tx_bytes = Vec::new();
         tx.tx.inner.encode_2718(&mut tx_bytes);
-        batch.put_cf(RepositoryCF::Tx, tx_hash.as_slice(), &tx_bytes);
-
         let mut receipt_bytes = Vec::new();
         tx.receipt.encode_2718(&mut receipt_bytes);
-        batch.put_cf(RepositoryCF::TxReceipt, tx_hash.as_slice(), &receipt_bytes);
-
         let mut tx_meta_bytes = Vec::new();
         tx.meta.encode(&mut tx_meta_bytes);
-        batch.put_cf(RepositoryCF::TxMeta, tx_hash.as_slice(), &tx_meta_bytes);
+        [
+            (RepositoryCF::Tx, tx_hash.clone(), tx_bytes),
+            (RepositoryCF::TxReceipt, tx_hash.clone(), receipt_bytes),
+            (RepositoryCF::TxMeta, tx_hash.clone(), tx_meta_bytes),
+            (
+                RepositoryCF::InitiatorAndNonceToHash,
+                initiator_and_nonce_key(tx.tx.signer(), tx.tx.inner.nonce()),
+                tx_hash,
+            ),
+        ]
+    }
 
-        let initiator = tx.tx.signer();
-        let nonce = tx.tx.inner.nonce();
-        let mut initiator_and_nonce_key = Vec::with_capacity(20 + 8);
-        initiator_and_nonce_key.extend_from_slice(initiator.as_slice());
-        initiator_and_nonce_key.extend_from_slice(&nonce.to_be_bytes());
+    pub fn write_block(&self, block: &Sealed<Block<TxHash>>, txs: &[Arc<StoredTxData>]) {
+        Self::write_block_inner(&self.db, block, txs);
+        self.latest_block_number.send_replace(block.number);
+    }
+
+    /// Startup consistency pass: repairs the latest block and the block after it (which may be
+    /// partially written if the node crashed while persisting it) if they are not fully indexed.
+    fn repair_head(&self) -> RepositoryResult<()> {
+        let latest_block_number = self.get_latest_block();
+        for number in [latest_block_number, latest_block_number + 1] {
+            match self.verify_block_complete(number) {
+                Ok(()) => {}
+                Err(RepositoryError::IncompleteBlock { number, missing }) if number > 0 => {
+                    tracing::warn!(number, ?missing, "Repairing partially indexed block");
+                    return self.repair_block(number);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+
+    /// Repairs partially indexed block `number` by removing all entries of this and later blocks,
+    /// so that they are re-populated from their replay records when replayed. Blocks at and above
+    /// the latest persisted block + 1 are always replayed on startup.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `number` is 0, since the genesis block is not replayed.
+    pub fn repair_block(&self, number: BlockNumber) -> RepositoryResult<()> {
+        assert!(number > 0, "cannot repair genesis block");
+        let latest_block_number = self.get_latest_block();
+        let mut batch = self.db.new_write_batch();
+        for block_number in number..=latest_block_number.max(number) {
+            self.delete_block_entries(&mut batch, block_number)?;
+        }
+        let new_latest_block_number = latest_block_number.min(number - 1);
         batch.put_cf(
-            RepositoryCF::InitiatorAndNonceToHash,
-            &initiator_and_nonce_key,
-            tx_hash.as_slice(),
+            RepositoryCF::Meta,
+            RepositoryCF::block_number_key(),
+            &new_latest_block_number.to_be_bytes(),
+        );
+        self.db.write(batch)?;
+        self.latest_block_number
+            .send_replace(new_latest_block_number);
+        REPOSITORIES_METRICS.repaired_blocks.inc();
+        tracing::info!(
+            number,
+            latest_block_number = new_latest_block_number,
+            "Removed partially indexed block from repository DB"
         );
+        Ok(())
+    }
+
+    /// Adds deletions of all present entries of block `number` to `batch`.
+    fn delete_block_entries(
+        &self,
+        batch: &mut WriteBatch<RepositoryCF>,
+        number: BlockNumber,
+    ) -> RepositoryResult<()> {
+        let block_number_bytes = number.to_be_bytes();
+        let Some(block_hash_bytes) = self
+            .db
+            .get_cf(RepositoryCF::BlockNumberToHash, &block_number_bytes)?
+        else {
+            return Ok(());
+        };
+        batch.delete_cf(RepositoryCF::BlockNumberToHash, &block_number_bytes);
+        batch.delete_cf(RepositoryCF::BlockData, &block_hash_bytes);
+        let hash = BlockHash::from(
+            <[u8; 32]>::try_from(block_hash_bytes).expect("block hash must be 32 bytes long"),
+        );
+        let Some(block) = self.get_block_by_hash(hash)? else {
+            return Ok(());
+        };
+        for tx_hash in &block.body.transactions {
+            batch.delete_cf(RepositoryCF::Tx, &tx_hash.0);
+            batch.delete_cf(RepositoryCF::TxReceipt, &tx_hash.0);
+            batch.delete_cf(RepositoryCF::TxMeta, &tx_hash.0);
+            if let Some(tx) = self.get_transaction(*tx_hash)? {
+                batch.delete_cf(
+                    RepositoryCF::InitiatorAndNonceToHash,
+                    &initiator_and_nonce_key(tx.signer(), tx.inner.nonce()),
+                );
+            }
+        }
+        Ok(())
     }
 
     pub fn rollback(&self, last_block_to_keep: u64) -> RepositoryResult<()> {
@@ -242,6 +399,75 @@ impl RepositoryDb {
 
         Ok(())
     }
+
+    /// Deletes transactions, receipts, transaction metadata and initiator/nonce index entries of
+    /// blocks before `prune_before`. Block data and block number to hash mappings are kept, so that
+    /// headers and transaction hashes of pruned blocks are still served.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prune_before` is above the block after the latest persisted block.
+    pub fn prune(&self, prune_before: BlockNumber) -> RepositoryResult<PruneStats> {
+        let first_unpruned_block = *self.first_unpruned_block.borrow();
+        let latest_block_number = self.get_latest_block();
+        assert!(
+            prune_before <= latest_block_number + 1,
+            "cannot prune blocks before {prune_before}: latest persisted block is {latest_block_number}"
+        );
+        let mut stats = PruneStats::default();
+        if prune_before <= first_unpruned_block {
+            return Ok(stats);
+        }
+
+        let mut batch = self.db.new_write_batch();
+        for block_number in first_unpruned_block..prune_before {
+            let block = self
+                .get_block_by_number(block_number)?
+                .expect("block to prune must be present in DB");
+            for tx_hash in &block.body.transactions {
+                let tx = self
+                    .get_transaction(*tx_hash)?
+                    .expect("tx to prune must be present in DB");
+                let mut initiator_and_nonce_key = Vec::with_capacity(20 + 8);
+                initiator_and_nonce_key.extend_from_slice(tx.signer().as_slice());
+                initiator_and_nonce_key.extend_from_slice(&tx.inner.nonce().to_be_bytes());
+
+                for (cf, key) in [
+                    (RepositoryCF::Tx, tx_hash.as_slice()),
+                    (RepositoryCF::TxReceipt, tx_hash.as_slice()),
+                    (RepositoryCF::TxMeta, tx_hash.as_slice()),
+                    (
+                        RepositoryCF::InitiatorAndNonceToHash,
+                        initiator_and_nonce_key.as_slice(),
+                    ),
+                ] {
+                    let Some(value) = self.db.get_cf(cf, key)? else {
+                        continue;
+                    };
+                    batch.delete_cf(cf, key);
+                    REPOSITORIES_METRICS.pruned_entries[&cf.name()].inc();
+                    stats.entries += 1;
+                    stats.reclaimed_bytes += key.len() + value.len();
+                }
+                stats.transactions += 1;
+            }
+        }
+        batch.put_cf(
+            RepositoryCF::Meta,
+            RepositoryCF::first_unpruned_block_key(),
+            &prune_before.to_be_bytes(),
+        );
+
+        // Readers must observe the new horizon before data is deleted, so that lookups of pruned
+        // blocks fail with `RepositoryError::Pruned` rather than miss transactions.
+        self.first_unpruned_block.send_replace(prune_before);
+        self.db.write(batch)?;
+        REPOSITORIES_METRICS
+            .pruned
+            .inc_by(stats.reclaimed_bytes as u64);
+        REPOSITORIES_METRICS.first_unpruned_block.set(prune_before);
+        Ok(stats)
+    }
 }
 
 impl ReadRepository for RepositoryDb {
@@ -337,4 +563,287 @@ impl ReadRepository for RepositoryDb {
     fn get_latest_block(&self) -> u64 {
         *self.latest_block_number.borrow()
     }
+
+    fn first_unpruned_block(&self) -> BlockNumber {
+        *self.first_unpruned_block.borrow()
+    }
+
+    fn verify_block_complete(&self, number: BlockNumber) -> RepositoryResult<()> {
+        let Some(block_hash_bytes) = self
+            .db
+            .get_cf(RepositoryCF::BlockNumberToHash, &number.to_be_bytes())?
+        else {
+            return Ok(());
+        };
+        let hash = BlockHash::from(
+            <[u8; 32]>::try_from(block_hash_bytes).expect("block hash must be 32 bytes long"),
+        );
+        let mut missing = match self.get_block_by_hash(hash)? {
+            Some(block) => self.missing_transaction_entries(&block)?,
+            None => vec![MissingBlockEntry::BlockData],
+        };
+        if number > self.get_latest_block() {
+            missing.push(MissingBlockEntry::CompletionMarker);
+        }
+        if !missing.is_empty() {
+            return Err(RepositoryError::IncompleteBlock { number, missing });
+        }
+        Ok(())
+    }
+}
+
+fn initiator_and_nonce_key(initiator: Address, nonce: TxNonce) -> Vec<u8> {
+    let mut key = Vec::with_capacity(20 + 8);
+    key.extend_from_slice(initiator.as_slice());
+    key.extend_from_slice(&nonce.to_be_bytes());
+    key
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use alloy::consensus::{BlockBody, Header, ReceiptWithBloom, SignableTransaction, TxLegacy};
+    use alloy::primitives::{B256, Bloom, Bytes, TxKind, U256};
+    use alloy::providers::{Provider, ProviderBuilder};
+    use alloy::signers::SignerSync;
+    use alloy::signers::local::PrivateKeySigner;
+    use alloy::transports::mock::Asserter;
+    use zksync_os_contract_interface::ZkChain;
+    use zksync_os_genesis::GenesisBuilder;
+    use zksync_os_storage_api::RepositoryError;
+    use zksync_os_types::{L2Envelope, ZkReceipt};
+
+    pub(crate) fn test_genesis() -> Genesis {
+        let provider = ProviderBuilder::new()
+            .connect_mocked_client(Asserter::new())
+            .erased();
+        GenesisBuilder::new()
+            .execution_version(1)
+            .into_genesis(ZkChain::new(Address::ZERO, provider))
+            .unwrap()
+    }
+
+    /// Writes block `number` on top of the repository head. The block contains a single transfer
+    /// from `signer` with nonce `number`, whose hash is returned.
+    pub(crate) fn write_block(
+        repository: &RepositoryDb,
+        signer: &PrivateKeySigner,
+        number: BlockNumber,
+    ) -> TxHash {
+        let (block, txs) = test_block(repository, signer, number);
+        repository.write_block(&block, &txs);
+        block.body.transactions[0]
+    }
+
+    fn test_block(
+        repository: &RepositoryDb,
+        signer: &PrivateKeySigner,
+        number: BlockNumber,
+    ) -> (Sealed<Block<TxHash>>, Vec<Arc<StoredTxData>>) {
+        let tx = TxLegacy {
+            chain_id: Some(270),
+            nonce: number,
+            gas_price: 1_000_000_000,
+            gas_limit: 21_000,
+            to: TxKind::Call(Address::repeat_byte(1)),
+            value: U256::from(1),
+            input: Bytes::new(),
+        };
+        let signature = signer.sign_hash_sync(&tx.signature_hash()).unwrap();
+        let tx_bytes = L2Envelope::from(tx.into_signed(signature)).encoded_2718();
+        let tx = ZkEnvelope::decode_2718(&mut tx_bytes.as_slice())
+            .unwrap()
+            .try_into_recovered()
+            .unwrap();
+        let tx_hash = *tx.hash();
+
+        let hash = B256::repeat_byte(number as u8);
+        let receipt = ZkReceiptEnvelope::Legacy(ReceiptWithBloom {
+            receipt: ZkReceipt {
+                status: true.into(),
+                cumulative_gas_used: 21_000,
+                logs: vec![],
+                l2_to_l1_logs: vec![],
+            },
+            logs_bloom: Bloom::default(),
+        });
+        let meta = TxMeta {
+            block_hash: hash,
+            block_number: number,
+            block_timestamp: number,
+            tx_index_in_block: 0,
+            effective_gas_price: 1_000_000_000,
+            number_of_logs_before_this_tx: 0,
+            gas_used: 21_000,
+            contract_address: None,
+        };
+        let parent = repository.get_block_by_number(number - 1).unwrap().unwrap();
+        let block = Block {
+            header: Header {
+                number,
+                timestamp: number,
+                parent_hash: parent.hash(),
+                ..Header::default()
+            },
+            body: BlockBody {
+                transactions: vec![tx_hash],
+                ommers: vec![],
+                withdrawals: None,
+            },
+        };
+        (
+            Sealed::new_unchecked(block, hash),
+            vec![Arc::new(StoredTxData { tx, receipt, meta })],
+        )
+    }
+
+    #[tokio::test]
+    async fn pruned_transactions_are_not_served() {
+        let dir = tempfile::tempdir().unwrap();
+        let repository = RepositoryDb::new(dir.path(), &test_genesis()).await;
+        let signer = PrivateKeySigner::random();
+        let tx_hashes: Vec<_> = (1..=5)
+            .map(|number| write_block(&repository, &signer, number))
+            .collect();
+
+        let stats = repository.prune(3).unwrap();
+        assert_eq!(stats.transactions, 2);
+        assert_eq!(stats.entries, 8);
+        assert!(stats.reclaimed_bytes > 0);
+        assert_eq!(repository.first_unpruned_block(), 3);
+
+        for (number, tx_hash) in (1..=5).zip(tx_hashes) {
+            // Headers and transaction hashes are kept for pruned blocks
+            let block = repository.get_block_by_number(number).unwrap().unwrap();
+            assert_eq!(block.body.transactions, [tx_hash]);
+            assert_eq!(
+                repository.get_block_by_hash(block.hash()).unwrap().as_ref(),
+                Some(&block)
+            );
+
+            let available = repository.ensure_transactions_available(&block);
+            let stored_tx = repository.get_stored_transaction(tx_hash).unwrap();
+            let hash_by_nonce = repository
+                .get_transaction_hash_by_sender_nonce(signer.address(), number)
+                .unwrap();
+            if number < 3 {
+                assert!(
+                    matches!(available, Err(RepositoryError::Pruned(n)) if n == number),
+                    "{available:?}"
+                );
+                assert!(stored_tx.is_none());
+                assert!(repository.get_raw_transaction(tx_hash).unwrap().is_none());
+                assert_eq!(hash_by_nonce, None);
+            } else {
+                available.unwrap();
+                assert_eq!(stored_tx.unwrap().meta.block_number, number);
+                assert_eq!(hash_by_nonce, Some(tx_hash));
+            }
+        }
+        // Genesis has no transactions to prune
+        let genesis_block = repository.get_block_by_number(0).unwrap().unwrap();
+        repository
+            .ensure_transactions_available(&genesis_block)
+            .unwrap();
+
+        // Pruning below the horizon is a no-op, and the horizon is persisted
+        assert_eq!(repository.prune(2).unwrap(), PruneStats::default());
+        drop(repository);
+        let repository = RepositoryDb::open_existing(dir.path()).unwrap();
+        assert_eq!(repository.first_unpruned_block(), 3);
+        assert_eq!(repository.prune(6).unwrap().transactions, 3);
+    }
+
+    #[tokio::test]
+    async fn partially_written_blocks_are_repaired_on_startup() {
+        let signer = PrivateKeySigner::random();
+        // Simulate a crash after every prefix of the writes persisting block 2
+        for written in 0..7 {
+            let dir = tempfile::tempdir().unwrap();
+            let repository = RepositoryDb::new(dir.path(), &test_genesis()).await;
+            write_block(&repository, &signer, 1);
+            let (block, txs) = test_block(&repository, &signer, 2);
+            let writes = RepositoryDb::block_writes(&block, &txs);
+            // Block number to hash, block data, 4 entries for the transaction and the marker
+            assert_eq!(writes.len(), 7);
+            let mut batch = repository.db.new_write_batch();
+            for (cf, key, value) in &writes[..written] {
+                batch.put_cf(*cf, key, value);
+            }
+            repository.db.write(batch).unwrap();
+
+            let result = repository.verify_block_complete(2);
+            if written == 0 {
+                result.unwrap();
+            } else {
+                assert!(
+                    matches!(
+                        &result,
+                        Err(RepositoryError::IncompleteBlock { number: 2, missing })
+                            if missing.last() == Some(&MissingBlockEntry::CompletionMarker)
+                    ),
+                    "{written}: {result:?}"
+                );
+            }
+            drop(repository);
+
+            let repository = RepositoryDb::new(dir.path(), &test_genesis()).await;
+            assert_eq!(repository.get_latest_block(), 1);
+            repository.verify_block_complete(1).unwrap();
+            repository.verify_block_complete(2).unwrap();
+            for (cf, key, _) in &writes[..writes.len() - 1] {
+                assert_eq!(repository.db.get_cf(*cf, key).unwrap(), None, "{cf:?}");
+            }
+
+            // The block is re-populated when replayed
+            let tx_hash = write_block(&repository, &signer, 2);
+            repository.verify_block_complete(2).unwrap();
+            assert!(
+                repository
+                    .get_stored_transaction(tx_hash)
+                    .unwrap()
+                    .is_some()
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_entries_of_persisted_blocks_are_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let repository = RepositoryDb::new(dir.path(), &test_genesis()).await;
+        let signer = PrivateKeySigner::random();
+        let tx_hashes: Vec<_> = (1..=3)
+            .map(|number| write_block(&repository, &signer, number))
+            .collect();
+
+        let mut batch = repository.db.new_write_batch();
+        batch.delete_cf(RepositoryCF::TxReceipt, &tx_hashes[1].0);
+        repository.db.write(batch).unwrap();
+        let result = repository.verify_block_complete(2);
+        assert!(
+            matches!(
+                &result,
+                Err(RepositoryError::IncompleteBlock { number: 2, missing })
+                    if *missing == [MissingBlockEntry::Receipt(tx_hashes[1])]
+            ),
+            "{result:?}"
+        );
+        repository.verify_block_complete(3).unwrap();
+
+        repository.repair_block(2).unwrap();
+        assert_eq!(repository.get_latest_block(), 1);
+        for number in 2..=3 {
+            assert!(repository.get_block_by_number(number).unwrap().is_none());
+            assert!(
+                repository
+                    .get_transaction_hash_by_sender_nonce(signer.address(), number)
+                    .unwrap()
+                    .is_none()
+            );
+        }
+        repository.verify_block_complete(1).unwrap();
+        drop(repository);
+        let repository = RepositoryDb::open_existing(dir.path()).unwrap();
+        assert_eq!(repository.get_latest_block(), 1);
+    }
 }
@@ -12,12 +12,15 @@ use zksync_os_genesis::Genesis;
 use zksync_os_interface::types::BlockOutput;
 use zksync_os_storage_api::notifications::{BlockNotification, SubscribeToBlocks};
 use zksync_os_storage_api::{
-    ReadRepository, RepositoryBlock, RepositoryResult, StoredTxData, TxMeta, WriteRepository,
+    ReadFinality, ReadRepository, RepositoryBlock, RepositoryResult, StoredTxData, TxMeta,
+    WriteRepository,
 };
 use zksync_os_types::{ZkReceiptEnvelope, ZkTransaction};
 
 /// Size of the broadcast channel used to notify about new blocks.
 const BLOCK_NOTIFICATION_CHANNEL_SIZE: usize = 256;
+/// Max number of blocks pruned in a single DB write.
+const PRUNE_CHUNK_BLOCKS: u64 = 1_000;
 
 /// Manages a composed view on in-memory repositories and DB-backed repositories.
 /// Persists in-memory objects in the background and makes sure in-memory storage does not grow above
@@ -50,6 +53,11 @@ impl RepositoryManager {
         }
     }
 
+    /// Returns the latest block persisted to the DB. Blocks above it are only kept in memory.
+    pub fn persisted_block(&self) -> u64 {
+        self.db.get_latest_block()
+    }
+
     // fixme: as this loop is not tied to state compacting, it can fall behind and result in
     //        unrecoverable state on restart
     pub async fn run_persist_loop(&self) {
@@ -97,6 +105,59 @@ impl RepositoryManager {
             REPOSITORIES_METRICS.persist_block_number.set(block_number);
         }
     }
+
+    /// Prunes transactions, receipts and transaction metadata of blocks that are neither among
+    /// `keep_blocks` latest persisted blocks nor above the last block executed on L1 - the latter
+    /// may still be needed by the batcher or batch verification. Block headers are kept.
+    pub async fn run_pruning_loop(&self, keep_blocks: u64, finality: impl ReadFinality) {
+        let mut finality = finality.subscribe();
+        REPOSITORIES_METRICS
+            .first_unpruned_block
+            .set(self.db.first_unpruned_block());
+        loop {
+            let last_executed_block = finality.borrow_and_update().last_executed_block;
+            let prune_before =
+                prune_horizon(self.db.get_latest_block(), keep_blocks, last_executed_block);
+            while self.db.first_unpruned_block() < prune_before {
+                let first_unpruned_block = self.db.first_unpruned_block();
+                let chunk_end = (first_unpruned_block + PRUNE_CHUNK_BLOCKS).min(prune_before);
+                let db = self.db.clone();
+                let stats = tokio::task::spawn_blocking(move || db.prune(chunk_end))
+                    .await
+                    .expect("repository pruning panicked");
+                match stats {
+                    Ok(stats) => tracing::info!(
+                        pruned_blocks = ?(first_unpruned_block..chunk_end),
+                        transactions = stats.transactions,
+                        entries = stats.entries,
+                        reclaimed_bytes = stats.reclaimed_bytes,
+                        "pruned repository",
+                    ),
+                    Err(err) => {
+                        tracing::error!(?err, chunk_end, "failed to prune repository");
+                        break;
+                    }
+                }
+            }
+
+            if finality.changed().await.is_err() {
+                tracing::warn!("finality channel closed, stopping repository pruning");
+                return;
+            }
+        }
+    }
+}
+
+/// Returns the first block that must not be pruned: either the first of `keep_blocks` latest
+/// blocks or the block after the last executed one, whichever is lower.
+fn prune_horizon(
+    latest_block: BlockNumber,
+    keep_blocks: u64,
+    last_executed_block: BlockNumber,
+) -> BlockNumber {
+    (latest_block + 1)
+        .saturating_sub(keep_blocks)
+        .min(last_executed_block + 1)
 }
 
 impl ReadRepository for RepositoryManager {
@@ -179,6 +240,10 @@ impl ReadRepository for RepositoryManager {
             .get_latest_block()
             .max(self.db.get_latest_block())
     }
+
+    fn first_unpruned_block(&self) -> BlockNumber {
+        self.db.first_unpruned_block()
+    }
 }
 
 impl WriteRepository for RepositoryManager {
@@ -225,3 +290,76 @@ impl SubscribeToBlocks for RepositoryManager {
         self.block_sender.subscribe()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::{test_genesis, write_block};
+    use crate::in_memory::Finality;
+    use alloy::signers::local::PrivateKeySigner;
+    use zksync_os_storage_api::{FinalityStatus, WriteFinality};
+
+    #[test]
+    fn prune_horizon_is_gated_by_executed_blocks() {
+        assert_eq!(prune_horizon(100, 10, 100), 91);
+        assert_eq!(prune_horizon(100, 10, 50), 51);
+        assert_eq!(prune_horizon(100, 0, 100), 101);
+        // Nothing is pruned while the chain is shorter than `keep_blocks`
+        assert_eq!(prune_horizon(5, 10, 5), 0);
+    }
+
+    #[tokio::test]
+    async fn pruning_follows_executed_blocks() {
+        let dir = tempfile::tempdir().unwrap();
+        let repositories = RepositoryManager::new(16, dir.path().to_owned(), &test_genesis()).await;
+        let signer = PrivateKeySigner::random();
+        for number in 1..=5 {
+            write_block(&repositories.db, &signer, number);
+        }
+        let finality = Finality::new(FinalityStatus {
+            last_committed_block: 5,
+            last_committed_batch: 5,
+            last_executed_block: 2,
+            last_executed_batch: 2,
+        });
+        let mut pruning = tokio::spawn({
+            let repositories = repositories.clone();
+            let finality = finality.clone();
+            async move { repositories.run_pruning_loop(1, finality).await }
+        });
+
+        let wait_for_horizon = |horizon| {
+            let repositories = repositories.clone();
+            async move {
+                while repositories.first_unpruned_block() < horizon {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(10), wait_for_horizon(3))
+            .await
+            .unwrap();
+        // Unexecuted blocks are not pruned
+        tokio::time::timeout(Duration::from_millis(100), &mut pruning)
+            .await
+            .unwrap_err();
+        assert_eq!(repositories.first_unpruned_block(), 3);
+
+        finality.update_finality_status(|status| {
+            status.last_executed_block = 5;
+            status.last_executed_batch = 5;
+        });
+        tokio::time::timeout(Duration::from_secs(10), wait_for_horizon(5))
+            .await
+            .unwrap();
+        // The latest block is kept
+        tokio::time::timeout(Duration::from_millis(100), &mut pruning)
+            .await
+            .unwrap_err();
+        assert_eq!(repositories.first_unpruned_block(), 5);
+        let latest_block = repositories.get_block_by_number(5).unwrap().unwrap();
+        repositories
+            .ensure_transactions_available(&latest_block)
+            .unwrap();
+    }
+}
@@ -1,6 +1,6 @@
 use alloy::primitives::BlockNumber;
 use std::time::Duration;
-use vise::{Buckets, Gauge, Histogram, LabeledFamily, Metrics, Unit};
+use vise::{Buckets, Counter, Gauge, Histogram, LabeledFamily, Metrics, Unit};
 
 const LATENCIES_FAST: Buckets = Buckets::exponential(0.0000001..=1.0, 2.0);
 const LATENCIES: Buckets = Buckets::exponential(0.00001..=5.0, 2.0);
@@ -24,6 +24,16 @@ pub struct RepositoriesMetrics {
     pub block_data_size_per_tx: Histogram<usize>,
     pub in_memory_txs_count: Gauge<usize>,
     pub persist_block_number: Gauge<BlockNumber>,
+    /// Entries deleted by repository pruning, per column family.
+    #[metrics(labels = ["column_family"])]
+    pub pruned_entries: LabeledFamily<&'static str, Counter>,
+    /// Total size of keys and values deleted by repository pruning.
+    #[metrics(unit = Unit::Bytes)]
+    pub pruned: Counter,
+    /// Transactions of blocks before this one are pruned.
+    pub first_unpruned_block: Gauge<BlockNumber>,
+    /// Partially indexed blocks removed from the DB to be re-populated.
+    pub repaired_blocks: Counter,
 }
 
 #[vise::register]
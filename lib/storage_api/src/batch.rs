@@ -1,5 +1,6 @@
 use crate::ReadFinality;
 use alloy::primitives::BlockNumber;
+use zksync_os_types::BatchDetails;
 
 #[async_trait::async_trait]
 pub trait ReadBatch: Send + Sync + 'static {
@@ -17,4 +18,12 @@ pub trait ReadBatch: Send + Sync + 'static {
         &self,
         batch_number: u64,
     ) -> anyhow::Result<Option<(BlockNumber, BlockNumber)>>;
+
+    /// Get details of the batch with the given number, including L1 transactions of its lifecycle
+    /// stages. Returns `None` for unknown batches and for the genesis batch, which is not stored.
+    async fn get_batch_details(
+        &self,
+        batch_number: u64,
+        finality: &dyn ReadFinality,
+    ) -> anyhow::Result<Option<BatchDetails>>;
 }
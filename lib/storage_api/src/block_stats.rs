@@ -0,0 +1,139 @@
+use alloy::primitives::B256;
+use std::collections::HashSet;
+use zksync_os_interface::types::{BlockOutput, StorageWrite};
+use zksync_os_types::L1PricePrediction;
+
+/// Per-block resource usage and state-diff statistics.
+///
+/// Computed once by the sequencer right after a block is executed and passed downstream along with
+/// the `BlockOutput`, so that consumers (batch sealing, metrics, logs) don't need to iterate the output again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BlockStats {
+    /// Number of successfully executed transactions.
+    pub tx_count: u64,
+    pub gas_used: u64,
+    pub gas_limit: u64,
+    pub computational_native_used: u64,
+    pub pubdata_bytes: u64,
+    pub storage_writes: u64,
+    /// Number of distinct storage slots written in the block.
+    pub unique_storage_keys: u64,
+    pub new_preimages: u64,
+    /// Total size of the new preimages.
+    pub preimage_bytes: u64,
+    pub account_diffs: u64,
+    pub l2_to_l1_logs: u64,
+    /// L1 prices predicted when the block was produced; `None` for replayed blocks.
+    /// Set by the sequencer.
+    pub l1_price_prediction: Option<L1PricePrediction>,
+}
+
+impl BlockStats {
+    pub fn new(block_output: &BlockOutput) -> Self {
+        let executed_txs = block_output.tx_results.iter().flatten();
+        Self {
+            tx_count: executed_txs.clone().count() as u64,
+            gas_used: block_output.header.gas_used,
+            gas_limit: block_output.header.gas_limit,
+            computational_native_used: block_output.computaional_native_used,
+            pubdata_bytes: block_output.pubdata.len() as u64,
+            account_diffs: block_output.account_diffs.len() as u64,
+            l2_to_l1_logs: executed_txs.map(|tx| tx.l2_to_l1_logs.len() as u64).sum(),
+            ..Self::default()
+        }
+        .with_state_diff(
+            &block_output.storage_writes,
+            &block_output.published_preimages,
+        )
+    }
+
+    fn with_state_diff(
+        mut self,
+        storage_writes: &[StorageWrite],
+        preimages: &[(B256, Vec<u8>)],
+    ) -> Self {
+        self.storage_writes = storage_writes.len() as u64;
+        self.unique_storage_keys = storage_writes
+            .iter()
+            .map(|write| write.key)
+            .collect::<HashSet<_>>()
+            .len() as u64;
+        self.new_preimages = preimages.len() as u64;
+        self.preimage_bytes = preimages
+            .iter()
+            .map(|(_, preimage)| preimage.len() as u64)
+            .sum();
+        self
+    }
+
+    /// Share of the block gas limit used by the block, in `[0, 1]`.
+    pub fn gas_utilization(&self) -> f64 {
+        ratio(self.gas_used, self.gas_limit)
+    }
+
+    /// Share of `pubdata_limit_bytes` used by the block, in `[0, 1]`.
+    pub fn pubdata_utilization(&self, pubdata_limit_bytes: u64) -> f64 {
+        ratio(self.pubdata_bytes, pubdata_limit_bytes)
+    }
+}
+
+fn ratio(used: u64, limit: u64) -> f64 {
+    if limit == 0 {
+        0.0
+    } else {
+        used as f64 / limit as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(key: u8, value: u8) -> StorageWrite {
+        StorageWrite {
+            key: B256::repeat_byte(key),
+            value: B256::repeat_byte(value),
+            account: Default::default(),
+            account_key: Default::default(),
+        }
+    }
+
+    #[test]
+    fn state_diff_stats() {
+        // 4 writes to 3 distinct slots; 2 preimages of 3 + 5 bytes
+        let storage_writes = [write(1, 1), write(2, 1), write(1, 2), write(3, 3)];
+        let preimages = [
+            (B256::repeat_byte(10), vec![0; 3]),
+            (B256::repeat_byte(11), vec![0; 5]),
+        ];
+        let stats = BlockStats::default().with_state_diff(&storage_writes, &preimages);
+        assert_eq!(
+            stats,
+            BlockStats {
+                storage_writes: 4,
+                unique_storage_keys: 3,
+                new_preimages: 2,
+                preimage_bytes: 8,
+                ..BlockStats::default()
+            }
+        );
+
+        let empty = BlockStats::default().with_state_diff(&[], &[]);
+        assert_eq!(empty, BlockStats::default());
+    }
+
+    #[test]
+    fn utilization() {
+        let stats = BlockStats {
+            gas_used: 25,
+            gas_limit: 100,
+            pubdata_bytes: 30,
+            ..BlockStats::default()
+        };
+        assert_eq!(stats.gas_utilization(), 0.25);
+        assert_eq!(stats.pubdata_utilization(120), 0.25);
+        // Zero limits don't produce NaNs
+        assert_eq!(BlockStats::default().gas_utilization(), 0.0);
+        assert_eq!(stats.pubdata_utilization(0), 0.0);
+    }
+}
@@ -1,7 +1,10 @@
+mod block_stats;
+pub use block_stats::BlockStats;
+
 mod model;
 mod replay_wire_format;
 pub use model::{FinalityStatus, ReplayRecord, StoredTxData, TxMeta};
-pub use replay_wire_format::REPLAY_WIRE_FORMAT_VERSION;
+pub use replay_wire_format::{REPLAY_WIRE_FORMAT_VERSION, ReplayDecodeError};
 
 mod replay;
 pub use replay::{ReadReplay, ReadReplayExt, WriteReplay};
@@ -16,14 +19,20 @@ pub use finality::{ReadFinality, WriteFinality};
 
 mod repository;
 pub use repository::{
-    ReadRepository, RepositoryBlock, RepositoryError, RepositoryResult, WriteRepository,
+    MissingBlockEntry, ReadRepository, RepositoryBlock, RepositoryError, RepositoryResult,
+    WriteRepository,
 };
 
 mod metered_state;
 pub use metered_state::{MeteredViewState, StateAccessLabel};
 
 mod state;
-pub use state::{ReadStateHistory, StateError, StateResult, ViewState, WriteState};
+pub use state::{
+    ReadAccountKeys, ReadStateHistory, StateError, StateResult, ViewState, WriteState,
+};
 
 pub mod state_override_view;
 pub use state_override_view::OverriddenStateView;
+
+mod stacked_state_view;
+pub use stacked_state_view::StackedStateView;
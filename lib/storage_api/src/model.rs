@@ -36,6 +36,11 @@ pub struct ReplayRecord {
     /// The field is used to generate the prover input for the block in ProverInputGenerator.
     /// Will be moved to the BlockContext at some point
     pub previous_block_timestamp: u64,
+    /// Block timestamp in milliseconds, as recorded by the sequencer when producing the block.
+    /// `block_context.timestamp` is this value in seconds; the milliseconds part is not visible
+    /// to execution. Always taken from the record, never derived from the wall clock on replay.
+    /// For blocks recorded before it was introduced, equals `block_context.timestamp * 1000`.
+    pub block_timestamp_millis: u64,
     /// Version of the node that created this replay record.
     pub node_version: semver::Version,
     /// Hash of the block output.
@@ -48,6 +53,7 @@ impl ReplayRecord {
         starting_l1_priority_id: L1TxSerialId,
         transactions: Vec<ZkTransaction>,
         previous_block_timestamp: u64,
+        block_timestamp_millis: u64,
         node_version: semver::Version,
         block_output_hash: B256,
     ) -> Self {
@@ -62,11 +68,17 @@ impl ReplayRecord {
                 "First L1 tx priority id must match next_l1_priority_id"
             );
         }
+        assert_eq!(
+            block_timestamp_millis / 1000,
+            block_context.timestamp,
+            "Block timestamp in millis must match the block context"
+        );
         Self {
             block_context,
             starting_l1_priority_id,
             transactions,
             previous_block_timestamp,
+            block_timestamp_millis,
             node_version,
             block_output_hash,
         }
@@ -43,6 +43,17 @@ pub trait ReadReplay: Send + Sync + 'static {
     /// * MAY return `Some(_)` for block numbers after latest
     fn get_replay_record(&self, block_number: BlockNumber) -> Option<ReplayRecord>;
 
+    /// Get block's timestamp in milliseconds (see [`ReplayRecord::block_timestamp_millis`]).
+    ///
+    /// This method:
+    /// * MUST be thread-safe
+    /// * MUST return `Some(_)` if [`get_replay_record`](Self::get_replay_record) returns `Some(_)`
+    ///   for the same block number, and the same value as the replay record
+    fn get_block_timestamp_millis(&self, block_number: BlockNumber) -> Option<u64> {
+        self.get_replay_record(block_number)
+            .map(|record| record.block_timestamp_millis)
+    }
+
     /// Returns the latest (greatest) record's block number.
     ///
     /// This method:
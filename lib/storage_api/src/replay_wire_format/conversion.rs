@@ -2,6 +2,7 @@ use super::v1::ReplayWireFormatV1;
 use super::v2::ReplayWireFormatV2;
 use super::v3::ReplayWireFormatV3;
 use super::v4::ReplayWireFormatV4;
+use super::v5::ReplayWireFormatV5;
 use crate::ReplayRecord;
 use alloy::eips::{Decodable2718, Encodable2718};
 use alloy::primitives::{Address, U256};
@@ -52,6 +53,8 @@ impl From<ReplayWireFormatV1> for ReplayRecord {
             starting_l1_priority_id,
             transactions: transactions.into_iter().map(|tx| tx.into()).collect(),
             previous_block_timestamp,
+            // Not recorded before v5
+            block_timestamp_millis: timestamp * 1000,
             node_version,
             block_output_hash,
         }
@@ -103,6 +106,8 @@ impl From<ReplayWireFormatV2> for ReplayRecord {
             starting_l1_priority_id,
             transactions: transactions.into_iter().map(|tx| tx.into()).collect(),
             previous_block_timestamp,
+            // Not recorded before v5
+            block_timestamp_millis: timestamp * 1000,
             node_version,
             block_output_hash,
         }
@@ -152,6 +157,8 @@ impl From<ReplayWireFormatV3> for ReplayRecord {
             starting_l1_priority_id,
             transactions: transactions.into_iter().map(|tx| tx.into()).collect(),
             previous_block_timestamp,
+            // Not recorded before v5
+            block_timestamp_millis: timestamp * 1000,
             node_version,
             block_output_hash,
         }
@@ -202,19 +209,74 @@ impl From<ReplayWireFormatV4> for ReplayRecord {
             starting_l1_priority_id,
             transactions: transactions.into_iter().map(|tx| tx.into()).collect(),
             previous_block_timestamp,
+            // Not recorded before v5
+            block_timestamp_millis: timestamp * 1000,
             node_version,
             block_output_hash,
         }
     }
 }
 
-impl From<ReplayRecord> for ReplayWireFormatV4 {
+impl From<ReplayWireFormatV5> for ReplayRecord {
+    fn from(value: ReplayWireFormatV5) -> Self {
+        let ReplayWireFormatV5 {
+            block_context,
+            starting_l1_priority_id,
+            transactions,
+            previous_block_timestamp,
+            block_timestamp_millis,
+            node_version,
+            block_output_hash,
+        } = value;
+        let super::v5::BlockContext {
+            chain_id,
+            block_number,
+            block_hashes,
+            timestamp,
+            eip1559_basefee,
+            pubdata_price,
+            native_price,
+            coinbase,
+            gas_limit,
+            pubdata_limit,
+            mix_hash,
+            execution_version,
+            blob_fee,
+        } = block_context;
+        Self {
+            block_context: BlockContext {
+                chain_id,
+                block_number,
+                block_hashes: BlockHashes(block_hashes.0),
+                timestamp,
+                eip1559_basefee,
+                pubdata_price,
+                native_price,
+                coinbase,
+                gas_limit,
+                pubdata_limit,
+                mix_hash,
+                execution_version,
+                blob_fee,
+            },
+            starting_l1_priority_id,
+            transactions: transactions.into_iter().map(|tx| tx.into()).collect(),
+            previous_block_timestamp,
+            block_timestamp_millis,
+            node_version,
+            block_output_hash,
+        }
+    }
+}
+
+impl From<ReplayRecord> for ReplayWireFormatV5 {
     fn from(value: ReplayRecord) -> Self {
         let ReplayRecord {
             block_context,
             starting_l1_priority_id,
             transactions,
             previous_block_timestamp,
+            block_timestamp_millis,
             node_version,
             block_output_hash,
         } = value;
@@ -234,10 +296,10 @@ impl From<ReplayRecord> for ReplayWireFormatV4 {
             blob_fee,
         } = block_context;
         Self {
-            block_context: super::v4::BlockContext {
+            block_context: super::v5::BlockContext {
                 chain_id,
                 block_number,
-                block_hashes: super::v4::BlockHashes(block_hashes.0),
+                block_hashes: super::v5::BlockHashes(block_hashes.0),
                 timestamp,
                 eip1559_basefee,
                 pubdata_price,
@@ -252,13 +314,14 @@ impl From<ReplayRecord> for ReplayWireFormatV4 {
             starting_l1_priority_id,
             transactions: transactions.into_iter().map(|tx| tx.into()).collect(),
             previous_block_timestamp,
+            block_timestamp_millis,
             node_version,
             block_output_hash,
         }
     }
 }
 
-impl From<zksync_os_types::ZkTransaction> for super::v4::ZkTransactionWireFormat {
+impl From<zksync_os_types::ZkTransaction> for super::v5::ZkTransactionWireFormat {
     fn from(value: zksync_os_types::ZkTransaction) -> Self {
         Self(value.inner.encoded_2718())
     }
@@ -299,3 +362,12 @@ impl From<super::v4::ZkTransactionWireFormat> for zksync_os_types::ZkTransaction
             .unwrap()
     }
 }
+
+impl From<super::v5::ZkTransactionWireFormat> for zksync_os_types::ZkTransaction {
+    fn from(value: super::v5::ZkTransactionWireFormat) -> Self {
+        ZkEnvelope::decode_2718(&mut &value.0[..])
+            .unwrap()
+            .try_into_recovered()
+            .unwrap()
+    }
+}
@@ -11,52 +11,53 @@ mod v2;
 mod v3;
 #[rustfmt::skip]
 mod v4;
+#[rustfmt::skip]
+mod v5;
 
 #[cfg(test)]
 mod tests;
 
-pub const REPLAY_WIRE_FORMAT_VERSION: u32 = 4;
+pub const REPLAY_WIRE_FORMAT_VERSION: u32 = 5;
+
+/// Error decoding a replay record.
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayDecodeError {
+    #[error("Unsupported replay wire format version: {0}")]
+    UnsupportedVersion(u32),
+    #[error("malformed replay record: {0}")]
+    Malformed(#[from] bincode::error::DecodeError),
+}
 
 impl ReplayRecord {
     /// Encodes the replay using the current wire format version
     pub fn encode_with_current_version(self) -> Vec<u8> {
-        let wire_format = v4::ReplayWireFormatV4::from(self);
+        let wire_format = v5::ReplayWireFormatV5::from(self);
         bincode::encode_to_vec(wire_format, bincode::config::standard()).unwrap()
     }
 
     /// Decodes the replay from the given bytes using the specified wire format version.
     /// Panics if the wire format version is too old.
     pub fn decode(bytes: &[u8], version: u32) -> Self {
+        Self::try_decode(bytes, version).unwrap_or_else(|err| panic!("{err}"))
+    }
+
+    /// Fallible version of [`Self::decode`] for replays received from untrusted sources.
+    pub fn try_decode(bytes: &[u8], version: u32) -> Result<Self, ReplayDecodeError> {
+        fn decode_as<T: bincode::Decode<()> + Into<ReplayRecord>>(
+            bytes: &[u8],
+        ) -> Result<ReplayRecord, ReplayDecodeError> {
+            let (wire_format, _): (T, _) =
+                bincode::decode_from_slice(bytes, bincode::config::standard())?;
+            Ok(wire_format.into())
+        }
+
         match version {
-            1 => {
-                let wire_format: v1::ReplayWireFormatV1 =
-                    bincode::decode_from_slice(bytes, bincode::config::standard())
-                        .unwrap()
-                        .0;
-                wire_format.into()
-            }
-            2 => {
-                let wire_format: v2::ReplayWireFormatV2 =
-                    bincode::decode_from_slice(bytes, bincode::config::standard())
-                        .unwrap()
-                        .0;
-                wire_format.into()
-            }
-            3 => {
-                let wire_format: v3::ReplayWireFormatV3 =
-                    bincode::decode_from_slice(bytes, bincode::config::standard())
-                        .unwrap()
-                        .0;
-                wire_format.into()
-            }
-            4 => {
-                let wire_format: v4::ReplayWireFormatV4 =
-                    bincode::decode_from_slice(bytes, bincode::config::standard())
-                        .unwrap()
-                        .0;
-                wire_format.into()
-            }
-            _ => panic!("Unsupported replay wire format version: {version}"),
+            1 => decode_as::<v1::ReplayWireFormatV1>(bytes),
+            2 => decode_as::<v2::ReplayWireFormatV2>(bytes),
+            3 => decode_as::<v3::ReplayWireFormatV3>(bytes),
+            4 => decode_as::<v4::ReplayWireFormatV4>(bytes),
+            5 => decode_as::<v5::ReplayWireFormatV5>(bytes),
+            _ => Err(ReplayDecodeError::UnsupportedVersion(version)),
         }
     }
 }
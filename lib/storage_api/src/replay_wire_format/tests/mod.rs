@@ -17,3 +17,93 @@ pub fn can_decode_v3() {
     let encoded = include_bytes!("encoded_replay_v3.bin");
     let _replay_record = ReplayRecord::decode(encoded, 3);
 }
+
+#[test]
+pub fn sub_second_blocks_roundtrip() {
+    use alloy::primitives::B256;
+    use zksync_os_interface::types::BlockContext;
+
+    // 4 blocks within one second share the timestamp, but not the millis
+    for (i, millis) in [1_000_000, 1_000_250, 1_000_500, 1_000_750]
+        .into_iter()
+        .enumerate()
+    {
+        let record = ReplayRecord::new(
+            BlockContext {
+                block_number: i as u64 + 1,
+                timestamp: 1_000,
+                ..Default::default()
+            },
+            0,
+            vec![],
+            1_000,
+            millis,
+            "0.1.0".parse().unwrap(),
+            B256::ZERO,
+        );
+        let decoded = ReplayRecord::decode(
+            &record.clone().encode_with_current_version(),
+            super::REPLAY_WIRE_FORMAT_VERSION,
+        );
+        assert_eq!(decoded.block_timestamp_millis, millis);
+        assert_eq!(decoded.block_context.block_number, i as u64 + 1);
+        assert_eq!(decoded.block_context.timestamp, 1_000);
+        assert_eq!(decoded.previous_block_timestamp, 1_000);
+    }
+}
+
+#[test]
+pub fn base_token_prices_roundtrip() {
+    use alloy::primitives::{B256, U256};
+    use zksync_os_interface::types::BlockContext;
+
+    // Prices converted to a base token worth a tiny fraction of ETH may not fit into `u128`. They are
+    // recorded as is, so replaying the block doesn't need the conversion rate used by the sequencer.
+    let block_context = BlockContext {
+        block_number: 1,
+        timestamp: 1_000,
+        eip1559_basefee: U256::from(100_000_000_u64) * U256::from(10_u128.pow(30)),
+        native_price: U256::from(1_000_000_u64) * U256::from(10_u128.pow(30)),
+        pubdata_price: U256::from(17_000_000_000_u64) * U256::from(10_u128.pow(30)),
+        ..Default::default()
+    };
+    let record = ReplayRecord::new(
+        block_context,
+        0,
+        vec![],
+        999,
+        1_000_000,
+        "0.1.0".parse().unwrap(),
+        B256::ZERO,
+    );
+    let decoded = ReplayRecord::decode(
+        &record.encode_with_current_version(),
+        super::REPLAY_WIRE_FORMAT_VERSION,
+    );
+    assert_eq!(
+        decoded.block_context.eip1559_basefee,
+        block_context.eip1559_basefee
+    );
+    assert_eq!(
+        decoded.block_context.native_price,
+        block_context.native_price
+    );
+    assert_eq!(
+        decoded.block_context.pubdata_price,
+        block_context.pubdata_price
+    );
+}
+
+#[test]
+pub fn malformed_replay_is_an_error() {
+    use super::ReplayDecodeError;
+
+    let encoded = include_bytes!("encoded_replay_v3.bin");
+    let err = ReplayRecord::try_decode(&encoded[..encoded.len() / 2], 3).unwrap_err();
+    assert!(matches!(err, ReplayDecodeError::Malformed(_)), "{err}");
+    let err = ReplayRecord::try_decode(encoded, 0).unwrap_err();
+    assert!(
+        matches!(err, ReplayDecodeError::UnsupportedVersion(0)),
+        "{err}"
+    );
+}
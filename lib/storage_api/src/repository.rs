@@ -50,10 +50,71 @@ pub trait ReadRepository: Debug + Send + Sync + 'static {
 
     /// Returns earliest block number that is stored in the repository.
     fn get_earliest_block(&self) -> u64 {
-        // We presume that blocks never get pruned, so genesis block is always our earliest
-        // block.
+        // Block headers never get pruned, so genesis block is always our earliest block.
         0
     }
+
+    /// Returns the first block whose transactions, receipts and transaction metadata are stored.
+    /// Data of transactions from earlier blocks is pruned and looking it up by hash returns `None`.
+    fn first_unpruned_block(&self) -> BlockNumber {
+        0
+    }
+
+    /// Fails with [`RepositoryError::Pruned`] if transactions of `block` are pruned.
+    fn ensure_transactions_available(&self, block: &RepositoryBlock) -> RepositoryResult<()> {
+        if !block.body.transactions.is_empty() && block.number < self.first_unpruned_block() {
+            return Err(RepositoryError::Pruned(block.number));
+        }
+        Ok(())
+    }
+
+    /// Checks that block `number` is fully indexed, failing with
+    /// [`RepositoryError::IncompleteBlock`] otherwise. Blocks that are not present at all pass the
+    /// check.
+    fn verify_block_complete(&self, number: BlockNumber) -> RepositoryResult<()> {
+        let Some(block) = self.get_block_by_number(number)? else {
+            return Ok(());
+        };
+        let missing = self.missing_transaction_entries(&block)?;
+        if !missing.is_empty() {
+            return Err(RepositoryError::IncompleteBlock { number, missing });
+        }
+        Ok(())
+    }
+
+    /// Returns entries of `block`'s transactions (transactions themselves, receipts, metadata and
+    /// sender/nonce index entries) that are missing from the repository. Transactions of pruned
+    /// blocks are not checked.
+    fn missing_transaction_entries(
+        &self,
+        block: &RepositoryBlock,
+    ) -> RepositoryResult<Vec<MissingBlockEntry>> {
+        let mut missing = Vec::new();
+        if block.number < self.first_unpruned_block() {
+            return Ok(missing);
+        }
+        for &tx_hash in &block.body.transactions {
+            let tx = self.get_transaction(tx_hash)?;
+            if tx.is_none() {
+                missing.push(MissingBlockEntry::Transaction(tx_hash));
+            }
+            if self.get_transaction_receipt(tx_hash)?.is_none() {
+                missing.push(MissingBlockEntry::Receipt(tx_hash));
+            }
+            if self.get_transaction_meta(tx_hash)?.is_none() {
+                missing.push(MissingBlockEntry::Meta(tx_hash));
+            }
+            // The index is keyed by the transaction's sender and nonce, so it can only be checked
+            // if the transaction is present
+            if let Some(tx) = tx
+                && self.get_transaction_hash_by_sender_nonce(tx.signer(), tx.nonce())?
+                    != Some(tx_hash)
+            {
+                missing.push(MissingBlockEntry::SenderNonceIndex(tx_hash));
+            }
+        }
+        Ok(missing)
+    }
 }
 
 pub trait WriteRepository: ReadRepository {
@@ -64,6 +125,20 @@ pub trait WriteRepository: ReadRepository {
     ) -> impl Future<Output = RepositoryResult<()>> + Send;
 }
 
+/// Entry of a block missing from the repository.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MissingBlockEntry {
+    /// Block header and transaction hashes.
+    BlockData,
+    Transaction(TxHash),
+    Receipt(TxHash),
+    Meta(TxHash),
+    /// Sender and nonce to transaction hash mapping.
+    SenderNonceIndex(TxHash),
+    /// Marker written after all other entries of the block.
+    CompletionMarker,
+}
+
 /// Repository result type.
 pub type RepositoryResult<Ok> = Result<Ok, RepositoryError>;
 
@@ -76,4 +151,13 @@ pub enum RepositoryError {
     Eip2718(#[from] alloy::eips::eip2718::Eip2718Error),
     #[error(transparent)]
     Rlp(#[from] alloy::rlp::Error),
+    /// Transactions of the block were pruned on this node.
+    #[error("transactions of block {0} are pruned on this node; query an archive node instead")]
+    Pruned(BlockNumber),
+    /// Block is only partially indexed, e.g. because the node crashed while persisting it.
+    #[error("block {number} is partially indexed; missing entries: {missing:?}")]
+    IncompleteBlock {
+        number: BlockNumber,
+        missing: Vec<MissingBlockEntry>,
+    },
 }
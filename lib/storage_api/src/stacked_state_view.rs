@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::ViewState;
+use crate::state_override_view::build_state_override_maps;
+use alloy::primitives::B256;
+use alloy::rpc::types::state::StateOverride;
+use zksync_os_interface::traits::{PreimageSource, ReadStorage};
+use zksync_os_interface::types::StorageWrite;
+
+/// A `ViewState` wrapper with a stack of in-memory layers on top of the inner state, e.g. one
+/// layer per block simulated on top of a real block. Reads are served by the topmost layer that
+/// contains the key; all other reads/preimage lookups delegate to the inner state.
+///
+/// Layers are shared between clones, so cloning the view (as done for every executed
+/// transaction) doesn't copy the accumulated state.
+#[derive(Debug, Clone)]
+pub struct StackedStateView<V: ViewState> {
+    inner: V,
+    layers: Vec<Arc<StateLayer>>,
+}
+
+#[derive(Debug, Default)]
+struct StateLayer {
+    // flat storage keys
+    storage: HashMap<B256, B256>,
+    preimages: HashMap<B256, Vec<u8>>,
+}
+
+impl<V: ViewState> StackedStateView<V> {
+    pub fn new(inner: V) -> Self {
+        Self {
+            inner,
+            layers: Vec::new(),
+        }
+    }
+
+    /// Pushes a layer with the state diff of an executed block.
+    pub fn push_state_diff(
+        &mut self,
+        storage_writes: &[StorageWrite],
+        preimages: &[(B256, Vec<u8>)],
+    ) {
+        // Later writes to the same key take precedence
+        let storage = storage_writes
+            .iter()
+            .map(|write| (write.key, write.value))
+            .collect();
+        let preimages = preimages.iter().cloned().collect();
+        self.layers
+            .push(Arc::new(StateLayer { storage, preimages }));
+    }
+
+    /// Pushes a layer with RPC state overrides. Account overrides are applied on top of the
+    /// current top of the stack, e.g. overriding the balance keeps the nonce from lower layers.
+    pub fn push_overrides(&mut self, state_overrides: StateOverride) {
+        let (storage, preimages) = build_state_override_maps(self, state_overrides);
+        self.layers
+            .push(Arc::new(StateLayer { storage, preimages }));
+    }
+
+    /// Number of layers on top of the inner state.
+    pub fn depth(&self) -> usize {
+        self.layers.len()
+    }
+}
+
+impl<V: ViewState> ReadStorage for StackedStateView<V> {
+    fn read(&mut self, key: B256) -> Option<B256> {
+        for layer in self.layers.iter().rev() {
+            if let Some(value) = layer.storage.get(&key) {
+                return Some(*value);
+            }
+        }
+
+        self.inner.read(key)
+    }
+}
+
+impl<V: ViewState> PreimageSource for StackedStateView<V> {
+    fn get_preimage(&mut self, hash: B256) -> Option<Vec<u8>> {
+        for layer in self.layers.iter().rev() {
+            if let Some(bytes) = layer.preimages.get(&hash) {
+                return Some(bytes.clone());
+            }
+        }
+
+        self.inner.get_preimage(hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+    use alloy::rpc::types::state::AccountOverride;
+    use zk_os_api::helpers::{get_balance, get_nonce};
+
+    #[derive(Debug, Clone, Default)]
+    struct MapState {
+        storage: HashMap<B256, B256>,
+        preimages: HashMap<B256, Vec<u8>>,
+    }
+
+    impl ReadStorage for MapState {
+        fn read(&mut self, key: B256) -> Option<B256> {
+            self.storage.get(&key).copied()
+        }
+    }
+
+    impl PreimageSource for MapState {
+        fn get_preimage(&mut self, hash: B256) -> Option<Vec<u8>> {
+            self.preimages.get(&hash).cloned()
+        }
+    }
+
+    fn write(key: u8, value: u8) -> StorageWrite {
+        StorageWrite {
+            key: B256::repeat_byte(key),
+            value: B256::repeat_byte(value),
+            account: Address::repeat_byte(key),
+            account_key: B256::with_last_byte(key),
+        }
+    }
+
+    fn account_override(nonce: Option<u64>, balance: Option<u64>) -> AccountOverride {
+        AccountOverride {
+            nonce,
+            balance: balance.map(U256::from),
+            ..AccountOverride::default()
+        }
+    }
+
+    #[test]
+    fn upper_layers_take_precedence() {
+        let inner = MapState {
+            storage: HashMap::from([
+                (B256::repeat_byte(1), B256::repeat_byte(1)),
+                (B256::repeat_byte(2), B256::repeat_byte(1)),
+            ]),
+            preimages: HashMap::from([(B256::repeat_byte(1), vec![1])]),
+        };
+        let mut view = StackedStateView::new(inner);
+        view.push_state_diff(
+            &[write(2, 2), write(3, 2), write(3, 3)],
+            &[(B256::repeat_byte(1), vec![2])],
+        );
+        let lower_view = view.clone();
+        view.push_state_diff(&[write(3, 4)], &[]);
+        assert_eq!(view.depth(), 2);
+
+        assert_eq!(view.read(B256::repeat_byte(1)), Some(B256::repeat_byte(1)));
+        assert_eq!(view.read(B256::repeat_byte(2)), Some(B256::repeat_byte(2)));
+        assert_eq!(view.read(B256::repeat_byte(3)), Some(B256::repeat_byte(4)));
+        assert_eq!(view.read(B256::repeat_byte(4)), None);
+        assert_eq!(view.get_preimage(B256::repeat_byte(1)), Some(vec![2]));
+
+        // Clones taken before pushing a layer don't see it
+        let mut lower_view = lower_view;
+        assert_eq!(
+            lower_view.read(B256::repeat_byte(3)),
+            Some(B256::repeat_byte(3))
+        );
+    }
+
+    #[test]
+    fn overrides_are_applied_on_top_of_layers() {
+        let address = Address::repeat_byte(1);
+        let mut view = StackedStateView::new(MapState::default());
+        view.push_overrides(StateOverride::from_iter([(
+            address,
+            account_override(Some(5), Some(100)),
+        )]));
+        let account = view.get_account(address).unwrap();
+        assert_eq!(
+            (get_nonce(&account), get_balance(&account)),
+            (5, U256::from(100))
+        );
+
+        // Balance override keeps the nonce from the lower layer
+        view.push_overrides(StateOverride::from_iter([(
+            address,
+            account_override(None, Some(7)),
+        )]));
+        let account = view.get_account(address).unwrap();
+        assert_eq!(
+            (get_nonce(&account), get_balance(&account)),
+            (5, U256::from(7))
+        );
+    }
+}
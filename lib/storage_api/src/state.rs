@@ -39,6 +39,25 @@ pub trait ReadStateHistory: Debug + Send + Sync + 'static {
     /// Note that the block numbers that can be **run** against this state implementation are
     /// `(block_range_available.min + 1)..=(block_range_available.max + 1)`
     fn block_range_available(&self) -> std::ops::RangeInclusive<u64>;
+
+    /// Keys written in blocks `range` together with their values after the last block of `range`,
+    /// i.e. the latest write within the range. Each key is returned once, in unspecified order.
+    ///
+    /// Fails with [`StateError::Compacted`] if diffs for some blocks in `range` are already pruned,
+    /// and with [`StateError::NotFound`] if `range` ends after the latest block.
+    fn changed_keys(
+        &self,
+        range: std::ops::RangeInclusive<BlockNumber>,
+    ) -> StateResult<impl Iterator<Item = (B256, B256)> + '_>;
+
+    /// Writes to `key` in blocks `range` as `(block_number, value)` pairs ordered by block number.
+    ///
+    /// Fails in the same cases as [`Self::changed_keys()`].
+    fn key_history(
+        &self,
+        key: B256,
+        range: std::ops::RangeInclusive<BlockNumber>,
+    ) -> StateResult<impl Iterator<Item = (BlockNumber, B256)> + '_>;
 }
 
 pub trait WriteState: Send + Sync + 'static {
@@ -54,6 +73,21 @@ pub trait WriteState: Send + Sync + 'static {
         J: IntoIterator<Item = (B256, &'a Vec<u8>)>;
 }
 
+/// Reverse index of flat storage keys written for each account (i.e. keys derived from
+/// `(address, slot)` pairs, see [`derive_flat_storage_key`]).
+///
+/// The index is **not** part of the consensus state: it's only maintained on nodes that enable it,
+/// may miss blocks (e.g. while it's being backfilled), and must never be used for execution.
+pub trait ReadAccountKeys: Debug + Send + Sync + 'static {
+    /// Flat storage keys ever written for `address`, in ascending order. Keys whose value was
+    /// overwritten with zero are still returned.
+    fn storage_keys_for_account(&self, address: Address) -> anyhow::Result<Vec<B256>>;
+
+    /// Block up to which (inclusively) writes of all blocks are indexed, or `None` if no block is
+    /// indexed yet. Writes of later blocks may be indexed partially.
+    fn indexed_block(&self) -> Option<BlockNumber>;
+}
+
 /// State reader result type.
 pub type StateResult<Ok> = Result<Ok, StateError>;
 
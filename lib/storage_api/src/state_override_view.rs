@@ -55,7 +55,7 @@ impl<V: ViewState> PreimageSource for OverriddenStateView<V> {
 }
 
 /// Converts RPC `StateOverride` into unified slot overrides and preimage overrides.
-fn build_state_override_maps<V: ViewState>(
+pub(crate) fn build_state_override_maps<V: ViewState>(
     inner: &V,
     state_overrides: StateOverride,
 ) -> (HashMap<B256, B256>, HashMap<B256, Vec<u8>>) {
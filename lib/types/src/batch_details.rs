@@ -0,0 +1,63 @@
+use alloy::primitives::{B256, TxHash};
+use serde::{Deserialize, Serialize};
+
+/// Lifecycle stage of a batch; blocks share the status of their batch.
+///
+/// Ordered from the earliest to the latest stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BatchStatus {
+    /// Produced by the sequencer, but not committed to L1 yet.
+    Sequenced,
+    Committed,
+    Proven,
+    Executed,
+    /// The execute transaction is in a finalized L1 block.
+    Finalized,
+}
+
+/// Batch as known to the batcher and L1 senders, as returned by `zks_getBatchDetails`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchDetails {
+    pub number: u64,
+    pub status: BatchStatus,
+    pub first_block_number: u64,
+    pub last_block_number: u64,
+    pub tx_count: u64,
+    /// Criterion the batch was sealed by, e.g. `timeout` or `pubdata`. Missing for batches sealed
+    /// before it was recorded.
+    pub seal_reason: Option<String>,
+    /// Batch commitment (public input hash) as stored on L1.
+    pub commitment: B256,
+    /// L1 transactions of the batch lifecycle stages; missing for stages not reached yet, and for
+    /// stages performed by another node (e.g. on external nodes).
+    pub commit_tx: Option<BatchL1Tx>,
+    pub prove_tx: Option<BatchL1Tx>,
+    pub execute_tx: Option<BatchL1Tx>,
+}
+
+impl BatchDetails {
+    /// L1 transaction hashes of the commit, prove and execute stages.
+    pub fn l1_tx_hashes(&self) -> [Option<TxHash>; 3] {
+        [&self.commit_tx, &self.prove_tx, &self.execute_tx]
+            .map(|tx| tx.as_ref().map(|tx| tx.tx_hash))
+    }
+}
+
+/// L1 transaction that performed a lifecycle stage of a batch, along with the fees attributed to
+/// the batch. Transactions covering several batches have their fees split between them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchL1Tx {
+    pub tx_hash: TxHash,
+    /// Missing for transactions included before it was recorded.
+    pub l1_block_number: Option<u64>,
+    /// Unix timestamp (seconds) at which the transaction was observed as included.
+    pub timestamp: u64,
+    /// Whether the L1 block with the transaction is finalized.
+    pub finalized: bool,
+    pub gas_used: u64,
+    pub execution_fee_wei: u128,
+    pub blob_fee_wei: u128,
+}
@@ -0,0 +1,120 @@
+use alloy::primitives::{Address, B256, Bytes, Signature, SignatureError, keccak256};
+use serde::{Deserialize, Serialize};
+
+/// Blocks a transaction bundle may be included in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BundleInclusion {
+    /// Only the block with this number.
+    TargetBlock(u64),
+    /// Any block up to (and including) this number.
+    ExpiryBlock(u64),
+}
+
+impl BundleInclusion {
+    /// Returns whether the bundle may be included in `block_number`.
+    pub fn allows(&self, block_number: u64) -> bool {
+        match *self {
+            Self::TargetBlock(target) => block_number == target,
+            Self::ExpiryBlock(expiry) => block_number <= expiry,
+        }
+    }
+
+    /// Last block the bundle may be included in.
+    pub fn last_block(&self) -> u64 {
+        match *self {
+            Self::TargetBlock(block) | Self::ExpiryBlock(block) => block,
+        }
+    }
+}
+
+/// Ordered group of signed L2 transactions that must be included in a block atomically: either all
+/// of them in this order, or none.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionBundle {
+    /// EIP-2718 encoded transactions.
+    pub transactions: Vec<Bytes>,
+    pub inclusion: BundleInclusion,
+}
+
+impl TransactionBundle {
+    /// Domain separator preventing bundle signatures from being valid for other payloads.
+    const DOMAIN: &'static [u8] = b"zksync-os:bundle:v1";
+
+    /// Hash identifying the bundle and signed by its submitter: keccak256 of the domain separator
+    /// followed by transaction hashes and the inclusion rule (a `0` / `1` byte for target / expiry
+    /// block and the block number as a big-endian `u64`).
+    pub fn hash(&self) -> B256 {
+        let mut payload = Self::DOMAIN.to_vec();
+        for tx in &self.transactions {
+            payload.extend_from_slice(keccak256(tx).as_slice());
+        }
+        let (tag, block) = match self.inclusion {
+            BundleInclusion::TargetBlock(block) => (0_u8, block),
+            BundleInclusion::ExpiryBlock(block) => (1_u8, block),
+        };
+        payload.push(tag);
+        payload.extend_from_slice(&block.to_be_bytes());
+        keccak256(payload)
+    }
+}
+
+/// [`TransactionBundle`] together with the submitter's signature over its
+/// [hash](TransactionBundle::hash).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedTransactionBundle {
+    #[serde(flatten)]
+    pub bundle: TransactionBundle,
+    pub signature: Signature,
+}
+
+impl SignedTransactionBundle {
+    /// Recovers the address that signed the bundle.
+    pub fn recover_signer(&self) -> Result<Address, SignatureError> {
+        self.signature
+            .recover_address_from_prehash(&self.bundle.hash())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inclusion_rules() {
+        assert!(BundleInclusion::TargetBlock(5).allows(5));
+        assert!(!BundleInclusion::TargetBlock(5).allows(4));
+        assert!(BundleInclusion::ExpiryBlock(5).allows(1));
+        assert!(BundleInclusion::ExpiryBlock(5).allows(5));
+        assert!(!BundleInclusion::ExpiryBlock(5).allows(6));
+    }
+
+    #[test]
+    fn hash_commits_to_inclusion_and_order() {
+        let bundle = TransactionBundle {
+            transactions: vec![Bytes::from_static(&[1]), Bytes::from_static(&[2])],
+            inclusion: BundleInclusion::TargetBlock(7),
+        };
+        let other_rule = TransactionBundle {
+            inclusion: BundleInclusion::ExpiryBlock(7),
+            ..bundle.clone()
+        };
+        let reordered = TransactionBundle {
+            transactions: bundle.transactions.iter().rev().cloned().collect(),
+            ..bundle.clone()
+        };
+        assert_ne!(bundle.hash(), other_rule.hash());
+        assert_ne!(bundle.hash(), reordered.hash());
+
+        let json = serde_json::json!({
+            "transactions": ["0x01", "0x02"],
+            "inclusion": { "targetBlock": 7 },
+        });
+        assert_eq!(
+            serde_json::from_value::<TransactionBundle>(json).unwrap(),
+            bundle
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+/// L1 prices (in wei) predicted by the gas adjuster when a block was sequenced. Compared against
+/// the prices actually paid once the batch with the block is processed on L1.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct L1PricePrediction {
+    /// Gas price for L1 transactions.
+    pub gas_price: u128,
+    /// Price of a pubdata byte, including `pubdata_pricing_multiplier`.
+    pub pubdata_price: u128,
+}
+
+impl L1PricePrediction {
+    /// Mean of `predictions`, or `None` if there are none.
+    pub fn mean(predictions: impl IntoIterator<Item = Self>) -> Option<Self> {
+        let (count, gas_price, pubdata_price) = predictions.into_iter().fold(
+            (0u128, 0u128, 0u128),
+            |(count, gas_price, pubdata_price), prediction| {
+                (
+                    count + 1,
+                    gas_price + prediction.gas_price,
+                    pubdata_price + prediction.pubdata_price,
+                )
+            },
+        );
+        (count > 0).then(|| Self {
+            gas_price: gas_price / count,
+            pubdata_price: pubdata_price / count,
+        })
+    }
+}
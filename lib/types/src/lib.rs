@@ -1,12 +1,30 @@
 mod transaction_acceptance_state;
 pub use transaction_acceptance_state::{NotAcceptingReason, TransactionAcceptanceState};
 
+mod batch_details;
+pub use batch_details::{BatchDetails, BatchL1Tx, BatchStatus};
+
 mod block;
 pub use block::BlockExt;
 
+mod bundle;
+pub use bundle::{BundleInclusion, SignedTransactionBundle, TransactionBundle};
+
+mod l1_price_prediction;
+pub use l1_price_prediction::L1PricePrediction;
+
 mod log;
 pub use log::{L2_TO_L1_TREE_SIZE, L2ToL1Log};
 
+mod priority_deadlines;
+pub use priority_deadlines::PriorityDeadlines;
+
+mod replay_divergence;
+pub use replay_divergence::{ReplayDivergence, ReplayDivergenceStatus};
+
+mod shadow_execution;
+pub use shadow_execution::{ShadowDivergence, ShadowExecutionStatus};
+
 mod receipt;
 pub use receipt::{ZkReceipt, ZkReceiptEnvelope};
 
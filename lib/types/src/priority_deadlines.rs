@@ -0,0 +1,146 @@
+use crate::L1TxSerialId;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+
+/// Max number of deadlines of included transactions kept for the batcher. Nodes that don't run
+/// the batcher never prune them otherwise.
+const MAX_INCLUDED_DEADLINES: usize = 100_000;
+
+/// Expiration deadlines (unix timestamps, in seconds) of priority transactions that are not yet
+/// processed by the sequencer, keyed by priority id.
+///
+/// L1 contracts only allow a limited window for processing a priority transaction. Deadlines are
+/// recorded by the L1 transaction watcher, pruned by the sequencer once transactions are included in
+/// a block, and used by the sequencer and the expiry monitor to act before any of them expires.
+/// Deadlines of included transactions are kept until the batcher seals their batch, so that it can
+/// prioritize batches with transactions close to expiring.
+/// Cloning yields a handle to the same set of deadlines.
+#[derive(Debug, Clone, Default)]
+pub struct PriorityDeadlines(Arc<Mutex<Deadlines>>);
+
+#[derive(Debug, Default)]
+struct Deadlines {
+    pending: BTreeMap<L1TxSerialId, u64>,
+    /// Included in a block but not yet batched.
+    included: BTreeMap<L1TxSerialId, u64>,
+}
+
+impl PriorityDeadlines {
+    pub fn record(&self, priority_id: L1TxSerialId, expiration_timestamp: u64) {
+        self.0
+            .lock()
+            .unwrap()
+            .pending
+            .insert(priority_id, expiration_timestamp);
+    }
+
+    /// Removes deadlines of all transactions with priority id below `next_priority_id`.
+    pub fn remove_processed(&self, next_priority_id: L1TxSerialId) {
+        let mut deadlines = self.0.lock().unwrap();
+        let pending = deadlines.pending.split_off(&next_priority_id);
+        let processed = std::mem::replace(&mut deadlines.pending, pending);
+        deadlines.included.extend(processed);
+        while deadlines.included.len() > MAX_INCLUDED_DEADLINES {
+            deadlines.included.pop_first();
+        }
+    }
+
+    /// Returns the earliest deadline of included transactions with priority ids in `priority_ids`.
+    pub fn earliest_included(&self, priority_ids: Range<L1TxSerialId>) -> Option<u64> {
+        self.0
+            .lock()
+            .unwrap()
+            .included
+            .range(priority_ids)
+            .map(|(_, deadline)| *deadline)
+            .min()
+    }
+
+    /// Removes deadlines of included transactions with priority id below `next_priority_id`,
+    /// once their batch is sealed.
+    pub fn remove_batched(&self, next_priority_id: L1TxSerialId) {
+        let mut deadlines = self.0.lock().unwrap();
+        deadlines.included = deadlines.included.split_off(&next_priority_id);
+    }
+
+    /// Returns the priority id and deadline of the oldest unprocessed transaction.
+    pub fn oldest(&self) -> Option<(L1TxSerialId, u64)> {
+        self.0
+            .lock()
+            .unwrap()
+            .pending
+            .first_key_value()
+            .map(|(id, deadline)| (*id, *deadline))
+    }
+
+    /// Returns the greatest priority id whose deadline is at or before `timestamp`.
+    pub fn last_expiring_by(&self, timestamp: u64) -> Option<L1TxSerialId> {
+        self.0
+            .lock()
+            .unwrap()
+            .pending
+            .iter()
+            .filter(|(_, deadline)| **deadline <= timestamp)
+            .map(|(id, _)| *id)
+            .next_back()
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<L1TxSerialId, u64> {
+        self.0.lock().unwrap().pending.clone()
+    }
+
+    /// Adds deadlines (e.g. restored from persistent storage); already known deadlines are kept.
+    pub fn extend(&self, deadlines: BTreeMap<L1TxSerialId, u64>) {
+        let mut current = self.0.lock().unwrap();
+        for (priority_id, deadline) in deadlines {
+            current.pending.entry(priority_id).or_insert(deadline);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadlines_lifecycle() {
+        let deadlines = PriorityDeadlines::default();
+        assert_eq!(deadlines.oldest(), None);
+
+        deadlines.record(3, 1_300);
+        deadlines.record(1, 1_100);
+        deadlines.record(2, 1_200);
+        assert_eq!(deadlines.oldest(), Some((1, 1_100)));
+        assert_eq!(deadlines.last_expiring_by(1_000), None);
+        assert_eq!(deadlines.last_expiring_by(1_250), Some(2));
+
+        // Clones share state
+        deadlines.clone().remove_processed(2);
+        assert_eq!(deadlines.oldest(), Some((2, 1_200)));
+
+        deadlines.extend(BTreeMap::from([(2, 5_000), (4, 1_400)]));
+        assert_eq!(
+            deadlines.snapshot(),
+            BTreeMap::from([(2, 1_200), (3, 1_300), (4, 1_400)])
+        );
+    }
+
+    #[test]
+    fn included_deadlines_are_kept_until_batched() {
+        let deadlines = PriorityDeadlines::default();
+        for (priority_id, deadline) in [(0, 1_300), (1, 1_100), (2, 1_200), (3, 1_000)] {
+            deadlines.record(priority_id, deadline);
+        }
+        deadlines.remove_processed(3);
+        assert_eq!(deadlines.oldest(), Some((3, 1_000)));
+        assert_eq!(deadlines.earliest_included(0..2), Some(1_100));
+        assert_eq!(deadlines.earliest_included(2..3), Some(1_200));
+        // Not included yet
+        assert_eq!(deadlines.earliest_included(3..4), None);
+
+        deadlines.remove_batched(2);
+        assert_eq!(deadlines.earliest_included(0..3), Some(1_200));
+        assert_eq!(deadlines.snapshot(), BTreeMap::from([(3, 1_000)]));
+    }
+}
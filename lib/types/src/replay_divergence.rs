@@ -0,0 +1,42 @@
+use alloy::primitives::B256;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Replayed block whose locally computed output differs from the one in the replay stream.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayDivergence {
+    pub block_number: u64,
+    /// Block output hash from the replay record.
+    pub expected_output_hash: B256,
+    /// Block output hash computed by this node.
+    pub computed_output_hash: B256,
+    /// Block dump with the block inputs and the divergence evidence, if it was written.
+    pub dump_path: Option<PathBuf>,
+}
+
+/// Status of replay divergence quarantine on an external node, shared between the sequencer,
+/// status server and admin API.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum ReplayDivergenceStatus {
+    #[default]
+    None,
+    /// Replay is halted at the diverged block; nothing is applied until the operator resumes it.
+    Diverged(ReplayDivergence),
+    /// The operator acknowledged the divergence and keeps replay halted.
+    Halted(ReplayDivergence),
+    /// The operator acknowledged the divergence as benign; the locally computed output is applied
+    /// and replay continues.
+    Resumed(ReplayDivergence),
+}
+
+impl ReplayDivergenceStatus {
+    /// Returns the divergence that replay is currently halted at, if any.
+    pub fn halted_at(&self) -> Option<&ReplayDivergence> {
+        match self {
+            Self::Diverged(divergence) | Self::Halted(divergence) => Some(divergence),
+            Self::None | Self::Resumed(_) => None,
+        }
+    }
+}
@@ -0,0 +1,35 @@
+use alloy::primitives::B256;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Block whose output computed with the shadow execution version differs from the output of
+/// the active version.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShadowDivergence {
+    pub block_number: u64,
+    /// Execution version the block was executed with.
+    pub active_execution_version: u32,
+    /// Hash of the block header computed with the active version.
+    pub active_block_hash: B256,
+    /// Hash of the block header computed with the shadow version.
+    pub shadow_block_hash: B256,
+    /// Detailed divergence report, if it was written.
+    pub report_path: Option<PathBuf>,
+}
+
+/// Status of shadow execution, shared between the shadow executor and status server.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShadowExecutionStatus {
+    /// Shadow execution version; `None` if shadow execution is disabled.
+    pub execution_version: Option<u32>,
+    /// Last block executed with the shadow version.
+    pub last_compared_block: Option<u64>,
+    pub compared_blocks: u64,
+    /// Blocks not executed with the shadow version because the shadow queue was full.
+    pub dropped_blocks: u64,
+    pub divergent_blocks: u64,
+    /// First block with divergent output since the node started.
+    pub first_divergence: Option<ShadowDivergence>,
+}
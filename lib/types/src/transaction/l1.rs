@@ -100,6 +100,7 @@ pub struct L1Tx<T: L1TxType> {
 
 mod tx_serde {
     use super::*;
+    use crate::ZksyncTxFields;
 
     // This is the "JSON shape". It mirrors L1Tx fields PLUS the signature fields.
     // Copy over the same serde attributes so wire format matches.
@@ -140,11 +141,15 @@ mod tx_serde {
         /// are returned by nodes.
         #[serde(with = "alloy::serde::quantity")]
         pub y_parity: bool,
+
+        /// ZKsync-specific fields.
+        pub zksync: ZksyncTxFields,
     }
 
-    // Serialize: inject defaults for (r,s,v,yParity)
+    // Serialize: inject defaults for (r,s,v,yParity) and ZKsync-specific fields
     impl<T: L1TxType> From<L1Tx<T>> for TransactionSerdeHelper<T> {
         fn from(tx: L1Tx<T>) -> Self {
+            let zksync = ZksyncTxFields::new(&tx);
             Self {
                 hash: tx.hash,
                 initiator: tx.initiator,
@@ -166,6 +171,7 @@ mod tx_serde {
                 r: B256::ZERO,
                 s: B256::ZERO,
                 y_parity: false,
+                zksync,
             }
         }
     }
@@ -585,6 +591,10 @@ mod tests {
           "r": "0x0000000000000000000000000000000000000000000000000000000000000000",
           "s": "0x0000000000000000000000000000000000000000000000000000000000000000",
           "yParity": "0x0",
+          "zksync": {
+            "l1SerialId": "0x1",
+            "canonicalTxHash": "0x4164624346d4c915977debf68dbd721f8ae86b964080925aecf6911dd47a6ece",
+          },
         });
         let l1_tx: alloy::rpc::types::Transaction<ZkEnvelope> =
             serde_json::from_value(l1_tx_json.clone()).unwrap();
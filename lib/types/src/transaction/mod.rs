@@ -4,6 +4,8 @@ mod l1;
 pub use l1::*;
 mod l2;
 pub use l2::*;
+mod rpc_json;
+pub use rpc_json::*;
 
 use std::fmt;
 
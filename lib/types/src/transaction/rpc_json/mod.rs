@@ -0,0 +1,73 @@
+//! Canonical JSON representation of transactions, as returned by the RPC.
+//!
+//! * L2 transactions are represented exactly like Ethereum transactions of the same type.
+//! * L1 priority transactions have `type` `0x7f` and upgrade transactions have `type` `0x7e`.
+//!   These transactions are not signed, so they always carry zero `r` and `s` with `v` and
+//!   `yParity` set to `0x0`, the same as other ZK stacks do for L1->L2 transactions. This keeps
+//!   parsers that require signature fields working. `from` is the L1 initiator of the transaction.
+//!   ZKsync-specific data is put under the `zksync` key, see [`ZksyncTxFields`].
+//!
+//! RPC responses add block-related fields on top of this representation.
+
+use crate::{L1Tx, L1TxSerialId, L1TxType, UpgradeTxType, ZkEnvelope, ZkTransaction};
+use alloy::consensus::transaction::Recovered;
+use alloy::primitives::B256;
+use serde::{Deserialize, Serialize};
+
+/// ZKsync-specific fields of L1->L2 transactions in their JSON representation.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ZksyncTxFields {
+    /// Serial id of a priority transaction.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "alloy::serde::quantity::opt"
+    )]
+    pub l1_serial_id: Option<L1TxSerialId>,
+    /// Packed protocol version installed by an upgrade transaction.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "alloy::serde::quantity::opt"
+    )]
+    pub upgrade_id: Option<u64>,
+    /// Hash assigned to the transaction on L1, e.g. emitted in the `NewPriorityRequest` event.
+    /// Links the L2 transaction to the L1 request that originated it; it's also the hash of the
+    /// transaction on L2.
+    pub canonical_tx_hash: B256,
+}
+
+impl ZksyncTxFields {
+    pub(crate) fn new<T: L1TxType>(tx: &L1Tx<T>) -> Self {
+        let (l1_serial_id, upgrade_id) = if T::TX_TYPE == UpgradeTxType::TX_TYPE {
+            (None, Some(tx.nonce))
+        } else {
+            (Some(tx.nonce), None)
+        };
+        Self {
+            l1_serial_id,
+            upgrade_id,
+            canonical_tx_hash: tx.hash,
+        }
+    }
+}
+
+impl ZkTransaction {
+    /// Returns the canonical JSON representation of the transaction, see the module docs.
+    pub fn to_rpc_json(&self) -> serde_json::Value {
+        serde_json::to_value(&self.inner).expect("transactions are always serializable")
+    }
+
+    /// Parses a transaction from its canonical JSON representation. Accepts RPC responses, i.e.
+    /// ignores block-related fields. Inverse of [`Self::to_rpc_json`].
+    ///
+    /// The signer is taken from the `from` field as is; it's not checked against the signature.
+    pub fn from_rpc_json(json: serde_json::Value) -> Result<Self, serde_json::Error> {
+        let inner: Recovered<ZkEnvelope> = serde_json::from_value(json)?;
+        Ok(Self { inner })
+    }
+}
+
+#[cfg(test)]
+mod tests;
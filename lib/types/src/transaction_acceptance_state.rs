@@ -13,4 +13,10 @@ pub enum NotAcceptingReason {
     BlockProductionDisabled,
     #[error("Transaction submission not implemented on external nodes.")]
     ExternalNode,
+    /// Transaction acceptance has been halted via the admin API
+    #[error("Node is not currently accepting transactions: halted by operator.")]
+    HaltedByOperator,
+    /// The node is shutting down and doesn't produce blocks anymore
+    #[error("Node is not currently accepting transactions: shutting down.")]
+    ShuttingDown,
 }
@@ -0,0 +1,88 @@
+//! Maintenance of the optional account keys index (see [`AccountKeysIndex`]): executed blocks are
+//! indexed by a pipeline component right after the sequencer, and blocks before the pipeline's
+//! starting block are backfilled in the background by re-executing their replay records.
+
+use anyhow::Context;
+use async_trait::async_trait;
+use std::future;
+use tokio::sync::mpsc::Sender;
+use zksync_os_interface::types::BlockOutput;
+use zksync_os_observability::{ComponentStateReporter, GenericComponentState};
+use zksync_os_pipeline::{PeekableReceiver, PipelineComponent};
+use zksync_os_sequencer::execution::shadow::run_block_on_state;
+use zksync_os_state::AccountKeysIndex;
+use zksync_os_storage_api::{BlockStats, ReadReplay, ReadStateHistory, ReplayRecord};
+
+/// Indexes storage writes of executed blocks; blocks are passed downstream unchanged.
+pub struct AccountKeysIndexer {
+    index: AccountKeysIndex,
+}
+
+impl AccountKeysIndexer {
+    pub fn new(index: AccountKeysIndex) -> Self {
+        Self { index }
+    }
+}
+
+#[async_trait]
+impl PipelineComponent for AccountKeysIndexer {
+    type Input = (BlockOutput, ReplayRecord, BlockStats);
+    type Output = (BlockOutput, ReplayRecord, BlockStats);
+
+    const NAME: &'static str = "account_keys_indexer";
+    const OUTPUT_BUFFER_SIZE: usize = 5;
+
+    async fn run(
+        self,
+        mut input: PeekableReceiver<Self::Input>,
+        output: Sender<Self::Output>,
+    ) -> anyhow::Result<()> {
+        let latency_tracker = ComponentStateReporter::global()
+            .handle_for("account_keys_indexer", GenericComponentState::WaitingRecv);
+        loop {
+            latency_tracker.enter_state(GenericComponentState::WaitingRecv);
+            let Some((block_output, replay_record, stats)) = input.recv().await else {
+                anyhow::bail!("inbound channel closed");
+            };
+            latency_tracker.enter_state(GenericComponentState::Processing);
+            self.index
+                .add_block(block_output.header.number, &block_output.storage_writes);
+
+            latency_tracker.enter_state(GenericComponentState::WaitingSend);
+            if output
+                .send((block_output, replay_record, stats))
+                .await
+                .is_err()
+            {
+                anyhow::bail!("outbound channel closed");
+            }
+        }
+    }
+}
+
+/// Backfills the index up to `last_block` by re-executing blocks on top of `state`. Failures
+/// (e.g. if the state before a block is already compacted) are logged, leaving the index
+/// incomplete; the backfill is retried on the next start. Never returns.
+pub async fn backfill_account_keys(
+    index: AccountKeysIndex,
+    state: impl ReadStateHistory + Clone,
+    replay: impl ReadReplay + Clone,
+    last_block: u64,
+) {
+    let result = tokio::task::spawn_blocking(move || {
+        index.backfill(last_block, |block_number| {
+            let record = replay
+                .get_replay_record(block_number)
+                .with_context(|| format!("replay record for block {block_number} is missing"))?;
+            let output = run_block_on_state(&state, record.block_context, &record)?;
+            Ok(output.storage_writes)
+        })
+    })
+    .await
+    .context("account keys backfill panicked")
+    .and_then(|result| result);
+    if let Err(err) = result {
+        tracing::error!("failed to backfill account keys index: {err:#}");
+    }
+    future::pending().await
+}
@@ -0,0 +1,197 @@
+//! Append-only audit log of admin API invocations.
+//!
+//! Records are stored as JSON lines; the file is only ever appended to and synced after every
+//! record, so that the log survives crashes right after an action was performed.
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Maximum number of records returned by a single [`AuditLog::page`] call.
+pub const MAX_AUDIT_PAGE_SIZE: usize = 1_000;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum AuditOutcome {
+    /// Recorded before a mutating action is performed; followed by a record of its outcome.
+    Started,
+    Success,
+    Failure {
+        error: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Sequential id of the record, starting from 0.
+    pub id: u64,
+    /// Unix timestamp (milliseconds) of the invocation.
+    pub timestamp_ms: u64,
+    pub action: String,
+    pub params: serde_json::Value,
+    /// Caller identity as declared in the request, along with the peer address.
+    pub caller: String,
+    pub outcome: AuditOutcome,
+}
+
+/// Page of audit records, oldest first.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditLogPage {
+    pub records: Vec<AuditRecord>,
+    /// Total number of records in the log.
+    pub total: u64,
+}
+
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    /// Append handle and the number of records written so far.
+    writer: Mutex<(File, u64)>,
+}
+
+impl AuditLog {
+    /// Opens the log at `path`, creating it if necessary.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("cannot create `{}`", parent.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("cannot open audit log `{}`", path.display()))?;
+        let len = Self::read_records(path)?.count() as u64;
+        tracing::info!(path = %path.display(), records = len, "opened admin audit log");
+        Ok(Self {
+            path: path.to_owned(),
+            writer: Mutex::new((file, len)),
+        })
+    }
+
+    /// Appends a record and returns it. Id is assigned by the log.
+    pub fn append(
+        &self,
+        timestamp_ms: u64,
+        action: &str,
+        params: serde_json::Value,
+        caller: &str,
+        outcome: AuditOutcome,
+    ) -> anyhow::Result<AuditRecord> {
+        let mut writer = self.writer.lock().unwrap();
+        let (file, len) = &mut *writer;
+        let record = AuditRecord {
+            id: *len,
+            timestamp_ms,
+            action: action.to_owned(),
+            params,
+            caller: caller.to_owned(),
+            outcome,
+        };
+        let mut line = serde_json::to_vec(&record)?;
+        line.push(b'\n');
+        file.write_all(&line)
+            .and_then(|()| file.sync_data())
+            .context("cannot write audit log")?;
+        *len += 1;
+        Ok(record)
+    }
+
+    /// Makes all subsequent appends fail.
+    #[cfg(test)]
+    pub(super) fn break_writes(&self) {
+        // Writes to a read-only handle fail
+        self.writer.lock().unwrap().0 = File::open(&self.path).unwrap();
+    }
+
+    pub fn record_count(&self) -> u64 {
+        self.writer.lock().unwrap().1
+    }
+
+    /// Returns up to `limit` (capped by [`MAX_AUDIT_PAGE_SIZE`]) records starting from `offset`.
+    pub fn page(&self, offset: u64, limit: usize) -> anyhow::Result<AuditLogPage> {
+        // Hold the writer lock so that `total` is consistent with the returned records
+        let writer = self.writer.lock().unwrap();
+        let records = Self::read_records(&self.path)?
+            .skip(offset as usize)
+            .take(limit.min(MAX_AUDIT_PAGE_SIZE))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(AuditLogPage {
+            records,
+            total: writer.1,
+        })
+    }
+
+    fn read_records(
+        path: &Path,
+    ) -> anyhow::Result<impl Iterator<Item = anyhow::Result<AuditRecord>>> {
+        let file = File::open(path)
+            .with_context(|| format!("cannot open audit log `{}`", path.display()))?;
+        Ok(BufReader::new(file)
+            .lines()
+            .filter(|line| !line.as_ref().is_ok_and(|line| line.is_empty()))
+            .map(|line| Ok(serde_json::from_str(&line?)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn append_and_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.jsonl");
+        let log = AuditLog::open(&path).unwrap();
+        for i in 0..5 {
+            let outcome = if i % 2 == 0 {
+                AuditOutcome::Success
+            } else {
+                AuditOutcome::Failure {
+                    error: "boom".to_owned(),
+                }
+            };
+            let record = log
+                .append(
+                    i,
+                    "admin_test",
+                    serde_json::json!([i]),
+                    "ops@127.0.0.1",
+                    outcome,
+                )
+                .unwrap();
+            assert_eq!(record.id, i);
+        }
+
+        let page = log.page(1, 2).unwrap();
+        assert_eq!(page.total, 5);
+        assert_eq!(
+            page.records.iter().map(|r| r.id).collect::<Vec<_>>(),
+            [1, 2]
+        );
+        assert!(matches!(
+            page.records[0].outcome,
+            AuditOutcome::Failure { .. }
+        ));
+        assert!(log.page(5, 10).unwrap().records.is_empty());
+
+        // Records survive reopening, and ids continue from where they stopped
+        drop(log);
+        let log = AuditLog::open(&path).unwrap();
+        assert_eq!(log.record_count(), 5);
+        let record = log
+            .append(
+                10,
+                "admin_test",
+                serde_json::Value::Null,
+                "ops",
+                AuditOutcome::Success,
+            )
+            .unwrap();
+        assert_eq!(record.id, 5);
+        assert_eq!(log.page(0, 100).unwrap().records.len(), 6);
+    }
+}
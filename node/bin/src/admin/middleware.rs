@@ -0,0 +1,240 @@
+//! Authentication and auditing of admin API calls, done as jsonrpsee middleware so that admin
+//! methods themselves only implement the actions.
+
+use super::audit_log::{AuditLog, AuditOutcome};
+use super::{AdminError, bearer_token_matches, now_ms};
+use jsonrpsee::MethodResponse;
+use jsonrpsee::core::middleware::{Batch, Notification};
+use jsonrpsee::server::middleware::rpc::{RpcService, RpcServiceT};
+use jsonrpsee::types::error::INVALID_REQUEST_CODE;
+use jsonrpsee::types::{ErrorObject, Id, Request};
+use serde_json::Value;
+use smart_config::value::SecretString;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Header callers may use to identify themselves in the audit log.
+const CALLER_HEADER: &str = "x-admin-caller";
+/// Methods that change the node state. They are recorded in the audit log before they are
+/// performed, and refused if the record cannot be written.
+const MUTATING_METHODS: &[&str] = &[
+    "admin_haltTransactionAcceptance",
+    "admin_resumeTransactionAcceptance",
+    "admin_reassignFriJob",
+    "admin_acknowledgeCommitmentFormatTransition",
+    "admin_requeueRevertedBatches",
+    "admin_resolveReplayDivergence",
+    "admin_submitBundle",
+    "admin_reloadConfig",
+];
+/// Min interval between audit records of unauthorized calls. Unauthorized calls in between are
+/// only counted, so that unauthenticated callers cannot flood the audit log (each record is synced
+/// to disk).
+const UNAUTHORIZED_RECORD_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Caller identity and credentials, attached to HTTP request extensions by [`tag_admin_caller`].
+/// jsonrpsee passes them on to every JSON-RPC call of the request.
+#[derive(Debug, Clone)]
+struct AdminCaller {
+    /// Caller identity as declared in the request, along with the peer address.
+    name: String,
+    authorization: Option<String>,
+}
+
+pub(super) fn tag_admin_caller<B>(
+    mut request: hyper::Request<B>,
+    peer: IpAddr,
+) -> hyper::Request<B> {
+    let headers = request.headers();
+    let declared_caller = headers
+        .get(CALLER_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("unknown");
+    let caller = AdminCaller {
+        name: format!("{declared_caller}@{peer}"),
+        authorization: headers
+            .get(http::header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned),
+    };
+    request.extensions_mut().insert(caller);
+    request
+}
+
+#[derive(Debug, Default)]
+struct UnauthorizedCalls {
+    last_recorded_at: Option<Instant>,
+    /// Number of unauthorized calls since the last recorded one.
+    unrecorded: u64,
+}
+
+/// State shared by middleware instances of all connections.
+#[derive(Debug)]
+pub(super) struct AuthAndAuditState {
+    /// Hot-reloadable, see [`crate::config_reload`].
+    auth_token: watch::Receiver<SecretString>,
+    audit_log: Arc<AuditLog>,
+    unauthorized_calls: Mutex<UnauthorizedCalls>,
+}
+
+impl AuthAndAuditState {
+    pub(super) fn new(auth_token: watch::Receiver<SecretString>, audit_log: Arc<AuditLog>) -> Self {
+        Self {
+            auth_token,
+            audit_log,
+            unauthorized_calls: Mutex::new(UnauthorizedCalls::default()),
+        }
+    }
+
+    fn is_authorized(&self, caller: Option<&AdminCaller>) -> bool {
+        let authorization = caller.and_then(|caller| caller.authorization.as_deref());
+        bearer_token_matches(&self.auth_token.borrow(), authorization)
+    }
+
+    fn record(&self, method: &str, params: Value, caller: &str, outcome: AuditOutcome) -> bool {
+        match self
+            .audit_log
+            .append(now_ms(), method, params, caller, outcome)
+        {
+            Ok(_) => true,
+            Err(err) => {
+                tracing::error!(method, caller, "failed to record admin API call: {err:#}");
+                false
+            }
+        }
+    }
+
+    /// Records an unauthorized call unless one was recorded within
+    /// [`UNAUTHORIZED_RECORD_INTERVAL`]; the number of skipped calls is added to the next record.
+    fn record_unauthorized(&self, method: &str, params: Value, caller: &str) {
+        let unrecorded = {
+            let mut calls = self.unauthorized_calls.lock().unwrap();
+            let now = Instant::now();
+            if calls
+                .last_recorded_at
+                .is_some_and(|at| now.duration_since(at) < UNAUTHORIZED_RECORD_INTERVAL)
+            {
+                calls.unrecorded += 1;
+                tracing::debug!(caller, method, "unauthorized admin API call");
+                return;
+            }
+            calls.last_recorded_at = Some(now);
+            std::mem::take(&mut calls.unrecorded)
+        };
+        tracing::warn!(caller, method, unrecorded, "unauthorized admin API call");
+        let error = if unrecorded == 0 {
+            AdminError::Unauthorized.to_string()
+        } else {
+            format!(
+                "{}; {unrecorded} unauthorized calls since the previous record",
+                AdminError::Unauthorized
+            )
+        };
+        self.record(method, params, caller, AuditOutcome::Failure { error });
+    }
+}
+
+/// Rejects calls without a valid bearer token and records calls in the [`AuditLog`].
+#[derive(Clone)]
+pub(super) struct AuthAndAudit {
+    inner: RpcService,
+    state: Arc<AuthAndAuditState>,
+}
+
+impl AuthAndAudit {
+    pub(super) fn new(inner: RpcService, state: Arc<AuthAndAuditState>) -> Self {
+        Self { inner, state }
+    }
+}
+
+impl RpcServiceT for AuthAndAudit {
+    type MethodResponse = <RpcService as RpcServiceT>::MethodResponse;
+    type NotificationResponse = <RpcService as RpcServiceT>::NotificationResponse;
+    type BatchResponse = <RpcService as RpcServiceT>::BatchResponse;
+
+    fn call<'a>(
+        &self,
+        request: Request<'a>,
+    ) -> impl Future<Output = Self::MethodResponse> + Send + 'a {
+        let service = self.clone();
+        async move {
+            let method = request.method_name().to_owned();
+            let params = request
+                .params
+                .as_ref()
+                .and_then(|params| serde_json::from_str(params.get()).ok())
+                .unwrap_or(Value::Null);
+            let caller = request.extensions().get::<AdminCaller>().cloned();
+            let caller_name = caller
+                .as_ref()
+                .map_or("unknown", |caller| caller.name.as_str());
+            let id = request.id().into_owned();
+
+            if !service.state.is_authorized(caller.as_ref()) {
+                service
+                    .state
+                    .record_unauthorized(&method, params, caller_name);
+                return MethodResponse::error(id, AdminError::Unauthorized);
+            }
+            if MUTATING_METHODS.contains(&method.as_str())
+                && !service.state.record(
+                    &method,
+                    params.clone(),
+                    caller_name,
+                    AuditOutcome::Started,
+                )
+            {
+                return MethodResponse::error(
+                    id,
+                    AdminError::Failed(
+                        "cannot write audit log; the action was not performed".to_owned(),
+                    ),
+                );
+            }
+
+            let response = service.inner.call(request).await;
+            let outcome = if response.is_success() {
+                AuditOutcome::Success
+            } else {
+                AuditOutcome::Failure {
+                    error: error_message(&response),
+                }
+            };
+            tracing::info!(caller = caller_name, method, ?outcome, "admin API call");
+            service.state.record(&method, params, caller_name, outcome);
+            response
+        }
+    }
+
+    fn batch<'a>(
+        &self,
+        _batch: Batch<'a>,
+    ) -> impl Future<Output = Self::BatchResponse> + Send + 'a {
+        // Batches are disabled in the server config; never run them without authentication
+        std::future::ready(MethodResponse::error(
+            Id::Null,
+            ErrorObject::borrowed(
+                INVALID_REQUEST_CODE,
+                "batch requests are not supported",
+                None,
+            ),
+        ))
+    }
+
+    fn notification<'a>(
+        &self,
+        _notification: Notification<'a>,
+    ) -> impl Future<Output = Self::NotificationResponse> + Send + 'a {
+        // Admin actions must be called with an id; notifications are never run
+        std::future::ready(MethodResponse::notification())
+    }
+}
+
+fn error_message(response: &MethodResponse) -> String {
+    serde_json::from_str::<Value>(response.as_json().get())
+        .ok()
+        .and_then(|response| response["error"]["message"].as_str().map(str::to_owned))
+        .unwrap_or_default()
+}
@@ -0,0 +1,897 @@
+//! Admin JSON-RPC API - a single authenticated surface for operational actions.
+//!
+//! The server speaks JSON-RPC 2.0 over HTTP and only serves the `admin_` namespace (see
+//! [`AdminApiServer`]). Every call must carry an `Authorization: Bearer <token>` header matching
+//! the configured token; unauthorized calls are rejected with structured JSON-RPC errors.
+//! Callers may identify themselves via the `X-Admin-Caller` header - it is recorded in the audit log
+//! along with the peer address.
+//!
+//! Authentication and auditing are done by the server middleware (see [`middleware`]). Every
+//! authorized call is appended to the [`AuditLog`], which is exposed via `admin_getAuditLog`;
+//! mutating actions are additionally recorded before they are performed. Actions are performed
+//! through the hooks exposed by the corresponding components (see [`AdminHooks`]); actions whose
+//! component is not running on this node are rejected as unavailable.
+
+mod audit_log;
+mod middleware;
+
+pub use self::audit_log::{AuditLog, AuditLogPage, AuditOutcome, AuditRecord};
+
+use self::middleware::{AuthAndAudit, AuthAndAuditState, tag_admin_caller};
+use crate::config_reload::{ConfigChange, ConfigReloader};
+use crate::prover_api::fri_job_manager::FriJobManager;
+use alloy::primitives::{Address, B256};
+use hyper::body::Incoming;
+use jsonrpsee::Methods;
+use jsonrpsee::core::RpcResult;
+use jsonrpsee::core::middleware::RpcServiceBuilder;
+use jsonrpsee::proc_macros::rpc;
+use jsonrpsee::server::{
+    BatchRequestConfig, HttpRequest, ServerBuilder, ServerConfigBuilder,
+    serve_with_graceful_shutdown, stop_channel,
+};
+use jsonrpsee::types::ErrorObjectOwned;
+use jsonrpsee::types::error::{INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE};
+use serde::{Deserialize, Serialize};
+use smart_config::value::{ExposeSecret, SecretString};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tower_http::timeout::TimeoutLayer;
+use zksync_os_l1_sender::batcher_model::{L1BatchRevert, L1RevertStatus};
+use zksync_os_sequencer::execution::bundles::{BundleStatus, BundleStore};
+use zksync_os_state::AccountKeysIndex;
+use zksync_os_storage_api::ReadAccountKeys;
+use zksync_os_types::{
+    NotAcceptingReason, ReplayDivergenceStatus, SignedTransactionBundle, TransactionAcceptanceState,
+};
+
+/// Default page size of `admin_getAuditLog`.
+const DEFAULT_AUDIT_PAGE_SIZE: usize = 100;
+/// Delay before accepting connections again after a failure to accept one.
+const ACCEPT_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Channels / handles of components that admin actions are performed through.
+/// `None` means that the component is not running on this node.
+#[derive(Clone, Default)]
+pub struct AdminHooks {
+    /// Transaction acceptance state of the JSON-RPC server (main node only).
+    pub tx_acceptance: Option<watch::Sender<TransactionAcceptanceState>>,
+    /// FRI prover job queue (main node only).
+    pub fri_job_manager: Option<Arc<FriJobManager>>,
+    /// Commitment encoding version acknowledged for committing (main node only).
+    pub commitment_format_acks: Option<watch::Sender<Option<u8>>>,
+    /// Pending transaction bundles (main node with configured bundle signers only).
+    pub bundles: Option<BundleStore>,
+    /// Status of batch reverts detected on L1 (main node only).
+    pub l1_reverts: Option<watch::Sender<L1RevertStatus>>,
+    /// Replay divergence quarantine (external nodes with quarantine enabled only).
+    pub replay_divergence: Option<watch::Sender<ReplayDivergenceStatus>>,
+    /// Hot reload of the config file (nodes with a config file only).
+    pub config_reloader: Option<Arc<ConfigReloader>>,
+    /// Account keys index (nodes with `account_keys_index_enabled` only).
+    pub account_keys: Option<AccountKeysIndex>,
+}
+
+/// Operator decision on a replay divergence, passed to `admin_resolveReplayDivergence`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DivergenceResolution {
+    /// Accept the locally computed output as a known benign difference and continue replay.
+    Resume,
+    /// Acknowledge the divergence, but keep replay halted.
+    KeepHalted,
+}
+
+/// Response of `admin_getStorageKeysForAccount`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountStorageKeys {
+    pub keys: Vec<B256>,
+    /// Keys written after this block may be missing.
+    pub indexed_block: u64,
+}
+
+#[rpc(server, namespace = "admin")]
+pub trait AdminApi {
+    /// Halts acceptance of new transactions. Returns whether the state changed.
+    #[method(name = "haltTransactionAcceptance")]
+    fn halt_transaction_acceptance(&self) -> RpcResult<bool>;
+
+    /// Lifts an operator halt of transaction acceptance. Other reasons (e.g. block production
+    /// limit) stay. Returns whether the state changed.
+    #[method(name = "resumeTransactionAcceptance")]
+    fn resume_transaction_acceptance(&self) -> RpcResult<bool>;
+
+    #[method(name = "reassignFriJob")]
+    fn reassign_fri_job(&self, batch_number: u64) -> RpcResult<bool>;
+
+    /// Returns whether the acknowledged version changed.
+    #[method(name = "acknowledgeCommitmentFormatTransition")]
+    fn acknowledge_commitment_format_transition(&self, version: u8) -> RpcResult<bool>;
+
+    #[method(name = "getL1RevertStatus")]
+    fn get_l1_revert_status(&self) -> RpcResult<L1RevertStatus>;
+
+    /// The batch L1 was reverted to serves as the operator's confirmation of the revert.
+    #[method(name = "requeueRevertedBatches")]
+    fn requeue_reverted_batches(&self, reverted_to_batch: u64) -> RpcResult<L1BatchRevert>;
+
+    #[method(name = "getReplayDivergence")]
+    fn get_replay_divergence(&self) -> RpcResult<ReplayDivergenceStatus>;
+
+    /// The diverged block number serves as the operator's confirmation of the divergence.
+    #[method(name = "resolveReplayDivergence")]
+    fn resolve_replay_divergence(
+        &self,
+        block_number: u64,
+        resolution: DivergenceResolution,
+    ) -> RpcResult<ReplayDivergenceStatus>;
+
+    #[method(name = "submitBundle")]
+    fn submit_bundle(&self, bundle: SignedTransactionBundle) -> RpcResult<B256>;
+
+    #[method(name = "getBundleStatus")]
+    fn get_bundle_status(&self, hash: B256) -> RpcResult<Option<BundleStatus>>;
+
+    /// Returns applied changes (with secrets redacted).
+    #[method(name = "reloadConfig")]
+    fn reload_config(&self) -> RpcResult<Vec<ConfigChange>>;
+
+    #[method(name = "getStorageKeysForAccount")]
+    fn get_storage_keys_for_account(&self, address: Address) -> RpcResult<AccountStorageKeys>;
+
+    /// Returns up to `limit` records starting from `offset`, oldest first.
+    #[method(name = "getAuditLog")]
+    fn get_audit_log(&self, offset: Option<u64>, limit: Option<usize>) -> RpcResult<AuditLogPage>;
+}
+
+/// Structured errors returned by the admin API.
+#[derive(Debug, thiserror::Error)]
+pub enum AdminError {
+    #[error("invalid params: {0}")]
+    InvalidParams(String),
+    #[error("unauthorized")]
+    Unauthorized,
+    #[error("action is not available on this node: {0}")]
+    Unavailable(&'static str),
+    #[error("action failed: {0}")]
+    Failed(String),
+}
+
+impl AdminError {
+    pub fn code(&self) -> i32 {
+        match self {
+            Self::InvalidParams(_) => INVALID_PARAMS_CODE,
+            Self::Failed(_) => INTERNAL_ERROR_CODE,
+            Self::Unauthorized => -32001,
+            Self::Unavailable(_) => -32002,
+        }
+    }
+}
+
+impl From<AdminError> for ErrorObjectOwned {
+    fn from(err: AdminError) -> Self {
+        ErrorObjectOwned::owned(err.code(), err.to_string(), None::<()>)
+    }
+}
+
+pub struct AdminNamespace {
+    hooks: AdminHooks,
+    audit_log: Arc<AuditLog>,
+}
+
+impl AdminNamespace {
+    pub fn new(hooks: AdminHooks, audit_log: Arc<AuditLog>) -> Self {
+        Self { hooks, audit_log }
+    }
+
+    fn tx_acceptance(&self) -> Result<&watch::Sender<TransactionAcceptanceState>, AdminError> {
+        self.hooks
+            .tx_acceptance
+            .as_ref()
+            .ok_or(AdminError::Unavailable(
+                "transaction acceptance is only controlled on the main node",
+            ))
+    }
+
+    fn l1_reverts(&self) -> Result<&watch::Sender<L1RevertStatus>, AdminError> {
+        self.hooks
+            .l1_reverts
+            .as_ref()
+            .ok_or(AdminError::Unavailable("L1 senders are not running"))
+    }
+
+    fn replay_divergence(&self) -> Result<&watch::Sender<ReplayDivergenceStatus>, AdminError> {
+        self.hooks
+            .replay_divergence
+            .as_ref()
+            .ok_or(AdminError::Unavailable(
+                "replay divergence quarantine is not enabled on this node",
+            ))
+    }
+
+    fn bundles(&self) -> Result<&BundleStore, AdminError> {
+        self.hooks.bundles.as_ref().ok_or(AdminError::Unavailable(
+            "bundles are not enabled on this node",
+        ))
+    }
+}
+
+impl AdminApiServer for AdminNamespace {
+    fn halt_transaction_acceptance(&self) -> RpcResult<bool> {
+        let sender = self.tx_acceptance()?;
+        Ok(sender.send_if_modified(|state| {
+            if matches!(state, TransactionAcceptanceState::Accepting) {
+                *state =
+                    TransactionAcceptanceState::NotAccepting(NotAcceptingReason::HaltedByOperator);
+                true
+            } else {
+                false
+            }
+        }))
+    }
+
+    fn resume_transaction_acceptance(&self) -> RpcResult<bool> {
+        let sender = self.tx_acceptance()?;
+        Ok(sender.send_if_modified(|state| {
+            if matches!(
+                state,
+                TransactionAcceptanceState::NotAccepting(NotAcceptingReason::HaltedByOperator)
+            ) {
+                *state = TransactionAcceptanceState::Accepting;
+                true
+            } else {
+                false
+            }
+        }))
+    }
+
+    fn reassign_fri_job(&self, batch_number: u64) -> RpcResult<bool> {
+        let fri_job_manager = self
+            .hooks
+            .fri_job_manager
+            .as_ref()
+            .ok_or(AdminError::Unavailable("FRI job manager is not running"))?;
+        if !fri_job_manager.reassign_job(batch_number) {
+            return Err(AdminError::Failed(format!(
+                "batch {batch_number} is not assigned to a prover"
+            ))
+            .into());
+        }
+        Ok(true)
+    }
+
+    fn acknowledge_commitment_format_transition(&self, version: u8) -> RpcResult<bool> {
+        let sender = self
+            .hooks
+            .commitment_format_acks
+            .as_ref()
+            .ok_or(AdminError::Unavailable("commit sender is not running"))?;
+        Ok(sender.send_replace(Some(version)) != Some(version))
+    }
+
+    fn get_l1_revert_status(&self) -> RpcResult<L1RevertStatus> {
+        Ok(*self.l1_reverts()?.borrow())
+    }
+
+    fn requeue_reverted_batches(&self, reverted_to_batch: u64) -> RpcResult<L1BatchRevert> {
+        let sender = self.l1_reverts()?;
+        let L1RevertStatus::Detected(revert) = *sender.borrow() else {
+            return Err(AdminError::Failed(
+                "there is no unacknowledged L1 batch revert".to_owned(),
+            )
+            .into());
+        };
+        if revert.reverted_to_batch != reverted_to_batch {
+            return Err(AdminError::InvalidParams(format!(
+                "L1 was reverted to batch {}, got {reverted_to_batch}",
+                revert.reverted_to_batch
+            ))
+            .into());
+        }
+        if revert.requires_local_rollback {
+            return Err(AdminError::Failed(format!(
+                "local batch {reverted_to_batch} doesn't match L1; reverted batches cannot be \
+                 re-committed, roll back local blocks above {} manually (see node logs)",
+                revert.reverted_to_block
+            ))
+            .into());
+        }
+        sender.send_replace(L1RevertStatus::Acknowledged(revert));
+        Ok(revert)
+    }
+
+    fn get_replay_divergence(&self) -> RpcResult<ReplayDivergenceStatus> {
+        Ok(self.replay_divergence()?.borrow().clone())
+    }
+
+    fn resolve_replay_divergence(
+        &self,
+        block_number: u64,
+        resolution: DivergenceResolution,
+    ) -> RpcResult<ReplayDivergenceStatus> {
+        let sender = self.replay_divergence()?;
+        let Some(divergence) = sender.borrow().halted_at().cloned() else {
+            return Err(
+                AdminError::Failed("replay is not halted on a divergence".to_owned()).into(),
+            );
+        };
+        if divergence.block_number != block_number {
+            return Err(AdminError::InvalidParams(format!(
+                "replay is halted at block {}, got {block_number}",
+                divergence.block_number
+            ))
+            .into());
+        }
+        let status = match resolution {
+            DivergenceResolution::Resume => ReplayDivergenceStatus::Resumed(divergence),
+            DivergenceResolution::KeepHalted => ReplayDivergenceStatus::Halted(divergence),
+        };
+        sender.send_replace(status.clone());
+        Ok(status)
+    }
+
+    fn submit_bundle(&self, bundle: SignedTransactionBundle) -> RpcResult<B256> {
+        let hash = self
+            .bundles()?
+            .submit(bundle)
+            .map_err(|err| AdminError::InvalidParams(err.to_string()))?;
+        Ok(hash)
+    }
+
+    fn get_bundle_status(&self, hash: B256) -> RpcResult<Option<BundleStatus>> {
+        Ok(self.bundles()?.status(hash))
+    }
+
+    fn reload_config(&self) -> RpcResult<Vec<ConfigChange>> {
+        let reloader = self
+            .hooks
+            .config_reloader
+            .as_ref()
+            .ok_or(AdminError::Unavailable(
+                "no config file is used by this node",
+            ))?;
+        let changes = reloader
+            .reload("admin_reloadConfig")
+            .map_err(|err| AdminError::Failed(err.to_string()))?;
+        Ok(changes)
+    }
+
+    fn get_storage_keys_for_account(&self, address: Address) -> RpcResult<AccountStorageKeys> {
+        let index = self
+            .hooks
+            .account_keys
+            .as_ref()
+            .ok_or(AdminError::Unavailable("account keys index is not enabled"))?;
+        let keys = index
+            .storage_keys_for_account(address)
+            .map_err(|err| AdminError::Failed(format!("{err:#}")))?;
+        Ok(AccountStorageKeys {
+            keys,
+            indexed_block: index.indexed_block(),
+        })
+    }
+
+    fn get_audit_log(&self, offset: Option<u64>, limit: Option<usize>) -> RpcResult<AuditLogPage> {
+        let page = self
+            .audit_log
+            .page(
+                offset.unwrap_or(0),
+                limit.unwrap_or(DEFAULT_AUDIT_PAGE_SIZE),
+            )
+            .map_err(|err| AdminError::Failed(format!("{err:#}")))?;
+        Ok(page)
+    }
+}
+
+/// Checks that the `Authorization` header value carries `expected` as a bearer token.
+pub(crate) fn bearer_token_matches(expected: &SecretString, authorization: Option<&str>) -> bool {
+    let expected = expected.expose_secret();
+    let Some(provided) = authorization.and_then(|value| value.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    // Constant-time comparison - don't leak the matching prefix length via timing
+    !expected.is_empty()
+        && provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+/// Requests are rejected if their body exceeds `max_request_body_bytes`, and time out with
+/// `408 Request Timeout` after `request_timeout`.
+pub async fn run_admin_server(
+    bind_address: String,
+    auth_token: watch::Receiver<SecretString>,
+    namespace: AdminNamespace,
+    request_timeout: Duration,
+    max_request_body_bytes: usize,
+) -> anyhow::Result<()> {
+    let bind_address: SocketAddr = bind_address.parse()?;
+    tracing::info!("starting admin API server on {bind_address}");
+    let listener = TcpListener::bind(bind_address).await?;
+    serve_admin_api(
+        listener,
+        auth_token,
+        namespace,
+        request_timeout,
+        max_request_body_bytes,
+    )
+    .await
+}
+
+async fn serve_admin_api(
+    listener: TcpListener,
+    auth_token: watch::Receiver<SecretString>,
+    namespace: AdminNamespace,
+    request_timeout: Duration,
+    max_request_body_bytes: usize,
+) -> anyhow::Result<()> {
+    let state = Arc::new(AuthAndAuditState::new(
+        auth_token,
+        namespace.audit_log.clone(),
+    ));
+    let rpc_middleware =
+        RpcServiceBuilder::new().layer_fn(move |service| AuthAndAudit::new(service, state.clone()));
+    let server_config = ServerConfigBuilder::default()
+        .http_only()
+        .max_request_body_size(max_request_body_bytes.try_into().unwrap_or(u32::MAX))
+        .set_batch_request_config(BatchRequestConfig::Disabled)
+        .build();
+    let service_builder = ServerBuilder::default()
+        .set_config(server_config)
+        .set_http_middleware(tower::ServiceBuilder::new().layer(TimeoutLayer::new(request_timeout)))
+        .set_rpc_middleware(rpc_middleware)
+        .to_service_builder();
+    let methods = Methods::from(namespace.into_rpc());
+
+    // Connections are accepted here rather than by `Server::start()`, since the caller recorded
+    // in the audit log includes the connection peer. The server is never stopped.
+    let (stop_handle, _server_handle) = stop_channel();
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(err) => {
+                tracing::warn!("Failed accepting admin API connection: {err}");
+                tokio::time::sleep(ACCEPT_ERROR_BACKOFF).await;
+                continue;
+            }
+        };
+        let service = tower::ServiceBuilder::new()
+            .map_request(move |request: HttpRequest<Incoming>| tag_admin_caller(request, peer.ip()))
+            .service(
+                service_builder
+                    .clone()
+                    .build(methods.clone(), stop_handle.clone()),
+            );
+        tokio::spawn(serve_with_graceful_shutdown(
+            stream,
+            service,
+            std::future::pending::<()>(),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{Value, json};
+
+    const TOKEN: &str = "0123456789abcdef0123456789abcdef";
+
+    struct TestApi {
+        url: String,
+        audit_log: Arc<AuditLog>,
+        _dir: tempfile::TempDir,
+    }
+
+    async fn start(hooks: AdminHooks) -> TestApi {
+        let dir = tempfile::tempdir().unwrap();
+        let audit_log = Arc::new(AuditLog::open(&dir.path().join("audit.jsonl")).unwrap());
+        let (_, auth_token) = watch::channel(TOKEN.into());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve_admin_api(
+            listener,
+            auth_token,
+            AdminNamespace::new(hooks, audit_log.clone()),
+            Duration::from_secs(10),
+            64 * 1024,
+        ));
+        TestApi {
+            url,
+            audit_log,
+            _dir: dir,
+        }
+    }
+
+    fn acceptance_hooks() -> (AdminHooks, watch::Receiver<TransactionAcceptanceState>) {
+        let (sender, receiver) = watch::channel(TransactionAcceptanceState::Accepting);
+        let hooks = AdminHooks {
+            tx_acceptance: Some(sender),
+            ..AdminHooks::default()
+        };
+        (hooks, receiver)
+    }
+
+    async fn send(api: &TestApi, authorization: Option<String>, body: String) -> Value {
+        let mut request = reqwest::Client::new()
+            .post(&api.url)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .header("x-admin-caller", "ops")
+            .body(body);
+        if let Some(authorization) = authorization {
+            request = request.header(http::header::AUTHORIZATION, authorization);
+        }
+        let response = request.send().await.unwrap();
+        serde_json::from_slice(&response.bytes().await.unwrap()).unwrap()
+    }
+
+    async fn call(api: &TestApi, token: &str, method: &str, params: Value) -> Value {
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params });
+        send(api, Some(format!("Bearer {token}")), body.to_string()).await
+    }
+
+    async fn audit_log_page(api: &TestApi, params: Value) -> AuditLogPage {
+        let response = call(api, TOKEN, "admin_getAuditLog", params).await;
+        serde_json::from_value(response["result"].clone()).unwrap()
+    }
+
+    fn error_code(response: &Value) -> Option<i64> {
+        response["error"]["code"].as_i64()
+    }
+
+    #[tokio::test]
+    async fn authentication() {
+        let (hooks, acceptance) = acceptance_hooks();
+        let api = start(hooks).await;
+
+        let response = call(&api, "wrong", "admin_haltTransactionAcceptance", json!([])).await;
+        assert_eq!(error_code(&response), Some(-32001));
+        let body = json!({ "jsonrpc": "2.0", "id": 1, "method": "admin_getAuditLog" });
+        let response = send(&api, None, body.to_string()).await;
+        assert_eq!(error_code(&response), Some(-32001));
+        assert!(matches!(
+            *acceptance.borrow(),
+            TransactionAcceptanceState::Accepting
+        ));
+
+        let response = call(&api, TOKEN, "admin_haltTransactionAcceptance", json!([])).await;
+        assert_eq!(response["result"], json!(true));
+        assert!(matches!(
+            *acceptance.borrow(),
+            TransactionAcceptanceState::NotAccepting(NotAcceptingReason::HaltedByOperator)
+        ));
+    }
+
+    #[tokio::test]
+    async fn malformed_requests() {
+        let (hooks, _) = acceptance_hooks();
+        let api = start(hooks).await;
+
+        let response = send(
+            &api,
+            Some(format!("Bearer {TOKEN}")),
+            "{not json".to_owned(),
+        )
+        .await;
+        assert_eq!(error_code(&response), Some(-32700));
+        let response = call(&api, TOKEN, "admin_unknown", json!([])).await;
+        assert_eq!(error_code(&response), Some(-32601));
+        let response = call(&api, TOKEN, "admin_reassignFriJob", json!(["five"])).await;
+        assert_eq!(error_code(&response), Some(-32602));
+        let batch = json!([
+            { "jsonrpc": "2.0", "id": 1, "method": "admin_haltTransactionAcceptance" },
+        ]);
+        let response = send(&api, Some(format!("Bearer {TOKEN}")), batch.to_string()).await;
+        assert!(error_code(&response).is_some(), "{response}");
+    }
+
+    #[tokio::test]
+    async fn actions_are_audited() {
+        let (hooks, acceptance) = acceptance_hooks();
+        let api = start(hooks).await;
+
+        call(&api, TOKEN, "admin_haltTransactionAcceptance", json!([])).await;
+        call(&api, TOKEN, "admin_resumeTransactionAcceptance", json!([])).await;
+        let response = call(&api, TOKEN, "admin_reassignFriJob", json!([5])).await;
+        assert_eq!(error_code(&response), Some(-32002));
+        call(&api, "wrong", "admin_haltTransactionAcceptance", json!([])).await;
+        assert!(matches!(
+            *acceptance.borrow(),
+            TransactionAcceptanceState::Accepting
+        ));
+
+        let page = audit_log_page(&api, json!([])).await;
+        // The `admin_getAuditLog` call itself is recorded after the page is read
+        assert_eq!(page.total, 7);
+        let records: Vec<_> = page
+            .records
+            .iter()
+            .map(|r| (r.action.as_str(), &r.outcome))
+            .collect();
+        assert_eq!(
+            records[..5],
+            [
+                ("admin_haltTransactionAcceptance", &AuditOutcome::Started),
+                ("admin_haltTransactionAcceptance", &AuditOutcome::Success),
+                ("admin_resumeTransactionAcceptance", &AuditOutcome::Started),
+                ("admin_resumeTransactionAcceptance", &AuditOutcome::Success),
+                ("admin_reassignFriJob", &AuditOutcome::Started),
+            ]
+        );
+        assert_eq!(page.records[0].caller, "ops@127.0.0.1");
+        assert_eq!(page.records[5].params, json!([5]));
+        assert!(matches!(
+            &page.records[5].outcome,
+            AuditOutcome::Failure { error } if error.contains("not available")
+        ));
+        assert_eq!(page.records[6].action, "admin_haltTransactionAcceptance");
+        assert_eq!(
+            page.records[6].outcome,
+            AuditOutcome::Failure {
+                error: "unauthorized".to_owned()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn unauthorized_calls_are_aggregated() {
+        let api = start(AdminHooks::default()).await;
+        for _ in 0..3 {
+            let response = call(&api, "wrong", "admin_haltTransactionAcceptance", json!([])).await;
+            assert_eq!(error_code(&response), Some(-32001));
+        }
+        assert_eq!(api.audit_log.record_count(), 1);
+    }
+
+    #[tokio::test]
+    async fn actions_are_refused_if_not_audited() {
+        let (hooks, acceptance) = acceptance_hooks();
+        let api = start(hooks).await;
+        call(&api, TOKEN, "admin_haltTransactionAcceptance", json!([])).await;
+
+        api.audit_log.break_writes();
+        let response = call(&api, TOKEN, "admin_resumeTransactionAcceptance", json!([])).await;
+        assert_eq!(error_code(&response), Some(-32603));
+        assert!(matches!(
+            *acceptance.borrow(),
+            TransactionAcceptanceState::NotAccepting(NotAcceptingReason::HaltedByOperator)
+        ));
+        // Read-only calls are still served
+        let page = audit_log_page(&api, json!([])).await;
+        assert_eq!(page.total, 2);
+    }
+
+    #[tokio::test]
+    async fn commitment_format_acknowledgment() {
+        let method = "admin_acknowledgeCommitmentFormatTransition";
+        let api = start(AdminHooks::default()).await;
+        let response = call(&api, TOKEN, method, json!([3])).await;
+        assert_eq!(error_code(&response), Some(-32002));
+
+        let (sender, acks) = watch::channel(None);
+        let api = start(AdminHooks {
+            commitment_format_acks: Some(sender),
+            ..AdminHooks::default()
+        })
+        .await;
+        let response = call(&api, TOKEN, method, json!([])).await;
+        assert_eq!(error_code(&response), Some(-32602));
+        let response = call(&api, TOKEN, method, json!([256])).await;
+        assert_eq!(error_code(&response), Some(-32602));
+        assert_eq!(*acks.borrow(), None);
+
+        let response = call(&api, TOKEN, method, json!([3])).await;
+        assert_eq!(response["result"], json!(true));
+        assert_eq!(*acks.borrow(), Some(3));
+        let response = call(&api, TOKEN, method, json!([3])).await;
+        assert_eq!(response["result"], json!(false));
+    }
+
+    #[tokio::test]
+    async fn requeue_reverted_batches() {
+        let method = "admin_requeueRevertedBatches";
+        let api = start(AdminHooks::default()).await;
+        let response = call(&api, TOKEN, method, json!([3])).await;
+        assert_eq!(error_code(&response), Some(-32002));
+
+        let (sender, reverts) = watch::channel(L1RevertStatus::None);
+        let api = start(AdminHooks {
+            l1_reverts: Some(sender.clone()),
+            ..AdminHooks::default()
+        })
+        .await;
+        let response = call(&api, TOKEN, method, json!([3])).await;
+        assert_eq!(error_code(&response), Some(-32603));
+
+        let revert = L1BatchRevert {
+            reverted_to_batch: 3,
+            reverted_to_block: 30,
+            last_reverted_batch: 5,
+            last_reverted_block: 50,
+            total_batches_verified: 3,
+            total_batches_executed: 2,
+            requires_local_rollback: true,
+        };
+        sender.send_replace(L1RevertStatus::Detected(revert));
+        let status = call(&api, TOKEN, "admin_getL1RevertStatus", json!([])).await;
+        assert_eq!(status["result"]["status"], "detected", "{status}");
+        assert_eq!(status["result"]["lastRevertedBatch"], 5, "{status}");
+        // Local blocks must be rolled back manually
+        let response = call(&api, TOKEN, method, json!([3])).await;
+        assert_eq!(error_code(&response), Some(-32603));
+        assert!(matches!(*reverts.borrow(), L1RevertStatus::Detected(_)));
+
+        sender.send_replace(L1RevertStatus::Detected(L1BatchRevert {
+            requires_local_rollback: false,
+            ..revert
+        }));
+        // The confirmation must name the exact batch L1 was reverted to
+        let response = call(&api, TOKEN, method, json!([4])).await;
+        assert_eq!(error_code(&response), Some(-32602));
+        let response = call(&api, TOKEN, method, json!([3])).await;
+        assert_eq!(response["result"]["revertedToBatch"], 3, "{response}");
+        assert!(matches!(
+            *reverts.borrow(),
+            L1RevertStatus::Acknowledged(L1BatchRevert {
+                reverted_to_batch: 3,
+                ..
+            })
+        ));
+        let response = call(&api, TOKEN, method, json!([3])).await;
+        assert_eq!(error_code(&response), Some(-32603));
+    }
+
+    #[tokio::test]
+    async fn resolve_replay_divergence() {
+        use zksync_os_types::ReplayDivergence;
+
+        let method = "admin_resolveReplayDivergence";
+        let api = start(AdminHooks::default()).await;
+        let response = call(&api, TOKEN, method, json!([5, "resume"])).await;
+        assert_eq!(error_code(&response), Some(-32002));
+
+        let (sender, status) = watch::channel(ReplayDivergenceStatus::None);
+        let api = start(AdminHooks {
+            replay_divergence: Some(sender.clone()),
+            ..AdminHooks::default()
+        })
+        .await;
+        let response = call(&api, TOKEN, method, json!([5, "resume"])).await;
+        assert_eq!(error_code(&response), Some(-32603));
+
+        let divergence = ReplayDivergence {
+            block_number: 5,
+            expected_output_hash: B256::repeat_byte(1),
+            computed_output_hash: B256::repeat_byte(2),
+            dump_path: None,
+        };
+        sender.send_replace(ReplayDivergenceStatus::Diverged(divergence.clone()));
+        let response = call(&api, TOKEN, "admin_getReplayDivergence", json!([])).await;
+        assert_eq!(response["result"]["status"], "diverged", "{response}");
+        assert_eq!(response["result"]["blockNumber"], 5, "{response}");
+        let response = call(&api, TOKEN, method, json!([5, "ignore"])).await;
+        assert_eq!(error_code(&response), Some(-32602));
+        // The confirmation must name the diverged block
+        let response = call(&api, TOKEN, method, json!([4, "resume"])).await;
+        assert_eq!(error_code(&response), Some(-32602));
+
+        let response = call(&api, TOKEN, method, json!([5, "keepHalted"])).await;
+        assert_eq!(response["result"]["status"], "halted", "{response}");
+        assert_eq!(
+            *status.borrow(),
+            ReplayDivergenceStatus::Halted(divergence.clone())
+        );
+        // A halted node may still be resumed later
+        let response = call(&api, TOKEN, method, json!([5, "resume"])).await;
+        assert_eq!(response["result"]["status"], "resumed", "{response}");
+        assert_eq!(
+            *status.borrow(),
+            ReplayDivergenceStatus::Resumed(divergence)
+        );
+        let response = call(&api, TOKEN, method, json!([5, "resume"])).await;
+        assert_eq!(error_code(&response), Some(-32603));
+    }
+
+    #[tokio::test]
+    async fn bundle_methods() {
+        use zksync_os_sequencer::execution::bundles::BundleStoreConfig;
+
+        let hash = B256::repeat_byte(1);
+        let api = start(AdminHooks::default()).await;
+        let response = call(&api, TOKEN, "admin_getBundleStatus", json!([hash])).await;
+        assert_eq!(error_code(&response), Some(-32002));
+
+        let api = start(AdminHooks {
+            bundles: Some(BundleStore::new(BundleStoreConfig {
+                allowed_signers: vec![],
+                max_pending_bundles: 1,
+                max_bundle_transactions: 1,
+            })),
+            ..AdminHooks::default()
+        })
+        .await;
+        let response = call(&api, TOKEN, "admin_getBundleStatus", json!([hash])).await;
+        assert_eq!(response["result"], Value::Null);
+        let bundle = json!({
+            "transactions": ["0x01"],
+            "inclusion": { "targetBlock": 7 },
+            "signature": { "r": "0x1", "s": "0x1", "yParity": "0x0" },
+        });
+        // The signer isn't allowed
+        let response = call(&api, TOKEN, "admin_submitBundle", json!([bundle])).await;
+        assert_eq!(error_code(&response), Some(-32602));
+        let response = call(&api, TOKEN, "admin_submitBundle", json!([])).await;
+        assert_eq!(error_code(&response), Some(-32602));
+    }
+
+    #[tokio::test]
+    async fn storage_keys_for_account() {
+        use zksync_os_interface::types::StorageWrite;
+
+        let method = "admin_getStorageKeysForAccount";
+        let address = Address::repeat_byte(1);
+        let api = start(AdminHooks::default()).await;
+        let response = call(&api, TOKEN, method, json!([address])).await;
+        assert_eq!(error_code(&response), Some(-32002));
+
+        let dir = tempfile::tempdir().unwrap();
+        let index = AccountKeysIndex::new(&dir.path().join("account_keys")).unwrap();
+        let write = StorageWrite {
+            key: B256::repeat_byte(2),
+            value: B256::repeat_byte(3),
+            account: address,
+            account_key: B256::ZERO,
+        };
+        index.add_block(1, &[write]);
+        let api = start(AdminHooks {
+            account_keys: Some(index),
+            ..AdminHooks::default()
+        })
+        .await;
+        let response = call(&api, TOKEN, method, json!([address])).await;
+        assert_eq!(
+            response["result"],
+            json!({ "keys": [B256::repeat_byte(2)], "indexedBlock": 1 })
+        );
+        let response = call(&api, TOKEN, method, json!(["0x01"])).await;
+        assert_eq!(error_code(&response), Some(-32602));
+    }
+
+    #[tokio::test]
+    async fn audit_log_pagination() {
+        let (hooks, _) = acceptance_hooks();
+        let api = start(hooks).await;
+        for _ in 0..3 {
+            call(&api, TOKEN, "admin_haltTransactionAcceptance", json!([])).await;
+            call(&api, TOKEN, "admin_resumeTransactionAcceptance", json!([])).await;
+        }
+
+        let page = audit_log_page(&api, json!([2, 3])).await;
+        assert_eq!(page.total, 12);
+        assert_eq!(
+            page.records.iter().map(|r| r.id).collect::<Vec<_>>(),
+            [2, 3, 4]
+        );
+
+        // Offset only; the previous `admin_getAuditLog` call is recorded as well
+        let page = audit_log_page(&api, json!([5])).await;
+        assert_eq!(page.total, 13);
+        let last = page.records.last().unwrap();
+        assert_eq!(last.action, "admin_getAuditLog");
+        assert_eq!(last.params, json!([2, 3]));
+    }
+}
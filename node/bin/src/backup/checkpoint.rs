@@ -0,0 +1,539 @@
+//! Verification of restored backups against L1 (trusted checkpoint sync).
+//!
+//! A restored backup set is only as trustworthy as whoever produced it. Unless restored as trusted,
+//! a backup set is marked as pending verification, and the node refuses to use it until it's verified:
+//! on startup, the state commitment is recomputed from the restored state tree and repository at
+//! the last block of the tree, and compared with the state commitment of the batch ending at this block
+//! committed on L1.
+//!
+//! The snapshot must end at a batch boundary, i.e. the last block of the restored tree must be the last
+//! block of a batch committed on L1; otherwise, it's rejected before any recomputation. If the commitments
+//! don't match, the restored databases are deleted. The verification result is kept in the data directory
+//! and reported by the status server.
+
+use crate::tree_manager::open_tree;
+use crate::{REPOSITORY_DB_NAME, STATE_TREE_DB_NAME};
+use alloy::consensus::Transaction;
+use alloy::eips::BlockId;
+use alloy::primitives::{B256, U256};
+use alloy::providers::{DynProvider, Provider};
+use alloy::rpc::types::Filter;
+use alloy::sol_types::{SolCall, SolEvent, SolValue};
+use anyhow::Context;
+use blake2::{Blake2s256, Digest};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use zksync_os_contract_interface::models::{CommitBatchInfo, StoredBatchInfo};
+use zksync_os_contract_interface::{IExecutor, ZkChain};
+use zksync_os_l1_watcher::util::find_l1_block_by_predicate;
+use zksync_os_status_server::CheckpointStatus;
+use zksync_os_storage::db::RepositoryDb;
+use zksync_os_storage_api::ReadRepository;
+
+/// Marker of a restored backup set pending verification.
+pub const PENDING_FILE_NAME: &str = "checkpoint_pending.json";
+/// Result of a successful verification.
+pub const CHECKPOINT_FILE_NAME: &str = "checkpoint.json";
+
+/// Commit encoding version produced by ZKsync OS L1 senders.
+const COMMIT_ENCODING_VERSION: u8 = 2;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingCheckpoint {
+    pub backup_set_id: u64,
+    /// Restored databases; deleted if verification fails.
+    pub databases: Vec<String>,
+}
+
+impl PendingCheckpoint {
+    pub fn save(&self, data_dir: &Path) -> anyhow::Result<()> {
+        std::fs::write(
+            data_dir.join(PENDING_FILE_NAME),
+            serde_json::to_vec_pretty(self)?,
+        )
+        .context("failed writing pending checkpoint marker")
+    }
+
+    fn load(data_dir: &Path) -> anyhow::Result<Option<Self>> {
+        read_json(&data_dir.join(PENDING_FILE_NAME))
+    }
+}
+
+/// Batch commitment as seen on L1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct L1BatchCommitment {
+    pub batch_number: u64,
+    pub state_commitment: B256,
+    pub last_block_timestamp: u64,
+    pub l1_tx_hash: B256,
+}
+
+/// Source of batch commitments on L1.
+#[allow(async_fn_in_trait)]
+pub trait L1Commitments {
+    async fn last_committed_batch(&self) -> anyhow::Result<u64>;
+
+    async fn batch_commitment(&self, batch_number: u64) -> anyhow::Result<L1BatchCommitment>;
+}
+
+impl L1Commitments for ZkChain<DynProvider> {
+    async fn last_committed_batch(&self) -> anyhow::Result<u64> {
+        Ok(self.get_total_batches_committed(BlockId::latest()).await?)
+    }
+
+    /// Reads the commitment from the calldata of the L1 transaction that emitted `BlockCommit`
+    /// for the batch, and checks it against the batch hash currently stored on L1 (so that
+    /// reverted commits are not used).
+    async fn batch_commitment(&self, batch_number: u64) -> anyhow::Result<L1BatchCommitment> {
+        let l1_block =
+            find_l1_block_by_predicate(Arc::new(self.clone()), move |zk, block| async move {
+                Ok(zk.get_total_batches_committed(block.into()).await? >= batch_number)
+            })
+            .await
+            .with_context(|| format!("cannot find L1 block committing batch {batch_number}"))?;
+        let filter = Filter::new()
+            .address(*self.address())
+            .event_signature(IExecutor::BlockCommit::SIGNATURE_HASH)
+            .topic1(U256::from(batch_number))
+            .from_block(l1_block)
+            .to_block(l1_block);
+        let logs = self.provider().get_logs(&filter).await?;
+        let log = logs
+            .last()
+            .with_context(|| format!("no `BlockCommit` event for batch {batch_number}"))?;
+        let event = log.log_decode::<IExecutor::BlockCommit>()?.inner.data;
+        let l1_tx_hash = log.transaction_hash.context("commit log without tx hash")?;
+
+        let tx = self
+            .provider()
+            .get_transaction_by_hash(l1_tx_hash)
+            .await?
+            .with_context(|| format!("commit transaction {l1_tx_hash} not found"))?;
+        let call = IExecutor::commitBatchesSharedBridgeCall::abi_decode(tx.input())
+            .context("unexpected commit transaction calldata")?;
+        let (version, commit_data) = call
+            ._commitData
+            .split_first()
+            .context("empty commit data")?;
+        anyhow::ensure!(
+            *version == COMMIT_ENCODING_VERSION,
+            "unsupported commit encoding version {version}"
+        );
+        let (_, new_batches) = <(
+            IExecutor::StoredBatchInfo,
+            Vec<IExecutor::CommitBatchInfoZKsyncOS>,
+        )>::abi_decode_params(commit_data)?;
+        let commit_info = new_batches
+            .into_iter()
+            .map(CommitBatchInfo::from)
+            .find(|info| info.batch_number == batch_number)
+            .with_context(|| format!("batch {batch_number} not found in commit calldata"))?;
+        anyhow::ensure!(
+            commit_info.new_state_commitment == event.batchHash,
+            "commit calldata doesn't match `BlockCommit` event for batch {batch_number}"
+        );
+
+        let stored_batch_info = StoredBatchInfo {
+            batch_number,
+            state_commitment: commit_info.new_state_commitment,
+            number_of_layer1_txs: commit_info.number_of_layer1_txs,
+            priority_operations_hash: commit_info.priority_operations_hash,
+            dependency_roots_rolling_hash: commit_info.dependency_roots_rolling_hash,
+            l2_to_l1_logs_root_hash: commit_info.l2_to_l1_logs_root_hash,
+            commitment: event.commitment,
+            last_block_timestamp: commit_info.last_block_timestamp,
+        };
+        let stored_batch_hash = self.stored_batch_hash(batch_number).await?;
+        anyhow::ensure!(
+            stored_batch_info.hash() == stored_batch_hash,
+            "commit of batch {batch_number} in {l1_tx_hash} is not the one stored on L1"
+        );
+
+        Ok(L1BatchCommitment {
+            batch_number,
+            state_commitment: commit_info.new_state_commitment,
+            last_block_timestamp: commit_info.last_block_timestamp,
+            l1_tx_hash,
+        })
+    }
+}
+
+/// State of the restored snapshot at the last block of the restored tree.
+#[derive(Debug)]
+struct Snapshot {
+    block_number: u64,
+    tree_root: B256,
+    state_commitment: B256,
+}
+
+/// Verifies a restored backup set pending verification in `data_dir`, see the [module docs](self).
+/// Returns the result of the latest successful verification, or `None` if the node wasn't
+/// bootstrapped from a backup that needed one.
+pub async fn verify_restored_checkpoint(
+    data_dir: &Path,
+    l1: &impl L1Commitments,
+) -> anyhow::Result<Option<CheckpointStatus>> {
+    let Some(pending) = PendingCheckpoint::load(data_dir)? else {
+        return read_json(&data_dir.join(CHECKPOINT_FILE_NAME));
+    };
+    tracing::info!(
+        backup_set_id = pending.backup_set_id,
+        "Verifying restored backup set against L1"
+    );
+
+    let (block_number, block_timestamp) = read_snapshot_block(data_dir)?;
+    let candidates = find_batches_ending_at(l1, block_timestamp).await?;
+    anyhow::ensure!(
+        !candidates.is_empty(),
+        "restored snapshot at block {block_number} doesn't end at a boundary of a batch committed on L1; \
+         snapshots must be taken at batch boundaries (the last block of the state tree must be the last block \
+         of a committed batch). Restored data is kept in `{}` for inspection",
+        data_dir.display()
+    );
+
+    let snapshot = tokio::task::spawn_blocking({
+        let data_dir = data_dir.to_owned();
+        move || recompute_snapshot(&data_dir, block_number, block_timestamp)
+    })
+    .await
+    .context("snapshot recomputation panicked")??;
+
+    let Some(batch) = candidates
+        .iter()
+        .find(|batch| batch.state_commitment == snapshot.state_commitment)
+    else {
+        let l1_commitments: Vec<_> = candidates
+            .iter()
+            .map(|batch| {
+                format!(
+                    "batch {}: {:?} (committed in {:?})",
+                    batch.batch_number, batch.state_commitment, batch.l1_tx_hash
+                )
+            })
+            .collect();
+        for name in &pending.databases {
+            std::fs::remove_dir_all(data_dir.join(name))
+                .with_context(|| format!("failed deleting restored `{name}`"))?;
+        }
+        std::fs::remove_file(data_dir.join(PENDING_FILE_NAME))?;
+        anyhow::bail!(
+            "restored snapshot at block {block_number} doesn't match L1: recomputed state commitment {:?} \
+             (tree root {:?}), L1 state commitment {}. Restored data was deleted",
+            snapshot.state_commitment,
+            snapshot.tree_root,
+            l1_commitments.join(", ")
+        );
+    };
+
+    let status = CheckpointStatus {
+        backup_set_id: pending.backup_set_id,
+        block_number: snapshot.block_number,
+        batch_number: batch.batch_number,
+        tree_root: snapshot.tree_root,
+        state_commitment: snapshot.state_commitment,
+        l1_commit_tx_hash: batch.l1_tx_hash,
+        verified_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Incorrect system time")
+            .as_secs(),
+    };
+    std::fs::write(
+        data_dir.join(CHECKPOINT_FILE_NAME),
+        serde_json::to_vec_pretty(&status)?,
+    )
+    .context("failed writing checkpoint")?;
+    std::fs::remove_file(data_dir.join(PENDING_FILE_NAME))?;
+    tracing::info!(?status, "Restored backup set is verified against L1");
+    Ok(Some(status))
+}
+
+/// Returns the last block of the restored tree and its timestamp.
+fn read_snapshot_block(data_dir: &Path) -> anyhow::Result<(u64, u64)> {
+    let block_number = open_tree(&data_dir.join(STATE_TREE_DB_NAME))?
+        .latest_version()?
+        .context("restored state tree is empty")?;
+    let repository = RepositoryDb::open_existing(&data_dir.join(REPOSITORY_DB_NAME))
+        .context("restored repository is empty")?;
+    let block = repository
+        .get_block_by_number(block_number)?
+        .with_context(|| format!("restored repository misses block {block_number}"))?;
+    Ok((block_number, block.header.timestamp))
+}
+
+/// Finds committed batches whose last block has `timestamp`. Batch timestamps are monotonic,
+/// so batches are binary searched.
+async fn find_batches_ending_at(
+    l1: &impl L1Commitments,
+    timestamp: u64,
+) -> anyhow::Result<Vec<L1BatchCommitment>> {
+    let last_committed_batch = l1.last_committed_batch().await?;
+    let mut fetched = HashMap::new();
+    let mut fetch = async |batch_number: u64| -> anyhow::Result<L1BatchCommitment> {
+        if let Some(batch) = fetched.get(&batch_number) {
+            return Ok(batch.clone());
+        }
+        let batch = l1.batch_commitment(batch_number).await?;
+        fetched.insert(batch_number, batch.clone());
+        Ok(batch)
+    };
+
+    // First batch with the last block timestamp >= `timestamp`
+    let (mut lo, mut hi) = (1, last_committed_batch + 1);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if fetch(mid).await?.last_block_timestamp >= timestamp {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+
+    let mut candidates = Vec::new();
+    for batch_number in lo..=last_committed_batch {
+        let batch = fetch(batch_number).await?;
+        if batch.last_block_timestamp != timestamp {
+            break;
+        }
+        candidates.push(batch);
+    }
+    Ok(candidates)
+}
+
+/// Recomputes the state commitment at `block_number` from the restored tree (checking its consistency)
+/// and repository.
+fn recompute_snapshot(
+    data_dir: &Path,
+    block_number: u64,
+    block_timestamp: u64,
+) -> anyhow::Result<Snapshot> {
+    let tree = open_tree(&data_dir.join(STATE_TREE_DB_NAME))?;
+    tree.verify_consistency(block_number)
+        .context("restored state tree is inconsistent")?;
+    let (tree_root, leaf_count) = tree
+        .root_info(block_number)?
+        .with_context(|| format!("restored state tree misses block {block_number}"))?;
+
+    let repository = RepositoryDb::open_existing(&data_dir.join(REPOSITORY_DB_NAME))
+        .context("restored repository is empty")?;
+    let mut block_hashes = Vec::with_capacity(256);
+    for number in (block_number as i64 - 255)..=block_number as i64 {
+        let hash = if number < 0 {
+            B256::ZERO
+        } else {
+            repository
+                .get_block_by_number(number as u64)?
+                .with_context(|| format!("restored repository misses block {number}"))?
+                .hash()
+        };
+        block_hashes.push(hash);
+    }
+
+    Ok(Snapshot {
+        block_number,
+        tree_root,
+        state_commitment: state_commitment(
+            tree_root,
+            leaf_count,
+            block_number,
+            &block_hashes,
+            block_timestamp,
+        ),
+    })
+}
+
+/// State commitment of the chain after `block_number`, as committed on L1. `block_hashes` are
+/// the hashes of the last 256 blocks, ending with `block_number`.
+fn state_commitment(
+    tree_root: B256,
+    leaf_count: u64,
+    block_number: u64,
+    block_hashes: &[B256],
+    block_timestamp: u64,
+) -> B256 {
+    let mut blocks_hasher = Blake2s256::new();
+    for hash in block_hashes {
+        blocks_hasher.update(hash);
+    }
+    let mut hasher = Blake2s256::new();
+    hasher.update(tree_root.as_slice());
+    hasher.update(leaf_count.to_be_bytes());
+    hasher.update(block_number.to_be_bytes());
+    hasher.update(blocks_hasher.finalize());
+    hasher.update(block_timestamp.to_be_bytes());
+    B256::from_slice(&hasher.finalize())
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> anyhow::Result<Option<T>> {
+    match std::fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map(Some)
+            .with_context(|| format!("failed parsing `{}`", path.display())),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("failed reading `{}`", path.display())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BLOCK_REPLAY_WAL_DB_NAME;
+    use crate::backup::tests::{test_genesis, write_block};
+    use zksync_os_storage::db::BlockReplayStorage;
+
+    /// L1 with batches of `BLOCKS_PER_BATCH` blocks committed from an honest snapshot.
+    struct MockL1 {
+        batches: Vec<L1BatchCommitment>,
+    }
+
+    impl L1Commitments for MockL1 {
+        async fn last_committed_batch(&self) -> anyhow::Result<u64> {
+            Ok(self.batches.len() as u64)
+        }
+
+        async fn batch_commitment(&self, batch_number: u64) -> anyhow::Result<L1BatchCommitment> {
+            self.batches
+                .get(batch_number as usize - 1)
+                .cloned()
+                .context("batch is not committed")
+        }
+    }
+
+    const BLOCKS_PER_BATCH: u64 = 2;
+
+    /// Creates a data directory with `blocks` blocks; block `doctored_block` (if any) has
+    /// a different state diff. Returns the state commitment after every block.
+    async fn create_data_dir(
+        data_dir: &Path,
+        blocks: u64,
+        doctored_block: Option<u64>,
+    ) -> Vec<B256> {
+        let genesis = test_genesis();
+        let wal = BlockReplayStorage::new(
+            &data_dir.join(BLOCK_REPLAY_WAL_DB_NAME),
+            &genesis,
+            "0.1.0".parse().unwrap(),
+        )
+        .await;
+        let repository = RepositoryDb::new(&data_dir.join(REPOSITORY_DB_NAME), &genesis).await;
+        let mut tree = open_tree(&data_dir.join(STATE_TREE_DB_NAME)).unwrap();
+        tree.extend(&[]).unwrap(); // genesis
+        for block_number in 1..=blocks {
+            let value = if doctored_block == Some(block_number) {
+                2
+            } else {
+                1
+            };
+            write_block(&wal, &repository, &mut tree, &genesis, block_number, value).await;
+        }
+        drop((wal, repository, tree));
+
+        let mut commitments = vec![];
+        for block_number in 1..=blocks {
+            let snapshot = recompute_snapshot(data_dir, block_number, block_number).unwrap();
+            commitments.push(snapshot.state_commitment);
+        }
+        commitments
+    }
+
+    async fn honest_l1(blocks: u64) -> MockL1 {
+        let dir = tempfile::tempdir().unwrap();
+        let commitments = create_data_dir(dir.path(), blocks, None).await;
+        let batches = (1..=blocks / BLOCKS_PER_BATCH)
+            .map(|batch_number| {
+                let last_block = batch_number * BLOCKS_PER_BATCH;
+                L1BatchCommitment {
+                    batch_number,
+                    state_commitment: commitments[last_block as usize - 1],
+                    last_block_timestamp: last_block,
+                    l1_tx_hash: B256::repeat_byte(batch_number as u8),
+                }
+            })
+            .collect();
+        MockL1 { batches }
+    }
+
+    fn mark_pending(data_dir: &Path) {
+        PendingCheckpoint {
+            backup_set_id: 3,
+            databases: vec![
+                STATE_TREE_DB_NAME.to_owned(),
+                REPOSITORY_DB_NAME.to_owned(),
+                BLOCK_REPLAY_WAL_DB_NAME.to_owned(),
+            ],
+        }
+        .save(data_dir)
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn correct_snapshot_is_accepted() {
+        let l1 = honest_l1(6).await;
+        let dir = tempfile::tempdir().unwrap();
+        create_data_dir(dir.path(), 4, None).await;
+        mark_pending(dir.path());
+
+        let status = verify_restored_checkpoint(dir.path(), &l1)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(status.backup_set_id, 3);
+        assert_eq!(status.block_number, 4);
+        assert_eq!(status.batch_number, 2);
+        assert_eq!(status.state_commitment, l1.batches[1].state_commitment);
+        assert_eq!(status.l1_commit_tx_hash, B256::repeat_byte(2));
+        assert!(!dir.path().join(PENDING_FILE_NAME).exists());
+
+        // Result is retained across restarts
+        let reloaded = verify_restored_checkpoint(dir.path(), &l1).await.unwrap();
+        assert_eq!(reloaded, Some(status));
+    }
+
+    #[tokio::test]
+    async fn doctored_snapshot_is_rejected() {
+        let l1 = honest_l1(6).await;
+        let dir = tempfile::tempdir().unwrap();
+        create_data_dir(dir.path(), 4, Some(3)).await;
+        mark_pending(dir.path());
+
+        let err = verify_restored_checkpoint(dir.path(), &l1)
+            .await
+            .unwrap_err();
+        let err = format!("{err:#}");
+        assert!(err.contains("doesn't match L1"), "{err}");
+        assert!(
+            err.contains(&format!("{:?}", l1.batches[1].state_commitment)),
+            "{err}"
+        );
+        assert!(
+            err.contains(&format!("{:?}", B256::repeat_byte(2))),
+            "{err}"
+        );
+        for name in [
+            STATE_TREE_DB_NAME,
+            REPOSITORY_DB_NAME,
+            BLOCK_REPLAY_WAL_DB_NAME,
+        ] {
+            assert!(!dir.path().join(name).exists(), "{name}");
+        }
+        assert!(!dir.path().join(PENDING_FILE_NAME).exists());
+    }
+
+    #[tokio::test]
+    async fn non_boundary_snapshot_is_rejected_upfront() {
+        let l1 = honest_l1(6).await;
+        let dir = tempfile::tempdir().unwrap();
+        create_data_dir(dir.path(), 3, None).await;
+        mark_pending(dir.path());
+
+        let err = verify_restored_checkpoint(dir.path(), &l1)
+            .await
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("batch boundaries"), "{err:#}");
+        // Nothing is deleted, and the snapshot stays unverified
+        assert!(dir.path().join(STATE_TREE_DB_NAME).exists());
+        assert!(dir.path().join(PENDING_FILE_NAME).exists());
+    }
+}
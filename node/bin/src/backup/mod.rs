@@ -0,0 +1,424 @@
+//! Periodic online backups of node databases.
+//!
+//! Each run of [`BackupScheduler`] creates a *backup set* - a backup of every node database taken with
+//! the RocksDB backup engine while the node keeps writing. Backups of the same database share unchanged
+//! files, so each set only copies files created since the previous one. Sets are recorded in
+//! a manifest (`manifest.json` in the backup directory) and can be restored with
+//! the `zksync-os-restore-backup` tool, see [`restore`].
+//!
+//! Databases are backed up in the reverse order of the block pipeline, the block replay WAL being the last
+//! one. This way, every other database in a set is at most as recent as the WAL, so that the node
+//! can catch up by replaying blocks after a restore.
+
+use crate::config::BackupConfig;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use vise::{Buckets, Counter, Gauge, Histogram, LabeledFamily, Metrics, Unit};
+use zksync_os_rocksdb::backup;
+
+pub mod checkpoint;
+pub mod restore;
+
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+/// Scratch directory (in the backup directory) for verifying backups by restoring them.
+const VERIFY_DIR_NAME: &str = "verify";
+
+/// Database to back up.
+pub struct BackupTarget {
+    /// Directory name of the database in `rocks_db_path`; also used as its name in the manifest.
+    pub name: &'static str,
+    /// Returns the last block persisted to the database, if the database tracks blocks.
+    pub block_number: Box<dyn Fn() -> Option<u64> + Send + Sync>,
+}
+
+impl BackupTarget {
+    pub fn new(
+        name: &'static str,
+        block_number: impl Fn() -> Option<u64> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name,
+            block_number: Box::new(block_number),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DatabaseBackup {
+    pub name: String,
+    /// ID of the backup in the RocksDB backup engine of the database.
+    pub backup_id: u32,
+    /// Last block persisted to the database when the backup started. The backup contains at least
+    /// this block (and may contain later ones).
+    pub block_number: Option<u64>,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupSet {
+    pub id: u64,
+    /// Unix timestamp (in seconds) of the start of the backup.
+    pub created_at: u64,
+    pub duration_ms: u64,
+    pub databases: Vec<DatabaseBackup>,
+}
+
+impl BackupSet {
+    pub fn database(&self, name: &str) -> Option<&DatabaseBackup> {
+        self.databases.iter().find(|db| db.name == name)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// Retained backup sets, oldest first.
+    pub sets: Vec<BackupSet>,
+}
+
+impl BackupManifest {
+    pub fn load(backup_dir: &Path) -> anyhow::Result<Self> {
+        let path = backup_dir.join(MANIFEST_FILE_NAME);
+        match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .with_context(|| format!("failed parsing backup manifest `{}`", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).context("failed reading backup manifest"),
+        }
+    }
+
+    fn save(&self, backup_dir: &Path) -> anyhow::Result<()> {
+        let path = backup_dir.join(MANIFEST_FILE_NAME);
+        let tmp_path = path.with_extension("json.tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec_pretty(self)?)
+            .context("failed writing backup manifest")?;
+        std::fs::rename(&tmp_path, &path).context("failed replacing backup manifest")
+    }
+}
+
+/// Periodically backs up node databases, see the [module docs](self).
+pub struct BackupScheduler {
+    config: BackupConfig,
+    backup_dir: PathBuf,
+    rocks_db_path: PathBuf,
+    /// Databases in the backup order.
+    targets: Vec<BackupTarget>,
+}
+
+impl BackupScheduler {
+    pub fn new(config: BackupConfig, rocks_db_path: PathBuf, targets: Vec<BackupTarget>) -> Self {
+        let backup_dir = config
+            .target_dir
+            .clone()
+            .expect("`backup.target_dir` must be set when backups are enabled");
+        Self {
+            config,
+            backup_dir,
+            rocks_db_path,
+            targets,
+        }
+    }
+
+    pub async fn run(self) -> anyhow::Result<()> {
+        let interval = self.config.interval;
+        let mut timer = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+        timer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let this = Arc::new(self);
+        loop {
+            timer.tick().await;
+            let this = this.clone();
+            let result = tokio::task::spawn_blocking(move || this.create_backup_set())
+                .await
+                .expect("backup task panicked");
+            if let Err(err) = result {
+                tracing::error!(?err, "Failed creating backup set");
+            }
+        }
+    }
+
+    /// Backs up all target databases, verifies the backups and records them in the manifest.
+    pub fn create_backup_set(&self) -> anyhow::Result<BackupSet> {
+        let started_at = Instant::now();
+        let result = self.create_backup_set_inner(started_at);
+        BACKUP_METRICS.duration.observe(started_at.elapsed());
+        match &result {
+            Ok(set) => {
+                BACKUP_METRICS.sets[&"success"].inc();
+                BACKUP_METRICS.last_success_timestamp.set(set.created_at);
+                let wal_block = set
+                    .database(crate::BLOCK_REPLAY_WAL_DB_NAME)
+                    .and_then(|db| db.block_number);
+                if let Some(block_number) = wal_block {
+                    BACKUP_METRICS.last_backed_up_block.set(block_number);
+                }
+                for db in &set.databases {
+                    BACKUP_METRICS.size_bytes[&db.name].set(db.size_bytes);
+                }
+            }
+            Err(_) => BACKUP_METRICS.sets[&"failure"].inc(),
+        }
+        result
+    }
+
+    fn create_backup_set_inner(&self, started_at: Instant) -> anyhow::Result<BackupSet> {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Incorrect system time")
+            .as_secs();
+        let mut manifest = BackupManifest::load(&self.backup_dir)?;
+
+        let mut databases = Vec::with_capacity(self.targets.len());
+        for target in &self.targets {
+            let block_number = (target.block_number)();
+            let engine_dir = self.backup_dir.join(target.name);
+            std::fs::create_dir_all(&engine_dir)
+                .with_context(|| format!("cannot create `{}`", engine_dir.display()))?;
+            let info = backup::backup_open_instance(
+                &self.rocks_db_path.join(target.name),
+                &engine_dir,
+                self.config.retention,
+            )
+            .with_context(|| format!("failed backing up `{}`", target.name))?
+            .with_context(|| format!("database `{}` is not open", target.name))?;
+            if self.config.verify_checksums {
+                self.verify_checksums(target.name, info.backup_id)?;
+            }
+            databases.push(DatabaseBackup {
+                name: target.name.to_owned(),
+                backup_id: info.backup_id,
+                block_number,
+                size_bytes: info.size,
+            });
+        }
+
+        let set = BackupSet {
+            id: manifest.sets.last().map_or(0, |set| set.id + 1),
+            created_at,
+            duration_ms: started_at.elapsed().as_millis() as u64,
+            databases,
+        };
+        manifest.sets.push(set.clone());
+        self.prune(&mut manifest)?;
+        manifest.save(&self.backup_dir)?;
+        tracing::info!(
+            set_id = set.id,
+            duration_ms = set.duration_ms,
+            databases = ?set.databases,
+            "Created backup set"
+        );
+        Ok(set)
+    }
+
+    /// Restores the backup into a scratch directory; RocksDB checks file checksums while restoring.
+    fn verify_checksums(&self, name: &str, backup_id: u32) -> anyhow::Result<()> {
+        let verify_path = self.backup_dir.join(VERIFY_DIR_NAME).join(name);
+        let result = backup::restore_backup(&self.backup_dir.join(name), backup_id, &verify_path)
+            .with_context(|| format!("checksum verification of `{name}` backup failed"));
+        std::fs::remove_dir_all(&verify_path).ok();
+        result
+    }
+
+    /// Drops sets beyond retention, as well as sets referencing backups that were purged
+    /// by the backup engine (which may happen after failed runs).
+    fn prune(&self, manifest: &mut BackupManifest) -> anyhow::Result<()> {
+        let excess = manifest
+            .sets
+            .len()
+            .saturating_sub(self.config.retention.max(1));
+        manifest.sets.drain(..excess);
+
+        let mut available = HashMap::new();
+        for target in &self.targets {
+            let ids: Vec<u32> = backup::list_backups(&self.backup_dir.join(target.name))?
+                .into_iter()
+                .map(|info| info.backup_id)
+                .collect();
+            available.insert(target.name, ids);
+        }
+        manifest.sets.retain(|set| {
+            set.databases.iter().all(|db| {
+                available
+                    .get(db.name.as_str())
+                    .is_some_and(|ids| ids.contains(&db.backup_id))
+            })
+        });
+        Ok(())
+    }
+}
+
+#[derive(Debug, Metrics)]
+#[metrics(prefix = "backup")]
+pub(crate) struct BackupMetrics {
+    /// Created backup sets by result (`success` or `failure`).
+    #[metrics(labels = ["result"])]
+    pub sets: LabeledFamily<&'static str, Counter>,
+    #[metrics(unit = Unit::Seconds, buckets = Buckets::LATENCIES)]
+    pub duration: Histogram<Duration>,
+    /// Unix timestamp (in seconds) of the latest successful backup set.
+    pub last_success_timestamp: Gauge<u64>,
+    /// Last block of the block replay WAL in the latest successful backup set.
+    pub last_backed_up_block: Gauge<u64>,
+    /// Size of the latest backup by database, including files shared with previous backups.
+    #[metrics(unit = Unit::Bytes, labels = ["database"])]
+    pub size_bytes: LabeledFamily<String, Gauge<u64>>,
+}
+
+#[vise::register]
+pub(crate) static BACKUP_METRICS: vise::Global<BackupMetrics> = vise::Global::new();
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::tree_manager::open_tree;
+    use crate::{BLOCK_REPLAY_WAL_DB_NAME, REPOSITORY_DB_NAME, STATE_TREE_DB_NAME};
+    use alloy::consensus::{Block, BlockBody, Header, Sealed};
+    use alloy::primitives::{Address, B256};
+    use alloy::providers::{Provider, ProviderBuilder};
+    use alloy::transports::mock::Asserter;
+    use zksync_os_contract_interface::ZkChain;
+    use zksync_os_genesis::{Genesis, GenesisBuilder};
+    use zksync_os_merkle_tree::{MerkleTree, RocksDBWrapper, TreeEntry};
+    use zksync_os_storage::db::{BlockReplayStorage, RepositoryDb};
+    use zksync_os_storage_api::{ReadReplay, ReadRepository, ReplayRecord, WriteReplay};
+
+    pub(crate) fn test_genesis() -> Genesis {
+        let provider = ProviderBuilder::new()
+            .connect_mocked_client(Asserter::new())
+            .erased();
+        GenesisBuilder::new()
+            .execution_version(1)
+            .into_genesis(ZkChain::new(Address::ZERO, provider))
+            .unwrap()
+    }
+
+    /// Writes block `block_number` (with timestamp equal to its number) to all DBs; the block sets
+    /// a single tree slot to `tree_value`.
+    pub(super) async fn write_block(
+        wal: &BlockReplayStorage,
+        repository: &RepositoryDb,
+        tree: &mut MerkleTree<RocksDBWrapper>,
+        genesis: &Genesis,
+        block_number: u64,
+        tree_value: u8,
+    ) {
+        let mut block_context = genesis.state().await.unwrap().context;
+        block_context.block_number = block_number;
+        wal.write(
+            ReplayRecord {
+                block_context,
+                starting_l1_priority_id: 0,
+                transactions: vec![],
+                previous_block_timestamp: 0,
+                block_timestamp_millis: 0,
+                node_version: "0.1.0".parse().unwrap(),
+                block_output_hash: B256::ZERO,
+            },
+            false,
+        );
+        let header = Header {
+            number: block_number,
+            timestamp: block_number,
+            ..Header::default()
+        };
+        let block = Block {
+            header,
+            body: BlockBody::default(),
+        };
+        repository.write_block(
+            &Sealed::new_unchecked(block, B256::with_last_byte(block_number as u8)),
+            &[],
+        );
+        tree.extend(&[TreeEntry {
+            key: B256::with_last_byte(block_number as u8),
+            value: B256::repeat_byte(tree_value),
+        }])
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn backup_of_live_databases_is_restored() {
+        let dir = tempfile::tempdir().unwrap();
+        let rocks_db_path = dir.path().join("db");
+        let backup_dir = dir.path().join("backups");
+        let genesis = test_genesis();
+
+        let wal = BlockReplayStorage::new(
+            &rocks_db_path.join(BLOCK_REPLAY_WAL_DB_NAME),
+            &genesis,
+            "0.1.0".parse().unwrap(),
+        )
+        .await;
+        let repository = RepositoryDb::new(&rocks_db_path.join(REPOSITORY_DB_NAME), &genesis).await;
+        let mut tree = open_tree(&rocks_db_path.join(STATE_TREE_DB_NAME)).unwrap();
+        tree.extend(&[]).unwrap(); // genesis
+        for block_number in 1..=3 {
+            write_block(&wal, &repository, &mut tree, &genesis, block_number, 1).await;
+        }
+
+        let scheduler = BackupScheduler::new(
+            BackupConfig {
+                enabled: true,
+                target_dir: Some(backup_dir.clone()),
+                interval: Duration::from_secs(3600),
+                retention: 2,
+                verify_checksums: true,
+            },
+            rocks_db_path.clone(),
+            vec![
+                BackupTarget::new(STATE_TREE_DB_NAME, {
+                    let tree = tree.clone();
+                    move || tree.latest_version().unwrap()
+                }),
+                BackupTarget::new(REPOSITORY_DB_NAME, {
+                    let repository = repository.clone();
+                    move || Some(repository.get_latest_block())
+                }),
+                BackupTarget::new(BLOCK_REPLAY_WAL_DB_NAME, {
+                    let wal = wal.clone();
+                    move || Some(wal.latest_record())
+                }),
+            ],
+        );
+        let first_set = scheduler.create_backup_set().unwrap();
+        // Databases are written to between backups
+        write_block(&wal, &repository, &mut tree, &genesis, 4, 1).await;
+        let second_set = scheduler.create_backup_set().unwrap();
+        write_block(&wal, &repository, &mut tree, &genesis, 5, 1).await;
+        let third_set = scheduler.create_backup_set().unwrap();
+
+        // Only 2 sets are retained
+        let manifest = BackupManifest::load(&backup_dir).unwrap();
+        assert_eq!(manifest.sets, [second_set.clone(), third_set]);
+        assert!(
+            restore::restore_backup_set(
+                &backup_dir,
+                Some(first_set.id),
+                &dir.path().join("old"),
+                true
+            )
+            .is_err()
+        );
+
+        let restored_path = dir.path().join("restored");
+        let restored =
+            restore::restore_backup_set(&backup_dir, Some(second_set.id), &restored_path, false)
+                .unwrap();
+        assert_eq!(restored.set, second_set);
+        // Untrusted backups must be verified against L1 before use
+        assert!(restored_path.join(checkpoint::PENDING_FILE_NAME).exists());
+        for name in [
+            STATE_TREE_DB_NAME,
+            REPOSITORY_DB_NAME,
+            BLOCK_REPLAY_WAL_DB_NAME,
+        ] {
+            assert_eq!(restored.block_numbers[name], 4, "{name}");
+            assert_eq!(second_set.database(name).unwrap().block_number, Some(4));
+        }
+
+        // Restoring into a non-empty directory is refused
+        assert!(restore::restore_backup_set(&backup_dir, None, &restored_path, true).is_err());
+    }
+}
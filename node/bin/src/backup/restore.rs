@@ -0,0 +1,127 @@
+//! Restoring backup sets created by [`BackupScheduler`](super::BackupScheduler).
+
+use super::checkpoint::PendingCheckpoint;
+use super::{BackupManifest, BackupSet};
+use crate::tree_manager::open_tree;
+use crate::{BLOCK_REPLAY_WAL_DB_NAME, REPOSITORY_DB_NAME, STATE_TREE_DB_NAME};
+use anyhow::Context;
+use std::collections::BTreeMap;
+use std::path::Path;
+use zksync_os_rocksdb::backup;
+use zksync_os_state::StateHandle;
+use zksync_os_state_full_diffs::FullDiffsState;
+use zksync_os_storage::db::{BlockReplayStorage, RepositoryDb};
+
+#[derive(Debug)]
+pub struct RestoredBackup {
+    pub set: BackupSet,
+    /// Last blocks of the restored databases that track blocks.
+    pub block_numbers: BTreeMap<String, u64>,
+}
+
+/// Restores the backup set `set_id` (or the latest set) from `backup_dir` into `data_dir`, which must
+/// be missing or empty, and checks consistency of the restored databases.
+///
+/// Unless `trusted` is set, the restored set is marked as pending verification; the node will verify it
+/// against L1 on startup (see [`checkpoint`](super::checkpoint)).
+pub fn restore_backup_set(
+    backup_dir: &Path,
+    set_id: Option<u64>,
+    data_dir: &Path,
+    trusted: bool,
+) -> anyhow::Result<RestoredBackup> {
+    let manifest = BackupManifest::load(backup_dir)?;
+    let set = match set_id {
+        Some(id) => manifest.sets.into_iter().find(|set| set.id == id),
+        None => manifest.sets.into_iter().last(),
+    }
+    .with_context(|| {
+        format!(
+            "backup set {set_id:?} not found in `{}`",
+            backup_dir.display()
+        )
+    })?;
+
+    let is_empty = match std::fs::read_dir(data_dir) {
+        Ok(mut entries) => entries.next().is_none(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => true,
+        Err(err) => return Err(err).context("cannot read data directory"),
+    };
+    anyhow::ensure!(
+        is_empty,
+        "data directory `{}` is not empty",
+        data_dir.display()
+    );
+
+    for db in &set.databases {
+        backup::restore_backup(
+            &backup_dir.join(&db.name),
+            db.backup_id,
+            &data_dir.join(&db.name),
+        )
+        .with_context(|| format!("failed restoring `{}`", db.name))?;
+        tracing::info!(
+            name = db.name,
+            backup_id = db.backup_id,
+            "Restored database"
+        );
+    }
+
+    let block_numbers = check_consistency(&set, data_dir)?;
+    if !trusted {
+        PendingCheckpoint {
+            backup_set_id: set.id,
+            databases: set.databases.iter().map(|db| db.name.clone()).collect(),
+        }
+        .save(data_dir)?;
+    }
+    Ok(RestoredBackup { set, block_numbers })
+}
+
+/// Checks that restored databases contain at least the blocks recorded in the manifest, and that no database
+/// is ahead of the block replay WAL (otherwise, the node cannot catch up by replaying blocks).
+fn check_consistency(set: &BackupSet, data_dir: &Path) -> anyhow::Result<BTreeMap<String, u64>> {
+    let mut block_numbers = BTreeMap::new();
+    for db in &set.databases {
+        let Some(block_number) = read_block_number(&db.name, data_dir)? else {
+            continue;
+        };
+        if let Some(expected) = db.block_number {
+            anyhow::ensure!(
+                block_number >= expected,
+                "restored `{}` is at block {block_number}, but the backup was taken at block {expected}",
+                db.name
+            );
+        }
+        block_numbers.insert(db.name.clone(), block_number);
+    }
+
+    let wal_block = *block_numbers
+        .get(BLOCK_REPLAY_WAL_DB_NAME)
+        .context("backup set does not contain the block replay WAL")?;
+    for (name, block_number) in &block_numbers {
+        anyhow::ensure!(
+            *block_number <= wal_block,
+            "restored `{name}` is at block {block_number}, ahead of the block replay WAL (block {wal_block})"
+        );
+    }
+    tracing::info!(?block_numbers, "Restored databases are consistent");
+    Ok(block_numbers)
+}
+
+fn read_block_number(name: &str, data_dir: &Path) -> anyhow::Result<Option<u64>> {
+    let path = data_dir.join(name);
+    Ok(match name {
+        BLOCK_REPLAY_WAL_DB_NAME => BlockReplayStorage::read_latest_record(&path),
+        REPOSITORY_DB_NAME => RepositoryDb::read_latest_block(&path),
+        STATE_TREE_DB_NAME => open_tree(&path)?.latest_version()?,
+        zksync_os_state_full_diffs::STATE_STORAGE_DB_NAME => {
+            Some(FullDiffsState::read_latest_block(data_dir)?)
+        }
+        zksync_os_state::STATE_STORAGE_DB_NAME => {
+            StateHandle::read_compacted_block_number(data_dir)?
+        }
+        // Preimages and the priority tree don't track blocks
+        _ => None,
+    })
+}
@@ -1,5 +1,7 @@
 use async_trait::async_trait;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc;
+use zksync_os_l1_sender::batcher_metrics::BATCHER_METRICS;
 use zksync_os_l1_sender::batcher_model::{FriProof, SignedBatchEnvelope};
 use zksync_os_pipeline::{PeekableReceiver, PipelineComponent};
 
@@ -32,6 +34,22 @@ impl PipelineComponent for BatchSink {
                 proof = ?envelope.data,
                 " ▶▶▶ Batch has been fully processed"
             );
+            if let Some(priority_deadline) = envelope.batch.priority_deadline {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .expect("Incorrect system time")
+                    .as_secs();
+                let margin = priority_deadline.saturating_sub(now);
+                tracing::info!(
+                    batch_number = envelope.batch_number(),
+                    priority_deadline,
+                    margin,
+                    "Executed batch with priority transactions close to expiring"
+                );
+                BATCHER_METRICS
+                    .priority_deadline_margin
+                    .observe(Duration::from_secs(margin));
+            }
         }
         anyhow::bail!("Failed to receive committed batch");
     }
@@ -3,9 +3,9 @@ use zksync_os_contract_interface::models::StoredBatchInfo;
 use zksync_os_interface::types::BlockOutput;
 use zksync_os_l1_sender::batcher_metrics::BatchExecutionStage;
 use zksync_os_l1_sender::batcher_model::{
-    BatchEnvelope, BatchForSigning, BatchMetadata, ProverInput,
+    BatchEnvelope, BatchForSigning, BatchMetadata, ProverInput, ProverInputVersion,
 };
-use zksync_os_l1_sender::commitment::BatchInfo;
+use zksync_os_l1_sender::commitment::{BatchInfo, COMMITMENT_ENCODING_VERSION};
 
 use zksync_os_storage_api::ReplayRecord;
 
@@ -65,6 +65,14 @@ pub(crate) fn seal_batch(
                 .map(|(block_output, _, _, _)| block_output.tx_results.len())
                 .sum(),
             execution_version,
+            first_block_timestamp_millis: Some(blocks.first().unwrap().1.block_timestamp_millis),
+            prover_input_version: Some(ProverInputVersion::current(execution_version)?),
+            commitment_encoding_version: COMMITMENT_ENCODING_VERSION,
+            commitment_format_transition: None,
+            l1_price_prediction: None,
+            pubdata_bytes: None,
+            seal_reason: None,
+            priority_deadline: None,
         },
         batch_prover_input,
     )
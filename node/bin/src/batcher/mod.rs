@@ -4,8 +4,10 @@ use crate::prover_api::proof_storage::ProofStorage;
 use alloy::primitives::Address;
 use anyhow::Context;
 use async_trait::async_trait;
+use std::ops::Range;
 use std::pin::Pin;
-use tokio::sync::mpsc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::{mpsc, watch};
 use tokio::time::Sleep;
 use tracing;
 use zksync_os_batch_types::BlockMerkleTreeData;
@@ -13,14 +15,15 @@ use zksync_os_contract_interface::models::StoredBatchInfo;
 use zksync_os_interface::types::BlockOutput;
 use zksync_os_l1_sender::batcher_metrics::BATCHER_METRICS;
 use zksync_os_l1_sender::batcher_model::{
-    BatchEnvelope, BatchForSigning, MissingSignature, ProverInput,
+    BatchEnvelope, BatchForSigning, BatchSealReason, MissingSignature, ProverInput,
 };
 use zksync_os_merkle_tree::TreeBatchOutput;
 use zksync_os_observability::{
     ComponentStateHandle, ComponentStateReporter, GenericComponentState,
 };
 use zksync_os_pipeline::{PeekableReceiver, PipelineComponent};
-use zksync_os_storage_api::ReplayRecord;
+use zksync_os_storage_api::{BlockStats, ReplayRecord};
+use zksync_os_types::{L1PricePrediction, L1TxSerialId, PriorityDeadlines};
 
 pub mod batch_builder;
 mod seal_criteria;
@@ -55,11 +58,22 @@ pub struct Batcher {
     pub pubdata_limit_bytes: u64,
     pub batcher_config: BatcherConfig,
     pub batch_storage: ProofStorage,
+    /// Number of the last sealed batch.
+    pub last_sealed_batch: watch::Sender<u64>,
+    /// Deadlines of priority transactions, used to flag batches with transactions close to
+    /// expiring on L1.
+    pub priority_deadlines: PriorityDeadlines,
 }
 
 #[async_trait]
 impl PipelineComponent for Batcher {
-    type Input = (BlockOutput, ReplayRecord, ProverInput, BlockMerkleTreeData);
+    type Input = (
+        BlockOutput,
+        ReplayRecord,
+        BlockStats,
+        ProverInput,
+        BlockMerkleTreeData,
+    );
     type Output = BatchEnvelope<ProverInput, MissingSignature>;
 
     const NAME: &'static str = "batcher";
@@ -80,7 +94,7 @@ impl PipelineComponent for Batcher {
 
             // Peek at the next block to decide whether to recreate or create anew.
             let next_block_number = input
-                .peek_recv(|(_, replay_record, _, _)| replay_record.block_context.block_number)
+                .peek_recv(|(_, replay_record, _, _, _)| replay_record.block_context.block_number)
                 .await
                 .context("batcher inbound channel unexpectedly closed")?;
             latency_tracker.enter_state(GenericComponentState::Processing);
@@ -97,6 +111,8 @@ impl PipelineComponent for Batcher {
 
             // Update prev_batch_info for the next iteration
             prev_batch_info = batch_envelope.batch.batch_info.clone().into_stored();
+            self.last_sealed_batch
+                .send_replace(batch_envelope.batch_number());
 
             BATCHER_METRICS
                 .transactions_per_batch
@@ -128,12 +144,7 @@ impl PipelineComponent for Batcher {
 impl Batcher {
     async fn create_batch(
         &mut self,
-        block_receiver: &mut PeekableReceiver<(
-            BlockOutput,
-            ReplayRecord,
-            ProverInput,
-            BlockMerkleTreeData,
-        )>,
+        block_receiver: &mut PeekableReceiver<<Self as PipelineComponent>::Input>,
         latency_tracker: &ComponentStateHandle<GenericComponentState>,
         prev_batch_info: &StoredBatchInfo,
     ) -> anyhow::Result<BatchForSigning<ProverInput>> {
@@ -147,7 +158,7 @@ impl Batcher {
             self.pubdata_limit_bytes,
         );
 
-        loop {
+        let seal_reason = loop {
             latency_tracker.enter_state(GenericComponentState::WaitingRecv);
             tokio::select! {
                 /* ---------- check for timeout ---------- */
@@ -156,24 +167,26 @@ impl Batcher {
                         d.as_mut().await
                     }
                 }, if deadline.is_some() => {
-                    BATCHER_METRICS.seal_reason[&"timeout"].inc();
                     tracing::debug!(batch_number, "Timeout reached, sealing the batch.");
-                    break;
+                    break BatchSealReason::Timeout;
                 }
 
                 /* ---------- collect blocks ---------- */
-               should_seal = block_receiver.peek_recv(|(block_output, replay_record, _, _)| {
+               seal_reason = block_receiver.peek_recv(|(_, replay_record, stats, _, _)| {
                     // determine if the block fits into the current batch
-                    accumulator.clone().add(block_output, replay_record).should_seal()
+                    accumulator
+                        .clone()
+                        .add(stats, replay_record.block_context.execution_version)
+                        .seal_reason()
                 }) => {
                     latency_tracker.enter_state(GenericComponentState::Processing);
-                    match should_seal {
-                        Some(true) => {
+                    match seal_reason {
+                        Some(Some(seal_reason)) => {
                             // some of the limits was reached, start sealing the batch
-                            break;
+                            break seal_reason;
                         }
-                        Some(false) => {
-                            let Some((block_output, replay_record, prover_input, tree)) = block_receiver.pop_buffer() else {
+                        Some(None) => {
+                            let Some((block_output, replay_record, stats, prover_input, tree)) = block_receiver.pop_buffer() else {
                                 anyhow::bail!("No block received in buffer after peeking")
                             };
 
@@ -193,7 +206,7 @@ impl Batcher {
                             };
 
                             // ---------- accumulate batch data ----------
-                            accumulator.add(&block_output, &replay_record);
+                            accumulator.add(&stats, replay_record.block_context.execution_version);
 
                             blocks.push((
                                 block_output,
@@ -222,30 +235,67 @@ impl Batcher {
                     }
                 }
             }
-        }
+        };
+        BATCHER_METRICS.seal_reason[&seal_reason.as_str()].inc();
         BATCHER_METRICS
             .blocks_per_batch
             .observe(blocks.len() as u64);
         accumulator.report_accumulated_resources_to_metrics();
         /* ---------- seal the batch ---------- */
-        let batch_envelope = batch_builder::seal_batch(
+        let mut batch_envelope = batch_builder::seal_batch(
             &blocks,
             prev_batch_info.clone(),
             batch_number,
             self.chain_id,
             self.chain_address,
         )?;
+        batch_envelope.batch.l1_price_prediction =
+            L1PricePrediction::mean(accumulator.l1_price_predictions);
+        batch_envelope.batch.pubdata_bytes = Some(accumulator.pubdata_bytes);
+        batch_envelope.batch.seal_reason = Some(seal_reason);
+
+        let first_priority_id = blocks.first().unwrap().1.starting_l1_priority_id;
+        let next_priority_id = first_priority_id
+            + batch_envelope
+                .batch
+                .batch_info
+                .commit_info
+                .number_of_layer1_txs;
+        batch_envelope.batch.priority_deadline =
+            self.priority_deadline(batch_number, first_priority_id..next_priority_id);
+        self.priority_deadlines.remove_batched(next_priority_id);
         Ok(batch_envelope)
     }
 
+    /// Earliest deadline of the batch's priority transactions, if it's within
+    /// `priority_deadline_window`.
+    fn priority_deadline(
+        &self,
+        batch_number: u64,
+        priority_ids: Range<L1TxSerialId>,
+    ) -> Option<u64> {
+        let deadline = self.priority_deadlines.earliest_included(priority_ids)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("Incorrect system time")
+            .as_secs();
+        let window = self.batcher_config.priority_deadline_window.as_secs();
+        if deadline > now + window {
+            return None;
+        }
+        tracing::info!(
+            batch_number,
+            deadline,
+            time_left = deadline.saturating_sub(now),
+            "batch contains priority transactions close to expiring, prioritizing it"
+        );
+        BATCHER_METRICS.priority_deadline_batches.inc();
+        Some(deadline)
+    }
+
     async fn recreate_existing_batch(
         &mut self,
-        block_receiver: &mut PeekableReceiver<(
-            BlockOutput,
-            ReplayRecord,
-            ProverInput,
-            BlockMerkleTreeData,
-        )>,
+        block_receiver: &mut PeekableReceiver<<Self as PipelineComponent>::Input>,
         latency_tracker: &ComponentStateHandle<GenericComponentState>,
         prev_batch_info: &StoredBatchInfo,
     ) -> anyhow::Result<BatchForSigning<ProverInput>> {
@@ -274,7 +324,7 @@ impl Batcher {
         // Collect all blocks in this batch
         while blocks.len() < expected_block_count as usize {
             latency_tracker.enter_state(GenericComponentState::WaitingRecv);
-            let (block_output, replay_record, prover_input, tree) = block_receiver
+            let (block_output, replay_record, _, prover_input, tree) = block_receiver
                 .recv()
                 .await
                 .context("channel closed while recreating batch")?;
@@ -301,7 +351,7 @@ impl Batcher {
         );
 
         // Rebuild the batch from blocks
-        let rebuilt_batch = batch_builder::seal_batch(
+        let mut rebuilt_batch = batch_builder::seal_batch(
             &blocks,
             prev_batch_info.clone(),
             batch_number,
@@ -321,6 +371,17 @@ impl Batcher {
             rebuilt_stored_batch_info,
             stored_stored_batch_info
         );
+        // The batch is already committed - keep the encoding version it was committed with,
+        // so that the commit sender detects a format change for the next batches
+        rebuilt_batch.batch.commitment_encoding_version =
+            existing_batch.batch.commitment_encoding_version;
+        rebuilt_batch.batch.commitment_format_transition =
+            existing_batch.batch.commitment_format_transition;
+        // Blocks of a recreated batch are replayed, so the original prediction is kept
+        rebuilt_batch.batch.l1_price_prediction = existing_batch.batch.l1_price_prediction;
+        rebuilt_batch.batch.pubdata_bytes = existing_batch.batch.pubdata_bytes;
+        rebuilt_batch.batch.seal_reason = existing_batch.batch.seal_reason;
+        rebuilt_batch.batch.priority_deadline = existing_batch.batch.priority_deadline;
 
         Ok(rebuilt_batch)
     }
@@ -1,8 +1,9 @@
 use std::collections::HashSet;
 use zk_ee::{common_structs::MAX_NUMBER_OF_LOGS, system::MAX_NATIVE_COMPUTATIONAL};
-use zksync_os_interface::types::BlockOutput;
 use zksync_os_l1_sender::batcher_metrics::BATCHER_METRICS;
-use zksync_os_storage_api::ReplayRecord;
+use zksync_os_l1_sender::batcher_model::BatchSealReason;
+use zksync_os_storage_api::BlockStats;
+use zksync_os_types::L1PricePrediction;
 
 #[derive(Default, Clone)]
 pub(crate) struct BatchInfoAccumulator {
@@ -13,6 +14,8 @@ pub(crate) struct BatchInfoAccumulator {
     pub block_count: u64,
 
     pub execution_versions: HashSet<u32>,
+    /// L1 price predictions of the blocks that have one.
+    pub l1_price_predictions: Vec<L1PricePrediction>,
 
     // Limits
     pub blocks_per_batch_limit: u64,
@@ -28,17 +31,14 @@ impl BatchInfoAccumulator {
         }
     }
 
-    pub fn add(&mut self, block_output: &BlockOutput, replay_record: &ReplayRecord) -> &Self {
-        self.native_cycles += block_output.computaional_native_used;
-        self.pubdata_bytes += block_output.pubdata.len() as u64;
-        self.l2_to_l1_logs_count += block_output
-            .tx_results
-            .iter()
-            .map(|tx_result| tx_result.as_ref().map_or(0, |tx| tx.l2_to_l1_logs.len()))
-            .sum::<usize>() as u64;
+    /// Accumulates a block using the stats precomputed by the sequencer.
+    pub fn add(&mut self, stats: &BlockStats, execution_version: u32) -> &Self {
+        self.native_cycles += stats.computational_native_used;
+        self.pubdata_bytes += stats.pubdata_bytes;
+        self.l2_to_l1_logs_count += stats.l2_to_l1_logs;
         self.block_count += 1;
-        self.execution_versions
-            .insert(replay_record.block_context.execution_version);
+        self.execution_versions.insert(execution_version);
+        self.l1_price_predictions.extend(stats.l1_price_prediction);
 
         self
     }
@@ -46,37 +46,37 @@ impl BatchInfoAccumulator {
     /// Checks if the batch should be sealed based on the content of the blocks.
     /// e.g. due to the block count limit, tx count limit, or pubdata size limit.
     pub fn should_seal(&self) -> bool {
+        self.seal_reason().is_some()
+    }
+
+    /// Returns the criterion the batch should be sealed by, if any.
+    pub fn seal_reason(&self) -> Option<BatchSealReason> {
         if self.block_count > self.blocks_per_batch_limit {
-            BATCHER_METRICS.seal_reason[&"blocks_per_batch"].inc();
             tracing::debug!("Batcher: reached blocks per batch limit");
-            return true;
+            return Some(BatchSealReason::BlocksPerBatch);
         }
 
         if self.native_cycles > MAX_NATIVE_COMPUTATIONAL {
-            BATCHER_METRICS.seal_reason[&"native_cycles"].inc();
             tracing::debug!("Batcher: reached native cycles limit for the batch");
-            return true;
+            return Some(BatchSealReason::NativeCycles);
         }
 
         if self.pubdata_bytes > self.batch_pubdata_limit_bytes {
-            BATCHER_METRICS.seal_reason[&"pubdata"].inc();
             tracing::debug!("Batcher: reached pubdata bytes limit for the batch");
-            return true;
+            return Some(BatchSealReason::Pubdata);
         }
 
         if self.l2_to_l1_logs_count > MAX_NUMBER_OF_LOGS {
-            BATCHER_METRICS.seal_reason[&"l2_l1_logs"].inc();
             tracing::debug!("Batcher: reached max number of L2 to L1 logs");
-            return true;
+            return Some(BatchSealReason::L2L1Logs);
         }
 
         if self.execution_versions.len() > 1 {
-            BATCHER_METRICS.seal_reason[&"execution_version_change"].inc();
             tracing::debug!("Batcher: ZKsync OS version changed within the batch");
-            return true;
+            return Some(BatchSealReason::ExecutionVersionChange);
         }
 
-        false
+        None
     }
 
     pub fn report_accumulated_resources_to_metrics(&self) {
@@ -88,3 +88,54 @@ impl BatchInfoAccumulator {
             .observe(self.pubdata_bytes);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(pubdata_bytes: u64, l2_to_l1_logs: u64) -> BlockStats {
+        BlockStats {
+            pubdata_bytes,
+            l2_to_l1_logs,
+            computational_native_used: 1_000,
+            ..BlockStats::default()
+        }
+    }
+
+    #[test]
+    fn accumulates_precomputed_stats() {
+        let mut accumulator = BatchInfoAccumulator::new(10, 1_000);
+        accumulator.add(&stats(300, 2), 1);
+        accumulator.add(&stats(400, 3), 1);
+        assert_eq!(accumulator.pubdata_bytes, 700);
+        assert_eq!(accumulator.l2_to_l1_logs_count, 5);
+        assert_eq!(accumulator.native_cycles, 2_000);
+        assert_eq!(accumulator.block_count, 2);
+        assert!(!accumulator.should_seal());
+
+        // The next block would exceed the pubdata limit
+        assert_eq!(
+            accumulator.clone().add(&stats(301, 0), 1).seal_reason(),
+            Some(BatchSealReason::Pubdata)
+        );
+        assert!(!accumulator.clone().add(&stats(300, 0), 1).should_seal());
+        // Execution version change
+        assert_eq!(
+            accumulator.clone().add(&stats(0, 0), 2).seal_reason(),
+            Some(BatchSealReason::ExecutionVersionChange)
+        );
+    }
+
+    #[test]
+    fn seals_on_block_count() {
+        let mut accumulator = BatchInfoAccumulator::new(2, 1_000);
+        accumulator.add(&BlockStats::default(), 1);
+        accumulator.add(&BlockStats::default(), 1);
+        assert!(!accumulator.should_seal());
+        accumulator.add(&BlockStats::default(), 1);
+        assert_eq!(
+            accumulator.seal_reason(),
+            Some(BatchSealReason::BlocksPerBatch)
+        );
+    }
+}
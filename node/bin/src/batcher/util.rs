@@ -1,6 +1,6 @@
 use alloy::primitives::{B256, U256, keccak256};
-use blake2::{Blake2s256, Digest};
 use zksync_os_contract_interface::models::StoredBatchInfo;
+use zksync_os_genesis::genesis_state_commitment;
 use zksync_os_merkle_tree::{MerkleTree, MerkleTreeVersion, RocksDBWrapper};
 use zksync_os_storage_api::RepositoryBlock;
 
@@ -14,26 +14,11 @@ pub async fn load_genesis_stored_batch_info(
         .root_info()
         .expect("Failed to get genesis root info");
 
-    let number = 0u64;
-    let timestamp = 0u64;
-
-    let last_256_block_hashes_blake = {
-        let mut blocks_hasher = Blake2s256::new();
-        for _ in 0..255 {
-            blocks_hasher.update([0u8; 32]);
-        }
-        blocks_hasher.update(genesis_block.hash());
-
-        blocks_hasher.finalize()
-    };
-
-    let mut hasher = Blake2s256::new();
-    hasher.update(genesis_root_info.0.as_slice());
-    hasher.update(genesis_root_info.1.to_be_bytes());
-    hasher.update(number.to_be_bytes());
-    hasher.update(last_256_block_hashes_blake);
-    hasher.update(timestamp.to_be_bytes());
-    let state_commitment = B256::from_slice(&hasher.finalize());
+    let state_commitment = genesis_state_commitment(
+        genesis_root_info.0,
+        genesis_root_info.1,
+        genesis_block.hash(),
+    );
 
     anyhow::ensure!(
         expected_genesis_root == state_commitment,
@@ -50,6 +35,59 @@ pub async fn load_genesis_stored_batch_info(
         // See `era-contracts/l1-contracts/contracts/common/Config.sol`.
         l2_to_l1_logs_root_hash: B256::ZERO,
         commitment: B256::from(U256::ONE.to_be_bytes()),
-        last_block_timestamp: timestamp,
+        last_block_timestamp: 0,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::genesis_stored_batch_info;
+    use crate::tree_manager::open_tree;
+    use alloy::primitives::{Address, B256, Bytes, U256};
+    use alloy::providers::{Provider, ProviderBuilder};
+    use alloy::transports::mock::Asserter;
+    use zksync_os_contract_interface::ZkChain;
+    use zksync_os_genesis::GenesisBuilder;
+    use zksync_os_merkle_tree::TreeEntry;
+    use zksync_os_storage::db::RepositoryDb;
+
+    #[tokio::test]
+    async fn genesis_root_from_builder_is_accepted() {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = ProviderBuilder::new()
+            .connect_mocked_client(Asserter::new())
+            .erased();
+        let genesis = GenesisBuilder::new()
+            .deploy_contract(Address::with_last_byte(1), Bytes::from_static(&[0x00]))
+            .set_storage(
+                Address::with_last_byte(1),
+                B256::ZERO,
+                B256::with_last_byte(1),
+            )
+            .fund(Address::with_last_byte(2), U256::from(1_000))
+            .into_genesis(ZkChain::new(Address::ZERO, provider))
+            .unwrap();
+
+        let repository = RepositoryDb::new(&dir.path().join("repository"), &genesis).await;
+        let mut tree = open_tree(&dir.path().join("tree")).unwrap();
+        let genesis_entries = genesis
+            .state()
+            .await
+            .unwrap()
+            .storage_logs
+            .iter()
+            .map(|(key, value)| TreeEntry {
+                key: *key,
+                value: *value,
+            })
+            .collect::<Vec<_>>();
+        tree.extend(&genesis_entries).unwrap();
+
+        // Panics if the root computed by the builder doesn't match the one computed by the node
+        let stored_batch_info = genesis_stored_batch_info(&repository, &tree, &genesis).await;
+        assert_eq!(
+            stored_batch_info.state_commitment,
+            genesis.state().await.unwrap().expected_genesis_root
+        );
+    }
+}
@@ -0,0 +1,118 @@
+//! Exports block ranges from node databases into chunk files and imports them into another node,
+//! e.g. for offline analysis or recovery. The node(s) must be stopped. See
+//! [`block_transfer`](zksync_os_server::block_transfer) for the file format.
+
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use zksync_os_server::block_transfer::{export_blocks, import_blocks, open_databases, read_blocks};
+use zksync_os_storage_api::ReadRepository;
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Exports a block range into chunk files
+    ExportBlocks {
+        /// Node data directory (`general.rocks_db_path` in the node config)
+        #[arg(long)]
+        data_dir: PathBuf,
+        /// First block to export
+        #[arg(long)]
+        from_block: u64,
+        /// Last block to export; defaults to the latest block
+        #[arg(long)]
+        to_block: Option<u64>,
+        /// Directory to write chunk files into
+        #[arg(long)]
+        output_dir: PathBuf,
+        /// Maximum number of blocks per chunk file
+        #[arg(long, default_value_t = 1_000)]
+        blocks_per_chunk: u64,
+    },
+    /// Validates exported chunk files and imports them into node databases
+    ImportBlocks {
+        /// Directory with chunk files
+        #[arg(long)]
+        input_dir: PathBuf,
+        /// Node data directory to import blocks into; must already be initialized with
+        /// the same genesis
+        #[arg(long, required_unless_present = "verify_only")]
+        data_dir: Option<PathBuf>,
+        /// Only validate chunk files and report mismatches, without importing
+        #[arg(long)]
+        verify_only: bool,
+    },
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let _observability_guard = zksync_os_observability::ObservabilityBuilder::new()
+        .with_logs(Some(zksync_os_observability::Logs::default()))
+        .build();
+
+    match args.command {
+        Command::ExportBlocks {
+            data_dir,
+            from_block,
+            to_block,
+            output_dir,
+            blocks_per_chunk,
+        } => {
+            let (repository, wal) = open_databases(&data_dir)?;
+            let to_block = to_block.unwrap_or_else(|| repository.get_latest_block());
+            let paths = export_blocks(
+                &repository,
+                &wal,
+                from_block..=to_block,
+                blocks_per_chunk,
+                &output_dir,
+            )?;
+            println!(
+                "Exported blocks #{from_block}..=#{to_block} into {} chunk file(s) in `{}`",
+                paths.len(),
+                output_dir.display()
+            );
+        }
+        Command::ImportBlocks {
+            input_dir,
+            data_dir,
+            verify_only,
+        } => {
+            let (blocks, mismatches) = read_blocks(&input_dir)?;
+            for mismatch in &mismatches {
+                println!("{mismatch}");
+            }
+            anyhow::ensure!(
+                mismatches.is_empty(),
+                "{} mismatch(es) found in blocks #{}..=#{}",
+                mismatches.len(),
+                blocks[0].number(),
+                blocks[blocks.len() - 1].number()
+            );
+            if verify_only {
+                println!(
+                    "Verified blocks #{}..=#{}",
+                    blocks[0].number(),
+                    blocks[blocks.len() - 1].number()
+                );
+                return Ok(());
+            }
+
+            let data_dir = data_dir.expect("required by clap");
+            let (repository, wal) = open_databases(&data_dir)?;
+            let summary = import_blocks(&blocks, &repository, &wal)?;
+            println!(
+                "Imported blocks into `{}`; written: {:?}, already present: {:?}",
+                data_dir.display(),
+                summary.written,
+                summary.skipped
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,50 @@
+//! Restores node databases from a backup directory populated by the node's backup scheduler.
+
+use clap::Parser;
+use std::path::PathBuf;
+use zksync_os_server::backup::{BackupManifest, restore::restore_backup_set};
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Backup directory (`backup.target_dir` in the node config)
+    #[arg(long)]
+    backup_dir: PathBuf,
+    /// Directory to restore databases into (`general.rocks_db_path` in the node config); must be missing or empty
+    #[arg(long, required_unless_present = "list")]
+    data_dir: Option<PathBuf>,
+    /// Backup set to restore; defaults to the latest set
+    #[arg(long)]
+    set_id: Option<u64>,
+    /// Skip verification of the restored state against L1 on node startup; only use for backups
+    /// made by your own node
+    #[arg(long)]
+    trusted: bool,
+    /// List backup sets instead of restoring
+    #[arg(long)]
+    list: bool,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let _observability_guard = zksync_os_observability::ObservabilityBuilder::new()
+        .with_logs(Some(zksync_os_observability::Logs::default()))
+        .build();
+
+    if args.list {
+        for set in BackupManifest::load(&args.backup_dir)?.sets {
+            println!("{}", serde_json::to_string(&set)?);
+        }
+        return Ok(());
+    }
+
+    let data_dir = args.data_dir.expect("required by clap");
+    let restored = restore_backup_set(&args.backup_dir, args.set_id, &data_dir, args.trusted)?;
+    println!(
+        "Restored backup set {} into `{}`; last blocks: {:?}",
+        restored.set.id,
+        data_dir.display(),
+        restored.block_numbers
+    );
+    Ok(())
+}
@@ -0,0 +1,764 @@
+//! Export of block ranges into flat files and their import into the databases of another node,
+//! used by the `zksync-os-block-transfer` tool.
+//!
+//! Blocks are exported into *chunk files* of up to `blocks_per_chunk` blocks each, named after
+//! the block range they contain. Each chunk file is self-describing and checksummed:
+//!
+//! ```text
+//! magic (8 bytes) | format version: u32 | first block: u64 | block count: u32
+//! block entries
+//! blake2s-256 checksum of all preceding bytes (32 bytes)
+//! ```
+//!
+//! A block entry holds the same encodings the node uses in its databases, so that no data is
+//! converted on export or import:
+//!
+//! - block hash and RLP of the block (header and transaction hashes), as in the repository;
+//! - for each transaction, its EIP-2718 envelope, EIP-2718 receipt and RLP of its metadata,
+//!   as in the repository;
+//! - the replay record, as in the block replay WAL (bincode'd `BlockContext` and transactions).
+//!
+//! Variable-size fields are prefixed with their length; all integers are big-endian.
+//!
+//! Imported blocks are written through [`WriteReplay`] and [`RepositoryDb::write_block`], the same
+//! APIs the node uses. Other databases (state, trees) are not touched: the node catches them up by
+//! replaying the imported blocks from the WAL on startup.
+
+use crate::{BLOCK_REPLAY_WAL_DB_NAME, REPOSITORY_DB_NAME};
+use alloy::consensus::Block;
+use alloy::eips::{Decodable2718, Encodable2718};
+use alloy::primitives::{B256, BlockNumber, Bloom, Sealed, TxHash};
+use alloy::rlp::{Decodable, Encodable};
+use anyhow::Context;
+use blake2::{Blake2s256, Digest};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use zksync_os_storage::db::{BlockReplayStorage, RepositoryDb};
+use zksync_os_storage_api::{
+    ReadReplay, ReadRepository, ReplayRecord, RepositoryBlock, StoredTxData, TxMeta, WriteReplay,
+};
+use zksync_os_types::{ZkEnvelope, ZkReceiptEnvelope, ZkTransaction};
+
+const MAGIC: &[u8; 8] = b"ZKOSBLKS";
+const FORMAT_VERSION: u32 = 1;
+const CHECKSUM_LEN: usize = 32;
+const CHUNK_FILE_EXTENSION: &str = "bin";
+
+/// Block with all its data from the repository and the block replay WAL.
+#[derive(Debug, Clone)]
+pub struct ExportedBlock {
+    pub block: RepositoryBlock,
+    pub transactions: Vec<StoredTxData>,
+    pub replay_record: ReplayRecord,
+}
+
+impl ExportedBlock {
+    fn read(
+        repository: &impl ReadRepository,
+        wal: &impl ReadReplay,
+        number: BlockNumber,
+    ) -> anyhow::Result<Self> {
+        let block = repository
+            .get_block_by_number(number)?
+            .with_context(|| format!("block #{number} is missing in the repository"))?;
+        let transactions =
+            block
+                .body
+                .transactions
+                .iter()
+                .map(|tx_hash| {
+                    repository.get_stored_transaction(*tx_hash)?.with_context(|| {
+                    format!("transaction {tx_hash} of block #{number} is missing in the repository")
+                })
+                })
+                .collect::<anyhow::Result<_>>()?;
+        let replay_record = wal
+            .get_replay_record(number)
+            .with_context(|| format!("block #{number} is missing in the block replay WAL"))?;
+        Ok(Self {
+            block,
+            transactions,
+            replay_record,
+        })
+    }
+
+    pub fn number(&self) -> BlockNumber {
+        self.block.number
+    }
+
+    /// Re-derives data the node derives from transactions and receipts when populating
+    /// the repository, and returns descriptions of mismatches with the stored block.
+    ///
+    /// Block headers don't commit to Ethereum-style transaction and receipt tries, so
+    /// the transaction list (the transaction hashes in the block) is re-derived from
+    /// the transaction envelopes, and the logs bloom from the receipts.
+    pub fn verify(&self) -> Vec<String> {
+        let number = self.number();
+        let mut mismatches = vec![];
+        let tx_hashes: Vec<TxHash> = self.transactions.iter().map(|tx| *tx.tx.hash()).collect();
+        if tx_hashes != self.block.body.transactions {
+            mismatches.push(format!(
+                "block #{number}: transaction hashes {tx_hashes:?} don't match the block ({:?})",
+                self.block.body.transactions
+            ));
+        }
+
+        let mut logs_bloom = Bloom::default();
+        for (index, tx) in self.transactions.iter().enumerate() {
+            logs_bloom.accrue_bloom(tx.receipt.logs_bloom());
+            let meta = &tx.meta;
+            if meta.block_hash != self.block.hash()
+                || meta.block_number != number
+                || meta.tx_index_in_block != index as u64
+            {
+                mismatches.push(format!(
+                    "block #{number}: metadata of transaction {} points to block {} (#{}) at index {}",
+                    tx.tx.hash(),
+                    meta.block_hash,
+                    meta.block_number,
+                    meta.tx_index_in_block
+                ));
+            }
+        }
+        if logs_bloom != self.block.header.logs_bloom {
+            mismatches.push(format!(
+                "block #{number}: logs bloom derived from receipts doesn't match the block header"
+            ));
+        }
+
+        let record_number = self.replay_record.block_context.block_number;
+        if record_number != number {
+            mismatches.push(format!(
+                "block #{number}: replay record is for block #{record_number}"
+            ));
+        }
+        mismatches
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.block.hash().as_slice());
+        let mut block_bytes = Vec::new();
+        self.block.encode(&mut block_bytes);
+        put_bytes(out, &block_bytes);
+
+        out.extend_from_slice(&(self.transactions.len() as u32).to_be_bytes());
+        for tx in &self.transactions {
+            put_bytes(out, &tx.tx.inner.encoded_2718());
+            put_bytes(out, &tx.receipt.encoded_2718());
+            let mut meta_bytes = Vec::new();
+            tx.meta.encode(&mut meta_bytes);
+            put_bytes(out, &meta_bytes);
+        }
+
+        // Encoded the same way as in the block replay WAL
+        let record = &self.replay_record;
+        let context =
+            bincode::serde::encode_to_vec(record.block_context, bincode::config::standard())
+                .expect("Failed to serialize block context");
+        put_bytes(out, &context);
+        out.extend_from_slice(&record.starting_l1_priority_id.to_be_bytes());
+        let transactions =
+            bincode::encode_to_vec(&record.transactions, bincode::config::standard())
+                .expect("Failed to serialize transactions");
+        put_bytes(out, &transactions);
+        out.extend_from_slice(&record.previous_block_timestamp.to_be_bytes());
+        out.extend_from_slice(&record.block_timestamp_millis.to_be_bytes());
+        put_bytes(out, record.node_version.to_string().as_bytes());
+        out.extend_from_slice(record.block_output_hash.as_slice());
+    }
+
+    fn decode(reader: &mut Reader<'_>) -> anyhow::Result<Self> {
+        let hash = B256::from(reader.array::<32>()?);
+        let block = Block::<TxHash>::decode(&mut reader.bytes()?).context("invalid block")?;
+        let block = Sealed::new_unchecked(block, hash);
+
+        let tx_count = reader.u32()?;
+        let mut transactions = Vec::with_capacity(tx_count as usize);
+        for _ in 0..tx_count {
+            let tx: ZkTransaction = ZkEnvelope::decode_2718(&mut reader.bytes()?)
+                .context("invalid transaction")?
+                .try_into_recovered()
+                .map_err(|_| anyhow::anyhow!("transaction is not EC recoverable"))?;
+            let receipt = ZkReceiptEnvelope::decode_2718(&mut reader.bytes()?)
+                .context("invalid transaction receipt")?;
+            let meta =
+                TxMeta::decode(&mut reader.bytes()?).context("invalid transaction metadata")?;
+            transactions.push(StoredTxData { tx, receipt, meta });
+        }
+
+        let (block_context, _) =
+            bincode::serde::decode_from_slice(reader.bytes()?, bincode::config::standard())
+                .context("invalid block context")?;
+        let starting_l1_priority_id = reader.u64()?;
+        let (record_transactions, _) =
+            bincode::decode_from_slice(reader.bytes()?, bincode::config::standard())
+                .context("invalid replay record transactions")?;
+        let previous_block_timestamp = reader.u64()?;
+        let block_timestamp_millis = reader.u64()?;
+        let node_version = std::str::from_utf8(reader.bytes()?)
+            .context("invalid node version")?
+            .parse()
+            .context("invalid node version")?;
+        let block_output_hash = B256::from(reader.array::<32>()?);
+        Ok(Self {
+            block,
+            transactions,
+            replay_record: ReplayRecord {
+                block_context,
+                starting_l1_priority_id,
+                transactions: record_transactions,
+                previous_block_timestamp,
+                block_timestamp_millis,
+                node_version,
+                block_output_hash,
+            },
+        })
+    }
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    out.extend_from_slice(bytes);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        anyhow::ensure!(self.bytes.len() >= len, "unexpected end of chunk");
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> anyhow::Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(u32::from_be_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> anyhow::Result<u64> {
+        Ok(u64::from_be_bytes(self.array()?))
+    }
+
+    fn bytes(&mut self) -> anyhow::Result<&'a [u8]> {
+        let len = self.u32()?;
+        self.take(len as usize)
+    }
+}
+
+/// Encodes `blocks` (consecutive, non-empty) as a chunk file.
+pub fn encode_chunk(blocks: &[ExportedBlock]) -> Vec<u8> {
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&FORMAT_VERSION.to_be_bytes());
+    out.extend_from_slice(&blocks[0].number().to_be_bytes());
+    out.extend_from_slice(&(blocks.len() as u32).to_be_bytes());
+    for block in blocks {
+        block.encode(&mut out);
+    }
+    let checksum = Blake2s256::digest(&out);
+    out.extend_from_slice(&checksum);
+    out
+}
+
+/// Decodes a chunk file, checking its checksum and that it contains consecutive blocks.
+pub fn decode_chunk(bytes: &[u8]) -> anyhow::Result<Vec<ExportedBlock>> {
+    anyhow::ensure!(
+        bytes.len() >= MAGIC.len() + CHECKSUM_LEN && bytes.starts_with(MAGIC),
+        "not a block chunk file"
+    );
+    let (contents, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+    anyhow::ensure!(
+        Blake2s256::digest(contents).as_slice() == checksum,
+        "checksum mismatch; the chunk is corrupted"
+    );
+
+    let mut reader = Reader {
+        bytes: &contents[MAGIC.len()..],
+    };
+    let version = reader.u32()?;
+    anyhow::ensure!(
+        version == FORMAT_VERSION,
+        "unsupported chunk format version {version}, expected {FORMAT_VERSION}"
+    );
+    let first_block = reader.u64()?;
+    let block_count = reader.u32()?;
+    let mut blocks = Vec::with_capacity(block_count as usize);
+    for number in first_block..first_block + u64::from(block_count) {
+        let block = ExportedBlock::decode(&mut reader)
+            .with_context(|| format!("cannot decode block #{number}"))?;
+        anyhow::ensure!(
+            block.number() == number,
+            "expected block #{number}, got #{}",
+            block.number()
+        );
+        blocks.push(block);
+    }
+    anyhow::ensure!(
+        reader.bytes.is_empty(),
+        "unexpected data after the last block"
+    );
+    Ok(blocks)
+}
+
+fn chunk_file_name(blocks: &RangeInclusive<BlockNumber>) -> String {
+    format!(
+        "blocks_{:012}_{:012}.{CHUNK_FILE_EXTENSION}",
+        blocks.start(),
+        blocks.end()
+    )
+}
+
+/// Opens the repository and the block replay WAL in the node data directory
+/// (`general.rocks_db_path` in the node config). The node must not be running.
+pub fn open_databases(data_dir: &Path) -> anyhow::Result<(RepositoryDb, BlockReplayStorage)> {
+    let repository = RepositoryDb::open_existing(&data_dir.join(REPOSITORY_DB_NAME))
+        .with_context(|| format!("no repository in `{}`", data_dir.display()))?;
+    let wal = BlockReplayStorage::open_existing(&data_dir.join(BLOCK_REPLAY_WAL_DB_NAME))
+        .with_context(|| format!("no block replay WAL in `{}`", data_dir.display()))?;
+    Ok((repository, wal))
+}
+
+/// Exports `blocks` into chunk files of up to `blocks_per_chunk` blocks in `output_dir`.
+/// Returns paths of the written files.
+pub fn export_blocks(
+    repository: &impl ReadRepository,
+    wal: &impl ReadReplay,
+    blocks: RangeInclusive<BlockNumber>,
+    blocks_per_chunk: u64,
+    output_dir: &Path,
+) -> anyhow::Result<Vec<PathBuf>> {
+    anyhow::ensure!(blocks_per_chunk > 0, "`blocks_per_chunk` must be positive");
+    anyhow::ensure!(!blocks.is_empty(), "block range {blocks:?} is empty");
+    std::fs::create_dir_all(output_dir).context("cannot create output directory")?;
+
+    let mut paths = vec![];
+    let mut chunk_start = *blocks.start();
+    while chunk_start <= *blocks.end() {
+        let chunk = chunk_start..=(chunk_start + blocks_per_chunk - 1).min(*blocks.end());
+        let exported = chunk
+            .clone()
+            .map(|number| ExportedBlock::read(repository, wal, number))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let path = output_dir.join(chunk_file_name(&chunk));
+        std::fs::write(&path, encode_chunk(&exported))
+            .with_context(|| format!("cannot write `{}`", path.display()))?;
+        tracing::info!(?chunk, path = %path.display(), "exported blocks");
+        paths.push(path);
+        chunk_start = *chunk.end() + 1;
+    }
+    Ok(paths)
+}
+
+/// Reads and validates all chunk files in `input_dir`: checksums, consecutive block numbers,
+/// parent hash linkage and [re-derived block data](ExportedBlock::verify).
+///
+/// Fails on corrupted or missing chunks. Mismatches of re-derived data are returned, so that
+/// they can be reported all at once.
+pub fn read_blocks(input_dir: &Path) -> anyhow::Result<(Vec<ExportedBlock>, Vec<String>)> {
+    let mut paths = std::fs::read_dir(input_dir)
+        .with_context(|| format!("cannot read `{}`", input_dir.display()))?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    paths.retain(|path| {
+        path.extension()
+            .is_some_and(|ext| ext == CHUNK_FILE_EXTENSION)
+    });
+    // Names are zero-padded, so that they are sorted by block number
+    paths.sort();
+    anyhow::ensure!(
+        !paths.is_empty(),
+        "no chunk files in `{}`",
+        input_dir.display()
+    );
+
+    let mut blocks: Vec<ExportedBlock> = vec![];
+    let mut mismatches = vec![];
+    for path in paths {
+        let bytes =
+            std::fs::read(&path).with_context(|| format!("cannot read `{}`", path.display()))?;
+        let chunk =
+            decode_chunk(&bytes).with_context(|| format!("invalid `{}`", path.display()))?;
+        for block in chunk {
+            if let Some(previous) = blocks.last() {
+                anyhow::ensure!(
+                    block.number() == previous.number() + 1,
+                    "`{}`: expected block #{}, got #{}; a chunk is missing",
+                    path.display(),
+                    previous.number() + 1,
+                    block.number()
+                );
+                if block.block.parent_hash != previous.block.hash() {
+                    mismatches.push(format!(
+                        "block #{}: parent hash {} doesn't match the previous block {}",
+                        block.number(),
+                        block.block.parent_hash,
+                        previous.block.hash()
+                    ));
+                }
+            }
+            mismatches.extend(block.verify());
+            blocks.push(block);
+        }
+    }
+    Ok((blocks, mismatches))
+}
+
+/// Outcome of [`import_blocks`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportSummary {
+    /// Imported blocks that were already present in the destination (and are identical).
+    pub skipped: Option<RangeInclusive<BlockNumber>>,
+    /// Written blocks.
+    pub written: Option<RangeInclusive<BlockNumber>>,
+}
+
+/// Imports validated `blocks` (as returned by [`read_blocks`]) into the destination databases.
+///
+/// Blocks already present in the destination must be identical to the imported ones, and
+/// the first written block must extend the destination chain head; otherwise, nothing is written.
+pub fn import_blocks(
+    blocks: &[ExportedBlock],
+    repository: &RepositoryDb,
+    wal: &BlockReplayStorage,
+) -> anyhow::Result<ImportSummary> {
+    let (Some(first), Some(last)) = (blocks.first(), blocks.last()) else {
+        anyhow::bail!("no blocks to import");
+    };
+    let head = repository.get_latest_block();
+    let wal_head = wal.latest_record();
+    anyhow::ensure!(
+        head == wal_head,
+        "destination repository (block #{head}) and block replay WAL (block #{wal_head}) are \
+         at different blocks; start the node to let it catch up before importing"
+    );
+    anyhow::ensure!(
+        first.number() <= head + 1,
+        "imported blocks start at #{}, but the destination chain ends at #{head}",
+        first.number()
+    );
+
+    // Overlapping blocks must match the destination chain
+    let mut new_blocks = blocks;
+    while let Some((block, rest)) = new_blocks.split_first()
+        && block.number() <= head
+    {
+        let existing = repository
+            .get_block_by_number(block.number())?
+            .with_context(|| format!("block #{} is missing in the destination", block.number()))?;
+        anyhow::ensure!(
+            existing.hash() == block.block.hash(),
+            "block #{} conflicts with the destination chain: hash {}, expected {}",
+            block.number(),
+            block.block.hash(),
+            existing.hash()
+        );
+        new_blocks = rest;
+    }
+    let skipped = (new_blocks.len() < blocks.len())
+        .then(|| first.number()..=first.number() + (blocks.len() - new_blocks.len()) as u64 - 1);
+
+    let Some(first_new) = new_blocks.first() else {
+        return Ok(ImportSummary {
+            skipped,
+            written: None,
+        });
+    };
+    let head_block = repository
+        .get_block_by_number(head)?
+        .context("destination chain head is missing")?;
+    anyhow::ensure!(
+        first_new.block.parent_hash == head_block.hash(),
+        "block #{} doesn't extend the destination chain: parent hash {}, head hash {}",
+        first_new.number(),
+        first_new.block.parent_hash,
+        head_block.hash()
+    );
+
+    for block in new_blocks {
+        // The WAL goes first, as in the node
+        let appended = wal.write(block.replay_record.clone(), false);
+        anyhow::ensure!(
+            appended,
+            "block #{} was not appended to the block replay WAL",
+            block.number()
+        );
+        let transactions: Vec<_> = block.transactions.iter().cloned().map(Arc::new).collect();
+        repository.write_block(&block.block, &transactions);
+    }
+    Ok(ImportSummary {
+        skipped,
+        written: Some(first_new.number()..=last.number()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backup::tests::test_genesis;
+    use alloy::consensus::{BlockBody, Header, ReceiptWithBloom};
+    use alloy::primitives::hex;
+    use zksync_os_genesis::Genesis;
+    use zksync_os_types::ZkReceipt;
+
+    // Mainnet tx 0x280cde7cdefe4b188750e76c888f13bd05ce9a4d7767730feefe8a0e50ca6fc4
+    const RAW_TX: &str = "f9015482078b8505d21dba0083022ef1947a250d5630b4cf539739df2c5dacb4c659f2488d880c46549a521b13d8b8e47ff36ab50000000000000000000000000000000000000000000066ab5a608bd00a23f2fe000000000000000000000000000000000000000000000000000000000000008000000000000000000000000048c04ed5691981c42154c6167398f95e8f38a7ff00000000000000000000000000000000000000000000000000000000632ceac70000000000000000000000000000000000000000000000000000000000000002000000000000000000000000c02aaa39b223fe8d0a0e5c4f27ead9083c756cc20000000000000000000000006c6ee5e31d828de241282b9606c8e98ea48526e225a0c9077369501641a92ef7399ff81c21639ed4fd8fc69cb793cfa1dbfab342e10aa0615facb2f1bcf3274a354cfe384a38d0cc008a11c2dd23a69111bc6930ba27a8";
+
+    async fn open_node(data_dir: &Path, genesis: &Genesis) -> (RepositoryDb, BlockReplayStorage) {
+        let wal = BlockReplayStorage::new(
+            &data_dir.join(BLOCK_REPLAY_WAL_DB_NAME),
+            genesis,
+            "0.1.0".parse().unwrap(),
+        )
+        .await;
+        let repository = RepositoryDb::new(&data_dir.join(REPOSITORY_DB_NAME), genesis).await;
+        (repository, wal)
+    }
+
+    /// Writes block `number` on top of the repository head; block 2 contains a transaction.
+    async fn write_block(
+        repository: &RepositoryDb,
+        wal: &BlockReplayStorage,
+        genesis: &Genesis,
+        number: BlockNumber,
+    ) {
+        let parent = repository.get_block_by_number(number - 1).unwrap().unwrap();
+        let hash = B256::repeat_byte(number as u8);
+        let transactions = if number == 2 {
+            let tx = ZkEnvelope::decode_2718(&mut hex::decode(RAW_TX).unwrap().as_slice())
+                .unwrap()
+                .try_into_recovered()
+                .unwrap();
+            let receipt = ZkReceiptEnvelope::Legacy(ReceiptWithBloom {
+                receipt: ZkReceipt {
+                    status: true.into(),
+                    cumulative_gas_used: 21_000,
+                    logs: vec![],
+                    l2_to_l1_logs: vec![],
+                },
+                logs_bloom: Bloom::default(),
+            });
+            let meta = TxMeta {
+                block_hash: hash,
+                block_number: number,
+                block_timestamp: number,
+                tx_index_in_block: 0,
+                effective_gas_price: 100,
+                number_of_logs_before_this_tx: 0,
+                gas_used: 21_000,
+                contract_address: None,
+            };
+            vec![StoredTxData { tx, receipt, meta }]
+        } else {
+            vec![]
+        };
+
+        let mut block_context = genesis.state().await.unwrap().context;
+        block_context.block_number = number;
+        block_context.timestamp = number;
+        wal.write(
+            ReplayRecord {
+                block_context,
+                starting_l1_priority_id: 0,
+                transactions: transactions.iter().map(|tx| tx.tx.clone()).collect(),
+                previous_block_timestamp: number - 1,
+                block_timestamp_millis: number * 1_000,
+                node_version: "0.1.0".parse().unwrap(),
+                block_output_hash: B256::repeat_byte(0xff),
+            },
+            false,
+        );
+        let block = Block {
+            header: Header {
+                number,
+                timestamp: number,
+                parent_hash: parent.hash(),
+                ..Header::default()
+            },
+            body: BlockBody {
+                transactions: transactions.iter().map(|tx| *tx.tx.hash()).collect(),
+                ommers: vec![],
+                withdrawals: None,
+            },
+        };
+        let transactions: Vec<_> = transactions.into_iter().map(Arc::new).collect();
+        repository.write_block(&Sealed::new_unchecked(block, hash), &transactions);
+    }
+
+    #[tokio::test]
+    async fn blocks_are_transplanted_between_nodes() {
+        let dir = tempfile::tempdir().unwrap();
+        let genesis = test_genesis();
+        let (source_repository, source_wal) = open_node(&dir.path().join("source"), &genesis).await;
+        for number in 1..=5 {
+            write_block(&source_repository, &source_wal, &genesis, number).await;
+        }
+        let (repository, wal) = open_node(&dir.path().join("destination"), &genesis).await;
+        write_block(&repository, &wal, &genesis, 1).await;
+        drop((repository, wal));
+
+        let export_dir = dir.path().join("export");
+        let paths = export_blocks(&source_repository, &source_wal, 1..=5, 2, &export_dir).unwrap();
+        let names: Vec<_> = paths
+            .iter()
+            .map(|path| path.file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "blocks_000000000001_000000000002.bin",
+                "blocks_000000000003_000000000004.bin",
+                "blocks_000000000005_000000000005.bin"
+            ]
+        );
+
+        let (blocks, mismatches) = read_blocks(&export_dir).unwrap();
+        assert!(mismatches.is_empty(), "{mismatches:?}");
+        assert_eq!(blocks.len(), 5);
+        assert_eq!(blocks[1].transactions.len(), 1);
+
+        let (repository, wal) = open_databases(&dir.path().join("destination")).unwrap();
+        let summary = import_blocks(&blocks, &repository, &wal).unwrap();
+        assert_eq!(
+            summary,
+            ImportSummary {
+                skipped: Some(1..=1),
+                written: Some(2..=5)
+            }
+        );
+        assert_eq!(repository.get_latest_block(), 5);
+        assert_eq!(wal.latest_record(), 5);
+
+        // The destination now holds the same data, so it's exported identically
+        let reexport_dir = dir.path().join("reexport");
+        export_blocks(&repository, &wal, 1..=5, 2, &reexport_dir).unwrap();
+        for name in names {
+            assert_eq!(
+                std::fs::read(export_dir.join(name)).unwrap(),
+                std::fs::read(reexport_dir.join(name)).unwrap(),
+                "{name}"
+            );
+        }
+        let tx_hash = blocks[1].block.body.transactions[0];
+        assert_eq!(
+            repository
+                .get_transaction_meta(tx_hash)
+                .unwrap()
+                .unwrap()
+                .block_number,
+            2
+        );
+
+        // Importing again is a no-op
+        let summary = import_blocks(&blocks, &repository, &wal).unwrap();
+        assert_eq!(summary.written, None);
+        assert_eq!(summary.skipped, Some(1..=5));
+    }
+
+    #[tokio::test]
+    async fn conflicting_and_detached_blocks_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let genesis = test_genesis();
+        let (source_repository, source_wal) = open_node(&dir.path().join("source"), &genesis).await;
+        for number in 1..=3 {
+            write_block(&source_repository, &source_wal, &genesis, number).await;
+        }
+        let export_dir = dir.path().join("export");
+        export_blocks(&source_repository, &source_wal, 2..=3, 10, &export_dir).unwrap();
+        let (blocks, _) = read_blocks(&export_dir).unwrap();
+
+        // There's a gap between the destination head and the imported blocks
+        let (repository, wal) = open_node(&dir.path().join("empty"), &genesis).await;
+        let err = import_blocks(&blocks, &repository, &wal).unwrap_err();
+        assert!(err.to_string().contains("chain ends at #0"), "{err:#}");
+
+        // The imported blocks don't extend the destination head
+        let (repository, wal) = open_node(&dir.path().join("forked"), &genesis).await;
+        write_block(&repository, &wal, &genesis, 1).await;
+        let mut detached = blocks.clone();
+        let (mut block, hash) = detached[0].block.clone().into_parts();
+        block.header.parent_hash = B256::ZERO;
+        detached[0].block = Sealed::new_unchecked(block, hash);
+        let err = import_blocks(&detached, &repository, &wal).unwrap_err();
+        assert!(err.to_string().contains("doesn't extend"), "{err:#}");
+
+        // The destination has a different block 2
+        write_block(&repository, &wal, &genesis, 2).await;
+        let mut forked = blocks.clone();
+        forked[0].block = Sealed::new_unchecked(forked[0].block.clone().into_inner(), B256::ZERO);
+        let err = import_blocks(&forked, &repository, &wal).unwrap_err();
+        assert!(err.to_string().contains("conflicts"), "{err:#}");
+        assert_eq!(repository.get_latest_block(), 2);
+    }
+
+    #[tokio::test]
+    async fn corrupted_chunk_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let genesis = test_genesis();
+        let (repository, wal) = open_node(&dir.path().join("source"), &genesis).await;
+        for number in 1..=4 {
+            write_block(&repository, &wal, &genesis, number).await;
+        }
+        let export_dir = dir.path().join("export");
+        let paths = export_blocks(&repository, &wal, 1..=4, 2, &export_dir).unwrap();
+
+        let mut bytes = std::fs::read(&paths[1]).unwrap();
+        bytes[100] ^= 1;
+        std::fs::write(&paths[1], &bytes).unwrap();
+        let err = read_blocks(&export_dir).unwrap_err();
+        let err = format!("{err:#}");
+        assert!(err.contains("checksum mismatch"), "{err}");
+        assert!(
+            err.contains("blocks_000000000003_000000000004.bin"),
+            "{err}"
+        );
+
+        // A missing chunk breaks the block sequence
+        std::fs::remove_file(&paths[1]).unwrap();
+        export_blocks(&repository, &wal, 4..=4, 2, &export_dir).unwrap();
+        let err = read_blocks(&export_dir).unwrap_err();
+        assert!(format!("{err:#}").contains("a chunk is missing"), "{err:#}");
+    }
+
+    #[tokio::test]
+    async fn verification_reports_mismatches() {
+        let dir = tempfile::tempdir().unwrap();
+        let genesis = test_genesis();
+        let (repository, wal) = open_node(dir.path(), &genesis).await;
+        for number in 1..=3 {
+            write_block(&repository, &wal, &genesis, number).await;
+        }
+        let mut blocks: Vec<_> = (1..=3)
+            .map(|number| ExportedBlock::read(&repository, &wal, number).unwrap())
+            .collect();
+        assert!(blocks.iter().all(|block| block.verify().is_empty()));
+
+        // Data not matching the block, e.g. written by a buggy node
+        blocks[1].transactions[0].meta.tx_index_in_block = 1;
+        blocks[1].replay_record.block_context.block_number = 1;
+        let mut header = blocks[2].block.header.clone();
+        header.logs_bloom = Bloom::repeat_byte(1);
+        blocks[2].block = Sealed::new_unchecked(
+            Block {
+                header,
+                body: BlockBody::default(),
+            },
+            blocks[2].block.hash(),
+        );
+        let export_dir = dir.path().join("export");
+        std::fs::create_dir(&export_dir).unwrap();
+        std::fs::write(export_dir.join("blocks.bin"), encode_chunk(&blocks)).unwrap();
+
+        let (_, mismatches) = read_blocks(&export_dir).unwrap();
+        assert_eq!(mismatches.len(), 3, "{mismatches:#?}");
+        assert!(mismatches[0].contains("metadata of transaction"));
+        assert!(mismatches[1].contains("replay record is for block #1"));
+        assert!(mismatches[2].contains("logs bloom"));
+    }
+}
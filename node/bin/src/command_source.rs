@@ -1,13 +1,21 @@
-use crate::replay_transport::replay_receiver;
+use crate::replay_transport::{PeerFallback, replay_receiver};
+use anyhow::Context;
 use async_trait::async_trait;
 use futures::StreamExt;
 use futures::stream::BoxStream;
 use std::collections::HashSet;
 use std::time::Duration;
 use tokio::sync::mpsc;
+use tokio::time::Instant;
 use zksync_os_pipeline::{PeekableReceiver, PipelineComponent};
 use zksync_os_sequencer::model::blocks::{BlockCommand, ProduceCommand, RebuildCommand};
-use zksync_os_storage_api::{ReadReplay, ReadReplayExt};
+use zksync_os_socket::ConnectOptions;
+use zksync_os_storage_api::{ReadReplay, ReadReplayExt, ReadRepository};
+
+/// How often an external node with peer fallback retries connecting to the main node.
+const MAIN_NODE_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+/// Max time to connect to the main node when peer fallback is configured.
+const MAIN_NODE_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// Main node command source
 #[derive(Debug)]
@@ -26,10 +34,14 @@ pub struct RebuildOptions {
 }
 
 /// External node command source
-#[derive(Debug)]
-pub struct ExternalNodeCommandSource {
+pub struct ExternalNodeCommandSource<Replay, Repository> {
     pub starting_block: u64,
     pub replay_download_address: String,
+    /// Max size of a replay record received from the main node.
+    pub max_frame_bytes: usize,
+    /// Fetches blocks from other nodes while the main node is unavailable. Without it, the source
+    /// fails as soon as the main node's replay stream does.
+    pub peer_fallback: Option<PeerFallback<Replay, Repository>>,
 }
 
 #[async_trait]
@@ -67,7 +79,11 @@ impl<Replay: ReadReplay> PipelineComponent for MainNodeCommandSource<Replay> {
 }
 
 #[async_trait]
-impl PipelineComponent for ExternalNodeCommandSource {
+impl<Replay, Repository> PipelineComponent for ExternalNodeCommandSource<Replay, Repository>
+where
+    Replay: ReadReplay,
+    Repository: ReadRepository,
+{
     type Input = ();
     type Output = BlockCommand;
 
@@ -75,24 +91,89 @@ impl PipelineComponent for ExternalNodeCommandSource {
     const OUTPUT_BUFFER_SIZE: usize = 5;
 
     async fn run(
-        self,
+        mut self,
         _input: PeekableReceiver<()>,
         output: mpsc::Sender<BlockCommand>,
     ) -> anyhow::Result<()> {
+        let mut next_block = self.starting_block;
+        let mut main_node_down_since = None;
+        loop {
+            let result = self
+                .stream_from_main_node(&mut next_block, &mut main_node_down_since, &output)
+                .await;
+            if output.is_closed() {
+                tracing::warn!("Command output channel closed, stopping source");
+                return Ok(());
+            }
+            let Some(peer_fallback) = &mut self.peer_fallback else {
+                return result.inspect_err(|err| {
+                    tracing::error!(?err, "Failed to receive blocks from main node");
+                });
+            };
+            match result {
+                Ok(()) => tracing::warn!(next_block, "Main node closed the replay stream"),
+                Err(err) => tracing::warn!(next_block, "Main node is unavailable: {err:#}"),
+            }
+
+            let down_since = *main_node_down_since.get_or_insert_with(Instant::now);
+            if down_since.elapsed() < peer_fallback.fallback_after()
+                || peer_fallback
+                    .sync_from_peers(&mut next_block, &output)
+                    .await?
+                    == 0
+            {
+                tokio::time::sleep(MAIN_NODE_RETRY_INTERVAL).await;
+            }
+        }
+    }
+}
+
+impl<Replay, Repository> ExternalNodeCommandSource<Replay, Repository>
+where
+    Replay: ReadReplay,
+    Repository: ReadRepository,
+{
+    /// Forwards blocks from the main node's replay stream starting from `next_block`, until the
+    /// stream ends or fails. Resets `main_node_down_since` once a block is received.
+    async fn stream_from_main_node(
+        &self,
+        next_block: &mut u64,
+        main_node_down_since: &mut Option<Instant>,
+        output: &mpsc::Sender<BlockCommand>,
+    ) -> anyhow::Result<()> {
+        // With peer fallback, connection failures are handled by retrying in the main loop
+        let connect_options = if self.peer_fallback.is_some() {
+            ConnectOptions {
+                max_attempts: Some(0),
+                deadline: Some(MAIN_NODE_CONNECT_TIMEOUT),
+                ..ConnectOptions::default()
+            }
+        } else {
+            ConnectOptions::default()
+        };
         // TODO: no need for a Stream in `replay_receiver` - just send to channel right away instead
-        let mut stream = replay_receiver(self.starting_block, self.replay_download_address.clone())
-            .await
-            .map_err(|err| {
-                tracing::error!(?err, "Failed to connect to main node to receive blocks");
-                err
-            })?;
+        let mut stream = replay_receiver(
+            *next_block,
+            self.replay_download_address.clone(),
+            self.max_frame_bytes,
+            &connect_options,
+        )
+        .await
+        .context("Failed to connect to main node to receive blocks")?;
 
         while let Some(command) = stream.next().await {
+            let command = command.context("Failed to receive block from main node")?;
             tracing::debug!(?command, "Received block command from main node");
+            if main_node_down_since.take().is_some() {
+                tracing::info!(next_block = *next_block, "Main node replay stream is back");
+                if let Some(peer_fallback) = &self.peer_fallback {
+                    peer_fallback.on_main_node_back();
+                }
+            }
             if output.send(command).await.is_err() {
-                tracing::warn!("Command output channel closed, stopping source");
-                break;
+                return Ok(());
             }
+            *next_block += 1;
         }
 
         Ok(())
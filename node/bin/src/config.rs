@@ -1,23 +1,34 @@
 use crate::command_source::RebuildOptions;
 use alloy::consensus::constants::GWEI_TO_WEI;
-use alloy::primitives::{Address, U128};
+use alloy::primitives::{Address, B256, U128};
+use alloy::signers::local::PrivateKeySigner;
 use serde::{Deserialize, Serialize};
 use smart_config::metadata::TimeUnit;
-use smart_config::value::SecretString;
+use smart_config::value::{ExposeSecret, SecretString};
 use smart_config::{
     DescribeConfig, DeserializeConfig, Serde,
     de::{Delimited, Optional},
 };
-use std::{path::PathBuf, time::Duration};
+use std::collections::{BTreeMap, HashMap};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 use zksync_os_batch_verification;
 use zksync_os_contract_interface::models::BatchDaInputMode;
 use zksync_os_l1_sender::commands::commit::CommitCommand;
 use zksync_os_l1_sender::commands::execute::ExecuteCommand;
 use zksync_os_l1_sender::commands::prove::ProofCommand;
 use zksync_os_mempool::SubPoolLimit;
+use zksync_os_multivm::ExecutionVersion;
 use zksync_os_object_store::ObjectStoreConfig;
 use zksync_os_observability::LogFormat;
 use zksync_os_observability::opentelemetry::OpenTelemetryLevel;
+use zksync_os_sequencer::config::DumpDetailLevel;
+use zksync_os_sequencer::execution::shadow::ShadowExecutionConfig;
+use zksync_os_socket::{KeepaliveConfig, ListenerLimits, TlsConfig, TlsIdentity, TlsServerConfig};
 
 /// Configuration for the sequencer node.
 /// Includes configurations of all subsystems.
@@ -36,9 +47,11 @@ pub struct Config {
     pub prover_input_generator_config: ProverInputGeneratorConfig,
     pub prover_api_config: ProverApiConfig,
     pub status_server_config: StatusServerConfig,
+    pub admin_api_config: AdminApiConfig,
     pub observability_config: ObservabilityConfig,
     pub gas_adjuster_config: GasAdjusterConfig,
     pub batch_verification_config: BatchVerificationConfig,
+    pub backup_config: BackupConfig,
 }
 
 /// "Umbrella" config for the node.
@@ -74,6 +87,25 @@ pub struct GeneralConfig {
     #[config(with = Serde![str])]
     pub state_backend: StateBackendConfig,
 
+    /// Whether to rebuild the Merkle tree from genesis if its database is corrupted or inconsistent
+    /// with the block replay storage. The tree catches up by replaying all blocks on startup,
+    /// so this requires the `FullDiffs` state backend. If disabled, the node refuses to start instead.
+    #[config(default_t = false)]
+    pub tree_rebuild_on_corruption: bool,
+
+    /// Whether to maintain a reverse index from account addresses to the flat storage keys written
+    /// for them (`account_keys` DB), exposed via `admin_getStorageKeysForAccount`. The index is
+    /// not part of the consensus state. Blocks produced before it's enabled are backfilled in the
+    /// background by re-executing them, which requires the `FullDiffs` state backend.
+    #[config(default_t = false)]
+    pub account_keys_index_enabled: bool,
+
+    /// Whether to enable RocksDB statistics for all databases. Statistics are exported as
+    /// `rocksdb_statistics_*` metrics (block cache hits and misses, write stall time,
+    /// compaction I/O) and have a performance overhead.
+    #[config(default_t = false)]
+    pub rocksdb_statistics: bool,
+
     /// Min number of blocks to retain in memory
     /// it defines the blocks for which the node can handle API requests
     /// older blocks will be compacted into RocksDb - and thus unavailable for `eth_call`.
@@ -83,9 +115,23 @@ pub struct GeneralConfig {
     #[config(default_t = 512)]
     pub blocks_to_retain_in_memory: usize,
 
+    /// Retention of transactions, receipts and transaction metadata in the repository DB.
+    /// Default: all history is kept (archive node).
+    #[config(nest, default)]
+    pub repository_retention: RepositoryRetentionConfig,
+
     /// If set - initialize the configs based off the values from the yaml files from that directory.
     pub zkstack_cli_config_dir: Option<String>,
 
+    /// YAML config file with sections as top-level keys (e.g. `mempool: { ... }`). Values from the
+    /// file take precedence over env variables. Hot-reloadable fields (see `config_reload` module)
+    /// are re-applied when the file changes or on `admin_reloadConfig`.
+    pub config_file_path: Option<PathBuf>,
+
+    /// How often the config file is checked for changes.
+    #[config(default_t = Duration::from_secs(10))]
+    pub config_reload_interval: Duration,
+
     /// **IMPORTANT: It must be set for an external node. However, setting this DOES NOT make the node into an external node.
     /// `SequencerConfig::block_replay_download_address` is the source of truth for node type. **
     #[config(default_t = None)]
@@ -98,6 +144,35 @@ pub enum StateBackendConfig {
     Compacted,
 }
 
+#[derive(Debug, Clone, PartialEq, DescribeConfig, DeserializeConfig)]
+pub struct RepositoryRetentionConfig {
+    #[config(flatten)]
+    pub mode: RepositoryRetentionMode,
+}
+
+impl Default for RepositoryRetentionConfig {
+    fn default() -> Self {
+        Self {
+            mode: RepositoryRetentionMode::Archive,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, DescribeConfig, DeserializeConfig)]
+#[config(tag = "mode")]
+pub enum RepositoryRetentionMode {
+    /// Transactions of all blocks are kept.
+    #[config(default)]
+    Archive,
+    /// Transactions of blocks executed on L1 are pruned once they are older than `keep_blocks`
+    /// latest blocks. Block headers are kept; RPC requests for pruned transactions fail with
+    /// a "pruned history" error.
+    Pruned {
+        /// Number of latest blocks whose transactions are kept.
+        keep_blocks: u64,
+    },
+}
+
 #[derive(Clone, Debug, DescribeConfig, DeserializeConfig)]
 #[config(derive(Default))]
 pub struct GenesisConfig {
@@ -114,6 +189,37 @@ pub struct GenesisConfig {
     /// Path to the file with genesis input.
     #[config(with = Optional(Serde![int]), default_t = Some("./genesis/genesis.json".into()))]
     pub genesis_input_path: Option<PathBuf>,
+
+    /// URL to fetch genesis input from instead of `genesis_input_path`. Requires `genesis_input_sha256`.
+    pub genesis_input_url: Option<String>,
+
+    /// SHA-256 checksum of the file at `genesis_input_url`. The node refuses to start if the fetched file
+    /// doesn't match it.
+    #[config(default_t = None, with = Optional(Serde![str]))]
+    pub genesis_input_sha256: Option<B256>,
+
+    /// Where the file fetched from `genesis_input_url` is cached, so that restarts don't need the URL
+    /// to be reachable. Defaults to `genesis_input.json` in `rocks_db_path`.
+    pub genesis_input_cache_path: Option<PathBuf>,
+
+    /// L1 block to look up the `GenesisUpgrade` event of the chain in. By default, the block the chain was deployed
+    /// in is used, and the latest event is picked if there are several (e.g. if the chain was re-initialized).
+    pub genesis_upgrade_block_hint: Option<u64>,
+
+    /// Whether to skip the startup sanity check of the L1 contracts (chain ID and bridgehub stored in the diamond
+    /// proxy, genesis batch, facet code hashes). Only meant for air-gapped or replay-only nodes.
+    #[config(default_t = false)]
+    pub skip_l1_validation: bool,
+
+    /// Whether to start even if the genesis root computed from genesis input doesn't match its `genesis_root`.
+    /// Only meant for dev chains; batches of such a chain cannot be committed to L1.
+    #[config(default_t = false)]
+    pub allow_unverified_genesis: bool,
+
+    /// Known-good code hashes of the diamond proxy facets. If non-empty, the node refuses to start if any facet
+    /// has a code hash outside of this list.
+    #[config(default, with = Delimited(","))]
+    pub allowed_facet_code_hashes: Vec<String>,
 }
 
 #[derive(Clone, Debug, DescribeConfig, DeserializeConfig)]
@@ -122,6 +228,52 @@ pub struct StatusServerConfig {
     /// Status server address to listen on.
     #[config(default_t = "0.0.0.0:3071".into())]
     pub address: String,
+    /// Max time to respond to a status request.
+    #[config(default_t = Duration::from_secs(10))]
+    pub request_timeout: Duration,
+}
+
+#[derive(Clone, Debug, DescribeConfig, DeserializeConfig)]
+#[config(derive(Default))]
+pub struct AdminApiConfig {
+    /// Whether to run the admin JSON-RPC API (operational actions, see `admin` module).
+    #[config(default_t = false)]
+    pub enabled: bool,
+    /// Admin API address to listen on. Must not be exposed publicly.
+    #[config(default_t = "127.0.0.1:3073".into())]
+    pub address: String,
+    /// Bearer token required from admin API callers. Required if the admin API is enabled.
+    /// Accepts `env:` / `file:` references.
+    pub auth_token: Option<SecretString>,
+    /// Path of the append-only audit log. Defaults to `admin_audit_log.jsonl` in `rocks_db_path`.
+    pub audit_log_path: Option<PathBuf>,
+    /// Max time to receive an admin API request and respond to it.
+    #[config(default_t = Duration::from_secs(30))]
+    pub request_timeout: Duration,
+    /// Max body size of admin API requests in bytes.
+    #[config(default_t = 64 * 1024)]
+    pub max_request_body_bytes: usize,
+}
+
+#[derive(Clone, Debug, DescribeConfig, DeserializeConfig)]
+#[config(derive(Default))]
+pub struct BackupConfig {
+    /// Whether to periodically back up node databases (see `backup` module).
+    #[config(default_t = false)]
+    pub enabled: bool,
+    /// Directory to store backups in. Required if backups are enabled.
+    /// Should be on a different disk than `rocks_db_path`.
+    pub target_dir: Option<PathBuf>,
+    /// Interval between backups. The first backup is taken one interval after startup.
+    #[config(default_t = 6 * TimeUnit::Hours)]
+    pub interval: Duration,
+    /// Number of most recent backup sets to keep.
+    #[config(default_t = 4)]
+    pub retention: usize,
+    /// Whether to verify checksums of every backup by restoring it into a scratch directory
+    /// in `target_dir`. Doubles the IO of backups.
+    #[config(default_t = true)]
+    pub verify_checksums: bool,
 }
 
 #[derive(Clone, Debug, DescribeConfig, DeserializeConfig)]
@@ -149,11 +301,85 @@ pub struct SequencerConfig {
     #[config(default_t = "0.0.0.0:3053".into())]
     pub block_replay_server_address: String,
 
+    /// Max number of replay server subscribers that read historical (catch-up) records from storage
+    /// at the same time. Subscribers that are in sync with the head are not limited.
+    #[config(default_t = 2)]
+    pub block_replay_server_max_backfill_readers: usize,
+
+    /// Max number of replay records per second sent to a single catching-up subscriber.
+    /// `None` means unlimited.
+    #[config(default_t = None)]
+    pub block_replay_server_max_records_per_second: Option<u64>,
+
+    /// Max number of replay bytes per second sent to a single catching-up subscriber.
+    /// `None` means unlimited.
+    #[config(default_t = None)]
+    pub block_replay_server_max_bytes_per_second: Option<u64>,
+
+    /// Number of recently encoded replay records shared between replay server subscribers.
+    #[config(default_t = 1024)]
+    pub block_replay_server_cache_size: usize,
+
+    /// Max number of replay server subscribers connected at the same time. Connections over the cap
+    /// are closed right away.
+    #[config(default_t = 256)]
+    pub block_replay_server_max_connections: usize,
+
+    /// Max time for a replay subscriber to send its handshake and start block after connecting.
+    #[config(default_t = Duration::from_secs(10))]
+    pub block_replay_server_handshake_timeout: Duration,
+
+    /// Max size of an encoded replay record in bytes, enforced both by the replay server and by
+    /// external nodes receiving replays. Must fit the largest block the sequencer produces;
+    /// larger records cannot be synced.
+    #[config(default_t = 64 * 1024 * 1024)]
+    pub block_replay_max_frame_bytes: usize,
+
+    /// Whether to serve block replays to other nodes over HTTP (`GET /sync/replay-records`), so that
+    /// external nodes can sync from this node while the main node is unavailable. Responses are
+    /// paced by the `block_replay_server_max_*_per_second` limits, shared by all requests.
+    #[config(default_t = false)]
+    pub peer_sync_server_enabled: bool,
+
+    /// Address of the peer sync HTTP API.
+    #[config(default_t = "0.0.0.0:3055".into())]
+    pub peer_sync_server_address: String,
+
+    /// Bearer token required from peers by the peer sync API, also sent to peers when fetching
+    /// blocks from them. The API is open if not set. Accepts `env:` / `file:` references.
+    pub peer_sync_auth_token: Option<SecretString>,
+
+    /// Max number of replay records served or requested in a single peer sync request.
+    #[config(default_t = 256)]
+    pub peer_sync_max_records_per_request: u64,
+
+    /// Max time to serve or fetch a single peer sync request.
+    #[config(default_t = Duration::from_secs(30))]
+    pub peer_sync_request_timeout: Duration,
+
+    /// Base URLs of the peer sync APIs of other nodes (e.g. `http://en-1:3055`) to fetch blocks from
+    /// while the main node's replay stream is unavailable. Fetched blocks are only applied if they
+    /// link to the locally executed chain. Only affects External Nodes.
+    #[config(default, with = Delimited(","))]
+    pub peer_sync_urls: Vec<String>,
+
+    /// How long the main node's replay stream must be unavailable before blocks are fetched
+    /// from `peer_sync_urls`.
+    #[config(default_t = Duration::from_secs(30))]
+    pub peer_sync_fallback_after: Duration,
+
     /// Defines the block time for the sequencer.
     /// One of the block Seal Criteria. Only affects the Main Node.
     #[config(default_t = Duration::from_millis(250))]
     pub block_time: Duration,
 
+    /// Require strictly increasing block timestamps. If set, the sequencer waits for the next second
+    /// before producing a block, so effective block time is at least 1s. Otherwise, consecutive blocks
+    /// may share a timestamp (in seconds); they are still ordered by their timestamps in milliseconds.
+    /// Only affects the Main Node.
+    #[config(default_t = false)]
+    pub strict_block_timestamps: bool,
+
     /// Max number of transactions in a block.
     /// One of the block Seal Criteria. Only affects the Main Node.
     #[config(default_t = 1000)]
@@ -173,6 +399,39 @@ pub struct SequencerConfig {
     #[config(default_t = "./db/block_dumps".into())]
     pub block_dump_path: PathBuf,
 
+    /// How much of the failed block is written to block dumps:
+    /// `full` (complete transactions), `redacted` (transaction summaries with calldata replaced by
+    /// its hash and length; the error message is kept as is) or `minimal` (block context,
+    /// transaction hashes and the error only).
+    #[config(default_t = DumpDetailLevel::Full, with = Serde![str])]
+    pub dump_detail_level: DumpDetailLevel,
+
+    /// Hard size limit of a single block dump in bytes.
+    /// Larger dumps are truncated section by section: full transactions, redacted transactions,
+    /// transaction hashes and finally the error message. Block context is always kept.
+    #[config(default_t = 256 * 1024 * 1024)]
+    pub max_dump_bytes: u64,
+
+    /// Whether to record decision traces of produced blocks: hashes of transactions considered
+    /// for the block in mempool order, with the decision taken for each of them (included,
+    /// skipped, purged or deferred) and block counters at that point. No calldata is recorded.
+    /// Traces are written to `block_traces` inside `block_dump_path`.
+    #[config(default_t = false)]
+    pub block_traces_enabled: bool,
+
+    /// Number of most recent block traces to keep.
+    #[config(default_t = 1000)]
+    pub max_block_traces: usize,
+
+    /// What an external node does if a replayed block output differs from the replay record.
+    /// By default, the node saves a block dump and crashes. If set, the node instead halts replay
+    /// at the diverged block (still serving state as of the previous block and reporting itself
+    /// as not ready), saves a block dump with the divergence evidence and waits for the operator
+    /// to resolve the divergence via `admin_resolveReplayDivergence`.
+    /// Only affects External Nodes.
+    #[config(default_t = false)]
+    pub quarantine_on_replay_divergence: bool,
+
     /// Address that receives the transaction fees.
     #[config(with = Serde![str], default_t = "0x36615Cf349d7F6344891B1e7CA7C72883F5dc049".parse().unwrap())]
     pub fee_collector_address: Address,
@@ -199,6 +458,30 @@ pub struct SequencerConfig {
     #[config(default_t = None)]
     pub max_blocks_to_produce: Option<u64>,
 
+    /// Number of best mempool transactions to pre-execute against the head state while the
+    /// sequencer waits for the next block. This only warms up storage and preimage caches used by
+    /// the real execution; results are discarded. `0` disables the warm-up.
+    /// Only affects the Main Node.
+    #[config(default_t = 0)]
+    pub warm_up_max_txs: usize,
+
+    /// Max number of blocking tasks used for the speculative warm-up.
+    #[config(default_t = 2)]
+    pub warm_up_concurrency: usize,
+
+    /// Addresses allowed to sign transaction bundles submitted via `admin_submitBundle`.
+    /// Bundles are disabled if empty. Only affects the Main Node.
+    #[config(default, with = Delimited(","))]
+    pub bundle_signers: Vec<String>,
+
+    /// Max number of bundles waiting for inclusion; new bundles are rejected once reached.
+    #[config(default_t = 64)]
+    pub max_pending_bundles: usize,
+
+    /// Max number of transactions in a single bundle.
+    #[config(default_t = 8)]
+    pub max_bundle_transactions: usize,
+
     /// Enable REVM consistency checker.
     /// If enabled, an additional pipeline process will be executed after the sequencer.
     /// The process re-executes transactions on the REVM client and checks state diff consistency.
@@ -208,6 +491,23 @@ pub struct SequencerConfig {
     #[config(default_t = false)]
     pub revm_consistency_checker_enabled: bool,
 
+    /// Execution version to shadow-execute blocks with, e.g. the next version before activating it.
+    /// If set, every executed block is re-executed with this version in the background and its
+    /// output is compared with the output of the active version. Divergences are reported in
+    /// metrics, logs, `/debug/status` and as reports in `{block_dump_path}/shadow_execution`.
+    /// Shadow execution never affects state or block production; blocks are skipped if it
+    /// falls behind. Disabled by default.
+    #[config(default_t = None)]
+    pub shadow_execution_version: Option<u32>,
+
+    /// Max number of blocks waiting for shadow execution; further blocks are skipped.
+    #[config(default_t = 16)]
+    pub shadow_execution_queue_size: usize,
+
+    /// Max number of detailed shadow divergence reports written to disk.
+    #[config(default_t = 10)]
+    pub shadow_execution_max_reports: usize,
+
     /// Block rebuild options.
     #[config(nest)]
     pub block_rebuild: Option<RebuildBlocksConfig>,
@@ -217,6 +517,18 @@ impl SequencerConfig {
     pub fn is_main_node(&self) -> bool {
         self.block_replay_download_address.is_none()
     }
+
+    pub fn shadow_execution_config(&self) -> Option<ShadowExecutionConfig> {
+        let version = self.shadow_execution_version?;
+        Some(ShadowExecutionConfig {
+            execution_version: ExecutionVersion::try_from(version).unwrap_or_else(|_| {
+                panic!("`sequencer.shadow_execution_version` {version} is not supported")
+            }),
+            queue_size: self.shadow_execution_queue_size,
+            max_reports: self.shadow_execution_max_reports,
+            report_path: self.block_dump_path.join("shadow_execution"),
+        })
+    }
 }
 
 #[derive(Clone, Debug, DescribeConfig, DeserializeConfig)]
@@ -253,6 +565,63 @@ pub struct RpcConfig {
     /// Duration since the last filter poll, after which the filter is considered stale
     #[config(default_t = 15 * TimeUnit::Minutes)]
     pub stale_filter_ttl: Duration,
+
+    /// Maximum number of blocks returned by `eth_feeHistory`; larger requests are capped
+    #[config(default_t = 1_024)]
+    pub max_fee_history_blocks: u64,
+
+    /// Maximum number of blocks simulated by a single `eth_simulateV1` request
+    #[config(default_t = 256)]
+    pub simulate_max_blocks: u64,
+
+    /// Maximum number of calls in a single `eth_simulateV1` request
+    #[config(default_t = 1_000)]
+    pub simulate_max_calls: usize,
+
+    /// Maximum total gas limit of calls in a single `eth_simulateV1` request
+    #[config(default_t = 500_000_000)]
+    pub simulate_max_gas: u64,
+
+    /// Whether to check that blocks are fully indexed in the repository (i.e., that their
+    /// transactions, receipts and index entries are present) before serving them. Debug option.
+    #[config(default_t = false)]
+    pub verify_block_completeness: bool,
+
+    /// IP addresses of proxies in front of the node (e.g. load balancers). `X-Forwarded-For`
+    /// entries appended by these proxies are used to determine the client IP for spam scoring;
+    /// for requests from other peers, the header is ignored.
+    #[config(default, with = Delimited(","))]
+    pub trusted_proxies: Vec<String>,
+
+    /// Sequencer-signed preconfirmations of accepted transactions.
+    #[config(nest, default)]
+    pub preconfirmations: PreconfirmationConfig,
+}
+
+#[derive(Clone, Debug, DescribeConfig, DeserializeConfig)]
+#[config(derive(Default))]
+pub struct PreconfirmationConfig {
+    /// Whether to sign preconfirmations for transactions accepted to the mempool
+    /// (served via `zks_sendRawTransactionWithPreconfirmation` and `zks_getPreconfirmation`).
+    #[config(default_t = false)]
+    pub enabled: bool,
+    /// Private key used to sign preconfirmations. Required if preconfirmations are enabled.
+    /// Accepts `env:` / `file:` references.
+    pub signing_key: Option<SecretString>,
+    /// Time after acceptance by which the sequencer commits to include a preconfirmed transaction
+    /// in a block.
+    #[config(default_t = Duration::from_secs(60))]
+    pub inclusion_deadline: Duration,
+    /// How long signed preconfirmations are kept for `zks_getPreconfirmation`.
+    #[config(default_t = 10 * TimeUnit::Minutes)]
+    pub ttl: Duration,
+    /// Max number of signed preconfirmations kept for `zks_getPreconfirmation`.
+    #[config(default_t = 100_000)]
+    pub store_capacity: usize,
+    /// Max number of transactions waiting to be preconfirmed. Transactions accepted while the queue
+    /// is full don't get a preconfirmation.
+    #[config(default_t = 1_000)]
+    pub queue_capacity: usize,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -291,6 +660,21 @@ pub struct L1SenderConfig {
     #[config(default_t = 2)]
     pub max_priority_fee_per_gas_gwei: u64,
 
+    /// Max fee per blob gas we are willing to spend (in gwei).
+    /// Only used when publishing pubdata in blobs.
+    #[config(default_t = 50)]
+    pub max_fee_per_blob_gas_gwei: u64,
+
+    /// Multiplier applied to the gas estimated for commit/prove/execute transactions to get their
+    /// gas limit.
+    #[config(default_t = 1.2)]
+    pub gas_limit_safety_factor: f64,
+
+    /// Max gas limit of commit/prove/execute transactions; must not exceed the L1 block gas limit.
+    /// Used as the gas limit if gas estimation fails.
+    #[config(default_t = 30_000_000)]
+    pub max_gas_limit: u64,
+
     /// Max number of commands (to commit/prove/execute one batch) to be processed at a time.
     #[config(default_t = 16)]
     pub command_limit: usize,
@@ -299,6 +683,55 @@ pub struct L1SenderConfig {
     #[config(default_t = Duration::from_millis(100))]
     pub poll_interval: Duration,
 
+    /// Time after which a commit/prove/execute transaction that is not included on L1 is replaced
+    /// with one paying fees bumped by 12.5%, up to `max_fee_per_gas_gwei` and
+    /// `max_priority_fee_per_gas_gwei`. Should be well below 5 minutes, after which the L1 sender
+    /// fails if the last sent transaction is not included.
+    #[config(default_t = 1 * TimeUnit::Minutes)]
+    pub resubmission_interval: Duration,
+
+    /// Max number of batches processed by an L1 sender that may wait for the next pipeline step
+    /// (e.g. committed batches waiting for SNARK proofs). The sender pauses once the backlog is full.
+    #[config(default_t = 64)]
+    pub max_outbound_backlog: usize,
+
+    /// L1 senders pause while their operator balance is below this value (in gwei).
+    /// Zero disables the check.
+    #[config(default_t = 0)]
+    pub min_operator_balance_gwei: u64,
+
+    /// Whether to commit batches whose commitment encoding version differs from the previous batch
+    /// (e.g. after a node upgrade or rollback) without waiting for the operator to acknowledge
+    /// the transition via `admin_acknowledgeCommitmentFormatTransition`.
+    #[config(default_t = false)]
+    pub allow_commitment_format_transition: bool,
+
+    /// File the report of an L1 sender dry run (would-be L1 transactions with their calldata,
+    /// gas and cost estimates) is written to as JSON. Not written if unset.
+    pub dry_run_report_path: Option<PathBuf>,
+
+    /// How often the watchdog samples sealed and committed batches.
+    #[config(default_t = 30 * TimeUnit::Seconds)]
+    pub watchdog_poll_interval: Duration,
+
+    /// Number of sealed batches not committed on L1 at which the watchdog raises a warning,
+    /// provided that blocks keep being produced.
+    #[config(default_t = 20)]
+    pub watchdog_warning_lag_batches: u64,
+
+    /// Number of sealed batches not committed on L1 at which the watchdog raises a critical alert.
+    #[config(default_t = 50)]
+    pub watchdog_critical_lag_batches: u64,
+
+    /// Time without commit progress (while sealed batches wait for a commit) at which
+    /// the watchdog raises a warning, provided that blocks keep being produced.
+    #[config(default_t = 15 * TimeUnit::Minutes)]
+    pub watchdog_warning_stall: Duration,
+
+    /// Time without commit progress at which the watchdog raises a critical alert.
+    #[config(default_t = 1 * TimeUnit::Hours)]
+    pub watchdog_critical_stall: Duration,
+
     /// Whether L1 senders are enabled.
     /// Only affects the Main Node.
     /// Only useful for debug. When L1 senders are disabled,
@@ -337,6 +770,28 @@ pub struct L1WatcherConfig {
     /// Default: 10 minutes
     #[config(default_t = 10 * TimeUnit::Minutes)]
     pub proof_storage_grace_period: Duration,
+
+    /// How often to check L1 `safe`/`finalized` heads against the L1 transactions sent by this
+    /// node. One L1 slot by default.
+    #[config(default_t = 12 * TimeUnit::Seconds)]
+    pub finality_poll_interval: Duration,
+
+    /// Time left until the oldest unprocessed priority transaction expires on L1 at which
+    /// a warning is raised.
+    #[config(default_t = 24 * TimeUnit::Hours)]
+    pub priority_expiry_warning_threshold: Duration,
+
+    /// Time left until the oldest unprocessed priority transaction expires on L1 at which
+    /// a critical alert is raised. Priority transactions this close to expiry are included in
+    /// the next block regardless of the block time and transaction count limits.
+    #[config(default_t = 2 * TimeUnit::Hours)]
+    pub priority_expiry_critical_threshold: Duration,
+
+    /// How often to poll L1 for governance events (validator role changes, fee param updates,
+    /// scheduled protocol upgrades). Governance actions are rare, so there is no need to poll
+    /// often.
+    #[config(default_t = 1 * TimeUnit::Minutes)]
+    pub governance_poll_interval: Duration,
 }
 
 #[derive(Clone, Debug, DescribeConfig, DeserializeConfig)]
@@ -346,6 +801,34 @@ pub struct MempoolConfig {
     pub max_pending_txs: usize,
     #[config(default_t = usize::MAX)]
     pub max_pending_size: usize,
+
+    /// Spam score starting from which the sender's transactions are included after all other ones.
+    /// Scores grow on replacements, failed submissions and evictions, by 1-2 points per event.
+    #[config(default_t = 10.0)]
+    pub spam_deprioritize_score: f64,
+    /// Spam score starting from which submissions from the sender (or client IP) are rejected.
+    #[config(default_t = 50.0)]
+    pub spam_reject_score: f64,
+    /// Time it takes for a spam score to halve.
+    #[config(default_t = 10 * TimeUnit::Minutes)]
+    pub spam_score_half_life: Duration,
+    /// Max number of client IPs spam scores are tracked for. Once reached, the lowest-scoring IP is
+    /// dropped to track a new one.
+    #[config(default_t = 100_000)]
+    pub spam_max_tracked_origins: usize,
+    /// Path spam scores are persisted to. Defaults to `mempool_spam_scores.json` in
+    /// `rocks_db_path`.
+    pub spam_scores_path: Option<PathBuf>,
+
+    /// Whether to journal pool contents, so that pooled transactions survive node restarts.
+    #[config(default_t = false)]
+    pub journal_enabled: bool,
+    /// Path pool contents are journaled to. Defaults to `mempool_journal.jsonl` in
+    /// `rocks_db_path`.
+    pub journal_path: Option<PathBuf>,
+    /// How often the journal is rewritten from the pool contents to bound its size.
+    #[config(default_t = 1 * TimeUnit::Minutes)]
+    pub journal_compaction_interval: Duration,
 }
 
 #[derive(Clone, Debug, DescribeConfig, DeserializeConfig)]
@@ -354,6 +837,43 @@ pub struct TxValidatorConfig {
     /// Max input size of a transaction to be accepted by mempool
     #[config(default_t = 128 * 1024 * 1024)]
     pub max_input_bytes: usize,
+    /// Max number of blocks ahead of the next block a transaction can be scheduled for.
+    #[config(default_t = 86_400)]
+    pub max_schedule_blocks_ahead: u64,
+    /// Max time ahead a transaction can be scheduled for.
+    #[config(default_t = 24 * TimeUnit::Hours)]
+    pub max_schedule_time_ahead: Duration,
+    /// Max number of scheduled transactions waiting to become due.
+    #[config(default_t = 10_000)]
+    pub max_scheduled_transactions: usize,
+    /// Max number of transactions listed when inspecting the mempool contents.
+    #[config(default_t = 10_000)]
+    pub max_content_entries: usize,
+    /// Pooled transactions are removed after this time, e.g. if they have a nonce gap that is
+    /// never filled.
+    #[config(default_t = 3 * TimeUnit::Hours)]
+    pub max_tx_lifetime: Duration,
+    /// Min priority fee per gas (in wei) for transactions to be accepted. Legacy transactions are
+    /// checked against their gas price. Disabled if not set.
+    #[config(default_t = None, with = Optional(Serde![str]))]
+    pub min_priority_fee_per_gas: Option<U128>,
+    /// Senders whose transactions are rejected.
+    #[config(default, with = Delimited(","))]
+    pub denied_senders: Vec<String>,
+    /// If non-empty, only transactions from these senders are accepted.
+    #[config(default, with = Delimited(","))]
+    pub allowed_senders: Vec<String>,
+    /// Sponsors that can pay fees of transactions submitted with `zks_sendRawTransactionSponsored`,
+    /// as `<address>:<budget>` entries. The budget is the max amount of fees (in wei) a sponsor can
+    /// be charged within `sponsor_budget_window`.
+    #[config(default, with = Delimited(","))]
+    pub sponsors: Vec<String>,
+    /// Rolling window sponsor budgets apply to.
+    #[config(default_t = 24 * TimeUnit::Hours)]
+    pub sponsor_budget_window: Duration,
+    /// Max time between the submission of a sponsored transaction and its voucher's expiry.
+    #[config(default_t = 1 * TimeUnit::Hours)]
+    pub max_sponsorship_lifetime: Duration,
 }
 
 /// Only used on the Main Node.
@@ -367,6 +887,26 @@ pub struct BatcherConfig {
     /// Max number of blocks per batch
     #[config(default_t = 10)]
     pub blocks_per_batch_limit: u64,
+
+    /// Min time between sealing a batch and sending its commit transaction to L1.
+    #[config(default_t = Duration::ZERO)]
+    pub min_batch_age: Duration,
+
+    /// Max time between sealing a batch and sending its commit transaction; once reached, the batch
+    /// is committed regardless of L1 fees.
+    #[config(default_t = Duration::from_secs(30 * 60))]
+    pub max_commit_delay: Duration,
+
+    /// Commit transactions are deferred while the current L1 base fee is above this percentile
+    /// (in `[0, 1]`) of base fees sampled by the gas adjuster. Disabled if not set.
+    /// Execute transactions are not affected.
+    #[config(default_t = None)]
+    pub commit_fee_percentile: Option<f64>,
+
+    /// Batches with priority transactions expiring on L1 within this window are proven ahead of
+    /// other batches, and committed without waiting for `min_batch_age` or lower L1 fees.
+    #[config(default_t = 24 * TimeUnit::Hours)]
+    pub priority_deadline_window: Duration,
 }
 
 /// Only used on the Main Node.
@@ -424,6 +964,30 @@ pub struct ProverApiConfig {
     /// Default: backed by files under `./db/shared` folder.
     #[config(nest, default)]
     pub object_store: ObjectStoreConfig,
+
+    /// Prefix object store keys with the chain id.
+    /// Must be enabled when `object_store` is shared between several chains.
+    /// Disabled by default to keep the existing storage layout of single-chain deployments.
+    #[config(default_t = false)]
+    pub namespace_storage_by_chain_id: bool,
+
+    /// Max time to receive a prover API request and respond to it.
+    #[config(default_t = Duration::from_secs(60))]
+    pub request_timeout: Duration,
+
+    /// Max body size of proof submissions in bytes. Other prover API routes don't accept bodies.
+    #[config(default_t = 10 * 1024 * 1024)]
+    pub max_proof_body_bytes: usize,
+
+    /// Whether to verify submitted SNARK proofs with the chain's L1 verifier before accepting them.
+    /// Invalid proofs are rejected, so that they don't fail the prove transaction on L1.
+    /// Disable if the L1 verifier cannot check real proofs.
+    #[config(default_t = true)]
+    pub snark_verification_enabled: bool,
+
+    /// Max time to verify a submitted SNARK proof.
+    #[config(default_t = Duration::from_secs(30))]
+    pub snark_verification_timeout: Duration,
 }
 
 #[derive(Clone, Debug, DescribeConfig, DeserializeConfig)]
@@ -528,6 +1092,70 @@ pub struct GasAdjusterConfig {
     pub poll_period: Duration,
     #[config(default_t = 1.0)]
     pub pubdata_pricing_multiplier: f64,
+    /// Percentile (in `[0, 1]`) of the sampled L1 base fees used to estimate prices; 0.5 is the median.
+    #[config(default_t = 0.5)]
+    pub base_fee_percentile: f64,
+    /// L1 gas paid for a non-zero pubdata byte when pubdata is sent in calldata; zero bytes cost a quarter of it.
+    /// Bigger than 16 by default to account for potential overhead.
+    #[config(default_t = 17)]
+    pub calldata_gas_per_byte: u64,
+    /// Expected share (in `[0, 1]`) of zero bytes in calldata pubdata. The share observed in committed batches
+    /// is used instead if it results in a higher price.
+    #[config(default_t = 0.0)]
+    pub zero_byte_ratio_estimate: f64,
+    /// Number of last committed batches the observed share of zero pubdata bytes is computed from.
+    #[config(default_t = 10)]
+    pub pubdata_composition_batches: usize,
+    /// L1 JSON RPC endpoints to fetch fee data from if `general.l1_rpc_url` fails, in the order they are tried.
+    #[config(default, with = Delimited(","))]
+    pub fallback_l1_rpc_urls: Vec<String>,
+    /// If L1 fee data couldn't be fetched for longer than this, it's considered stale and errors are logged.
+    #[config(default_t = 5 * TimeUnit::Minutes)]
+    pub max_fee_data_age: Duration,
+    /// Window over which L1 prices predicted by the gas adjuster are compared with the prices paid
+    /// for commit/prove/execute transactions.
+    #[config(default_t = 1 * TimeUnit::Hours)]
+    pub price_drift_window: Duration,
+    /// Mean absolute relative error of predicted L1 prices within `price_drift_window` above which
+    /// a drift alert is raised (the `l1_sender_l1_price_drift_alert` metric).
+    #[config(default_t = 0.25)]
+    pub price_drift_threshold: f64,
+
+    /// Conversion of L1 costs to the base token for chains with a custom base token.
+    #[config(nest, default)]
+    pub base_token: BaseTokenConfig,
+}
+
+/// Source of the ETH -> base token conversion ratio.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BaseTokenRateSource {
+    /// The chain uses ETH as the base token; no conversion.
+    Eth,
+    /// Constant ratio from `static_ratio_numerator` / `static_ratio_denominator`.
+    Static,
+    /// Ratio maintained on L1 in the chain's diamond proxy.
+    L1,
+}
+
+/// Only used on the Main Node.
+#[derive(Debug, Clone, PartialEq, DescribeConfig, DeserializeConfig)]
+#[config(derive(Default))]
+pub struct BaseTokenConfig {
+    #[config(default_t = BaseTokenRateSource::Eth)]
+    #[config(with = Serde![str])]
+    pub rate_source: BaseTokenRateSource,
+    /// Amount of the base token equivalent to `static_ratio_denominator` wei. Only used with the `Static` source.
+    #[config(default_t = 1)]
+    pub static_ratio_numerator: u64,
+    #[config(default_t = 1)]
+    pub static_ratio_denominator: u64,
+    /// How often the ratio is fetched.
+    #[config(default_t = 30 * TimeUnit::Seconds)]
+    pub poll_period: Duration,
+    /// If the last successfully fetched ratio is older than this, block prices are not adjusted
+    /// (stay equal to the previous block's prices) until a fresh ratio is fetched.
+    #[config(default_t = 5 * TimeUnit::Minutes)]
+    pub max_rate_staleness: Duration,
 }
 
 /// Configuration for the opentelemetry stack.
@@ -568,9 +1196,17 @@ pub struct BatchVerificationConfig {
     /// [server] Threshold (number of needed signatures)
     #[config(default_t = 1)]
     pub threshold: usize,
-    /// [server] Accepted signer pubkeys
+    /// [server] Weighted quorum of signers. If set, `threshold` is ignored.
+    #[config(nest)]
+    pub weighted_threshold: Option<WeightedThresholdConfig>,
+    /// [server] Accepted signers: addresses of EOAs or, as `contract:<address>`, of smart-contract
+    /// wallets validating signatures via EIP-1271.
     #[config(default_t = vec!["0x36615Cf349d7F6344891B1e7CA7C72883F5dc049".into()])]
     pub accepted_signers: Vec<String>,
+    /// [server] Max time of an `isValidSignature` call to a contract signer on L1. Signatures that
+    /// couldn't be checked are ignored until the batch is requested to be signed again.
+    #[config(default_t = Duration::from_secs(10))]
+    pub contract_signer_call_timeout: Duration,
     /// [server] Iteration timeout
     #[config(default_t = Duration::from_secs(5))]
     pub request_timeout: Duration,
@@ -584,6 +1220,145 @@ pub struct BatchVerificationConfig {
     // default address 0x36615Cf349d7F6344891B1e7CA7C72883F5dc049
     #[config(default_t = "0x7726827caac94a7f9e1b160f7ea819f172f7b6f9d2a97f992c38edeab82d4110".into())]
     pub signing_key: SecretString,
+    /// [en] Path of the journal of produced signatures, used to never sign conflicting commit data
+    /// for the same batch. Defaults to `batch_signing_journal.jsonl` in `rocks_db_path`.
+    pub signing_journal_path: Option<PathBuf>,
+    /// [en] Number of most recent signatures kept in the signing journal. Requests for batches older
+    /// than all retained signatures are refused.
+    #[config(default_t = 10_000)]
+    pub signing_journal_retention: usize,
+    /// [en] Max number of blocks kept in memory for verification. Blocks are also evicted once
+    /// their batch is signed or committed; evicted blocks are re-executed if requested again.
+    #[config(default_t = 5_000)]
+    pub client_max_cached_blocks: usize,
+    /// [server] PEM certificate chain of the server. If set, connections are accepted over TLS only;
+    /// plaintext otherwise.
+    pub server_tls_cert_path: Option<PathBuf>,
+    /// [server] PEM private key matching `server_tls_cert_path`.
+    pub server_tls_key_path: Option<PathBuf>,
+    /// [server] PEM bundle of CAs issuing client certificates. If set, clients must authenticate with a certificate.
+    pub server_tls_client_ca_path: Option<PathBuf>,
+    /// [en] PEM bundle of root CAs to verify the server certificate. If set, connects over TLS;
+    /// plaintext otherwise.
+    pub client_tls_root_ca_path: Option<PathBuf>,
+    /// [en] PEM client certificate chain, for servers requiring client authentication.
+    pub client_tls_cert_path: Option<PathBuf>,
+    /// [en] PEM private key matching `client_tls_cert_path`.
+    pub client_tls_key_path: Option<PathBuf>,
+    /// [en] Name the server certificate is verified for. Defaults to the host of `connect_address`.
+    pub client_tls_server_name: Option<String>,
+    /// [en] Idle time of the connection to the server after which TCP keepalive probes are sent.
+    #[config(default_t = Duration::from_secs(30))]
+    pub client_keepalive_time: Duration,
+    /// [en] Interval between TCP keepalive probes.
+    #[config(default_t = Duration::from_secs(10))]
+    pub client_keepalive_interval: Duration,
+    /// [en] Number of unanswered TCP keepalive probes after which the server is considered
+    /// unreachable and the client reconnects.
+    #[config(default_t = 3)]
+    pub client_keepalive_retries: u32,
+    /// [server] Max number of client connections served at the same time. Connections over the cap
+    /// are closed right away.
+    #[config(default_t = 64)]
+    pub server_max_connections: usize,
+    /// [server] Max time for a client to complete the TLS, HTTP and identity handshakes after
+    /// connecting.
+    #[config(default_t = Duration::from_secs(10))]
+    pub server_handshake_timeout: Duration,
+    /// Max size of an encoded verification request in bytes. Requests carry the commit data
+    /// of a batch, including its pubdata. Peers sending larger requests are disconnected.
+    #[config(default_t = 8 * 1024 * 1024)]
+    pub max_request_frame_bytes: usize,
+    /// Max size of an encoded verification response in bytes. Responses only carry a signature
+    /// or a refusal reason. Peers sending larger responses are disconnected.
+    #[config(default_t = 64 * 1024)]
+    pub max_response_frame_bytes: usize,
+}
+
+/// Weighted quorum of batch verification signers.
+#[derive(Clone, Debug, DescribeConfig, DeserializeConfig)]
+pub struct WeightedThresholdConfig {
+    /// Weights of accepted signers as `<address>:<weight>` entries. Signers not listed have
+    /// weight 1.
+    #[config(default, with = Delimited(","))]
+    pub weights: Vec<String>,
+    /// Total weight of signers needed for a batch to be verified.
+    pub quorum: u64,
+}
+
+impl BatchVerificationConfig {
+    pub fn quorum(&self) -> zksync_os_batch_verification::SignatureQuorum {
+        let Some(weighted) = &self.weighted_threshold else {
+            return zksync_os_batch_verification::SignatureQuorum::Threshold(self.threshold);
+        };
+        zksync_os_batch_verification::SignatureQuorum::Weighted {
+            weights: Arc::new(parse_signer_weights(&weighted.weights)),
+            quorum: weighted.quorum,
+        }
+    }
+
+    pub fn server_tls(&self) -> Option<TlsServerConfig> {
+        let cert_path = self.server_tls_cert_path.clone()?;
+        Some(TlsServerConfig {
+            identity: TlsIdentity {
+                cert_path,
+                key_path: self.server_tls_key_path.clone().expect(
+                    "`batch_verification.server_tls_key_path` must be set when TLS is enabled",
+                ),
+            },
+            client_ca_path: self.server_tls_client_ca_path.clone(),
+        })
+    }
+
+    pub fn server_limits(&self) -> ListenerLimits {
+        ListenerLimits {
+            max_connections: self.server_max_connections,
+            handshake_timeout: self.server_handshake_timeout,
+        }
+    }
+
+    pub fn frame_limits(&self) -> zksync_os_batch_verification::FrameLimits {
+        zksync_os_batch_verification::FrameLimits {
+            max_request_bytes: self.max_request_frame_bytes,
+            max_response_bytes: self.max_response_frame_bytes,
+        }
+    }
+
+    pub fn client_keepalive(&self) -> KeepaliveConfig {
+        KeepaliveConfig {
+            time: self.client_keepalive_time,
+            interval: self.client_keepalive_interval,
+            retries: self.client_keepalive_retries,
+        }
+    }
+
+    pub fn client_tls(&self) -> Option<TlsConfig> {
+        let root_ca_path = self.client_tls_root_ca_path.clone()?;
+        let client_identity =
+            self.client_tls_cert_path.clone().map(|cert_path| {
+                TlsIdentity {
+            cert_path,
+            key_path: self.client_tls_key_path.clone().expect(
+                "`batch_verification.client_tls_key_path` must be set with `client_tls_cert_path`",
+            ),
+        }
+            });
+        Some(TlsConfig {
+            root_ca_path,
+            client_identity,
+            server_name: self.client_tls_server_name.clone(),
+        })
+    }
+}
+
+impl From<BatcherConfig> for zksync_os_l1_sender::commit_scheduler::CommitSchedulerConfig {
+    fn from(c: BatcherConfig) -> Self {
+        Self {
+            min_batch_age: c.min_batch_age,
+            max_commit_delay: c.max_commit_delay,
+            fee_percentile: c.commit_fee_percentile,
+        }
+    }
 }
 
 impl From<RpcConfig> for zksync_os_rpc::RpcConfig {
@@ -597,7 +1372,41 @@ impl From<RpcConfig> for zksync_os_rpc::RpcConfig {
             max_blocks_per_filter: c.max_blocks_per_filter,
             max_logs_per_response: c.max_logs_per_response,
             stale_filter_ttl: c.stale_filter_ttl,
+            max_fee_history_blocks: c.max_fee_history_blocks,
+            simulate_max_blocks: c.simulate_max_blocks,
+            simulate_max_calls: c.simulate_max_calls,
+            simulate_max_gas: c.simulate_max_gas,
+            verify_block_completeness: c.verify_block_completeness,
+            trusted_proxies: c
+                .trusted_proxies
+                .iter()
+                .map(|proxy| {
+                    proxy.parse().unwrap_or_else(|err| {
+                        panic!("invalid IP address {proxy:?} in `trusted_proxies`: {err}")
+                    })
+                })
+                .collect(),
+            preconfirmations: c.preconfirmations.into(),
+        }
+    }
+}
+
+impl From<PreconfirmationConfig> for Option<zksync_os_rpc::PreconfirmationConfig> {
+    fn from(c: PreconfirmationConfig) -> Self {
+        if !c.enabled {
+            return None;
         }
+        let signing_key = c.signing_key.expect(
+            "`rpc.preconfirmations.signing_key` must be set when preconfirmations are enabled",
+        );
+        Some(zksync_os_rpc::PreconfirmationConfig {
+            signer: PrivateKeySigner::from_str(signing_key.expose_secret())
+                .expect("Invalid preconfirmation signing key"),
+            inclusion_deadline: c.inclusion_deadline,
+            ttl: c.ttl,
+            store_capacity: c.store_capacity,
+            queue_capacity: c.queue_capacity,
+        })
     }
 }
 
@@ -605,18 +1414,43 @@ impl From<SequencerConfig> for zksync_os_sequencer::config::SequencerConfig {
     fn from(c: SequencerConfig) -> Self {
         Self {
             block_time: c.block_time,
+            strict_block_timestamps: c.strict_block_timestamps,
             max_transactions_in_block: c.max_transactions_in_block,
+            block_trace_path: c
+                .block_traces_enabled
+                .then(|| c.block_dump_path.join("block_traces")),
+            max_block_traces: c.max_block_traces,
             block_dump_path: c.block_dump_path,
+            dump_detail_level: c.dump_detail_level,
+            max_dump_bytes: c.max_dump_bytes,
             block_replay_server_address: c.block_replay_server_address,
             block_replay_download_address: c.block_replay_download_address,
             block_gas_limit: c.block_gas_limit,
             block_pubdata_limit_bytes: c.block_pubdata_limit_bytes,
             max_blocks_to_produce: c.max_blocks_to_produce,
+            warm_up_max_txs: c.warm_up_max_txs,
+            warm_up_concurrency: c.warm_up_concurrency,
         }
     }
 }
 
 impl L1SenderConfig {
+    /// Addresses of the commit, prove and execute operators.
+    pub fn operator_addresses(&self) -> Vec<Address> {
+        [
+            &self.operator_commit_pk,
+            &self.operator_prove_pk,
+            &self.operator_execute_pk,
+        ]
+        .into_iter()
+        .map(|pk| {
+            PrivateKeySigner::from_str(pk.expose_secret())
+                .expect("Invalid operator private key")
+                .address()
+        })
+        .collect()
+    }
+
     fn into_lib_l1_sender_config<Input>(
         self,
         operator_pk: SecretString,
@@ -625,8 +1459,16 @@ impl L1SenderConfig {
             operator_pk,
             max_fee_per_gas_gwei: self.max_fee_per_gas_gwei,
             max_priority_fee_per_gas_gwei: self.max_priority_fee_per_gas_gwei,
+            max_fee_per_blob_gas_gwei: self.max_fee_per_blob_gas_gwei,
+            gas_limit_safety_factor: self.gas_limit_safety_factor,
+            max_gas_limit: self.max_gas_limit,
             command_limit: self.command_limit,
             poll_interval: self.poll_interval,
+            resubmission_interval: self.resubmission_interval,
+            max_outbound_backlog: self.max_outbound_backlog,
+            min_operator_balance_gwei: self.min_operator_balance_gwei,
+            allow_commitment_format_transition: self.allow_commitment_format_transition,
+            dry_run_report_path: self.dry_run_report_path.clone(),
             phantom_data: Default::default(),
         }
     }
@@ -651,12 +1493,26 @@ impl From<L1SenderConfig> for zksync_os_l1_sender::config::L1SenderConfig<Execut
     }
 }
 
+impl From<L1SenderConfig> for zksync_os_l1_sender::watchdog::WatchdogConfig {
+    fn from(c: L1SenderConfig) -> Self {
+        Self {
+            poll_interval: c.watchdog_poll_interval,
+            warning_lag_batches: c.watchdog_warning_lag_batches,
+            critical_lag_batches: c.watchdog_critical_lag_batches,
+            warning_stall: c.watchdog_warning_stall,
+            critical_stall: c.watchdog_critical_stall,
+        }
+    }
+}
+
 impl From<L1WatcherConfig> for zksync_os_l1_watcher::L1WatcherConfig {
     fn from(c: L1WatcherConfig) -> Self {
         Self {
             max_blocks_to_process: c.max_blocks_to_process,
             poll_interval: c.poll_interval,
             proof_storage_grace_period: c.proof_storage_grace_period,
+            finality_poll_interval: c.finality_poll_interval,
+            governance_poll_interval: c.governance_poll_interval,
         }
     }
 }
@@ -670,12 +1526,104 @@ impl From<MempoolConfig> for zksync_os_mempool::PoolConfig {
     }
 }
 
-impl From<TxValidatorConfig> for zksync_os_mempool::TxValidatorConfig {
-    fn from(c: TxValidatorConfig) -> Self {
-        Self {
-            max_input_bytes: c.max_input_bytes,
+impl MempoolConfig {
+    pub fn spam_scoring_config(&self) -> zksync_os_mempool::SpamScoringConfig {
+        zksync_os_mempool::SpamScoringConfig {
+            deprioritize_score: self.spam_deprioritize_score,
+            reject_score: self.spam_reject_score,
+            half_life: self.spam_score_half_life,
+            max_origins: self.spam_max_tracked_origins,
         }
     }
+
+    pub fn pool_persistence_config(
+        &self,
+        rocks_db_path: &Path,
+    ) -> Option<zksync_os_mempool::PoolPersistenceConfig> {
+        self.journal_enabled
+            .then(|| zksync_os_mempool::PoolPersistenceConfig {
+                journal_path: self
+                    .journal_path
+                    .clone()
+                    .unwrap_or_else(|| rocks_db_path.join("mempool_journal.jsonl")),
+                compaction_interval: self.journal_compaction_interval,
+            })
+    }
+}
+
+impl TxValidatorConfig {
+    pub fn into_lib_tx_validator_config(
+        self,
+        execution_version: ExecutionVersion,
+    ) -> zksync_os_mempool::TxValidatorConfig {
+        zksync_os_mempool::TxValidatorConfig {
+            max_input_bytes: self.max_input_bytes,
+            execution_version,
+            schedule: zksync_os_mempool::TxScheduleConfig {
+                max_blocks_ahead: self.max_schedule_blocks_ahead,
+                max_time_ahead: self.max_schedule_time_ahead,
+                max_scheduled_transactions: self.max_scheduled_transactions,
+            },
+            sponsorship: zksync_os_mempool::SponsorshipConfig {
+                budgets: parse_sponsors(&self.sponsors),
+                budget_window: self.sponsor_budget_window,
+                max_voucher_lifetime: self.max_sponsorship_lifetime,
+            },
+            max_content_entries: self.max_content_entries,
+            max_tx_lifetime: self.max_tx_lifetime,
+            min_priority_fee_per_gas: self.min_priority_fee_per_gas.map(|fee| fee.to()),
+            denied_senders: parse_senders(&self.denied_senders, "denied_senders"),
+            allowed_senders: (!self.allowed_senders.is_empty())
+                .then(|| parse_senders(&self.allowed_senders, "allowed_senders")),
+        }
+    }
+}
+
+fn parse_senders(senders: &[String], field: &str) -> Vec<Address> {
+    senders
+        .iter()
+        .map(|sender| {
+            sender
+                .parse()
+                .unwrap_or_else(|err| panic!("invalid address {sender:?} in `{field}`: {err}"))
+        })
+        .collect()
+}
+
+fn parse_sponsors(sponsors: &[String]) -> HashMap<Address, u128> {
+    sponsors
+        .iter()
+        .map(|entry| {
+            let (sponsor, budget) = entry.split_once(':').unwrap_or_else(|| {
+                panic!("invalid entry {entry:?} in `sponsors`: expected `<address>:<budget>`")
+            });
+            let sponsor = sponsor
+                .parse()
+                .unwrap_or_else(|err| panic!("invalid address {sponsor:?} in `sponsors`: {err}"));
+            let budget = budget
+                .parse()
+                .unwrap_or_else(|err| panic!("invalid budget {budget:?} in `sponsors`: {err}"));
+            (sponsor, budget)
+        })
+        .collect()
+}
+
+fn parse_signer_weights(weights: &[String]) -> BTreeMap<Address, u64> {
+    weights
+        .iter()
+        .map(|entry| {
+            let (signer, weight) = entry.split_once(':').unwrap_or_else(|| {
+                panic!("invalid entry {entry:?} in `weighted_threshold.weights`: expected `<address>:<weight>`")
+            });
+            let signer = signer.parse().unwrap_or_else(|err| {
+                panic!("invalid address {signer:?} in `weighted_threshold.weights`: {err}")
+            });
+            let weight = weight.parse().unwrap_or_else(|err| {
+                panic!("invalid weight {weight:?} in `weighted_threshold.weights`: {err}")
+            });
+            (signer, weight)
+        })
+        .collect()
 }
 
 impl From<RebuildBlocksConfig> for RebuildOptions {
@@ -689,28 +1637,41 @@ impl From<RebuildBlocksConfig> for RebuildOptions {
 
 impl From<BatchVerificationConfig> for zksync_os_batch_verification::BatchVerificationConfig {
     fn from(c: BatchVerificationConfig) -> Self {
+        let server_tls = c.server_tls();
+        let client_tls = c.client_tls();
+        let client_keepalive = c.client_keepalive();
+        let server_limits = c.server_limits();
+        let frame_limits = c.frame_limits();
+        let quorum = c.quorum();
         Self {
             server_enabled: c.server_enabled,
             listen_address: c.listen_address,
             client_enabled: c.client_enabled,
             connect_address: c.connect_address,
-            threshold: c.threshold,
+            quorum,
             accepted_signers: c.accepted_signers,
+            contract_signer_call_timeout: c.contract_signer_call_timeout,
             request_timeout: c.request_timeout,
             retry_delay: c.retry_delay,
             total_timeout: c.total_timeout,
             signing_key: c.signing_key,
+            signing_journal_path: c.signing_journal_path,
+            signing_journal_retention: c.signing_journal_retention,
+            server_tls,
+            client_tls,
+            client_keepalive,
+            server_limits,
+            frame_limits,
         }
     }
 }
 
-pub fn gas_adjuster_config(
-    c: GasAdjusterConfig,
+/// How pubdata is published on L1, given the DA mode of the chain.
+pub fn pubdata_mode(
     da_input_mode: BatchDaInputMode,
     rollup_pubdata_mode: RollupPubdataMode,
-    max_priority_fee_per_gas_gwei: u64,
-) -> zksync_os_gas_adjuster::GasAdjusterConfig {
-    let pubdata_mode = match (da_input_mode, rollup_pubdata_mode) {
+) -> zksync_os_gas_adjuster::PubdataMode {
+    match (da_input_mode, rollup_pubdata_mode) {
         (BatchDaInputMode::Validium, _) => zksync_os_gas_adjuster::PubdataMode::Validium,
         (BatchDaInputMode::Rollup, RollupPubdataMode::Blobs) => {
             zksync_os_gas_adjuster::PubdataMode::Blobs
@@ -718,7 +1679,16 @@ pub fn gas_adjuster_config(
         (BatchDaInputMode::Rollup, RollupPubdataMode::Calldata) => {
             zksync_os_gas_adjuster::PubdataMode::Calldata
         }
-    };
+    }
+}
+
+pub fn gas_adjuster_config(
+    c: GasAdjusterConfig,
+    da_input_mode: BatchDaInputMode,
+    rollup_pubdata_mode: RollupPubdataMode,
+    max_priority_fee_per_gas_gwei: u64,
+) -> zksync_os_gas_adjuster::GasAdjusterConfig {
+    let pubdata_mode = pubdata_mode(da_input_mode, rollup_pubdata_mode);
     let max_priority_fee_per_gas = max_priority_fee_per_gas_gwei as u128 * (GWEI_TO_WEI as u128);
     zksync_os_gas_adjuster::GasAdjusterConfig {
         pubdata_mode,
@@ -727,5 +1697,10 @@ pub fn gas_adjuster_config(
         max_priority_fee_per_gas,
         poll_period: c.poll_period,
         pubdata_pricing_multiplier: c.pubdata_pricing_multiplier,
+        base_fee_percentile: c.base_fee_percentile,
+        calldata_gas_per_byte: c.calldata_gas_per_byte.into(),
+        zero_byte_ratio_estimate: c.zero_byte_ratio_estimate,
+        pubdata_composition_batches: c.pubdata_composition_batches,
+        max_fee_data_age: c.max_fee_data_age,
     }
 }
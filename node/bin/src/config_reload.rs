@@ -0,0 +1,544 @@
+//! Hot reload of configuration without restarting the node.
+//!
+//! The optional config file (`general.config_file_path`) is layered over env variables at startup.
+//! Afterwards, it is re-read whenever it changes (checked every `general.config_reload_interval`)
+//! or on `admin_reloadConfig`, and compared field by field with the values applied so far.
+//!
+//! Only fields registered in the [`HotReloadRegistry`] can change at runtime. Each registration
+//! comes with a `watch` channel the consuming component reads the value from and a parser that
+//! validates new values. A reload is applied atomically: if any changed field is not registered
+//! or its new value is invalid, the whole reload is rejected, reporting every offending field,
+//! and nothing is applied. Applied reloads are recorded in the admin [`AuditLog`] with the old and
+//! new values of every changed field; values of secret fields are redacted.
+
+use crate::admin::{AuditLog, AuditOutcome};
+use anyhow::Context;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value, json};
+use smart_config::Json;
+use smart_config::value::SecretString;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+
+/// Audit log action of applied and rejected reloads.
+const AUDIT_ACTION: &str = "config_reload";
+/// Replaces values of secret fields in audit records and errors.
+const REDACTED: &str = "<redacted>";
+
+/// Values of config fields by their path (`<section>.<field>`, e.g. `admin_api.auth_token`).
+type ConfigValues = BTreeMap<String, Value>;
+
+/// Parses and validates a new field value, returning a closure applying it.
+type PrepareFn = Box<dyn Fn(&Value) -> anyhow::Result<Box<dyn FnOnce() + Send>> + Send + Sync>;
+
+struct ReloadableField {
+    secret: bool,
+    prepare: PrepareFn,
+}
+
+/// Config fields that can be changed at runtime, see the module docs.
+#[derive(Default)]
+pub struct HotReloadRegistry {
+    fields: BTreeMap<String, ReloadableField>,
+}
+
+impl fmt::Debug for HotReloadRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.fields.keys()).finish()
+    }
+}
+
+impl HotReloadRegistry {
+    /// Registers the field at `path`; new values are checked with `validate` and sent to `sender`.
+    pub fn register<T>(
+        &mut self,
+        path: &str,
+        sender: watch::Sender<T>,
+        validate: impl Fn(&T) -> anyhow::Result<()> + Send + Sync + 'static,
+    ) where
+        T: DeserializeOwned + Send + Sync + 'static,
+    {
+        self.insert(path, false, sender, move |value| {
+            let value = T::deserialize(value)?;
+            validate(&value)?;
+            Ok(value)
+        });
+    }
+
+    /// Registers the secret field at `path`; new values are resolved and checked by `resolve` and
+    /// sent to `sender`. Values of the field are never logged or recorded in the audit log.
+    pub fn register_secret(
+        &mut self,
+        path: &str,
+        sender: watch::Sender<SecretString>,
+        resolve: impl Fn(&SecretString) -> anyhow::Result<SecretString> + Send + Sync + 'static,
+    ) {
+        self.insert(path, true, sender, move |value| {
+            let value = value.as_str().context("expected a string")?;
+            resolve(&value.into())
+        });
+    }
+
+    fn insert<T: Send + Sync + 'static>(
+        &mut self,
+        path: &str,
+        secret: bool,
+        sender: watch::Sender<T>,
+        parse: impl Fn(&Value) -> anyhow::Result<T> + Send + Sync + 'static,
+    ) {
+        let prepare: PrepareFn = Box::new(move |value| {
+            let value = parse(value)?;
+            let sender = sender.clone();
+            Ok(Box::new(move || {
+                sender.send_replace(value);
+            }))
+        });
+        let previous = self
+            .fields
+            .insert(path.to_owned(), ReloadableField { secret, prepare });
+        assert!(previous.is_none(), "`{path}` is registered twice");
+    }
+}
+
+/// Change of a single config field applied by a reload.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigChange {
+    pub field: String,
+    /// `null` if the field was not set in the config file.
+    pub old: Value,
+    pub new: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum FieldRejection {
+    /// The field can only be changed by restarting the node.
+    NotReloadable {
+        field: String,
+    },
+    Invalid {
+        field: String,
+        error: String,
+    },
+}
+
+impl fmt::Display for FieldRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotReloadable { field } => {
+                write!(f, "`{field}` cannot be changed without a restart")
+            }
+            Self::Invalid { field, error } => write!(f, "invalid value of `{field}`: {error}"),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReloadError {
+    #[error("failed to read config file: {0:#}")]
+    Read(anyhow::Error),
+    #[error("config reload rejected: {}", format_rejections(.0))]
+    Rejected(Vec<FieldRejection>),
+}
+
+fn format_rejections(rejections: &[FieldRejection]) -> String {
+    rejections
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Reloads hot-reloadable fields from the config file, see the module docs.
+#[derive(Debug)]
+pub struct ConfigReloader {
+    path: PathBuf,
+    registry: HotReloadRegistry,
+    /// Values of the config file applied so far.
+    current: Mutex<ConfigValues>,
+    audit_log: Option<Arc<AuditLog>>,
+}
+
+impl ConfigReloader {
+    /// Creates a reloader for the config file at `path`, as it was loaded at startup.
+    pub fn new(
+        path: PathBuf,
+        registry: HotReloadRegistry,
+        audit_log: Option<Arc<AuditLog>>,
+    ) -> anyhow::Result<Self> {
+        let current = flatten(read_config_file(&path)?);
+        Ok(Self {
+            path,
+            registry,
+            current: Mutex::new(current),
+            audit_log,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Re-reads the config file and applies changed fields. `source` identifies who requested
+    /// the reload in the audit log. Returns applied changes (with secrets redacted).
+    pub fn reload(&self, source: &str) -> Result<Vec<ConfigChange>, ReloadError> {
+        let new_values = read_config_file(&self.path)
+            .map(flatten)
+            .map_err(ReloadError::Read)?;
+        let mut current = self.current.lock().unwrap();
+
+        let mut changes = Vec::new();
+        let mut rejections = Vec::new();
+        let mut updates = Vec::new();
+        let fields: BTreeSet<_> = current.keys().chain(new_values.keys()).collect();
+        for field in fields {
+            let old = current.get(field).unwrap_or(&Value::Null);
+            let new = new_values.get(field).unwrap_or(&Value::Null);
+            if old == new {
+                continue;
+            }
+            let Some(reloadable) = self.registry.fields.get(field) else {
+                rejections.push(FieldRejection::NotReloadable {
+                    field: field.clone(),
+                });
+                continue;
+            };
+            let redact = |value: &Value| {
+                if reloadable.secret && !value.is_null() {
+                    json!(REDACTED)
+                } else {
+                    value.clone()
+                }
+            };
+            changes.push(ConfigChange {
+                field: field.clone(),
+                old: redact(old),
+                new: redact(new),
+            });
+            match (reloadable.prepare)(new) {
+                Ok(update) => updates.push(update),
+                Err(err) => rejections.push(FieldRejection::Invalid {
+                    field: field.clone(),
+                    // Errors of secret fields may echo the value
+                    error: if reloadable.secret {
+                        REDACTED.to_owned()
+                    } else {
+                        format!("{err:#}")
+                    },
+                }),
+            }
+        }
+
+        if !rejections.is_empty() {
+            let err = ReloadError::Rejected(rejections);
+            tracing::warn!(source, "{err}");
+            self.record(
+                source,
+                &changes,
+                AuditOutcome::Failure {
+                    error: err.to_string(),
+                },
+            );
+            return Err(err);
+        }
+        if changes.is_empty() {
+            tracing::debug!(source, "config file reloaded without changes");
+            return Ok(changes);
+        }
+        for update in updates {
+            update();
+        }
+        *current = new_values;
+        for change in &changes {
+            tracing::info!(
+                source,
+                field = %change.field,
+                old = %change.old,
+                new = %change.new,
+                "applied config change"
+            );
+        }
+        self.record(source, &changes, AuditOutcome::Success);
+        Ok(changes)
+    }
+
+    fn record(&self, source: &str, changes: &[ConfigChange], outcome: AuditOutcome) {
+        let Some(audit_log) = &self.audit_log else {
+            return;
+        };
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let params = json!({ "changes": changes });
+        if let Err(err) = audit_log.append(timestamp_ms, AUDIT_ACTION, params, source, outcome) {
+            tracing::error!("failed to record config reload: {err:#}");
+        }
+    }
+}
+
+/// Reloads the config file every time its modification time changes.
+pub async fn watch_config_file(reloader: Arc<ConfigReloader>, interval: Duration) {
+    let modified_at = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let mut last_modified = modified_at(reloader.path());
+    let mut interval = tokio::time::interval(interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let modified = modified_at(reloader.path());
+        if modified == last_modified {
+            continue;
+        }
+        last_modified = modified;
+        // Rejections are logged and recorded by the reloader
+        let _ = reloader.reload("config_file_watcher");
+    }
+}
+
+/// Source of config values layered over env variables at startup.
+pub fn config_file_source(path: &Path) -> anyhow::Result<Json> {
+    let Value::Object(values) = read_config_file(path)? else {
+        anyhow::bail!("config file `{}` must be a mapping", path.display());
+    };
+    Ok(Json::new(&path.display().to_string(), values))
+}
+
+fn read_config_file(path: &Path) -> anyhow::Result<Value> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("cannot read config file `{}`", path.display()))?;
+    let value = serde_yaml::from_str::<Option<Value>>(&text)
+        .with_context(|| format!("cannot parse config file `{}`", path.display()))?;
+    Ok(value.unwrap_or_else(|| Value::Object(Map::new())))
+}
+
+/// Flattens nested mappings into values by field path; other values (incl. lists) are leaves.
+fn flatten(value: Value) -> ConfigValues {
+    fn flatten_into(prefix: &str, value: Value, values: &mut ConfigValues) {
+        match value {
+            Value::Object(fields) if !fields.is_empty() => {
+                for (name, value) in fields {
+                    let path = if prefix.is_empty() {
+                        name
+                    } else {
+                        format!("{prefix}.{name}")
+                    };
+                    flatten_into(&path, value, values);
+                }
+            }
+            value => {
+                values.insert(prefix.to_owned(), value);
+            }
+        }
+    }
+
+    let mut values = ConfigValues::new();
+    if let Value::Object(fields) = value {
+        for (name, value) in fields {
+            flatten_into(&name, value, &mut values);
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smart_config::value::ExposeSecret;
+
+    struct Setup {
+        dir: tempfile::TempDir,
+        path: PathBuf,
+        audit_log: Arc<AuditLog>,
+        fee_floor: watch::Receiver<u64>,
+        blocklist: watch::Receiver<Vec<String>>,
+        token: watch::Receiver<SecretString>,
+        reloader: ConfigReloader,
+    }
+
+    const INITIAL: &str = "
+mempool:
+  fee_floor: 10
+  blocklist: [\"0x01\"]
+admin_api:
+  auth_token: old-token
+sequencer:
+  block_time: 250ms
+";
+
+    fn setup() -> Setup {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        std::fs::write(&path, INITIAL).unwrap();
+        let audit_log = Arc::new(AuditLog::open(&dir.path().join("audit.jsonl")).unwrap());
+
+        let (fee_floor_sender, fee_floor) = watch::channel(10);
+        let (blocklist_sender, blocklist) = watch::channel(vec!["0x01".to_owned()]);
+        let (token_sender, token) = watch::channel(SecretString::from("old-token"));
+        let mut registry = HotReloadRegistry::default();
+        registry.register("mempool.fee_floor", fee_floor_sender, |floor| {
+            anyhow::ensure!(*floor > 0, "fee floor must be positive");
+            Ok(())
+        });
+        registry.register("mempool.blocklist", blocklist_sender, |_| Ok(()));
+        registry.register_secret("admin_api.auth_token", token_sender, |token| {
+            Ok(token.clone())
+        });
+        let reloader =
+            ConfigReloader::new(path.clone(), registry, Some(audit_log.clone())).unwrap();
+        Setup {
+            dir,
+            path,
+            audit_log,
+            fee_floor,
+            blocklist,
+            token,
+            reloader,
+        }
+    }
+
+    fn write(setup: &Setup, replacements: &[(&str, &str)]) {
+        let config = replacements
+            .iter()
+            .fold(INITIAL.to_owned(), |config, (from, to)| {
+                config.replace(from, to)
+            });
+        std::fs::write(&setup.path, config).unwrap();
+    }
+
+    #[test]
+    fn multi_field_reload() {
+        let setup = setup();
+        assert_eq!(setup.reloader.reload("test").unwrap(), []);
+        assert_eq!(setup.audit_log.record_count(), 0);
+
+        write(
+            &setup,
+            &[
+                ("fee_floor: 10", "fee_floor: 20"),
+                ("[\"0x01\"]", "[\"0x01\", \"0x02\"]"),
+                ("old-token", "new-token"),
+            ],
+        );
+        let changes = setup.reloader.reload("test").unwrap();
+        let fields: Vec<_> = changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            [
+                "admin_api.auth_token",
+                "mempool.blocklist",
+                "mempool.fee_floor"
+            ]
+        );
+        assert_eq!(*setup.fee_floor.borrow(), 20);
+        assert_eq!(*setup.blocklist.borrow(), ["0x01", "0x02"]);
+        assert_eq!(setup.token.borrow().expose_secret(), "new-token");
+
+        // Reloading the same file again is a no-op
+        assert_eq!(setup.reloader.reload("test").unwrap(), []);
+    }
+
+    #[test]
+    fn non_reloadable_change_rejects_reload() {
+        let setup = setup();
+        write(
+            &setup,
+            &[
+                ("fee_floor: 10", "fee_floor: 20"),
+                ("block_time: 250ms", "block_time: 1s"),
+            ],
+        );
+        let err = setup.reloader.reload("test").unwrap_err();
+        let ReloadError::Rejected(rejections) = &err else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(
+            *rejections,
+            [FieldRejection::NotReloadable {
+                field: "sequencer.block_time".to_owned()
+            }]
+        );
+        assert_eq!(*setup.fee_floor.borrow(), 10);
+
+        // Reverting the non-reloadable change lets the rest be applied
+        write(&setup, &[("fee_floor: 10", "fee_floor: 20")]);
+        setup.reloader.reload("test").unwrap();
+        assert_eq!(*setup.fee_floor.borrow(), 20);
+    }
+
+    #[test]
+    fn invalid_value_rejects_reload() {
+        let setup = setup();
+        write(
+            &setup,
+            &[
+                ("fee_floor: 10", "fee_floor: 0"),
+                ("[\"0x01\"]", "[]"),
+                ("old-token", "[1]"),
+            ],
+        );
+        let err = setup.reloader.reload("test").unwrap_err();
+        let ReloadError::Rejected(rejections) = &err else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(
+            *rejections,
+            [
+                FieldRejection::Invalid {
+                    field: "admin_api.auth_token".to_owned(),
+                    error: REDACTED.to_owned(),
+                },
+                FieldRejection::Invalid {
+                    field: "mempool.fee_floor".to_owned(),
+                    error: "fee floor must be positive".to_owned(),
+                },
+            ]
+        );
+        assert_eq!(*setup.fee_floor.borrow(), 10);
+        assert_eq!(*setup.blocklist.borrow(), ["0x01"]);
+        assert_eq!(setup.token.borrow().expose_secret(), "old-token");
+    }
+
+    #[test]
+    fn reloads_are_audited() {
+        let setup = setup();
+        write(
+            &setup,
+            &[
+                ("fee_floor: 10", "fee_floor: 20"),
+                ("old-token", "new-token"),
+            ],
+        );
+        setup.reloader.reload("ops").unwrap();
+        write(&setup, &[("block_time: 250ms", "block_time: 1s")]);
+        setup.reloader.reload("ops").unwrap_err();
+
+        let records = setup.audit_log.page(0, 10).unwrap().records;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].action, AUDIT_ACTION);
+        assert_eq!(records[0].caller, "ops");
+        assert_eq!(records[0].outcome, AuditOutcome::Success);
+        assert_eq!(
+            records[0].params,
+            json!({ "changes": [
+                { "field": "admin_api.auth_token", "old": REDACTED, "new": REDACTED },
+                { "field": "mempool.fee_floor", "old": 10, "new": 20 },
+            ]})
+        );
+        let log = std::fs::read_to_string(setup.dir.path().join("audit.jsonl")).unwrap();
+        assert!(!log.contains("old-token") && !log.contains("new-token"));
+
+        // Rejected reloads are recorded with the reason
+        let AuditOutcome::Failure { error } = &records[1].outcome else {
+            panic!("unexpected outcome: {:?}", records[1].outcome);
+        };
+        assert!(
+            error.contains("`sequencer.block_time` cannot be changed"),
+            "{error}"
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! Startup sanity check of the L1 contracts the node is about to work with.
+//!
+//! A misconfigured bridgehub or chain ID otherwise only shows up as confusing downstream failures
+//! (missing genesis upgrade log, mismatching priority ids etc.), so we fail fast with a message that
+//! names what was configured, what was found on L1 and what was expected.
+
+use alloy::primitives::{Address, B256, keccak256};
+use alloy::providers::{DynProvider, Provider};
+use anyhow::Context;
+use std::collections::HashSet;
+use zksync_os_contract_interface::ZkChain;
+
+/// Values the diamond proxy is expected to hold.
+#[derive(Debug, Clone)]
+pub struct ExpectedL1Contracts {
+    pub chain_id: u64,
+    pub bridgehub: Address,
+    /// Known-good facet code hashes. Empty means any code is accepted.
+    pub allowed_facet_code_hashes: HashSet<B256>,
+}
+
+impl ExpectedL1Contracts {
+    pub fn new(
+        chain_id: u64,
+        bridgehub: Address,
+        allowed_facet_code_hashes: &[String],
+    ) -> anyhow::Result<Self> {
+        let allowed_facet_code_hashes = allowed_facet_code_hashes
+            .iter()
+            .map(|hash| {
+                hash.parse()
+                    .with_context(|| format!("invalid facet code hash `{hash}`"))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            chain_id,
+            bridgehub,
+            allowed_facet_code_hashes,
+        })
+    }
+}
+
+/// Checks that the diamond proxy belongs to the configured chain and, if an allowlist is configured,
+/// that it only delegates to known facets.
+pub async fn validate_l1_contracts(
+    zk_chain: &ZkChain<DynProvider>,
+    expected: &ExpectedL1Contracts,
+) -> anyhow::Result<()> {
+    let address = *zk_chain.address();
+
+    let chain_id = zk_chain
+        .get_chain_id()
+        .await
+        .with_context(|| format!("failed to read chain ID from diamond proxy {address}"))?;
+    anyhow::ensure!(
+        chain_id == expected.chain_id,
+        "diamond proxy {address} resolved for the configured chain stores chain ID {chain_id}, \
+         expected {}; check `genesis.bridgehub_address` and `genesis.chain_id`",
+        expected.chain_id
+    );
+
+    let bridgehub = zk_chain
+        .get_bridgehub()
+        .await
+        .with_context(|| format!("failed to read bridgehub from diamond proxy {address}"))?;
+    anyhow::ensure!(
+        bridgehub == expected.bridgehub,
+        "diamond proxy {address} references bridgehub {bridgehub}, expected the configured {}",
+        expected.bridgehub
+    );
+
+    let genesis_batch_hash = zk_chain
+        .stored_batch_hash(0)
+        .await
+        .with_context(|| format!("failed to read genesis batch from diamond proxy {address}"))?;
+    anyhow::ensure!(
+        !genesis_batch_hash.is_zero(),
+        "diamond proxy {address} has no genesis batch stored; the chain was not initialized"
+    );
+
+    if expected.allowed_facet_code_hashes.is_empty() {
+        return Ok(());
+    }
+    let facets = zk_chain
+        .facet_addresses()
+        .await
+        .with_context(|| format!("failed to read facets of diamond proxy {address}"))?;
+    for facet in facets {
+        let code = zk_chain
+            .provider()
+            .get_code_at(facet)
+            .await
+            .with_context(|| format!("failed to read code of facet {facet}"))?;
+        let code_hash = keccak256(&code);
+        anyhow::ensure!(
+            expected.allowed_facet_code_hashes.contains(&code_hash),
+            "facet {facet} of diamond proxy {address} has code hash {code_hash}, which is not in \
+             `genesis.allowed_facet_code_hashes` (expected one of {:?})",
+            expected.allowed_facet_code_hashes
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Bytes, U256, address};
+    use alloy::providers::ProviderBuilder;
+    use alloy::sol_types::SolValue;
+    use alloy::transports::mock::Asserter;
+
+    const CHAIN_ID: u64 = 270;
+    const DIAMOND_PROXY: Address = address!("0x1000000000000000000000000000000000000001");
+    const BRIDGEHUB: Address = address!("0x2000000000000000000000000000000000000002");
+    const FACET: Address = address!("0x3000000000000000000000000000000000000003");
+
+    fn mock_zk_chain(asserter: &Asserter) -> ZkChain<DynProvider> {
+        let provider = ProviderBuilder::new()
+            .connect_mocked_client(asserter.clone())
+            .erased();
+        ZkChain::new(DIAMOND_PROXY, provider)
+    }
+
+    /// Pushes responses for the calls of [`validate_l1_contracts`], in order.
+    fn push_l1_state(asserter: &Asserter, chain_id: u64, facet_code: Option<&[u8]>) {
+        asserter.push_success(&Bytes::from(U256::from(chain_id).abi_encode()));
+        asserter.push_success(&Bytes::from(BRIDGEHUB.abi_encode()));
+        asserter.push_success(&Bytes::from(B256::repeat_byte(1).abi_encode()));
+        if let Some(code) = facet_code {
+            asserter.push_success(&Bytes::from(vec![FACET].abi_encode()));
+            asserter.push_success(&Bytes::copy_from_slice(code));
+        }
+    }
+
+    fn expected(allowed_facet_code_hashes: &[B256]) -> ExpectedL1Contracts {
+        let hashes: Vec<_> = allowed_facet_code_hashes
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        ExpectedL1Contracts::new(CHAIN_ID, BRIDGEHUB, &hashes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn matching_contracts() {
+        let facet_code = b"facet code";
+        let asserter = Asserter::new();
+        push_l1_state(&asserter, CHAIN_ID, None);
+        validate_l1_contracts(&mock_zk_chain(&asserter), &expected(&[]))
+            .await
+            .unwrap();
+
+        push_l1_state(&asserter, CHAIN_ID, Some(facet_code));
+        validate_l1_contracts(
+            &mock_zk_chain(&asserter),
+            &expected(&[keccak256(facet_code)]),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn chain_id_mismatch() {
+        let asserter = Asserter::new();
+        push_l1_state(&asserter, 271, None);
+        let err = validate_l1_contracts(&mock_zk_chain(&asserter), &expected(&[]))
+            .await
+            .unwrap_err();
+        let message = format!("{err:#}");
+        assert!(message.contains(&DIAMOND_PROXY.to_string()), "{message}");
+        assert!(message.contains("chain ID 271"), "{message}");
+        assert!(message.contains("expected 270"), "{message}");
+    }
+
+    #[tokio::test]
+    async fn unknown_facet_code_hash() {
+        let asserter = Asserter::new();
+        push_l1_state(&asserter, CHAIN_ID, Some(b"unexpected code"));
+        let known_hash = keccak256(b"facet code");
+        let err = validate_l1_contracts(&mock_zk_chain(&asserter), &expected(&[known_hash]))
+            .await
+            .unwrap_err();
+        let message = format!("{err:#}");
+        assert!(message.contains(&FACET.to_string()), "{message}");
+        assert!(
+            message.contains(&keccak256(b"unexpected code").to_string()),
+            "{message}"
+        );
+        assert!(message.contains(&known_hash.to_string()), "{message}");
+    }
+}
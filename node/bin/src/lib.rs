@@ -1,28 +1,45 @@
 #![feature(allocator_api)]
 #![allow(incomplete_features)]
 #![feature(generic_const_exprs)]
+mod account_keys_indexer;
+pub mod admin;
+pub mod backup;
 mod batch_sink;
 pub mod batcher;
+pub mod block_transfer;
 mod command_source;
 pub mod config;
+pub mod config_reload;
 mod en_remote_config;
 mod l1_provider;
+mod l1_validation;
 pub mod metadata;
+pub mod migrations;
 mod node_state_on_startup;
 mod priority_tree_steps;
 pub mod prover_api;
 mod prover_input_generator;
 mod replay_transport;
+pub mod secrets;
 mod state_initializer;
 pub mod tree_manager;
+mod verification_block_source;
 pub mod zkstack_config;
 
+use crate::account_keys_indexer::{AccountKeysIndexer, backfill_account_keys};
+use crate::admin::{AdminHooks, AdminNamespace, AuditLog, run_admin_server};
+use crate::backup::{BackupScheduler, BackupTarget};
 use crate::batch_sink::{BatchSink, NoOpSink};
 use crate::batcher::{Batcher, BatcherStartupConfig, util::load_genesis_stored_batch_info};
 use crate::command_source::{ExternalNodeCommandSource, MainNodeCommandSource};
-use crate::config::{Config, ProverApiConfig, gas_adjuster_config};
+use crate::config::{
+    BaseTokenRateSource, Config, ProverApiConfig, RepositoryRetentionMode, gas_adjuster_config,
+    pubdata_mode,
+};
+use crate::config_reload::{ConfigReloader, HotReloadRegistry, watch_config_file};
 use crate::en_remote_config::load_remote_config;
 use crate::l1_provider::build_node_l1_provider;
+use crate::l1_validation::{ExpectedL1Contracts, validate_l1_contracts};
 use crate::metadata::NODE_VERSION;
 use crate::node_state_on_startup::NodeStateOnStartup;
 use crate::priority_tree_steps::priority_tree_en_step::PriorityTreeENStep;
@@ -34,11 +51,17 @@ use crate::prover_api::gapless_committer::GaplessCommitter;
 use crate::prover_api::proof_storage::ProofStorage;
 use crate::prover_api::prover_server;
 use crate::prover_api::snark_job_manager::{FakeSnarkProver, SnarkJobManager};
+use crate::prover_api::snark_proof_verifier::SnarkProofVerification;
 use crate::prover_api::snark_proving_pipeline_step::SnarkProvingPipelineStep;
 use crate::prover_input_generator::ProverInputGenerator;
-use crate::replay_transport::replay_server;
+use crate::replay_transport::{
+    PeerFallback, PeerFallbackConfig, PeerSyncServerConfig, ReplayServerLimits, replay_server,
+    run_peer_sync_server,
+};
+use crate::secrets::resolve_auth_token;
 use crate::state_initializer::StateInitializer;
 use crate::tree_manager::TreeManager;
+use crate::verification_block_source::LocalVerificationBlocks;
 use alloy::network::EthereumWallet;
 use alloy::providers::{Provider, WalletProvider};
 use anyhow::Result;
@@ -46,29 +69,49 @@ use futures::FutureExt;
 use ruint::aliases::U256;
 use std::path::Path;
 use std::sync::Arc;
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::watch;
 use tokio::task::JoinSet;
 use zksync_os_batch_verification::{BatchVerificationClient, BatchVerificationPipelineStep};
 use zksync_os_contract_interface::l1_discovery::L1State;
 use zksync_os_contract_interface::models::StoredBatchInfo;
-use zksync_os_gas_adjuster::GasAdjuster;
-use zksync_os_genesis::{FileGenesisInputSource, Genesis, GenesisInputSource};
+use zksync_os_gas_adjuster::{
+    BaseTokenConversionRatio, BaseTokenRateProvider, BaseTokenRateUpdater, GasAdjuster,
+    GasAdjusterSnapshot, PubdataComposition,
+};
+use zksync_os_genesis::{
+    FileGenesisInputSource, Genesis, GenesisInputSource, UrlGenesisInputSource,
+};
 use zksync_os_interface::types::BlockHashes;
-use zksync_os_l1_sender::batcher_model::BatchMetadata;
+use zksync_os_l1_sender::batcher_model::{BatchMetadata, L1FinalitySnapshot, L1RevertStatus};
 use zksync_os_l1_sender::commands::commit::CommitCommand;
 use zksync_os_l1_sender::commands::prove::ProofCommand;
+use zksync_os_l1_sender::commit_scheduler::CommitScheduler;
+use zksync_os_l1_sender::cost_accounting::{L1CostSummary, L1CostTracker};
 use zksync_os_l1_sender::pipeline_component::L1Sender;
-use zksync_os_l1_watcher::{L1CommitWatcher, L1ExecuteWatcher, L1TxWatcher, util};
-use zksync_os_mempool::L2TransactionPool;
+use zksync_os_l1_sender::price_drift::PriceDriftConfig;
+use zksync_os_l1_sender::watchdog::{L1SenderWatchdog, LocalProgress, WatchdogStatus};
+use zksync_os_l1_watcher::{
+    GovernanceStatus, L1CommitWatcher, L1ExecuteWatcher, L1FinalityTracker, L1GovernanceWatcher,
+    L1RevertWatcher, L1TxWatcher, NodeGovernanceParams, PriorityExpiryMonitor, util,
+};
+use zksync_os_mempool::{L2TransactionPool, SpamScores};
 use zksync_os_merkle_tree::{MerkleTree, RocksDBWrapper};
+use zksync_os_multivm::{ExecutionVersion, LATEST_EXECUTION_VERSION};
 use zksync_os_object_store::ObjectStoreFactory;
 use zksync_os_observability::GENERAL_METRICS;
 use zksync_os_pipeline::Pipeline;
 use zksync_os_revm_consistency_checker::node::RevmConsistencyChecker;
+use zksync_os_rocksdb::RocksDBOptions;
 use zksync_os_rpc::{RpcStorage, run_jsonrpsee_server};
 use zksync_os_sequencer::execution::Sequencer;
-use zksync_os_sequencer::execution::block_context_provider::BlockContextProvider;
+use zksync_os_sequencer::execution::block_context_provider::{
+    BaseTokenPricing, BlockContextProvider,
+};
+use zksync_os_sequencer::execution::bundles::{BundleStore, BundleStoreConfig};
+use zksync_os_sequencer::execution::shadow::{MultivmShadowRunner, ShadowExecution};
+use zksync_os_socket::ListenerLimits;
+use zksync_os_state::{ACCOUNT_KEYS_DB_NAME, AccountKeysIndex};
 use zksync_os_status_server::run_status_server;
 use zksync_os_storage::db::BlockReplayStorage;
 use zksync_os_storage::in_memory::Finality;
@@ -77,12 +120,21 @@ use zksync_os_storage_api::{
     FinalityStatus, ReadBatch, ReadFinality, ReadReplay, ReadRepository, ReadStateHistory,
     WriteReplay, WriteRepository, WriteState,
 };
-use zksync_os_types::{NotAcceptingReason, TransactionAcceptanceState};
+use zksync_os_types::{
+    NotAcceptingReason, PriorityDeadlines, ReplayDivergenceStatus, ShadowExecutionStatus,
+    TransactionAcceptanceState,
+};
 
 const BLOCK_REPLAY_WAL_DB_NAME: &str = "block_replay_wal";
 const STATE_TREE_DB_NAME: &str = "tree";
 const PRIORITY_TREE_DB_NAME: &str = "priority_txs_tree";
 const REPOSITORY_DB_NAME: &str = "repository";
+/// How often mempool spam scores are saved to disk.
+const SPAM_SCORES_PERSIST_PERIOD: Duration = Duration::from_secs(30);
+/// How often scheduled transactions are checked for promotion while no blocks are produced.
+const SCHEDULED_TRANSACTIONS_PROMOTION_PERIOD: Duration = Duration::from_secs(1);
+/// How often the mempool is scanned for expired transactions.
+const MEMPOOL_MAINTENANCE_PERIOD: Duration = Duration::from_secs(60);
 
 #[allow(clippy::too_many_arguments)]
 pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone>(
@@ -107,17 +159,39 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
         unimplemented!("running without L1 Senders is temporarily not supported");
     }
     tracing::info!(version = %node_version, role, "Initializing Node");
+    if config.general_config.rocksdb_statistics {
+        RocksDBOptions::enable_statistics_by_default();
+    }
 
     let (bridgehub_address, chain_id, genesis_input_source) =
         if config.sequencer_config.is_main_node() {
+            let genesis_config = &config.genesis_config;
             let genesis_input_source: Arc<dyn GenesisInputSource> =
-                Arc::new(FileGenesisInputSource::new(
-                    config
-                        .genesis_config
-                        .genesis_input_path
+                if let Some(url) = genesis_config.genesis_input_url.clone() {
+                    let cache_path = genesis_config
+                        .genesis_input_cache_path
                         .clone()
-                        .expect("Missing `genesis_input_path`"),
-                ));
+                        .unwrap_or_else(|| {
+                            config
+                                .general_config
+                                .rocks_db_path
+                                .join("genesis_input.json")
+                        });
+                    Arc::new(UrlGenesisInputSource::new(
+                        url,
+                        genesis_config
+                            .genesis_input_sha256
+                            .expect("Missing `genesis_input_sha256` for `genesis_input_url`"),
+                        cache_path,
+                    ))
+                } else {
+                    Arc::new(FileGenesisInputSource::new(
+                        genesis_config
+                            .genesis_input_path
+                            .clone()
+                            .expect("Missing `genesis_input_path`"),
+                    ))
+                };
             (
                 config
                     .genesis_config
@@ -148,12 +222,15 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
     let (l1_transactions_sender, l1_transactions_for_sequencer) = tokio::sync::mpsc::channel(5);
 
     tracing::info!("Initializing BatchStorage");
-    let batch_storage = ProofStorage::new(
+    let mut batch_storage = ProofStorage::new(
         ObjectStoreFactory::new(config.prover_api_config.object_store.clone())
             .create_store()
             .await
             .unwrap(),
     );
+    if config.prover_api_config.namespace_storage_by_chain_id {
+        batch_storage = batch_storage.with_chain_namespace(chain_id);
+    }
 
     // This is the only place where we initialize L1 provider, every component shares the same
     // cloned provider.
@@ -173,10 +250,36 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
     tracing::info!(?l1_state, "L1 state");
     l1_state.report_metrics();
 
+    if config.genesis_config.skip_l1_validation {
+        tracing::warn!("skipping validation of L1 contracts as requested by config");
+    } else {
+        let expected = ExpectedL1Contracts::new(
+            chain_id,
+            bridgehub_address,
+            &config.genesis_config.allowed_facet_code_hashes,
+        )
+        .expect("invalid `allowed_facet_code_hashes`");
+        validate_l1_contracts(&l1_state.diamond_proxy, &expected)
+            .await
+            .expect("L1 contracts do not match node configuration");
+        tracing::info!("L1 contracts validated");
+    }
+
+    // Must run before any DB is opened: a restored backup that fails verification is deleted
+    let checkpoint = backup::checkpoint::verify_restored_checkpoint(
+        &config.general_config.rocks_db_path,
+        &l1_state.diamond_proxy,
+    )
+    .await
+    .expect("restored backup failed verification against L1");
+    migrations::run_migrations::<State>(&config.general_config)
+        .expect("failed to migrate databases");
+
     let genesis = Genesis::new(
         genesis_input_source.clone(),
         l1_state.diamond_proxy.clone(),
         chain_id,
+        config.genesis_config.genesis_upgrade_block_hint,
     );
 
     tracing::info!("Initializing BlockReplayStorage");
@@ -195,8 +298,21 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
     let tree_db = TreeManager::load_or_initialize_tree(
         Path::new(&config.general_config.rocks_db_path.join(STATE_TREE_DB_NAME)),
         &genesis,
+        block_replay_storage.latest_record(),
+        config.general_config.tree_rebuild_on_corruption,
     )
     .await;
+    let genesis_state = genesis
+        .state()
+        .await
+        .expect("Failed to build genesis state");
+    match genesis_state.verify_root(&tree_db) {
+        Ok(()) => tracing::info!("Genesis root verified"),
+        Err(err) if config.genesis_config.allow_unverified_genesis => {
+            tracing::warn!("{err:#}; starting anyway as `allow_unverified_genesis` is set");
+        }
+        Err(err) => panic!("genesis verification failed: {err:#}"),
+    }
 
     tracing::info!("Initializing RepositoryManager");
     let repositories = RepositoryManager::new(
@@ -207,14 +323,51 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
     .await;
 
     let state = State::new(&config.general_config, &genesis).await;
+    let account_keys_index = config.general_config.account_keys_index_enabled.then(|| {
+        AccountKeysIndex::new(
+            &config
+                .general_config
+                .rocks_db_path
+                .join(ACCOUNT_KEYS_DB_NAME),
+        )
+        .expect("failed to open account keys index")
+    });
 
     tracing::info!("Initializing mempools");
-    let l2_mempool = zksync_os_mempool::in_memory(
+    // Transactions are validated against the execution version of the latest block; the mempool
+    // is switched to newer versions by the sequencer once upgraded blocks are executed.
+    let execution_version = block_replay_storage
+        .get_context(block_replay_storage.latest_record())
+        .and_then(|context| ExecutionVersion::try_from(context.execution_version).ok())
+        .unwrap_or(LATEST_EXECUTION_VERSION);
+    let spam_scores_path = config
+        .mempool_config
+        .spam_scores_path
+        .clone()
+        .unwrap_or_else(|| {
+            config
+                .general_config
+                .rocks_db_path
+                .join("mempool_spam_scores.json")
+        });
+    let spam_scores = SpamScores::open(
+        &spam_scores_path,
+        config.mempool_config.spam_scoring_config(),
+    )
+    .expect("failed to load mempool spam scores");
+    let (l2_mempool, pool_persistence) = zksync_os_mempool::in_memory_with_persistence(
         state.clone(),
         repositories.clone(),
         chain_id,
         config.mempool_config.clone().into(),
-        config.tx_validator_config.clone().into(),
+        config
+            .tx_validator_config
+            .clone()
+            .into_lib_tx_validator_config(execution_version),
+        spam_scores.clone(),
+        config
+            .mempool_config
+            .pool_persistence_config(&config.general_config.rocks_db_path),
     );
 
     let (last_l1_committed_block, last_l1_proved_block, last_l1_executed_block) =
@@ -288,8 +441,47 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
 
     node_startup_state.assert_consistency();
 
-    tracing::info!("Initializing L1 Watchers");
+    let backup_scheduler = config.backup_config.enabled.then(|| {
+        // Backed up in this order; see `backup` module docs
+        let targets = vec![
+            BackupTarget::new(STATE_TREE_DB_NAME, {
+                let tree = tree_db.clone();
+                move || tree.latest_version().ok().flatten()
+            }),
+            BackupTarget::new(REPOSITORY_DB_NAME, {
+                let repositories = repositories.clone();
+                move || Some(repositories.persisted_block())
+            }),
+            BackupTarget::new(State::STORAGE_DB_NAME, {
+                let state = state.clone();
+                move || Some(state.persisted_block())
+            }),
+            BackupTarget::new(State::PREIMAGES_DB_NAME, || None),
+            BackupTarget::new(PRIORITY_TREE_DB_NAME, || None),
+            BackupTarget::new(BLOCK_REPLAY_WAL_DB_NAME, {
+                let block_replay_storage = block_replay_storage.clone();
+                move || Some(block_replay_storage.latest_record())
+            }),
+        ];
+        BackupScheduler::new(
+            config.backup_config.clone(),
+            config.general_config.rocks_db_path.clone(),
+            targets,
+        )
+    });
+
     let mut tasks: JoinSet<()> = JoinSet::new();
+    if let Some(index) = &account_keys_index {
+        // Blocks from `starting_block` on are indexed by the pipeline
+        tasks.spawn(backfill_account_keys(
+            index.clone(),
+            state.clone(),
+            block_replay_storage.clone(),
+            starting_block - 1,
+        ));
+    }
+
+    tracing::info!("Initializing L1 Watchers");
     tasks.spawn(
         L1CommitWatcher::new(
             config.l1_watcher_config.clone().into(),
@@ -303,6 +495,21 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
         .map(report_exit("L1 commit watcher")),
     );
 
+    let (l1_revert_sender, _) = watch::channel(L1RevertStatus::None);
+    tasks.spawn(
+        L1RevertWatcher::new(
+            config.l1_watcher_config.clone().into(),
+            node_startup_state.l1_state.diamond_proxy.clone(),
+            finality_storage.clone(),
+            batch_storage.clone(),
+            l1_revert_sender.clone(),
+        )
+        .await
+        .expect("failed to start L1 revert watcher")
+        .run()
+        .map(report_exit("L1 revert watcher")),
+    );
+
     tasks.spawn(
         L1ExecuteWatcher::new(
             config.l1_watcher_config.clone().into(),
@@ -326,12 +533,14 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
         .as_ref()
         .map_or(0, |record| record.starting_l1_priority_id);
 
+    let priority_deadlines = PriorityDeadlines::default();
     tasks.spawn(
         L1TxWatcher::new(
             config.l1_watcher_config.clone().into(),
             node_startup_state.l1_state.diamond_proxy.clone(),
             l1_transactions_sender,
             next_l1_priority_id,
+            priority_deadlines.clone(),
         )
         .await
         .expect("failed to start L1 transaction watcher")
@@ -339,11 +548,72 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
         .map(report_exit("L1 transaction watcher")),
     );
 
+    // External nodes don't process priority transactions on their own, so there is nothing to alert on
+    if config.sequencer_config.is_main_node() {
+        tasks.spawn(
+            PriorityExpiryMonitor::new(
+                priority_deadlines.clone(),
+                batch_storage.clone(),
+                next_l1_priority_id,
+                config.l1_watcher_config.priority_expiry_warning_threshold,
+                config.l1_watcher_config.priority_expiry_critical_threshold,
+            )
+            .await
+            .expect("failed to start priority transaction expiry monitor")
+            .run()
+            .map(report_exit("Priority transaction expiry monitor")),
+        );
+    }
+
+    let (governance_status_sender, governance_status_receiver) =
+        watch::channel(GovernanceStatus::default());
+    let operator_addresses = if config.sequencer_config.is_main_node() {
+        config.l1_sender_config.operator_addresses()
+    } else {
+        Vec::new()
+    };
+    tasks.spawn(
+        L1GovernanceWatcher::new(
+            config.l1_watcher_config.clone().into(),
+            node_startup_state.l1_state.diamond_proxy.clone(),
+            node_startup_state.l1_state.validator_timelock,
+            NodeGovernanceParams {
+                operator_addresses,
+                da_input_mode: node_startup_state.l1_state.da_input_mode,
+            },
+            governance_status_sender,
+        )
+        .await
+        .expect("failed to start L1 governance watcher")
+        .run()
+        .map(report_exit("L1 governance watcher")),
+    );
+
+    // Only populated on the main node - external nodes do not send L1 transactions
+    let (l1_finality_sender, l1_finality_receiver) = watch::channel(L1FinalitySnapshot::default());
+    let (l1_costs_sender, l1_costs_receiver) = watch::channel(L1CostSummary::default());
+    let (watchdog_status_sender, watchdog_status_receiver) =
+        watch::channel(WatchdogStatus::default());
+    let (replay_subscribers_sender, replay_subscribers_receiver) = watch::channel(Vec::new());
+    let (replay_divergence_sender, replay_divergence_receiver) =
+        watch::channel(ReplayDivergenceStatus::None);
+    let (shadow_execution_sender, shadow_execution_receiver) =
+        watch::channel(ShadowExecutionStatus::default());
+
     // ======== Start Status Server ========
     tasks.spawn(
         run_status_server(
             config.status_server_config.address.clone(),
+            config.status_server_config.request_timeout,
             _stop_receiver.clone(),
+            l1_finality_receiver,
+            l1_costs_receiver,
+            watchdog_status_receiver,
+            replay_subscribers_receiver,
+            replay_divergence_receiver,
+            shadow_execution_receiver,
+            checkpoint,
+            governance_status_receiver,
         )
         .map(report_exit("Status server")),
     );
@@ -370,6 +640,25 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
             ))
         };
 
+    if let Some(pool_persistence) = pool_persistence {
+        tasks.spawn(
+            pool_persistence
+                .run()
+                .map(|()| tracing::warn!("pool_persistence.run() unexpectedly exited")),
+        );
+    }
+    let l2_mempool_clone = l2_mempool.clone();
+    tasks.spawn(async move {
+        l2_mempool_clone
+            .run_schedule_promotion_loop(SCHEDULED_TRANSACTIONS_PROMOTION_PERIOD)
+            .await;
+        tracing::warn!("l2_mempool.run_schedule_promotion_loop() unexpectedly exited");
+    });
+    tasks.spawn(
+        zksync_os_mempool::run_pool_maintenance(l2_mempool.clone(), MEMPOOL_MAINTENANCE_PERIOD)
+            .map(|()| tracing::warn!("run_pool_maintenance() unexpectedly exited")),
+    );
+
     let (pending_block_context_sender, pending_block_context_receiver) = watch::channel(None);
     tasks.spawn(
         run_jsonrpsee_server(
@@ -387,6 +676,9 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
 
     tracing::info!("Initializing pubdata price provider");
     let (pubdata_price_sender, pubdata_price_receiver) = watch::channel(None);
+    let mut base_token_pricing = None;
+    let mut pubdata_composition = None;
+    let mut l1_price_predictions = None;
     if config.sequencer_config.is_main_node() {
         let gas_adjuster_config = gas_adjuster_config(
             config.gas_adjuster_config.clone(),
@@ -394,14 +686,47 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
             config.l1_sender_config.rollup_pubdata_mode,
             config.l1_sender_config.max_priority_fee_per_gas_gwei,
         );
-        let gas_adjuster = GasAdjuster::new(
-            l1_provider.clone().erased(),
-            gas_adjuster_config,
-            pubdata_price_sender,
-        )
-        .await
-        .unwrap();
+        let mut fee_providers = vec![l1_provider.clone().erased()];
+        for url in &config.gas_adjuster_config.fallback_l1_rpc_urls {
+            fee_providers.push(build_node_l1_provider(url).await.erased());
+        }
+        let gas_adjuster =
+            GasAdjuster::new(fee_providers, gas_adjuster_config, pubdata_price_sender)
+                .await
+                .unwrap();
+        pubdata_composition = Some(gas_adjuster.pubdata_composition());
+        l1_price_predictions = Some(gas_adjuster.subscribe());
         tasks.spawn(gas_adjuster.run().map(report_exit("Gas adjuster server")));
+
+        let base_token_config = &config.gas_adjuster_config.base_token;
+        let rate_provider: Option<Box<dyn BaseTokenRateProvider>> =
+            match base_token_config.rate_source {
+                BaseTokenRateSource::Eth => None,
+                BaseTokenRateSource::Static => Some(Box::new(
+                    BaseTokenConversionRatio::new(
+                        base_token_config.static_ratio_numerator.into(),
+                        base_token_config.static_ratio_denominator.into(),
+                    )
+                    .expect("invalid static base token ratio"),
+                )),
+                BaseTokenRateSource::L1 => Some(Box::new(l1_state.diamond_proxy.clone())),
+            };
+        if let Some(rate_provider) = rate_provider {
+            tracing::info!(source = ?base_token_config.rate_source, "Initializing base token rate updater");
+            let (rate_sender, rate_receiver) = watch::channel(None);
+            let updater = BaseTokenRateUpdater::new(
+                rate_provider,
+                base_token_config.poll_period,
+                rate_sender,
+            )
+            .await
+            .unwrap();
+            tasks.spawn(updater.run().map(report_exit("Base token rate updater")));
+            base_token_pricing = Some(BaseTokenPricing {
+                rate_provider: rate_receiver,
+                max_rate_staleness: base_token_config.max_rate_staleness,
+            });
+        }
     }
 
     // ========== Start BlockContextProvider and its state ===========
@@ -410,6 +735,9 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
     let previous_block_timestamp: u64 = first_replay_record
         .as_ref()
         .map_or(0, |record| record.previous_block_timestamp); // if no previous block, assume genesis block
+    let previous_block_timestamp_millis = block_replay_storage
+        .get_block_timestamp_millis(starting_block - 1)
+        .unwrap_or(0);
 
     let block_hashes_for_next_block = first_replay_record
         .as_ref()
@@ -417,14 +745,33 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
         .unwrap_or_else(|| block_hashes_for_first_block(&repositories));
 
     let genesis = Arc::new(genesis);
+    let bundle_store = (config.sequencer_config.is_main_node()
+        && !config.sequencer_config.bundle_signers.is_empty())
+    .then(|| {
+        let allowed_signers = config
+            .sequencer_config
+            .bundle_signers
+            .iter()
+            .map(|signer| signer.parse().expect("invalid bundle signer address"))
+            .collect();
+        BundleStore::new(BundleStoreConfig {
+            allowed_signers,
+            max_pending_bundles: config.sequencer_config.max_pending_bundles,
+            max_bundle_transactions: config.sequencer_config.max_bundle_transactions,
+        })
+    });
     // todo: `BlockContextProvider` initialization and its dependencies
     // should be moved to `sequencer`
     let block_context_provider = BlockContextProvider::new(
         next_l1_priority_id,
         l1_transactions_for_sequencer,
+        priority_deadlines.clone(),
+        config.l1_watcher_config.priority_expiry_critical_threshold,
         l2_mempool,
         block_hashes_for_next_block,
         previous_block_timestamp,
+        previous_block_timestamp_millis,
+        config.sequencer_config.strict_block_timestamps,
         chain_id,
         config.sequencer_config.block_gas_limit,
         config.sequencer_config.block_pubdata_limit_bytes,
@@ -435,14 +782,54 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
         config.sequencer_config.pubdata_price_override,
         config.sequencer_config.native_price_override,
         pubdata_price_receiver,
+        base_token_pricing,
         pending_block_context_sender,
+        bundle_store.clone(),
     );
 
     // ========== Start Sequencer ===========
+    let replay_server_limits = ReplayServerLimits {
+        max_backfill_readers: config
+            .sequencer_config
+            .block_replay_server_max_backfill_readers,
+        max_records_per_second: config
+            .sequencer_config
+            .block_replay_server_max_records_per_second,
+        max_bytes_per_second: config
+            .sequencer_config
+            .block_replay_server_max_bytes_per_second,
+        cache_size: config.sequencer_config.block_replay_server_cache_size,
+        listener: ListenerLimits {
+            max_connections: config.sequencer_config.block_replay_server_max_connections,
+            handshake_timeout: config
+                .sequencer_config
+                .block_replay_server_handshake_timeout,
+        },
+        max_frame_bytes: config.sequencer_config.block_replay_max_frame_bytes,
+    };
+    if config.sequencer_config.peer_sync_server_enabled {
+        tasks.spawn(
+            run_peer_sync_server(
+                block_replay_storage.clone(),
+                PeerSyncServerConfig {
+                    address: config.sequencer_config.peer_sync_server_address.clone(),
+                    auth_token: config.sequencer_config.peer_sync_auth_token.clone(),
+                    max_records_per_request: config
+                        .sequencer_config
+                        .peer_sync_max_records_per_request,
+                    request_timeout: config.sequencer_config.peer_sync_request_timeout,
+                },
+                replay_server_limits.clone(),
+            )
+            .map(report_exit("peer sync server")),
+        );
+    }
     tasks.spawn(
         replay_server(
             block_replay_storage.clone(),
             config.sequencer_config.block_replay_server_address.clone(),
+            replay_server_limits,
+            replay_subscribers_sender,
         )
         .map(report_exit("replay server")),
     );
@@ -454,6 +841,27 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
             .map(|_| tracing::warn!("repositories.run_persist_loop() unexpectedly exited"))
             .await
     });
+    if let RepositoryRetentionMode::Pruned { keep_blocks } =
+        config.general_config.repository_retention.mode
+    {
+        tracing::info!(
+            keep_blocks,
+            "Pruning historical transactions from repository DB"
+        );
+        let repositories_clone = repositories.clone();
+        let finality = finality_storage.clone();
+        tasks.spawn(async move {
+            repositories_clone
+                .run_pruning_loop(keep_blocks, finality)
+                .map(|_| tracing::warn!("repositories.run_pruning_loop() unexpectedly exited"))
+                .await
+        });
+    }
+    tasks.spawn(
+        spam_scores
+            .run_persist_loop(SPAM_SCORES_PERSIST_PERIOD)
+            .map(|()| tracing::warn!("spam_scores.run_persist_loop() unexpectedly exited")),
+    );
     let state_clone = state.clone();
     tasks.spawn(async move {
         state_clone
@@ -462,9 +870,28 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
             .await;
     });
 
+    let admin_api_config = config.admin_api_config.clone();
+    let admin_audit_log_path = admin_api_config.audit_log_path.clone().unwrap_or_else(|| {
+        config
+            .general_config
+            .rocks_db_path
+            .join("admin_audit_log.jsonl")
+    });
+    let config_file_path = config.general_config.config_file_path.clone();
+    let config_reload_interval = config.general_config.config_reload_interval;
+    let mut admin_hooks = AdminHooks {
+        account_keys: account_keys_index.clone(),
+        ..Default::default()
+    };
+
     if config.sequencer_config.is_main_node() {
         // Main Node
-        run_main_node_pipeline(
+        admin_hooks.tx_acceptance = Some(tx_acceptance_state_sender.clone());
+        let (commitment_format_ack_sender, commitment_format_acks) = watch::channel(None);
+        admin_hooks.commitment_format_acks = Some(commitment_format_ack_sender);
+        admin_hooks.l1_reverts = Some(l1_revert_sender.clone());
+        admin_hooks.bundles = bundle_store;
+        let fri_job_manager = run_main_node_pipeline(
             config,
             l1_provider.clone(),
             batch_storage,
@@ -481,10 +908,26 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
             _stop_receiver.clone(),
             tx_acceptance_state_sender,
             batcher_prev_batch_info,
+            l1_finality_sender,
+            l1_costs_sender,
+            watchdog_status_sender,
+            commitment_format_acks,
+            pubdata_composition,
+            l1_price_predictions,
+            l1_revert_sender.subscribe(),
+            shadow_execution_sender,
+            priority_deadlines,
+            account_keys_index,
         )
         .await;
+        admin_hooks.fri_job_manager = Some(fri_job_manager);
     } else {
         // External Node
+        let replay_divergence = config
+            .sequencer_config
+            .quarantine_on_replay_divergence
+            .then_some(replay_divergence_sender);
+        admin_hooks.replay_divergence = replay_divergence.clone();
         run_en_pipeline(
             config,
             batch_storage,
@@ -499,9 +942,57 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
             finality_storage,
             _stop_receiver.clone(),
             tx_acceptance_state_sender,
+            replay_divergence,
+            shadow_execution_sender,
+            account_keys_index,
         )
         .await;
     };
+
+    let audit_log = (admin_api_config.enabled || config_file_path.is_some()).then(|| {
+        Arc::new(AuditLog::open(&admin_audit_log_path).expect("failed to open admin audit log"))
+    });
+    let mut reload_registry = HotReloadRegistry::default();
+    let admin_auth_token = admin_api_config.enabled.then(|| {
+        let auth_token = admin_api_config
+            .auth_token
+            .clone()
+            .expect("`admin_api.auth_token` must be set when the admin API is enabled");
+        let (auth_token_sender, auth_token) = watch::channel(auth_token);
+        reload_registry.register_secret("admin_api.auth_token", auth_token_sender, |token| {
+            resolve_auth_token("admin_api.auth_token", token)
+        });
+        auth_token
+    });
+    if let Some(path) = config_file_path {
+        let reloader = Arc::new(
+            ConfigReloader::new(path, reload_registry, audit_log.clone())
+                .expect("failed to load config file"),
+        );
+        admin_hooks.config_reloader = Some(reloader.clone());
+        tasks.spawn(
+            watch_config_file(reloader, config_reload_interval)
+                .map(|()| tracing::warn!("Config file watcher unexpectedly exited")),
+        );
+    }
+
+    if let (Some(auth_token), Some(audit_log)) = (admin_auth_token, audit_log) {
+        tasks.spawn(
+            run_admin_server(
+                admin_api_config.address,
+                auth_token,
+                AdminNamespace::new(admin_hooks, audit_log),
+                admin_api_config.request_timeout,
+                admin_api_config.max_request_body_bytes,
+            )
+            .map(report_exit("Admin API server")),
+        );
+    }
+
+    if let Some(backup_scheduler) = backup_scheduler {
+        tasks.spawn(backup_scheduler.run().map(report_exit("Backup scheduler")));
+    }
+
     let startup_time = process_started_at.elapsed();
     GENERAL_METRICS.startup_time[&"total"].set(startup_time.as_secs_f64());
     tracing::info!("All components initialized in {startup_time:?}");
@@ -509,6 +1000,33 @@ pub async fn run<State: ReadStateHistory + WriteState + StateInitializer + Clone
     tracing::info!("One of the subsystems exited - exiting process.");
 }
 
+/// Shadow execution of blocks with the configured execution version, if enabled.
+fn shadow_execution<State: ReadStateHistory>(
+    config: &Config,
+    state: State,
+    status: watch::Sender<ShadowExecutionStatus>,
+) -> Option<ShadowExecution<MultivmShadowRunner<State>>> {
+    let shadow_config = config.sequencer_config.shadow_execution_config()?;
+    let runner = MultivmShadowRunner::new(state, shadow_config.execution_version);
+    Some(ShadowExecution::new(runner, shadow_config, status))
+}
+
+/// Progress of the main node sampled by [`L1SenderWatchdog`].
+struct NodeProgress<Repo> {
+    repositories: Repo,
+    last_sealed_batch: watch::Receiver<u64>,
+}
+
+impl<Repo: ReadRepository> LocalProgress for NodeProgress<Repo> {
+    fn latest_block(&self) -> u64 {
+        self.repositories.get_latest_block()
+    }
+
+    fn latest_sealed_batch(&self) -> u64 {
+        *self.last_sealed_batch.borrow()
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 async fn run_main_node_pipeline(
     config: Config,
@@ -524,19 +1042,89 @@ async fn run_main_node_pipeline(
     tree: MerkleTree<RocksDBWrapper>,
     finality: impl ReadFinality + Clone,
     chain_id: u64,
-    _stop_receiver: watch::Receiver<bool>,
+    stop_receiver: watch::Receiver<bool>,
     tx_acceptance_state_sender: watch::Sender<TransactionAcceptanceState>,
     batcher_prev_batch_info: StoredBatchInfo,
-) {
+    l1_finality_sender: watch::Sender<L1FinalitySnapshot>,
+    l1_costs_sender: watch::Sender<L1CostSummary>,
+    watchdog_status_sender: watch::Sender<WatchdogStatus>,
+    commitment_format_acks: watch::Receiver<Option<u8>>,
+    pubdata_composition: Option<PubdataComposition>,
+    l1_price_predictions: Option<watch::Receiver<GasAdjusterSnapshot>>,
+    l1_reverts: watch::Receiver<L1RevertStatus>,
+    shadow_execution_status: watch::Sender<ShadowExecutionStatus>,
+    priority_deadlines: PriorityDeadlines,
+    account_keys_index: Option<AccountKeysIndex>,
+) -> Arc<FriJobManager> {
     let starting_batch_number = batcher_prev_batch_info.batch_number + 1;
+
+    let (l1_tx_records_sender, l1_tx_records_receiver) = tokio::sync::mpsc::unbounded_channel();
+    tasks.spawn(
+        L1FinalityTracker::new(
+            l1_provider.clone().erased(),
+            batch_storage.clone(),
+            l1_tx_records_receiver,
+            l1_finality_sender,
+            config.l1_watcher_config.finality_poll_interval,
+        )
+        .await
+        .expect("failed to start L1 finality tracker")
+        .run()
+        .map(report_exit("L1 finality tracker")),
+    );
+
+    let (l1_tx_costs_sender, l1_tx_costs_receiver) = tokio::sync::mpsc::unbounded_channel();
+    tasks.spawn(
+        L1CostTracker::new(
+            batch_storage.clone(),
+            l1_tx_costs_receiver,
+            l1_costs_sender,
+            PriceDriftConfig {
+                window: config.gas_adjuster_config.price_drift_window,
+                threshold: config.gas_adjuster_config.price_drift_threshold,
+            },
+        )
+        .await
+        .expect("failed to start L1 cost tracker")
+        .run()
+        .map(report_exit("L1 cost tracker")),
+    );
+
+    let (last_sealed_batch_sender, last_sealed_batch) =
+        watch::channel(batcher_prev_batch_info.batch_number);
+    tasks.spawn(
+        L1SenderWatchdog::new(
+            config.l1_sender_config.clone().into(),
+            NodeProgress {
+                repositories: repositories.clone(),
+                last_sealed_batch,
+            },
+            node_state_on_startup.l1_state.diamond_proxy.clone(),
+            watchdog_status_sender,
+        )
+        .run()
+        .map(report_exit("L1 sender watchdog")),
+    );
+
     let (fri_proving_step, fri_job_manager) = FriProvingPipelineStep::new(
         batch_storage.clone(),
+        chain_id,
         config.prover_api_config.job_timeout,
         config.prover_api_config.max_assigned_batch_range,
     );
 
+    let snark_verification = config
+        .prover_api_config
+        .snark_verification_enabled
+        .then(|| SnarkProofVerification {
+            verifier: Arc::new(node_state_on_startup.l1_state.diamond_proxy.clone()),
+            timeout: config.prover_api_config.snark_verification_timeout,
+        });
     let (snark_proving_step, snark_job_manager) = SnarkProvingPipelineStep::new(
+        batch_storage.clone(),
+        chain_id,
         config.prover_api_config.max_fris_per_snark,
+        snark_verification,
         node_state_on_startup.l1_state.last_proved_batch,
     );
 
@@ -546,12 +1134,16 @@ async fn run_main_node_pipeline(
             snark_job_manager.clone(),
             batch_storage.clone(),
             config.prover_api_config.address.clone(),
+            prover_server::ProverServerLimits {
+                request_timeout: config.prover_api_config.request_timeout,
+                max_proof_bytes: config.prover_api_config.max_proof_body_bytes,
+            },
         )
         .map(report_exit("prover_server_job")),
     );
 
     if config.prover_api_config.fake_fri_provers.enabled {
-        run_fake_fri_provers(&config.prover_api_config, tasks, fri_job_manager);
+        run_fake_fri_provers(&config.prover_api_config, tasks, fri_job_manager.clone());
     }
 
     if config.prover_api_config.fake_snark_provers.enabled {
@@ -582,14 +1174,29 @@ async fn run_main_node_pipeline(
             repositories: repositories.clone(),
             sequencer_config: config.sequencer_config.clone().into(),
             tx_acceptance_state_sender,
+            stop_receiver,
+            replay_divergence: None,
+            l1_price_predictions: l1_price_predictions.clone(),
         })
+        .pipe_opt(account_keys_index.map(AccountKeysIndexer::new))
+        .pipe_opt(shadow_execution(
+            &config,
+            state.clone(),
+            shadow_execution_status,
+        ))
         .pipe_opt(
             config
                 .sequencer_config
                 .revm_consistency_checker_enabled
                 .then(|| RevmConsistencyChecker::new(state.clone())),
         )
-        .pipe(TreeManager { tree: tree.clone() })
+        .pipe(
+            TreeManager::new(
+                tree.clone(),
+                &config.general_config.rocks_db_path.join(STATE_TREE_DB_NAME),
+            )
+            .expect("failed to initialize tree manager"),
+        )
         .pipe(ProverInputGenerator {
             enable_logging: config.prover_input_generator_config.logging_enabled,
             maximum_in_flight_blocks: config
@@ -609,27 +1216,52 @@ async fn run_main_node_pipeline(
             pubdata_limit_bytes: config.sequencer_config.block_pubdata_limit_bytes,
             batcher_config: config.batcher_config.clone(),
             batch_storage: batch_storage.clone(),
+            last_sealed_batch: last_sealed_batch_sender,
+            priority_deadlines,
         })
         .pipe(BatchVerificationPipelineStep::new(
             config.batch_verification_config.into(),
+            chain_id,
+            l1_provider.clone().erased(),
         ))
         .pipe(fri_proving_step)
         .pipe(GaplessCommitter {
             next_expected_batch_number: starting_batch_number,
             last_committed_batch_number: node_state_on_startup.l1_state.last_committed_batch,
             proof_storage: batch_storage.clone(),
-            da_input_mode: node_state_on_startup.l1_state.da_input_mode,
+            pubdata_mode: pubdata_mode(
+                node_state_on_startup.l1_state.da_input_mode,
+                config.l1_sender_config.rollup_pubdata_mode,
+            ),
+        })
+        .pipe(CommitScheduler {
+            config: config.batcher_config.clone().into(),
+            l1_fees: l1_price_predictions,
         })
         .pipe(L1Sender::<_, CommitCommand> {
             provider: l1_provider.clone(),
             config: config.l1_sender_config.clone().into(),
             to_address: node_state_on_startup.l1_state.validator_timelock,
+            chain_address: node_state_on_startup.l1_state.diamond_proxy_address(),
+            l1_tx_records: Some(l1_tx_records_sender.clone()),
+            l1_tx_costs: Some(l1_tx_costs_sender.clone()),
+            commitment_format_acks: Some(commitment_format_acks),
+            pubdata_composition,
+            l1_reverts: Some(l1_reverts.clone()),
+            key_rotations: None,
         })
         .pipe(snark_proving_step)
         .pipe(L1Sender::<_, ProofCommand> {
             provider: l1_provider.clone(),
             config: config.l1_sender_config.clone().into(),
             to_address: node_state_on_startup.l1_state.validator_timelock,
+            chain_address: node_state_on_startup.l1_state.diamond_proxy_address(),
+            l1_tx_records: Some(l1_tx_records_sender.clone()),
+            l1_tx_costs: Some(l1_tx_costs_sender.clone()),
+            commitment_format_acks: None,
+            pubdata_composition: None,
+            l1_reverts: Some(l1_reverts.clone()),
+            key_rotations: None,
         })
         .pipe(
             PriorityTreePipelineStep::new(
@@ -645,9 +1277,17 @@ async fn run_main_node_pipeline(
             provider: l1_provider,
             config: config.l1_sender_config.clone().into(),
             to_address: node_state_on_startup.l1_state.validator_timelock,
+            chain_address: node_state_on_startup.l1_state.diamond_proxy_address(),
+            l1_tx_records: Some(l1_tx_records_sender),
+            l1_tx_costs: Some(l1_tx_costs_sender),
+            commitment_format_acks: None,
+            pubdata_composition: None,
+            l1_reverts: Some(l1_reverts),
+            key_rotations: None,
         })
         .pipe(BatchSink)
         .spawn(tasks);
+    fri_job_manager
 }
 
 /// Only for EN - we still populate channels destined for the batcher subsystem -
@@ -665,10 +1305,26 @@ async fn run_en_pipeline(
     starting_block: u64,
     repositories: impl WriteRepository + Clone,
     finality: impl ReadFinality + Clone,
-    _stop_receiver: watch::Receiver<bool>,
+    stop_receiver: watch::Receiver<bool>,
     tx_acceptance_state_sender: watch::Sender<TransactionAcceptanceState>,
+    replay_divergence: Option<watch::Sender<ReplayDivergenceStatus>>,
+    shadow_execution_status: watch::Sender<ShadowExecutionStatus>,
+    account_keys_index: Option<AccountKeysIndex>,
 ) {
-    Pipeline::new()
+    let peer_fallback = (!config.sequencer_config.peer_sync_urls.is_empty()).then(|| {
+        PeerFallback::new(
+            PeerFallbackConfig {
+                peer_urls: config.sequencer_config.peer_sync_urls.clone(),
+                auth_token: config.sequencer_config.peer_sync_auth_token.clone(),
+                fallback_after: config.sequencer_config.peer_sync_fallback_after,
+                max_records_per_request: config.sequencer_config.peer_sync_max_records_per_request,
+                request_timeout: config.sequencer_config.peer_sync_request_timeout,
+            },
+            block_replay_storage.clone(),
+            repositories.clone(),
+        )
+    });
+    let blocks = Pipeline::new()
         .pipe(ExternalNodeCommandSource {
             starting_block,
             replay_download_address: config
@@ -676,6 +1332,8 @@ async fn run_en_pipeline(
                 .block_replay_download_address
                 .clone()
                 .expect("EN must have replay_download_address"),
+            max_frame_bytes: config.sequencer_config.block_replay_max_frame_bytes,
+            peer_fallback,
         })
         .pipe(Sequencer {
             block_context_provider,
@@ -684,26 +1342,76 @@ async fn run_en_pipeline(
             repositories: repositories.clone(),
             sequencer_config: config.sequencer_config.clone().into(),
             tx_acceptance_state_sender,
+            stop_receiver,
+            replay_divergence,
+            l1_price_predictions: None,
         })
+        .pipe_opt(account_keys_index.map(AccountKeysIndexer::new))
+        .pipe_opt(shadow_execution(
+            &config,
+            state.clone(),
+            shadow_execution_status,
+        ))
         .pipe_opt(
             config
                 .sequencer_config
                 .revm_consistency_checker_enabled
                 .then(|| RevmConsistencyChecker::new(state.clone())),
-        )
-        .pipe(TreeManager { tree: tree.clone() })
-        .pipe_if(
-            config.batch_verification_config.client_enabled,
-            BatchVerificationClient::new(
-                finality.clone(),
-                config.batch_verification_config.signing_key.clone(),
-                config.genesis_config.chain_id.unwrap(),
-                *node_state_on_startup.l1_state.diamond_proxy.address(),
-                config.batch_verification_config.connect_address,
-            ),
-            NoOpSink::new(),
-        )
-        .spawn(tasks);
+        );
+    let tree_manager = TreeManager::new(
+        tree.clone(),
+        &config.general_config.rocks_db_path.join(STATE_TREE_DB_NAME),
+    )
+    .expect("failed to initialize tree manager");
+
+    if !config.batch_verification_config.client_enabled {
+        blocks.pipe(tree_manager).pipe(NoOpSink::new()).spawn(tasks);
+    } else {
+        // The client receives blocks from the sequencer and their tree data from the tree
+        // as separate inputs
+        let (blocks, verified_blocks) = blocks.split(
+            "batch_verification_blocks",
+            5,
+            |(block_output, replay_record, stats)| {
+                (
+                    (block_output.clone(), replay_record.clone(), stats),
+                    (block_output, replay_record),
+                )
+            },
+        );
+        let tree_data = blocks.pipe(tree_manager).map(
+            "batch_verification_tree_data",
+            5,
+            |(_, _, _, tree_data)| tree_data,
+        );
+        verified_blocks
+            .join(
+                tree_data,
+                BatchVerificationClient::new(
+                    finality.clone(),
+                    LocalVerificationBlocks::new(state, block_replay_storage.clone(), tree),
+                    config.batch_verification_config.client_max_cached_blocks,
+                    config.batch_verification_config.signing_key.clone(),
+                    config.genesis_config.chain_id.unwrap(),
+                    *node_state_on_startup.l1_state.diamond_proxy.address(),
+                    config.batch_verification_config.connect_address.clone(),
+                    config.batch_verification_config.client_tls(),
+                    config.batch_verification_config.client_keepalive(),
+                    config.batch_verification_config.frame_limits(),
+                    config
+                        .batch_verification_config
+                        .signing_journal_path
+                        .unwrap_or_else(|| {
+                            config
+                                .general_config
+                                .rocks_db_path
+                                .join("batch_signing_journal.jsonl")
+                        }),
+                    config.batch_verification_config.signing_journal_retention,
+                ),
+            )
+            .spawn(tasks);
+    }
 
     // Run Priority Tree tasks for EN - not part of the pipeline.
     let priority_tree_en_step = PriorityTreeENStep::new(
@@ -960,7 +1668,11 @@ pub async fn genesis_stored_batch_info(
     load_genesis_stored_batch_info(
         genesis_block,
         tree_db.clone(),
-        genesis.state().await.expected_genesis_root,
+        genesis
+            .state()
+            .await
+            .expect("Failed to build genesis state")
+            .expected_genesis_root,
     )
     .await
     .unwrap()
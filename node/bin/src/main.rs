@@ -1,3 +1,4 @@
+use clap::Parser;
 use smart_config::value::ExposeSecret;
 use smart_config::{ConfigRepository, ConfigSchema, DescribeConfig, Environment};
 use std::time::Duration;
@@ -5,20 +6,33 @@ use tokio::signal::unix::{SignalKind, signal};
 use tokio::sync::watch;
 use zksync_os_observability::prometheus::PrometheusExporterConfig;
 use zksync_os_server::config::{
-    BatchVerificationConfig, BatcherConfig, Config, GasAdjusterConfig, GeneralConfig,
-    GenesisConfig, L1SenderConfig, L1WatcherConfig, MempoolConfig, ObservabilityConfig,
-    ProverApiConfig, ProverInputGeneratorConfig, RollupPubdataMode, RpcConfig, SequencerConfig,
-    StateBackendConfig, StatusServerConfig, TxValidatorConfig,
+    AdminApiConfig, BackupConfig, BatchVerificationConfig, BatcherConfig, Config,
+    GasAdjusterConfig, GeneralConfig, GenesisConfig, L1SenderConfig, L1WatcherConfig,
+    MempoolConfig, ObservabilityConfig, ProverApiConfig, ProverInputGeneratorConfig, RpcConfig,
+    SequencerConfig, StateBackendConfig, StatusServerConfig, TxValidatorConfig,
 };
+use zksync_os_server::config_reload::config_file_source;
+use zksync_os_server::migrations::plan_migrations;
 use zksync_os_server::run;
+use zksync_os_server::secrets::resolve_secrets;
 use zksync_os_server::zkstack_config::ZkStackConfig;
 use zksync_os_state::StateHandle;
 use zksync_os_state_full_diffs::FullDiffsState;
 
 const GRACEFUL_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
 
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Args {
+    /// Print database migrations that would run on startup and exit
+    #[arg(long)]
+    dry_run_migrations: bool,
+}
+
 #[tokio::main]
 pub async fn main() {
+    let args = Args::parse();
+
     // =========== load configs ===========
     let config = build_configs();
 
@@ -52,6 +66,20 @@ pub async fn main() {
         .build();
     tracing::info!(?config, "Loaded config");
 
+    if args.dry_run_migrations {
+        let plans = match config.general_config.state_backend {
+            StateBackendConfig::FullDiffs => {
+                plan_migrations::<FullDiffsState>(&config.general_config)
+            }
+            StateBackendConfig::Compacted => plan_migrations::<StateHandle>(&config.general_config),
+        }
+        .unwrap_or_else(|err| panic!("Failed to plan database migrations: {err:#}"));
+        for (db_name, plan) in plans {
+            println!("{db_name}: {plan}");
+        }
+        return;
+    }
+
     let prometheus: PrometheusExporterConfig =
         PrometheusExporterConfig::pull(config.observability_config.prometheus.port);
 
@@ -165,6 +193,9 @@ fn build_configs() -> Config {
     schema
         .insert(&StatusServerConfig::DESCRIPTION, "status_server")
         .expect("Failed to insert status server config");
+    schema
+        .insert(&AdminApiConfig::DESCRIPTION, "admin_api")
+        .expect("Failed to insert admin API config");
     schema
         .insert(&ObservabilityConfig::DESCRIPTION, "observability")
         .expect("Failed to insert observability config");
@@ -174,8 +205,24 @@ fn build_configs() -> Config {
     schema
         .insert(&BatchVerificationConfig::DESCRIPTION, "batch_verification")
         .expect("Failed to insert batch verification config");
+    schema
+        .insert(&BackupConfig::DESCRIPTION, "backup")
+        .expect("Failed to insert backup config");
 
     let repo = ConfigRepository::new(&schema).with(Environment::prefixed(""));
+    let config_file_path = repo
+        .single::<GeneralConfig>()
+        .expect("Failed to load general config")
+        .parse()
+        .expect("Failed to parse general config")
+        .config_file_path;
+    let repo = match config_file_path {
+        Some(path) => repo.with(
+            config_file_source(&path)
+                .unwrap_or_else(|err| panic!("Failed to load config file: {err:#}")),
+        ),
+        None => repo,
+    };
 
     let mut general_config = repo
         .single::<GeneralConfig>()
@@ -249,6 +296,12 @@ fn build_configs() -> Config {
         .parse()
         .expect("Failed to parse status server config");
 
+    let mut admin_api_config = repo
+        .single::<AdminApiConfig>()
+        .expect("Failed to load admin API config")
+        .parse()
+        .expect("Failed to parse admin API config");
+
     let mut observability_config = repo
         .single::<ObservabilityConfig>()
         .expect("Failed to load observability config")
@@ -261,12 +314,18 @@ fn build_configs() -> Config {
         .parse()
         .expect("Failed to parse gas adjuster config");
 
-    let batch_verification_config = repo
+    let mut batch_verification_config = repo
         .single::<BatchVerificationConfig>()
         .expect("Failed to load batch verification config")
         .parse()
         .expect("Failed to parse batch verification config");
 
+    let backup_config = repo
+        .single::<BackupConfig>()
+        .expect("Failed to load backup config")
+        .parse()
+        .expect("Failed to parse backup config");
+
     if let Some(config_dir) = general_config.zkstack_cli_config_dir.clone() {
         // If set, then update the configs based off the values from the yaml files.
         // This is a temporary measure until we update zkstack cli (or create a new tool) to create
@@ -285,6 +344,16 @@ fn build_configs() -> Config {
             .unwrap_or_else(|_| panic!("Failed to load zkstack config from `{config_dir}`: "));
     }
 
+    // Resolve `env:` / `file:` secret references before any cross-config validation
+    resolve_secrets(
+        &mut l1_sender_config,
+        &mut batch_verification_config,
+        &mut admin_api_config,
+        &mut rpc_config,
+        &mut sequencer_config,
+    )
+    .unwrap_or_else(|err| panic!("Failed to resolve secrets: {err:#}"));
+
     // Validate that operator keys are different
     if l1_sender_config.operator_commit_pk.expose_secret()
         == l1_sender_config.operator_prove_pk.expose_secret()
@@ -297,11 +366,11 @@ fn build_configs() -> Config {
         panic!("Operator addresses for commit, prove and execute must be different");
     }
 
-    if matches!(
-        l1_sender_config.rollup_pubdata_mode,
-        RollupPubdataMode::Blobs
-    ) {
-        panic!("Blobs mode is not supported yet");
+    // Rebuilt trees catch up by replaying all blocks, which needs full state diffs
+    if general_config.tree_rebuild_on_corruption
+        && matches!(general_config.state_backend, StateBackendConfig::Compacted)
+    {
+        panic!("`tree_rebuild_on_corruption` requires the `FullDiffs` state backend");
     }
 
     Config {
@@ -317,8 +386,10 @@ fn build_configs() -> Config {
         prover_input_generator_config,
         prover_api_config,
         status_server_config,
+        admin_api_config,
         observability_config,
         gas_adjuster_config,
         batch_verification_config,
+        backup_config,
     }
 }
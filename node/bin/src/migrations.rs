@@ -0,0 +1,49 @@
+//! Startup migrations of node databases, see [`zksync_os_rocksdb::migrations`].
+
+use crate::REPOSITORY_DB_NAME;
+use crate::config::GeneralConfig;
+use crate::state_initializer::StateInitializer;
+use std::path::PathBuf;
+use zksync_os_rocksdb::migrations::{MigrationPlan, VersionedDb};
+use zksync_os_state::{ACCOUNT_KEYS_DB_NAME, ACCOUNT_KEYS_SCHEMA};
+use zksync_os_storage::db::REPOSITORY_SCHEMA;
+
+/// Databases with versioned schemas used with `config`, keyed by their names. The block replay
+/// WAL and the tree databases have no meta column family and are not versioned.
+fn versioned_dbs<State: StateInitializer>(
+    config: &GeneralConfig,
+) -> Vec<(&'static str, PathBuf, &'static dyn VersionedDb)> {
+    let mut dbs: Vec<(&'static str, &'static dyn VersionedDb)> =
+        vec![(REPOSITORY_DB_NAME, &REPOSITORY_SCHEMA)];
+    dbs.extend(State::versioned_dbs());
+    if config.account_keys_index_enabled {
+        dbs.push((ACCOUNT_KEYS_DB_NAME, &ACCOUNT_KEYS_SCHEMA));
+    }
+    dbs.into_iter()
+        .map(|(name, schema)| (name, config.rocks_db_path.join(name), schema))
+        .collect()
+}
+
+/// Returns migrations that would be run on startup for each database, without running them.
+pub fn plan_migrations<State: StateInitializer>(
+    config: &GeneralConfig,
+) -> anyhow::Result<Vec<(&'static str, MigrationPlan)>> {
+    versioned_dbs::<State>(config)
+        .into_iter()
+        .map(|(name, path, schema)| Ok((name, schema.plan(&path)?)))
+        .collect()
+}
+
+/// Runs pending migrations of all databases; must run before any of them is opened. Fails without
+/// migrating anything if any database was written by a newer node version.
+pub fn run_migrations<State: StateInitializer>(config: &GeneralConfig) -> anyhow::Result<()> {
+    let dbs = versioned_dbs::<State>(config);
+    for (_, path, schema) in &dbs {
+        schema.plan(path)?;
+    }
+    for (name, path, schema) in dbs {
+        let plan = schema.migrate(&path)?;
+        tracing::info!(db_name = name, "Database schema: {plan}");
+    }
+    Ok(())
+}
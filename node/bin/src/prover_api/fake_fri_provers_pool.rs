@@ -4,6 +4,7 @@ use std::time::Duration;
 use tokio::time::{Instant, sleep};
 
 use crate::prover_api::fri_job_manager::FriJobManager;
+use crate::prover_api::job_filter::JobFilter;
 
 const POLL_INTERVAL_MS: u64 = 250;
 const PROVER_LABEL: &str = "fake_prover";
@@ -46,8 +47,8 @@ impl FakeFriProversPool {
             let handle = tokio::spawn(async move {
                 loop {
                     // Only take inbound items whose age >= min_age.
-                    match jm.pick_next_job(min_age) {
-                        Some((fri_job, _prover_input)) => {
+                    match jm.pick_next_job(min_age, &JobFilter::default()) {
+                        Ok(Some((fri_job, _input_version, _prover_input))) => {
                             // Emulate proving work.
                             let start = Instant::now();
                             sleep(compute_time).await;
@@ -70,10 +71,14 @@ impl FakeFriProversPool {
                                 );
                             }
                         }
-                        None => {
+                        Ok(None) => {
                             // Nothing eligible now; back off a bit.
                             sleep(Duration::from_millis(POLL_INTERVAL_MS)).await;
                         }
+                        Err(err) => {
+                            tracing::error!("fake prover cannot pick a job: {err}");
+                            sleep(Duration::from_millis(POLL_INTERVAL_MS)).await;
+                        }
                     }
                 }
             });
@@ -3,11 +3,16 @@
 //! * Incoming jobs are received through an async channel.
 //! * Job is only consumed from the channel once there is a prover available.
 //! * Assigned jobs are added to `ProverJobMap` immediately.
-//! * Provers request work via [`pick_next_job`]:
+//! * Provers request work via [`pick_next_job`], optionally restricted by a [`JobFilter`]
+//!   (supported chain ids / VK hashes):
 //!     * If there is an already assigned job that has timed out, it is reassigned.
 //!     * Otherwise, the next job from inbound is assigned and inserted into `ProverJobMap`.
+//!     * Batches with priority transactions close to expiring on L1 are assigned ahead of
+//!       earlier batches.
 //! * Fake provers call [`pick_next_job`] with a `min_age` param to avoid taking fresh items,
 //!   letting real provers race first.
+//! * Jobs are handed out with the [`ProverInputVersion`] their input was generated for; provers echo it back
+//!   on submission. Proofs for a different version are rejected, and the job is handed out again.
 //! * When any proof is submitted (real or fake):
 //!     * It is enqueued to the ordered committer as `SignedBatchEnvelope<FriProof>`.
 //!     * It is removed from `ProverJobMap` so the map cannot grow without bounds.
@@ -15,6 +20,7 @@
 //! `ComponentStateLatencyTracker`: Only tracks `Processing` / `WaitingSend` states
 
 use crate::prover_api::fri_proof_verifier;
+use crate::prover_api::job_filter::JobFilter;
 use crate::prover_api::metrics::{PROVER_METRICS, ProverStage, ProverType};
 use crate::prover_api::proof_storage::{ProofStorage, StoredFailedProof};
 use crate::prover_api::prover_job_map::ProverJobMap;
@@ -28,9 +34,9 @@ use tokio::sync::mpsc::error::TrySendError;
 use tokio::sync::{Mutex, mpsc};
 use zksync_os_l1_sender::batcher_metrics::BatchExecutionStage;
 use zksync_os_l1_sender::batcher_model::{
-    FriProof, ProverInput, RealFriProof, SignedBatchEnvelope,
+    FriProof, ProverInput, ProverInputVersion, RealFriProof, SignedBatchEnvelope,
 };
-use zksync_os_multivm::{ExecutionVersion, proving_run_execution_version};
+use zksync_os_multivm::{ExecutionVersion, MultivmError};
 use zksync_os_observability::{
     ComponentStateHandle, ComponentStateReporter, GenericComponentState,
 };
@@ -50,6 +56,15 @@ pub enum SubmitError {
     // server execution version, prover execution version
     #[error("execution error mismatch - server expects {0:?}, but got {1:?} from prover")]
     ExecutionVersionMismatch(ExecutionVersion, ExecutionVersion),
+    #[error(
+        "prover input version mismatch - server expects {expected}, but got {actual} from prover"
+    )]
+    ProverInputVersionMismatch {
+        expected: ProverInputVersion,
+        actual: ProverInputVersion,
+    },
+    #[error(transparent)]
+    UnsupportedExecutionVersion(#[from] MultivmError),
     #[error("internal error: {0}")]
     Other(String),
 }
@@ -68,10 +83,21 @@ pub struct FailedFriProof {
 
 #[derive(Debug, Serialize)]
 pub struct FriJob {
+    pub chain_id: u64,
     pub batch_number: u64,
     pub vk_hash: String,
 }
 
+/// Queue depth of a single chain served by this prover API.
+#[derive(Debug, Serialize)]
+pub struct ChainQueueState {
+    pub chain_id: u64,
+    /// Jobs currently assigned to provers.
+    pub assigned_jobs: usize,
+    /// Jobs waiting to be picked. `None` if the inbound queue is contended at the moment.
+    pub pending_jobs: Option<usize>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct JobState {
     pub fri_job: FriJob,
@@ -107,6 +133,7 @@ pub struct FriJobManager {
     // == storage ==
     proof_storage: ProofStorage,
     // == config ==
+    chain_id: u64,
     max_assigned_batch_range: usize,
     // == metrics ==
     latency_tracker: ComponentStateHandle<GenericComponentState>,
@@ -117,10 +144,11 @@ impl FriJobManager {
         batches_for_prove_receiver: mpsc::Receiver<SignedBatchEnvelope<ProverInput>>,
         batches_with_proof_sender: mpsc::Sender<SignedBatchEnvelope<FriProof>>,
         proof_storage: ProofStorage,
+        chain_id: u64,
         assignment_timeout: Duration,
         max_assigned_batch_range: usize,
     ) -> Self {
-        let jobs = ProverJobMap::new(assignment_timeout);
+        let jobs = ProverJobMap::new(chain_id, assignment_timeout);
         let latency_tracker = ComponentStateReporter::global().handle_for(
             "fri_job_manager",
             GenericComponentState::ProcessingOrWaitingRecv,
@@ -130,6 +158,7 @@ impl FriJobManager {
             inbound: Mutex::new(PeekableReceiver::new(batches_for_prove_receiver)),
             batches_with_proof_sender,
             proof_storage,
+            chain_id,
             max_assigned_batch_range,
             latency_tracker,
         }
@@ -152,7 +181,9 @@ impl FriJobManager {
     }
 
     /// Picks the **smallest** batch number that is either **pending** (from inbound)
-    /// or whose assignment has **timed‑out** (from the assigned map).
+    /// or whose assignment has **timed‑out** (from the assigned map). Among the next
+    /// `max_assigned_batch_range` pending batches, ones with priority transactions close to
+    /// expiring (see `BatchMetadata::priority_deadline`) are picked first.
     ///
     /// If `min_inbound_age` is provided, will **not** consume a fresh inbound head item
     /// whose trace age is **younger** than this threshold; in that case returns `None`
@@ -160,17 +191,40 @@ impl FriJobManager {
     ///
     /// `min_inbound_age` is used for fake provers to avoid taking fresh items,
     /// letting real provers race first.
-    pub fn pick_next_job(&self, min_inbound_age: Duration) -> Option<(FriJob, ProverInput)> {
+    ///
+    /// Only jobs accepted by `filter` are returned. Jobs are still handed out in order - if the
+    /// inbound head uses a VK that is not accepted, `None` is returned and the job is left for
+    /// other provers.
+    ///
+    /// Errors if the next job was executed with an execution version unknown to this build; such
+    /// a job is left in place.
+    pub fn pick_next_job(
+        &self,
+        min_inbound_age: Duration,
+        filter: &JobFilter,
+    ) -> Result<Option<(FriJob, ProverInputVersion, ProverInput)>, MultivmError> {
+        if !filter.accepts_chain(self.chain_id) {
+            tracing::trace!(
+                chain_id = self.chain_id,
+                ?filter,
+                "prover doesn't serve this chain; returning None"
+            );
+            return Ok(None);
+        }
+
         // 1) Prefer a timed-out reassignment
-        if let Some((fri_job, prover_input)) = self.assigned_jobs.pick_timed_out_job() {
+        if let Some((fri_job, input_version, prover_input)) =
+            self.assigned_jobs.pick_timed_out_job(filter)?
+        {
             tracing::info!(
                 fri_job.batch_number,
                 fri_job.vk_hash,
+                %input_version,
                 assigned_jobs_count = self.assigned_jobs.len(),
                 ?min_inbound_age,
                 "Assigned a timed out job"
             );
-            return Some((fri_job, prover_input));
+            return Ok(Some((fri_job, input_version, prover_input)));
         }
 
         if let MinMax(min, max) = self.assigned_jobs.minmax_assigned_batch_number()
@@ -182,26 +236,57 @@ impl FriJobManager {
                 max_assigned_batch_range = self.max_assigned_batch_range,
                 "too many assigned jobs; returning None"
             );
-            return None;
+            return Ok(None);
         }
 
         // 2) Otherwise, consume one item from inbound - if it meets the age gate.
         // take a lock on the inbound channel - only one thread can receive messages at a time
         if let Ok(mut rx) = self.inbound.try_lock() {
-            let old_enough =
-                rx.peek_with(|env| env.latency_tracker.current_stage_age() >= min_inbound_age);
-            if old_enough != Some(true) {
-                // no element in Inbound or it's not old enough
-                return None;
-            }
+            let eligible = |env: &SignedBatchEnvelope<ProverInput>| {
+                env.latency_tracker.current_stage_age() >= min_inbound_age
+                    && env
+                        .batch
+                        .proving_execution_version()
+                        .is_ok_and(|version| filter.accepts_vk(version.vk_hash()))
+            };
+            // Batches with priority transactions close to expiring are handed out first
+            let head_batch_number = rx.peek_with(|env| env.batch_number());
+            let prioritized = rx.try_recv_first(self.max_assigned_batch_range, |env| {
+                env.batch.priority_deadline.is_some() && eligible(env)
+            });
+            let picked = match prioritized {
+                Some(env) => {
+                    if Some(env.batch_number()) != head_batch_number {
+                        tracing::info!(
+                            batch_number = env.batch_number(),
+                            head_batch_number,
+                            priority_deadline = env.batch.priority_deadline,
+                            "Prioritized a job with priority transactions close to expiring"
+                        );
+                        PROVER_METRICS.prioritized_jobs.inc();
+                    }
+                    Ok(env)
+                }
+                None => {
+                    match rx.peek_with(|env| {
+                        env.batch.proving_execution_version().map(|_| eligible(env))
+                    }) {
+                        Some(Ok(true)) => rx.try_recv(),
+                        Some(Err(err)) => return Err(err),
+                        // no element in Inbound, it's not old enough or the prover doesn't support its VK
+                        _ => return Ok(None),
+                    }
+                }
+            };
 
-            match rx.try_recv() {
+            match picked {
                 Ok(env) => {
                     let env = env.with_stage(BatchExecutionStage::FriProverPicked);
                     let prover_input = env.data.clone();
-                    let proving_execution_version =
-                        proving_run_execution_version(env.batch.execution_version);
+                    let input_version = env.batch.prover_input_version()?;
+                    let proving_execution_version = env.batch.proving_execution_version()?;
                     let fri_job = FriJob {
+                        chain_id: self.chain_id,
                         batch_number: env.batch_number(),
                         vk_hash: proving_execution_version.vk_hash().to_string(),
                     };
@@ -212,15 +297,15 @@ impl FriJobManager {
                         "Assigned a new job from inbound channel"
                     );
                     self.assigned_jobs.insert(env);
-                    Some((fri_job, prover_input))
+                    Ok(Some((fri_job, input_version, prover_input)))
                 }
-                Err(_) => None,
+                Err(_) => Ok(None),
             }
         } else {
             // in fact, we could wait for mutex to unlock -
             // but we return early and let prover poll again
             tracing::trace!("inbound receiver is contended; returning None");
-            None
+            Ok(None)
         }
     }
 
@@ -232,6 +317,8 @@ impl FriJobManager {
         proof_bytes: Bytes,
         // TODO: migrate to ExecutionVersion, once legacy is deprecated
         execution_version: Option<ExecutionVersion>,
+        // Not provided by legacy clients
+        input_version: Option<ProverInputVersion>,
         prover_id: &str,
     ) -> Result<(), SubmitError> {
         // Snapshot the assigned job entry (if any).
@@ -247,10 +334,8 @@ impl FriJobManager {
         //
         // NOTE: We don't check the actual values, but the value that server believes the prove should use.
         // NOTE2: Checking only if prover provided VK version - legacy clients will not provide it
+        let server_execution_version = batch_metadata.proving_execution_version()?;
         if let Some(exec_version) = execution_version {
-            // should never panic
-            let server_execution_version =
-                proving_run_execution_version(batch_metadata.execution_version);
             if server_execution_version != exec_version {
                 return Err(SubmitError::ExecutionVersionMismatch(
                     server_execution_version,
@@ -259,6 +344,27 @@ impl FriJobManager {
             }
         }
 
+        // The proof is generated for the input handed out to the prover; if the prover worked on an input
+        // for another version (e.g., it was generated before a version rotation, or the prover binary
+        // doesn't match the server), the proof is useless. Hand the job out again, with the input version
+        // the server expects.
+        if let Some(actual) = input_version {
+            let expected = batch_metadata.prover_input_version()?;
+            if expected != actual {
+                let requeued = self.assigned_jobs.request_reassignment(batch_number);
+                tracing::warn!(
+                    batch_number,
+                    prover_id,
+                    %expected,
+                    %actual,
+                    requeued,
+                    "Rejected proof for mismatched prover input version"
+                );
+                PROVER_METRICS.input_version_mismatches.inc();
+                return Err(SubmitError::ProverInputVersionMismatch { expected, actual });
+            }
+        }
+
         // Deserialize and verify using metadata from the batch.
         let program_proof =
             bincode::serde::decode_from_slice(&proof_bytes, bincode::config::standard())
@@ -338,17 +444,11 @@ impl FriJobManager {
         };
         tracing::info!(batch_number, "Real proof accepted");
 
-        // get execution version from prover, if available, otherwise fallback
-        let execution_version = if let Some(execution_version) = execution_version {
-            proving_run_execution_version(execution_version as u32) as u32
-        } else {
-            proving_run_execution_version(batch_metadata.execution_version) as u32
-        };
-
-        // Prepare the envelope and send it downstream.
+        // Prepare the envelope and send it downstream. The version provided by the prover (if any)
+        // was checked to match the server one above.
         let proof = RealFriProof::V2 {
             proof: proof_bytes,
-            proving_execution_version: execution_version,
+            proving_execution_version: server_execution_version as u32,
         };
         let envelope = removed_job
             .batch_envelope
@@ -415,10 +515,30 @@ impl FriJobManager {
         })
     }
 
+    /// Hands the job for `batch_number` out to the next polling prover, without waiting for
+    /// the current assignment to time out. Returns `false` if the batch is not assigned.
+    pub fn reassign_job(&self, batch_number: u64) -> bool {
+        let found = self.assigned_jobs.request_reassignment(batch_number);
+        tracing::info!(batch_number, found, "FRI job reassignment requested");
+        found
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
     pub fn status(&self) -> Vec<JobState> {
         self.assigned_jobs.status()
     }
 
+    pub fn queue_state(&self) -> ChainQueueState {
+        ChainQueueState {
+            chain_id: self.chain_id,
+            assigned_jobs: self.assigned_jobs.len(),
+            pending_jobs: self.inbound.try_lock().ok().map(|rx| rx.len()),
+        }
+    }
+
     fn set_status(&self, status: GenericComponentState) {
         self.latency_tracker.enter_state(status);
     }
@@ -427,8 +547,174 @@ impl FriJobManager {
 impl std::fmt::Debug for FriJobManager {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("FriJobManager")
+            .field("chain_id", &self.chain_id)
             .field("assigned_jobs_len", &self.assigned_jobs.len())
             .field("max_assigned_batch_range", &self.max_assigned_batch_range)
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zksync_os_l1_sender::batcher_model::{
+        BatchEnvelope, BatchMetadata, BatchSignatureData, PROVER_INPUT_FORMAT_VERSION,
+    };
+    use zksync_os_object_store::MockObjectStore;
+
+    fn batch_envelope(input_version: ProverInputVersion) -> SignedBatchEnvelope<ProverInput> {
+        let mut metadata = BatchMetadata::for_tests(1, 1..=1, 1);
+        metadata.prover_input_version = Some(input_version);
+        BatchEnvelope::new(metadata, vec![1, 2, 3]).with_signatures(BatchSignatureData::NotNeeded)
+    }
+
+    fn prioritized_batch_envelope(
+        batch_number: u64,
+        priority_deadline: Option<u64>,
+    ) -> SignedBatchEnvelope<ProverInput> {
+        let mut envelope = batch_envelope(ProverInputVersion::current(4).unwrap());
+        envelope.batch.batch_info.commit_info.batch_number = batch_number;
+        envelope.batch.priority_deadline = priority_deadline;
+        envelope
+    }
+
+    #[tokio::test]
+    async fn batches_close_to_priority_deadline_are_picked_first() {
+        let (inbound_sender, inbound) = mpsc::channel(10);
+        let (proof_sender, _proof_receiver) = mpsc::channel(1);
+        let manager = FriJobManager::new(
+            inbound,
+            proof_sender,
+            ProofStorage::new(MockObjectStore::arc()),
+            270,
+            Duration::from_secs(3_600),
+            10,
+        );
+        let filter = JobFilter::default();
+
+        for (batch_number, priority_deadline) in [(1, None), (2, None), (3, Some(1_000)), (4, None)]
+        {
+            inbound_sender
+                .send(prioritized_batch_envelope(batch_number, priority_deadline))
+                .await
+                .unwrap();
+        }
+        let picked: Vec<_> =
+            std::iter::from_fn(|| manager.pick_next_job(Duration::ZERO, &filter).unwrap())
+                .map(|(fri_job, _, _)| fri_job.batch_number)
+                .collect();
+        assert_eq!(picked, [3, 1, 2, 4]);
+        assert_eq!(PROVER_METRICS.prioritized_jobs.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn proofs_for_another_input_version_are_rejected() {
+        let (inbound_sender, inbound) = mpsc::channel(1);
+        let (proof_sender, _proof_receiver) = mpsc::channel(1);
+        let manager = FriJobManager::new(
+            inbound,
+            proof_sender,
+            ProofStorage::new(MockObjectStore::arc()),
+            270,
+            Duration::from_secs(3_600),
+            10,
+        );
+        let filter = JobFilter::default();
+
+        let generated = ProverInputVersion::current(4).unwrap();
+        inbound_sender
+            .send(batch_envelope(generated))
+            .await
+            .unwrap();
+        let (fri_job, input_version, _) = manager
+            .pick_next_job(Duration::ZERO, &filter)
+            .unwrap()
+            .unwrap();
+        assert_eq!(fri_job.batch_number, 1);
+        assert_eq!(input_version, generated);
+
+        // The prover was rotated to another input format after the input was generated
+        let rotated = ProverInputVersion {
+            input_format_version: PROVER_INPUT_FORMAT_VERSION + 1,
+            ..generated
+        };
+        let err = manager
+            .submit_proof(
+                1,
+                Bytes::from_static(b"proof"),
+                None,
+                Some(rotated),
+                "prover",
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                SubmitError::ProverInputVersionMismatch { expected, actual }
+                    if expected == generated && actual == rotated
+            ),
+            "{err:?}"
+        );
+
+        // The job is handed out again right away, despite the long assignment timeout
+        let (fri_job, input_version, _) = manager
+            .pick_next_job(Duration::ZERO, &filter)
+            .unwrap()
+            .unwrap();
+        assert_eq!(fri_job.batch_number, 1);
+        assert_eq!(input_version, generated);
+        assert!(
+            manager
+                .pick_next_job(Duration::ZERO, &filter)
+                .unwrap()
+                .is_none()
+        );
+
+        // A prover on the matching version gets past the version check (the proof itself is garbage)
+        let err = manager
+            .submit_proof(
+                1,
+                Bytes::from_static(b"proof"),
+                None,
+                Some(generated),
+                "prover",
+            )
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, SubmitError::DeserializationFailed(_)),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn jobs_with_unsupported_execution_version_are_not_handed_out() {
+        let (inbound_sender, inbound) = mpsc::channel(1);
+        let (proof_sender, _proof_receiver) = mpsc::channel(1);
+        let manager = FriJobManager::new(
+            inbound,
+            proof_sender,
+            ProofStorage::new(MockObjectStore::arc()),
+            270,
+            Duration::from_secs(3_600),
+            10,
+        );
+        let mut envelope = batch_envelope(ProverInputVersion::current(4).unwrap());
+        envelope.batch.execution_version = 999;
+        inbound_sender.send(envelope).await.unwrap();
+
+        for _ in 0..2 {
+            let err = manager
+                .pick_next_job(Duration::ZERO, &JobFilter::default())
+                .unwrap_err();
+            assert!(
+                matches!(
+                    err,
+                    MultivmError::UnsupportedExecutionVersion { got: 999, .. }
+                ),
+                "{err:?}"
+            );
+        }
+    }
+}
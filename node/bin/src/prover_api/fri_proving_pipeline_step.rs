@@ -26,6 +26,7 @@ pub struct FriProvingPipelineStep {
 impl FriProvingPipelineStep {
     pub fn new(
         proof_storage: ProofStorage,
+        chain_id: u64,
         assignment_timeout: Duration,
         max_assigned_batch_range: usize,
     ) -> (Self, Arc<FriJobManager>) {
@@ -42,6 +43,7 @@ impl FriProvingPipelineStep {
             batches_for_prove_receiver,
             batches_with_proof_sender,
             proof_storage,
+            chain_id,
             assignment_timeout,
             max_assigned_batch_range,
         ));
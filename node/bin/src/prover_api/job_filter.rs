@@ -0,0 +1,97 @@
+//! Chain / VK namespacing for prover jobs.
+//!
+//! A single prover fleet may serve several chains (i.e. several nodes), each possibly running
+//! on a different verification key. Provers declare the chains and VKs they support at pick time;
+//! jobs outside of this set are never handed out to them.
+//!
+//! Both sets are optional - provers that don't declare anything receive every job, which keeps
+//! single-chain deployments and existing provers working unmodified.
+
+use crate::prover_api::fri_job_manager::FriJob;
+use anyhow::Context;
+
+/// Jobs a prover is able to serve. Empty lists match everything.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JobFilter {
+    pub chain_ids: Vec<u64>,
+    pub vk_hashes: Vec<String>,
+}
+
+impl JobFilter {
+    /// Parses comma-separated lists of chain ids and VK hashes as passed in the pick query.
+    pub fn parse(chain_ids: Option<&str>, vk_hashes: Option<&str>) -> anyhow::Result<Self> {
+        let chain_ids = split_list(chain_ids)
+            .map(|chain_id| {
+                chain_id
+                    .parse()
+                    .with_context(|| format!("invalid chain id `{chain_id}`"))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let vk_hashes = split_list(vk_hashes).map(str::to_owned).collect();
+        Ok(Self {
+            chain_ids,
+            vk_hashes,
+        })
+    }
+
+    pub fn accepts_chain(&self, chain_id: u64) -> bool {
+        self.chain_ids.is_empty() || self.chain_ids.contains(&chain_id)
+    }
+
+    pub fn accepts_vk(&self, vk_hash: &str) -> bool {
+        self.vk_hashes.is_empty() || self.vk_hashes.iter().any(|vk| vk == vk_hash)
+    }
+
+    pub fn accepts(&self, fri_job: &FriJob) -> bool {
+        self.accepts_chain(fri_job.chain_id) && self.accepts_vk(&fri_job.vk_hash)
+    }
+}
+
+fn split_list(list: Option<&str>) -> impl Iterator<Item = &str> {
+    list.unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn job(chain_id: u64, vk_hash: &str) -> FriJob {
+        FriJob {
+            chain_id,
+            batch_number: 1,
+            vk_hash: vk_hash.to_owned(),
+        }
+    }
+
+    #[test]
+    fn unfiltered_accepts_everything() {
+        let filter = JobFilter::parse(None, None).unwrap();
+        assert_eq!(filter, JobFilter::default());
+        assert!(filter.accepts(&job(270, "0xaa")));
+        assert!(filter.accepts(&job(271, "0xbb")));
+    }
+
+    #[test]
+    fn filters_by_chain_and_vk() {
+        let filter = JobFilter::parse(Some("270, 272"), Some("0xaa")).unwrap();
+        assert_eq!(filter.chain_ids, [270, 272]);
+        assert!(filter.accepts(&job(270, "0xaa")));
+        assert!(filter.accepts(&job(272, "0xaa")));
+        assert!(!filter.accepts(&job(271, "0xaa")));
+        assert!(!filter.accepts(&job(270, "0xbb")));
+    }
+
+    #[test]
+    fn empty_lists_are_unfiltered() {
+        let filter = JobFilter::parse(Some(""), Some(",")).unwrap();
+        assert_eq!(filter, JobFilter::default());
+    }
+
+    #[test]
+    fn invalid_chain_id() {
+        assert!(JobFilter::parse(Some("270,mainnet"), None).is_err());
+    }
+}
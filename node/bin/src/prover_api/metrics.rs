@@ -1,5 +1,5 @@
 use std::time::Duration;
-use vise::{Buckets, EncodeLabelValue, Histogram, LabeledFamily, Metrics, Unit};
+use vise::{Buckets, Counter, EncodeLabelValue, Histogram, LabeledFamily, Metrics, Unit};
 
 #[derive(Debug, Metrics)]
 #[metrics(prefix = "prover")]
@@ -9,6 +9,10 @@ pub struct ProverMetrics {
     #[metrics(unit = Unit::Seconds, labels = ["stage", "type", "id"], buckets = Buckets::LATENCIES)]
     pub prove_time_per_tx:
         LabeledFamily<(ProverStage, ProverType, &'static str), Histogram<Duration>, 3>,
+    /// FRI proofs rejected because the prover used an input for another `ProverInputVersion`.
+    pub input_version_mismatches: Counter,
+    /// FRI jobs with priority transactions close to expiring handed out ahead of earlier batches.
+    pub prioritized_jobs: Counter,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, EncodeLabelValue)]
@@ -3,9 +3,11 @@ pub mod fri_job_manager;
 mod fri_proof_verifier;
 pub mod fri_proving_pipeline_step;
 pub mod gapless_committer;
+pub mod job_filter;
 mod metrics;
 pub mod proof_storage;
 mod prover_job_map;
 pub mod prover_server;
 pub mod snark_job_manager;
+pub mod snark_proof_verifier;
 pub mod snark_proving_pipeline_step;
@@ -6,15 +6,38 @@
 //!  * batch -> its FRI proof
 //!  * batch -> its commitment (used for l1 senders)
 //!  * batch -> failed FRI proof with batch metadata
+//!  * batch -> verification record of its SNARK proof
+//!  * L1 finality of batches' commit/prove/execute transactions
+//!  * batch -> L1 fees paid for its commit/prove/execute transactions (and running aggregates)
+//!  * expiration deadlines of unprocessed priority transactions
+//!
+//! When several chains share one object store, keys are prefixed with the chain id
+//! (see [`ProofStorage::with_chain_namespace`]). Unprefixed keys keep the legacy layout.
 
 use crate::prover_api::fri_job_manager::FailedFriProof;
-use alloy::primitives::BlockNumber;
+use crate::prover_api::snark_proof_verifier::SnarkVerificationRecord;
+use alloy::primitives::{B256, BlockNumber};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Arc;
-use zksync_os_l1_sender::batcher_model::{FriProof, SignedBatchEnvelope};
+use zksync_os_l1_sender::batcher_model::{
+    BatchMetadata, FriProof, L1BatchOperation, L1FinalitySnapshot, L1TxFinality,
+    SignedBatchEnvelope,
+};
+use zksync_os_l1_sender::cost_accounting::{BatchCost, L1CostAggregates, L1CostStorage};
+use zksync_os_l1_watcher::{L1FinalityStorage, PriorityDeadlinesStorage, StoredBatchHashes};
 use zksync_os_object_store::_reexports::BoxedError;
 use zksync_os_object_store::{Bucket, ObjectStore, ObjectStoreError, StoredObject};
-use zksync_os_storage_api::{ReadBatch, ReadFinality};
+use zksync_os_storage_api::{FinalityStatus, ReadBatch, ReadFinality};
+use zksync_os_types::{BatchDetails, BatchL1Tx, BatchStatus, L1TxSerialId};
+
+/// Prefixes `key` with the chain id, if the storage is namespaced.
+fn chain_scoped_key(chain_id: Option<u64>, key: String) -> String {
+    match chain_id {
+        Some(chain_id) => format!("chain_{chain_id}_{key}"),
+        None => key,
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 #[non_exhaustive]
@@ -24,10 +47,11 @@ pub enum StoredBatch {
 
 impl StoredObject for StoredBatch {
     const BUCKET: Bucket = Bucket("fri_batch_envelopes");
-    type Key<'a> = u64;
+    /// (chain id namespace, batch number)
+    type Key<'a> = (Option<u64>, u64);
 
-    fn encode_key(key: Self::Key<'_>) -> String {
-        format!("fri_batch_envelope_{key}.json")
+    fn encode_key((chain_id, batch_number): Self::Key<'_>) -> String {
+        chain_scoped_key(chain_id, format!("fri_batch_envelope_{batch_number}.json"))
     }
 
     fn serialize(&self) -> Result<Vec<u8>, BoxedError> {
@@ -61,10 +85,11 @@ pub struct StoredFailedProof {
 
 impl StoredObject for StoredFailedProof {
     const BUCKET: Bucket = Bucket("failed_fri_proofs");
-    type Key<'a> = u64;
+    /// (chain id namespace, batch number)
+    type Key<'a> = (Option<u64>, u64);
 
-    fn encode_key(key: Self::Key<'_>) -> String {
-        format!("failed_fri_proof_{key}.json")
+    fn encode_key((chain_id, batch_number): Self::Key<'_>) -> String {
+        chain_scoped_key(chain_id, format!("failed_fri_proof_{batch_number}.json"))
     }
 
     fn serialize(&self) -> Result<Vec<u8>, BoxedError> {
@@ -82,20 +107,159 @@ impl StoredFailedProof {
     }
 }
 
+/// Verification record of a batch's SNARK proof.
+#[derive(Debug, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum StoredSnarkVerification {
+    V1(SnarkVerificationRecord),
+}
+
+impl StoredObject for StoredSnarkVerification {
+    const BUCKET: Bucket = Bucket("snark_verifications");
+    /// (chain id namespace, batch number)
+    type Key<'a> = (Option<u64>, u64);
+
+    fn encode_key((chain_id, batch_number): Self::Key<'_>) -> String {
+        chain_scoped_key(chain_id, format!("snark_verification_{batch_number}.json"))
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, BoxedError> {
+        serde_json::to_vec(self).map_err(From::from)
+    }
+
+    fn deserialize(bytes: Vec<u8>) -> Result<Self, BoxedError> {
+        serde_json::from_slice(&bytes).map_err(From::from)
+    }
+}
+
+/// Latest state of the L1 finality tracker. Stored as a single object as it only contains
+/// non-finalized transactions and is thus small.
+#[derive(Debug, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum StoredL1Finality {
+    V1(L1FinalitySnapshot),
+}
+
+impl StoredObject for StoredL1Finality {
+    const BUCKET: Bucket = Bucket("l1_finality");
+    /// Chain id namespace
+    type Key<'a> = Option<u64>;
+
+    fn encode_key(chain_id: Self::Key<'_>) -> String {
+        chain_scoped_key(chain_id, "l1_finality_snapshot.json".to_owned())
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, BoxedError> {
+        serde_json::to_vec(self).map_err(From::from)
+    }
+
+    fn deserialize(bytes: Vec<u8>) -> Result<Self, BoxedError> {
+        serde_json::from_slice(&bytes).map_err(From::from)
+    }
+}
+
+/// Expiration deadlines of priority transactions not yet processed by the sequencer.
+#[derive(Debug, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum StoredPriorityDeadlines {
+    V1(BTreeMap<L1TxSerialId, u64>),
+}
+
+impl StoredObject for StoredPriorityDeadlines {
+    const BUCKET: Bucket = Bucket("l1_watcher");
+    /// Chain id namespace
+    type Key<'a> = Option<u64>;
+
+    fn encode_key(chain_id: Self::Key<'_>) -> String {
+        chain_scoped_key(chain_id, "priority_deadlines.json".to_owned())
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, BoxedError> {
+        serde_json::to_vec(self).map_err(From::from)
+    }
+
+    fn deserialize(bytes: Vec<u8>) -> Result<Self, BoxedError> {
+        serde_json::from_slice(&bytes).map_err(From::from)
+    }
+}
+
+/// L1 fees paid for a batch.
+#[derive(Debug, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum StoredBatchCost {
+    V1(BatchCost),
+}
+
+impl StoredObject for StoredBatchCost {
+    const BUCKET: Bucket = Bucket("l1_costs");
+    /// (chain id namespace, batch number)
+    type Key<'a> = (Option<u64>, u64);
+
+    fn encode_key((chain_id, batch_number): Self::Key<'_>) -> String {
+        chain_scoped_key(chain_id, format!("l1_batch_cost_{batch_number}.json"))
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, BoxedError> {
+        serde_json::to_vec(self).map_err(From::from)
+    }
+
+    fn deserialize(bytes: Vec<u8>) -> Result<Self, BoxedError> {
+        serde_json::from_slice(&bytes).map_err(From::from)
+    }
+}
+
+/// Running L1 cost aggregates (only recent periods are retained, so the object stays small).
+#[derive(Debug, Serialize, Deserialize)]
+#[non_exhaustive]
+pub enum StoredL1CostAggregates {
+    V1(L1CostAggregates),
+}
+
+impl StoredObject for StoredL1CostAggregates {
+    const BUCKET: Bucket = Bucket("l1_costs");
+    /// Chain id namespace
+    type Key<'a> = Option<u64>;
+
+    fn encode_key(chain_id: Self::Key<'_>) -> String {
+        chain_scoped_key(chain_id, "l1_cost_aggregates.json".to_owned())
+    }
+
+    fn serialize(&self) -> Result<Vec<u8>, BoxedError> {
+        serde_json::to_vec(self).map_err(From::from)
+    }
+
+    fn deserialize(bytes: Vec<u8>) -> Result<Self, BoxedError> {
+        serde_json::from_slice(&bytes).map_err(From::from)
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ProofStorage {
     object_store: Arc<dyn ObjectStore>,
+    /// Chain id used to namespace keys; `None` for the legacy (single-chain) layout.
+    chain_id: Option<u64>,
 }
 
 impl ProofStorage {
     pub fn new(object_store: Arc<dyn ObjectStore>) -> Self {
-        Self { object_store }
+        Self {
+            object_store,
+            chain_id: None,
+        }
+    }
+
+    /// Prefixes all keys with `chain_id` - required when the object store is shared between chains.
+    pub fn with_chain_namespace(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
     }
 
     /// Persist a BatchWithProof. Overwrites any existing entry for the same batch.
     /// Doesn't allow gaps - if a proof for batch `n` is missing, then no proof for batch `n+1` is allowed.
     pub async fn save_batch_with_proof(&self, value: &StoredBatch) -> anyhow::Result<()> {
-        self.object_store.put(value.batch_number(), value).await?;
+        self.object_store
+            .put((self.chain_id, value.batch_number()), value)
+            .await?;
         Ok(())
     }
 
@@ -104,7 +268,11 @@ impl ProofStorage {
         &self,
         batch_number: u64,
     ) -> anyhow::Result<Option<SignedBatchEnvelope<FriProof>>> {
-        match self.object_store.get::<StoredBatch>(batch_number).await {
+        match self
+            .object_store
+            .get::<StoredBatch>((self.chain_id, batch_number))
+            .await
+        {
             Ok(o) => Ok(Some(o.batch_envelope())),
             Err(ObjectStoreError::KeyNotFound(_)) => Ok(None),
             Err(err) => Err(err.into()),
@@ -114,7 +282,7 @@ impl ProofStorage {
     /// Save a failed FRI proof with batch metadata for debugging.
     pub async fn save_failed_proof(&self, failed_proof: &StoredFailedProof) -> anyhow::Result<()> {
         self.object_store
-            .put(failed_proof.batch_number(), failed_proof)
+            .put((self.chain_id, failed_proof.batch_number()), failed_proof)
             .await?;
         Ok(())
     }
@@ -127,7 +295,7 @@ impl ProofStorage {
     ) -> anyhow::Result<Option<FailedFriProof>> {
         match self
             .object_store
-            .get::<StoredFailedProof>(batch_number)
+            .get::<StoredFailedProof>((self.chain_id, batch_number))
             .await
         {
             Ok(o) => Ok(Some(o.failed_proof)),
@@ -135,6 +303,139 @@ impl ProofStorage {
             Err(err) => Err(err.into()),
         }
     }
+
+    /// Marks the SNARK proof of every batch covered by `record` as verified.
+    pub async fn save_snark_verification(
+        &self,
+        record: &SnarkVerificationRecord,
+    ) -> anyhow::Result<()> {
+        let stored = StoredSnarkVerification::V1(record.clone());
+        for batch_number in record.from_batch..=record.to_batch {
+            self.object_store
+                .put((self.chain_id, batch_number), &stored)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the verification record of the SNARK proof of `batch_number`, if it was verified.
+    pub async fn get_snark_verification(
+        &self,
+        batch_number: u64,
+    ) -> anyhow::Result<Option<SnarkVerificationRecord>> {
+        match self
+            .object_store
+            .get::<StoredSnarkVerification>((self.chain_id, batch_number))
+            .await
+        {
+            Ok(StoredSnarkVerification::V1(record)) => Ok(Some(record)),
+            Err(ObjectStoreError::KeyNotFound(_)) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+impl L1FinalityStorage for ProofStorage {
+    async fn load_l1_finality(&self) -> anyhow::Result<Option<L1FinalitySnapshot>> {
+        match self
+            .object_store
+            .get::<StoredL1Finality>(self.chain_id)
+            .await
+        {
+            Ok(StoredL1Finality::V1(snapshot)) => Ok(Some(snapshot)),
+            Err(ObjectStoreError::KeyNotFound(_)) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn save_l1_finality(&self, snapshot: &L1FinalitySnapshot) -> anyhow::Result<()> {
+        self.object_store
+            .put(self.chain_id, &StoredL1Finality::V1(snapshot.clone()))
+            .await?;
+        Ok(())
+    }
+}
+
+impl PriorityDeadlinesStorage for ProofStorage {
+    async fn load_priority_deadlines(&self) -> anyhow::Result<Option<BTreeMap<L1TxSerialId, u64>>> {
+        match self
+            .object_store
+            .get::<StoredPriorityDeadlines>(self.chain_id)
+            .await
+        {
+            Ok(StoredPriorityDeadlines::V1(deadlines)) => Ok(Some(deadlines)),
+            Err(ObjectStoreError::KeyNotFound(_)) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn save_priority_deadlines(
+        &self,
+        deadlines: &BTreeMap<L1TxSerialId, u64>,
+    ) -> anyhow::Result<()> {
+        self.object_store
+            .put(
+                self.chain_id,
+                &StoredPriorityDeadlines::V1(deadlines.clone()),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+impl L1CostStorage for ProofStorage {
+    async fn load_batch_cost(&self, batch_number: u64) -> anyhow::Result<Option<BatchCost>> {
+        match self
+            .object_store
+            .get::<StoredBatchCost>((self.chain_id, batch_number))
+            .await
+        {
+            Ok(StoredBatchCost::V1(cost)) => Ok(Some(cost)),
+            Err(ObjectStoreError::KeyNotFound(_)) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn save_batch_cost(&self, cost: &BatchCost) -> anyhow::Result<()> {
+        self.object_store
+            .put(
+                (self.chain_id, cost.batch_number),
+                &StoredBatchCost::V1(cost.clone()),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn load_l1_cost_aggregates(&self) -> anyhow::Result<Option<L1CostAggregates>> {
+        match self
+            .object_store
+            .get::<StoredL1CostAggregates>(self.chain_id)
+            .await
+        {
+            Ok(StoredL1CostAggregates::V1(aggregates)) => Ok(Some(aggregates)),
+            Err(ObjectStoreError::KeyNotFound(_)) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn save_l1_cost_aggregates(&self, aggregates: &L1CostAggregates) -> anyhow::Result<()> {
+        self.object_store
+            .put(
+                self.chain_id,
+                &StoredL1CostAggregates::V1(aggregates.clone()),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+impl StoredBatchHashes for ProofStorage {
+    async fn stored_batch_hash(&self, batch_number: u64) -> anyhow::Result<Option<B256>> {
+        Ok(self
+            .get_batch_with_proof(batch_number)
+            .await?
+            .map(|envelope| envelope.batch.batch_info.into_stored().hash()))
+    }
 }
 
 #[async_trait::async_trait]
@@ -193,4 +494,303 @@ impl ReadBatch for ProofStorage {
                 )
             }))
     }
+
+    async fn get_batch_details(
+        &self,
+        batch_number: u64,
+        finality: &dyn ReadFinality,
+    ) -> anyhow::Result<Option<BatchDetails>> {
+        let Some(envelope) = self.get_batch_with_proof(batch_number).await? else {
+            return Ok(None);
+        };
+        let cost = self.load_batch_cost(batch_number).await?;
+        let l1_finality = self.load_l1_finality().await?.unwrap_or_default();
+        Ok(Some(batch_details(
+            envelope.batch,
+            cost,
+            &l1_finality,
+            &finality.get_finality_status(),
+        )))
+    }
+}
+
+/// Combines batch metadata with the L1 transactions recorded by the L1 cost tracker and their
+/// finality. Committed / executed batches are also recognized by `finality_status`, so that
+/// the status is correct on nodes not sending L1 transactions themselves.
+fn batch_details(
+    batch: BatchMetadata,
+    cost: Option<BatchCost>,
+    l1_finality: &L1FinalitySnapshot,
+    finality_status: &FinalityStatus,
+) -> BatchDetails {
+    let batch_number = batch.batch_info.batch_number;
+    let l1_tx = |operation| {
+        let cost = cost.as_ref()?.operations.get(&operation)?;
+        Some(BatchL1Tx {
+            tx_hash: cost.tx_hash,
+            l1_block_number: cost.l1_block_number,
+            timestamp: cost.timestamp,
+            finalized: l1_finality.batch_finality(batch_number, operation)
+                == Some(L1TxFinality::Finalized),
+            gas_used: cost.gas_used,
+            execution_fee_wei: cost.execution_fee_wei,
+            blob_fee_wei: cost.blob_fee_wei,
+        })
+    };
+    let commit_tx = l1_tx(L1BatchOperation::Commit);
+    let prove_tx = l1_tx(L1BatchOperation::Prove);
+    let execute_tx = l1_tx(L1BatchOperation::Execute);
+
+    let status = if execute_tx.as_ref().is_some_and(|tx| tx.finalized) {
+        BatchStatus::Finalized
+    } else if execute_tx.is_some() || batch_number <= finality_status.last_executed_batch {
+        BatchStatus::Executed
+    } else if prove_tx.is_some() {
+        BatchStatus::Proven
+    } else if commit_tx.is_some() || batch_number <= finality_status.last_committed_batch {
+        BatchStatus::Committed
+    } else {
+        BatchStatus::Sequenced
+    };
+    BatchDetails {
+        number: batch_number,
+        status,
+        first_block_number: batch.first_block_number,
+        last_block_number: batch.last_block_number,
+        tx_count: batch.tx_count as u64,
+        seal_reason: batch
+            .seal_reason
+            .map(|seal_reason| seal_reason.as_str().to_owned()),
+        commitment: batch.batch_info.into_stored().commitment,
+        commit_tx,
+        prove_tx,
+        execute_tx,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Bytes, TxHash};
+    use serde_json::json;
+    use zksync_os_l1_sender::batcher_model::{BatchEnvelope, BatchSealReason, BatchSignatureData};
+    use zksync_os_l1_sender::cost_accounting::OperationCost;
+    use zksync_os_object_store::MockObjectStore;
+    use zksync_os_storage::in_memory::Finality;
+    use zksync_os_storage_api::WriteFinality;
+
+    fn failed_proof(batch_number: u64, vk_hash: &str) -> StoredFailedProof {
+        StoredFailedProof {
+            failed_proof: FailedFriProof {
+                batch_number,
+                last_block_timestamp: 0,
+                expected_hash_u32s: [0; 8],
+                proof_final_register_values: [0; 16],
+                vk_hash: Some(vk_hash.to_owned()),
+                proof_bytes: Bytes::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn unscoped_keys_keep_legacy_layout() {
+        assert_eq!(
+            StoredBatch::encode_key((None, 5)),
+            "fri_batch_envelope_5.json"
+        );
+        assert_eq!(
+            StoredBatch::encode_key((Some(270), 5)),
+            "chain_270_fri_batch_envelope_5.json"
+        );
+        assert_eq!(
+            StoredL1Finality::encode_key(None),
+            "l1_finality_snapshot.json"
+        );
+    }
+
+    #[tokio::test]
+    async fn chains_are_isolated_in_shared_store() {
+        let object_store = MockObjectStore::arc();
+        let chain_a = ProofStorage::new(object_store.clone()).with_chain_namespace(270);
+        let chain_b = ProofStorage::new(object_store.clone()).with_chain_namespace(271);
+        let legacy = ProofStorage::new(object_store);
+
+        chain_a
+            .save_failed_proof(&failed_proof(1, "0xaa"))
+            .await
+            .unwrap();
+        chain_b
+            .save_failed_proof(&failed_proof(1, "0xbb"))
+            .await
+            .unwrap();
+
+        let proof_a = chain_a.get_failed_proof(1).await.unwrap().unwrap();
+        let proof_b = chain_b.get_failed_proof(1).await.unwrap().unwrap();
+        assert_eq!(proof_a.vk_hash.as_deref(), Some("0xaa"));
+        assert_eq!(proof_b.vk_hash.as_deref(), Some("0xbb"));
+        assert!(legacy.get_failed_proof(1).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn unscoped_storage_is_backward_compatible() {
+        let object_store = MockObjectStore::arc();
+        let storage = ProofStorage::new(object_store.clone());
+        storage
+            .save_failed_proof(&failed_proof(3, "0xaa"))
+            .await
+            .unwrap();
+
+        // Objects written by the unscoped storage are found under the legacy key.
+        let stored = object_store
+            .get::<StoredFailedProof>((None, 3))
+            .await
+            .unwrap();
+        assert_eq!(stored.batch_number(), 3);
+        assert!(
+            ProofStorage::new(object_store)
+                .with_chain_namespace(270)
+                .get_failed_proof(3)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    fn stored_batch() -> StoredBatch {
+        let mut metadata = BatchMetadata::for_tests(1, 5..=7, 3);
+        metadata.seal_reason = Some(BatchSealReason::Timeout);
+        StoredBatch::V1(
+            BatchEnvelope::new(metadata, FriProof::Fake)
+                .with_signatures(BatchSignatureData::NotNeeded),
+        )
+    }
+
+    fn operation_cost(tx_hash: u8, l1_block_number: u64) -> OperationCost {
+        OperationCost {
+            tx_hash: TxHash::repeat_byte(tx_hash),
+            l1_block_number: Some(l1_block_number),
+            timestamp: 1_700_000_000,
+            gas_used: 100_000,
+            execution_fee_wei: 2_000_000,
+            blob_fee_wei: 0,
+            predicted_prices: None,
+        }
+    }
+
+    fn l1_finality(finalized: &[L1BatchOperation]) -> L1FinalitySnapshot {
+        L1FinalitySnapshot {
+            last_finalized_batch: finalized.iter().map(|operation| (*operation, 1)).collect(),
+            tracked: vec![],
+        }
+    }
+
+    async fn batch_details_json(storage: &ProofStorage, finality: &Finality) -> serde_json::Value {
+        let details = storage
+            .get_batch_details(1, finality)
+            .await
+            .unwrap()
+            .unwrap();
+        serde_json::to_value(details).unwrap()
+    }
+
+    #[tokio::test]
+    async fn batch_details_follow_batch_lifecycle() {
+        let storage = ProofStorage::new(MockObjectStore::arc());
+        let finality = Finality::new(FinalityStatus {
+            last_committed_block: 0,
+            last_committed_batch: 0,
+            last_executed_block: 0,
+            last_executed_batch: 0,
+        });
+        assert!(
+            storage
+                .get_batch_details(1, &finality)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        let commitment = stored_batch()
+            .batch_envelope()
+            .batch
+            .batch_info
+            .into_stored()
+            .commitment;
+        storage
+            .save_batch_with_proof(&stored_batch())
+            .await
+            .unwrap();
+
+        // Sealed, not committed yet
+        assert_eq!(
+            batch_details_json(&storage, &finality).await,
+            json!({
+                "number": 1,
+                "status": "sequenced",
+                "firstBlockNumber": 5,
+                "lastBlockNumber": 7,
+                "txCount": 3,
+                "sealReason": "timeout",
+                "commitment": commitment,
+                "commitTx": null,
+                "proveTx": null,
+                "executeTx": null,
+            })
+        );
+
+        let mut cost = BatchCost::new(1);
+        cost.operations
+            .insert(L1BatchOperation::Commit, operation_cost(1, 100));
+        storage.save_batch_cost(&cost).await.unwrap();
+        let json = batch_details_json(&storage, &finality).await;
+        assert_eq!(json["status"], "committed");
+        assert_eq!(
+            json["commitTx"],
+            json!({
+                "txHash": TxHash::repeat_byte(1),
+                "l1BlockNumber": 100,
+                "timestamp": 1_700_000_000,
+                "finalized": false,
+                "gasUsed": 100_000,
+                "executionFeeWei": 2_000_000,
+                "blobFeeWei": 0,
+            })
+        );
+        assert_eq!(json["proveTx"], json!(null));
+
+        // Proven; the commit transaction got finalized on L1 in the meantime
+        cost.operations
+            .insert(L1BatchOperation::Prove, operation_cost(2, 110));
+        storage.save_batch_cost(&cost).await.unwrap();
+        storage
+            .save_l1_finality(&l1_finality(&[L1BatchOperation::Commit]))
+            .await
+            .unwrap();
+        let json = batch_details_json(&storage, &finality).await;
+        assert_eq!(json["status"], "proven");
+        assert_eq!(json["commitTx"]["finalized"], true);
+        assert_eq!(json["proveTx"]["txHash"], json!(TxHash::repeat_byte(2)));
+        assert_eq!(json["proveTx"]["finalized"], false);
+        assert_eq!(json["executeTx"], json!(null));
+
+        // Executed as observed on L1, before the execute transaction is recorded
+        finality.update_finality_status(|status| {
+            status.last_committed_batch = 1;
+            status.last_executed_batch = 1;
+        });
+        let json = batch_details_json(&storage, &finality).await;
+        assert_eq!(json["status"], "executed");
+        assert_eq!(json["executeTx"], json!(null));
+
+        cost.operations
+            .insert(L1BatchOperation::Execute, operation_cost(3, 120));
+        storage.save_batch_cost(&cost).await.unwrap();
+        storage
+            .save_l1_finality(&l1_finality(&L1BatchOperation::ALL))
+            .await
+            .unwrap();
+        let json = batch_details_json(&storage, &finality).await;
+        assert_eq!(json["status"], "finalized");
+        assert_eq!(json["executeTx"]["l1BlockNumber"], 120);
+        assert_eq!(json["executeTx"]["finalized"], true);
+    }
 }
@@ -1,14 +1,19 @@
 use crate::prover_api::fri_job_manager::{FriJob, JobState};
+use crate::prover_api::job_filter::JobFilter;
 use dashmap::DashMap;
 use itertools::{Itertools, MinMaxResult};
 use std::time::{Duration, Instant};
-use zksync_os_l1_sender::batcher_model::{BatchMetadata, ProverInput, SignedBatchEnvelope};
-use zksync_os_multivm::proving_run_execution_version;
+use zksync_os_l1_sender::batcher_model::{
+    BatchMetadata, ProverInput, ProverInputVersion, SignedBatchEnvelope,
+};
+use zksync_os_multivm::MultivmError;
 
 #[derive(Debug)]
 pub struct AssignedJobEntry {
     pub batch_envelope: SignedBatchEnvelope<ProverInput>,
     pub assigned_at: Instant,
+    /// Set by the operator to hand the job out again without waiting for the assignment timeout.
+    pub reassign_requested: bool,
 }
 
 /// Concurrent map of jobs that are currently assigned to provers.
@@ -19,14 +24,16 @@ pub struct ProverJobMap {
     jobs: DashMap<u64, AssignedJobEntry>,
 
     // == config ==
+    chain_id: u64,
     // assigns to another prover if it takes longer than this
     assignment_timeout: Duration,
 }
 
 impl ProverJobMap {
-    pub fn new(assignment_timeout: Duration) -> Self {
+    pub fn new(chain_id: u64, assignment_timeout: Duration) -> Self {
         Self {
             jobs: DashMap::new(),
+            chain_id,
             assignment_timeout,
         }
     }
@@ -38,18 +45,36 @@ impl ProverJobMap {
         let job_entry = AssignedJobEntry {
             batch_envelope,
             assigned_at: Instant::now(),
+            reassign_requested: false,
         };
         self.jobs.insert(job_id, job_entry);
     }
 
-    /// Picks the **smallest** batch number whose job has timed out, if any.
-    /// Returns `None` if no job has timed‑out.
+    /// Marks the job for `batch_number` as timed out, so that it's handed out to the next prover.
+    /// Returns `false` if no such job is assigned.
+    pub fn request_reassignment(&self, batch_number: u64) -> bool {
+        match self.jobs.get_mut(&batch_number) {
+            Some(mut entry) => {
+                entry.reassign_requested = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Picks the **smallest** batch number whose job has timed out (or whose reassignment was
+    /// requested) and is accepted by `filter`, if any.
+    /// Returns `None` if no such job has timed‑out.
+    /// Errors if the picked batch was executed with an unsupported execution version.
     ///
     /// Thread safety:
     ///   Races are possible if multiple threads call this at the same time.
     ///   Some calls may return `None` even if others observe a timed‑out job.
     ///   This is acceptable; callers will simply poll again.
-    pub fn pick_timed_out_job(&self) -> Option<(FriJob, ProverInput)> {
+    pub fn pick_timed_out_job(
+        &self,
+        filter: &JobFilter,
+    ) -> Result<Option<(FriJob, ProverInputVersion, ProverInput)>, MultivmError> {
         let now = Instant::now();
 
         // Single scan to locate the minimal eligible key.
@@ -57,7 +82,15 @@ impl ProverJobMap {
             .jobs
             .iter()
             .filter_map(|entry| {
-                if now.duration_since(entry.assigned_at) > self.assignment_timeout {
+                let accepted = entry
+                    .batch_envelope
+                    .batch
+                    .proving_execution_version()
+                    .is_ok_and(|version| filter.accepts_vk(version.vk_hash()));
+                if (entry.reassign_requested
+                    || now.duration_since(entry.assigned_at) > self.assignment_timeout)
+                    && accepted
+                {
                     Some(*entry.key())
                 } else {
                     None
@@ -75,17 +108,20 @@ impl ProverJobMap {
             );
             // Refresh assignment time to avoid immediate re-pick.
             entry.assigned_at = now;
+            entry.reassign_requested = false;
             let proving_execution_version =
-                proving_run_execution_version(entry.batch_envelope.batch.execution_version);
-            return Some((
+                entry.batch_envelope.batch.proving_execution_version()?;
+            return Ok(Some((
                 FriJob {
+                    chain_id: self.chain_id,
                     batch_number: entry.batch_envelope.batch_number(),
                     vk_hash: proving_execution_version.vk_hash().to_string(),
                 },
+                entry.batch_envelope.batch.prover_input_version()?,
                 entry.batch_envelope.data.clone(),
-            ));
+            )));
         }
-        None
+        Ok(None)
     }
 
     /// If a job is present for given batch_number, returns
@@ -120,6 +156,7 @@ impl ProverJobMap {
             .iter()
             .map(|r| JobState {
                 fri_job: FriJob {
+                    chain_id: self.chain_id,
                     batch_number: r.batch_envelope.batch_number(),
                     vk_hash: r.batch_envelope.batch.verification_key_hash().to_string(),
                 },
@@ -11,20 +11,25 @@ use zksync_os_l1_sender::batcher_model::FriProof;
 
 use crate::prover_api::{
     fri_job_manager::{JobStateLegacy, SubmitError},
+    job_filter::JobFilter,
     prover_server::{
         AppState,
         legacy::models::{
             BatchDataPayload, FailedProofResponse, FriProofPayload, NextSnarkProverJobPayload,
             ProverQuery, SnarkProofPayload,
         },
+        snark_proof_rejection,
     },
 };
 
 pub(super) async fn pick_fri_job(State(state): State<AppState>) -> Response {
     // for real provers, we return the next job immediately -
     // see `FakeProversPool` for fake provers implementation
-    match state.fri_job_manager.pick_next_job(Duration::from_secs(0)) {
-        Some((fri_job, input)) => {
+    match state
+        .fri_job_manager
+        .pick_next_job(Duration::from_secs(0), &JobFilter::default())
+    {
+        Ok(Some((fri_job, _input_version, input))) => {
             let bytes: Vec<u8> = input.iter().flat_map(|v| v.to_le_bytes()).collect();
             Json(BatchDataPayload {
                 block_number: fri_job.batch_number,
@@ -32,7 +37,11 @@ pub(super) async fn pick_fri_job(State(state): State<AppState>) -> Response {
             })
             .into_response()
         }
-        None => StatusCode::NO_CONTENT.into_response(),
+        Ok(None) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => {
+            tracing::error!("cannot hand out FRI job: {err}");
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+        }
     }
 }
 
@@ -48,11 +57,11 @@ pub(super) async fn submit_fri_proof(
     let prover_id = query.id.as_deref().unwrap_or("unknown_prover");
     match state
         .fri_job_manager
-        .submit_proof(payload.block_number, proof_bytes.into(), None, prover_id)
+        .submit_proof(payload.block_number, proof_bytes.into(), None, None, prover_id)
         .await
     {
         Ok(()) => Ok((StatusCode::NO_CONTENT, "proof accepted".to_string()).into_response()),
-        Err(SubmitError::ExecutionVersionMismatch(_, _)) =>
+        Err(SubmitError::ExecutionVersionMismatch(_, _) | SubmitError::ProverInputVersionMismatch { .. }) =>
             panic!("Should never happen, as provers don't provide execution_version"),
         Err(SubmitError::FriProofVerificationError {
             expected_hash_u32s,
@@ -68,6 +77,10 @@ pub(super) async fn submit_fri_proof(
         Err(SubmitError::DeserializationFailed(err)) => {
             Err((StatusCode::BAD_REQUEST, err.to_string()))
         }
+        Err(SubmitError::UnsupportedExecutionVersion(err)) => {
+            tracing::error!(batch_number = payload.block_number, "cannot verify proof: {err}");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+        }
         Err(SubmitError::Other(e)) => {
             tracing::error!("internal error: {e}");
             Err((StatusCode::INTERNAL_SERVER_ERROR, e))
@@ -76,7 +89,11 @@ pub(super) async fn submit_fri_proof(
 }
 
 pub(super) async fn pick_snark_job(State(state): State<AppState>) -> Response {
-    match state.snark_job_manager.pick_real_job().await {
+    match state
+        .snark_job_manager
+        .pick_real_job(&JobFilter::default())
+        .await
+    {
         Ok(Some(batches)) => {
             // Expect non-empty and all real FRI proofs
             let from = batches.first().unwrap().0.batch_number;
@@ -134,10 +151,7 @@ pub(super) async fn submit_snark_proof(
         .await
     {
         Ok(()) => Ok((StatusCode::NO_CONTENT, "proof accepted".to_string()).into_response()),
-        Err(err) => Err((
-            StatusCode::BAD_REQUEST,
-            format!("proof rejected: {err}").to_string(),
-        )),
+        Err(err) => Err(snark_proof_rejection(err)),
     }
 }
 
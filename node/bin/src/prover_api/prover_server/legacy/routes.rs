@@ -1,5 +1,6 @@
 use axum::{
     Router,
+    extract::DefaultBodyLimit,
     routing::{get, post},
 };
 
@@ -11,13 +12,22 @@ use crate::prover_api::prover_server::{
     },
 };
 
-pub(in crate::prover_api::prover_server) fn legacy_routes() -> Router<AppState> {
+pub(in crate::prover_api::prover_server) fn legacy_routes(
+    max_proof_bytes: usize,
+) -> Router<AppState> {
+    let proof_body_limit = DefaultBodyLimit::max(max_proof_bytes);
     Router::new()
         // server <-> prover routes
         .route("/FRI/pick", post(pick_fri_job))
-        .route("/FRI/submit", post(submit_fri_proof))
+        .route(
+            "/FRI/submit",
+            post(submit_fri_proof).layer(proof_body_limit),
+        )
         .route("/SNARK/pick", post(pick_snark_job))
-        .route("/SNARK/submit", post(submit_snark_proof))
+        .route(
+            "/SNARK/submit",
+            post(submit_snark_proof).layer(proof_body_limit),
+        )
         // debugging routes
         .route("/FRI/{id}/peek", get(peek_batch_data))
         .route("/FRI/{id}/failed", get(get_failed_fri_proof))
@@ -3,20 +3,38 @@
 //! This module provides an HTTP server that manages proof generation jobs
 //! and proof storage. It supports both legacy (to be deprecated end of Q4 2025)
 //! and v1 (adds support for VKs and VK filtering) API routes for prover job management.
+//!
+//! Jobs are namespaced by chain id, so that a single prover fleet can serve several chains:
+//! v1 provers may declare supported chain ids / VK hashes when picking jobs
+//! (see [`crate::prover_api::job_filter`]).
 mod legacy;
 mod v1;
 
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
 use crate::prover_api::{
     fri_job_manager::FriJobManager,
     proof_storage::ProofStorage,
     prover_server::{legacy::legacy_routes, v1::v1_routes},
-    snark_job_manager::SnarkJobManager,
+    snark_job_manager::{SnarkJobManager, SnarkSubmitError},
 };
 
 use axum::{Router, extract::DefaultBodyLimit};
+use http::StatusCode;
 use tokio::net::TcpListener;
+use tower_http::timeout::TimeoutLayer;
+
+/// Body limit of routes other than proof submission; none of them expect a body.
+const MAX_REQUEST_BODY_BYTES: usize = 16 * 1024;
+
+/// Limits of requests served by the prover API.
+#[derive(Debug, Clone, Copy)]
+pub struct ProverServerLimits {
+    /// Max time to receive a request and respond to it.
+    pub request_timeout: Duration,
+    /// Body limit of proof submission routes.
+    pub max_proof_bytes: usize,
+}
 
 /// Application state shared across all request handlers.
 #[derive(Clone)]
@@ -26,6 +44,21 @@ pub(in crate::prover_api::prover_server) struct AppState {
     proof_storage: ProofStorage,
 }
 
+/// Response to a rejected SNARK proof submission. Proofs failing verification are rejected with
+/// 422, naming the failed check; the job stays available to provers.
+pub(in crate::prover_api::prover_server) fn snark_proof_rejection(
+    err: SnarkSubmitError,
+) -> (StatusCode, String) {
+    let status = match &err {
+        SnarkSubmitError::Verification(err) if err.is_invalid_proof() => {
+            StatusCode::UNPROCESSABLE_ENTITY
+        }
+        SnarkSubmitError::Verification(_) => StatusCode::SERVICE_UNAVAILABLE,
+        SnarkSubmitError::Other(_) => StatusCode::BAD_REQUEST,
+    };
+    (status, format!("proof rejected: {err}"))
+}
+
 /// Entry point for prover API server.
 /// Starts an HTTP server listening on the specified bind address.
 pub async fn run(
@@ -33,6 +66,7 @@ pub async fn run(
     snark_job_manager: Arc<SnarkJobManager>,
     proof_storage: ProofStorage,
     bind_address: String,
+    limits: ProverServerLimits,
 ) -> anyhow::Result<()> {
     let app_state = AppState {
         fri_job_manager,
@@ -41,11 +75,12 @@ pub async fn run(
     };
 
     let app = Router::new()
-        .nest("/prover-jobs", legacy_routes())
-        .nest("/prover-jobs/v1", v1_routes())
+        .nest("/prover-jobs", legacy_routes(limits.max_proof_bytes))
+        .nest("/prover-jobs/v1", v1_routes(limits.max_proof_bytes))
         .with_state(app_state)
-        // Set the request body limit to 10MiB
-        .layer(DefaultBodyLimit::max(10 * 1024 * 1024));
+        // Overridden by proof submission routes
+        .layer(DefaultBodyLimit::max(MAX_REQUEST_BODY_BYTES))
+        .layer(TimeoutLayer::new(limits.request_timeout));
 
     let bind_address: SocketAddr = bind_address.parse()?;
     tracing::info!("starting proof data server on {bind_address}");
@@ -8,40 +8,59 @@ use axum::{
 use base64::{Engine, engine::general_purpose};
 use http::StatusCode;
 use zksync_os_l1_sender::batcher_model::FriProof;
+use zksync_os_l1_sender::cost_accounting::{BatchCost, L1CostStorage, costs_to_csv};
+use zksync_os_l1_sender::price_drift::PriceDriftReport;
 use zksync_os_multivm::ExecutionVersion;
 
 use crate::prover_api::{
     fri_job_manager::SubmitError,
     prover_server::{
-        AppState,
+        AppState, snark_proof_rejection,
         v1::models::{
-            BatchDataPayload, FailedProofResponse, FriProofPayload, NextSnarkProverJobPayload,
-            ProverQuery, SnarkProofPayload,
+            BatchDataPayload, DriftReportQuery, FailedProofResponse, FriProofPayload,
+            NextSnarkProverJobPayload, PickQuery, ProverQuery, SnarkProofPayload,
         },
     },
 };
 
+/// Maximum number of batches that can be requested from the cost endpoints at once.
+const MAX_COST_RANGE: u64 = 10_000;
+
 pub(super) async fn pick_fri_job(
-    Query(query): Query<ProverQuery>,
+    Query(query): Query<PickQuery>,
     State(state): State<AppState>,
 ) -> Response {
     tracing::trace!(
         "Received FRI job pick request from prover with ID: {}",
         query.id
     );
+    let filter = match query.filter() {
+        Ok(filter) => filter,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("{e:#}")).into_response(),
+    };
     // for real provers, we return the next job immediately -
     // see `FakeProversPool` for fake provers implementation
-    match state.fri_job_manager.pick_next_job(Duration::from_secs(0)) {
-        Some((fri_job, input)) => {
+    match state
+        .fri_job_manager
+        .pick_next_job(Duration::from_secs(0), &filter)
+    {
+        Ok(Some((fri_job, input_version, input))) => {
             let bytes: Vec<u8> = input.iter().flat_map(|v| v.to_le_bytes()).collect();
             Json(BatchDataPayload {
+                chain_id: fri_job.chain_id,
                 batch_number: fri_job.batch_number,
                 vk_hash: fri_job.vk_hash,
+                execution_version: input_version.execution_version,
+                input_format_version: input_version.input_format_version,
                 prover_input: general_purpose::STANDARD.encode(&bytes),
             })
             .into_response()
         }
-        None => StatusCode::NO_CONTENT.into_response(),
+        Ok(None) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => {
+            tracing::error!("cannot hand out FRI job: {err}");
+            (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response()
+        }
     }
 }
 
@@ -65,9 +84,18 @@ pub(super) async fn submit_fri_proof(
             format!("no Execution Version matches the provided Verification Key: {e}"),
         )
     })?;
+    let input_version = payload
+        .input_version()
+        .map_err(|e| (StatusCode::BAD_REQUEST, format!("{e:#}")))?;
     match state
         .fri_job_manager
-        .submit_proof(payload.batch_number, proof_bytes.into(), Some(execution_version), &prover_id)
+        .submit_proof(
+            payload.batch_number,
+            proof_bytes.into(),
+            Some(execution_version),
+            input_version,
+            &prover_id,
+        )
         .await
     {
         Ok(()) => Ok((StatusCode::NO_CONTENT, "proof accepted".to_string()).into_response()),
@@ -81,6 +109,18 @@ pub(super) async fn submit_fri_proof(
             )
             .to_string(),
         ))},
+        // The job is handed out again; the prover should pick a new one
+        Err(SubmitError::ProverInputVersionMismatch { expected, actual }) => Err((
+            StatusCode::CONFLICT,
+            format!(
+                "prover input version mismatch: expected execution_version = {}, input_format_version = {}; \
+                 got execution_version = {}, input_format_version = {}",
+                expected.execution_version,
+                expected.input_format_version,
+                actual.execution_version,
+                actual.input_format_version
+            ),
+        )),
         Err(SubmitError::FriProofVerificationError {
             expected_hash_u32s,
             proof_final_register_values,
@@ -95,6 +135,10 @@ pub(super) async fn submit_fri_proof(
         Err(SubmitError::DeserializationFailed(err)) => {
             Err((StatusCode::BAD_REQUEST, err.to_string()))
         }
+        Err(SubmitError::UnsupportedExecutionVersion(err)) => {
+            tracing::error!(payload.batch_number, "cannot verify proof: {err}");
+            Err((StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+        }
         Err(SubmitError::Other(e)) => {
             tracing::error!("internal error: {e}");
             Err((StatusCode::INTERNAL_SERVER_ERROR, e))
@@ -103,16 +147,21 @@ pub(super) async fn submit_fri_proof(
 }
 
 pub(super) async fn pick_snark_job(
-    Query(query): Query<ProverQuery>,
+    Query(query): Query<PickQuery>,
     State(state): State<AppState>,
 ) -> Response {
     tracing::debug!(
         "Received SNARK job pick request from prover with ID: {}",
         query.id
     );
-    match state.snark_job_manager.pick_real_job().await {
+    let filter = match query.filter() {
+        Ok(filter) => filter,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("{e:#}")).into_response(),
+    };
+    match state.snark_job_manager.pick_real_job(&filter).await {
         Ok(Some(batches)) => {
             // Expect non-empty and all real FRI proofs
+            let chain_id = batches.first().unwrap().0.chain_id;
             let from = batches.first().unwrap().0.batch_number;
             let to = batches.last().unwrap().0.batch_number;
             let vk_hash = batches.first().unwrap().0.vk_hash.clone();
@@ -135,6 +184,7 @@ pub(super) async fn pick_snark_job(
                 .collect();
 
             Json(NextSnarkProverJobPayload {
+                chain_id,
                 from_batch_number: from,
                 to_batch_number: to,
                 vk_hash,
@@ -179,10 +229,7 @@ pub(super) async fn submit_snark_proof(
         .await
     {
         Ok(()) => Ok((StatusCode::NO_CONTENT, "proof accepted".to_string()).into_response()),
-        Err(err) => Err((
-            StatusCode::BAD_REQUEST,
-            format!("proof rejected: {err}").to_string(),
-        )),
+        Err(err) => Err(snark_proof_rejection(err)),
     }
 }
 
@@ -194,6 +241,7 @@ pub(super) async fn peek_fri_job(
         Some((vk_hash, prover_input)) => {
             let bytes: Vec<u8> = prover_input.iter().flat_map(|v| v.to_le_bytes()).collect();
             Json(BatchDataPayload {
+                chain_id: state.fri_job_manager.chain_id(),
                 batch_number,
                 vk_hash: vk_hash.to_string(),
                 prover_input: general_purpose::STANDARD.encode(&bytes),
@@ -263,6 +311,7 @@ pub(super) async fn peek_snark_job(
         }
     }
     Json(NextSnarkProverJobPayload {
+        chain_id: state.fri_job_manager.chain_id(),
         from_batch_number,
         to_batch_number,
         vk_hash,
@@ -276,6 +325,12 @@ pub(super) async fn status(State(state): State<AppState>) -> Response {
     Json(status).into_response()
 }
 
+/// Queue depth grouped per chain.
+pub(super) async fn chains_status(State(state): State<AppState>) -> Response {
+    let status = vec![state.fri_job_manager.queue_state()];
+    Json(status).into_response()
+}
+
 /// Get detailed information about a failed FRI proof for debugging.
 /// Returns the most recent failed proof for the given batch number.
 pub(super) async fn get_failed_fri_proof(
@@ -310,3 +365,70 @@ pub(super) async fn get_failed_fri_proof(
         }
     }
 }
+
+/// L1 fees paid for batches `from..=to` (batches without recorded L1 transactions are skipped).
+pub(super) async fn batch_costs(
+    Path((from_batch_number, to_batch_number)): Path<(u64, u64)>,
+    State(state): State<AppState>,
+) -> Result<Response, (StatusCode, String)> {
+    let costs = load_batch_costs(&state, from_batch_number, to_batch_number).await?;
+    Ok(Json(costs).into_response())
+}
+
+/// Same as [`batch_costs`], rendered as CSV (one row per batch and operation).
+pub(super) async fn batch_costs_csv(
+    Path((from_batch_number, to_batch_number)): Path<(u64, u64)>,
+    State(state): State<AppState>,
+) -> Result<Response, (StatusCode, String)> {
+    let costs = load_batch_costs(&state, from_batch_number, to_batch_number).await?;
+    Ok((
+        [(http::header::CONTENT_TYPE, "text/csv")],
+        costs_to_csv(&costs),
+    )
+        .into_response())
+}
+
+/// Drift of the L1 prices predicted for batches `from..=to` from the ones paid on L1, optionally
+/// restricted to L1 transactions included within `from_timestamp..=to_timestamp`.
+pub(super) async fn batch_costs_drift(
+    Path((from_batch_number, to_batch_number)): Path<(u64, u64)>,
+    Query(query): Query<DriftReportQuery>,
+    State(state): State<AppState>,
+) -> Result<Response, (StatusCode, String)> {
+    let costs = load_batch_costs(&state, from_batch_number, to_batch_number).await?;
+    let report = PriceDriftReport::new(
+        &costs,
+        query.from_timestamp.unwrap_or(0),
+        query.to_timestamp.unwrap_or(u64::MAX),
+    );
+    Ok(Json(report).into_response())
+}
+
+async fn load_batch_costs(
+    state: &AppState,
+    from_batch_number: u64,
+    to_batch_number: u64,
+) -> Result<Vec<BatchCost>, (StatusCode, String)> {
+    if from_batch_number > to_batch_number || to_batch_number - from_batch_number >= MAX_COST_RANGE
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!(
+                "Invalid range: expected from_batch_number ({from_batch_number}) <= to_batch_number ({to_batch_number}) spanning at most {MAX_COST_RANGE} batches"
+            ),
+        ));
+    }
+    state
+        .proof_storage
+        .costs(from_batch_number, to_batch_number)
+        .await
+        .map_err(|e| {
+            tracing::info!(
+                "Error retrieving L1 costs for batches {from_batch_number}-{to_batch_number}: {e}"
+            );
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error retrieving L1 costs: {e}"),
+            )
+        })
+}
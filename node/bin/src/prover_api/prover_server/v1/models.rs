@@ -1,9 +1,17 @@
 use serde::{Deserialize, Serialize};
 
+use crate::prover_api::job_filter::JobFilter;
+use zksync_os_l1_sender::batcher_model::ProverInputVersion;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub(super) struct BatchDataPayload {
+    pub chain_id: u64,
     pub batch_number: u64,
     pub vk_hash: String,
+    /// Proving execution version the input was generated for; must be echoed back on submission.
+    pub execution_version: u32,
+    /// Format version of the input; must be echoed back on submission.
+    pub input_format_version: u32,
     pub prover_input: String, // base64‑encoded little‑endian u32 array
 }
 
@@ -12,15 +20,58 @@ pub(super) struct ProverQuery {
     pub id: String,
 }
 
+/// Query of pick requests. On top of its id, a prover may declare the chains and VKs it supports
+/// as comma-separated lists. Omitted lists mean "any".
+#[derive(Debug, Deserialize)]
+pub(super) struct PickQuery {
+    pub id: String,
+    pub chain_id: Option<String>,
+    pub vk_hash: Option<String>,
+}
+
+impl PickQuery {
+    pub fn filter(&self) -> anyhow::Result<JobFilter> {
+        JobFilter::parse(self.chain_id.as_deref(), self.vk_hash.as_deref())
+    }
+}
+
+/// Time range (unix timestamps, inclusive) of the L1 price drift report.
+#[derive(Debug, Deserialize)]
+pub(super) struct DriftReportQuery {
+    pub from_timestamp: Option<u64>,
+    pub to_timestamp: Option<u64>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub(super) struct FriProofPayload {
     pub batch_number: u64,
     pub vk_hash: String,
     pub proof: String,
+    /// Versions received with the job. Optional for provers predating input versioning.
+    #[serde(default)]
+    pub execution_version: Option<u32>,
+    #[serde(default)]
+    pub input_format_version: Option<u32>,
+}
+
+impl FriProofPayload {
+    pub fn input_version(&self) -> anyhow::Result<Option<ProverInputVersion>> {
+        match (self.execution_version, self.input_format_version) {
+            (Some(execution_version), Some(input_format_version)) => Ok(Some(ProverInputVersion {
+                execution_version,
+                input_format_version,
+            })),
+            (None, None) => Ok(None),
+            _ => Err(anyhow::anyhow!(
+                "`execution_version` and `input_format_version` must be provided together"
+            )),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub(super) struct NextSnarkProverJobPayload {
+    pub chain_id: u64,
     pub from_batch_number: u64,
     pub to_batch_number: u64,
     pub vk_hash: String,
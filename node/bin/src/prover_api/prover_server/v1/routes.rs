@@ -1,26 +1,40 @@
 use axum::{
     Router,
+    extract::DefaultBodyLimit,
     routing::{get, post},
 };
 
 use crate::prover_api::prover_server::{
     AppState,
     v1::handlers::{
-        get_failed_fri_proof, peek_fri_job, peek_snark_job, pick_fri_job, pick_snark_job, status,
-        submit_fri_proof, submit_snark_proof,
+        batch_costs, batch_costs_csv, batch_costs_drift, chains_status, get_failed_fri_proof,
+        peek_fri_job, peek_snark_job, pick_fri_job, pick_snark_job, status, submit_fri_proof,
+        submit_snark_proof,
     },
 };
 
-pub(in crate::prover_api::prover_server) fn v1_routes() -> Router<AppState> {
+pub(in crate::prover_api::prover_server) fn v1_routes(max_proof_bytes: usize) -> Router<AppState> {
+    let proof_body_limit = DefaultBodyLimit::max(max_proof_bytes);
     Router::new()
         // server <-> prover routes
         .route("/FRI/pick", post(pick_fri_job))
-        .route("/FRI/submit", post(submit_fri_proof))
+        .route(
+            "/FRI/submit",
+            post(submit_fri_proof).layer(proof_body_limit),
+        )
         .route("/SNARK/pick", post(pick_snark_job))
-        .route("/SNARK/submit", post(submit_snark_proof))
+        .route(
+            "/SNARK/submit",
+            post(submit_snark_proof).layer(proof_body_limit),
+        )
         // debugging routes
         .route("/FRI/{id}/peek", get(peek_fri_job))
         .route("/FRI/{id}/failed", get(get_failed_fri_proof))
         .route("/SNARK/{from}/{to}/peek", get(peek_snark_job))
         .route("/status/", get(status))
+        .route("/status/chains/", get(chains_status))
+        // L1 cost accounting
+        .route("/costs/{from}/{to}", get(batch_costs))
+        .route("/costs/{from}/{to}/csv", get(batch_costs_csv))
+        .route("/costs/{from}/{to}/drift", get(batch_costs_drift))
 }
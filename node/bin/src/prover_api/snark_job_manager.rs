@@ -1,5 +1,5 @@
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::Mutex;
 use tokio::sync::mpsc::Sender;
 use zksync_os_l1_sender::batcher_metrics::BatchExecutionStage;
@@ -14,6 +14,20 @@ use zksync_os_observability::{
 use zksync_os_pipeline::PeekableReceiver;
 
 use crate::prover_api::fri_job_manager::FriJob;
+use crate::prover_api::job_filter::JobFilter;
+use crate::prover_api::proof_storage::ProofStorage;
+use crate::prover_api::snark_proof_verifier::{
+    SnarkProofVerification, SnarkVerificationError, SnarkVerificationRecord,
+    check_snark_proof_format, verify_snark_proof,
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SnarkSubmitError {
+    #[error("SNARK proof verification failed: {0}")]
+    Verification(#[from] SnarkVerificationError),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
 
 /// Job manager for SNARK proving.
 ///
@@ -51,9 +65,13 @@ pub struct SnarkJobManager {
     committed_batch_receiver: Mutex<PeekableReceiver<SignedBatchEnvelope<FriProof>>>,
     // outbound
     prove_batches_sender: Sender<ProofCommand>,
+    proof_storage: ProofStorage,
 
     // config
+    chain_id: u64,
     max_fris_per_snark: usize,
+    /// `None` if submitted proofs are accepted without verification.
+    verification: Option<SnarkProofVerification>,
     // metrics
     latency_tracker: ComponentStateHandle<GenericComponentState>,
 }
@@ -65,8 +83,11 @@ impl SnarkJobManager {
         committed_batch_receiver: PeekableReceiver<SignedBatchEnvelope<FriProof>>,
         // outbound
         prove_batches_sender: Sender<ProofCommand>,
+        proof_storage: ProofStorage,
         // config
+        chain_id: u64,
         max_fris_per_snark: usize,
+        verification: Option<SnarkProofVerification>,
     ) -> Self {
         let latency_tracker = ComponentStateReporter::global().handle_for(
             "snark_job_manager",
@@ -76,13 +97,23 @@ impl SnarkJobManager {
         Self {
             committed_batch_receiver,
             prove_batches_sender,
+            proof_storage,
+            chain_id,
             max_fris_per_snark,
+            verification,
             latency_tracker,
         }
     }
 
     // If there is a job pending, returns a non-empty list of tuples (`batch_number`, `verification_key_hash`, `real_fri_proof`)
-    pub async fn pick_real_job(&self) -> anyhow::Result<Option<Vec<(FriJob, FriProof)>>> {
+    // Returns `None` if the pending job is not accepted by `filter` (other chain or unsupported VK).
+    pub async fn pick_real_job(
+        &self,
+        filter: &JobFilter,
+    ) -> anyhow::Result<Option<Vec<(FriJob, FriProof)>>> {
+        if !filter.accepts_chain(self.chain_id) {
+            return Ok(None);
+        }
         self.consume_fake_proves_from_head(None).await?;
         // note that here we don't consume the messages from channel -
         // the job will be picked, but there is no guarantee it will be completed
@@ -103,6 +134,7 @@ impl SnarkJobManager {
                     .expect("execution version must exist as it was set by server");
                     Some((
                         FriJob {
+                            chain_id: self.chain_id,
                             batch_number: envelope.batch_number(),
                             vk_hash: proving_execution_version.vk_hash().to_string(),
                         },
@@ -116,6 +148,9 @@ impl SnarkJobManager {
 
         // Get proofs that were created for the same execution version/VK.
         let first_vk_hash = batches_with_real_proofs[0].0.vk_hash.clone();
+        if !filter.accepts_vk(&first_vk_hash) {
+            return Ok(None);
+        }
         let batches_with_real_proofs: Vec<_> = batches_with_real_proofs
             .into_iter()
             .take_while(|(fri_job, _)| fri_job.vk_hash == first_vk_hash)
@@ -130,13 +165,17 @@ impl SnarkJobManager {
         Ok(Some(batches_with_real_proofs))
     }
 
+    /// Accepts a real SNARK proof for batches `batch_from..=batch_to` and sends it to L1. If
+    /// verification is enabled, the proof must pass it; rejected proofs leave the job available
+    /// to provers.
     pub async fn submit_proof(
         &self,
         batch_from: u64,
         batch_to: u64,
         execution_version: Option<ExecutionVersion>,
         payload: Vec<u8>,
-    ) -> anyhow::Result<()> {
+    ) -> Result<(), SnarkSubmitError> {
+        check_snark_proof_format(&payload)?;
         let mut receiver = self.committed_batch_receiver.lock().await;
 
         // first check that queue is consistent with the submitted proof
@@ -145,13 +184,12 @@ impl SnarkJobManager {
         let pending_batch_number = receiver.peek_with(|envelope| envelope.batch_number());
         match pending_batch_number {
             Some(expected_batch_number) if batch_from != expected_batch_number => {
-                anyhow::bail!(
+                return Err(anyhow::anyhow!(
                     "Batch range error. Expected first batch: {expected_batch_number}, received: {batch_from}-{batch_to}"
-                );
-            }
-            None => {
-                anyhow::bail!("No pending batches to prove")
+                )
+                .into());
             }
+            None => return Err(anyhow::anyhow!("No pending batches to prove").into()),
             _ => {
                 tracing::debug!(
                     "submitted proof is consistent with queue state. (proof for batches {batch_from}-{batch_to})"
@@ -159,39 +197,30 @@ impl SnarkJobManager {
             }
         }
 
+        // (stored batch info, verification key hash) of proven batches
         let batches_proven = receiver
             // we don't apply max_fris_per_snark when accepting complete jobs (maybe it was changed after job was picked)
             .peek_until(usize::MAX, |envelope| {
                 if envelope.batch_number() <= batch_to {
-                    Some(envelope.data.clone())
+                    Some((
+                        envelope.batch.batch_info.clone().into_stored(),
+                        envelope.batch.verification_key_hash(),
+                    ))
                 } else {
                     None
                 }
             });
-
-        anyhow::ensure!(
-            batches_proven.len() == (batch_to - batch_from + 1) as usize,
-            "Fatal error: inconsistent queue state ({} batches between numbers {batch_from} and {batch_to})",
-            batches_proven.len()
-        );
-
-        // note: we still hold mutex while verifying the proof -
-        // this is desired since we don't want the batches to timeout
-
-        // todo: verify_snark_proof()
-        // if false {
-        //     anyhow::bail!("proof validation failed")
-        // }
-
-        // prove is valid - consuming proven batches
-        let consumed_batches_proven: Vec<SignedBatchEnvelope<FriProof>> =
-            receiver.try_recv_while(usize::MAX, |envelope| envelope.batch_number() <= batch_to);
-
-        // very unlikely - we just peeked the same batches
-        anyhow::ensure!(
-            batches_proven.len() == consumed_batches_proven.len(),
-            "Fatal error: inconsistency in PeekableReceiver"
-        );
+        let previous_batch = receiver
+            .peek_with(|envelope| envelope.batch.previous_stored_batch_info.clone())
+            .expect("queue head was just peeked");
+
+        if batches_proven.len() != (batch_to - batch_from + 1) as usize {
+            return Err(anyhow::anyhow!(
+                "Fatal error: inconsistent queue state ({} batches between numbers {batch_from} and {batch_to})",
+                batches_proven.len()
+            )
+            .into());
+        }
 
         // Prover should generate the proof with VK received from server. These must always match.
         // If they don't, proof won't be accepted, validation will fail, therefore it's pointless to proceed.
@@ -200,23 +229,64 @@ impl SnarkJobManager {
         //
         // NOTE: Checking only if prover provided VK version - legacy clients may not provide it
         if let Some(exec_version) = execution_version {
-            let server_vk = consumed_batches_proven[0].batch.verification_key_hash();
+            let server_vk = batches_proven[0].1;
             let prover_vk = exec_version.vk_hash();
-            anyhow::ensure!(
-                server_vk == prover_vk,
-                "Verification key hash mismatch: server got {server_vk}, prover got {prover_vk}"
-            );
+            if server_vk != prover_vk {
+                return Err(anyhow::anyhow!(
+                    "Verification key hash mismatch: server got {server_vk}, prover got {prover_vk}"
+                )
+                .into());
+            }
         }
 
         // get verification key, if available, otherwise fallback
-        let execution_version = if let Some(execution_version) = execution_version {
-            execution_version as u32
-        } else {
-            consumed_batches_proven[0]
-                .data
-                .proving_execution_version()
-                .unwrap_or(2)
+        let execution_version = match execution_version {
+            Some(execution_version) => execution_version as u32,
+            None => receiver
+                .peek_with(|envelope| envelope.data.proving_execution_version())
+                .flatten()
+                .unwrap_or(2),
         };
+        let proof = SnarkProof::Real(RealSnarkProof::V2 {
+            proof: payload,
+            proving_execution_version: execution_version,
+        });
+
+        // note: we still hold mutex while verifying the proof -
+        // this is desired since we don't want the batches to timeout
+        if let Some(verification) = &self.verification {
+            let stored_batches: Vec<_> = batches_proven
+                .into_iter()
+                .map(|(stored_batch, _)| stored_batch)
+                .collect();
+            if let Err(err) =
+                verify_snark_proof(verification, &previous_batch, &stored_batches, &proof).await
+            {
+                tracing::warn!(
+                    "real SNARK proof for batches {batch_from}-{batch_to} is rejected: {err}"
+                );
+                return Err(err.into());
+            }
+            let record = SnarkVerificationRecord {
+                from_batch: batch_from,
+                to_batch: batch_to,
+                proving_execution_version: execution_version,
+                verified_at: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+            };
+            self.proof_storage.save_snark_verification(&record).await?;
+        }
+
+        // prove is valid - consuming proven batches
+        let consumed_batches_proven: Vec<SignedBatchEnvelope<FriProof>> =
+            receiver.try_recv_while(usize::MAX, |envelope| envelope.batch_number() <= batch_to);
+
+        // very unlikely - we just peeked the same batches
+        if consumed_batches_proven.len() != (batch_to - batch_from + 1) as usize {
+            return Err(anyhow::anyhow!("Fatal error: inconsistency in PeekableReceiver").into());
+        }
 
         drop(receiver);
 
@@ -227,14 +297,8 @@ impl SnarkJobManager {
             .map(|batch| batch.with_stage(BatchExecutionStage::SnarkProvedReal))
             .collect();
 
-        self.send_downstream(ProofCommand::new(
-            consumed_batches_proven,
-            SnarkProof::Real(RealSnarkProof::V2 {
-                proof: payload,
-                proving_execution_version: execution_version,
-            }),
-        ))
-        .await?;
+        self.send_downstream(ProofCommand::new(consumed_batches_proven, proof))
+            .await?;
         Ok(())
     }
 
@@ -347,3 +411,112 @@ impl FakeSnarkProver {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prover_api::snark_proof_verifier::SnarkProofVerifier;
+    use alloy::primitives::{Bytes, U256};
+    use tokio::sync::mpsc;
+    use zksync_os_l1_sender::batcher_model::{
+        BatchEnvelope, BatchMetadata, BatchSignatureData, RealFriProof,
+    };
+    use zksync_os_object_store::MockObjectStore;
+
+    /// Stands in for a SNARK proof produced by a prover.
+    fn fixture_proof() -> Vec<u8> {
+        (0..64).collect()
+    }
+
+    /// Only accepts [`fixture_proof`] for a single batch.
+    struct FixtureVerifier;
+
+    #[async_trait::async_trait]
+    impl SnarkProofVerifier for FixtureVerifier {
+        async fn verify(&self, public_inputs: Vec<U256>, proof: Vec<U256>) -> anyhow::Result<bool> {
+            // The proof is prefixed with the verifier type and the previous public input hash
+            let fixture: Vec<_> = fixture_proof()
+                .chunks(32)
+                .map(|chunk| U256::from_be_slice(chunk))
+                .collect();
+            Ok(public_inputs.len() == 1 && proof[2..] == fixture[..])
+        }
+    }
+
+    fn batch_envelope() -> SignedBatchEnvelope<FriProof> {
+        let metadata = BatchMetadata::for_tests(1, 1..=1, 1);
+        let fri_proof = FriProof::Real(RealFriProof::V2 {
+            proof: Bytes::from_static(b"fri proof"),
+            proving_execution_version: 4,
+        });
+        BatchEnvelope::new(metadata, fri_proof).with_signatures(BatchSignatureData::NotNeeded)
+    }
+
+    #[tokio::test]
+    async fn only_verified_proofs_are_accepted() {
+        let (inbound_sender, inbound) = mpsc::channel(1);
+        let (proof_sender, mut proof_receiver) = mpsc::channel(1);
+        let proof_storage = ProofStorage::new(MockObjectStore::arc());
+        let manager = SnarkJobManager::new(
+            PeekableReceiver::new(inbound),
+            proof_sender,
+            proof_storage.clone(),
+            270,
+            10,
+            Some(SnarkProofVerification {
+                verifier: Arc::new(FixtureVerifier),
+                timeout: Duration::from_secs(10),
+            }),
+        );
+        let filter = JobFilter::default();
+        inbound_sender.send(batch_envelope()).await.unwrap();
+        assert!(manager.pick_real_job(&filter).await.unwrap().is_some());
+
+        let mut corrupted_proof = fixture_proof();
+        corrupted_proof[40] ^= 1;
+        let err = manager
+            .submit_proof(1, 1, Some(ExecutionVersion::V4), corrupted_proof)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                &err,
+                SnarkSubmitError::Verification(err @ SnarkVerificationError::InvalidProof { .. })
+                    if err.is_invalid_proof()
+            ),
+            "{err:?}"
+        );
+        let err = manager
+            .submit_proof(1, 1, Some(ExecutionVersion::V4), vec![0; 33])
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                err,
+                SnarkSubmitError::Verification(SnarkVerificationError::MalformedProof(33))
+            ),
+            "{err:?}"
+        );
+
+        // Rejected proofs are neither sent to L1 nor marked verified, and the job can be picked again
+        assert!(proof_receiver.try_recv().is_err());
+        assert_eq!(proof_storage.get_snark_verification(1).await.unwrap(), None);
+        let jobs = manager.pick_real_job(&filter).await.unwrap().unwrap();
+        assert_eq!(jobs[0].0.batch_number, 1);
+
+        manager
+            .submit_proof(1, 1, Some(ExecutionVersion::V4), fixture_proof())
+            .await
+            .unwrap();
+        let proof_command = proof_receiver.try_recv().unwrap();
+        assert_eq!(proof_command.as_ref().len(), 1);
+        let record = proof_storage
+            .get_snark_verification(1)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((record.from_batch, record.to_batch), (1, 1));
+        assert_eq!(record.proving_execution_version, 4);
+        assert!(manager.pick_real_job(&filter).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,109 @@
+//! Verification of submitted SNARK proofs.
+//!
+//! Proofs are checked by the same verifier contract the prove transaction is going to call on L1
+//! (with the verification key of the proof's execution version), against public inputs recomputed
+//! from the commitments of the proven batches. This way invalid proofs are rejected on submission,
+//! rather than by a reverted prove transaction.
+
+use alloy::primitives::U256;
+use alloy::providers::DynProvider;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use zksync_os_contract_interface::ZkChain;
+use zksync_os_contract_interface::models::StoredBatchInfo;
+use zksync_os_l1_sender::batcher_model::SnarkProof;
+use zksync_os_l1_sender::commands::prove::ProofCommand;
+
+/// Checks SNARK proofs as the L1 verifier does.
+#[async_trait]
+pub trait SnarkProofVerifier: Send + Sync {
+    /// Returns whether `proof` is valid for `public_inputs`.
+    async fn verify(&self, public_inputs: Vec<U256>, proof: Vec<U256>) -> anyhow::Result<bool>;
+}
+
+#[async_trait]
+impl SnarkProofVerifier for ZkChain<DynProvider> {
+    async fn verify(&self, public_inputs: Vec<U256>, proof: Vec<U256>) -> anyhow::Result<bool> {
+        match self.verify_proof(public_inputs, proof).await {
+            Ok(valid) => Ok(valid),
+            // Verifiers revert on some malformed proofs instead of returning `false`
+            Err(err) if err.as_revert_data().is_some() => {
+                tracing::debug!(?err, "SNARK verifier reverted");
+                Ok(false)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// Verifier of submitted SNARK proofs along with its limits.
+#[derive(Clone)]
+pub struct SnarkProofVerification {
+    pub verifier: Arc<dyn SnarkProofVerifier>,
+    /// Max time to verify a proof.
+    pub timeout: Duration,
+}
+
+/// Reason for rejecting a submitted SNARK proof.
+#[derive(Debug, thiserror::Error)]
+pub enum SnarkVerificationError {
+    #[error("proof length check failed: {0} bytes is not a multiple of 32")]
+    MalformedProof(usize),
+    #[error("verifier check failed: proof is invalid for public inputs {public_inputs:?}")]
+    InvalidProof { public_inputs: Vec<U256> },
+    #[error("verification timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("verifier is unavailable: {0:#}")]
+    Unavailable(anyhow::Error),
+}
+
+impl SnarkVerificationError {
+    /// Whether the proof itself is invalid, as opposed to the verification not being possible.
+    pub fn is_invalid_proof(&self) -> bool {
+        matches!(self, Self::MalformedProof(_) | Self::InvalidProof { .. })
+    }
+}
+
+/// Checks that a submitted SNARK proof can be passed to the verifier.
+pub fn check_snark_proof_format(proof: &[u8]) -> Result<(), SnarkVerificationError> {
+    if proof.is_empty() || proof.len() % 32 != 0 {
+        return Err(SnarkVerificationError::MalformedProof(proof.len()));
+    }
+    Ok(())
+}
+
+/// Verifies `proof` of `batches` following `previous_batch`.
+pub async fn verify_snark_proof(
+    verification: &SnarkProofVerification,
+    previous_batch: &StoredBatchInfo,
+    batches: &[StoredBatchInfo],
+    proof: &SnarkProof,
+) -> Result<(), SnarkVerificationError> {
+    let (public_inputs, proof) = ProofCommand::verifier_inputs(previous_batch, batches, proof);
+    let verified = tokio::time::timeout(
+        verification.timeout,
+        verification.verifier.verify(public_inputs.clone(), proof),
+    )
+    .await
+    .map_err(|_| SnarkVerificationError::Timeout(verification.timeout))?
+    .map_err(SnarkVerificationError::Unavailable)?;
+    if verified {
+        Ok(())
+    } else {
+        Err(SnarkVerificationError::InvalidProof { public_inputs })
+    }
+}
+
+/// Record of a SNARK proof that passed verification, stored for each batch it proves.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnarkVerificationRecord {
+    /// First batch proven by the SNARK proof.
+    pub from_batch: u64,
+    /// Last batch proven by the SNARK proof.
+    pub to_batch: u64,
+    pub proving_execution_version: u32,
+    /// Unix timestamp (in seconds) of the verification.
+    pub verified_at: u64,
+}
@@ -1,4 +1,6 @@
+use super::proof_storage::ProofStorage;
 use super::snark_job_manager::SnarkJobManager;
+use super::snark_proof_verifier::SnarkProofVerification;
 use async_trait::async_trait;
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -26,7 +28,10 @@ pub struct SnarkProvingPipelineStep {
 
 impl SnarkProvingPipelineStep {
     pub fn new(
+        proof_storage: ProofStorage,
+        chain_id: u64,
         max_fris_per_snark: usize,
+        verification: Option<SnarkProofVerification>,
         last_proved_batch_number: u64,
     ) -> (Self, Arc<SnarkJobManager>) {
         // Create channels for SnarkJobManager
@@ -41,7 +46,10 @@ impl SnarkProvingPipelineStep {
         let snark_job_manager = Arc::new(SnarkJobManager::new(
             PeekableReceiver::new(batches_for_prove_receiver),
             proof_commands_sender,
+            proof_storage,
+            chain_id,
             max_fris_per_snark,
+            verification,
         ));
 
         let result = Self {
@@ -12,10 +12,12 @@ use zksync_os_interface::traits::TxListSource;
 use zksync_os_interface::types::BlockOutput;
 use zksync_os_l1_sender::batcher_model::ProverInput;
 use zksync_os_merkle_tree::{MerkleTreeVersion, RocksDBWrapper, fixed_bytes_to_bytes32};
-use zksync_os_multivm::{AbiTxSource, ExecutionVersion, proving_run_execution_version};
+use zksync_os_multivm::{
+    AbiTxSource, ExecutionVersion, MultivmError, proving_run_execution_version,
+};
 use zksync_os_observability::{ComponentStateReporter, GenericComponentState};
 use zksync_os_pipeline::{PeekableReceiver, PipelineComponent};
-use zksync_os_storage_api::{ReadStateHistory, ReplayRecord};
+use zksync_os_storage_api::{BlockStats, ReadStateHistory, ReplayRecord};
 use zksync_os_types::ZksyncOsEncode;
 
 /// This component generates prover input from batch replay data
@@ -30,8 +32,14 @@ pub struct ProverInputGenerator<ReadState> {
 impl<ReadState: ReadStateHistory + Clone + Send + 'static> PipelineComponent
     for ProverInputGenerator<ReadState>
 {
-    type Input = (BlockOutput, ReplayRecord, BlockMerkleTreeData);
-    type Output = (BlockOutput, ReplayRecord, ProverInput, BlockMerkleTreeData);
+    type Input = (BlockOutput, ReplayRecord, BlockStats, BlockMerkleTreeData);
+    type Output = (
+        BlockOutput,
+        ReplayRecord,
+        BlockStats,
+        ProverInput,
+        BlockMerkleTreeData,
+    );
 
     const NAME: &'static str = "prover_input_generator";
     const OUTPUT_BUFFER_SIZE: usize = 5;
@@ -55,7 +63,7 @@ impl<ReadState: ReadStateHistory + Clone + Send + 'static> PipelineComponent
 
         ReceiverStream::new(input.into_inner())
             // generate prover input. Use up to `maximum_in_flight_blocks` threads
-            .map(|(block_output, replay_record, tree)| {
+            .map(|(block_output, replay_record, stats, tree)| {
                 let block_number = replay_record.block_context.block_number;
 
                 tracing::debug!(
@@ -72,24 +80,30 @@ impl<ReadState: ReadStateHistory + Clone + Send + 'static> PipelineComponent
                         tree.block_start.clone(),
                         app_bin_base_path_clone,
                         enable_logging,
-                    );
-                    (block_output, replay_record, prover_input, tree)
+                    )?;
+                    anyhow::Ok((block_output, replay_record, stats, prover_input, tree))
                 })
             })
             .buffered(maximum_in_flight_blocks)
-            .map_err(|e| anyhow::anyhow!(e))
-            .try_for_each(|(block_output, replay_record, prover_input, tree)| async {
-                latency_tracker.enter_state(GenericComponentState::WaitingSend);
-                tracing::debug!(
-                    block_number = block_output.header.number,
-                    "sending block with prover input to batcher",
-                );
-                output
-                    .send((block_output, replay_record, prover_input, tree))
-                    .await?;
-                latency_tracker.enter_state(GenericComponentState::ProcessingOrWaitingRecv);
-                Ok(())
+            .map(|joined| {
+                joined
+                    .map_err(|e| anyhow::anyhow!(e))
+                    .and_then(|result| result)
             })
+            .try_for_each(
+                |(block_output, replay_record, stats, prover_input, tree)| async {
+                    latency_tracker.enter_state(GenericComponentState::WaitingSend);
+                    tracing::debug!(
+                        block_number = block_output.header.number,
+                        "sending block with prover input to batcher",
+                    );
+                    output
+                        .send((block_output, replay_record, stats, prover_input, tree))
+                        .await?;
+                    latency_tracker.enter_state(GenericComponentState::ProcessingOrWaitingRecv);
+                    Ok(())
+                },
+            )
             .await
     }
 }
@@ -100,7 +114,7 @@ fn compute_prover_input(
     tree_view: MerkleTreeVersion<RocksDBWrapper>,
     app_bin_base_path: PathBuf,
     enable_logging: bool,
-) -> Vec<u32> {
+) -> Result<Vec<u32>> {
     let block_number = replay_record.block_context.block_number;
     let state_view = state_handle.state_view_at(block_number - 1).unwrap();
     let (root_hash, leaf_count) = tree_view.root_info().unwrap();
@@ -112,85 +126,94 @@ fn compute_prover_input(
 
     let prover_input_generation_latency =
         PROVER_INPUT_GENERATOR_METRICS.prover_input_generation[&"prover_input_generation"].start();
-    let prover_input =
-        match proving_run_execution_version(replay_record.block_context.execution_version) {
-            ExecutionVersion::V1 | ExecutionVersion::V2 => {
-                unreachable!("proving_run_execution_version does not return 1 or 2")
-            } // we prove v1 and v2 blocks with v3, it's reflected in `proving_run_execution_version`
-            ExecutionVersion::V3 => {
-                use zk_ee_0_0_26::{
-                    common_structs::ProofData, system::metadata::BlockMetadataFromOracle,
-                };
-                use zk_os_forward_system_0_0_26::run::{
-                    StorageCommitment, convert::FromInterface, generate_proof_input,
-                };
-
-                let initial_storage_commitment = StorageCommitment {
-                    root: fixed_bytes_to_bytes32(root_hash).as_u8_array().into(),
-                    next_free_slot: leaf_count,
-                };
-
-                let list_source = AbiTxSource::new(TxListSource { transactions });
-
-                let bin_path = if enable_logging {
-                    zksync_os_multivm::apps::v3::singleblock_batch_logging_enabled_path(
-                        &app_bin_base_path,
-                    )
-                } else {
-                    zksync_os_multivm::apps::v3::singleblock_batch_path(&app_bin_base_path)
-                };
-
-                generate_proof_input(
-                    bin_path,
-                    BlockMetadataFromOracle::from_interface(replay_record.block_context),
-                    ProofData {
-                        state_root_view: initial_storage_commitment,
-                        last_block_timestamp: replay_record.previous_block_timestamp,
-                    },
-                    tree_view,
-                    state_view,
-                    list_source,
+    let proving_execution_version =
+        proving_run_execution_version(replay_record.block_context.execution_version).map_err(
+            |err| match err {
+                MultivmError::UnsupportedExecutionVersion { got, .. } => anyhow::Error::new(err)
+                    .context(format!(
+                        "cannot prove block {block_number} with execution version {got} unknown \
+                         to this node; upgrade the node"
+                    )),
+                MultivmError::ExecutionFailed(err) => err,
+            },
+        )?;
+    let prover_input = match proving_execution_version {
+        ExecutionVersion::V1 | ExecutionVersion::V2 => {
+            unreachable!("proving_run_execution_version does not return 1 or 2")
+        } // we prove v1 and v2 blocks with v3, it's reflected in `proving_run_execution_version`
+        ExecutionVersion::V3 => {
+            use zk_ee_0_0_26::{
+                common_structs::ProofData, system::metadata::BlockMetadataFromOracle,
+            };
+            use zk_os_forward_system_0_0_26::run::{
+                StorageCommitment, convert::FromInterface, generate_proof_input,
+            };
+
+            let initial_storage_commitment = StorageCommitment {
+                root: fixed_bytes_to_bytes32(root_hash).as_u8_array().into(),
+                next_free_slot: leaf_count,
+            };
+
+            let list_source = AbiTxSource::new(TxListSource { transactions });
+
+            let bin_path = if enable_logging {
+                zksync_os_multivm::apps::v3::singleblock_batch_logging_enabled_path(
+                    &app_bin_base_path,
                 )
-                .expect("proof gen failed")
-            }
-            ExecutionVersion::V4 => {
-                use zk_ee::{
-                    common_structs::ProofData,
-                    system::metadata::zk_metadata::BlockMetadataFromOracle,
-                };
-                use zk_os_forward_system::run::{
-                    StorageCommitment, convert::FromInterface, generate_proof_input,
-                };
-
-                let initial_storage_commitment = StorageCommitment {
-                    root: fixed_bytes_to_bytes32(root_hash).as_u8_array().into(),
-                    next_free_slot: leaf_count,
-                };
-
-                let list_source = TxListSource { transactions };
-
-                let bin_path = if enable_logging {
-                    zksync_os_multivm::apps::v4::singleblock_batch_logging_enabled_path(
-                        &app_bin_base_path,
-                    )
-                } else {
-                    zksync_os_multivm::apps::v4::singleblock_batch_path(&app_bin_base_path)
-                };
-
-                generate_proof_input(
-                    bin_path,
-                    BlockMetadataFromOracle::from_interface(replay_record.block_context),
-                    ProofData {
-                        state_root_view: initial_storage_commitment,
-                        last_block_timestamp: replay_record.previous_block_timestamp,
-                    },
-                    tree_view,
-                    state_view,
-                    list_source,
+            } else {
+                zksync_os_multivm::apps::v3::singleblock_batch_path(&app_bin_base_path)
+            };
+
+            generate_proof_input(
+                bin_path,
+                BlockMetadataFromOracle::from_interface(replay_record.block_context),
+                ProofData {
+                    state_root_view: initial_storage_commitment,
+                    last_block_timestamp: replay_record.previous_block_timestamp,
+                },
+                tree_view,
+                state_view,
+                list_source,
+            )
+            .expect("proof gen failed")
+        }
+        ExecutionVersion::V4 => {
+            use zk_ee::{
+                common_structs::ProofData, system::metadata::zk_metadata::BlockMetadataFromOracle,
+            };
+            use zk_os_forward_system::run::{
+                StorageCommitment, convert::FromInterface, generate_proof_input,
+            };
+
+            let initial_storage_commitment = StorageCommitment {
+                root: fixed_bytes_to_bytes32(root_hash).as_u8_array().into(),
+                next_free_slot: leaf_count,
+            };
+
+            let list_source = TxListSource { transactions };
+
+            let bin_path = if enable_logging {
+                zksync_os_multivm::apps::v4::singleblock_batch_logging_enabled_path(
+                    &app_bin_base_path,
                 )
-                .expect("proof gen failed")
-            }
-        };
+            } else {
+                zksync_os_multivm::apps::v4::singleblock_batch_path(&app_bin_base_path)
+            };
+
+            generate_proof_input(
+                bin_path,
+                BlockMetadataFromOracle::from_interface(replay_record.block_context),
+                ProofData {
+                    state_root_view: initial_storage_commitment,
+                    last_block_timestamp: replay_record.previous_block_timestamp,
+                },
+                tree_view,
+                state_view,
+                list_source,
+            )
+            .expect("proof gen failed")
+        }
+    };
     let latency = prover_input_generation_latency.observe();
 
     tracing::info!(
@@ -199,7 +222,7 @@ fn compute_prover_input(
         latency
     );
 
-    prover_input
+    Ok(prover_input)
 }
 
 const LATENCIES_FAST: Buckets = Buckets::exponential(0.001..=30.0, 2.0);
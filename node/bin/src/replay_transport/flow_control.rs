@@ -0,0 +1,288 @@
+//! Resource controls of the block replay server, protecting the sequencer from many syncing external nodes.
+
+use alloy::primitives::BlockNumber;
+use alloy::rlp::Bytes;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::Instant;
+use vise::{Counter, Gauge, LabeledFamily, Metrics};
+use zksync_os_socket::ListenerLimits;
+use zksync_os_status_server::{ReplaySubscriberState, ReplaySubscriberStatus};
+use zksync_os_storage_api::ReplayRecord;
+
+#[derive(Debug, Clone)]
+pub struct ReplayServerLimits {
+    /// Max number of subscribers reading historical records from storage at the same time.
+    pub max_backfill_readers: usize,
+    /// Max records per second sent to a single catching-up subscriber.
+    pub max_records_per_second: Option<u64>,
+    /// Max bytes per second sent to a single catching-up subscriber.
+    pub max_bytes_per_second: Option<u64>,
+    /// Number of encoded records kept in the shared cache.
+    pub cache_size: usize,
+    /// Connection cap and handshake deadline of the listener.
+    pub listener: ListenerLimits,
+    /// Max size of an encoded record; the same limit is enforced by subscribers.
+    pub max_frame_bytes: usize,
+}
+
+/// LRU cache of encoded replay records shared by all subscribers, so that subscribers at similar
+/// positions don't read and encode the same records again.
+#[derive(Debug)]
+pub(super) struct EncodedReplayCache {
+    capacity: usize,
+    /// Encoded records along with the tick of their last access.
+    entries: Mutex<(HashMap<BlockNumber, (Bytes, u64)>, u64)>,
+}
+
+impl EncodedReplayCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new((HashMap::with_capacity(capacity), 0)),
+        }
+    }
+
+    /// Returns the encoded record, calling `load` on a cache miss. The lock is not held while loading.
+    pub fn get_or_load(
+        &self,
+        block_number: BlockNumber,
+        load: impl FnOnce() -> Option<ReplayRecord>,
+    ) -> Option<Bytes> {
+        {
+            let mut guard = self.entries.lock().unwrap();
+            let (entries, tick) = &mut *guard;
+            *tick += 1;
+            if let Some((bytes, last_access)) = entries.get_mut(&block_number) {
+                *last_access = *tick;
+                REPLAY_SERVER_METRICS.cache[&"hit"].inc();
+                return Some(bytes.clone());
+            }
+        }
+        REPLAY_SERVER_METRICS.cache[&"miss"].inc();
+        let bytes = Bytes::from(load()?.encode_with_current_version());
+        if self.capacity == 0 {
+            return Some(bytes);
+        }
+
+        let mut guard = self.entries.lock().unwrap();
+        let (entries, tick) = &mut *guard;
+        if entries.len() >= self.capacity && !entries.contains_key(&block_number) {
+            let lru = entries
+                .iter()
+                .min_by_key(|(_, (_, last_access))| *last_access)
+                .map(|(block_number, _)| *block_number);
+            if let Some(lru) = lru {
+                entries.remove(&lru);
+            }
+        }
+        *tick += 1;
+        entries.insert(block_number, (bytes.clone(), *tick));
+        Some(bytes)
+    }
+}
+
+/// Paces records sent to a single subscriber according to the configured records/bytes per second.
+#[derive(Debug)]
+pub(super) struct SendRateLimiter {
+    max_records_per_second: Option<u64>,
+    max_bytes_per_second: Option<u64>,
+    /// Earliest time the next record may be sent.
+    next_send: Instant,
+}
+
+impl SendRateLimiter {
+    pub fn new(limits: &ReplayServerLimits) -> Self {
+        Self {
+            max_records_per_second: limits.max_records_per_second,
+            max_bytes_per_second: limits.max_bytes_per_second,
+            next_send: Instant::now(),
+        }
+    }
+
+    /// Accounts for a record of `len` bytes and returns how long to wait before sending it.
+    pub fn delay(&mut self, len: usize, now: Instant) -> Duration {
+        let per_record = self.max_records_per_second.map_or(Duration::ZERO, |limit| {
+            Duration::from_secs(1).div_f64(limit.max(1) as f64)
+        });
+        let per_bytes = self.max_bytes_per_second.map_or(Duration::ZERO, |limit| {
+            Duration::from_secs_f64(len as f64 / limit.max(1) as f64)
+        });
+        let send_at = self.next_send.max(now);
+        self.next_send = send_at + per_record.max(per_bytes);
+        send_at - now
+    }
+}
+
+/// Tracks connected subscribers and publishes their status to the status server.
+#[derive(Debug)]
+pub(super) struct SubscriberRegistry {
+    statuses: watch::Sender<Vec<ReplaySubscriberStatus>>,
+    next_id: AtomicU64,
+}
+
+impl SubscriberRegistry {
+    pub fn new(statuses: watch::Sender<Vec<ReplaySubscriberStatus>>) -> Self {
+        Self {
+            statuses,
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    pub fn register(&self, peer: String, position: BlockNumber) -> SubscriberHandle<'_> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.statuses.send_modify(|statuses| {
+            statuses.push(ReplaySubscriberStatus {
+                id,
+                peer,
+                position,
+                lag: 0,
+                state: ReplaySubscriberState::Live,
+                records_sent: 0,
+                bytes_sent: 0,
+            })
+        });
+        self.report_metrics();
+        SubscriberHandle { registry: self, id }
+    }
+
+    fn report_metrics(&self) {
+        let statuses = self.statuses.borrow();
+        for state in [
+            ReplaySubscriberState::Live,
+            ReplaySubscriberState::WaitingForReader,
+            ReplaySubscriberState::Backfilling,
+            ReplaySubscriberState::Throttled,
+        ] {
+            let count = statuses.iter().filter(|s| s.state == state).count();
+            REPLAY_SERVER_METRICS.subscribers[&state_label(state)].set(count);
+        }
+        let max_lag = statuses.iter().map(|s| s.lag).max().unwrap_or(0);
+        REPLAY_SERVER_METRICS.max_subscriber_lag.set(max_lag);
+    }
+}
+
+/// Subscriber entry in [`SubscriberRegistry`]; removed on drop.
+#[derive(Debug)]
+pub(super) struct SubscriberHandle<'a> {
+    registry: &'a SubscriberRegistry,
+    id: u64,
+}
+
+impl SubscriberHandle<'_> {
+    pub fn update(
+        &self,
+        position: BlockNumber,
+        latest: BlockNumber,
+        state: ReplaySubscriberState,
+        sent_bytes: Option<usize>,
+    ) {
+        let mut state_changed = false;
+        self.registry.statuses.send_modify(|statuses| {
+            let Some(status) = statuses.iter_mut().find(|s| s.id == self.id) else {
+                return;
+            };
+            state_changed = status.state != state;
+            status.position = position;
+            status.lag = (latest + 1).saturating_sub(position);
+            status.state = state;
+            if let Some(bytes) = sent_bytes {
+                status.records_sent += 1;
+                status.bytes_sent += bytes as u64;
+            }
+        });
+        if let Some(bytes) = sent_bytes {
+            let path = if state == ReplaySubscriberState::Live {
+                "live"
+            } else {
+                "backfill"
+            };
+            REPLAY_SERVER_METRICS.records_sent[&path].inc();
+            REPLAY_SERVER_METRICS.bytes_sent[&path].inc_by(bytes as u64);
+        }
+        if state_changed {
+            self.registry.report_metrics();
+        }
+    }
+}
+
+impl Drop for SubscriberHandle<'_> {
+    fn drop(&mut self) {
+        self.registry
+            .statuses
+            .send_modify(|statuses| statuses.retain(|s| s.id != self.id));
+        self.registry.report_metrics();
+    }
+}
+
+fn state_label(state: ReplaySubscriberState) -> &'static str {
+    match state {
+        ReplaySubscriberState::Live => "live",
+        ReplaySubscriberState::WaitingForReader => "waiting_for_reader",
+        ReplaySubscriberState::Backfilling => "backfilling",
+        ReplaySubscriberState::Throttled => "throttled",
+    }
+}
+
+#[derive(Debug, Metrics)]
+#[metrics(prefix = "replay_server")]
+pub(super) struct ReplayServerMetrics {
+    /// Connected subscribers by state.
+    #[metrics(labels = ["state"])]
+    pub subscribers: LabeledFamily<&'static str, Gauge<usize>>,
+    /// Lag (in blocks) of the subscriber that is furthest behind.
+    pub max_subscriber_lag: Gauge<u64>,
+    /// Records sent to subscribers, by path (`live` or `backfill`).
+    #[metrics(labels = ["path"])]
+    pub records_sent: LabeledFamily<&'static str, Counter>,
+    #[metrics(labels = ["path"])]
+    pub bytes_sent: LabeledFamily<&'static str, Counter>,
+    /// Lookups of the encoded records cache (`hit` or `miss`).
+    #[metrics(labels = ["kind"])]
+    pub cache: LabeledFamily<&'static str, Counter>,
+}
+
+#[vise::register]
+pub(super) static REPLAY_SERVER_METRICS: vise::Global<ReplayServerMetrics> = vise::Global::new();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(
+        max_records_per_second: Option<u64>,
+        max_bytes_per_second: Option<u64>,
+    ) -> ReplayServerLimits {
+        ReplayServerLimits {
+            max_backfill_readers: 1,
+            max_records_per_second,
+            max_bytes_per_second,
+            cache_size: 0,
+            listener: ListenerLimits::default(),
+            max_frame_bytes: usize::MAX,
+        }
+    }
+
+    #[test]
+    fn rate_limiter_paces_records() {
+        let mut limiter = SendRateLimiter::new(&limits(Some(10), Some(1_000)));
+        let now = Instant::now();
+        assert_eq!(limiter.delay(10, now), Duration::ZERO);
+        assert_eq!(limiter.delay(10, now), Duration::from_millis(100));
+        // The bytes limit is stricter for large records
+        assert_eq!(limiter.delay(500, now), Duration::from_millis(200));
+        assert_eq!(limiter.delay(10, now), Duration::from_millis(700));
+        // Idle time is not accumulated as a burst allowance
+        let later = now + Duration::from_secs(10);
+        assert_eq!(limiter.delay(10, later), Duration::ZERO);
+        assert_eq!(limiter.delay(10, later), Duration::from_millis(100));
+
+        let mut unlimited = SendRateLimiter::new(&limits(None, None));
+        for _ in 0..10 {
+            assert_eq!(unlimited.delay(1_000_000, now), Duration::ZERO);
+        }
+    }
+}
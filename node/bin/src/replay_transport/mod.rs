@@ -0,0 +1,505 @@
+use std::fmt::Display;
+use std::sync::Arc;
+use std::time::Duration;
+
+use alloy::primitives::BlockNumber;
+use alloy::rlp::Bytes;
+use anyhow::Context;
+use futures::{Sink, SinkExt, StreamExt, stream::BoxStream};
+use tokio::io::BufReader;
+use tokio::net::ToSocketAddrs;
+use tokio::sync::{Semaphore, watch};
+use tokio::time::Instant;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tokio_util::codec::{self, FramedRead, FramedWrite};
+use zksync_os_sequencer::model::blocks::BlockCommand;
+use zksync_os_socket::{
+    ConnectOptions, ConnectionLimiter, DEFAULT_MAX_HTTP_HEADER_BYTES, FrameCodec,
+    connect_with_options, read_http_headers,
+};
+use zksync_os_status_server::{ReplaySubscriberState, ReplaySubscriberStatus};
+use zksync_os_storage_api::{REPLAY_WIRE_FORMAT_VERSION, ReadReplay, ReplayRecord};
+
+use self::flow_control::{EncodedReplayCache, SendRateLimiter, SubscriberRegistry};
+
+mod flow_control;
+mod peer_sync;
+
+pub use self::flow_control::ReplayServerLimits;
+pub use self::peer_sync::{
+    PeerFallback, PeerFallbackConfig, PeerSyncServerConfig, run_peer_sync_server,
+};
+
+/// Subscribers at most this many blocks behind the head are streamed without limits.
+const LIVE_LAG_THRESHOLD: u64 = 8;
+/// Max number of historical records read at once while holding a backfill reader permit.
+const BACKFILL_CHUNK_SIZE: u64 = 64;
+/// How often a live subscriber checks for new records.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// Path clients connect to, sent in the handshake.
+const REPLAY_PATH: &str = "/block_replays";
+
+pub async fn replay_server(
+    block_replays: impl ReadReplay + Clone,
+    address: impl ToSocketAddrs,
+    limits: ReplayServerLimits,
+    subscribers: watch::Sender<Vec<ReplaySubscriberStatus>>,
+) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(address).await?;
+    let limiter = ConnectionLimiter::new("block_replays", limits.listener.max_connections);
+    let server = Arc::new(ReplayServer::new(block_replays, limits, subscribers));
+
+    loop {
+        let (socket, peer, permit) = limiter.accept(&listener).await?;
+
+        let server = server.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            server.serve_subscriber(socket, peer.to_string()).await;
+        });
+    }
+}
+
+/// State shared by all replay server connections.
+struct ReplayServer<R> {
+    block_replays: R,
+    limits: ReplayServerLimits,
+    backfill_readers: Semaphore,
+    cache: EncodedReplayCache,
+    subscribers: SubscriberRegistry,
+}
+
+impl<R: ReadReplay> ReplayServer<R> {
+    fn new(
+        block_replays: R,
+        limits: ReplayServerLimits,
+        subscribers: watch::Sender<Vec<ReplaySubscriberStatus>>,
+    ) -> Self {
+        Self {
+            block_replays,
+            backfill_readers: Semaphore::new(limits.max_backfill_readers.max(1)),
+            cache: EncodedReplayCache::new(limits.cache_size),
+            subscribers: SubscriberRegistry::new(subscribers),
+            limits,
+        }
+    }
+
+    /// Performs the handshake with a subscriber and streams replays to it until it disconnects.
+    ///
+    /// The subscriber is disconnected if it doesn't send its headers and start block within
+    /// the handshake timeout.
+    async fn serve_subscriber(&self, mut socket: TcpStream, peer: String) {
+        let (recv, mut send) = socket.split();
+        let mut reader = BufReader::new(recv);
+
+        let handshake_timeout = self.limits.listener.handshake_timeout;
+        let handshake = async {
+            let headers = read_http_headers(&mut reader, DEFAULT_MAX_HTTP_HEADER_BYTES)
+                .await
+                .context("could not read replay handshake")?;
+            if headers.path() != Some(REPLAY_PATH) {
+                anyhow::bail!(
+                    "unexpected path {:?} (user agent {:?})",
+                    headers.path(),
+                    headers.user_agent()
+                );
+            }
+            let starting_block = reader
+                .read_u64()
+                .await
+                .context("could not read start block for replays")?;
+            anyhow::Ok((headers, starting_block))
+        };
+        let (headers, starting_block) =
+            match tokio::time::timeout(handshake_timeout, handshake).await {
+                Ok(Ok(handshake)) => handshake,
+                Ok(Err(e)) => {
+                    tracing::info!("Rejecting replay client {}: {:#}", peer, e);
+                    return;
+                }
+                Err(_) => {
+                    tracing::info!(
+                        "Replay client {} did not complete the handshake within {:?}",
+                        peer,
+                        handshake_timeout
+                    );
+                    return;
+                }
+            };
+
+        if let Err(e) = send.write_u32(REPLAY_WIRE_FORMAT_VERSION).await {
+            tracing::info!("Could not write replay version: {}", e);
+            return;
+        }
+
+        tracing::info!(
+            user_agent = headers.user_agent(),
+            "Streaming replays to {} starting from {}",
+            peer,
+            starting_block
+        );
+
+        let mut replay_sender =
+            FramedWrite::new(send, FrameCodec::new(self.limits.max_frame_bytes));
+        if let Err(e) = self
+            .stream_replays(peer, starting_block, &mut replay_sender)
+            .await
+        {
+            tracing::info!("Failed to send replay: {}", e);
+        }
+    }
+
+    fn encoded_record(&self, block_number: BlockNumber) -> anyhow::Result<Bytes> {
+        self.cache
+            .get_or_load(block_number, || {
+                self.block_replays.get_replay_record(block_number)
+            })
+            .with_context(|| format!("replay record for block {block_number} is missing"))
+    }
+
+    /// Streams encoded replay records starting from `position` to `sink` until the subscriber disconnects.
+    ///
+    /// Subscribers close to the head are served right away. Catching-up subscribers read historical records
+    /// in chunks, at most `max_backfill_readers` at a time, and are paced by the per-connection rate limit,
+    /// so that they cannot starve live subscribers.
+    async fn stream_replays<S>(
+        &self,
+        peer: String,
+        mut position: BlockNumber,
+        sink: &mut S,
+    ) -> anyhow::Result<()>
+    where
+        S: Sink<Bytes> + Unpin,
+        S::Error: std::error::Error + Send + Sync + 'static,
+    {
+        let subscriber = self.subscribers.register(peer, position);
+        let mut rate_limiter = SendRateLimiter::new(&self.limits);
+        loop {
+            let latest = self.block_replays.latest_record();
+            if position > latest {
+                subscriber.update(position, latest, ReplaySubscriberState::Live, None);
+                tokio::time::sleep(POLL_INTERVAL).await;
+                continue;
+            }
+
+            if latest - position < LIVE_LAG_THRESHOLD {
+                let record = self.encoded_record(position)?;
+                let len = record.len();
+                sink.send(record).await?;
+                position += 1;
+                subscriber.update(position, latest, ReplaySubscriberState::Live, Some(len));
+                continue;
+            }
+
+            subscriber.update(
+                position,
+                latest,
+                ReplaySubscriberState::WaitingForReader,
+                None,
+            );
+            let chunk = {
+                let _permit = self.backfill_readers.acquire().await?;
+                subscriber.update(position, latest, ReplaySubscriberState::Backfilling, None);
+                let chunk_end = latest.min(position + BACKFILL_CHUNK_SIZE - 1);
+                (position..=chunk_end)
+                    .map(|block_number| self.encoded_record(block_number))
+                    .collect::<anyhow::Result<Vec<_>>>()?
+            };
+            for record in chunk {
+                let len = record.len();
+                let delay = rate_limiter.delay(len, Instant::now());
+                if !delay.is_zero() {
+                    subscriber.update(position, latest, ReplaySubscriberState::Throttled, None);
+                    tokio::time::sleep(delay).await;
+                }
+                sink.send(record).await?;
+                position += 1;
+                subscriber.update(
+                    position,
+                    latest,
+                    ReplaySubscriberState::Backfilling,
+                    Some(len),
+                );
+            }
+        }
+    }
+}
+
+/// Streams replays from the server at `address` starting from `starting_block`, connecting
+/// according to `connect_options`.
+///
+/// The stream yields an error and should be dropped (closing the connection) if the server sends
+/// a record larger than `max_frame_bytes` or the connection fails.
+pub async fn replay_receiver(
+    starting_block: BlockNumber,
+    address: impl ToSocketAddrs + Display,
+    max_frame_bytes: usize,
+    connect_options: &ConnectOptions,
+) -> anyhow::Result<BoxStream<'static, std::io::Result<BlockCommand>>> {
+    let mut socket = connect_with_options(&address, REPLAY_PATH, connect_options).await?;
+
+    // Instead of negotiating an upgrade, we just drop down to the TCP layer after the headers.
+    socket.write_u64(starting_block).await?;
+    let replay_version = socket.read_u32().await?;
+
+    Ok(FramedRead::new(
+        socket,
+        BlockReplayDecoder::new(replay_version, max_frame_bytes),
+    )
+    .map(|replay| replay.map(|replay| BlockCommand::Replay(Box::new(replay))))
+    .boxed())
+}
+
+struct BlockReplayDecoder {
+    inner: FrameCodec,
+    wire_format_version: u32,
+}
+
+impl BlockReplayDecoder {
+    fn new(wire_format_version: u32, max_frame_bytes: usize) -> Self {
+        Self {
+            inner: FrameCodec::new(max_frame_bytes),
+            wire_format_version,
+        }
+    }
+}
+
+impl codec::Decoder for BlockReplayDecoder {
+    type Item = ReplayRecord;
+    type Error = std::io::Error;
+
+    fn decode(
+        &mut self,
+        src: &mut alloy::rlp::BytesMut,
+    ) -> Result<Option<Self::Item>, Self::Error> {
+        self.inner
+            .decode(src)
+            .map(|inner| inner.map(|bytes| ReplayRecord::decode(&bytes, self.wire_format_version)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::B256;
+    use futures::channel::mpsc;
+    use std::sync::RwLock;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use zksync_os_interface::types::BlockContext;
+    use zksync_os_socket::{FrameTooLarge, ListenerLimits};
+
+    /// In-memory replay storage counting record reads.
+    #[derive(Clone, Default)]
+    struct CountingReplays {
+        records: Arc<RwLock<Vec<ReplayRecord>>>,
+        reads: Arc<AtomicU64>,
+    }
+
+    impl CountingReplays {
+        fn with_records(count: u64) -> Self {
+            let this = Self::default();
+            for _ in 0..count {
+                this.push();
+            }
+            this
+        }
+
+        fn push(&self) {
+            let mut records = self.records.write().unwrap();
+            let block_context = BlockContext {
+                block_number: records.len() as u64,
+                ..Default::default()
+            };
+            records.push(ReplayRecord::new(
+                block_context,
+                0,
+                vec![],
+                0,
+                0,
+                semver::Version::new(0, 1, 0),
+                B256::ZERO,
+            ));
+        }
+
+        fn reads(&self) -> u64 {
+            self.reads.load(Ordering::Relaxed)
+        }
+    }
+
+    impl ReadReplay for CountingReplays {
+        fn get_context(&self, block_number: BlockNumber) -> Option<BlockContext> {
+            self.get_replay_record(block_number)
+                .map(|record| record.block_context)
+        }
+
+        fn get_replay_record(&self, block_number: BlockNumber) -> Option<ReplayRecord> {
+            self.reads.fetch_add(1, Ordering::Relaxed);
+            self.records
+                .read()
+                .unwrap()
+                .get(block_number as usize)
+                .cloned()
+        }
+
+        fn latest_record(&self) -> BlockNumber {
+            self.records.read().unwrap().len() as u64 - 1
+        }
+    }
+
+    fn limits(max_records_per_second: Option<u64>) -> ReplayServerLimits {
+        ReplayServerLimits {
+            max_backfill_readers: 1,
+            max_records_per_second,
+            max_bytes_per_second: None,
+            cache_size: 1_024,
+            listener: ListenerLimits {
+                max_connections: 16,
+                handshake_timeout: Duration::from_millis(200),
+            },
+            max_frame_bytes: 1024 * 1024,
+        }
+    }
+
+    /// Spawns a subscriber streaming from `start`; returns the receiving end of its connection.
+    fn subscribe(
+        server: &Arc<ReplayServer<CountingReplays>>,
+        start: BlockNumber,
+    ) -> mpsc::Receiver<Bytes> {
+        let (mut sender, receiver) = mpsc::channel(8);
+        let server = server.clone();
+        tokio::spawn(async move {
+            server
+                .stream_replays("test".to_owned(), start, &mut sender)
+                .await
+        });
+        receiver
+    }
+
+    fn block_number(bytes: &[u8]) -> BlockNumber {
+        ReplayRecord::decode(bytes, REPLAY_WIRE_FORMAT_VERSION)
+            .block_context
+            .block_number
+    }
+
+    #[tokio::test]
+    async fn catching_up_subscribers_share_storage_reads() {
+        const RECORDS: u64 = 300;
+        const SUBSCRIBERS: usize = 5;
+
+        let replays = CountingReplays::with_records(RECORDS);
+        let (statuses, _) = watch::channel(vec![]);
+        let server = Arc::new(ReplayServer::new(replays.clone(), limits(None), statuses));
+
+        let subscribers = (0..SUBSCRIBERS).map(|_| {
+            let mut receiver = subscribe(&server, 0);
+            async move {
+                for expected in 0..RECORDS {
+                    let bytes = receiver.next().await.unwrap();
+                    assert_eq!(block_number(&bytes), expected);
+                }
+            }
+        });
+        futures::future::join_all(subscribers).await;
+
+        // Each record is read from storage once rather than once per subscriber
+        assert_eq!(replays.reads(), RECORDS);
+    }
+
+    #[tokio::test]
+    async fn live_subscriber_is_not_slowed_down_by_catching_up_ones() {
+        const RECORDS: u64 = 500;
+
+        let replays = CountingReplays::with_records(RECORDS);
+        let (statuses, statuses_receiver) = watch::channel(vec![]);
+        // Catching-up subscribers would need 5 seconds to sync
+        let server = Arc::new(ReplayServer::new(
+            replays.clone(),
+            limits(Some(100)),
+            statuses,
+        ));
+        let slow_subscribers: Vec<_> = (0..4).map(|_| subscribe(&server, 0)).collect();
+        let mut live_subscriber = subscribe(&server, RECORDS);
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        for expected in RECORDS..RECORDS + 3 {
+            replays.push();
+            let pushed_at = Instant::now();
+            let bytes = tokio::time::timeout(Duration::from_secs(1), live_subscriber.next())
+                .await
+                .expect("live subscriber lags")
+                .unwrap();
+            assert_eq!(block_number(&bytes), expected);
+            assert!(pushed_at.elapsed() < POLL_INTERVAL * 4);
+        }
+        // Let the live subscriber publish its latest status
+        tokio::time::sleep(POLL_INTERVAL * 2).await;
+
+        let statuses = statuses_receiver.borrow().clone();
+        assert_eq!(statuses.len(), 5);
+        let live = statuses
+            .iter()
+            .filter(|s| s.state == ReplaySubscriberState::Live)
+            .collect::<Vec<_>>();
+        assert_eq!(live.len(), 1);
+        assert_eq!(live[0].position, RECORDS + 3);
+        for status in statuses
+            .iter()
+            .filter(|s| s.state != ReplaySubscriberState::Live)
+        {
+            assert!(status.lag > RECORDS / 2, "{status:?}");
+        }
+        // Storage reads are bounded by the shared cache despite several catching-up subscribers
+        assert!(replays.reads() <= 2 * BACKFILL_CHUNK_SIZE + 3);
+        drop(slow_subscribers);
+    }
+
+    #[tokio::test]
+    async fn slow_handshake_is_disconnected_at_deadline() {
+        let replays = CountingReplays::with_records(1);
+        let (statuses, _) = watch::channel(vec![]);
+        let server = Arc::new(ReplayServer::new(replays, limits(None), statuses));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (socket, peer) = listener.accept().await.unwrap();
+            server.serve_subscriber(socket, peer.to_string()).await;
+        });
+
+        // The client sends a part of its headers and stalls
+        let mut client = TcpStream::connect(address).await.unwrap();
+        let connected_at = Instant::now();
+        client
+            .write_all(b"POST /block_replays HTTP/1.0\r\nUser-Ag")
+            .await
+            .unwrap();
+        let mut response = vec![];
+        let read = tokio::time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+            .await
+            .expect("slow client is not disconnected");
+        // The connection is closed without the replay version being sent
+        assert!(read.is_err() || response.is_empty());
+        assert!(connected_at.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn oversized_replay_record_is_rejected() {
+        let mut encoded = alloy::rlp::BytesMut::new();
+        let record = CountingReplays::with_records(1)
+            .get_replay_record(0)
+            .unwrap();
+        let record = Bytes::from(record.encode_with_current_version());
+        codec::Encoder::encode(
+            &mut FrameCodec::new(usize::MAX),
+            record.clone(),
+            &mut encoded,
+        )
+        .unwrap();
+
+        let mut decoder = BlockReplayDecoder::new(REPLAY_WIRE_FORMAT_VERSION, record.len() - 1);
+        let err = codec::Decoder::decode(&mut decoder, &mut encoded).unwrap_err();
+        assert!(FrameTooLarge::from_io(&err).is_some(), "{err}");
+    }
+}
@@ -0,0 +1,736 @@
+//! Syncing of block replays between nodes, used by external nodes as a fallback while the main
+//! node's replay stream is unavailable (e.g. during sequencer maintenance).
+//!
+//! Nodes can serve their block replay WAL over HTTP: `GET /sync/replay-records?from=N&limit=K`
+//! responds with a bincode-encoded list of encoded replay records, the wire format version of
+//! which is sent in the `x-replay-wire-format-version` header. Responses are paced by the replay
+//! server rate limits (shared by all requests) and require a bearer token if one is configured.
+//!
+//! Peers are not trusted. A fetched record is only applied once its parent block was executed
+//! locally, and only if it links to it: it must have the next block number, the parent's timestamp
+//! as the previous block timestamp and the parent's block hashes shifted by the locally computed
+//! parent hash. The record itself is then re-executed by the sequencer, which checks its output
+//! hash just like for records received from the main node.
+
+use super::flow_control::{EncodedReplayCache, SendRateLimiter};
+use crate::admin::bearer_token_matches;
+use crate::replay_transport::ReplayServerLimits;
+use alloy::primitives::{B256, BlockNumber, U256};
+use anyhow::Context;
+use axum::Router;
+use axum::extract::{Query, State};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use http::{HeaderMap, StatusCode};
+use serde::Deserialize;
+use smart_config::value::{ExposeSecret, SecretString};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tower_http::timeout::TimeoutLayer;
+use vise::{Counter, Gauge, LabeledFamily, Metrics};
+use zksync_os_sequencer::model::blocks::BlockCommand;
+use zksync_os_storage_api::{REPLAY_WIRE_FORMAT_VERSION, ReadReplay, ReadRepository, ReplayRecord};
+
+const REPLAY_RECORDS_PATH: &str = "/sync/replay-records";
+const WIRE_FORMAT_VERSION_HEADER: &str = "x-replay-wire-format-version";
+/// Max size of a peer response; bounds allocations while decoding untrusted responses.
+const MAX_RESPONSE_BYTES: usize = 1 << 30;
+/// How often the fallback checks whether the parent of the next fetched record was executed.
+const PARENT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone)]
+pub struct PeerSyncServerConfig {
+    pub address: String,
+    /// Bearer token required from peers; `None` means the API is open.
+    pub auth_token: Option<SecretString>,
+    /// Max number of records returned by a single request.
+    pub max_records_per_request: u64,
+    /// Max time to respond to a request, including rate limiting delays.
+    pub request_timeout: Duration,
+}
+
+/// State shared by all peer sync requests.
+struct PeerSyncServer<R> {
+    block_replays: R,
+    auth_token: Option<SecretString>,
+    max_records_per_request: u64,
+    cache: EncodedReplayCache,
+    rate_limiter: Mutex<SendRateLimiter>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReplayRecordsQuery {
+    from: BlockNumber,
+    limit: Option<u64>,
+}
+
+impl<R: ReadReplay> PeerSyncServer<R> {
+    fn new(block_replays: R, config: &PeerSyncServerConfig, limits: &ReplayServerLimits) -> Self {
+        Self {
+            block_replays,
+            auth_token: config.auth_token.clone(),
+            max_records_per_request: config.max_records_per_request.max(1),
+            cache: EncodedReplayCache::new(limits.cache_size),
+            rate_limiter: Mutex::new(SendRateLimiter::new(limits)),
+        }
+    }
+
+    /// Returns up to `limit` encoded records starting from `from`; empty if `from` is after the
+    /// latest record.
+    async fn replay_records(
+        &self,
+        authorization: Option<&str>,
+        query: ReplayRecordsQuery,
+    ) -> Result<Vec<Vec<u8>>, (StatusCode, String)> {
+        if let Some(auth_token) = &self.auth_token
+            && !bearer_token_matches(auth_token, authorization)
+        {
+            return Err((StatusCode::UNAUTHORIZED, "unauthorized".to_owned()));
+        }
+
+        let latest = self.block_replays.latest_record();
+        if query.from > latest {
+            return Ok(vec![]);
+        }
+        let limit = query
+            .limit
+            .unwrap_or(self.max_records_per_request)
+            .clamp(1, self.max_records_per_request);
+        let last = latest.min(query.from.saturating_add(limit - 1));
+        let records = (query.from..=last)
+            .map(|block_number| {
+                self.cache
+                    .get_or_load(block_number, || {
+                        self.block_replays.get_replay_record(block_number)
+                    })
+                    .map(|record| record.to_vec())
+                    .ok_or_else(|| {
+                        let message = format!("replay record for block {block_number} is missing");
+                        (StatusCode::INTERNAL_SERVER_ERROR, message)
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let delay = {
+            let mut rate_limiter = self.rate_limiter.lock().unwrap();
+            let now = Instant::now();
+            records
+                .iter()
+                .map(|record| rate_limiter.delay(record.len(), now))
+                .max()
+                .unwrap_or_default()
+        };
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        PEER_SYNC_METRICS
+            .records_served
+            .inc_by(records.len() as u64);
+        Ok(records)
+    }
+}
+
+async fn replay_records<R: ReadReplay>(
+    State(server): State<Arc<PeerSyncServer<R>>>,
+    headers: HeaderMap,
+    Query(query): Query<ReplayRecordsQuery>,
+) -> Response {
+    let authorization = headers
+        .get(http::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    match server.replay_records(authorization, query).await {
+        Ok(records) => {
+            let body = bincode::encode_to_vec(records, bincode::config::standard())
+                .expect("failed to encode replay records");
+            (
+                [(
+                    WIRE_FORMAT_VERSION_HEADER,
+                    REPLAY_WIRE_FORMAT_VERSION.to_string(),
+                )],
+                body,
+            )
+                .into_response()
+        }
+        Err(err) => err.into_response(),
+    }
+}
+
+/// Serves the block replays of this node to other nodes, see the module docs.
+pub async fn run_peer_sync_server(
+    block_replays: impl ReadReplay,
+    config: PeerSyncServerConfig,
+    limits: ReplayServerLimits,
+) -> anyhow::Result<()> {
+    let bind_address: SocketAddr = config.address.parse()?;
+    tracing::info!("starting peer sync server on {bind_address}");
+    let listener = TcpListener::bind(bind_address).await?;
+    let server = PeerSyncServer::new(block_replays, &config, &limits);
+    serve(listener, server, config.request_timeout).await
+}
+
+async fn serve<R: ReadReplay>(
+    listener: TcpListener,
+    server: PeerSyncServer<R>,
+    request_timeout: Duration,
+) -> anyhow::Result<()> {
+    let app = Router::new()
+        .route(REPLAY_RECORDS_PATH, get(replay_records::<R>))
+        .with_state(Arc::new(server))
+        .layer(TimeoutLayer::new(request_timeout));
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/// Configuration of the external node's fallback to syncing from peers.
+#[derive(Debug, Clone)]
+pub struct PeerFallbackConfig {
+    /// Base URLs of the peer sync APIs of other nodes, e.g. `http://en-1:3055`.
+    pub peer_urls: Vec<String>,
+    /// Bearer token sent to peers.
+    pub auth_token: Option<SecretString>,
+    /// How long the main node's replay stream must be unavailable before syncing from peers.
+    pub fallback_after: Duration,
+    /// Max number of records requested from a peer at once.
+    pub max_records_per_request: u64,
+    /// Max time to fetch records from a peer.
+    pub request_timeout: Duration,
+}
+
+/// Reason for rejecting a replay record fetched from a peer.
+#[derive(Debug, PartialEq, thiserror::Error)]
+pub enum PeerRecordError {
+    #[error("expected block #{expected}, got #{actual}")]
+    UnexpectedBlockNumber {
+        expected: BlockNumber,
+        actual: BlockNumber,
+    },
+    #[error("previous block timestamp {actual} differs from the parent's timestamp {expected}")]
+    PreviousTimestampMismatch { expected: u64, actual: u64 },
+    #[error("block hashes don't link to the locally executed parent (first mismatch at {index})")]
+    BlockHashesMismatch { index: usize },
+}
+
+/// Locally executed block the next fetched record must link to.
+#[derive(Debug)]
+struct LocalParent {
+    record: ReplayRecord,
+    /// Block hash computed by local execution.
+    hash: B256,
+}
+
+impl LocalParent {
+    /// Returns `None` if block `number` is not executed yet.
+    fn read(
+        replay: &impl ReadReplay,
+        repository: &impl ReadRepository,
+        number: BlockNumber,
+    ) -> anyhow::Result<Option<Self>> {
+        if replay.latest_record() < number {
+            return Ok(None);
+        }
+        let Some(block) = repository.get_block_by_number(number)? else {
+            return Ok(None);
+        };
+        let record = replay
+            .get_replay_record(number)
+            .with_context(|| format!("replay record for executed block {number} is missing"))?;
+        Ok(Some(Self {
+            record,
+            hash: block.hash(),
+        }))
+    }
+
+    fn check_child(&self, child: &ReplayRecord) -> Result<(), PeerRecordError> {
+        let parent_context = &self.record.block_context;
+        let expected = parent_context.block_number + 1;
+        let actual = child.block_context.block_number;
+        if actual != expected {
+            return Err(PeerRecordError::UnexpectedBlockNumber { expected, actual });
+        }
+
+        // The first block after genesis is produced without a previous block timestamp
+        let expected = if parent_context.block_number == 0 {
+            0
+        } else {
+            parent_context.timestamp
+        };
+        if child.previous_block_timestamp != expected {
+            return Err(PeerRecordError::PreviousTimestampMismatch {
+                expected,
+                actual: child.previous_block_timestamp,
+            });
+        }
+
+        let expected_hashes = parent_context.block_hashes.0[1..]
+            .iter()
+            .copied()
+            .chain([U256::from_be_bytes(self.hash.0)]);
+        if let Some(index) = expected_hashes
+            .zip(child.block_context.block_hashes.0)
+            .position(|(expected, actual)| expected != actual)
+        {
+            return Err(PeerRecordError::BlockHashesMismatch { index });
+        }
+        Ok(())
+    }
+}
+
+/// Fetches blocks from peers while the main node's replay stream is unavailable.
+pub struct PeerFallback<Replay, Repository> {
+    config: PeerFallbackConfig,
+    client: reqwest::Client,
+    /// Local block replay WAL and repository, used to check fetched records against the
+    /// locally executed chain.
+    replay: Replay,
+    repository: Repository,
+    /// Index of the peer to try first.
+    next_peer: usize,
+}
+
+impl<Replay: ReadReplay, Repository: ReadRepository> PeerFallback<Replay, Repository> {
+    pub fn new(config: PeerFallbackConfig, replay: Replay, repository: Repository) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+            replay,
+            repository,
+            next_peer: 0,
+        }
+    }
+
+    pub fn fallback_after(&self) -> Duration {
+        self.config.fallback_after
+    }
+
+    /// Reports that the main node's replay stream is available again.
+    pub fn on_main_node_back(&self) {
+        PEER_SYNC_METRICS.fallback_active.set(0);
+    }
+
+    /// Fetches records starting from `*next_block` from the first peer that has them, and sends
+    /// records that pass the checks to `output`. Stops at the first rejected record, so that the
+    /// rest is fetched from another peer. Returns the number of sent records.
+    pub async fn sync_from_peers(
+        &mut self,
+        next_block: &mut BlockNumber,
+        output: &mpsc::Sender<BlockCommand>,
+    ) -> anyhow::Result<usize> {
+        PEER_SYNC_METRICS.fallback_active.set(1);
+        for _ in 0..self.config.peer_urls.len() {
+            let peer_url = self.config.peer_urls[self.next_peer].clone();
+            let records = match self.fetch(&peer_url, *next_block).await {
+                Ok(records) if records.is_empty() => {
+                    tracing::debug!(peer_url, next_block, "peer has no new blocks");
+                    self.rotate_peers();
+                    continue;
+                }
+                Ok(records) => records,
+                Err(err) => {
+                    tracing::warn!(peer_url, next_block, "failed to fetch blocks: {err:#}");
+                    self.rotate_peers();
+                    continue;
+                }
+            };
+
+            let mut sent = 0;
+            for record in records {
+                let block_number = record.block_context.block_number;
+                let parent = self.wait_for_parent(*next_block - 1).await?;
+                if let Err(err) = parent.check_child(&record) {
+                    tracing::warn!(
+                        peer_url,
+                        block_number,
+                        "rejecting block fetched from peer: {err}"
+                    );
+                    PEER_SYNC_METRICS.records_fetched[&"rejected"].inc();
+                    // The peer is on another chain or serves tampered records
+                    self.rotate_peers();
+                    break;
+                }
+                tracing::debug!(peer_url, block_number, "applying block fetched from peer");
+                output
+                    .send(BlockCommand::Replay(Box::new(record)))
+                    .await
+                    .context("command output channel closed")?;
+                PEER_SYNC_METRICS.records_fetched[&"applied"].inc();
+                *next_block += 1;
+                sent += 1;
+            }
+            if sent > 0 {
+                return Ok(sent);
+            }
+        }
+        Ok(0)
+    }
+
+    fn rotate_peers(&mut self) {
+        self.next_peer = (self.next_peer + 1) % self.config.peer_urls.len();
+    }
+
+    async fn fetch(&self, peer_url: &str, from: BlockNumber) -> anyhow::Result<Vec<ReplayRecord>> {
+        let url = format!("{}{REPLAY_RECORDS_PATH}", peer_url.trim_end_matches('/'));
+        let mut request = self
+            .client
+            .get(url)
+            .query(&[
+                ("from", from),
+                ("limit", self.config.max_records_per_request),
+            ])
+            .timeout(self.config.request_timeout);
+        if let Some(auth_token) = &self.config.auth_token {
+            request = request.bearer_auth(auth_token.expose_secret());
+        }
+        let response = request.send().await?.error_for_status()?;
+        let version: u32 = response
+            .headers()
+            .get(WIRE_FORMAT_VERSION_HEADER)
+            .context("wire format version is missing")?
+            .to_str()?
+            .parse()
+            .context("invalid wire format version")?;
+        let body = response.bytes().await?;
+        let (records, _): (Vec<Vec<u8>>, _) = bincode::decode_from_slice(
+            &body,
+            bincode::config::standard().with_limit::<MAX_RESPONSE_BYTES>(),
+        )
+        .context("malformed response")?;
+        records
+            .iter()
+            .map(|record| ReplayRecord::try_decode(record, version).map_err(Into::into))
+            .collect()
+    }
+
+    /// Waits until block `number` is executed locally.
+    async fn wait_for_parent(&self, number: BlockNumber) -> anyhow::Result<LocalParent> {
+        loop {
+            if let Some(parent) = LocalParent::read(&self.replay, &self.repository, number)? {
+                return Ok(parent);
+            }
+            tokio::time::sleep(PARENT_POLL_INTERVAL).await;
+        }
+    }
+}
+
+#[derive(Debug, Metrics)]
+#[metrics(prefix = "peer_sync")]
+pub(super) struct PeerSyncMetrics {
+    /// Replay records served to peers.
+    pub records_served: Counter,
+    /// Replay records fetched from peers, by outcome (`applied` or `rejected`).
+    #[metrics(labels = ["outcome"])]
+    pub records_fetched: LabeledFamily<&'static str, Counter>,
+    /// 1 while the external node syncs from peers because the main node is unavailable.
+    pub fallback_active: Gauge<u64>,
+}
+
+#[vise::register]
+pub(super) static PEER_SYNC_METRICS: vise::Global<PeerSyncMetrics> = vise::Global::new();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command_source::ExternalNodeCommandSource;
+    use crate::replay_transport::replay_server;
+    use alloy::consensus::Block;
+    use alloy::primitives::{Address, BlockHash, Sealed, TxHash, TxNonce};
+    use std::sync::RwLock;
+    use tokio::sync::watch;
+    use zksync_os_interface::types::{BlockContext, BlockHashes};
+    use zksync_os_pipeline::{PeekableReceiver, PipelineComponent};
+    use zksync_os_socket::ListenerLimits;
+    use zksync_os_storage_api::{RepositoryBlock, RepositoryResult, StoredTxData, TxMeta};
+    use zksync_os_types::{ZkReceiptEnvelope, ZkTransaction};
+
+    const TOKEN: &str = "0123456789abcdef0123456789abcdef";
+
+    /// Hash of block `number` in test chains.
+    fn block_hash(number: BlockNumber) -> B256 {
+        B256::from(U256::from(number + 1))
+    }
+
+    /// Replay record of block `number` in test chains.
+    fn record(number: BlockNumber) -> ReplayRecord {
+        let block_hashes = BlockHashes(std::array::from_fn(|i| {
+            let depth = (256 - i) as u64;
+            if depth <= number {
+                U256::from_be_bytes(block_hash(number - depth).0)
+            } else {
+                U256::ZERO
+            }
+        }));
+        let block_context = BlockContext {
+            block_number: number,
+            timestamp: 1_000 + number,
+            block_hashes,
+            ..Default::default()
+        };
+        let previous_block_timestamp = if number <= 1 { 0 } else { 999 + number };
+        ReplayRecord::new(
+            block_context,
+            0,
+            vec![],
+            previous_block_timestamp,
+            (1_000 + number) * 1_000,
+            semver::Version::new(0, 1, 0),
+            B256::ZERO,
+        )
+    }
+
+    /// Block replay WAL and repository of an in-process node.
+    #[derive(Debug, Clone, Default)]
+    struct TestNode {
+        records: Arc<RwLock<Vec<ReplayRecord>>>,
+    }
+
+    impl TestNode {
+        fn with_blocks(last: BlockNumber) -> Self {
+            Self {
+                records: Arc::new(RwLock::new((0..=last).map(record).collect())),
+            }
+        }
+
+        fn push(&self, record: ReplayRecord) {
+            self.records.write().unwrap().push(record);
+        }
+    }
+
+    impl ReadReplay for TestNode {
+        fn get_context(&self, block_number: BlockNumber) -> Option<BlockContext> {
+            self.get_replay_record(block_number)
+                .map(|record| record.block_context)
+        }
+
+        fn get_replay_record(&self, block_number: BlockNumber) -> Option<ReplayRecord> {
+            self.records
+                .read()
+                .unwrap()
+                .get(block_number as usize)
+                .cloned()
+        }
+
+        fn latest_record(&self) -> BlockNumber {
+            self.records.read().unwrap().len() as u64 - 1
+        }
+    }
+
+    impl ReadRepository for TestNode {
+        fn get_block_by_number(
+            &self,
+            number: BlockNumber,
+        ) -> RepositoryResult<Option<RepositoryBlock>> {
+            let block = Block::<TxHash>::default();
+            Ok((number <= self.latest_record())
+                .then(|| Sealed::new_unchecked(block, block_hash(number))))
+        }
+
+        fn get_block_by_hash(&self, _: BlockHash) -> RepositoryResult<Option<RepositoryBlock>> {
+            unimplemented!()
+        }
+
+        fn get_raw_transaction(&self, _: TxHash) -> RepositoryResult<Option<Vec<u8>>> {
+            unimplemented!()
+        }
+
+        fn get_transaction(&self, _: TxHash) -> RepositoryResult<Option<ZkTransaction>> {
+            unimplemented!()
+        }
+
+        fn get_transaction_receipt(
+            &self,
+            _: TxHash,
+        ) -> RepositoryResult<Option<ZkReceiptEnvelope>> {
+            unimplemented!()
+        }
+
+        fn get_transaction_meta(&self, _: TxHash) -> RepositoryResult<Option<TxMeta>> {
+            unimplemented!()
+        }
+
+        fn get_transaction_hash_by_sender_nonce(
+            &self,
+            _: Address,
+            _: TxNonce,
+        ) -> RepositoryResult<Option<TxHash>> {
+            unimplemented!()
+        }
+
+        fn get_stored_transaction(&self, _: TxHash) -> RepositoryResult<Option<StoredTxData>> {
+            unimplemented!()
+        }
+
+        fn get_latest_block(&self) -> u64 {
+            self.latest_record()
+        }
+    }
+
+    fn limits() -> ReplayServerLimits {
+        ReplayServerLimits {
+            max_backfill_readers: 1,
+            max_records_per_second: None,
+            max_bytes_per_second: None,
+            cache_size: 16,
+            listener: ListenerLimits::default(),
+            max_frame_bytes: usize::MAX,
+        }
+    }
+
+    /// Serves the blocks of `node` via the peer sync API; returns its URL.
+    async fn spawn_peer(node: TestNode) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let config = PeerSyncServerConfig {
+            address: url.clone(),
+            auth_token: Some(TOKEN.into()),
+            max_records_per_request: 4,
+            request_timeout: Duration::from_secs(10),
+        };
+        let server = PeerSyncServer::new(node, &config, &limits());
+        tokio::spawn(serve(listener, server, config.request_timeout));
+        url
+    }
+
+    fn peer_fallback(peer_urls: Vec<String>, local: &TestNode) -> PeerFallback<TestNode, TestNode> {
+        let config = PeerFallbackConfig {
+            peer_urls,
+            auth_token: Some(TOKEN.into()),
+            fallback_after: Duration::ZERO,
+            max_records_per_request: 4,
+            request_timeout: Duration::from_secs(5),
+        };
+        PeerFallback::new(config, local.clone(), local.clone())
+    }
+
+    /// Address nothing listens on.
+    async fn unused_address() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap()
+    }
+
+    /// Runs an external node command source for `local` whose blocks are applied right away,
+    /// as if executed by the sequencer.
+    fn spawn_external_node(
+        local: &TestNode,
+        main_node_address: SocketAddr,
+        peer_fallback: PeerFallback<TestNode, TestNode>,
+    ) {
+        let source = ExternalNodeCommandSource {
+            starting_block: local.latest_record() + 1,
+            replay_download_address: main_node_address.to_string(),
+            max_frame_bytes: usize::MAX,
+            peer_fallback: Some(peer_fallback),
+        };
+        let (_, input) = mpsc::channel(1);
+        let (output, mut commands) = mpsc::channel(5);
+        tokio::spawn(source.run(PeekableReceiver::new(input), output));
+
+        let local = local.clone();
+        tokio::spawn(async move {
+            while let Some(command) = commands.recv().await {
+                let BlockCommand::Replay(record) = command else {
+                    panic!("external node received a non-replay command");
+                };
+                assert_eq!(record.block_context.block_number, local.latest_record() + 1);
+                local.push(*record);
+            }
+        });
+    }
+
+    async fn wait_for_block(node: &TestNode, number: BlockNumber) {
+        tokio::time::timeout(Duration::from_secs(20), async {
+            while node.latest_record() < number {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("block {number} was not synced"));
+    }
+
+    /// Checks that `node` has the test chain up to block `last`.
+    fn assert_synced(node: &TestNode, last: BlockNumber) {
+        assert_eq!(node.latest_record(), last);
+        for number in 0..=last {
+            let (actual, expected) = (node.get_replay_record(number).unwrap(), record(number));
+            assert_eq!(
+                actual.block_context.timestamp,
+                expected.block_context.timestamp
+            );
+            assert_eq!(
+                actual.block_context.block_hashes.0,
+                expected.block_context.block_hashes.0
+            );
+            assert_eq!(
+                actual.previous_block_timestamp,
+                expected.previous_block_timestamp
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn external_node_syncs_from_peer_and_returns_to_main_node() {
+        let peer_url = spawn_peer(TestNode::with_blocks(10)).await;
+        let main_node_address = unused_address().await;
+        let local = TestNode::with_blocks(0);
+        spawn_external_node(
+            &local,
+            main_node_address,
+            peer_fallback(vec![peer_url], &local),
+        );
+        wait_for_block(&local, 10).await;
+
+        // The peer doesn't have blocks after 10, so they can only come from the main node
+        let main_node = TestNode::with_blocks(15);
+        let (statuses, _) = watch::channel(vec![]);
+        tokio::spawn(replay_server(
+            main_node,
+            main_node_address,
+            limits(),
+            statuses,
+        ));
+        wait_for_block(&local, 15).await;
+        assert_synced(&local, 15);
+    }
+
+    #[tokio::test]
+    async fn tampered_records_are_rejected() {
+        let tampered = TestNode::with_blocks(8);
+        tampered.records.write().unwrap()[5]
+            .block_context
+            .block_hashes
+            .0[255] = U256::from(0xbad);
+        let tampered_url = spawn_peer(tampered).await;
+        let honest_url = spawn_peer(TestNode::with_blocks(8)).await;
+        let rejected_before = PEER_SYNC_METRICS.records_fetched[&"rejected"].get();
+
+        let local = TestNode::with_blocks(0);
+        spawn_external_node(
+            &local,
+            unused_address().await,
+            peer_fallback(vec![tampered_url, honest_url], &local),
+        );
+        wait_for_block(&local, 8).await;
+        assert_synced(&local, 8);
+        assert!(PEER_SYNC_METRICS.records_fetched[&"rejected"].get() > rejected_before);
+    }
+
+    #[tokio::test]
+    async fn peer_sync_api_requires_auth_token() {
+        let peer_url = spawn_peer(TestNode::with_blocks(3)).await;
+        let local = TestNode::with_blocks(0);
+        let mut fallback = peer_fallback(vec![peer_url.clone()], &local);
+        let records = fallback.fetch(&peer_url, 2).await.unwrap();
+        let numbers: Vec<_> = records
+            .iter()
+            .map(|record| record.block_context.block_number)
+            .collect();
+        assert_eq!(numbers, [2, 3]);
+        assert!(fallback.fetch(&peer_url, 4).await.unwrap().is_empty());
+
+        fallback.config.auth_token = Some("fedcba9876543210fedcba9876543210".into());
+        let err = fallback.fetch(&peer_url, 2).await.unwrap_err();
+        assert!(format!("{err:#}").contains("401"), "{err:#}");
+    }
+}
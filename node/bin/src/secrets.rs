@@ -0,0 +1,340 @@
+//! Resolution of secret config values.
+//!
+//! Every secret field accepts one of:
+//!  * a literal value (`0xabcd...`),
+//!  * `env:VAR_NAME` - the value is read from the `VAR_NAME` environment variable,
+//!  * `file:/path/to/secret` - the value is read from the file (surrounding whitespace is trimmed).
+//!
+//! Secrets are resolved and validated right after configs are loaded, so the rest of the node
+//! only sees resolved values. Errors name the offending field and the reference but never
+//! include the secret itself.
+
+use crate::config::{
+    AdminApiConfig, BatchVerificationConfig, L1SenderConfig, RpcConfig, SequencerConfig,
+};
+use alloy::primitives::B256;
+use anyhow::Context;
+use smart_config::value::{ExposeSecret, SecretString};
+use std::path::Path;
+use zksync_os_batch_types::AcceptedSigner;
+
+const ENV_PREFIX: &str = "env:";
+const FILE_PREFIX: &str = "file:";
+
+/// Resolves all secret fields of the configs in place.
+pub fn resolve_secrets(
+    l1_sender_config: &mut L1SenderConfig,
+    batch_verification_config: &mut BatchVerificationConfig,
+    admin_api_config: &mut AdminApiConfig,
+    rpc_config: &mut RpcConfig,
+    sequencer_config: &mut SequencerConfig,
+) -> anyhow::Result<()> {
+    resolve_secrets_with(
+        l1_sender_config,
+        batch_verification_config,
+        admin_api_config,
+        rpc_config,
+        sequencer_config,
+        &|name: &str| std::env::var(name).ok(),
+    )
+}
+
+fn resolve_secrets_with(
+    l1_sender_config: &mut L1SenderConfig,
+    batch_verification_config: &mut BatchVerificationConfig,
+    admin_api_config: &mut AdminApiConfig,
+    rpc_config: &mut RpcConfig,
+    sequencer_config: &mut SequencerConfig,
+    env: &dyn Fn(&str) -> Option<String>,
+) -> anyhow::Result<()> {
+    for (field, value) in [
+        (
+            "l1_sender.operator_commit_pk",
+            &mut l1_sender_config.operator_commit_pk,
+        ),
+        (
+            "l1_sender.operator_prove_pk",
+            &mut l1_sender_config.operator_prove_pk,
+        ),
+        (
+            "l1_sender.operator_execute_pk",
+            &mut l1_sender_config.operator_execute_pk,
+        ),
+        (
+            "batch_verification.signing_key",
+            &mut batch_verification_config.signing_key,
+        ),
+    ] {
+        *value = resolve_secret(field, value, env, validate_private_key)?;
+    }
+
+    batch_verification_config.accepted_signers = resolve_signer_list(
+        "batch_verification.accepted_signers",
+        &batch_verification_config.accepted_signers,
+        env,
+    )?;
+
+    if let Some(auth_token) = &admin_api_config.auth_token {
+        admin_api_config.auth_token = Some(resolve_secret(
+            "admin_api.auth_token",
+            auth_token,
+            env,
+            validate_auth_token,
+        )?);
+    }
+    anyhow::ensure!(
+        !admin_api_config.enabled || admin_api_config.auth_token.is_some(),
+        "`admin_api.auth_token` must be set when the admin API is enabled"
+    );
+    if let Some(auth_token) = &sequencer_config.peer_sync_auth_token {
+        sequencer_config.peer_sync_auth_token = Some(resolve_secret(
+            "sequencer.peer_sync_auth_token",
+            auth_token,
+            env,
+            validate_auth_token,
+        )?);
+    }
+
+    let preconfirmations = &mut rpc_config.preconfirmations;
+    if let Some(signing_key) = &preconfirmations.signing_key {
+        preconfirmations.signing_key = Some(resolve_secret(
+            "rpc.preconfirmations.signing_key",
+            signing_key,
+            env,
+            validate_private_key,
+        )?);
+    }
+    anyhow::ensure!(
+        !preconfirmations.enabled || preconfirmations.signing_key.is_some(),
+        "`rpc.preconfirmations.signing_key` must be set when preconfirmations are enabled"
+    );
+    Ok(())
+}
+
+/// Resolves an auth token set at runtime (see [`crate::config_reload`]).
+pub(crate) fn resolve_auth_token(field: &str, raw: &SecretString) -> anyhow::Result<SecretString> {
+    resolve_secret(
+        field,
+        raw,
+        &|name: &str| std::env::var(name).ok(),
+        validate_auth_token,
+    )
+}
+
+/// Resolves a single secret and validates the resolved value with `validate`.
+fn resolve_secret(
+    field: &str,
+    raw: &SecretString,
+    env: &dyn Fn(&str) -> Option<String>,
+    validate: fn(&str) -> anyhow::Result<()>,
+) -> anyhow::Result<SecretString> {
+    let resolved = resolve_reference(raw.expose_secret(), env)
+        .with_context(|| format!("failed to resolve `{field}`"))?;
+    validate(&resolved).with_context(|| format!("invalid value of `{field}`"))?;
+    Ok(resolved.into())
+}
+
+/// Accepted signers may be given either as a literal list or as a single reference to
+/// a comma- or newline-separated list. Entries are addresses, optionally prefixed with `contract:`.
+fn resolve_signer_list(
+    field: &str,
+    raw: &[String],
+    env: &dyn Fn(&str) -> Option<String>,
+) -> anyhow::Result<Vec<String>> {
+    let entries = match raw {
+        [single] if is_reference(single) => resolve_reference(single, env)
+            .with_context(|| format!("failed to resolve `{field}`"))?
+            .split([',', '\n'])
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(str::to_owned)
+            .collect(),
+        _ => raw.to_vec(),
+    };
+    for (i, entry) in entries.iter().enumerate() {
+        entry
+            .parse::<AcceptedSigner>()
+            .with_context(|| format!("invalid value of `{field}`: entry #{i} is not an address"))?;
+    }
+    Ok(entries)
+}
+
+fn is_reference(raw: &str) -> bool {
+    raw.starts_with(ENV_PREFIX) || raw.starts_with(FILE_PREFIX)
+}
+
+fn resolve_reference(raw: &str, env: &dyn Fn(&str) -> Option<String>) -> anyhow::Result<String> {
+    if let Some(name) = raw.strip_prefix(ENV_PREFIX) {
+        let value =
+            env(name).with_context(|| format!("environment variable `{name}` is not set"))?;
+        anyhow::ensure!(!value.is_empty(), "environment variable `{name}` is empty");
+        Ok(value)
+    } else if let Some(path) = raw.strip_prefix(FILE_PREFIX) {
+        let path = Path::new(path);
+        let value = std::fs::read_to_string(path)
+            .with_context(|| format!("cannot read secret file `{}`", path.display()))?;
+        let value = value.trim();
+        anyhow::ensure!(
+            !value.is_empty(),
+            "secret file `{}` is empty",
+            path.display()
+        );
+        Ok(value.to_owned())
+    } else {
+        Ok(raw.to_owned())
+    }
+}
+
+/// Checks that the value is a 32-byte hex private key. Never echoes the value back.
+fn validate_private_key(value: &str) -> anyhow::Result<()> {
+    let key: B256 = value
+        .parse()
+        .map_err(|_| anyhow::anyhow!("expected a 32-byte hex private key"))?;
+    anyhow::ensure!(!key.is_zero(), "private key must be non-zero");
+    Ok(())
+}
+
+/// Bearer tokens must be long enough not to be guessable.
+fn validate_auth_token(value: &str) -> anyhow::Result<()> {
+    const MIN_LEN: usize = 32;
+    anyhow::ensure!(
+        value.len() >= MIN_LEN,
+        "auth token must be at least {MIN_LEN} characters long"
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const KEY: &str = "0x5ec1b0bfb8c4f1e4a8d6c0b9e1f2a3b4c5d6e7f8091a2b3c4d5e6f708192a3b4";
+    const OTHER_KEY: &str = "0x1e7e1b0bfb8c4f1e4a8d6c0b9e1f2a3b4c5d6e7f8091a2b3c4d5e6f708192a3b";
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name: &str| vars.get(name).cloned()
+    }
+
+    fn resolve(raw: &str, env: &dyn Fn(&str) -> Option<String>) -> anyhow::Result<SecretString> {
+        resolve_secret("test.key", &raw.into(), env, validate_private_key)
+    }
+
+    #[test]
+    fn literal() {
+        let resolved = resolve(KEY, &env(&[])).unwrap();
+        assert_eq!(resolved.expose_secret(), KEY);
+    }
+
+    #[test]
+    fn env_reference() {
+        let resolved = resolve("env:OPERATOR_KEY", &env(&[("OPERATOR_KEY", KEY)])).unwrap();
+        assert_eq!(resolved.expose_secret(), KEY);
+
+        let err = resolve("env:MISSING_KEY", &env(&[])).unwrap_err();
+        let message = format!("{err:#}");
+        assert!(message.contains("test.key"), "{message}");
+        assert!(message.contains("MISSING_KEY"), "{message}");
+    }
+
+    #[test]
+    fn file_reference() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key");
+        std::fs::write(&path, format!("{KEY}\n")).unwrap();
+
+        let resolved = resolve(&format!("file:{}", path.display()), &env(&[])).unwrap();
+        assert_eq!(resolved.expose_secret(), KEY);
+
+        let missing = dir.path().join("missing");
+        let err = resolve(&format!("file:{}", missing.display()), &env(&[])).unwrap_err();
+        let message = format!("{err:#}");
+        assert!(message.contains("test.key"), "{message}");
+        assert!(message.contains("missing"), "{message}");
+    }
+
+    #[test]
+    fn invalid_key_is_not_echoed() {
+        let invalid = "0xdeadbeef";
+        for raw in [invalid, "env:SHORT_KEY"] {
+            let err = resolve(raw, &env(&[("SHORT_KEY", invalid)])).unwrap_err();
+            let message = format!("{err:#} {err:?}");
+            assert!(message.contains("test.key"), "{message}");
+            assert!(message.contains("32-byte hex"), "{message}");
+            assert!(!message.contains("deadbeef"), "{message}");
+        }
+    }
+
+    #[test]
+    fn accepted_signers() {
+        let first = "0x36615Cf349d7F6344891B1e7CA7C72883F5dc049";
+        let second = "contract:0xa61464658AfeAf65CccaaFD3a512b69A83B77618";
+        let signers = format!("{first},\n{second}\n");
+        let env = env(&[("SIGNERS", signers.as_str()), ("BAD_SIGNERS", "0x123")]);
+
+        let resolved = resolve_signer_list("test.signers", &["env:SIGNERS".into()], &env).unwrap();
+        assert_eq!(resolved, [first, second]);
+        let literal = resolve_signer_list("test.signers", &[first.into()], &env).unwrap();
+        assert_eq!(literal, [first]);
+        let err =
+            resolve_signer_list("test.signers", &["env:BAD_SIGNERS".into()], &env).unwrap_err();
+        assert!(format!("{err:#}").contains("test.signers"));
+    }
+
+    #[test]
+    fn resolved_configs_do_not_expose_secrets() {
+        let mut l1_sender_config = L1SenderConfig {
+            operator_commit_pk: "env:COMMIT_KEY".into(),
+            operator_prove_pk: OTHER_KEY.into(),
+            ..Default::default()
+        };
+        let mut batch_verification_config = BatchVerificationConfig::default();
+        let mut admin_api_config = AdminApiConfig {
+            auth_token: Some("env:ADMIN_TOKEN".into()),
+            ..Default::default()
+        };
+        let mut rpc_config = RpcConfig::default();
+        let mut sequencer_config = SequencerConfig {
+            peer_sync_auth_token: Some("env:PEER_SYNC_TOKEN".into()),
+            ..Default::default()
+        };
+        rpc_config.preconfirmations.enabled = true;
+        rpc_config.preconfirmations.signing_key = Some("env:PRECONFIRMATION_KEY".into());
+        resolve_secrets_with(
+            &mut l1_sender_config,
+            &mut batch_verification_config,
+            &mut admin_api_config,
+            &mut rpc_config,
+            &mut sequencer_config,
+            &env(&[
+                ("COMMIT_KEY", KEY),
+                ("ADMIN_TOKEN", &KEY[2..]),
+                ("PRECONFIRMATION_KEY", OTHER_KEY),
+                ("PEER_SYNC_TOKEN", &OTHER_KEY[2..]),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(l1_sender_config.operator_commit_pk.expose_secret(), KEY);
+        let preconfirmation_key = rpc_config.preconfirmations.signing_key.as_ref().unwrap();
+        assert_eq!(preconfirmation_key.expose_secret(), OTHER_KEY);
+
+        let peer_sync_token = sequencer_config.peer_sync_auth_token.as_ref().unwrap();
+        assert_eq!(peer_sync_token.expose_secret(), &OTHER_KEY[2..]);
+
+        let debug = format!(
+            "{l1_sender_config:?} {batch_verification_config:?} {admin_api_config:?} {rpc_config:?} \
+             {sequencer_config:?}"
+        );
+        for secret in [
+            KEY,
+            OTHER_KEY,
+            batch_verification_config.signing_key.expose_secret(),
+        ] {
+            assert!(!debug.contains(&secret[2..]), "{debug}");
+        }
+    }
+}
@@ -2,13 +2,26 @@ use crate::config::GeneralConfig;
 use async_trait::async_trait;
 use std::future;
 use zksync_os_genesis::Genesis;
+use zksync_os_rocksdb::migrations::VersionedDb;
 use zksync_os_state::StateHandle;
 use zksync_os_state_full_diffs::FullDiffsState;
+use zksync_os_storage_api::ReadStateHistory;
 
 #[async_trait]
 pub trait StateInitializer: Sized {
+    /// Name of the state storage database (directory in `rocks_db_path`).
+    const STORAGE_DB_NAME: &'static str;
+    /// Name of the preimages database (directory in `rocks_db_path`).
+    const PREIMAGES_DB_NAME: &'static str;
+
     async fn new(config: &GeneralConfig, genesis: &Genesis) -> Self;
 
+    /// State databases with versioned schemas, keyed by their names.
+    fn versioned_dbs() -> Vec<(&'static str, &'static dyn VersionedDb)>;
+
+    /// Latest block persisted to the state storage database.
+    fn persisted_block(&self) -> u64;
+
     // default no-op
     async fn compact_periodically_optional(&self) {
         future::pending::<()>().await;
@@ -17,6 +30,9 @@ pub trait StateInitializer: Sized {
 
 #[async_trait]
 impl StateInitializer for StateHandle {
+    const STORAGE_DB_NAME: &'static str = zksync_os_state::STATE_STORAGE_DB_NAME;
+    const PREIMAGES_DB_NAME: &'static str = zksync_os_state::PREIMAGES_STORAGE_DB_NAME;
+
     async fn new(config: &GeneralConfig, genesis: &Genesis) -> Self {
         StateHandle::new(
             config.rocks_db_path.clone(),
@@ -24,6 +40,18 @@ impl StateInitializer for StateHandle {
             genesis,
         )
         .await
+        .expect("Failed to initialize state")
+    }
+
+    fn versioned_dbs() -> Vec<(&'static str, &'static dyn VersionedDb)> {
+        vec![
+            (Self::STORAGE_DB_NAME, &zksync_os_state::STORAGE_MAP_SCHEMA),
+            (Self::PREIMAGES_DB_NAME, &zksync_os_state::PREIMAGES_SCHEMA),
+        ]
+    }
+
+    fn persisted_block(&self) -> u64 {
+        self.compacted_block_number()
     }
 
     async fn compact_periodically_optional(&self) {
@@ -33,9 +61,25 @@ impl StateInitializer for StateHandle {
 
 #[async_trait]
 impl StateInitializer for FullDiffsState {
+    const STORAGE_DB_NAME: &'static str = zksync_os_state_full_diffs::STATE_STORAGE_DB_NAME;
+    const PREIMAGES_DB_NAME: &'static str = zksync_os_state_full_diffs::PREIMAGES_STORAGE_DB_NAME;
+
     async fn new(config: &GeneralConfig, genesis: &Genesis) -> Self {
         FullDiffsState::new(config.rocks_db_path.clone(), genesis)
             .await
             .expect("Failed to initialize full diffs state")
     }
+
+    fn versioned_dbs() -> Vec<(&'static str, &'static dyn VersionedDb)> {
+        // The preimages DB has no meta column family to store the schema version in
+        vec![(
+            Self::STORAGE_DB_NAME,
+            &zksync_os_state_full_diffs::STORAGE_SCHEMA,
+        )]
+    }
+
+    fn persisted_block(&self) -> u64 {
+        // Full diffs state persists every block right away
+        *self.block_range_available().end()
+    }
 }
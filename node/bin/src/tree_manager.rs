@@ -1,8 +1,10 @@
 use alloy::primitives::BlockNumber;
+use anyhow::Context;
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use std::ops::Div;
-use std::path::Path;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::sync::mpsc;
 use tokio::time::Instant;
 use vise::{Buckets, Gauge, Histogram, Metrics, Unit};
@@ -10,23 +12,107 @@ use zksync_os_batch_types::BlockMerkleTreeData;
 use zksync_os_genesis::Genesis;
 use zksync_os_interface::types::BlockOutput;
 use zksync_os_merkle_tree::{
-    MerkleTree, MerkleTreeColumnFamily, MerkleTreeVersion, RocksDBWrapper, TreeEntry,
+    DeserializeError, MerkleTree, MerkleTreeColumnFamily, MerkleTreeVersion, RocksDBWrapper,
+    TreeEntry,
 };
+use zksync_os_observability::exemplars::{ExemplarLabels, HistogramExemplars};
 use zksync_os_observability::{ComponentStateReporter, GenericComponentState};
 use zksync_os_pipeline::{PeekableReceiver, PipelineComponent};
-use zksync_os_rocksdb::{RocksDB, RocksDBOptions, StalledWritesRetries};
+use zksync_os_rocksdb::{RocksDB, RocksDBOptions, StalledWritesRetries, rocksdb};
+use zksync_os_storage_api::BlockStats;
+
+/// How often rebuild progress is logged and checkpointed.
+const REBUILD_PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
 
 #[derive(Debug)]
 pub(crate) struct TreeManager {
     pub tree: MerkleTree<RocksDBWrapper>,
+    /// Set while the tree catches up after being rebuilt from genesis.
+    pub rebuild: Option<TreeRebuild>,
+}
+
+/// Persisted progress of a tree rebuild.
+///
+/// The tree persists every processed block, so a rebuild interrupted by a restart resumes from the
+/// latest tree version on its own (blocks are replayed starting from `tree_last_block + 1`).
+/// The checkpoint only keeps the rebuild target and counters across restarts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct TreeRebuildCheckpoint {
+    /// Block replay storage head at the moment corruption was detected.
+    pub target_block: u64,
+    pub keys_processed: u64,
+}
+
+#[derive(Debug)]
+pub(crate) struct TreeRebuild {
+    checkpoint_path: PathBuf,
+    checkpoint: TreeRebuildCheckpoint,
+    // == progress reporting (current process only) ==
+    started_at: Instant,
+    first_block: Option<u64>,
+    last_reported_at: Instant,
+}
+
+impl TreeRebuild {
+    fn new(checkpoint_path: PathBuf, checkpoint: TreeRebuildCheckpoint) -> Self {
+        Self {
+            checkpoint_path,
+            checkpoint,
+            started_at: Instant::now(),
+            first_block: None,
+            last_reported_at: Instant::now(),
+        }
+    }
+
+    /// Records a processed block. Returns `true` once the rebuild target is reached.
+    fn on_block(&mut self, block_number: u64, keys: usize) -> anyhow::Result<bool> {
+        self.checkpoint.keys_processed += keys as u64;
+        let first_block = *self.first_block.get_or_insert(block_number);
+        let target_block = self.checkpoint.target_block;
+        TREE_METRICS
+            .rebuild_remaining_blocks
+            .set(target_block.saturating_sub(block_number));
+
+        if block_number >= target_block {
+            tracing::info!(
+                target_block,
+                keys_processed = self.checkpoint.keys_processed,
+                elapsed = ?self.started_at.elapsed(),
+                "Tree rebuild completed"
+            );
+            std::fs::remove_file(&self.checkpoint_path)
+                .context("failed removing tree rebuild checkpoint")?;
+            return Ok(true);
+        }
+
+        if self.last_reported_at.elapsed() >= REBUILD_PROGRESS_INTERVAL {
+            self.last_reported_at = Instant::now();
+            let blocks_done = block_number - first_block + 1;
+            let remaining = target_block - block_number;
+            let eta = self
+                .started_at
+                .elapsed()
+                .mul_f64(remaining as f64 / blocks_done as f64);
+            tracing::info!(
+                block_number,
+                target_block,
+                keys_processed = self.checkpoint.keys_processed,
+                ?eta,
+                "Tree rebuild in progress"
+            );
+            write_checkpoint(&self.checkpoint_path, &self.checkpoint)?;
+        }
+        Ok(false)
+    }
 }
 
 #[async_trait]
 impl PipelineComponent for TreeManager {
-    type Input = (BlockOutput, zksync_os_storage_api::ReplayRecord);
+    type Input = (BlockOutput, zksync_os_storage_api::ReplayRecord, BlockStats);
     type Output = (
         BlockOutput,
         zksync_os_storage_api::ReplayRecord,
+        BlockStats,
         BlockMerkleTreeData,
     );
     const NAME: &'static str = "merkle_tree";
@@ -38,6 +124,7 @@ impl PipelineComponent for TreeManager {
         output: mpsc::Sender<Self::Output>,
     ) -> anyhow::Result<()> {
         let tree = self.tree;
+        let mut rebuild = self.rebuild;
 
         // only used to skip blocks that were already processed by the tree -
         // will be removed once idempotency is handled on the framework level
@@ -48,7 +135,7 @@ impl PipelineComponent for TreeManager {
         loop {
             latency_tracker.enter_state(GenericComponentState::WaitingRecv);
 
-            let Some((block_output, replay_record)) = input.recv().await else {
+            let Some((block_output, replay_record, stats)) = input.recv().await else {
                 anyhow::bail!("inbound channel closed");
             };
             latency_tracker.enter_state(GenericComponentState::Processing);
@@ -99,10 +186,20 @@ impl PipelineComponent for TreeManager {
                 .entry_time
                 .observe(started_at.elapsed().div(count.max(1) as u32));
             TREE_METRICS.unique_leafs.set(tree_batch_output.leaf_count);
-            TREE_METRICS.block_time.observe(started_at.elapsed());
+            TREE_BLOCK_TIME_EXEMPLARS.observe(
+                &TREE_METRICS.block_time,
+                &[],
+                started_at.elapsed(),
+                ExemplarLabels::block(block_number),
+            );
 
             TREE_METRICS.processing_range.observe(count.max(1) as u64);
             TREE_METRICS.block_number.set(block_number);
+            if let Some(in_progress) = &mut rebuild
+                && in_progress.on_block(block_number, count)?
+            {
+                rebuild = None;
+            }
             let tree_block = BlockMerkleTreeData {
                 block_start: MerkleTreeVersion {
                     tree: tree.clone(),
@@ -115,54 +212,198 @@ impl PipelineComponent for TreeManager {
             };
             latency_tracker.enter_state(GenericComponentState::WaitingSend);
             output
-                .send((block_output, replay_record, tree_block))
+                .send((block_output, replay_record, stats, tree_block))
                 .await?;
         }
     }
 }
 
 impl TreeManager {
+    /// Creates the pipeline component. Picks up an in-progress rebuild (if any) for progress reporting.
+    pub fn new(tree: MerkleTree<RocksDBWrapper>, path: &Path) -> anyhow::Result<Self> {
+        let checkpoint_path = rebuild_checkpoint_path(path);
+        let rebuild = match std::fs::read(&checkpoint_path) {
+            Ok(bytes) => {
+                let checkpoint: TreeRebuildCheckpoint = serde_json::from_slice(&bytes)
+                    .context("failed parsing tree rebuild checkpoint")?;
+                tracing::info!(
+                    target_block = checkpoint.target_block,
+                    keys_processed = checkpoint.keys_processed,
+                    "Resuming tree rebuild"
+                );
+                Some(TreeRebuild::new(checkpoint_path, checkpoint))
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err).context("failed reading tree rebuild checkpoint"),
+        };
+        Ok(Self { tree, rebuild })
+    }
+
+    /// Opens the tree at `path`, initializing it with genesis if empty.
+    ///
+    /// If the tree database is corrupted or inconsistent with the block replay storage (whose
+    /// head is `replay_head`) and `rebuild_on_corruption` is set, the damaged database is moved
+    /// aside and a fresh tree is initialized from genesis. Other errors (e.g., I/O errors or
+    /// a held database lock) are returned as is. Blocks are then replayed from block 1 on startup,
+    /// which restores the tree; the resulting state commitments are verified against the stored
+    /// batches by the batcher.
     pub async fn load_or_initialize_tree(
         path: &Path,
         genesis: &Genesis,
+        replay_head: u64,
+        rebuild_on_corruption: bool,
     ) -> MerkleTree<RocksDBWrapper> {
-        let db: RocksDB<MerkleTreeColumnFamily> = RocksDB::with_options(
-            path,
-            RocksDBOptions {
-                block_cache_capacity: Some(128 << 20),
-                include_indices_and_filters_in_block_cache: false,
-                large_memtable_capacity: Some(256 << 20),
-                stalled_writes_retries: StalledWritesRetries::new(Duration::from_secs(10)),
-                max_open_files: None,
-            },
-        )
-        .unwrap();
+        let genesis_entries = genesis
+            .state()
+            .await
+            .expect("cannot build genesis state on startup")
+            .storage_logs
+            .iter()
+            .map(|(key, value)| TreeEntry {
+                key: *key,
+                value: *value,
+            })
+            .collect::<Vec<_>>();
+        Self::open_or_rebuild(path, &genesis_entries, replay_head, rebuild_on_corruption)
+            .expect("cannot initialize tree on startup")
+    }
 
-        let tree_wrapper = RocksDBWrapper::from(db);
-        let mut tree = MerkleTree::new(tree_wrapper).unwrap();
+    fn open_or_rebuild(
+        path: &Path,
+        genesis_entries: &[TreeEntry],
+        replay_head: u64,
+        rebuild_on_corruption: bool,
+    ) -> anyhow::Result<MerkleTree<RocksDBWrapper>> {
+        let tree = open_tree(path).and_then(|tree| {
+            check_tree(&tree, replay_head)?;
+            Ok(tree)
+        });
+        let mut tree = match tree {
+            Ok(tree) => tree,
+            // E.g., the database lock is held by another process; rebuilding wouldn't help
+            Err(err) if !is_corruption(&err) => {
+                return Err(err.context("failed opening tree database"));
+            }
+            Err(err) if rebuild_on_corruption => {
+                tracing::error!(?err, ?path, "Tree database is corrupted; rebuilding it");
+                quarantine(path)?;
+                open_tree(path)?
+            }
+            Err(err) => {
+                return Err(err.context(
+                    "tree database is corrupted; enable `tree_rebuild_on_corruption` to rebuild it",
+                ));
+            }
+        };
 
-        let version = tree
-            .latest_version()
-            .expect("cannot access tree on startup");
+        let version = tree.latest_version()?;
         if version.is_none() {
-            let tree_entries = genesis
-                .state()
-                .await
-                .storage_logs
-                .iter()
-                .map(|(key, value)| TreeEntry {
-                    key: *key,
-                    value: *value,
-                })
-                .collect::<Vec<_>>();
-            tree.extend(&tree_entries).unwrap();
+            tree.extend(genesis_entries)
+                .context("failed initializing tree with genesis")?;
+            if replay_head > 0 {
+                // The tree was missing or rebuilt - all blocks will be replayed to catch up.
+                tracing::warn!(
+                    target_block = replay_head,
+                    "Tree is initialized from genesis, it will be rebuilt by replaying all blocks"
+                );
+                write_checkpoint(
+                    &rebuild_checkpoint_path(path),
+                    &TreeRebuildCheckpoint {
+                        target_block: replay_head,
+                        keys_processed: 0,
+                    },
+                )?;
+            }
         }
 
         tracing::info!("Loaded tree with last processed block at {:?}", version);
-        tree
+        Ok(tree)
     }
 }
 
+pub(crate) fn open_tree(path: &Path) -> anyhow::Result<MerkleTree<RocksDBWrapper>> {
+    let db: RocksDB<MerkleTreeColumnFamily> = RocksDB::with_options(
+        path,
+        RocksDBOptions {
+            block_cache_capacity: Some(128 << 20),
+            include_indices_and_filters_in_block_cache: false,
+            large_memtable_capacity: Some(256 << 20),
+            stalled_writes_retries: StalledWritesRetries::new(Duration::from_secs(10)),
+            max_open_files: None,
+            ..RocksDBOptions::default()
+        },
+    )
+    .context("failed opening tree RocksDB")?;
+
+    let tree_wrapper = RocksDBWrapper::from(db);
+    MerkleTree::new(tree_wrapper).context("failed loading tree")
+}
+
+/// Checks that the tree manifest is readable, the latest version has a root
+/// and the tree is not ahead of the block replay storage.
+fn check_tree(tree: &MerkleTree<RocksDBWrapper>, replay_head: u64) -> anyhow::Result<()> {
+    let Some(version) = tree
+        .latest_version()
+        .context("failed reading tree manifest")?
+    else {
+        return Ok(());
+    };
+    let root = tree
+        .root_hash(version)
+        .context("failed reading tree root")?;
+    if root.is_none() {
+        let message = format!("missing root for latest tree version {version}");
+        return Err(TreeCorruption(message).into());
+    }
+    if version > replay_head {
+        let message = format!("tree is ahead of block replay storage: {version} > {replay_head}");
+        return Err(TreeCorruption(message).into());
+    }
+    Ok(())
+}
+
+/// Inconsistency of the tree database found by [`check_tree()`].
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+struct TreeCorruption(String);
+
+/// Checks whether `err` positively indicates that the tree database is corrupted or inconsistent,
+/// as opposed to it failing to be accessed (e.g., because of I/O errors or a held lock).
+fn is_corruption(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        cause.is::<TreeCorruption>()
+            || cause.is::<DeserializeError>()
+            || cause
+                .downcast_ref::<rocksdb::Error>()
+                .is_some_and(|err| err.kind() == rocksdb::ErrorKind::Corruption)
+    })
+}
+
+/// Moves a corrupted tree database aside, so that it can be inspected later.
+fn quarantine(path: &Path) -> anyhow::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Incorrect system time")
+        .as_secs();
+    let mut file_name = path.file_name().context("invalid tree path")?.to_owned();
+    file_name.push(format!("_corrupted_{seconds}"));
+    let destination = path.with_file_name(file_name);
+    tracing::warn!(?destination, "Moving corrupted tree database");
+    std::fs::rename(path, &destination).context("failed moving corrupted tree database")
+}
+
+fn rebuild_checkpoint_path(path: &Path) -> PathBuf {
+    path.with_extension("rebuild_checkpoint.json")
+}
+
+fn write_checkpoint(path: &Path, checkpoint: &TreeRebuildCheckpoint) -> anyhow::Result<()> {
+    let bytes = serde_json::to_vec(checkpoint).context("failed serializing checkpoint")?;
+    std::fs::write(path, bytes).context("failed writing tree rebuild checkpoint")
+}
+
 const LATENCIES_FAST: Buckets = Buckets::exponential(0.0000001..=1.0, 2.0);
 const BLOCK_RANGE_SIZE: Buckets = Buckets::exponential(1.0..=1000.0, 2.0);
 
@@ -179,7 +420,123 @@ pub struct TreeMetrics {
     #[metrics(buckets = BLOCK_RANGE_SIZE)]
     pub processing_range: Histogram<u64>,
     pub block_number: Gauge<BlockNumber>,
+    /// Number of blocks left until an in-progress tree rebuild catches up.
+    pub rebuild_remaining_blocks: Gauge<u64>,
 }
 
 #[vise::register]
 pub(crate) static TREE_METRICS: vise::Global<TreeMetrics> = vise::Global::new();
+
+static TREE_BLOCK_TIME_EXEMPLARS: HistogramExemplars =
+    HistogramExemplars::new("tree_block_time_seconds");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::B256;
+
+    fn entries(block: u8) -> Vec<TreeEntry> {
+        (1..=3)
+            .map(|i| TreeEntry {
+                key: B256::repeat_byte(block * 16 + i),
+                value: B256::repeat_byte(block),
+            })
+            .collect()
+    }
+
+    /// Applies `blocks` to the tree and returns the resulting root hash.
+    fn apply_blocks(
+        tree: &mut MerkleTree<RocksDBWrapper>,
+        blocks: std::ops::RangeInclusive<u8>,
+    ) -> B256 {
+        for block in blocks {
+            tree.extend(&entries(block)).unwrap();
+        }
+        tree.latest_root_hash().unwrap().unwrap()
+    }
+
+    #[test]
+    fn corrupted_tree_is_rebuilt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tree");
+        let genesis = entries(0);
+
+        let mut tree = TreeManager::open_or_rebuild(&path, &genesis, 0, true).unwrap();
+        let expected_root = apply_blocks(&mut tree, 1..=2);
+        drop(tree);
+
+        // Corrupt the database.
+        std::fs::write(path.join("CURRENT"), b"garbage").unwrap();
+        assert!(TreeManager::open_or_rebuild(&path, &genesis, 2, false).is_err());
+
+        let mut tree = TreeManager::open_or_rebuild(&path, &genesis, 2, true).unwrap();
+        assert_eq!(tree.latest_version().unwrap(), Some(0));
+        let checkpoint: TreeRebuildCheckpoint =
+            serde_json::from_slice(&std::fs::read(rebuild_checkpoint_path(&path)).unwrap())
+                .unwrap();
+        assert_eq!(checkpoint.target_block, 2);
+
+        // Replaying the blocks restores the same root, and the tree keeps processing new blocks.
+        assert_eq!(apply_blocks(&mut tree, 1..=2), expected_root);
+        apply_blocks(&mut tree, 3..=3);
+        assert_eq!(tree.latest_version().unwrap(), Some(3));
+    }
+
+    #[test]
+    fn tree_ahead_of_replay_storage_is_rebuilt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tree");
+        let genesis = entries(0);
+
+        let mut tree = TreeManager::open_or_rebuild(&path, &genesis, 0, true).unwrap();
+        apply_blocks(&mut tree, 1..=3);
+        drop(tree);
+
+        let tree = TreeManager::open_or_rebuild(&path, &genesis, 1, true).unwrap();
+        assert_eq!(tree.latest_version().unwrap(), Some(0));
+    }
+
+    #[test]
+    fn locked_tree_is_not_rebuilt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tree");
+        let genesis = entries(0);
+        let mut tree = TreeManager::open_or_rebuild(&path, &genesis, 0, true).unwrap();
+        apply_blocks(&mut tree, 1..=2);
+
+        // The database lock is held by `tree`
+        let err = TreeManager::open_or_rebuild(&path, &genesis, 2, true).unwrap_err();
+        assert!(!is_corruption(&err), "{err:#}");
+        drop(tree);
+        let quarantined: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .filter(|name| name.to_string_lossy().contains("_corrupted_"))
+            .collect();
+        assert!(quarantined.is_empty(), "{quarantined:?}");
+        let tree = TreeManager::open_or_rebuild(&path, &genesis, 2, true).unwrap();
+        assert_eq!(tree.latest_version().unwrap(), Some(2));
+    }
+
+    #[test]
+    fn rebuild_checkpoint_is_resumed_and_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tree");
+        let checkpoint_path = rebuild_checkpoint_path(&path);
+        write_checkpoint(
+            &checkpoint_path,
+            &TreeRebuildCheckpoint {
+                target_block: 2,
+                keys_processed: 10,
+            },
+        )
+        .unwrap();
+
+        let tree = TreeManager::open_or_rebuild(&path, &entries(0), 0, true).unwrap();
+        let mut rebuild = TreeManager::new(tree, &path).unwrap().rebuild.unwrap();
+        assert!(!rebuild.on_block(1, 3).unwrap());
+        assert!(rebuild.on_block(2, 3).unwrap());
+        assert_eq!(rebuild.checkpoint.keys_processed, 16);
+        assert!(!checkpoint_path.exists());
+    }
+}
@@ -0,0 +1,51 @@
+use zksync_os_batch_types::BlockMerkleTreeData;
+use zksync_os_batch_verification::{BlockSource, VerificationBlock};
+use zksync_os_merkle_tree::{MerkleTree, MerkleTreeVersion, RocksDBWrapper};
+use zksync_os_sequencer::execution::shadow::run_block_on_state;
+use zksync_os_storage_api::{ReadReplay, ReadStateHistory};
+
+/// Loads blocks for batch verification from local storage: block outputs are recomputed by
+/// re-executing replay records, and tree data is taken from the tree DB.
+pub struct LocalVerificationBlocks<State, Replay> {
+    state: State,
+    replay: Replay,
+    tree: MerkleTree<RocksDBWrapper>,
+}
+
+impl<State, Replay> LocalVerificationBlocks<State, Replay> {
+    pub fn new(state: State, replay: Replay, tree: MerkleTree<RocksDBWrapper>) -> Self {
+        Self {
+            state,
+            replay,
+            tree,
+        }
+    }
+}
+
+impl<State: ReadStateHistory, Replay: ReadReplay> BlockSource
+    for LocalVerificationBlocks<State, Replay>
+{
+    fn load_block(&self, block_number: u64) -> anyhow::Result<Option<VerificationBlock>> {
+        // Blocks not processed by the tree yet are not loaded, so that they are verified against
+        // the same tree data as blocks received from the pipeline
+        if self.tree.latest_version()? < Some(block_number) {
+            return Ok(None);
+        }
+        let Some(replay_record) = self.replay.get_replay_record(block_number) else {
+            return Ok(None);
+        };
+        let block_output =
+            run_block_on_state(&self.state, replay_record.block_context, &replay_record)?;
+        let tree_data = BlockMerkleTreeData {
+            block_start: MerkleTreeVersion {
+                tree: self.tree.clone(),
+                block: block_number - 1,
+            },
+            block_end: MerkleTreeVersion {
+                tree: self.tree.clone(),
+                block: block_number,
+            },
+        };
+        Ok(Some((block_output, replay_record, tree_data)))
+    }
+}